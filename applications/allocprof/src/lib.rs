@@ -0,0 +1,120 @@
+//! Starts or stops profiling the allocation sites of heap and frame allocations,
+//! and shows the sites with the most live memory.
+//!
+//! Without any options, this shows the top allocation sites collected so far.
+//! Each site's return addresses are symbolicated using the current task's namespace,
+//! outermost last, such that the output can easily be folded into a flamegraph.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::{format, string::String, sync::Arc, vec::Vec};
+use alloc_profiler::{AllocKind, SiteStats};
+use app_io::println;
+use command::{Arg, Command, Matches, Value};
+use memory::VirtualAddress;
+use mod_mgmt::CrateNamespace;
+
+/// The number of sites shown by default.
+const DEFAULT_TOP_SITES: usize = 10;
+
+pub static COMMAND: Command = Command {
+    name: "allocprof",
+    about: "Profile the allocation sites of live heap and frame allocations",
+    args: &[
+        Arg::flag("start").short('s').help("start profiling, discarding previously-collected statistics"),
+        Arg::flag("stop").short('x').help("stop profiling, keeping the statistics collected so far"),
+        Arg::option("top")
+            .short('n')
+            .value(Value::Integer)
+            .help("show this many of the sites with the most live memory (default: 10)"),
+        Arg::flag("folded").short('f').help("show every site as one line of semicolon-separated frames, for a flamegraph"),
+    ],
+};
+
+pub fn command() -> &'static Command {
+    &COMMAND
+}
+
+pub fn main(args: Vec<String>) -> isize {
+    let matches = match COMMAND.get_matches(args) {
+        Ok(m) => m,
+        Err(exit_value) => return exit_value,
+    };
+
+    match run(&matches) {
+        Ok(()) => 0,
+        Err(e) => {
+            println!("Error: {}", e);
+            -1
+        }
+    }
+}
+
+fn run(matches: &Matches) -> Result<(), String> {
+    let top = matches
+        .value_of("top")
+        .map_err(|e| format!("{}", e))?
+        .unwrap_or(DEFAULT_TOP_SITES);
+
+    if matches.is_present("stop") {
+        alloc_profiler::disable();
+        println!("Stopped profiling allocations");
+    }
+    if matches.is_present("start") {
+        alloc_profiler::enable(frame_allocator::general_memory_end().value())?;
+        println!("Started profiling allocations");
+        return Ok(());
+    }
+
+    let namespace = task::with_current_task(|t| t.get_namespace().clone())
+        .map_err(|_| "failed to get current task")?;
+    let sites = alloc_profiler::live_sites();
+    if matches.is_present("folded") {
+        for site in &sites {
+            let frames: Vec<String> = site.return_addresses().iter().rev()
+                .map(|&addr| symbolicate(&namespace, addr))
+                .collect();
+            println!("{:?};{} {}", site.kind, frames.join(";"), site.live_bytes);
+        }
+        return Ok(());
+    }
+
+    print_summary(&sites);
+    for site in sites.iter().take(top) {
+        println!(
+            "\n{:?}: {} bytes live in {} {}",
+            site.kind, site.live_bytes, site.live_allocations,
+            match site.kind { AllocKind::Heap => "allocations", AllocKind::Frames => "frames" },
+        );
+        if site.return_addresses().is_empty() {
+            println!("    <unknown site; frame pointers are disabled>");
+        }
+        for &addr in site.return_addresses() {
+            println!("    {:#018x} {}", addr, symbolicate(&namespace, addr));
+        }
+    }
+    Ok(())
+}
+
+fn print_summary(sites: &[SiteStats]) {
+    let total = |kind: AllocKind| -> usize {
+        sites.iter().filter(|s| s.kind == kind).map(|s| s.live_bytes).sum()
+    };
+    println!(
+        "Profiling is {}. {} sites with {} live heap bytes and {} live frame bytes ({} untracked allocations).",
+        if alloc_profiler::is_enabled() { "enabled" } else { "disabled" },
+        sites.len(), total(AllocKind::Heap), total(AllocKind::Frames),
+        alloc_profiler::untracked_allocations(),
+    );
+}
+
+/// Returns the name of the function containing the call instruction that precedes the given return address.
+fn symbolicate(namespace: &Arc<CrateNamespace>, return_address: usize) -> String {
+    // The return address is just past the call instruction, which might be the last instruction in the function.
+    VirtualAddress::new(return_address.saturating_sub(1))
+        .and_then(|addr| namespace.get_section_containing_address(addr, false))
+        .map(|(section, offset)| format!("{}+{:#x}", section.name, offset))
+        .unwrap_or_else(|| String::from("<unknown>"))
+}
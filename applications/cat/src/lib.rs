@@ -4,7 +4,7 @@
 
 #[macro_use] extern crate alloc;
 extern crate task;
-extern crate getopts;
+extern crate command;
 extern crate path;
 extern crate fs_node;
 extern crate core2;
@@ -14,40 +14,41 @@ use alloc::{
     string::String,
     vec::Vec,
 };
-use getopts::Options;
+use command::{Arg, Command, Value};
 use path::Path;
 use fs_node::FileOrDir;
 
 
+pub static COMMAND: Command = Command {
+    name: "cat",
+    about: "concatenate and print files",
+    args: &[
+        Arg::positional("FILE").value(Value::Path).help("the file to print instead of standard input"),
+    ],
+};
+
+pub fn command() -> &'static Command {
+    &COMMAND
+}
+
 pub fn main(args: Vec<String>) -> isize {
-    let mut opts = Options::new();
-    opts.optflag("h", "help", "print this help menu");
-    
-    let matches = match opts.parse(args) {
+    let matches = match COMMAND.get_matches(args) {
         Ok(m) => m,
-        Err(_f) => {
-            println!("{}", _f);
-            print_usage(opts);
-            return -1;
-        }
+        Err(exit_value) => return exit_value,
     };
-    if matches.opt_present("h") {
-        print_usage(opts);
-        return 0;
-    }
-    if matches.free.is_empty() {
+    let Some(path) = matches.value("FILE") else {
         if let Err(e) = echo_from_stdin() {
             println!("{}", e);
             return -1;
         }
         return 0;
-    }
+    };
     
     let Ok(cwd) = task::with_current_task(|t| t.get_env().lock().working_dir.clone()) else {
         println!("failed to get current task");
         return -1;
     };
-    let path: &Path = matches.free[0].as_ref();
+    let path: &Path = path.as_ref();
     
     // navigate to the filepath specified by first argument
     match path.get(&cwd) {
@@ -88,10 +89,6 @@ pub fn main(args: Vec<String>) -> isize {
     0
 }
 
-fn print_usage(opts: Options) {
-    println!("{}", opts.usage(USAGE));
-}
-
 fn echo_from_stdin() -> Result<(), &'static str> {
     let stdin = app_io::stdin()?;
     let stdout = app_io::stdout()?;
@@ -106,6 +103,3 @@ fn echo_from_stdin() -> Result<(), &'static str> {
     }
     Ok(())
 }
-
-const USAGE: &str = "Usage: cat [file ...]
-concatenate and print files";
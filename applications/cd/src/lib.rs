@@ -4,7 +4,7 @@
 
 extern crate alloc;
 extern crate fs_node;
-extern crate getopts;
+extern crate command;
 extern crate path;
 extern crate root;
 extern crate task;
@@ -12,19 +12,24 @@ extern crate task;
 use alloc::string::String;
 use alloc::sync::Arc;
 use alloc::vec::Vec;
-use getopts::Options;
+use command::{Arg, Command, Value};
+
+pub static COMMAND: Command = Command {
+    name: "cd",
+    about: "Change directory",
+    args: &[
+        Arg::positional("PATH").value(Value::Path).help("the new working directory (default: the root directory)"),
+    ],
+};
+
+pub fn command() -> &'static Command {
+    &COMMAND
+}
 
 pub fn main(args: Vec<String>) -> isize {
-    let mut opts = Options::new();
-    opts.optflag("h", "help", "print this help menu");
-
-    let matches = match opts.parse(args) {
+    let matches = match COMMAND.get_matches(args) {
         Ok(m) => m,
-        Err(_f) => {
-            println!("{}", _f);
-            print_usage(opts);
-            return -1;
-        }
+        Err(exit_value) => return exit_value,
     };
 
     let Ok(curr_env) = task::with_current_task(|t| t.get_env()) else {
@@ -32,11 +37,8 @@ pub fn main(args: Vec<String>) -> isize {
         return -1;
     };
 
-    // go to root directory
-    if matches.free.is_empty() {
-        curr_env.lock().working_dir = Arc::clone(root::get_root());
-    } else {
-        let path = matches.free[0].as_ref();
+    if let Some(path) = matches.value("PATH") {
+        let path = path.as_ref();
         match curr_env.lock().chdir(path) {
             Err(environment::Error::NotADirectory) => {
                 println!("not a directory: {}", path);
@@ -48,13 +50,9 @@ pub fn main(args: Vec<String>) -> isize {
             }
             _ => {}
         }
+    } else {
+        // go to root directory
+        curr_env.lock().working_dir = Arc::clone(root::get_root());
     }
     0
 }
-
-fn print_usage(opts: Options) {
-    println!("{}", opts.usage(USAGE));
-}
-
-const USAGE: &str = "Usage: cd [PATH]
-Change directory";
@@ -0,0 +1,107 @@
+//! Copies files and directories.
+//!
+//! If the destination is an existing directory, each source is copied into it.
+//! Otherwise, the single source is copied to the destination path.
+//! Source paths may contain wildcards.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::{format, string::String, vec::Vec};
+use app_io::{print, println};
+use command::{Arg, Command, Matches, Value};
+use fs_node::{DirRef, FileOrDir, FsNode, WalkEntry};
+use fs_utils::Progress;
+use path::Path;
+
+/// The number of nodes copied between each update of the progress output.
+const PROGRESS_INTERVAL: usize = 64;
+
+pub static COMMAND: Command = Command {
+    name: "cp",
+    about: "Copy files and directories",
+    args: &[
+        Arg::flag("recursive").short('r').help("copy directories and their contents"),
+        Arg::flag("progress").short('p').help("show the number of files copied so far"),
+        Arg::positional("PATH")
+            .required()
+            .multiple()
+            .value(Value::Path)
+            .help("the files or directories to copy, followed by the destination"),
+    ],
+};
+
+pub fn command() -> &'static Command {
+    &COMMAND
+}
+
+pub fn main(args: Vec<String>) -> isize {
+    let matches = match COMMAND.get_matches(args) {
+        Ok(m) => m,
+        Err(exit_value) => return exit_value,
+    };
+
+    match run(&matches) {
+        Ok(()) => 0,
+        Err(e) => {
+            println!("Error: {}", e);
+            -1
+        }
+    }
+}
+
+fn run(matches: &Matches) -> Result<(), String> {
+    let cwd = task::with_current_task(|t| t.get_env().lock().working_dir.clone())
+        .map_err(|_| "failed to get current task")?;
+    let mut sources = fs_utils::expand_globs(matches.values("PATH"), &cwd)?;
+    let dest = sources.pop().ok_or("missing destination")?;
+    if sources.is_empty() {
+        return Err(format!("missing destination after {}", dest));
+    }
+    let dest_dir = match dest.get(&cwd) {
+        Some(FileOrDir::Dir(dir)) => Some(dir),
+        _ if sources.len() > 1 => return Err(format!("{} is not a directory", dest)),
+        _ => None,
+    };
+
+    let show_progress = matches.is_present("progress");
+    let mut progress = Progress::default();
+    let mut on_copied = |entry: &WalkEntry| {
+        progress.record(&entry.node);
+        if show_progress && progress.count() % PROGRESS_INTERVAL == 0 {
+            print!("\rCopied {}", progress);
+        }
+    };
+
+    for source_path in &sources {
+        let source = source_path
+            .get(&cwd)
+            .ok_or_else(|| format!("{} doesn't exist", source_path))?;
+        if source.is_dir() && !matches.is_present("recursive") {
+            println!("Skipping directory {}, try specifying the \"-r\" flag", source_path);
+            continue;
+        }
+        let (parent, name) = match &dest_dir {
+            Some(dir) => (dir.clone(), source.get_name()),
+            None => destination(&dest, &cwd)?,
+        };
+        fs_utils::copy(&source, &parent, &name, &mut on_copied)
+            .map_err(|e| format!("failed to copy {}: {}", source_path, e))?;
+    }
+
+    if show_progress {
+        println!("\rCopied {}", progress);
+    }
+    Ok(())
+}
+
+/// Returns the directory that will contain the node at the given path, and the node's name.
+fn destination(path: &Path, cwd: &DirRef) -> Result<(DirRef, String), String> {
+    let name = path.file_name().ok_or_else(|| format!("invalid destination {}", path))?;
+    let parent = path
+        .parent()
+        .and_then(|parent| parent.get_dir(cwd))
+        .ok_or_else(|| format!("the parent directory of {} doesn't exist", path))?;
+    Ok((parent, name.into()))
+}
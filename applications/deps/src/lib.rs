@@ -68,6 +68,8 @@ pub fn main(args: Vec<String>) -> isize {
     opts.optopt ("",  "num-deps-section", "sum up the count of all dependencies for the given section", "SECTION");
     opts.optflag("",  "num-deps-all",     "sum up the count of all dependencies for all crates");
     opts.optflag("",  "num-rodata",       "count the private .rodata sections for all crates");
+    opts.optflag("",  "order",            "list all crates such that each comes after the crates it depends on");
+    opts.optflag("",  "dot",              "output the dependency graph of all crates in the Graphviz DOT format");
     
 
     let matches = match opts.parse(args) {
@@ -132,6 +134,12 @@ fn rmain(matches: Matches) -> Result<(), String> {
     else if matches.opt_present("num-rodata") {
         count_private_rodata_sections()
     }
+    else if matches.opt_present("order") {
+        crates_in_dependency_order()
+    }
+    else if matches.opt_present("dot") {
+        dependency_graph_dot()
+    }
     else {
         Err("no supported options/arguments found.".to_string())
     }
@@ -315,8 +323,19 @@ fn section_dependency_count(sec: &StrongSectionRef) -> (usize, usize, usize) {
 /// 
 /// If there are multiple matches, this returns an Error containing 
 /// all of the matching crate names separated by the newline character `'\n'`.
-fn crates_dependent_on_me(_crate_name: &str) -> Result<(), String> {
-    Err("unimplemented".to_string())
+fn crates_dependent_on_me(crate_prefix: &str) -> Result<(), String> {
+    let (crate_name, crate_ref) = find_crate(crate_prefix)?;
+    let mut crate_list = crate_ref
+        .lock_as_ref()
+        .crates_dependent_on_me()
+        .iter()
+        .filter_map(|wc| wc.upgrade().map(|c| c.lock_as_ref().crate_name.clone()))
+        .collect::<Vec<_>>();
+
+    crate_list.sort_unstable();
+
+    println!("Crate {} has direct dependents:\n  {}", crate_name, crate_list.join("\n  "));
+    Ok(())
 }
 
 
@@ -335,8 +354,6 @@ fn crates_i_depend_on(crate_prefix: &str) -> Result<(), String> {
         .collect::<Vec<_>>();
 
     crate_list.sort_unstable();
-    crate_list.dedup();
-
 
     println!("Crate {} has direct dependences:\n  {}", crate_name, crate_list.join("\n  "));
     Ok(())
@@ -344,6 +361,29 @@ fn crates_i_depend_on(crate_prefix: &str) -> Result<(), String> {
 
 
 
+/// Outputs all crates in the current namespace, such that each crate
+/// comes after the crates that it depends on.
+fn crates_in_dependency_order() -> Result<(), String> {
+    let graph = get_my_current_namespace().dependency_graph(true);
+    for (crate_name, _) in graph.topological_order() {
+        println!("{}", crate_name);
+    }
+    Ok(())
+}
+
+
+/// Outputs the dependency graph of all crates in the current namespace in the Graphviz DOT format.
+fn dependency_graph_dot() -> Result<(), String> {
+    let mut dot = String::new();
+    get_my_current_namespace()
+        .dependency_graph(true)
+        .write_dot(&mut dot)
+        .map_err(|_| "failed to format dependency graph".to_string())?;
+    print!("{}", dot);
+    Ok(())
+}
+
+
 /// Outputs the list of sections in the given crate.
 /// 
 /// # Arguments
@@ -0,0 +1,197 @@
+//! Shows the machine's hardware as described by its SMBIOS (a.k.a. DMI) tables, like Unix's `dmidecode`.
+//!
+//! By default, every known section is shown: the firmware, the system, the motherboard,
+//! the processor sockets, and the memory devices.
+//! A single value can be shown with `-s`, e.g., `dmidecode -s system-serial-number`,
+//! which is useful for scripts that record which machine they ran on.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::{format, string::{String, ToString}, vec::Vec};
+use app_io::{print, println};
+use command::{Arg, Command, Matches, Value};
+use firmware_info::FirmwareInfo;
+use fmt_utils::{or_dash, Align, Size, Table};
+
+const TYPES: &[&str] = &["bios", "system", "baseboard", "processor", "memory"];
+
+const KEYWORDS: &[&str] = &[
+    "bios-vendor",
+    "bios-version",
+    "bios-release-date",
+    "system-manufacturer",
+    "system-product-name",
+    "system-version",
+    "system-serial-number",
+    "system-uuid",
+    "system-sku-number",
+    "system-family",
+    "baseboard-manufacturer",
+    "baseboard-product-name",
+    "baseboard-version",
+    "baseboard-serial-number",
+    "baseboard-asset-tag",
+];
+
+pub static COMMAND: Command = Command {
+    name: "dmidecode",
+    about: "Show the machine's hardware as described by its SMBIOS tables",
+    args: &[
+        Arg::option("type")
+            .short('t')
+            .multiple()
+            .value(Value::OneOf(TYPES))
+            .help("only show the sections of this type"),
+        Arg::option("string")
+            .short('s')
+            .value(Value::OneOf(KEYWORDS))
+            .help("only show the value of this keyword"),
+    ],
+};
+
+pub fn command() -> &'static Command {
+    &COMMAND
+}
+
+pub fn main(args: Vec<String>) -> isize {
+    let matches = match COMMAND.get_matches(args) {
+        Ok(m) => m,
+        Err(exit_value) => return exit_value,
+    };
+
+    match run(&matches) {
+        Ok(()) => 0,
+        Err(e) => {
+            println!("Error: {}", e);
+            -1
+        }
+    }
+}
+
+fn run(matches: &Matches) -> Result<(), String> {
+    let info = firmware_info::get()?;
+
+    if let Some(keyword) = matches.value("string") {
+        // Like `dmidecode`, print nothing but the value, so that it can be used by scripts.
+        match keyword_value(info, keyword) {
+            Some(value) => println!("{}", value),
+            None => return Err(format!("the firmware doesn't specify the {}", keyword)),
+        }
+        return Ok(());
+    }
+
+    let types = matches.values("type");
+    let shown = |typ: &str| types.is_empty() || types.iter().any(|t| t == typ);
+    println!("SMBIOS {}.{} present.", info.smbios_version.0, info.smbios_version.1);
+
+    if shown("bios") {
+        if let Some(bios) = &info.bios {
+            section("BIOS Information", &[
+                ("Vendor", bios.vendor.clone()),
+                ("Version", bios.version.clone()),
+                ("Release Date", bios.release_date.clone()),
+                ("BIOS Revision", bios.release.map(|(major, minor)| format!("{}.{}", major, minor))),
+            ]);
+        }
+    }
+    if shown("system") {
+        if let Some(system) = &info.system {
+            section("System Information", &[
+                ("Manufacturer", system.manufacturer.clone()),
+                ("Product Name", system.product_name.clone()),
+                ("Version", system.version.clone()),
+                ("Serial Number", system.serial_number.clone()),
+                ("UUID", system.uuid.clone()),
+                ("SKU Number", system.sku_number.clone()),
+                ("Family", system.family.clone()),
+            ]);
+        }
+    }
+    if shown("baseboard") {
+        if let Some(baseboard) = &info.baseboard {
+            section("Base Board Information", &[
+                ("Manufacturer", baseboard.manufacturer.clone()),
+                ("Product Name", baseboard.product_name.clone()),
+                ("Version", baseboard.version.clone()),
+                ("Serial Number", baseboard.serial_number.clone()),
+                ("Asset Tag", baseboard.asset_tag.clone()),
+            ]);
+        }
+    }
+    if shown("processor") && !info.processors.is_empty() {
+        println!("\nProcessor Information");
+        let mut table = Table::new(["SOCKET", "CORES", "THREADS", "MHZ", "MAX MHZ", "VERSION"])
+            .align(1, Align::Right)
+            .align(2, Align::Right)
+            .align(3, Align::Right)
+            .align(4, Align::Right);
+        for processor in &info.processors {
+            table.row([
+                or_dash(processor.socket.as_ref()),
+                or_dash(processor.core_count),
+                or_dash(processor.thread_count),
+                or_dash(processor.current_speed_mhz),
+                or_dash(processor.max_speed_mhz),
+                if processor.populated {
+                    or_dash(processor.version.as_ref())
+                } else {
+                    String::from("(not populated)")
+                },
+            ]);
+        }
+        print!("{}", table);
+    }
+    if shown("memory") && !info.memory_devices.is_empty() {
+        println!("\nMemory Devices ({} installed)", Size(info.installed_memory()));
+        let mut table = Table::new(["LOCATOR", "SIZE", "TYPE", "MT/S", "MANUFACTURER", "PART NUMBER"])
+            .align(1, Align::Right)
+            .align(3, Align::Right);
+        for device in &info.memory_devices {
+            table.row([
+                or_dash(device.locator.as_ref()),
+                device.size.map_or_else(|| String::from("(empty)"), |size| Size(size).to_string()),
+                device.memory_type.to_string(),
+                or_dash(device.speed_mts),
+                or_dash(device.manufacturer.as_ref()),
+                or_dash(device.part_number.as_ref()),
+            ]);
+        }
+        print!("{}", table);
+    }
+    Ok(())
+}
+
+/// Prints a section with the given title and fields, skipping the fields without a value.
+fn section(title: &str, fields: &[(&str, Option<String>)]) {
+    println!("\n{}", title);
+    for (name, value) in fields {
+        if let Some(value) = value {
+            println!("\t{}: {}", name, value);
+        }
+    }
+}
+
+/// Returns the value of the given keyword, which is one of [`KEYWORDS`].
+fn keyword_value(info: &FirmwareInfo, keyword: &str) -> Option<String> {
+    let (bios, system, baseboard) = (info.bios.as_ref(), info.system.as_ref(), info.baseboard.as_ref());
+    match keyword {
+        "bios-vendor"             => bios?.vendor.clone(),
+        "bios-version"            => bios?.version.clone(),
+        "bios-release-date"       => bios?.release_date.clone(),
+        "system-manufacturer"     => system?.manufacturer.clone(),
+        "system-product-name"     => system?.product_name.clone(),
+        "system-version"          => system?.version.clone(),
+        "system-serial-number"    => system?.serial_number.clone(),
+        "system-uuid"             => system?.uuid.clone(),
+        "system-sku-number"       => system?.sku_number.clone(),
+        "system-family"           => system?.family.clone(),
+        "baseboard-manufacturer"  => baseboard?.manufacturer.clone(),
+        "baseboard-product-name"  => baseboard?.product_name.clone(),
+        "baseboard-version"       => baseboard?.version.clone(),
+        "baseboard-serial-number" => baseboard?.serial_number.clone(),
+        "baseboard-asset-tag"     => baseboard?.asset_tag.clone(),
+        _ => None,
+    }
+}
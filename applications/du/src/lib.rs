@@ -0,0 +1,124 @@
+//! Shows the total size in bytes of the files within directories.
+//!
+//! Directories themselves currently have no size, so a directory's size
+//! is the sum of the lengths of all files beneath it.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::{format, string::{String, ToString}, vec::Vec};
+use app_io::println;
+use command::{Arg, Command, Matches, Value};
+use fmt_utils::Size;
+use fs_node::FileOrDir;
+
+pub static COMMAND: Command = Command {
+    name: "du",
+    about: "Show the total size of the files within each directory",
+    args: &[
+        Arg::flag("all").short('a').help("show the sizes of files as well as directories"),
+        Arg::flag("summarize").short('s').help("show only the total size of each PATH"),
+        Arg::flag("human-readable").short('h').help("show sizes in binary units, e.g., 1.5 KiB"),
+        Arg::option("max-depth")
+            .short('d')
+            .value(Value::Integer)
+            .help("show sizes only for nodes at most this many levels below each PATH"),
+        Arg::positional("PATH")
+            .multiple()
+            .value(Value::Path)
+            .help("the files or directories to measure, which may contain wildcards (default: .)"),
+    ],
+};
+
+pub fn command() -> &'static Command {
+    &COMMAND
+}
+
+pub fn main(args: Vec<String>) -> isize {
+    let matches = match COMMAND.get_matches(args) {
+        Ok(m) => m,
+        Err(exit_value) => return exit_value,
+    };
+
+    match run(&matches) {
+        Ok(()) => 0,
+        Err(e) => {
+            println!("Error: {}", e);
+            -1
+        }
+    }
+}
+
+fn run(matches: &Matches) -> Result<(), String> {
+    let cwd = task::with_current_task(|t| t.get_env().lock().working_dir.clone())
+        .map_err(|_| "failed to get current task")?;
+    let max_depth = if matches.is_present("summarize") {
+        0
+    } else {
+        matches
+            .value_of("max-depth")
+            .map_err(|e| format!("{}", e))?
+            .unwrap_or(usize::MAX)
+    };
+    let all = matches.is_present("all");
+    let human_readable = matches.is_present("human-readable");
+
+    let paths = match matches.values("PATH") {
+        [] => fs_utils::expand_globs(&["."], &cwd)?,
+        args => fs_utils::expand_globs(args, &cwd)?,
+    };
+    for path in paths {
+        let node = path.get(&cwd).ok_or_else(|| format!("{} doesn't exist", path))?;
+        let root: &str = path.as_ref();
+        let root = root.trim_end_matches('/');
+
+        // The directories that contain the current node, along with the sizes found in them so far.
+        let mut dirs: Vec<(String, usize, usize)> = Vec::new();
+        let report = |path: &str, depth: usize, size: usize| {
+            if depth <= max_depth {
+                let size = if human_readable { Size::from(size).to_string() } else { size.to_string() };
+                println!("{}\t{}", size, display_path(root, path));
+            }
+        };
+        for entry in node.walk() {
+            // Report the directories that this node is not within.
+            while dirs.last().map_or(false, |(_, depth, _)| *depth >= entry.depth) {
+                let (path, depth, size) = dirs.pop().unwrap();
+                report(&path, depth, size);
+                if let Some((_, _, parent_size)) = dirs.last_mut() {
+                    *parent_size += size;
+                }
+            }
+            match entry.node {
+                FileOrDir::File(file) => {
+                    let size = file.lock().len();
+                    if let Some((_, _, parent_size)) = dirs.last_mut() {
+                        *parent_size += size;
+                    }
+                    // A file given as a PATH is always reported.
+                    if all || entry.depth == 0 {
+                        report(&entry.path, entry.depth, size);
+                    }
+                }
+                FileOrDir::Dir(_) => dirs.push((entry.path, entry.depth, 0)),
+            }
+        }
+        while let Some((path, depth, size)) = dirs.pop() {
+            report(&path, depth, size);
+            if let Some((_, _, parent_size)) = dirs.last_mut() {
+                *parent_size += size;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Joins the `root` path that a walk started from and the path of a node relative to it.
+fn display_path(root: &str, relative: &str) -> String {
+    match (root, relative) {
+        (root, "") => String::from(if root.is_empty() { "/" } else { root }),
+        ("", relative) => format!("/{}", relative),
+        (root, relative) => format!("{}/{}", root, relative),
+    }
+}
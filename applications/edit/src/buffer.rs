@@ -0,0 +1,273 @@
+//! The text being edited, stored as a list of lines, and the cursor's position within it.
+//!
+//! Columns are counted in characters rather than bytes,
+//! so the cursor can never be placed in the middle of a multi-byte UTF-8 character.
+
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+
+pub struct Buffer {
+    lines: Vec<String>,
+    /// The line that the cursor is on.
+    row: usize,
+    /// The character within the line that the cursor is before.
+    col: usize,
+    modified: bool,
+}
+
+impl Buffer {
+    /// Creates a buffer containing the given text, with the cursor at its start.
+    ///
+    /// Lines may end with either `\n` or `\r\n`, but `\r\n` is saved as `\n`.
+    pub fn new(text: &str) -> Self {
+        let lines = text
+            .split('\n')
+            .map(|line| line.strip_suffix('\r').unwrap_or(line).to_string())
+            .collect();
+        Self {
+            lines,
+            row: 0,
+            col: 0,
+            modified: false,
+        }
+    }
+
+    /// Returns the contents of the buffer, with lines separated by `\n`.
+    pub fn text(&self) -> String {
+        self.lines.join("\n")
+    }
+
+    pub fn line(&self, row: usize) -> Option<&str> {
+        self.lines.get(row).map(String::as_str)
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.lines.len()
+    }
+
+    /// Returns the cursor's line and column.
+    pub fn cursor(&self) -> (usize, usize) {
+        (self.row, self.col)
+    }
+
+    /// Returns whether the buffer was modified since it was created or last saved.
+    pub fn is_modified(&self) -> bool {
+        self.modified
+    }
+
+    pub fn mark_saved(&mut self) {
+        self.modified = false;
+    }
+
+    pub fn insert_char(&mut self, c: char) {
+        let index = self.byte_index(self.col);
+        self.lines[self.row].insert(index, c);
+        self.col += 1;
+        self.modified = true;
+    }
+
+    /// Splits the current line at the cursor, moving the cursor to the start of the new line.
+    pub fn insert_newline(&mut self) {
+        let index = self.byte_index(self.col);
+        let rest = self.lines[self.row].split_off(index);
+        self.row += 1;
+        self.col = 0;
+        self.lines.insert(self.row, rest);
+        self.modified = true;
+    }
+
+    /// Inserts the given text at the cursor, moving the cursor to the end of it.
+    pub fn insert_text(&mut self, text: &str) {
+        for c in text.chars() {
+            match c {
+                '\n' => self.insert_newline(),
+                '\r' => {}
+                c => self.insert_char(c),
+            }
+        }
+    }
+
+    /// Removes the line that the cursor is on and returns it, including its newline.
+    ///
+    /// If it's the only line, it's cleared instead.
+    pub fn cut_line(&mut self) -> String {
+        let mut line = if self.lines.len() > 1 {
+            let line = self.lines.remove(self.row);
+            self.row = self.row.min(self.lines.len() - 1);
+            line
+        } else {
+            core::mem::take(&mut self.lines[0])
+        };
+        line.push('\n');
+        self.col = 0;
+        self.modified = true;
+        line
+    }
+
+    /// Removes the character before the cursor,
+    /// joining the current line to the previous one if the cursor is at the start of the line.
+    pub fn backspace(&mut self) {
+        if self.col > 0 {
+            self.col -= 1;
+            self.delete();
+        } else if self.row > 0 {
+            let line = self.lines.remove(self.row);
+            self.row -= 1;
+            self.col = self.line_len(self.row);
+            self.lines[self.row].push_str(&line);
+            self.modified = true;
+        }
+    }
+
+    /// Removes the character after the cursor,
+    /// joining the next line to the current one if the cursor is at the end of the line.
+    pub fn delete(&mut self) {
+        if self.col < self.line_len(self.row) {
+            let index = self.byte_index(self.col);
+            self.lines[self.row].remove(index);
+            self.modified = true;
+        } else if self.row + 1 < self.lines.len() {
+            let next = self.lines.remove(self.row + 1);
+            self.lines[self.row].push_str(&next);
+            self.modified = true;
+        }
+    }
+
+    pub fn move_left(&mut self) {
+        if self.col > 0 {
+            self.col -= 1;
+        } else if self.row > 0 {
+            self.row -= 1;
+            self.col = self.line_len(self.row);
+        }
+    }
+
+    pub fn move_right(&mut self) {
+        if self.col < self.line_len(self.row) {
+            self.col += 1;
+        } else if self.row + 1 < self.lines.len() {
+            self.row += 1;
+            self.col = 0;
+        }
+    }
+
+    /// Moves the cursor up by the given number of lines, stopping at the first line.
+    pub fn move_up(&mut self, lines: usize) {
+        self.row = self.row.saturating_sub(lines);
+        self.col = self.col.min(self.line_len(self.row));
+    }
+
+    /// Moves the cursor down by the given number of lines, stopping at the last line.
+    pub fn move_down(&mut self, lines: usize) {
+        self.row = (self.row + lines).min(self.lines.len() - 1);
+        self.col = self.col.min(self.line_len(self.row));
+    }
+
+    pub fn move_home(&mut self) {
+        self.col = 0;
+    }
+
+    pub fn move_end(&mut self) {
+        self.col = self.line_len(self.row);
+    }
+
+    /// Moves the cursor to the next occurrence of `query` after the cursor,
+    /// wrapping around to the start of the buffer.
+    ///
+    /// Returns whether an occurrence was found.
+    pub fn find(&mut self, query: &str) -> bool {
+        if query.is_empty() {
+            return false;
+        }
+        let row_count = self.lines.len();
+        // The current line is searched twice: after the cursor first, and before it last.
+        for i in 0..=row_count {
+            let row = (self.row + i) % row_count;
+            let line = &self.lines[row];
+            let start = if i == 0 { self.byte_index(self.col + 1) } else { 0 };
+            let Some(found) = line.get(start..).and_then(|rest| rest.find(query)) else {
+                continue;
+            };
+            self.row = row;
+            self.col = line[..start + found].chars().count();
+            return true;
+        }
+        false
+    }
+
+    fn line_len(&self, row: usize) -> usize {
+        self.lines[row].chars().count()
+    }
+
+    /// Returns the byte index of the given column within the current line.
+    fn byte_index(&self, col: usize) -> usize {
+        let line = &self.lines[self.row];
+        line.char_indices().nth(col).map_or(line.len(), |(index, _)| index)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn edit_lines() {
+        let mut buffer = Buffer::new("ab\r\ncd\n");
+        assert_eq!(buffer.line_count(), 3);
+        buffer.move_right();
+        buffer.insert_newline();
+        assert_eq!(buffer.text(), "a\nb\ncd\n");
+        buffer.backspace();
+        buffer.backspace();
+        assert_eq!(buffer.text(), "b\ncd\n");
+        buffer.move_end();
+        buffer.delete();
+        assert_eq!(buffer.text(), "bcd\n");
+        assert_eq!(buffer.cursor(), (0, 1));
+        assert!(buffer.is_modified());
+    }
+
+    #[test]
+    fn multibyte_chars() {
+        let mut buffer = Buffer::new("añb");
+        buffer.move_down(1);
+        buffer.move_end();
+        assert_eq!(buffer.cursor(), (0, 3));
+        buffer.move_left();
+        buffer.backspace();
+        buffer.insert_char('é');
+        assert_eq!(buffer.text(), "aéb");
+        buffer.move_up(1);
+        assert_eq!(buffer.cursor(), (0, 2));
+    }
+
+    #[test]
+    fn cut_and_paste_lines() {
+        let mut buffer = Buffer::new("one\ntwo\nthree");
+        buffer.move_down(1);
+        let cut = buffer.cut_line();
+        assert_eq!(cut, "two\n");
+        assert_eq!(buffer.text(), "one\nthree");
+        assert_eq!(buffer.cursor(), (1, 0));
+        buffer.insert_text(&cut);
+        assert_eq!(buffer.text(), "one\ntwo\nthree");
+        assert_eq!(buffer.cursor(), (2, 0));
+        buffer.move_down(1);
+        assert_eq!(buffer.cut_line(), "three\n");
+        assert_eq!(buffer.cursor(), (1, 0));
+    }
+
+    #[test]
+    fn find_wraps_around() {
+        let mut buffer = Buffer::new("foo bar\nbaz foo\n");
+        assert!(buffer.find("foo"));
+        assert_eq!(buffer.cursor(), (1, 4));
+        assert!(buffer.find("foo"));
+        assert_eq!(buffer.cursor(), (0, 0));
+        assert!(buffer.find("ba"));
+        assert_eq!(buffer.cursor(), (0, 4));
+        assert!(!buffer.find("qux"));
+    }
+}
@@ -0,0 +1,143 @@
+//! Decoding of the bytes received from a terminal into key presses.
+
+use alloc::vec::Vec;
+use core::str;
+
+const ESCAPE: u8 = 0x1b;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Key {
+    Char(char),
+    /// A letter pressed along with the Ctrl key, given in lower case.
+    Ctrl(char),
+    Enter,
+    Tab,
+    Backspace,
+    Delete,
+    Up,
+    Down,
+    Left,
+    Right,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+}
+
+/// Decodes UTF-8 characters and the escape sequences that terminals send for special keys.
+///
+/// Unrecognized escape sequences are ignored.
+#[derive(Default)]
+pub struct KeyDecoder {
+    /// The bytes of the escape sequence or character being received.
+    pending: Vec<u8>,
+}
+
+impl KeyDecoder {
+    /// Adds the next byte received from the terminal,
+    /// returning the key that it completes, if any.
+    pub fn push(&mut self, byte: u8) -> Option<Key> {
+        match self.pending.first() {
+            None => self.start(byte),
+            Some(&ESCAPE) => self.push_escape(byte),
+            Some(&lead) => {
+                self.pending.push(byte);
+                if byte & 0xc0 != 0x80 {
+                    // Not a continuation byte, so the character was invalid.
+                    self.pending.clear();
+                    return None;
+                }
+                if self.pending.len() < utf8_len(lead) {
+                    return None;
+                }
+                let c = str::from_utf8(&self.pending).ok().and_then(|s| s.chars().next());
+                self.pending.clear();
+                c.map(Key::Char)
+            }
+        }
+    }
+
+    fn start(&mut self, byte: u8) -> Option<Key> {
+        match byte {
+            b'\r' | b'\n' => Some(Key::Enter),
+            b'\t' => Some(Key::Tab),
+            0x08 | 0x7f => Some(Key::Backspace),
+            0x01..=0x1a => Some(Key::Ctrl((b'a' + byte - 1) as char)),
+            0x20..=0x7e => Some(Key::Char(byte as char)),
+            _ if byte == ESCAPE || utf8_len(byte) > 1 => {
+                self.pending.push(byte);
+                None
+            }
+            _ => None,
+        }
+    }
+
+    fn push_escape(&mut self, byte: u8) -> Option<Key> {
+        self.pending.push(byte);
+        let key = match &self.pending[1..] {
+            [b'[' | b'O'] => return None,
+            // Control sequences end with a byte in this range, after any parameters.
+            [b'[', .., 0x40..=0x7e] | [b'O', _] => decode_sequence(&self.pending[2..]),
+            [b'[', ..] if self.pending.len() < 8 => return None,
+            _ => None,
+        };
+        self.pending.clear();
+        key
+    }
+}
+
+/// Decodes the body of a control sequence, without its leading `ESC [` or `ESC O`.
+fn decode_sequence(sequence: &[u8]) -> Option<Key> {
+    let (&last, params) = sequence.split_last()?;
+    match (params, last) {
+        (_, b'A') => Some(Key::Up),
+        (_, b'B') => Some(Key::Down),
+        (_, b'C') => Some(Key::Right),
+        (_, b'D') => Some(Key::Left),
+        (_, b'H') | (b"1" | b"7", b'~') => Some(Key::Home),
+        (_, b'F') | (b"4" | b"8", b'~') => Some(Key::End),
+        (b"3", b'~') => Some(Key::Delete),
+        (b"5", b'~') => Some(Key::PageUp),
+        (b"6", b'~') => Some(Key::PageDown),
+        _ => None,
+    }
+}
+
+/// Returns the length of the UTF-8 character that starts with the given byte.
+fn utf8_len(lead: u8) -> usize {
+    match lead {
+        0xc0..=0xdf => 2,
+        0xe0..=0xef => 3,
+        0xf0..=0xf7 => 4,
+        _ => 1,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn decode(bytes: &[u8]) -> Vec<Key> {
+        let mut decoder = KeyDecoder::default();
+        bytes.iter().filter_map(|&byte| decoder.push(byte)).collect()
+    }
+
+    #[test]
+    fn keys() {
+        assert_eq!(
+            decode(b"a\x1b[A\x1bOD\x1b[3~\x1b[1;5C\x13\r\x7f"),
+            [
+                Key::Char('a'),
+                Key::Up,
+                Key::Left,
+                Key::Delete,
+                Key::Right,
+                Key::Ctrl('s'),
+                Key::Enter,
+                Key::Backspace,
+            ],
+        );
+        assert_eq!(decode("é€".as_bytes()), [Key::Char('é'), Key::Char('€')]);
+        assert!(decode(b"\x1b[99z\xe2\x82b").is_empty());
+    }
+}
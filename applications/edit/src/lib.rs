@@ -0,0 +1,345 @@
+//! A small full-screen text editor that runs in a terminal.
+//!
+//! The cursor is moved with the arrow keys, Home, End, Page Up, and Page Down.
+//! Ctrl+S saves the file, Ctrl+F searches for text, and Ctrl+Q quits.
+//! Ctrl+K cuts the current line to the system [`clipboard`], and Ctrl+U pastes the clipboard's text,
+//! which may also have been copied from elsewhere, e.g., from a selection in the terminal.
+//!
+//! The editor draws the screen with ANSI escape sequences,
+//! so it must be run from a shell attached to a terminal that supports them, e.g., `hull`
+//! on a serial console. Every character is assumed to occupy a single column;
+//! tabs are shown as a single space and other control characters as `?`.
+
+#![no_std]
+
+extern crate alloc;
+
+mod buffer;
+mod input;
+
+use alloc::{
+    format,
+    string::{String, ToString},
+    sync::Arc,
+    vec,
+    vec::Vec,
+};
+use app_io::{println, ImmutableRead, ImmutableWrite};
+use buffer::Buffer;
+use command::{Arg, Command, Matches, Value};
+use fs_node::{DirRef, FileOrDir, FileRef};
+use input::{Key, KeyDecoder};
+use memfs::MemFile;
+use memory::{create_mapping, MappedPages, PteFlags};
+use path::Path;
+
+const DEFAULT_ROWS: usize = 24;
+const DEFAULT_COLUMNS: usize = 80;
+
+/// The message shown when the editor starts.
+const KEYS_MESSAGE: &str = "Ctrl+S: save | Ctrl+F: find | Ctrl+K: cut line | Ctrl+U: paste | Ctrl+Q: quit";
+
+pub static COMMAND: Command = Command {
+    name: "edit",
+    about: "Edit the given text file, which is created when it's first saved if it doesn't exist.",
+    args: &[
+        Arg::option("rows")
+            .short('r')
+            .value(Value::Integer)
+            .help("the height of the terminal (default: 24)"),
+        Arg::option("columns")
+            .short('c')
+            .value(Value::Integer)
+            .help("the width of the terminal (default: 80)"),
+        Arg::positional("FILE")
+            .required()
+            .value(Value::Path)
+            .help("the file to edit"),
+    ],
+};
+
+pub fn command() -> &'static Command {
+    &COMMAND
+}
+
+pub fn main(args: Vec<String>) -> isize {
+    let matches = match COMMAND.get_matches(args) {
+        Ok(m) => m,
+        Err(exit_value) => return exit_value,
+    };
+
+    match run(&matches) {
+        Ok(()) => 0,
+        Err(e) => {
+            println!("Error: {}", e);
+            -1
+        }
+    }
+}
+
+fn run(matches: &Matches) -> Result<(), String> {
+    let rows = matches
+        .value_of("rows")
+        .map_err(|e| e.to_string())?
+        .unwrap_or(DEFAULT_ROWS);
+    let columns = matches
+        .value_of("columns")
+        .map_err(|e| e.to_string())?
+        .unwrap_or(DEFAULT_COLUMNS);
+    if rows < 3 || columns < 2 {
+        return Err("the terminal is too small".into());
+    }
+
+    let path = Path::new(matches.value("FILE").ok_or("missing FILE argument")?);
+    let cwd = task::with_current_task(|t| t.get_env().lock().working_dir.clone())
+        .map_err(|_| "failed to get current task")?;
+    let name = path.file_name().ok_or("invalid file path")?.to_string();
+    let parent = path
+        .parent()
+        .ok_or("invalid file path")?
+        .get_dir(&cwd)
+        .ok_or_else(|| format!("the parent directory of {} doesn't exist", path))?;
+    let buffer = match path.get(&cwd) {
+        Some(FileOrDir::File(file)) => Buffer::new(&read_text(&file)?),
+        Some(FileOrDir::Dir(_)) => return Err(format!("{} is a directory", path)),
+        None => Buffer::new(""),
+    };
+
+    let discipline = app_io::line_discipline()?;
+    let stdin = app_io::stdin()?;
+    let stdout = app_io::stdout()?;
+    discipline.set_raw();
+
+    let mut editor = Editor {
+        buffer,
+        parent,
+        name,
+        stdout: stdout.clone(),
+        decoder: KeyDecoder::default(),
+        rows: rows - 2,
+        columns,
+        top: 0,
+        left: 0,
+        message: String::from(KEYS_MESSAGE),
+        last_query: String::new(),
+        quit_pending: false,
+    };
+    let result = editor.run(&*stdin);
+
+    // Clear the screen and restore the line discipline for the shell, even if editing failed.
+    let _ = stdout.write_all(b"\x1b[2J\x1b[H");
+    discipline.set_sane();
+    result
+}
+
+struct Editor {
+    buffer: Buffer,
+    /// The directory that the file is saved in.
+    parent: DirRef,
+    /// The name of the file within `parent`.
+    name: String,
+    stdout: Arc<dyn ImmutableWrite>,
+    decoder: KeyDecoder,
+    /// The number of rows used to show the buffer,
+    /// which excludes the status line and the message line below it.
+    rows: usize,
+    columns: usize,
+    /// The first line shown on the screen.
+    top: usize,
+    /// The first column shown on the screen.
+    left: usize,
+    /// The message shown below the status line.
+    message: String,
+    /// The text that was last searched for.
+    last_query: String,
+    /// Whether Ctrl+Q was just pressed while the buffer had unsaved changes.
+    quit_pending: bool,
+}
+
+impl Editor {
+    /// Handles key presses until the user quits.
+    fn run(&mut self, stdin: &dyn ImmutableRead) -> Result<(), String> {
+        loop {
+            self.draw(None)?;
+            let key = self.read_key(stdin)?;
+            let quit_pending = core::mem::take(&mut self.quit_pending);
+            match key {
+                Key::Ctrl('q') => {
+                    if quit_pending || !self.buffer.is_modified() {
+                        return Ok(());
+                    }
+                    self.quit_pending = true;
+                    self.message = String::from("The file has unsaved changes. Press Ctrl+Q again to quit anyway.");
+                }
+                Key::Ctrl('s') => self.save(),
+                Key::Ctrl('f') => self.find(stdin)?,
+                Key::Ctrl('k') => clipboard::set(self.buffer.cut_line()),
+                Key::Ctrl('u') => self.buffer.insert_text(&clipboard::get()),
+                Key::Char(c) => self.buffer.insert_char(c),
+                Key::Tab => self.buffer.insert_char('\t'),
+                Key::Enter => self.buffer.insert_newline(),
+                Key::Backspace => self.buffer.backspace(),
+                Key::Delete => self.buffer.delete(),
+                Key::Up => self.buffer.move_up(1),
+                Key::Down => self.buffer.move_down(1),
+                Key::Left => self.buffer.move_left(),
+                Key::Right => self.buffer.move_right(),
+                Key::Home => self.buffer.move_home(),
+                Key::End => self.buffer.move_end(),
+                Key::PageUp => self.buffer.move_up(self.rows),
+                Key::PageDown => self.buffer.move_down(self.rows),
+                Key::Ctrl(_) => {}
+            }
+        }
+    }
+
+    fn save(&mut self) {
+        let text = self.buffer.text();
+        self.message = match write_file(&self.parent, &self.name, text.as_bytes()) {
+            Ok(()) => {
+                self.buffer.mark_saved();
+                format!("Saved {} bytes to {}.", text.len(), self.name)
+            }
+            Err(e) => format!("Failed to save {}: {}", self.name, e),
+        };
+    }
+
+    /// Moves the cursor to the next occurrence of text entered by the user.
+    ///
+    /// If no text is entered, the text that was last searched for is used.
+    fn find(&mut self, stdin: &dyn ImmutableRead) -> Result<(), String> {
+        let Some(query) = self.prompt(stdin, "Search")? else {
+            self.message.clear();
+            return Ok(());
+        };
+        if !query.is_empty() {
+            self.last_query = query;
+        }
+        self.message = if self.buffer.find(&self.last_query) {
+            String::new()
+        } else {
+            format!("{:?} was not found.", self.last_query)
+        };
+        Ok(())
+    }
+
+    /// Reads a line of text entered on the message line,
+    /// returning `None` if the user cancels it with Ctrl+G.
+    fn prompt(&mut self, stdin: &dyn ImmutableRead, label: &str) -> Result<Option<String>, String> {
+        let mut input = String::new();
+        loop {
+            self.draw(Some(&format!("{} (Ctrl+G to cancel): {}", label, input)))?;
+            match self.read_key(stdin)? {
+                Key::Enter => return Ok(Some(input)),
+                Key::Ctrl('g') => return Ok(None),
+                Key::Backspace => {
+                    input.pop();
+                }
+                Key::Char(c) => input.push(c),
+                _ => {}
+            }
+        }
+    }
+
+    fn read_key(&mut self, stdin: &dyn ImmutableRead) -> Result<Key, String> {
+        let mut byte = [0];
+        loop {
+            if stdin.read(&mut byte).map_err(|_| "failed to read from stdin")? == 0 {
+                return Err("stdin was closed".into());
+            }
+            if let Some(key) = self.decoder.push(byte[0]) {
+                return Ok(key);
+            }
+        }
+    }
+
+    /// Redraws the whole screen, with the given prompt in place of the message if there is one.
+    fn draw(&mut self, prompt: Option<&str>) -> Result<(), String> {
+        // Scroll so that the cursor is on the screen.
+        let (row, col) = self.buffer.cursor();
+        self.top = self.top.clamp((row + 1).saturating_sub(self.rows), row);
+        self.left = self.left.clamp((col + 1).saturating_sub(self.columns), col);
+
+        // Hide the cursor while drawing, and start at the top left corner.
+        let mut screen = String::from("\x1b[?25l\x1b[H");
+        for y in self.top..self.top + self.rows {
+            match self.buffer.line(y) {
+                Some(line) => screen.extend(
+                    line.chars()
+                        .skip(self.left)
+                        .take(self.columns)
+                        .map(displayed_char),
+                ),
+                None => screen.push('~'),
+            }
+            // Clear the rest of the line.
+            screen.push_str("\x1b[K\n");
+        }
+
+        let modified = if self.buffer.is_modified() { " [modified]" } else { "" };
+        let name = format!(" {}{}", self.name, modified);
+        let position = format!("line {}/{}, column {} ", row + 1, self.buffer.line_count(), col + 1);
+        let padding = self.columns.saturating_sub(name.chars().count() + position.chars().count());
+        let status = format!("{}{:padding$}{}", name, "", position, padding = padding);
+        // Show the status line in reverse video.
+        screen.push_str("\x1b[7m");
+        screen.extend(status.chars().take(self.columns));
+        screen.push_str("\x1b[m\n");
+
+        // Leave the last column empty, such that the terminal doesn't scroll.
+        let message = prompt.unwrap_or(&self.message);
+        screen.extend(message.chars().take(self.columns - 1).map(displayed_char));
+        screen.push_str("\x1b[K");
+
+        let (cursor_row, cursor_col) = match prompt {
+            Some(prompt) => (self.rows + 2, prompt.chars().count().min(self.columns - 1) + 1),
+            None => (row - self.top + 1, col - self.left + 1),
+        };
+        screen.push_str(&format!("\x1b[{};{}H\x1b[?25h", cursor_row, cursor_col));
+        self.stdout
+            .write_all(screen.as_bytes())
+            .map_err(|_| "failed to write to stdout".into())
+    }
+}
+
+/// Returns the character that's shown on the screen for the given character.
+fn displayed_char(c: char) -> char {
+    match c {
+        '\t' => ' ',
+        c if c.is_control() => '?',
+        c => c,
+    }
+}
+
+fn read_text(file: &FileRef) -> Result<String, String> {
+    let mut file = file.lock();
+    let mut bytes = vec![0; file.len()];
+    if bytes.is_empty() {
+        return Ok(String::new());
+    }
+    let read = file
+        .read_at(&mut bytes, 0)
+        .map_err(|_| format!("failed to read {}", file.get_name()))?;
+    bytes.truncate(read);
+    String::from_utf8(bytes).map_err(|_| format!("{} is not a UTF-8 text file", file.get_name()))
+}
+
+/// Replaces the file with the given name in `parent`, or creates it if it doesn't exist,
+/// with a file containing `contents`.
+///
+/// A new file is inserted rather than writing to the existing file,
+/// because files can't be truncated. Inserting a file also copies its contents
+/// into directories backed by other filesystems.
+fn write_file(parent: &DirRef, name: &str, contents: &[u8]) -> Result<(), &'static str> {
+    let mapped_pages = if contents.is_empty() {
+        MappedPages::empty()
+    } else {
+        let mut mapped_pages = create_mapping(contents.len(), PteFlags::new().valid(true).writable(true))?;
+        mapped_pages
+            .as_slice_mut(0, contents.len())?
+            .copy_from_slice(contents);
+        mapped_pages
+    };
+    MemFile::from_mapped_pages(mapped_pages, name.to_string(), contents.len(), parent)?;
+    Ok(())
+}
@@ -0,0 +1,94 @@
+//! Searches directory trees for files and directories, printing the path of each one that matches.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::{format, string::String, vec::Vec};
+use app_io::println;
+use command::{Arg, Command, Matches, Value};
+
+pub static COMMAND: Command = Command {
+    name: "find",
+    about: "Search directory trees for files and directories",
+    args: &[
+        Arg::option("name")
+            .short('n')
+            .help("only show nodes whose names match this wildcard pattern, e.g., \"*.rs\""),
+        Arg::option("type")
+            .short('t')
+            .value(Value::OneOf(&["f", "d"]))
+            .help("only show files (f) or directories (d)"),
+        Arg::option("max-depth")
+            .short('d')
+            .value(Value::Integer)
+            .help("descend at most this many levels below each PATH"),
+        Arg::positional("PATH")
+            .multiple()
+            .value(Value::Path)
+            .help("the directories to search, which may contain wildcards (default: .)"),
+    ],
+};
+
+pub fn command() -> &'static Command {
+    &COMMAND
+}
+
+pub fn main(args: Vec<String>) -> isize {
+    let matches = match COMMAND.get_matches(args) {
+        Ok(m) => m,
+        Err(exit_value) => return exit_value,
+    };
+
+    match run(&matches) {
+        Ok(()) => 0,
+        Err(e) => {
+            println!("Error: {}", e);
+            -1
+        }
+    }
+}
+
+fn run(matches: &Matches) -> Result<(), String> {
+    let cwd = task::with_current_task(|t| t.get_env().lock().working_dir.clone())
+        .map_err(|_| "failed to get current task")?;
+    let max_depth = matches
+        .value_of("max-depth")
+        .map_err(|e| format!("{}", e))?
+        .unwrap_or(usize::MAX);
+    let name_pattern = matches.value("name");
+    let wanted_type = matches.value("type");
+
+    let paths = match matches.values("PATH") {
+        [] => fs_utils::expand_globs(&["."], &cwd)?,
+        args => fs_utils::expand_globs(args, &cwd)?,
+    };
+    for path in paths {
+        let node = path.get(&cwd).ok_or_else(|| format!("{} doesn't exist", path))?;
+        let root: &str = path.as_ref();
+        let root = root.trim_end_matches('/');
+
+        for entry in node.walk().max_depth(max_depth) {
+            let metadata = entry.node.metadata();
+            let type_matches = match wanted_type {
+                Some("f") => !metadata.is_dir,
+                Some("d") => metadata.is_dir,
+                _ => true,
+            };
+            let name_matches = name_pattern.map_or(true, |pattern| path::glob_match(pattern, &metadata.name));
+            if type_matches && name_matches {
+                println!("{}", display_path(root, &entry.path));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Joins the `root` path that a walk started from and the path of a node relative to it.
+fn display_path(root: &str, relative: &str) -> String {
+    match (root, relative) {
+        (root, "") => String::from(if root.is_empty() { "/" } else { root }),
+        ("", relative) => format!("/{}", relative),
+        (root, relative) => format!("{}/{}", root, relative),
+    }
+}
@@ -0,0 +1,140 @@
+//! Shows the bytes of a file, or of a section loaded by a crate, in hexadecimal and ASCII.
+//!
+//! Each line shows the position of its first byte, followed by up to 16 bytes in hexadecimal
+//! and then as ASCII characters, with non-printable characters shown as `.`.
+//! The bytes of a section are shown at their virtual addresses,
+//! which makes it easy to check the values written into them by relocations.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::{format, string::String, sync::Arc, vec, vec::Vec};
+use app_io::println;
+use command::{Arg, Command, Matches, Value};
+use core::fmt::Write;
+use fs_node::FileOrDir;
+use mod_mgmt::{CrateNamespace, StrongSectionRef};
+use path::Path;
+
+const BYTES_PER_LINE: usize = 16;
+
+pub static COMMAND: Command = Command {
+    name: "hexdump",
+    about: "Show the bytes of a file or a loaded section in hexadecimal and ASCII",
+    args: &[
+        Arg::flag("symbol")
+            .short('s')
+            .help("treat TARGET as the symbol name, or a unique prefix of it, of a loaded section"),
+        Arg::option("offset")
+            .short('o')
+            .value(Value::Integer)
+            .help("skip this many bytes from the start of TARGET"),
+        Arg::option("length")
+            .short('n')
+            .value(Value::Integer)
+            .help("show at most this many bytes"),
+        Arg::positional("TARGET")
+            .required()
+            .value(Value::Path)
+            .help("the file to show, or a section's symbol if --symbol is given"),
+    ],
+};
+
+pub fn command() -> &'static Command {
+    &COMMAND
+}
+
+pub fn main(args: Vec<String>) -> isize {
+    let matches = match COMMAND.get_matches(args) {
+        Ok(m) => m,
+        Err(exit_value) => return exit_value,
+    };
+
+    match run(&matches) {
+        Ok(()) => 0,
+        Err(e) => {
+            println!("Error: {}", e);
+            -1
+        }
+    }
+}
+
+fn run(matches: &Matches) -> Result<(), String> {
+    let offset = matches.value_of("offset").map_err(|e| format!("{}", e))?.unwrap_or(0);
+    let length = matches.value_of("length").map_err(|e| format!("{}", e))?.unwrap_or(usize::MAX);
+    let target = matches.value("TARGET").ok_or("missing TARGET argument")?;
+
+    if matches.is_present("symbol") {
+        let section = find_section(target)?;
+        let mapped_pages = section.mapped_pages.lock();
+        let bytes: &[u8] = mapped_pages.as_slice(section.mapped_pages_offset, section.size)?;
+        let bytes = bytes.get(offset..).unwrap_or(&[]);
+        println!("{} ({} bytes at {:#X}):", section.name, section.size, section.virt_addr);
+        dump(section.virt_addr.value() + offset, 16, &bytes[..length.min(bytes.len())]);
+    } else {
+        dump(offset, 8, &read_file(target, offset, length)?);
+    }
+    Ok(())
+}
+
+/// Prints the given bytes, labeling each line with its position
+/// as a hexadecimal number with the given number of digits.
+fn dump(start: usize, digits: usize, bytes: &[u8]) {
+    for (i, chunk) in bytes.chunks(BYTES_PER_LINE).enumerate() {
+        let mut line = format!("{:0digits$x} ", start + i * BYTES_PER_LINE, digits = digits);
+        for j in 0..BYTES_PER_LINE {
+            // Separate the two halves of the line.
+            if j == BYTES_PER_LINE / 2 {
+                line.push(' ');
+            }
+            match chunk.get(j) {
+                Some(byte) => { let _ = write!(line, " {:02x}", byte); }
+                None => line.push_str("   "),
+            }
+        }
+        line.push_str("  |");
+        line.extend(chunk.iter().map(|&byte| {
+            if byte.is_ascii_graphic() || byte == b' ' { byte as char } else { '.' }
+        }));
+        line.push('|');
+        println!("{}", line);
+    }
+}
+
+/// Reads up to `length` bytes from the file at the given path, starting at `offset`.
+fn read_file(path: &str, offset: usize, length: usize) -> Result<Vec<u8>, String> {
+    let cwd = task::with_current_task(|t| t.get_env().lock().working_dir.clone())
+        .map_err(|_| "failed to get current task")?;
+    let file = match Path::new(path).get(&cwd) {
+        Some(FileOrDir::File(file)) => file,
+        Some(FileOrDir::Dir(_)) => return Err(format!("{} is a directory", path)),
+        None => return Err(format!("{} doesn't exist", path)),
+    };
+    let mut file = file.lock();
+    let mut bytes = vec![0; length.min(file.len().saturating_sub(offset))];
+    if !bytes.is_empty() {
+        let read = file
+            .read_at(&mut bytes, offset)
+            .map_err(|_| format!("failed to read {}", path))?;
+        bytes.truncate(read);
+    }
+    Ok(bytes)
+}
+
+/// Returns the loaded section whose symbol is `name`, or uniquely starts with `name`.
+fn find_section(name: &str) -> Result<StrongSectionRef, String> {
+    let namespace: Arc<CrateNamespace> = task::with_current_task(|t| t.get_namespace().clone())
+        .map_err(|_| "failed to get current task")?;
+    let matches = namespace.find_symbols_starting_with(name);
+    let (symbol, section) = match matches.iter().find(|(symbol, _)| symbol == name) {
+        Some(exact_match) => exact_match,
+        None if matches.len() == 1 => &matches[0],
+        None if matches.is_empty() => return Err(format!("couldn't find a section for symbol {}", name)),
+        None => {
+            let symbols: Vec<&str> = matches.iter().map(|(symbol, _)| symbol.as_str()).collect();
+            return Err(format!("{} matches multiple symbols:\n{}", name, symbols.join("\n")));
+        }
+    };
+    section.upgrade().ok_or_else(|| format!("section {} was unloaded", symbol))
+}
@@ -0,0 +1,246 @@
+//! This application runs an HTTP server that serves JSON views of the running system,
+//! such that browsers and scripts can observe it remotely.
+//!
+//! The following endpoints are served:
+//! * `/tasks`: every task and its state.
+//! * `/memory`: how much physical memory is in use.
+//! * `/crates`: the crates loaded into each namespace visible to the server.
+//! * `/interrupts`: the registered interrupt handlers (x86_64 only).
+//! * `/metrics`: all registered metrics, in the Prometheus text format.
+//! * `/metrics.json`: all registered metrics and their samples, as JSON.
+//! * `/sysreport`: a full system report, as produced by the `sysreport` application.
+//!
+//! When running in QEMU with `net=user`, the server can be reached from the host
+//! by forwarding a host port to it, e.g., with `hostfwd=tcp::8080-:80`.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::{format, string::String, vec::Vec};
+use app_io::println;
+use core::fmt::Write;
+use getopts::{Matches, Options};
+use http_server::{HttpServer, Request, Response, Router};
+use serialize::Serialize;
+
+/// The port that the server listens on by default.
+const DEFAULT_PORT: u16 = 80;
+
+/// The number of clients that the server serves at once by default.
+const DEFAULT_MAX_CONNECTIONS: usize = 4;
+
+pub fn main(args: Vec<String>) -> isize {
+    let mut opts = Options::new();
+    opts.optflag("h", "help", "print this help menu");
+    opts.optopt("p", "port", "listen on <port> (default: 80)", "<port>");
+    opts.optopt(
+        "I",
+        "interface",
+        "listen on <interface> (default: the default interface)",
+        "<interface>",
+    );
+    opts.optopt(
+        "c",
+        "connections",
+        "serve up to <connections> clients at once (default: 4)",
+        "<connections>",
+    );
+
+    let matches = match opts.parse(args) {
+        Ok(m) => m,
+        Err(_f) => {
+            println!("{}", _f);
+            print_usage(&opts);
+            return -1;
+        }
+    };
+
+    if matches.opt_present("h") {
+        print_usage(&opts);
+        return 0;
+    }
+
+    match run(matches) {
+        Ok(()) => 0,
+        Err(e) => {
+            println!("Error: {}", e);
+            -1
+        }
+    }
+}
+
+fn run(matches: Matches) -> Result<(), &'static str> {
+    let interface = match matches.opt_str("I") {
+        Some(name) => net::get_interface(&name).ok_or("no such network interface")?,
+        None => net::get_default_interface().ok_or("no network interfaces available")?,
+    };
+    let port = matches
+        .opt_get_default("p", DEFAULT_PORT)
+        .map_err(|_| "invalid port")?;
+    let max_connections = matches
+        .opt_get_default("c", DEFAULT_MAX_CONNECTIONS)
+        .map_err(|_| "invalid number of connections")?;
+
+    let mut router = Router::new();
+    router
+        .get("/tasks", tasks)
+        .get("/memory", memory)
+        .get("/crates", crates)
+        .get("/metrics", metrics)
+        .get("/metrics.json", metrics_json)
+        .get("/sysreport", sysreport);
+    #[cfg(target_arch = "x86_64")]
+    router.get("/interrupts", interrupts);
+
+    let paths: Vec<String> = router.paths().map(String::from).collect();
+    router.get("/", move |_| {
+        let mut body = String::from("Theseus system status\n\n");
+        for path in &paths {
+            let _ = writeln!(body, "{path}");
+        }
+        Response::text(body)
+    });
+
+    let mut server = HttpServer::new(interface.clone(), port, router, max_connections)?;
+    let addrs = interface.ip_addrs();
+    match addrs.first() {
+        Some(addr) => println!("serving on http://{}:{}/", addr.address(), port),
+        None => println!("serving on port {} of {}", port, interface.name()),
+    }
+    server.run()
+}
+
+#[derive(Serialize)]
+struct TaskInfo {
+    id: usize,
+    name: String,
+    runstate: String,
+    cpu: Option<u32>,
+    pinned_cpu: Option<u32>,
+    is_application: bool,
+    is_idle: bool,
+}
+
+fn tasks(_: &Request) -> Response {
+    let tasks: Vec<TaskInfo> = task::all_tasks()
+        .iter()
+        .filter_map(|(_, t)| t.upgrade())
+        .map(|task| TaskInfo {
+            id: task.id,
+            name: task.name.clone(),
+            runstate: format!("{:?}", task.runstate()),
+            cpu: task.running_on_cpu().map(|c| c.value()),
+            pinned_cpu: task.pinned_cpu().map(|c| c.value()),
+            is_application: task.is_application(),
+            is_idle: task.is_an_idle_task,
+        })
+        .collect();
+    Response::serialized(&tasks)
+}
+
+#[derive(Serialize)]
+struct MemoryInfo {
+    frame_size: usize,
+    general_frames: usize,
+    free_general_frames: usize,
+    reserved_frames: usize,
+    free_reserved_frames: usize,
+    total_bytes: usize,
+    free_bytes: usize,
+}
+
+fn memory(_: &Request) -> Response {
+    let stats = frame_allocator::stats();
+    let frame_size = memory::PAGE_SIZE;
+    Response::serialized(&MemoryInfo {
+        frame_size,
+        general_frames: stats.general_frames,
+        free_general_frames: stats.free_general_frames,
+        reserved_frames: stats.reserved_frames,
+        free_reserved_frames: stats.free_reserved_frames,
+        total_bytes: stats.general_frames * frame_size,
+        free_bytes: stats.free_general_frames * frame_size,
+    })
+}
+
+#[derive(Serialize)]
+struct NamespaceInfo<'n> {
+    namespace: &'n str,
+    crates: Vec<String>,
+}
+
+fn crates(_: &Request) -> Response {
+    let Ok(namespace) = task::with_current_task(|t| t.get_namespace().clone()) else {
+        return Response::error(500);
+    };
+    let mut namespaces: Vec<NamespaceInfo> = Vec::new();
+    let mut next = Some(&namespace);
+    while let Some(ns) = next {
+        let mut crates: Vec<String> = ns
+            .crate_names(false)
+            .iter()
+            .map(|name| String::from(&**name))
+            .collect();
+        crates.sort();
+        namespaces.push(NamespaceInfo {
+            namespace: ns.name(),
+            crates,
+        });
+        next = ns.recursive_namespace();
+    }
+    Response::serialized(&namespaces)
+}
+
+fn metrics(_: &Request) -> Response {
+    let mut body = String::new();
+    if metrics::render_prometheus(&mut body).is_err() {
+        return Response::error(500);
+    }
+    Response::with_body(200, "text/plain; version=0.0.4", body)
+}
+
+fn metrics_json(_: &Request) -> Response {
+    Response::serialized(&metrics::snapshot())
+}
+
+fn sysreport(_: &Request) -> Response {
+    Response::serialized(&system_report::generate())
+}
+
+#[cfg(target_arch = "x86_64")]
+#[derive(Serialize)]
+struct InterruptInfo {
+    vector: u8,
+    handler_address: String,
+    handler: Option<String>,
+}
+
+#[cfg(target_arch = "x86_64")]
+fn interrupts(_: &Request) -> Response {
+    let mut handlers = Vec::new();
+    interrupts::for_each_registered_interrupt(|num, addr| handlers.push((num, addr)));
+
+    // Look up handler names after the IDT has been unlocked, since that can be slow.
+    let namespace = mod_mgmt::get_initial_kernel_namespace();
+    let handlers: Vec<InterruptInfo> = handlers
+        .into_iter()
+        .map(|(num, addr)| InterruptInfo {
+            vector: num,
+            handler_address: format!("{:#x}", addr),
+            handler: namespace
+                .and_then(|ns| {
+                    ns.get_section_containing_address(memory::VirtualAddress::new_canonical(addr), false)
+                })
+                .map(|(section, _)| String::from(&*section.name)),
+        })
+        .collect();
+    Response::serialized(&handlers)
+}
+
+fn print_usage(opts: &Options) {
+    println!("{}", opts.usage(USAGE));
+}
+
+const USAGE: &str = "Usage: httpd [OPTION]...
+Serves JSON views of tasks, memory, crates, and interrupts, and all metrics, over HTTP.";
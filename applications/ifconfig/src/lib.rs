@@ -0,0 +1,125 @@
+//! This application shows and configures network interfaces.
+//!
+//! Without arguments, it shows all interfaces.
+//! Given an interface name, it shows that interface and applies any settings that follow it.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::{string::String, sync::Arc, vec::Vec};
+use app_io::println;
+use core::str::FromStr;
+use getopts::Options;
+use net::{IpAddress, IpCidr, NetworkInterface};
+
+pub fn main(args: Vec<String>) -> isize {
+    let mut opts = Options::new();
+    opts.optflag("h", "help", "print this help menu");
+
+    let matches = match opts.parse(args) {
+        Ok(m) => m,
+        Err(_f) => {
+            println!("{}", _f);
+            print_usage(&opts);
+            return -1;
+        }
+    };
+
+    if matches.opt_present("h") {
+        print_usage(&opts);
+        return 0;
+    }
+
+    match run(&matches.free) {
+        Ok(()) => 0,
+        Err(e) => {
+            println!("Error: {}", e);
+            -1
+        }
+    }
+}
+
+fn run(args: &[String]) -> Result<(), &'static str> {
+    let Some((name, settings)) = args.split_first() else {
+        for interface in net::get_interfaces().lock().iter() {
+            print_interface(interface);
+        }
+        return Ok(());
+    };
+
+    let interface = net::get_interface(name).ok_or("no such interface")?;
+    let mut settings = settings.iter();
+    while let Some(setting) = settings.next() {
+        match setting.as_str() {
+            "up" => interface.set_up(true),
+            "down" => interface.set_up(false),
+            "mtu" => {
+                let mtu = settings.next().ok_or("missing MTU")?;
+                interface.set_mtu(mtu.parse().map_err(|_| "invalid MTU")?)?;
+            }
+            "addr" => {
+                let addrs = settings.next().ok_or("missing address")?;
+                let addrs = addrs
+                    .split(',')
+                    .map(IpCidr::from_str)
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| "invalid address, expected <ip>/<prefix length>")?;
+                interface.set_ip_addrs(&addrs)?;
+            }
+            "gateway" => {
+                let gateway = match settings.next().ok_or("missing gateway")?.as_str() {
+                    "none" => None,
+                    ip => Some(IpAddress::from_str(ip).map_err(|_| "invalid gateway address")?),
+                };
+                interface.set_gateway(gateway)?;
+            }
+            _ => return Err("unknown setting"),
+        }
+    }
+    print_interface(&interface);
+    Ok(())
+}
+
+fn print_interface(interface: &Arc<NetworkInterface>) {
+    let state = if interface.is_up() { "UP" } else { "DOWN" };
+    let kind = if interface.is_loopback() { ",LOOPBACK" } else { "" };
+    println!("{}: <{}{}> mtu {}", interface.name(), state, kind, interface.mtu());
+    let mac = interface.mac_address();
+    println!(
+        "    ether {:02x}:{:02x}:{:02x}:{:02x}:{:02x}:{:02x}",
+        mac[0], mac[1], mac[2], mac[3], mac[4], mac[5]
+    );
+    for addr in interface.ip_addrs() {
+        println!("    inet {}", addr);
+    }
+    if let Some(gateway) = interface.gateway() {
+        println!("    gateway {}", gateway);
+    }
+    let counters = interface.counters();
+    println!(
+        "    RX packets {} bytes {} dropped {}",
+        counters.rx_packets, counters.rx_bytes, counters.rx_dropped
+    );
+    println!(
+        "    RX zero-copy packets {} bytes {} copied bytes {}",
+        counters.rx_zero_copy_packets, counters.rx_zero_copy_bytes, counters.rx_copied_bytes
+    );
+    println!(
+        "    TX packets {} bytes {} dropped {}",
+        counters.tx_packets, counters.tx_bytes, counters.tx_dropped
+    );
+}
+
+fn print_usage(opts: &Options) {
+    println!("{}", opts.usage(USAGE));
+}
+
+const USAGE: &str = "Usage: ifconfig [OPTION]... [INTERFACE [SETTING]...]
+Shows all network interfaces, or shows and configures the given INTERFACE.
+
+Settings:
+    up | down              bring the interface up or down
+    mtu <bytes>            set the MTU
+    addr <ip>/<prefix>     replace the interface's addresses (comma-separated)
+    gateway <ip> | none    set or remove the default gateway";
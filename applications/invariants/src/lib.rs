@@ -0,0 +1,86 @@
+//! Enables, disables, or runs the invariant checks of kernel subsystems.
+//!
+//! Without any options, this lists all subsystems and the most recently reported violations.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::{string::String, vec::Vec};
+use app_io::println;
+use command::{Arg, Command, Matches};
+
+pub static COMMAND: Command = Command {
+    name: "invariants",
+    about: "Enable, disable, or run the invariant checks of kernel subsystems",
+    args: &[
+        Arg::option("enable")
+            .short('e')
+            .multiple()
+            .help("enable the checks of the given subsystem, which then run periodically"),
+        Arg::option("disable")
+            .short('d')
+            .multiple()
+            .help("disable the checks of the given subsystem"),
+        Arg::option("run")
+            .short('r')
+            .multiple()
+            .help("run the checks of the given subsystem once, even if it's disabled"),
+    ],
+};
+
+pub fn command() -> &'static Command {
+    &COMMAND
+}
+
+pub fn main(args: Vec<String>) -> isize {
+    let matches = match COMMAND.get_matches(args) {
+        Ok(m) => m,
+        Err(exit_value) => return exit_value,
+    };
+
+    match run(&matches) {
+        Ok(()) => 0,
+        Err(e) => {
+            println!("Error: {}", e);
+            -1
+        }
+    }
+}
+
+fn run(matches: &Matches) -> Result<(), &'static str> {
+    let (enable, disable, run) = (matches.values("enable"), matches.values("disable"), matches.values("run"));
+    if enable.is_empty() && disable.is_empty() && run.is_empty() {
+        print_status();
+        return Ok(());
+    }
+
+    for subsystem in disable {
+        debug_invariants::set_enabled(subsystem, false)?;
+        println!("Disabled the invariant checks of {}", subsystem);
+    }
+    for subsystem in enable {
+        debug_invariants::set_enabled(subsystem, true)?;
+        println!("Enabled the invariant checks of {}", subsystem);
+    }
+    for subsystem in run {
+        let violations = debug_invariants::run_checks(subsystem)?;
+        println!("{}: {} violation(s)", subsystem, violations.len());
+        for violation in violations {
+            println!("    {}", violation);
+        }
+    }
+    Ok(())
+}
+
+fn print_status() {
+    println!("Subsystems:");
+    for (subsystem, enabled) in debug_invariants::subsystem_states() {
+        println!("    {:<16} {}", subsystem, if enabled { "enabled" } else { "disabled" });
+    }
+    let recent = debug_invariants::recent_violations();
+    println!("Violations: {} in total, {} recent", debug_invariants::violation_count(), recent.len());
+    for violation in recent {
+        println!("    {}", violation);
+    }
+}
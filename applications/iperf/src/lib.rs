@@ -0,0 +1,330 @@
+//! Measures TCP throughput between Theseus and another host, similar to `iperf3`.
+//!
+//! In server mode, this waits for a single connection and receives data until the client closes it.
+//! In client mode, this connects to a server and sends data for a given number of seconds.
+//! Either side can be a regular iperf server or client, e.g., `iperf -c <theseus> -p 5201`,
+//! although the iperf3 control protocol isn't supported.
+//!
+//! The socket options can be tuned to compare their effect on throughput,
+//! e.g., a window larger than 64 KiB is only possible with window scaling.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use app_io::println;
+use command::{Arg, Command, Matches, Value};
+use core::{str::FromStr, time::Duration};
+use fmt_utils::Size;
+use net::{tcp, IpAddress, NetworkInterface, Socket, TcpOptions};
+use time::Instant;
+
+/// The port that iperf3 listens on by default.
+const DEFAULT_PORT: u16 = 5201;
+
+/// How long the interface is left idle when no data could be sent or received.
+const IDLE_SLEEP: Duration = Duration::from_millis(1);
+
+/// How long to wait for a connection to be established or closed.
+const CONNECTION_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub static COMMAND: Command = Command {
+    name: "iperf",
+    about: "Measure TCP throughput to or from another host",
+    args: &[
+        Arg::flag("server").short('s').help("receive data from a single client"),
+        Arg::option("client")
+            .short('c')
+            .value(Value::Any)
+            .help("send data to the server at this IP address"),
+        Arg::option("port")
+            .short('p')
+            .value(Value::Integer)
+            .help("the server's port (default: 5201)"),
+        Arg::option("time")
+            .short('t')
+            .value(Value::Integer)
+            .help("the number of seconds to send data for (default: 10)"),
+        Arg::option("interval")
+            .short('i')
+            .value(Value::Integer)
+            .help("the number of seconds between throughput reports (default: 1)"),
+        Arg::option("length")
+            .short('l')
+            .value(Value::Integer)
+            .help("the number of bytes to send at once (default: 16384)"),
+        Arg::option("window")
+            .short('w')
+            .value(Value::Integer)
+            .help("the size of the socket buffers in bytes (default: 262144)"),
+        Arg::flag("no-delay")
+            .short('N')
+            .help("disable Nagle's algorithm, sending small segments immediately"),
+        Arg::option("ack-delay")
+            .value(Value::Integer)
+            .help("the number of milliseconds to delay ACKs by, or 0 to disable delayed ACKs (default: 10)"),
+        Arg::option("interface")
+            .short('I')
+            .value(Value::Any)
+            .help("the network interface to use (default: the default interface)"),
+    ],
+};
+
+pub fn command() -> &'static Command {
+    &COMMAND
+}
+
+pub fn main(args: Vec<String>) -> isize {
+    let matches = match COMMAND.get_matches(args) {
+        Ok(m) => m,
+        Err(exit_value) => return exit_value,
+    };
+
+    match run(&matches) {
+        Ok(()) => 0,
+        Err(e) => {
+            println!("Error: {}", e);
+            -1
+        }
+    }
+}
+
+fn run(matches: &Matches) -> Result<(), String> {
+    let interface = match matches.value("interface") {
+        Some(name) => net::get_interface(name).ok_or("no such network interface")?,
+        None => net::get_default_interface().ok_or("no network interfaces available")?,
+    };
+    let port = value_or(matches, "port", DEFAULT_PORT)?;
+    let interval = Duration::from_secs(value_or(matches, "interval", 1)?.max(1));
+
+    let window = value_or(matches, "window", 256 * 1024)?;
+    let options = TcpOptions {
+        rx_buffer_size: window,
+        tx_buffer_size: window,
+        nagle: !matches.is_present("no-delay"),
+        ack_delay: match value_or(matches, "ack-delay", 10)? {
+            0 => None,
+            millis => Some(Duration::from_millis(millis)),
+        },
+        ..Default::default()
+    };
+    println!(
+        "TCP window: {} (scale {}), Nagle: {}, delayed ACKs: {}",
+        Size::from(window),
+        options.window_shift(),
+        if options.nagle { "on" } else { "off" },
+        match options.ack_delay {
+            Some(delay) => format!("{} ms", delay.as_millis()),
+            None => "off".to_string(),
+        },
+    );
+    let socket = interface.clone().add_tcp_socket(&options)?;
+
+    match (matches.is_present("server"), matches.value("client")) {
+        (true, None) => serve(&interface, &socket, port, interval),
+        (false, Some(server)) => {
+            let server = IpAddress::from_str(server).map_err(|_| "invalid server address")?;
+            let duration = Duration::from_secs(value_or(matches, "time", 10)?);
+            let length = value_or(matches, "length", 16384)?.max(1);
+            send(&interface, &socket, (server, port), duration, interval, length)
+        }
+        _ => Err("exactly one of --server and --client must be given".into()),
+    }
+}
+
+/// Returns the value of the given option, or `default` if it wasn't given.
+fn value_or<T: FromStr>(matches: &Matches, name: &str, default: T) -> Result<T, String> {
+    Ok(matches
+        .value_of(name)
+        .map_err(|e| format!("{}", e))?
+        .unwrap_or(default))
+}
+
+/// Accepts a single connection on the given port and receives data until it's closed.
+fn serve(
+    interface: &NetworkInterface,
+    socket: &Socket<tcp::Socket<'static>>,
+    port: u16,
+    interval: Duration,
+) -> Result<(), String> {
+    socket
+        .lock()
+        .listen(port)
+        .map_err(|_| "failed to listen on port")?;
+    println!("Server listening on port {}", port);
+
+    while !socket.lock().is_active() {
+        poll_or_sleep(interface, false);
+    }
+    let remote = socket.lock().remote_endpoint();
+    if let Some(remote) = remote {
+        println!("Accepted connection from {}", remote);
+    }
+
+    let mut report = Report::new(interval);
+    loop {
+        let mut locked = socket.lock();
+        if !locked.may_recv() && !locked.can_recv() {
+            break;
+        }
+        let received = match locked.can_recv() {
+            true => locked
+                .recv(|data| (data.len(), data.len()))
+                .map_err(|_| "failed to receive data")?,
+            false => 0,
+        };
+        drop(locked);
+
+        report.add(received);
+        poll_or_sleep(interface, received > 0);
+    }
+
+    // Wait for the client to acknowledge that the connection was closed.
+    socket.lock().close();
+    let closing = Instant::now();
+    while socket.lock().state() == tcp::State::LastAck && closing.elapsed() < CONNECTION_TIMEOUT {
+        poll_or_sleep(interface, false);
+    }
+    report.finish("received");
+    Ok(())
+}
+
+/// Connects to the given server and sends data to it for the given duration.
+fn send(
+    interface: &NetworkInterface,
+    socket: &Socket<tcp::Socket<'static>>,
+    server: (IpAddress, u16),
+    duration: Duration,
+    interval: Duration,
+    length: usize,
+) -> Result<(), String> {
+    // Use a random ephemeral port, as smoltcp doesn't pick one.
+    let local_port = 49152 + (random::next_u32() % 16384) as u16;
+    socket
+        .lock()
+        .connect(server, local_port)
+        .map_err(|_| "failed to connect socket")?;
+
+    let start = Instant::now();
+    loop {
+        let state = socket.lock().state();
+        match state {
+            tcp::State::Established => break,
+            tcp::State::Closed => return Err("connection refused".into()),
+            _ if start.elapsed() >= CONNECTION_TIMEOUT => {
+                socket.lock().abort();
+                return Err("timed out connecting to server".into());
+            }
+            _ => poll_or_sleep(interface, false),
+        }
+    }
+    println!("Connected to {}:{}", server.0, server.1);
+
+    let data = vec![0u8; length];
+    let mut report = Report::new(interval);
+    while report.start.elapsed() < duration {
+        let mut locked = socket.lock();
+        if !locked.may_send() {
+            return Err("connection closed by server".into());
+        }
+        let sent = match locked.can_send() {
+            true => locked.send_slice(&data).map_err(|_| "failed to send data")?,
+            false => 0,
+        };
+        drop(locked);
+
+        report.add(sent);
+        poll_or_sleep(interface, sent > 0);
+    }
+
+    // Wait for the remaining data to be acknowledged before reporting the total.
+    socket.lock().close();
+    let closing = Instant::now();
+    while socket.lock().send_queue() > 0 && closing.elapsed() < CONNECTION_TIMEOUT {
+        poll_or_sleep(interface, false);
+    }
+    report.finish("sent");
+    Ok(())
+}
+
+/// Polls the interface, and sleeps briefly if neither it nor the caller made any progress.
+fn poll_or_sleep(interface: &NetworkInterface, made_progress: bool) {
+    if !interface.poll() && !made_progress {
+        let _ = sleep::sleep(IDLE_SLEEP);
+    }
+}
+
+/// Tracks the bytes transferred, and prints the throughput of each interval.
+struct Report {
+    start: Instant,
+    interval: Duration,
+    interval_start: Instant,
+    interval_bytes: usize,
+    total_bytes: usize,
+}
+
+impl Report {
+    fn new(interval: Duration) -> Report {
+        let now = Instant::now();
+        println!("{:<17} {:>10} {:>14}", "Interval", "Transfer", "Bitrate");
+        Report {
+            start: now,
+            interval,
+            interval_start: now,
+            interval_bytes: 0,
+            total_bytes: 0,
+        }
+    }
+
+    /// Adds bytes to the current interval, and prints it if it's over.
+    fn add(&mut self, bytes: usize) {
+        self.interval_bytes += bytes;
+        self.total_bytes += bytes;
+        if self.interval_start.elapsed() >= self.interval {
+            let now = Instant::now();
+            self.print_line(self.interval_start, now, self.interval_bytes, "");
+            self.interval_start = now;
+            self.interval_bytes = 0;
+        }
+    }
+
+    /// Prints the total throughput.
+    fn finish(&self, direction: &str) {
+        println!("- - - - - - - - - - - - - - - - - - - - - - -");
+        self.print_line(self.start, Instant::now(), self.total_bytes, direction);
+    }
+
+    fn print_line(&self, from: Instant, to: Instant, bytes: usize, suffix: &str) {
+        let offset = |instant: Instant| instant.duration_since(self.start).as_secs_f64();
+        let elapsed = to.duration_since(from).as_secs_f64();
+        println!(
+            "{:>6.2}-{:<6.2} sec {:>10} {:>14} {}",
+            offset(from),
+            offset(to),
+            Size::from(bytes),
+            bitrate(bytes, elapsed),
+            suffix,
+        );
+    }
+}
+
+/// Formats the given throughput in decimal bits per second, as iperf does.
+fn bitrate(bytes: usize, seconds: f64) -> String {
+    const UNITS: [&str; 4] = ["bits/sec", "Kbits/sec", "Mbits/sec", "Gbits/sec"];
+    if seconds <= 0.0 {
+        return "-".to_string();
+    }
+    let mut rate = bytes as f64 * 8.0 / seconds;
+    let mut unit = 0;
+    while rate >= 1000.0 && unit < UNITS.len() - 1 {
+        rate /= 1000.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", rate, UNITS[unit])
+}
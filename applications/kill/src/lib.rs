@@ -1,113 +1,71 @@
 #![no_std]
 extern crate alloc;
 #[macro_use] extern crate app_io;
-// #[macro_use] extern crate debugit;
 
 extern crate task;
-extern crate getopts;
+extern crate command;
 
-use getopts::Options;
 use alloc::vec::Vec;
-use alloc::string::{String, ToString};
+use alloc::string::String;
+use command::{Arg, Command, Value};
+use task::control::{Action, Notification};
 
-pub fn main(args: Vec<String>) -> isize {
-    let mut opts = Options::new();
-    
-    opts.optflag("h", "help", "print this help menu");
-    opts.optflag("r", "reap", 
-        "reap the task (consume its exit value) in addition to killing it, removing it from the task list."
-    );
-
-    let matches = match opts.parse(args) {
-        Ok(m) => m,
-        Err(_f) => {
-            println!("{}", _f);
-            return -1; 
-        }
-    };
+pub static COMMAND: Command = Command {
+    name: "kill",
+    about: "Send a notification to tasks, which kills them by default, or suspend or resume them",
+    args: &[
+        Arg::option("signal")
+            .short('s')
+            .value(Value::OneOf(&["kill", "interrupt", "user1", "user2", "suspend", "resume"]))
+            .help("the notification to send (default: kill), or suspend or resume the tasks instead"),
+        Arg::positional("TASK_ID")
+            .required()
+            .multiple()
+            .value(Value::Integer)
+            .help("the IDs of the tasks, as shown by `ps`"),
+    ],
+};
 
-    if matches.opt_present("h") {
-        return print_usage(opts);
-    }
-
-    println!("`kill` has temporarily been disabled because it needs to be reimplemented.");
-    -1
+pub fn command() -> &'static Command {
+    &COMMAND
 }
 
-/*
-    let reap = matches.opt_present("r");
-
-    for task_id_str in matches.free.iter() {
-        match task_id_str.parse::<usize>(){
-            Ok(task_id) => {
-                match kill_task(task_id, reap) {
-                    Ok(_) => { }
-                    Err(e) => {
-                        println!("{}", e);
-                        return -1;
-                    }
-                }
-            }, 
-            _ => { 
-                println!("Invalid argument {}, not a valid task ID (usize)", task_id_str); 
-                return -1;
-            },
-        };   
-    }
-    0
-}
+pub fn main(args: Vec<String>) -> isize {
+    let matches = match COMMAND.get_matches(args) {
+        Ok(m) => m,
+        Err(exit_value) => return exit_value,
+    };
 
+    let action = match matches.value("signal") {
+        Some("interrupt") => Action::Notify(Notification::Interrupt),
+        Some("user1") => Action::Notify(Notification::User1),
+        Some("user2") => Action::Notify(Notification::User2),
+        Some("suspend") => Action::Suspend,
+        Some("resume") => Action::Resume,
+        _ => Action::Notify(Notification::Kill),
+    };
 
-fn kill_task(task_id: usize, reap: bool) -> Result<(), String> {
-    if let Some(task_ref) = task::get_task(task_id) {
-        if task_ref.kill(task::KillReason::Requested)
-            .and_then(|_| runqueue::remove_task_from_all(&task_ref))
-            .is_ok() 
-        {
-            println!("Killed task {}", &*task_ref);
-            if reap {
-                match task_ref.take_exit_value() {
-                    Some(exit_val) => { 
-                        println!("Reaped task {}, got exit value {}", task_id, debugit!(exit_val));
-                        Ok(())
-                    }
-                    _ => {
-                        Err(format!("Failed to reap task {}", task_id))
-                    }
-                }
-            } 
-            else {
-                // killed the task successfully, no reap request, so return success.
-                Ok(())
-            }
-        }
-        else if reap {
-            // if we failed to kill the task, but it was a reap request, then reap it anyway.
-            match task_ref.take_exit_value() {
-                Some(exit_val) => {
-                    println!("Reaped task {}, got exit value {}", task_id, debugit!(exit_val));
-                    Ok(())
-                }
-                _ => {
-                    Err(format!("Failed to reap task {}", task_id))
-                }
-            }
+    let mut exit_value = 0;
+    for task_id in matches.values("TASK_ID") {
+        let result = task_id
+            .parse::<usize>()
+            .map_err(|_| "not a valid task ID")
+            .and_then(|id| task::control::request(id, action));
+        if let Err(e) = result {
+            println!("Failed to {} task {}: {}", verb(action), task_id, e);
+            exit_value = -1;
         }
-        else {
-            // failed to kill the task, no reap request, so return failure.
-            Err(format!("Failed to kill task {}, it was already exited.", task_id))
-        }
-    }
-    else {
-        Err(format!("Task ID {} does not exist", task_id))
     }
+    exit_value
 }
-*/
 
-#[allow(dead_code)]
-fn print_usage(opts: Options) -> isize {
-    let brief = "Usage: kill [OPTS] TASK_ID".to_string();
-    println!("{}", opts.usage(&brief));
-    0
+/// Returns how the given action is described in error messages.
+fn verb(action: Action) -> &'static str {
+    match action {
+        Action::Notify(Notification::Kill) => "kill",
+        Action::Notify(_) => "notify",
+        Action::Suspend => "suspend",
+        Action::Resume => "resume",
+        Action::SetPriority(_) => "set the priority of",
+    }
 }
-
@@ -12,7 +12,6 @@ extern crate getopts;
 extern crate fs_node;
 extern crate path;
 extern crate memory;
-extern crate rustc_demangle;
 extern crate mod_mgmt;
 extern crate task;
 extern crate xmas_elf;
@@ -25,9 +24,8 @@ use core::{
 use alloc::{collections::BTreeSet, string::{String, ToString}, sync::Arc, vec::Vec};
 use getopts::{Matches, Options};
 use memory::{Page, MappedPages, VirtualAddress, PteFlagsArch, PteFlags};
-use mod_mgmt::{CrateNamespace, StrongDependency, find_symbol_table, RelocationEntry, write_relocation};
+use mod_mgmt::{CrateNamespace, StrongDependency, demangle_symbol, find_symbol_table, RelocationEntry, write_relocation};
 use path::Path;
-use rustc_demangle::demangle;
 use xmas_elf::{
     ElfFile,
     program::SegmentData,
@@ -432,7 +430,7 @@ fn overwrite_relocations(
                     source_sec_entry.shndx(), source_sec_entry.value(), source_sec_entry.size());
             }
 
-            let demangled = demangle(source_sec_name).to_string();
+            let demangled = demangle_symbol(source_sec_name);
 
             // If the source section exists in this namespace already, rewrite the relocation entry to point to the existing section instead.
             if let Some(existing_source_sec) = namespace.get_symbol_or_load(&demangled, None, mmi, verbose_log).upgrade() {
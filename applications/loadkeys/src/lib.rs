@@ -0,0 +1,64 @@
+//! Switches the keyboard layout of the current terminal, like Unix's `loadkeys`.
+//!
+//! Without arguments, it lists the available layouts and marks the one in use.
+//! The layout is stored in the terminal's `KEYMAP` environment variable,
+//! so it applies to the terminal that runs this application and not to other terminals.
+//! See the `keymap` crate for the keymap files that define the layouts.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::{string::{String, ToString}, vec::Vec};
+use app_io::println;
+use command::{Arg, Command, Matches};
+
+pub static COMMAND: Command = Command {
+    name: "loadkeys",
+    about: "Switch the keyboard layout of this terminal, or list the available layouts",
+    args: &[
+        Arg::positional("LAYOUT").help("the name of the layout, e.g., `us`, `de`, `fr`, or `dvorak`"),
+    ],
+};
+
+pub fn command() -> &'static Command {
+    &COMMAND
+}
+
+pub fn main(args: Vec<String>) -> isize {
+    let matches = match COMMAND.get_matches(args) {
+        Ok(m) => m,
+        Err(exit_value) => return exit_value,
+    };
+
+    match run(&matches) {
+        Ok(()) => 0,
+        Err(e) => {
+            println!("Error: {}", e);
+            -1
+        }
+    }
+}
+
+fn run(matches: &Matches) -> Result<(), String> {
+    let env = task::with_current_task(|t| t.get_env()).map_err(|_| "failed to get current task")?;
+    let current = env
+        .lock()
+        .get(keymap::ENV_VAR)
+        .cloned()
+        .unwrap_or_else(|| keymap::DEFAULT_LAYOUT.to_string());
+
+    let Some(name) = matches.value("LAYOUT") else {
+        for layout in keymap::available() {
+            let marker = if layout == current { '*' } else { ' ' };
+            println!("{} {}", marker, layout);
+        }
+        return Ok(());
+    };
+
+    // Load the layout now to report errors in its keymap file, which the terminal would only log.
+    let keymap = keymap::load(name)?;
+    env.lock().set(keymap::ENV_VAR.to_string(), keymap.name().to_string());
+    println!("Switched the keyboard layout of this terminal to {}", keymap.name());
+    Ok(())
+}
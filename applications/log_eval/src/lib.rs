@@ -0,0 +1,134 @@
+//! Measures the latency of log statements with and without buffered logging.
+//!
+//! Each log statement is made with interrupts held, as in an interrupt handler,
+//! such that it isn't preempted and its latency is exactly what an interrupt handler would observe.
+//! Without buffering, a log statement writes its record to the serial port before returning,
+//! so its latency grows with the length of the record.
+//! With buffering, it only formats the record and copies it into the current CPU's ring buffer.
+//!
+//! The worst-case latency is the most relevant result, as it bounds how long
+//! a log statement can delay the rest of an interrupt handler.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::{format, string::String, vec::Vec};
+use app_io::println;
+use command::{Arg, Command, Matches, Value};
+use time::{Duration, Instant};
+
+pub static COMMAND: Command = Command {
+    name: "log_eval",
+    about: "Measure the latency of log statements with and without buffered logging",
+    args: &[
+        Arg::option("iterations")
+            .short('n')
+            .value(Value::Integer)
+            .help("the number of log statements to measure in each mode (default: 100)"),
+        Arg::option("length")
+            .short('l')
+            .value(Value::Integer)
+            .help("the length of each logged message in bytes (default: 64)"),
+    ],
+};
+
+pub fn command() -> &'static Command {
+    &COMMAND
+}
+
+pub fn main(args: Vec<String>) -> isize {
+    let matches = match COMMAND.get_matches(args) {
+        Ok(m) => m,
+        Err(exit_value) => return exit_value,
+    };
+
+    match run(&matches) {
+        Ok(()) => 0,
+        Err(e) => {
+            println!("Error: {}", e);
+            -1
+        }
+    }
+}
+
+fn run(matches: &Matches) -> Result<(), String> {
+    let iterations: usize = matches
+        .value_of("iterations")
+        .map_err(|e| format!("{}", e))?
+        .unwrap_or(100);
+    let length: usize = matches
+        .value_of("length")
+        .map_err(|e| format!("{}", e))?
+        .unwrap_or(64);
+    if iterations == 0 {
+        return Err("the number of iterations must be at least one".into());
+    }
+    let message = "x".repeat(length);
+
+    let was_buffering = logger::is_buffering();
+
+    logger::disable_buffering();
+    let unbuffered = measure(iterations, &message);
+
+    logger::enable_buffering(log_flusher::BUFFER_CAPACITY)?;
+    let dropped_before = logger::dropped_records();
+    let buffered = measure(iterations, &message);
+    let dropped = logger::dropped_records() - dropped_before;
+    // Write the records now, in case the flusher task isn't running.
+    logger::flush_buffered();
+
+    if !was_buffering {
+        logger::disable_buffering();
+    }
+
+    println!("{} log statements of {} bytes each, with interrupts held:", iterations, length);
+    println!("{:<12} {:>12} {:>12} {:>12}", "Mode", "Min (ns)", "Mean (ns)", "Max (ns)");
+    unbuffered.print("unbuffered");
+    buffered.print("buffered");
+    if dropped > 0 {
+        println!("Warning: {} buffered records were dropped; try fewer iterations.", dropped);
+    }
+    Ok(())
+}
+
+/// Makes the given number of log statements, returning statistics about their latencies.
+fn measure(iterations: usize, message: &str) -> Latencies {
+    let mut latencies = Latencies { min: Duration::MAX, total: Duration::ZERO, max: Duration::ZERO, count: 0 };
+    for i in 0..iterations {
+        let held_interrupts = sync_irq::hold_interrupts();
+        let start = Instant::now();
+        log::info!("log_eval {}: {}", i, message);
+        let latency = Instant::now().duration_since(start);
+        drop(held_interrupts);
+        latencies.add(latency);
+    }
+    latencies
+}
+
+/// The minimum, mean, and maximum latency of a set of log statements.
+struct Latencies {
+    min: Duration,
+    total: Duration,
+    max: Duration,
+    count: u32,
+}
+
+impl Latencies {
+    fn add(&mut self, latency: Duration) {
+        self.min = self.min.min(latency);
+        self.max = self.max.max(latency);
+        self.total += latency;
+        self.count += 1;
+    }
+
+    fn print(&self, mode: &str) {
+        println!(
+            "{:<12} {:>12} {:>12} {:>12}",
+            mode,
+            self.min.as_nanos(),
+            (self.total / self.count).as_nanos(),
+            self.max.as_nanos(),
+        );
+    }
+}
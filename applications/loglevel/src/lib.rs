@@ -0,0 +1,107 @@
+//! Shows or changes the global log level and the log levels of individual crates.
+//!
+//! Each `SETTING` is either a level, which sets the global log level,
+//! or `CRATE=LEVEL`, which sets the log level of the given crate,
+//! e.g., `loglevel mod_mgmt=trace` enables trace logging for only the `mod_mgmt` crate.
+//! `CRATE=default` removes the crate's own log level, such that the global log level applies again.
+//! Without any settings, the current log levels are printed.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::{format, string::String, vec::Vec};
+use app_io::println;
+use command::{Arg, Command, Matches};
+use log::LevelFilter;
+use mod_mgmt::CrateNamespace;
+
+pub static COMMAND: Command = Command {
+    name: "loglevel",
+    about: "Show or change the global and per-crate log levels",
+    args: &[
+        Arg::flag("force")
+            .short('f')
+            .help("set a crate's log level even if no crate with that name is currently loaded"),
+        Arg::positional("SETTING")
+            .multiple()
+            .help("a level (off, error, warn, info, debug, trace) to set the global log level, \
+                or CRATE=LEVEL to set a crate's log level, where LEVEL may also be `default`"),
+    ],
+};
+
+pub fn command() -> &'static Command {
+    &COMMAND
+}
+
+pub fn main(args: Vec<String>) -> isize {
+    let matches = match COMMAND.get_matches(args) {
+        Ok(m) => m,
+        Err(exit_value) => return exit_value,
+    };
+
+    match run(&matches) {
+        Ok(()) => 0,
+        Err(e) => {
+            println!("Error: {}", e);
+            -1
+        }
+    }
+}
+
+fn run(matches: &Matches) -> Result<(), String> {
+    let settings = matches.values("SETTING");
+    if settings.is_empty() {
+        print_levels();
+        return Ok(());
+    }
+
+    for setting in settings {
+        match setting.split_once('=') {
+            Some((crate_name, level)) => {
+                let level = if level.eq_ignore_ascii_case("default") {
+                    None
+                } else {
+                    Some(parse_level(level)?)
+                };
+                if level.is_some() && !matches.is_present("force") && !is_crate_loaded(crate_name)? {
+                    return Err(format!("no crate named {:?} is loaded (use --force to set its log level anyway)", crate_name));
+                }
+                logger::set_crate_log_level(crate_name, level);
+                match level {
+                    Some(level) => println!("Set the log level of crate {} to {}", crate_name, level),
+                    None => println!("Crate {} now uses the global log level", crate_name),
+                }
+            }
+            None => {
+                let level = parse_level(setting)?;
+                let Some(level) = level.to_level() else {
+                    return Err("the global log level cannot be `off`".into());
+                };
+                logger::set_log_level(level);
+                println!("Set the global log level to {}", level);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn print_levels() {
+    println!("global: {}", logger::log_level());
+    for (crate_name, level) in logger::crate_log_levels() {
+        println!("{}: {}", crate_name, level);
+    }
+}
+
+fn parse_level(level: &str) -> Result<LevelFilter, String> {
+    level.parse().map_err(|_| format!("{:?} is not a valid log level", level))
+}
+
+/// Returns whether a crate with the given name is loaded into the current task's namespace.
+fn is_crate_loaded(crate_name: &str) -> Result<bool, String> {
+    let namespace = task::with_current_task(|t| t.get_namespace().clone())
+        .map_err(|_| "couldn't get the current task's namespace")?;
+    // A loaded crate's name uses underscores instead of dashes, and is followed by a dash and its hash.
+    let prefix = format!("{}-", crate_name.replace('-', "_"));
+    Ok(!CrateNamespace::get_crates_starting_with(&namespace, &prefix).is_empty())
+}
@@ -4,38 +4,48 @@ extern crate task;
 #[macro_use] extern crate app_io;
 extern crate alloc;
 extern crate fs_node;
-extern crate getopts;
+extern crate command;
 extern crate path;
+extern crate fmt_utils;
 
 use alloc::{
-    string::String,
+    string::{String, ToString},
     vec::Vec,
 };
 use core::fmt::Write;
 use fs_node::{FileOrDir, DirRef};
-use getopts::Options;
+use command::{Arg, Command, Value};
+use fmt_utils::{Align, Size, Table};
 use path::Path;
 
-pub fn main(args: Vec<String>) -> isize {
-    let mut opts = Options::new();
-    opts.optflag("h", "help", "print this help menu");
-    opts.optflag("s", "size", "print the size of each file in directory");
+pub static COMMAND: Command = Command {
+    name: "ls",
+    about: "List the contents of the given directory or info about the given file.
+If no arguments are provided, it lists the contents of the current directory.",
+    args: &[
+        Arg::flag("size").short('s').help("print the size of each file in directory"),
+        Arg::flag("human-readable").short('h').help("with -s, print sizes in binary units, e.g., 1.5 KiB"),
+        Arg::positional("PATH").value(Value::Path).help("the directory to list"),
+    ],
+};
+
+pub fn command() -> &'static Command {
+    &COMMAND
+}
 
-    let matches = match opts.parse(args) {
+pub fn main(args: Vec<String>) -> isize {
+    let matches = match COMMAND.get_matches(args) {
         Ok(m) => m,
-        Err(_f) => {
-            println!("{}", _f);
-            print_usage(opts);
-            return -1; 
-        }
+        Err(exit_value) => return exit_value,
     };
 
-    if matches.opt_present("h") {
-        print_usage(opts);
-        return 0;
-    }
-
-    let size_option = matches.opt_present("s");
+    let size_option = if !matches.is_present("size") {
+        SizeFormat::None
+    } else if matches.is_present("human-readable") {
+        SizeFormat::Human
+    } else {
+        SizeFormat::Bytes
+    };
 
     let Ok(curr_wd) = task::with_current_task(|t| t.get_env().lock().working_dir.clone()) else {
         println!("failed to get current task");
@@ -43,12 +53,12 @@ pub fn main(args: Vec<String>) -> isize {
     };
 
     // print children of working directory if no child is specified
-    if matches.free.is_empty() {
+    let Some(path) = matches.value("PATH") else {
         print_children(&curr_wd, size_option);
         return 0;
-    }
+    };
 
-    let path: &Path = matches.free[0].as_ref();
+    let path: &Path = path.as_ref();
 
     // Navigate to the path specified by first argument
     match path.get(&curr_wd) {
@@ -67,34 +77,40 @@ pub fn main(args: Vec<String>) -> isize {
     }
 }
 
-fn print_children(dir: &DirRef, print_size: bool) {
-    let mut child_string = String::new();
+/// How the sizes of files are printed, if at all.
+#[derive(Clone, Copy)]
+enum SizeFormat {
+    None,
+    Bytes,
+    Human,
+}
+
+fn print_children(dir: &DirRef, size_format: SizeFormat) {
     let mut child_list = dir.lock().list(); 
     child_list.reverse();
-    for child in child_list.iter() {
-        let child_path = dir.lock().get(child).expect("Failed to get child path");
-        if print_size {
-            match &child_path {
-                FileOrDir::File(file_ref) => {
-                    let file = file_ref.lock();
-                    writeln!(child_string, "   {}    {}", file.len(), child).expect("Failed to write child_string");
-                },
-                FileOrDir::Dir(_) => {
-                    writeln!(child_string, "   --    {}", child).expect("Failed to write child_string");
-                },
-            };
-        } else {
+    if let SizeFormat::None = size_format {
+        let mut child_string = String::new();
+        for child in child_list.iter() {
             writeln!(child_string, "{}", child).expect("Failed to write child_string");
         }
+        println!("{}", child_string);
+        return;
     }
-    println!("{}", child_string);
-}
 
-fn print_usage(opts: Options) {
-    println!("{}", opts.usage(USAGE));
+    let mut table = Table::new(["SIZE", "NAME"]).align(0, Align::Right);
+    for child in child_list.iter() {
+        let child_path = dir.lock().get(child).expect("Failed to get child path");
+        let size = match &child_path {
+            FileOrDir::File(file_ref) => {
+                let len = file_ref.lock().len();
+                match size_format {
+                    SizeFormat::Human => Size::from(len).to_string(),
+                    _ => len.to_string(),
+                }
+            }
+            FileOrDir::Dir(_) => String::from("--"),
+        };
+        table.row([size, child.clone()]);
+    }
+    println!("{}", table);
 }
-
-
-const USAGE: &str = "Usage: ls [DIR | FILE]
-List the contents of the given directory or info about the given file.
-If no arguments are provided, it lists the contents of the current directory.";
@@ -0,0 +1,172 @@
+//! Runs microbenchmarks of core kernel operations and reports their timing statistics in cycles.
+//!
+//! The benchmarks run in a task pinned to the given CPU (by default, the current CPU):
+//! * `context_switch`: a round trip through the scheduler to a task pinned to the same CPU and back.
+//! * `spin_mutex`: acquiring and releasing an uncontended `spin::Mutex`.
+//! * `irq_safe_mutex`: acquiring and releasing an uncontended `IrqSafeMutex`.
+//! * `channel`: sending a message on a `sync_channel` and receiving it on the same task.
+//! * `symbol_lookup`: looking up a symbol in the current task's namespace by its full name.
+//!
+//! With `--json`, each benchmark's results are printed as a single-line JSON object.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::{string::String, sync::Arc, vec::Vec};
+use app_io::println;
+use bench_harness::{Bench, Results};
+use command::{Arg, Command, Matches, Value};
+use core::{hint::black_box, sync::atomic::{AtomicBool, Ordering}};
+use sync_irq::IrqSafeMutex;
+
+type BenchFn = fn(Bench) -> Result<Results, &'static str>;
+
+/// All benchmarks, in the order in which they run.
+static BENCHMARKS: &[(&str, BenchFn)] = &[
+    ("context_switch", context_switch),
+    ("spin_mutex", spin_mutex),
+    ("irq_safe_mutex", irq_safe_mutex),
+    ("channel", channel),
+    ("symbol_lookup", symbol_lookup),
+];
+
+pub static COMMAND: Command = Command {
+    name: "microbench",
+    about: "Run microbenchmarks of core kernel operations",
+    args: &[
+        Arg::option("cpu")
+            .short('c')
+            .value(Value::Integer)
+            .help("the CPU to run the benchmarks on (default: the current CPU)"),
+        Arg::option("iterations")
+            .short('n')
+            .value(Value::Integer)
+            .help("the number of measured iterations of each benchmark (default: 1000)"),
+        Arg::flag("json")
+            .short('j')
+            .help("print each benchmark's results as a single-line JSON object"),
+        Arg::positional("BENCHMARK")
+            .multiple()
+            .value(Value::OneOf(&["context_switch", "spin_mutex", "irq_safe_mutex", "channel", "symbol_lookup"]))
+            .help("the benchmarks to run (default: all of them)"),
+    ],
+};
+
+pub fn command() -> &'static Command {
+    &COMMAND
+}
+
+pub fn main(args: Vec<String>) -> isize {
+    let matches = match COMMAND.get_matches(args) {
+        Ok(m) => m,
+        Err(exit_value) => return exit_value,
+    };
+
+    match run(&matches) {
+        Ok(()) => 0,
+        Err(e) => {
+            println!("Error: {}", e);
+            -1
+        }
+    }
+}
+
+fn run(matches: &Matches) -> Result<(), &'static str> {
+    let cpu = match matches.value_of::<u32>("cpu").map_err(|_| "invalid CPU")? {
+        Some(id) => cpu::cpus().find(|cpu| cpu.value() == id).ok_or("no such CPU")?,
+        None => cpu::current_cpu(),
+    };
+    let iterations = matches.value_of::<usize>("iterations")
+        .map_err(|_| "invalid number of iterations")?
+        .unwrap_or(1000);
+    if iterations == 0 {
+        return Err("the number of iterations must be positive");
+    }
+
+    let names = matches.values("BENCHMARK");
+    let selected: Vec<(&'static str, BenchFn)> = BENCHMARKS.iter()
+        .filter(|(name, _)| names.is_empty() || names.iter().any(|n| n.as_str() == *name))
+        .copied()
+        .collect();
+
+    let results = bench_harness::run_on_cpu(cpu, move || {
+        selected.into_iter()
+            .map(|(name, func)| func(Bench::new(name).iterations(iterations)))
+            .collect::<Result<Vec<_>, _>>()
+    })??;
+
+    if matches.is_present("json") {
+        for result in &results {
+            println!("{}", result.to_json());
+        }
+    } else {
+        println!("Results on CPU {}, in cycles ({} cycles/us):", cpu, bench_harness::cycles_per_microsecond());
+        println!("{}", bench_harness::table_header());
+        for result in &results {
+            println!("{}", result);
+        }
+    }
+    Ok(())
+}
+
+fn context_switch(bench: Bench) -> Result<Results, &'static str> {
+    // The partner task yields right back to this task, so each `schedule()` switches there and back.
+    let done = Arc::new(AtomicBool::new(false));
+    let partner = spawn::new_task_builder(yield_until_done, done.clone())
+        .name(String::from("microbench_partner"))
+        .pin_on_cpu(cpu::current_cpu())
+        .spawn()?;
+    let results = bench.preemptible().run(|| {
+        task::scheduler::schedule();
+    });
+    done.store(true, Ordering::Release);
+    partner.join()?;
+    Ok(results)
+}
+
+fn yield_until_done(done: Arc<AtomicBool>) {
+    while !done.load(Ordering::Acquire) {
+        task::scheduler::schedule();
+    }
+}
+
+fn spin_mutex(bench: Bench) -> Result<Results, &'static str> {
+    let lock = spin::Mutex::new(0usize);
+    Ok(bench.run(|| {
+        *black_box(&lock).lock() += 1;
+    }))
+}
+
+fn irq_safe_mutex(bench: Bench) -> Result<Results, &'static str> {
+    let lock = IrqSafeMutex::new(0usize);
+    Ok(bench.run(|| {
+        *black_box(&lock).lock() += 1;
+    }))
+}
+
+fn channel(bench: Bench) -> Result<Results, &'static str> {
+    let (sender, receiver) = sync_channel::new_channel::<usize>(1);
+    let mut result = Ok(());
+    let results = bench.run(|| {
+        let received = sender.send(black_box(1)).and_then(|_| receiver.receive());
+        if received.is_err() {
+            result = Err("channel: failed to send or receive a message");
+        }
+    });
+    result.map(|_| results)
+}
+
+fn symbol_lookup(bench: Bench) -> Result<Results, &'static str> {
+    let namespace = task::with_current_task(|t| t.get_namespace().clone())
+        .map_err(|_| "couldn't get the current task's namespace")?;
+    // A symbol from the middle of the map, so that lookups don't benefit from it being first or last.
+    let name = {
+        let symbols = namespace.symbol_map().lock();
+        let (name, _) = symbols.iter().nth(symbols.count() / 2).ok_or("the namespace has no symbols")?;
+        String::from(name.as_str())
+    };
+    Ok(bench.run(|| {
+        black_box(namespace.get_symbol(black_box(&name)));
+    }))
+}
@@ -3,21 +3,34 @@
 
 extern crate alloc;
 extern crate task;
-extern crate getopts;
+extern crate command;
 extern crate fs_node;
 extern crate vfs_node;
 
 use alloc::vec::Vec;
 use alloc::string::String;
 use alloc::string::ToString;
+use command::{Arg, Command, Value};
 // use fs_node::FileOrDir;
 use vfs_node::VFSDirectory;
 
+pub static COMMAND: Command = Command {
+    name: "mkdir",
+    about: "Create the given directories",
+    args: &[
+        Arg::positional("DIRECTORY").required().multiple().value(Value::Path).help("the directories to create"),
+    ],
+};
+
+pub fn command() -> &'static Command {
+    &COMMAND
+}
+
 pub fn main(args: Vec<String>) -> isize {
-    if args.is_empty() {
-        println!("Error: missing arguments");
-        return -1;
-    }
+    let matches = match COMMAND.get_matches(args) {
+        Ok(m) => m,
+        Err(exit_value) => return exit_value,
+    };
 
     let Ok(curr_wd) = task::with_current_task(|t| t.get_env().lock().working_dir.clone()) else {
         println!("failed to get current task");
@@ -26,7 +39,7 @@ pub fn main(args: Vec<String>) -> isize {
 
     let mut ret = 0;
 
-    for dir_name in args.iter() {
+    for dir_name in matches.values("DIRECTORY") {
         // add child dir to current directory
         if let Err(err) = VFSDirectory::create(dir_name.to_string(), &curr_wd) {
             println!("Error creating {:?}: {}", dir_name, err);
@@ -0,0 +1,108 @@
+//! This application mounts a directory tree shared by a 9P2000.L server into the filesystem.
+//!
+//! When running in QEMU with `net=user`, a 9P server listening on the host
+//! can be reached at `10.0.2.2`, e.g., `mount9p 10.0.2.2 /host`.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::{string::String, vec::Vec};
+use app_io::println;
+use core::str::FromStr;
+use getopts::{Matches, Options};
+use net::{IpAddress, IpEndpoint};
+use p9fs::Client;
+use path::Path;
+
+/// Where the tree is mounted if no mount point is given.
+const DEFAULT_MOUNT_POINT: &str = "/host";
+
+pub fn main(args: Vec<String>) -> isize {
+    let mut opts = Options::new();
+    opts.optflag("h", "help", "print this help menu");
+    opts.optopt(
+        "I",
+        "interface",
+        "connect using <interface> (default: the default interface)",
+        "<interface>",
+    );
+    opts.optopt("u", "user", "attach as <user> (default: root)", "<user>");
+    opts.optopt(
+        "a",
+        "aname",
+        "attach to the tree named <aname> (default: the server's default tree)",
+        "<aname>",
+    );
+
+    let matches = match opts.parse(args) {
+        Ok(m) => m,
+        Err(_f) => {
+            println!("{}", _f);
+            print_usage(&opts);
+            return -1;
+        }
+    };
+
+    if matches.opt_present("h") || matches.free.is_empty() {
+        print_usage(&opts);
+        return 0;
+    }
+
+    match run(matches) {
+        Ok(()) => 0,
+        Err(e) => {
+            println!("Error: {}", e);
+            -1
+        }
+    }
+}
+
+fn run(matches: Matches) -> Result<(), &'static str> {
+    let server = &matches.free[0];
+    let server = match IpAddress::from_str(server) {
+        Ok(addr) => IpEndpoint::new(addr, p9fs::DEFAULT_PORT),
+        Err(_) => IpEndpoint::from_str(server)
+            .map_err(|_| "invalid server, expected <ip> or <ip>:<port>")?,
+    };
+    let mount_point = Path::new(
+        matches
+            .free
+            .get(1)
+            .map_or(DEFAULT_MOUNT_POINT, String::as_str),
+    );
+    let name = mount_point.file_name().ok_or("invalid mount point")?;
+    let cwd = task::with_current_task(|t| t.get_env().lock().working_dir.clone())
+        .map_err(|_| "failed to get current task")?;
+    let parent = mount_point
+        .parent()
+        .ok_or("invalid mount point")?
+        .get_dir(&cwd)
+        .ok_or("the mount point's parent directory doesn't exist")?;
+
+    let interface = match matches.opt_str("I") {
+        Some(name) => net::get_interface(&name).ok_or("no such network interface")?,
+        None => net::get_default_interface().ok_or("no network interfaces available")?,
+    };
+    let uname = matches.opt_str("u").unwrap_or_else(|| String::from("root"));
+    let aname = matches.opt_str("a").unwrap_or_default();
+
+    let client = Client::connect(interface, server)?;
+    let msize = client.msize();
+    let root = p9fs::mount(client, &uname, &aname, &parent, String::from(name))?;
+    println!(
+        "mounted {} at {} (msize {})",
+        server,
+        root.lock().get_absolute_path(),
+        msize
+    );
+    Ok(())
+}
+
+fn print_usage(opts: &Options) {
+    println!("{}", opts.usage(USAGE));
+}
+
+const USAGE: &str = "Usage: mount9p [OPTION]... SERVER [MOUNT_POINT]
+Mounts the directory tree shared by the 9P2000.L SERVER, given as <ip> or <ip>:<port>,
+at MOUNT_POINT (default: /host).";
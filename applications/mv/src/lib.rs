@@ -0,0 +1,106 @@
+//! Moves files and directories.
+//!
+//! If the destination is an existing directory, each source is moved into it.
+//! Otherwise, the single source is moved to the destination path, which renames it.
+//! Source paths may contain wildcards.
+//!
+//! Renaming a node within its directory and moving it into another directory under the same name
+//! are done without copying. Other moves, or renames in directories that don't support renaming,
+//! copy the node and then remove the original.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::{format, string::String, vec::Vec};
+use app_io::{print, println};
+use command::{Arg, Command, Matches, Value};
+use fs_node::{DirRef, FileOrDir, FsNode, WalkEntry};
+use fs_utils::Progress;
+use path::Path;
+
+/// The number of nodes moved between each update of the progress output.
+const PROGRESS_INTERVAL: usize = 64;
+
+pub static COMMAND: Command = Command {
+    name: "mv",
+    about: "Move or rename files and directories",
+    args: &[
+        Arg::flag("progress").short('p').help("show the number of files moved so far"),
+        Arg::positional("PATH")
+            .required()
+            .multiple()
+            .value(Value::Path)
+            .help("the files or directories to move, followed by the destination"),
+    ],
+};
+
+pub fn command() -> &'static Command {
+    &COMMAND
+}
+
+pub fn main(args: Vec<String>) -> isize {
+    let matches = match COMMAND.get_matches(args) {
+        Ok(m) => m,
+        Err(exit_value) => return exit_value,
+    };
+
+    match run(&matches) {
+        Ok(()) => 0,
+        Err(e) => {
+            println!("Error: {}", e);
+            -1
+        }
+    }
+}
+
+fn run(matches: &Matches) -> Result<(), String> {
+    let cwd = task::with_current_task(|t| t.get_env().lock().working_dir.clone())
+        .map_err(|_| "failed to get current task")?;
+    let mut sources = fs_utils::expand_globs(matches.values("PATH"), &cwd)?;
+    let dest = sources.pop().ok_or("missing destination")?;
+    if sources.is_empty() {
+        return Err(format!("missing destination after {}", dest));
+    }
+    let dest_dir = match dest.get(&cwd) {
+        Some(FileOrDir::Dir(dir)) => Some(dir),
+        _ if sources.len() > 1 => return Err(format!("{} is not a directory", dest)),
+        _ => None,
+    };
+
+    let show_progress = matches.is_present("progress");
+    let mut progress = Progress::default();
+    let mut on_moved = |entry: &WalkEntry| {
+        progress.record(&entry.node);
+        if show_progress && progress.count() % PROGRESS_INTERVAL == 0 {
+            print!("\rMoved {}", progress);
+        }
+    };
+
+    for source_path in &sources {
+        let source = source_path
+            .get(&cwd)
+            .ok_or_else(|| format!("{} doesn't exist", source_path))?;
+        let (parent, name) = match &dest_dir {
+            Some(dir) => (dir.clone(), source.get_name()),
+            None => destination(&dest, &cwd)?,
+        };
+        fs_utils::move_node(&source, &parent, &name, &mut on_moved)
+            .map_err(|e| format!("failed to move {}: {}", source_path, e))?;
+    }
+
+    if show_progress {
+        println!("\rMoved {}", progress);
+    }
+    Ok(())
+}
+
+/// Returns the directory that will contain the node at the given path, and the node's name.
+fn destination(path: &Path, cwd: &DirRef) -> Result<(DirRef, String), String> {
+    let name = path.file_name().ok_or_else(|| format!("invalid destination {}", path))?;
+    let parent = path
+        .parent()
+        .and_then(|parent| parent.get_dir(cwd))
+        .ok_or_else(|| format!("the parent directory of {} doesn't exist", path))?;
+    Ok((parent, name.into()))
+}
@@ -0,0 +1,97 @@
+//! Fetches crate object files from a remote server over HTTP or TFTP,
+//! and loads them into the current task's namespace.
+//!
+//! Object files are named with their crate type prefix, e.g., `k#keyboard-36be916209949cef.o`,
+//! just like the modules in the boot image.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::{boxed::Box, format, string::String, vec::Vec};
+use app_io::println;
+use command::{Arg, Command, Matches};
+use core::str::FromStr;
+use mod_mgmt::CrateFetcher;
+use net::{wire::Ipv4Address, IpEndpoint};
+use net_crate_fetcher::{tftp::TFTP_PORT, HttpFetcher, TftpFetcher};
+
+/// The IP address of the host machine when running on QEMU.
+const DEFAULT_SERVER_ADDR: [u8; 4] = [10, 0, 2, 2];
+/// The default port of the HTTP server, which is the same as the update server's.
+const DEFAULT_HTTP_PORT: u16 = 8090;
+
+pub static COMMAND: Command = Command {
+    name: "netload",
+    about: "Fetch crate object files from a remote server and load them into the current namespace",
+    args: &[
+        Arg::option("server")
+            .short('s')
+            .help("the IP address (and optionally, the port) of the server (default: 10.0.2.2)"),
+        Arg::option("path")
+            .short('p')
+            .help("the path on the HTTP server of the directory of object files (default: /)"),
+        Arg::flag("tftp")
+            .short('t')
+            .help("fetch object files over TFTP instead of HTTP"),
+        Arg::flag("verbose")
+            .short('v')
+            .help("enable verbose logging of crate loading"),
+        Arg::positional("OBJECT_FILE")
+            .multiple()
+            .required()
+            .help("the object files to fetch and load"),
+    ],
+};
+
+pub fn command() -> &'static Command {
+    &COMMAND
+}
+
+pub fn main(args: Vec<String>) -> isize {
+    let matches = match COMMAND.get_matches(args) {
+        Ok(m) => m,
+        Err(exit_value) => return exit_value,
+    };
+
+    match run(&matches) {
+        Ok(()) => 0,
+        Err(e) => {
+            println!("Error: {}", e);
+            -1
+        }
+    }
+}
+
+fn run(matches: &Matches) -> Result<(), String> {
+    let tftp = matches.is_present("tftp");
+    let mut server = match matches.value("server") {
+        Some(s) => IpEndpoint::from_str(s)
+            .or_else(|_| Ipv4Address::from_str(s).map(|addr| IpEndpoint::new(addr.into(), 0)))
+            .map_err(|_| format!("invalid server address {s:?}"))?,
+        None => IpEndpoint::new(Ipv4Address::from_bytes(&DEFAULT_SERVER_ADDR).into(), 0),
+    };
+    if server.port == 0 {
+        server.port = if tftp { TFTP_PORT } else { DEFAULT_HTTP_PORT };
+    }
+
+    let interface = net::get_default_interface().ok_or("no network interface is available")?;
+    let fetcher: Box<dyn CrateFetcher> = if tftp {
+        Box::new(TftpFetcher::new(interface, server))
+    } else {
+        Box::new(HttpFetcher::new(interface, server, matches.value("path").unwrap_or("/")))
+    };
+
+    let namespace = task::with_current_task(|t| t.get_namespace().clone())
+        .map_err(|_| "failed to get current task")?;
+    let kernel_mmi_ref = memory::get_kernel_mmi_ref().ok_or("couldn't get kernel MMI")?;
+    let verbose = matches.is_present("verbose");
+
+    for object_file in matches.values("OBJECT_FILE") {
+        let (_crate_ref, new_syms) = namespace
+            .fetch_and_load_crate(&*fetcher, object_file, None, kernel_mmi_ref, verbose)
+            .map_err(|e| format!("failed to load {object_file:?}: {e}"))?;
+        println!("Loaded {} from {}, which added {} new symbols", object_file, server, new_syms);
+    }
+    Ok(())
+}
@@ -0,0 +1,98 @@
+//! This application mounts a directory exported by an NFSv3 server into the filesystem.
+//!
+//! The server must provide its MOUNT and NFS services over TCP,
+//! and since Theseus doesn't connect from a privileged port,
+//! the export must allow that, e.g., with the `insecure` option on Linux.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::{string::String, vec::Vec};
+use app_io::println;
+use core::str::FromStr;
+use getopts::{Matches, Options};
+use net::IpAddress;
+use nfs::{Client, Credentials};
+use path::Path;
+
+/// Where the export is mounted if no mount point is given.
+const DEFAULT_MOUNT_POINT: &str = "/nfs";
+
+pub fn main(args: Vec<String>) -> isize {
+    let mut opts = Options::new();
+    opts.optflag("h", "help", "print this help menu");
+    opts.optopt(
+        "I",
+        "interface",
+        "connect using <interface> (default: the default interface)",
+        "<interface>",
+    );
+    opts.optopt("u", "uid", "access files as user <uid> (default: 0)", "<uid>");
+    opts.optopt("g", "gid", "access files as group <gid> (default: 0)", "<gid>");
+
+    let matches = match opts.parse(args) {
+        Ok(m) => m,
+        Err(_f) => {
+            println!("{}", _f);
+            print_usage(&opts);
+            return -1;
+        }
+    };
+
+    if matches.opt_present("h") || matches.free.is_empty() {
+        print_usage(&opts);
+        return 0;
+    }
+
+    match run(matches) {
+        Ok(()) => 0,
+        Err(e) => {
+            println!("Error: {}", e);
+            -1
+        }
+    }
+}
+
+fn run(matches: Matches) -> Result<(), &'static str> {
+    let (server, export) = matches.free[0]
+        .split_once(':')
+        .ok_or("invalid export, expected <ip>:<path>")?;
+    let server = IpAddress::from_str(server).map_err(|_| "invalid server address")?;
+    let mount_point = Path::new(
+        matches
+            .free
+            .get(1)
+            .map_or(DEFAULT_MOUNT_POINT, String::as_str),
+    );
+    let name = mount_point.file_name().ok_or("invalid mount point")?;
+    let cwd = task::with_current_task(|t| t.get_env().lock().working_dir.clone())
+        .map_err(|_| "failed to get current task")?;
+    let parent = mount_point
+        .parent()
+        .ok_or("invalid mount point")?
+        .get_dir(&cwd)
+        .ok_or("the mount point's parent directory doesn't exist")?;
+
+    let interface = match matches.opt_str("I") {
+        Some(name) => net::get_interface(&name).ok_or("no such network interface")?,
+        None => net::get_default_interface().ok_or("no network interfaces available")?,
+    };
+    let credentials = Credentials {
+        uid: matches.opt_get_default("u", 0).map_err(|_| "invalid uid")?,
+        gid: matches.opt_get_default("g", 0).map_err(|_| "invalid gid")?,
+    };
+
+    let (client, root) = Client::connect(interface, server, export, credentials)?;
+    let root = nfs::mount(client, root, &parent, String::from(name))?;
+    println!("mounted {}:{} at {}", server, export, root.lock().get_absolute_path());
+    Ok(())
+}
+
+fn print_usage(opts: &Options) {
+    println!("{}", opts.usage(USAGE));
+}
+
+const USAGE: &str = "Usage: nfsmount [OPTION]... SERVER:EXPORT [MOUNT_POINT]
+Mounts the directory EXPORT of the NFSv3 server at the address SERVER
+at MOUNT_POINT (default: /nfs).";
@@ -34,6 +34,7 @@ pub fn main(args: Vec<String>) -> isize {
     opts.optflag("r", "recursive", "include recursive namespaces");
     opts.optflag("f", "files", "lists crate object files available in this namespace rather than currently-loaded crates");
     opts.optopt("", "load", "load a crate into the current namespace. Ignores all other arguments.", "CRATE_OBJ_FILE_PATH");
+    opts.optflag("", "gc", "unload crates that other crates no longer depend on from the current namespace");
 
     let matches = match opts.parse(args) {
         Ok(m) => m,
@@ -73,6 +74,8 @@ fn rmain(matches: Matches) -> Result<(), String> {
             format!("Couldn't resolve path to crate object file at {path:?}")
         )?;
         load_crate(&mut output, file, &namespace)?;
+    } else if matches.opt_present("gc") {
+        gc(&mut output, &namespace).map_err(|_e| String::from("String formatting error"))?;
     } else if matches.opt_present("f") {
         print_files(&mut output, 0, namespace.deref(), recursive)
             .map_err(|_e| String::from("String formatting error"))?;
@@ -99,6 +102,16 @@ fn load_crate(output: &mut String, crate_file_ref: FileRef, namespace: &Arc<Crat
 }
 
 
+fn gc(output: &mut String, namespace: &CrateNamespace) -> core::fmt::Result {
+    let unloaded = namespace.gc();
+    writeln!(output, "Unloaded {} orphaned crates from the {} CrateNamespace", unloaded.len(), namespace.name())?;
+    for crate_name in unloaded {
+        writeln!(output, "    {crate_name}")?;
+    }
+    Ok(())
+}
+
+
 fn print_files(output: &mut String, indent: usize, namespace: &CrateNamespace, recursive: bool) -> core::fmt::Result {
     writeln!(output, "\n{:indent$}{} CrateNamespace has crate object files:", "", namespace.name(), indent = indent)?;
     let mut files = namespace.dir().lock().list();
@@ -0,0 +1,170 @@
+//! Disassembles the x86_64 machine code in a section loaded by a crate, or in a file.
+//!
+//! Each instruction is shown at its virtual address alongside its bytes.
+//! For a section, the targets of calls, jumps, and RIP-relative memory accesses are also shown
+//! as the loaded section that contains them, which makes it easy to verify that
+//! the section's relocations were applied correctly, e.g., after a live patch.
+//!
+//! A file is treated as raw machine code starting at address `0`; ELF files are not parsed.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::{format, string::String, sync::Arc, vec, vec::Vec};
+use app_io::println;
+use command::{Arg, Command, Matches, Value};
+use core::fmt::Write;
+use fs_node::FileOrDir;
+use memory::VirtualAddress;
+use mod_mgmt::{CrateNamespace, StrongSectionRef};
+use path::Path;
+
+/// The number of instruction bytes shown on each line,
+/// beyond which the bytes of longer instructions are omitted.
+const MAX_BYTES_SHOWN: usize = 10;
+
+pub static COMMAND: Command = Command {
+    name: "objdump",
+    about: "Disassemble the machine code of a loaded section or a file",
+    args: &[
+        Arg::flag("file")
+            .short('f')
+            .help("treat TARGET as the path of a file containing raw machine code"),
+        Arg::option("offset")
+            .short('o')
+            .value(Value::Integer)
+            .help("start disassembling at this many bytes from the start of TARGET"),
+        Arg::option("length")
+            .short('n')
+            .value(Value::Integer)
+            .help("disassemble at most this many bytes"),
+        Arg::positional("TARGET")
+            .required()
+            .help("the symbol name, or a unique prefix of it, of a loaded section, or a file if --file is given"),
+    ],
+};
+
+pub fn command() -> &'static Command {
+    &COMMAND
+}
+
+pub fn main(args: Vec<String>) -> isize {
+    let matches = match COMMAND.get_matches(args) {
+        Ok(m) => m,
+        Err(exit_value) => return exit_value,
+    };
+
+    match run(&matches) {
+        Ok(()) => 0,
+        Err(e) => {
+            println!("Error: {}", e);
+            -1
+        }
+    }
+}
+
+fn run(matches: &Matches) -> Result<(), String> {
+    let offset = matches.value_of("offset").map_err(|e| format!("{}", e))?.unwrap_or(0);
+    let length = matches.value_of("length").map_err(|e| format!("{}", e))?.unwrap_or(usize::MAX);
+    let target = matches.value("TARGET").ok_or("missing TARGET argument")?;
+    let namespace: Arc<CrateNamespace> = task::with_current_task(|t| t.get_namespace().clone())
+        .map_err(|_| "failed to get current task")?;
+
+    if matches.is_present("file") {
+        disassemble(offset, &read_file(target, offset, length)?, None);
+    } else {
+        let section = find_section(&namespace, target)?;
+        // Copy the bytes, such that the section's pages don't stay locked while printing.
+        let bytes = {
+            let mapped_pages = section.mapped_pages.lock();
+            let bytes: &[u8] = mapped_pages.as_slice(section.mapped_pages_offset, section.size)?;
+            let bytes = bytes.get(offset..).unwrap_or(&[]);
+            bytes[..length.min(bytes.len())].to_vec()
+        };
+        println!("{} ({} bytes at {:#X}):", section.name, section.size, section.virt_addr);
+        disassemble(section.virt_addr.value() + offset, &bytes, Some(&namespace));
+    }
+    Ok(())
+}
+
+/// Prints the instructions in the given bytes, which start at the given address.
+///
+/// If a namespace is given, the targets of instructions are described
+/// by the sections in that namespace that contain them.
+fn disassemble(start: usize, bytes: &[u8], namespace: Option<&CrateNamespace>) {
+    let mut pos = 0;
+    while pos < bytes.len() {
+        let address = (start + pos) as u64;
+        let instruction = x86_decoder::decode(&bytes[pos..], address);
+        let len = instruction.as_ref().map_or(1, |instruction| instruction.len);
+
+        let mut line = format!("{:16x}:  ", address);
+        for byte in &bytes[pos..pos + len.min(MAX_BYTES_SHOWN)] {
+            let _ = write!(line, "{:02x} ", byte);
+        }
+        let padding = 3 * MAX_BYTES_SHOWN.saturating_sub(len);
+        let _ = write!(line, "{:padding$} ", "", padding = padding);
+        match instruction {
+            Some(instruction) => {
+                let _ = write!(line, "{}", instruction);
+                let target = instruction
+                    .target()
+                    .zip(namespace)
+                    .and_then(|(target, namespace)| describe(namespace, target));
+                if let Some(target) = target {
+                    let _ = write!(line, "    # {}", target);
+                }
+            }
+            None => line.push_str("(bad)"),
+        }
+        println!("{}", line);
+        pos += len;
+    }
+}
+
+/// Returns the name of the section that contains the given address, and the offset into it.
+fn describe(namespace: &CrateNamespace, address: u64) -> Option<String> {
+    let virt_addr = VirtualAddress::new(address as usize)?;
+    let (section, offset) = namespace.get_section_containing_address(virt_addr, true)?;
+    Some(if offset == 0 {
+        format!("<{}>", section.name)
+    } else {
+        format!("<{}+{:#x}>", section.name, offset)
+    })
+}
+
+/// Reads up to `length` bytes from the file at the given path, starting at `offset`.
+fn read_file(path: &str, offset: usize, length: usize) -> Result<Vec<u8>, String> {
+    let cwd = task::with_current_task(|t| t.get_env().lock().working_dir.clone())
+        .map_err(|_| "failed to get current task")?;
+    let file = match Path::new(path).get(&cwd) {
+        Some(FileOrDir::File(file)) => file,
+        Some(FileOrDir::Dir(_)) => return Err(format!("{} is a directory", path)),
+        None => return Err(format!("{} doesn't exist", path)),
+    };
+    let mut file = file.lock();
+    let mut bytes = vec![0; length.min(file.len().saturating_sub(offset))];
+    if !bytes.is_empty() {
+        let read = file
+            .read_at(&mut bytes, offset)
+            .map_err(|_| format!("failed to read {}", path))?;
+        bytes.truncate(read);
+    }
+    Ok(bytes)
+}
+
+/// Returns the loaded section whose symbol is `name`, or uniquely starts with `name`.
+fn find_section(namespace: &CrateNamespace, name: &str) -> Result<StrongSectionRef, String> {
+    let matches = namespace.find_symbols_starting_with(name);
+    let (symbol, section) = match matches.iter().find(|(symbol, _)| symbol == name) {
+        Some(exact_match) => exact_match,
+        None if matches.len() == 1 => &matches[0],
+        None if matches.is_empty() => return Err(format!("couldn't find a section for symbol {}", name)),
+        None => {
+            let symbols: Vec<&str> = matches.iter().map(|(symbol, _)| symbol.as_str()).collect();
+            return Err(format!("{} matches multiple symbols:\n{}", name, symbols.join("\n")));
+        }
+    };
+    section.upgrade().ok_or_else(|| format!("section {} was unloaded", symbol))
+}
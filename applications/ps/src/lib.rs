@@ -3,91 +3,84 @@ extern crate alloc;
 #[macro_use] extern crate app_io;
 
 extern crate task;
-extern crate getopts;
-extern crate scheduler;
+extern crate command;
+extern crate fmt_utils;
 
-use getopts::Options;
 use alloc::vec::Vec;
-use alloc::string::String;
-use core::fmt::Write;
+use alloc::string::{String, ToString};
+use alloc::format;
+use command::{Arg, Command, Matches, Value};
+use fmt_utils::{or_dash, Align, Clock, Table};
+use task::control::TaskInfo;
 
-pub fn main(args: Vec<String>) -> isize {
-    let mut opts = Options::new();
-    opts.optflag("h", "help", "print this help menu");
-    opts.optflag("b", "brief", "print only task id and name");
+pub static COMMAND: Command = Command {
+    name: "ps",
+    about: "List all tasks. \
+        KIND is 'I' for an idle task, 'A' for an application task, or 'K' for a kernel task; \
+        CPU is the core the task is running on and PIN is the core it's pinned to, if any; \
+        TIME is the total time the task has spent running.",
+    args: &[
+        Arg::flag("brief").short('b').help("print only task id and name"),
+        Arg::option("sort")
+            .short('s')
+            .value(Value::OneOf(&["id", "name", "time"]))
+            .help("sort tasks by id (the default), name, or CPU time"),
+    ],
+};
+
+pub fn command() -> &'static Command {
+    &COMMAND
+}
 
-    let matches = match opts.parse(args) {
+pub fn main(args: Vec<String>) -> isize {
+    let matches = match COMMAND.get_matches(args) {
         Ok(m) => m,
-        Err(_f) => { 
-            println!("{} \n", _f);
-            return -1; 
-        }
+        Err(exit_value) => return exit_value,
     };
+    print!("{}", list(&matches));
+    0
+}
 
-    if matches.opt_present("h") {
-        return print_usage(opts)
+fn list(matches: &Matches) -> String {
+    let mut tasks = task::control::list();
+    match matches.value("sort") {
+        Some("name") => tasks.sort_by(|a, b| a.name.cmp(&b.name)),
+        // Show the tasks that have run the longest first.
+        Some("time") => tasks.sort_by(|a, b| b.cpu_time.cmp(&a.cpu_time)),
+        _ => { }
     }
 
-    // Print headers
-    if matches.opt_present("b") {
-        println!("{0:<5}  {1}", "ID", "NAME");
-    }
-    else {
-        #[cfg(any(epoch_scheduler, priority_scheduler))] {
-            println!("{0:<5}  {1:<10}  {2:<4}  {3:<4}  {4:<5}  {5:<10}  {6}", "ID", "RUNSTATE", "CPU", "PIN", "TYPE", "PRIORITY", "NAME");
+    let table = if matches.is_present("brief") {
+        let mut table = Table::new(["ID", "NAME"]);
+        for task in &tasks {
+            table.row([task.id.to_string(), task.name.clone()]);
         }
-        #[cfg(not(any(epoch_scheduler, priority_scheduler)))] {
-            println!("{0:<5}  {1:<10}  {2:<4}  {3:<4}  {4:<5}  {5}", "ID", "RUNSTATE", "CPU", "PIN", "TYPE", "NAME");
+        table
+    } else {
+        let mut table = Table::new(["ID", "STATE", "CPU", "PIN", "KIND", "PRI", "TIME", "NAME"])
+            .align(6, Align::Right);
+        for task in &tasks {
+            table.row([
+                task.id.to_string(),
+                state(task),
+                or_dash(task.cpu),
+                or_dash(task.pinned_cpu),
+                task.kind().to_string(),
+                or_dash(task.priority),
+                Clock(task.cpu_time).to_string(),
+                task.name.clone(),
+            ]);
         }
-    }
-
-    // Print all tasks
-    let mut num_tasks = 0;
-    let mut task_string = String::new();
-    for (id, wtask) in task::all_tasks() {
-        let Some(task) = wtask.upgrade() else { continue };
-        num_tasks += 1;
-        if matches.opt_present("b") {
-            writeln!(task_string, "{0:<5}  {1}", id, task.name).expect("Failed to write to task_string.");
-        }
-        else {
-            // All printed fields below must be strings to ensure the width formatting specifier below works properly.
-            let runstate = format!("{:?}", task.runstate());
-            let cpu = task.running_on_cpu().map(|cpu| format!("{cpu}")).unwrap_or_else(|| String::from("-"));
-            let pinned = task.pinned_cpu().map(|pin| format!("{pin}")).unwrap_or_else(|| String::from("-"));
-            let task_type = if task.is_an_idle_task {"I"}
-                else if task.is_application() {"A"}
-                else {" "} ;
-
-            #[cfg(any(epoch_scheduler, priority_scheduler))] {
-                let priority = scheduler::priority(&task).map(|priority| format!("{}", priority)).unwrap_or_else(|| String::from("-"));
-                task_string.push_str(
-                    &format!("{0:<5}  {1:<10}  {2:<4}  {3:<4}  {4:<5}  {5:<10}  {6}\n", 
-                    id, runstate, cpu, pinned, task_type, priority, task.name)
-                );
-            }
-            #[cfg(not(any(epoch_scheduler, priority_scheduler)))] {
-                writeln!(task_string, "{0:<5}  {1:<10}  {2:<4}  {3:<4}  {4:<5}  {5}", 
-                    id, runstate, cpu, pinned, task_type, task.name).expect("Failed to write to task_string.");
-            }
-        }
-    }
-    print!("{}", task_string);
-    println!("Total number of tasks: {}", num_tasks);
-    
-    0
+        table
+    };
+    format!("{}Total number of tasks: {}\n", table, tasks.len())
 }
 
-fn print_usage(opts: Options) -> isize {
-    println!("{}", opts.usage(BRIEF));
-    0
+/// Returns the task's runstate, or `Suspended` if it is suspended.
+fn state(task: &TaskInfo) -> String {
+    if task.suspended {
+        String::from("Suspended")
+    } else {
+        format!("{:?}", task.runstate)
+    }
 }
-
-const BRIEF: &str = "Usage: ps [options]\n
-    TYPE:      'I' if an idle task, 'A' if an application task, '-' otherwise.
-    CPU:       the cpu core the task is currently running on.
-    PIN:       the core the task is pinned on, if any.
-    RUNSTATE:  runnability status of this task, e.g., whether it can be scheduled in.
-    ID:        the unique identifier for this task.
-    NAME:      the name of the task.";
-    
\ No newline at end of file
@@ -0,0 +1,103 @@
+//! Shows and sets the storage quotas of task groups, i.e., crate namespaces.
+//!
+//! Without any options, `quota` shows how much storage each task group's files use,
+//! along with its quota. Given a group and the `--bytes` or `--files` options,
+//! it sets that group's quota instead, and `--clear` removes it.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::{format, string::{String, ToString}, vec::Vec};
+use app_io::{print, println};
+use command::{Arg, Command, Matches, Value};
+use fmt_utils::{or_dash, Align, Size, Table};
+use fs_quota::Quota;
+
+pub static COMMAND: Command = Command {
+    name: "quota",
+    about: "Show or set the storage quotas of task groups",
+    args: &[
+        Arg::option("bytes")
+            .short('b')
+            .value(Value::Integer)
+            .help("set the maximum number of bytes used by the group's files"),
+        Arg::option("files")
+            .short('f')
+            .value(Value::Integer)
+            .help("set the maximum number of files owned by the group"),
+        Arg::flag("clear")
+            .short('c')
+            .help("remove the group's quota"),
+        Arg::positional("GROUP")
+            .help("the task group, i.e., the name of a crate namespace"),
+    ],
+};
+
+pub fn command() -> &'static Command {
+    &COMMAND
+}
+
+pub fn main(args: Vec<String>) -> isize {
+    let matches = match COMMAND.get_matches(args) {
+        Ok(m) => m,
+        Err(exit_value) => return exit_value,
+    };
+
+    match run(&matches) {
+        Ok(()) => 0,
+        Err(e) => {
+            println!("Error: {}", e);
+            -1
+        }
+    }
+}
+
+fn run(matches: &Matches) -> Result<(), String> {
+    let max_bytes: Option<usize> = matches.value_of("bytes").map_err(|e| format!("{}", e))?;
+    let max_files: Option<usize> = matches.value_of("files").map_err(|e| format!("{}", e))?;
+    let clear = matches.is_present("clear");
+    let group = matches.value("GROUP");
+
+    if clear || max_bytes.is_some() || max_files.is_some() {
+        let group = group.ok_or("a GROUP is required to set its quota")?;
+        if clear && (max_bytes.is_some() || max_files.is_some()) {
+            return Err("--clear can't be combined with --bytes or --files".into());
+        }
+        // Limits that aren't given are left unchanged.
+        let old = fs_quota::quota(group);
+        let quota = if clear {
+            Quota::default()
+        } else {
+            Quota {
+                max_bytes: max_bytes.or(old.max_bytes),
+                max_files: max_files.or(old.max_files),
+            }
+        };
+        fs_quota::set_quota(group, quota);
+    }
+
+    let mut table = Table::new(["USED", "LIMIT", "FILES", "LIMIT", "GROUP"])
+        .align(0, Align::Right)
+        .align(1, Align::Right)
+        .align(2, Align::Right)
+        .align(3, Align::Right);
+    for (name, usage, quota) in fs_quota::groups() {
+        if group.map_or(false, |group| group != name) {
+            continue;
+        }
+        table.row([
+            Size(usage.bytes as u64).to_string(),
+            or_dash(quota.max_bytes.map(|max| Size(max as u64))),
+            usage.files.to_string(),
+            or_dash(quota.max_files),
+            name,
+        ]);
+    }
+    if table.is_empty() {
+        println!("No files are owned by any task group.");
+    } else {
+        print!("{}", table);
+    }
+    Ok(())
+}
@@ -0,0 +1,135 @@
+//! This application forwards kernel log records to a remote syslog collector over UDP.
+//!
+//! Without arguments, it shows whether records are being forwarded and where to.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::{string::String, vec::Vec};
+use app_io::println;
+use core::str::FromStr;
+use getopts::{Matches, Options};
+use log::LevelFilter;
+use net::{IpAddress, IpEndpoint};
+use remote_logger::Config;
+
+pub fn main(args: Vec<String>) -> isize {
+    let mut opts = Options::new();
+    opts.optflag("h", "help", "print this help menu");
+    opts.optflag("s", "stop", "stop forwarding log records");
+    opts.optopt(
+        "l",
+        "level",
+        "forward records up to <level>, e.g., warn or debug (default: info)",
+        "<level>",
+    );
+    opts.optopt(
+        "r",
+        "rate",
+        "send at most <rate> records per second (default: 100)",
+        "<rate>",
+    );
+    opts.optopt(
+        "b",
+        "burst",
+        "send at most <burst> records at once (default: 200)",
+        "<burst>",
+    );
+    opts.optopt(
+        "c",
+        "capacity",
+        "hold at most <capacity> records while the network is down (default: 1024)",
+        "<capacity>",
+    );
+    opts.optopt("n", "hostname", "identify this machine as <hostname>", "<hostname>");
+
+    let matches = match opts.parse(args) {
+        Ok(m) => m,
+        Err(_f) => {
+            println!("{}", _f);
+            print_usage(&opts);
+            return -1;
+        }
+    };
+
+    if matches.opt_present("h") {
+        print_usage(&opts);
+        return 0;
+    }
+
+    match run(matches) {
+        Ok(()) => 0,
+        Err(e) => {
+            println!("Error: {}", e);
+            -1
+        }
+    }
+}
+
+fn run(matches: Matches) -> Result<(), &'static str> {
+    if matches.opt_present("s") {
+        remote_logger::stop();
+        println!("stopped forwarding log records");
+        return Ok(());
+    }
+
+    let Some(collector) = matches.free.first() else {
+        print_status();
+        return Ok(());
+    };
+    let collector = match IpAddress::from_str(collector) {
+        Ok(addr) => IpEndpoint::new(addr, remote_logger::DEFAULT_COLLECTOR_PORT),
+        Err(_) => IpEndpoint::from_str(collector)
+            .map_err(|_| "invalid collector, expected <ip> or <ip>:<port>")?,
+    };
+
+    let mut config = Config::new(collector);
+    if let Some(level) = matches.opt_str("l") {
+        config.max_level = LevelFilter::from_str(&level).map_err(|_| "invalid log level")?;
+    }
+    if let Some(rate) = matches.opt_get("r").map_err(|_| "invalid rate")? {
+        config.max_rate = rate;
+    }
+    if let Some(burst) = matches.opt_get("b").map_err(|_| "invalid burst")? {
+        config.burst = burst;
+    }
+    if let Some(capacity) = matches.opt_get("c").map_err(|_| "invalid capacity")? {
+        config.buffer_capacity = capacity;
+    }
+    if let Some(hostname) = matches.opt_str("n") {
+        config.hostname = hostname;
+    }
+
+    remote_logger::init(config)?;
+    print_status();
+    Ok(())
+}
+
+fn print_status() {
+    match remote_logger::config() {
+        Some(config) => println!(
+            "forwarding {} records to {} as {} (rate {}/s, burst {}, capacity {})",
+            config.max_level,
+            config.collector,
+            config.hostname,
+            config.max_rate,
+            config.burst,
+            config.buffer_capacity
+        ),
+        None => println!("not forwarding log records"),
+    }
+    let stats = remote_logger::statistics();
+    println!(
+        "sent {}, dropped {}, buffered {}",
+        stats.sent, stats.dropped, stats.buffered
+    );
+}
+
+fn print_usage(opts: &Options) {
+    println!("{}", opts.usage(USAGE));
+}
+
+const USAGE: &str = "Usage: remote_log [OPTION]... [COLLECTOR]
+Forwards kernel log records to the syslog COLLECTOR, given as <ip> or <ip>:<port>.
+Without a COLLECTOR, shows whether log records are being forwarded.";
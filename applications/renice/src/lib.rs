@@ -0,0 +1,76 @@
+//! Changes the scheduling priority of tasks.
+//!
+//! Unlike Unix's `nice` values, a higher priority means that a task is scheduled more often.
+//! Priorities only apply when a priority-based scheduler is in use,
+//! e.g., when Theseus is built with the `priority_scheduler` or `epoch_scheduler` config.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::{string::String, vec::Vec};
+use app_io::println;
+use command::{Arg, Command, Matches, Value};
+use task::control::Action;
+
+pub static COMMAND: Command = Command {
+    name: "renice",
+    about: "Change the scheduling priority of tasks",
+    args: &[
+        Arg::positional("PRIORITY")
+            .required()
+            .value(Value::Integer)
+            .help("the new priority, from 0 to 255, where higher values are scheduled more often"),
+        Arg::positional("TASK_ID")
+            .required()
+            .multiple()
+            .value(Value::Integer)
+            .help("the IDs of the tasks, as shown by `ps`"),
+    ],
+};
+
+pub fn command() -> &'static Command {
+    &COMMAND
+}
+
+pub fn main(args: Vec<String>) -> isize {
+    let matches = match COMMAND.get_matches(args) {
+        Ok(m) => m,
+        Err(exit_value) => return exit_value,
+    };
+
+    match run(&matches) {
+        Ok(()) => 0,
+        Err(e) => {
+            println!("Error: {}", e);
+            -1
+        }
+    }
+}
+
+fn run(matches: &Matches) -> Result<(), String> {
+    let priority: u8 = matches
+        .value_of("PRIORITY")
+        .map_err(|_| "PRIORITY must be between 0 and 255")?
+        .ok_or("missing PRIORITY argument")?;
+
+    let mut failed = false;
+    for task_id in matches.values("TASK_ID") {
+        let result = task_id
+            .parse::<usize>()
+            .map_err(|_| "not a valid task ID")
+            .and_then(|id| task::control::request(id, Action::SetPriority(priority)));
+        match result {
+            Ok(()) => println!("Set the priority of task {} to {}", task_id, priority),
+            Err(e) => {
+                println!("Failed to set the priority of task {}: {}", task_id, e);
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        Err("couldn't set the priority of every task".into())
+    } else {
+        Ok(())
+    }
+}
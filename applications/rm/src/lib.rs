@@ -4,21 +4,41 @@
 
 #[macro_use] extern crate alloc;
 extern crate task;
-extern crate getopts;
+extern crate command;
 extern crate path;
 extern crate fs_node;
-extern crate root;
+extern crate fs_utils;
 
 use alloc::vec::Vec;
 use alloc::string::String;
-use alloc::string::ToString;
-use getopts::Options;
-use path::PathBuf;
-use fs_node::{FsNode, FileOrDir};
+use command::{Arg, Command, Matches, Value};
+use fs_node::{FsNode, WalkEntry};
+use fs_utils::Progress;
 
 
+/// The number of nodes removed between each update of the progress output.
+const PROGRESS_INTERVAL: usize = 64;
+
+pub static COMMAND: Command = Command {
+    name: "rm",
+    about: "Remove files or directories from filesystem",
+    args: &[
+        Arg::flag("recursive").short('r').help("recursively remove directories and their contents"),
+        Arg::flag("progress").short('p').help("show the number of files removed so far"),
+        Arg::positional("PATH").required().multiple().value(Value::Path).help("the files or directories to remove, which may contain wildcards"),
+    ],
+};
+
+pub fn command() -> &'static Command {
+    &COMMAND
+}
+
 pub fn main(args: Vec<String>) -> isize {
-    match remove_node(args) {
+    let matches = match COMMAND.get_matches(args) {
+        Ok(m) => m,
+        Err(exit_value) => return exit_value,
+    };
+    match remove_node(&matches) {
         Ok(_) => 0,
         Err(err) => {
             println!("{}", err);
@@ -27,68 +47,40 @@ pub fn main(args: Vec<String>) -> isize {
     }
 }
 
-pub fn remove_node(args: Vec<String>) -> Result<(), String> {
-    let mut opts = Options::new();
-    opts.optflag("h", "help", "print this help menu");
-    opts.optflag("r", "recursive", "recursively remove directories and their contents");
-    
-    let matches = match opts.parse(args) {
-        Ok(m) => m,
-        Err(e) => {
-            print_usage(opts);
-            return Err(e.to_string());
-        }
-    };
-
-    if matches.opt_present("h") {
-        print_usage(opts);
-        return Ok(());
-    }
-
+pub fn remove_node(matches: &Matches) -> Result<(), String> {
     let Ok(working_dir) = task::with_current_task(|t|
         t.get_env().lock().working_dir.clone()
     ) else {
         return Err("failed to get current task".into());
     };
 
-    if matches.free.is_empty() {
-        return Err("rm: missing argument".into());
-    }
+    let show_progress = matches.is_present("progress");
+    let mut progress = Progress::default();
+    let mut on_removed = |entry: &WalkEntry| {
+        progress.record(&entry.node);
+        if show_progress && progress.count() % PROGRESS_INTERVAL == 0 {
+            print!("\rRemoved {}", progress);
+        }
+    };
 
-    for path_string in &matches.free {
-        let path = PathBuf::from(path_string.clone());
+    for path in fs_utils::expand_globs(matches.values("PATH"), &working_dir)? {
         let node_to_delete = match path.get(&working_dir) {
             Some(node) => node,
             _ => return Err(format!("Couldn't find path {path}")),
         };
 
         // Only remove directories if the user specified "-r". 
-        let can_remove_dirs = matches.opt_present("r");
-        let path_error = || { format!("Couldn't remove {} from its parent directory.", &path) };
-        let parent = node_to_delete.get_parent_dir().ok_or_else(path_error)?;
-
-        match node_to_delete {
-            FileOrDir::File(_) => {
-                parent.lock().remove(&node_to_delete).ok_or_else(path_error)?;
-            } 
-            FileOrDir::Dir(_) => {
-                if can_remove_dirs {
-                    parent.lock().remove(&node_to_delete).ok_or_else(path_error)?;
-                } else {
-                    println!("Skipping the removal of directory '{}', try specifying the \"-r\" flag", 
-                        node_to_delete.get_name());
-                }
-            }
+        if node_to_delete.is_dir() && !matches.is_present("recursive") {
+            println!("Skipping the removal of directory '{}', try specifying the \"-r\" flag", 
+                node_to_delete.get_name());
+            continue;
         }
+        fs_utils::remove(&node_to_delete, &mut on_removed)
+            .map_err(|e| format!("Couldn't remove {}: {}", &path, e))?;
     }
 
+    if show_progress {
+        println!("\rRemoved {}", progress);
+    }
     Ok(())
 }
-
-fn print_usage(opts: Options) {
-    println!("{}", opts.usage(USAGE));
-}
-
-
-const USAGE: &str = "Usage: rm [PATH]
-Remove files or directories from filesystem";
\ No newline at end of file
@@ -0,0 +1,117 @@
+//! Saves the screen, i.e., the window manager's composited framebuffer, as a PNG or PPM image.
+//!
+//! By default, a single screenshot is saved to the given file.
+//! With `--record`, a frame is saved every `--interval` into the given directory
+//! by a background task, until `--count` frames have been saved or `screenshot --stop` is run.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::{format, string::String, vec::Vec};
+use app_io::println;
+use command::{Arg, Command, Matches, Value};
+use core::time::Duration;
+use fs_node::{DirRef, FileOrDir};
+use path::Path;
+use screen_capture::Format;
+
+pub static COMMAND: Command = Command {
+    name: "screenshot",
+    about: "Save the screen as a PNG or PPM image, once or periodically",
+    args: &[
+        Arg::option("format")
+            .short('f')
+            .value(Value::OneOf(&["png", "ppm"]))
+            .help("the image format (default: based on the file extension, otherwise png)"),
+        Arg::flag("record")
+            .short('r')
+            .help("save a frame periodically into the directory PATH, in a background task"),
+        Arg::option("interval")
+            .short('i')
+            .value(Value::Integer)
+            .help("with --record, the number of milliseconds between frames (default: 1000)"),
+        Arg::option("count")
+            .short('n')
+            .value(Value::Integer)
+            .help("with --record, the number of frames to save (default: until --stop)"),
+        Arg::flag("stop")
+            .short('s')
+            .help("stop the ongoing recording"),
+        Arg::positional("PATH")
+            .value(Value::Path)
+            .help("the file to save the screenshot to, or with --record, the directory to save frames to"),
+    ],
+};
+
+pub fn command() -> &'static Command {
+    &COMMAND
+}
+
+pub fn main(args: Vec<String>) -> isize {
+    let matches = match COMMAND.get_matches(args) {
+        Ok(m) => m,
+        Err(exit_value) => return exit_value,
+    };
+
+    match run(&matches) {
+        Ok(()) => 0,
+        Err(e) => {
+            println!("Error: {}", e);
+            -1
+        }
+    }
+}
+
+fn run(matches: &Matches) -> Result<(), String> {
+    if matches.is_present("stop") {
+        let frames = screen_capture::stop_recording().ok_or("no recording is in progress")?;
+        println!("Stopped recording after {} frames", frames);
+        return Ok(());
+    }
+
+    let cwd = task::with_current_task(|t| t.get_env().lock().working_dir.clone())
+        .map_err(|_| "failed to get current task")?;
+    let path = matches.value("PATH").map(Path::new);
+    let format = matches.value("format")
+        .or_else(|| path.and_then(Path::extension))
+        .and_then(Format::from_extension)
+        .unwrap_or(Format::Png);
+
+    if matches.is_present("record") {
+        let dir = match path {
+            Some(path) => path.get_dir(&cwd).ok_or_else(|| format!("{} is not a directory", path))?,
+            None => cwd,
+        };
+        let interval = matches.value_of::<u64>("interval")
+            .map_err(|_| "invalid interval")?
+            .unwrap_or(1000);
+        let count = matches.value_of::<usize>("count").map_err(|_| "invalid count")?;
+        screen_capture::start_recording(dir, "frame", format, Duration::from_millis(interval), count)?;
+        println!("Recording a frame every {} ms; run `screenshot --stop` to stop", interval);
+        return Ok(());
+    }
+
+    let (dir, name) = match path {
+        Some(path) => {
+            let name = path.file_name().ok_or_else(|| format!("{} is not a file name", path))?;
+            (parent_dir(path, &cwd)?, String::from(name))
+        }
+        None => (cwd, format!("screenshot.{}", format.extension())),
+    };
+    let image = screen_capture::capture()?;
+    screen_capture::save(&image, format, &dir, &name)?;
+    println!("Saved a {}x{} screenshot to {}", image.width(), image.height(), name);
+    Ok(())
+}
+
+/// Returns the directory that contains the file at the given path.
+fn parent_dir(path: &Path, cwd: &DirRef) -> Result<DirRef, String> {
+    match path.parent() {
+        Some(parent) if parent != Path::new("") => match parent.get(cwd) {
+            Some(FileOrDir::Dir(dir)) => Ok(dir),
+            _ => Err(format!("{} is not a directory", parent)),
+        },
+        _ => Ok(cwd.clone()),
+    }
+}
@@ -21,6 +21,11 @@ extern crate app_io;
 extern crate fs_node;
 extern crate environment;
 extern crate libterm;
+extern crate command;
+extern crate memory;
+extern crate mod_mgmt;
+extern crate clipboard;
+extern crate keymap;
 
 #[macro_use] extern crate alloc;
 #[macro_use] extern crate log;
@@ -30,12 +35,13 @@ use keycodes_ascii::{Keycode, KeyAction, KeyEvent};
 use alloc::string::{String, ToString};
 use alloc::vec::Vec;
 use path::Path;
-use task::{ExitValue, KillReason, JoinableTaskRef};
+use task::{ExitValue, KillReason, JoinableTaskRef, notifications::Notification};
 use libterm::Terminal;
 use dfqueue::{DFQueue, DFQueueConsumer, DFQueueProducer};
 use alloc::sync::Arc;
 use spin::Mutex;
 use environment::Environment;
+use keymap::Keymap;
 use core::mem;
 use alloc::collections::BTreeMap;
 use stdio::{Stdio, KeyEventQueue, KeyEventQueueReader, KeyEventQueueWriter,
@@ -44,6 +50,8 @@ use core2::io::Write;
 use core::ops::Deref;
 use app_io::IoStreams;
 use fs_node::FileOrDir;
+use command::{Command, Completion};
+use mod_mgmt::{CrateNamespace, SectionType, SECTION_HASH_DELIMITER};
 
 /// The status of a job.
 #[derive(PartialEq)]
@@ -132,7 +140,13 @@ enum AppErr {
     NamespaceErr,
     /// The terminal could not spawn a new task to run the new application.
     /// Includes the String error returned from the task spawn function.
-    SpawnErr(String)
+    SpawnErr(String),
+    /// The arguments are invalid according to the application's declared command-line interface.
+    /// Includes the message describing the error and the application's usage.
+    InvalidArgs(String),
+    /// The arguments asked for the application's help text, so it should be printed
+    /// instead of running the application. Includes the help text.
+    Help(String),
 }
 
 struct Shell {
@@ -165,6 +179,8 @@ struct Shell {
     print_producer: DFQueueProducer<Event>,
     /// The terminal's current environment
     env: Arc<Mutex<Environment>>,
+    /// The keyboard layout of the terminal, which is chosen by the `KEYMAP` environment variable.
+    keymap: Keymap,
     /// the terminal that is bind with the shell instance
     terminal: Arc<Mutex<Terminal>>
 }
@@ -202,6 +218,7 @@ impl Shell {
             print_consumer,
             print_producer,
             env: Arc::new(Mutex::new(env)),
+            keymap: Keymap::us(),
             terminal
         })
     }
@@ -212,7 +229,8 @@ impl Shell {
     fn insert_char_to_cmdline(&mut self, c: char, sync_terminal: bool) -> Result<(), &'static str> {
         let mut terminal = self.terminal.lock();
         let offset_from_end = terminal.get_cursor_offset_from_end();
-        let insert_idx = self.cmdline.len() - offset_from_end;
+        let insert_idx = libterm::byte_index_from_end(&self.cmdline, offset_from_end)
+            .ok_or("cursor is beyond the start of the command line")?;
         self.cmdline.insert(insert_idx, c);
         if sync_terminal {
             // disable cursor before updating in case the cursor is not at the end and the old text is the prefix of the new one
@@ -231,8 +249,11 @@ impl Shell {
     fn remove_char_from_cmdline(&mut self, erase_left: bool, sync_terminal: bool) -> Result<(), &'static str> {
         let mut cursor_offset_from_end = self.terminal.lock().get_cursor_offset_from_end();
         if erase_left { cursor_offset_from_end += 1; }
-        if cursor_offset_from_end > self.cmdline.len() || cursor_offset_from_end == 0 { return Ok(()); }
-        let erase_idx = self.cmdline.len() - cursor_offset_from_end;
+        if cursor_offset_from_end == 0 { return Ok(()); }
+        let erase_idx = match libterm::byte_index_from_end(&self.cmdline, cursor_offset_from_end) {
+            Some(idx) => idx,
+            None => return Ok(()),
+        };
         self.cmdline.remove(erase_idx);
         if sync_terminal {
             self.terminal.lock().remove_char(cursor_offset_from_end)?;
@@ -247,7 +268,7 @@ impl Shell {
     /// `sync_terminal` indicates whether the terminal screen will be synchronically updated.
     fn clear_cmdline(&mut self, sync_terminal: bool) -> Result<(), &'static str> {
         if sync_terminal {
-            for _i in 0..self.cmdline.len() {
+            for _i in 0..self.cmdline.chars().count() {
                 self.terminal.lock().remove_char(1)?;
             }
         }
@@ -280,6 +301,44 @@ impl Shell {
         Ok(())
     }
 
+    /// Sends the characters in the input buffer to the foreground application as a line of input.
+    fn send_input_buff(&mut self) -> Result<(), &'static str> {
+        let Some(fg_job_num) = self.fg_job_num else {
+            return Ok(());
+        };
+        if let Some(job) = self.jobs.get(&fg_job_num) {
+            self.terminal.lock().print_to_terminal("\n".to_string());
+            let mut buffered_string = String::new();
+            mem::swap(&mut buffered_string, &mut self.input_buffer);
+            buffered_string.push('\n');
+            job.stdin_writer.lock().write_all(buffered_string.as_bytes())
+                .or(Err("shell failed to write to stdin"))?;
+        }
+        Ok(())
+    }
+
+    /// Inserts the given text as if it were typed.
+    ///
+    /// If an application is running, each line of the text is sent to it.
+    /// Otherwise, the text is inserted into the command line, with newlines and tabs replaced by spaces
+    /// such that pasting text never runs a command by accident.
+    fn paste(&mut self, text: &str) -> Result<(), &'static str> {
+        for c in text.chars() {
+            if self.fg_job_num.is_some() {
+                if c == '\n' {
+                    self.send_input_buff()?;
+                } else {
+                    self.insert_char_to_input_buff(c, true)?;
+                }
+            } else if c == '\n' || c == '\t' {
+                self.insert_char_to_cmdline(' ', true)?;
+            } else if !c.is_control() {
+                self.insert_char_to_cmdline(c, true)?;
+            }
+        }
+        Ok(())
+    }
+
     /// Remove a character from the input buffer to the application.
     /// `sync_terminal` indicates whether the terminal screen will be synchronically updated.
     fn remove_char_from_input_buff(&mut self, sync_terminal: bool) -> Result<(), &'static str> {
@@ -292,7 +351,7 @@ impl Shell {
 
     /// Move the cursor to the very beginning of the input command line.
     fn move_cursor_leftmost(&mut self) -> Result<(), &'static str> {
-        self.update_cursor_pos(self.cmdline.len())?;
+        self.update_cursor_pos(self.cmdline.chars().count())?;
         Ok(())
     }
 
@@ -306,7 +365,7 @@ impl Shell {
     /// it simply returns.
     fn move_cursor_left(&mut self) -> Result<(), &'static str> {
         let offset_from_end = self.terminal.lock().get_cursor_offset_from_end();
-        if offset_from_end < self.cmdline.len() {
+        if offset_from_end < self.cmdline.chars().count() {
             self.update_cursor_pos(offset_from_end + 1)?;
         }
         Ok(())
@@ -330,9 +389,9 @@ impl Shell {
         terminal.cursor.disable();
         terminal.display_cursor()?;
         if offset_from_end == 0 {
-            terminal.update_cursor_pos(0, 0)
-        } else if offset_from_end <= self.cmdline.len() {
-            terminal.update_cursor_pos(offset_from_end, self.cmdline.as_bytes()[self.cmdline.len() - offset_from_end]);
+            terminal.update_cursor_pos(0, ' ')
+        } else if let Some(c) = self.cmdline.chars().rev().nth(offset_from_end - 1) {
+            terminal.update_cursor_pos(offset_from_end, c);
         }
         terminal.cursor.enable();
         
@@ -387,6 +446,17 @@ impl Shell {
             return Ok(()); 
         }
 
+        // Ctrl+Shift+C copies the text selected with the mouse to the clipboard.
+        if keyevent.modifiers.is_control() && keyevent.modifiers.is_shift() && keyevent.keycode == Keycode::C {
+            self.terminal.lock().copy_selection();
+            return Ok(());
+        }
+
+        // Ctrl+Shift+V pastes the text on the clipboard as if it were typed.
+        if keyevent.modifiers.is_control() && keyevent.modifiers.is_shift() && keyevent.keycode == Keycode::V {
+            return self.paste(&clipboard::get());
+        }
+
         // Ctrl+C signals the shell to exit the job
         if keyevent.modifiers.is_control() && keyevent.keycode == Keycode::C {
             let fg_job_num = if let Some(fg_job_num) = self.fg_job_num {
@@ -402,27 +472,13 @@ impl Shell {
             };
 
             if let Some(task_refs) = self.jobs.get(&fg_job_num).map(|job| &job.tasks) {
-                // Lock the shared structure in `app_io` and then kill the running application
-                // Kill all tasks in the job.
+                // Interrupt all tasks in the job. Each task receives the notification
+                // at its next preemption point or blocking call, even if it's in a busy loop,
+                // and is then killed unless it has registered a handler for it.
                 for task_ref in task_refs {
                     if task_ref.has_exited() { continue; }
-                    match task_ref.kill(KillReason::Requested) {
-                        Ok(_) => {
-                            task::scheduler::remove_task(task_ref);
-                        }
-                        Err(e) => error!("Could not kill task, error: {}", e),
-                    }
-
-                    // Here we must wait for the running application to quit before releasing the lock,
-                    // because the previous `kill` method will NOT stop the application immediately.
-                    // We must circumvent the situation where the application is killed while holding the
-                    // lock. We wait for the application to finish its last time slice. It will then be
-                    // removed from the run queue. We can thereafter release the lock.
-                    loop {
-                        scheduler::schedule(); // yield the CPU
-                        if !task_ref.is_running() {
-                            break;
-                        }
+                    if let Err(e) = task::notifications::send(task_ref, Notification::Interrupt) {
+                        error!("Could not interrupt task, error: {}", e);
                     }
                 }
                 self.terminal.lock().print_to_terminal("^C\n".to_string());
@@ -502,23 +558,15 @@ impl Shell {
         }
 
         // Attempts to run the command whenever the user presses enter and updates the cursor tracking variables 
-        if keyevent.keycode == Keycode::Enter && keyevent.keycode.to_ascii(keyevent.modifiers).is_some() {
+        if keyevent.keycode == Keycode::Enter && self.key_to_char(&keyevent).is_some() {
             let cmdline = self.cmdline.clone();
             if cmdline.is_empty() && self.fg_job_num.is_none() {
                 // reprints the prompt on the next line if the user presses enter and hasn't typed anything into the prompt
                 self.terminal.lock().print_to_terminal("\n".to_string());
                 self.redisplay_prompt();
                 return Ok(());
-            } else if let Some(ref fg_job_num) = self.fg_job_num { // send buffered characters to the running application
-                if let Some(job) = self.jobs.get(fg_job_num) {
-                    self.terminal.lock().print_to_terminal("\n".to_string());
-                    let mut buffered_string = String::new();
-                    mem::swap(&mut buffered_string, &mut self.input_buffer);
-                    buffered_string.push('\n');
-                    job.stdin_writer.lock().write_all(buffered_string.as_bytes())
-                        .or(Err("shell failed to write to stdin"))?;
-                }
-                return Ok(());
+            } else if self.fg_job_num.is_some() { // send buffered characters to the running application
+                return self.send_input_buff();
             } else { // start a new job
                 self.terminal.lock().print_to_terminal("\n".to_string());
                 self.command_history.push(cmdline);
@@ -529,7 +577,9 @@ impl Shell {
                     self.execute_internal()?;
                     self.clear_cmdline(false)?;
                 } else { // shell invokes user programs
-                    let new_job_num = self.build_new_job()?;
+                    let Some(new_job_num) = self.build_new_job()? else {
+                        return Ok(());
+                    };
                     self.fg_job_num = Some(new_job_num);
 
                     // If the new job is to run in the background, then we should not put it to foreground.
@@ -604,31 +654,54 @@ impl Shell {
         }
 
         // Tracks what the user has typed so far, excluding any keypresses by the backspace and Enter key, which are special and are handled directly below
-        if keyevent.keycode.to_ascii(keyevent.modifiers).is_some() {
-            match keyevent.keycode.to_ascii(keyevent.modifiers) {
-                Some(c) => {
-                    // If currently we have a task running, insert it to the input buffer, otherwise
-                    // to the cmdline.
-                    if let Some(_fg_job_num) = self.fg_job_num {
-                        self.insert_char_to_input_buff(c, true)?;
-                        return Ok(());
-                    }
-                    else {
-                        self.insert_char_to_cmdline(c, true)?;
-                    }
-                },
-                None => {
-                    return Err("Couldn't get key event");
-                }
+        if let Some(c) = self.key_to_char(&keyevent) {
+            // If currently we have a task running, insert it to the input buffer, otherwise
+            // to the cmdline.
+            if let Some(_fg_job_num) = self.fg_job_num {
+                self.insert_char_to_input_buff(c, true)?;
+                return Ok(());
+            }
+            else {
+                self.insert_char_to_cmdline(c, true)?;
             }
         }
         Ok(())
     }
 
+    /// Returns the character that the given key types in this terminal's keyboard layout, if any.
+    ///
+    /// The layout is named by the `KEYMAP` environment variable, which the `loadkeys` application sets,
+    /// so it's reloaded whenever that variable has changed.
+    fn key_to_char(&mut self, keyevent: &KeyEvent) -> Option<char> {
+        let name = self.env.lock().get(keymap::ENV_VAR).cloned();
+        let name = name.as_deref().unwrap_or(keymap::DEFAULT_LAYOUT);
+        if name != self.keymap.name() {
+            match keymap::load(name) {
+                Ok(new_keymap) => self.keymap = new_keymap,
+                Err(e) => {
+                    // Keep the current layout, and don't try to load the broken one again.
+                    error!("failed to load keyboard layout {:?}: {}", name, e);
+                    self.env.lock().set(keymap::ENV_VAR.to_string(), self.keymap.name().to_string());
+                }
+            }
+        }
+        self.keymap.translate(keyevent.keycode, keyevent.modifiers)
+    }
+
     /// Create a single task. `cmd` is the name of the application. `args` are the provided
     /// arguments. It returns a task reference on success.
     fn create_single_task(&mut self, cmd: String, args: Vec<String>) -> Result<JoinableTaskRef, AppErr> {
 
+        // Validate the arguments if the application declares its command-line interface.
+        let validation = with_app_command(&cmd, |command| match command.parse(&args) {
+            Ok(_) => Ok(()),
+            Err(command::Error::HelpRequested) => Err(AppErr::Help(command.help())),
+            Err(e) => Err(AppErr::InvalidArgs(command.error_message(&e))),
+        });
+        if let Some(Err(e)) = validation {
+            return Err(e);
+        }
+
         // Check that the application actually exists
         let namespace_dir = task::with_current_task(|t|
             t.get_namespace().dir().clone()
@@ -695,7 +768,8 @@ impl Shell {
     }
 
     /// Start a new job in the shell by the command line.
-    fn build_new_job(&mut self) -> Result<isize, &'static str> {
+    /// Returns `None` if no job was started because only the help text of an application was requested.
+    fn build_new_job(&mut self) -> Result<Option<isize>, &'static str> {
         match self.eval_cmdline() {
             Ok(task_refs) => {
 
@@ -763,9 +837,10 @@ impl Shell {
                 }
 
                 self.jobs.insert(new_job_num, new_job);
-                Ok(new_job_num)
+                Ok(Some(new_job_num))
             },
             Err(err) => {
+                let help_requested = matches!(err, AppErr::Help(_));
                 let err_msg = match err {
                     AppErr::NotFound(command) => {
                         // No need to return err if command is empty
@@ -778,13 +853,19 @@ impl Shell {
                     },
                     AppErr::NamespaceErr      => "Failed to find directory of application executables.\n".to_string(),
                     AppErr::SpawnErr(e)       => format!("Failed to spawn new task to run command. Error: {e}.\n"),
+                    AppErr::InvalidArgs(msg)  => format!("{msg}\n"),
+                    AppErr::Help(help)        => format!("{help}\n"),
                 };
                 self.terminal.lock().print_to_terminal(err_msg);
                 if let Err(msg) = self.clear_cmdline(false) {
                     self.terminal.lock().print_to_terminal(format!("{msg}\n"));
                 }
                 self.redisplay_prompt();
-                Err("Failed to evaluate command line.")
+                if help_requested {
+                    Ok(None)
+                } else {
+                    Err("Failed to evaluate command line.")
+                }
             }
         }
     }
@@ -966,7 +1047,9 @@ impl Shell {
     fn complete_cmdline(&mut self) -> Result<(), &'static str> {
 
         // Get the last string slice in the pipe chain.
-        let cmdline = self.cmdline[0..self.cmdline.len()-self.terminal.lock().get_cursor_offset_from_end()].to_string();
+        let cursor_idx = libterm::byte_index_from_end(&self.cmdline, self.terminal.lock().get_cursor_offset_from_end())
+            .unwrap_or(0);
+        let cmdline = self.cmdline[0..cursor_idx].to_string();
         let last_cmd_in_pipe = match cmdline.split('|').last() {
             Some(cmd) => cmd,
             None => return Ok(())
@@ -978,14 +1061,32 @@ impl Shell {
             None => return Ok(())
         };
 
-        // Try to find matches. Only match against internal commands and applications
+        // If we are completing an argument of an application that declares its command-line
+        // interface, complete it according to what the application accepts at that position.
+        let app_completion = last_cmd_in_pipe.trim_start().split_once(' ').and_then(|(cmd, args)| {
+            let mut preceding_args: Vec<&str> = args.split_whitespace().collect();
+            if !last_word_in_cmd.is_empty() {
+                preceding_args.pop();
+            }
+            with_app_command(cmd, |command| command.complete(&preceding_args, &last_word_in_cmd))
+        });
+
+        // Otherwise, try to find matches. Only match against internal commands and applications
         // within the namespace if we are entering the command. Otherwise, we are trying
         // to complete an argument, then we also include file paths to match against.
-        let mut possible_names = self.find_internal_cmd_match(&last_word_in_cmd)?;
-        possible_names.extend(self.find_app_name_match(&last_word_in_cmd)?.iter().cloned());
-        if !last_cmd_in_pipe.trim().is_empty() {
-            possible_names.extend(self.find_file_path_match(&last_word_in_cmd)?.iter().cloned());
-        }
+        let possible_names = match app_completion {
+            Some(Completion::Candidates(names)) => names,
+            Some(Completion::Path) => self.find_file_path_match(&last_word_in_cmd)?,
+            Some(Completion::Nothing) => Vec::new(),
+            None => {
+                let mut possible_names = self.find_internal_cmd_match(&last_word_in_cmd)?;
+                possible_names.extend(self.find_app_name_match(&last_word_in_cmd)?.iter().cloned());
+                if !last_cmd_in_pipe.trim().is_empty() {
+                    possible_names.extend(self.find_file_path_match(&last_word_in_cmd)?.iter().cloned());
+                }
+                possible_names
+            }
+        };
 
         // If there is only one possiblity, complete the command line.
         if possible_names.len() == 1 {
@@ -1286,6 +1387,13 @@ impl Shell {
                         self.key_event_producer.write_one(input_event.key_event);
                     }
 
+                    // Handles text selection and scrolling with the mouse
+                    Event::MousePositionEvent(ref mouse_event) => {
+                        if let Err(e) = self.terminal.lock().handle_mouse_event(mouse_event) {
+                            error!("{}", e);
+                        }
+                    }
+
                     _unhandled => { 
                         // trace!("Shell is ignoring unhandled event: {:?}", _unhandled);
                     }
@@ -1464,3 +1572,36 @@ fn shell_loop(mut _dummy: ()) -> Result<(), &'static str> {
     Shell::new()?.start()?;
     Ok(())
 }
+
+/// Calls `f` with the command-line interface declared by the application with the given name.
+///
+/// The application crate is loaded only for the duration of `f`,
+/// in order to call the function named [`command::COMMAND_FUNCTION_NAME`] that it exposes.
+/// Returns `None` if there is no such application or it doesn't declare its command-line interface.
+fn with_app_command<R>(cmd: &str, f: impl FnOnce(&Command) -> R) -> Option<R> {
+    let namespace = task::with_current_task(|t| t.get_namespace().clone()).ok()?;
+    let mut matching_apps = namespace.dir().get_files_starting_with(&format!("{cmd}-")).into_iter();
+    let (Some(app_file), None) = (matching_apps.next(), matching_apps.next()) else {
+        return None;
+    };
+    let kernel_mmi_ref = memory::get_kernel_mmi_ref()?;
+    let app_crate = match CrateNamespace::load_crate_as_application(&namespace, &app_file, kernel_mmi_ref, false) {
+        Ok(app_crate) => app_crate,
+        Err(e) => {
+            warn!("Failed to load application {:?} to get its command-line interface: {}", cmd, e);
+            return None;
+        }
+    };
+
+    let command_func_sec = {
+        let app_crate = app_crate.lock_as_ref();
+        let expected_section_name = format!("{}{}{}", app_crate.crate_name_as_prefix(), command::COMMAND_FUNCTION_NAME, SECTION_HASH_DELIMITER);
+        app_crate.find_section(|sec|
+            sec.typ == SectionType::Text && sec.name_without_hash() == expected_section_name
+        ).cloned()
+    }?;
+    // SAFETY: the `command` crate requires that this function has the `CommandFunc` signature.
+    let command_func = unsafe { command_func_sec.as_func::<command::CommandFunc>() }.ok()?;
+    // The returned `Command` lives in the application crate, so it must not be used after `app_crate` is dropped.
+    Some(f(command_func()))
+}
@@ -0,0 +1,96 @@
+//! Gathers the state of the system, e.g., its boot timeline, loaded crates, tasks, and recent log records,
+//! into a single report that can be attached to a bug report.
+//!
+//! By default, the report is streamed to stdout as JSON.
+//! With `--output`, it's saved to the given file instead, as JSON or in a compact binary format.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::{format, string::String, vec::Vec};
+use app_io::{print, println};
+use command::{Arg, Command, Matches, Value};
+use core::fmt;
+use fs_node::{DirRef, FileOrDir};
+use path::Path;
+use system_report::Format;
+
+pub static COMMAND: Command = Command {
+    name: "sysreport",
+    about: "Gather the state of the system into a report for attaching to bug reports",
+    args: &[
+        Arg::option("output")
+            .short('o')
+            .value(Value::Path)
+            .help("save the report to the given file instead of printing it"),
+        Arg::option("format")
+            .short('f')
+            .value(Value::OneOf(&["json", "bin"]))
+            .help("the format of the saved report (default: based on the file extension, otherwise json)"),
+    ],
+};
+
+pub fn command() -> &'static Command {
+    &COMMAND
+}
+
+pub fn main(args: Vec<String>) -> isize {
+    let matches = match COMMAND.get_matches(args) {
+        Ok(m) => m,
+        Err(exit_value) => return exit_value,
+    };
+
+    match run(&matches) {
+        Ok(()) => 0,
+        Err(e) => {
+            println!("Error: {}", e);
+            -1
+        }
+    }
+}
+
+fn run(matches: &Matches) -> Result<(), String> {
+    let report = system_report::generate();
+
+    let Some(path) = matches.value("output").map(Path::new) else {
+        if matches.value("format") == Some("bin") {
+            return Err("the binary format can only be saved to a file".into());
+        }
+        report.write_json(Stdout).map_err(|e| format!("failed to print report: {e}"))?;
+        println!();
+        return Ok(());
+    };
+
+    let format = matches.value("format")
+        .or_else(|| path.extension())
+        .and_then(Format::from_extension)
+        .unwrap_or(Format::Json);
+    let cwd = task::with_current_task(|t| t.get_env().lock().working_dir.clone())
+        .map_err(|_| "failed to get current task")?;
+    let name = path.file_name().ok_or_else(|| format!("{} is not a file name", path))?;
+    system_report::save(&report, format, &parent_dir(path, &cwd)?, name)?;
+    println!("Saved the system report to {}", path);
+    Ok(())
+}
+
+/// Writes to the current task's stdout.
+struct Stdout;
+
+impl fmt::Write for Stdout {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        print!("{}", s);
+        Ok(())
+    }
+}
+
+/// Returns the directory that contains the file at the given path.
+fn parent_dir(path: &Path, cwd: &DirRef) -> Result<DirRef, String> {
+    match path.parent() {
+        Some(parent) if parent != Path::new("") => match parent.get(cwd) {
+            Some(FileOrDir::Dir(dir)) => Ok(dir),
+            _ => Err(format!("{} is not a directory", parent)),
+        },
+        _ => Ok(cwd.clone()),
+    }
+}
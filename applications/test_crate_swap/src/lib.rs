@@ -0,0 +1,91 @@
+//! Tests that a crate swap whose state transfer callback fails
+//! leaves the existing crates in the namespace untouched.
+//!
+//! The crate to swap can be given as the first argument; by default, `app_io` is swapped,
+//! as it is always loaded and has many dependents.
+//! The new crate is loaded from the same object file as the old crate.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::{format, string::String, sync::Arc, vec::Vec};
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use app_io::println;
+use mod_mgmt::{CrateNamespace, IntoCrateObjectFile, StrongCrateRef};
+
+const STATE_TRANSFER_ERROR: &str = "test_crate_swap: intentional state transfer failure";
+
+pub fn main(args: Vec<String>) -> isize {
+    match rmain(args.first().map(String::as_str).unwrap_or("app_io")) {
+        Ok(()) => {
+            println!("the failed swap left the old crate and its dependents untouched");
+            0
+        }
+        Err(e) => {
+            println!("Error: {}", e);
+            -1
+        }
+    }
+}
+
+fn rmain(crate_name: &str) -> Result<(), String> {
+    let kernel_mmi_ref = memory::get_kernel_mmi_ref().ok_or("couldn't get kernel_mmi_ref")?;
+    let namespace = task::with_current_task(|t| t.get_namespace().clone())
+        .map_err(|_| "couldn't get current task")?;
+
+    let (full_crate_name, old_crate, _) = CrateNamespace::get_crate_starting_with(&namespace, &format!("{crate_name}-"))
+        .ok_or_else(|| format!("crate {crate_name:?} isn't loaded"))?;
+    let dependencies_before = count_dependencies_on(&old_crate);
+    if dependencies_before == 0 {
+        return Err(format!("no sections depend on crate {crate_name:?}, so it can't be used for this test"));
+    }
+
+    let callback_invoked = AtomicBool::new(false);
+    let state_transfer = |_: &Arc<CrateNamespace>, _: &CrateNamespace| {
+        callback_invoked.store(true, Ordering::Relaxed);
+        Err(STATE_TRANSFER_ERROR)
+    };
+    let result = crate_swap::swap_crate(
+        &namespace,
+        &full_crate_name,
+        IntoCrateObjectFile::Prefix(format!("{crate_name}-")),
+        Some(&state_transfer),
+        kernel_mmi_ref,
+        false,
+    );
+
+    if result != Err(STATE_TRANSFER_ERROR) {
+        return Err(format!("expected the swap to fail with the state transfer error, but it returned {result:?}"));
+    }
+    if !callback_invoked.load(Ordering::Relaxed) {
+        return Err("the state transfer callback wasn't invoked".into());
+    }
+    match CrateNamespace::get_crate_and_namespace(&namespace, &full_crate_name) {
+        Some((current_crate, _)) if current_crate.ptr_eq(&old_crate) => { }
+        _ => return Err("the old crate is no longer loaded in the namespace".into()),
+    }
+    let dependencies_after = count_dependencies_on(&old_crate);
+    if dependencies_after != dependencies_before {
+        return Err(format!(
+            "{dependencies_before} sections depended on the old crate before the swap, but only {dependencies_after} do afterwards"
+        ));
+    }
+    Ok(())
+}
+
+/// Returns the number of sections in other crates that currently depend on a section in the given crate.
+fn count_dependencies_on(crate_ref: &StrongCrateRef) -> usize {
+    let krate = crate_ref.lock_as_ref();
+    krate.global_sections_iter()
+        .map(|sec| {
+            sec.inner.read().sections_dependent_on_me.iter()
+                .filter_map(|dependent| dependent.section.upgrade())
+                .filter(|dependent| dependent.inner.read().sections_i_depend_on.iter()
+                    .any(|dependency| Arc::ptr_eq(&dependency.section, sec))
+                )
+                .count()
+        })
+        .sum()
+}
@@ -0,0 +1,109 @@
+//! Tests that each task's FPU/SIMD registers are preserved across context switches.
+//!
+//! Two tasks pinned to the same CPU each write a distinct value into `xmm0`,
+//! then repeatedly yield to each other and check that their value is unchanged.
+//! This works regardless of whether the registers are saved by the `fpu_state` crate
+//! (in either eager or lazy mode) or by the context switch routine itself.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::{format, string::String, vec::Vec};
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use app_io::println;
+
+/// How many times each task yields and then checks its register value.
+const ITERATIONS: usize = 1000;
+
+/// The number of times a task observed another task's value in its register.
+static FAILURES: AtomicUsize = AtomicUsize::new(0);
+
+pub fn main(_: Vec<String>) -> isize {
+    if !cfg!(target_arch = "x86_64") {
+        println!("test_fpu_state is only supported on x86_64");
+        return 0;
+    }
+
+    let cpu = cpu::current_cpu();
+    let tasks = [0x1111_1111_1111_1111u64, 0x2222_2222_2222_2222]
+        .into_iter()
+        .map(|value| {
+            spawn::new_task_builder(worker, value)
+                .name(format!("test_fpu_state-{value:#X}"))
+                .pin_on_cpu(cpu)
+                .block()
+                .spawn()
+        })
+        .collect::<Result<Vec<_>, _>>();
+    let tasks = match tasks {
+        Ok(tasks) => tasks,
+        Err(e) => {
+            println!("failed to spawn tasks: {}", e);
+            return -1;
+        }
+    };
+
+    for task in tasks.iter() {
+        task.unblock().unwrap();
+    }
+    for task in tasks {
+        if task.join().is_err() {
+            println!("failed to join task");
+            return -1;
+        }
+    }
+
+    match FAILURES.load(Ordering::Relaxed) {
+        0 => {
+            println!("xmm0 was preserved across all context switches");
+            0
+        }
+        failures => {
+            println!("xmm0 was clobbered {} times", failures);
+            -1
+        }
+    }
+}
+
+fn worker(value: u64) {
+    // SAFETY: the CPU supports SSE2, which is required by x86_64.
+    unsafe { write_xmm0(value) };
+    for _ in 0..ITERATIONS {
+        task::scheduler::schedule();
+        // SAFETY: see above.
+        let actual = unsafe { read_xmm0() };
+        if actual != value {
+            FAILURES.fetch_add(1, Ordering::Relaxed);
+            // Restore our value such that each clobbering is only counted once.
+            // SAFETY: see above.
+            unsafe { write_xmm0(value) };
+        }
+    }
+}
+
+// Theseus is compiled without SSE by default, so it must be enabled for these functions
+// in order to access the `xmm` registers.
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "sse2")]
+unsafe fn write_xmm0(value: u64) {
+    core::arch::asm!("movq xmm0, {}", in(reg) value, out("xmm0") _, options(nomem, nostack));
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "sse2")]
+unsafe fn read_xmm0() -> u64 {
+    let value;
+    core::arch::asm!("movq {}, xmm0", out(reg) value, options(nomem, nostack));
+    value
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+unsafe fn write_xmm0(_value: u64) { }
+
+#[cfg(not(target_arch = "x86_64"))]
+unsafe fn read_xmm0() -> u64 {
+    0
+}
@@ -0,0 +1,161 @@
+//! Periodically shows the tasks that used the most CPU time, like Unix's `top`.
+//!
+//! Each refresh shows how much of the CPU each task used since the previous refresh,
+//! as a percentage of the total time available across all CPUs,
+//! so a task that keeps one CPU of a four-CPU machine busy is shown at 25%.
+//! The time used by the idle tasks is shown as the idle percentage in the summary line.
+//!
+//! `top` keeps refreshing until it is interrupted with `Ctrl + C`,
+//! unless a number of iterations is given.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::{collections::BTreeMap, format, string::{String, ToString}, vec::Vec};
+use app_io::{print, println};
+use command::{Arg, Command, Matches, Value};
+use core::{fmt::Write, time::Duration};
+use fmt_utils::{or_dash, Align, Clock, Table};
+use task::{control::TaskInfo, RunState};
+use time::Instant;
+
+pub static COMMAND: Command = Command {
+    name: "top",
+    about: "Periodically show the tasks using the most CPU time",
+    args: &[
+        Arg::option("delay")
+            .short('d')
+            .value(Value::Integer)
+            .help("the number of seconds between refreshes (default: 2)"),
+        Arg::option("iterations")
+            .short('n')
+            .value(Value::Integer)
+            .help("exit after refreshing this many times"),
+        Arg::option("rows")
+            .short('r')
+            .value(Value::Integer)
+            .help("the maximum number of tasks to show (default: 20)"),
+    ],
+};
+
+pub fn command() -> &'static Command {
+    &COMMAND
+}
+
+pub fn main(args: Vec<String>) -> isize {
+    let matches = match COMMAND.get_matches(args) {
+        Ok(m) => m,
+        Err(exit_value) => return exit_value,
+    };
+
+    match run(&matches) {
+        Ok(()) => 0,
+        Err(e) => {
+            println!("Error: {}", e);
+            -1
+        }
+    }
+}
+
+fn run(matches: &Matches) -> Result<(), String> {
+    let delay: u64 = matches.value_of("delay").map_err(|e| format!("{}", e))?.unwrap_or(2);
+    let iterations = matches.value_of("iterations").map_err(|e| format!("{}", e))?.unwrap_or(usize::MAX);
+    let rows = matches.value_of("rows").map_err(|e| format!("{}", e))?.unwrap_or(20);
+    if delay == 0 {
+        return Err("the delay must be at least one second".into());
+    }
+
+    let mut previous = Snapshot::take();
+    for _ in 0..iterations {
+        sleep::sleep(Duration::from_secs(delay)).map_err(|_| "failed to sleep")?;
+        let current = Snapshot::take();
+        // Clear the screen and draw the whole frame at once to avoid flickering.
+        print!("\x1b[2J\x1b[H{}", current.render(&previous, rows));
+        previous = current;
+    }
+    Ok(())
+}
+
+/// The states of all tasks at a given time.
+struct Snapshot {
+    time: Instant,
+    tasks: Vec<TaskInfo>,
+}
+
+impl Snapshot {
+    fn take() -> Snapshot {
+        Snapshot {
+            time: Instant::now(),
+            tasks: task::control::list(),
+        }
+    }
+
+    /// Returns the screen that shows the CPU usage of tasks since the `previous` snapshot,
+    /// including at most `rows` tasks.
+    fn render(&self, previous: &Snapshot, rows: usize) -> String {
+        let previous_times: BTreeMap<usize, Duration> = previous
+            .tasks
+            .iter()
+            .map(|task| (task.id, task.cpu_time))
+            .collect();
+        let available = self.time.duration_since(previous.time).as_nanos() * cpu::cpu_count() as u128;
+        // A task that didn't exist in the previous snapshot has run only since then.
+        let usage = |task: &TaskInfo| {
+            let used = task.cpu_time.saturating_sub(previous_times.get(&task.id).copied().unwrap_or_default());
+            percentage(used.as_nanos(), available)
+        };
+
+        let count = |runstate: RunState| {
+            self.tasks.iter().filter(|task| !task.suspended && task.runstate == runstate).count()
+        };
+        let idle: f64 = self.tasks.iter().filter(|task| task.is_idle).map(usage).sum();
+        let mut screen = String::new();
+        let _ = writeln!(
+            screen,
+            "Tasks: {} total, {} runnable, {} blocked, {} suspended    CPUs: {}, {:.1}% idle\n",
+            self.tasks.len(),
+            count(RunState::Runnable),
+            count(RunState::Blocked),
+            self.tasks.iter().filter(|task| task.suspended).count(),
+            cpu::cpu_count(),
+            idle,
+        );
+
+        let mut tasks: Vec<(f64, &TaskInfo)> = self
+            .tasks
+            .iter()
+            .filter(|task| !task.is_idle)
+            .map(|task| (usage(task), task))
+            .collect();
+        // Show the busiest tasks first, breaking ties by the total time used.
+        tasks.sort_by(|(a_usage, a), (b_usage, b)| {
+            b_usage.total_cmp(a_usage).then(b.cpu_time.cmp(&a.cpu_time))
+        });
+
+        let mut table = Table::new(["ID", "%CPU", "TIME", "CPU", "PRI", "NAME"])
+            .align(1, Align::Right)
+            .align(2, Align::Right);
+        for (usage, task) in tasks.into_iter().take(rows) {
+            table.row([
+                task.id.to_string(),
+                format!("{:.1}", usage),
+                Clock(task.cpu_time).to_string(),
+                or_dash(task.cpu),
+                or_dash(task.priority),
+                task.name.clone(),
+            ]);
+        }
+        let _ = write!(screen, "{}", table);
+        screen
+    }
+}
+
+/// Returns `part` as a percentage of `total`.
+fn percentage(part: u128, total: u128) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        (part as f64 * 100.0 / total as f64).min(100.0)
+    }
+}
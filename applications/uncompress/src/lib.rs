@@ -0,0 +1,99 @@
+//! Decompresses zstd- or lz4-compressed files.
+//!
+//! Each compressed file, e.g., `notes.txt.zst`, is decompressed into a new file
+//! in the same directory, named without the extension, e.g., `notes.txt`.
+//! The compressed file is kept.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::{format, string::{String, ToString}, vec::Vec};
+use app_io::{print, println};
+use command::{Arg, Command, Matches, Value};
+use decompress::Format;
+use fs_node::{DirRef, FileOrDir};
+use memfs::MemFile;
+use path::Path;
+
+pub static COMMAND: Command = Command {
+    name: "uncompress",
+    about: "Decompress zstd- or lz4-compressed files",
+    args: &[
+        Arg::flag("stdout")
+            .short('c')
+            .help("print the decompressed contents instead of writing them to new files"),
+        Arg::positional("FILE")
+            .required()
+            .multiple()
+            .value(Value::Path)
+            .help("the compressed files, whose names end with .zst or .lz4"),
+    ],
+};
+
+pub fn command() -> &'static Command {
+    &COMMAND
+}
+
+pub fn main(args: Vec<String>) -> isize {
+    let matches = match COMMAND.get_matches(args) {
+        Ok(m) => m,
+        Err(exit_value) => return exit_value,
+    };
+
+    match run(&matches) {
+        Ok(()) => 0,
+        Err(e) => {
+            println!("Error: {}", e);
+            -1
+        }
+    }
+}
+
+fn run(matches: &Matches) -> Result<(), String> {
+    let cwd = task::with_current_task(|t| t.get_env().lock().working_dir.clone())
+        .map_err(|_| "failed to get current task")?;
+    let to_stdout = matches.is_present("stdout");
+
+    let mut failed = false;
+    for path in matches.values("FILE") {
+        if let Err(e) = uncompress(path, &cwd, to_stdout) {
+            println!("{}: {}", path, e);
+            failed = true;
+        }
+    }
+    if failed {
+        Err("couldn't decompress every file".into())
+    } else {
+        Ok(())
+    }
+}
+
+/// Decompresses the file at the given path, either into a new file or to standard output.
+fn uncompress(path: &str, cwd: &DirRef, to_stdout: bool) -> Result<(), String> {
+    let file = match Path::new(path).get(cwd) {
+        Some(FileOrDir::File(file)) => file,
+        Some(FileOrDir::Dir(_)) => return Err("is a directory".into()),
+        None => return Err("doesn't exist".into()),
+    };
+    let name = file.lock().get_name();
+    let (format, new_name) = Format::from_file_name(&name)
+        .ok_or("unknown extension, expected .zst or .lz4")?;
+    let decompressed = decompress::decompress_reader(Some(format), &mut *file.lock())?;
+
+    if to_stdout {
+        print!("{}", String::from_utf8_lossy(decompressed.as_ref()));
+        return Ok(());
+    }
+
+    let parent = file.lock().get_parent_dir().ok_or("couldn't get the file's directory")?;
+    if parent.lock().get(new_name).is_some() {
+        return Err(format!("{} already exists", new_name));
+    }
+    let new_file = MemFile::create(new_name.to_string(), &parent)?;
+    new_file
+        .lock()
+        .write_at(decompressed.as_ref(), 0)
+        .map_err(|_| format!("failed to write {}", new_name))?;
+    Ok(())
+}
@@ -49,6 +49,7 @@ use path::Path;
 use vfs_node::VFSDirectory;
 use fs_node::{FileOrDir, DirRef};
 use ota_update_client::DIFF_FILE_NAME;
+use ota_update_client::repository::{CrateEntry, RepositoryClient};
 
 
 
@@ -121,6 +122,12 @@ fn rmain(matches: Matches) -> Result<(), String> {
             let update_build = matches.free.get(1).ok_or_else(|| String::from("missing UPDATE_BUILD argument"))?;
             download(remote_endpoint, update_build, matches.free.get(2..))
         }
+        "search" => {
+            search(remote_endpoint, matches.free.get(1))
+        }
+        "stage" => {
+            stage(remote_endpoint, &matches.free[1..])
+        }
         "apply" | "ap" => {
             let base_dir_path = matches.free.get(1).ok_or_else(|| String::from("missing BASE_DIR path argument"))?;
             apply(base_dir_path.as_ref())
@@ -212,6 +219,95 @@ fn download(remote_endpoint: IpEndpoint, update_build: &str, crate_list: Option<
 }
 
 
+/// Lists the versions of crates available from the repository on the update server,
+/// optionally only those of the crate with the given name.
+/// Versions that are currently loaded are marked with a `*`.
+fn search(remote_endpoint: IpEndpoint, crate_name: Option<&String>) -> Result<(), String> {
+    let iface = get_default_interface().ok_or_else(|| "couldn't get default interface".to_owned())?;
+    let repository = RepositoryClient::connect(&iface, remote_endpoint).map_err(|e| e.to_string())?;
+    let index = repository.index().map_err(|e| e.to_string())?;
+    let curr_namespace = get_my_current_namespace();
+
+    let mut names: Vec<&str> = index.entries().iter().map(|e| e.name.as_str()).collect();
+    names.sort_unstable();
+    names.dedup();
+    for name in names.into_iter().filter(|n| crate_name.map_or(true, |c| c.as_str() == *n)) {
+        for entry in index.versions_of(name) {
+            let loaded = if is_loaded(&curr_namespace, &entry.object_file) { "*" } else { " " };
+            let signed = if entry.signature.is_some() { "signed" } else { "unsigned" };
+            println!("{} {} {} ({}, {})", loaded, entry.name, entry.version, entry.object_file, signed);
+        }
+    }
+    Ok(())
+}
+
+
+/// Downloads the given crates from the repository on the update server, 
+/// along with any of their dependencies that aren't already loaded,
+/// into a new base directory that can then be applied with the `apply` command.
+/// 
+/// Each crate is given as `NAME` for its newest version or `NAME@VERSION`.
+fn stage(remote_endpoint: IpEndpoint, crate_args: &[String]) -> Result<(), String> {
+    if crate_args.is_empty() {
+        return Err("missing CRATES to stage".to_string());
+    }
+    let iface = get_default_interface().ok_or_else(|| "couldn't get default interface".to_owned())?;
+    let repository = RepositoryClient::connect(&iface, remote_endpoint).map_err(|e| e.to_string())?;
+    let index = repository.index().map_err(|e| e.to_string())?;
+    let curr_namespace = get_my_current_namespace();
+
+    let mut requested: Vec<&CrateEntry> = Vec::with_capacity(crate_args.len());
+    for arg in crate_args {
+        let (name, version) = match arg.split_once('@') {
+            Some((name, version)) => (name, Some(version)),
+            None => (arg.as_str(), None),
+        };
+        let entry = index.find(name, version)
+            .ok_or_else(|| format!("the repository has no crate {arg:?}"))?;
+        requested.push(entry);
+    }
+    let crates = index.resolve(&requested, |dep| is_loaded(&curr_namespace, dep))?;
+    for entry in crates.iter().filter(|e| !requested.contains(*e)) {
+        println!("Also staging dependency {} {}", entry.name, entry.version);
+    }
+    let contents = repository.download(&crates, None)?;
+
+    let Ok(curr_dir) = task::with_current_task(|t| t.get_env().lock().working_dir.clone()) else {
+        return Err("failed to get current task's working directory".to_string());
+    };
+    let new_namespace_dir = NamespaceDir::new(make_unique_directory("staged", &curr_dir)?);
+    let mut diff_lines: Vec<String> = Vec::with_capacity(crates.len());
+    for (entry, content) in crates.iter().zip(contents) {
+        let cfile = new_namespace_dir.write_crate_object_file(&entry.object_file, &content)?;
+        println!("Staged crate: {:?}, size {}", cfile.lock().get_absolute_path(), content.len());
+
+        // Replace the currently-loaded version of this crate, if any.
+        let old_crate = CrateNamespace::get_crate_starting_with(&curr_namespace, &format!("{}-", entry.name))
+            .map(|(old_crate_name, ..)| old_crate_name)
+            .or_else(|| curr_namespace.get_crate(&entry.name).map(|_| entry.name.as_str().into()));
+        match old_crate {
+            Some(old_crate_name) => diff_lines.push(format!("{}.o -> {}", old_crate_name, entry.object_file)),
+            None => diff_lines.push(format!("+ {}", entry.object_file)),
+        }
+    }
+
+    let diff_file = MemFile::create(String::from(DIFF_FILE_NAME), &new_namespace_dir)?;
+    diff_file.lock().write_at(diff_lines.join("\n").as_bytes(), 0)?;
+    println!("Staged {} crates, run `upd apply {}` to apply them.",
+        crates.len(),
+        new_namespace_dir.lock().get_absolute_path(),
+    );
+    Ok(())
+}
+
+
+/// Returns whether the crate with the given object file name is loaded in the given namespace.
+fn is_loaded(namespace: &Arc<CrateNamespace>, object_file: &str) -> bool {
+    mod_mgmt::crate_name_from_path(Path::new(object_file))
+        .map_or(false, |crate_name| namespace.get_crate(crate_name).is_some())
+}
+
+
 /// Applies an already-downloaded update according the "diff.txt" file
 /// that must be in the given base directory.
 fn apply(base_dir_path: &Path) -> Result<(), String> {
@@ -345,6 +441,15 @@ Runs the given update-related COMMAND. Choices include:
         
     download UPDATE_BUILD CRATES
         Downloads the given list of CRATES for the given UPDATE_BUILD.
+
+    search [CRATE]
+        Lists the versions of all crates, or of the given CRATE,
+        available from the crate repository on the update server.
+
+    stage CRATES
+        Downloads the given list of CRATES, each given as NAME or NAME@VERSION,
+        and any of their dependencies that aren't loaded from the crate repository,
+        into a new base directory that can be used with the apply command.
         
     apply BASE_DIR
         Applies the evolutionary update specified by the diff file 
@@ -0,0 +1,113 @@
+//! This application runs a VNC server that serves the screen to remote VNC clients,
+//! such that a machine can be used interactively over the network.
+//!
+//! On a machine without a display, e.g., when running QEMU with `-nographic`,
+//! the `--headless` option creates an in-memory screen of the given size,
+//! on which applications like `shell` can then open windows.
+//!
+//! When running in QEMU with `net=user`, the server can be reached from the host
+//! by forwarding a host port to it, e.g., with `hostfwd=tcp::5900-:5900`.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::{string::String, vec::Vec};
+use app_io::println;
+use getopts::{Matches, Options};
+use vnc_server::VncServer;
+
+/// The number of clients that the server serves at once by default.
+const DEFAULT_MAX_CONNECTIONS: usize = 2;
+
+/// The size of the in-memory screen created by `--headless` by default.
+const DEFAULT_HEADLESS_SIZE: (usize, usize) = (1024, 768);
+
+pub fn main(args: Vec<String>) -> isize {
+    let mut opts = Options::new();
+    opts.optflag("h", "help", "print this help menu");
+    opts.optopt("p", "port", "listen on <port> (default: 5900)", "<port>");
+    opts.optopt(
+        "I",
+        "interface",
+        "listen on <interface> (default: the default interface)",
+        "<interface>",
+    );
+    opts.optopt(
+        "c",
+        "connections",
+        "serve up to <connections> clients at once (default: 2)",
+        "<connections>",
+    );
+    opts.optflagopt(
+        "",
+        "headless",
+        "if there's no display, create a screen of <width>x<height> pixels (default: 1024x768)",
+        "<width>x<height>",
+    );
+
+    let matches = match opts.parse(args) {
+        Ok(m) => m,
+        Err(_f) => {
+            println!("{}", _f);
+            print_usage(&opts);
+            return -1;
+        }
+    };
+
+    if matches.opt_present("h") {
+        print_usage(&opts);
+        return 0;
+    }
+
+    match run(matches) {
+        Ok(()) => 0,
+        Err(e) => {
+            println!("Error: {}", e);
+            -1
+        }
+    }
+}
+
+fn run(matches: Matches) -> Result<(), &'static str> {
+    let interface = match matches.opt_str("I") {
+        Some(name) => net::get_interface(&name).ok_or("no such network interface")?,
+        None => net::get_default_interface().ok_or("no network interfaces available")?,
+    };
+    let port = matches
+        .opt_get_default("p", vnc_server::DEFAULT_PORT)
+        .map_err(|_| "invalid port")?;
+    let max_connections = matches
+        .opt_get_default("c", DEFAULT_MAX_CONNECTIONS)
+        .map_err(|_| "invalid number of connections")?;
+
+    if matches.opt_present("headless") && window_manager::WINDOW_MANAGER.get().is_none() {
+        let (width, height) = match matches.opt_str("headless") {
+            Some(size) => parse_size(&size).ok_or("invalid screen size, expected <width>x<height>")?,
+            None => DEFAULT_HEADLESS_SIZE,
+        };
+        window_manager::init_headless(width, height)?;
+        println!("created a headless screen of {}x{} pixels", width, height);
+    }
+
+    let mut server = VncServer::new(interface.clone(), port, max_connections)?;
+    let addrs = interface.ip_addrs();
+    match addrs.first() {
+        Some(addr) => println!("serving VNC on {}:{}", addr.address(), port),
+        None => println!("serving VNC on port {} of {}", port, interface.name()),
+    }
+    server.run()
+}
+
+/// Parses a screen size given as `<width>x<height>`.
+fn parse_size(size: &str) -> Option<(usize, usize)> {
+    let (width, height) = size.split_once('x')?;
+    Some((width.parse().ok()?, height.parse().ok()?))
+}
+
+fn print_usage(opts: &Options) {
+    println!("{}", opts.usage(USAGE));
+}
+
+const USAGE: &str = "Usage: vncd [OPTION]...
+Serves the screen over VNC and injects the keyboard and mouse input of VNC clients.";
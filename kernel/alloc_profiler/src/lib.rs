@@ -0,0 +1,508 @@
+//! An optional profiler that attributes live heap and frame allocations to their allocation sites.
+//!
+//! When enabled, the heap and the frame allocator report each allocation and deallocation to this crate,
+//! which identifies the allocation site by the chain of return addresses on the current stack
+//! and tracks how much memory allocated from each site is still live.
+//! The sites with the most live memory are good candidates for memory leaks;
+//! their return addresses can be symbolicated via `mod_mgmt`, e.g., by the `allocprof` application.
+//!
+//! This crate never allocates memory while recording, because it is invoked from within the allocators.
+//! Thus, its tables have a fixed capacity, and allocations that don't fit are only counted as untracked.
+//!
+//! Return address chains can only be obtained when frame pointers are enabled (`-C force-frame-pointers=yes`);
+//! otherwise, all allocations of a given kind are attributed to a single unknown site.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, Ordering};
+use sync_irq::IrqSafeMutex;
+
+/// The maximum number of return addresses that identify an allocation site.
+pub const CALL_CHAIN_DEPTH: usize = 6;
+
+/// The chain of return addresses that identifies an allocation site, innermost first.
+/// Unused trailing entries are zero.
+pub type CallChain = [usize; CALL_CHAIN_DEPTH];
+
+/// The maximum number of distinct allocation sites that can be tracked.
+const MAX_SITES: usize = 1024;
+/// The maximum number of live heap allocations that can be tracked.
+/// This must be a power of two.
+const MAX_LIVE_HEAP_ALLOCATIONS: usize = 1 << 14;
+/// The size of each physical frame tracked by the profiler.
+const FRAME_SIZE: usize = 4096;
+
+/// The number of innermost stack frames that belong to the heap's allocation path
+/// and are thus skipped when capturing the call chain of a heap allocation.
+const HEAP_SKIPPED_FRAMES: usize = 2;
+/// The number of innermost stack frames that belong to the frame allocator's allocation path.
+const FRAMES_SKIPPED_FRAMES: usize = 3;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static PROFILER: IrqSafeMutex<Profiler> = IrqSafeMutex::new(Profiler::new());
+
+/// The kind of memory that was allocated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AllocKind {
+    /// Memory allocated from the heap.
+    Heap,
+    /// Physical frames allocated from the frame allocator.
+    Frames,
+}
+
+/// Statistics about the live allocations from one allocation site.
+#[derive(Clone, Debug)]
+pub struct SiteStats {
+    pub kind: AllocKind,
+    pub call_chain: CallChain,
+    /// The number of bytes allocated from this site that haven't been freed.
+    pub live_bytes: usize,
+    /// For heap sites, the number of allocations from this site that haven't been freed.
+    /// For frame sites, the number of frames.
+    pub live_allocations: usize,
+}
+
+impl SiteStats {
+    /// Returns the non-zero return addresses in this site's call chain, innermost first.
+    pub fn return_addresses(&self) -> &[usize] {
+        let len = self.call_chain.iter().position(|&addr| addr == 0).unwrap_or(CALL_CHAIN_DEPTH);
+        &self.call_chain[..len]
+    }
+}
+
+/// Starts profiling allocations, discarding any previously-collected statistics.
+///
+/// Only frames below `max_frame_address` are tracked, because the profiler needs
+/// a table entry for every frame; this should typically be the end of general-purpose physical memory.
+///
+/// Allocations made before profiling started are ignored when they are freed.
+pub fn enable(max_frame_address: usize) -> Result<(), &'static str> {
+    if ENABLED.load(Ordering::Acquire) {
+        return Err("the allocation profiler is already enabled");
+    }
+    // Allocate the frame table before acquiring the lock, since allocating it invokes the allocators.
+    let frame_table = alloc::vec![0u16; max_frame_address / FRAME_SIZE];
+    let old_frame_table = {
+        let mut profiler = PROFILER.lock();
+        profiler.reset();
+        core::mem::replace(&mut profiler.frame_table, frame_table)
+    };
+    drop(old_frame_table);
+    ENABLED.store(true, Ordering::Release);
+    Ok(())
+}
+
+/// Stops profiling allocations.
+///
+/// The statistics collected so far can still be obtained via [`live_sites()`].
+pub fn disable() {
+    ENABLED.store(false, Ordering::Release);
+}
+
+/// Returns whether the allocation profiler is currently enabled.
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Returns the statistics of every allocation site that still has live allocations,
+/// sorted in descending order of live bytes.
+pub fn live_sites() -> Vec<SiteStats> {
+    // Allocate the full capacity first, such that pushing doesn't allocate while the lock is held.
+    let mut sites = Vec::with_capacity(MAX_SITES);
+    PROFILER.lock().sites.for_each_live(|site| sites.push(site));
+    sites.sort_unstable_by(|a, b| b.live_bytes.cmp(&a.live_bytes));
+    sites
+}
+
+/// Returns the number of allocations that couldn't be tracked because the profiler's tables were full,
+/// or because their frames were beyond the profiler's `max_frame_address`.
+pub fn untracked_allocations() -> usize {
+    PROFILER.lock().untracked
+}
+
+/// Records that `size` bytes of heap memory were allocated at `ptr`.
+///
+/// This is invoked by the heap.
+#[inline(never)]
+pub fn record_heap_alloc(ptr: *mut u8, size: usize) {
+    if !is_enabled() || ptr.is_null() {
+        return;
+    }
+    let call_chain = capture_call_chain(HEAP_SKIPPED_FRAMES);
+    PROFILER.lock().heap_alloc(ptr as usize, size, &call_chain);
+}
+
+/// Records that the `size` bytes of heap memory at `ptr` were freed.
+///
+/// This is invoked by the heap.
+pub fn record_heap_dealloc(ptr: *mut u8, size: usize) {
+    if !is_enabled() {
+        return;
+    }
+    PROFILER.lock().heap_dealloc(ptr as usize, size);
+}
+
+/// Records that the frames spanning `size_in_bytes` starting at the physical address `start` were allocated.
+///
+/// This is invoked by the frame allocator.
+#[inline(never)]
+pub fn record_frames_alloc(start: usize, size_in_bytes: usize) {
+    if !is_enabled() {
+        return;
+    }
+    let call_chain = capture_call_chain(FRAMES_SKIPPED_FRAMES);
+    PROFILER.lock().frames_alloc(start, size_in_bytes, &call_chain);
+}
+
+/// Records that the frames spanning `size_in_bytes` starting at the physical address `start` were freed.
+///
+/// This is invoked by the frame allocator.
+pub fn record_frames_dealloc(start: usize, size_in_bytes: usize) {
+    if !is_enabled() {
+        return;
+    }
+    PROFILER.lock().frames_dealloc(start, size_in_bytes);
+}
+
+
+/// Returns the chain of return addresses on the current stack, skipping the innermost `skip` frames.
+///
+/// The frame pointer chain is only followed while it moves up the current stack by a plausible amount,
+/// such that a frame without a valid frame pointer ends the chain rather than causing a fault.
+#[inline(always)]
+fn capture_call_chain(skip: usize) -> CallChain {
+    #[cfg_attr(not(frame_pointers), allow(unused_mut))]
+    let mut call_chain = [0; CALL_CHAIN_DEPTH];
+
+    #[cfg(frame_pointers)] {
+        /// The maximum distance between the current frame and an outer frame on the same stack.
+        const MAX_STACK_SPAN: usize = 64 * FRAME_SIZE;
+
+        let mut frame_pointer: usize;
+        // SAFETY: just reading the current frame pointer register.
+        unsafe {
+            #[cfg(target_arch = "x86_64")]
+            core::arch::asm!("mov {}, rbp", out(reg) frame_pointer);
+            #[cfg(target_arch = "aarch64")]
+            core::arch::asm!("mov {}, x29", out(reg) frame_pointer);
+        }
+        let stack_start = frame_pointer;
+        let mut index = 0;
+        for depth in 0 .. (skip + CALL_CHAIN_DEPTH) {
+            if frame_pointer == 0
+                || frame_pointer % core::mem::size_of::<usize>() != 0
+                || frame_pointer - stack_start >= MAX_STACK_SPAN
+            {
+                break;
+            }
+            // SAFETY: the frame pointer lies on the current stack, and every frame
+            // stores the caller's frame pointer followed by the return address.
+            let (next_frame_pointer, return_address) = unsafe {
+                let frame = frame_pointer as *const usize;
+                (frame.read(), frame.add(1).read())
+            };
+            if return_address == 0 {
+                break;
+            }
+            if depth >= skip {
+                call_chain[index] = return_address;
+                index += 1;
+            }
+            if next_frame_pointer <= frame_pointer {
+                break;
+            }
+            frame_pointer = next_frame_pointer;
+        }
+    }
+    #[cfg(not(frame_pointers))]
+    let _ = skip;
+
+    call_chain
+}
+
+
+/// The state of the profiler, which is only accessed with its lock held.
+struct Profiler {
+    sites: SiteTable,
+    heap: LiveTable,
+    /// The index of the site that allocated each frame, plus one, or zero if the frame isn't tracked.
+    frame_table: Vec<u16>,
+    untracked: usize,
+}
+
+impl Profiler {
+    const fn new() -> Profiler {
+        Profiler {
+            sites: SiteTable::new(),
+            heap: LiveTable::new(),
+            frame_table: Vec::new(),
+            untracked: 0,
+        }
+    }
+
+    /// Clears the tables in place, since they're too large to be constructed on the stack.
+    fn reset(&mut self) {
+        self.sites.used.fill(false);
+        self.sites.len = 0;
+        self.heap.addrs.fill(0);
+        self.heap.len = 0;
+        self.untracked = 0;
+    }
+
+    fn heap_alloc(&mut self, ptr: usize, size: usize, call_chain: &CallChain) {
+        let Some(site) = self.sites.get_or_insert(AllocKind::Heap, call_chain) else {
+            self.untracked += 1;
+            return;
+        };
+        if !self.heap.insert(ptr, site) {
+            self.untracked += 1;
+            return;
+        }
+        let stats = &mut self.sites.sites[site as usize];
+        stats.live_bytes += size;
+        stats.live_allocations += 1;
+    }
+
+    fn heap_dealloc(&mut self, ptr: usize, size: usize) {
+        if let Some(site) = self.heap.remove(ptr) {
+            let stats = &mut self.sites.sites[site as usize];
+            stats.live_bytes = stats.live_bytes.saturating_sub(size);
+            stats.live_allocations = stats.live_allocations.saturating_sub(1);
+        }
+    }
+
+    fn frames_alloc(&mut self, start: usize, size_in_bytes: usize, call_chain: &CallChain) {
+        let Some(site) = self.sites.get_or_insert(AllocKind::Frames, call_chain) else {
+            self.untracked += 1;
+            return;
+        };
+        let first_frame = start / FRAME_SIZE;
+        let end_frame = (start + size_in_bytes).div_ceil(FRAME_SIZE);
+        if end_frame > self.frame_table.len() {
+            self.untracked += 1;
+        }
+        let mut num_frames = 0;
+        for entry in self.frame_table.iter_mut().take(end_frame).skip(first_frame) {
+            *entry = site + 1;
+            num_frames += 1;
+        }
+        let stats = &mut self.sites.sites[site as usize];
+        stats.live_bytes += num_frames * FRAME_SIZE;
+        stats.live_allocations += num_frames;
+    }
+
+    /// Frames are tracked individually, so frames that were split or merged after being allocated
+    /// are still attributed to the site that allocated them.
+    fn frames_dealloc(&mut self, start: usize, size_in_bytes: usize) {
+        let first_frame = start / FRAME_SIZE;
+        let end_frame = (start + size_in_bytes).div_ceil(FRAME_SIZE);
+        for entry in self.frame_table.iter_mut().take(end_frame).skip(first_frame) {
+            if *entry != 0 {
+                let stats = &mut self.sites.sites[*entry as usize - 1];
+                stats.live_bytes = stats.live_bytes.saturating_sub(FRAME_SIZE);
+                stats.live_allocations = stats.live_allocations.saturating_sub(1);
+                *entry = 0;
+            }
+        }
+    }
+}
+
+
+/// A fixed-capacity hash table of allocation sites, which are never removed until the profiler is reset.
+struct SiteTable {
+    sites: [SiteStats; MAX_SITES],
+    used: [bool; MAX_SITES],
+    len: usize,
+}
+
+impl SiteTable {
+    const EMPTY_SITE: SiteStats = SiteStats {
+        kind: AllocKind::Heap,
+        call_chain: [0; CALL_CHAIN_DEPTH],
+        live_bytes: 0,
+        live_allocations: 0,
+    };
+
+    const fn new() -> SiteTable {
+        SiteTable {
+            sites: [Self::EMPTY_SITE; MAX_SITES],
+            used: [false; MAX_SITES],
+            len: 0,
+        }
+    }
+
+    /// Returns the index of the site with the given kind and call chain,
+    /// adding it if it doesn't exist, or `None` if the table is full.
+    fn get_or_insert(&mut self, kind: AllocKind, call_chain: &CallChain) -> Option<u16> {
+        let mut hash = kind as usize;
+        for addr in call_chain {
+            hash = hash.rotate_left(5) ^ addr;
+            hash = hash.wrapping_mul(0x9e37_79b9_7f4a_7c15_u64 as usize);
+        }
+        let mut index = hash % MAX_SITES;
+        for _ in 0 .. MAX_SITES {
+            if !self.used[index] {
+                if self.len == MAX_SITES - 1 {
+                    // Leave one slot empty such that lookups of new sites always terminate quickly.
+                    return None;
+                }
+                self.used[index] = true;
+                self.len += 1;
+                self.sites[index] = SiteStats { kind, call_chain: *call_chain, ..Self::EMPTY_SITE };
+                return Some(index as u16);
+            }
+            let site = &self.sites[index];
+            if site.kind == kind && site.call_chain == *call_chain {
+                return Some(index as u16);
+            }
+            index = (index + 1) % MAX_SITES;
+        }
+        None
+    }
+
+    /// Invokes `f` on every site that still has live allocations.
+    fn for_each_live(&self, mut f: impl FnMut(SiteStats)) {
+        for (site, _) in self.sites.iter().zip(self.used).filter(|(site, used)| *used && site.live_bytes > 0) {
+            f(site.clone());
+        }
+    }
+}
+
+
+/// A fixed-capacity hash table from the address of each live heap allocation to the index of its site.
+///
+/// This uses linear probing, with backward-shift deletion such that no tombstones are needed.
+struct LiveTable {
+    /// The address of each allocation, or zero for an empty slot.
+    addrs: [usize; MAX_LIVE_HEAP_ALLOCATIONS],
+    sites: [u16; MAX_LIVE_HEAP_ALLOCATIONS],
+    len: usize,
+}
+
+impl LiveTable {
+    const fn new() -> LiveTable {
+        LiveTable {
+            addrs: [0; MAX_LIVE_HEAP_ALLOCATIONS],
+            sites: [0; MAX_LIVE_HEAP_ALLOCATIONS],
+            len: 0,
+        }
+    }
+
+    fn home_slot(addr: usize) -> usize {
+        // Heap allocations are at least 8-byte aligned, so the low bits carry no information.
+        (addr >> 3).wrapping_mul(0x9e37_79b9_7f4a_7c15_u64 as usize) >> 16 & (MAX_LIVE_HEAP_ALLOCATIONS - 1)
+    }
+
+    /// Inserts the given allocation, returning `false` if the table is full.
+    fn insert(&mut self, addr: usize, site: u16) -> bool {
+        let mut index = Self::home_slot(addr);
+        loop {
+            if self.addrs[index] == addr {
+                self.sites[index] = site;
+                return true;
+            }
+            if self.addrs[index] == 0 {
+                // Leave one slot empty such that probing always terminates.
+                if self.len == MAX_LIVE_HEAP_ALLOCATIONS - 1 {
+                    return false;
+                }
+                self.addrs[index] = addr;
+                self.sites[index] = site;
+                self.len += 1;
+                return true;
+            }
+            index = (index + 1) & (MAX_LIVE_HEAP_ALLOCATIONS - 1);
+        }
+    }
+
+    /// Removes the given allocation, returning the index of its site if it was present.
+    fn remove(&mut self, addr: usize) -> Option<u16> {
+        let mask = MAX_LIVE_HEAP_ALLOCATIONS - 1;
+        let mut index = Self::home_slot(addr);
+        while self.addrs[index] != addr {
+            if self.addrs[index] == 0 {
+                return None;
+            }
+            index = (index + 1) & mask;
+        }
+        let site = self.sites[index];
+        self.len -= 1;
+
+        // Shift back any later entries in this probe sequence that could have been placed in the emptied slot.
+        let mut hole = index;
+        let mut next = (hole + 1) & mask;
+        while self.addrs[next] != 0 {
+            let home = Self::home_slot(self.addrs[next]);
+            // The entry at `next` can fill the hole only if its home slot isn't cyclically within (hole, next].
+            if (next.wrapping_sub(home) & mask) >= (next.wrapping_sub(hole) & mask) {
+                self.addrs[hole] = self.addrs[next];
+                self.sites[hole] = self.sites[next];
+                hole = next;
+            }
+            next = (next + 1) & mask;
+        }
+        self.addrs[hole] = 0;
+        Some(site)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use super::*;
+    use std::boxed::Box;
+
+    #[test]
+    fn live_table_insert_remove() {
+        let mut table = Box::new(LiveTable::new());
+        // Many addresses that collide in their home slot exercise the backward-shift deletion.
+        let addrs: Vec<usize> = (1..2000).map(|i| i * 8 * MAX_LIVE_HEAP_ALLOCATIONS).chain((1..2000).map(|i| i * 16)).collect();
+        for (i, &addr) in addrs.iter().enumerate() {
+            assert!(table.insert(addr, i as u16));
+        }
+        for (i, &addr) in addrs.iter().enumerate().step_by(2) {
+            assert_eq!(table.remove(addr), Some(i as u16));
+        }
+        for (i, &addr) in addrs.iter().enumerate() {
+            let expected = if i % 2 == 0 { None } else { Some(i as u16) };
+            assert_eq!(table.remove(addr), expected);
+        }
+        assert_eq!(table.len, 0);
+    }
+
+    #[test]
+    fn heap_and_frame_sites() {
+        let mut profiler = Box::new(Profiler::new());
+        profiler.frame_table = alloc::vec![0; 16];
+        let site_a = [0x1000, 0x2000, 0, 0, 0, 0];
+        let site_b = [0x3000, 0, 0, 0, 0, 0];
+
+        profiler.heap_alloc(0x8000, 100, &site_a);
+        profiler.heap_alloc(0x8100, 50, &site_a);
+        profiler.heap_alloc(0x8200, 10, &site_b);
+        profiler.heap_dealloc(0x8000, 100);
+        profiler.heap_dealloc(0x9999, 1); // not tracked
+
+        // Allocate 4 frames, then free them in pieces.
+        profiler.frames_alloc(2 * FRAME_SIZE, 4 * FRAME_SIZE, &site_a);
+        profiler.frames_dealloc(3 * FRAME_SIZE, 2 * FRAME_SIZE);
+        // Frames beyond the table are untracked.
+        profiler.frames_alloc(15 * FRAME_SIZE, 2 * FRAME_SIZE, &site_b);
+
+        let mut sites = Vec::new();
+        profiler.sites.for_each_live(|s| sites.push(s));
+        sites.sort_by_key(|s| s.live_bytes);
+        let summary: Vec<_> = sites.iter().map(|s| (s.kind, s.return_addresses().len(), s.live_bytes, s.live_allocations)).collect();
+        assert_eq!(summary, [
+            (AllocKind::Heap, 1, 10, 1),
+            (AllocKind::Heap, 2, 50, 1),
+            (AllocKind::Frames, 1, FRAME_SIZE, 1),
+            (AllocKind::Frames, 2, 2 * FRAME_SIZE, 2),
+        ]);
+        assert_eq!(profiler.untracked, 1);
+    }
+}
@@ -109,6 +109,9 @@ pub fn kstart_ap(
         cpu::register_cpu(false).unwrap();
     }
 
+    // Run feature-dependent setup hooks, which must be done individually on each CPU.
+    cpu_features::run_hooks();
+
     // Now that the Local APIC has been initialized for this CPU, we can initialize the
     // per-CPU storage, tasking, and create the idle task for this CPU.
     cls_allocator::reload_current_cpu();
@@ -6,7 +6,6 @@ use derive_more::*;
 use volatile::{Volatile, ReadOnly, WriteOnly};
 use zerocopy::FromBytes;
 use spin::Once;
-use raw_cpuid::CpuId as X86CpuIdInstr;
 use msr::*;
 use sync_irq::IrqSafeRwLock;
 use memory::{PageTable, PhysicalAddress, PteFlags, MappedPages, allocate_pages, allocate_frames_at, AllocatedFrames, BorrowedMappedPages, Mutable};
@@ -34,6 +33,12 @@ const APIC_TIMER_MODE_PERIODIC:        u32 = 0b01 << 17;
 const _APIC_TIMER_MODE_TSC_DEADLINE:   u32 = 0b10 << 17;
 /// The IRQ number reserved for Local APIC timer interrupts in the IDT.
 pub const LOCAL_APIC_LVT_IRQ:          u8  = 0x22;
+/// The shortest period (in microseconds) that the Local APIC timer may be set to.
+pub const MIN_TIMER_PERIOD_MICROSECONDS: u32 = 100;
+/// The longest period (in microseconds) that the Local APIC timer may be set to.
+pub const MAX_TIMER_PERIOD_MICROSECONDS: u32 = 1_000_000;
+/// The fixed-point scaling factor used for a `LocalApic`'s `scaled_ticks_per_microsecond`.
+const TICKS_PER_US_SCALE: u64 = 1 << 16;
 
 
 /// A unique identifier for a Local APIC, i.e., a CPU on x86_64.
@@ -100,14 +105,7 @@ pub fn is_bootstrap_cpu() -> bool {
 
 /// Returns true if the machine has support for x2apic
 pub fn has_x2apic() -> bool {
-    static IS_X2APIC: Once<bool> = Once::new(); // cache the result
-    let res: &bool = IS_X2APIC.call_once(||
-        X86CpuIdInstr::new()
-            .get_feature_info()
-            .expect("Couldn't get CpuId feature info")
-            .has_x2apic()
-    );
-    *res // because call_once returns a reference to the cached IS_X2APIC value
+    cpu_features::has_feature(cpu_features::Feature::X2Apic)
 }
 
 /// Returns a reference to the list of LocalApics, one per CPU core.
@@ -164,7 +162,7 @@ impl LapicDeliveryMode {
 }
 
 /// The possible values for the Local APIC Timer Divide Configuration Register.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 #[repr(u32)]
 pub enum LapicTimerDivide {
     By1   = 1,
@@ -190,6 +188,12 @@ impl LapicTimerDivide {
             Self::By128 => 0b1010,
         }
     }
+
+    /// All possible divide values, from smallest to largest.
+    const ALL: [LapicTimerDivide; 8] = [
+        Self::By1, Self::By2, Self::By4, Self::By8,
+        Self::By16, Self::By32, Self::By64, Self::By128,
+    ];
 }
 
 /// The possible destination shorthand values for IPI ICR.
@@ -426,7 +430,16 @@ pub struct LocalApic {
     /// The value that should be written to the APIC timer's initial count register
     /// when enabling the LVT timer.
     initial_timer_count: u32,
+    /// The value currently written to the APIC timer's divide configuration register.
+    timer_divide: LapicTimerDivide,
+    /// The number of APIC timer ticks that elapse per microsecond
+    /// when the timer divide value is [`LapicTimerDivide::By1`],
+    /// scaled up by `TICKS_PER_US_SCALE` to preserve precision.
+    scaled_ticks_per_microsecond: u64,
+    /// The period of the LVT timer, in microseconds.
+    timer_period_microseconds: u32,
 }
+
 impl fmt::Debug for LocalApic {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.debug_struct("LocalApic")
@@ -524,6 +537,9 @@ impl LocalApic {
             apic_id: ApicId(u32::MAX), // placeholder, is replaced below.
             is_bootstrap_cpu,
             initial_timer_count: 0, // set in `calibrate_lapic_timer()`
+            timer_divide: LapicTimerDivide::By16,
+            scaled_ticks_per_microsecond: 0, // set in `init_lvt_timer()`
+            timer_period_microseconds: CONFIG_TIMESLICE_PERIOD_MICROSECONDS,
         };
 
         // Now that the APIC hardware is enabled, we can safely obtain this Local APIC's ID.
@@ -667,6 +683,10 @@ impl LocalApic {
         };
         trace!("LocalApic {}, timer period count: {} ({:#X})", self.apic_id, apic_period, apic_period);
         self.initial_timer_count = apic_period;
+        self.timer_divide = LapicTimerDivide::By16;
+        self.timer_period_microseconds = CONFIG_TIMESLICE_PERIOD_MICROSECONDS;
+        self.scaled_ticks_per_microsecond = (apic_period as u64 * LapicTimerDivide::By16 as u64 * TICKS_PER_US_SCALE)
+            / CONFIG_TIMESLICE_PERIOD_MICROSECONDS as u64;
 
         match &mut self.inner {
             LapicType::X2Apic => unsafe {
@@ -697,6 +717,65 @@ impl LocalApic {
         }
     }
 
+    /// Returns the current period of this lapic's LVT timer, in microseconds.
+    pub fn timer_period_microseconds(&self) -> u32 {
+        self.timer_period_microseconds
+    }
+
+    /// Sets the period of this lapic's LVT timer to the given number of `microseconds`,
+    /// reprogramming the timer's divide configuration and initial count registers.
+    ///
+    /// The smallest divide value that allows the required number of ticks
+    /// to fit into the 32-bit initial count register is chosen,
+    /// which offers the finest timer granularity.
+    ///
+    /// ## Important Usage Note
+    /// This MUST be invoked on the CPU that this lapic belongs to,
+    /// as each CPU can only access its own Local APIC registers.
+    ///
+    /// ## Return
+    /// * `Ok(old_period)`, the previous timer period in microseconds, upon success.
+    /// * `Err` if `microseconds` is outside of the range from
+    ///   [`MIN_TIMER_PERIOD_MICROSECONDS`] to [`MAX_TIMER_PERIOD_MICROSECONDS`],
+    ///   or if this function was invoked on a different CPU.
+    pub fn set_timer_period(&mut self, microseconds: u32) -> Result<u32, &'static str> {
+        if !(MIN_TIMER_PERIOD_MICROSECONDS ..= MAX_TIMER_PERIOD_MICROSECONDS).contains(&microseconds) {
+            return Err("the given LAPIC timer period was out of bounds");
+        }
+        if current_cpu() != self.apic_id {
+            return Err("a LAPIC timer can only be reprogrammed from its own CPU");
+        }
+
+        let ticks_at_div1 = (self.scaled_ticks_per_microsecond * microseconds as u64) / TICKS_PER_US_SCALE;
+        let (divide, count) = LapicTimerDivide::ALL.iter()
+            .map(|div| (*div, ticks_at_div1 / (*div as u64)))
+            .find(|(_div, count)| *count <= u32::MAX as u64)
+            .ok_or("the given LAPIC timer period requires too many ticks")?;
+        // A count of zero would never fire the timer, so use at least one tick.
+        let count = core::cmp::max(count, 1) as u32;
+
+        let old_period = self.timer_period_microseconds;
+        self.timer_divide = divide;
+        self.initial_timer_count = count;
+        self.timer_period_microseconds = microseconds;
+
+        // Writing the initial count register restarts the timer with the new period.
+        match &mut self.inner {
+            LapicType::X2Apic => unsafe {
+                wrmsr(IA32_X2APIC_DIV_CONF, divide.as_register_value() as u64);
+                wrmsr(IA32_X2APIC_INIT_COUNT, count as u64);
+            }
+            LapicType::XApic(regs) => {
+                regs.timer_divide.write(divide.as_register_value());
+                regs.timer_initial_count.write(count);
+            }
+        }
+        debug!("LocalApic {}: set timer period to {} us (count: {}, divide: {:?})",
+            self.apic_id, microseconds, count, divide
+        );
+        Ok(old_period)
+    }
+
     /// Enable (unmask) or disable (mask) the LVT timer interrupt on this lapic.
     pub fn enable_lvt_timer(&mut self, enable: bool) {
         // From section 10.5.4 of Intel SDM:
@@ -108,11 +108,11 @@ enum AtaCommand {
 	WriteDma        = 0xCA,
 	/// Write sectors using DMA (48-bit LBA)
 	WriteDmaExt     = 0x35,
-	/// Flush the drive's bus cache (28-bit LBA).
-	/// This is to be used after each write.
+	/// Flush the drive's write cache (28-bit LBA).
+	/// This must be used to ensure that prior writes are durable.
 	CacheFlush      = 0xE7,
-	/// Flush the drive's bus cache (48-bit LBA).
-	/// This is to be used after each write.
+	/// Flush the drive's write cache (48-bit LBA).
+	/// This must be used to ensure that prior writes are durable.
 	CacheFlushExt   = 0xEA,
 	/// Sends a packet, for ATAPI devices using the packet interface (PI).
 	Packet          = 0xA0,
@@ -395,13 +395,25 @@ impl AtaBus {
 			buffer_offset += SECTOR_SIZE_IN_BYTES;
 		}
 		self.wait_for_data_done().map_err(|_| "error after data write")?;
+		Ok(sector_count)
+	}
 
-		// Flush the drive's cache after each write command
-		let cache_flush_cmd = if using_lba_28 { AtaCommand::CacheFlush } else { AtaCommand::CacheFlushExt };
-		unsafe { self.command.write(cache_flush_cmd as u8) };
+	/// Issues a cache flush command on the ATA Bus, which blocks until the given drive
+	/// has written all data in its volatile write cache to the medium.
+	fn flush_cache(&mut self, which: BusDriveSelect, using_lba_28: bool) -> Result<(), &'static str> {
+		self.wait_for_data_done().map_err(|_| "error before issuing cache flush command")?;
 
-		self.wait_for_data_done().map_err(|_| "error after cache flush after data write")?;
-		Ok(sector_count)
+		let (drive_select, cache_flush_cmd) = if using_lba_28 {
+			(0xE0, AtaCommand::CacheFlush)
+		} else {
+			(0x40, AtaCommand::CacheFlushExt)
+		};
+		unsafe {
+			self.drive_select.write(drive_select | (which as u8));
+			self.command.write(cache_flush_cmd as u8);
+		}
+
+		self.wait_for_data_done().map_err(|_| "error after cache flush")
 	}
 
 	/// Issues an ATA identify command to probe the drive
@@ -641,6 +653,9 @@ impl AtaDrive {
 	/// 
 	/// Returns the number of sectors (*not bytes*) that were successfully written to the drive.
 	/// 
+	/// The written data may remain in the drive's volatile write cache
+	/// until it is flushed via [`AtaDrive::flush_cache()`].
+	/// 
 	/// # Note
 	/// This is slow, as it uses blocking port I/O instead of DMA. 
 	pub fn write_pio(&mut self, buffer: &[u8], offset_in_sectors: usize) -> Result<usize, &'static str> {
@@ -668,6 +683,14 @@ impl AtaDrive {
 		self.bus.lock().write_pio(buffer, self.master_slave, lba_start, sector_count)
 	}
 
+	/// Blocks until all data previously written to this drive is durable,
+	/// i.e., has been written from the drive's volatile write cache to the medium.
+	pub fn flush_cache(&mut self) -> Result<(), &'static str> {
+		// The 48-bit variant of the flush command is only supported by drives that support 48-bit LBAs.
+		let using_lba_28 = self.identify_data.max_48_bit_lba == 0;
+		self.bus.lock().flush_cache(self.master_slave, using_lba_28)
+	}
+
 
 	/// Returns `true` if this drive is the master, or `false` if it is the slave 
 	/// on the IDE controller bus.
@@ -706,7 +729,9 @@ impl BlockWriter for AtaDrive {
 		self.write_pio(buffer, block_offset).map_err(|_e| IoError::InvalidInput)
 	}
 
-	fn flush(&mut self) -> Result<(), IoError> { Ok(()) }
+	fn flush(&mut self) -> Result<(), IoError> {
+		self.flush_cache().map_err(IoError::from)
+	}
 }
 
 pub type AtaDriveRef = Arc<Mutex<AtaDrive>>;
@@ -0,0 +1,208 @@
+//! A harness for microbenchmarks of kernel operations.
+//!
+//! A [`Bench`] runs an operation for a number of warmup iterations, whose timings are discarded,
+//! and then for a number of measured iterations, each of which is timed individually
+//! with the CPU's cycle counter: the TSC on x86_64, or the virtual counter on aarch64.
+//! The overhead of reading the counter is measured beforehand and subtracted from each sample.
+//!
+//! By default, each measured iteration runs with preemption held, so it can't be interrupted by a task switch.
+//! Other interrupts can still occur, which is why the results focus on the median and percentiles
+//! rather than the mean.
+//!
+//! To measure on a specific CPU, run the benchmarks via [`run_on_cpu()`], which spawns a task pinned to it.
+//! The [`Results`] can be printed for humans via their `Display` implementation,
+//! or as a single-line JSON object via [`Results::to_json()`].
+
+#![no_std]
+
+extern crate alloc;
+
+mod stats;
+
+pub use stats::Summary;
+
+use alloc::{format, string::{String, ToString}, vec::Vec};
+use core::fmt;
+use cpu::CpuId;
+use spin::Once;
+use task::ExitValue;
+use time::{Duration, Instant};
+
+/// The number of counter reads used to measure their overhead.
+const OVERHEAD_SAMPLES: usize = 1000;
+/// How long to measure the cycle counter's frequency for.
+const CALIBRATION_DURATION: Duration = Duration::from_millis(10);
+
+/// The number of cycles per microsecond, measured once.
+static CYCLES_PER_MICROSECOND: Once<u64> = Once::new();
+
+/// A microbenchmark of a single operation.
+#[derive(Clone, Copy, Debug)]
+pub struct Bench {
+    name: &'static str,
+    warmup: usize,
+    iterations: usize,
+    preemptible: bool,
+}
+
+impl Bench {
+    /// Creates a benchmark with the given name,
+    /// which runs 100 warmup iterations and 1000 measured iterations by default.
+    pub const fn new(name: &'static str) -> Bench {
+        Bench {
+            name,
+            warmup: 100,
+            iterations: 1000,
+            preemptible: false,
+        }
+    }
+
+    /// Sets the number of warmup iterations, which aren't measured.
+    pub const fn warmup(mut self, warmup: usize) -> Bench {
+        self.warmup = warmup;
+        self
+    }
+
+    /// Sets the number of measured iterations.
+    pub const fn iterations(mut self, iterations: usize) -> Bench {
+        self.iterations = iterations;
+        self
+    }
+
+    /// Allows the measured iterations to be preempted,
+    /// which is required for operations that switch tasks themselves.
+    pub const fn preemptible(mut self) -> Bench {
+        self.preemptible = true;
+        self
+    }
+
+    /// Runs the given operation for the configured numbers of iterations, timing each measured iteration.
+    pub fn run<F: FnMut()>(&self, mut operation: F) -> Results {
+        for _ in 0..self.warmup {
+            operation();
+        }
+        let overhead = counter_overhead();
+        let mut samples = Vec::with_capacity(self.iterations);
+        for _ in 0..self.iterations {
+            let held_preemption = (!self.preemptible).then(preemption::hold_preemption);
+            let start = cycles();
+            operation();
+            let end = cycles();
+            drop(held_preemption);
+            samples.push(end.wrapping_sub(start).saturating_sub(overhead));
+        }
+        Results {
+            name: self.name,
+            summary: Summary::new(&mut samples),
+            overhead,
+            cycles_per_microsecond: cycles_per_microsecond(),
+        }
+    }
+}
+
+/// The results of running a [`Bench`], in cycles.
+#[derive(Clone, Debug)]
+pub struct Results {
+    pub name: &'static str,
+    pub summary: Summary,
+    /// The overhead of reading the cycle counter, which was subtracted from each sample.
+    pub overhead: u64,
+    /// The measured frequency of the cycle counter.
+    pub cycles_per_microsecond: u64,
+}
+
+impl Results {
+    /// Converts the given number of cycles into nanoseconds.
+    pub fn nanoseconds(&self, cycles: u64) -> u64 {
+        (cycles as u128 * 1000 / self.cycles_per_microsecond.max(1) as u128) as u64
+    }
+
+    /// Returns these results as a single-line JSON object, with all statistics in cycles.
+    pub fn to_json(&self) -> String {
+        let s = &self.summary;
+        format!(
+            "{{\"name\":\"{}\",\"unit\":\"cycles\",\"iterations\":{},\"min\":{},\"median\":{},\"mean\":{},\
+            \"p90\":{},\"p99\":{},\"max\":{},\"overhead\":{},\"cycles_per_us\":{}}}",
+            self.name, s.count, s.min, s.median, s.mean, s.p90, s.p99, s.max, self.overhead, self.cycles_per_microsecond,
+        )
+    }
+}
+
+impl fmt::Display for Results {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = &self.summary;
+        write!(
+            f,
+            "{:<24} {:>10} {:>10} {:>10} {:>10} {:>10} {:>10}",
+            self.name, s.min, s.median, s.p90, s.p99, s.max, self.nanoseconds(s.median),
+        )
+    }
+}
+
+/// Returns the header of the table whose rows are the `Display` output of [`Results`].
+pub fn table_header() -> String {
+    format!(
+        "{:<24} {:>10} {:>10} {:>10} {:>10} {:>10} {:>10}",
+        "Benchmark", "Min", "Median", "P90", "P99", "Max", "Median ns",
+    )
+}
+
+/// Runs the given function in a new task pinned to the given CPU, waiting for it to complete
+/// and returning its result.
+pub fn run_on_cpu<F, R>(cpu: CpuId, func: F) -> Result<R, &'static str>
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Send + 'static,
+{
+    let task = spawn::new_task_builder(|func: F| func(), func)
+        .name("bench_harness".to_string())
+        .pin_on_cpu(cpu)
+        .spawn()?;
+    match task.join()? {
+        ExitValue::Completed(value) => value.downcast::<R>()
+            .map(|value| *value)
+            .map_err(|_| "bench_harness: the benchmark task returned an unexpected value"),
+        ExitValue::Killed(_) => Err("bench_harness: the benchmark task was killed"),
+    }
+}
+
+/// Returns the number of cycles that the cycle counter advances per microsecond,
+/// measuring it against the system clock on the first invocation.
+pub fn cycles_per_microsecond() -> u64 {
+    *CYCLES_PER_MICROSECOND.call_once(|| {
+        let start_time = Instant::now();
+        let start = cycles();
+        let mut elapsed = Duration::ZERO;
+        while elapsed < CALIBRATION_DURATION {
+            elapsed = start_time.elapsed();
+        }
+        let cycles = cycles().wrapping_sub(start);
+        (cycles as u128 / elapsed.as_micros().max(1)) as u64
+    })
+}
+
+/// Returns the minimum number of cycles that reading the cycle counter twice takes.
+fn counter_overhead() -> u64 {
+    let _held_preemption = preemption::hold_preemption();
+    (0..OVERHEAD_SAMPLES)
+        .map(|_| {
+            let start = cycles();
+            cycles().wrapping_sub(start)
+        })
+        .min()
+        .unwrap_or(0)
+}
+
+/// Returns the current value of the cycle counter.
+#[inline(always)]
+fn cycles() -> u64 {
+    #[cfg(target_arch = "x86_64")] {
+        tsc::tsc_value()
+    }
+    #[cfg(target_arch = "aarch64")] {
+        let count: u64;
+        // SAFETY: reading the virtual counter has no side effects.
+        unsafe { core::arch::asm!("isb", "mrs {}, cntvct_el0", out(reg) count, options(nomem, nostack)) };
+        count
+    }
+}
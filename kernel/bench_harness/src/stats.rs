@@ -0,0 +1,73 @@
+//! Summary statistics of benchmark samples.
+
+/// Summary statistics of a set of samples.
+///
+/// Percentiles use the nearest-rank method, so each is one of the samples.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Summary {
+    pub count: usize,
+    pub min: u64,
+    pub max: u64,
+    pub mean: u64,
+    pub median: u64,
+    pub p90: u64,
+    pub p99: u64,
+}
+
+impl Summary {
+    /// Summarizes the given samples, sorting them in the process.
+    ///
+    /// Returns an all-zero summary if there are no samples.
+    pub fn new(samples: &mut [u64]) -> Summary {
+        if samples.is_empty() {
+            return Summary::default();
+        }
+        samples.sort_unstable();
+        let total: u128 = samples.iter().map(|&s| s as u128).sum();
+        Summary {
+            count: samples.len(),
+            min: samples[0],
+            max: samples[samples.len() - 1],
+            mean: (total / samples.len() as u128) as u64,
+            median: percentile(samples, 50),
+            p90: percentile(samples, 90),
+            p99: percentile(samples, 99),
+        }
+    }
+}
+
+/// Returns the given percentile of the given sorted, non-empty samples.
+fn percentile(sorted: &[u64], percent: usize) -> u64 {
+    let rank = (percent * sorted.len()).div_ceil(100);
+    sorted[rank.saturating_sub(1)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summarizes_unsorted_samples() {
+        let mut samples: [u64; 10] = [7, 3, 10, 1, 9, 2, 8, 4, 6, 5];
+        let summary = Summary::new(&mut samples);
+        assert_eq!(summary, Summary { count: 10, min: 1, max: 10, mean: 5, median: 5, p90: 9, p99: 10 });
+    }
+
+    #[test]
+    fn single_sample() {
+        let summary = Summary::new(&mut [42]);
+        assert_eq!((summary.min, summary.median, summary.p99, summary.max), (42, 42, 42, 42));
+    }
+
+    #[test]
+    fn no_samples() {
+        assert_eq!(Summary::new(&mut []), Summary::default());
+    }
+
+    #[test]
+    fn percentiles_of_many_samples() {
+        let mut samples: [u64; 1000] = core::array::from_fn(|i| 1000 - i as u64);
+        let summary = Summary::new(&mut samples);
+        assert_eq!((summary.median, summary.p90, summary.p99), (500, 900, 990));
+    }
+}
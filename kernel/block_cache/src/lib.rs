@@ -59,6 +59,9 @@ impl BlockCache {
     /// Flushes the given block to the backing storage device. 
     /// If the `block_to_flush` is None, all blocks in the entire cache
     /// will be written back to the storage device.
+    ///
+    /// The storage device itself is then flushed as well,
+    /// such that all written blocks are durable once this returns.
     pub fn flush(&mut self, block_num: Option<usize>) -> Result<(), &'static str> {
         let mut locked_device = self.storage_device.lock();
         if let Some(bn) = block_num {
@@ -74,6 +77,7 @@ impl BlockCache {
                 Self::flush_block(&mut *locked_device, *bn, cached_block)?;
             }
         }
+        locked_device.flush()?;
         Ok(())
     }
 
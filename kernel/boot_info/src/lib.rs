@@ -183,6 +183,91 @@ pub enum FramebufferFormat {
     },
 }
 
+/// The maximum length in bytes of the kernel command line kept in a [`CommandLine`].
+pub const MAX_COMMAND_LINE_LEN: usize = 256;
+
+/// A copy of the kernel command line given by the bootloader,
+/// which remains available after the boot information itself is gone.
+///
+/// The command line consists of whitespace-separated options,
+/// each of which is either a flag (e.g., `quiet`) or a `key=value` pair (e.g., `fpu=eager`).
+#[derive(Clone, Copy)]
+pub struct CommandLine {
+    bytes: [u8; MAX_COMMAND_LINE_LEN],
+    len: usize,
+}
+
+impl CommandLine {
+    /// Copies the given command line, truncating it to [`MAX_COMMAND_LINE_LEN`] bytes.
+    pub fn new(command_line: &str) -> CommandLine {
+        let mut len = command_line.len().min(MAX_COMMAND_LINE_LEN);
+        while !command_line.is_char_boundary(len) {
+            len -= 1;
+        }
+        let mut bytes = [0; MAX_COMMAND_LINE_LEN];
+        bytes[..len].copy_from_slice(&command_line.as_bytes()[..len]);
+        CommandLine { bytes, len }
+    }
+
+    /// Returns the whole command line.
+    pub fn as_str(&self) -> &str {
+        // SAFETY: the bytes were copied from a `str` and truncated at a char boundary.
+        unsafe { core::str::from_utf8_unchecked(&self.bytes[..self.len]) }
+    }
+
+    /// Returns the value of the last `key=value` option with the given `key`, if any.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.as_str()
+            .split_whitespace()
+            .filter_map(|option| option.split_once('='))
+            .filter(|(k, _)| *k == key)
+            .map(|(_, value)| value)
+            .last()
+    }
+
+    /// Returns whether the given flag is present.
+    pub fn has_flag(&self, flag: &str) -> bool {
+        self.as_str().split_whitespace().any(|option| option == flag)
+    }
+}
+
+impl Default for CommandLine {
+    fn default() -> CommandLine {
+        CommandLine::new("")
+    }
+}
+
+impl core::fmt::Debug for CommandLine {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        core::fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+/// The maximum number of EFI runtime services memory regions that can be described
+/// by an [`EfiRuntimeInfo`].
+pub const MAX_EFI_RUNTIME_REGIONS: usize = 32;
+
+/// A region of physical memory used by the EFI runtime services,
+/// which must remain mapped in order to call them.
+#[derive(Clone, Copy, Debug)]
+pub struct EfiRuntimeRegion {
+    pub start: PhysicalAddress,
+    pub len: usize,
+    /// Whether the region contains the runtime services' code, as opposed to their data.
+    pub executable: bool,
+}
+
+/// Information needed to call the EFI runtime services after boot services have exited.
+#[derive(Clone, Copy, Debug)]
+pub struct EfiRuntimeInfo {
+    /// The physical address of the EFI system table.
+    pub system_table: PhysicalAddress,
+    /// The memory regions used by the runtime services.
+    ///
+    /// Only the first [`MAX_EFI_RUNTIME_REGIONS`] regions are included.
+    pub regions: [Option<EfiRuntimeRegion>; MAX_EFI_RUNTIME_REGIONS],
+}
+
 pub trait BootInformation: 'static {
     type MemoryRegion<'a>: MemoryRegion;
     type MemoryRegions<'a>: Iterator<Item = Self::MemoryRegion<'a>>;
@@ -224,9 +309,16 @@ pub trait BootInformation: 'static {
     /// Returns the RSDP if it was provided by the bootloader.
     fn rsdp(&self) -> Option<PhysicalAddress>;
 
+    /// Returns the kernel command line, if it was provided by the bootloader.
+    fn command_line(&self) -> Option<&str>;
+
     /// Returns the stack size in bytes.
     fn stack_size(&self) -> Result<usize, &'static str>;
 
     /// Returns information about the graphical framebuffer, if available.
     fn framebuffer_info(&self) -> Option<FramebufferInfo>;
+
+    /// Returns information about the EFI runtime services,
+    /// if the machine was booted via UEFI and the bootloader provided it.
+    fn efi_runtime(&self) -> Option<EfiRuntimeInfo>;
 }
@@ -214,6 +214,10 @@ impl crate::BootInformation for multiboot2::BootInformation {
             .and_then(PhysicalAddress::new)
     }
 
+    fn command_line(&self) -> Option<&str> {
+        self.command_line_tag()?.command_line().ok()
+    }
+
     fn stack_size(&self) -> Result<usize, &'static str> {
         use crate::ElfSection;
 
@@ -261,4 +265,29 @@ impl crate::BootInformation for multiboot2::BootInformation {
             format,
         })
     }
+
+    fn efi_runtime(&self) -> Option<crate::EfiRuntimeInfo> {
+        use multiboot2::EFIMemoryAreaType;
+
+        let system_table = PhysicalAddress::new(self.efi_sdt_64_tag()?.sdt_address())?;
+        let mut regions = [None; crate::MAX_EFI_RUNTIME_REGIONS];
+        let runtime_areas = self.efi_memory_map_tag()?
+            .memory_areas()
+            .filter_map(|area| {
+                let executable = match area.typ() {
+                    EFIMemoryAreaType::EfiRuntimeServicesCode => true,
+                    EFIMemoryAreaType::EfiRuntimeServicesData => false,
+                    _ => return None,
+                };
+                Some(crate::EfiRuntimeRegion {
+                    start: PhysicalAddress::new(area.physical_address() as usize)?,
+                    len: area.size() as usize,
+                    executable,
+                })
+            });
+        for (slot, region) in regions.iter_mut().zip(runtime_areas) {
+            *slot = Some(region);
+        }
+        Some(crate::EfiRuntimeInfo { system_table, regions })
+    }
 }
@@ -180,6 +180,11 @@ impl crate::BootInformation for &'static uefi_bootloader_api::BootInformation {
             .map(PhysicalAddress::new_canonical)
     }
 
+    fn command_line(&self) -> Option<&str> {
+        // TODO: `uefi-bootloader-api` doesn't yet pass along a kernel command line.
+        None
+    }
+
     fn stack_size(&self) -> Result<usize, &'static str> {
         Ok(STACK_SIZE)
     }
@@ -220,4 +225,10 @@ impl crate::BootInformation for &'static uefi_bootloader_api::BootInformation {
             format,
         })
     }
+
+    fn efi_runtime(&self) -> Option<crate::EfiRuntimeInfo> {
+        // TODO: `uefi-bootloader-api` doesn't yet pass along the EFI system table
+        //       or the runtime services' memory regions, so they can't be used.
+        None
+    }
 }
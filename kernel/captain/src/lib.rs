@@ -19,6 +19,7 @@
 
 extern crate alloc;
 
+use boot_info::{CommandLine, EfiRuntimeInfo};
 use log::{error, info};
 use memory::{EarlyIdentityMappedPages, MmiRef, PhysicalAddress};
 use irq_safety::enable_interrupts;
@@ -45,6 +46,31 @@ mod mirror_log_callbacks {
     }
 }
 
+/// The number of bytes of recent log records that are kept in memory for system reports.
+const LOG_HISTORY_SIZE: usize = 64 * 1024;
+
+/// The amount of physical memory available for general use,
+/// which is computed whenever metrics are exported.
+static PHYSICAL_MEMORY_METRIC: metrics::FnMetric = metrics::FnMetric::new(
+    "theseus_physical_memory_bytes",
+    "The amount of physical memory available for general use, by whether it is free or used.",
+    metrics::MetricKind::Gauge,
+    collect_physical_memory_metric,
+);
+
+fn collect_physical_memory_metric(samples: &mut metrics::Samples<'_>) -> core::fmt::Result {
+    let stats = frame_allocator::stats();
+    let free = stats.free_general_frames * memory::PAGE_SIZE;
+    let used = (stats.general_frames - stats.free_general_frames) * memory::PAGE_SIZE;
+    samples.write("", &[("state", &"free" as &dyn core::fmt::Display)], free)?;
+    samples.write("", &[("state", &"used" as &dyn core::fmt::Display)], used)
+}
+
+/// Returns the name of the current task's group, which is the crate namespace it runs in.
+fn current_task_group() -> Option<alloc::string::String> {
+    task::with_current_task(|t| alloc::string::String::from(t.get_namespace().name())).ok()
+}
+
 /// Items that must be held until the end of [`init()`] and should be dropped after.
 pub struct DropAfterInit {
     pub identity_mappings: NoDrop<EarlyIdentityMappedPages>,
@@ -71,6 +97,9 @@ pub use multicore_bringup::MulticoreBringupInfo;
 /// * `multicore_info`: information needed to bring up secondary CPUs.
 /// * `rsdp_address`: the physical address of the RSDP (an ACPI table pointer),
 ///    if available and provided by the bootloader.
+/// * `efi_runtime`: information needed to call the EFI runtime services,
+///    if the machine was booted via UEFI and the bootloader provided it.
+/// * `command_line`: the kernel command line provided by the bootloader, if any.
 #[cfg_attr(target_arch = "aarch64", allow(unreachable_code, unused_variables))]
 pub fn init(
     kernel_mmi_ref: MmiRef,
@@ -78,11 +107,14 @@ pub fn init(
     drop_after_init: DropAfterInit,
     multicore_info: MulticoreBringupInfo,
     rsdp_address: Option<PhysicalAddress>,
+    efi_runtime: Option<EfiRuntimeInfo>,
+    command_line: CommandLine,
 ) -> Result<(), &'static str> {
     #[cfg(all(mirror_log_to_vga, target_arch = "x86_64"))] {
         // Enable early mirroring of logger output to VGA buffer (for real hardware)
         logger::set_log_mirror_function(mirror_log_callbacks::mirror_to_early_vga);
     }
+    logger::enable_history(LOG_HISTORY_SIZE);
 
     // calculate TSC period and initialize it
     // not strictly necessary, but more accurate if we do it early on before interrupts, multicore, and multitasking
@@ -92,11 +124,18 @@ pub fn init(
     } else {
         log::warn!("Couldn't get TSC period");
     }
+    system_report::record_boot_milestone("clock source registered");
 
     // Initialize early devices, which currently only includes ACPI (x86-specific).
     #[cfg(target_arch = "x86_64")]
     device_manager::early_init(rsdp_address, kernel_mmi_ref.lock().deref_mut())?;
 
+    // Map the EFI runtime services, if available, such that EFI variables and time can be used.
+    // Legacy BIOS boots don't have them, so failing to initialize them isn't fatal.
+    if let Err(e) = efi_runtime::init(efi_runtime, &mut kernel_mmi_ref.lock().page_table) {
+        error!("Couldn't initialize EFI runtime services: {}", e);
+    }
+
     // Initialize local and system-wide interrupt controllers.
     // TODO: move this into `interrupts::init()`.
     interrupt_controller::init(&kernel_mmi_ref)?;
@@ -128,12 +167,14 @@ pub fn init(
     // get BSP's CPU ID
     let bsp_id = cpu::bootstrap_cpu().ok_or("captain::init(): couldn't get ID of bootstrap CPU!")?;
     cls_allocator::reload_current_cpu();
+    system_report::record_boot_milestone("interrupts initialized");
 
     // Initialize the scheduler and create the initial `Task`,
     // which is bootstrapped from this current execution context.
     scheduler::init()?;
     let bootstrap_task = spawn::init(kernel_mmi_ref.clone(), bsp_id, bsp_initial_stack)?;
     info!("Created initial bootstrap task: {:?}", bootstrap_task);
+    system_report::record_boot_milestone("tasking initialized");
 
     // after we've initialized the task subsystem, we can use better exception handlers
     // arch-gate: aarch64 simply logs exceptions and crash; porting exceptions_full
@@ -141,6 +182,33 @@ pub fn init(
     #[cfg(target_arch = "x86_64")]
     exceptions_full::init(idt);
     
+    // Set up FPU/SIMD state management, which registers a feature hook to enable XSAVE.
+    // The mode is chosen by the `fpu=eager|lazy` command line option, defaulting to lazy mode,
+    // which defers restoring a task's FPU state until that task actually uses the FPU.
+    // SIMD builds save those registers in the context switch routine itself, so they skip this.
+    if !context_switch::SAVES_SIMD_REGISTERS {
+        let fpu_mode = match command_line.get("fpu") {
+            Some(value) => fpu_state::FpuMode::parse(value).unwrap_or_else(|| {
+                log::warn!("Ignoring invalid kernel command line option \"fpu={}\"", value);
+                fpu_state::FpuMode::default()
+            }),
+            None => fpu_state::FpuMode::default(),
+        };
+        fpu_state::init(fpu_mode)?;
+    }
+    // Enforce SMEP, SMAP, and NX on every CPU that supports them.
+    memory_protection::init();
+    // Set up the shared page that exposes clock and CPU info to unprivileged code.
+    vdso::init()?;
+    // Allow tasks to run code in userspace and handle the system calls it invokes.
+    #[cfg(target_arch = "x86_64")]
+    syscall::init()?;
+
+    // Run feature-dependent setup hooks on this CPU before booting the APs,
+    // which will each run the same hooks themselves.
+    let hook_count = cpu_features::run_hooks();
+    info!("Ran {} CPU feature hooks on BSP core {}", hook_count, bsp_id);
+
     // boot up the other cores (APs)
     let ap_count = multicore_bringup::handle_ap_cores(
         &kernel_mmi_ref,
@@ -150,6 +218,7 @@ pub fn init(
     let cpu_count = ap_count + 1;
     info!("Finished booting all {} AP cores; {} total CPUs are running.", ap_count, cpu_count);
     info!("Proceeding with system initialization, please wait...");
+    system_report::record_boot_milestone("AP cores booted");
 
     // arch-gate: no framebuffer support on aarch64 at the moment
     #[cfg(all(mirror_log_to_vga, target_arch = "x86_64"))] {
@@ -159,8 +228,9 @@ pub fn init(
         logger::set_log_mirror_function(mirror_log_callbacks::mirror_to_terminal);
     }
 
-    // Now that other CPUs are fully booted, init TLB shootdowns,
+    // Now that other CPUs are fully booted, init cross-CPU function calls and TLB shootdowns,
     // which rely on Local APICs to broadcast an IPI to all running CPUs.
+    cpu_call::init()?;
     tlb_shootdown::init();
     
     // Initialize the per-core heaps.
@@ -170,6 +240,12 @@ pub fn init(
         info!("Initialized per-core heaps");
     }
 
+    // Start measuring how long interrupts stay disabled on each CPU.
+    irq_off_tracker::init()?;
+
+    // Start detecting CPUs that are stuck in one task for too long.
+    soft_lockup::init()?;
+
     // Initialize the window manager, and also the PAT, if available.
     // The PAT supports write-combining caching of graphics video memory for better performance
     // and must be initialized explicitly on every CPU, 
@@ -193,7 +269,12 @@ pub fn init(
     #[cfg(target_arch = "aarch64")]
     device_manager::init()?;
 
+    system_report::record_boot_milestone("devices initialized");
+
     task_fs::init()?;
+    metrics::register(&PHYSICAL_MEMORY_METRIC)?;
+    // Charge the files created by each task to its task group, i.e., its crate namespace.
+    fs_quota::init(current_task_group)?;
 
     // create a SIMD personality
     #[cfg(simd_personality)] {
@@ -211,11 +292,19 @@ pub fn init(
     // 1. Drop the items that needed to be held through initialization,
     drop_after_init.drop_all();
 
+    // Optionally check that no kernel mappings are both writable and executable,
+    // which must be done after the early identity mappings have been dropped.
+    #[cfg(wx_audit)]
+    memory_protection::audit_and_report()?;
+
     // 2. Spawn various system tasks/daemons,
     console::start_connection_detection()?;
+    // including the one that writes buffered log records, such that logging no longer waits for the serial port.
+    log_flusher::init()?;
 
     // 3. Start the first application(s).
     first_application::start()?;
+    system_report::record_boot_milestone("first application started");
 
     info!("captain::init(): initialization done! Spawning an idle task on BSP core {} and enabling interrupts...", bsp_id);
     // The following final initialization steps are important, and order matters:
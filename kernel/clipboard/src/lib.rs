@@ -0,0 +1,50 @@
+//! A system-wide clipboard for copying and pasting text.
+//!
+//! The clipboard holds a single piece of text that is shared by all tasks,
+//! so text copied from one terminal or application can be pasted into another.
+//! For example, text selected with the mouse in a terminal is copied here with `Ctrl + Shift + C`,
+//! and the editor's cut and paste commands use it too.
+//!
+//! Every change to the clipboard's contents increments its [`generation()`],
+//! which allows a task to cheaply check whether the clipboard changed since it last looked.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::string::String;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use spin::Mutex;
+
+/// The text on the clipboard.
+static CONTENTS: Mutex<String> = Mutex::new(String::new());
+
+/// The number of times the clipboard's contents have been changed.
+static GENERATION: AtomicUsize = AtomicUsize::new(0);
+
+/// Replaces the text on the clipboard with the given `text`.
+pub fn set<S: Into<String>>(text: S) {
+    let mut contents = CONTENTS.lock();
+    *contents = text.into();
+    GENERATION.fetch_add(1, Ordering::Release);
+}
+
+/// Returns a copy of the text on the clipboard, which is empty if nothing was copied.
+pub fn get() -> String {
+    CONTENTS.lock().clone()
+}
+
+/// Returns `true` if there is no text on the clipboard.
+pub fn is_empty() -> bool {
+    CONTENTS.lock().is_empty()
+}
+
+/// Removes all text from the clipboard.
+pub fn clear() {
+    set(String::new());
+}
+
+/// Returns the number of times the clipboard's contents have been changed.
+pub fn generation() -> usize {
+    GENERATION.load(Ordering::Acquire)
+}
@@ -0,0 +1,637 @@
+//! Declarative command-line interfaces for applications.
+//!
+//! An application declares the flags, options, and positional arguments it accepts
+//! as a static [`Command`], and exposes it through a public function named `command`:
+//!
+//! ```ignore
+//! use command::{Arg, Command, Value};
+//!
+//! pub static COMMAND: Command = Command {
+//!     name: "mkdir",
+//!     about: "Creates the given directories.",
+//!     args: &[
+//!         Arg::positional("DIRECTORY")
+//!             .required()
+//!             .multiple()
+//!             .value(Value::Path)
+//!             .help("the directories to create"),
+//!     ],
+//! };
+//!
+//! pub fn command() -> &'static Command {
+//!     &COMMAND
+//! }
+//!
+//! pub fn main(args: Vec<String>) -> isize {
+//!     let matches = match COMMAND.get_matches(args) {
+//!         Ok(matches) => matches,
+//!         Err(exit_value) => return exit_value,
+//!     };
+//!     for dir_name in matches.values("DIRECTORY") {
+//!         // ...
+//!     }
+//!     0
+//! }
+//! ```
+//!
+//! Before spawning an application, the shell looks up its `command` function
+//! in order to reject invalid arguments and print the help text for `--help`
+//! without running the application at all.
+//! The shell also uses it to complete flags and argument values.
+//!
+//! Every command implicitly accepts `--help`, as well as `-h`
+//! unless the command uses that short name for something else.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::{
+    collections::BTreeMap,
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{fmt, str::FromStr};
+
+/// The name of the public function through which an application exposes its [`Command`].
+pub const COMMAND_FUNCTION_NAME: &str = "command";
+
+/// The signature of the public function through which an application exposes its [`Command`].
+pub type CommandFunc = fn() -> &'static Command;
+
+/// The description of the implicit `--help` flag.
+const HELP_DESCRIPTION: &str = "print this help menu";
+
+/// The kind of value that an option or positional argument accepts,
+/// which is used to both validate and complete it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Value {
+    /// Any string.
+    Any,
+    /// An integer, which may be negative.
+    Integer,
+    /// The path of a file or directory.
+    Path,
+    /// One of the given strings.
+    OneOf(&'static [&'static str]),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Kind {
+    Flag,
+    Option,
+    Positional,
+}
+
+/// A flag, option, or positional argument accepted by a [`Command`].
+///
+/// An `Arg` is created with [`Arg::flag()`], [`Arg::option()`], or [`Arg::positional()`]
+/// and then configured with its builder methods, all of which can be used in a `static`.
+#[derive(Clone, Copy, Debug)]
+pub struct Arg {
+    name: &'static str,
+    kind: Kind,
+    short: Option<char>,
+    help: &'static str,
+    value: Value,
+    required: bool,
+    multiple: bool,
+}
+
+impl Arg {
+    /// A flag that takes no value, given as `--<name>`.
+    ///
+    /// A flag may be given more than once; see [`Matches::occurrences()`].
+    pub const fn flag(name: &'static str) -> Self {
+        Self::new(name, Kind::Flag)
+    }
+
+    /// An option that takes a value, given as `--<name> VALUE` or `--<name>=VALUE`.
+    pub const fn option(name: &'static str) -> Self {
+        Self::new(name, Kind::Option)
+    }
+
+    /// A positional argument, which is shown as `name` in the usage text.
+    ///
+    /// Positional arguments must be given in the order they're declared in.
+    pub const fn positional(name: &'static str) -> Self {
+        Self::new(name, Kind::Positional)
+    }
+
+    const fn new(name: &'static str, kind: Kind) -> Self {
+        Self {
+            name,
+            kind,
+            short: None,
+            help: "",
+            value: Value::Any,
+            required: false,
+            multiple: false,
+        }
+    }
+
+    /// Allows a flag or option to also be given as `-<short>`.
+    pub const fn short(mut self, short: char) -> Self {
+        self.short = Some(short);
+        self
+    }
+
+    /// Sets the description shown in the help text.
+    pub const fn help(mut self, help: &'static str) -> Self {
+        self.help = help;
+        self
+    }
+
+    /// Sets the kind of value that an option or positional argument accepts.
+    pub const fn value(mut self, value: Value) -> Self {
+        self.value = value;
+        self
+    }
+
+    /// Requires that the option or positional argument is given.
+    pub const fn required(mut self) -> Self {
+        self.required = true;
+        self
+    }
+
+    /// Allows an option or positional argument to be given more than once.
+    ///
+    /// A positional argument that can be given more than once consumes all remaining
+    /// positional values, so it must be declared last.
+    pub const fn multiple(mut self) -> Self {
+        self.multiple = true;
+        self
+    }
+
+    /// Returns the name of this argument, which is used to look it up in [`Matches`].
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Returns how this argument is referred to in messages.
+    fn label(&self) -> String {
+        match self.kind {
+            Kind::Flag | Kind::Option => format!("--{}", self.name),
+            Kind::Positional => self.name.to_string(),
+        }
+    }
+
+    /// Returns how this argument is shown in the usage line.
+    fn usage(&self) -> String {
+        match (self.required, self.multiple) {
+            (true, false) => self.name.to_string(),
+            (true, true) => format!("{}...", self.name),
+            (false, false) => format!("[{}]", self.name),
+            (false, true) => format!("[{}]...", self.name),
+        }
+    }
+
+    /// Returns whether `value` is valid for this argument.
+    fn accepts(&self, value: &str) -> bool {
+        match self.value {
+            Value::Any | Value::Path => true,
+            Value::Integer => value.parse::<i64>().is_ok() || value.parse::<u64>().is_ok(),
+            Value::OneOf(choices) => choices.contains(&value),
+        }
+    }
+}
+
+/// The command-line interface of an application.
+#[derive(Debug)]
+pub struct Command {
+    /// The name that the application is invoked with.
+    pub name: &'static str,
+    /// A short description of what the application does.
+    pub about: &'static str,
+    /// The flags, options, and positional arguments that the application accepts.
+    pub args: &'static [Arg],
+}
+
+impl Command {
+    /// Parses the given arguments, which don't include the command's name.
+    ///
+    /// Flags and options may be given before, after, or between positional arguments.
+    /// Short flags may be combined, e.g., `-ab`, and every argument after `--` is positional.
+    pub fn parse<S: AsRef<str>>(&self, args: &[S]) -> Result<Matches, Error> {
+        let mut matches = Matches::default();
+        let mut positionals = self.positionals().peekable();
+        let mut only_positionals = false;
+        let mut args = args.iter().map(S::as_ref);
+
+        while let Some(arg) = args.next() {
+            if only_positionals || arg == "-" || !arg.starts_with('-') {
+                let positional = *positionals
+                    .peek()
+                    .ok_or_else(|| Error::UnexpectedArgument(arg.to_string()))?;
+                matches.add_value(positional, arg)?;
+                if !positional.multiple {
+                    positionals.next();
+                }
+            } else if arg == "--" {
+                only_positionals = true;
+            } else if let Some(long) = arg.strip_prefix("--") {
+                let (name, value) = match long.split_once('=') {
+                    Some((name, value)) => (name, Some(value)),
+                    None => (long, None),
+                };
+                let option = self.find_long(name)?;
+                match (option.kind, value) {
+                    (Kind::Flag, Some(_)) => return Err(Error::UnexpectedValue(option.label())),
+                    (Kind::Flag, None) => matches.add_flag(option),
+                    (_, value) => {
+                        let value = value
+                            .or_else(|| args.next())
+                            .ok_or_else(|| Error::MissingValue(option.label()))?;
+                        matches.add_value(option, value)?;
+                    }
+                }
+            } else {
+                let shorts = &arg[1..];
+                for (i, c) in shorts.char_indices() {
+                    let option = self.find_short(c)?;
+                    if option.kind == Kind::Flag {
+                        matches.add_flag(option);
+                        continue;
+                    }
+                    // The rest of the argument, or else the next argument, is the option's value.
+                    let rest = &shorts[i + c.len_utf8()..];
+                    let value = if rest.is_empty() { args.next() } else { Some(rest) };
+                    let value = value.ok_or_else(|| Error::MissingValue(option.label()))?;
+                    matches.add_value(option, value)?;
+                    break;
+                }
+            }
+        }
+
+        if let Some(missing) = self.args.iter().find(|a| a.required && !matches.is_present(a.name)) {
+            return Err(Error::MissingArgument(missing.label()));
+        }
+        Ok(matches)
+    }
+
+    /// Parses the arguments given to an application's `main` function.
+    ///
+    /// If `--help` was given, the help text is printed,
+    /// and if the arguments are invalid, the error and the usage line are printed.
+    /// In both cases, the value that `main` should return is returned as the error.
+    pub fn get_matches(&self, args: Vec<String>) -> Result<Matches, isize> {
+        match self.parse(&args) {
+            Ok(matches) => Ok(matches),
+            Err(Error::HelpRequested) => {
+                app_io::println!("{}", self.help());
+                Err(0)
+            }
+            Err(e) => {
+                app_io::println!("{}", self.error_message(&e));
+                Err(-1)
+            }
+        }
+    }
+
+    /// Returns the message describing the given error from parsing this command's arguments.
+    pub fn error_message(&self, error: &Error) -> String {
+        format!(
+            "{}: {}\n{}\nTry `{} --help` for more information.",
+            self.name,
+            error,
+            self.usage(),
+            self.name,
+        )
+    }
+
+    /// Returns the one-line summary of how to invoke this command.
+    pub fn usage(&self) -> String {
+        let mut usage = format!("Usage: {} [OPTION]...", self.name);
+        for positional in self.positionals() {
+            usage.push(' ');
+            usage.push_str(&positional.usage());
+        }
+        usage
+    }
+
+    /// Returns the full help text, which describes every argument this command accepts.
+    pub fn help(&self) -> String {
+        let mut rows: Vec<(String, String)> = Vec::new();
+        for arg in self.args.iter().filter(|a| a.kind != Kind::Positional) {
+            let short = match arg.short {
+                Some(short) => format!("-{}, ", short),
+                None => String::from("    "),
+            };
+            let value = match arg.kind {
+                Kind::Option => format!(" {}", arg.name.to_uppercase()),
+                _ => String::new(),
+            };
+            let required = if arg.required { " (required)" } else { "" };
+            rows.push((format!("{}--{}{}", short, arg.name, value), format!("{}{}", arg.help, required)));
+        }
+        if self.find_arg_long("help").is_none() {
+            let short = if self.find_arg_short('h').is_none() { "-h, " } else { "    " };
+            rows.push((format!("{}--help", short), HELP_DESCRIPTION.to_string()));
+        }
+        let options_len = rows.len();
+        rows.extend(self.positionals().map(|a| (a.usage(), a.help.to_string())));
+
+        let width = rows.iter().map(|(left, _)| left.len()).max().unwrap_or(0);
+        let mut help = format!("{}\n{}\n", self.usage(), self.about);
+        let (options, positionals) = rows.split_at(options_len);
+        for (title, rows) in [("Arguments", positionals), ("Options", options)] {
+            if rows.is_empty() {
+                continue;
+            }
+            help.push_str(&format!("\n{}:\n", title));
+            for (left, right) in rows {
+                let line = format!("    {:width$}    {}", left, right, width = width);
+                help.push_str(line.trim_end());
+                help.push('\n');
+            }
+        }
+        help.truncate(help.trim_end().len());
+        help
+    }
+
+    /// Returns the possible completions of `word`, the word being typed
+    /// after the given preceding arguments.
+    pub fn complete<S: AsRef<str>>(&self, preceding: &[S], word: &str) -> Completion {
+        let mut only_positionals = false;
+        let mut expecting_value: Option<&Arg> = None;
+        let mut positional_count = 0;
+        for arg in preceding.iter().map(S::as_ref) {
+            if expecting_value.take().is_some() {
+                continue;
+            }
+            if only_positionals || arg == "-" || !arg.starts_with('-') {
+                positional_count += 1;
+            } else if arg == "--" {
+                only_positionals = true;
+            } else if let Some(long) = arg.strip_prefix("--") {
+                if !long.contains('=') {
+                    expecting_value = self.find_arg_long(long).filter(|a| a.kind == Kind::Option);
+                }
+            } else {
+                let shorts = &arg[1..];
+                for (i, c) in shorts.char_indices() {
+                    match self.find_arg_short(c) {
+                        Some(option) if option.kind == Kind::Option => {
+                            if i + c.len_utf8() == shorts.len() {
+                                expecting_value = Some(option);
+                            }
+                            break;
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        let arg = match expecting_value {
+            Some(option) => option,
+            None if !only_positionals && word.starts_with('-') => {
+                if word.contains('=') {
+                    return Completion::Nothing;
+                }
+                let mut names: Vec<String> = self.args
+                    .iter()
+                    .filter(|a| a.kind != Kind::Positional)
+                    .map(|a| format!("--{}", a.name))
+                    .collect();
+                if self.find_arg_long("help").is_none() {
+                    names.push(String::from("--help"));
+                }
+                names.retain(|name| name.starts_with(word));
+                return Completion::Candidates(names);
+            }
+            None => match self.nth_positional(positional_count) {
+                Some(positional) => positional,
+                None => return Completion::Nothing,
+            },
+        };
+        match arg.value {
+            Value::Path => Completion::Path,
+            Value::OneOf(choices) => Completion::Candidates(
+                choices
+                    .iter()
+                    .filter(|choice| choice.starts_with(word))
+                    .map(|choice| choice.to_string())
+                    .collect(),
+            ),
+            Value::Any | Value::Integer => Completion::Nothing,
+        }
+    }
+
+    fn positionals(&self) -> impl Iterator<Item = &'static Arg> {
+        self.args.iter().filter(|a| a.kind == Kind::Positional)
+    }
+
+    /// Returns the positional argument that the value at the given position is given for.
+    fn nth_positional(&self, n: usize) -> Option<&'static Arg> {
+        self.positionals()
+            .enumerate()
+            .find(|(i, a)| *i == n || (a.multiple && *i < n))
+            .map(|(_, a)| a)
+    }
+
+    fn find_arg_long(&self, name: &str) -> Option<&'static Arg> {
+        self.args.iter().find(|a| a.kind != Kind::Positional && a.name == name)
+    }
+
+    fn find_arg_short(&self, short: char) -> Option<&'static Arg> {
+        self.args.iter().find(|a| a.kind != Kind::Positional && a.short == Some(short))
+    }
+
+    fn find_long(&self, name: &str) -> Result<&'static Arg, Error> {
+        match self.find_arg_long(name) {
+            Some(arg) => Ok(arg),
+            None if name == "help" => Err(Error::HelpRequested),
+            None => Err(Error::UnknownOption(format!("--{}", name))),
+        }
+    }
+
+    fn find_short(&self, short: char) -> Result<&'static Arg, Error> {
+        match self.find_arg_short(short) {
+            Some(arg) => Ok(arg),
+            None if short == 'h' => Err(Error::HelpRequested),
+            None => Err(Error::UnknownOption(format!("-{}", short))),
+        }
+    }
+}
+
+/// The possible completions of a partially-typed argument.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Completion {
+    /// The argument can be completed to any of these strings.
+    Candidates(Vec<String>),
+    /// The argument is the path of a file or directory.
+    Path,
+    /// The argument can't be completed.
+    Nothing,
+}
+
+/// The arguments given to a [`Command`], as parsed by [`Command::parse()`].
+///
+/// Arguments are looked up by the names they were declared with.
+#[derive(Debug, Default)]
+pub struct Matches {
+    /// The values given for each argument, with an empty value for each occurrence of a flag.
+    args: BTreeMap<&'static str, Vec<String>>,
+}
+
+impl Matches {
+    /// Returns whether the given argument was given at least once.
+    pub fn is_present(&self, name: &str) -> bool {
+        self.args.contains_key(name)
+    }
+
+    /// Returns how many times the given argument was given.
+    pub fn occurrences(&self, name: &str) -> usize {
+        self.args.get(name).map_or(0, Vec::len)
+    }
+
+    /// Returns the value of the given option or positional argument, if it was given.
+    pub fn value(&self, name: &str) -> Option<&str> {
+        self.values(name).last().map(String::as_str)
+    }
+
+    /// Returns every value given for the given option or positional argument.
+    pub fn values(&self, name: &str) -> &[String] {
+        self.args.get(name).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// Parses the value of the given option or positional argument, if it was given.
+    pub fn value_of<T: FromStr>(&self, name: &str) -> Result<Option<T>, Error> {
+        self.value(name)
+            .map(|value| {
+                value.parse().map_err(|_| Error::InvalidValue {
+                    arg: name.to_string(),
+                    value: value.to_string(),
+                })
+            })
+            .transpose()
+    }
+
+    fn add_flag(&mut self, flag: &'static Arg) {
+        self.args.entry(flag.name).or_default().push(String::new());
+    }
+
+    fn add_value(&mut self, arg: &'static Arg, value: &str) -> Result<(), Error> {
+        if !arg.accepts(value) {
+            return Err(Error::InvalidValue {
+                arg: arg.label(),
+                value: value.to_string(),
+            });
+        }
+        let values = self.args.entry(arg.name).or_default();
+        if !values.is_empty() && !arg.multiple {
+            return Err(Error::Repeated(arg.label()));
+        }
+        values.push(value.to_string());
+        Ok(())
+    }
+}
+
+/// An error from parsing the arguments given to a [`Command`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// `--help` was given, so the help text should be printed instead.
+    HelpRequested,
+    /// The given flag or option isn't accepted by the command.
+    UnknownOption(String),
+    /// The given option was given without a value.
+    MissingValue(String),
+    /// The given flag was given a value, e.g., `--flag=value`.
+    UnexpectedValue(String),
+    /// The given option or positional argument was given more than once.
+    Repeated(String),
+    /// The given required option or positional argument wasn't given.
+    MissingArgument(String),
+    /// The given positional argument was given, but the command doesn't accept any more.
+    UnexpectedArgument(String),
+    /// The given value isn't valid for the given argument.
+    InvalidValue { arg: String, value: String },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::HelpRequested => write!(f, "help requested"),
+            Error::UnknownOption(option) => write!(f, "unknown option `{}`", option),
+            Error::MissingValue(option) => write!(f, "missing value for `{}`", option),
+            Error::UnexpectedValue(flag) => write!(f, "`{}` doesn't take a value", flag),
+            Error::Repeated(arg) => write!(f, "`{}` was given more than once", arg),
+            Error::MissingArgument(arg) => write!(f, "missing required argument `{}`", arg),
+            Error::UnexpectedArgument(arg) => write!(f, "unexpected argument `{}`", arg),
+            Error::InvalidValue { arg, value } => write!(f, "invalid value `{}` for `{}`", value, arg),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use alloc::vec;
+
+    static TEST: Command = Command {
+        name: "test",
+        about: "Tests things.",
+        args: &[
+            Arg::flag("verbose").short('v').help("print more"),
+            Arg::option("count").short('n').value(Value::Integer).help("how many"),
+            Arg::option("mode").value(Value::OneOf(&["fast", "slow"])),
+            Arg::positional("SOURCE").required().value(Value::Path),
+            Arg::positional("DEST").multiple(),
+        ],
+    };
+
+    #[test]
+    fn parse_args() {
+        let matches = TEST.parse(&["-vv", "a", "--count=3", "b", "-n", "4", "--", "-c"]);
+        assert_eq!(matches.unwrap_err(), Error::Repeated(String::from("--count")));
+
+        let matches = TEST.parse(&["-vvn3", "a", "b", "--mode", "slow", "--", "-c"]).unwrap();
+        assert_eq!(matches.occurrences("verbose"), 2);
+        assert_eq!(matches.value_of::<u32>("count"), Ok(Some(3)));
+        assert_eq!(matches.value("mode"), Some("slow"));
+        assert_eq!(matches.value("SOURCE"), Some("a"));
+        assert_eq!(matches.values("DEST"), &[String::from("b"), String::from("-c")]);
+        assert!(!TEST.parse(&["a"]).unwrap().is_present("DEST"));
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(TEST.parse(&["a", "-h"]).unwrap_err(), Error::HelpRequested);
+        assert_eq!(TEST.parse::<&str>(&[]).unwrap_err(), Error::MissingArgument(String::from("SOURCE")));
+        assert_eq!(TEST.parse(&["-x"]).unwrap_err(), Error::UnknownOption(String::from("-x")));
+        assert_eq!(TEST.parse(&["a", "-n"]).unwrap_err(), Error::MissingValue(String::from("--count")));
+        assert_eq!(
+            TEST.parse(&["a", "--verbose=1"]).unwrap_err(),
+            Error::UnexpectedValue(String::from("--verbose")),
+        );
+        assert_eq!(
+            TEST.parse(&["a", "--mode=medium"]).unwrap_err(),
+            Error::InvalidValue { arg: String::from("--mode"), value: String::from("medium") },
+        );
+    }
+
+    #[test]
+    fn help_text() {
+        assert_eq!(TEST.usage(), "Usage: test [OPTION]... SOURCE [DEST]...");
+        let help = TEST.help();
+        assert!(help.contains("\n    SOURCE\n"));
+        assert!(help.contains("\n    -v, --verbose        print more\n"));
+        assert!(help.contains("\n        --mode MODE\n"));
+        assert!(help.ends_with("\n    -h, --help           print this help menu"));
+    }
+
+    #[test]
+    fn completion() {
+        let no_args: &[&str] = &[];
+        assert_eq!(TEST.complete(no_args, "--c"), Completion::Candidates(vec![String::from("--count")]));
+        assert_eq!(TEST.complete(no_args, ""), Completion::Path);
+        assert_eq!(TEST.complete(&["a"], ""), Completion::Nothing);
+        assert_eq!(TEST.complete(&["-n"], ""), Completion::Nothing);
+        assert_eq!(
+            TEST.complete(&["a", "--mode"], "f"),
+            Completion::Candidates(vec![String::from("fast")]),
+        );
+    }
+}
@@ -12,6 +12,11 @@ pub use context_switch_regular::read_first_register;
 cfg_if::cfg_if! {
     if #[cfg(simd_personality)] {
         use core::arch::asm;
+
+        /// Whether the context switch routines save and restore SIMD registers,
+        /// in which case they must not also be managed by the `fpu_state` crate.
+        pub const SAVES_SIMD_REGISTERS: bool = true;
+
         pub use context_switch_sse::*;
         pub use context_switch_regular::*;
         pub use context_switch_avx::*;
@@ -159,17 +164,23 @@ cfg_if::cfg_if! {
     else if #[cfg(target_feature = "avx")] {
         pub use context_switch_avx::ContextAVX as Context;
         pub use context_switch_avx::context_switch_avx as context_switch;
+        /// Whether the context switch routine saves and restores SIMD registers.
+        pub const SAVES_SIMD_REGISTERS: bool = true;
     }
 
     else if #[cfg(target_feature = "sse2")] {
         // this crate covers SSE, SSE2, SSE3, SSE4, but we're only currently using it for SSE2
         pub use context_switch_sse::ContextSSE as Context;
         pub use context_switch_sse::context_switch_sse as context_switch;
+        /// Whether the context switch routine saves and restores SIMD registers.
+        pub const SAVES_SIMD_REGISTERS: bool = true;
     }
 
     else {
         // this covers only the default x86_64 registers
         pub use context_switch_regular::ContextRegular as Context;
         pub use context_switch_regular::context_switch_regular as context_switch;
+        /// Whether the context switch routine saves and restores SIMD registers.
+        pub const SAVES_SIMD_REGISTERS: bool = false;
     }
 }
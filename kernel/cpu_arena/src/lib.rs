@@ -0,0 +1,339 @@
+//! Per-CPU bump arenas for short-lived allocations on hot paths,
+//! such as packet metadata in network drivers or log records written by interrupt handlers.
+//!
+//! Allocating from a [`CpuArena`] only bumps an offset within the current CPU's region of the arena,
+//! with interrupts held, so it never waits for the global heap's locks.
+//! Individual allocations are never returned to the arena; instead, the arena is reset in epochs.
+//!
+//! Each CPU's region consists of two generations, and all allocations made during an epoch
+//! come from that epoch's generation.
+//! Invoking [`CpuArena::advance_epoch()`] retires the current generation on all CPUs
+//! and starts allocating from the other one.
+//! Once all allocations from a retired generation have been dropped,
+//! the arena's deferred task resets that generation such that the next epoch can reuse it.
+//!
+//! Allocation fails if the current generation is full;
+//! callers are expected to fall back to the regular heap in that case.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::{
+    boxed::Box,
+    format,
+    string::String,
+    sync::{Arc, Weak},
+    vec::Vec,
+};
+use core::{
+    alloc::Layout,
+    cell::UnsafeCell,
+    fmt,
+    mem::MaybeUninit,
+    ops::{Deref, DerefMut},
+    ptr::{self, NonNull},
+    sync::atomic::{AtomicU64, AtomicU8, AtomicUsize, Ordering},
+};
+use cpu::CpuId;
+use spin::{Mutex, Once};
+use task::JoinableTaskRef;
+
+/// The number of generations in each CPU's region of an arena.
+const NUM_GENERATIONS: usize = 2;
+
+/// A generation that has been reset and can be activated by the next epoch.
+const FREE: u8 = 0;
+/// A generation that allocations are currently made from.
+const ACTIVE: u8 = 1;
+/// A generation from a previous epoch, which may still have live allocations.
+const RETIRED: u8 = 2;
+/// A retired generation that the deferred task is currently trying to reset.
+const RESETTING: u8 = 3;
+
+/// A set of per-CPU bump allocators that are reset together in epochs.
+///
+/// See the [crate-level documentation](crate) for more details.
+pub struct CpuArena {
+    name: String,
+    /// The generations of each CPU's region, sorted by CPU ID.
+    cpus: Box<[(CpuId, [Generation; NUM_GENERATIONS])]>,
+    /// The current epoch, which determines the generation that allocations are made from.
+    epoch: AtomicU64,
+    /// Serializes invocations of [`CpuArena::advance_epoch()`].
+    advancing: Mutex<()>,
+    /// The deferred task that resets retired generations.
+    reclaimer: Once<JoinableTaskRef>,
+}
+
+/// One generation of a single CPU's region in a [`CpuArena`].
+struct Generation {
+    memory: Box<[UnsafeCell<MaybeUninit<u8>>]>,
+    /// The offset of the next free byte in `memory`,
+    /// which only the generation's CPU advances while holding interrupts,
+    /// and only the deferred task resets while the generation is `RESETTING`.
+    offset: AtomicUsize,
+    /// The number of allocations from this generation that haven't been dropped yet.
+    live: AtomicUsize,
+    /// One of `FREE`, `ACTIVE`, `RETIRED`, or `RESETTING`.
+    state: AtomicU8,
+}
+
+// SAFETY: each allocation is a disjoint range of `memory`, which is only handed out once per epoch,
+// and `memory` is only reused after all allocations from it have been dropped.
+unsafe impl Sync for Generation {}
+
+impl Generation {
+    fn new(bytes: usize, state: u8) -> Generation {
+        Generation {
+            memory: (0..bytes).map(|_| UnsafeCell::new(MaybeUninit::uninit())).collect(),
+            offset: AtomicUsize::new(0),
+            live: AtomicUsize::new(0),
+            state: AtomicU8::new(state),
+        }
+    }
+
+    /// Allocates the given `layout` from this generation, if it's active and has enough space.
+    ///
+    /// This must only be invoked on the generation's CPU with interrupts held.
+    /// If this returns `Err(true)`, the deferred task must be woken up to reset this generation.
+    fn bump(&self, layout: Layout) -> Result<NonNull<u8>, bool> {
+        // Count this allocation before checking the state, such that the deferred task
+        // either sees it and doesn't reset this generation, or we see that it's no longer active.
+        self.live.fetch_add(1, Ordering::SeqCst);
+        if self.state.load(Ordering::SeqCst) != ACTIVE {
+            return Err(self.release());
+        }
+        let base = UnsafeCell::raw_get(self.memory.as_ptr()) as usize;
+        let next_free = base + self.offset.load(Ordering::Relaxed);
+        let start = (next_free + layout.align() - 1) & !(layout.align() - 1);
+        let end = match start.checked_add(layout.size()) {
+            Some(end) if end <= base + self.memory.len() => end,
+            _ => return Err(self.release()),
+        };
+        self.offset.store(end - base, Ordering::Relaxed);
+        NonNull::new(start as *mut u8).ok_or_else(|| self.release())
+    }
+
+    /// Releases one allocation from this generation,
+    /// returning whether the deferred task must be woken up to reset it.
+    fn release(&self) -> bool {
+        self.live.fetch_sub(1, Ordering::SeqCst) == 1
+            && matches!(self.state.load(Ordering::SeqCst), RETIRED | RESETTING)
+    }
+
+    /// Resets this generation if it's retired and all of its allocations have been dropped.
+    fn try_reset(&self) {
+        if self.state.compare_exchange(RETIRED, RESETTING, Ordering::SeqCst, Ordering::SeqCst).is_err() {
+            return;
+        }
+        if self.live.load(Ordering::SeqCst) != 0 {
+            self.state.store(RETIRED, Ordering::SeqCst);
+            return;
+        }
+        self.offset.store(0, Ordering::Relaxed);
+        self.state.store(FREE, Ordering::SeqCst);
+    }
+}
+
+impl CpuArena {
+    /// Creates a new arena in which each CPU can allocate up to `bytes_per_epoch` bytes in each epoch,
+    /// and spawns its deferred task that resets retired generations.
+    ///
+    /// Each CPU that exists now gets its own region of `2 * bytes_per_epoch` bytes,
+    /// so this must be invoked after all CPUs have been registered.
+    pub fn new(name: &str, bytes_per_epoch: usize) -> Result<Arc<CpuArena>, &'static str> {
+        if bytes_per_epoch == 0 {
+            return Err("a CpuArena must have a non-zero capacity");
+        }
+        let mut cpus: Vec<CpuId> = cpu::cpus().collect();
+        cpus.sort_unstable();
+        let arena = Arc::new(CpuArena {
+            name: String::from(name),
+            cpus: cpus.into_iter()
+                .map(|cpu| (cpu, [Generation::new(bytes_per_epoch, ACTIVE), Generation::new(bytes_per_epoch, FREE)]))
+                .collect(),
+            epoch: AtomicU64::new(0),
+            advancing: Mutex::new(()),
+            reclaimer: Once::new(),
+        });
+        let reclaimer = deferred_interrupt_tasks::spawn_deferred_task(
+            reclaim,
+            Arc::downgrade(&arena),
+            Some(format!("cpu_arena_reclaimer_{}", name)),
+        )?;
+        arena.reclaimer.call_once(|| reclaimer);
+        Ok(arena)
+    }
+
+    /// Returns the name of this arena.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the current epoch of this arena, which starts at `0`.
+    pub fn epoch(&self) -> u64 {
+        self.epoch.load(Ordering::Acquire)
+    }
+
+    /// Returns the number of bytes that the given CPU has allocated in the current epoch,
+    /// or `None` if this arena has no region for that CPU.
+    pub fn used(&self, cpu: CpuId) -> Option<usize> {
+        let index = self.cpus.binary_search_by_key(&cpu, |(cpu, _)| *cpu).ok()?;
+        let generation = &self.cpus[index].1[self.epoch() as usize % NUM_GENERATIONS];
+        Some(generation.offset.load(Ordering::Relaxed))
+    }
+
+    /// Moves the given `value` into the current CPU's region of this arena.
+    ///
+    /// Returns the `value` back if it doesn't fit,
+    /// in which case the caller should allocate it from the heap instead.
+    pub fn alloc<T>(&self, value: T) -> Result<ArenaBox<'_, T>, T> {
+        match self.alloc_layout(Layout::new::<T>()) {
+            Some((ptr, generation)) => {
+                let ptr = ptr.cast::<T>();
+                // SAFETY: the arena allocated a properly-aligned region for a `T` that nothing else uses.
+                unsafe { ptr.as_ptr().write(value) };
+                Ok(ArenaBox { ptr, generation, arena: self })
+            }
+            None => Err(value),
+        }
+    }
+
+    /// Copies the given slice into the current CPU's region of this arena,
+    /// or returns `None` if it doesn't fit.
+    pub fn alloc_slice_copy<T: Copy>(&self, src: &[T]) -> Option<ArenaBox<'_, [T]>> {
+        let (ptr, generation) = self.alloc_layout(Layout::for_value(src))?;
+        let ptr = ptr.cast::<T>();
+        // SAFETY: the arena allocated a properly-aligned region for `src.len()` elements that nothing else uses.
+        unsafe { ptr::copy_nonoverlapping(src.as_ptr(), ptr.as_ptr(), src.len()) };
+        Some(ArenaBox {
+            ptr: NonNull::slice_from_raw_parts(ptr, src.len()),
+            generation,
+            arena: self,
+        })
+    }
+
+    /// Allocates the given `layout` from the current CPU's active generation.
+    fn alloc_layout(&self, layout: Layout) -> Option<(NonNull<u8>, &Generation)> {
+        // Hold interrupts such that this task can't migrate to another CPU
+        // and an interrupt handler on this CPU can't allocate from the same generation concurrently.
+        let _held_interrupts = sync_irq::hold_interrupts();
+        let current = cpu::current_cpu();
+        let index = self.cpus.binary_search_by_key(&current, |(cpu, _)| *cpu).ok()?;
+        let generation = &self.cpus[index].1[self.epoch() as usize % NUM_GENERATIONS];
+        match generation.bump(layout) {
+            Ok(ptr) => Some((ptr, generation)),
+            Err(needs_reset) => {
+                if needs_reset {
+                    self.wake_reclaimer();
+                }
+                None
+            }
+        }
+    }
+
+    /// Starts a new epoch, in which allocations come from the generation that was reset
+    /// after the previous epoch ended. The current generation of every CPU is retired,
+    /// and will be reset by the deferred task once all of its allocations have been dropped.
+    ///
+    /// Returns the new epoch, or an error if some allocations from two epochs ago are still live.
+    pub fn advance_epoch(&self) -> Result<u64, &'static str> {
+        let _advancing = self.advancing.lock();
+        let epoch = self.epoch();
+        let current = epoch as usize % NUM_GENERATIONS;
+        let next = (epoch as usize + 1) % NUM_GENERATIONS;
+        for (_cpu, generations) in self.cpus.iter() {
+            // The deferred task may not have gotten around to resetting it yet.
+            generations[next].try_reset();
+            if generations[next].state.load(Ordering::SeqCst) != FREE {
+                return Err("some allocations from the previous epoch of this CpuArena are still live");
+            }
+        }
+        for (_cpu, generations) in self.cpus.iter() {
+            generations[next].state.store(ACTIVE, Ordering::SeqCst);
+        }
+        self.epoch.store(epoch + 1, Ordering::Release);
+        for (_cpu, generations) in self.cpus.iter() {
+            generations[current].state.store(RETIRED, Ordering::SeqCst);
+        }
+        self.wake_reclaimer();
+        Ok(epoch + 1)
+    }
+
+    /// Unblocks the deferred task such that it resets all retired generations
+    /// whose allocations have all been dropped.
+    fn wake_reclaimer(&self) {
+        if let Some(reclaimer) = self.reclaimer.get() {
+            let _ = reclaimer.unblock();
+        }
+    }
+}
+
+impl fmt::Debug for CpuArena {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CpuArena")
+            .field("name", &self.name)
+            .field("epoch", &self.epoch())
+            .field("cpus", &self.cpus.len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// The action of an arena's deferred task, which resets its retired generations.
+fn reclaim(arena: &Weak<CpuArena>) -> Result<(), &'static str> {
+    let arena = arena.upgrade().ok_or("the CpuArena was dropped")?;
+    for (_cpu, generations) in arena.cpus.iter() {
+        for generation in generations {
+            generation.try_reset();
+        }
+    }
+    Ok(())
+}
+
+/// An owned value that was allocated from a [`CpuArena`].
+///
+/// Dropping it drops the value, but its memory is only reused once its arena's epoch has ended
+/// and all other values allocated in that epoch on the same CPU have been dropped too.
+/// It can be dropped on any CPU.
+pub struct ArenaBox<'a, T: ?Sized> {
+    ptr: NonNull<T>,
+    generation: &'a Generation,
+    arena: &'a CpuArena,
+}
+
+// SAFETY: an `ArenaBox` owns its value like a `Box`, and releasing its generation is atomic.
+unsafe impl<T: ?Sized + Send> Send for ArenaBox<'_, T> {}
+// SAFETY: an `ArenaBox` only gives out shared references to its value through `&self`.
+unsafe impl<T: ?Sized + Sync> Sync for ArenaBox<'_, T> {}
+
+impl<T: ?Sized> Deref for ArenaBox<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        // SAFETY: the value was initialized upon allocation and is exclusively owned by this `ArenaBox`.
+        unsafe { self.ptr.as_ref() }
+    }
+}
+
+impl<T: ?Sized> DerefMut for ArenaBox<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        // SAFETY: the value was initialized upon allocation and is exclusively owned by this `ArenaBox`.
+        unsafe { self.ptr.as_mut() }
+    }
+}
+
+impl<T: ?Sized> Drop for ArenaBox<'_, T> {
+    fn drop(&mut self) {
+        // SAFETY: the value was initialized upon allocation and is never used again.
+        unsafe { ptr::drop_in_place(self.ptr.as_ptr()) };
+        if self.generation.release() {
+            self.arena.wake_reclaimer();
+        }
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for ArenaBox<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
@@ -0,0 +1,98 @@
+//! A reusable spinning barrier.
+
+use core::{hint::spin_loop, sync::atomic::{AtomicUsize, Ordering}};
+
+/// A barrier that blocks a fixed number of participants until all of them have reached it.
+///
+/// Unlike `std::sync::Barrier`, this spins instead of blocking,
+/// so it can be used in contexts that cannot block, e.g., with interrupts disabled
+/// or within a function run on other CPUs by [`run_on_each_cpu()`](crate::run_on_each_cpu).
+///
+/// The barrier can be reused: once all participants have passed it,
+/// they can wait on it again in order to synchronize at a later point.
+pub struct Barrier {
+    participants: usize,
+    /// The number of participants that have reached the barrier in the current round.
+    arrived: AtomicUsize,
+    /// Incremented by the last participant to arrive, which releases the others.
+    generation: AtomicUsize,
+}
+
+impl Barrier {
+    /// Creates a new barrier for the given number of participants.
+    pub const fn new(participants: usize) -> Barrier {
+        Barrier {
+            participants,
+            arrived: AtomicUsize::new(0),
+            generation: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns the number of participants that this barrier waits for.
+    pub fn participants(&self) -> usize {
+        self.participants
+    }
+
+    /// Spins until all participants have reached this barrier.
+    ///
+    /// Returns `true` for exactly one participant of each round, the last one to arrive,
+    /// and `false` for the others.
+    pub fn wait(&self) -> bool {
+        let generation = self.generation.load(Ordering::Acquire);
+        if self.arrived.fetch_add(1, Ordering::AcqRel) + 1 >= self.participants {
+            // Reset the count before releasing the others, such that they can immediately reuse the barrier.
+            self.arrived.store(0, Ordering::Relaxed);
+            self.generation.fetch_add(1, Ordering::Release);
+            true
+        } else {
+            while self.generation.load(Ordering::Acquire) == generation {
+                spin_loop();
+            }
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use std::{sync::Arc, thread, vec::Vec};
+
+    #[test]
+    fn single_participant_never_waits() {
+        let barrier = Barrier::new(1);
+        assert!(barrier.wait());
+        assert!(barrier.wait());
+    }
+
+    #[test]
+    fn rounds_are_synchronized() {
+        const THREADS: usize = 4;
+        const ROUNDS: usize = 100;
+        let barrier = Arc::new(Barrier::new(THREADS));
+        let counter = Arc::new(AtomicUsize::new(0));
+        let leaders = Arc::new(AtomicUsize::new(0));
+
+        let handles: Vec<_> = (0..THREADS).map(|_| {
+            let (barrier, counter, leaders) = (barrier.clone(), counter.clone(), leaders.clone());
+            thread::spawn(move || {
+                for round in 0..ROUNDS {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    if barrier.wait() {
+                        leaders.fetch_add(1, Ordering::SeqCst);
+                    }
+                    // Every thread must have incremented the counter for this round before anyone passes.
+                    assert!(counter.load(Ordering::SeqCst) >= (round + 1) * THREADS);
+                    barrier.wait();
+                }
+            })
+        }).collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(counter.load(Ordering::SeqCst), THREADS * ROUNDS);
+        assert_eq!(leaders.load(Ordering::SeqCst), ROUNDS);
+    }
+}
@@ -0,0 +1,376 @@
+//! Support for running functions on other CPUs and synchronizing CPUs with barriers.
+//!
+//! [`run_on_cpu()`], [`run_on_other_cpus()`], and [`run_on_each_cpu()`] interrupt the target CPUs
+//! with an IPI, run the given function on each of them, and wait until all of them have completed it.
+//! The IPI is an NMI on x86_64 and a fast interrupt (FIQ) on aarch64,
+//! so target CPUs respond even if they currently have regular interrupts disabled.
+//!
+//! # Important Note
+//! Because of that, the given function runs in a very restricted context:
+//! it must not acquire ANY locks, even irq-safe ones, nor allocate memory or log messages,
+//! as the code it interrupted on a target CPU may currently hold them.
+//! The same applies to code that waits on a [`Barrier`] from within such a function.
+//!
+//! If the function panics on a target CPU, the panic is caught on that CPU
+//! and reported to the caller as a [`CallError`], instead of killing the interrupted task.
+//!
+//! Only one cross-CPU call can be in progress at a time.
+//! A CPU that is waiting to start its own call still handles calls from other CPUs,
+//! so concurrent calls from multiple CPUs cannot deadlock.
+//!
+//! A target CPU that doesn't respond within [`CALL_TIMEOUT`], e.g., because it is offline or hung,
+//! is skipped and reported to the caller as [`CallError::TimedOut`], instead of blocking the caller forever.
+//! Callers that cannot tolerate a skipped CPU, e.g., TLB shootdowns, must instead use
+//! [`run_on_other_cpus_until_complete()`], which waits as long as necessary.
+
+#![no_std]
+
+extern crate alloc;
+
+mod barrier;
+
+pub use barrier::Barrier;
+
+use alloc::vec::Vec;
+use core::{
+    hint::spin_loop,
+    ptr,
+    sync::atomic::{AtomicBool, AtomicPtr, AtomicU32, AtomicUsize, Ordering},
+    time::Duration,
+};
+use cpu::CpuId;
+use irq_safety::hold_interrupts;
+use time::Instant;
+
+/// The maximum number of CPUs that a single call can target.
+const MAX_CPUS: usize = 256;
+
+/// How long to wait for the target CPUs to start running a call's function,
+/// and for another CPU's call to complete before starting a new call.
+pub const CALL_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// This lock ensures only one cross-CPU call can be in progress at a time.
+static CALL_LOCK: AtomicBool = AtomicBool::new(false);
+/// The function to be run by the targets of the current call, or null if there is no current call.
+static CALL_FUNC: AtomicPtr<&'static (dyn Fn() + Sync)> = AtomicPtr::new(ptr::null_mut());
+/// The CPUs targeted by the current call; only the first `NUM_TARGETS` entries are valid.
+static TARGETS: [Target; MAX_CPUS] = [EMPTY_TARGET; MAX_CPUS];
+static NUM_TARGETS: AtomicUsize = AtomicUsize::new(0);
+/// The number of targets that have not yet completed the current call.
+static REMAINING: AtomicUsize = AtomicUsize::new(0);
+/// The number of CPUs currently inspecting the current call in [`handle_call_ipi()`],
+/// which must reach zero before the call's state can be reused.
+static ACTIVE_HANDLERS: AtomicUsize = AtomicUsize::new(0);
+/// The number of IPIs sent to targets that timed out, which may still arrive after their call was abandoned.
+static LATE_IPIS: AtomicUsize = AtomicUsize::new(0);
+
+/// A CPU targeted by the current call.
+struct Target {
+    /// The raw value of the target's `CpuId`.
+    cpu: AtomicU32,
+    /// Whether the target has yet to run the current call's function.
+    pending: AtomicBool,
+    /// Whether the call's function panicked on the target.
+    panicked: AtomicBool,
+    /// Whether the target didn't start running the call's function before the timeout elapsed.
+    timed_out: AtomicBool,
+}
+
+// Only used to initialize `TARGETS`, since `Target` isn't `Copy`.
+#[allow(clippy::declare_interior_mutable_const)]
+const EMPTY_TARGET: Target = Target {
+    cpu: AtomicU32::new(0),
+    pending: AtomicBool::new(false),
+    panicked: AtomicBool::new(false),
+    timed_out: AtomicBool::new(false),
+};
+
+/// The error returned when a function could not be run on all of its target CPUs.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CallError {
+    /// The function panicked on the given CPUs.
+    Panicked(Vec<CpuId>),
+    /// The given CPUs didn't respond within [`CALL_TIMEOUT`], so the function wasn't run on them.
+    TimedOut(Vec<CpuId>),
+    /// Another CPU's call didn't complete within [`CALL_TIMEOUT`], so the function wasn't run at all.
+    Busy,
+    /// There are more CPUs in the system than a single call can target.
+    TooManyCpus,
+}
+
+/// The CPUs that a call should be run on, excluding the current CPU.
+#[derive(Clone, Copy)]
+enum Destination {
+    Cpu(CpuId),
+    AllOtherCpus,
+}
+
+/// Initializes the delivery of cross-CPU function call IPIs.
+///
+/// On x86_64, this does nothing, as the NMI handler invokes [`handle_call_ipi()`] directly.
+pub fn init() -> Result<(), &'static str> {
+    #[cfg(target_arch = "aarch64")]
+    interrupts::setup_cpu_call_handler(cpu_call_ipi_handler)?;
+
+    Ok(())
+}
+
+/// Runs the given function on the given CPU and waits for it to complete.
+///
+/// If `cpu` is the current CPU, the function is simply invoked with interrupts disabled.
+/// See the [crate-level docs](crate) for the restrictions on what the function may do.
+pub fn run_on_cpu<F: Fn() + Sync>(cpu: CpuId, func: F) -> Result<(), CallError> {
+    if cpu == cpu::current_cpu() {
+        let _held_ints = hold_interrupts();
+        return run_local(&func).map_err(|cpu| CallError::Panicked(alloc::vec![cpu]));
+    }
+    call(Destination::Cpu(cpu), &func, || Ok(()), Some(CALL_TIMEOUT))
+}
+
+/// Runs the given function on every CPU except the current one and waits for all of them to complete it.
+///
+/// See the [crate-level docs](crate) for the restrictions on what the function may do.
+pub fn run_on_other_cpus<F: Fn() + Sync>(func: F) -> Result<(), CallError> {
+    call(Destination::AllOtherCpus, &func, || Ok(()), Some(CALL_TIMEOUT))
+}
+
+/// Runs the given function on every CPU except the current one and waits for all of them
+/// to complete it, without ever timing out.
+///
+/// This is for callers that cannot proceed unless the function has run on every other CPU,
+/// so it never returns [`CallError::TimedOut`] or [`CallError::Busy`].
+/// See the [crate-level docs](crate) for the restrictions on what the function may do.
+pub fn run_on_other_cpus_until_complete<F: Fn() + Sync>(func: F) -> Result<(), CallError> {
+    call(Destination::AllOtherCpus, &func, || Ok(()), None)
+}
+
+/// Runs the given function on every CPU, including the current one,
+/// and waits for all of them to complete it.
+///
+/// The function runs on the current CPU with interrupts disabled,
+/// concurrently with the other CPUs running it.
+/// See the [crate-level docs](crate) for the restrictions on what the function may do.
+pub fn run_on_each_cpu<F: Fn() + Sync>(func: F) -> Result<(), CallError> {
+    call(Destination::AllOtherCpus, &func, || run_local(&func), Some(CALL_TIMEOUT))
+}
+
+/// Runs the given function on the current CPU while all other CPUs spin with interrupts disabled,
+/// such that none of them can observe the intermediate states of what the function modifies.
+///
+/// The other CPUs are held in an NMI or FIQ context, so the function must not acquire
+/// any locks that code on another CPU might hold, nor allocate memory or log messages.
+///
+/// If any other CPU doesn't stop within [`CALL_TIMEOUT`], the function isn't run
+/// and the CPUs that did stop are released.
+pub fn stop_other_cpus_while<F: FnOnce() -> R, R>(func: F) -> Result<R, CallError> {
+    let stopped = AtomicUsize::new(0);
+    let done = AtomicBool::new(false);
+    let hold = || {
+        // Signal that this CPU has stopped, then wait until the function has completed.
+        stopped.fetch_add(1, Ordering::AcqRel);
+        while !done.load(Ordering::Acquire) {
+            spin_loop();
+        }
+    };
+    let mut result = None;
+    call(Destination::AllOtherCpus, &hold, || {
+        // Don't use a `Barrier` here, as it would wait forever for a CPU that never stops.
+        let others = cpu::cpu_count() as usize - 1;
+        let start = Instant::now();
+        while stopped.load(Ordering::Acquire) < others {
+            if start.elapsed() >= CALL_TIMEOUT {
+                // The call itself then times out and reports the CPUs that didn't respond.
+                done.store(true, Ordering::Release);
+                return Ok(());
+            }
+            spin_loop();
+        }
+        let func_result = catch_unwind::catch_unwind_with_arg(|func: F| func(), func);
+        done.store(true, Ordering::Release);
+        result = Some(func_result.map_err(|_| cpu::current_cpu())?);
+        Ok(())
+    }, Some(CALL_TIMEOUT))?;
+    // The other CPUs may have all stopped right after the above timeout elapsed.
+    result.ok_or(CallError::TimedOut(Vec::new()))
+}
+
+/// Handles a cross-CPU function call IPI by running the current call's function
+/// if this CPU is one of its targets and hasn't yet run it.
+///
+/// There is no need to invoke this directly, it will be called by an IPI interrupt handler.
+///
+/// ## Return
+/// Returns `true` if this CPU ran the current call's function, `false` otherwise.
+pub fn handle_call_ipi() -> bool {
+    ACTIVE_HANDLERS.fetch_add(1, Ordering::SeqCst);
+    let handled = run_pending_call();
+    ACTIVE_HANDLERS.fetch_sub(1, Ordering::Release);
+    // An IPI for a call that timed out may arrive after the call was abandoned,
+    // which must not be mistaken for an unexpected NMI.
+    handled || LATE_IPIS.fetch_update(Ordering::AcqRel, Ordering::Relaxed, |n| n.checked_sub(1)).is_ok()
+}
+
+fn run_pending_call() -> bool {
+    let func = CALL_FUNC.load(Ordering::SeqCst);
+    if func.is_null() {
+        return false;
+    }
+    let cpu = cpu::current_cpu().value();
+    let targets = &TARGETS[..NUM_TARGETS.load(Ordering::Relaxed)];
+    let Some(target) = targets.iter().find(|t| t.cpu.load(Ordering::Relaxed) == cpu) else {
+        return false;
+    };
+    // Only run the function once, even if this CPU is interrupted again during the call.
+    if !target.pending.swap(false, Ordering::AcqRel) {
+        return false;
+    }
+    // SAFETY: the caller waits until every target that started running the function has finished
+    //         and no handler is inspecting the call anymore before the function is dropped.
+    let func = unsafe { *func };
+    if catch_unwind::catch_unwind_with_arg(|func: &(dyn Fn() + Sync)| func(), func).is_err() {
+        target.panicked.store(true, Ordering::Relaxed);
+    }
+    REMAINING.fetch_sub(1, Ordering::Release);
+    true
+}
+
+/// Runs `func` on the current CPU, catching a panic such that the caller still waits
+/// for the target CPUs to finish running `func` before it is dropped.
+fn run_local(func: &(dyn Fn() + Sync)) -> Result<(), CpuId> {
+    catch_unwind::catch_unwind_with_arg(|func: &(dyn Fn() + Sync)| func(), func)
+        .map_err(|_| cpu::current_cpu())
+}
+
+/// Runs `func` on the given destination CPUs, running `meanwhile` on the current CPU
+/// after the IPIs have been sent but before waiting for the destination CPUs to complete.
+///
+/// If a `timeout` is given, it bounds both the wait for another CPU's call to complete
+/// and the wait for the destination CPUs to start running `func`;
+/// otherwise, this waits as long as necessary.
+fn call(
+    destination: Destination,
+    func: &(dyn Fn() + Sync),
+    meanwhile: impl FnOnce() -> Result<(), CpuId>,
+    timeout: Option<Duration>,
+) -> Result<(), CallError> {
+    if cpu::cpu_count() as usize > MAX_CPUS {
+        return Err(CallError::TooManyCpus);
+    }
+
+    // interrupts must be disabled here, because this IPI sequence must be fully synchronous with other cores,
+    // and we wouldn't want this core to be interrupted while coordinating IPI responses across multiple cores.
+    let _held_ints = hold_interrupts();
+
+    // acquire lock, handling calls from other CPUs while waiting for them to complete.
+    let start = Instant::now();
+    while CALL_LOCK.compare_exchange_weak(false, true, Ordering::AcqRel, Ordering::Relaxed).is_err() {
+        if timeout.is_some_and(|timeout| start.elapsed() >= timeout) {
+            return Err(CallError::Busy);
+        }
+        spin_loop();
+    }
+
+    let current_cpu = cpu::current_cpu();
+    let mut num_targets = 0;
+    let mut add_target = |cpu: CpuId| {
+        let target = &TARGETS[num_targets];
+        target.cpu.store(cpu.value(), Ordering::Relaxed);
+        target.pending.store(true, Ordering::Relaxed);
+        target.panicked.store(false, Ordering::Relaxed);
+        target.timed_out.store(false, Ordering::Relaxed);
+        num_targets += 1;
+    };
+    match destination {
+        Destination::Cpu(cpu) => add_target(cpu),
+        Destination::AllOtherCpus => cpu::cpus()
+            .filter(|&cpu| cpu != current_cpu)
+            .take(MAX_CPUS)
+            .for_each(add_target),
+    }
+    NUM_TARGETS.store(num_targets, Ordering::Relaxed);
+    REMAINING.store(num_targets, Ordering::Relaxed);
+
+    let func_ref: &(dyn Fn() + Sync) = func;
+    if num_targets > 0 {
+        // Publishing the function releases the above stores to the targets.
+        CALL_FUNC.store(&func_ref as *const &(dyn Fn() + Sync) as *mut _, Ordering::SeqCst);
+        send_call_ipi(destination);
+    }
+
+    let local_result = meanwhile();
+
+    // wait for all targets to complete the call
+    let start = Instant::now();
+    while REMAINING.load(Ordering::Acquire) > 0 {
+        if timeout.is_some_and(|timeout| start.elapsed() >= timeout) {
+            // Withdraw the call from targets that haven't started it yet,
+            // but keep waiting for those that are currently running the function.
+            for target in &TARGETS[..num_targets] {
+                if target.pending.swap(false, Ordering::AcqRel) {
+                    target.timed_out.store(true, Ordering::Relaxed);
+                    LATE_IPIS.fetch_add(1, Ordering::Relaxed);
+                    REMAINING.fetch_sub(1, Ordering::Release);
+                }
+            }
+            while REMAINING.load(Ordering::Acquire) > 0 {
+                spin_loop();
+            }
+            break;
+        }
+        spin_loop();
+    }
+
+    // clear the call, then wait for any handler that may still be inspecting it
+    CALL_FUNC.store(ptr::null_mut(), Ordering::SeqCst);
+    while ACTIVE_HANDLERS.load(Ordering::SeqCst) > 0 {
+        spin_loop();
+    }
+
+    let targets = &TARGETS[..num_targets];
+    let failed_cpus = |failed: fn(&Target) -> bool| cpu::cpus().filter(move |cpu| targets.iter().any(|t|
+        t.cpu.load(Ordering::Relaxed) == cpu.value() && failed(t)
+    ));
+    let panicked = |t: &Target| t.panicked.load(Ordering::Relaxed);
+    let timed_out = |t: &Target| t.timed_out.load(Ordering::Relaxed);
+    // Only allocate in these unlikely cases, as the caller may be in the midst of modifying the heap.
+    let result = if local_result.is_err() || targets.iter().any(panicked) {
+        let mut panicked_cpus: Vec<CpuId> = local_result.err().into_iter().collect();
+        panicked_cpus.extend(failed_cpus(panicked));
+        Err(CallError::Panicked(panicked_cpus))
+    } else if targets.iter().any(timed_out) {
+        Err(CallError::TimedOut(failed_cpus(timed_out).collect()))
+    } else {
+        Ok(())
+    };
+
+    // release lock
+    CALL_LOCK.store(false, Ordering::Release);
+    result
+}
+
+#[cfg(target_arch = "x86_64")]
+fn send_call_ipi(destination: Destination) {
+    let my_lapic = apic::get_my_apic()
+        .expect("BUG: cpu_call: couldn't get LocalApic");
+    let destination = match destination {
+        Destination::Cpu(cpu) => apic::LapicIpiDestination::One(cpu.into()),
+        Destination::AllOtherCpus => apic::LapicIpiDestination::AllButMe,
+    };
+    // use NMI, since it will interrupt everyone forcibly and result in the fastest handling
+    my_lapic.write().send_nmi_ipi(destination);
+}
+
+#[cfg(target_arch = "aarch64")]
+fn send_call_ipi(destination: Destination) {
+    interrupts::send_cpu_call_ipi(match destination {
+        Destination::Cpu(cpu) => interrupts::InterruptDestination::SpecificCpu(cpu),
+        Destination::AllOtherCpus => interrupts::InterruptDestination::AllOtherCpus,
+    });
+}
+
+/// Interrupt Handler for cross-CPU function calls on aarch64
+#[cfg(target_arch = "aarch64")]
+extern "C" fn cpu_call_ipi_handler(_exc: &interrupts::ExceptionContext) -> interrupts::EoiBehaviour {
+    handle_call_ipi();
+    interrupts::EoiBehaviour::HandlerDidNotSendEoi
+}
@@ -0,0 +1,486 @@
+//! CPU feature detection and a registry of feature-dependent boot-time hooks.
+//!
+//! On x86_64, this crate enumerates the standard and extended CPUID leaves once
+//! and caches the result, such that [`has_feature()`] queries are cheap.
+//! Theseus assumes that all CPUs in the system are homogeneous,
+//! so the features of the first CPU to query them are used system-wide.
+//!
+//! Subsystems can register hooks via [`register_hook()`] that will be run
+//! on each CPU during boot, but only if the CPU supports the given [`Feature`].
+//! This is used to enable optional hardware features, such as SMEP/SMAP or XSAVE,
+//! that must be explicitly turned on for every CPU.
+//!
+//! On other architectures, no features are currently detected.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use core::fmt;
+use spin::{Mutex, Once};
+
+/// A general-purpose register that CPUID returns feature bits in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Reg { Eax, Ebx, Ecx, Edx }
+
+/// The location of a feature's bit in the output of the CPUID instruction.
+struct FeatureBit {
+    leaf: u32,
+    subleaf: u32,
+    reg: Reg,
+    bit: u8,
+}
+
+macro_rules! features {
+    ($(
+        $(#[$attr:meta])*
+        $variant:ident = ($leaf:expr, $subleaf:expr, $reg:ident, $bit:expr)
+    ),* $(,)?) => {
+        /// A CPU feature that can be queried via [`has_feature()`].
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        #[repr(u8)]
+        #[non_exhaustive]
+        pub enum Feature {
+            $( $(#[$attr])* $variant, )*
+        }
+
+        impl Feature {
+            /// All features known to this crate.
+            pub const ALL: &'static [Feature] = &[ $( Feature::$variant, )* ];
+
+            /// Returns the name of this feature.
+            pub const fn name(self) -> &'static str {
+                match self {
+                    $( Feature::$variant => stringify!($variant), )*
+                }
+            }
+
+            /// Returns the location of this feature's bit in the CPUID output.
+            #[cfg_attr(not(target_arch = "x86_64"), allow(dead_code))]
+            const fn location(self) -> FeatureBit {
+                match self {
+                    $( Feature::$variant => FeatureBit {
+                        leaf: $leaf, subleaf: $subleaf, reg: Reg::$reg, bit: $bit,
+                    }, )*
+                }
+            }
+        }
+    };
+}
+
+features! {
+    // CPUID leaf 0x1, EDX
+    /// x87 floating-point unit on-chip.
+    Fpu          = (0x1, 0, Edx, 0),
+    /// Time Stamp Counter.
+    Tsc          = (0x1, 0, Edx, 4),
+    /// Model-specific registers (RDMSR/WRMSR).
+    Msr          = (0x1, 0, Edx, 5),
+    /// Physical Address Extension.
+    Pae          = (0x1, 0, Edx, 6),
+    /// On-chip APIC.
+    Apic         = (0x1, 0, Edx, 9),
+    /// SYSENTER/SYSEXIT instructions.
+    Sep          = (0x1, 0, Edx, 11),
+    /// Memory Type Range Registers.
+    Mtrr         = (0x1, 0, Edx, 12),
+    /// Page Global Enable.
+    Pge          = (0x1, 0, Edx, 13),
+    /// Page Attribute Table.
+    Pat          = (0x1, 0, Edx, 16),
+    /// CLFLUSH instruction.
+    Clflush      = (0x1, 0, Edx, 19),
+    /// MMX instructions.
+    Mmx          = (0x1, 0, Edx, 23),
+    /// FXSAVE/FXRSTOR instructions.
+    Fxsr         = (0x1, 0, Edx, 24),
+    /// SSE instructions.
+    Sse          = (0x1, 0, Edx, 25),
+    /// SSE2 instructions.
+    Sse2         = (0x1, 0, Edx, 26),
+    /// Multi-threading (hyper-threading) support.
+    Htt          = (0x1, 0, Edx, 28),
+    // CPUID leaf 0x1, ECX
+    /// SSE3 instructions.
+    Sse3         = (0x1, 0, Ecx, 0),
+    /// PCLMULQDQ (carry-less multiplication) instruction.
+    Pclmulqdq    = (0x1, 0, Ecx, 1),
+    /// MONITOR/MWAIT instructions.
+    Monitor      = (0x1, 0, Ecx, 3),
+    /// Virtual Machine Extensions.
+    Vmx          = (0x1, 0, Ecx, 5),
+    /// Supplemental SSE3 instructions.
+    Ssse3        = (0x1, 0, Ecx, 9),
+    /// Fused multiply-add instructions.
+    Fma          = (0x1, 0, Ecx, 12),
+    /// CMPXCHG16B instruction.
+    Cx16         = (0x1, 0, Ecx, 13),
+    /// Process-context identifiers.
+    Pcid         = (0x1, 0, Ecx, 17),
+    /// SSE4.1 instructions.
+    Sse4_1       = (0x1, 0, Ecx, 19),
+    /// SSE4.2 instructions, including hardware CRC32C.
+    Sse4_2       = (0x1, 0, Ecx, 20),
+    /// x2APIC mode of the Local APIC.
+    X2Apic       = (0x1, 0, Ecx, 21),
+    /// MOVBE instruction.
+    Movbe        = (0x1, 0, Ecx, 22),
+    /// POPCNT instruction.
+    Popcnt       = (0x1, 0, Ecx, 23),
+    /// TSC-deadline mode of the Local APIC timer.
+    TscDeadline  = (0x1, 0, Ecx, 24),
+    /// AES-NI instructions.
+    Aes          = (0x1, 0, Ecx, 25),
+    /// XSAVE/XRSTOR instructions and XCR0.
+    Xsave        = (0x1, 0, Ecx, 26),
+    /// The OS has enabled XSAVE (CR4.OSXSAVE is set).
+    OsXsave      = (0x1, 0, Ecx, 27),
+    /// AVX instructions.
+    Avx          = (0x1, 0, Ecx, 28),
+    /// 16-bit floating-point conversion instructions.
+    F16c         = (0x1, 0, Ecx, 29),
+    /// RDRAND instruction.
+    Rdrand       = (0x1, 0, Ecx, 30),
+    /// Running under a hypervisor.
+    Hypervisor   = (0x1, 0, Ecx, 31),
+    // CPUID leaf 0x7, subleaf 0, EBX
+    /// RDFSBASE/WRFSBASE/RDGSBASE/WRGSBASE instructions.
+    FsGsBase     = (0x7, 0, Ebx, 0),
+    /// Bit manipulation instruction set 1.
+    Bmi1         = (0x7, 0, Ebx, 3),
+    /// AVX2 instructions.
+    Avx2         = (0x7, 0, Ebx, 5),
+    /// Supervisor-Mode Execution Prevention.
+    Smep         = (0x7, 0, Ebx, 7),
+    /// Bit manipulation instruction set 2.
+    Bmi2         = (0x7, 0, Ebx, 8),
+    /// Enhanced REP MOVSB/STOSB.
+    Erms         = (0x7, 0, Ebx, 9),
+    /// INVPCID instruction.
+    Invpcid      = (0x7, 0, Ebx, 10),
+    /// AVX-512 foundation instructions.
+    Avx512f      = (0x7, 0, Ebx, 16),
+    /// RDSEED instruction.
+    Rdseed       = (0x7, 0, Ebx, 18),
+    /// ADCX/ADOX instructions.
+    Adx          = (0x7, 0, Ebx, 19),
+    /// Supervisor-Mode Access Prevention.
+    Smap         = (0x7, 0, Ebx, 20),
+    /// CLFLUSHOPT instruction.
+    ClflushOpt   = (0x7, 0, Ebx, 23),
+    /// CLWB instruction.
+    Clwb         = (0x7, 0, Ebx, 24),
+    /// SHA extensions.
+    Sha          = (0x7, 0, Ebx, 29),
+    // CPUID leaf 0x7, subleaf 0, ECX
+    /// User-Mode Instruction Prevention.
+    Umip         = (0x7, 0, Ecx, 2),
+    /// Protection keys for user-mode pages.
+    Pku          = (0x7, 0, Ecx, 3),
+    /// RDPID instruction.
+    Rdpid        = (0x7, 0, Ecx, 22),
+    // CPUID leaf 0xD, subleaf 1, EAX
+    /// XSAVEOPT instruction.
+    XsaveOpt     = (0xD, 1, Eax, 0),
+    /// XSAVEC instruction (compacted XSAVE format).
+    XsaveC       = (0xD, 1, Eax, 1),
+    /// XGETBV with ECX = 1 (reading XINUSE).
+    XgetbvEcx1   = (0xD, 1, Eax, 2),
+    /// XSAVES/XRSTORS instructions and the IA32_XSS MSR.
+    XsaveS       = (0xD, 1, Eax, 3),
+    /// Extended feature disable (XFD) of XSAVE-managed state.
+    Xfd          = (0xD, 1, Eax, 4),
+    // CPUID leaf 0x8000_0001, ECX
+    /// LAHF/SAHF in 64-bit mode.
+    LahfLm       = (0x8000_0001, 0, Ecx, 0),
+    /// LZCNT instruction.
+    Lzcnt        = (0x8000_0001, 0, Ecx, 5),
+    // CPUID leaf 0x8000_0001, EDX
+    /// SYSCALL/SYSRET instructions.
+    Syscall      = (0x8000_0001, 0, Edx, 11),
+    /// No-execute page protection.
+    Nx           = (0x8000_0001, 0, Edx, 20),
+    /// 1-GiB huge pages.
+    Page1Gb      = (0x8000_0001, 0, Edx, 26),
+    /// RDTSCP instruction and the IA32_TSC_AUX MSR.
+    Rdtscp       = (0x8000_0001, 0, Edx, 27),
+    /// 64-bit long mode.
+    LongMode     = (0x8000_0001, 0, Edx, 29),
+    // CPUID leaf 0x8000_0007, EDX
+    /// The TSC runs at a constant rate across all power states.
+    InvariantTsc = (0x8000_0007, 0, Edx, 8),
+}
+
+/// A set of [`Feature`]s, stored as a bitmask.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct FeatureSet(u128);
+
+// Ensure that every `Feature` fits into a `FeatureSet` bitmask.
+const _: () = assert!(Feature::ALL.len() <= u128::BITS as usize);
+
+impl FeatureSet {
+    /// Returns an empty set of features.
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    /// Returns `true` if this set contains the given `feature`.
+    pub const fn contains(&self, feature: Feature) -> bool {
+        self.0 & (1 << feature as u8) != 0
+    }
+
+    /// Adds the given `feature` to this set.
+    pub fn insert(&mut self, feature: Feature) {
+        self.0 |= 1 << feature as u8;
+    }
+
+    /// Returns the raw bitmask of this set, in which bit `n`
+    /// corresponds to the `Feature` with discriminant `n`.
+    pub const fn bits(&self) -> u128 {
+        self.0
+    }
+
+    /// Returns an iterator over all features in this set.
+    pub fn iter(&self) -> impl Iterator<Item = Feature> + '_ {
+        Feature::ALL.iter().copied().filter(|f| self.contains(*f))
+    }
+}
+
+impl fmt::Debug for FeatureSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter().map(Feature::name)).finish()
+    }
+}
+
+/// Basic identifying information about the CPU, obtained from CPUID.
+#[derive(Clone, Debug)]
+pub struct CpuInfo {
+    /// The vendor ID string, e.g., "GenuineIntel" or "AuthenticAMD".
+    pub vendor: [u8; 12],
+    /// The processor brand string, if supported.
+    pub brand: [u8; 48],
+    /// The highest supported standard CPUID leaf.
+    pub max_leaf: u32,
+    /// The highest supported extended CPUID leaf.
+    pub max_extended_leaf: u32,
+    /// The display family of the processor.
+    pub family: u32,
+    /// The display model of the processor.
+    pub model: u32,
+    /// The stepping ID of the processor.
+    pub stepping: u32,
+    /// The bitmask of XSAVE-managed state components supported by the CPU,
+    /// i.e., those that may be enabled in XCR0 (from CPUID leaf 0xD).
+    pub xsave_supported_components: u64,
+    /// The size in bytes of the XSAVE area needed for all
+    /// supported state components (from CPUID leaf 0xD).
+    pub xsave_max_size: u32,
+    /// The set of features supported by the CPU.
+    pub features: FeatureSet,
+}
+
+impl Default for CpuInfo {
+    fn default() -> Self {
+        Self {
+            vendor: [0; 12],
+            brand: [0; 48],
+            max_leaf: 0,
+            max_extended_leaf: 0,
+            family: 0,
+            model: 0,
+            stepping: 0,
+            xsave_supported_components: 0,
+            xsave_max_size: 0,
+            features: FeatureSet::empty(),
+        }
+    }
+}
+
+impl CpuInfo {
+    /// Returns the vendor ID string.
+    pub fn vendor_str(&self) -> &str {
+        core::str::from_utf8(&self.vendor).unwrap_or("<invalid>")
+    }
+
+    /// Returns the processor brand string, with surrounding whitespace and null bytes trimmed.
+    pub fn brand_str(&self) -> &str {
+        core::str::from_utf8(&self.brand)
+            .unwrap_or("<invalid>")
+            .trim_matches(|c: char| c == '\0' || c.is_whitespace())
+    }
+}
+
+/// The cached result of CPUID enumeration.
+static CPU_INFO: Once<CpuInfo> = Once::new();
+
+/// Returns information about the CPU, enumerating it upon the first invocation.
+pub fn cpu_info() -> &'static CpuInfo {
+    CPU_INFO.call_once(enumerate)
+}
+
+/// Returns the (cached) set of all features supported by the CPU.
+pub fn features() -> FeatureSet {
+    cpu_info().features
+}
+
+/// Returns `true` if the CPU supports the given `feature`.
+pub fn has_feature(feature: Feature) -> bool {
+    cpu_info().features.contains(feature)
+}
+
+/// Re-reads the given `feature` directly from the current CPU,
+/// bypassing the cache.
+///
+/// This is only useful for features whose value may change at runtime,
+/// such as [`Feature::OsXsave`], which reflects whether CR4.OSXSAVE is set.
+pub fn query_uncached(feature: Feature) -> bool {
+    #[cfg(target_arch = "x86_64")] {
+        let info = cpu_info();
+        x86::has_feature(feature, info.max_leaf, info.max_extended_leaf)
+    }
+    #[cfg(not(target_arch = "x86_64"))] {
+        let _ = feature;
+        false
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+fn enumerate() -> CpuInfo {
+    x86::enumerate()
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn enumerate() -> CpuInfo {
+    CpuInfo::default()
+}
+
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use super::*;
+    use core::arch::x86_64::{CpuidResult, __cpuid_count};
+
+    fn cpuid(leaf: u32, subleaf: u32) -> CpuidResult {
+        // SAFE: the CPUID instruction is always available on x86_64.
+        unsafe { __cpuid_count(leaf, subleaf) }
+    }
+
+    /// Returns `true` if the given `feature` bit is set on the current CPU,
+    /// which supports standard leaves up to `max_leaf`
+    /// and extended leaves up to `max_extended_leaf`.
+    pub(crate) fn has_feature(feature: Feature, max_leaf: u32, max_extended_leaf: u32) -> bool {
+        let loc = feature.location();
+        let max = if loc.leaf >= 0x8000_0000 { max_extended_leaf } else { max_leaf };
+        if loc.leaf > max {
+            return false;
+        }
+        let res = cpuid(loc.leaf, loc.subleaf);
+        let reg = match loc.reg {
+            Reg::Eax => res.eax,
+            Reg::Ebx => res.ebx,
+            Reg::Ecx => res.ecx,
+            Reg::Edx => res.edx,
+        };
+        reg & (1 << loc.bit) != 0
+    }
+
+    pub(crate) fn enumerate() -> CpuInfo {
+        let mut info = CpuInfo::default();
+
+        let leaf0 = cpuid(0, 0);
+        info.max_leaf = leaf0.eax;
+        info.vendor[0..4].copy_from_slice(&leaf0.ebx.to_le_bytes());
+        info.vendor[4..8].copy_from_slice(&leaf0.edx.to_le_bytes());
+        info.vendor[8..12].copy_from_slice(&leaf0.ecx.to_le_bytes());
+        info.max_extended_leaf = cpuid(0x8000_0000, 0).eax;
+
+        if info.max_leaf >= 1 {
+            let eax = cpuid(1, 0).eax;
+            let base_family = (eax >> 8) & 0xF;
+            let base_model = (eax >> 4) & 0xF;
+            info.stepping = eax & 0xF;
+            info.family = if base_family == 0xF {
+                base_family + ((eax >> 20) & 0xFF)
+            } else {
+                base_family
+            };
+            info.model = if base_family == 0x6 || base_family == 0xF {
+                (((eax >> 16) & 0xF) << 4) | base_model
+            } else {
+                base_model
+            };
+        }
+
+        if info.max_extended_leaf >= 0x8000_0004 {
+            for (i, leaf) in (0x8000_0002 ..= 0x8000_0004).enumerate() {
+                let res = cpuid(leaf, 0);
+                for (j, reg) in [res.eax, res.ebx, res.ecx, res.edx].into_iter().enumerate() {
+                    let offset = i * 16 + j * 4;
+                    info.brand[offset .. offset + 4].copy_from_slice(&reg.to_le_bytes());
+                }
+            }
+        }
+
+        for feature in Feature::ALL {
+            if has_feature(*feature, info.max_leaf, info.max_extended_leaf) {
+                info.features.insert(*feature);
+            }
+        }
+
+        if info.features.contains(Feature::Xsave) && info.max_leaf >= 0xD {
+            let leaf_d = cpuid(0xD, 0);
+            info.xsave_supported_components = ((leaf_d.edx as u64) << 32) | leaf_d.eax as u64;
+            info.xsave_max_size = leaf_d.ecx;
+        }
+
+        info
+    }
+}
+
+
+/// A function that is run on each CPU during boot if that CPU supports a given [`Feature`].
+pub type HookFn = fn() -> Result<(), &'static str>;
+
+/// A boot-time hook registered via [`register_hook()`].
+#[derive(Clone, Copy)]
+struct FeatureHook {
+    name: &'static str,
+    feature: Feature,
+    func: HookFn,
+}
+
+/// The list of registered boot-time hooks, in registration order.
+static HOOKS: Mutex<Vec<FeatureHook>> = Mutex::new(Vec::new());
+
+/// Registers a hook named `name` that will be run on each CPU
+/// by [`run_hooks()`] if that CPU supports the given `feature`.
+///
+/// Hooks are run in the order in which they were registered.
+/// A hook must be registered before the CPUs it should run on have booted;
+/// it will not be retroactively run on CPUs that already ran their hooks.
+pub fn register_hook(name: &'static str, feature: Feature, func: HookFn) {
+    HOOKS.lock().push(FeatureHook { name, feature, func });
+}
+
+/// Runs all registered hooks whose feature is supported, on the current CPU.
+///
+/// This should be invoked once on each CPU during its boot procedure.
+/// Hooks that fail are logged but do not prevent other hooks from running.
+///
+/// Returns the number of hooks that ran successfully.
+pub fn run_hooks() -> usize {
+    // Clone the list such that hooks can themselves register new hooks.
+    let hooks = HOOKS.lock().clone();
+    let mut succeeded = 0;
+    for hook in hooks {
+        if !has_feature(hook.feature) {
+            log::debug!("Skipping CPU feature hook {:?}, {} is unsupported", hook.name, hook.feature.name());
+            continue;
+        }
+        match (hook.func)() {
+            Ok(()) => succeeded += 1,
+            Err(e) => log::error!("CPU feature hook {:?} ({}) failed: {}", hook.name, hook.feature.name(), e),
+        }
+    }
+    succeeded
+}
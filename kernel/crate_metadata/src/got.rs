@@ -0,0 +1,65 @@
+//! A global offset table (GOT) that is shared by all loaded crates.
+//!
+//! Code that isn't compiled with the large code model may access a symbol indirectly
+//! via a GOT entry that holds the symbol's address, e.g., `mov foo@GOTPCREL(%rip), %rax`,
+//! or, for the initial-exec TLS model, via a GOT entry that holds the symbol's offset from the thread pointer.
+//!
+//! Instead of giving each crate its own GOT, entries are allocated on demand from this single table
+//! and shared by all relocations that need the same value.
+//! Since an entry's value never changes, entries are never freed,
+//! and they remain valid even after the crate that first needed them has been unloaded.
+
+use alloc::{collections::BTreeMap, vec::Vec};
+use core::mem::size_of;
+use memory::{MappedPages, VirtualAddress, PAGE_SIZE};
+use spin::Mutex;
+use crate::{DATA_BSS_SECTION_FLAGS, RODATA_SECTION_FLAGS};
+
+const ENTRIES_PER_PAGE: usize = PAGE_SIZE / size_of::<u64>();
+
+static GOT: Mutex<GlobalOffsetTable> = Mutex::new(GlobalOffsetTable {
+    pages: Vec::new(),
+    entries_in_last_page: ENTRIES_PER_PAGE,
+    entries: BTreeMap::new(),
+});
+
+struct GlobalOffsetTable {
+    /// The read-only pages that hold the entries, which are filled in order.
+    pages: Vec<MappedPages>,
+    /// The number of entries used in the last page of `pages`.
+    entries_in_last_page: usize,
+    /// The value held in each entry, mapped to that entry's address.
+    entries: BTreeMap<u64, VirtualAddress>,
+}
+
+/// Returns the address of the GOT entry that holds the given `value`,
+/// allocating and initializing a new entry if none exists yet.
+///
+/// This acquires the lock on the kernel's `MemoryManagementInfo` if a new entry is needed,
+/// so the caller must not hold that lock.
+pub(crate) fn entry_for(value: u64) -> Result<VirtualAddress, &'static str> {
+    let mut got = GOT.lock();
+    if let Some(&entry) = got.entries.get(&value) {
+        return Ok(entry);
+    }
+
+    if got.entries_in_last_page == ENTRIES_PER_PAGE {
+        got.pages.push(memory::create_mapping(PAGE_SIZE, DATA_BSS_SECTION_FLAGS)?);
+        got.entries_in_last_page = 0;
+    }
+    let index = got.entries_in_last_page;
+    let kernel_mmi_ref = memory::get_kernel_mmi_ref().ok_or("BUG: couldn't get the kernel MMI to add a GOT entry")?;
+    let page = got.pages.last_mut().ok_or("BUG: GOT had no pages")?;
+
+    // Only a new page is already writable; existing pages must be temporarily remapped as writable.
+    if index != 0 {
+        page.remap(&mut kernel_mmi_ref.lock().page_table, DATA_BSS_SECTION_FLAGS)?;
+    }
+    *page.as_type_mut::<u64>(index * size_of::<u64>())? = value;
+    page.remap(&mut kernel_mmi_ref.lock().page_table, RODATA_SECTION_FLAGS)?;
+
+    let entry = page.start_address() + index * size_of::<u64>();
+    got.entries_in_last_page += 1;
+    got.entries.insert(value, entry);
+    Ok(entry)
+}
@@ -45,7 +45,7 @@
 
 extern crate alloc;
 
-use core::{fmt, mem::size_of, ops::Range};
+use core::{fmt, ops::Range};
 use log::{error, debug, trace};
 use spin::{Mutex, RwLock, Once};
 use alloc::{
@@ -62,8 +62,16 @@ use memory::{MappedPages, VirtualAddress, PteFlags};
 use cow_arc::{CowArc, CowWeak};
 use fs_node::{FileRef, WeakFileRef};
 use hashbrown::HashMap;
-use goblin::elf::reloc::*;
 
+mod got;
+
+#[cfg_attr(target_arch = "x86_64", path = "relocation/x86_64.rs")]
+#[cfg_attr(target_arch = "aarch64", path = "relocation/aarch64.rs")]
+#[cfg_attr(target_arch = "riscv64", path = "relocation/riscv64.rs")]
+mod arch;
+pub mod unwind_info;
+
+pub use arch::ELF_MACHINE;
 pub use str_ref::StrRef;
 pub use crate_metadata_serde::{
     SectionType,
@@ -254,6 +262,17 @@ pub struct LoadedCrate {
     /// The `Shndx` values in this set are the section index (shndx) numbers, 
     /// which can be used as the key to look up the actual `LoadedSection` in the `sections` list above.
     pub data_sections: BTreeSet<Shndx>,
+    /// The `.eh_frame` section of this crate, if it has one,
+    /// which is registered in the [`unwind_info`] registry once this crate is fully loaded.
+    /// This `Shndx` can be used as the key to look up the actual `LoadedSection` in the `sections` list above.
+    pub eh_frame: Option<Shndx>,
+    /// The `.init_array` sections of this crate, in the order in which their constructors must be invoked,
+    /// which happens once this crate has been added to its `CrateNamespace`.
+    /// These `Shndx`s can be used as the key to look up the actual `LoadedSection`s in the `sections` list above.
+    pub init_array: Vec<Shndx>,
+    /// The `.fini_array` sections of this crate, in the order in which their destructors must be invoked,
+    /// which happens right before this crate is unloaded from its `CrateNamespace`.
+    pub fini_array: Vec<Shndx>,
     /// The set of symbols that this crate's global symbols are reexported under,
     /// i.e., they have been added to the enclosing `CrateNamespace`'s symbol map under these names.
     /// 
@@ -286,6 +305,17 @@ impl fmt::Debug for LoadedCrate {
 impl Drop for LoadedCrate {
     fn drop(&mut self) {
         trace!("### Dropped LoadedCrate: {}", self.crate_name);
+        unwind_info::deregister(self);
+
+        // Drop this crate's sections first, as they hold references to its `MappedPages`.
+        // If no other references to those `MappedPages` remain, recycle them for future crates.
+        self.sections.clear();
+        let pages = [self.text_pages.take(), self.rodata_pages.take(), self.data_pages.take()];
+        for (mp, _) in IntoIterator::into_iter(pages).flatten() {
+            if let Ok(mp) = Arc::try_unwrap(mp) {
+                mapped_pages_pool::recycle(mp.into_inner());
+            }
+        }
     }
 }
 
@@ -306,6 +336,27 @@ impl LoadedCrate {
             .filter_map(move |shndx| self.sections.get(shndx))
     }
 
+    /// Returns this crate's `.eh_frame` section, if it has one.
+    pub fn eh_frame_section(&self) -> Option<&StrongSectionRef> {
+        self.eh_frame.and_then(|shndx| self.sections.get(&shndx))
+    }
+
+    /// A convenience function to iterate over this crate's `.init_array` sections,
+    /// in the order in which their constructors must be invoked.
+    pub fn init_array_sections_iter(&self) -> impl Iterator<Item = &StrongSectionRef> {
+        self.init_array
+            .iter()
+            .filter_map(move |shndx| self.sections.get(shndx))
+    }
+
+    /// A convenience function to iterate over this crate's `.fini_array` sections,
+    /// in the order in which their destructors must be invoked.
+    pub fn fini_array_sections_iter(&self) -> impl Iterator<Item = &StrongSectionRef> {
+        self.fini_array
+            .iter()
+            .filter_map(move |shndx| self.sections.get(shndx))
+    }
+
     /// A convenience function to iterate over only the global (public) sections in this crate.
     pub fn global_sections_iter(&self) -> impl Iterator<Item = &StrongSectionRef> {
         self.global_sections
@@ -313,6 +364,29 @@ impl LoadedCrate {
             .filter_map(move |shndx| self.sections.get(shndx))
     }
 
+    /// A convenience function to iterate over only the sections of the given type in this crate.
+    pub fn sections_of_type(&self, typ: SectionType) -> impl Iterator<Item = &StrongSectionRef> {
+        self.sections
+            .values()
+            .filter(move |sec| sec.typ == typ)
+    }
+
+    /// Returns the total size in bytes of all sections of the given type in this crate.
+    ///
+    /// Merged sections, e.g., `.text`, overlap the individual symbol sections they contain,
+    /// so they are not counted if the crate also has individual sections of that type.
+    pub fn size_of_sections(&self, typ: SectionType) -> usize {
+        let (merged, individual) = self.sections_of_type(typ)
+            .fold((0, 0), |(merged, individual), sec| {
+                if sec.name.as_str() == typ.name() {
+                    (merged + sec.size, individual)
+                } else {
+                    (merged, individual + sec.size)
+                }
+            });
+        if individual == 0 { merged } else { individual }
+    }
+
     /// Returns the **first** `LoadedSection` that matches the given predicate,
     /// i.e., for which the `predicate` closure returns `true`.
     /// 
@@ -342,14 +416,17 @@ impl LoadedCrate {
         format!("{}::", self.crate_name_without_hash())
     }
 
-    /// Currently may contain duplicates!
+    /// Returns the set of crates that depend on this crate, i.e.,
+    /// the crates containing a section that depends on one of this crate's sections.
+    /// Only includes direct dependents "one hop" away,
+    /// not recursive dependents "multiple hops" away.
+    ///
+    /// Each crate is included only once.
     pub fn crates_dependent_on_me(&self) -> Vec<WeakCrateRef> {
-        let mut results: Vec<WeakCrateRef> = Vec::new();
+        let mut results = Vec::new();
         for sec in self.sections.values() {
-            for weak_dep in &sec.inner.read().sections_dependent_on_me {
-                if let Some(dep_sec) = weak_dep.section.upgrade() {
-                    results.push(dep_sec.parent_crate.clone());
-                }
+            for dep_sec in sec.sections_dependent_on_me() {
+                push_unique_crate(&mut results, &dep_sec.parent_crate);
             }
         }
         results
@@ -359,14 +436,13 @@ impl LoadedCrate {
     /// Returns the set of crates that this crate depends on. 
     /// Only includes direct dependencies "one hop" away, 
     /// not recursive dependencies "multiples hops" away.
-    /// 
-    /// Currently, the list may include duplicates.
-    /// The caller is responsible for filtering out duplicates when using the list.
+    ///
+    /// Each crate is included only once.
     pub fn crates_i_depend_on(&self) -> Vec<WeakCrateRef> {
-        let mut results: Vec<WeakCrateRef> = Vec::new();
+        let mut results = Vec::new();
         for sec in self.sections.values() {
             for strong_dep in &sec.inner.read().sections_i_depend_on {
-                results.push(strong_dep.section.parent_crate.clone());
+                push_unique_crate(&mut results, &strong_dep.section.parent_crate);
             }
         }
         results
@@ -443,6 +519,9 @@ impl LoadedCrate {
             global_sections:         self.global_sections.clone(),
             tls_sections:            self.tls_sections.clone(),
             data_sections:           self.data_sections.clone(),
+            eh_frame:                self.eh_frame,
+            init_array:              self.init_array.clone(),
+            fini_array:              self.fini_array.clone(),
             reexported_symbols:      self.reexported_symbols.clone(),
         });
         let new_crate_weak_ref = CowArc::downgrade(&new_crate);
@@ -473,7 +552,9 @@ impl LoadedCrate {
                     new_rodata_pages_locked.as_ref().and_then(|rp| rp.address_at_offset(new_sec_mapped_pages_offset)),
                 ),
                 SectionType::Data
-                | SectionType::Bss => (
+                | SectionType::Bss
+                | SectionType::InitArray
+                | SectionType::FiniArray => (
                     new_data_pages_ref.clone().ok_or("BUG: missing data pages in newly-copied crate")?,
                     new_data_pages_locked.as_ref().and_then(|dp| dp.address_at_offset(new_sec_mapped_pages_offset)),
                 ),
@@ -492,7 +573,7 @@ impl LoadedCrate {
                 old_sec_inner.sections_i_depend_on.clone(),   // dependencies are the same, but relocations need to be re-written
                 Vec::new(),                             // no sections can possibly depend on this one, since we just created it
                 old_sec_inner.internal_dependencies.clone()   // internal dependencies are the same, but relocations need to be re-written
-            ));
+            ).with_weak_binding(old_sec.weak)); // weakness is the same
 
             new_sections.insert(*shndx, new_sec);
         }
@@ -516,7 +597,9 @@ impl LoadedCrate {
                     .as_mut()
                     .ok_or("BUG: missing rodata pages in newly-copied crate")?,
                 SectionType::Data
-                | SectionType::Bss => new_data_pages_locked
+                | SectionType::Bss
+                | SectionType::InitArray
+                | SectionType::FiniArray => new_data_pages_locked
                     .as_mut()
                     .ok_or("BUG: missing data pages in newly-copied crate")?,
             };
@@ -615,6 +698,8 @@ pub fn section_name_str_ref(section_type: &SectionType) -> StrRef {
     static CLS              : Once<StrRef> = Once::new();
     static GCC_EXCEPT_TABLE : Once<StrRef> = Once::new();
     static EH_FRAME         : Once<StrRef> = Once::new();
+    static INIT_ARRAY       : Once<StrRef> = Once::new();
+    static FINI_ARRAY       : Once<StrRef> = Once::new();
 
     let instance = match section_type {
         SectionType::Text           => &TEXT,
@@ -626,11 +711,21 @@ pub fn section_name_str_ref(section_type: &SectionType) -> StrRef {
         SectionType::Cls            => &CLS,
         SectionType::GccExceptTable => &GCC_EXCEPT_TABLE,
         SectionType::EhFrame        => &EH_FRAME,
+        SectionType::InitArray      => &INIT_ARRAY,
+        SectionType::FiniArray      => &FINI_ARRAY,
     };
     instance.call_once(|| StrRef::from(section_type.name())).clone()
 }
 
 
+/// Adds `crate_ref` to `crates`, unless a reference to the same crate is already included.
+fn push_unique_crate(crates: &mut Vec<WeakCrateRef>, crate_ref: &WeakCrateRef) {
+    if !crates.iter().any(|c| c.ptr_eq(crate_ref)) {
+        crates.push(crate_ref.clone());
+    }
+}
+
+
 /// The parts of a `LoadedSection` that may be mutable, i.e., 
 /// only the parts that could change after a section is initially loaded and linked.
 #[derive(Default)]
@@ -672,6 +767,9 @@ pub struct LoadedSection {
     pub typ: SectionType,
     /// Whether or not this section's symbol was exported globally (is public)
     pub global: bool,
+    /// Whether this section's symbol has weak binding, i.e., it may be overridden
+    /// by a non-weak (strong) symbol with the same name.
+    pub weak: bool,
     /// The `MappedPages` that cover this section.
     pub mapped_pages: Arc<Mutex<MappedPages>>, 
     /// The offset into the `mapped_pages` where this section starts
@@ -746,6 +844,7 @@ impl LoadedSection {
             virt_addr,
             size,
             global,
+            weak: false,
             parent_crate,
             inner: RwLock::new(LoadedSectionInner {
                 sections_i_depend_on,
@@ -756,6 +855,21 @@ impl LoadedSection {
         }
     }
 
+    /// Sets whether this section's symbol has weak binding, which is `false` by default.
+    pub fn with_weak_binding(mut self, weak: bool) -> LoadedSection {
+        self.weak = weak;
+        self
+    }
+
+    /// Returns the range of virtual addresses covered by this section,
+    /// or `None` for TLS and CLS sections, whose `virt_addr` is an offset rather than an address.
+    pub fn address_range(&self) -> Option<Range<VirtualAddress>> {
+        if self.typ.is_tls() || self.typ == SectionType::Cls {
+            return None;
+        }
+        Some(self.virt_addr .. self.virt_addr + self.size)
+    }
+
     /// Returns the substring of this section's name that excludes the trailing hash. 
     /// 
     /// See the identical associated function [`section_name_without_hash()`](#fn.section_name_without_hash.html) for more. 
@@ -776,6 +890,44 @@ impl LoadedSection {
             .unwrap_or(sec_name)
     }
 
+    /// Returns the trailing hash of the given section's name, excluding the hash delimiter "`::h`",
+    /// or `None` if the section name has no hash.
+    ///
+    /// # Examples
+    /// name: "`keyboard_new::init::h832430094f98e56b`", return value: `Some("832430094f98e56b")`
+    /// name: "`start_me`", return value: `None`
+    pub fn section_name_hash(sec_name: &str) -> Option<&str> {
+        sec_name.rfind(SECTION_HASH_DELIMITER)
+            .and_then(|start| sec_name.get((start + SECTION_HASH_DELIMITER.len()) ..))
+            .filter(|hash| !hash.is_empty())
+    }
+
+    /// Returns the sections in foreign crates that this section depends on,
+    /// i.e., the targets of its [`StrongDependency`]s, each included only once.
+    pub fn sections_i_depend_on(&self) -> Vec<StrongSectionRef> {
+        let mut results: Vec<StrongSectionRef> = Vec::new();
+        for strong_dep in &self.inner.read().sections_i_depend_on {
+            if !results.iter().any(|sec| Arc::ptr_eq(sec, &strong_dep.section)) {
+                results.push(Arc::clone(&strong_dep.section));
+            }
+        }
+        results
+    }
+
+    /// Returns the sections in foreign crates that depend on this section,
+    /// i.e., the sources of its [`WeakDependent`]s that haven't been dropped, each included only once.
+    pub fn sections_dependent_on_me(&self) -> Vec<StrongSectionRef> {
+        let mut results: Vec<StrongSectionRef> = Vec::new();
+        for weak_dep in &self.inner.read().sections_dependent_on_me {
+            if let Some(sec) = weak_dep.section.upgrade() {
+                if !results.iter().any(|s| Arc::ptr_eq(s, &sec)) {
+                    results.push(sec);
+                }
+            }
+        }
+        results
+    }
+
     /// Returns the index of the first `WeakDependent` object in this `LoadedSection`'s `sections_dependent_on_me` list
     /// in which the section matches the given `matching_section` 
     pub fn find_weak_dependent(&self, matching_section: &StrongSectionRef) -> Option<usize> {
@@ -998,7 +1150,35 @@ impl RelocationEntry {
     /// does NOT depend on the target section's address itself in any way 
     /// (i.e., it only depends on the source section)
     pub fn is_absolute(&self) -> bool {
-        matches!(self.typ, R_X86_64_32 | R_X86_64_64)
+        arch::is_absolute(self.typ)
+    }
+
+    /// Returns true if the relocation type has no effect on a loaded crate,
+    /// e.g., a hint for linker relaxation, so it can be skipped without resolving its symbol.
+    pub fn is_ignored(&self) -> bool {
+        arch::is_ignored(self.typ)
+    }
+}
+
+#[cfg(target_arch = "riscv64")]
+impl RelocationEntry {
+    /// Returns true if this relocation computes the high part of a PC-relative value,
+    /// e.g., `R_RISCV_PCREL_HI20`, which other relocations may refer to for the low part.
+    pub fn is_high_part(&self) -> bool {
+        arch::is_high_part(self.typ)
+    }
+
+    /// Returns true if this relocation computes the low part of a PC-relative value,
+    /// e.g., `R_RISCV_PCREL_LO12_I`, and thus refers to a label at its high-part relocation
+    /// instead of the symbol that it actually depends on.
+    pub fn is_paired_low_part(&self) -> bool {
+        arch::is_paired_low_part(self.typ)
+    }
+
+    /// Combines this low-part relocation with the `high_part` relocation in the same target section
+    /// that it refers to, producing a relocation that depends on the high part's symbol directly.
+    pub fn pair_with(&self, high_part: &RelocationEntry) -> Result<RelocationEntry, &'static str> {
+        arch::pair_with(*self, *high_part)
     }
 }
 
@@ -1039,6 +1219,9 @@ impl InternalDependency {
 ///   <https://docs.rs/goblin/0.6.0/goblin/elf/reloc/index.html>.
 /// * aarch64-specific relocation docs here:
 ///   <https://github.com/ARM-software/abi-aa/blob/main/aaelf64/aaelf64.rst#relocation-types>.
+/// * riscv64-specific relocation docs here:
+///   <https://github.com/riscv-non-isa/riscv-elf-psabi-doc/blob/master/riscv-elf.adoc#relocations>.
+/// * The calculations for each architecture are implemented in the `relocation/<arch>.rs` modules.
 pub fn write_relocation(
     relocation_entry: RelocationEntry,
     target_sec_slice: &mut [u8],
@@ -1048,7 +1231,7 @@ pub fn write_relocation(
 ) -> Result<(), &'static str> {
     // Calculate exactly where we should write the relocation data to.
     let target_sec_offset = target_sec_offset + relocation_entry.offset;
-    write_relocation_arch(
+    arch::write_relocation(
         relocation_entry,
         target_sec_slice,
         target_sec_offset,
@@ -1068,363 +1251,3 @@ fn unsupported(relocation_type: u32) -> Result<(), &'static str> {
         --> Compile with 'relocation-model=static', 'code-model=large', and 'tls-model=local-exec'",
     )
 }
-
-/// Implement x86_64-specific relocation calculations.
-#[cfg(target_arch = "x86_64")]
-#[inline(always)]
-fn write_relocation_arch(
-    relocation_entry: RelocationEntry,
-    target_sec_slice: &mut [u8],
-    target_sec_offset: usize,
-    source_sec_vaddr: VirtualAddress,
-    verbose_log: bool
-) -> Result<(), &'static str> {
-
-    match relocation_entry.typ {
-        R_X86_64_32 => {
-            let target_range = target_sec_offset .. (target_sec_offset + size_of::<u32>());
-            let target_ref = &mut target_sec_slice[target_range];
-            let source_val = source_sec_vaddr.value().wrapping_add(relocation_entry.addend) as u32;
-            if verbose_log { trace!("                    target_ptr: {:p}, source_val: {:#X} (from source_sec_vaddr {:#X})", target_ref.as_ptr(), source_val, source_sec_vaddr); }
-            target_ref.copy_from_slice(&source_val.to_ne_bytes());
-        }
-        R_X86_64_64 => {
-            let target_range = target_sec_offset .. (target_sec_offset + size_of::<u64>());
-            let target_ref = &mut target_sec_slice[target_range];
-            let source_val = source_sec_vaddr.value().wrapping_add(relocation_entry.addend) as u64;
-            if verbose_log { trace!("                    target_ptr: {:p}, source_val: {:#X} (from source_sec_vaddr {:#X})", target_ref.as_ptr(), source_val, source_sec_vaddr); }
-            target_ref.copy_from_slice(&source_val.to_ne_bytes());
-        }
-        R_X86_64_PC32
-        | R_X86_64_PLT32 => {
-            let target_range = target_sec_offset .. (target_sec_offset + size_of::<u32>());
-            let target_ref = &mut target_sec_slice[target_range];
-            let source_val = source_sec_vaddr.value().wrapping_add(relocation_entry.addend).wrapping_sub(target_ref.as_ptr() as usize) as u32;
-            if verbose_log { trace!("                    target_ptr: {:p}, source_val: {:#X} (from source_sec_vaddr {:#X})", target_ref.as_ptr(), source_val, source_sec_vaddr); }
-            target_ref.copy_from_slice(&source_val.to_ne_bytes());
-        }
-        R_X86_64_PC64 => {
-            let target_range = target_sec_offset .. (target_sec_offset + size_of::<u64>());
-            let target_ref = &mut target_sec_slice[target_range];
-            let source_val = source_sec_vaddr.value().wrapping_add(relocation_entry.addend).wrapping_sub(target_ref.as_ptr() as usize);
-            if verbose_log { trace!("                    target_ptr: {:p}, source_val: {:#X} (from source_sec_vaddr {:#X})", target_ref.as_ptr(), source_val, source_sec_vaddr); }
-            target_ref.copy_from_slice(&source_val.to_ne_bytes());
-        }
-        R_X86_64_TPOFF32 => {
-            use core::convert::TryFrom;
-            let target_range = target_sec_offset .. (target_sec_offset + size_of::<i32>());
-            let target_ref = &mut target_sec_slice[target_range];
-            // Here we treat the `source_sec_vaddr` value as a signed value 
-            // by casting its bit value directly, i.e., `usize as isize`.
-            let offset_val = source_sec_vaddr.value() as isize;
-            // Now we must check that the signed `offset_val` fits in `i32`
-            let source_val = i32::try_from(offset_val)
-                .map_err(|_| "BUG: TLS relocation (R_X86_64_TPOFF32) source section value (TLS offset) cannot fit in a `i32`")?;
-            if verbose_log { trace!("                    target_ptr: {:p}, source_val: {:#X} (from source_sec_vaddr {:#X})", target_ref.as_ptr(), source_val, source_sec_vaddr); }
-            target_ref.copy_from_slice(&source_val.to_ne_bytes());
-        }
-        // R_X86_64_GOTTPOFF => {
-        //     // 32-bit signed PC-relative offset to the GOT entry for the IE (Initial Exec(utable) TLS model))
-        //     debug!("R_X86_64_GOTTPOFF: {:#X?}", relocation_entry);
-        //     debug!("R_X86_64_GOTTPOFF: target: {:#X}, source: {:#X}", target_sec_slice.as_ptr() as usize + target_sec_offset, source_sec_vaddr);
-        //     unimplemented!()
-        // }
-        // R_X86_64_GOTPCREL => { 
-        //     unimplemented!(); // if we stop using the large code model, we need to create a Global Offset Table
-        // }
-        other => return unsupported(other),
-    }
-
-    Ok(())
-}
-
-
-/// Implement aarch64-specific relocation calculations.
-#[cfg(target_arch = "aarch64")]
-#[inline(always)]
-fn write_relocation_arch(
-    relocation_entry: RelocationEntry,
-    target_sec_slice: &mut [u8],
-    target_sec_offset: usize,
-    source_sec_vaddr: VirtualAddress,
-    verbose_log: bool
-) -> Result<(), &'static str> {
-    use core::convert::TryInto;
-
-    const TWO: isize = 2;
-    const RANGE_16_BIT_SIGNED: Range<isize> = -TWO.pow(15) .. TWO.pow(16);
-    const RANGE_32_BIT_SIGNED: Range<isize> = -TWO.pow(31) .. TWO.pow(32);
-    const RANGE_12_BIT_UNSIGNED: Range<isize> = 0 .. TWO.pow(12);
-    const RANGE_16_BIT_UNSIGNED: Range<isize> = 0 .. TWO.pow(16);
-    const RANGE_24_BIT_UNSIGNED: Range<isize> = 0 .. TWO.pow(24);
-    const RANGE_32_BIT_UNSIGNED: Range<isize> = 0 .. TWO.pow(32);
-    const RANGE_48_BIT_UNSIGNED: Range<isize> = 0 .. TWO.pow(48);
-
-    #[allow(clippy::needless_late_init)]
-    let overflow_check: Option<(usize, Range<isize>)>;
-    match relocation_entry.typ {
-        R_AARCH64_ABS64 => {
-            let target_range = target_sec_offset .. (target_sec_offset + size_of::<u64>());
-            let target_ref = &mut target_sec_slice[target_range];
-            let source_val_usize = source_sec_vaddr.value().wrapping_add(relocation_entry.addend);
-            let source_val = source_val_usize as u64;
-            if verbose_log { trace!("                    target_ptr: {:p}, source_val: {:#X} (from source_sec_vaddr {:#X})", target_ref.as_ptr(), source_val, source_sec_vaddr); }
-            target_ref.copy_from_slice(&source_val.to_ne_bytes());
-            overflow_check = None;
-        }
-        R_AARCH64_ABS32 => {
-            let target_range = target_sec_offset .. (target_sec_offset + size_of::<u32>());
-            let target_ref = &mut target_sec_slice[target_range];
-            let source_val_usize = source_sec_vaddr.value().wrapping_add(relocation_entry.addend);
-            let source_val = source_val_usize as u32;
-            if verbose_log { trace!("                    target_ptr: {:p}, source_val: {:#X} (from source_sec_vaddr {:#X})", target_ref.as_ptr(), source_val, source_sec_vaddr); }
-            target_ref.copy_from_slice(&source_val.to_ne_bytes());
-            overflow_check = Some((source_val_usize, RANGE_32_BIT_SIGNED));
-        }
-        R_AARCH64_ABS16 => {
-            let target_range = target_sec_offset .. (target_sec_offset + size_of::<u16>());
-            let target_ref = &mut target_sec_slice[target_range];
-            let source_val_usize = source_sec_vaddr.value().wrapping_add(relocation_entry.addend);
-            let source_val = source_val_usize as u16;
-            if verbose_log { trace!("                    target_ptr: {:p}, source_val: {:#X} (from source_sec_vaddr {:#X})", target_ref.as_ptr(), source_val, source_sec_vaddr); }
-            target_ref.copy_from_slice(&source_val.to_ne_bytes());
-            overflow_check = Some((source_val_usize, RANGE_16_BIT_SIGNED));
-        }
-        R_AARCH64_PREL64 => {
-            let target_range = target_sec_offset .. (target_sec_offset + size_of::<u64>());
-            let target_ref = &mut target_sec_slice[target_range];
-            let source_val_usize = source_sec_vaddr.value().wrapping_add(relocation_entry.addend).wrapping_sub(target_ref.as_ptr() as usize);
-            let source_val = source_val_usize as u64;
-            if verbose_log { trace!("                    target_ptr: {:p}, source_val: {:#X} (from source_sec_vaddr {:#X})", target_ref.as_ptr(), source_val, source_sec_vaddr); }
-            target_ref.copy_from_slice(&source_val.to_ne_bytes());
-            overflow_check = None;
-        }
-        R_AARCH64_PREL32 => {
-            let target_range = target_sec_offset .. (target_sec_offset + size_of::<u32>());
-            let target_ref = &mut target_sec_slice[target_range];
-            let source_val_usize = source_sec_vaddr.value().wrapping_add(relocation_entry.addend).wrapping_sub(target_ref.as_ptr() as usize);
-            let source_val = source_val_usize as u32;
-            if verbose_log { trace!("                    target_ptr: {:p}, source_val: {:#X} (from source_sec_vaddr {:#X})", target_ref.as_ptr(), source_val, source_sec_vaddr); }
-            target_ref.copy_from_slice(&source_val.to_ne_bytes());
-            overflow_check = Some((source_val_usize, RANGE_32_BIT_SIGNED));
-        }
-        R_AARCH64_PREL16 => {
-            let target_range = target_sec_offset .. (target_sec_offset + size_of::<u16>());
-            let target_ref = &mut target_sec_slice[target_range];
-            let source_val_usize = source_sec_vaddr.value().wrapping_add(relocation_entry.addend).wrapping_sub(target_ref.as_ptr() as usize);
-            let source_val = source_val_usize as u16;
-            if verbose_log { trace!("                    target_ptr: {:p}, source_val: {:#X} (from source_sec_vaddr {:#X})", target_ref.as_ptr(), source_val, source_sec_vaddr); }
-            target_ref.copy_from_slice(&source_val.to_ne_bytes());
-            overflow_check = Some((source_val_usize, RANGE_16_BIT_SIGNED));
-        }
-
-        // These relocation types are for data move instructions that access data
-        // using 64-bit unsigned offset values, which exist when using the "large" code-model.
-        R_AARCH64_MOVW_UABS_G0
-        | R_AARCH64_MOVW_UABS_G0_NC
-        | R_AARCH64_MOVW_UABS_G1
-        | R_AARCH64_MOVW_UABS_G1_NC
-        | R_AARCH64_MOVW_UABS_G2
-        | R_AARCH64_MOVW_UABS_G2_NC
-        | R_AARCH64_MOVW_UABS_G3 => {
-            // The immediate field occupies 16 bits [20:5] in the MOV* series of instructions
-            // that these relocation types apply to.
-            // See: <https://developer.arm.com/documentation/ddi0596/2021-12/Base-Instructions/MOVK--Move-wide-with-keep->
-            const IMMEDIATE_FIELD_SHIFT: u8 = 5;
-            const IMMEDIATE_FIELD_MASK: u32 = 0xFFFF;
-            let (source_value_shift, overflow_range): (usize, _) = match relocation_entry.typ {
-                // Set immediate value to bits [15:0]  of the source_val --> 0-bit right shift.
-                R_AARCH64_MOVW_UABS_G0    => (0, Some(RANGE_16_BIT_UNSIGNED)),
-                R_AARCH64_MOVW_UABS_G0_NC => (0, None),
-                // Set immediate value to bits [31:16] of the source_val --> 16-bit right shift.
-                R_AARCH64_MOVW_UABS_G1    => (16, Some(RANGE_32_BIT_UNSIGNED)),
-                R_AARCH64_MOVW_UABS_G1_NC => (16, None),
-                // Set immediate value to bits [47:32] of the source_val --> 32-bit right shift.
-                R_AARCH64_MOVW_UABS_G2    => (32, Some(RANGE_48_BIT_UNSIGNED)),
-                R_AARCH64_MOVW_UABS_G2_NC => (32, None),
-                // Set immediate value to bits [63:48] of the source_val --> 48-bit right shift.
-                _g3                       => (48, None),
-            };
-    
-            let target_range = target_sec_offset .. (target_sec_offset + size_of::<u32>());
-            let target_ref = &mut target_sec_slice[target_range];
-            let source_val = source_sec_vaddr.value().wrapping_add(relocation_entry.addend);
-            let shifted_source_val = source_val >> source_value_shift;
-            if verbose_log { trace!("                    target_ptr: {:p}, source_val: {:#X}, shifted_source_val: {:#X} (from source_sec_vaddr {:#X})", target_ref.as_ptr(), source_val, shifted_source_val, source_sec_vaddr); }
-            let existing_target_val = u32::from_ne_bytes(
-                target_ref.try_into()
-                    .map_err(|_| "BUG: R_AARCH64_MOVW_UABS_G* relocation target val was not a u32")?
-            );
-            // Set the instruction's immediate value to the shifted source value.
-            let immediate_field_value = shifted_source_val & (IMMEDIATE_FIELD_MASK as usize);
-            let new_source_val = (existing_target_val & !(IMMEDIATE_FIELD_MASK << IMMEDIATE_FIELD_SHIFT))
-                | ((immediate_field_value << IMMEDIATE_FIELD_SHIFT) as u32);
-            if verbose_log { trace!("                    existing_instr: {:#X}, new_instr: {:#X}, imm val: {:#X}", existing_target_val, new_source_val, immediate_field_value); }
-            target_ref.copy_from_slice(&new_source_val.to_ne_bytes());
-            overflow_check = overflow_range.map(|range| (source_val, range));
-        }
-
-        R_AARCH64_ADR_PREL_PG_HI21 => {
-            // This is a "page" relocation, in which values used for relocation calculations
-            // are "page-aligned", i.e., the least-significant 12 bits are cleared.
-            // It is always 12 bits, regardless of the hardware's actual page size.
-            fn page_mask(val: usize) -> usize {
-                val & !0xFFF
-            }
-
-            // The immediate field is a total of 21 bits, split into two ranges:
-            // * The highest (most-significant) 19 bits occupy bits [23:5] of the instruction.
-            // * The lowest (least-significant) 2 bits occupy bits [30:29] of the instruction.
-            // See: <https://developer.arm.com/documentation/ddi0596/2021-12/Base-Instructions/ADRP--Form-PC-relative-address-to-4KB-page->
-            const IMMEDIATE_FIELD_SHIFT_HI: u8 = 5;
-            const IMMEDIATE_FIELD_MASK_HI: u32 = 0x7FFFF;
-            const IMMEDIATE_FIELD_SHIFT_LO: u8 = 29;
-            const IMMEDIATE_FIELD_MASK_LO: u32 = 0x3;
-            const SOURCE_VALUE_SHIFT:       u8 = 12;
-
-            let target_range = target_sec_offset .. (target_sec_offset + size_of::<u32>());
-            let target_ref = &mut target_sec_slice[target_range];
-            let source_val_usize = page_mask(source_sec_vaddr.value().wrapping_add(relocation_entry.addend))
-                .wrapping_sub(page_mask(target_ref.as_ptr() as usize));
-            let shifted_source_val = source_val_usize >> SOURCE_VALUE_SHIFT;
-            // now that we've shifted the source value, it's okay to truncate it into a `u32`.
-            let shifted_source_val = shifted_source_val as u32;
-            if verbose_log { trace!("                    target_ptr: {:p}, source_val: {:#X}, shifted_source_val: {:#X} (from source_sec_vaddr {:#X})", target_ref.as_ptr(), source_val_usize, shifted_source_val, source_sec_vaddr); }
-            let existing_target_val = u32::from_ne_bytes(
-                target_ref.try_into()
-                    .map_err(|_| "BUG: R_AARCH64_ADR_PREL_PG_HI21 relocation target val was not a u32")?
-            );
-            // Set the instruction's two immediate value ranges to the proper ranges of the shifted source value.
-            let new_source_val =
-                  (existing_target_val & !(IMMEDIATE_FIELD_MASK_LO << IMMEDIATE_FIELD_SHIFT_LO))
-                | (existing_target_val & !(IMMEDIATE_FIELD_MASK_HI << IMMEDIATE_FIELD_SHIFT_HI))
-                | ((shifted_source_val & IMMEDIATE_FIELD_MASK_LO) << IMMEDIATE_FIELD_SHIFT_LO)
-                | ((shifted_source_val & IMMEDIATE_FIELD_MASK_HI) << IMMEDIATE_FIELD_SHIFT_HI);
-            if verbose_log { trace!("                    existing_instr: {:#X}, new_instr: {:#X}", existing_target_val, new_source_val); }
-            target_ref.copy_from_slice(&new_source_val.to_ne_bytes());
-
-            const RANGE_32_BIT_ADR_SIGNED: Range<isize> = -TWO.pow(32) .. TWO.pow(32);
-            overflow_check = Some((source_val_usize, RANGE_32_BIT_ADR_SIGNED));
-        }
-
-        // These relocation types all use the same logic, but have different bit masks
-        // for the range of the immediate value (`source_val`) that gets used.
-        R_AARCH64_ADD_ABS_LO12_NC
-        | R_AARCH64_LDST8_ABS_LO12_NC
-        | R_AARCH64_LDST16_ABS_LO12_NC
-        | R_AARCH64_LDST32_ABS_LO12_NC
-        | R_AARCH64_LDST64_ABS_LO12_NC
-        | R_AARCH64_LDST128_ABS_LO12_NC => {
-            // The immediate field occupies 12 bits [21:10] in instructions
-            // that these relocation types apply to.
-            // See: <https://developer.arm.com/documentation/ddi0596/2021-12/Base-Instructions/ADD--immediate---Add--immediate-->
-            const IMMEDIATE_FIELD_SHIFT: u8 = 10;
-            const IMMEDIATE_FIELD_MASK: u32 = 0xFFF;
-            let source_value_shift = match relocation_entry.typ {
-                // Set immediate value to bits [11:4] of the source_val --> 4-bit right shift.
-                R_AARCH64_LDST128_ABS_LO12_NC => 4,
-                // Set immediate value to bits [11:3] of the source_val --> 3-bit right shift.
-                R_AARCH64_LDST64_ABS_LO12_NC  => 3,
-                // Set immediate value to bits [11:2] of the source_val --> 2-bit right shift.
-                R_AARCH64_LDST32_ABS_LO12_NC  => 2,
-                // Set immediate value to bits [11:1] of the source_val --> 1-bit right shift.
-                R_AARCH64_LDST16_ABS_LO12_NC  => 1,
-                // Set immediate value to bits [11:0] of the source_val --> 0-bit right shift.
-                _both_add_and_ldst8           => 0,
-            };
-    
-            let target_range = target_sec_offset .. (target_sec_offset + size_of::<u32>());
-            let target_ref = &mut target_sec_slice[target_range];
-            let source_val = source_sec_vaddr.value().wrapping_add(relocation_entry.addend) as u32;
-            let shifted_source_val = source_val >> source_value_shift;
-            if verbose_log { trace!("                    target_ptr: {:p}, source_val: {:#X}, shifted_source_val: {:#X} (from source_sec_vaddr {:#X})", target_ref.as_ptr(), source_val, shifted_source_val, source_sec_vaddr); }
-            let existing_target_val = u32::from_ne_bytes(
-                target_ref.try_into()
-                    .map_err(|_| "BUG: R_AARCH64_ADD/LDST*_ABS_LO12_NC relocation target val was not a u32")?
-            );
-            // Set the instruction's immediate value to the shifted source value.
-            let new_source_val = (existing_target_val & !(IMMEDIATE_FIELD_MASK << IMMEDIATE_FIELD_SHIFT))
-                | ((shifted_source_val & IMMEDIATE_FIELD_MASK) << IMMEDIATE_FIELD_SHIFT);
-            if verbose_log { trace!("                    existing_instr: {:#X}, new_instr: {:#X}", existing_target_val, new_source_val); }
-            target_ref.copy_from_slice(&new_source_val.to_ne_bytes());
-            overflow_check = None;
-        }
-
-        // These relocation types are for branch instructions, i.e., call and jump.
-        // The immediate field is a signed offset value.
-        R_AARCH64_CALL26 
-        | R_AARCH64_JUMP26 => {
-            // The immediate field occupies 26 bits [25:0] in call/jump instructions. 
-            // See: <https://developer.arm.com/documentation/ddi0596/2021-12/Base-Instructions/B--Branch->
-            const IMMEDIATE_FIELD_SHIFT: u8 = 0;
-            const IMMEDIATE_FIELD_MASK: u32 = 0x03FF_FFFF;
-            const SOURCE_VALUE_SHIFT: u8    = 2;
-
-            let target_range = target_sec_offset .. (target_sec_offset + size_of::<u32>());
-            let target_ref = &mut target_sec_slice[target_range];
-            let source_val = (source_sec_vaddr.value()).wrapping_add(relocation_entry.addend).wrapping_sub(target_ref.as_ptr() as usize);
-            let shifted_source_val = source_val >> SOURCE_VALUE_SHIFT;
-            if verbose_log { trace!("                    target_ptr: {:p}, source_val: {:#X}, shifted_source_val: {:#X} (from source_sec_vaddr {:#X})", target_ref.as_ptr(), source_val, shifted_source_val, source_sec_vaddr); }
-            let existing_target_val = u32::from_ne_bytes(
-                target_ref.try_into()
-                    .map_err(|_| "BUG: R_AARCH64_CALL26/JUMP26 relocation target val was not a u32")?
-            );
-            // Set the instruction's immediate value to the shifted source value.
-            let immediate_field_value = shifted_source_val as u32 & IMMEDIATE_FIELD_MASK;
-            let new_source_val = (existing_target_val & !(IMMEDIATE_FIELD_MASK << IMMEDIATE_FIELD_SHIFT))
-                | (immediate_field_value << IMMEDIATE_FIELD_SHIFT);
-            if verbose_log { trace!("                    existing_instr: {:#X}, new_instr: {:#X}, imm val: {:#X}", existing_target_val, new_source_val, immediate_field_value); }
-            target_ref.copy_from_slice(&new_source_val.to_ne_bytes());
-
-            const RANGE_27_BIT_SIGNED: Range<isize> = -TWO.pow(27) .. TWO.pow(27);
-            overflow_check = Some((source_val, RANGE_27_BIT_SIGNED));
-        }
-
-        // These relocation types are for thread-local storage, only the "local-exec" tls model.
-        R_AARCH64_TLSLE_ADD_TPREL_HI12
-        | R_AARCH64_TLSLE_ADD_TPREL_LO12
-        | R_AARCH64_TLSLE_ADD_TPREL_LO12_NC => {
-            // The immediate field occupies 12 bits [21:10] in the ADD instruction
-            // that these relocation types apply to.
-            // See: <https://developer.arm.com/documentation/ddi0596/2021-12/Base-Instructions/ADD--immediate---Add--immediate-->
-            const IMMEDIATE_FIELD_SHIFT: u8 = 10;
-            const IMMEDIATE_FIELD_MASK: u32 = 0xFFF;
-            let (source_value_shift, overflow_range): (usize, _) = match relocation_entry.typ {
-                R_AARCH64_TLSLE_ADD_TPREL_HI12 => (12, Some(RANGE_24_BIT_UNSIGNED)),
-                R_AARCH64_TLSLE_ADD_TPREL_LO12 => (0, Some(RANGE_12_BIT_UNSIGNED)),
-                _lo_12_nc                      => (0, None),
-            };
-            
-            let target_range = target_sec_offset .. (target_sec_offset + size_of::<u32>());
-            let target_ref = &mut target_sec_slice[target_range];
-            let source_val_usize = source_sec_vaddr.value().wrapping_add(relocation_entry.addend);
-            let source_val = source_val_usize as u32;
-            let shifted_source_val = source_val >> source_value_shift;
-            if verbose_log { trace!("                    target_ptr: {:p}, source_val: {:#X}, shifted_source_val: {:#X} (from source_sec_vaddr {:#X})", target_ref.as_ptr(), source_val, shifted_source_val, source_sec_vaddr); }
-            let existing_target_val = u32::from_ne_bytes(
-                target_ref.try_into()
-                    .map_err(|_| "BUG: R_AARCH64_TLSLE_ADD_TPREL_* relocation target val was not a u32")?
-            );
-            // Set the instruction's immediate value to the shifted source value.
-            let new_source_val = (existing_target_val & !(IMMEDIATE_FIELD_MASK << IMMEDIATE_FIELD_SHIFT))
-                | ((shifted_source_val & IMMEDIATE_FIELD_MASK) << IMMEDIATE_FIELD_SHIFT);
-            if verbose_log { trace!("                    existing_instr: {:#X}, new_instr: {:#X}", existing_target_val, new_source_val); }
-            target_ref.copy_from_slice(&new_source_val.to_ne_bytes());
-            overflow_check = overflow_range.map(|range| (source_val_usize, range));
-        }
-        other => return unsupported(other),
-    }
-
-    // Perform the overflow check, if the relocation type requires it.
-    if let Some((source_val_usize, overflow_range)) = overflow_check {
-        let source_val_isize = source_val_usize as isize;
-        if overflow_range.contains(&source_val_isize) {
-            if verbose_log { trace!("                    overflow check: {} <= {} < {}, {:#X} <= {:#X} < {:#X} --> PASS", overflow_range.start, source_val_isize, overflow_range.end, overflow_range.start, source_val_isize, overflow_range.end); }
-        } else {
-            error!("Overflow check: {:#X} <= {:#X} < {:#X} --> FAIL", overflow_range.start, source_val_isize, overflow_range.end);
-            return Err("Relocation failed overflow check");
-        }
-    }
-
-    Ok(())
-}
@@ -0,0 +1,422 @@
+//! Relocation calculations for aarch64.
+//!
+//! Most relocation types on aarch64 patch the immediate field of a single instruction,
+//! so they must preserve the instruction's other bits and check that the value fits in the field.
+
+use core::{convert::TryInto, mem::size_of, ops::Range};
+use goblin::elf::{header::EM_AARCH64, reloc::*};
+use log::{error, trace};
+use memory::VirtualAddress;
+use crate::{got, unsupported, RelocationEntry};
+
+/// The ELF machine type of object files that can be loaded on aarch64.
+pub const ELF_MACHINE: u16 = EM_AARCH64;
+
+/// Returns true if the given relocation type's value depends only on the source section.
+pub(crate) fn is_absolute(relocation_type: u32) -> bool {
+    matches!(relocation_type,
+        R_AARCH64_ABS64
+        | R_AARCH64_ABS32
+        | R_AARCH64_ABS16
+        | R_AARCH64_MOVW_UABS_G0
+        | R_AARCH64_MOVW_UABS_G0_NC
+        | R_AARCH64_MOVW_UABS_G1
+        | R_AARCH64_MOVW_UABS_G1_NC
+        | R_AARCH64_MOVW_UABS_G2
+        | R_AARCH64_MOVW_UABS_G2_NC
+        | R_AARCH64_MOVW_UABS_G3
+        | R_AARCH64_ADD_ABS_LO12_NC
+        | R_AARCH64_LDST8_ABS_LO12_NC
+        | R_AARCH64_LDST16_ABS_LO12_NC
+        | R_AARCH64_LDST32_ABS_LO12_NC
+        | R_AARCH64_LDST64_ABS_LO12_NC
+        | R_AARCH64_LDST128_ABS_LO12_NC
+    )
+}
+
+/// Returns true if the given relocation type has no effect on a loaded crate.
+pub(crate) fn is_ignored(relocation_type: u32) -> bool {
+    relocation_type == R_AARCH64_NONE
+}
+
+/// Writes the value of the given aarch64 relocation entry; see [`crate::write_relocation()`].
+#[inline(always)]
+pub(crate) fn write_relocation(
+    relocation_entry: RelocationEntry,
+    target_sec_slice: &mut [u8],
+    target_sec_offset: usize,
+    source_sec_vaddr: VirtualAddress,
+    verbose_log: bool
+) -> Result<(), &'static str> {
+    const TWO: isize = 2;
+    const RANGE_16_BIT_SIGNED: Range<isize> = -TWO.pow(15) .. TWO.pow(16);
+    const RANGE_32_BIT_SIGNED: Range<isize> = -TWO.pow(31) .. TWO.pow(32);
+    const RANGE_12_BIT_UNSIGNED: Range<isize> = 0 .. TWO.pow(12);
+    const RANGE_16_BIT_UNSIGNED: Range<isize> = 0 .. TWO.pow(16);
+    const RANGE_24_BIT_UNSIGNED: Range<isize> = 0 .. TWO.pow(24);
+    const RANGE_32_BIT_UNSIGNED: Range<isize> = 0 .. TWO.pow(32);
+    const RANGE_48_BIT_UNSIGNED: Range<isize> = 0 .. TWO.pow(48);
+
+    #[allow(clippy::needless_late_init)]
+    let overflow_check: Option<(usize, Range<isize>)>;
+    match relocation_entry.typ {
+        R_AARCH64_ABS64 => {
+            let target_range = target_sec_offset .. (target_sec_offset + size_of::<u64>());
+            let target_ref = &mut target_sec_slice[target_range];
+            let source_val_usize = source_sec_vaddr.value().wrapping_add(relocation_entry.addend);
+            let source_val = source_val_usize as u64;
+            if verbose_log { trace!("                    target_ptr: {:p}, source_val: {:#X} (from source_sec_vaddr {:#X})", target_ref.as_ptr(), source_val, source_sec_vaddr); }
+            target_ref.copy_from_slice(&source_val.to_ne_bytes());
+            overflow_check = None;
+        }
+        R_AARCH64_ABS32 => {
+            let target_range = target_sec_offset .. (target_sec_offset + size_of::<u32>());
+            let target_ref = &mut target_sec_slice[target_range];
+            let source_val_usize = source_sec_vaddr.value().wrapping_add(relocation_entry.addend);
+            let source_val = source_val_usize as u32;
+            if verbose_log { trace!("                    target_ptr: {:p}, source_val: {:#X} (from source_sec_vaddr {:#X})", target_ref.as_ptr(), source_val, source_sec_vaddr); }
+            target_ref.copy_from_slice(&source_val.to_ne_bytes());
+            overflow_check = Some((source_val_usize, RANGE_32_BIT_SIGNED));
+        }
+        R_AARCH64_ABS16 => {
+            let target_range = target_sec_offset .. (target_sec_offset + size_of::<u16>());
+            let target_ref = &mut target_sec_slice[target_range];
+            let source_val_usize = source_sec_vaddr.value().wrapping_add(relocation_entry.addend);
+            let source_val = source_val_usize as u16;
+            if verbose_log { trace!("                    target_ptr: {:p}, source_val: {:#X} (from source_sec_vaddr {:#X})", target_ref.as_ptr(), source_val, source_sec_vaddr); }
+            target_ref.copy_from_slice(&source_val.to_ne_bytes());
+            overflow_check = Some((source_val_usize, RANGE_16_BIT_SIGNED));
+        }
+        R_AARCH64_PREL64 => {
+            let target_range = target_sec_offset .. (target_sec_offset + size_of::<u64>());
+            let target_ref = &mut target_sec_slice[target_range];
+            let source_val_usize = source_sec_vaddr.value().wrapping_add(relocation_entry.addend).wrapping_sub(target_ref.as_ptr() as usize);
+            let source_val = source_val_usize as u64;
+            if verbose_log { trace!("                    target_ptr: {:p}, source_val: {:#X} (from source_sec_vaddr {:#X})", target_ref.as_ptr(), source_val, source_sec_vaddr); }
+            target_ref.copy_from_slice(&source_val.to_ne_bytes());
+            overflow_check = None;
+        }
+        R_AARCH64_PREL32 => {
+            let target_range = target_sec_offset .. (target_sec_offset + size_of::<u32>());
+            let target_ref = &mut target_sec_slice[target_range];
+            let source_val_usize = source_sec_vaddr.value().wrapping_add(relocation_entry.addend).wrapping_sub(target_ref.as_ptr() as usize);
+            let source_val = source_val_usize as u32;
+            if verbose_log { trace!("                    target_ptr: {:p}, source_val: {:#X} (from source_sec_vaddr {:#X})", target_ref.as_ptr(), source_val, source_sec_vaddr); }
+            target_ref.copy_from_slice(&source_val.to_ne_bytes());
+            overflow_check = Some((source_val_usize, RANGE_32_BIT_SIGNED));
+        }
+        R_AARCH64_PREL16 => {
+            let target_range = target_sec_offset .. (target_sec_offset + size_of::<u16>());
+            let target_ref = &mut target_sec_slice[target_range];
+            let source_val_usize = source_sec_vaddr.value().wrapping_add(relocation_entry.addend).wrapping_sub(target_ref.as_ptr() as usize);
+            let source_val = source_val_usize as u16;
+            if verbose_log { trace!("                    target_ptr: {:p}, source_val: {:#X} (from source_sec_vaddr {:#X})", target_ref.as_ptr(), source_val, source_sec_vaddr); }
+            target_ref.copy_from_slice(&source_val.to_ne_bytes());
+            overflow_check = Some((source_val_usize, RANGE_16_BIT_SIGNED));
+        }
+
+        // These relocation types are for data move instructions that access data
+        // using 64-bit unsigned offset values, which exist when using the "large" code-model,
+        // or that build a TLS offset for the "local-exec" tls model.
+        R_AARCH64_MOVW_UABS_G0
+        | R_AARCH64_MOVW_UABS_G0_NC
+        | R_AARCH64_MOVW_UABS_G1
+        | R_AARCH64_MOVW_UABS_G1_NC
+        | R_AARCH64_MOVW_UABS_G2
+        | R_AARCH64_MOVW_UABS_G2_NC
+        | R_AARCH64_MOVW_UABS_G3
+        | R_AARCH64_TLSLE_MOVW_TPREL_G0
+        | R_AARCH64_TLSLE_MOVW_TPREL_G0_NC
+        | R_AARCH64_TLSLE_MOVW_TPREL_G1
+        | R_AARCH64_TLSLE_MOVW_TPREL_G1_NC
+        | R_AARCH64_TLSLE_MOVW_TPREL_G2 => {
+            // The immediate field occupies 16 bits [20:5] in the MOV* series of instructions
+            // that these relocation types apply to.
+            // See: <https://developer.arm.com/documentation/ddi0596/2021-12/Base-Instructions/MOVK--Move-wide-with-keep->
+            const IMMEDIATE_FIELD_SHIFT: u8 = 5;
+            const IMMEDIATE_FIELD_MASK: u32 = 0xFFFF;
+            // For the TPREL types, the `source_sec_vaddr` value is the TLS offset,
+            // which is never negative on aarch64, so the instruction is always a MOVZ or MOVK (never a MOVN).
+            let (source_value_shift, overflow_range): (usize, _) = match relocation_entry.typ {
+                // Set immediate value to bits [15:0]  of the source_val --> 0-bit right shift.
+                R_AARCH64_MOVW_UABS_G0
+                | R_AARCH64_TLSLE_MOVW_TPREL_G0    => (0, Some(RANGE_16_BIT_UNSIGNED)),
+                R_AARCH64_MOVW_UABS_G0_NC
+                | R_AARCH64_TLSLE_MOVW_TPREL_G0_NC => (0, None),
+                // Set immediate value to bits [31:16] of the source_val --> 16-bit right shift.
+                R_AARCH64_MOVW_UABS_G1
+                | R_AARCH64_TLSLE_MOVW_TPREL_G1    => (16, Some(RANGE_32_BIT_UNSIGNED)),
+                R_AARCH64_MOVW_UABS_G1_NC
+                | R_AARCH64_TLSLE_MOVW_TPREL_G1_NC => (16, None),
+                // Set immediate value to bits [47:32] of the source_val --> 32-bit right shift.
+                R_AARCH64_MOVW_UABS_G2
+                | R_AARCH64_TLSLE_MOVW_TPREL_G2    => (32, Some(RANGE_48_BIT_UNSIGNED)),
+                R_AARCH64_MOVW_UABS_G2_NC          => (32, None),
+                // Set immediate value to bits [63:48] of the source_val --> 48-bit right shift.
+                _g3                                => (48, None),
+            };
+    
+            let target_range = target_sec_offset .. (target_sec_offset + size_of::<u32>());
+            let target_ref = &mut target_sec_slice[target_range];
+            let source_val = source_sec_vaddr.value().wrapping_add(relocation_entry.addend);
+            let shifted_source_val = source_val >> source_value_shift;
+            if verbose_log { trace!("                    target_ptr: {:p}, source_val: {:#X}, shifted_source_val: {:#X} (from source_sec_vaddr {:#X})", target_ref.as_ptr(), source_val, shifted_source_val, source_sec_vaddr); }
+            let existing_target_val = u32::from_ne_bytes(
+                target_ref.try_into()
+                    .map_err(|_| "BUG: R_AARCH64_MOVW_UABS_G*/TLSLE_MOVW_TPREL_G* relocation target val was not a u32")?
+            );
+            // Set the instruction's immediate value to the shifted source value.
+            let immediate_field_value = shifted_source_val & (IMMEDIATE_FIELD_MASK as usize);
+            let new_source_val = (existing_target_val & !(IMMEDIATE_FIELD_MASK << IMMEDIATE_FIELD_SHIFT))
+                | ((immediate_field_value << IMMEDIATE_FIELD_SHIFT) as u32);
+            if verbose_log { trace!("                    existing_instr: {:#X}, new_instr: {:#X}, imm val: {:#X}", existing_target_val, new_source_val, immediate_field_value); }
+            target_ref.copy_from_slice(&new_source_val.to_ne_bytes());
+            overflow_check = overflow_range.map(|range| (source_val, range));
+        }
+
+        // These relocation types are for the ADR and ADRP instructions,
+        // which form a PC-relative address or PC-relative 4KiB page address, respectively.
+        // For the "initial-exec" tls model, the ADRP forms the page address of the GOT entry
+        // that holds the TLS offset.
+        R_AARCH64_ADR_PREL_LO21
+        | R_AARCH64_ADR_PREL_PG_HI21
+        | R_AARCH64_ADR_PREL_PG_HI21_NC
+        | R_AARCH64_TLSIE_ADR_GOTTPREL_PAGE21 => {
+            // ADRP relocations are "page" relocations, in which values used for relocation calculations
+            // are "page-aligned", i.e., the least-significant 12 bits are cleared.
+            // It is always 12 bits, regardless of the hardware's actual page size.
+            fn page_mask(val: usize) -> usize {
+                val & !0xFFF
+            }
+
+            // The immediate field is a total of 21 bits, split into two ranges:
+            // * The highest (most-significant) 19 bits occupy bits [23:5] of the instruction.
+            // * The lowest (least-significant) 2 bits occupy bits [30:29] of the instruction.
+            // See: <https://developer.arm.com/documentation/ddi0596/2021-12/Base-Instructions/ADRP--Form-PC-relative-address-to-4KB-page->
+            const IMMEDIATE_FIELD_SHIFT_HI: u8 = 5;
+            const IMMEDIATE_FIELD_MASK_HI: u32 = 0x7FFFF;
+            const IMMEDIATE_FIELD_SHIFT_LO: u8 = 29;
+            const IMMEDIATE_FIELD_MASK_LO: u32 = 0x3;
+            const RANGE_21_BIT_SIGNED: Range<isize> = -TWO.pow(20) .. TWO.pow(20);
+            const RANGE_32_BIT_ADR_SIGNED: Range<isize> = -TWO.pow(32) .. TWO.pow(32);
+            let (is_page_relocation, overflow_range) = match relocation_entry.typ {
+                R_AARCH64_ADR_PREL_LO21    => (false, Some(RANGE_21_BIT_SIGNED)),
+                R_AARCH64_ADR_PREL_PG_HI21
+                | R_AARCH64_TLSIE_ADR_GOTTPREL_PAGE21 => (true, Some(RANGE_32_BIT_ADR_SIGNED)),
+                _pg_hi21_nc                => (true, None),
+            };
+
+            let target_range = target_sec_offset .. (target_sec_offset + size_of::<u32>());
+            let target_ref = &mut target_sec_slice[target_range];
+            let mut source_val = source_sec_vaddr.value().wrapping_add(relocation_entry.addend);
+            if relocation_entry.typ == R_AARCH64_TLSIE_ADR_GOTTPREL_PAGE21 {
+                source_val = got::entry_for(source_val as u64)?.value();
+            }
+            let target_val = target_ref.as_ptr() as usize;
+            let (source_val_usize, shifted_source_val) = if is_page_relocation {
+                let source_val_usize = page_mask(source_val).wrapping_sub(page_mask(target_val));
+                (source_val_usize, source_val_usize >> 12)
+            } else {
+                let source_val_usize = source_val.wrapping_sub(target_val);
+                (source_val_usize, source_val_usize)
+            };
+            // now that we've shifted the source value, it's okay to truncate it into a `u32`.
+            let shifted_source_val = shifted_source_val as u32;
+            if verbose_log { trace!("                    target_ptr: {:p}, source_val: {:#X}, shifted_source_val: {:#X} (from source_sec_vaddr {:#X})", target_ref.as_ptr(), source_val_usize, shifted_source_val, source_sec_vaddr); }
+            let existing_target_val = u32::from_ne_bytes(
+                target_ref.try_into()
+                    .map_err(|_| "BUG: R_AARCH64_ADR_PREL_* relocation target val was not a u32")?
+            );
+            // Set the instruction's two immediate value ranges to the proper ranges of the shifted source value.
+            let new_source_val =
+                  (existing_target_val & !(IMMEDIATE_FIELD_MASK_LO << IMMEDIATE_FIELD_SHIFT_LO) & !(IMMEDIATE_FIELD_MASK_HI << IMMEDIATE_FIELD_SHIFT_HI))
+                | ((shifted_source_val & IMMEDIATE_FIELD_MASK_LO) << IMMEDIATE_FIELD_SHIFT_LO)
+                | (((shifted_source_val >> 2) & IMMEDIATE_FIELD_MASK_HI) << IMMEDIATE_FIELD_SHIFT_HI);
+            if verbose_log { trace!("                    existing_instr: {:#X}, new_instr: {:#X}", existing_target_val, new_source_val); }
+            target_ref.copy_from_slice(&new_source_val.to_ne_bytes());
+            overflow_check = overflow_range.map(|range| (source_val_usize, range));
+        }
+
+        // These relocation types all use the same logic, but have different bit masks
+        // for the range of the immediate value (`source_val`) that gets used.
+        // The TPREL types use the TLS offset as the immediate value, for the "local-exec" tls model,
+        // while the GOTTPREL type uses the address of the GOT entry that holds the TLS offset,
+        // for the "initial-exec" tls model.
+        R_AARCH64_ADD_ABS_LO12_NC
+        | R_AARCH64_LDST8_ABS_LO12_NC
+        | R_AARCH64_LDST16_ABS_LO12_NC
+        | R_AARCH64_LDST32_ABS_LO12_NC
+        | R_AARCH64_LDST64_ABS_LO12_NC
+        | R_AARCH64_LDST128_ABS_LO12_NC
+        | R_AARCH64_TLSLE_LDST8_TPREL_LO12
+        | R_AARCH64_TLSLE_LDST8_TPREL_LO12_NC
+        | R_AARCH64_TLSLE_LDST16_TPREL_LO12
+        | R_AARCH64_TLSLE_LDST16_TPREL_LO12_NC
+        | R_AARCH64_TLSLE_LDST32_TPREL_LO12
+        | R_AARCH64_TLSLE_LDST32_TPREL_LO12_NC
+        | R_AARCH64_TLSLE_LDST64_TPREL_LO12
+        | R_AARCH64_TLSLE_LDST64_TPREL_LO12_NC
+        | R_AARCH64_TLSIE_LD64_GOTTPREL_LO12_NC => {
+            // The immediate field occupies 12 bits [21:10] in instructions
+            // that these relocation types apply to.
+            // See: <https://developer.arm.com/documentation/ddi0596/2021-12/Base-Instructions/ADD--immediate---Add--immediate-->
+            const IMMEDIATE_FIELD_SHIFT: u8 = 10;
+            const IMMEDIATE_FIELD_MASK: u32 = 0xFFF;
+            let (source_value_shift, overflow_range) = match relocation_entry.typ {
+                // Set immediate value to bits [11:4] of the source_val --> 4-bit right shift.
+                R_AARCH64_LDST128_ABS_LO12_NC          => (4, None),
+                // Set immediate value to bits [11:3] of the source_val --> 3-bit right shift.
+                R_AARCH64_LDST64_ABS_LO12_NC
+                | R_AARCH64_TLSLE_LDST64_TPREL_LO12_NC
+                | R_AARCH64_TLSIE_LD64_GOTTPREL_LO12_NC => (3, None),
+                R_AARCH64_TLSLE_LDST64_TPREL_LO12      => (3, Some(RANGE_12_BIT_UNSIGNED)),
+                // Set immediate value to bits [11:2] of the source_val --> 2-bit right shift.
+                R_AARCH64_LDST32_ABS_LO12_NC
+                | R_AARCH64_TLSLE_LDST32_TPREL_LO12_NC => (2, None),
+                R_AARCH64_TLSLE_LDST32_TPREL_LO12      => (2, Some(RANGE_12_BIT_UNSIGNED)),
+                // Set immediate value to bits [11:1] of the source_val --> 1-bit right shift.
+                R_AARCH64_LDST16_ABS_LO12_NC
+                | R_AARCH64_TLSLE_LDST16_TPREL_LO12_NC => (1, None),
+                R_AARCH64_TLSLE_LDST16_TPREL_LO12      => (1, Some(RANGE_12_BIT_UNSIGNED)),
+                // Set immediate value to bits [11:0] of the source_val --> 0-bit right shift.
+                R_AARCH64_TLSLE_LDST8_TPREL_LO12       => (0, Some(RANGE_12_BIT_UNSIGNED)),
+                _both_add_and_ldst8                    => (0, None),
+            };
+    
+            let target_range = target_sec_offset .. (target_sec_offset + size_of::<u32>());
+            let target_ref = &mut target_sec_slice[target_range];
+            let mut source_val_usize = source_sec_vaddr.value().wrapping_add(relocation_entry.addend);
+            if relocation_entry.typ == R_AARCH64_TLSIE_LD64_GOTTPREL_LO12_NC {
+                source_val_usize = got::entry_for(source_val_usize as u64)?.value();
+            }
+            // Only bits [11:0] are used, so the higher bits must not be shifted into the immediate field.
+            let source_val = source_val_usize as u32 & 0xFFF;
+            let shifted_source_val = source_val >> source_value_shift;
+            if verbose_log { trace!("                    target_ptr: {:p}, source_val: {:#X}, shifted_source_val: {:#X} (from source_sec_vaddr {:#X})", target_ref.as_ptr(), source_val, shifted_source_val, source_sec_vaddr); }
+            let existing_target_val = u32::from_ne_bytes(
+                target_ref.try_into()
+                    .map_err(|_| "BUG: R_AARCH64_ADD/LDST*_LO12 relocation target val was not a u32")?
+            );
+            // Set the instruction's immediate value to the shifted source value.
+            let new_source_val = (existing_target_val & !(IMMEDIATE_FIELD_MASK << IMMEDIATE_FIELD_SHIFT))
+                | ((shifted_source_val & IMMEDIATE_FIELD_MASK) << IMMEDIATE_FIELD_SHIFT);
+            if verbose_log { trace!("                    existing_instr: {:#X}, new_instr: {:#X}", existing_target_val, new_source_val); }
+            target_ref.copy_from_slice(&new_source_val.to_ne_bytes());
+            overflow_check = overflow_range.map(|range| (source_val_usize, range));
+        }
+
+        // These relocation types are for branch instructions, i.e., call and jump.
+        // The immediate field is a signed offset value.
+        R_AARCH64_CALL26 
+        | R_AARCH64_JUMP26 => {
+            // The immediate field occupies 26 bits [25:0] in call/jump instructions. 
+            // See: <https://developer.arm.com/documentation/ddi0596/2021-12/Base-Instructions/B--Branch->
+            const IMMEDIATE_FIELD_SHIFT: u8 = 0;
+            const IMMEDIATE_FIELD_MASK: u32 = 0x03FF_FFFF;
+            const SOURCE_VALUE_SHIFT: u8    = 2;
+
+            let target_range = target_sec_offset .. (target_sec_offset + size_of::<u32>());
+            let target_ref = &mut target_sec_slice[target_range];
+            let source_val = (source_sec_vaddr.value()).wrapping_add(relocation_entry.addend).wrapping_sub(target_ref.as_ptr() as usize);
+            let shifted_source_val = source_val >> SOURCE_VALUE_SHIFT;
+            if verbose_log { trace!("                    target_ptr: {:p}, source_val: {:#X}, shifted_source_val: {:#X} (from source_sec_vaddr {:#X})", target_ref.as_ptr(), source_val, shifted_source_val, source_sec_vaddr); }
+            let existing_target_val = u32::from_ne_bytes(
+                target_ref.try_into()
+                    .map_err(|_| "BUG: R_AARCH64_CALL26/JUMP26 relocation target val was not a u32")?
+            );
+            // Set the instruction's immediate value to the shifted source value.
+            let immediate_field_value = shifted_source_val as u32 & IMMEDIATE_FIELD_MASK;
+            let new_source_val = (existing_target_val & !(IMMEDIATE_FIELD_MASK << IMMEDIATE_FIELD_SHIFT))
+                | (immediate_field_value << IMMEDIATE_FIELD_SHIFT);
+            if verbose_log { trace!("                    existing_instr: {:#X}, new_instr: {:#X}, imm val: {:#X}", existing_target_val, new_source_val, immediate_field_value); }
+            target_ref.copy_from_slice(&new_source_val.to_ne_bytes());
+
+            const RANGE_27_BIT_SIGNED: Range<isize> = -TWO.pow(27) .. TWO.pow(27);
+            overflow_check = Some((source_val, RANGE_27_BIT_SIGNED));
+        }
+
+        // These relocation types are for conditional branch instructions and PC-relative literal loads,
+        // whose immediate field is a signed offset value in units of 4-byte instructions.
+        R_AARCH64_CONDBR19
+        | R_AARCH64_LD_PREL_LO19
+        | R_AARCH64_TSTBR14 => {
+            // The immediate field occupies 19 bits [23:5] in conditional branch and LDR (literal) instructions,
+            // or 14 bits [18:5] in test-and-branch instructions.
+            // See: <https://developer.arm.com/documentation/ddi0596/2021-12/Base-Instructions/B-cond--Branch-conditionally->
+            // and <https://developer.arm.com/documentation/ddi0596/2021-12/Base-Instructions/TBZ--Test-bit-and-Branch-if-Zero->
+            const IMMEDIATE_FIELD_SHIFT: u8 = 5;
+            const SOURCE_VALUE_SHIFT: u8    = 2;
+            const RANGE_16_BIT_BRANCH_SIGNED: Range<isize> = -TWO.pow(15) .. TWO.pow(15);
+            const RANGE_21_BIT_BRANCH_SIGNED: Range<isize> = -TWO.pow(20) .. TWO.pow(20);
+            let (immediate_field_mask, overflow_range): (u32, _) = match relocation_entry.typ {
+                R_AARCH64_TSTBR14 => (0x3FFF, RANGE_16_BIT_BRANCH_SIGNED),
+                _condbr_or_ld     => (0x7FFFF, RANGE_21_BIT_BRANCH_SIGNED),
+            };
+
+            let target_range = target_sec_offset .. (target_sec_offset + size_of::<u32>());
+            let target_ref = &mut target_sec_slice[target_range];
+            let source_val = (source_sec_vaddr.value()).wrapping_add(relocation_entry.addend).wrapping_sub(target_ref.as_ptr() as usize);
+            let shifted_source_val = source_val >> SOURCE_VALUE_SHIFT;
+            if verbose_log { trace!("                    target_ptr: {:p}, source_val: {:#X}, shifted_source_val: {:#X} (from source_sec_vaddr {:#X})", target_ref.as_ptr(), source_val, shifted_source_val, source_sec_vaddr); }
+            let existing_target_val = u32::from_ne_bytes(
+                target_ref.try_into()
+                    .map_err(|_| "BUG: R_AARCH64_CONDBR19/LD_PREL_LO19/TSTBR14 relocation target val was not a u32")?
+            );
+            // Set the instruction's immediate value to the shifted source value.
+            let immediate_field_value = shifted_source_val as u32 & immediate_field_mask;
+            let new_source_val = (existing_target_val & !(immediate_field_mask << IMMEDIATE_FIELD_SHIFT))
+                | (immediate_field_value << IMMEDIATE_FIELD_SHIFT);
+            if verbose_log { trace!("                    existing_instr: {:#X}, new_instr: {:#X}, imm val: {:#X}", existing_target_val, new_source_val, immediate_field_value); }
+            target_ref.copy_from_slice(&new_source_val.to_ne_bytes());
+            overflow_check = Some((source_val, overflow_range));
+        }
+
+        // These relocation types are for the ADD instruction that applies a TLS offset
+        // in the "local-exec" tls model.
+        R_AARCH64_TLSLE_ADD_TPREL_HI12
+        | R_AARCH64_TLSLE_ADD_TPREL_LO12
+        | R_AARCH64_TLSLE_ADD_TPREL_LO12_NC => {
+            // The immediate field occupies 12 bits [21:10] in the ADD instruction
+            // that these relocation types apply to.
+            // See: <https://developer.arm.com/documentation/ddi0596/2021-12/Base-Instructions/ADD--immediate---Add--immediate-->
+            const IMMEDIATE_FIELD_SHIFT: u8 = 10;
+            const IMMEDIATE_FIELD_MASK: u32 = 0xFFF;
+            let (source_value_shift, overflow_range): (usize, _) = match relocation_entry.typ {
+                R_AARCH64_TLSLE_ADD_TPREL_HI12 => (12, Some(RANGE_24_BIT_UNSIGNED)),
+                R_AARCH64_TLSLE_ADD_TPREL_LO12 => (0, Some(RANGE_12_BIT_UNSIGNED)),
+                _lo_12_nc                      => (0, None),
+            };
+            
+            let target_range = target_sec_offset .. (target_sec_offset + size_of::<u32>());
+            let target_ref = &mut target_sec_slice[target_range];
+            let source_val_usize = source_sec_vaddr.value().wrapping_add(relocation_entry.addend);
+            let source_val = source_val_usize as u32;
+            let shifted_source_val = source_val >> source_value_shift;
+            if verbose_log { trace!("                    target_ptr: {:p}, source_val: {:#X}, shifted_source_val: {:#X} (from source_sec_vaddr {:#X})", target_ref.as_ptr(), source_val, shifted_source_val, source_sec_vaddr); }
+            let existing_target_val = u32::from_ne_bytes(
+                target_ref.try_into()
+                    .map_err(|_| "BUG: R_AARCH64_TLSLE_ADD_TPREL_* relocation target val was not a u32")?
+            );
+            // Set the instruction's immediate value to the shifted source value.
+            let new_source_val = (existing_target_val & !(IMMEDIATE_FIELD_MASK << IMMEDIATE_FIELD_SHIFT))
+                | ((shifted_source_val & IMMEDIATE_FIELD_MASK) << IMMEDIATE_FIELD_SHIFT);
+            if verbose_log { trace!("                    existing_instr: {:#X}, new_instr: {:#X}", existing_target_val, new_source_val); }
+            target_ref.copy_from_slice(&new_source_val.to_ne_bytes());
+            overflow_check = overflow_range.map(|range| (source_val_usize, range));
+        }
+        other => return unsupported(other),
+    }
+
+    // Perform the overflow check, if the relocation type requires it.
+    if let Some((source_val_usize, overflow_range)) = overflow_check {
+        let source_val_isize = source_val_usize as isize;
+        if overflow_range.contains(&source_val_isize) {
+            if verbose_log { trace!("                    overflow check: {} <= {} < {}, {:#X} <= {:#X} < {:#X} --> PASS", overflow_range.start, source_val_isize, overflow_range.end, overflow_range.start, source_val_isize, overflow_range.end); }
+        } else {
+            error!("Overflow check: {:#X} <= {:#X} < {:#X} --> FAIL", overflow_range.start, source_val_isize, overflow_range.end);
+            return Err("Relocation failed overflow check");
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,348 @@
+//! Relocation calculations for riscv64 (rv64gc).
+//!
+//! RISC-V has no large code model, so crates are compiled with the `medany` code model,
+//! in which code addresses symbols PC-relatively with an `auipc` instruction that holds the high 20 bits
+//! of the offset, followed by an instruction that holds its low 12 bits.
+//! The relocation for the low part (e.g., `R_RISCV_PCREL_LO12_I`) doesn't refer to the symbol itself,
+//! but to a label at the `auipc` instruction whose relocation refers to the symbol.
+//! The loader pairs each low part with its high part via [`RelocationEntry::pair_with()`],
+//! producing a self-contained relocation that can be rewritten on its own, e.g., during crate swapping.
+//!
+//! Linker relaxation isn't performed, so `R_RISCV_RELAX` and `R_RISCV_ALIGN` are ignored.
+//!
+//! See the RISC-V ELF psABI: <https://github.com/riscv-non-isa/riscv-elf-psabi-doc/blob/master/riscv-elf.adoc>.
+
+use core::{convert::TryInto, mem::size_of, ops::Range};
+use log::{error, trace};
+use memory::VirtualAddress;
+use crate::{got, unsupported, RelocationEntry};
+
+/// The ELF machine type of object files that can be loaded on riscv64, `EM_RISCV`.
+pub const ELF_MACHINE: u16 = 243;
+
+// The relocation types defined by the RISC-V ELF psABI, which aren't defined by `goblin`.
+const R_RISCV_NONE: u32 = 0;
+const R_RISCV_32: u32 = 1;
+const R_RISCV_64: u32 = 2;
+const R_RISCV_BRANCH: u32 = 16;
+const R_RISCV_JAL: u32 = 17;
+const R_RISCV_CALL: u32 = 18;
+const R_RISCV_CALL_PLT: u32 = 19;
+const R_RISCV_GOT_HI20: u32 = 20;
+const R_RISCV_TLS_GOT_HI20: u32 = 21;
+const R_RISCV_PCREL_HI20: u32 = 23;
+const R_RISCV_PCREL_LO12_I: u32 = 24;
+const R_RISCV_PCREL_LO12_S: u32 = 25;
+const R_RISCV_HI20: u32 = 26;
+const R_RISCV_LO12_I: u32 = 27;
+const R_RISCV_LO12_S: u32 = 28;
+const R_RISCV_TPREL_HI20: u32 = 29;
+const R_RISCV_TPREL_LO12_I: u32 = 30;
+const R_RISCV_TPREL_LO12_S: u32 = 31;
+const R_RISCV_TPREL_ADD: u32 = 32;
+const R_RISCV_ALIGN: u32 = 43;
+const R_RISCV_RVC_BRANCH: u32 = 44;
+const R_RISCV_RVC_JUMP: u32 = 45;
+const R_RISCV_RELAX: u32 = 51;
+const R_RISCV_32_PCREL: u32 = 57;
+
+/// The base of the relocation types that Theseus synthesizes by pairing a low-part relocation
+/// with its high part, which is beyond the range of types that the psABI may ever define.
+///
+/// A paired type holds the high part's type in bits `[15:8]` and the low part's type in bits `[7:0]`.
+const PAIRED_BASE: u32 = 0x1_0000;
+
+/// Returns true if the given relocation type's value depends only on the source section.
+pub(crate) fn is_absolute(relocation_type: u32) -> bool {
+    matches!(relocation_type,
+        R_RISCV_32
+        | R_RISCV_64
+        | R_RISCV_HI20
+        | R_RISCV_LO12_I
+        | R_RISCV_LO12_S
+        | R_RISCV_TPREL_HI20
+        | R_RISCV_TPREL_LO12_I
+        | R_RISCV_TPREL_LO12_S
+    )
+}
+
+/// Returns true if the given relocation type has no effect on a loaded crate.
+///
+/// `R_RISCV_TPREL_ADD` only marks the instruction that adds the thread pointer, for relaxation.
+pub(crate) fn is_ignored(relocation_type: u32) -> bool {
+    matches!(relocation_type, R_RISCV_NONE | R_RISCV_ALIGN | R_RISCV_RELAX | R_RISCV_TPREL_ADD)
+}
+
+/// Returns true if the given relocation type computes the high 20 bits of a PC-relative value
+/// whose low 12 bits are computed by other relocations that refer to it.
+pub(crate) fn is_high_part(relocation_type: u32) -> bool {
+    matches!(relocation_type, R_RISCV_PCREL_HI20 | R_RISCV_GOT_HI20 | R_RISCV_TLS_GOT_HI20)
+}
+
+/// Returns true if the given relocation type computes the low 12 bits of a PC-relative value
+/// and refers to the high-part relocation rather than to a symbol.
+pub(crate) fn is_paired_low_part(relocation_type: u32) -> bool {
+    matches!(relocation_type, R_RISCV_PCREL_LO12_I | R_RISCV_PCREL_LO12_S)
+}
+
+/// Combines the given low-part relocation with the high-part relocation it refers to;
+/// see [`RelocationEntry::pair_with()`].
+pub(crate) fn pair_with(low_part: RelocationEntry, high_part: RelocationEntry) -> Result<RelocationEntry, &'static str> {
+    if !is_paired_low_part(low_part.typ) || !is_high_part(high_part.typ) {
+        return Err("BUG: tried to pair relocations that aren't a low part and a high part");
+    }
+    // The low part's value is `S + A - P` of the high part, where `P` is the high part's address.
+    // Adding the distance from the high part to the low part to the addend
+    // lets the value be computed from the low part's own address instead.
+    Ok(RelocationEntry {
+        typ: PAIRED_BASE | (high_part.typ << 8) | low_part.typ,
+        addend: high_part.addend.wrapping_add(low_part.offset).wrapping_sub(high_part.offset),
+        offset: low_part.offset,
+    })
+}
+
+/// Writes the value of the given riscv64 relocation entry; see [`crate::write_relocation()`].
+#[inline(always)]
+pub(crate) fn write_relocation(
+    relocation_entry: RelocationEntry,
+    target_sec_slice: &mut [u8],
+    target_sec_offset: usize,
+    source_sec_vaddr: VirtualAddress,
+    verbose_log: bool
+) -> Result<(), &'static str> {
+    const TWO: isize = 2;
+    const RANGE_9_BIT_SIGNED: Range<isize> = -TWO.pow(8) .. TWO.pow(8);
+    const RANGE_12_BIT_SIGNED: Range<isize> = -TWO.pow(11) .. TWO.pow(11);
+    const RANGE_13_BIT_SIGNED: Range<isize> = -TWO.pow(12) .. TWO.pow(12);
+    const RANGE_21_BIT_SIGNED: Range<isize> = -TWO.pow(20) .. TWO.pow(20);
+    // The range of a 20-bit high part plus a sign-extended 12-bit low part.
+    const RANGE_HI20_LO12: Range<isize> = -TWO.pow(31) - TWO.pow(11) .. TWO.pow(31) - TWO.pow(11);
+
+    let source_val = source_sec_vaddr.value().wrapping_add(relocation_entry.addend);
+    let target_addr = target_sec_slice[target_sec_offset ..].as_ptr() as usize;
+
+    #[allow(clippy::needless_late_init)]
+    let overflow_check: Option<(usize, Range<isize>)>;
+    match relocation_entry.typ {
+        R_RISCV_32 => {
+            write_data(target_sec_slice, target_sec_offset, &(source_val as u32).to_ne_bytes(), verbose_log, source_sec_vaddr);
+            overflow_check = None;
+        }
+        R_RISCV_64 => {
+            write_data(target_sec_slice, target_sec_offset, &(source_val as u64).to_ne_bytes(), verbose_log, source_sec_vaddr);
+            overflow_check = None;
+        }
+        R_RISCV_32_PCREL => {
+            let value = source_val.wrapping_sub(target_addr);
+            write_data(target_sec_slice, target_sec_offset, &(value as u32).to_ne_bytes(), verbose_log, source_sec_vaddr);
+            overflow_check = Some((value, -TWO.pow(31) .. TWO.pow(31)));
+        }
+
+        // Conditional branches (B-type) and jumps (J-type), and their compressed forms.
+        R_RISCV_BRANCH => {
+            let value = source_val.wrapping_sub(target_addr);
+            patch_instruction(target_sec_slice, target_sec_offset, encode_b_type, value, verbose_log)?;
+            overflow_check = Some((value, RANGE_13_BIT_SIGNED));
+        }
+        R_RISCV_JAL => {
+            let value = source_val.wrapping_sub(target_addr);
+            patch_instruction(target_sec_slice, target_sec_offset, encode_j_type, value, verbose_log)?;
+            overflow_check = Some((value, RANGE_21_BIT_SIGNED));
+        }
+        R_RISCV_RVC_BRANCH => {
+            let value = source_val.wrapping_sub(target_addr);
+            patch_compressed_instruction(target_sec_slice, target_sec_offset, encode_cb_type, value, verbose_log)?;
+            overflow_check = Some((value, RANGE_9_BIT_SIGNED));
+        }
+        R_RISCV_RVC_JUMP => {
+            let value = source_val.wrapping_sub(target_addr);
+            patch_compressed_instruction(target_sec_slice, target_sec_offset, encode_cj_type, value, verbose_log)?;
+            overflow_check = Some((value, RANGE_12_BIT_SIGNED));
+        }
+
+        // A call is an `auipc` followed by a `jalr`, which are relocated together.
+        R_RISCV_CALL
+        | R_RISCV_CALL_PLT => {
+            let value = source_val.wrapping_sub(target_addr);
+            patch_instruction(target_sec_slice, target_sec_offset, encode_u_type_hi20, value, verbose_log)?;
+            patch_instruction(target_sec_slice, target_sec_offset + size_of::<u32>(), encode_i_type, value, verbose_log)?;
+            overflow_check = Some((value, RANGE_HI20_LO12));
+        }
+
+        // The high parts of PC-relative values, whose low parts are handled by the paired relocations below.
+        R_RISCV_PCREL_HI20 => {
+            let value = source_val.wrapping_sub(target_addr);
+            patch_instruction(target_sec_slice, target_sec_offset, encode_u_type_hi20, value, verbose_log)?;
+            overflow_check = Some((value, RANGE_HI20_LO12));
+        }
+        R_RISCV_GOT_HI20
+        | R_RISCV_TLS_GOT_HI20 => {
+            let value = got_entry_value(source_sec_vaddr, relocation_entry.addend)?.wrapping_sub(target_addr);
+            patch_instruction(target_sec_slice, target_sec_offset, encode_u_type_hi20, value, verbose_log)?;
+            overflow_check = Some((value, RANGE_HI20_LO12));
+        }
+
+        // Absolute values, used by the `medlow` code model, and TLS offsets, used by the local-exec TLS model.
+        R_RISCV_HI20
+        | R_RISCV_TPREL_HI20 => {
+            patch_instruction(target_sec_slice, target_sec_offset, encode_u_type_hi20, source_val, verbose_log)?;
+            overflow_check = Some((source_val, RANGE_HI20_LO12));
+        }
+        R_RISCV_LO12_I
+        | R_RISCV_TPREL_LO12_I => {
+            patch_instruction(target_sec_slice, target_sec_offset, encode_i_type, source_val, verbose_log)?;
+            overflow_check = None;
+        }
+        R_RISCV_LO12_S
+        | R_RISCV_TPREL_LO12_S => {
+            patch_instruction(target_sec_slice, target_sec_offset, encode_s_type, source_val, verbose_log)?;
+            overflow_check = None;
+        }
+
+        // The low part of a PC-relative value that has been paired with its high part.
+        paired if paired & !0xFFFF == PAIRED_BASE => {
+            let (high_part_typ, low_part_typ) = ((paired >> 8) & 0xFF, paired & 0xFF);
+            let value = match high_part_typ {
+                R_RISCV_PCREL_HI20 => source_val,
+                R_RISCV_GOT_HI20 | R_RISCV_TLS_GOT_HI20 => got_entry_value(source_sec_vaddr, relocation_entry.addend)?,
+                _ => return unsupported(paired),
+            }.wrapping_sub(target_addr);
+            let encode = match low_part_typ {
+                R_RISCV_PCREL_LO12_I => encode_i_type,
+                R_RISCV_PCREL_LO12_S => encode_s_type,
+                _ => return unsupported(paired),
+            };
+            patch_instruction(target_sec_slice, target_sec_offset, encode, value, verbose_log)?;
+            overflow_check = None;
+        }
+        R_RISCV_PCREL_LO12_I
+        | R_RISCV_PCREL_LO12_S => {
+            error!("BUG: R_RISCV_PCREL_LO12_* relocation wasn't paired with its high part before being written");
+            return Err("BUG: R_RISCV_PCREL_LO12_* relocation wasn't paired with its high part");
+        }
+        other => return unsupported(other),
+    }
+
+    // Perform the overflow check, if the relocation type requires it.
+    if let Some((source_val_usize, overflow_range)) = overflow_check {
+        let source_val_isize = source_val_usize as isize;
+        if overflow_range.contains(&source_val_isize) {
+            if verbose_log { trace!("                    overflow check: {} <= {} < {}, {:#X} <= {:#X} < {:#X} --> PASS", overflow_range.start, source_val_isize, overflow_range.end, overflow_range.start, source_val_isize, overflow_range.end); }
+        } else {
+            error!("Overflow check: {:#X} <= {:#X} < {:#X} --> FAIL", overflow_range.start, source_val_isize, overflow_range.end);
+            return Err("Relocation failed overflow check");
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the address of the GOT entry for the source section's address (or TLS offset), plus the addend.
+fn got_entry_value(source_sec_vaddr: VirtualAddress, addend: usize) -> Result<usize, &'static str> {
+    Ok(got::entry_for(source_sec_vaddr.value() as u64)?.value().wrapping_add(addend))
+}
+
+/// Writes the given bytes of a data value to the target.
+fn write_data(target_sec_slice: &mut [u8], target_sec_offset: usize, bytes: &[u8], verbose_log: bool, source_sec_vaddr: VirtualAddress) {
+    let target_ref = &mut target_sec_slice[target_sec_offset .. (target_sec_offset + bytes.len())];
+    if verbose_log { trace!("                    target_ptr: {:p}, source_val: {:X?} (from source_sec_vaddr {:#X})", target_ref.as_ptr(), bytes, source_sec_vaddr); }
+    target_ref.copy_from_slice(bytes);
+}
+
+/// Replaces the immediate fields of the 32-bit instruction at the target with the given `value`,
+/// as encoded by `encode`, which returns the new instruction.
+///
+/// Instructions are always little-endian, and may only be 2-byte aligned.
+fn patch_instruction(
+    target_sec_slice: &mut [u8],
+    target_sec_offset: usize,
+    encode: fn(u32, usize) -> u32,
+    value: usize,
+    verbose_log: bool,
+) -> Result<(), &'static str> {
+    let target_ref = &mut target_sec_slice[target_sec_offset .. (target_sec_offset + size_of::<u32>())];
+    let existing_instr = u32::from_le_bytes(target_ref.try_into().map_err(|_| "BUG: RISC-V relocation target was not a u32")?);
+    let new_instr = encode(existing_instr, value);
+    if verbose_log { trace!("                    target_ptr: {:p}, value: {:#X}, existing_instr: {:#X}, new_instr: {:#X}", target_ref.as_ptr(), value, existing_instr, new_instr); }
+    target_ref.copy_from_slice(&new_instr.to_le_bytes());
+    Ok(())
+}
+
+/// Like [`patch_instruction()`], but for a 16-bit compressed instruction.
+fn patch_compressed_instruction(
+    target_sec_slice: &mut [u8],
+    target_sec_offset: usize,
+    encode: fn(u16, usize) -> u16,
+    value: usize,
+    verbose_log: bool,
+) -> Result<(), &'static str> {
+    let target_ref = &mut target_sec_slice[target_sec_offset .. (target_sec_offset + size_of::<u16>())];
+    let existing_instr = u16::from_le_bytes(target_ref.try_into().map_err(|_| "BUG: RISC-V compressed relocation target was not a u16")?);
+    let new_instr = encode(existing_instr, value);
+    if verbose_log { trace!("                    target_ptr: {:p}, value: {:#X}, existing_instr: {:#X}, new_instr: {:#X}", target_ref.as_ptr(), value, existing_instr, new_instr); }
+    target_ref.copy_from_slice(&new_instr.to_le_bytes());
+    Ok(())
+}
+
+/// Returns the bits `[hi:lo]` of the given value, shifted down to bit 0.
+const fn bits(value: usize, hi: u32, lo: u32) -> u32 {
+    ((value >> lo) & ((1 << (hi - lo + 1)) - 1)) as u32
+}
+
+/// U-type (`lui`, `auipc`): `imm[31:12]` in bits `[31:12]`.
+///
+/// The high part is rounded, because the paired low part is sign-extended.
+fn encode_u_type_hi20(instr: u32, value: usize) -> u32 {
+    (instr & 0xFFF) | (bits(value.wrapping_add(0x800), 31, 12) << 12)
+}
+
+/// I-type (loads, `addi`, `jalr`): `imm[11:0]` in bits `[31:20]`.
+fn encode_i_type(instr: u32, value: usize) -> u32 {
+    (instr & 0x000F_FFFF) | (bits(value, 11, 0) << 20)
+}
+
+/// S-type (stores): `imm[11:5]` in bits `[31:25]` and `imm[4:0]` in bits `[11:7]`.
+fn encode_s_type(instr: u32, value: usize) -> u32 {
+    (instr & 0x01FF_F07F) | (bits(value, 11, 5) << 25) | (bits(value, 4, 0) << 7)
+}
+
+/// B-type (conditional branches): `imm[12|10:5]` in bits `[31:25]` and `imm[4:1|11]` in bits `[11:7]`.
+fn encode_b_type(instr: u32, value: usize) -> u32 {
+    (instr & 0x01FF_F07F)
+        | (bits(value, 12, 12) << 31)
+        | (bits(value, 10, 5) << 25)
+        | (bits(value, 4, 1) << 8)
+        | (bits(value, 11, 11) << 7)
+}
+
+/// J-type (`jal`): `imm[20|10:1|11|19:12]` in bits `[31:12]`.
+fn encode_j_type(instr: u32, value: usize) -> u32 {
+    (instr & 0xFFF)
+        | (bits(value, 20, 20) << 31)
+        | (bits(value, 10, 1) << 21)
+        | (bits(value, 11, 11) << 20)
+        | (bits(value, 19, 12) << 12)
+}
+
+/// CB-type (`c.beqz`, `c.bnez`): `offset[8|4:3]` in bits `[12:10]` and `offset[7:6|2:1|5]` in bits `[6:2]`.
+fn encode_cb_type(instr: u16, value: usize) -> u16 {
+    let encoded = (bits(value, 8, 8) << 12)
+        | (bits(value, 4, 3) << 10)
+        | (bits(value, 7, 6) << 5)
+        | (bits(value, 2, 1) << 3)
+        | (bits(value, 5, 5) << 2);
+    (instr & 0xE383) | encoded as u16
+}
+
+/// CJ-type (`c.j`): `offset[11|4|9:8|10|6|7|3:1|5]` in bits `[12:2]`.
+fn encode_cj_type(instr: u16, value: usize) -> u16 {
+    let encoded = (bits(value, 11, 11) << 12)
+        | (bits(value, 4, 4) << 11)
+        | (bits(value, 9, 8) << 9)
+        | (bits(value, 10, 10) << 8)
+        | (bits(value, 6, 6) << 7)
+        | (bits(value, 7, 7) << 6)
+        | (bits(value, 3, 1) << 3)
+        | (bits(value, 5, 5) << 2);
+    (instr & 0xE003) | encoded as u16
+}
@@ -0,0 +1,133 @@
+//! Relocation calculations for x86_64.
+
+use core::mem::size_of;
+use goblin::elf::{header::EM_X86_64, reloc::*};
+use log::trace;
+use memory::VirtualAddress;
+use crate::{got, unsupported, RelocationEntry};
+
+/// The ELF machine type of object files that can be loaded on x86_64.
+pub const ELF_MACHINE: u16 = EM_X86_64;
+
+/// Relocation types for GOT-relative accesses that the static linker may relax
+/// into direct accesses, which may not be defined by `goblin`.
+const R_X86_64_GOTPCRELX: u32 = 41;
+const R_X86_64_REX_GOTPCRELX: u32 = 42;
+
+/// The opcodes of the `mov` instruction that loads a symbol's address from its GOT entry,
+/// and of the `lea` instruction that computes the symbol's address directly, which it can be relaxed into.
+const MOV_OPCODE: u8 = 0x8b;
+const LEA_OPCODE: u8 = 0x8d;
+
+/// Returns true if the given relocation type's value depends only on the source section.
+pub(crate) fn is_absolute(relocation_type: u32) -> bool {
+    matches!(relocation_type, R_X86_64_32 | R_X86_64_64)
+}
+
+/// Returns true if the given relocation type has no effect on a loaded crate.
+pub(crate) fn is_ignored(relocation_type: u32) -> bool {
+    relocation_type == R_X86_64_NONE
+}
+
+/// Writes the value of the given x86_64 relocation entry; see [`crate::write_relocation()`].
+#[inline(always)]
+pub(crate) fn write_relocation(
+    relocation_entry: RelocationEntry,
+    target_sec_slice: &mut [u8],
+    target_sec_offset: usize,
+    source_sec_vaddr: VirtualAddress,
+    verbose_log: bool
+) -> Result<(), &'static str> {
+
+    match relocation_entry.typ {
+        R_X86_64_32 => {
+            let target_range = target_sec_offset .. (target_sec_offset + size_of::<u32>());
+            let target_ref = &mut target_sec_slice[target_range];
+            let source_val = source_sec_vaddr.value().wrapping_add(relocation_entry.addend) as u32;
+            if verbose_log { trace!("                    target_ptr: {:p}, source_val: {:#X} (from source_sec_vaddr {:#X})", target_ref.as_ptr(), source_val, source_sec_vaddr); }
+            target_ref.copy_from_slice(&source_val.to_ne_bytes());
+        }
+        R_X86_64_64 => {
+            let target_range = target_sec_offset .. (target_sec_offset + size_of::<u64>());
+            let target_ref = &mut target_sec_slice[target_range];
+            let source_val = source_sec_vaddr.value().wrapping_add(relocation_entry.addend) as u64;
+            if verbose_log { trace!("                    target_ptr: {:p}, source_val: {:#X} (from source_sec_vaddr {:#X})", target_ref.as_ptr(), source_val, source_sec_vaddr); }
+            target_ref.copy_from_slice(&source_val.to_ne_bytes());
+        }
+        R_X86_64_PC32
+        | R_X86_64_PLT32 => {
+            let target_range = target_sec_offset .. (target_sec_offset + size_of::<u32>());
+            let target_ref = &mut target_sec_slice[target_range];
+            let source_val = source_sec_vaddr.value().wrapping_add(relocation_entry.addend).wrapping_sub(target_ref.as_ptr() as usize) as u32;
+            if verbose_log { trace!("                    target_ptr: {:p}, source_val: {:#X} (from source_sec_vaddr {:#X})", target_ref.as_ptr(), source_val, source_sec_vaddr); }
+            target_ref.copy_from_slice(&source_val.to_ne_bytes());
+        }
+        R_X86_64_PC64 => {
+            let target_range = target_sec_offset .. (target_sec_offset + size_of::<u64>());
+            let target_ref = &mut target_sec_slice[target_range];
+            let source_val = source_sec_vaddr.value().wrapping_add(relocation_entry.addend).wrapping_sub(target_ref.as_ptr() as usize);
+            if verbose_log { trace!("                    target_ptr: {:p}, source_val: {:#X} (from source_sec_vaddr {:#X})", target_ref.as_ptr(), source_val, source_sec_vaddr); }
+            target_ref.copy_from_slice(&source_val.to_ne_bytes());
+        }
+        R_X86_64_TPOFF32 => {
+            use core::convert::TryFrom;
+            let target_range = target_sec_offset .. (target_sec_offset + size_of::<i32>());
+            let target_ref = &mut target_sec_slice[target_range];
+            // Here we treat the `source_sec_vaddr` value as a signed value 
+            // by casting its bit value directly, i.e., `usize as isize`.
+            let offset_val = source_sec_vaddr.value() as isize;
+            // Now we must check that the signed `offset_val` fits in `i32`
+            let source_val = i32::try_from(offset_val)
+                .map_err(|_| "BUG: TLS relocation (R_X86_64_TPOFF32) source section value (TLS offset) cannot fit in a `i32`")?;
+            if verbose_log { trace!("                    target_ptr: {:p}, source_val: {:#X} (from source_sec_vaddr {:#X})", target_ref.as_ptr(), source_val, source_sec_vaddr); }
+            target_ref.copy_from_slice(&source_val.to_ne_bytes());
+        }
+        R_X86_64_TPOFF64 => {
+            let target_range = target_sec_offset .. (target_sec_offset + size_of::<i64>());
+            let target_ref = &mut target_sec_slice[target_range];
+            // As above, the `source_sec_vaddr` value is the signed TLS offset.
+            let source_val = source_sec_vaddr.value() as i64;
+            if verbose_log { trace!("                    target_ptr: {:p}, source_val: {:#X} (from source_sec_vaddr {:#X})", target_ref.as_ptr(), source_val, source_sec_vaddr); }
+            target_ref.copy_from_slice(&source_val.to_ne_bytes());
+        }
+        // 32-bit signed PC-relative offsets to a GOT entry that holds the source section's address
+        // or, for `R_X86_64_GOTTPOFF` (the initial-exec TLS model), its TLS offset.
+        R_X86_64_GOTPCREL
+        | R_X86_64_GOTPCRELX
+        | R_X86_64_REX_GOTPCRELX
+        | R_X86_64_GOTTPOFF => {
+            use core::convert::TryFrom;
+            let target_range = target_sec_offset .. (target_sec_offset + size_of::<i32>());
+            let target_addr = target_sec_slice[target_range.clone()].as_ptr() as usize;
+            let relaxable = matches!(relocation_entry.typ, R_X86_64_GOTPCRELX | R_X86_64_REX_GOTPCRELX);
+            let opcode = target_sec_offset.checked_sub(2).map(|i| target_sec_slice[i]);
+
+            // A relaxable `mov foo@GOTPCREL(%rip), %reg` is replaced with `lea foo(%rip), %reg`
+            // if `foo` is close enough, such that it doesn't need a GOT entry at all.
+            // Once relaxed, the instruction must stay relaxed when it's relocated again, e.g., during crate swapping.
+            let direct_val = i32::try_from(
+                source_sec_vaddr.value().wrapping_add(relocation_entry.addend).wrapping_sub(target_addr) as isize
+            );
+            let source_val = match (relaxable, opcode, direct_val) {
+                (true, Some(MOV_OPCODE | LEA_OPCODE), Ok(direct_val)) => {
+                    target_sec_slice[target_sec_offset - 2] = LEA_OPCODE;
+                    direct_val
+                }
+                (true, Some(LEA_OPCODE), Err(_)) => {
+                    return Err("relaxed GOTPCRELX relocation's source section is too far away from its target");
+                }
+                _ => {
+                    let entry = got::entry_for(source_sec_vaddr.value() as u64)?;
+                    i32::try_from(entry.value().wrapping_add(relocation_entry.addend).wrapping_sub(target_addr) as isize)
+                        .map_err(|_| "GOT entry is too far away from the target of its GOT-relative relocation")?
+                }
+            };
+            let target_ref = &mut target_sec_slice[target_range];
+            if verbose_log { trace!("                    target_ptr: {:p}, source_val: {:#X} (from source_sec_vaddr {:#X})", target_ref.as_ptr(), source_val, source_sec_vaddr); }
+            target_ref.copy_from_slice(&source_val.to_ne_bytes());
+        }
+        other => return unsupported(other),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,121 @@
+//! A system-wide registry of the unwind information of all loaded crates.
+//!
+//! Once a crate has been loaded and relocated, its `.eh_frame` section is parsed and
+//! registered here under the range of its text pages, such that the unwinder can find
+//! the unwind info for any instruction address, regardless of which `CrateNamespace`
+//! the crate containing it was loaded into.
+//! A crate's unwind info is deregistered when that crate is dropped, i.e., unloaded.
+//!
+//! Crate object files are relocatable, so unlike a fully-linked executable,
+//! they have no `.eh_frame_hdr` section, only an `.eh_frame` section.
+
+use core::ops::{Bound::{Included, Unbounded}, Range};
+use alloc::{collections::BTreeMap, sync::Arc, vec::Vec};
+use gimli::{BaseAddresses, CieOrFde, EhFrame, NativeEndian, UnwindSection};
+use log::error;
+use memory::VirtualAddress;
+use spin::Mutex;
+use crate::{LoadedCrate, StrRef, StrongSectionRef, WeakSectionRef};
+
+/// The unwind info of all loaded crates, keyed by the starting address of each crate's text pages.
+static UNWIND_INFO: Mutex<BTreeMap<VirtualAddress, UnwindInfo>> = Mutex::new(BTreeMap::new());
+
+/// The unwind info of a loaded crate.
+#[derive(Debug, Clone)]
+pub struct UnwindInfo {
+    /// The name of the crate that this unwind info belongs to.
+    pub crate_name: StrRef,
+    /// The bounds of the crate's text pages, which the FDEs in its `.eh_frame` describe.
+    pub text: Range<VirtualAddress>,
+    /// The crate's `.eh_frame` section.
+    pub eh_frame: WeakSectionRef,
+    /// The number of FDEs (frame description entries) in the `.eh_frame` section
+    /// that describe functions in the crate's text pages.
+    pub fde_count: usize,
+}
+
+/// Parses the `.eh_frame` section of the given crate and registers it.
+///
+/// This must only be invoked once the crate's relocations have been performed,
+/// as the addresses in the `.eh_frame` section are relative to the crate's sections.
+///
+/// Returns the number of FDEs that were found in the `.eh_frame` section.
+pub fn register(krate: &LoadedCrate) -> Result<usize, &'static str> {
+    let eh_frame_sec = krate.eh_frame_section().ok_or("crate has no .eh_frame section")?;
+    let text = krate.text_pages.as_ref().ok_or("crate has no text pages")?.1.clone();
+
+    let fde_count = {
+        let sec_pages = eh_frame_sec.mapped_pages.lock();
+        let eh_frame_slice: &[u8] = sec_pages.as_slice(eh_frame_sec.mapped_pages_offset, eh_frame_sec.size)?;
+        let bases = BaseAddresses::default()
+            .set_eh_frame(eh_frame_sec.virt_addr.value() as u64)
+            .set_text(text.start.value() as u64);
+        count_fdes(eh_frame_slice, &bases, &text)?
+    };
+
+    let info = UnwindInfo {
+        crate_name: krate.crate_name.clone(),
+        text: text.clone(),
+        eh_frame: Arc::downgrade(eh_frame_sec),
+        fde_count,
+    };
+    if UNWIND_INFO.lock().insert(text.start, info).is_some() {
+        error!("BUG: unwind info for crate {:?} at {:#X} replaced existing unwind info", krate.crate_name, text.start);
+    }
+    Ok(fde_count)
+}
+
+/// Removes the unwind info of the given crate, returning it if it was registered.
+///
+/// This is done automatically when a [`LoadedCrate`] is dropped.
+pub fn deregister(krate: &LoadedCrate) -> Option<UnwindInfo> {
+    let text_start = krate.text_pages.as_ref()?.1.start;
+    let mut unwind_info = UNWIND_INFO.lock();
+    match unwind_info.get(&text_start) {
+        Some(info) if info.crate_name == krate.crate_name => unwind_info.remove(&text_start),
+        _ => None,
+    }
+}
+
+/// Returns the `.eh_frame` section that contains the unwind info for the given instruction address,
+/// along with the bounds of the text pages of the crate that it belongs to.
+pub fn lookup(address: VirtualAddress) -> Option<(StrongSectionRef, Range<VirtualAddress>)> {
+    let unwind_info = UNWIND_INFO.lock();
+    let (_, info) = unwind_info.range((Unbounded, Included(address))).next_back()?;
+    if !info.text.contains(&address) {
+        return None;
+    }
+    Some((info.eh_frame.upgrade()?, info.text.clone()))
+}
+
+/// Returns a snapshot of all registered unwind info, in order of address.
+pub fn registered() -> Vec<UnwindInfo> {
+    UNWIND_INFO.lock().values().cloned().collect()
+}
+
+/// Parses all entries in the given `.eh_frame` section,
+/// returning the number of FDEs that describe functions within the given `text` bounds.
+///
+/// FDEs outside of those bounds, e.g., for functions in sections that weren't loaded, are ignored.
+fn count_fdes(eh_frame_slice: &[u8], bases: &BaseAddresses, text: &Range<VirtualAddress>) -> Result<usize, &'static str> {
+    let eh_frame = EhFrame::new(eh_frame_slice, NativeEndian);
+    let text_range = text.start.value() as u64 .. text.end.value() as u64;
+    let mut entries = eh_frame.entries(bases);
+    let mut fde_count = 0;
+    while let Some(entry) = entries.next().map_err(|_e| {
+        error!("gimli error while parsing .eh_frame entry: {:?}", _e);
+        "gimli error while parsing .eh_frame entry"
+    })? {
+        if let CieOrFde::Fde(partial_fde) = entry {
+            let fde = partial_fde.parse(EhFrame::cie_from_offset).map_err(|_e| {
+                error!("gimli error while parsing .eh_frame FDE: {:?}", _e);
+                "gimli error while parsing .eh_frame FDE"
+            })?;
+            let start = fde.initial_address();
+            if text_range.contains(&start) && start.saturating_add(fde.len()) <= text_range.end {
+                fde_count += 1;
+            }
+        }
+    }
+    Ok(fde_count)
+}
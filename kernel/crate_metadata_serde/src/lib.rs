@@ -98,6 +98,8 @@ pub const TLS_BSS_SECTION_NAME          : &str = ".tbss";
 pub const CLS_SECTION_NAME              : &str = ".cls";
 pub const GCC_EXCEPT_TABLE_SECTION_NAME : &str = ".gcc_except_table";
 pub const EH_FRAME_SECTION_NAME         : &str = ".eh_frame";
+pub const INIT_ARRAY_SECTION_NAME       : &str = ".init_array";
+pub const FINI_ARRAY_SECTION_NAME       : &str = ".fini_array";
 
 /// The possible types of sections that can be loaded from a crate object file.
 #[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
@@ -138,6 +140,12 @@ pub enum SectionType {
     /// Some documentation here: <https://gcc.gnu.org/wiki/Dwarf2EHNewbiesHowto>
     /// 
     EhFrame,
+    /// An `.init_array` section is a writable array of pointers to constructor functions
+    /// that must be invoked once the crate has been loaded and linked.
+    InitArray,
+    /// A `.fini_array` section is a writable array of pointers to destructor functions
+    /// that must be invoked before the crate is unloaded.
+    FiniArray,
 }
 impl SectionType {    
     /// Returns the const `&str` name of this `SectionType`.
@@ -152,6 +160,8 @@ impl SectionType {
             Self::Cls            => CLS_SECTION_NAME,
             Self::GccExceptTable => GCC_EXCEPT_TABLE_SECTION_NAME,
             Self::EhFrame        => EH_FRAME_SECTION_NAME,
+            Self::InitArray      => INIT_ARRAY_SECTION_NAME,
+            Self::FiniArray      => FINI_ARRAY_SECTION_NAME,
         }
     } 
 
@@ -41,6 +41,8 @@ use mod_mgmt::{
     write_relocation,
     crate_name_from_path,
     replace_containing_crate_name,
+    LoadedCrate,
+    StrongCrateRef,
     StrongSectionRef,
     WeakDependent, StrRef,
 };
@@ -72,14 +74,91 @@ pub fn clear_unloaded_crate_cache() {
 /// See the `swap_crates()` function for more details. 
 pub type StateTransferFunction = fn(&Arc<CrateNamespace>, &CrateNamespace) -> Result<(), &'static str>;
 
+/// A state transfer callback that is passed directly to [`swap_crate()`]
+/// rather than being found by its symbol name in the new crates.
+///
+/// It is invoked with the same arguments as a [`StateTransferFunction`].
+pub type StateTransferCallback<'a> = &'a dyn Fn(&Arc<CrateNamespace>, &CrateNamespace) -> Result<(), &'static str>;
+
+
+/// Callbacks that allow other subsystems to coordinate with crate swapping,
+/// e.g., to quiesce the components implemented by the old crates and then
+/// hand them over to the new crates.
+///
+/// These are invoked by every call to [`swap_crates()`].
+#[derive(Clone, Copy)]
+pub struct SwapHooks {
+    /// Invoked before any crates are swapped, with the full names of the old crates being replaced.
+    /// If this returns an error, the swap is aborted.
+    pub prepare: fn(&[&str]) -> Result<(), &'static str>,
+    /// Invoked after all crates have been successfully swapped, with the new crates that replaced them.
+    pub complete: fn(&[StrongCrateRef]) -> Result<(), &'static str>,
+    /// Invoked if the swap failed after `prepare` succeeded, in which case the old crates are still in use.
+    pub abort: fn(),
+}
+
+/// The hooks registered via [`register_swap_hooks()`].
+static SWAP_HOOKS: Mutex<Vec<SwapHooks>> = Mutex::new(Vec::new());
+
+/// Registers the given `hooks` to be invoked during every crate swapping operation.
+pub fn register_swap_hooks(hooks: SwapHooks) {
+    SWAP_HOOKS.lock().push(hooks);
+}
+
+
+/// Atomically replaces the loaded crate `old_crate_name` in `this_namespace` with a newer version of it
+/// loaded from `new_crate_object_file`.
+///
+/// This is a convenience function for the common case of swapping a single crate;
+/// see [`swap_crates()`] for details of how the new crate is loaded and linked in place of the old one.
+/// The old crate is retired, i.e., it is not cached for future swaps.
+///
+/// The optional `state_transfer` callback is invoked after the new crate has been loaded and
+/// the old crate's `.data` and `.bss` sections have been copied into it,
+/// but before any sections that depend on the old crate are relinked to the new crate,
+/// allowing the old crate's state to be moved into the new crate.
+/// If it returns an error, the swap is aborted and the old crate remains in use, unmodified.
+pub fn swap_crate(
+    this_namespace: &Arc<CrateNamespace>,
+    old_crate_name: &str,
+    new_crate_object_file: IntoCrateObjectFile,
+    state_transfer: Option<StateTransferCallback>,
+    kernel_mmi_ref: &MmiRef,
+    verbose_log: bool,
+) -> Result<(), &'static str> {
+    let swap_request = SwapRequest::new(
+        Some(old_crate_name),
+        Arc::clone(this_namespace),
+        new_crate_object_file,
+        None,
+        false,
+    ).map_err(|invalid_req| {
+        error!("swap_crate(): invalid swap request: {:?}", invalid_req);
+        "swap_crate(): invalid swap request, see the log for details"
+    })?;
+    swap_crates_hooked(
+        this_namespace,
+        vec![swap_request],
+        None,
+        Vec::new(),
+        state_transfer,
+        kernel_mmi_ref,
+        verbose_log,
+        false,
+    )
+}
+
 
 /// Swaps in new crates that can optionally replace existing crates in this `CrateNamespace`.
 /// 
+/// Before and after swapping, this invokes the [`SwapHooks`] registered by other subsystems.
+/// 
 /// See the documentation of the [`SwapRequest`](#struct.SwapRequest.html) struct for more details.
 /// 
 /// In general, the strategy for replacing an old crate `C` with a new crate `C2` consists of three steps:
 /// 1) Load the new replacement crate `C2` from its object file.
-/// 2) Copy the .data and .bss sections from old crate `C` to the new crate `C2`
+/// 2) Copy the .data and .bss sections from old crate `C` to the new crate `C2`,
+///    and invoke the state transfer functions.
 /// 3) Set up new relocation entries that redirect all dependencies on the old crate `C` to the new crate `C2`.
 /// 4) Remove crate `C` and clean it up, e.g., removing its entries from the symbol map.
 ///    Save the removed crate (and its symbol subtrie) in a cache for later use to expedite future swapping operations.
@@ -101,8 +180,10 @@ pub type StateTransferFunction = fn(&Arc<CrateNamespace>, &CrateNamespace) -> Re
 ///   If a crate cannot be found in this directory set, this namespace's directory set will be searched for the crate. 
 ///   If `None`, only this `CrateNamespace`'s directory set (and its recursive namespace's) will be used to find missing crates to be loaded.
 /// * `state_transfer_functions`: the fully-qualified symbol names of the state transfer functions, 
-///   arbitrary functions that are invoked after all of the new crates are loaded but before the old crates are unloaded, 
-///   in order to allow transfer of states from old crates to new crates or proper setup of states for the new crates.
+///   arbitrary functions that are invoked after all of the new crates are loaded but before any existing crates
+///   are relinked to depend on them, in order to allow transfer of states from old crates to new crates
+///   or proper setup of states for the new crates.
+///   If any of them returns an error, the swap is aborted before this namespace has been modified.
 ///   These function should exist in the new namespace (or its directory set) and should take the form of a 
 ///   [`StateTransferFunction`](../type.StateTransferFunction.html).
 ///   The given functions are invoked with the arguments `(current_namespace, new_namespace)`, 
@@ -131,6 +212,77 @@ pub fn swap_crates(
     verbose_log: bool,
     cache_old_crates: bool
 ) -> Result<(), &'static str> {
+    swap_crates_hooked(this_namespace, swap_requests, override_namespace_dir, state_transfer_functions, None, kernel_mmi_ref, verbose_log, cache_old_crates)
+}
+
+/// The implementation of [`swap_crates()`] and [`swap_crate()`], which invokes the [`SwapHooks`] around the actual swap.
+#[allow(clippy::too_many_arguments)]
+fn swap_crates_hooked(
+    this_namespace: &Arc<CrateNamespace>,
+    swap_requests: SwapRequestList,
+    override_namespace_dir: Option<NamespaceDir>,
+    state_transfer_functions: Vec<String>,
+    state_transfer_callback: Option<StateTransferCallback>,
+    kernel_mmi_ref: &MmiRef,
+    verbose_log: bool,
+    cache_old_crates: bool
+) -> Result<(), &'static str> {
+    let hooks = SWAP_HOOKS.lock().clone();
+    if hooks.is_empty() {
+        return swap_crates_inner(this_namespace, swap_requests, override_namespace_dir, state_transfer_functions, state_transfer_callback, kernel_mmi_ref, verbose_log, cache_old_crates);
+    }
+
+    let old_crate_names: Vec<String> = swap_requests.iter()
+        .filter_map(|req| req.old_crate_name.clone())
+        .collect();
+    let mut new_crates = Vec::with_capacity(swap_requests.len());
+    for req in &swap_requests {
+        let new_crate_name = crate_name_from_path(&PathBuf::from(req.new_crate_object_file.lock().get_name()))
+            .ok_or("invalid crate path")?
+            .to_owned();
+        new_crates.push((new_crate_name, Arc::clone(&req.new_namespace)));
+    }
+
+    let old_crate_name_refs: Vec<&str> = old_crate_names.iter().map(String::as_str).collect();
+    for (i, hook) in hooks.iter().enumerate() {
+        if let Err(e) = (hook.prepare)(&old_crate_name_refs) {
+            error!("swap_crates(): a swap hook failed to prepare, aborting swap: {}", e);
+            hooks[..i].iter().for_each(|h| (h.abort)());
+            return Err(e);
+        }
+    }
+
+    match swap_crates_inner(this_namespace, swap_requests, override_namespace_dir, state_transfer_functions, state_transfer_callback, kernel_mmi_ref, verbose_log, cache_old_crates) {
+        Ok(()) => {
+            let new_crate_refs: Vec<StrongCrateRef> = new_crates.iter()
+                .filter_map(|(name, ns)| ns.get_crate(name))
+                .collect();
+            for hook in &hooks {
+                if let Err(e) = (hook.complete)(&new_crate_refs) {
+                    error!("swap_crates(): a swap hook failed to complete: {}", e);
+                }
+            }
+            Ok(())
+        }
+        Err(e) => {
+            hooks.iter().for_each(|h| (h.abort)());
+            Err(e)
+        }
+    }
+}
+
+/// The implementation of [`swap_crates()`], excluding the invocation of [`SwapHooks`].
+#[allow(clippy::too_many_arguments)]
+fn swap_crates_inner(
+    this_namespace: &Arc<CrateNamespace>,
+    swap_requests: SwapRequestList,
+    override_namespace_dir: Option<NamespaceDir>,
+    state_transfer_functions: Vec<String>,
+    state_transfer_callback: Option<StateTransferCallback>,
+    kernel_mmi_ref: &MmiRef,
+    verbose_log: bool,
+    cache_old_crates: bool
+) -> Result<(), &'static str> {
 
     #[cfg(not(loscd_eval))]
     debug!("swap_crates()[0]: \n\t-->override dir: {:?}, \n\t-->cache_old_crates: {:?}, \n\t-->state transfer: {:?},\n\t-->swap_requests: {:?}", 
@@ -217,17 +369,83 @@ pub fn swap_crates(
     #[cfg(loscd_eval)]
     let mut hpet_total_bss_transfer = 0;
 
+    // Before changing anything, ensure that each new crate satisfies the version requirements
+    // of the existing crates that depend on the old crate it replaces.
+    for req in &swap_requests {
+        let old_crate_is_loaded = req.old_crate_name.as_deref()
+            .and_then(|ocn| CrateNamespace::get_crate_and_namespace(&req.old_namespace, ocn))
+            .is_some();
+        if !old_crate_is_loaded {
+            continue;
+        }
+        let new_crate_name = crate_name_from_path(&PathBuf::from(req.new_crate_object_file.lock().get_name())).ok_or("invalid crate path")?.to_owned();
+        if let Some(new_crate_ref) = namespace_of_new_crates.get_crate(&new_crate_name) {
+            mod_mgmt::versions::check_dependents(this_namespace, &new_crate_ref.lock_as_ref()).map_err(|e| {
+                error!("swap_crates(): can't swap in new crate {:?}: {}", new_crate_name, e);
+                "swap_crates(): the new crate doesn't satisfy the version requirements of crates that depend on the old one"
+            })?;
+        }
+    }
+
     // The name of the new crate in each swap request. There is one entry per swap request.
     let mut new_crate_names: Vec<String> = Vec::with_capacity(swap_requests.len());
     // Whether the old crate was actually loaded into the old namespace. There is one entry per swap request.
     let mut old_crates_are_loaded: Vec<bool> = Vec::with_capacity(swap_requests.len());
 
+    // Declare the renamed symbols of each new crate, such that the sections of the old crates
+    // can be matched with the corresponding renamed sections in the new crates.
+    for req in &swap_requests {
+        for (old_symbol, new_symbol) in &req.symbol_aliases {
+            namespace_of_new_crates.add_symbol_alias(old_symbol, new_symbol)?;
+        }
+    }
+
+    // Transfer the state of each old crate into its new crate before relinking anything,
+    // such that a failed state transfer leaves the existing crates in this namespace untouched.
+    for req in &swap_requests {
+        let old_crate_ref = match req.old_crate_name.as_deref().and_then(|ocn| CrateNamespace::get_crate_and_namespace(&req.old_namespace, ocn)) {
+            Some((ocr, _ns)) => ocr,
+            _ => continue,
+        };
+        let new_crate_name = crate_name_from_path(&PathBuf::from(req.new_crate_object_file.lock().get_name())).ok_or("invalid crate path")?.to_owned();
+        let new_crate_ref = namespace_of_new_crates.get_crate(&new_crate_name)
+            .ok_or("BUG: swap_crates(): Couldn't get new crate that should've been loaded into the new namespace")?;
+
+        #[cfg(loscd_eval)]
+        let hpet_start_bss_transfer = hpet.get_counter();
+
+        copy_data_sections(&old_crate_ref.lock_as_ref(), &new_crate_ref.lock_as_ref())?;
+
+        #[cfg(loscd_eval)] {
+            let hpet_end_bss_transfer = hpet.get_counter();
+            hpet_total_bss_transfer += hpet_end_bss_transfer - hpet_start_bss_transfer;
+        }
+    }
+
+    // Execute the provided state transfer functions
+    for symbol in state_transfer_functions {
+        let state_transfer_fn_sec = namespace_of_new_crates.get_symbol_or_load(&symbol, Some(this_namespace), kernel_mmi_ref, verbose_log).upgrade()
+            // as a backup, search fuzzily to accommodate state transfer function symbol names without full hashes
+            .or_else(|| namespace_of_new_crates.get_symbol_starting_with(&symbol).upgrade())
+            .ok_or("couldn't find specified state transfer function in the new CrateNamespace")?;
+        // FIXME SAFETY: None. swap_crates should probably be unsafe as there is no guaranteed that the state transfer functions have the correct signature.
+        let st_fn = unsafe { state_transfer_fn_sec.as_func::<StateTransferFunction>() }?;
+        #[cfg(not(loscd_eval))]
+        debug!("swap_crates(): invoking the state transfer function {:?} with old_ns: {:?}, new_ns: {:?}", symbol, this_namespace.name(), namespace_of_new_crates.name());
+        st_fn(this_namespace, &namespace_of_new_crates)?;
+    }
+    if let Some(callback) = state_transfer_callback {
+        #[cfg(not(loscd_eval))]
+        debug!("swap_crates(): invoking the state transfer callback with old_ns: {:?}, new_ns: {:?}", this_namespace.name(), namespace_of_new_crates.name());
+        callback(this_namespace, &namespace_of_new_crates)?;
+    }
+
     // Now that we have loaded all of the new modules into the new namepsace in isolation,
     // we simply need to fix up all of the relocations `WeakDependents` for each of the existing sections
     // that depend on the old crate that we're replacing here,
     // such that they refer to the new_module instead of the old_crate.
     for req in &swap_requests {
-        let SwapRequest { old_crate_name, old_namespace, new_crate_object_file, new_namespace: _new_ns, reexport_new_symbols_as_old } = req; 
+        let SwapRequest { old_crate_name, old_namespace, new_crate_object_file, new_namespace: _new_ns, reexport_new_symbols_as_old, symbol_aliases: _ } = req; 
         let reexport_new_symbols_as_old = *reexport_new_symbols_as_old;
 
         // Populate the list of new crate names for future usage.
@@ -281,38 +499,6 @@ pub fn swap_crates(
             let new_crate_name_without_hash = String::from(new_crate.crate_name_without_hash());
             let crates_have_same_name = old_crate_name_without_hash == new_crate_name_without_hash;
 
-            #[cfg(loscd_eval)]
-            let hpet_start_bss_transfer = hpet.get_counter();
-
-            // Go through all the `.data` and `.bss` sections and copy over the old_sec into the new source_sec,
-            // as they represent static variables that would otherwise result in a loss of data.
-            for old_sec in old_crate.data_sections_iter() {
-                let old_sec_name_without_hash = old_sec.name_without_hash();
-                // get the section from the new crate that corresponds to the `old_sec`
-                let prefix = if crates_have_same_name {
-                    Cow::from(old_sec_name_without_hash)
-                } else if let Some(s) = replace_containing_crate_name(old_sec_name_without_hash, &old_crate_name_without_hash, &new_crate_name_without_hash) {
-                    Cow::from(s)
-                } else {
-                    Cow::from(old_sec_name_without_hash)
-                };
-                let new_dest_sec = {
-                    let mut iter = new_crate.data_sections_iter().filter(|sec| sec.name.starts_with(&*prefix));
-                    iter.next()
-                        .filter(|_| iter.next().is_none()) // ensure single element
-                        .ok_or("couldn't find destination section in new crate to copy old_sec's data into (.data/.bss state transfer)")
-                }?;
-
-                #[cfg(not(loscd_eval))]
-                debug!("swap_crates(): copying .data or .bss section from old {:?} to new {:?}", old_sec, new_dest_sec);
-                old_sec.copy_section_data_to(new_dest_sec)?;
-            }
-
-            #[cfg(loscd_eval)] {
-                let hpet_end_bss_transfer = hpet.get_counter();
-                hpet_total_bss_transfer += hpet_end_bss_transfer - hpet_start_bss_transfer;
-            }
-
             // We need to find all of the "weak dependents" (sections that depend on the sections in the old crate)
             // and replace them by rewriting their relocation entries to point to the corresponding new section in the new_crate.
             //
@@ -359,7 +545,11 @@ pub fn swap_crates(
                             namespace_of_new_crates.get_symbol(&old_sec.name).upgrade()
                                 .or_else(|| namespace_of_new_crates.get_symbol_starting_with(old_sec_name_without_hash).upgrade())
                         }
-                    }.ok_or_else(|| {
+                    }.or_else(|| {
+                        // As a last resort, the old section may have been renamed in the new crate.
+                        namespace_of_new_crates.get_aliased_symbol_and_namespace(&old_sec.name)
+                            .and_then(|(new_sec, _ns)| new_sec.upgrade())
+                    }).ok_or_else(|| {
                         error!("swap_crates(): couldn't find section in the new crate that corresponds to a match of the old section {:?}", old_sec.name);
                         "couldn't find section in the new crate that corresponds to a match of the old section"
                     })?;
@@ -501,20 +691,6 @@ pub fn swap_crates(
         } // end of scope, drops lock on `new_crate_ref`
     } // end of iterating over all swap requests to fix up old crate dependents
 
-
-    // Execute the provided state transfer functions
-    for symbol in state_transfer_functions {
-        let state_transfer_fn_sec = namespace_of_new_crates.get_symbol_or_load(&symbol, Some(this_namespace), kernel_mmi_ref, verbose_log).upgrade()
-            // as a backup, search fuzzily to accommodate state transfer function symbol names without full hashes
-            .or_else(|| namespace_of_new_crates.get_symbol_starting_with(&symbol).upgrade())
-            .ok_or("couldn't find specified state transfer function in the new CrateNamespace")?;
-        // FIXME SAFETY: None. swap_crates should probably be unsafe as there is no guaranteed that the state transfer functions have the correct signature.
-        let st_fn = unsafe { state_transfer_fn_sec.as_func::<StateTransferFunction>() }?;
-        #[cfg(not(loscd_eval))]
-        debug!("swap_crates(): invoking the state transfer function {:?} with old_ns: {:?}, new_ns: {:?}", symbol, this_namespace.name(), namespace_of_new_crates.name());
-        st_fn(this_namespace, &namespace_of_new_crates)?;
-    }
-
     // Sanity check that we correctly populated the lists "new_crate_names" and "old_crates_are_loaded". 
     if swap_requests.len() != new_crate_names.len() &&  swap_requests.len() != old_crates_are_loaded.len() {
         return Err("BUG: swap_crates(): didn't properly populate the list of `new_crate_names` and/or `old_crates_are_loaded`.");
@@ -524,13 +700,13 @@ pub fn swap_crates(
     // This doesn't mean each crate will be immediately dropped -- they still might be in use by other crates or tasks.
     for ((req, new_crate_name), is_old_crate_loaded) in swap_requests.iter().zip(new_crate_names.iter()).zip(old_crates_are_loaded.iter()) {
         if !is_old_crate_loaded { continue; }
-        let SwapRequest { old_crate_name, old_namespace, new_crate_object_file: _, new_namespace, reexport_new_symbols_as_old } = req;
+        let SwapRequest { old_crate_name, old_namespace, new_crate_object_file: _, new_namespace, reexport_new_symbols_as_old, symbol_aliases: _ } = req;
         let old_crate_name = match old_crate_name {
             Some(ocn) => ocn,
             _ => continue,
         };
         // Remove the old crate from the namespace that it was previously in, and remove its sections' symbols too.
-        if let Some(old_crate_ref) = old_namespace.crate_tree().lock().remove(old_crate_name.as_bytes()) {
+        if let Some(old_crate_ref) = old_namespace.remove_crate(old_crate_name) {
             {
                 let old_crate = old_crate_ref.lock_as_ref();
 
@@ -555,6 +731,7 @@ pub fn swap_crates(
                             new_crate_object_file: ByAddress(old_crate.object_file.clone()),
                             new_namespace: ByAddress(Arc::clone(old_namespace)),
                             reexport_new_symbols_as_old: !old_crate.reexported_symbols.is_empty(),
+                            symbol_aliases: Vec::new(),
                         };
                         future_swap_requests.push(future_swap_req);
                     }
@@ -593,7 +770,7 @@ pub fn swap_crates(
             
             if cache_old_crates {
                 #[cfg(not(loscd_eval))]
-                cached_crates.crate_tree().lock().insert(old_crate_name.as_str().into(), old_crate_ref);
+                cached_crates.add_crate(old_crate_name.as_str().into(), old_crate_ref);
             }
 
             #[cfg(loscd_eval)]
@@ -612,14 +789,22 @@ pub fn swap_crates(
     for ((req, new_crate_name), is_old_crate_loaded) in swap_requests.iter().zip(new_crate_names.iter()).zip(old_crates_are_loaded.iter()) {
         // We only expect the new crate to have been loaded into the temp namespace if the old crate was actually loaded in the old namespace
         if !is_old_crate_loaded { continue; }
-        let new_crate_ref = namespace_of_new_crates.crate_tree().lock().remove(new_crate_name.as_bytes())
+        let new_crate_ref = namespace_of_new_crates.remove_crate(new_crate_name)
             .ok_or("BUG: swap_crates(): new crate specified by swap request was not found in the new namespace")?;
         
         #[cfg(not(loscd_eval))]
         debug!("swap_crates(): adding new crate {:?} to namespace {}", new_crate_ref, req.new_namespace.name());
 
         req.new_namespace.add_symbols(new_crate_ref.lock_as_ref().sections.values(), verbose_log);
-        req.new_namespace.crate_tree().lock().insert(new_crate_name.as_str().into(), new_crate_ref.clone());
+        req.new_namespace.add_crate(new_crate_name.as_str().into(), new_crate_ref.clone());
+    }
+    
+    // Keep the symbol aliases declared by each swap request in the new namespace,
+    // such that crates loaded later that still refer to the old symbol names can be linked against the renamed ones.
+    for req in swap_requests.iter() {
+        for (old_symbol, new_symbol) in &req.symbol_aliases {
+            req.new_namespace.add_symbol_alias(old_symbol, new_symbol)?;
+        }
     }
     
     // Other crates may have been loaded from their object files into the `namespace_of_new_crates` as dependendencies (required by the new crates specified by swap requests).
@@ -664,7 +849,7 @@ pub fn swap_crates(
             // #[cfg(not(loscd_eval))]
             // warn!("swap_crates(): untested scenario of adding new non-requested (dependency) crate {:?} to namespace {}", new_crate_ref, target_ns.name());
             target_ns.add_symbols(new_crate_ref.lock_as_ref().sections.values(), verbose_log);
-            target_ns.crate_tree().lock().insert(new_crate_name.into(), new_crate_ref.clone());
+            target_ns.add_crate(new_crate_name.into(), new_crate_ref.clone());
         }
         else {
             #[cfg(not(loscd_eval))] {
@@ -688,7 +873,7 @@ pub fn swap_crates(
     // Effectively, we're swapping the new crate object file with the old. 
     // Also, since the SwapRequest struct uses direct file references, we don't need to update them when we move the file. 
     for req in swap_requests.iter() {
-        let SwapRequest { old_crate_name, old_namespace, new_crate_object_file, new_namespace, reexport_new_symbols_as_old: _ } = req;
+        let SwapRequest { old_crate_name, old_namespace, new_crate_object_file, new_namespace, reexport_new_symbols_as_old: _, symbol_aliases: _ } = req;
 
         let source_dir_ref = new_crate_object_file.lock().get_parent_dir().ok_or("BUG: new_crate_object_file has no parent directory")?;
         let dest_dir_ref   = new_namespace.dir().deref();
@@ -774,6 +959,39 @@ pub fn swap_crates(
 }
 
 
+/// Copies the contents of all `.data` and `.bss` sections in the `old_crate` into the
+/// corresponding sections in the `new_crate`, as they represent static variables
+/// that would otherwise result in a loss of data.
+fn copy_data_sections(old_crate: &LoadedCrate, new_crate: &LoadedCrate) -> Result<(), &'static str> {
+    let old_crate_name_without_hash = old_crate.crate_name_without_hash();
+    let new_crate_name_without_hash = new_crate.crate_name_without_hash();
+    let crates_have_same_name = old_crate_name_without_hash == new_crate_name_without_hash;
+
+    for old_sec in old_crate.data_sections_iter() {
+        let old_sec_name_without_hash = old_sec.name_without_hash();
+        // get the section from the new crate that corresponds to the `old_sec`
+        let prefix = if crates_have_same_name {
+            Cow::from(old_sec_name_without_hash)
+        } else if let Some(s) = replace_containing_crate_name(old_sec_name_without_hash, old_crate_name_without_hash, new_crate_name_without_hash) {
+            Cow::from(s)
+        } else {
+            Cow::from(old_sec_name_without_hash)
+        };
+        let new_dest_sec = {
+            let mut iter = new_crate.data_sections_iter().filter(|sec| sec.name.starts_with(&*prefix));
+            iter.next()
+                .filter(|_| iter.next().is_none()) // ensure single element
+                .ok_or("couldn't find destination section in new crate to copy old_sec's data into (.data/.bss state transfer)")
+        }?;
+
+        #[cfg(not(loscd_eval))]
+        debug!("swap_crates(): copying .data or .bss section from old {:?} to new {:?}", old_sec, new_dest_sec);
+        old_sec.copy_section_data_to(new_dest_sec)?;
+    }
+    Ok(())
+}
+
+
 /// Convenience function that removes the given `file` from its parent directory 
 /// and inserts it into the given destination directory. 
 /// 
@@ -883,6 +1101,9 @@ pub struct SwapRequest {
     /// Whether to expose the new crate's sections with symbol names that match those from the old crate.
     /// For more details, see the above docs for this struct.
     reexport_new_symbols_as_old: bool,
+    /// Aliases from the old names of symbols that were renamed in the new crate to their new names.
+    /// See [`SwapRequest::add_symbol_alias()`].
+    symbol_aliases: Vec<(String, String)>,
 }
 impl fmt::Debug for SwapRequest {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -895,6 +1116,7 @@ impl fmt::Debug for SwapRequest {
             )
             .field("new_namespace", &self.new_namespace.name())
             .field("reexport_symbols", &self.reexport_new_symbols_as_old)
+            .field("symbol_aliases", &self.symbol_aliases)
             .finish()
     }
 }
@@ -1036,8 +1258,20 @@ impl SwapRequest {
             new_crate_object_file: ByAddress(verified_new_crate_file),
             new_namespace: ByAddress(new_namespace),
             reexport_new_symbols_as_old,
+            symbol_aliases: Vec::new(),
         })
     }
+
+    /// Declares that the symbol `old_symbol` from the old crate was renamed to `new_symbol` in the new crate.
+    ///
+    /// Crates that depend on `old_symbol` will be relinked against `new_symbol` during the swap.
+    /// Afterwards, this alias is kept in the `new_namespace`, such that crates loaded later
+    /// that still refer to `old_symbol` can be linked against `new_symbol` too,
+    /// until the alias is removed via [`CrateNamespace::remove_symbol_alias()`].
+    /// See [`CrateNamespace::add_symbol_alias()`] for the format of symbol names.
+    pub fn add_symbol_alias(&mut self, old_symbol: &str, new_symbol: &str) {
+        self.symbol_aliases.push((String::from(old_symbol), String::from(new_symbol)));
+    }
 }
 
 /// The possible errors that can occur when trying to create a valid `SwapRequest`. 
@@ -0,0 +1,324 @@
+//! Embeds a crate's version, and the versions of the crates it depends on, into its object file.
+//!
+//! A crate declares its own version by invoking [`declare!`] once, which records the crate's
+//! `Cargo.toml` version (and optionally a hash of its API) in a `__CRATE_VERSION` static.
+//! A crate that depends on other versioned crates invokes [`requires!`] with their names,
+//! which records the version of each dependency that it was *compiled* against
+//! in a `__CRATE_REQUIREMENTS` static.
+//!
+//! When `mod_mgmt` links a crate, or when `crate_swap` replaces one, it reads these records from the
+//! crates' loaded sections and refuses to link crates whose dependencies aren't compatible,
+//! following semantic versioning: a dependency satisfies a requirement if it has the same
+//! major version (or the same minor version, for `0.x` versions) and isn't older than the requirement.
+//!
+//! ```ignore
+//! crate_version::declare!();
+//! crate_version::requires!(task, memory);
+//! ```
+
+#![no_std]
+
+use core::fmt;
+
+/// The name of the static generated by [`declare!`], which `mod_mgmt` looks for in loaded crates.
+pub const VERSION_SYMBOL_NAME: &str = "__CRATE_VERSION";
+/// The name of the static generated by [`requires!`], which `mod_mgmt` looks for in loaded crates.
+pub const REQUIREMENTS_SYMBOL_NAME: &str = "__CRATE_REQUIREMENTS";
+
+/// The magic bytes at the start of an encoded [`CrateVersion`].
+const MAGIC: [u8; 4] = *b"CVER";
+/// The size of an encoded [`CrateVersion`]: the magic bytes, three `u32`s, padding, and a `u64`.
+pub const VERSION_RECORD_SIZE: usize = 4 + 3 * 4 + 4 + 8;
+/// The maximum length of a crate name in a [`Requirement`].
+pub const MAX_NAME_LEN: usize = 40;
+/// The size of an encoded [`Requirement`]: the name's length, the name, and a [`CrateVersion`].
+pub const REQUIREMENT_SIZE: usize = 8 + MAX_NAME_LEN + VERSION_RECORD_SIZE;
+
+/// The version of a crate, along with an optional hash of its API.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CrateVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+    /// A hash of the crate's exported signatures, or `0` if unknown.
+    pub api_hash: u64,
+}
+
+impl CrateVersion {
+    /// Parses a version of the form `major.minor.patch`, ignoring any pre-release or build suffix.
+    ///
+    /// Panics (at compile time, if used in a const context) if the version is malformed.
+    pub const fn parse(version: &str) -> CrateVersion {
+        let bytes = version.as_bytes();
+        let mut parts = [0u32; 3];
+        let mut part = 0;
+        let mut digits = 0;
+        let mut i = 0;
+        while i < bytes.len() {
+            let b = bytes[i];
+            if b == b'-' || b == b'+' {
+                break;
+            } else if b == b'.' {
+                assert!(digits > 0 && part < 2, "malformed crate version");
+                part += 1;
+                digits = 0;
+            } else {
+                assert!(b.is_ascii_digit(), "malformed crate version");
+                parts[part] = parts[part] * 10 + (b - b'0') as u32;
+                digits += 1;
+            }
+            i += 1;
+        }
+        assert!(digits > 0 && part == 2, "malformed crate version");
+        CrateVersion { major: parts[0], minor: parts[1], patch: parts[2], api_hash: 0 }
+    }
+
+    /// Returns this version with the given API hash.
+    pub const fn with_api_hash(mut self, api_hash: u64) -> CrateVersion {
+        self.api_hash = api_hash;
+        self
+    }
+
+    /// Checks whether this version of a crate satisfies the given `required` version of it.
+    ///
+    /// API hashes are only compared if both versions have one and are otherwise identical,
+    /// which catches API changes that weren't accompanied by a version bump.
+    pub fn satisfies(&self, required: &CrateVersion) -> Result<(), Incompatibility> {
+        let same_series = self.major == required.major && (self.major != 0 || self.minor == required.minor);
+        if !same_series {
+            return Err(Incompatibility::Breaking);
+        }
+        if (self.minor, self.patch) < (required.minor, required.patch) {
+            return Err(Incompatibility::Older);
+        }
+        let same_version = (self.minor, self.patch) == (required.minor, required.patch);
+        if same_version && self.api_hash != 0 && required.api_hash != 0 && self.api_hash != required.api_hash {
+            return Err(Incompatibility::ApiChanged);
+        }
+        Ok(())
+    }
+
+    /// Encodes this version as it's embedded in an object file.
+    pub const fn encode(&self) -> [u8; VERSION_RECORD_SIZE] {
+        let mut out = [0u8; VERSION_RECORD_SIZE];
+        out = copy(out, 0, &MAGIC);
+        out = copy(out, 4, &self.major.to_le_bytes());
+        out = copy(out, 8, &self.minor.to_le_bytes());
+        out = copy(out, 12, &self.patch.to_le_bytes());
+        out = copy(out, 20, &self.api_hash.to_le_bytes());
+        out
+    }
+
+    /// Decodes a version that was encoded by [`CrateVersion::encode()`].
+    pub fn decode(bytes: &[u8]) -> Option<CrateVersion> {
+        let bytes = bytes.get(..VERSION_RECORD_SIZE)?;
+        if bytes[..4] != MAGIC {
+            return None;
+        }
+        let u32_at = |i: usize| u32::from_le_bytes([bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]]);
+        let mut api_hash = [0u8; 8];
+        api_hash.copy_from_slice(&bytes[20..28]);
+        Some(CrateVersion {
+            major: u32_at(4),
+            minor: u32_at(8),
+            patch: u32_at(12),
+            api_hash: u64::from_le_bytes(api_hash),
+        })
+    }
+}
+
+impl fmt::Display for CrateVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)?;
+        if self.api_hash != 0 {
+            write!(f, " (API {:016x})", self.api_hash)?;
+        }
+        Ok(())
+    }
+}
+
+/// The reasons why a version of a crate doesn't satisfy a requirement.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Incompatibility {
+    /// The versions differ in a way that semantic versioning considers to be breaking.
+    Breaking,
+    /// The version is older than the required version.
+    Older,
+    /// The versions are identical, but the crate's API hash differs.
+    ApiChanged,
+    /// The crate doesn't declare a version.
+    Unversioned,
+}
+
+impl fmt::Display for Incompatibility {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Breaking => "incompatible version",
+            Self::Older => "older version",
+            Self::ApiChanged => "API changed without a version change",
+            Self::Unversioned => "no version declared",
+        })
+    }
+}
+
+/// A crate's requirement on the version of one of its dependencies.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Requirement {
+    name: [u8; MAX_NAME_LEN],
+    name_len: usize,
+    pub version: CrateVersion,
+}
+
+impl Requirement {
+    /// Creates a requirement on the given version of the crate with the given name.
+    pub const fn new(crate_name: &str, version: CrateVersion) -> Requirement {
+        let bytes = crate_name.as_bytes();
+        assert!(bytes.len() <= MAX_NAME_LEN, "crate name is too long for a version requirement");
+        let mut name = [0u8; MAX_NAME_LEN];
+        name = copy(name, 0, bytes);
+        Requirement { name, name_len: bytes.len(), version }
+    }
+
+    /// Returns the name of the required crate, without a hash.
+    pub fn crate_name(&self) -> &str {
+        core::str::from_utf8(&self.name[..self.name_len]).unwrap_or("<invalid>")
+    }
+
+    /// Encodes this requirement as it's embedded in an object file.
+    pub const fn encode(&self) -> [u8; REQUIREMENT_SIZE] {
+        let mut out = [0u8; REQUIREMENT_SIZE];
+        out = copy(out, 0, &(self.name_len as u64).to_le_bytes());
+        out = copy(out, 8, &self.name);
+        out = copy(out, 8 + MAX_NAME_LEN, &self.version.encode());
+        out
+    }
+
+    /// Decodes a requirement that was encoded by [`Requirement::encode()`].
+    pub fn decode(bytes: &[u8]) -> Option<Requirement> {
+        let bytes = bytes.get(..REQUIREMENT_SIZE)?;
+        let mut name_len = [0u8; 8];
+        name_len.copy_from_slice(&bytes[..8]);
+        let name_len = usize::try_from(u64::from_le_bytes(name_len)).ok().filter(|&len| len <= MAX_NAME_LEN)?;
+        let mut name = [0u8; MAX_NAME_LEN];
+        name.copy_from_slice(&bytes[8..8 + MAX_NAME_LEN]);
+        core::str::from_utf8(&name[..name_len]).ok()?;
+        let version = CrateVersion::decode(&bytes[8 + MAX_NAME_LEN..])?;
+        Some(Requirement { name, name_len, version })
+    }
+
+    /// Decodes all requirements in the contents of a `__CRATE_REQUIREMENTS` static,
+    /// skipping any that are malformed.
+    pub fn decode_all(bytes: &[u8]) -> impl Iterator<Item = Requirement> + '_ {
+        bytes.chunks_exact(REQUIREMENT_SIZE).filter_map(Requirement::decode)
+    }
+}
+
+/// Encodes the given requirements as they're embedded in an object file.
+pub const fn encode_requirements<const N: usize>(requirements: [Requirement; N]) -> [[u8; REQUIREMENT_SIZE]; N] {
+    let mut out = [[0u8; REQUIREMENT_SIZE]; N];
+    let mut i = 0;
+    while i < N {
+        out[i] = requirements[i].encode();
+        i += 1;
+    }
+    out
+}
+
+/// Returns `dest` with `src` copied into it at the given offset.
+const fn copy<const N: usize>(mut dest: [u8; N], offset: usize, src: &[u8]) -> [u8; N] {
+    let mut i = 0;
+    while i < src.len() {
+        dest[offset + i] = src[i];
+        i += 1;
+    }
+    dest
+}
+
+/// Declares the version of the current crate, which is taken from its `Cargo.toml`.
+///
+/// An optional API hash, e.g., of the crate's exported signatures as computed by a build script,
+/// can be given as `declare!(api_hash = <expr>)`.
+///
+/// This must be invoked at most once per crate, at the top level of the crate's root module.
+#[macro_export]
+macro_rules! declare {
+    () => {
+        $crate::declare!(api_hash = 0);
+    };
+    (api_hash = $api_hash:expr) => {
+        /// The version of this crate, which dependent crates record via `crate_version::requires!()`.
+        pub const CRATE_VERSION: $crate::CrateVersion =
+            $crate::CrateVersion::parse(env!("CARGO_PKG_VERSION")).with_api_hash($api_hash);
+
+        #[doc(hidden)]
+        #[used]
+        pub static __CRATE_VERSION: [u8; $crate::VERSION_RECORD_SIZE] = CRATE_VERSION.encode();
+    };
+}
+
+/// Records the versions of the given dependencies that the current crate is compiled against,
+/// each of which must have invoked [`declare!`].
+///
+/// This must be invoked at most once per crate, at the top level of the crate's root module.
+#[macro_export]
+macro_rules! requires {
+    ($($dependency:ident),+ $(,)?) => {
+        #[doc(hidden)]
+        #[used]
+        pub static __CRATE_REQUIREMENTS: [[u8; $crate::REQUIREMENT_SIZE]; [$(stringify!($dependency)),+].len()] =
+            $crate::encode_requirements([
+                $($crate::Requirement::new(stringify!($dependency), $dependency::CRATE_VERSION)),+
+            ]);
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use super::*;
+
+    const fn v(major: u32, minor: u32, patch: u32) -> CrateVersion {
+        CrateVersion { major, minor, patch, api_hash: 0 }
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(CrateVersion::parse("1.22.333"), v(1, 22, 333));
+        assert_eq!(CrateVersion::parse("0.1.0-alpha.1"), v(0, 1, 0));
+        assert_eq!(CrateVersion::parse("2.0.1+build5"), v(2, 0, 1));
+        assert!(std::panic::catch_unwind(|| CrateVersion::parse("1.2")).is_err());
+        assert!(std::panic::catch_unwind(|| CrateVersion::parse("1..2")).is_err());
+    }
+
+    #[test]
+    fn semver_compatibility() {
+        assert_eq!(v(1, 4, 0).satisfies(&v(1, 2, 3)), Ok(()));
+        assert_eq!(v(1, 2, 2).satisfies(&v(1, 2, 3)), Err(Incompatibility::Older));
+        assert_eq!(v(2, 0, 0).satisfies(&v(1, 2, 3)), Err(Incompatibility::Breaking));
+        assert_eq!(v(0, 1, 5).satisfies(&v(0, 1, 2)), Ok(()));
+        assert_eq!(v(0, 2, 0).satisfies(&v(0, 1, 2)), Err(Incompatibility::Breaking));
+    }
+
+    #[test]
+    fn api_hash_only_compared_for_identical_versions() {
+        let required = v(1, 2, 3).with_api_hash(0xAAAA);
+        assert_eq!(v(1, 2, 3).with_api_hash(0xBBBB).satisfies(&required), Err(Incompatibility::ApiChanged));
+        assert_eq!(v(1, 2, 3).with_api_hash(0xAAAA).satisfies(&required), Ok(()));
+        assert_eq!(v(1, 2, 3).satisfies(&required), Ok(()));
+        assert_eq!(v(1, 3, 0).with_api_hash(0xBBBB).satisfies(&required), Ok(()));
+    }
+
+    #[test]
+    fn encode_decode() {
+        let version = v(3, 1, 4).with_api_hash(0x1234_5678_9ABC_DEF0);
+        assert_eq!(CrateVersion::decode(&version.encode()), Some(version));
+        assert_eq!(CrateVersion::decode(&[0u8; VERSION_RECORD_SIZE]), None);
+
+        let encoded = encode_requirements([Requirement::new("task", version), Requirement::new("memory", v(0, 1, 0))]);
+        let bytes: std::vec::Vec<u8> = encoded.iter().flatten().copied().collect();
+        let decoded: std::vec::Vec<Requirement> = Requirement::decode_all(&bytes).collect();
+        assert_eq!(decoded.len(), 2);
+        assert_eq!((decoded[0].crate_name(), decoded[0].version), ("task", version));
+        assert_eq!((decoded[1].crate_name(), decoded[1].version), ("memory", v(0, 1, 0)));
+    }
+}
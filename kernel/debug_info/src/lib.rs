@@ -17,7 +17,6 @@ extern crate crate_metadata;
 extern crate mod_mgmt;
 extern crate hashbrown;
 extern crate by_address;
-extern crate rustc_demangle;
 
 use core::{
     ops::{Deref, Range},
@@ -52,11 +51,10 @@ use gimli::{
         // Section,
     }, RawRngListEntry,
 };
-use rustc_demangle::demangle;
 use hashbrown::{HashMap, HashSet};
 use by_address::ByAddress;
 use crate_metadata::{StrongCrateRef, StrongSectionRef, RelocationEntry, write_relocation};
-use mod_mgmt::{CrateNamespace, find_symbol_table};
+use mod_mgmt::{CrateNamespace, demangle_symbol, find_symbol_table};
 
 
 /// The set of debug sections that we need to use from a crate object file.
@@ -752,7 +750,7 @@ impl DebugSymbols {
                                 source_sec_name
                             };
                             use alloc::string::ToString;
-                            let demangled = demangle(source_sec_name).to_string();
+                            let demangled = demangle_symbol(source_sec_name);
                             warn!("Looking for foreign relocation source section {:?}", demangled);
 
                             // search for the symbol's demangled name in the kernel's symbol map
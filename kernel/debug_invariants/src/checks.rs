@@ -0,0 +1,133 @@
+//! The built-in invariant checks.
+//!
+//! * `heap`: allocates blocks of various sizes and alignments, and verifies that they're
+//!   properly aligned, within the kernel heap, disjoint, and not modified by other allocations.
+//! * `runqueue`: verifies that no task is in more than one runqueue,
+//!   and that pinned tasks are only in the runqueue of the CPU they're pinned to.
+//! * `namespace`: verifies that each namespace's symbol map is internally consistent,
+//!   that every symbol refers to a loaded section of the same name,
+//!   and that every global section of every loaded crate has a symbol.
+
+use alloc::{alloc::{alloc, dealloc, Layout}, collections::BTreeMap, format, sync::Arc, vec::Vec};
+use memory_regions::RegionKind;
+use memory_structs::VirtualAddress;
+use mod_mgmt::CrateNamespace;
+use crate::{Check, Report};
+
+pub(crate) static BUILTIN_CHECKS: &[Check] = &[
+    Check { subsystem: "heap", name: "probe_allocations", func: check_heap_allocations },
+    Check { subsystem: "runqueue", name: "task_placement", func: check_runqueue_placement },
+    Check { subsystem: "namespace", name: "symbol_maps", func: check_namespace_symbols },
+];
+
+/// The sizes of the blocks allocated by [`check_heap_allocations()`].
+///
+/// Large allocations are satisfied by mapping pages outside of the heap, so these sizes stay below that threshold.
+const PROBE_SIZES: [usize; 8] = [8, 24, 64, 200, 512, 1000, 2048, 4096];
+
+fn check_heap_allocations(report: &mut Report) {
+    let mut blocks: Vec<(*mut u8, Layout)> = Vec::with_capacity(PROBE_SIZES.len());
+    for (i, &size) in PROBE_SIZES.iter().enumerate() {
+        let Ok(layout) = Layout::from_size_align(size, 8 << (i % 4)) else { continue };
+        // SAFETY: the layout has a non-zero size.
+        let ptr = unsafe { alloc(layout) };
+        if ptr.is_null() {
+            report.violation(format!("failed to allocate {:?}", layout));
+            continue;
+        }
+        let range = ptr as usize .. ptr as usize + size;
+        if range.start % layout.align() != 0 {
+            report.violation(format!("allocation {:#X} for {:?} is misaligned", range.start, layout));
+        }
+        match memory_regions::region_containing(VirtualAddress::new_canonical(range.start)) {
+            Some(region) if region.kind == RegionKind::Heap => { }
+            region => report.violation(format!("allocation {:#X} for {:?} is outside the heap, in {:?}", range.start, layout, region)),
+        }
+        for &(other, other_layout) in &blocks {
+            let other_range = other as usize .. other as usize + other_layout.size();
+            if range.start < other_range.end && other_range.start < range.end {
+                report.violation(format!("allocation {:?} overlaps allocation {:?}", range, other_range));
+            }
+        }
+        // SAFETY: the block was just allocated with this size.
+        unsafe { ptr.write_bytes(probe_pattern(i), size) };
+        blocks.push((ptr, layout));
+    }
+
+    for (i, (ptr, layout)) in blocks.into_iter().enumerate() {
+        // SAFETY: the block was allocated above with this layout and fully initialized.
+        let bytes = unsafe { core::slice::from_raw_parts(ptr, layout.size()) };
+        if let Some(offset) = bytes.iter().position(|&b| b != probe_pattern(i)) {
+            report.violation(format!("allocation {:#X} for {:?} was modified at offset {}", ptr as usize, layout, offset));
+        }
+        // SAFETY: the block was allocated above with this layout.
+        unsafe { dealloc(ptr, layout) };
+    }
+}
+
+fn probe_pattern(index: usize) -> u8 {
+    0xA0 | index as u8
+}
+
+fn check_runqueue_placement(report: &mut Report) {
+    // `tasks()` locks all runqueues at once, so tasks can't migrate while they're being listed.
+    let mut seen = BTreeMap::new();
+    for (cpu, tasks) in task::scheduler::tasks() {
+        for task in tasks {
+            if let Some(other_cpu) = seen.insert(task.id, cpu) {
+                report.violation(format!("task {} ({:?}) is in the runqueues of CPU {} and CPU {}", task.id, task.name, other_cpu, cpu));
+            }
+            if let Some(pinned) = task.pinned_cpu().filter(|&pinned| pinned != cpu) {
+                report.violation(format!("task {} ({:?}) is pinned to CPU {} but is in the runqueue of CPU {}", task.id, task.name, pinned, cpu));
+            }
+        }
+    }
+}
+
+fn check_namespace_symbols(report: &mut Report) {
+    let Some(namespace) = mod_mgmt::get_initial_kernel_namespace() else { return };
+    let mut next = Some(namespace);
+    // Check the current task's namespace as well, which is typically built atop the kernel namespace.
+    let current = task::with_current_task(|t| t.get_namespace().clone()).ok();
+    if let Some(current) = current.as_ref().filter(|c| !Arc::ptr_eq(c, namespace)) {
+        next = Some(current);
+    }
+    while let Some(namespace) = next {
+        check_symbol_map(namespace, report);
+        next = namespace.recursive_namespace();
+    }
+}
+
+fn check_symbol_map(namespace: &CrateNamespace, report: &mut Report) {
+    let symbol_map = namespace.symbol_map();
+    for problem in symbol_map.check_consistency() {
+        report.violation(format!("namespace {:?}: {}", namespace.name(), problem));
+    }
+
+    for (name, weak_section) in symbol_map.lock().iter() {
+        let Some(section) = weak_section.upgrade() else {
+            report.violation(format!("namespace {:?}: symbol {:?} refers to a section that was dropped", namespace.name(), name));
+            continue;
+        };
+        if section.name.as_str() != name.as_str() {
+            report.violation(format!("namespace {:?}: symbol {:?} refers to section {:?}", namespace.name(), name, section.name));
+        }
+        if section.parent_crate.upgrade().is_none() {
+            report.violation(format!("namespace {:?}: symbol {:?} refers to a section whose crate was dropped", namespace.name(), name));
+        }
+    }
+
+    namespace.for_each_crate(false, |crate_name, crate_ref| {
+        let krate = crate_ref.lock_as_ref();
+        for shndx in &krate.global_sections {
+            let Some(section) = krate.sections.get(shndx) else {
+                report.violation(format!("namespace {:?}: crate {} has no global section {}", namespace.name(), crate_name, shndx));
+                continue;
+            };
+            if symbol_map.get(section.name.as_str()).is_none() {
+                report.violation(format!("namespace {:?}: global section {:?} of crate {} has no symbol", namespace.name(), section.name, crate_name));
+            }
+        }
+        true
+    });
+}
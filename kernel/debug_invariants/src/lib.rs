@@ -0,0 +1,243 @@
+//! Invariant checks that can be enabled per subsystem at runtime.
+//!
+//! Invariants are grouped into subsystems, e.g., `heap` or `runqueue`, each of which is disabled by default.
+//! An invariant can be checked in two ways:
+//! * inline, via the [`invariant!`] macro, which checks a condition at a specific point in the code
+//!   only if that condition's subsystem is enabled;
+//! * periodically, via a [`Check`] function registered with [`register()`], which inspects the state
+//!   of a whole subsystem. Once any subsystem is enabled, a checker task runs the checks
+//!   of all enabled subsystems every [`CHECK_INTERVAL`].
+//!
+//! Violations are logged along with their subsystem and the check or code location that found them,
+//! and the most recent ones are kept for later inspection via [`recent_violations()`].
+//!
+//! This crate provides built-in checks for the `heap`, `runqueue`, and `namespace` subsystems;
+//! see the [`checks`] module.
+
+#![no_std]
+
+extern crate alloc;
+
+pub mod checks;
+
+use alloc::{collections::{BTreeMap, VecDeque}, string::{String, ToString}, vec::Vec};
+use core::{fmt, sync::atomic::{AtomicBool, AtomicUsize, Ordering}, time::Duration};
+use log::error;
+use spin::Once;
+use sync_irq::IrqSafeMutex;
+
+#[doc(hidden)]
+pub use alloc::format as __format;
+
+/// How often the checker task runs the checks of all enabled subsystems.
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The number of recent violations that are kept.
+const MAX_RECENT_VIOLATIONS: usize = 32;
+
+/// All known subsystems, keyed by name.
+static SUBSYSTEMS: Once<IrqSafeMutex<BTreeMap<String, Subsystem>>> = Once::new();
+/// Whether any subsystem is enabled, which lets disabled [`invariant!`]s skip looking up their subsystem.
+static ANY_ENABLED: AtomicBool = AtomicBool::new(false);
+/// The total number of violations reported so far.
+static VIOLATION_COUNT: AtomicUsize = AtomicUsize::new(0);
+static RECENT_VIOLATIONS: IrqSafeMutex<VecDeque<Violation>> = IrqSafeMutex::new(VecDeque::new());
+/// Whether the checker task has been spawned.
+static CHECKER_STARTED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Default)]
+struct Subsystem {
+    enabled: bool,
+    checks: Vec<Check>,
+}
+
+/// A function that checks the invariants of a subsystem, reporting each violation it finds.
+#[derive(Clone, Copy)]
+pub struct Check {
+    /// The subsystem whose invariants this checks.
+    pub subsystem: &'static str,
+    /// A short name that identifies this check in reported violations.
+    pub name: &'static str,
+    pub func: fn(&mut Report),
+}
+
+/// Collects the violations found by a [`Check`].
+pub struct Report {
+    check: Check,
+    violations: Vec<Violation>,
+}
+
+impl Report {
+    /// Records a violation of the check's invariants, described by the given message.
+    pub fn violation(&mut self, description: impl Into<String>) {
+        self.violations.push(Violation {
+            subsystem: self.check.subsystem,
+            source: self.check.name,
+            description: description.into(),
+        });
+    }
+}
+
+/// A violated invariant.
+#[derive(Clone, Debug)]
+pub struct Violation {
+    pub subsystem: &'static str,
+    /// The check that found the violation, or the code location of a violated [`invariant!`].
+    pub source: &'static str,
+    pub description: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {}: {}", self.subsystem, self.source, self.description)
+    }
+}
+
+/// Checks the given condition if the given subsystem is enabled,
+/// reporting a violation with the given message and the current code location if it's false.
+///
+/// The condition and the message are not evaluated at all if the subsystem is disabled.
+///
+/// # Example
+/// ```ignore
+/// debug_invariants::invariant!("runqueue", !task.has_exited(), "exited task {} is runnable", task.id);
+/// ```
+#[macro_export]
+macro_rules! invariant {
+    ($subsystem:expr, $condition:expr, $($arg:tt)+) => {
+        if $crate::is_enabled($subsystem) && !$condition {
+            $crate::report($crate::Violation {
+                subsystem: $subsystem,
+                source: concat!(file!(), ":", line!()),
+                description: $crate::__format!($($arg)+),
+            });
+        }
+    };
+}
+
+fn subsystems() -> &'static IrqSafeMutex<BTreeMap<String, Subsystem>> {
+    SUBSYSTEMS.call_once(|| {
+        let mut subsystems = BTreeMap::<String, Subsystem>::new();
+        for check in checks::BUILTIN_CHECKS {
+            subsystems.entry(check.subsystem.to_string()).or_default().checks.push(*check);
+        }
+        IrqSafeMutex::new(subsystems)
+    })
+}
+
+/// Registers the given check, which will run periodically while its subsystem is enabled.
+pub fn register(check: Check) {
+    subsystems().lock().entry(check.subsystem.to_string()).or_default().checks.push(check);
+}
+
+/// Enables or disables the invariant checks of the given subsystem.
+///
+/// Subsystems don't need to be registered beforehand,
+/// as [`invariant!`]s can use any subsystem name without any checks having been registered.
+///
+/// When a subsystem is first enabled, this spawns the checker task.
+pub fn set_enabled(subsystem: &str, enabled: bool) -> Result<(), &'static str> {
+    {
+        let mut subsystems = subsystems().lock();
+        subsystems.entry(subsystem.to_string()).or_default().enabled = enabled;
+        ANY_ENABLED.store(subsystems.values().any(|s| s.enabled), Ordering::Relaxed);
+    }
+    if enabled {
+        start_checker_task()?;
+    }
+    Ok(())
+}
+
+/// Returns whether the invariant checks of the given subsystem are enabled.
+#[inline]
+pub fn is_enabled(subsystem: &str) -> bool {
+    ANY_ENABLED.load(Ordering::Relaxed)
+        && subsystems().lock().get(subsystem).is_some_and(|s| s.enabled)
+}
+
+/// Returns the names of all known subsystems and whether each is enabled.
+pub fn subsystem_states() -> Vec<(String, bool)> {
+    subsystems().lock().iter().map(|(name, s)| (name.clone(), s.enabled)).collect()
+}
+
+/// Runs all registered checks of the given subsystem, regardless of whether it's enabled,
+/// and returns the violations they found, which are also reported.
+pub fn run_checks(subsystem: &str) -> Result<Vec<Violation>, &'static str> {
+    let checks = subsystems().lock()
+        .get(subsystem)
+        .map(|s| s.checks.clone())
+        .ok_or("debug_invariants: no such subsystem")?;
+    Ok(run(&checks))
+}
+
+/// Runs all registered checks of all enabled subsystems,
+/// returning the number of violations they found, which are also reported.
+pub fn run_enabled_checks() -> usize {
+    let checks: Vec<Check> = subsystems().lock()
+        .values()
+        .filter(|s| s.enabled)
+        .flat_map(|s| s.checks.iter().copied())
+        .collect();
+    run(&checks).len()
+}
+
+fn run(checks: &[Check]) -> Vec<Violation> {
+    // The checks are run without holding the lock, as they may take a while or report violations themselves.
+    let mut violations = Vec::new();
+    for check in checks {
+        let mut report = Report { check: *check, violations: Vec::new() };
+        (check.func)(&mut report);
+        for violation in report.violations {
+            report_violation(&violation);
+            violations.push(violation);
+        }
+    }
+    violations
+}
+
+/// Reports the given violation, logging it and saving it as one of the recent violations.
+///
+/// This is typically invoked via the [`invariant!`] macro.
+pub fn report(violation: Violation) {
+    report_violation(&violation);
+}
+
+fn report_violation(violation: &Violation) {
+    error!("Invariant violated: {}", violation);
+    VIOLATION_COUNT.fetch_add(1, Ordering::Relaxed);
+    let mut recent = RECENT_VIOLATIONS.lock();
+    if recent.len() == MAX_RECENT_VIOLATIONS {
+        recent.pop_front();
+    }
+    recent.push_back(violation.clone());
+}
+
+/// Returns the most recently reported violations, oldest first.
+pub fn recent_violations() -> Vec<Violation> {
+    RECENT_VIOLATIONS.lock().iter().cloned().collect()
+}
+
+/// Returns the total number of violations reported so far.
+pub fn violation_count() -> usize {
+    VIOLATION_COUNT.load(Ordering::Relaxed)
+}
+
+fn start_checker_task() -> Result<(), &'static str> {
+    if CHECKER_STARTED.swap(true, Ordering::AcqRel) {
+        return Ok(());
+    }
+    let result = spawn::new_task_builder(checker_loop, ())
+        .name("invariant_checker".to_string())
+        .spawn();
+    if result.is_err() {
+        CHECKER_STARTED.store(false, Ordering::Release);
+    }
+    result.map(|_| ())
+}
+
+fn checker_loop(_: ()) -> Result<(), &'static str> {
+    loop {
+        run_enabled_checks();
+        sleep::sleep(CHECK_INTERVAL).map_err(|_| "invariant checker task failed to sleep")?;
+    }
+}
@@ -0,0 +1,333 @@
+//! Decompression of compressed data, such as bootloader modules and network payloads.
+//!
+//! Three formats are supported:
+//! * [`Format::Zstd`]: one or more zstd frames, as produced by the `zstd` command-line tool.
+//!   This is the preferred format, as zstd data identifies itself with a magic number.
+//! * [`Format::Gzip`]: a single gzip member, as produced by the `gzip` command-line tool,
+//!   which also identifies itself with a magic number.
+//! * [`Format::Lz4`]: a single lz4 block prefixed with its uncompressed size
+//!   as a little-endian `u32`, as produced by the `limine_compress_modules` tool.
+//!
+//! Compressed data can be given as a byte slice, e.g., a memory-mapped module,
+//! or as any [`ByteReader`] with a known length, e.g., a file.
+//! The [`Decompressed`] type is itself a [`ByteReader`],
+//! so decompressed data can be passed to anything that accepts a byte source.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::{borrow::Cow, vec, vec::Vec};
+use hashing::{Crc32, Digest, XxHash64};
+use io::{ByteReader, IoError, KnownLength};
+use log::error;
+use ruzstd::{io::Read, StreamingDecoder};
+
+/// The magic number at the start of every zstd frame, in little-endian byte order.
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
+
+/// The magic number at the start of every gzip member.
+const GZIP_MAGIC: [u8; 2] = [0x1F, 0x8B];
+
+/// The size of the chunks in which zstd data is decompressed.
+const CHUNK_SIZE: usize = 4096;
+
+/// A compression format.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Zstd,
+    Gzip,
+    Lz4,
+}
+
+impl Format {
+    /// Returns the format of the given compressed data,
+    /// or `None` if it doesn't start with a known magic number.
+    ///
+    /// Only zstd and gzip data can be detected this way, as lz4 blocks have no magic number.
+    pub fn detect(bytes: &[u8]) -> Option<Format> {
+        if bytes.starts_with(&ZSTD_MAGIC) {
+            Some(Format::Zstd)
+        } else if bytes.starts_with(&GZIP_MAGIC) {
+            Some(Format::Gzip)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the format of a file with the given name, based on its extension,
+    /// along with the name without that extension.
+    ///
+    /// For example, `"k#foo.o.zst"` is zstd-compressed, and is named `"k#foo.o"` once decompressed.
+    pub fn from_file_name(name: &str) -> Option<(Format, &str)> {
+        [Format::Zstd, Format::Gzip, Format::Lz4].into_iter().find_map(|format| {
+            name.strip_suffix(format.extension())
+                .and_then(|stem| stem.strip_suffix('.'))
+                .filter(|stem| !stem.is_empty())
+                .map(|stem| (format, stem))
+        })
+    }
+
+    /// Returns the format with the given name, as used in the HTTP `Content-Encoding` header.
+    pub fn from_encoding(encoding: &str) -> Option<Format> {
+        match encoding.trim() {
+            e if e.eq_ignore_ascii_case("zstd") => Some(Format::Zstd),
+            e if e.eq_ignore_ascii_case("gzip") => Some(Format::Gzip),
+            e if e.eq_ignore_ascii_case("lz4") => Some(Format::Lz4),
+            _ => None,
+        }
+    }
+
+    /// Returns the file extension used for this format, without the leading dot.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Zstd => "zst",
+            Format::Gzip => "gz",
+            Format::Lz4 => "lz4",
+        }
+    }
+
+    /// Returns the name of this format, as used in the HTTP `Content-Encoding` header.
+    pub fn encoding(self) -> &'static str {
+        match self {
+            Format::Zstd => "zstd",
+            Format::Gzip => "gzip",
+            Format::Lz4 => "lz4",
+        }
+    }
+}
+
+/// Decompresses the given data, which is in the given format.
+pub fn decompress(format: Format, bytes: &[u8]) -> Result<Vec<u8>, &'static str> {
+    match format {
+        Format::Zstd => decompress_zstd(bytes),
+        Format::Gzip => decompress_gzip(bytes),
+        Format::Lz4 => lz4_flex::block::decompress_size_prepended(bytes).map_err(|_e| {
+            error!("decompress: lz4 decompression failed: {:?}", _e);
+            "lz4 decompression failed"
+        }),
+    }
+}
+
+/// Decompresses the given data if it starts with a known magic number,
+/// otherwise returns it unchanged.
+///
+/// This allows callers to accept both compressed and uncompressed data,
+/// without copying the latter.
+pub fn decompress_if_compressed(bytes: &[u8]) -> Result<Cow<'_, [u8]>, &'static str> {
+    match Format::detect(bytes) {
+        Some(format) => decompress(format, bytes).map(Cow::Owned),
+        None => Ok(Cow::Borrowed(bytes)),
+    }
+}
+
+/// Reads all of the compressed data from the given `reader` and decompresses it.
+///
+/// If `format` is `None`, the format is detected from the data,
+/// and data that isn't compressed is returned unchanged.
+pub fn decompress_reader<R>(format: Option<Format>, mut reader: R) -> Result<Decompressed, &'static str>
+where
+    R: ByteReader + KnownLength,
+{
+    let mut bytes = vec![0; reader.len()];
+    let read = reader.read_at(&mut bytes, 0).map_err(|_| "failed to read compressed data")?;
+    bytes.truncate(read);
+    let bytes = match format.or_else(|| Format::detect(&bytes)) {
+        Some(format) => decompress(format, &bytes)?,
+        None => bytes,
+    };
+    Ok(Decompressed(bytes))
+}
+
+/// Decompresses a sequence of zstd frames,
+/// verifying the checksum of the contents of each frame that has one.
+fn decompress_zstd(mut bytes: &[u8]) -> Result<Vec<u8>, &'static str> {
+    let mut output = Vec::new();
+    let mut chunk = [0; CHUNK_SIZE];
+    // The zstd format allows multiple frames to be concatenated,
+    // which must be decompressed one after the other.
+    while !bytes.is_empty() {
+        // The decoder advances `bytes` past the frame as it reads it.
+        let mut decoder = StreamingDecoder::new(&mut bytes).map_err(|_e| {
+            error!("decompress: invalid zstd frame header: {}", _e);
+            "invalid zstd frame header"
+        })?;
+        let mut hasher = XxHash64::default();
+        loop {
+            let read = decoder.read(&mut chunk).map_err(|_e| {
+                error!("decompress: zstd decompression failed: {:?}", _e);
+                "zstd decompression failed"
+            })?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&chunk[..read]);
+            output.extend_from_slice(&chunk[..read]);
+        }
+        // The checksum is the lower 32 bits of the XXH64 hash of the frame's contents.
+        if let Some(checksum) = decoder.decoder.get_checksum_from_data() {
+            if hasher.finalize() as u32 != checksum {
+                return Err("zstd checksum mismatch");
+            }
+        }
+    }
+    Ok(output)
+}
+
+/// Decompresses a single gzip member, verifying the checksum and size of its contents.
+fn decompress_gzip(bytes: &[u8]) -> Result<Vec<u8>, &'static str> {
+    // The flags in the gzip header that indicate which optional fields follow it.
+    const FHCRC: u8 = 1 << 1;
+    const FEXTRA: u8 = 1 << 2;
+    const FNAME: u8 = 1 << 3;
+    const FCOMMENT: u8 = 1 << 4;
+    const HEADER_SIZE: usize = 10;
+    const TRAILER_SIZE: usize = 8;
+    /// The compression method of deflate, the only one defined for gzip.
+    const CM_DEFLATE: u8 = 8;
+
+    if bytes.len() < HEADER_SIZE + TRAILER_SIZE || !bytes.starts_with(&GZIP_MAGIC) {
+        return Err("invalid gzip header");
+    }
+    if bytes[2] != CM_DEFLATE {
+        return Err("unsupported gzip compression method");
+    }
+    let flags = bytes[3];
+
+    // Skip the optional header fields to find the start of the deflate stream.
+    let mut start = HEADER_SIZE;
+    if flags & FEXTRA != 0 {
+        let extra_len = bytes.get(start .. start + 2).ok_or("invalid gzip header")?;
+        start += 2 + u16::from_le_bytes([extra_len[0], extra_len[1]]) as usize;
+    }
+    for flag in [FNAME, FCOMMENT] {
+        if flags & flag != 0 {
+            let len = bytes.get(start..).and_then(|b| b.iter().position(|&c| c == 0)).ok_or("invalid gzip header")?;
+            start += len + 1;
+        }
+    }
+    if flags & FHCRC != 0 {
+        start += 2;
+    }
+    let end = bytes.len() - TRAILER_SIZE;
+    let deflated = bytes.get(start..end).ok_or("invalid gzip header")?;
+
+    let output = miniz_oxide::inflate::decompress_to_vec(deflated).map_err(|_e| {
+        error!("decompress: gzip decompression failed: {:?}", _e);
+        "gzip decompression failed"
+    })?;
+
+    // The trailer holds the CRC-32 and the size (modulo 2^32) of the decompressed contents.
+    let trailer = &bytes[end..];
+    let crc = u32::from_le_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]);
+    let size = u32::from_le_bytes([trailer[4], trailer[5], trailer[6], trailer[7]]);
+    if Crc32::digest(&output) != crc || output.len() as u32 != size {
+        return Err("gzip checksum mismatch");
+    }
+    Ok(output)
+}
+
+/// Decompressed data, which can be read as a byte source.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Decompressed(pub Vec<u8>);
+
+impl Decompressed {
+    /// Returns the decompressed data.
+    pub fn into_inner(self) -> Vec<u8> {
+        self.0
+    }
+}
+
+impl AsRef<[u8]> for Decompressed {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl ByteReader for Decompressed {
+    fn read_at(&mut self, buffer: &mut [u8], offset: usize) -> Result<usize, IoError> {
+        let remaining = self.0.get(offset..).ok_or(IoError::InvalidInput)?;
+        let count = buffer.len().min(remaining.len());
+        buffer[..count].copy_from_slice(&remaining[..count]);
+        Ok(count)
+    }
+}
+
+impl KnownLength for Decompressed {
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `"hello, hello, hello, world\n"` compressed by `zstd`, containing one frame.
+    const HELLO_ZST: &[u8] = &[
+        0x28, 0xb5, 0x2f, 0xfd, 0x20, 0x1b, 0x9d, 0x00, 0x00, 0x68, 0x68, 0x65, 0x6c, 0x6c,
+        0x6f, 0x2c, 0x20, 0x77, 0x6f, 0x72, 0x6c, 0x64, 0x0a, 0x01, 0x00, 0x92, 0x8b, 0x11,
+    ];
+    const HELLO: &[u8] = b"hello, hello, hello, world\n";
+    /// The same as [`HELLO_ZST`], but with a checksum of the contents at the end.
+    const HELLO_ZST_CHECKSUM: &[u8] = &[
+        0x28, 0xb5, 0x2f, 0xfd, 0x24, 0x1b, 0x9d, 0x00, 0x00, 0x68, 0x68, 0x65, 0x6c, 0x6c,
+        0x6f, 0x2c, 0x20, 0x77, 0x6f, 0x72, 0x6c, 0x64, 0x0a, 0x01, 0x00, 0x92, 0x8b, 0x11,
+        0x8a, 0x96, 0xf4, 0x0b,
+    ];
+
+    #[test]
+    fn zstd() {
+        assert_eq!(Format::detect(HELLO_ZST), Some(Format::Zstd));
+        assert_eq!(decompress(Format::Zstd, HELLO_ZST).unwrap(), HELLO);
+
+        let two_frames = [HELLO_ZST, HELLO_ZST].concat();
+        assert_eq!(decompress(Format::Zstd, &two_frames).unwrap(), [HELLO, HELLO].concat());
+
+        assert!(decompress(Format::Zstd, &HELLO_ZST[..12]).is_err());
+
+        assert_eq!(decompress(Format::Zstd, HELLO_ZST_CHECKSUM).unwrap(), HELLO);
+        let mut corrupted = HELLO_ZST_CHECKSUM.to_vec();
+        *corrupted.last_mut().unwrap() ^= 1;
+        assert_eq!(decompress(Format::Zstd, &corrupted), Err("zstd checksum mismatch"));
+        assert!(decompress_if_compressed(b"plain").unwrap() == Cow::Borrowed(b"plain".as_slice()));
+    }
+
+    /// [`HELLO`] compressed by `gzip`, with the original file name `hello.txt` in its header.
+    const HELLO_GZ: &[u8] = &[
+        0x1f, 0x8b, 0x08, 0x08, 0xfc, 0x52, 0xd2, 0x6a, 0x00, 0x03, 0x68, 0x65, 0x6c, 0x6c,
+        0x6f, 0x2e, 0x74, 0x78, 0x74, 0x00, 0xcb, 0x48, 0xcd, 0xc9, 0xc9, 0xd7, 0x51, 0xc8,
+        0x40, 0xa1, 0xca, 0xf3, 0x8b, 0x72, 0x52, 0xb8, 0x00, 0xc3, 0x70, 0xa3, 0xc2, 0x1b,
+        0x00, 0x00, 0x00,
+    ];
+
+    #[test]
+    fn gzip() {
+        assert_eq!(Format::detect(HELLO_GZ), Some(Format::Gzip));
+        assert_eq!(decompress(Format::Gzip, HELLO_GZ).unwrap(), HELLO);
+        assert!(decompress(Format::Gzip, &HELLO_GZ[..12]).is_err());
+
+        let mut corrupted = HELLO_GZ.to_vec();
+        corrupted[HELLO_GZ.len() - 8] ^= 1;
+        assert_eq!(decompress(Format::Gzip, &corrupted), Err("gzip checksum mismatch"));
+    }
+
+    #[test]
+    fn lz4() {
+        let compressed = lz4_flex::block::compress_prepend_size(b"abcabcabcabcabcabc");
+        assert_eq!(decompress(Format::Lz4, &compressed).unwrap(), b"abcabcabcabcabcabc");
+    }
+
+    #[test]
+    fn file_names() {
+        assert_eq!(Format::from_file_name("k#foo.o.zst"), Some((Format::Zstd, "k#foo.o")));
+        assert_eq!(Format::from_file_name("modules.cpio.lz4"), Some((Format::Lz4, "modules.cpio")));
+        assert_eq!(Format::from_file_name("k#foo.o"), None);
+        assert_eq!(Format::from_file_name(".zst"), None);
+        assert_eq!(Format::from_file_name("zst"), None);
+        assert_eq!(Format::from_encoding(" ZSTD"), Some(Format::Zstd));
+        assert_eq!(Format::from_file_name("k#foo.o.gz"), Some((Format::Gzip, "k#foo.o")));
+        assert_eq!(Format::from_encoding("gzip"), Some(Format::Gzip));
+        assert_eq!(Format::from_encoding("br"), None);
+    }
+}
@@ -126,6 +126,24 @@ pub fn register_interrupt_handler<DIA, Arg, Success, Failure, S>(
             }
         })?;
 
+    spawn_deferred_task(deferred_interrupt_action, deferred_action_argument, deferred_task_name)
+        .map_err(InterruptRegistrationError::SpawnError)
+}
+
+/// Spawns a deferred task for an interrupt handler that was registered separately,
+/// e.g., for an MSI vector allocated via `interrupts::register_msi_interrupt()`.
+///
+/// The arguments and the behavior of the returned deferred task are the same
+/// as in [`register_interrupt_handler()`].
+pub fn spawn_deferred_task<DIA, Arg, Success, Failure, S>(
+    deferred_interrupt_action: DIA,
+    deferred_action_argument: Arg,
+    deferred_task_name: Option<S>,
+) -> Result<JoinableTaskRef, &'static str>
+    where DIA: Fn(&Arg) -> Result<Success, Failure> + Send + 'static,
+          Arg: Send + 'static,
+          S: Into<String>,
+{
     // Spawn the deferred task, which should be initially blocked from running.
     // It will be unblocked by the interrupt handler whenever it needs to run.
     let mut tb = spawn::new_task_builder(
@@ -135,7 +153,7 @@ pub fn register_interrupt_handler<DIA, Arg, Success, Failure, S>(
     if let Some(name) = deferred_task_name {
         tb = tb.name(name.into());
     }
-    tb.spawn().map_err(InterruptRegistrationError::SpawnError)
+    tb.spawn()
 }
 
 
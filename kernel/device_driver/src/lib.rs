@@ -0,0 +1,207 @@
+//! The standard interface between device drivers and the device manager.
+//!
+//! Each driver implements the [`Driver`] trait, which defines the lifecycle of a device
+//! bound to it: [`probe`], [`suspend`], [`resume`], and [`remove`].
+//! The device manager matches each device to a driver whose [`id_table`] includes it,
+//! and then probes that driver with the device.
+//!
+//! A driver crate makes its driver known by invoking the [`register_driver!`] macro once,
+//! which allows the device manager to find it even if the driver crate was loaded
+//! at runtime (via `mod_mgmt`) rather than being built into the kernel.
+//!
+//! When a driver crate is swapped for a new version, its devices are [`quiesce`]d
+//! by the old driver, which exports each device's state (e.g., its DMA descriptor rings),
+//! and then [`adopt`]ed by the new driver along with that state.
+//! This allows a driver to be updated without resetting its devices or dropping in-flight I/O.
+//!
+//! Each driver also keeps a [`DeviceStats`] block for every device bound to it,
+//! which the device manager exports as metrics.
+//!
+//! If a device stops responding, e.g., a NIC hangs or a storage controller times out,
+//! its driver can report it via [`needs_recovery`], upon which the device manager
+//! removes the device, resets it, restores its PCI configuration space, and probes it again.
+//!
+//! [`probe`]: Driver::probe
+//! [`suspend`]: Driver::suspend
+//! [`resume`]: Driver::resume
+//! [`remove`]: Driver::remove
+//! [`id_table`]: Driver::id_table
+//! [`quiesce`]: Driver::quiesce
+//! [`adopt`]: Driver::adopt
+//! [`needs_recovery`]: Driver::needs_recovery
+
+#![no_std]
+
+extern crate alloc;
+
+pub mod stats;
+
+pub use stats::{DeviceStats, ErrorKind, StatsSnapshot};
+
+use alloc::boxed::Box;
+use core::any::Any;
+use pci::PciDevice;
+
+/// The name of the function generated by [`register_driver!`],
+/// which the device manager looks for in newly-loaded driver crates.
+pub const REGISTRATION_FUNC_NAME: &str = "__device_driver_registration";
+
+/// The signature of the function generated by [`register_driver!`].
+pub type RegistrationFunc = fn() -> &'static dyn Driver;
+
+/// The state of a quiesced device, which is transferred from one driver to another.
+///
+/// Because each version of a driver crate defines distinct types, the concrete type of this state
+/// should be defined in a crate that isn't swapped along with the driver, e.g., a shared NIC crate.
+pub type DeviceState = Box<dyn Any + Send>;
+
+/// A pattern that matches PCI devices, in which `None` fields match any value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PciDeviceId {
+    pub vendor_id: Option<u16>,
+    pub device_id: Option<u16>,
+    pub class: Option<u8>,
+    pub subclass: Option<u8>,
+}
+
+impl PciDeviceId {
+    /// Matches the device with the given vendor and device IDs.
+    pub const fn new(vendor_id: u16, device_id: u16) -> PciDeviceId {
+        PciDeviceId {
+            vendor_id: Some(vendor_id),
+            device_id: Some(device_id),
+            class: None,
+            subclass: None,
+        }
+    }
+
+    /// Matches any device with the given class and subclass codes.
+    pub const fn class(class: u8, subclass: u8) -> PciDeviceId {
+        PciDeviceId {
+            vendor_id: None,
+            device_id: None,
+            class: Some(class),
+            subclass: Some(subclass),
+        }
+    }
+
+    /// Returns whether the given `device` matches this pattern.
+    pub fn matches(&self, device: &PciDevice) -> bool {
+        self.vendor_id.map_or(true, |v| v == device.vendor_id)
+            && self.device_id.map_or(true, |d| d == device.device_id)
+            && self.class.map_or(true, |c| c == device.class)
+            && self.subclass.map_or(true, |s| s == device.subclass)
+    }
+}
+
+/// A device driver, which manages the lifecycle of each device bound to it.
+///
+/// A single driver instance may be bound to multiple devices at once,
+/// so it must internally keep track of the state of each one.
+pub trait Driver: Send + Sync {
+    /// Returns the unique name of this driver.
+    fn name(&self) -> &'static str;
+
+    /// Returns the devices that this driver supports.
+    fn id_table(&self) -> &[PciDeviceId];
+
+    /// Returns whether this driver supports the given `device`.
+    fn matches(&self, device: &PciDevice) -> bool {
+        self.id_table().iter().any(|id| id.matches(device))
+    }
+
+    /// Initializes the given `device`, after which it is bound to this driver.
+    ///
+    /// If this returns an error, the device is left unbound.
+    fn probe(&self, device: &'static PciDevice) -> Result<(), &'static str>;
+
+    /// Shuts down the given `device` and releases all resources associated with it,
+    /// after which it is no longer bound to this driver.
+    fn remove(&self, device: &'static PciDevice) -> Result<(), &'static str>;
+
+    /// Returns the statistics of the given `device`, or `None` if it isn't bound to this driver.
+    ///
+    /// This is invoked while the device manager's registry is locked, so it must not block.
+    fn stats(&self, device: &'static PciDevice) -> Option<&DeviceStats>;
+
+    /// Returns whether the given `device` stopped responding and must be reset,
+    /// after which it is [removed] from and [probed] by this driver again.
+    ///
+    /// This is polled periodically by the device manager while its registry is locked,
+    /// so it must not block; it's typically a flag set upon a timeout.
+    /// The default implementation always returns `false`.
+    ///
+    /// [removed]: Driver::remove
+    /// [probed]: Driver::probe
+    fn needs_recovery(&self, _device: &'static PciDevice) -> bool {
+        false
+    }
+
+    /// Stops the given `device` from doing any further work until it is [resumed].
+    ///
+    /// The default implementation does nothing.
+    ///
+    /// [resumed]: Driver::resume
+    fn suspend(&self, _device: &'static PciDevice) -> Result<(), &'static str> {
+        Ok(())
+    }
+
+    /// Restarts the given `device` after it was [suspended].
+    ///
+    /// The default implementation does nothing.
+    ///
+    /// [suspended]: Driver::suspend
+    fn resume(&self, _device: &'static PciDevice) -> Result<(), &'static str> {
+        Ok(())
+    }
+
+    /// Stops the given `device` in preparation for handing it over to another driver,
+    /// e.g., a newer version of this driver, after which it is no longer bound to this driver.
+    ///
+    /// The driver should stop accepting new requests and either complete in-flight requests
+    /// or capture them in the returned state, along with anything else the next driver
+    /// needs to continue operating the device without resetting it, e.g., its descriptor rings.
+    /// If the device cannot be handed over, the driver should fully remove it and return `None`.
+    ///
+    /// If handing over the device fails, it may be given back to this driver via [`adopt`].
+    ///
+    /// The default implementation removes the device and returns no state.
+    ///
+    /// [`adopt`]: Driver::adopt
+    fn quiesce(&self, device: &'static PciDevice) -> Result<Option<DeviceState>, &'static str> {
+        self.remove(device).map(|_| None)
+    }
+
+    /// Takes over the given `device` that was [quiesced] by another driver (or by this one),
+    /// after which it is bound to this driver.
+    ///
+    /// If `state` is `None` or cannot be used by this driver, the device should be fully reinitialized.
+    ///
+    /// The default implementation ignores the state and probes the device.
+    ///
+    /// [quiesced]: Driver::quiesce
+    fn adopt(&self, device: &'static PciDevice, state: Option<DeviceState>) -> Result<(), &'static str> {
+        drop(state);
+        self.probe(device)
+    }
+}
+
+/// Registers the given static driver instance as this crate's [`Driver`].
+///
+/// This must be invoked at most once per crate, at the top level of the crate's root module.
+///
+/// # Example
+/// ```ignore
+/// static MY_DRIVER: MyDriver = MyDriver::new();
+/// device_driver::register_driver!(MY_DRIVER);
+/// ```
+#[macro_export]
+macro_rules! register_driver {
+    ($driver:path) => {
+        #[doc(hidden)]
+        #[inline(never)]
+        pub fn __device_driver_registration() -> &'static dyn $crate::Driver {
+            &$driver
+        }
+    };
+}
@@ -0,0 +1,188 @@
+//! Statistics that every driver keeps for each device bound to it.
+//!
+//! A driver embeds one [`DeviceStats`] block in the state of each of its devices,
+//! updates it as requests complete or fail, and returns it from [`Driver::stats()`].
+//! The device manager exports the statistics of all bound devices as metrics,
+//! so drivers don't need to log or export them on their own.
+//!
+//! All counters are updated with relaxed atomics, so they can be updated
+//! from interrupt handlers and read at any time without locking.
+//!
+//! [`Driver::stats()`]: crate::Driver::stats
+
+use core::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+/// The maximum number of queues per device whose depths are tracked.
+pub const MAX_QUEUES: usize = 16;
+
+/// The kinds of errors that a driver counts separately.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// The device didn't complete a request in time.
+    Timeout,
+    /// The device reported that it failed to complete a request.
+    Device,
+    /// A DMA transfer to or from the device failed.
+    Dma,
+    /// A request was rejected because it was malformed or unsupported.
+    InvalidRequest,
+    /// A request couldn't be issued because no buffers, descriptors, or queue slots were free.
+    NoResources,
+}
+
+impl ErrorKind {
+    /// All error kinds, in the order that their counters are stored.
+    pub const ALL: [ErrorKind; 5] = [
+        ErrorKind::Timeout,
+        ErrorKind::Device,
+        ErrorKind::Dma,
+        ErrorKind::InvalidRequest,
+        ErrorKind::NoResources,
+    ];
+
+    /// Returns the name of this error kind, as used in metric labels.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorKind::Timeout => "timeout",
+            ErrorKind::Device => "device",
+            ErrorKind::Dma => "dma",
+            ErrorKind::InvalidRequest => "invalid_request",
+            ErrorKind::NoResources => "no_resources",
+        }
+    }
+}
+
+/// The statistics of a single device.
+pub struct DeviceStats {
+    ops_completed: AtomicU64,
+    errors: [AtomicU64; ErrorKind::ALL.len()],
+    retries: AtomicU64,
+    queue_depths: [AtomicUsize; MAX_QUEUES],
+    /// One more than the highest queue index whose depth has been set.
+    num_queues: AtomicUsize,
+}
+
+#[allow(clippy::declare_interior_mutable_const)]
+const ZERO_U64: AtomicU64 = AtomicU64::new(0);
+#[allow(clippy::declare_interior_mutable_const)]
+const ZERO_USIZE: AtomicUsize = AtomicUsize::new(0);
+
+impl DeviceStats {
+    /// Returns a new statistics block with all counters set to zero.
+    pub const fn new() -> DeviceStats {
+        DeviceStats {
+            ops_completed: AtomicU64::new(0),
+            errors: [ZERO_U64; ErrorKind::ALL.len()],
+            retries: AtomicU64::new(0),
+            queue_depths: [ZERO_USIZE; MAX_QUEUES],
+            num_queues: AtomicUsize::new(0),
+        }
+    }
+
+    /// Counts one successfully completed operation, e.g., a transmitted packet or a finished disk request.
+    pub fn op_completed(&self) {
+        self.ops_completed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Counts `count` successfully completed operations.
+    pub fn ops_completed(&self, count: u64) {
+        self.ops_completed.fetch_add(count, Ordering::Relaxed);
+    }
+
+    /// Counts one error of the given `kind`.
+    pub fn error(&self, kind: ErrorKind) {
+        self.errors[kind as usize].fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Counts one operation that was retried.
+    pub fn retry(&self) {
+        self.retries.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Sets the current number of outstanding requests in the given `queue`.
+    ///
+    /// Queues at or beyond [`MAX_QUEUES`] are ignored.
+    pub fn set_queue_depth(&self, queue: usize, depth: usize) {
+        if let Some(slot) = self.queue_depths.get(queue) {
+            slot.store(depth, Ordering::Relaxed);
+            self.num_queues.fetch_max(queue + 1, Ordering::Relaxed);
+        }
+    }
+
+    /// Returns the current value of every counter.
+    pub fn snapshot(&self) -> StatsSnapshot {
+        let num_queues = self.num_queues.load(Ordering::Relaxed);
+        let mut queue_depths = [0; MAX_QUEUES];
+        for (depth, slot) in queue_depths.iter_mut().zip(&self.queue_depths[.. num_queues]) {
+            *depth = slot.load(Ordering::Relaxed);
+        }
+        StatsSnapshot {
+            ops_completed: self.ops_completed.load(Ordering::Relaxed),
+            errors: core::array::from_fn(|i| self.errors[i].load(Ordering::Relaxed)),
+            retries: self.retries.load(Ordering::Relaxed),
+            queue_depths,
+            num_queues,
+        }
+    }
+}
+
+impl Default for DeviceStats {
+    fn default() -> Self {
+        DeviceStats::new()
+    }
+}
+
+/// The values of a device's statistics at a point in time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StatsSnapshot {
+    pub ops_completed: u64,
+    errors: [u64; ErrorKind::ALL.len()],
+    pub retries: u64,
+    queue_depths: [usize; MAX_QUEUES],
+    num_queues: usize,
+}
+
+impl StatsSnapshot {
+    /// Returns the number of errors of the given `kind`.
+    pub fn errors(&self, kind: ErrorKind) -> u64 {
+        self.errors[kind as usize]
+    }
+
+    /// Returns the total number of errors of all kinds.
+    pub fn total_errors(&self) -> u64 {
+        self.errors.iter().sum()
+    }
+
+    /// Returns the depth of each queue that the driver has reported, indexed by queue.
+    pub fn queue_depths(&self) -> &[usize] {
+        &self.queue_depths[.. self.num_queues]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use super::*;
+
+    #[test]
+    fn counters() {
+        let stats = DeviceStats::new();
+        stats.op_completed();
+        stats.ops_completed(4);
+        stats.error(ErrorKind::Timeout);
+        stats.error(ErrorKind::NoResources);
+        stats.error(ErrorKind::NoResources);
+        stats.retry();
+        stats.set_queue_depth(2, 7);
+        stats.set_queue_depth(MAX_QUEUES, 1);
+
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot.ops_completed, 5);
+        assert_eq!(snapshot.errors(ErrorKind::Timeout), 1);
+        assert_eq!(snapshot.errors(ErrorKind::NoResources), 2);
+        assert_eq!(snapshot.errors(ErrorKind::Dma), 0);
+        assert_eq!(snapshot.total_errors(), 3);
+        assert_eq!(snapshot.retries, 1);
+        assert_eq!(snapshot.queue_depths(), &[0, 0, 7]);
+    }
+}
@@ -0,0 +1,75 @@
+//! Metrics about the statistics that each driver keeps for the devices bound to it.
+
+use core::fmt::{self, Display};
+
+use device_driver::{ErrorKind, StatsSnapshot};
+use metrics::{FnMetric, MetricKind, Samples};
+
+static OPS: FnMetric = FnMetric::new(
+    "theseus_driver_ops_total",
+    "The number of operations that each device's driver has completed.",
+    MetricKind::Counter,
+    |samples| collect(samples, |s| s.ops_completed),
+);
+
+static ERRORS: FnMetric = FnMetric::new(
+    "theseus_driver_errors_total",
+    "The number of errors of each kind that each device's driver has encountered.",
+    MetricKind::Counter,
+    |samples| {
+        for (location, driver, stats) in crate::drivers::device_stats() {
+            for kind in ErrorKind::ALL {
+                let labels: [(&str, &dyn Display); 3] = [
+                    ("driver", &driver),
+                    ("device", &location),
+                    ("kind", &kind.as_str()),
+                ];
+                samples.write("", &labels, stats.errors(kind))?;
+            }
+        }
+        Ok(())
+    },
+);
+
+static RETRIES: FnMetric = FnMetric::new(
+    "theseus_driver_retries_total",
+    "The number of operations that each device's driver has retried.",
+    MetricKind::Counter,
+    |samples| collect(samples, |s| s.retries),
+);
+
+static QUEUE_DEPTH: FnMetric = FnMetric::new(
+    "theseus_driver_queue_depth",
+    "The number of outstanding requests in each queue of each device.",
+    MetricKind::Gauge,
+    |samples| {
+        for (location, driver, stats) in crate::drivers::device_stats() {
+            for (queue, depth) in stats.queue_depths().iter().enumerate() {
+                let labels: [(&str, &dyn Display); 3] = [
+                    ("driver", &driver),
+                    ("device", &location),
+                    ("queue", &queue),
+                ];
+                samples.write("", &labels, *depth)?;
+            }
+        }
+        Ok(())
+    },
+);
+
+/// Registers the driver metrics, if they haven't been registered already.
+pub(crate) fn register() {
+    for metric in [&OPS, &ERRORS, &RETRIES, &QUEUE_DEPTH] {
+        if let Err(e) = metrics::register(metric) {
+            log::warn!("device_manager: failed to register metric {}: {}", metrics::Metric::name(metric), e);
+        }
+    }
+}
+
+/// Writes a sample for each bound device, using `select` to pick the value from its statistics.
+fn collect(samples: &mut Samples<'_>, select: fn(&StatsSnapshot) -> u64) -> fmt::Result {
+    for (location, driver, stats) in crate::drivers::device_stats() {
+        samples.write("", &[("driver", &driver as &dyn Display), ("device", &location)], select(&stats))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,578 @@
+//! Matching devices to [`Driver`]s and managing the lifecycle of each binding.
+//!
+//! Devices that aren't claimed by one of the built-in drivers during [`init()`](crate::init)
+//! are handed over to this module, which binds each one to the first registered driver that
+//! supports it. Drivers can be registered at any time, e.g., after being loaded from
+//! a driver crate at runtime via [`load_driver_crate()`], upon which they're immediately
+//! probed with all matching unbound devices.
+//!
+//! When a driver crate is swapped via `crate_swap`, this module's swap hooks
+//! quiesce all devices bound to the old driver before the swap, and then hand them
+//! (and their exported state) over to the new driver once the swap completes.
+//! If the swap fails, the devices are handed back to the old driver.
+//!
+//! A device that stopped responding is recovered by removing it from its driver,
+//! resetting it, restoring its PCI configuration space as saved when it was handed over,
+//! and probing it again. This is done on demand via [`recover_device()`], and automatically
+//! by a task that polls each bound device's driver via [`Driver::needs_recovery()`].
+//!
+//! # Locking
+//! The lock on the driver registry is held while invoking a driver's lifecycle methods,
+//! so drivers must not call any functions in this module from within those methods.
+//! The lock is *not* held while a device is being reset, which may sleep for a long time;
+//! instead, the devices being reset are marked as such, so they aren't bound to any driver
+//! until their recovery completes.
+
+use alloc::{format, string::String, vec::Vec};
+use core::time::Duration;
+use device_driver::{DeviceState, Driver, RegistrationFunc, StatsSnapshot, REGISTRATION_FUNC_NAME};
+use fs_node::FileOrDir;
+use log::{error, info, warn};
+use mod_mgmt::{LoadedCrate, SectionType, StrongCrateRef, SECTION_HASH_DELIMITER};
+use path::{Path, PathBuf};
+use pci::{PciConfigState, PciDevice, PciLocation};
+use spin::Mutex;
+
+/// How often the recovery task checks whether any bound device needs to be recovered.
+pub const RECOVERY_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// The number of times that a device is automatically recovered
+/// before it is given up on, removed from its driver, and left unbound.
+pub const MAX_AUTOMATIC_RECOVERIES: usize = 3;
+
+static REGISTRY: Mutex<Registry> = Mutex::new(Registry {
+    drivers: Vec::new(),
+    devices: Vec::new(),
+    quiesced: Vec::new(),
+});
+
+/// The set of registered drivers and the devices that they can be bound to.
+struct Registry {
+    drivers: Vec<RegisteredDriver>,
+    devices: Vec<DeviceEntry>,
+    /// Devices that were quiesced in preparation for a crate swap.
+    quiesced: Vec<QuiescedDevice>,
+}
+
+struct RegisteredDriver {
+    driver: &'static dyn Driver,
+    /// The name (without hash) of the crate that this driver was loaded from, if known.
+    source_crate: Option<String>,
+}
+
+/// A device managed by this module and the driver it is currently bound to, if any.
+struct DeviceEntry {
+    device: &'static PciDevice,
+    driver: Option<&'static dyn Driver>,
+    /// The device's configuration space, saved before it was first probed,
+    /// which is restored after the device is reset.
+    config: Option<PciConfigState>,
+    /// The number of times that the device has been automatically recovered.
+    recoveries: usize,
+    /// Whether the device is currently being reset, during which it must remain unbound.
+    recovering: bool,
+}
+
+/// A device that was removed from its driver in order to be reset.
+struct RecoveringDevice {
+    device: &'static PciDevice,
+    previous_driver: Option<&'static dyn Driver>,
+}
+
+/// A device that was quiesced by its driver and is waiting to be adopted by a new driver.
+struct QuiescedDevice {
+    device: &'static PciDevice,
+    old_driver: &'static dyn Driver,
+    state: Option<DeviceState>,
+}
+
+impl Registry {
+    fn find_driver(&self, name: &str) -> Option<usize> {
+        self.drivers.iter().position(|d| d.driver.name() == name)
+    }
+
+    /// Binds the given unbound `entry` to the first registered driver that supports it and probes successfully.
+    fn bind_device(drivers: &[RegisteredDriver], entry: &mut DeviceEntry) -> bool {
+        if entry.recovering {
+            return false;
+        }
+        for driver in drivers.iter().map(|d| d.driver).filter(|d| d.matches(entry.device)) {
+            if try_probe(driver, entry.device) {
+                entry.driver = Some(driver);
+                return true;
+            }
+        }
+        false
+    }
+
+    fn register(&mut self, driver: &'static dyn Driver, source_crate: Option<String>) -> Result<usize, &'static str> {
+        if self.find_driver(driver.name()).is_some() {
+            return Err("a driver with that name is already registered");
+        }
+        self.drivers.push(RegisteredDriver { driver, source_crate });
+
+        let mut bound = 0;
+        for entry in self.devices.iter_mut().filter(|e| e.driver.is_none() && !e.recovering) {
+            if driver.matches(entry.device) && try_probe(driver, entry.device) {
+                entry.driver = Some(driver);
+                bound += 1;
+            }
+        }
+        Ok(bound)
+    }
+
+    /// Replaces the driver at `index` with the `new_driver`, handing over all devices bound to the old driver.
+    fn replace(&mut self, index: usize, new_driver: &'static dyn Driver, source_crate: Option<String>) -> usize {
+        let old = core::mem::replace(&mut self.drivers[index], RegisteredDriver { driver: new_driver, source_crate });
+        let old_name = old.driver.name();
+
+        let mut bound = 0;
+        for entry in self.devices.iter_mut().filter(|e| e.driver.is_some_and(|d| d.name() == old_name)) {
+            let state = quiesce(old.driver, entry.device);
+            entry.driver = None;
+            if try_adopt(new_driver, entry.device, state) {
+                entry.driver = Some(new_driver);
+                bound += 1;
+            }
+        }
+        info!("Rebound {} device(s) from driver {:?} to {:?}", bound, old_name, new_driver.name());
+        bound
+    }
+
+    /// Determines how the device at the given `location` must be reset,
+    /// and removes it (and all other devices affected by that reset) from its driver.
+    ///
+    /// If the device doesn't support a function-level reset, its whole bus is reset,
+    /// along with all buses behind bridges on that bus, so all devices on those buses
+    /// are recovered along with it.
+    ///
+    /// The affected devices are marked as recovering until they're passed to [`Registry::end_recovery()`].
+    fn begin_recovery(
+        &mut self,
+        location: PciLocation,
+    ) -> Result<(&'static PciDevice, ResetMethod, Vec<RecoveringDevice>), &'static str> {
+        let device = self.devices.iter()
+            .find(|e| e.device.location == location)
+            .ok_or("no device at that location is managed by the device manager")?
+            .device;
+        let (method, buses) = if device.supports_function_level_reset() {
+            (ResetMethod::FunctionLevel, location.bus()..=location.bus())
+        } else {
+            let buses = device.bus_reset_range()?;
+            // The other reset devices must be removed and probed again too,
+            // which isn't possible for devices that were claimed by a built-in driver.
+            let all_managed = pci::pci_device_iter()?
+                .filter(|d| buses.contains(&d.location.bus()))
+                .all(|d| self.devices.iter().any(|e| e.device.location == d.location));
+            if !all_managed {
+                return Err("device doesn't support FLR, and other devices reset along with it have built-in drivers");
+            }
+            (ResetMethod::Bus, buses)
+        };
+        let is_affected = |e: &DeviceEntry| match method {
+            ResetMethod::FunctionLevel => e.device.location == location,
+            ResetMethod::Bus => buses.contains(&e.device.location.bus()),
+        };
+        if self.devices.iter().any(|e| is_affected(e) && e.recovering) {
+            return Err("device (or another device on its bus) is already being recovered");
+        }
+
+        let recovering = self.devices.iter_mut()
+            .filter(|e| is_affected(e))
+            .map(|entry| {
+                let previous_driver = entry.driver.take();
+                if let Some(driver) = previous_driver {
+                    remove(driver, entry.device);
+                }
+                entry.recovering = true;
+                RecoveringDevice { device: entry.device, previous_driver }
+            })
+            .collect();
+        Ok((device, method, recovering))
+    }
+
+    /// Restores the configuration space of each device that was reset,
+    /// and then binds it to a driver again, preferring the current version of its previous driver.
+    fn end_recovery(&mut self, recovering: Vec<RecoveringDevice>) {
+        let Registry { drivers, devices, .. } = self;
+        for RecoveringDevice { device, previous_driver } in recovering {
+            let Some(entry) = devices.iter_mut().find(|e| core::ptr::eq(e.device, device)) else {
+                continue;
+            };
+            entry.recovering = false;
+            if let Some(config) = &entry.config {
+                if let Err(e) = device.restore_config_space(config) {
+                    error!("Couldn't restore the configuration space of PCI device at {:?}: {}", device.location, e);
+                }
+            }
+            // The previous driver may have been replaced or unregistered while the device was being reset.
+            let previous_driver = previous_driver.and_then(|prev|
+                drivers.iter().map(|d| d.driver).find(|d| d.name() == prev.name())
+            );
+            match previous_driver {
+                Some(driver) if try_probe(driver, device) => entry.driver = Some(driver),
+                _ => { Registry::bind_device(drivers, entry); }
+            }
+        }
+    }
+}
+
+/// How a device was reset by [`recover_device()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResetMethod {
+    /// Only the device itself was reset, via a function-level reset (FLR).
+    FunctionLevel,
+    /// All devices on the device's bus (and on any buses behind it) were reset, via its upstream bridge.
+    Bus,
+}
+
+/// Probes the given `driver` with the given `device`, returning whether it succeeded.
+fn try_probe(driver: &'static dyn Driver, device: &'static PciDevice) -> bool {
+    match driver.probe(device) {
+        Ok(()) => {
+            info!("Bound driver {:?} to PCI device at {:?}", driver.name(), device.location);
+            true
+        }
+        Err(e) => {
+            error!("Driver {:?} failed to probe PCI device at {:?}: {}", driver.name(), device.location, e);
+            false
+        }
+    }
+}
+
+/// Has the given `driver` adopt the given quiesced `device`, returning whether it succeeded.
+fn try_adopt(driver: &'static dyn Driver, device: &'static PciDevice, state: Option<DeviceState>) -> bool {
+    match driver.adopt(device, state) {
+        Ok(()) => {
+            info!("Driver {:?} adopted PCI device at {:?}", driver.name(), device.location);
+            true
+        }
+        Err(e) => {
+            error!("Driver {:?} failed to adopt PCI device at {:?}: {}", driver.name(), device.location, e);
+            false
+        }
+    }
+}
+
+/// Quiesces the given `device` using the given `driver`, falling back to removing it
+/// (with no state to hand over) if quiescing fails.
+fn quiesce(driver: &'static dyn Driver, device: &'static PciDevice) -> Option<DeviceState> {
+    driver.quiesce(device).unwrap_or_else(|e| {
+        error!("Driver {:?} failed to quiesce PCI device at {:?}, removing it instead: {}", driver.name(), device.location, e);
+        remove(driver, device);
+        None
+    })
+}
+
+/// Removes the given `device` from the given `driver`, logging any error.
+fn remove(driver: &'static dyn Driver, device: &'static PciDevice) {
+    if let Err(e) = driver.remove(device) {
+        error!("Driver {:?} failed to remove PCI device at {:?}: {}", driver.name(), device.location, e);
+    }
+}
+
+/// Hands the given device that wasn't claimed by a built-in driver over to this module,
+/// and binds it to a registered driver, if one supports it.
+pub(crate) fn add_unclaimed_device(device: &'static PciDevice) {
+    let mut registry = REGISTRY.lock();
+    let config = device.save_config_space()
+        .map_err(|e| warn!("Couldn't save the configuration space of PCI device at {:?}: {}", device.location, e))
+        .ok();
+    let mut entry = DeviceEntry { device, driver: None, config, recoveries: 0, recovering: false };
+    if !Registry::bind_device(&registry.drivers, &mut entry) {
+        warn!("No driver is currently registered for PCI device. {:X?}", device);
+    }
+    registry.devices.push(entry);
+}
+
+/// Registers the given `driver` and binds it to all unbound devices that it supports.
+///
+/// Returns the number of devices that were bound to it,
+/// or an error if a driver with the same name was already registered.
+pub fn register_driver(driver: &'static dyn Driver) -> Result<usize, &'static str> {
+    REGISTRY.lock().register(driver, None)
+}
+
+/// Removes all devices from the driver with the given `name` and unregisters it.
+///
+/// Each removed device is then bound to another registered driver, if one supports it.
+pub fn unregister_driver(name: &str) -> Result<(), &'static str> {
+    let mut registry = REGISTRY.lock();
+    let index = registry.find_driver(name).ok_or("no driver with that name is registered")?;
+    let driver = registry.drivers.remove(index).driver;
+
+    let Registry { drivers, devices, .. } = &mut *registry;
+    for entry in devices.iter_mut().filter(|e| e.driver.is_some_and(|d| d.name() == driver.name())) {
+        remove(driver, entry.device);
+        entry.driver = None;
+        Registry::bind_device(drivers, entry);
+    }
+    Ok(())
+}
+
+/// Replaces the registered driver with the given `old_name` with the given `new_driver`,
+/// e.g., a newer version of the same driver.
+///
+/// Each device bound to the old driver is quiesced by it and then adopted by the new driver.
+/// Returns the number of devices that were bound to the new driver.
+pub fn rebind_driver(old_name: &str, new_driver: &'static dyn Driver) -> Result<usize, &'static str> {
+    let mut registry = REGISTRY.lock();
+    let index = registry.find_driver(old_name).ok_or("no driver with that name is registered")?;
+    if new_driver.name() != old_name && registry.find_driver(new_driver.name()).is_some() {
+        return Err("a different driver with the new driver's name is already registered");
+    }
+    let source_crate = registry.drivers[index].source_crate.clone();
+    Ok(registry.replace(index, new_driver, source_crate))
+}
+
+/// Suspends all devices that are bound to a driver.
+///
+/// Returns an error if any device failed to suspend, after attempting to suspend all of them.
+pub fn suspend_all() -> Result<(), &'static str> {
+    for_each_bound_device(|driver, device| driver.suspend(device))
+}
+
+/// Resumes all devices that are bound to a driver.
+///
+/// Returns an error if any device failed to resume, after attempting to resume all of them.
+pub fn resume_all() -> Result<(), &'static str> {
+    for_each_bound_device(|driver, device| driver.resume(device))
+}
+
+fn for_each_bound_device<F>(mut f: F) -> Result<(), &'static str>
+    where F: FnMut(&'static dyn Driver, &'static PciDevice) -> Result<(), &'static str>
+{
+    let mut result = Ok(());
+    for entry in REGISTRY.lock().devices.iter() {
+        if let Some(driver) = entry.driver {
+            if let Err(e) = f(driver, entry.device) {
+                error!("Driver {:?} failed on PCI device at {:?}: {}", driver.name(), entry.device.location, e);
+                result = Err(e);
+            }
+        }
+    }
+    result
+}
+
+/// Recovers the device at the given `location` that stopped responding,
+/// by removing it from its driver, resetting it, restoring its configuration space,
+/// and then probing it again.
+///
+/// If the device doesn't support a function-level reset, all devices on its bus
+/// (and on any buses behind it) are reset and recovered along with it,
+/// which is only possible if they're all managed by this module.
+///
+/// The reset sleeps, so this must not be called while holding any spinlocks.
+///
+/// Returns how the device was reset, or an error if it couldn't be reset,
+/// in which case it's still probed again.
+pub fn recover_device(location: PciLocation) -> Result<ResetMethod, &'static str> {
+    let (device, method, recovering) = REGISTRY.lock().begin_recovery(location)?;
+    let result = match method {
+        ResetMethod::FunctionLevel => device.function_level_reset(),
+        ResetMethod::Bus => device.bus_reset(),
+    };
+    REGISTRY.lock().end_recovery(recovering);
+    result.map(|_| method)
+}
+
+/// Spawns the task that automatically recovers devices whose drivers report that they need it.
+pub(crate) fn spawn_recovery_task() -> Result<(), &'static str> {
+    spawn::new_task_builder(recovery_loop, ())
+        .name(String::from("device_recovery"))
+        .spawn()?;
+    Ok(())
+}
+
+fn recovery_loop(_: ()) -> Result<(), &'static str> {
+    loop {
+        sleep::sleep(RECOVERY_POLL_INTERVAL).map_err(|_| "device recovery task failed to sleep")?;
+
+        let needs_recovery = |e: &DeviceEntry| e.driver.is_some_and(|d| d.needs_recovery(e.device));
+        let locations: Vec<PciLocation> = REGISTRY.lock().devices.iter()
+            .filter(|e| needs_recovery(e))
+            .map(|e| e.device.location)
+            .collect();
+        for location in locations {
+            {
+                let mut registry = REGISTRY.lock();
+                // The device may have already been recovered by a bus reset for an earlier device.
+                let Some(entry) = registry.devices.iter_mut().find(|e| e.device.location == location && needs_recovery(e)) else {
+                    continue;
+                };
+                if entry.recoveries >= MAX_AUTOMATIC_RECOVERIES {
+                    error!("PCI device at {:?} stopped responding again after {} recoveries, leaving it unbound",
+                        location, entry.recoveries,
+                    );
+                    if let Some(driver) = entry.driver.take() {
+                        remove(driver, entry.device);
+                    }
+                    continue;
+                }
+                entry.recoveries += 1;
+            }
+            warn!("PCI device at {:?} stopped responding, recovering it", location);
+            match recover_device(location) {
+                Ok(method) => info!("Recovered PCI device at {:?} via {:?} reset", location, method),
+                Err(e) => error!("Couldn't reset PCI device at {:?}: {}", location, e),
+            }
+        }
+    }
+}
+
+/// Returns the location of each device managed by this module
+/// and the name of the driver it is bound to, if any.
+pub fn bindings() -> Vec<(PciLocation, Option<&'static str>)> {
+    REGISTRY.lock().devices.iter()
+        .map(|e| (e.device.location, e.driver.map(|d| d.name())))
+        .collect()
+}
+
+/// Returns the statistics of each device that is bound to a driver,
+/// along with its location and the name of its driver.
+pub fn device_stats() -> Vec<(PciLocation, &'static str, StatsSnapshot)> {
+    REGISTRY.lock().devices.iter()
+        .filter_map(|e| {
+            let driver = e.driver?;
+            let stats = driver.stats(e.device)?;
+            Some((e.device.location, driver.name(), stats.snapshot()))
+        })
+        .collect()
+}
+
+
+
+/// Returns the [`Driver`] registered by the given crate via `device_driver::register_driver!()`.
+///
+/// The returned driver is only valid for as long as the crate remains loaded.
+pub fn driver_from_crate(crate_ref: &StrongCrateRef) -> Result<&'static dyn Driver, &'static str> {
+    let registration_func_sec = {
+        let krate = crate_ref.lock_as_ref();
+        let expected_name = format!("{}{}{}", krate.crate_name_as_prefix(), REGISTRATION_FUNC_NAME, SECTION_HASH_DELIMITER);
+        krate.find_section(|sec| sec.typ == SectionType::Text && sec.name_without_hash() == expected_name)
+            .cloned()
+            .ok_or("crate doesn't register a driver, i.e., it didn't invoke `device_driver::register_driver!()`")?
+    };
+    // SAFE: the function was generated by `register_driver!()`, which guarantees its signature.
+    let registration_func = unsafe { registration_func_sec.as_func::<RegistrationFunc>() }?;
+    Ok(registration_func())
+}
+
+/// Returns the given crate's name without its hash.
+fn source_crate_name(krate: &LoadedCrate) -> String {
+    String::from(krate.crate_name_without_hash())
+}
+
+/// Loads the driver crate from the given object file into the current task's namespace,
+/// then registers its [`Driver`] and binds it to all unbound devices that it supports.
+///
+/// Returns the number of devices that were bound to the new driver.
+pub fn load_driver_crate(crate_object_file: &Path) -> Result<usize, &'static str> {
+    let namespace = task::with_current_task(|t| t.get_namespace().clone())
+        .map_err(|_| "load_driver_crate(): couldn't get current task")?;
+    let crate_object_file = match crate_object_file.get(namespace.dir())
+        .or_else(|| PathBuf::from(format!("{}.o", &crate_object_file)).get(namespace.dir()))
+    {
+        Some(FileOrDir::File(f)) => f,
+        _ => return Err("load_driver_crate(): couldn't find driver crate object file"),
+    };
+    let kernel_mmi_ref = memory::get_kernel_mmi_ref().ok_or("load_driver_crate(): couldn't get kernel MMI")?;
+    let (crate_ref, _num_syms) = namespace.load_crate(&crate_object_file, None, kernel_mmi_ref, false)?;
+    let driver = driver_from_crate(&crate_ref)?;
+    let source_crate = source_crate_name(&crate_ref.lock_as_ref());
+    REGISTRY.lock().register(driver, Some(source_crate))
+}
+
+/// Rebinds devices to the driver in the given newly-loaded crate,
+/// which has replaced an older version of the same driver crate.
+///
+/// The old driver is identified by having the same name as the new one;
+/// if no such driver is registered, the new driver is simply registered.
+///
+/// This is done automatically for crates swapped via `crate_swap`.
+///
+/// Returns the number of devices that were bound to the new driver.
+pub fn rebind_swapped_driver_crate(new_crate: &StrongCrateRef) -> Result<usize, &'static str> {
+    let new_driver = driver_from_crate(new_crate)?;
+    let source_crate = Some(source_crate_name(&new_crate.lock_as_ref()));
+    let mut registry = REGISTRY.lock();
+    match registry.find_driver(new_driver.name()) {
+        Some(index) => Ok(registry.replace(index, new_driver, source_crate)),
+        None => registry.register(new_driver, source_crate),
+    }
+}
+
+
+/// The hooks that coordinate driver rebinding with crate swapping.
+#[cfg(target_arch = "x86_64")]
+pub(crate) const SWAP_HOOKS: crate_swap::SwapHooks = crate_swap::SwapHooks {
+    prepare: prepare_swap,
+    complete: complete_swap,
+    abort: abort_swap,
+};
+
+/// Quiesces all devices bound to drivers from any of the given crates that are about to be swapped out.
+#[cfg(target_arch = "x86_64")]
+fn prepare_swap(old_crate_names: &[&str]) -> Result<(), &'static str> {
+    let mut registry = REGISTRY.lock();
+    let Registry { drivers, devices, quiesced } = &mut *registry;
+    let is_swapped = |d: &RegisteredDriver| d.source_crate.as_deref().is_some_and(|source|
+        old_crate_names.iter().any(|ocn| ocn.split(mod_mgmt::CRATE_HASH_DELIMITER).next() == Some(source))
+    );
+    for driver in drivers.iter().filter(|d| is_swapped(d)).map(|d| d.driver) {
+        for entry in devices.iter_mut().filter(|e| e.driver.is_some_and(|d| d.name() == driver.name())) {
+            let state = quiesce(driver, entry.device);
+            entry.driver = None;
+            quiesced.push(QuiescedDevice { device: entry.device, old_driver: driver, state });
+        }
+    }
+    if !quiesced.is_empty() {
+        info!("Quiesced {} device(s) before swapping crates {:?}", quiesced.len(), old_crate_names);
+    }
+    Ok(())
+}
+
+/// Hands each quiesced device over to the new version of its driver from the given swapped-in crates.
+#[cfg(target_arch = "x86_64")]
+fn complete_swap(new_crates: &[StrongCrateRef]) -> Result<(), &'static str> {
+    let mut registry = REGISTRY.lock();
+    let mut result = Ok(());
+
+    for quiesced in core::mem::take(&mut registry.quiesced) {
+        let old_name = quiesced.old_driver.name();
+        // Find the new driver that replaces the old one, which has the same name.
+        let new_driver = new_crates.iter()
+            .filter_map(|c| driver_from_crate(c).ok().map(|d| (d, c)))
+            .find(|(d, _c)| d.name() == old_name);
+        let Some((new_driver, new_crate)) = new_driver else {
+            error!("No swapped-in crate provides a new version of driver {:?}, its devices will remain unbound", old_name);
+            result = Err("couldn't find new version of a quiesced driver");
+            continue;
+        };
+        if let Some(index) = registry.find_driver(old_name) {
+            registry.drivers[index] = RegisteredDriver {
+                driver: new_driver,
+                source_crate: Some(source_crate_name(&new_crate.lock_as_ref())),
+            };
+        }
+        if try_adopt(new_driver, quiesced.device, quiesced.state) {
+            if let Some(entry) = registry.devices.iter_mut().find(|e| core::ptr::eq(e.device, quiesced.device)) {
+                entry.driver = Some(new_driver);
+            }
+        }
+    }
+    result
+}
+
+/// Hands each quiesced device back to its old driver after a failed crate swap.
+#[cfg(target_arch = "x86_64")]
+fn abort_swap() {
+    let mut registry = REGISTRY.lock();
+    for quiesced in core::mem::take(&mut registry.quiesced) {
+        if try_adopt(quiesced.old_driver, quiesced.device, quiesced.state) {
+            if let Some(entry) = registry.devices.iter_mut().find(|e| core::ptr::eq(e.device, quiesced.device)) {
+                entry.driver = Some(quiesced.old_driver);
+            }
+        }
+    }
+}
@@ -3,6 +3,9 @@
 
 extern crate alloc;
 
+pub mod drivers;
+mod driver_metrics;
+
 use log::*;
 
 #[cfg(target_arch = "x86_64")]
@@ -94,6 +97,21 @@ pub fn init(
     #[cfg(target_arch = "x86_64")]
     let mut ixgbe_devs = Vec::new();
 
+    // Export the statistics that drivers keep for their devices as metrics.
+    driver_metrics::register();
+
+    // Have driver crates that are swapped at runtime rebind their devices to the new driver version.
+    #[cfg(target_arch = "x86_64")]
+    crate_swap::register_swap_hooks(drivers::SWAP_HOOKS);
+
+    // Automatically recover devices that stopped responding, as reported by their drivers.
+    drivers::spawn_recovery_task()?;
+
+    // The loopback interface is always available, even if there are no NICs.
+    // No networking support on aarch64 at the moment
+    #[cfg(target_arch = "x86_64")]
+    net::init_loopback();
+
     // Iterate over all PCI devices and initialize the drivers for the devices we support.
 
     for dev in pci::pci_device_iter()? {
@@ -132,6 +150,20 @@ pub fn init(
 
                 continue;
             }
+            if dev.vendor_id == rtl8139::REALTEK_VEND && dev.device_id == rtl8139::RTL8139_DEV {
+                info!("rtl8139 PCI device found at: {:?}", dev.location);
+                let nic = rtl8139::Rtl8139Nic::init(dev)?;
+                let interface = net::register_device(nic);
+                nic.lock().init_interrupts(interface)?;
+                continue;
+            }
+            if dev.vendor_id == rtl8168::REALTEK_VEND && rtl8168::RTL8168_DEVS.contains(&dev.device_id) {
+                info!("rtl8168 PCI device found at: {:?}", dev.location);
+                let nic = rtl8168::Rtl8168Nic::init(dev)?;
+                let interface = net::register_device(nic);
+                nic.lock().init_interrupts(interface)?;
+                continue;
+            }
             if dev.vendor_id == ixgbe::INTEL_VEND && dev.device_id == ixgbe::INTEL_82599 {
                 info!("ixgbe PCI device found at: {:?}", dev.location);
                 
@@ -169,7 +201,8 @@ pub fn init(
             // here: check for and initialize other ethernet cards
         }
 
-        warn!("Ignoring PCI device with no handler. {:X?}", dev);
+        // Let the driver framework bind this device to a registered driver, if any.
+        drivers::add_unclaimed_device(dev);
     }
 
     // Once all the NICs have been initialized, we can store them and add them to the list of network interfaces.
@@ -194,11 +227,19 @@ pub fn init(
     #[cfg(target_arch = "x86_64")]
     if false {
         for storage_device in storage_manager::storage_devices() {
+            // Journal all writes to the volume, such that a crash can't leave it inconsistent.
+            let journaled_device = match fat_journal::JournaledDevice::open(
+                LockableIo::<dyn StorageDevice + Send, spin::Mutex<_>, _>::from(storage_device)
+            ) {
+                Ok(device) => device,
+                Err(e) => {
+                    debug!("Skipping storage device without a FAT volume: {:?}", e);
+                    continue;
+                }
+            };
             let disk = fatfs_adapter::FatFsAdapter::new(
                 ReaderWriter::new(
-                    ByteReaderWriterWrapper::from(
-                        LockableIo::<dyn StorageDevice + Send, spin::Mutex<_>, _>::from(storage_device)
-                    )
+                    ByteReaderWriterWrapper::from(journaled_device)
                 ),
             );
 
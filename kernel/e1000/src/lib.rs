@@ -25,6 +25,8 @@ extern crate nic_initialization;
 extern crate net;
 extern crate deferred_interrupt_tasks;
 extern crate task;
+extern crate nic_polling;
+extern crate cpu;
 
 pub mod test_e1000_driver;
 mod regs;
@@ -42,6 +44,7 @@ use nic_initialization::{init_rx_buf_pool, init_rx_queue, init_tx_queue};
 use intel_ethernet::descriptors::{LegacyRxDescriptor, LegacyTxDescriptor};
 use nic_buffers::{TransmitBuffer, ReceiveBuffer, ReceivedFrame};
 use nic_queues::{RxQueue, TxQueue, RxQueueRegisters, TxQueueRegisters};
+use nic_polling::{AdaptiveQueue, ModerationConfig, Mode, PolledQueue};
 
 pub const INTEL_VEND:           u16 = 0x8086;  // Vendor ID for Intel 
 pub const E1000_DEV:            u16 = 0x100E;  // Device ID for the e1000 Qemu, Bochs, and VirtualBox emmulated NICs
@@ -57,6 +60,10 @@ const INT_LSC:              u32 = 0x04;
 /// Interrupt type: Receive Timer Interrupt
 const INT_RX:               u32 = 0x80;
 
+/// The minimum interval between interrupts in 256 ns units, which limits the NIC to
+/// about 20,000 interrupts per second by coalescing frames received in between.
+const E1000_ITR_INTERVAL:   u32 = 195;
+
 
 /// The single instance of the E1000 NIC.
 /// TODO: in the future, we should support multiple NICs all stored elsewhere,
@@ -143,6 +150,9 @@ pub struct E1000Nic {
     /// memory-mapped registers holding the MAC address
     mac_regs: BorrowedMappedPages<E1000MacRegisters, Mutable>,
     deferred_task: Option<task::JoinableTaskRef>,
+    /// The adaptive interrupt moderation of the receive queue, which switches it to polled mode
+    /// under high packet rates.
+    rx_moderation: Option<Arc<AdaptiveQueue>>,
 }
 
 /// Functions that setup the NIC struct and handle the sending and receiving of packets.
@@ -228,6 +238,7 @@ impl E1000Nic {
             regs: mapped_registers,
             mac_regs: mac_registers,
             deferred_task: None,
+            rx_moderation: None,
         };
         
         let nic_ref = E1000_NIC.call_once(|| IrqSafeMutex::new(e1000_nic));
@@ -248,7 +259,7 @@ impl E1000Nic {
             self.interrupt_num,
             e1000_handler,
             poll_interface,
-            interface,
+            interface.clone(),
             Some(format!("e1000_deferred_task_irq_{:#X}", self.interrupt_num)),
         )
         .map_err(|error| {
@@ -257,6 +268,14 @@ impl E1000Nic {
         })?;
         self.deferred_task = Some(deferred_task);
 
+        let rx_moderation = nic_polling::register(
+            format!("{}:rx{}", interface.name(), self.rx_queue.id),
+            Arc::new(E1000PolledQueue { interface }),
+            cpu::current_cpu(),
+            ModerationConfig::default(),
+        )?;
+        self.rx_moderation = Some(rx_moderation);
+
         Ok(())
     }
 
@@ -397,6 +416,8 @@ impl E1000Nic {
 
         // Trigger interrupts on a Link Status Change and on a Receive Transfer.
         self.regs.ims.write(INT_LSC | INT_RX);
+        // Coalesce receive interrupts that would otherwise occur in quick succession.
+        self.regs.itr.write(E1000_ITR_INTERVAL);
         // Clear all pending interrupts.
         self.regs.icr.read();
     }
@@ -423,7 +444,12 @@ impl E1000Nic {
         // receiver timer interrupt
         if (status & INT_RX) == INT_RX {
             // debug!("e1000::handle_interrupt(): receive interrupt");
-            self.rx_queue.poll_queue_and_store_received_packets()?;
+            let frames = self.rx_queue.poll_queue_with_budget(usize::MAX)?;
+            let mode = self.rx_moderation.as_ref().map(|moderation| moderation.on_interrupt(frames));
+            if mode == Some(Mode::Polling) {
+                // The receive queue is now polled by its CPU's polling task until it's idle again.
+                self.regs.imc.write(INT_RX);
+            }
             handled = true;
         }
 
@@ -441,6 +467,42 @@ impl E1000Nic {
     }
 }
 
+/// The receive queue of the E1000 NIC, as polled while its interrupts are masked.
+struct E1000PolledQueue {
+    interface: Arc<net::NetworkInterface>,
+}
+
+impl PolledQueue for E1000PolledQueue {
+    fn poll(&self, budget: usize) -> usize {
+        let Some(e1000_nic_ref) = E1000_NIC.get() else {
+            return 0;
+        };
+        let received = e1000_nic_ref.lock().rx_queue.poll_queue_with_budget(budget);
+        match received {
+            Ok(0) => 0,
+            Ok(frames) => {
+                self.interface.poll();
+                frames
+            }
+            Err(e) => {
+                error!("e1000: error polling receive queue: {:?}", e);
+                0
+            }
+        }
+    }
+
+    fn set_interrupts_enabled(&self, enabled: bool) {
+        if let Some(e1000_nic_ref) = E1000_NIC.get() {
+            let mut e1000_nic = e1000_nic_ref.lock();
+            if enabled {
+                e1000_nic.regs.ims.write(INT_RX);
+            } else {
+                e1000_nic.regs.imc.write(INT_RX);
+            }
+        }
+    }
+}
+
 impl net::NetworkDevice for E1000Nic {
     fn send(&mut self, buf: TransmitBuffer) {
         self.tx_queue.send_on_queue(buf);
@@ -12,7 +12,7 @@
 //! * `E1000MacRegisters`
 
 
-use volatile::{Volatile, ReadOnly};
+use volatile::{Volatile, ReadOnly, WriteOnly};
 use zerocopy::FromBytes;
 
 /// The layout in memory of the first set of e1000 registers. 
@@ -26,9 +26,14 @@ pub struct E1000Registers {
     
     /// Interrupt control registers
     pub icr:                        ReadOnly<u32>,          // 0xC0   
-    _padding2:                      [u8; 12],               // 0xC4 - 0xCF
+    /// Interrupt throttling register, the minimum interval between interrupts in 256 ns units
+    pub itr:                        Volatile<u32>,          // 0xC4
+    _padding2:                      [u8; 8],                // 0xC8 - 0xCF
     pub ims:                        Volatile<u32>,          // 0xD0
-    _padding3:                      [u8; 44],               // 0xD4 - 0xFF 
+    _padding3:                      [u8; 4],                // 0xD4 - 0xD7
+    /// Interrupt mask clear register, which disables the interrupts whose bits are set
+    pub imc:                        WriteOnly<u32>,         // 0xD8
+    _padding3a:                     [u8; 36],               // 0xDC - 0xFF 
 
     /// Receive control register
     pub rctl:                       Volatile<u32>,          // 0x100
@@ -11,7 +11,6 @@ compile_error!("The `bios` feature can only be used on x86_64");
 
 use core::{fmt::{self, Write}, slice, ops::{Deref, DerefMut}};
 use boot_info::{FramebufferInfo, FramebufferFormat};
-use font::FONT_BASIC;
 use memory::{BorrowedSliceMappedPages, Mutable, PteFlags, PhysicalAddress, PteFlagsArch, PageTable};
 use spin::Mutex;
 
@@ -273,8 +272,7 @@ impl EarlyFramebufferPrinter {
             return self.newline(background_pixel_color);
         }
 
-        let ascii = if ch.is_ascii() { ch as u8 } else { b'?' };
-        let glyph = &FONT_BASIC[ascii as usize];
+        let glyph = font::glyph(ch);
 
         for (row_bits, row) in glyph.iter().zip(0u32..) {
             // Copy each row of the font glyph to the framebuffer in a single action
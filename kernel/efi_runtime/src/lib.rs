@@ -0,0 +1,432 @@
+//! Access to the EFI runtime services, which remain usable after the bootloader has exited.
+//!
+//! When Theseus is booted via UEFI, the firmware's runtime services can be used to
+//! read and write EFI variables, e.g., to persist boot choices or crash markers across reboots,
+//! and to get or set the time of the firmware's real-time clock.
+//!
+//! [`init()`] identity maps the memory regions used by the runtime services,
+//! which are then called in physical mode, i.e., without calling `SetVirtualAddressMap()`,
+//! such that the firmware's own pointers remain valid.
+//! All calls are serialized and made with interrupts disabled,
+//! as the runtime services are not reentrant.
+//!
+//! On legacy BIOS boots, or if the runtime services couldn't be initialized,
+//! every function in this crate returns [`EfiError::Unsupported`].
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::{string::String, vec, vec::Vec};
+use bitflags::bitflags;
+use boot_info::EfiRuntimeInfo;
+use core::{fmt, ptr};
+use log::{debug, warn};
+use memory::{
+    allocate_frames_by_bytes_at, allocate_pages_by_bytes, allocate_pages_by_bytes_at,
+    MappedPages, PageTable, PhysicalAddress, PteFlags, VirtualAddress,
+};
+use spin::Once;
+use sync_irq::IrqSafeMutex;
+
+/// The signature of the EFI system table, "IBI SYST".
+const SYSTEM_TABLE_SIGNATURE: u64 = 0x5453_5953_2049_4249;
+/// The signature of the EFI runtime services table, "RUNTSERV".
+const RUNTIME_SERVICES_SIGNATURE: u64 = 0x5652_4553_544E_5552;
+/// The offset of the pointer to the runtime services table within the EFI system table.
+const SYSTEM_TABLE_RUNTIME_SERVICES_OFFSET: usize = 88;
+
+/// The high bit of an EFI status code, which is set for errors but not for warnings.
+const STATUS_ERROR_BIT: usize = 1 << (usize::BITS - 1);
+
+static RUNTIME: Once<IrqSafeMutex<Runtime>> = Once::new();
+
+type Status = usize;
+
+/// The EFI runtime services table, up to the last service that we use.
+#[repr(C)]
+struct RuntimeServices {
+    /// The signature within the EFI table header.
+    signature: u64,
+    /// The rest of the EFI table header: revision, header size, CRC32, and reserved.
+    _header: [u32; 4],
+    get_time: unsafe extern "efiapi" fn(time: *mut EfiTime, capabilities: *mut u8) -> Status,
+    set_time: unsafe extern "efiapi" fn(time: *const EfiTime) -> Status,
+    _get_wakeup_time: usize,
+    _set_wakeup_time: usize,
+    _set_virtual_address_map: usize,
+    _convert_pointer: usize,
+    get_variable: unsafe extern "efiapi" fn(
+        name: *const u16,
+        vendor: *const Guid,
+        attributes: *mut u32,
+        data_size: *mut usize,
+        data: *mut u8,
+    ) -> Status,
+    get_next_variable_name: unsafe extern "efiapi" fn(
+        name_size: *mut usize,
+        name: *mut u16,
+        vendor: *mut Guid,
+    ) -> Status,
+    set_variable: unsafe extern "efiapi" fn(
+        name: *const u16,
+        vendor: *const Guid,
+        attributes: u32,
+        data_size: usize,
+        data: *const u8,
+    ) -> Status,
+}
+
+struct Runtime {
+    /// The runtime services table, which is within the identity-mapped `regions`.
+    services: *const RuntimeServices,
+    /// The identity mappings of the runtime services' memory regions,
+    /// which must never be dropped.
+    _regions: Vec<MappedPages>,
+}
+
+// SAFETY: the runtime services table is identity mapped in the kernel's page table,
+//         which is shared by all tasks, and is only accessed while holding the lock.
+unsafe impl Send for Runtime {}
+
+/// Initializes access to the EFI runtime services described by the given `info`,
+/// identity mapping their memory regions into the given `page_table`.
+///
+/// If `info` is `None`, e.g., on a legacy BIOS boot, this does nothing.
+pub fn init(info: Option<EfiRuntimeInfo>, page_table: &mut PageTable) -> Result<(), &'static str> {
+    let Some(info) = info else {
+        debug!("EFI runtime services aren't available");
+        return Ok(());
+    };
+
+    let runtime_services_address = {
+        let (mapped_pages, offset) = map_temporarily(info.system_table, page_table)?;
+        let signature: &u64 = mapped_pages.as_type(offset)?;
+        if *signature != SYSTEM_TABLE_SIGNATURE {
+            return Err("EFI system table had an invalid signature");
+        }
+        let address: &u64 = mapped_pages.as_type(offset + SYSTEM_TABLE_RUNTIME_SERVICES_OFFSET)?;
+        PhysicalAddress::new(*address as usize).ok_or("EFI runtime services table had an invalid address")?
+    };
+
+    let mut regions = Vec::new();
+    for region in info.regions.iter().flatten() {
+        let flags = PteFlags::new().valid(true).writable(true).executable(region.executable);
+        let pages = allocate_pages_by_bytes_at(VirtualAddress::new_canonical(region.start.value()), region.len)
+            .map_err(|_| "couldn't allocate pages to identity map EFI runtime services")?;
+        let frames = allocate_frames_by_bytes_at(region.start, region.len)
+            .map_err(|_| "couldn't allocate frames for EFI runtime services")?;
+        regions.push(page_table.map_allocated_pages_to(pages, frames, flags)?);
+    }
+
+    let services_start = runtime_services_address.value();
+    let services_end = services_start + core::mem::size_of::<RuntimeServices>();
+    let is_mapped = regions.iter().any(|mp| {
+        mp.start_address().value() <= services_start
+            && services_end <= mp.start_address().value() + mp.size_in_bytes()
+    });
+    if !is_mapped {
+        return Err("EFI runtime services table wasn't within a runtime services memory region");
+    }
+    let services = services_start as *const RuntimeServices;
+    // SAFETY: the table was just identity mapped above.
+    if unsafe { (*services).signature } != RUNTIME_SERVICES_SIGNATURE {
+        return Err("EFI runtime services table had an invalid signature");
+    }
+
+    debug!("Initialized EFI runtime services at {:#X}, {} memory regions", services_start, regions.len());
+    RUNTIME.call_once(|| IrqSafeMutex::new(Runtime { services, _regions: regions }));
+    Ok(())
+}
+
+/// Returns whether the EFI runtime services are available.
+pub fn is_available() -> bool {
+    RUNTIME.is_completed()
+}
+
+/// Maps the beginning of the EFI system table at the given physical `address` as read-only,
+/// returning the mapped pages and the offset of `address` within them.
+fn map_temporarily(address: PhysicalAddress, page_table: &mut PageTable) -> Result<(MappedPages, usize), &'static str> {
+    let offset = address.frame_offset();
+    let len = offset + core::mem::size_of::<u64>() + SYSTEM_TABLE_RUNTIME_SERVICES_OFFSET;
+    let pages = allocate_pages_by_bytes(len).ok_or("couldn't allocate pages for the EFI system table")?;
+    let frames = allocate_frames_by_bytes_at(address - offset, len)
+        .map_err(|_| "couldn't allocate frames for the EFI system table")?;
+    let mapped_pages = page_table.map_allocated_pages_to(pages, frames, PteFlags::new().valid(true))?;
+    Ok((mapped_pages, offset))
+}
+
+/// Calls the given function with the runtime services table, with interrupts disabled.
+fn with_services<R>(f: impl FnOnce(&RuntimeServices) -> R) -> Result<R, EfiError> {
+    let runtime = RUNTIME.get().ok_or(EfiError::Unsupported)?.lock();
+    // SAFETY: the table was identity mapped and validated in `init()`, and is never unmapped.
+    Ok(f(unsafe { &*runtime.services }))
+}
+
+/// An error returned by an EFI runtime service.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EfiError {
+    /// The runtime services aren't available, or the firmware doesn't support this service.
+    Unsupported,
+    /// The variable wasn't found.
+    NotFound,
+    /// The given buffer was too small; the required size in bytes is included.
+    BufferTooSmall(usize),
+    InvalidParameter,
+    DeviceError,
+    WriteProtected,
+    OutOfResources,
+    SecurityViolation,
+    /// Another EFI status code.
+    Other(usize),
+}
+
+impl EfiError {
+    fn from_status(status: Status) -> Result<(), EfiError> {
+        if status & STATUS_ERROR_BIT == 0 {
+            // Warnings are not errors.
+            return Ok(());
+        }
+        Err(match status & !STATUS_ERROR_BIT {
+            2  => EfiError::InvalidParameter,
+            3  => EfiError::Unsupported,
+            5  => EfiError::BufferTooSmall(0),
+            7  => EfiError::DeviceError,
+            8  => EfiError::WriteProtected,
+            9  => EfiError::OutOfResources,
+            14 => EfiError::NotFound,
+            26 => EfiError::SecurityViolation,
+            _  => EfiError::Other(status),
+        })
+    }
+}
+
+impl fmt::Display for EfiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EfiError::Unsupported => f.write_str("EFI runtime service is unsupported"),
+            EfiError::NotFound => f.write_str("EFI variable not found"),
+            EfiError::BufferTooSmall(size) => write!(f, "buffer too small, {} bytes are required", size),
+            EfiError::InvalidParameter => f.write_str("invalid parameter"),
+            EfiError::DeviceError => f.write_str("EFI device error"),
+            EfiError::WriteProtected => f.write_str("EFI variable is write protected"),
+            EfiError::OutOfResources => f.write_str("EFI firmware is out of resources"),
+            EfiError::SecurityViolation => f.write_str("EFI security violation"),
+            EfiError::Other(status) => write!(f, "EFI status {:#X}", status),
+        }
+    }
+}
+
+impl From<EfiError> for &'static str {
+    fn from(error: EfiError) -> &'static str {
+        match error {
+            EfiError::Unsupported => "EFI runtime service is unsupported",
+            EfiError::NotFound => "EFI variable not found",
+            EfiError::BufferTooSmall(_) => "buffer too small for EFI variable",
+            EfiError::InvalidParameter => "invalid parameter to EFI runtime service",
+            EfiError::DeviceError => "EFI device error",
+            EfiError::WriteProtected => "EFI variable is write protected",
+            EfiError::OutOfResources => "EFI firmware is out of resources",
+            EfiError::SecurityViolation => "EFI security violation",
+            EfiError::Other(_) => "EFI runtime service failed",
+        }
+    }
+}
+
+/// The GUID of the vendor that owns an EFI variable.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[repr(C)]
+pub struct Guid {
+    pub data1: u32,
+    pub data2: u16,
+    pub data3: u16,
+    pub data4: [u8; 8],
+}
+
+impl Guid {
+    /// The vendor of the variables defined by the UEFI specification, e.g., `BootOrder`.
+    pub const GLOBAL_VARIABLE: Guid = Guid::new(0x8BE4_DF61, 0x93CA, 0x11D2, [0xAA, 0x0D, 0x00, 0xE0, 0x98, 0x03, 0x2B, 0x8C]);
+
+    pub const fn new(data1: u32, data2: u16, data3: u16, data4: [u8; 8]) -> Guid {
+        Guid { data1, data2, data3, data4 }
+    }
+}
+
+impl fmt::Display for Guid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let d = &self.data4;
+        write!(f, "{:08x}-{:04x}-{:04x}-{:02x}{:02x}-{:02x}{:02x}{:02x}{:02x}{:02x}{:02x}",
+            self.data1, self.data2, self.data3, d[0], d[1], d[2], d[3], d[4], d[5], d[6], d[7],
+        )
+    }
+}
+
+bitflags! {
+    /// The attributes of an EFI variable.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub struct VariableAttributes: u32 {
+        /// The variable persists across reboots.
+        const NON_VOLATILE       = 0x1;
+        /// The variable can be accessed by the bootloader.
+        const BOOTSERVICE_ACCESS = 0x2;
+        /// The variable can be accessed by the OS, which is required for it to be used here.
+        const RUNTIME_ACCESS     = 0x4;
+    }
+}
+
+/// Converts the given variable `name` into a null-terminated UCS-2 string.
+fn to_ucs2(name: &str) -> Result<Vec<u16>, EfiError> {
+    let mut ucs2 = Vec::with_capacity(name.len() + 1);
+    for c in name.chars() {
+        let c = u16::try_from(u32::from(c)).map_err(|_| EfiError::InvalidParameter)?;
+        if c == 0 {
+            return Err(EfiError::InvalidParameter);
+        }
+        ucs2.push(c);
+    }
+    ucs2.push(0);
+    Ok(ucs2)
+}
+
+/// Reads the EFI variable with the given `name` and `vendor` into `buf`,
+/// returning its attributes and its size in bytes.
+///
+/// If `buf` is too small, [`EfiError::BufferTooSmall`] is returned with the required size.
+pub fn get_variable(name: &str, vendor: &Guid, buf: &mut [u8]) -> Result<(VariableAttributes, usize), EfiError> {
+    let name = to_ucs2(name)?;
+    let mut attributes = 0u32;
+    let mut size = buf.len();
+    let status = with_services(|services| unsafe {
+        (services.get_variable)(name.as_ptr(), vendor, &mut attributes, &mut size, buf.as_mut_ptr())
+    })?;
+    match EfiError::from_status(status) {
+        Err(EfiError::BufferTooSmall(_)) => Err(EfiError::BufferTooSmall(size)),
+        result => result.map(|_| (VariableAttributes::from_bits_retain(attributes), size)),
+    }
+}
+
+/// Reads the EFI variable with the given `name` and `vendor`,
+/// returning its attributes and its contents.
+pub fn get_variable_vec(name: &str, vendor: &Guid) -> Result<(VariableAttributes, Vec<u8>), EfiError> {
+    let mut buf = Vec::new();
+    loop {
+        match get_variable(name, vendor, &mut buf) {
+            Ok((attributes, size)) => {
+                buf.truncate(size);
+                return Ok((attributes, buf));
+            }
+            Err(EfiError::BufferTooSmall(size)) if size > buf.len() => buf.resize(size, 0),
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Writes the EFI variable with the given `name` and `vendor`, creating it if it doesn't exist.
+///
+/// `attributes` must include [`VariableAttributes::RUNTIME_ACCESS`],
+/// and should include [`VariableAttributes::NON_VOLATILE`] for the variable to persist across reboots.
+pub fn set_variable(name: &str, vendor: &Guid, attributes: VariableAttributes, data: &[u8]) -> Result<(), EfiError> {
+    if !attributes.contains(VariableAttributes::RUNTIME_ACCESS) {
+        return Err(EfiError::InvalidParameter);
+    }
+    let name = to_ucs2(name)?;
+    let status = with_services(|services| unsafe {
+        (services.set_variable)(name.as_ptr(), vendor, attributes.bits(), data.len(), data.as_ptr())
+    })?;
+    EfiError::from_status(status)
+}
+
+/// Deletes the EFI variable with the given `name` and `vendor`.
+pub fn delete_variable(name: &str, vendor: &Guid) -> Result<(), EfiError> {
+    let name = to_ucs2(name)?;
+    let status = with_services(|services| unsafe {
+        (services.set_variable)(name.as_ptr(), vendor, 0, 0, ptr::null())
+    })?;
+    EfiError::from_status(status)
+}
+
+/// Returns the name and vendor of every EFI variable that is accessible at runtime.
+pub fn variable_names() -> Result<Vec<(String, Guid)>, EfiError> {
+    let mut names = Vec::new();
+    // The first call must be given an empty name, and each subsequent call the previous name.
+    let mut name: Vec<u16> = vec![0; 64];
+    let mut vendor = Guid::new(0, 0, 0, [0; 8]);
+    loop {
+        let mut size = name.len() * core::mem::size_of::<u16>();
+        let status = with_services(|services| unsafe {
+            (services.get_next_variable_name)(&mut size, name.as_mut_ptr(), &mut vendor)
+        })?;
+        match EfiError::from_status(status) {
+            Ok(()) => {
+                let len = name.iter().position(|&c| c == 0).unwrap_or(name.len());
+                names.push((String::from_utf16_lossy(&name[..len]), vendor));
+            }
+            Err(EfiError::NotFound) => return Ok(names),
+            Err(EfiError::BufferTooSmall(_)) => {
+                // The name in the buffer is left unchanged, so it can be retried with a bigger buffer.
+                let required_len = size.div_ceil(core::mem::size_of::<u16>());
+                if required_len <= name.len() {
+                    warn!("EFI GetNextVariableName() requested a smaller buffer than it was given");
+                    return Err(EfiError::DeviceError);
+                }
+                name.resize(required_len, 0);
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// A time from the EFI real-time clock.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[repr(C)]
+pub struct EfiTime {
+    pub year: u16,
+    /// From 1 to 12.
+    pub month: u8,
+    /// From 1 to 31.
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+    _pad1: u8,
+    pub nanosecond: u32,
+    /// The offset from UTC in minutes, or [`EfiTime::UNSPECIFIED_TIMEZONE`] if the time is local time.
+    pub time_zone: i16,
+    /// Daylight saving time flags.
+    pub daylight: u8,
+    _pad2: u8,
+}
+
+impl EfiTime {
+    /// The value of `time_zone` that indicates that the time is local time.
+    pub const UNSPECIFIED_TIMEZONE: i16 = 0x07FF;
+}
+
+impl fmt::Display for EfiTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+            self.year, self.month, self.day, self.hour, self.minute, self.second,
+        )?;
+        if self.time_zone != EfiTime::UNSPECIFIED_TIMEZONE {
+            write!(f, " UTC{:+03}:{:02}", self.time_zone / 60, (self.time_zone % 60).abs())?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the current time from the EFI real-time clock.
+pub fn get_time() -> Result<EfiTime, EfiError> {
+    let mut time = EfiTime::default();
+    let status = with_services(|services| unsafe {
+        (services.get_time)(&mut time, ptr::null_mut())
+    })?;
+    EfiError::from_status(status).map(|_| time)
+}
+
+/// Sets the EFI real-time clock to the given `time`.
+pub fn set_time(time: &EfiTime) -> Result<(), EfiError> {
+    let status = with_services(|services| unsafe {
+        (services.set_time)(time)
+    })?;
+    EfiError::from_status(status)
+}
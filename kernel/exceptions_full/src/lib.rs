@@ -265,7 +265,7 @@ extern "x86-interrupt" fn debug_handler(stack_frame: InterruptStackFrame) {
 
 /// Exception 0x02 is a Non-Maskable Interrupt (NMI).
 ///
-/// Theseus uses this for TLB Shootdown IPIs and sampling interrupts.
+/// Theseus uses this for cross-CPU function call IPIs (e.g., TLB Shootdowns) and sampling interrupts.
 ///
 /// # Important Note
 /// Acquiring ANY locks in this function, even irq-safe ones, could cause a deadlock
@@ -276,7 +276,7 @@ extern "x86-interrupt" fn nmi_handler(stack_frame: InterruptStackFrame) {
     // trace!("nmi_handler (CPU {})", cpu::current_cpu());
     let mut expected_nmi = false;
 
-    if tlb_shootdown::handle_tlb_shootdown_ipi() {
+    if cpu_call::handle_call_ipi() {
         return;
     }
 
@@ -336,6 +336,10 @@ extern "x86-interrupt" fn invalid_opcode_handler(stack_frame: InterruptStackFram
 /// For more information about "spurious interrupts", 
 /// see [here](http://wiki.osdev.org/I_Cant_Get_Interrupts_Working#I_keep_getting_an_IRQ7_for_no_apparent_reason).
 extern "x86-interrupt" fn device_not_available_handler(stack_frame: InterruptStackFrame) {
+    // In lazy FPU mode, this is the expected result of a task's first FPU use after a context switch.
+    if fpu_state::handle_device_not_available() {
+        return;
+    }
     println_both!("\nEXCEPTION: DEVICE NOT AVAILABLE\n{:#X?}", stack_frame);
     kill_and_halt(0x7, &stack_frame, None, true)
 }
@@ -374,14 +378,29 @@ extern "x86-interrupt" fn stack_segment_fault_handler(stack_frame: InterruptStac
 }
 
 /// exception 0x0D
-extern "x86-interrupt" fn general_protection_fault_handler(stack_frame: InterruptStackFrame, error_code: u64) {
+extern "x86-interrupt" fn general_protection_fault_handler(mut stack_frame: InterruptStackFrame, error_code: u64) {
+    // Accessing a non-canonical address causes a general protection fault rather than a page fault.
+    if fault_tolerant_copy::handle_fault(&mut stack_frame) {
+        return;
+    }
     println_both!("\nEXCEPTION: GENERAL PROTECTION FAULT\n{:#X?}\nError code: {:#b}", stack_frame, error_code);
     kill_and_halt(0xD, &stack_frame, Some(error_code.into()), true)
 }
 
 /// exception 0x0E
-extern "x86-interrupt" fn page_fault_handler(stack_frame: InterruptStackFrame, error_code: PageFaultErrorCode) {
+extern "x86-interrupt" fn page_fault_handler(mut stack_frame: InterruptStackFrame, error_code: PageFaultErrorCode) {
     let accessed_vaddr = Cr2::read_raw() as usize;
+    // Memory-mapped files are populated on demand, upon the first access to each page.
+    if file_mmap::handle_page_fault(
+        VirtualAddress::new_canonical(accessed_vaddr),
+        error_code.contains(PageFaultErrorCode::CAUSED_BY_WRITE),
+    ) {
+        return;
+    }
+    // A fault-tolerant memory operation recovers from the fault by itself.
+    if fault_tolerant_copy::handle_fault(&mut stack_frame) {
+        return;
+    }
 
     println_both!("\nEXCEPTION: PAGE FAULT while accessing {:#x}\n\
         error code: {:?}\n{:#X?}",
@@ -392,6 +411,9 @@ extern "x86-interrupt" fn page_fault_handler(stack_frame: InterruptStackFrame, e
     if is_stack_overflow(VirtualAddress::new_canonical(accessed_vaddr)) {
         println_both!("--> Page fault was caused by stack overflow, tried to access {:#X}\n.", accessed_vaddr);
     }
+    if let Some(region) = memory_regions::try_region_containing(VirtualAddress::new_canonical(accessed_vaddr)) {
+        println_both!("--> The accessed address is within the reserved region {}\n", region);
+    }
     
     kill_and_halt(0xE, &stack_frame, Some(ErrorCode::PageFaultError { accessed_address: accessed_vaddr, pf_error: error_code }), true)
 }
@@ -0,0 +1,522 @@
+//! An intent journal for FAT volumes, which makes updates to the FAT tables
+//! and directory entries atomic with respect to crashes and power loss.
+//!
+//! The FAT format has no journal of its own, so a crash in the middle of an update can leave
+//! a volume inconsistent, e.g., with clusters allocated in the FAT that no directory entry
+//! refers to, or with a directory entry that refers to free clusters.
+//! A [`JournaledDevice`] sits between the FAT driver and the storage device and groups all blocks
+//! written between two flushes into a transaction. Upon each flush, the transaction is first
+//! written to the journal and committed, and only then written to its actual location.
+//! When the volume is opened again after a crash, a committed transaction is replayed
+//! and an uncommitted one is discarded, such that the volume reflects either all or none of its updates.
+//!
+//! Because the directories of FAT32 volumes are stored in data clusters just like file contents,
+//! which a block device can't tell apart, all writes are journaled, not only metadata.
+//! A transaction that grows larger than the journal is committed early,
+//! so the journal should be large enough for the largest metadata update.
+//!
+//! The journal is stored in the volume's reserved sectors, which precede the first FAT
+//! and aren't used beyond the boot sector, the FSInfo sector, and their backups.
+//! Thus, other operating systems can still use a journaled volume as usual.
+//! A volume only has room for a journal if it was formatted with enough reserved sectors,
+//! e.g., via `mkfs.fat -F 32 -R 512`; otherwise, writes pass through to the device unjournaled.
+//!
+//! # On-disk layout
+//! The journal holds at most one transaction at a time, laid out in the journal region as follows:
+//! * block 0: the header, which holds the sequence number of the next transaction,
+//! * block 1: the descriptor of the transaction, which lists the `n` blocks that it updates,
+//! * blocks `2 ..= n + 1`: the new contents of those blocks,
+//! * block `n + 2`: the commit record, which holds a checksum of the descriptor and all new contents.
+//!
+//! All integers are little-endian, and each structure ends with a CRC-32C checksum of itself.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::{collections::BTreeMap, vec, vec::Vec};
+use core::ops::Range;
+use hashing::{Crc32c, Digest};
+use io::{BlockIo, BlockReader, BlockWriter, IoError, KnownLength};
+use log::{info, warn};
+
+/// The fewest blocks that a journal region may have:
+/// the header, descriptor, and commit record, plus the contents of a typical metadata update.
+pub const MIN_JOURNAL_BLOCKS: usize = 16;
+
+const HEADER_MAGIC: &[u8; 8] = b"THSJRNL1";
+const DESCRIPTOR_MAGIC: &[u8; 8] = b"THSJDESC";
+const COMMIT_MAGIC: &[u8; 8] = b"THSJCMIT";
+
+/// The size of everything in a descriptor except its list of block numbers:
+/// the magic, sequence number, block count, and checksum.
+const DESCRIPTOR_OVERHEAD: usize = 8 + 8 + 4 + 4;
+
+// Offsets of the fields in a FAT volume's boot sector that describe its reserved sectors.
+const BPB_BYTES_PER_SECTOR: usize = 11;
+const BPB_RESERVED_SECTORS: usize = 14;
+/// This is zero for FAT32 volumes.
+const BPB_SECTORS_PER_FAT_16: usize = 22;
+const BPB_FS_INFO_SECTOR: usize = 48;
+const BPB_BACKUP_BOOT_SECTOR: usize = 50;
+const BOOT_SIGNATURE_OFFSET: usize = 510;
+/// The number of sectors in the backup of a FAT32 volume's boot sectors.
+const BACKUP_BOOT_SECTORS: usize = 3;
+
+/// Returns the range of reserved sectors of the FAT volume on the given `device` that aren't used by the FAT format,
+/// or `None` if there are fewer than [`MIN_JOURNAL_BLOCKS`] of them.
+pub fn find_journal_region<D: BlockReader + ?Sized>(device: &mut D) -> Result<Option<Range<usize>>, IoError> {
+    let mut boot_sector = vec![0; device.block_size()];
+    device.read_blocks(&mut boot_sector, 0)?;
+    if boot_sector.len() < 512 || boot_sector[BOOT_SIGNATURE_OFFSET .. BOOT_SIGNATURE_OFFSET + 2] != [0x55, 0xAA] {
+        return Err(IoError::Other("fat_journal: the device doesn't contain a FAT volume"));
+    }
+    let u16_at = |offset: usize| u16::from_le_bytes([boot_sector[offset], boot_sector[offset + 1]]) as usize;
+    if u16_at(BPB_BYTES_PER_SECTOR) != device.block_size() {
+        return Err(IoError::Other("fat_journal: the volume's sector size differs from the device's block size"));
+    }
+    let first_unused = if u16_at(BPB_SECTORS_PER_FAT_16) == 0 {
+        (u16_at(BPB_FS_INFO_SECTOR) + 1).max(u16_at(BPB_BACKUP_BOOT_SECTOR) + BACKUP_BOOT_SECTORS)
+    } else {
+        // FAT12 and FAT16 volumes only use their first reserved sector.
+        1
+    };
+    let region = first_unused .. u16_at(BPB_RESERVED_SECTORS);
+    Ok((region.len() >= MIN_JOURNAL_BLOCKS).then_some(region))
+}
+
+/// A storage device holding a FAT volume, whose writes are journaled.
+///
+/// Written blocks are kept in memory until the next [`flush`](BlockWriter::flush),
+/// which commits them to the volume as a single transaction.
+/// Pending writes are also committed when this is dropped, but any error is only logged.
+pub struct JournaledDevice<D: BlockReader + BlockWriter> {
+    device: D,
+    journal: Option<Journal>,
+}
+
+impl<D> JournaledDevice<D> where D: BlockReader + BlockWriter {
+    /// Opens the FAT volume on the given `device`, recovering from a crash by replaying
+    /// or discarding the transaction left in its journal.
+    ///
+    /// A journal is created in the volume's unused reserved sectors if it doesn't have one yet.
+    /// If there's no room for a journal, writes pass through to the `device` unjournaled.
+    pub fn open(mut device: D) -> Result<Self, IoError> {
+        match find_journal_region(&mut device)? {
+            Some(region) => Self::with_region(device, region),
+            None => {
+                warn!("fat_journal: the FAT volume has too few reserved sectors for a journal, writes won't be journaled");
+                Ok(JournaledDevice { device, journal: None })
+            }
+        }
+    }
+
+    /// Like [`JournaledDevice::open()`], but stores the journal in the given `region` of blocks,
+    /// which must not be used by anything else.
+    pub fn with_region(mut device: D, region: Range<usize>) -> Result<Self, IoError> {
+        let journal = Journal::open(&mut device, region)?;
+        Ok(JournaledDevice { device, journal: Some(journal) })
+    }
+
+    /// Returns whether writes to this device are journaled.
+    pub fn is_journaled(&self) -> bool {
+        self.journal.is_some()
+    }
+}
+
+impl<D> BlockIo for JournaledDevice<D> where D: BlockReader + BlockWriter {
+    fn block_size(&self) -> usize {
+        self.device.block_size()
+    }
+}
+
+impl<D> KnownLength for JournaledDevice<D> where D: BlockReader + BlockWriter + KnownLength {
+    fn len(&self) -> usize {
+        self.device.len()
+    }
+}
+
+impl<D> BlockReader for JournaledDevice<D> where D: BlockReader + BlockWriter {
+    fn read_blocks(&mut self, buffer: &mut [u8], block_offset: usize) -> Result<usize, IoError> {
+        let blocks_read = self.device.read_blocks(buffer, block_offset)?;
+        // Blocks that were written but not yet committed are newer than those on the device.
+        if let Some(journal) = &self.journal {
+            let block_size = self.device.block_size();
+            for (block, contents) in journal.pending.range(block_offset .. block_offset + blocks_read) {
+                let start = (block - block_offset) * block_size;
+                buffer[start .. start + block_size].copy_from_slice(contents);
+            }
+        }
+        Ok(blocks_read)
+    }
+}
+
+impl<D> BlockWriter for JournaledDevice<D> where D: BlockReader + BlockWriter {
+    fn write_blocks(&mut self, buffer: &[u8], block_offset: usize) -> Result<usize, IoError> {
+        let Some(journal) = self.journal.as_mut() else {
+            return self.device.write_blocks(buffer, block_offset);
+        };
+        let block_size = self.device.block_size();
+        if buffer.len() % block_size != 0 {
+            return Err(IoError::InvalidInput);
+        }
+        let blocks = block_offset .. block_offset + buffer.len() / block_size;
+        if blocks.start < journal.region.end && journal.region.start < blocks.end {
+            return Err(IoError::Other("fat_journal: cannot write to the journal region"));
+        }
+        for (block, contents) in blocks.clone().zip(buffer.chunks_exact(block_size)) {
+            if !journal.pending.contains_key(&block) && journal.pending.len() == journal.capacity(block_size) {
+                journal.commit(&mut self.device)?;
+            }
+            journal.pending.insert(block, contents.to_vec());
+        }
+        Ok(blocks.len())
+    }
+
+    fn flush(&mut self) -> Result<(), IoError> {
+        match self.journal.as_mut() {
+            Some(journal) => journal.commit(&mut self.device),
+            None => self.device.flush(),
+        }
+    }
+}
+
+impl<D> Drop for JournaledDevice<D> where D: BlockReader + BlockWriter {
+    fn drop(&mut self) {
+        if let Err(e) = self.flush() {
+            warn!("fat_journal: failed to commit pending writes: {:?}", e);
+        }
+    }
+}
+
+/// The state of a journal region on a device.
+struct Journal {
+    region: Range<usize>,
+    /// The sequence number of the next transaction to be committed.
+    sequence: u64,
+    /// The blocks written since the last commit, by block number.
+    pending: BTreeMap<usize, Vec<u8>>,
+}
+
+impl Journal {
+    /// Reads the journal in the given `region` of the `device`, creating it if it doesn't exist,
+    /// and then replays or discards the transaction left in it.
+    fn open<D: BlockReader + BlockWriter>(device: &mut D, region: Range<usize>) -> Result<Journal, IoError> {
+        let block_size = device.block_size();
+        if block_size < DESCRIPTOR_OVERHEAD + 8 || region.len() < MIN_JOURNAL_BLOCKS {
+            return Err(IoError::Other("fat_journal: the journal region is too small"));
+        }
+        let mut block = vec![0; block_size];
+        device.read_blocks(&mut block, region.start)?;
+        let mut journal = Journal { region, sequence: 1, pending: BTreeMap::new() };
+        match decode_header(&block) {
+            Some(sequence) => {
+                journal.sequence = sequence;
+                journal.recover(device)?;
+            }
+            None => {
+                info!("fat_journal: creating a new journal in blocks {:?}", journal.region);
+                journal.write_header(device)?;
+            }
+        }
+        Ok(journal)
+    }
+
+    /// Returns the maximum number of blocks in a transaction.
+    fn capacity(&self, block_size: usize) -> usize {
+        ((block_size - DESCRIPTOR_OVERHEAD) / 8).min(self.region.len() - 3)
+    }
+
+    /// Replays the transaction left in the journal if it was committed, or discards it otherwise.
+    fn recover<D: BlockReader + BlockWriter>(&mut self, device: &mut D) -> Result<(), IoError> {
+        let block_size = device.block_size();
+        let mut descriptor = vec![0; block_size];
+        device.read_blocks(&mut descriptor, self.region.start + 1)?;
+        let Some(blocks) = decode_descriptor(&descriptor, self.sequence)
+            .filter(|blocks| blocks.len() <= self.capacity(block_size))
+        else {
+            // The journal is clean: the last transaction was fully written in place.
+            return Ok(());
+        };
+
+        let mut checksum = Crc32c::default();
+        checksum.update(&descriptor);
+        let mut contents = vec![0; block_size * blocks.len()];
+        for (i, chunk) in contents.chunks_exact_mut(block_size).enumerate() {
+            device.read_blocks(chunk, self.region.start + 2 + i)?;
+            checksum.update(chunk);
+        }
+        let mut commit = vec![0; block_size];
+        device.read_blocks(&mut commit, self.region.start + 2 + blocks.len())?;
+
+        if decode_commit(&commit, self.sequence) == Some(checksum.finalize()) {
+            info!("fat_journal: replaying committed transaction {} of {} blocks", self.sequence, blocks.len());
+            for (&block, chunk) in blocks.iter().zip(contents.chunks_exact(block_size)) {
+                device.write_blocks(chunk, block)?;
+            }
+            device.flush()?;
+        } else {
+            info!("fat_journal: discarding uncommitted transaction {}", self.sequence);
+        }
+        self.sequence += 1;
+        self.write_header(device)
+    }
+
+    /// Commits all pending blocks as a single transaction and then writes them in place.
+    fn commit<D: BlockReader + BlockWriter>(&mut self, device: &mut D) -> Result<(), IoError> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        let block_size = device.block_size();
+        let descriptor = encode_descriptor(block_size, self.sequence, self.pending.keys().copied());
+        let mut checksum = Crc32c::default();
+        checksum.update(&descriptor);
+        device.write_blocks(&descriptor, self.region.start + 1)?;
+        for (i, contents) in self.pending.values().enumerate() {
+            checksum.update(contents);
+            device.write_blocks(contents, self.region.start + 2 + i)?;
+        }
+        // The commit record must not become durable before the rest of the transaction.
+        device.flush()?;
+        let commit = encode_commit(block_size, self.sequence, checksum.finalize());
+        device.write_blocks_fua(&commit, self.region.start + 2 + self.pending.len())?;
+
+        // From here on, the transaction will be replayed if writing it in place is interrupted.
+        for (&block, contents) in &self.pending {
+            device.write_blocks(contents, block)?;
+        }
+        device.flush()?;
+        self.pending.clear();
+        self.sequence += 1;
+        self.write_header(device)
+    }
+
+    /// Writes the journal header, which marks all transactions before the current sequence number as complete.
+    fn write_header<D: BlockWriter>(&self, device: &mut D) -> Result<(), IoError> {
+        let mut header = vec![0; device.block_size()];
+        header[.. 8].copy_from_slice(HEADER_MAGIC);
+        header[8 .. 16].copy_from_slice(&self.sequence.to_le_bytes());
+        append_checksum(&mut header, 16);
+        device.write_blocks_fua(&header, self.region.start).map(|_| ())
+    }
+}
+
+/// Writes the checksum of `block[.. len]` to `block[len .. len + 4]`.
+fn append_checksum(block: &mut [u8], len: usize) {
+    let checksum = Crc32c::digest(&block[.. len]);
+    block[len .. len + 4].copy_from_slice(&checksum.to_le_bytes());
+}
+
+/// Returns whether `block[.. len]` starts with `magic` and is followed by its checksum.
+fn is_valid(block: &[u8], magic: &[u8; 8], len: usize) -> bool {
+    block.len() >= len + 4
+        && &block[.. 8] == magic
+        && block[len .. len + 4] == Crc32c::digest(&block[.. len]).to_le_bytes()
+}
+
+fn u64_at(block: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(block[offset .. offset + 8].try_into().unwrap())
+}
+
+fn u32_at(block: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(block[offset .. offset + 4].try_into().unwrap())
+}
+
+/// Returns the sequence number in the given header block, if it's valid.
+fn decode_header(block: &[u8]) -> Option<u64> {
+    is_valid(block, HEADER_MAGIC, 16).then(|| u64_at(block, 8))
+}
+
+fn encode_descriptor(block_size: usize, sequence: u64, blocks: impl ExactSizeIterator<Item = usize>) -> Vec<u8> {
+    let mut descriptor = vec![0; block_size];
+    descriptor[.. 8].copy_from_slice(DESCRIPTOR_MAGIC);
+    descriptor[8 .. 16].copy_from_slice(&sequence.to_le_bytes());
+    descriptor[16 .. 20].copy_from_slice(&(blocks.len() as u32).to_le_bytes());
+    let mut len = 20;
+    for block in blocks {
+        descriptor[len .. len + 8].copy_from_slice(&(block as u64).to_le_bytes());
+        len += 8;
+    }
+    append_checksum(&mut descriptor, len);
+    descriptor
+}
+
+/// Returns the block numbers listed in the given descriptor block,
+/// if it's valid and belongs to the transaction with the given `sequence` number.
+fn decode_descriptor(block: &[u8], sequence: u64) -> Option<Vec<usize>> {
+    if block.len() < 20 {
+        return None;
+    }
+    let count = u32_at(block, 16) as usize;
+    let len = count.checked_mul(8)?.checked_add(20)?;
+    if !is_valid(block, DESCRIPTOR_MAGIC, len) || u64_at(block, 8) != sequence {
+        return None;
+    }
+    Some((0 .. count).map(|i| u64_at(block, 20 + i * 8) as usize).collect())
+}
+
+fn encode_commit(block_size: usize, sequence: u64, checksum: u32) -> Vec<u8> {
+    let mut commit = vec![0; block_size];
+    commit[.. 8].copy_from_slice(COMMIT_MAGIC);
+    commit[8 .. 16].copy_from_slice(&sequence.to_le_bytes());
+    commit[16 .. 20].copy_from_slice(&checksum.to_le_bytes());
+    append_checksum(&mut commit, 20);
+    commit
+}
+
+/// Returns the transaction checksum in the given commit record,
+/// if it's valid and belongs to the transaction with the given `sequence` number.
+fn decode_commit(block: &[u8], sequence: u64) -> Option<u32> {
+    (is_valid(block, COMMIT_MAGIC, 20) && u64_at(block, 8) == sequence).then(|| u32_at(block, 16))
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use super::*;
+
+    const BLOCK_SIZE: usize = 512;
+    const REGION: Range<usize> = 1 .. 17;
+
+    /// An in-memory device that fails all writes after a given number of them, as if it lost power.
+    struct MemDevice {
+        bytes: Vec<u8>,
+        writes_until_crash: Option<usize>,
+    }
+
+    impl MemDevice {
+        fn new(blocks: usize) -> MemDevice {
+            MemDevice { bytes: vec![0; blocks * BLOCK_SIZE], writes_until_crash: None }
+        }
+
+        fn block(&self, block: usize) -> &[u8] {
+            &self.bytes[block * BLOCK_SIZE .. (block + 1) * BLOCK_SIZE]
+        }
+    }
+
+    impl BlockIo for MemDevice {
+        fn block_size(&self) -> usize { BLOCK_SIZE }
+    }
+
+    impl BlockReader for MemDevice {
+        fn read_blocks(&mut self, buffer: &mut [u8], block_offset: usize) -> Result<usize, IoError> {
+            let start = block_offset * BLOCK_SIZE;
+            buffer.copy_from_slice(&self.bytes[start .. start + buffer.len()]);
+            Ok(buffer.len() / BLOCK_SIZE)
+        }
+    }
+
+    impl BlockWriter for MemDevice {
+        fn write_blocks(&mut self, buffer: &[u8], block_offset: usize) -> Result<usize, IoError> {
+            match &mut self.writes_until_crash {
+                Some(0) => return Err(IoError::TimedOut),
+                Some(n) => *n -= 1,
+                None => {}
+            }
+            let start = block_offset * BLOCK_SIZE;
+            self.bytes[start .. start + buffer.len()].copy_from_slice(buffer);
+            Ok(buffer.len() / BLOCK_SIZE)
+        }
+
+        fn flush(&mut self) -> Result<(), IoError> { Ok(()) }
+    }
+
+    fn write(journaled: &mut JournaledDevice<&mut MemDevice>, block: usize, value: u8) {
+        journaled.write_blocks(&[value; BLOCK_SIZE], block).unwrap();
+    }
+
+    #[test]
+    fn commit_on_flush() {
+        let mut device = MemDevice::new(32);
+        let mut journaled = JournaledDevice::with_region(&mut device, REGION).unwrap();
+        write(&mut journaled, 20, 0xAA);
+        write(&mut journaled, 21, 0xBB);
+
+        let mut buffer = [0; 2 * BLOCK_SIZE];
+        journaled.read_blocks(&mut buffer, 20).unwrap();
+        assert!(buffer[.. BLOCK_SIZE].iter().all(|&b| b == 0xAA));
+        assert!(buffer[BLOCK_SIZE ..].iter().all(|&b| b == 0xBB));
+        assert!(journaled.write_blocks(&[0; BLOCK_SIZE], REGION.start + 1).is_err());
+
+        journaled.flush().unwrap();
+        drop(journaled);
+        assert_eq!(device.block(20), &[0xAA; BLOCK_SIZE]);
+        assert_eq!(device.block(21), &[0xBB; BLOCK_SIZE]);
+    }
+
+    #[test]
+    fn replay_committed_transaction() {
+        let mut device = MemDevice::new(32);
+        let mut journaled = JournaledDevice::with_region(&mut device, REGION).unwrap();
+        write(&mut journaled, 20, 0xAA);
+        write(&mut journaled, 25, 0xBB);
+        // Crash after the descriptor, both blocks, and the commit record were written,
+        // but before the first block was written in place.
+        journaled.device.writes_until_crash = Some(4);
+        assert!(journaled.flush().is_err());
+        drop(journaled);
+        assert_eq!(device.block(20), &[0; BLOCK_SIZE]);
+
+        device.writes_until_crash = None;
+        drop(JournaledDevice::with_region(&mut device, REGION).unwrap());
+        assert_eq!(device.block(20), &[0xAA; BLOCK_SIZE]);
+        assert_eq!(device.block(25), &[0xBB; BLOCK_SIZE]);
+    }
+
+    #[test]
+    fn discard_uncommitted_transaction() {
+        let mut device = MemDevice::new(32);
+        let mut journaled = JournaledDevice::with_region(&mut device, REGION).unwrap();
+        write(&mut journaled, 20, 0xAA);
+        journaled.flush().unwrap();
+        write(&mut journaled, 20, 0xBB);
+        write(&mut journaled, 21, 0xCC);
+        // Crash before the commit record was written.
+        journaled.device.writes_until_crash = Some(3);
+        assert!(journaled.flush().is_err());
+        drop(journaled);
+
+        device.writes_until_crash = None;
+        let mut journaled = JournaledDevice::with_region(&mut device, REGION).unwrap();
+        let mut buffer = [0; 2 * BLOCK_SIZE];
+        journaled.read_blocks(&mut buffer, 20).unwrap();
+        assert!(buffer[.. BLOCK_SIZE].iter().all(|&b| b == 0xAA));
+        assert!(buffer[BLOCK_SIZE ..].iter().all(|&b| b == 0));
+
+        // Later transactions aren't affected by the discarded one.
+        write(&mut journaled, 21, 0xDD);
+        journaled.flush().unwrap();
+        drop(journaled);
+        drop(JournaledDevice::with_region(&mut device, REGION).unwrap());
+        assert_eq!(device.block(20), &[0xAA; BLOCK_SIZE]);
+        assert_eq!(device.block(21), &[0xDD; BLOCK_SIZE]);
+    }
+
+    #[test]
+    fn large_transaction_commits_early() {
+        let mut device = MemDevice::new(64);
+        let mut journaled = JournaledDevice::with_region(&mut device, REGION).unwrap();
+        let buffer = [0x11; 20 * BLOCK_SIZE];
+        assert_eq!(journaled.write_blocks(&buffer, 30).unwrap(), 20);
+        journaled.flush().unwrap();
+        drop(journaled);
+        assert!(device.bytes[30 * BLOCK_SIZE .. 50 * BLOCK_SIZE].iter().all(|&b| b == 0x11));
+    }
+
+    #[test]
+    fn reserved_sectors_of_fat32_volume() {
+        let mut device = MemDevice::new(4);
+        let boot_sector = &mut device.bytes[.. BLOCK_SIZE];
+        boot_sector[BPB_BYTES_PER_SECTOR .. BPB_BYTES_PER_SECTOR + 2].copy_from_slice(&512u16.to_le_bytes());
+        boot_sector[BPB_RESERVED_SECTORS .. BPB_RESERVED_SECTORS + 2].copy_from_slice(&512u16.to_le_bytes());
+        boot_sector[BPB_FS_INFO_SECTOR .. BPB_FS_INFO_SECTOR + 2].copy_from_slice(&1u16.to_le_bytes());
+        boot_sector[BPB_BACKUP_BOOT_SECTOR .. BPB_BACKUP_BOOT_SECTOR + 2].copy_from_slice(&6u16.to_le_bytes());
+        boot_sector[BOOT_SIGNATURE_OFFSET .. BOOT_SIGNATURE_OFFSET + 2].copy_from_slice(&[0x55, 0xAA]);
+        assert_eq!(find_journal_region(&mut device).unwrap(), Some(9 .. 512));
+
+        device.bytes[BPB_RESERVED_SECTORS .. BPB_RESERVED_SECTORS + 2].copy_from_slice(&32u16.to_le_bytes());
+        assert_eq!(find_journal_region(&mut device).unwrap(), Some(9 .. 32));
+        device.bytes[BPB_RESERVED_SECTORS .. BPB_RESERVED_SECTORS + 2].copy_from_slice(&20u16.to_le_bytes());
+        assert_eq!(find_journal_region(&mut device).unwrap(), None);
+    }
+}
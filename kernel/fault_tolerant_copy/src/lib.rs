@@ -0,0 +1,112 @@
+//! Memory copy and fill primitives that tolerate faults.
+//!
+//! Some kernel code must access memory that may not be mapped, or may be unmapped concurrently,
+//! e.g., when copying data to or from userspace, dumping memory after a crash,
+//! or reading memory on behalf of a debugger.
+//! Normally, a page fault in kernel code kills the current task;
+//! instead, [`copy_maybe_faulting()`] and [`set_maybe_faulting()`] stop at the first fault
+//! and return how many bytes were processed before it.
+//!
+//! This works by performing the entire operation with a single `rep movsb` or `rep stosb` instruction,
+//! each of which is the first instruction of a tiny routine that immediately returns after it.
+//! When such an instruction faults, the CPU leaves its registers describing the remaining work,
+//! so the exception handler only needs to check whether the faulting instruction is one of these
+//! via [`handle_fault()`], and if so, resume execution as if the routine had returned.
+
+#![no_std]
+#![feature(naked_functions)]
+
+use core::arch::asm;
+use x86_64::{structures::idt::InterruptStackFrame, VirtAddr};
+
+/// The error returned when a fault-tolerant operation was cut short by a fault.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Faulted {
+    /// The number of bytes that were copied or set before the fault occurred.
+    pub completed: usize,
+    /// The number of bytes that weren't copied or set.
+    pub remaining: usize,
+}
+
+/// Copies `len` bytes from `src` to `dst`, stopping at the first fault.
+///
+/// # Safety
+/// Aside from faulting, this has the same requirements as [`core::ptr::copy_nonoverlapping()`]:
+/// if the memory at `src` and `dst` is accessible, it must be valid to read and write, respectively,
+/// and the two ranges must not overlap.
+pub unsafe fn copy_maybe_faulting(dst: *mut u8, src: *const u8, len: usize) -> Result<(), Faulted> {
+    let remaining: usize;
+    asm!(
+        "call {copy}",
+        copy = sym copy_bytes,
+        inout("rcx") len => remaining,
+        inout("rdi") dst => _,
+        inout("rsi") src => _,
+    );
+    result(len, remaining)
+}
+
+/// Sets `len` bytes starting at `dst` to `value`, stopping at the first fault.
+///
+/// # Safety
+/// Aside from faulting, this has the same requirements as [`core::ptr::write_bytes()`]:
+/// if the memory at `dst` is accessible, it must be valid to write.
+pub unsafe fn set_maybe_faulting(dst: *mut u8, value: u8, len: usize) -> Result<(), Faulted> {
+    let remaining: usize;
+    asm!(
+        "call {set}",
+        set = sym set_bytes,
+        inout("rcx") len => remaining,
+        inout("rdi") dst => _,
+        in("al") value,
+    );
+    result(len, remaining)
+}
+
+/// Recovers from a fault that occurred within [`copy_maybe_faulting()`] or [`set_maybe_faulting()`].
+///
+/// This should be invoked by the handler of any exception that accessing memory can cause,
+/// such as page faults and general protection faults.
+/// If the fault occurred within one of those operations, this modifies the given `stack_frame`
+/// such that the operation returns the number of bytes that weren't processed,
+/// and returns `true`, in which case the exception handler should return immediately.
+/// Otherwise, this returns `false` and the fault must be handled as usual.
+pub fn handle_fault(stack_frame: &mut InterruptStackFrame) -> bool {
+    let ip = stack_frame.instruction_pointer.as_u64() as usize;
+    if ip != copy_bytes as usize && ip != set_bytes as usize {
+        return false;
+    }
+    // Emulate the `ret` that follows the faulting instruction.
+    let sp = stack_frame.stack_pointer.as_u64();
+    // SAFETY: the routine was entered via a `call`, so the stack pointer points to its return address.
+    let return_address = unsafe { *(sp as *const u64) };
+    // SAFETY: this resumes execution at the routine's caller as if the routine had returned,
+    // which leaves `rcx` holding the number of bytes that weren't processed.
+    unsafe {
+        stack_frame.as_mut().update(|frame| {
+            frame.instruction_pointer = VirtAddr::new(return_address);
+            frame.stack_pointer = VirtAddr::new(sp + 8);
+        });
+    }
+    true
+}
+
+fn result(len: usize, remaining: usize) -> Result<(), Faulted> {
+    if remaining == 0 {
+        Ok(())
+    } else {
+        Err(Faulted { completed: len - remaining, remaining })
+    }
+}
+
+/// Copies `rcx` bytes from `rsi` to `rdi`; the copy instruction must be the first instruction.
+#[naked]
+unsafe extern "C" fn copy_bytes() {
+    asm!("rep movsb", "ret", options(noreturn));
+}
+
+/// Sets `rcx` bytes starting at `rdi` to `al`; the fill instruction must be the first instruction.
+#[naked]
+unsafe extern "C" fn set_bytes() {
+    asm!("rep stosb", "ret", options(noreturn));
+}
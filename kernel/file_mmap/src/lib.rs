@@ -0,0 +1,486 @@
+//! Maps the contents of files into memory, such that they can be accessed
+//! without explicit read or write calls.
+//!
+//! A mapping created by [`mmap()`] reserves a range of virtual pages but doesn't populate them.
+//! Instead, each page is read from its file on demand the first time it is accessed,
+//! via the page fault handler invoking [`handle_page_fault()`].
+//!
+//! The page fault handler doesn't read the file itself, as that requires locking the file,
+//! allocating memory, and modifying the page table, none of which is safe in an exception context.
+//! Instead, it hands the fault over to a dedicated loader task and blocks the faulting task
+//! until the loader has populated the page.
+//! Thus, a file mapping must not be accessed for the first time with preemption disabled,
+//! e.g., while holding a preemption-safe lock, as the faulting task couldn't be blocked.
+//!
+//! There are two kinds of mappings:
+//! * Shared mappings (the default) are backed by a per-file page cache,
+//!   so all shared mappings of a file use the same pages and see each other's writes.
+//!   Each file page is read into the page cache only once, and no other copy of it is made.
+//!   Written pages are written back to the file by [`FileMapping::sync()`]
+//!   or once the file's page cache is no longer used by any mapping.
+//! * Private mappings ([`MmapFlags::PRIVATE`]) have their own pages,
+//!   which are copied from the file upon first access and never written back,
+//!   so writes to them are neither visible to other mappings nor stored in the file.
+//!
+//! Because Theseus maps each physical frame to only one virtual page,
+//! shared mappings of the same file are views into the same virtual range
+//! rather than separate virtual ranges that map the same frames.
+//!
+//! The loader locks the mapped file while reading a page,
+//! so a slice of a mapping must not be passed to the mapped file's own methods.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::{
+    collections::BTreeMap,
+    string::String,
+    sync::{Arc, Weak},
+    vec::Vec,
+};
+use core::ops::Range;
+use bitflags::bitflags;
+use fs_node::FileRef;
+use log::{error, warn};
+use memory::{AllocatedPages, MappedPages, Page, PteFlags, VirtualAddress, PAGE_SIZE};
+use spin::Once;
+use sync_irq::IrqSafeMutex;
+use sync_preemption::PreemptionSafeMutex;
+use task::TaskRef;
+
+bitflags! {
+    /// Options for how a file is mapped into memory.
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+    pub struct MmapFlags: u32 {
+        /// The mapping can be written to, not just read.
+        const WRITE   = 1 << 0;
+        /// Writes to the mapping are private to it, and never written back to the file.
+        const PRIVATE = 1 << 1;
+    }
+}
+
+/// The page cache: the region that caches each file's pages for all of its shared mappings,
+/// keyed by the address of the file.
+static PAGE_CACHE: PreemptionSafeMutex<BTreeMap<usize, Weak<Region>>> = PreemptionSafeMutex::new(BTreeMap::new());
+
+/// All existing regions, keyed by their starting virtual address, along with their end address,
+/// which is used to find the region that a page fault occurred in.
+///
+/// This is locked by the page fault handler, so it must be IRQ-safe.
+static REGIONS: IrqSafeMutex<BTreeMap<VirtualAddress, (VirtualAddress, Weak<Region>)>> = IrqSafeMutex::new(BTreeMap::new());
+
+/// The maximum number of page faults on file mappings that can be waiting for the loader at once.
+const MAX_PENDING_FAULTS: usize = 64;
+
+/// The page faults on file mappings that the loader task has yet to handle or report back.
+///
+/// This is a fixed-size array because the page fault handler must not allocate memory.
+static FAULTS: IrqSafeMutex<[Option<Fault>; MAX_PENDING_FAULTS]> = IrqSafeMutex::new([NO_FAULT; MAX_PENDING_FAULTS]);
+// Only used to initialize `FAULTS`, since `Fault` isn't `Copy`.
+const NO_FAULT: Option<Fault> = None;
+
+/// The task that populates the pages of file mappings upon page faults.
+static LOADER: Once<TaskRef> = Once::new();
+
+/// A page fault on a file mapping, which is handed over to the loader task.
+struct Fault {
+    /// The task that faulted, which is blocked until the fault is handled.
+    task: TaskRef,
+    /// The region that the fault occurred in, which is taken by the loader once it starts handling the fault.
+    ///
+    /// The loader drops the region, such that it's never dropped by the page fault handler.
+    region: Option<Arc<Region>>,
+    address: VirtualAddress,
+    is_write: bool,
+    /// Whether the fault was handled successfully, once the loader has finished handling it.
+    handled: Option<bool>,
+}
+
+/// Maps `len` bytes of the given `file`, starting at `offset`, into memory.
+///
+/// Shared mappings can only cover the part of the file that exists at the time of
+/// the file's first shared mapping, whereas private mappings can extend beyond the end of the file,
+/// in which case the memory beyond the end of the file is zeroed.
+///
+/// No file contents are read until the returned mapping is accessed.
+pub fn mmap(file: &FileRef, offset: usize, len: usize, flags: MmapFlags) -> Result<FileMapping, &'static str> {
+    if len == 0 {
+        return Err("cannot map zero bytes of a file");
+    }
+    let end = offset.checked_add(len).ok_or("file mapping range overflowed")?;
+    let writable = flags.contains(MmapFlags::WRITE);
+    LOADER.try_call_once(spawn_loader)?;
+
+    let (region, region_start) = if flags.contains(MmapFlags::PRIVATE) {
+        let region_start = offset - (offset % PAGE_SIZE);
+        let region = Region::new(file, region_start, end - region_start, true)?;
+        (region, region_start)
+    } else {
+        (cached_region(file, end)?, 0)
+    };
+
+    Ok(FileMapping {
+        region,
+        range: (offset - region_start)..(end - region_start),
+        writable,
+    })
+}
+
+/// Handles a page fault at the given address if it lies within a file mapping,
+/// by having the loader task read the accessed page from its file.
+///
+/// This should be invoked by the page fault handler before it treats a page fault as an error.
+/// The faulting task is blocked until the loader has handled the fault.
+/// Returns `true` if the page fault was handled, in which case the faulting access can be retried.
+/// Otherwise, returns `false` and the page fault must be handled as usual.
+pub fn handle_page_fault(accessed_address: VirtualAddress, is_write: bool) -> bool {
+    // The faulting task can only be blocked if it could otherwise be preempted,
+    // and the loader cannot wait on itself.
+    let Some(loader) = LOADER.get() else {
+        return false;
+    };
+    if !preemption::preemption_enabled() {
+        return false;
+    }
+    let Some(task) = task::get_my_current_task() else {
+        return false;
+    };
+    if task.id == loader.id {
+        return false;
+    }
+
+    let index = {
+        let mut faults = FAULTS.lock();
+        let Some(index) = faults.iter().position(Option::is_none) else {
+            return false;
+        };
+        // Check the address before upgrading the region, such that it's never dropped here.
+        let region = REGIONS.lock().range(..=accessed_address)
+            .next_back()
+            .filter(|(_, (end, _))| accessed_address < *end)
+            .and_then(|(_, (_, region))| region.upgrade());
+        let Some(region) = region else {
+            return false;
+        };
+        faults[index] = Some(Fault { task: task.clone(), region: Some(region), address: accessed_address, is_write, handled: None });
+        index
+    };
+    let _ = loader.unblock();
+
+    // Wait until the loader has handled the fault.
+    // Blocking while holding the lock ensures that the loader cannot unblock this task beforehand.
+    loop {
+        let mut faults = FAULTS.lock();
+        if let Some(handled) = faults[index].as_ref().and_then(|fault| fault.handled) {
+            faults[index] = None;
+            return handled;
+        }
+        let _ = task.block();
+        drop(faults);
+        task::schedule();
+    }
+}
+
+/// Writes back all written pages of the given file's page cache to the file.
+pub fn sync_file(file: &FileRef) -> Result<(), &'static str> {
+    let region = PAGE_CACHE.lock().get(&file_key(file)).and_then(Weak::upgrade);
+    match region {
+        Some(region) => region.sync(),
+        None => Ok(()),
+    }
+}
+
+
+/// A part of a file that has been mapped into memory via [`mmap()`].
+///
+/// The mapping is removed once this and all other mappings that share its pages are dropped.
+pub struct FileMapping {
+    region: Arc<Region>,
+    /// The range of bytes within the region that this mapping covers.
+    range: Range<usize>,
+    writable: bool,
+}
+
+impl FileMapping {
+    /// Returns the starting virtual address of this mapping,
+    /// which holds the byte at the file offset that was mapped.
+    pub fn start_address(&self) -> VirtualAddress {
+        self.region.pages.start_address() + self.range.start
+    }
+
+    /// Returns the number of bytes covered by this mapping.
+    pub fn len(&self) -> usize {
+        self.range.len()
+    }
+
+    /// Returns whether this mapping covers zero bytes, which is never true.
+    pub fn is_empty(&self) -> bool {
+        self.range.is_empty()
+    }
+
+    /// Returns whether writes to this mapping are private to it.
+    pub fn is_private(&self) -> bool {
+        self.region.private
+    }
+
+    /// Returns the contents of this mapping.
+    ///
+    /// Pages are read from the file as they are accessed.
+    pub fn as_slice(&self) -> &[u8] {
+        // SAFETY: the region's pages are reserved for as long as the region exists,
+        //         and are mapped on demand by the page fault handler upon first access.
+        unsafe { core::slice::from_raw_parts(self.start_address().value() as *const u8, self.len()) }
+    }
+
+    /// Returns the contents of this mapping mutably,
+    /// which is only possible if it was mapped with [`MmapFlags::WRITE`].
+    ///
+    /// Note that shared mappings of the same file can be written concurrently,
+    /// as with shared memory in general.
+    pub fn as_slice_mut(&mut self) -> Result<&mut [u8], &'static str> {
+        if !self.writable {
+            return Err("file mapping isn't writable");
+        }
+        // SAFETY: same as `as_slice()`.
+        Ok(unsafe { core::slice::from_raw_parts_mut(self.start_address().value() as *mut u8, self.len()) })
+    }
+
+    /// Writes back the written pages of this mapping's file to the file.
+    ///
+    /// This does nothing for private mappings.
+    pub fn sync(&self) -> Result<(), &'static str> {
+        self.region.sync()
+    }
+}
+
+
+/// A reserved range of virtual pages that caches a range of a file's contents.
+struct Region {
+    file: FileRef,
+    /// The file offset of the region's first byte, which is page-aligned.
+    file_offset: usize,
+    /// The length of the file when this region was created.
+    file_len: usize,
+    pages: memory::PageRange,
+    private: bool,
+    /// The state of each page in this region.
+    slots: PreemptionSafeMutex<Vec<Slot>>,
+}
+
+/// The state of a single page in a [`Region`].
+enum Slot {
+    /// The page hasn't been accessed yet.
+    Reserved(AllocatedPages),
+    /// The page has been read from the file.
+    Mapped {
+        mp: MappedPages,
+        /// Whether the page was written since it was last read from or written back to the file.
+        dirty: bool,
+    },
+    /// Only exists while a page is being mapped, or if mapping it failed.
+    Empty,
+}
+
+impl Region {
+    /// Creates and registers a new region that covers `len` bytes of the given file from `file_offset`.
+    fn new(file: &FileRef, file_offset: usize, len: usize, private: bool) -> Result<Arc<Region>, &'static str> {
+        let file_len = file.lock().len();
+        let reserved = memory::allocate_pages_by_bytes(len)
+            .ok_or("couldn't allocate pages for a file mapping")?;
+        let pages = reserved.range().clone();
+
+        // Split the reserved pages into individual pages, such that each can be mapped separately.
+        let mut slots = Vec::with_capacity(reserved.size_in_pages());
+        let mut remaining = reserved;
+        while remaining.size_in_pages() > 1 {
+            let next = *remaining.start() + 1;
+            let (first, rest) = remaining.split(next).map_err(|_| "BUG: couldn't split reserved pages")?;
+            slots.push(Slot::Reserved(first));
+            remaining = rest;
+        }
+        slots.push(Slot::Reserved(remaining));
+
+        let region = Arc::new(Region {
+            file: Arc::clone(file),
+            file_offset,
+            file_len,
+            pages,
+            private,
+            slots: PreemptionSafeMutex::new(slots),
+        });
+        let end = region.address_range().end;
+        REGIONS.lock().insert(region.pages.start_address(), (end, Arc::downgrade(&region)));
+        Ok(region)
+    }
+
+    fn address_range(&self) -> Range<VirtualAddress> {
+        self.pages.start_address() .. (self.pages.start_address() + self.pages.size_in_bytes())
+    }
+
+    /// Maps the page containing the given address, or makes it writable for a write access.
+    ///
+    /// This is only invoked by the loader task, which is the only one that populates slots,
+    /// so the page's slot isn't locked while the page is being read from the file.
+    fn populate(&self, address: VirtualAddress, is_write: bool) -> Result<(), &'static str> {
+        let page = Page::containing_address(address);
+        let index = page.number() - self.pages.start().number();
+        let kernel_mmi_ref = memory::get_kernel_mmi_ref().ok_or("couldn't get kernel MMI")?;
+        // Private pages are always writable, since they're never written back.
+        let writable_flags = PteFlags::new().valid(true).writable(true);
+        let readonly_flags = PteFlags::new().valid(true);
+
+        let slot = core::mem::replace(
+            self.slots.lock().get_mut(index).ok_or("BUG: page fault outside of region")?,
+            Slot::Empty,
+        );
+        let (new_slot, result) = match slot {
+            Slot::Reserved(ap) => {
+                let mut mp = kernel_mmi_ref.lock().page_table.map_allocated_pages(ap, writable_flags)?;
+                let offset = self.file_offset + index * PAGE_SIZE;
+                let buf = mp.as_slice_mut::<u8>(0, PAGE_SIZE)?;
+                let to_read = self.file_len.saturating_sub(offset).min(PAGE_SIZE);
+                if to_read > 0 {
+                    let bytes_read = self.file.lock().read_at(&mut buf[..to_read], offset).map_err(<&'static str>::from)?;
+                    buf[bytes_read..].fill(0);
+                } else {
+                    buf.fill(0);
+                }
+                if !self.private && !is_write {
+                    mp.remap(&mut kernel_mmi_ref.lock().page_table, readonly_flags)?;
+                }
+                (Slot::Mapped { mp, dirty: !self.private && is_write }, Ok(()))
+            }
+            Slot::Mapped { mut mp, dirty } => {
+                // A write to a shared page that was only read so far.
+                let result = if is_write && !mp.flags().is_writable() {
+                    mp.remap(&mut kernel_mmi_ref.lock().page_table, writable_flags)
+                } else {
+                    Err("page fault on an already-mapped page")
+                };
+                let dirty = dirty || result.is_ok();
+                (Slot::Mapped { mp, dirty }, result)
+            }
+            Slot::Empty => (Slot::Empty, Err("BUG: page fault on a page that couldn't be mapped")),
+        };
+        self.slots.lock()[index] = new_slot;
+        result
+    }
+
+    /// Writes back all dirty pages of this region to its file,
+    /// and makes them read-only again such that subsequent writes are detected.
+    fn sync(&self) -> Result<(), &'static str> {
+        if self.private {
+            return Ok(());
+        }
+        let kernel_mmi_ref = memory::get_kernel_mmi_ref().ok_or("couldn't get kernel MMI")?;
+        let num_slots = self.slots.lock().len();
+        for index in 0..num_slots {
+            let offset = self.file_offset + index * PAGE_SIZE;
+            let to_write = self.file_len.saturating_sub(offset).min(PAGE_SIZE);
+            // Copy the page's contents, such that the slots aren't locked while writing to the file.
+            let contents = {
+                let mut slots = self.slots.lock();
+                let Slot::Mapped { mp, dirty } = &mut slots[index] else { continue };
+                if !*dirty {
+                    continue;
+                }
+                mp.remap(&mut kernel_mmi_ref.lock().page_table, PteFlags::new().valid(true))?;
+                *dirty = false;
+                mp.as_slice::<u8>(0, to_write)?.to_vec()
+            };
+            if to_write > 0 {
+                if let Err(e) = self.file.lock().write_at(&contents, offset) {
+                    // The page must be written back again later.
+                    if let Slot::Mapped { dirty, .. } = &mut self.slots.lock()[index] {
+                        *dirty = true;
+                    }
+                    return Err(<&'static str>::from(e));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Region {
+    fn drop(&mut self) {
+        if let Err(e) = self.sync() {
+            warn!("file_mmap: couldn't write back mapped file {:?}: {}", self.file.lock().get_name(), e);
+        }
+        REGIONS.lock().remove(&self.pages.start_address());
+    }
+}
+
+
+/// Returns the address of the given file, which identifies it in the page cache.
+fn file_key(file: &FileRef) -> usize {
+    Arc::as_ptr(file) as *const () as usize
+}
+
+/// Returns the page cache region for the given file, creating it if necessary,
+/// which must cover at least the first `min_len` bytes of the file.
+fn cached_region(file: &FileRef, min_len: usize) -> Result<Arc<Region>, &'static str> {
+    let mut cache = PAGE_CACHE.lock();
+    cache.retain(|_, region| region.strong_count() > 0);
+    let key = file_key(file);
+    if let Some(region) = cache.get(&key).and_then(Weak::upgrade) {
+        return if min_len <= region.file_len {
+            Ok(region)
+        } else {
+            Err("shared file mapping extends beyond the end of the file")
+        };
+    }
+    let file_len = file.lock().len();
+    if min_len > file_len {
+        return Err("shared file mapping extends beyond the end of the file");
+    }
+    let region = Region::new(file, 0, file_len, false)?;
+    cache.insert(key, Arc::downgrade(&region));
+    Ok(region)
+}
+
+
+/// Spawns the loader task, which handles page faults on file mappings in a task context.
+fn spawn_loader() -> Result<TaskRef, &'static str> {
+    let loader = spawn::new_task_builder(loader_loop, ())
+        .name(String::from("file_mmap_loader"))
+        .spawn()?;
+    Ok(TaskRef::clone(&loader))
+}
+
+fn loader_loop(_: ()) {
+    loop {
+        // Take the next fault that hasn't been handled yet, or block until there is one.
+        let next = {
+            let mut faults = FAULTS.lock();
+            let next = faults.iter_mut().enumerate().find_map(|(index, fault)| {
+                let fault = fault.as_mut()?;
+                let region = fault.region.take()?;
+                Some((index, region, fault.address, fault.is_write))
+            });
+            if next.is_none() {
+                if let Some(loader) = LOADER.get() {
+                    let _ = loader.block();
+                }
+            }
+            next
+        };
+        let Some((index, region, address, is_write)) = next else {
+            task::schedule();
+            continue;
+        };
+
+        let result = region.populate(address, is_write);
+        if let Err(e) = result {
+            error!("file_mmap: couldn't handle page fault at {:#X}: {}", address, e);
+        }
+        // Dropping the last reference to the region writes it back, which must not happen under the lock.
+        drop(region);
+
+        if let Some(fault) = FAULTS.lock()[index].as_mut() {
+            fault.handled = Some(result.is_ok());
+            let _ = fault.task.unblock();
+        }
+    }
+}
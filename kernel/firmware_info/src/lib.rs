@@ -0,0 +1,127 @@
+//! Information about the machine that Theseus is running on, as described by its firmware.
+//!
+//! Currently, this information comes from the SMBIOS (a.k.a. DMI) tables,
+//! which describe the system's manufacturer, motherboard, firmware, processor sockets,
+//! and memory devices, e.g., to identify which machine produced a report.
+//!
+//! The SMBIOS tables are found and parsed the first time that [`get()`] is called,
+//! after which the parsed information is kept, so the tables needn't remain mapped.
+
+#![no_std]
+
+extern crate alloc;
+
+pub mod smbios;
+
+use alloc::vec::Vec;
+use log::{debug, warn};
+use memory::{allocate_frames_by_bytes_at, allocate_pages_by_bytes, get_kernel_mmi_ref, MappedPages, PhysicalAddress, PteFlags};
+use spin::Once;
+
+pub use smbios::{BaseboardInfo, BiosInfo, MemoryDevice, ProcessorInfo, SystemInfo};
+
+/// The starting physical address of the BIOS memory area that contains the SMBIOS entry point.
+const ENTRY_POINT_SEARCH_START: usize = 0xF_0000;
+/// The ending physical address (exclusive) of the BIOS memory area that contains the SMBIOS entry point.
+const ENTRY_POINT_SEARCH_END:   usize = 0x10_0000;
+/// The SMBIOS entry point is always aligned on a 16-byte boundary.
+const ENTRY_POINT_ALIGNMENT: usize = 16;
+
+static FIRMWARE_INFO: Once<FirmwareInfo> = Once::new();
+
+/// Information about the machine, as described by its firmware.
+#[derive(Clone, Debug, Default)]
+pub struct FirmwareInfo {
+    /// The SMBIOS version, as `(major, minor)`.
+    pub smbios_version: (u8, u8),
+    pub bios: Option<BiosInfo>,
+    pub system: Option<SystemInfo>,
+    pub baseboard: Option<BaseboardInfo>,
+    /// The processor sockets, whether or not a processor is installed in them.
+    pub processors: Vec<ProcessorInfo>,
+    /// The memory devices, e.g., DIMM slots, whether or not memory is installed in them.
+    pub memory_devices: Vec<MemoryDevice>,
+}
+
+impl FirmwareInfo {
+    /// Parses the given SMBIOS structure `table`, which has the given SMBIOS `version`.
+    pub fn from_smbios_table(table: &[u8], version: (u8, u8)) -> FirmwareInfo {
+        let mut info = FirmwareInfo { smbios_version: version, ..Default::default() };
+        for structure in smbios::structures(table) {
+            match structure.typ {
+                BiosInfo::TYPE      => { info.bios.get_or_insert_with(|| BiosInfo::parse(&structure)); }
+                SystemInfo::TYPE    => { info.system.get_or_insert_with(|| SystemInfo::parse(&structure, version)); }
+                BaseboardInfo::TYPE => { info.baseboard.get_or_insert_with(|| BaseboardInfo::parse(&structure)); }
+                ProcessorInfo::TYPE => info.processors.push(ProcessorInfo::parse(&structure)),
+                MemoryDevice::TYPE  => info.memory_devices.push(MemoryDevice::parse(&structure)),
+                _ => { }
+            }
+        }
+        info
+    }
+
+    /// Returns the total size in bytes of the memory installed in all memory devices.
+    pub fn installed_memory(&self) -> u64 {
+        self.memory_devices.iter().filter_map(|device| device.size).sum()
+    }
+}
+
+/// Returns the information about this machine from its firmware,
+/// finding and parsing the SMBIOS tables if this is the first call.
+pub fn get() -> Result<&'static FirmwareInfo, &'static str> {
+    FIRMWARE_INFO.try_call_once(|| {
+        let entry_point = find_entry_point()?;
+        debug!("Found SMBIOS {}.{} table at {:#X}, length {:#X}",
+            entry_point.version.0, entry_point.version.1, entry_point.table_address, entry_point.table_len,
+        );
+        let table_address = PhysicalAddress::new(entry_point.table_address as usize)
+            .ok_or("SMBIOS table had an invalid physical address")?;
+        if entry_point.table_len == 0 {
+            return Err("SMBIOS table was empty");
+        }
+        let (mapped_pages, offset) = map_physical(table_address, entry_point.table_len)?;
+        let table = mapped_pages.as_slice::<u8>(offset, entry_point.table_len)?;
+        Ok(FirmwareInfo::from_smbios_table(table, entry_point.version))
+    })
+}
+
+/// Searches the BIOS memory area for an SMBIOS entry point,
+/// preferring a 64-bit (SMBIOS 3.x) entry point over a 32-bit one.
+fn find_entry_point() -> Result<smbios::EntryPoint, &'static str> {
+    if cfg!(not(target_arch = "x86_64")) {
+        return Err("finding the SMBIOS entry point is only supported on x86_64");
+    }
+    let size = ENTRY_POINT_SEARCH_END - ENTRY_POINT_SEARCH_START;
+    let (mapped_pages, _) = map_physical(PhysicalAddress::new_canonical(ENTRY_POINT_SEARCH_START), size)?;
+    let region: &[u8] = mapped_pages.as_slice(0, size)?;
+
+    let mut entry_point_32 = None;
+    for offset in (0..size).step_by(ENTRY_POINT_ALIGNMENT) {
+        let bytes = &region[offset..];
+        if bytes.starts_with(smbios::SIGNATURE_64) {
+            match smbios::EntryPoint::parse(bytes) {
+                Ok(entry_point) => return Ok(entry_point),
+                Err(e) => warn!("Ignoring SMBIOS 3.x entry point at {:#X}: {}", ENTRY_POINT_SEARCH_START + offset, e),
+            }
+        } else if bytes.starts_with(smbios::SIGNATURE_32) && entry_point_32.is_none() {
+            match smbios::EntryPoint::parse(bytes) {
+                Ok(entry_point) => entry_point_32 = Some(entry_point),
+                Err(e) => warn!("Ignoring SMBIOS 2.x entry point at {:#X}: {}", ENTRY_POINT_SEARCH_START + offset, e),
+            }
+        }
+    }
+    entry_point_32.ok_or("couldn't find an SMBIOS entry point in BIOS memory")
+}
+
+/// Maps the given range of physical memory as read-only,
+/// returning the mapped pages and the offset of `address` within them.
+fn map_physical(address: PhysicalAddress, len: usize) -> Result<(MappedPages, usize), &'static str> {
+    let offset = address.frame_offset();
+    let kernel_mmi_ref = get_kernel_mmi_ref().ok_or("KERNEL_MMI was not yet initialized!")?;
+    let pages = allocate_pages_by_bytes(offset + len).ok_or("couldn't allocate pages for SMBIOS")?;
+    let frames = allocate_frames_by_bytes_at(address - offset, offset + len)
+        .map_err(|_| "couldn't allocate frames for SMBIOS")?;
+    let mapped_pages = kernel_mmi_ref.lock().page_table
+        .map_allocated_pages_to(pages, frames, PteFlags::new().valid(true))?;
+    Ok((mapped_pages, offset))
+}
@@ -0,0 +1,473 @@
+//! Parsing of SMBIOS entry points and the structures in an SMBIOS table.
+//!
+//! This module only parses bytes, so it doesn't depend on how the table was found or mapped.
+//! See the [SMBIOS specification] for the layout of each structure type.
+//!
+//! [SMBIOS specification]: https://www.dmtf.org/standards/smbios
+
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+/// The signature of a 32-bit (SMBIOS 2.x) entry point.
+pub const SIGNATURE_32: &[u8] = b"_SM_";
+/// The signature of a 64-bit (SMBIOS 3.x) entry point.
+pub const SIGNATURE_64: &[u8] = b"_SM3_";
+/// The intermediate signature within a 32-bit entry point.
+const INTERMEDIATE_SIGNATURE: &[u8] = b"_DMI_";
+
+/// The type of the structure that marks the end of the table.
+const END_OF_TABLE: u8 = 127;
+
+/// The location and version of an SMBIOS structure table, as described by an entry point.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EntryPoint {
+    /// The SMBIOS version, as `(major, minor)`.
+    pub version: (u8, u8),
+    /// The physical address of the structure table.
+    pub table_address: u64,
+    /// The length of the structure table in bytes.
+    /// For a 64-bit entry point, this is only the maximum length.
+    pub table_len: usize,
+}
+
+impl EntryPoint {
+    /// Parses the entry point at the start of `bytes`, which must begin with either signature.
+    pub fn parse(bytes: &[u8]) -> Result<EntryPoint, &'static str> {
+        if bytes.starts_with(SIGNATURE_64) {
+            let len = *bytes.get(6).ok_or("SMBIOS 3.x entry point was truncated")? as usize;
+            let entry = checksummed(bytes, len, 0x18)?;
+            Ok(EntryPoint {
+                version: (entry[7], entry[8]),
+                table_address: u64::from_le_bytes(entry[16..24].try_into().unwrap()),
+                table_len: read_u32(entry, 12) as usize,
+            })
+        } else if bytes.starts_with(SIGNATURE_32) {
+            let len = *bytes.get(5).ok_or("SMBIOS 2.x entry point was truncated")? as usize;
+            let entry = checksummed(bytes, len, 0x1F)?;
+            if &entry[16..21] != INTERMEDIATE_SIGNATURE {
+                return Err("SMBIOS 2.x entry point had an invalid intermediate signature");
+            }
+            if entry[16..].iter().fold(0u8, |sum, b| sum.wrapping_add(*b)) != 0 {
+                return Err("SMBIOS 2.x entry point had an invalid intermediate checksum");
+            }
+            Ok(EntryPoint {
+                version: (entry[6], entry[7]),
+                table_address: read_u32(entry, 24) as u64,
+                table_len: read_u16(entry, 22) as usize,
+            })
+        } else {
+            Err("bytes didn't start with an SMBIOS entry point signature")
+        }
+    }
+}
+
+/// Returns the first `len` bytes of `bytes` if they sum to zero, and `len` is at least `min_len`.
+fn checksummed(bytes: &[u8], len: usize, min_len: usize) -> Result<&[u8], &'static str> {
+    if len < min_len {
+        return Err("SMBIOS entry point had an invalid length");
+    }
+    let entry = bytes.get(..len).ok_or("SMBIOS entry point was truncated")?;
+    if entry.iter().fold(0u8, |sum, b| sum.wrapping_add(*b)) != 0 {
+        return Err("SMBIOS entry point had an invalid checksum");
+    }
+    Ok(entry)
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+/// A single structure in an SMBIOS table.
+#[derive(Clone, Debug)]
+pub struct Structure<'t> {
+    /// The type of the structure, e.g., `1` for system information.
+    pub typ: u8,
+    /// The handle that other structures use to refer to this one.
+    pub handle: u16,
+    /// The formatted area of the structure, including its 4-byte header.
+    pub formatted: &'t [u8],
+    /// The strings that follow the formatted area, which its fields refer to by index.
+    strings: Vec<&'t [u8]>,
+}
+
+impl<'t> Structure<'t> {
+    /// Returns the byte at `offset` within the formatted area, if the structure is long enough to have it.
+    ///
+    /// Fields added in later SMBIOS versions are simply missing from older, shorter structures.
+    pub fn byte(&self, offset: usize) -> Option<u8> {
+        self.formatted.get(offset).copied()
+    }
+
+    /// Returns the little-endian `u16` at `offset` within the formatted area, if present.
+    pub fn word(&self, offset: usize) -> Option<u16> {
+        Some(u16::from_le_bytes([self.byte(offset)?, self.byte(offset + 1)?]))
+    }
+
+    /// Returns the little-endian `u32` at `offset` within the formatted area, if present.
+    pub fn dword(&self, offset: usize) -> Option<u32> {
+        Some(u32::from_le_bytes(self.formatted.get(offset..offset + 4)?.try_into().ok()?))
+    }
+
+    /// Returns the string referred to by the string-index field at `offset`,
+    /// or `None` if the field is missing, refers to no string, or the string is empty.
+    pub fn string(&self, offset: usize) -> Option<String> {
+        let index = self.byte(offset)? as usize;
+        let bytes = self.strings.get(index.checked_sub(1)?)?;
+        // Strings are nominally ASCII, but firmware is known to include other bytes.
+        let string: String = bytes.iter().map(|b| if b.is_ascii() { *b as char } else { '.' }).collect();
+        let string = string.trim();
+        (!string.is_empty()).then(|| string.to_string())
+    }
+}
+
+/// Returns an iterator over the structures in the given SMBIOS `table`, up to the end-of-table structure.
+///
+/// Iteration stops at the first malformed structure.
+pub fn structures(table: &[u8]) -> impl Iterator<Item = Structure<'_>> {
+    let mut rest = table;
+    core::iter::from_fn(move || {
+        let (&typ, &len) = (rest.first()?, rest.get(1)?);
+        let len = len as usize;
+        if len < 4 || rest.len() < len {
+            return None;
+        }
+        let (formatted, strings_area) = rest.split_at(len);
+        // The string set ends with two null bytes, even if it has no strings.
+        let strings_len = strings_area.windows(2).position(|w| w == [0, 0])?;
+        let strings = if strings_len == 0 {
+            Vec::new()
+        } else {
+            strings_area[..strings_len].split(|b| *b == 0).collect()
+        };
+        rest = &strings_area[strings_len + 2..];
+        if typ == END_OF_TABLE {
+            rest = &[];
+        }
+        Some(Structure { typ, handle: read_u16(formatted, 2), formatted, strings })
+    })
+}
+
+/// Information about the BIOS or UEFI firmware, from a type 0 structure.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BiosInfo {
+    pub vendor: Option<String>,
+    pub version: Option<String>,
+    pub release_date: Option<String>,
+    /// The major and minor release of the firmware, if given.
+    pub release: Option<(u8, u8)>,
+}
+
+impl BiosInfo {
+    pub const TYPE: u8 = 0;
+
+    pub fn parse(s: &Structure) -> BiosInfo {
+        BiosInfo {
+            vendor: s.string(0x04),
+            version: s.string(0x05),
+            release_date: s.string(0x08),
+            release: s.byte(0x14).zip(s.byte(0x15)).filter(|release| *release != (0xFF, 0xFF)),
+        }
+    }
+}
+
+/// Information about the system as a whole, from a type 1 structure.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SystemInfo {
+    pub manufacturer: Option<String>,
+    pub product_name: Option<String>,
+    pub version: Option<String>,
+    pub serial_number: Option<String>,
+    /// The system's UUID, formatted as usual, e.g., `4c4c4544-0032-...`.
+    pub uuid: Option<String>,
+    pub sku_number: Option<String>,
+    pub family: Option<String>,
+}
+
+impl SystemInfo {
+    pub const TYPE: u8 = 1;
+
+    /// Parses a type 1 structure from a table with the given SMBIOS `version`.
+    pub fn parse(s: &Structure, version: (u8, u8)) -> SystemInfo {
+        SystemInfo {
+            manufacturer: s.string(0x04),
+            product_name: s.string(0x05),
+            version: s.string(0x06),
+            serial_number: s.string(0x07),
+            uuid: s.formatted.get(0x08..0x18).and_then(|uuid| format_uuid(uuid.try_into().ok()?, version)),
+            sku_number: s.string(0x19),
+            family: s.string(0x1A),
+        }
+    }
+}
+
+/// Formats the given UUID, or returns `None` if it isn't set.
+///
+/// Since SMBIOS 2.6, the first three fields of the UUID are stored in little-endian byte order.
+fn format_uuid(uuid: [u8; 16], version: (u8, u8)) -> Option<String> {
+    // All zeros means that the UUID isn't present, and all ones means that it isn't set.
+    if uuid.iter().all(|b| *b == 0) || uuid.iter().all(|b| *b == 0xFF) {
+        return None;
+    }
+    let (a, b, c) = if version >= (2, 6) {
+        (
+            u32::from_le_bytes(uuid[0..4].try_into().unwrap()),
+            u16::from_le_bytes([uuid[4], uuid[5]]),
+            u16::from_le_bytes([uuid[6], uuid[7]]),
+        )
+    } else {
+        (
+            u32::from_be_bytes(uuid[0..4].try_into().unwrap()),
+            u16::from_be_bytes([uuid[4], uuid[5]]),
+            u16::from_be_bytes([uuid[6], uuid[7]]),
+        )
+    };
+    let node: String = uuid[10..].iter().map(|b| format!("{:02x}", b)).collect();
+    Some(format!("{:08x}-{:04x}-{:04x}-{:02x}{:02x}-{}", a, b, c, uuid[8], uuid[9], node))
+}
+
+/// Information about the system's motherboard, from a type 2 structure.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BaseboardInfo {
+    pub manufacturer: Option<String>,
+    pub product_name: Option<String>,
+    pub version: Option<String>,
+    pub serial_number: Option<String>,
+    pub asset_tag: Option<String>,
+}
+
+impl BaseboardInfo {
+    pub const TYPE: u8 = 2;
+
+    pub fn parse(s: &Structure) -> BaseboardInfo {
+        BaseboardInfo {
+            manufacturer: s.string(0x04),
+            product_name: s.string(0x05),
+            version: s.string(0x06),
+            serial_number: s.string(0x07),
+            asset_tag: s.string(0x08),
+        }
+    }
+}
+
+/// Information about a processor socket, from a type 4 structure.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ProcessorInfo {
+    /// The name of the socket, e.g., `CPU 1`.
+    pub socket: Option<String>,
+    pub manufacturer: Option<String>,
+    pub version: Option<String>,
+    /// Whether a processor is installed in the socket.
+    pub populated: bool,
+    pub max_speed_mhz: Option<u16>,
+    pub current_speed_mhz: Option<u16>,
+    pub core_count: Option<u16>,
+    pub thread_count: Option<u16>,
+}
+
+impl ProcessorInfo {
+    pub const TYPE: u8 = 4;
+
+    pub fn parse(s: &Structure) -> ProcessorInfo {
+        let nonzero = |value: Option<u16>| value.filter(|v| *v != 0);
+        // A count of 0xFF means that the real count is in the larger field added in SMBIOS 3.0.
+        let count = |offset: usize, offset_2: usize| match s.byte(offset) {
+            Some(0xFF) => nonzero(s.word(offset_2)).filter(|c| *c != 0xFFFF),
+            other => nonzero(other.map(u16::from)),
+        };
+        ProcessorInfo {
+            socket: s.string(0x04),
+            manufacturer: s.string(0x07),
+            version: s.string(0x10),
+            populated: s.byte(0x18).map_or(false, |status| status & (1 << 6) != 0),
+            max_speed_mhz: nonzero(s.word(0x14)),
+            current_speed_mhz: nonzero(s.word(0x16)),
+            core_count: count(0x23, 0x2A),
+            thread_count: count(0x25, 0x2E),
+        }
+    }
+}
+
+/// Information about a memory device, e.g., a DIMM slot, from a type 17 structure.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MemoryDevice {
+    /// The name of the slot or chip, e.g., `DIMM 0`.
+    pub locator: Option<String>,
+    pub bank_locator: Option<String>,
+    /// The size of the installed memory in bytes, or `None` if no memory is installed or its size is unknown.
+    pub size: Option<u64>,
+    /// The type of memory, e.g., `DDR4`.
+    pub memory_type: &'static str,
+    /// The maximum speed of the device in megatransfers per second.
+    pub speed_mts: Option<u32>,
+    pub manufacturer: Option<String>,
+    pub serial_number: Option<String>,
+    pub part_number: Option<String>,
+}
+
+impl MemoryDevice {
+    pub const TYPE: u8 = 17;
+
+    pub fn parse(s: &Structure) -> MemoryDevice {
+        const MIB: u64 = 1024 * 1024;
+        let size = match s.word(0x0C) {
+            None | Some(0) | Some(0xFFFF) => None,
+            // The size is too large for this field, so it's given in MiB in the extended size field.
+            Some(0x7FFF) => s.dword(0x1C).map(|mib| (mib & 0x7FFF_FFFF) as u64 * MIB),
+            // The size is given in KiB if the top bit is set, and MiB otherwise.
+            Some(size) if size & 0x8000 != 0 => Some((size & 0x7FFF) as u64 * 1024),
+            Some(size) => Some(size as u64 * MIB),
+        };
+        let speed_mts = match s.word(0x15) {
+            None | Some(0) => None,
+            // The speed is too large for this field, so it's given in the extended speed field.
+            Some(0xFFFF) => s.dword(0x54).map(|speed| speed & 0x7FFF_FFFF),
+            Some(speed) => Some(speed as u32),
+        };
+        MemoryDevice {
+            locator: s.string(0x10),
+            bank_locator: s.string(0x11),
+            size,
+            memory_type: s.byte(0x12).map_or("Unknown", memory_type_name),
+            speed_mts,
+            manufacturer: s.string(0x17),
+            serial_number: s.string(0x18),
+            part_number: s.string(0x1A),
+        }
+    }
+}
+
+/// Returns the name of the given memory type of a type 17 structure.
+fn memory_type_name(memory_type: u8) -> &'static str {
+    match memory_type {
+        0x01 => "Other",
+        0x03 => "DRAM",
+        0x07 => "RAM",
+        0x08 => "ROM",
+        0x09 => "Flash",
+        0x0F => "SDRAM",
+        0x12 => "DDR",
+        0x13 => "DDR2",
+        0x14 => "DDR2 FB-DIMM",
+        0x18 => "DDR3",
+        0x1A => "DDR4",
+        0x1B => "LPDDR",
+        0x1C => "LPDDR2",
+        0x1D => "LPDDR3",
+        0x1E => "LPDDR4",
+        0x1F => "Logical non-volatile device",
+        0x20 => "HBM",
+        0x21 => "HBM2",
+        0x22 => "DDR5",
+        0x23 => "LPDDR5",
+        0x24 => "HBM3",
+        _ => "Unknown",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    /// Returns the given bytes with the byte at `checksum_offset` set such that `bytes[start..]` sums to zero.
+    fn with_checksum(mut bytes: Vec<u8>, start: usize, checksum_offset: usize) -> Vec<u8> {
+        let sum = bytes[start..].iter().fold(0u8, |sum, b| sum.wrapping_add(*b));
+        bytes[checksum_offset] = 0u8.wrapping_sub(sum);
+        bytes
+    }
+
+    #[test]
+    fn parses_64_bit_entry_point() {
+        let mut entry = vec![0; 0x18];
+        entry[..5].copy_from_slice(SIGNATURE_64);
+        entry[6] = 0x18;
+        entry[7] = 3;
+        entry[8] = 2;
+        entry[12..16].copy_from_slice(&0x1234u32.to_le_bytes());
+        entry[16..24].copy_from_slice(&0x7FFF_0000u64.to_le_bytes());
+        let entry = with_checksum(entry, 0, 5);
+        assert_eq!(
+            EntryPoint::parse(&entry),
+            Ok(EntryPoint { version: (3, 2), table_address: 0x7FFF_0000, table_len: 0x1234 }),
+        );
+
+        let mut corrupted = entry.clone();
+        corrupted[20] ^= 1;
+        assert!(EntryPoint::parse(&corrupted).is_err());
+    }
+
+    #[test]
+    fn parses_32_bit_entry_point() {
+        let mut entry = vec![0; 0x1F];
+        entry[..4].copy_from_slice(SIGNATURE_32);
+        entry[5] = 0x1F;
+        entry[6] = 2;
+        entry[7] = 8;
+        entry[16..21].copy_from_slice(INTERMEDIATE_SIGNATURE);
+        entry[22..24].copy_from_slice(&0x400u16.to_le_bytes());
+        entry[24..28].copy_from_slice(&0xF_0100u32.to_le_bytes());
+        let entry = with_checksum(entry, 16, 21);
+        let entry = with_checksum(entry, 0, 4);
+        assert_eq!(
+            EntryPoint::parse(&entry),
+            Ok(EntryPoint { version: (2, 8), table_address: 0xF_0100, table_len: 0x400 }),
+        );
+    }
+
+    #[test]
+    fn parses_structures_and_strings() {
+        let mut table = Vec::new();
+        // A type 1 structure with a UUID and three strings, one of which is unused.
+        let mut system = vec![1, 0x1B, 0x01, 0x00, 1, 3, 0, 2];
+        system.extend_from_slice(&[0x44, 0x45, 0x4C, 0x4C, 0x32, 0x00, 0x10, 0x52, 0x80, 0x4B, 0xB2, 0xC0, 0x4F, 0x38, 0x46, 0x32]);
+        system.extend_from_slice(&[0x06, 0, 0]);
+        table.extend_from_slice(&system);
+        table.extend_from_slice(b"Acme\0SN-42\0  Widget 9000 \0\0");
+        // A type 2 structure without any strings.
+        table.extend_from_slice(&[2, 0x08, 0x02, 0x00, 0, 0, 0, 0, 0, 0]);
+        // A type 17 structure for an 8 GiB DDR4 DIMM.
+        let mut memory = vec![0; 0x1C];
+        memory[..4].copy_from_slice(&[17, 0x1C, 0x03, 0x00]);
+        memory[0x0C..0x0E].copy_from_slice(&8192u16.to_le_bytes());
+        memory[0x10] = 1;
+        memory[0x12] = 0x1A;
+        memory[0x15..0x17].copy_from_slice(&3200u16.to_le_bytes());
+        table.extend_from_slice(&memory);
+        table.extend_from_slice(b"DIMM 0\0\0");
+        // The end of the table, after which nothing is parsed.
+        table.extend_from_slice(&[127, 4, 0xFF, 0xFF, 0, 0, 1, 4, 0, 0]);
+
+        let structures: Vec<Structure> = structures(&table).collect();
+        assert_eq!(structures.iter().map(|s| s.typ).collect::<Vec<_>>(), [1, 2, 17, 127]);
+
+        let system = SystemInfo::parse(&structures[0], (3, 2));
+        assert_eq!(system.manufacturer.as_deref(), Some("Acme"));
+        assert_eq!(system.product_name.as_deref(), Some("Widget 9000"));
+        assert_eq!(system.version, None);
+        assert_eq!(system.serial_number.as_deref(), Some("SN-42"));
+        assert_eq!(system.uuid.as_deref(), Some("4c4c4544-0032-5210-804b-b2c04f384632"));
+
+        assert_eq!(BaseboardInfo::parse(&structures[1]), BaseboardInfo::default());
+
+        let memory = MemoryDevice::parse(&structures[2]);
+        assert_eq!(memory.locator.as_deref(), Some("DIMM 0"));
+        assert_eq!(memory.size, Some(8 << 30));
+        assert_eq!(memory.memory_type, "DDR4");
+        assert_eq!(memory.speed_mts, Some(3200));
+    }
+
+    #[test]
+    fn stops_at_truncated_structure() {
+        let table = [1, 0x1B, 0x01, 0x00, 0, 0];
+        assert_eq!(structures(&table).count(), 0);
+        let table = [2, 0x04, 0x02, 0x00, b'A', 0];
+        assert_eq!(structures(&table).count(), 0);
+    }
+}
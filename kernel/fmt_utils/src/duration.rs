@@ -0,0 +1,74 @@
+//! Human-readable durations.
+
+use alloc::format;
+use core::{fmt, time::Duration};
+
+/// A duration displayed like a stopwatch, as minutes, seconds, and hundredths of a second,
+/// e.g., `12:03.45`. The minutes keep counting past an hour.
+///
+/// Width, fill, and alignment specifiers apply to the whole duration, e.g., `{:>10}`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Clock(pub Duration);
+
+impl fmt::Display for Clock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let centis = self.0.as_millis() / 10;
+        f.pad(&format!("{}:{:02}.{:02}", centis / 6000, centis / 100 % 60, centis % 100))
+    }
+}
+
+/// A duration displayed in the units that suit its magnitude,
+/// e.g., `750 ns`, `12.5 µs`, `2.50 s`, `1h 02m 03s`, or `3d 04h 05m`.
+///
+/// Width, fill, and alignment specifiers apply to the whole duration, e.g., `{:>10}`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct HumanDuration(pub Duration);
+
+impl fmt::Display for HumanDuration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let nanos = self.0.as_nanos();
+        let secs = self.0.as_secs();
+        let text = if nanos < 1_000 {
+            format!("{} ns", nanos)
+        } else if nanos < 1_000_000 {
+            format!("{:.1} µs", nanos as f64 / 1e3)
+        } else if nanos < 1_000_000_000 {
+            format!("{:.1} ms", nanos as f64 / 1e6)
+        } else if secs < 60 {
+            format!("{:.2} s", self.0.as_secs_f64())
+        } else if secs < 60 * 60 {
+            format!("{}m {:02}s", secs / 60, secs % 60)
+        } else if secs < 24 * 60 * 60 {
+            format!("{}h {:02}m {:02}s", secs / 3600, secs / 60 % 60, secs % 60)
+        } else {
+            format!("{}d {:02}h {:02}m", secs / 86400, secs / 3600 % 24, secs / 60 % 60)
+        };
+        f.pad(&text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn clock() {
+        assert_eq!(Clock(Duration::ZERO).to_string(), "0:00.00");
+        assert_eq!(Clock(Duration::from_millis(723_456)).to_string(), "12:03.45");
+        assert_eq!(Clock(Duration::from_secs(2 * 3600)).to_string(), "120:00.00");
+        assert_eq!(format!("{:>9}", Clock(Duration::from_millis(1500))), "  0:01.50");
+    }
+
+    #[test]
+    fn human_duration() {
+        let human = |d| HumanDuration(d).to_string();
+        assert_eq!(human(Duration::from_nanos(750)), "750 ns");
+        assert_eq!(human(Duration::from_nanos(12_500)), "12.5 µs");
+        assert_eq!(human(Duration::from_micros(3_300)), "3.3 ms");
+        assert_eq!(human(Duration::from_millis(2_500)), "2.50 s");
+        assert_eq!(human(Duration::from_secs(125)), "2m 05s");
+        assert_eq!(human(Duration::from_secs(3723)), "1h 02m 03s");
+        assert_eq!(human(Duration::from_secs(273_900)), "3d 04h 05m");
+    }
+}
@@ -0,0 +1,35 @@
+//! Formatting for the output of commands that report status,
+//! so that they don't each reimplement column alignment and unit conversions.
+//!
+//! * [`Size`] shows a number of bytes in binary units, e.g., `1.5 KiB`.
+//! * [`Clock`] and [`HumanDuration`] show durations, e.g., `12:03.45` or `1h 02m 03s`.
+//! * [`Table`] aligns rows of cells into columns.
+//! * [`wrap()`] breaks text into lines that fit within a given width.
+//!
+//! All output is independent of any locale: numbers use `.` as the decimal separator
+//! and have no thousands separators.
+//! Widths are measured in characters rather than bytes, so non-ASCII text is aligned correctly.
+
+#![no_std]
+
+extern crate alloc;
+
+mod duration;
+mod size;
+mod table;
+mod wrap;
+
+pub use duration::{Clock, HumanDuration};
+pub use size::Size;
+pub use table::{Align, Table};
+pub use wrap::wrap;
+
+use alloc::string::{String, ToString};
+use core::fmt::Display;
+
+/// Returns the given value as a string, or `-` if there is no value.
+///
+/// This is how tables show a missing value, e.g., the CPU of a task that isn't running.
+pub fn or_dash<T: Display>(value: Option<T>) -> String {
+    value.map_or_else(|| String::from("-"), |v| v.to_string())
+}
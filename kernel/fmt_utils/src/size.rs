@@ -0,0 +1,60 @@
+//! Human-readable byte sizes.
+
+use alloc::format;
+use core::fmt;
+
+const UNITS: [&str; 6] = ["KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
+
+/// A number of bytes, displayed in the largest binary unit that it amounts to at least one of,
+/// e.g., `512 B`, `1.5 KiB`, or `12 GiB`.
+///
+/// Sizes of less than ten units are shown with one decimal place.
+/// Width, fill, and alignment specifiers apply to the whole size, e.g., `{:>9}`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Size(pub u64);
+
+impl From<usize> for Size {
+    fn from(bytes: usize) -> Size {
+        Size(bytes as u64)
+    }
+}
+
+impl fmt::Display for Size {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0 < 1024 {
+            return f.pad(&format!("{} B", self.0));
+        }
+        let mut value = self.0 as f64 / 1024.0;
+        let mut unit = 0;
+        // Move up a unit if the value would be rounded up to 1024.
+        while value >= 1023.5 && unit < UNITS.len() - 1 {
+            value /= 1024.0;
+            unit += 1;
+        }
+        let size = if value < 9.95 {
+            format!("{:.1} {}", value, UNITS[unit])
+        } else {
+            format!("{:.0} {}", value, UNITS[unit])
+        };
+        f.pad(&size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn sizes() {
+        assert_eq!(Size(0).to_string(), "0 B");
+        assert_eq!(Size(1023).to_string(), "1023 B");
+        assert_eq!(Size(1024).to_string(), "1.0 KiB");
+        assert_eq!(Size(1536).to_string(), "1.5 KiB");
+        assert_eq!(Size(10 * 1024 - 1).to_string(), "10 KiB");
+        assert_eq!(Size(1024 * 1024 - 1).to_string(), "1.0 MiB");
+        assert_eq!(Size(12 << 30).to_string(), "12 GiB");
+        assert_eq!(Size(u64::MAX).to_string(), "16 EiB");
+        assert_eq!(format!("[{:>9}]", Size(1536)), "[  1.5 KiB]");
+    }
+}
@@ -0,0 +1,153 @@
+//! Tables whose cells are aligned into columns.
+
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::fmt::{self, Display, Write};
+
+/// How the cells of a column are aligned.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Align {
+    Left,
+    Right,
+}
+
+/// A table of text, which is displayed with a header line and the cells of each column aligned.
+///
+/// Columns are as wide as their widest cell and are separated by two spaces.
+/// Trailing spaces are never printed, so the last column should hold the cells that
+/// vary the most in width, such as names.
+///
+/// ```
+/// use fmt_utils::{Align, Table};
+///
+/// let mut table = Table::new(["ID", "SIZE", "NAME"]).align(1, Align::Right);
+/// table.row(["1", "512", "a.txt"]);
+/// table.row(["22", "4096", "b.txt"]);
+/// assert_eq!(table.to_string(), "ID  SIZE  NAME\n1    512  a.txt\n22  4096  b.txt\n");
+/// ```
+#[derive(Clone, Debug)]
+pub struct Table {
+    headers: Vec<String>,
+    aligns: Vec<Align>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    /// Creates an empty table with the given column headers, whose cells are aligned left.
+    pub fn new<I>(headers: I) -> Table
+    where
+        I: IntoIterator,
+        I::Item: Display,
+    {
+        let headers: Vec<String> = headers.into_iter().map(|h| h.to_string()).collect();
+        Table {
+            aligns: headers.iter().map(|_| Align::Left).collect(),
+            headers,
+            rows: Vec::new(),
+        }
+    }
+
+    /// Sets how the cells of the given column, including its header, are aligned.
+    pub fn align(mut self, column: usize, align: Align) -> Table {
+        if let Some(a) = self.aligns.get_mut(column) {
+            *a = align;
+        }
+        self
+    }
+
+    /// Adds a row with the given cells.
+    ///
+    /// Missing cells at the end of the row are left empty, and extra cells are ignored.
+    pub fn row<I>(&mut self, cells: I)
+    where
+        I: IntoIterator,
+        I::Item: Display,
+    {
+        let mut row: Vec<String> = cells
+            .into_iter()
+            .take(self.headers.len())
+            .map(|cell| cell.to_string())
+            .collect();
+        row.resize(self.headers.len(), String::new());
+        self.rows.push(row);
+    }
+
+    /// Returns the number of rows, not counting the header.
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Returns whether the table has no rows.
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+}
+
+impl Display for Table {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = |cell: &String| cell.chars().count();
+        let widths: Vec<usize> = (0..self.headers.len())
+            .map(|column| {
+                core::iter::once(&self.headers)
+                    .chain(&self.rows)
+                    .map(|row| width(&row[column]))
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+
+        let mut line = String::new();
+        for row in core::iter::once(&self.headers).chain(&self.rows) {
+            line.clear();
+            for (column, cell) in row.iter().enumerate() {
+                if column > 0 {
+                    line.push_str("  ");
+                }
+                let padding = widths[column] - width(cell);
+                match self.aligns[column] {
+                    Align::Left => {
+                        line.push_str(cell);
+                        line.extend(core::iter::repeat(' ').take(padding));
+                    }
+                    Align::Right => {
+                        line.extend(core::iter::repeat(' ').take(padding));
+                        line.push_str(cell);
+                    }
+                }
+            }
+            f.write_str(line.trim_end())?;
+            f.write_char('\n')?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn alignment() {
+        let mut table = Table::new(["ID", "%CPU", "NAME"]).align(1, Align::Right);
+        table.row(["1", "0.5", "idle"]);
+        table.row(["1234", "12.0", "späť"]);
+        table.row(["7"]);
+        assert_eq!(table.len(), 3);
+        assert_eq!(
+            table.to_string(),
+            "ID    %CPU  NAME\n\
+             1      0.5  idle\n\
+             1234  12.0  späť\n\
+             7\n"
+        );
+    }
+
+    #[test]
+    fn empty() {
+        let table = Table::new(["A", "B"]);
+        assert!(table.is_empty());
+        assert_eq!(table.to_string(), "A  B\n");
+    }
+}
@@ -0,0 +1,54 @@
+//! Wrapping text into lines of a given width.
+
+use alloc::{string::String, vec::Vec};
+
+/// Breaks `text` into lines of at most `width` characters.
+///
+/// Lines are broken at spaces where possible, and the spaces at a break are dropped.
+/// Words longer than `width` are broken wherever they reach the end of a line.
+/// Newlines in `text` always start a new line.
+/// A `width` of zero is treated as one.
+pub fn wrap(text: &str, width: usize) -> Vec<String> {
+    let width = width.max(1);
+    let mut lines = Vec::new();
+    for paragraph in text.split('\n') {
+        let mut line = String::new();
+        let mut line_width = 0;
+        for word in paragraph.split(' ').filter(|word| !word.is_empty()) {
+            let word_width = word.chars().count();
+            if line_width > 0 && line_width + 1 + word_width > width {
+                lines.push(core::mem::take(&mut line));
+                line_width = 0;
+            }
+            if line_width > 0 {
+                line.push(' ');
+                line_width += 1;
+            }
+            for c in word.chars() {
+                if line_width == width {
+                    lines.push(core::mem::take(&mut line));
+                    line_width = 0;
+                }
+                line.push(c);
+                line_width += 1;
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wrapping() {
+        assert_eq!(wrap("the quick brown fox", 10), ["the quick", "brown fox"]);
+        assert_eq!(wrap("a  b", 10), ["a b"]);
+        assert_eq!(wrap("abcdefghij", 4), ["abcd", "efgh", "ij"]);
+        assert_eq!(wrap("ab\n\ncd ef", 4), ["ab", "", "cd", "ef"]);
+        assert_eq!(wrap("naïve café", 5), ["naïve", "café"]);
+        assert_eq!(wrap("", 5), [""]);
+    }
+}
@@ -1,11 +1,54 @@
+//! The bitmap font used to display text on the screen.
+//!
+//! [`FONT_BASIC`] holds the glyphs of code page 437, which covers ASCII,
+//! some accented letters, and the box-drawing characters.
+//! Use [`glyph()`] to get the glyph of any Unicode character,
+//! which also covers the rest of the Latin-1 Supplement block
+//! and shows characters without a glyph as a box.
+
 #![no_std]
 extern crate spin;
 
+mod unicode;
+
 /// The width of a character.
 pub const CHARACTER_WIDTH: usize = 9;
 /// The height of a character.
 pub const CHARACTER_HEIGHT: usize = 16;
 
+/// The glyph shown for characters that the font doesn't cover.
+pub static REPLACEMENT_GLYPH: [u8; CHARACTER_HEIGHT] = [
+    0x00, 0x00, 0xFE, 0x82, 0x82, 0x82, 0x82, 0x82, 0x82, 0x82, 0x82, 0x82, 0x82, 0xFE, 0x00,
+    0x00,
+];
+
+/// Returns the glyph of the given character.
+///
+/// ASCII characters, including control characters, are shown with the glyph at the same index
+/// of [`FONT_BASIC`], like the VGA text mode does.
+/// Characters that the font doesn't cover are shown with the [`REPLACEMENT_GLYPH`].
+pub fn glyph(c: char) -> &'static [u8; CHARACTER_HEIGHT] {
+    if c.is_ascii() {
+        return &FONT_BASIC[c as usize];
+    }
+    if let Ok(i) = unicode::CP437.binary_search_by_key(&c, |&(ch, _)| ch) {
+        return &FONT_BASIC[unicode::CP437[i].1 as usize];
+    }
+    match unicode::LATIN_1.binary_search_by_key(&c, |&(ch, _)| ch) {
+        Ok(i) => &unicode::LATIN_1[i].1,
+        Err(_) => &REPLACEMENT_GLYPH,
+    }
+}
+
+/// Returns whether the given character is a line-drawing or block character
+/// whose glyph should touch the glyphs next to it.
+///
+/// The font's glyphs are only 8 pixels wide, so a one-pixel gap is left between characters.
+/// For these characters, that gap should be filled in with the glyph's leftmost column instead.
+pub fn is_line_drawing(c: char) -> bool {
+    ('\u{2500}'..='\u{259F}').contains(&c)
+}
+
 // Copied from: https://github.com/goto456/linux-2.6.26/blob/6def53aec8e32bb58a3b50c52a8fb7c48ebef012/arch/ppc/boot/include/iso_font.h
 /// The bitmap array of characters.
 pub static FONT_BASIC: [[u8; CHARACTER_HEIGHT]; 256] = [
@@ -1034,3 +1077,27 @@ pub static FONT_BASIC: [[u8; CHARACTER_HEIGHT]; 256] = [
         0x00,
     ],
 ];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tables_are_sorted() {
+        assert!(unicode::CP437.windows(2).all(|w| w[0].0 < w[1].0));
+        assert!(unicode::LATIN_1.windows(2).all(|w| w[0].0 < w[1].0));
+    }
+
+    #[test]
+    fn glyphs() {
+        assert_eq!(glyph('A'), &FONT_BASIC[0x41]);
+        assert_eq!(glyph('é'), &FONT_BASIC[0x82]);
+        assert_eq!(glyph('─'), &FONT_BASIC[0xC4]);
+        // A heavy line is shown as a light line.
+        assert_eq!(glyph('━'), &FONT_BASIC[0xC4]);
+        assert_ne!(glyph('Ó'), &REPLACEMENT_GLYPH);
+        assert_eq!(glyph('€'), &REPLACEMENT_GLYPH);
+        // Every Latin-1 Supplement character has a glyph of its own.
+        assert!(('\u{A0}'..='\u{FF}').all(|c| glyph(c) != &REPLACEMENT_GLYPH));
+    }
+}
@@ -0,0 +1,618 @@
+//! Tables that map Unicode characters beyond ASCII to glyphs.
+//!
+//! These were generated from the Unicode names of the characters;
+//! the glyphs of accented letters combine the glyph of the base letter with an accent.
+
+use CHARACTER_HEIGHT;
+
+/// The Unicode characters that are shown with a glyph of [`FONT_BASIC`](crate::FONT_BASIC),
+/// sorted by character so they can be binary searched.
+///
+/// This covers every character of code page 437, which [`FONT_BASIC`](crate::FONT_BASIC) follows,
+/// plus stand-ins for the box-drawing characters that code page 437 lacks,
+/// e.g., heavy and rounded lines are shown as light lines.
+pub(crate) static CP437: [(char, u8); 278] = [
+    ('\u{A0}', 0xFF), // no-break space
+    ('\u{A1}', 0xAD), // inverted exclamation mark
+    ('\u{A2}', 0x9B), // cent sign
+    ('\u{A3}', 0x9C), // pound sign
+    ('\u{A5}', 0x9D), // yen sign
+    ('\u{A7}', 0x15), // section sign
+    ('\u{AA}', 0xA6), // feminine ordinal indicator
+    ('\u{AB}', 0xAE), // left-pointing double angle quotation mark
+    ('\u{AC}', 0xAA), // not sign
+    ('\u{AD}', 0x2D), // soft hyphen
+    ('\u{B0}', 0xF8), // degree sign
+    ('\u{B1}', 0xF1), // plus-minus sign
+    ('\u{B2}', 0xFD), // superscript two
+    ('\u{B5}', 0xE6), // micro sign
+    ('\u{B6}', 0x14), // pilcrow sign
+    ('\u{B7}', 0xFA), // middle dot
+    ('\u{BA}', 0xA7), // masculine ordinal indicator
+    ('\u{BB}', 0xAF), // right-pointing double angle quotation mark
+    ('\u{BC}', 0xAC), // vulgar fraction one quarter
+    ('\u{BD}', 0xAB), // vulgar fraction one half
+    ('\u{BF}', 0xA8), // inverted question mark
+    ('\u{C4}', 0x8E), // latin capital letter a with diaeresis
+    ('\u{C5}', 0x8F), // latin capital letter a with ring above
+    ('\u{C6}', 0x92), // latin capital letter ae
+    ('\u{C7}', 0x80), // latin capital letter c with cedilla
+    ('\u{C9}', 0x90), // latin capital letter e with acute
+    ('\u{D1}', 0xA5), // latin capital letter n with tilde
+    ('\u{D6}', 0x99), // latin capital letter o with diaeresis
+    ('\u{DC}', 0x9A), // latin capital letter u with diaeresis
+    ('\u{DF}', 0xE1), // latin small letter sharp s
+    ('\u{E0}', 0x85), // latin small letter a with grave
+    ('\u{E1}', 0xA0), // latin small letter a with acute
+    ('\u{E2}', 0x83), // latin small letter a with circumflex
+    ('\u{E4}', 0x84), // latin small letter a with diaeresis
+    ('\u{E5}', 0x86), // latin small letter a with ring above
+    ('\u{E6}', 0x91), // latin small letter ae
+    ('\u{E7}', 0x87), // latin small letter c with cedilla
+    ('\u{E8}', 0x8A), // latin small letter e with grave
+    ('\u{E9}', 0x82), // latin small letter e with acute
+    ('\u{EA}', 0x88), // latin small letter e with circumflex
+    ('\u{EB}', 0x89), // latin small letter e with diaeresis
+    ('\u{EC}', 0x8D), // latin small letter i with grave
+    ('\u{ED}', 0xA1), // latin small letter i with acute
+    ('\u{EE}', 0x8C), // latin small letter i with circumflex
+    ('\u{EF}', 0x8B), // latin small letter i with diaeresis
+    ('\u{F1}', 0xA4), // latin small letter n with tilde
+    ('\u{F2}', 0x95), // latin small letter o with grave
+    ('\u{F3}', 0xA2), // latin small letter o with acute
+    ('\u{F4}', 0x93), // latin small letter o with circumflex
+    ('\u{F6}', 0x94), // latin small letter o with diaeresis
+    ('\u{F7}', 0xF6), // division sign
+    ('\u{F9}', 0x97), // latin small letter u with grave
+    ('\u{FA}', 0xA3), // latin small letter u with acute
+    ('\u{FB}', 0x96), // latin small letter u with circumflex
+    ('\u{FC}', 0x81), // latin small letter u with diaeresis
+    ('\u{FF}', 0x98), // latin small letter y with diaeresis
+    ('\u{192}', 0x9F), // latin small letter f with hook
+    ('\u{393}', 0xE2), // greek capital letter gamma
+    ('\u{398}', 0xE9), // greek capital letter theta
+    ('\u{3A3}', 0xE4), // greek capital letter sigma
+    ('\u{3A6}', 0xE8), // greek capital letter phi
+    ('\u{3A9}', 0xEA), // greek capital letter omega
+    ('\u{3B1}', 0xE0), // greek small letter alpha
+    ('\u{3B2}', 0xE1), // greek small letter beta
+    ('\u{3B4}', 0xEB), // greek small letter delta
+    ('\u{3B5}', 0xEE), // greek small letter epsilon
+    ('\u{3BC}', 0xE6), // greek small letter mu
+    ('\u{3C0}', 0xE3), // greek small letter pi
+    ('\u{3C3}', 0xE5), // greek small letter sigma
+    ('\u{3C4}', 0xE7), // greek small letter tau
+    ('\u{3C6}', 0xED), // greek small letter phi
+    ('\u{2022}', 0x07), // bullet
+    ('\u{203C}', 0x13), // double exclamation mark
+    ('\u{207F}', 0xFC), // superscript latin small letter n
+    ('\u{20A7}', 0x9E), // peseta sign
+    ('\u{2190}', 0x1B), // leftwards arrow
+    ('\u{2191}', 0x18), // upwards arrow
+    ('\u{2192}', 0x1A), // rightwards arrow
+    ('\u{2193}', 0x19), // downwards arrow
+    ('\u{2194}', 0x1D), // left right arrow
+    ('\u{2195}', 0x12), // up down arrow
+    ('\u{21A8}', 0x17), // up down arrow with base
+    ('\u{2205}', 0xED), // empty set
+    ('\u{2208}', 0xEE), // element of
+    ('\u{2211}', 0xE4), // n-ary summation
+    ('\u{2219}', 0xF9), // bullet operator
+    ('\u{221A}', 0xFB), // square root
+    ('\u{221E}', 0xEC), // infinity
+    ('\u{221F}', 0x1C), // right angle
+    ('\u{2229}', 0xEF), // intersection
+    ('\u{2248}', 0xF7), // almost equal to
+    ('\u{2261}', 0xF0), // identical to
+    ('\u{2264}', 0xF3), // less-than or equal to
+    ('\u{2265}', 0xF2), // greater-than or equal to
+    ('\u{2302}', 0x7F), // house
+    ('\u{2310}', 0xA9), // reversed not sign
+    ('\u{2320}', 0xF4), // top half integral
+    ('\u{2321}', 0xF5), // bottom half integral
+    ('\u{2500}', 0xC4), // box drawings light horizontal
+    ('\u{2501}', 0xC4), // box drawings heavy horizontal
+    ('\u{2502}', 0xB3), // box drawings light vertical
+    ('\u{2503}', 0xB3), // box drawings heavy vertical
+    ('\u{2504}', 0xC4), // box drawings light triple dash horizontal
+    ('\u{2505}', 0xC4), // box drawings heavy triple dash horizontal
+    ('\u{2506}', 0xB3), // box drawings light triple dash vertical
+    ('\u{2507}', 0xB3), // box drawings heavy triple dash vertical
+    ('\u{2508}', 0xC4), // box drawings light quadruple dash horizontal
+    ('\u{2509}', 0xC4), // box drawings heavy quadruple dash horizontal
+    ('\u{250A}', 0xB3), // box drawings light quadruple dash vertical
+    ('\u{250B}', 0xB3), // box drawings heavy quadruple dash vertical
+    ('\u{250C}', 0xDA), // box drawings light down and right
+    ('\u{250D}', 0xDA), // box drawings down light and right heavy
+    ('\u{250E}', 0xDA), // box drawings down heavy and right light
+    ('\u{250F}', 0xDA), // box drawings heavy down and right
+    ('\u{2510}', 0xBF), // box drawings light down and left
+    ('\u{2511}', 0xBF), // box drawings down light and left heavy
+    ('\u{2512}', 0xBF), // box drawings down heavy and left light
+    ('\u{2513}', 0xBF), // box drawings heavy down and left
+    ('\u{2514}', 0xC0), // box drawings light up and right
+    ('\u{2515}', 0xC0), // box drawings up light and right heavy
+    ('\u{2516}', 0xC0), // box drawings up heavy and right light
+    ('\u{2517}', 0xC0), // box drawings heavy up and right
+    ('\u{2518}', 0xD9), // box drawings light up and left
+    ('\u{2519}', 0xD9), // box drawings up light and left heavy
+    ('\u{251A}', 0xD9), // box drawings up heavy and left light
+    ('\u{251B}', 0xD9), // box drawings heavy up and left
+    ('\u{251C}', 0xC3), // box drawings light vertical and right
+    ('\u{251D}', 0xC3), // box drawings vertical light and right heavy
+    ('\u{251E}', 0xC3), // box drawings up heavy and right down light
+    ('\u{251F}', 0xC3), // box drawings down heavy and right up light
+    ('\u{2520}', 0xC3), // box drawings vertical heavy and right light
+    ('\u{2521}', 0xC3), // box drawings down light and right up heavy
+    ('\u{2522}', 0xC3), // box drawings up light and right down heavy
+    ('\u{2523}', 0xC3), // box drawings heavy vertical and right
+    ('\u{2524}', 0xB4), // box drawings light vertical and left
+    ('\u{2525}', 0xB4), // box drawings vertical light and left heavy
+    ('\u{2526}', 0xB4), // box drawings up heavy and left down light
+    ('\u{2527}', 0xB4), // box drawings down heavy and left up light
+    ('\u{2528}', 0xB4), // box drawings vertical heavy and left light
+    ('\u{2529}', 0xB4), // box drawings down light and left up heavy
+    ('\u{252A}', 0xB4), // box drawings up light and left down heavy
+    ('\u{252B}', 0xB4), // box drawings heavy vertical and left
+    ('\u{252C}', 0xC2), // box drawings light down and horizontal
+    ('\u{252D}', 0xC2), // box drawings left heavy and right down light
+    ('\u{252E}', 0xC2), // box drawings right heavy and left down light
+    ('\u{252F}', 0xC2), // box drawings down light and horizontal heavy
+    ('\u{2530}', 0xC2), // box drawings down heavy and horizontal light
+    ('\u{2531}', 0xC2), // box drawings right light and left down heavy
+    ('\u{2532}', 0xC2), // box drawings left light and right down heavy
+    ('\u{2533}', 0xC2), // box drawings heavy down and horizontal
+    ('\u{2534}', 0xC1), // box drawings light up and horizontal
+    ('\u{2535}', 0xC1), // box drawings left heavy and right up light
+    ('\u{2536}', 0xC1), // box drawings right heavy and left up light
+    ('\u{2537}', 0xC1), // box drawings up light and horizontal heavy
+    ('\u{2538}', 0xC1), // box drawings up heavy and horizontal light
+    ('\u{2539}', 0xC1), // box drawings right light and left up heavy
+    ('\u{253A}', 0xC1), // box drawings left light and right up heavy
+    ('\u{253B}', 0xC1), // box drawings heavy up and horizontal
+    ('\u{253C}', 0xC5), // box drawings light vertical and horizontal
+    ('\u{253D}', 0xC5), // box drawings left heavy and right vertical light
+    ('\u{253E}', 0xC5), // box drawings right heavy and left vertical light
+    ('\u{253F}', 0xC5), // box drawings vertical light and horizontal heavy
+    ('\u{2540}', 0xC5), // box drawings up heavy and down horizontal light
+    ('\u{2541}', 0xC5), // box drawings down heavy and up horizontal light
+    ('\u{2542}', 0xC5), // box drawings vertical heavy and horizontal light
+    ('\u{2543}', 0xC5), // box drawings left up heavy and right down light
+    ('\u{2544}', 0xC5), // box drawings right up heavy and left down light
+    ('\u{2545}', 0xC5), // box drawings left down heavy and right up light
+    ('\u{2546}', 0xC5), // box drawings right down heavy and left up light
+    ('\u{2547}', 0xC5), // box drawings down light and up horizontal heavy
+    ('\u{2548}', 0xC5), // box drawings up light and down horizontal heavy
+    ('\u{2549}', 0xC5), // box drawings right light and left vertical heavy
+    ('\u{254A}', 0xC5), // box drawings left light and right vertical heavy
+    ('\u{254B}', 0xC5), // box drawings heavy vertical and horizontal
+    ('\u{254C}', 0xC4), // box drawings light double dash horizontal
+    ('\u{254D}', 0xC4), // box drawings heavy double dash horizontal
+    ('\u{254E}', 0xB3), // box drawings light double dash vertical
+    ('\u{254F}', 0xB3), // box drawings heavy double dash vertical
+    ('\u{2550}', 0xCD), // box drawings double horizontal
+    ('\u{2551}', 0xBA), // box drawings double vertical
+    ('\u{2552}', 0xD5), // box drawings down single and right double
+    ('\u{2553}', 0xD6), // box drawings down double and right single
+    ('\u{2554}', 0xC9), // box drawings double down and right
+    ('\u{2555}', 0xB8), // box drawings down single and left double
+    ('\u{2556}', 0xB7), // box drawings down double and left single
+    ('\u{2557}', 0xBB), // box drawings double down and left
+    ('\u{2558}', 0xD4), // box drawings up single and right double
+    ('\u{2559}', 0xD3), // box drawings up double and right single
+    ('\u{255A}', 0xC8), // box drawings double up and right
+    ('\u{255B}', 0xBE), // box drawings up single and left double
+    ('\u{255C}', 0xBD), // box drawings up double and left single
+    ('\u{255D}', 0xBC), // box drawings double up and left
+    ('\u{255E}', 0xC6), // box drawings vertical single and right double
+    ('\u{255F}', 0xC7), // box drawings vertical double and right single
+    ('\u{2560}', 0xCC), // box drawings double vertical and right
+    ('\u{2561}', 0xB5), // box drawings vertical single and left double
+    ('\u{2562}', 0xB6), // box drawings vertical double and left single
+    ('\u{2563}', 0xB9), // box drawings double vertical and left
+    ('\u{2564}', 0xD1), // box drawings down single and horizontal double
+    ('\u{2565}', 0xD2), // box drawings down double and horizontal single
+    ('\u{2566}', 0xCB), // box drawings double down and horizontal
+    ('\u{2567}', 0xCF), // box drawings up single and horizontal double
+    ('\u{2568}', 0xD0), // box drawings up double and horizontal single
+    ('\u{2569}', 0xCA), // box drawings double up and horizontal
+    ('\u{256A}', 0xD8), // box drawings vertical single and horizontal double
+    ('\u{256B}', 0xD7), // box drawings vertical double and horizontal single
+    ('\u{256C}', 0xCE), // box drawings double vertical and horizontal
+    ('\u{256D}', 0xDA), // box drawings light arc down and right
+    ('\u{256E}', 0xBF), // box drawings light arc down and left
+    ('\u{256F}', 0xD9), // box drawings light arc up and left
+    ('\u{2570}', 0xC0), // box drawings light arc up and right
+    ('\u{2571}', 0x2F), // box drawings light diagonal upper right to lower left
+    ('\u{2572}', 0x5C), // box drawings light diagonal upper left to lower right
+    ('\u{2573}', 0x58), // box drawings light diagonal cross
+    ('\u{2574}', 0xC4), // box drawings light left
+    ('\u{2575}', 0xB3), // box drawings light up
+    ('\u{2576}', 0xC4), // box drawings light right
+    ('\u{2577}', 0xB3), // box drawings light down
+    ('\u{2578}', 0xC4), // box drawings heavy left
+    ('\u{2579}', 0xB3), // box drawings heavy up
+    ('\u{257A}', 0xC4), // box drawings heavy right
+    ('\u{257B}', 0xB3), // box drawings heavy down
+    ('\u{257C}', 0xC4), // box drawings light left and heavy right
+    ('\u{257D}', 0xB3), // box drawings light up and heavy down
+    ('\u{257E}', 0xC4), // box drawings heavy left and light right
+    ('\u{257F}', 0xB3), // box drawings heavy up and light down
+    ('\u{2580}', 0xDF), // upper half block
+    ('\u{2581}', 0xDC), // lower one eighth block
+    ('\u{2582}', 0xDC), // lower one quarter block
+    ('\u{2583}', 0xDC), // lower three eighths block
+    ('\u{2584}', 0xDC), // lower half block
+    ('\u{2585}', 0xDC), // lower five eighths block
+    ('\u{2586}', 0xDB), // lower three quarters block
+    ('\u{2587}', 0xDB), // lower seven eighths block
+    ('\u{2588}', 0xDB), // full block
+    ('\u{2589}', 0xDB), // left seven eighths block
+    ('\u{258A}', 0xDB), // left three quarters block
+    ('\u{258B}', 0xDD), // left five eighths block
+    ('\u{258C}', 0xDD), // left half block
+    ('\u{258D}', 0xDD), // left three eighths block
+    ('\u{258E}', 0xDD), // left one quarter block
+    ('\u{258F}', 0xDD), // left one eighth block
+    ('\u{2590}', 0xDE), // right half block
+    ('\u{2591}', 0xB0), // light shade
+    ('\u{2592}', 0xB1), // medium shade
+    ('\u{2593}', 0xB2), // dark shade
+    ('\u{2594}', 0xDF), // upper one eighth block
+    ('\u{2595}', 0xDE), // right one eighth block
+    ('\u{2596}', 0xDC), // quadrant lower left
+    ('\u{2597}', 0xDC), // quadrant lower right
+    ('\u{2598}', 0xDF), // quadrant upper left
+    ('\u{2599}', 0xDB), // quadrant upper left and lower left and lower right
+    ('\u{259A}', 0xB1), // quadrant upper left and lower right
+    ('\u{259B}', 0xDB), // quadrant upper left and upper right and lower left
+    ('\u{259C}', 0xDB), // quadrant upper left and upper right and lower right
+    ('\u{259D}', 0xDF), // quadrant upper right
+    ('\u{259E}', 0xB1), // quadrant upper right and lower left
+    ('\u{259F}', 0xDB), // quadrant upper right and lower left and lower right
+    ('\u{25A0}', 0xFE), // black square
+    ('\u{25AC}', 0x16), // black rectangle
+    ('\u{25B2}', 0x1E), // black up-pointing triangle
+    ('\u{25BA}', 0x10), // black right-pointing pointer
+    ('\u{25BC}', 0x1F), // black down-pointing triangle
+    ('\u{25C4}', 0x11), // black left-pointing pointer
+    ('\u{25CB}', 0x09), // white circle
+    ('\u{25D8}', 0x08), // inverse bullet
+    ('\u{25D9}', 0x0A), // inverse white circle
+    ('\u{263A}', 0x01), // white smiling face
+    ('\u{263B}', 0x02), // black smiling face
+    ('\u{263C}', 0x0F), // white sun with rays
+    ('\u{2640}', 0x0C), // female sign
+    ('\u{2642}', 0x0B), // male sign
+    ('\u{2660}', 0x06), // black spade suit
+    ('\u{2663}', 0x05), // black club suit
+    ('\u{2665}', 0x03), // black heart suit
+    ('\u{2666}', 0x04), // black diamond suit
+    ('\u{266A}', 0x0D), // eighth note
+    ('\u{266B}', 0x0E), // beamed eighth notes
+];
+
+/// Glyphs for the Latin-1 Supplement characters that code page 437 lacks,
+/// sorted by character so they can be binary searched.
+pub(crate) static LATIN_1: [(char, [u8; CHARACTER_HEIGHT]); 40] = [
+    // currency sign
+    (
+        '\u{A4}',
+        [
+            0x00, 0x00, 0x00, 0xC6, 0x7C, 0x6C, 0x6C, 0x7C, 0xC6, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00,
+        ],
+    ),
+    // broken bar
+    (
+        '\u{A6}',
+        [
+            0x00, 0x00, 0x18, 0x18, 0x18, 0x18, 0x00, 0x00, 0x18, 0x18, 0x18, 0x18, 0x00, 0x00,
+            0x00, 0x00,
+        ],
+    ),
+    // diaeresis
+    (
+        '\u{A8}',
+        [
+            0x00, 0xCC, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00,
+        ],
+    ),
+    // copyright sign
+    (
+        '\u{A9}',
+        [
+            0x00, 0x00, 0x3C, 0x42, 0x99, 0xA1, 0xA1, 0xA1, 0x99, 0x42, 0x3C, 0x00, 0x00, 0x00,
+            0x00, 0x00,
+        ],
+    ),
+    // registered sign
+    (
+        '\u{AE}',
+        [
+            0x00, 0x00, 0x3C, 0x42, 0xB9, 0xA5, 0xB9, 0xA9, 0xA5, 0x42, 0x3C, 0x00, 0x00, 0x00,
+            0x00, 0x00,
+        ],
+    ),
+    // macron
+    (
+        '\u{AF}',
+        [
+            0x00, 0xFE, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00,
+        ],
+    ),
+    // superscript three
+    (
+        '\u{B3}',
+        [
+            0x00, 0x70, 0x18, 0x70, 0x18, 0x70, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00,
+        ],
+    ),
+    // acute accent
+    (
+        '\u{B4}',
+        [
+            0x00, 0x0C, 0x18, 0x30, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00,
+        ],
+    ),
+    // cedilla
+    (
+        '\u{B8}',
+        [
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x18, 0x0C,
+            0x38, 0x00,
+        ],
+    ),
+    // superscript one
+    (
+        '\u{B9}',
+        [
+            0x00, 0x30, 0x70, 0x30, 0x30, 0x78, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00,
+        ],
+    ),
+    // vulgar fraction three quarters
+    (
+        '\u{BE}',
+        [
+            0x70, 0x18, 0x70, 0x19, 0x73, 0x06, 0x0C, 0x18, 0x33, 0x67, 0xCF, 0x1B, 0x1F, 0x03,
+            0x03, 0x00,
+        ],
+    ),
+    // latin capital letter a with grave
+    (
+        '\u{C0}',
+        [
+            0x60, 0x30, 0x00, 0x10, 0x38, 0x6C, 0xC6, 0xFE, 0xC6, 0xC6, 0xC6, 0xC6, 0x00, 0x00,
+            0x00, 0x00,
+        ],
+    ),
+    // latin capital letter a with acute
+    (
+        '\u{C1}',
+        [
+            0x0C, 0x18, 0x00, 0x10, 0x38, 0x6C, 0xC6, 0xFE, 0xC6, 0xC6, 0xC6, 0xC6, 0x00, 0x00,
+            0x00, 0x00,
+        ],
+    ),
+    // latin capital letter a with circumflex
+    (
+        '\u{C2}',
+        [
+            0x38, 0x6C, 0x00, 0x10, 0x38, 0x6C, 0xC6, 0xFE, 0xC6, 0xC6, 0xC6, 0xC6, 0x00, 0x00,
+            0x00, 0x00,
+        ],
+    ),
+    // latin capital letter a with tilde
+    (
+        '\u{C3}',
+        [
+            0x76, 0xDC, 0x00, 0x10, 0x38, 0x6C, 0xC6, 0xFE, 0xC6, 0xC6, 0xC6, 0xC6, 0x00, 0x00,
+            0x00, 0x00,
+        ],
+    ),
+    // latin capital letter e with grave
+    (
+        '\u{C8}',
+        [
+            0x60, 0x30, 0x00, 0xFE, 0x66, 0x68, 0x78, 0x68, 0x60, 0x62, 0x66, 0xFE, 0x00, 0x00,
+            0x00, 0x00,
+        ],
+    ),
+    // latin capital letter e with circumflex
+    (
+        '\u{CA}',
+        [
+            0x38, 0x6C, 0x00, 0xFE, 0x66, 0x68, 0x78, 0x68, 0x60, 0x62, 0x66, 0xFE, 0x00, 0x00,
+            0x00, 0x00,
+        ],
+    ),
+    // latin capital letter e with diaeresis
+    (
+        '\u{CB}',
+        [
+            0x00, 0x6C, 0x00, 0xFE, 0x66, 0x68, 0x78, 0x68, 0x60, 0x62, 0x66, 0xFE, 0x00, 0x00,
+            0x00, 0x00,
+        ],
+    ),
+    // latin capital letter i with grave
+    (
+        '\u{CC}',
+        [
+            0x60, 0x30, 0x00, 0x3C, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x3C, 0x00, 0x00,
+            0x00, 0x00,
+        ],
+    ),
+    // latin capital letter i with acute
+    (
+        '\u{CD}',
+        [
+            0x0C, 0x18, 0x00, 0x3C, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x3C, 0x00, 0x00,
+            0x00, 0x00,
+        ],
+    ),
+    // latin capital letter i with circumflex
+    (
+        '\u{CE}',
+        [
+            0x38, 0x6C, 0x00, 0x3C, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x3C, 0x00, 0x00,
+            0x00, 0x00,
+        ],
+    ),
+    // latin capital letter i with diaeresis
+    (
+        '\u{CF}',
+        [
+            0x00, 0x6C, 0x00, 0x3C, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x3C, 0x00, 0x00,
+            0x00, 0x00,
+        ],
+    ),
+    // latin capital letter eth
+    (
+        '\u{D0}',
+        [
+            0x00, 0x00, 0xF8, 0x6C, 0x66, 0x66, 0xF6, 0x66, 0x66, 0x66, 0x6C, 0xF8, 0x00, 0x00,
+            0x00, 0x00,
+        ],
+    ),
+    // latin capital letter o with grave
+    (
+        '\u{D2}',
+        [
+            0x60, 0x30, 0x00, 0x7C, 0xC6, 0xC6, 0xC6, 0xC6, 0xC6, 0xC6, 0xC6, 0x7C, 0x00, 0x00,
+            0x00, 0x00,
+        ],
+    ),
+    // latin capital letter o with acute
+    (
+        '\u{D3}',
+        [
+            0x0C, 0x18, 0x00, 0x7C, 0xC6, 0xC6, 0xC6, 0xC6, 0xC6, 0xC6, 0xC6, 0x7C, 0x00, 0x00,
+            0x00, 0x00,
+        ],
+    ),
+    // latin capital letter o with circumflex
+    (
+        '\u{D4}',
+        [
+            0x38, 0x6C, 0x00, 0x7C, 0xC6, 0xC6, 0xC6, 0xC6, 0xC6, 0xC6, 0xC6, 0x7C, 0x00, 0x00,
+            0x00, 0x00,
+        ],
+    ),
+    // latin capital letter o with tilde
+    (
+        '\u{D5}',
+        [
+            0x76, 0xDC, 0x00, 0x7C, 0xC6, 0xC6, 0xC6, 0xC6, 0xC6, 0xC6, 0xC6, 0x7C, 0x00, 0x00,
+            0x00, 0x00,
+        ],
+    ),
+    // multiplication sign
+    (
+        '\u{D7}',
+        [
+            0x00, 0x00, 0x00, 0x00, 0x00, 0xC6, 0x6C, 0x38, 0x38, 0x6C, 0xC6, 0x00, 0x00, 0x00,
+            0x00, 0x00,
+        ],
+    ),
+    // latin capital letter o with stroke
+    (
+        '\u{D8}',
+        [
+            0x00, 0x00, 0x7D, 0xC6, 0xCE, 0xDE, 0xD6, 0xF6, 0xE6, 0xC6, 0xC6, 0xBE, 0x00, 0x00,
+            0x00, 0x00,
+        ],
+    ),
+    // latin capital letter u with grave
+    (
+        '\u{D9}',
+        [
+            0x60, 0x30, 0x00, 0xC6, 0xC6, 0xC6, 0xC6, 0xC6, 0xC6, 0xC6, 0xC6, 0x7C, 0x00, 0x00,
+            0x00, 0x00,
+        ],
+    ),
+    // latin capital letter u with acute
+    (
+        '\u{DA}',
+        [
+            0x0C, 0x18, 0x00, 0xC6, 0xC6, 0xC6, 0xC6, 0xC6, 0xC6, 0xC6, 0xC6, 0x7C, 0x00, 0x00,
+            0x00, 0x00,
+        ],
+    ),
+    // latin capital letter u with circumflex
+    (
+        '\u{DB}',
+        [
+            0x38, 0x6C, 0x00, 0xC6, 0xC6, 0xC6, 0xC6, 0xC6, 0xC6, 0xC6, 0xC6, 0x7C, 0x00, 0x00,
+            0x00, 0x00,
+        ],
+    ),
+    // latin capital letter y with acute
+    (
+        '\u{DD}',
+        [
+            0x0C, 0x18, 0x00, 0x66, 0x66, 0x66, 0x3C, 0x18, 0x18, 0x18, 0x18, 0x3C, 0x00, 0x00,
+            0x00, 0x00,
+        ],
+    ),
+    // latin capital letter thorn
+    (
+        '\u{DE}',
+        [
+            0x00, 0x00, 0xF0, 0x60, 0x7C, 0x66, 0x66, 0x66, 0x7C, 0x60, 0x60, 0xF0, 0x00, 0x00,
+            0x00, 0x00,
+        ],
+    ),
+    // latin small letter a with tilde
+    (
+        '\u{E3}',
+        [
+            0x00, 0x00, 0x76, 0xDC, 0x00, 0x78, 0x0C, 0x7C, 0xCC, 0xCC, 0xCC, 0x76, 0x00, 0x00,
+            0x00, 0x00,
+        ],
+    ),
+    // latin small letter eth
+    (
+        '\u{F0}',
+        [
+            0x00, 0x00, 0x6C, 0x38, 0x6C, 0x0C, 0x7E, 0xC6, 0xC6, 0xC6, 0xC6, 0x7C, 0x00, 0x00,
+            0x00, 0x00,
+        ],
+    ),
+    // latin small letter o with tilde
+    (
+        '\u{F5}',
+        [
+            0x00, 0x00, 0x76, 0xDC, 0x00, 0x7C, 0xC6, 0xC6, 0xC6, 0xC6, 0xC6, 0x7C, 0x00, 0x00,
+            0x00, 0x00,
+        ],
+    ),
+    // latin small letter o with stroke
+    (
+        '\u{F8}',
+        [
+            0x00, 0x00, 0x00, 0x00, 0x01, 0x7C, 0xCE, 0xD6, 0xF6, 0xE6, 0xC6, 0xBE, 0x00, 0x00,
+            0x00, 0x00,
+        ],
+    ),
+    // latin small letter y with acute
+    (
+        '\u{FD}',
+        [
+            0x00, 0x00, 0x0C, 0x18, 0x00, 0xC6, 0xC6, 0xC6, 0xC6, 0xC6, 0xC6, 0x7E, 0x06, 0x0C,
+            0xF8, 0x00,
+        ],
+    ),
+    // latin small letter thorn
+    (
+        '\u{FE}',
+        [
+            0x00, 0x00, 0xE0, 0x60, 0x60, 0x7C, 0x66, 0x66, 0x66, 0x66, 0x66, 0x7C, 0x60, 0x60,
+            0xF0, 0x00,
+        ],
+    ),
+];
@@ -0,0 +1,447 @@
+//! Management of extended processor state, i.e., the x87 FPU, SSE, and AVX registers.
+//!
+//! Each task owns an [`FpuState`], which holds an XSAVE area sized according to
+//! the state components supported by the CPU (from CPUID leaf 0xD).
+//! If the CPU doesn't support XSAVE, the legacy 512-byte FXSAVE format is used instead.
+//!
+//! There are two modes of saving and restoring this state across context switches,
+//! one of which is selected at boot time via [`init()`], e.g., from the `fpu=eager`
+//! or `fpu=lazy` kernel command line option (see [`FpuMode::parse()`]):
+//! * [`FpuMode::Eager`]: the state of every task is saved when it is switched out
+//!   and restored when it is switched in.
+//! * [`FpuMode::Lazy`]: upon a context switch, the `CR0.TS` bit is set such that
+//!   the next task's first use of an FPU/SIMD instruction triggers a
+//!   Device Not Available (`#NM`) exception, whose handler ([`handle_device_not_available()`])
+//!   restores that task's state. A task's state is only saved if it actually used
+//!   the FPU during its timeslice, so tasks that never use the FPU cost nothing extra.
+//!
+//! Each task's save area is allocated when the task is created,
+//! such that neither a context switch nor a `#NM` exception ever allocates memory.
+//!
+//! Theseus kernel code is compiled without SIMD support, so it never touches these registers.
+//! Kernel code that wishes to use SIMD instructions (e.g., for crypto or blitting)
+//! must do so only while holding a guard returned by [`kernel_fpu_begin()`].
+//!
+//! This crate must *not* be initialized if the context switch routine itself
+//! saves and restores SIMD registers, i.e., in SSE or AVX builds of Theseus
+//! (see `context_switch::SAVES_SIMD_REGISTERS`), as that routine runs after [`switch()`]
+//! and would otherwise clobber this crate's state or fault on a set `CR0.TS` bit.
+//! If uninitialized, all functions in this crate are no-ops.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::sync::Arc;
+use core::{alloc::Layout, fmt, ptr::NonNull};
+use cpu_features::Feature;
+use preemption::PreemptionGuard;
+use spin::{Mutex, Once};
+
+/// How extended processor state is saved and restored across context switches.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FpuMode {
+    /// Always save the previous task's state and restore the next task's state.
+    Eager,
+    /// Defer restoring a task's state until it first uses the FPU,
+    /// and only save its state if it used the FPU.
+    Lazy,
+}
+
+impl FpuMode {
+    /// Parses an FPU mode from the value of a kernel command line option,
+    /// i.e., either `"eager"` or `"lazy"`.
+    pub fn parse(value: &str) -> Option<FpuMode> {
+        match value {
+            "eager" => Some(FpuMode::Eager),
+            "lazy" => Some(FpuMode::Lazy),
+            _ => None,
+        }
+    }
+}
+
+impl Default for FpuMode {
+    /// Lazy mode is the default, as Theseus kernel code doesn't use the FPU,
+    /// so most tasks never need their FPU state saved or restored.
+    fn default() -> FpuMode {
+        FpuMode::Lazy
+    }
+}
+
+/// The XSAVE-managed state components that Theseus enables in XCR0:
+/// x87, SSE, and AVX. Larger components like AVX-512 are not yet enabled.
+const XCR0_X87_SSE_AVX: u64 = 0b111;
+
+/// The required alignment of an XSAVE (or FXSAVE) area.
+const XSAVE_AREA_ALIGN: usize = 64;
+
+/// The size of the legacy FXSAVE area.
+const FXSAVE_AREA_SIZE: usize = 512;
+
+/// The offset of the MXCSR register within the legacy region of an XSAVE area.
+const MXCSR_OFFSET: usize = 24;
+
+/// The default value of the MXCSR register upon processor reset,
+/// which masks all SIMD floating-point exceptions.
+const MXCSR_DEFAULT: u32 = 0x1F80;
+
+/// The largest save area size that [`init()`] may choose: the end of the AVX component.
+const MAX_AREA_SIZE: usize = 576 + 256;
+
+/// A save area representing the initial (reset) processor state,
+/// which is restored for tasks created before [`init()`] that have no save area of their own.
+#[repr(C, align(64))]
+struct InitialArea([u8; MAX_AREA_SIZE]);
+
+static INITIAL_AREA: InitialArea = InitialArea::new();
+
+impl InitialArea {
+    /// An all-zero XSAVE header marks all components as being in their initial state,
+    /// but MXCSR is always loaded from the legacy region, so we must initialize it.
+    const fn new() -> InitialArea {
+        let mut bytes = [0; MAX_AREA_SIZE];
+        let mxcsr = MXCSR_DEFAULT.to_le_bytes();
+        let mut i = 0;
+        while i < mxcsr.len() {
+            bytes[MXCSR_OFFSET + i] = mxcsr[i];
+            i += 1;
+        }
+        InitialArea(bytes)
+    }
+}
+
+/// The configuration chosen in [`init()`].
+struct Config {
+    mode: FpuMode,
+    /// Whether XSAVE is used, as opposed to FXSAVE.
+    use_xsave: bool,
+    /// Whether the optimized XSAVEOPT instruction can be used for saving.
+    use_xsaveopt: bool,
+    /// The bitmask of state components enabled in XCR0.
+    xcr0: u64,
+    /// The size of each task's save area.
+    area_size: usize,
+}
+
+static CONFIG: Once<Config> = Once::new();
+
+/// The FPU state of the task currently running on each CPU.
+#[cls::cpu_local]
+static CURRENT_FPU_STATE: Option<Arc<FpuState>> = None;
+
+/// How many [`KernelFpuGuard`]s currently exist on each CPU.
+#[cls::cpu_local]
+static KERNEL_FPU_DEPTH: u8 = 0;
+
+/// Initializes extended state management using the given `mode`.
+///
+/// This registers a [`cpu_features`] boot-time hook that enables XSAVE on each CPU,
+/// so it must be invoked before [`cpu_features::run_hooks()`] is invoked on the BSP.
+pub fn init(mode: FpuMode) -> Result<(), &'static str> {
+    let info = cpu_features::cpu_info();
+    let use_xsave = info.features.contains(Feature::Xsave);
+    let xcr0 = if use_xsave {
+        info.xsave_supported_components & XCR0_X87_SSE_AVX
+    } else {
+        0
+    };
+    // We only enable a subset of all supported components, so we can't rely on
+    // the CPUID-reported size; instead, compute the end of the AVX component
+    // (located at offset 576, 256 bytes long) if it's enabled.
+    let area_size = if !use_xsave {
+        FXSAVE_AREA_SIZE
+    } else if xcr0 & 0b100 != 0 {
+        MAX_AREA_SIZE
+    } else {
+        // The legacy region plus the XSAVE header.
+        FXSAVE_AREA_SIZE + 64
+    };
+
+    let config = CONFIG.call_once(|| Config {
+        mode,
+        use_xsave,
+        use_xsaveopt: use_xsave && info.features.contains(Feature::XsaveOpt),
+        xcr0,
+        area_size,
+    });
+    if config.mode != mode {
+        return Err("fpu_state::init(): FPU mode was already initialized differently");
+    }
+
+    if use_xsave {
+        cpu_features::register_hook("enable XSAVE", Feature::Xsave, enable_xsave);
+    }
+    log::info!("Initialized FPU state management: {:?} mode, {} save area of {} bytes, XCR0: {:#b}",
+        mode, if use_xsave { "XSAVE" } else { "FXSAVE" }, area_size, xcr0,
+    );
+    Ok(())
+}
+
+/// Returns the FPU mode selected at boot, if [`init()`] has been invoked.
+pub fn mode() -> Option<FpuMode> {
+    CONFIG.get().map(|c| c.mode)
+}
+
+/// Enables the XSAVE feature set and the chosen state components on the current CPU.
+fn enable_xsave() -> Result<(), &'static str> {
+    let config = CONFIG.get().ok_or("fpu_state was not initialized")?;
+    #[cfg(target_arch = "x86_64")] {
+        use x86_64::registers::control::{Cr4, Cr4Flags};
+        // SAFE: XSAVE support was verified via CPUID before registering this hook.
+        unsafe {
+            Cr4::update(|flags| flags.insert(Cr4Flags::OSXSAVE));
+            arch::xsetbv(0, config.xcr0);
+        }
+    }
+    #[cfg(not(target_arch = "x86_64"))]
+    let _ = config;
+    Ok(())
+}
+
+
+/// A save area for a task's extended processor state,
+/// aligned to [`XSAVE_AREA_ALIGN`] as required by the XSAVE instructions.
+struct SaveArea {
+    ptr: NonNull<u8>,
+    layout: Layout,
+}
+
+// SAFE: a `SaveArea` exclusively owns its memory, like a `Box`.
+unsafe impl Send for SaveArea { }
+
+impl SaveArea {
+    /// Allocates a new save area that represents the initial (reset) processor state.
+    fn new(size: usize) -> Option<SaveArea> {
+        let layout = Layout::from_size_align(size, XSAVE_AREA_ALIGN).ok()?;
+        // SAFE: the layout has a non-zero size.
+        let ptr = NonNull::new(unsafe { alloc::alloc::alloc(layout) })?;
+        // SAFE: `size` is at most `MAX_AREA_SIZE`, and the regions don't overlap.
+        unsafe {
+            ptr.as_ptr().copy_from_nonoverlapping(INITIAL_AREA.0.as_ptr(), size);
+        }
+        Some(SaveArea { ptr, layout })
+    }
+}
+
+impl Drop for SaveArea {
+    fn drop(&mut self) {
+        // SAFE: this pointer was allocated with the same layout in `SaveArea::new()`.
+        unsafe { alloc::alloc::dealloc(self.ptr.as_ptr(), self.layout) }
+    }
+}
+
+
+/// The extended processor state (FPU/SIMD registers) of a single task.
+pub struct FpuState {
+    area: Mutex<Option<SaveArea>>,
+}
+
+impl fmt::Debug for FpuState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let allocated = self.area.try_lock().map(|a| a.is_some());
+        f.debug_struct("FpuState").field("allocated", &allocated).finish()
+    }
+}
+
+impl FpuState {
+    /// Creates a new `FpuState` for a new task, allocating its save area
+    /// if FPU state management has been initialized.
+    pub fn new() -> Arc<FpuState> {
+        let area = CONFIG.get().and_then(|c| SaveArea::new(c.area_size));
+        Arc::new(FpuState { area: Mutex::new(area) })
+    }
+
+    /// Returns whether this state has a save area, i.e.,
+    /// whether it can hold meaningful register values.
+    ///
+    /// Only tasks created before FPU state management was initialized lack a save area.
+    pub fn is_allocated(&self) -> bool {
+        self.area.lock().is_some()
+    }
+
+    /// Saves the current CPU's registers into this state's save area, if it has one.
+    fn save(&self, config: &Config) {
+        if let Some(area) = self.area.lock().as_mut() {
+            // SAFE: the area is properly sized and aligned, and the FPU is accessible.
+            unsafe { arch::save(area.ptr.as_ptr(), config) };
+        }
+    }
+
+    /// Loads this state's saved registers into the current CPU's registers,
+    /// or the initial processor state if it has no save area.
+    fn restore(&self, config: &Config) {
+        let area = self.area.lock();
+        let ptr = area.as_ref().map_or(INITIAL_AREA.0.as_ptr(), |a| a.ptr.as_ptr() as *const u8);
+        // SAFE: the area is properly sized, aligned, and initialized, and the FPU is accessible.
+        unsafe { arch::restore(ptr, config) };
+    }
+}
+
+
+/// Switches the extended processor state from the `prev` task to the `next` task.
+///
+/// This is invoked by the task switching routine on the current CPU,
+/// after which `next` is considered to own this CPU's FPU state.
+///
+/// This does nothing if [`init()`] wasn't invoked, which is the case in builds
+/// whose context switch routine already saves and restores SIMD registers.
+pub fn switch(prev: &Arc<FpuState>, next: &Arc<FpuState>, guard: &PreemptionGuard) {
+    let Some(config) = CONFIG.get() else { return };
+
+    match config.mode {
+        FpuMode::Eager => {
+            prev.save(config);
+            next.restore(config);
+        }
+        FpuMode::Lazy => {
+            // If TS is clear, the previous task used the FPU, so its state is live.
+            if !arch::task_switched_flag() {
+                prev.save(config);
+            }
+            arch::set_task_switched_flag(true);
+        }
+    }
+    CURRENT_FPU_STATE.set_guarded(Some(Arc::clone(next)), guard);
+}
+
+/// Handles a Device Not Available (`#NM`) exception, which occurs in lazy mode
+/// when a task uses the FPU for the first time since it was switched in.
+///
+/// This restores the current task's FPU state and clears `CR0.TS`.
+///
+/// Returns `true` if the exception was handled, or `false` if it was unexpected
+/// (e.g., lazy mode isn't enabled), in which case the caller should treat it as a fault.
+pub fn handle_device_not_available() -> bool {
+    let Some(config) = CONFIG.get() else { return false };
+    if config.mode != FpuMode::Lazy || !arch::task_switched_flag() {
+        return false;
+    }
+
+    let held_interrupts = irq_safety::hold_interrupts();
+    arch::set_task_switched_flag(false);
+    let current = CURRENT_FPU_STATE.update_guarded(|s| s.clone(), &held_interrupts);
+    match current {
+        Some(state) => {
+            state.restore(config);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Allows kernel code to safely use FPU/SIMD instructions until the returned guard is dropped.
+///
+/// This disables preemption and saves the current task's FPU state (if live)
+/// such that it is not clobbered.
+/// Upon dropping the guard, the current task's state is restored,
+/// either immediately (eager mode) or upon its next FPU use (lazy mode).
+///
+/// Nested invocations are permitted; only the outermost guard saves and restores state.
+pub fn kernel_fpu_begin() -> KernelFpuGuard {
+    let preemption_guard = preemption::hold_preemption();
+    if KERNEL_FPU_DEPTH.fetch_add(1) == 0 {
+        if let Some(config) = CONFIG.get() {
+            let live = match config.mode {
+                FpuMode::Eager => true,
+                FpuMode::Lazy => !arch::task_switched_flag(),
+            };
+            if live {
+                CURRENT_FPU_STATE.update_guarded(
+                    |s| if let Some(state) = s { state.save(config) },
+                    &preemption_guard,
+                );
+            }
+            arch::set_task_switched_flag(false);
+        }
+    }
+    KernelFpuGuard { preemption_guard }
+}
+
+/// A guard that permits kernel code to use FPU/SIMD instructions; see [`kernel_fpu_begin()`].
+pub struct KernelFpuGuard {
+    preemption_guard: PreemptionGuard,
+}
+
+impl Drop for KernelFpuGuard {
+    fn drop(&mut self) {
+        if KERNEL_FPU_DEPTH.fetch_sub(1) != 1 {
+            return;
+        }
+        let Some(config) = CONFIG.get() else { return };
+        match config.mode {
+            FpuMode::Eager => CURRENT_FPU_STATE.update_guarded(
+                |s| if let Some(state) = s { state.restore(config) },
+                &self.preemption_guard,
+            ),
+            // The next FPU use by the current task will restore its state.
+            FpuMode::Lazy => arch::set_task_switched_flag(true),
+        }
+    }
+}
+
+
+#[cfg(target_arch = "x86_64")]
+mod arch {
+    use super::Config;
+    use core::arch::asm;
+    use x86_64::registers::control::{Cr0, Cr0Flags};
+
+    /// Writes `value` to the extended control register `xcr`.
+    pub(crate) unsafe fn xsetbv(xcr: u32, value: u64) {
+        asm!(
+            "xsetbv",
+            in("ecx") xcr,
+            in("eax") value as u32,
+            in("edx") (value >> 32) as u32,
+            options(nomem, nostack),
+        );
+    }
+
+    /// Saves the current extended state into the area at `ptr`.
+    pub(crate) unsafe fn save(ptr: *mut u8, config: &Config) {
+        let (lo, hi) = (config.xcr0 as u32, (config.xcr0 >> 32) as u32);
+        if config.use_xsaveopt {
+            asm!("xsaveopt64 [{}]", in(reg) ptr, in("eax") lo, in("edx") hi, options(nostack));
+        } else if config.use_xsave {
+            asm!("xsave64 [{}]", in(reg) ptr, in("eax") lo, in("edx") hi, options(nostack));
+        } else {
+            asm!("fxsave64 [{}]", in(reg) ptr, options(nostack));
+        }
+    }
+
+    /// Loads the extended state from the area at `ptr`.
+    pub(crate) unsafe fn restore(ptr: *const u8, config: &Config) {
+        let (lo, hi) = (config.xcr0 as u32, (config.xcr0 >> 32) as u32);
+        if config.use_xsave {
+            asm!("xrstor64 [{}]", in(reg) ptr, in("eax") lo, in("edx") hi, options(nostack));
+        } else {
+            asm!("fxrstor64 [{}]", in(reg) ptr, options(nostack));
+        }
+    }
+
+    /// Returns the value of the `CR0.TS` (task switched) bit.
+    pub(crate) fn task_switched_flag() -> bool {
+        Cr0::read().contains(Cr0Flags::TASK_SWITCHED)
+    }
+
+    /// Sets or clears the `CR0.TS` (task switched) bit.
+    pub(crate) fn set_task_switched_flag(value: bool) {
+        if value {
+            // SAFE: setting TS only causes a #NM upon the next FPU use, which we handle.
+            unsafe { Cr0::update(|f| f.insert(Cr0Flags::TASK_SWITCHED)) };
+        } else {
+            // SAFE: `clts` only clears the TS bit.
+            unsafe { asm!("clts", options(nomem, nostack)) };
+        }
+    }
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+mod arch {
+    use super::Config;
+
+    pub(crate) unsafe fn save(_ptr: *mut u8, _config: &Config) { }
+    pub(crate) unsafe fn restore(_ptr: *const u8, _config: &Config) { }
+    pub(crate) fn task_switched_flag() -> bool { false }
+    pub(crate) fn set_task_switched_flag(_value: bool) { }
+}
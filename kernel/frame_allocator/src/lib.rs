@@ -561,6 +561,7 @@ impl<const S: MemoryState, P: PageSize> Drop for Frames<S, P> {
             // which itself is then dropped.
             MemoryState::Allocated => { 
                 // trace!("Converting AllocatedFrames to FreeFrames. Drop handler will be called again {:?}", self.frame_range);
+                alloc_profiler::record_frames_dealloc(self.frame_range.start_address().value(), self.frame_range.size_in_bytes());
                 let frame_range = mem::take(&mut self.frame_range);
                 let _to_drop = Frames::<{MemoryState::Free}, P> {
                     typ: self.typ,
@@ -1069,6 +1070,8 @@ fn allocate_from_chosen_chunk(
     // TODO: Re-use the allocated wrapper if possible, rather than allocate a new one entirely.
     // if let RemovedValue::RBTree(Some(wrapper_adapter)) = _removed_chunk { ... }
 
+    alloc_profiler::record_frames_alloc(new_allocation.start_address().value(), new_allocation.size_in_bytes());
+
     Ok((
         new_allocation.into_allocated_frames(),
         DeferredAllocAction::new(before_start, after_end),
@@ -1374,6 +1377,43 @@ pub fn convert_frame_allocator_to_heap_based() {
     RESERVED_REGIONS.lock().convert_to_heap_allocated();
 }
 
+/// Statistics about the physical memory managed by the frame allocator, in number of 4KiB frames.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FrameAllocatorStats {
+    /// The number of frames in all regions available for general use.
+    pub general_frames: usize,
+    /// The number of frames available for general use that are not currently allocated.
+    pub free_general_frames: usize,
+    /// The number of frames in all regions reserved for specific purposes, e.g., device memory.
+    pub reserved_frames: usize,
+    /// The number of reserved frames that are not currently allocated.
+    pub free_reserved_frames: usize,
+}
+
+/// Returns statistics about how much physical memory is known to and free in the frame allocator.
+pub fn stats() -> FrameAllocatorStats {
+    fn count_frames<'a>(frames: impl Iterator<Item = &'a FrameRange>) -> usize {
+        frames.map(|f| f.size_in_frames()).sum()
+    }
+    FrameAllocatorStats {
+        general_frames: count_frames(GENERAL_REGIONS.lock().iter().map(|r| &r.frames)),
+        free_general_frames: count_frames(FREE_GENERAL_FRAMES_LIST.lock().iter().map(|f| f.deref())),
+        reserved_frames: count_frames(RESERVED_REGIONS.lock().iter().map(|r| &r.frames)),
+        free_reserved_frames: count_frames(FREE_RESERVED_FRAMES_LIST.lock().iter().map(|f| f.deref())),
+    }
+}
+
+/// Returns the physical address just past the end of the highest region available for general use.
+///
+/// This bounds the addresses of all frames that can be allocated without specifically requesting them,
+/// e.g., for sizing a table with an entry for each such frame.
+pub fn general_memory_end() -> PhysicalAddress {
+    GENERAL_REGIONS.lock().iter()
+        .map(|r| r.frames.end().start_address() + FRAME_4K_SIZE_IN_BYTES)
+        .max()
+        .unwrap_or(PhysicalAddress::zero())
+}
+
 /// A debugging function used to dump the full internal state of the frame allocator. 
 #[doc(hidden)] 
 pub fn dump_frame_allocator_state() {
@@ -14,8 +14,6 @@ use framebuffer::{Framebuffer, Pixel};
 use shapes::{Coord, Rectangle};
 
 
-type Ascii = u8;
-
 /// Prints a string in a framebuffer.
 /// Returns (column, line, rectangle), i.e. the position of the next symbol and an rectangle which covers the updated area.
 /// A block item (index, width) represents the index of line number and the width of charaters in this line as pixels. It can be viewed as a framebuffer block which is described in the `framebuffer_compositor` crate.
@@ -48,8 +46,8 @@ pub fn print_string<P: Pixel>(
 
     let top_left = Coord::new(0, (curr_line * CHARACTER_HEIGHT) as isize);
 
-    for byte in slice.bytes() {
-        if byte == b'\n' {
+    for c in slice.chars() {
+        if c == '\n' {
             let mut blank = Rectangle {
                 top_left: Coord::new(
                     coordinate.x + (curr_column * CHARACTER_WIDTH) as isize,
@@ -80,9 +78,9 @@ pub fn print_string<P: Pixel>(
                 }
             }
             // print the next character
-            print_ascii_character(
+            print_character(
                 framebuffer,
-                byte,
+                c,
                 fg_pixel,
                 bg_pixel,
                 coordinate,
@@ -144,14 +142,14 @@ pub fn print_string<P: Pixel>(
 /// Prints a character to the framebuffer at position (line, column) of all characters in the text area.
 /// # Arguments
 /// * `framebuffer`: the framebuffer to display in.
-/// * `character`: the ASCII code of the character to display.
+/// * `character`: the character to display. Characters without a glyph in the font are shown as a box.
 /// * `fg_pixel`: the value of every pixel in the character.
 /// * `bg_color`: the value of every pixel in the background.
 /// * `coordinate`: the left top coordinate of the text block relative to the origin(top-left point) of the framebuffer.
 /// * `column`, `line`: the location of the character in the text block as symbols.
-pub fn print_ascii_character<P: Pixel>(
+pub fn print_character<P: Pixel>(
     framebuffer: &mut Framebuffer<P>,
-    character: Ascii,
+    character: char,
     fg_pixel: P,
     bg_pixel: P,
     coordinate: Coord,
@@ -166,16 +164,18 @@ pub fn print_ascii_character<P: Pixel>(
     let (buffer_width, buffer_height) = framebuffer.get_size();
     let off_set_x: usize = if start.x < 0 { -(start.x) as usize } else { 0 };
     let off_set_y: usize = if start.y < 0 { -(start.y) as usize } else { 0 };    
+    let glyph = font::glyph(character);
+    // line-drawing characters fill the gap so that they connect to the character on their left
+    let fill_gap = font::is_line_drawing(character);
     let mut j = off_set_x;
     let mut i = off_set_y;
     loop {
         let coordinate = start + (j as isize, i as isize);
         if framebuffer.contains(coordinate) {
-            let pixel = if j >= 1 {
+            let pixel = if j >= 1 || fill_gap {
                 // leave 1 pixel gap between two characters
-                let index = j - 1;
-                let char_font = font::FONT_BASIC[character as usize][i];
-                if get_bit(char_font, index) != 0 {
+                let index = j.saturating_sub(1);
+                if get_bit(glyph[i], index) != 0 {
                     fg_pixel
                 } else {
                     bg_pixel
@@ -22,7 +22,7 @@ use alloc::vec::Vec;
 use spin::Mutex;
 use alloc::sync::{Arc, Weak};
 use memory::MappedPages;
-use io::{ByteReader, ByteWriter, KnownLength};
+use io::{ByteReader, ByteWriter, IoError, KnownLength};
 
 /// A reference to any type that implements the [`File`] trait,
 /// which can only represent a File (not a Directory).
@@ -66,12 +66,91 @@ pub trait FsNode {
     /// This is useful for ensuring correctness when inserting or removing 
     /// files or directories from their parent directory.
     fn set_parent_dir(&mut self, new_parent: WeakDirRef);
+
+    /// Changes the name of this node.
+    ///
+    /// This only changes the name that the node itself reports;
+    /// nodes should be renamed via [`Directory::rename()`], which also updates their parent directory.
+    ///
+    /// The default implementation returns an error, for nodes that cannot be renamed.
+    fn set_name(&mut self, _name: String) -> Result<(), &'static str> {
+        Err("this node cannot be renamed")
+    }
 }
 
 // Trait for files, implementors of File must also implement FsNode
 pub trait File : FsNode + ByteReader + ByteWriter + KnownLength {
     /// Returns a view of this file as an immutable memory-mapped region.
     fn as_mapping(&self) -> Result<&MappedPages, &'static str>;
+
+    /// Blocks until all data written to this file and all of its metadata are durable,
+    /// i.e., they will survive a power loss, like POSIX `fsync()`.
+    ///
+    /// The default implementation flushes this file, which suffices for files
+    /// that aren't backed by a storage device.
+    fn sync_all(&mut self) -> Result<(), IoError> {
+        ByteWriter::flush(self)
+    }
+
+    /// Like [`File::sync_all()`], but only guarantees the durability of the metadata
+    /// needed to read back the written data, e.g., the file's length, like POSIX `fdatasync()`.
+    ///
+    /// The default implementation invokes [`File::sync_all()`].
+    fn sync_data(&mut self) -> Result<(), IoError> {
+        self.sync_all()
+    }
+
+    /// Ensures that all writes to this file issued before this call become durable
+    /// before any writes issued after it, without necessarily waiting for them to be durable.
+    ///
+    /// The default implementation invokes [`File::sync_data()`], which is a stronger guarantee.
+    fn write_barrier(&mut self) -> Result<(), IoError> {
+        self.sync_data()
+    }
+
+    /// Changes the length of this file to `len` bytes, like POSIX `ftruncate()`.
+    ///
+    /// Shrinking the file discards its contents beyond `len`.
+    /// Growing the file extends it with zeros, which sparse files store as a hole
+    /// that doesn't consume any memory or disk space.
+    ///
+    /// The default implementation returns an error, as not all files can change their length.
+    fn set_len(&mut self, _len: usize) -> Result<(), IoError> {
+        Err(IoError::Other("this file doesn't support changing its length"))
+    }
+
+    /// Zeroes `len` bytes of this file starting at `offset`, without changing the file's length,
+    /// like `fallocate()` with `FALLOC_FL_PUNCH_HOLE` on Linux.
+    ///
+    /// Sparse files release the memory or disk space of the pages or blocks within the hole;
+    /// the default implementation merely overwrites the range with zeros.
+    fn punch_hole(&mut self, offset: usize, len: usize) -> Result<(), IoError> {
+        const ZEROS: [u8; 512] = [0; 512];
+        let end = offset.saturating_add(len).min(self.len());
+        let mut pos = offset;
+        while pos < end {
+            let chunk = (end - pos).min(ZEROS.len());
+            pos += self.write_at(&ZEROS[..chunk], pos)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the offset of the first byte of data at or after the given `offset`,
+    /// like `lseek()` with `SEEK_DATA`, or `None` if only holes follow `offset`.
+    ///
+    /// The default implementation treats the entire file as data.
+    fn seek_data(&self, offset: usize) -> Option<usize> {
+        (offset < self.len()).then_some(offset)
+    }
+
+    /// Returns the offset of the first hole at or after the given `offset`,
+    /// like `lseek()` with `SEEK_HOLE`, or `None` if `offset` is beyond the end of the file.
+    ///
+    /// The end of the file is always considered to be the start of a hole.
+    /// The default implementation treats the entire file as data.
+    fn seek_hole(&self, offset: usize) -> Option<usize> {
+        (offset < self.len()).then_some(self.len())
+    }
 }
 
 /// Trait for directories, implementors of Directory must also implement FsNode
@@ -112,8 +191,41 @@ pub trait Directory : FsNode {
     /// The lock on `node` must not be held because it will be acquired within this function.
     fn remove(&mut self, node: &FileOrDir) -> Option<FileOrDir>;
 
+    /// Atomically renames the node named `old_name` in this directory to `new_name`,
+    /// replacing any existing node named `new_name`.
+    /// The replaced node is returned if this directory holds onto its nodes,
+    /// which isn't the case for directories that create their nodes on demand, e.g., remote ones.
+    ///
+    /// Other users of this directory observe either the old name or the new name, never neither or both,
+    /// and a node named `new_name` exists throughout the rename if one existed before it.
+    /// A file cannot replace a directory and vice versa. Whether a directory can replace
+    /// a directory that isn't empty depends on the filesystem; in-memory directories allow it.
+    /// For directories backed by storage, the rename is durable once [`Directory::sync()`] returns.
+    ///
+    /// The default implementation returns an error, for directories that don't support renaming.
+    fn rename(&mut self, _old_name: &str, _new_name: &str) -> Result<Option<FileOrDir>, &'static str> {
+        Err("this directory does not support renaming")
+    }
+
+    /// Blocks until all changes to this directory's entries, e.g., inserted or removed nodes,
+    /// are durable, i.e., they will survive a power loss.
+    ///
+    /// The default implementation does nothing, which suffices for directories
+    /// that aren't backed by a storage device.
+    fn sync(&mut self) -> Result<(), IoError> {
+        Ok(())
+    }
+
     /// Lists the names of the nodes in this directory.
     fn list(&self) -> Vec<String>;
+
+    /// Returns all of the nodes in this directory, in no particular order.
+    ///
+    /// The default implementation looks up each name returned by [`Directory::list()`],
+    /// so implementors should override it if they can return their nodes more efficiently.
+    fn entries(&self) -> Vec<FileOrDir> {
+        self.list().iter().filter_map(|name| self.get(name)).collect()
+    }
 }
 
 /// Allows us to return a generic type that can be matched by the caller to extract the underlying type
@@ -159,6 +271,13 @@ impl FsNode for FileOrDir {
             FileOrDir::Dir(dir) => dir.lock().set_parent_dir(new_parent),
         }
     }
+
+    fn set_name(&mut self, name: String) -> Result<(), &'static str> {
+        match self {
+            FileOrDir::File(file) => file.lock().set_name(name),
+            FileOrDir::Dir(dir) => dir.lock().set_name(name),
+        }
+    }
 }
 
 impl KnownLength for FileOrDir {
@@ -189,4 +308,100 @@ impl FileOrDir {
             FileOrDir::Dir(_) => true,
         }
     }
+
+    /// Returns information about this node, acquiring its lock only once.
+    pub fn metadata(&self) -> Metadata {
+        match self {
+            FileOrDir::File(f) => {
+                let file = f.lock();
+                Metadata { name: file.get_name(), is_dir: false, len: file.len() }
+            }
+            FileOrDir::Dir(d) => Metadata { name: d.lock().get_name(), is_dir: true, len: 0 },
+        }
+    }
+
+    /// Returns an iterator over this node and all of its descendants.
+    /// See [`Walk`] for more details.
+    pub fn walk(&self) -> Walk {
+        Walk {
+            stack: vec![WalkEntry { path: String::new(), depth: 0, node: self.clone() }],
+            max_depth: usize::MAX,
+        }
+    }
+}
+
+/// Information about a file or directory, returned by [`FileOrDir::metadata()`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Metadata {
+    pub name: String,
+    pub is_dir: bool,
+    /// The length (size) in bytes of a file. Directories currently have a length of `0`.
+    pub len: usize,
+}
+
+/// A node visited by a [`Walk`].
+#[derive(Clone, Debug)]
+pub struct WalkEntry {
+    /// The path of this node relative to the node that the walk started from,
+    /// which is empty for that starting node itself.
+    pub path: String,
+    /// The number of levels below the starting node that this node is at,
+    /// which is `0` for the starting node itself.
+    pub depth: usize,
+    pub node: FileOrDir,
+}
+
+/// An iterator that recursively visits a node and all of its descendants,
+/// created by [`FileOrDir::walk()`].
+/// 
+/// Nodes are visited depth-first, with each directory visited before its contents
+/// and the contents of each directory visited in order of their names.
+/// 
+/// Only one directory is locked at a time, and only while its entries are being retrieved,
+/// so the tree may be modified while it is being walked;
+/// for example, the current entry may be removed from its parent directory.
+/// However, the contents of a directory are retrieved when that directory is visited,
+/// so nodes added to or removed from it afterwards are not reflected in the walk.
+pub struct Walk {
+    /// The entries that have yet to be visited, with the next entry at the end.
+    stack: Vec<WalkEntry>,
+    max_depth: usize,
+}
+
+impl Walk {
+    /// Stops the walk from descending more than `max_depth` levels below the starting node.
+    /// 
+    /// For example, a `max_depth` of `0` visits only the starting node,
+    /// and a `max_depth` of `1` also visits the contents of the starting directory.
+    pub fn max_depth(mut self, max_depth: usize) -> Walk {
+        self.max_depth = max_depth;
+        self
+    }
+}
+
+impl Iterator for Walk {
+    type Item = WalkEntry;
+
+    fn next(&mut self) -> Option<WalkEntry> {
+        let entry = self.stack.pop()?;
+        if let FileOrDir::Dir(dir) = &entry.node {
+            if entry.depth < self.max_depth {
+                let entries = dir.lock().entries();
+                let mut children: Vec<(String, FileOrDir)> = entries.into_iter()
+                    .map(|node| (node.get_name(), node))
+                    .collect();
+                // Sort in reverse, such that the first child is at the top of the stack.
+                children.sort_by(|(a, _), (b, _)| b.cmp(a));
+                self.stack.extend(children.into_iter().map(|(name, node)| {
+                    let path = if entry.path.is_empty() {
+                        name
+                    } else {
+                        format!("{}/{}", entry.path, name)
+                    };
+                    WalkEntry { path, depth: entry.depth + 1, node }
+                }));
+            }
+        }
+        Some(entry)
+    }
 }
@@ -0,0 +1,231 @@
+//! Accounting of the storage used by files on a per-task-group basis, with optional quotas.
+//!
+//! Every file created by a tracked filesystem is owned by the task group of the task
+//! that created it, which is represented by a [`FileOwner`] stored within the file.
+//! The file charges its owner for the bytes of storage that it uses as it grows,
+//! and releases them as it shrinks or when it is dropped.
+//! Thus, writes are charged to the group that owns a file, not to the group of the writer.
+//!
+//! Which task group the current task belongs to is determined by a function
+//! registered via [`init()`], as this crate is used by filesystems
+//! that are too low-level to depend on the task subsystem.
+//! Files created before then, or by a task that doesn't belong to a group, aren't tracked.
+//!
+//! A group's usage can be queried with [`usage()`] or [`groups()`],
+//! and is also exported as the `theseus_fs_group_bytes` and `theseus_fs_group_files` metrics.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::{
+    collections::BTreeMap,
+    string::String,
+    sync::Arc,
+    vec::Vec,
+};
+use io::IoError;
+use spin::{Mutex, Once};
+
+/// The function that returns the name of the current task's group.
+static CURRENT_GROUP_FUNC: Once<fn() -> Option<String>> = Once::new();
+
+/// The accounts of all task groups that have ever owned a file, keyed by group name.
+static GROUPS: Mutex<BTreeMap<String, Arc<Mutex<Account>>>> = Mutex::new(BTreeMap::new());
+
+static BYTES_METRIC: metrics::FnMetric = metrics::FnMetric::new(
+    "theseus_fs_group_bytes",
+    "The number of bytes of storage used by files owned by each task group.",
+    metrics::MetricKind::Gauge,
+    collect_bytes,
+);
+
+static FILES_METRIC: metrics::FnMetric = metrics::FnMetric::new(
+    "theseus_fs_group_files",
+    "The number of files owned by each task group.",
+    metrics::MetricKind::Gauge,
+    collect_files,
+);
+
+/// Initializes storage accounting.
+///
+/// The given `current_group` function must return the name of the task group
+/// that the current task belongs to, or `None` if it shouldn't be tracked.
+pub fn init(current_group: fn() -> Option<String>) -> Result<(), &'static str> {
+    CURRENT_GROUP_FUNC.call_once(|| current_group);
+    metrics::register(&BYTES_METRIC)?;
+    metrics::register(&FILES_METRIC)
+}
+
+/// The storage used by the files owned by a task group.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Usage {
+    /// The number of bytes of storage used by the group's files.
+    pub bytes: usize,
+    /// The number of files owned by the group.
+    pub files: usize,
+}
+
+/// The limits on the storage that a task group may use.
+///
+/// A limit of `None` means that usage is unlimited.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Quota {
+    /// The maximum number of bytes of storage used by the group's files.
+    pub max_bytes: Option<usize>,
+    /// The maximum number of files owned by the group.
+    pub max_files: Option<usize>,
+}
+
+#[derive(Default)]
+struct Account {
+    usage: Usage,
+    quota: Quota,
+}
+
+fn account(group: &str) -> Arc<Mutex<Account>> {
+    GROUPS.lock().entry(String::from(group)).or_default().clone()
+}
+
+/// Sets the quota of the given task group.
+///
+/// The group may already exceed the new quota, in which case it just can't use any more storage.
+pub fn set_quota(group: &str, quota: Quota) {
+    account(group).lock().quota = quota;
+}
+
+/// Returns the quota of the given task group.
+pub fn quota(group: &str) -> Quota {
+    GROUPS.lock().get(group).map(|account| account.lock().quota).unwrap_or_default()
+}
+
+/// Returns the storage used by the given task group.
+pub fn usage(group: &str) -> Usage {
+    GROUPS.lock().get(group).map(|account| account.lock().usage).unwrap_or_default()
+}
+
+/// Returns the name, usage, and quota of every known task group.
+pub fn groups() -> Vec<(String, Usage, Quota)> {
+    GROUPS.lock()
+        .iter()
+        .map(|(name, account)| {
+            let account = account.lock();
+            (name.clone(), account.usage, account.quota)
+        })
+        .collect()
+}
+
+fn collect_bytes(samples: &mut metrics::Samples<'_>) -> core::fmt::Result {
+    collect(samples, |usage| usage.bytes)
+}
+
+fn collect_files(samples: &mut metrics::Samples<'_>) -> core::fmt::Result {
+    collect(samples, |usage| usage.files)
+}
+
+fn collect(samples: &mut metrics::Samples<'_>, value: fn(&Usage) -> usize) -> core::fmt::Result {
+    for (name, usage, _) in groups() {
+        samples.write("", &[("group", &name as &dyn core::fmt::Display)], value(&usage))?;
+    }
+    Ok(())
+}
+
+/// The task group that owns a file, which is charged for the storage that the file uses.
+///
+/// Dropping a `FileOwner` releases the file and all of the storage charged for it.
+pub struct FileOwner {
+    /// The owner's group and its account, or `None` if the file isn't tracked.
+    group: Option<(String, Arc<Mutex<Account>>)>,
+    /// The number of bytes currently charged to the group for this file.
+    bytes: usize,
+}
+
+impl FileOwner {
+    /// Returns an owner that doesn't belong to any group, whose usage isn't tracked.
+    pub const fn untracked() -> FileOwner {
+        FileOwner { group: None, bytes: 0 }
+    }
+
+    /// Charges the current task's group for a new file, and returns the new file's owner.
+    ///
+    /// Returns an error if the group has reached its quota of files.
+    pub fn for_current_group() -> Result<FileOwner, IoError> {
+        let Some(group) = CURRENT_GROUP_FUNC.get().and_then(|func| func()) else {
+            return Ok(FileOwner::untracked());
+        };
+        let account = account(&group);
+        {
+            let mut account = account.lock();
+            if account.quota.max_files.map_or(false, |max| account.usage.files >= max) {
+                return Err(IoError::Other("the task group's quota of files was exceeded"));
+            }
+            account.usage.files += 1;
+        }
+        Ok(FileOwner { group: Some((group, account)), bytes: 0 })
+    }
+
+    /// Returns the name of the group that owns the file, if it's tracked.
+    pub fn group(&self) -> Option<&str> {
+        self.group.as_ref().map(|(name, _)| name.as_str())
+    }
+
+    /// Returns the number of bytes currently charged for the file.
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    /// Charges the owner's group for `bytes` more bytes of storage.
+    ///
+    /// Returns an error without charging anything if that would exceed the group's quota.
+    pub fn charge(&mut self, bytes: usize) -> Result<(), IoError> {
+        if let Some((_, account)) = &self.group {
+            let mut account = account.lock();
+            let new_bytes = account.usage.bytes.saturating_add(bytes);
+            if account.quota.max_bytes.map_or(false, |max| new_bytes > max) {
+                return Err(IoError::Other("the task group's quota of bytes was exceeded"));
+            }
+            account.usage.bytes = new_bytes;
+        }
+        self.bytes += bytes;
+        Ok(())
+    }
+
+    /// Releases up to `bytes` bytes of storage previously charged to the owner's group.
+    pub fn release(&mut self, bytes: usize) {
+        let bytes = bytes.min(self.bytes);
+        if let Some((_, account)) = &self.group {
+            let mut account = account.lock();
+            account.usage.bytes = account.usage.bytes.saturating_sub(bytes);
+        }
+        self.bytes -= bytes;
+    }
+
+    /// Charges or releases storage such that `bytes` bytes in total are charged for the file.
+    pub fn resize(&mut self, bytes: usize) -> Result<(), IoError> {
+        if bytes > self.bytes {
+            self.charge(bytes - self.bytes)
+        } else {
+            self.release(self.bytes - bytes);
+            Ok(())
+        }
+    }
+}
+
+impl Drop for FileOwner {
+    fn drop(&mut self) {
+        self.release(self.bytes);
+        if let Some((_, account)) = &self.group {
+            let mut account = account.lock();
+            account.usage.files = account.usage.files.saturating_sub(1);
+        }
+    }
+}
+
+impl core::fmt::Debug for FileOwner {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("FileOwner")
+            .field("group", &self.group())
+            .field("bytes", &self.bytes)
+            .finish()
+    }
+}
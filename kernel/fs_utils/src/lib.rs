@@ -0,0 +1,319 @@
+//! Recursive operations on trees of files and directories, such as copying, moving, and removing them.
+//!
+//! These operations only use the [`fs_node`] traits to traverse and modify the trees,
+//! so they work across different filesystems, e.g., copying a directory from a mounted
+//! remote filesystem into memory. New files are created as [`MemFile`]s
+//! and new directories as [`VFSDirectory`]s.
+//!
+//! Each operation accepts a callback that is invoked for every node it processes,
+//! which applications can use to report progress through large trees.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::{format, string::{String, ToString}, sync::Arc, vec::Vec};
+use core::fmt;
+use fs_node::{DirRef, FileOrDir, FileRef, FsNode, WalkEntry};
+use memfs::MemFile;
+use memory::{create_mapping, MappedPages, PteFlags};
+use path::{Path, PathBuf};
+use vfs_node::VFSDirectory;
+
+/// Recursively copies `source` into `dest_dir` with the given `name`,
+/// replacing any existing node with that name, and returns the copy.
+///
+/// `on_copied` is invoked with each node of `source` after it has been copied.
+pub fn copy(
+    source: &FileOrDir,
+    dest_dir: &DirRef,
+    name: &str,
+    on_copied: &mut dyn FnMut(&WalkEntry),
+) -> Result<FileOrDir, &'static str> {
+    if let FileOrDir::Dir(dir) = source {
+        if is_within(dest_dir, dir) {
+            return Err("cannot copy a directory into itself");
+        }
+    }
+    if source.get_name() == name && source.get_parent_dir().map_or(false, |p| Arc::ptr_eq(&p, dest_dir)) {
+        return Err("cannot copy a node onto itself");
+    }
+
+    // The copies of the directories that contain the current entry, indexed by depth.
+    let mut dest_dirs: Vec<DirRef> = Vec::new();
+    let mut copy = None;
+    for entry in source.walk() {
+        dest_dirs.truncate(entry.depth);
+        let parent = dest_dirs.last().unwrap_or(dest_dir).clone();
+        let name = if entry.depth == 0 { name.to_string() } else { entry.node.get_name() };
+        let new_node = match &entry.node {
+            FileOrDir::File(file) => FileOrDir::File(copy_file(file, name, &parent)?),
+            FileOrDir::Dir(_) => {
+                let new_dir = VFSDirectory::create(name, &parent)?;
+                dest_dirs.push(new_dir.clone());
+                FileOrDir::Dir(new_dir)
+            }
+        };
+        copy.get_or_insert(new_node);
+        on_copied(&entry);
+    }
+    copy.ok_or("failed to copy node")
+}
+
+/// Moves `source` into `dest_dir` with the given `name`,
+/// replacing any existing node with that name, and returns the moved node.
+///
+/// If the name is unchanged, the node is simply relinked into `dest_dir`,
+/// and if only the name changes, the node is renamed in place via [`Directory::rename()`].
+/// In both cases, `on_moved` is invoked only once, for `source` itself.
+/// Otherwise, or if its directory doesn't support renaming, the node is copied and then removed,
+/// so `on_moved` is invoked with each node of `source` after it has been copied.
+///
+/// [`Directory::rename()`]: fs_node::Directory::rename
+pub fn move_node(
+    source: &FileOrDir,
+    dest_dir: &DirRef,
+    name: &str,
+    on_moved: &mut dyn FnMut(&WalkEntry),
+) -> Result<FileOrDir, &'static str> {
+    if let FileOrDir::Dir(dir) = source {
+        if is_within(dest_dir, dir) {
+            return Err("cannot move a directory into itself");
+        }
+    }
+    let parent = source.get_parent_dir().ok_or("cannot move a node without a parent directory")?;
+
+    if source.get_name() != name {
+        if Arc::ptr_eq(&parent, dest_dir) {
+            let renamed = parent.lock().rename(&source.get_name(), name);
+            if renamed.is_ok() {
+                on_moved(&WalkEntry { path: String::new(), depth: 0, node: source.clone() });
+                let renamed = parent.lock().get(name);
+                return renamed.ok_or("failed to find node after renaming it");
+            }
+        }
+        let copy = copy(source, dest_dir, name, on_moved)?;
+        parent.lock().remove(source).ok_or("failed to remove node from its parent directory")?;
+        return Ok(copy);
+    }
+
+    if Arc::ptr_eq(&parent, dest_dir) {
+        return Ok(source.clone());
+    }
+    let mut node = parent.lock().remove(source).ok_or("failed to remove node from its parent directory")?;
+    node.set_parent_dir(Arc::downgrade(dest_dir));
+    let result = dest_dir.lock().insert(node.clone());
+    if let Err(e) = result {
+        // Put the node back where it was.
+        node.set_parent_dir(Arc::downgrade(&parent));
+        parent.lock().insert(node)?;
+        return Err(e);
+    }
+    on_moved(&WalkEntry { path: String::new(), depth: 0, node: node.clone() });
+    Ok(node)
+}
+
+/// Recursively removes `node` and all of its descendants.
+///
+/// Descendants are removed before the directories that contain them,
+/// which filesystems that don't allow removing non-empty directories require.
+/// `on_removed` is invoked with each node after it has been removed.
+pub fn remove(node: &FileOrDir, on_removed: &mut dyn FnMut(&WalkEntry)) -> Result<(), &'static str> {
+    let entries: Vec<WalkEntry> = node.walk().collect();
+    // A directory is always visited before its contents, so reversing the walk
+    // visits the contents of a directory before the directory itself.
+    for entry in entries.iter().rev() {
+        let parent = entry.node.get_parent_dir().ok_or("cannot remove a node without a parent directory")?;
+        parent.lock().remove(&entry.node).ok_or("failed to remove node from its parent directory")?;
+        on_removed(entry);
+    }
+    Ok(())
+}
+
+/// Replaces the file named `name` in `dir` with a new file containing `contents`, and returns it.
+///
+/// The contents are first written to a temporary file in `dir`, which is synced and then renamed over
+/// the target, so other users of `dir` see either the old file or the new one in its entirety,
+/// never a partially written one, even if the system crashes during the update.
+pub fn write_atomically(dir: &DirRef, name: &str, contents: &[u8]) -> Result<FileRef, &'static str> {
+    let temp_name = format!(".{}.tmp", name);
+    // A temporary file left behind by an interrupted update is stale.
+    remove_if_exists(dir, &temp_name)?;
+
+    let result = create_file(dir, &temp_name, contents)
+        .and_then(|temp| temp.lock().sync_all().map_err(Into::into))
+        .and_then(|_| dir.lock().rename(&temp_name, name))
+        .and_then(|_| dir.lock().sync().map_err(Into::into));
+    if let Err(e) = result {
+        let _ = remove_if_exists(dir, &temp_name);
+        return Err(e);
+    }
+    dir.lock().get_file(name).ok_or("failed to find file after renaming it")
+}
+
+/// Replaces the directory named `name` in `parent` with a new directory
+/// that `populate` fills in, and returns it.
+///
+/// The new directory is populated under a temporary name, synced, and then renamed over the target,
+/// so other users of `parent` see either the complete old directory or the complete new one.
+/// If the filesystem can't replace a directory that isn't empty, the old directory is first
+/// renamed aside, so the target is briefly absent, but never partially populated.
+/// If `populate` fails, the old directory is left as it was.
+pub fn replace_dir_atomically(
+    parent: &DirRef,
+    name: &str,
+    populate: impl FnOnce(&DirRef) -> Result<(), &'static str>,
+) -> Result<DirRef, &'static str> {
+    let staging_name = format!(".{}.staging", name);
+    remove_if_exists(parent, &staging_name)?;
+
+    VFSDirectory::create(staging_name.clone(), parent)?;
+    // The parent may not keep the directory we created, e.g., if it's on a remote filesystem.
+    let staging = parent.lock().get_dir(&staging_name);
+    let result = staging
+        .ok_or("failed to create staging directory")
+        .and_then(|staging| populate(&staging).and(Ok(staging)))
+        .and_then(|staging| sync_tree(&FileOrDir::Dir(staging)))
+        .and_then(|_| swap_dir(parent, &staging_name, name));
+    if let Err(e) = result {
+        let _ = remove_if_exists(parent, &staging_name);
+        return Err(e);
+    }
+    parent.lock().get_dir(name).ok_or("failed to find directory after renaming it")
+}
+
+/// Renames the directory `staging_name` in `parent` over the directory `name`.
+fn swap_dir(parent: &DirRef, staging_name: &str, name: &str) -> Result<(), &'static str> {
+    let renamed = parent.lock().rename(staging_name, name);
+    if let Err(e) = renamed {
+        if parent.lock().get_dir(name).is_none() {
+            return Err(e);
+        }
+        let old_name = format!(".{}.old", name);
+        remove_if_exists(parent, &old_name)?;
+        parent.lock().rename(name, &old_name)?;
+        let renamed = parent.lock().rename(staging_name, name);
+        if let Err(e) = renamed {
+            // Put the old directory back.
+            let _ = parent.lock().rename(&old_name, name);
+            return Err(e);
+        }
+        remove_if_exists(parent, &old_name)?;
+    }
+    parent.lock().sync().map_err(Into::into)
+}
+
+/// Syncs every file and directory in the tree rooted at `node`.
+fn sync_tree(node: &FileOrDir) -> Result<(), &'static str> {
+    for entry in node.walk() {
+        match &entry.node {
+            FileOrDir::File(file) => file.lock().sync_all()?,
+            FileOrDir::Dir(dir) => dir.lock().sync()?,
+        }
+    }
+    Ok(())
+}
+
+/// Recursively removes the node named `name` in `dir`, if there is one.
+fn remove_if_exists(dir: &DirRef, name: &str) -> Result<(), &'static str> {
+    let node = dir.lock().get(name);
+    match node {
+        Some(node) => remove(&node, &mut |_| {}),
+        None => Ok(()),
+    }
+}
+
+/// Expands each argument that contains wildcards into the paths that match it,
+/// as described in [`Path::glob()`]. Other arguments are returned as they are.
+///
+/// Returns an error if a pattern doesn't match anything.
+pub fn expand_globs<S: AsRef<str>>(args: &[S], cwd: &DirRef) -> Result<Vec<PathBuf>, String> {
+    let mut paths = Vec::new();
+    for arg in args {
+        let path = Path::new(arg.as_ref());
+        if path.is_glob() {
+            let matches = path.glob(cwd);
+            if matches.is_empty() {
+                return Err(format!("no matches found for {}", path));
+            }
+            paths.extend(matches);
+        } else {
+            paths.push(path.into());
+        }
+    }
+    Ok(paths)
+}
+
+/// A tally of the nodes processed by a recursive operation.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Progress {
+    pub files: usize,
+    pub dirs: usize,
+    /// The total length of the files.
+    pub bytes: usize,
+}
+
+impl Progress {
+    pub fn record(&mut self, node: &FileOrDir) {
+        let metadata = node.metadata();
+        if metadata.is_dir {
+            self.dirs += 1;
+        } else {
+            self.files += 1;
+            self.bytes += metadata.len;
+        }
+    }
+
+    /// Returns the total number of nodes recorded.
+    pub fn count(&self) -> usize {
+        self.files + self.dirs
+    }
+}
+
+impl fmt::Display for Progress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} files, {} directories, {} bytes", self.files, self.dirs, self.bytes)
+    }
+}
+
+/// Returns whether `dir` is `ancestor` or one of its descendants.
+fn is_within(dir: &DirRef, ancestor: &DirRef) -> bool {
+    let mut current = Some(dir.clone());
+    while let Some(dir) = current {
+        if Arc::ptr_eq(&dir, ancestor) {
+            return true;
+        }
+        current = dir.lock().get_parent_dir();
+    }
+    false
+}
+
+/// Creates a new file in `dir` with the given `name` and `contents`, and returns it
+/// as `dir` holds it, which may differ from the file that was inserted into it.
+fn create_file(dir: &DirRef, name: &str, contents: &[u8]) -> Result<FileRef, &'static str> {
+    let mapped_pages = if contents.is_empty() {
+        MappedPages::empty()
+    } else {
+        let mut mapped_pages = create_mapping(contents.len(), PteFlags::new().valid(true).writable(true))?;
+        mapped_pages.as_slice_mut(0, contents.len())?.copy_from_slice(contents);
+        mapped_pages
+    };
+    MemFile::from_mapped_pages(mapped_pages, name.to_string(), contents.len(), dir)?;
+    dir.lock().get_file(name).ok_or("failed to create file")
+}
+
+/// Creates a copy of `file` in `parent` with the given `name`.
+fn copy_file(file: &FileRef, name: String, parent: &DirRef) -> Result<FileRef, &'static str> {
+    let mut file = file.lock();
+    let len = file.len();
+    let mapped_pages = if len == 0 {
+        MappedPages::empty()
+    } else {
+        let mut mapped_pages = create_mapping(len, PteFlags::new().valid(true).writable(true))?;
+        file.read_at(mapped_pages.as_slice_mut(0, len)?, 0)?;
+        mapped_pages
+    };
+    // Release the lock on the source file first, as inserting into `parent` may lock it.
+    drop(file);
+    MemFile::from_mapped_pages(mapped_pages, name, len, parent)
+}
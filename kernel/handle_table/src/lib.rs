@@ -0,0 +1,349 @@
+//! Generational handle tables, which map small unique handles to kernel objects.
+//!
+//! A [`HandleTable`] stores values in slots that are reused after their values are removed,
+//! so inserting, looking up, and removing values all take constant time.
+//! Each [`Handle`] records both the index of its slot and the slot's generation,
+//! which is incremented whenever the slot is freed.
+//! A stale handle to a value that was removed therefore never refers to
+//! whatever value later reuses its slot, which prevents use-after-free bugs through reused IDs.
+//!
+//! Values can optionally be owned by a task, identified by its task ID,
+//! such that they can all be removed when that task exits.
+//! Tables that hold per-task resources should register a hook with [`register_owner_cleanup()`],
+//! which is invoked via [`release_owned_by()`] when a task exits.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use core::fmt;
+use spin::Mutex;
+
+/// A unique reference to a value in a [`HandleTable`].
+///
+/// A handle is only valid for the table that created it.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Handle {
+    index: u32,
+    generation: u32,
+}
+
+impl Handle {
+    /// Returns this handle as a single non-zero integer.
+    ///
+    /// The lower 32 bits are one more than the slot index and the upper 32 bits are the generation,
+    /// so handles from a slot's first generation are small, consecutive numbers starting at 1.
+    pub const fn to_raw(self) -> u64 {
+        ((self.generation as u64) << 32) | (self.index as u64 + 1)
+    }
+
+    /// Returns the handle that was converted into the given integer by [`Handle::to_raw()`],
+    /// or `None` if it's zero.
+    pub const fn from_raw(raw: u64) -> Option<Handle> {
+        let index = raw as u32;
+        if index == 0 {
+            return None;
+        }
+        Some(Handle {
+            index: index - 1,
+            generation: (raw >> 32) as u32,
+        })
+    }
+
+    /// Returns the index of this handle's slot in its table.
+    pub const fn index(self) -> u32 {
+        self.index
+    }
+
+    /// Returns the generation of this handle's slot when this handle was created.
+    pub const fn generation(self) -> u32 {
+        self.generation
+    }
+}
+
+impl fmt::Debug for Handle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Handle({}v{})", self.index, self.generation)
+    }
+}
+
+impl fmt::Display for Handle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_raw())
+    }
+}
+
+struct Slot<T> {
+    generation: u32,
+    entry: Entry<T>,
+}
+
+enum Entry<T> {
+    Occupied {
+        value: T,
+        /// The ID of the task that owns the value, if any.
+        owner: Option<usize>,
+    },
+    Vacant {
+        /// The index of the next slot in the free list.
+        next_free: Option<u32>,
+    },
+    /// The slot's generation was exhausted, so the slot is never reused.
+    Retired,
+}
+
+/// A table of values, each identified by a unique [`Handle`].
+pub struct HandleTable<T> {
+    slots: Vec<Slot<T>>,
+    /// The index of the most recently freed slot, which is reused first.
+    free_head: Option<u32>,
+    len: usize,
+}
+
+impl<T> HandleTable<T> {
+    /// Creates an empty table.
+    pub const fn new() -> HandleTable<T> {
+        HandleTable {
+            slots: Vec::new(),
+            free_head: None,
+            len: 0,
+        }
+    }
+
+    /// Returns the number of values in this table.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Inserts a value that isn't owned by any task, returning its new handle.
+    ///
+    /// # Panics
+    /// Panics if the table already has `u32::MAX` slots.
+    pub fn insert(&mut self, value: T) -> Handle {
+        self.insert_entry(value, None)
+    }
+
+    /// Inserts a value owned by the task with the given ID, returning its new handle.
+    ///
+    /// The value is removed by [`HandleTable::remove_owned_by()`] when that task exits.
+    pub fn insert_owned(&mut self, owner: usize, value: T) -> Handle {
+        self.insert_entry(value, Some(owner))
+    }
+
+    fn insert_entry(&mut self, value: T, owner: Option<usize>) -> Handle {
+        self.len += 1;
+        let entry = Entry::Occupied { value, owner };
+        if let Some(index) = self.free_head {
+            let slot = &mut self.slots[index as usize];
+            if let Entry::Vacant { next_free } = slot.entry {
+                self.free_head = next_free;
+            }
+            slot.entry = entry;
+            return Handle { index, generation: slot.generation };
+        }
+        let index = u32::try_from(self.slots.len())
+            .ok()
+            .filter(|&index| index != u32::MAX)
+            .expect("handle table has no free slots");
+        self.slots.push(Slot { generation: 0, entry });
+        Handle { index, generation: 0 }
+    }
+
+    /// Returns the slot referred to by the given handle, if the slot is still in the same generation.
+    fn current_slot(&self, handle: Handle) -> Option<&Slot<T>> {
+        self.slots
+            .get(handle.index as usize)
+            .filter(|slot| slot.generation == handle.generation)
+    }
+
+    /// Returns a reference to the value with the given handle.
+    pub fn get(&self, handle: Handle) -> Option<&T> {
+        match &self.current_slot(handle)?.entry {
+            Entry::Occupied { value, .. } => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Returns a mutable reference to the value with the given handle.
+    pub fn get_mut(&mut self, handle: Handle) -> Option<&mut T> {
+        let slot = self.slots.get_mut(handle.index as usize)?;
+        match &mut slot.entry {
+            Entry::Occupied { value, .. } if slot.generation == handle.generation => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Returns whether this table contains a value with the given handle.
+    pub fn contains(&self, handle: Handle) -> bool {
+        self.get(handle).is_some()
+    }
+
+    /// Returns the ID of the task that owns the value with the given handle.
+    ///
+    /// Returns `None` if there's no such value or it isn't owned by any task.
+    pub fn owner(&self, handle: Handle) -> Option<usize> {
+        match &self.current_slot(handle)?.entry {
+            Entry::Occupied { owner, .. } => *owner,
+            _ => None,
+        }
+    }
+
+    /// Changes the task that owns the value with the given handle.
+    ///
+    /// Returns whether this table contains a value with the given handle.
+    pub fn set_owner(&mut self, handle: Handle, new_owner: Option<usize>) -> bool {
+        let Some(slot) = self.slots.get_mut(handle.index as usize) else {
+            return false;
+        };
+        match &mut slot.entry {
+            Entry::Occupied { owner, .. } if slot.generation == handle.generation => {
+                *owner = new_owner;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Removes and returns the value with the given handle,
+    /// after which the handle is no longer valid.
+    pub fn remove(&mut self, handle: Handle) -> Option<T> {
+        if !self.contains(handle) {
+            return None;
+        }
+        let slot = &mut self.slots[handle.index as usize];
+        let entry = match slot.generation.checked_add(1) {
+            Some(generation) => {
+                slot.generation = generation;
+                let entry = core::mem::replace(
+                    &mut slot.entry,
+                    Entry::Vacant { next_free: self.free_head },
+                );
+                self.free_head = Some(handle.index);
+                entry
+            }
+            None => core::mem::replace(&mut slot.entry, Entry::Retired),
+        };
+        self.len -= 1;
+        match entry {
+            Entry::Occupied { value, .. } => Some(value),
+            _ => unreachable!("slot was checked to be occupied"),
+        }
+    }
+
+    /// Removes and returns all values owned by the task with the given ID.
+    pub fn remove_owned_by(&mut self, owner: usize) -> Vec<(Handle, T)> {
+        let handles: Vec<Handle> = self
+            .iter_with_owners()
+            .filter(|(_, _, value_owner)| *value_owner == Some(owner))
+            .map(|(handle, ..)| handle)
+            .collect();
+        handles
+            .into_iter()
+            .filter_map(|handle| Some((handle, self.remove(handle)?)))
+            .collect()
+    }
+
+    /// Returns an iterator over the handles and values in this table, in order of their slots.
+    pub fn iter(&self) -> impl Iterator<Item = (Handle, &T)> {
+        self.iter_with_owners().map(|(handle, value, _)| (handle, value))
+    }
+
+    fn iter_with_owners(&self) -> impl Iterator<Item = (Handle, &T, Option<usize>)> {
+        self.slots.iter().enumerate().filter_map(|(index, slot)| match &slot.entry {
+            Entry::Occupied { value, owner } => Some((
+                Handle { index: index as u32, generation: slot.generation },
+                value,
+                *owner,
+            )),
+            _ => None,
+        })
+    }
+}
+
+impl<T> Default for HandleTable<T> {
+    fn default() -> Self {
+        HandleTable::new()
+    }
+}
+
+/// The functions invoked when a task exits, to remove the values it owned from their tables.
+static OWNER_CLEANUP_HOOKS: Mutex<Vec<fn(usize)>> = Mutex::new(Vec::new());
+
+/// Registers a function that removes the values owned by a task from a table
+/// when that task exits.
+///
+/// The function is invoked with the task's ID, typically calling [`HandleTable::remove_owned_by()`].
+/// Registering the same function more than once has no effect.
+pub fn register_owner_cleanup(hook: fn(usize)) {
+    let mut hooks = OWNER_CLEANUP_HOOKS.lock();
+    if !hooks.iter().any(|&existing| existing as usize == hook as usize) {
+        hooks.push(hook);
+    }
+}
+
+/// Invokes every registered cleanup function for the task with the given ID,
+/// which should be called when that task exits.
+pub fn release_owned_by(owner: usize) {
+    // Hooks are invoked without the lock held, since dropping values may register other hooks.
+    let hooks = OWNER_CLEANUP_HOOKS.lock().clone();
+    for hook in hooks {
+        hook(owner);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stale_handles() {
+        let mut table = HandleTable::new();
+        let a = table.insert("a");
+        let b = table.insert("b");
+        assert_eq!((a.to_raw(), b.to_raw()), (1, 2));
+        assert_eq!(table.remove(a), Some("a"));
+        assert_eq!(table.remove(a), None);
+
+        let c = table.insert("c");
+        assert_eq!(c.index(), a.index());
+        assert_ne!(c, a);
+        assert_eq!(table.get(a), None);
+        assert_eq!(table.get(c), Some(&"c"));
+        assert_eq!(table.get(b), Some(&"b"));
+        assert_eq!(table.len(), 2);
+        assert_eq!(Handle::from_raw(c.to_raw()), Some(c));
+        assert_eq!(Handle::from_raw(0), None);
+    }
+
+    #[test]
+    fn retired_slots() {
+        let mut table = HandleTable::new();
+        let a = table.insert(1);
+        table.slots[0].generation = u32::MAX;
+        let max = Handle { index: a.index(), generation: u32::MAX };
+        assert_eq!(table.remove(max), Some(1));
+        let b = table.insert(2);
+        assert_ne!(b.index(), a.index());
+        assert!(table.iter().map(|(handle, _)| handle).eq([b]));
+    }
+
+    #[test]
+    fn owners() {
+        let mut table = HandleTable::new();
+        let a = table.insert_owned(7, 'a');
+        let b = table.insert('b');
+        let c = table.insert_owned(7, 'c');
+        let d = table.insert_owned(8, 'd');
+        assert_eq!(table.owner(a), Some(7));
+        assert_eq!(table.owner(b), None);
+        assert!(table.set_owner(b, Some(9)));
+        assert_eq!(table.owner(b), Some(9));
+        assert_eq!(table.remove_owned_by(7), [(a, 'a'), (c, 'c')]);
+        assert!(table.iter().map(|(handle, _)| handle).eq([b, d]));
+    }
+}
@@ -0,0 +1,148 @@
+//! The CRC-32 and CRC-32C checksums.
+//!
+//! Both use the reflected bit order with an initial value and final XOR of `0xFFFFFFFF`,
+//! and differ only in their polynomials.
+
+use crate::Digest;
+use cpu_features::Feature;
+
+/// The reversed CRC-32 (IEEE 802.3) polynomial.
+const CRC32_POLYNOMIAL: u32 = 0xEDB8_8320;
+/// The reversed CRC-32C (Castagnoli) polynomial.
+const CRC32C_POLYNOMIAL: u32 = 0x82F6_3B78;
+
+static CRC32_TABLE: [u32; 256] = make_table(CRC32_POLYNOMIAL);
+static CRC32C_TABLE: [u32; 256] = make_table(CRC32C_POLYNOMIAL);
+
+/// Returns the table of the checksums of every byte value for the given polynomial.
+const fn make_table(polynomial: u32) -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ polynomial } else { crc >> 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+/// Updates the given (non-inverted) checksum with the given bytes, one byte at a time.
+fn update_with_table(table: &[u32; 256], crc: u32, bytes: &[u8]) -> u32 {
+    bytes
+        .iter()
+        .fold(crc, |crc, &byte| table[((crc ^ byte as u32) & 0xFF) as usize] ^ (crc >> 8))
+}
+
+/// The CRC-32 checksum, as used by Ethernet, gzip, and zip.
+#[derive(Clone, Copy, Debug)]
+pub struct Crc32(u32);
+
+impl Default for Crc32 {
+    fn default() -> Self {
+        Crc32(!0)
+    }
+}
+
+impl Digest for Crc32 {
+    type Output = u32;
+
+    fn update(&mut self, bytes: &[u8]) {
+        self.0 = update_with_table(&CRC32_TABLE, self.0, bytes);
+    }
+
+    fn finalize(self) -> u32 {
+        !self.0
+    }
+}
+
+/// The CRC-32C (Castagnoli) checksum, as used by iSCSI, ext4, and SCTP.
+///
+/// This uses the `crc32` instruction if the CPU supports SSE4.2.
+#[derive(Clone, Copy, Debug)]
+pub struct Crc32c(u32);
+
+impl Default for Crc32c {
+    fn default() -> Self {
+        Crc32c(!0)
+    }
+}
+
+impl Digest for Crc32c {
+    type Output = u32;
+
+    fn update(&mut self, bytes: &[u8]) {
+        self.0 = if cpu_features::has_feature(Feature::Sse4_2) {
+            update_with_instruction(self.0, bytes)
+        } else {
+            update_with_table(&CRC32C_TABLE, self.0, bytes)
+        };
+    }
+
+    fn finalize(self) -> u32 {
+        !self.0
+    }
+}
+
+/// Updates the given (non-inverted) CRC-32C checksum using the SSE4.2 `crc32` instruction,
+/// eight bytes at a time.
+///
+/// This uses inline assembly rather than the `_mm_crc32_*` intrinsics
+/// because the kernel is compiled without SSE, and enabling SSE4.2 for this function
+/// would allow the compiler to use SIMD registers, whereas `crc32` only uses general-purpose registers.
+#[cfg(target_arch = "x86_64")]
+fn update_with_instruction(crc: u32, bytes: &[u8]) -> u32 {
+    let mut chunks = bytes.chunks_exact(8);
+    let mut crc = crc as u64;
+    for chunk in &mut chunks {
+        let word = u64::from_le_bytes(chunk.try_into().unwrap());
+        // SAFETY: the caller checked that the CPU supports SSE4.2.
+        unsafe {
+            core::arch::asm!("crc32 {0}, {1}", inout(reg) crc, in(reg) word, options(pure, nomem, nostack));
+        }
+    }
+    let mut crc = crc as u32;
+    for &byte in chunks.remainder() {
+        // SAFETY: the caller checked that the CPU supports SSE4.2.
+        unsafe {
+            core::arch::asm!("crc32 {0:e}, {1}", inout(reg) crc, in(reg_byte) byte, options(pure, nomem, nostack));
+        }
+    }
+    crc
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn update_with_instruction(crc: u32, bytes: &[u8]) -> u32 {
+    update_with_table(&CRC32C_TABLE, crc, bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_values() {
+        assert_eq!(Crc32::digest(b"123456789"), 0xCBF4_3926);
+        assert_eq!(Crc32::digest(b""), 0);
+        assert_eq!(Crc32c::digest(b"123456789"), 0xE306_9283);
+        assert_eq!(Crc32c::digest(b""), 0);
+    }
+
+    #[test]
+    fn instruction_matches_table() {
+        if !cpu_features::has_feature(Feature::Sse4_2) {
+            return;
+        }
+        let bytes: alloc::vec::Vec<u8> = (0..=255).cycle().take(1000).collect();
+        for len in [0, 1, 7, 8, 9, 63, 1000] {
+            assert_eq!(
+                update_with_instruction(!0, &bytes[..len]),
+                update_with_table(&CRC32C_TABLE, !0, &bytes[..len]),
+            );
+        }
+    }
+}
@@ -0,0 +1,60 @@
+//! Checksums and hash functions for verifying the integrity of data.
+//!
+//! All algorithms implement the [`Digest`] trait, which hashes data incrementally:
+//! * [`Crc32`]: the CRC-32 used by Ethernet, gzip, zip, and PNG, among others.
+//! * [`Crc32c`]: the CRC-32C (Castagnoli) used by iSCSI, ext4, and SCTP,
+//!   which uses the SSE4.2 `crc32` instruction when the CPU supports it.
+//! * [`XxHash64`]: a fast non-cryptographic hash, also used by zstd frame checksums.
+//! * [`Sha256`]: the SHA-256 cryptographic hash.
+//!
+//! ```
+//! use hashing::{Crc32c, Digest};
+//!
+//! let mut crc = Crc32c::default();
+//! crc.update(b"1234");
+//! crc.update(b"56789");
+//! assert_eq!(crc.finalize(), Crc32c::digest(b"123456789"));
+//! ```
+
+#![no_std]
+
+extern crate alloc;
+
+mod crc32;
+mod sha256;
+mod xxhash;
+
+pub use crc32::{Crc32, Crc32c};
+pub use sha256::Sha256;
+pub use xxhash::XxHash64;
+
+use alloc::string::String;
+use core::fmt::Write;
+
+/// A hash function that hashes data incrementally.
+pub trait Digest: Default {
+    /// The type of the resulting hash value.
+    type Output;
+
+    /// Hashes the given bytes, as if they were appended to the bytes hashed so far.
+    fn update(&mut self, bytes: &[u8]);
+
+    /// Returns the hash value of all of the bytes hashed so far.
+    fn finalize(self) -> Self::Output;
+
+    /// Returns the hash value of the given bytes.
+    fn digest(bytes: &[u8]) -> Self::Output {
+        let mut hasher = Self::default();
+        hasher.update(bytes);
+        hasher.finalize()
+    }
+}
+
+/// Returns the given bytes, e.g., a [`Sha256`] hash value, as a string of lowercase hex digits.
+pub fn to_hex(bytes: &[u8]) -> String {
+    let mut hex = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        let _ = write!(hex, "{:02x}", byte);
+    }
+    hex
+}
@@ -0,0 +1,171 @@
+//! The 64-bit xxHash (XXH64) hash function.
+
+use crate::Digest;
+use core::hash::Hasher;
+
+const PRIME_1: u64 = 0x9E37_79B1_85EB_CA87;
+const PRIME_2: u64 = 0xC2B2_AE3D_27D4_EB4F;
+const PRIME_3: u64 = 0x1656_67B1_9E37_79F9;
+const PRIME_4: u64 = 0x85EB_CA77_C2B2_AE63;
+const PRIME_5: u64 = 0x27D4_EB2F_1656_67C5;
+
+/// The number of bytes consumed by each round of the four accumulators.
+const STRIPE_SIZE: usize = 32;
+
+/// The XXH64 hash function, a fast non-cryptographic hash.
+///
+/// This also implements [`Hasher`], so it can be used with hash maps.
+#[derive(Clone, Debug)]
+pub struct XxHash64 {
+    seed: u64,
+    accumulators: [u64; 4],
+    /// The bytes that don't yet fill a stripe.
+    buffer: [u8; STRIPE_SIZE],
+    buffered: usize,
+    total_len: u64,
+}
+
+impl XxHash64 {
+    /// Creates a hasher with the given seed.
+    pub fn with_seed(seed: u64) -> XxHash64 {
+        XxHash64 {
+            seed,
+            accumulators: [
+                seed.wrapping_add(PRIME_1).wrapping_add(PRIME_2),
+                seed.wrapping_add(PRIME_2),
+                seed,
+                seed.wrapping_sub(PRIME_1),
+            ],
+            buffer: [0; STRIPE_SIZE],
+            buffered: 0,
+            total_len: 0,
+        }
+    }
+
+    fn consume_stripe(&mut self, stripe: &[u8]) {
+        for (accumulator, lane) in self.accumulators.iter_mut().zip(stripe.chunks_exact(8)) {
+            *accumulator = round(*accumulator, read_u64(lane));
+        }
+    }
+}
+
+impl Default for XxHash64 {
+    fn default() -> Self {
+        XxHash64::with_seed(0)
+    }
+}
+
+impl Digest for XxHash64 {
+    type Output = u64;
+
+    fn update(&mut self, mut bytes: &[u8]) {
+        self.total_len += bytes.len() as u64;
+
+        if self.buffered > 0 {
+            let count = bytes.len().min(STRIPE_SIZE - self.buffered);
+            self.buffer[self.buffered..self.buffered + count].copy_from_slice(&bytes[..count]);
+            self.buffered += count;
+            bytes = &bytes[count..];
+            if self.buffered < STRIPE_SIZE {
+                return;
+            }
+            let stripe = self.buffer;
+            self.consume_stripe(&stripe);
+            self.buffered = 0;
+        }
+
+        let mut stripes = bytes.chunks_exact(STRIPE_SIZE);
+        for stripe in &mut stripes {
+            self.consume_stripe(stripe);
+        }
+        let remainder = stripes.remainder();
+        self.buffer[..remainder.len()].copy_from_slice(remainder);
+        self.buffered = remainder.len();
+    }
+
+    fn finalize(self) -> u64 {
+        let mut hash = if self.total_len >= STRIPE_SIZE as u64 {
+            let [a, b, c, d] = self.accumulators;
+            let mut hash = a
+                .rotate_left(1)
+                .wrapping_add(b.rotate_left(7))
+                .wrapping_add(c.rotate_left(12))
+                .wrapping_add(d.rotate_left(18));
+            for accumulator in self.accumulators {
+                hash = (hash ^ round(0, accumulator)).wrapping_mul(PRIME_1).wrapping_add(PRIME_4);
+            }
+            hash
+        } else {
+            self.seed.wrapping_add(PRIME_5)
+        };
+        hash = hash.wrapping_add(self.total_len);
+
+        let mut rest = &self.buffer[..self.buffered];
+        while rest.len() >= 8 {
+            hash ^= round(0, read_u64(rest));
+            hash = hash.rotate_left(27).wrapping_mul(PRIME_1).wrapping_add(PRIME_4);
+            rest = &rest[8..];
+        }
+        if rest.len() >= 4 {
+            hash ^= (u32::from_le_bytes(rest[..4].try_into().unwrap()) as u64).wrapping_mul(PRIME_1);
+            hash = hash.rotate_left(23).wrapping_mul(PRIME_2).wrapping_add(PRIME_3);
+            rest = &rest[4..];
+        }
+        for &byte in rest {
+            hash ^= (byte as u64).wrapping_mul(PRIME_5);
+            hash = hash.rotate_left(11).wrapping_mul(PRIME_1);
+        }
+
+        hash ^= hash >> 33;
+        hash = hash.wrapping_mul(PRIME_2);
+        hash ^= hash >> 29;
+        hash = hash.wrapping_mul(PRIME_3);
+        hash ^ (hash >> 32)
+    }
+}
+
+impl Hasher for XxHash64 {
+    fn write(&mut self, bytes: &[u8]) {
+        self.update(bytes);
+    }
+
+    fn finish(&self) -> u64 {
+        self.clone().finalize()
+    }
+}
+
+fn round(accumulator: u64, lane: u64) -> u64 {
+    accumulator
+        .wrapping_add(lane.wrapping_mul(PRIME_2))
+        .rotate_left(31)
+        .wrapping_mul(PRIME_1)
+}
+
+fn read_u64(bytes: &[u8]) -> u64 {
+    u64::from_le_bytes(bytes[..8].try_into().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn known_values() {
+        assert_eq!(XxHash64::digest(b""), 0xEF46_DB37_51D8_E999);
+        assert_eq!(XxHash64::digest(b"a"), 0xD24E_C4F1_A98C_6E5B);
+        assert_eq!(XxHash64::digest(b"abc"), 0x44BC_2CF5_AD77_0999);
+    }
+
+    #[test]
+    fn incremental() {
+        let bytes: alloc::vec::Vec<u8> = (0..=255).collect();
+        let expected = XxHash64::digest(&bytes);
+        for split in [1, 5, 31, 32, 33, 100] {
+            let mut hasher = XxHash64::default();
+            for chunk in bytes.chunks(split) {
+                hasher.update(chunk);
+            }
+            assert_eq!(hasher.finalize(), expected);
+        }
+    }
+}
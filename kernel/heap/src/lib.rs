@@ -11,6 +11,7 @@ extern crate spin;
 extern crate memory;
 extern crate kernel_config;
 extern crate block_allocator;
+extern crate alloc_profiler;
 
 use alloc::alloc::{GlobalAlloc, Layout};
 use memory::PteFlags;
@@ -78,17 +79,20 @@ impl Heap {
 unsafe impl GlobalAlloc for Heap {
 
     unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
-        match DEFAULT_ALLOCATOR.get() {
+        let ptr = match DEFAULT_ALLOCATOR.get() {
             Some(allocator) => {
                 allocator.alloc(layout)
             }
             None => {       
                 self.initial_allocator.lock().allocate(layout)
             }
-        }
+        };
+        alloc_profiler::record_heap_alloc(ptr, layout.size());
+        ptr
     }
 
     unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        alloc_profiler::record_heap_dealloc(ptr, layout.size());
         if KERNEL_HEAP_START <= (ptr as usize) && (ptr as usize) < INITIAL_HEAP_END_ADDR {
             self.initial_allocator.lock().deallocate(ptr, layout);
         }
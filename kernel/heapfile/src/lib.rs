@@ -8,6 +8,7 @@ extern crate spin;
 extern crate memory;
 extern crate fs_node;
 extern crate io;
+extern crate fs_quota;
 
 
 use alloc::{
@@ -19,6 +20,7 @@ use io::{ByteReader, ByteWriter, IoError, KnownLength};
 use spin::Mutex;
 use fs_node::{FileOrDir, FileRef, DirRef, WeakDirRef, File, FsNode};
 use memory::MappedPages;
+use fs_quota::FileOwner;
 
 /// A file in memory that is backed by the heap, i.e., a `Vec`.
 pub struct HeapFile {
@@ -28,6 +30,8 @@ pub struct HeapFile {
     vec: Vec<u8>,
     /// The parent directory that contains this file.
     parent: WeakDirRef,
+    /// The task group that owns this file, which is charged for the length of its contents.
+    owner: FileOwner,
 }
 
 impl HeapFile {
@@ -40,10 +44,13 @@ impl HeapFile {
     /// Creates a new `HeapFile` in the given `parent` directory with the contents of the given `Vec`.
     /// No additional allocation or reallocation is performed.
     pub fn from_vec(vec: Vec<u8>, name: String, parent: &DirRef) -> Result<FileRef, &'static str> {
+        let mut owner = FileOwner::for_current_group()?;
+        owner.charge(vec.len())?;
         let hf = HeapFile {
             name, 
             vec, 
             parent: Arc::downgrade(parent), 
+            owner,
         };
         let file_ref = Arc::new(Mutex::new(hf)) as FileRef;
         parent.lock().insert(FileOrDir::File(file_ref.clone()))?;
@@ -68,6 +75,7 @@ impl ByteWriter for HeapFile {
         let final_len = offset + buffer.len();
         // Handle the need for reallocation and padding bytes.
         if final_len > self.vec.len() {
+            self.owner.charge(final_len - self.vec.len())?;
             self.vec.resize(final_len, 0u8);
         }
 
@@ -1,4 +1,8 @@
 //! Functions for creating and sending HTTP requests and receiving responses.
+//!
+//! Responses whose content was compressed by the server, as indicated by their
+//! `Content-Encoding` header, are decompressed upon receipt.
+//! Requests can include [`ACCEPT_ENCODING_HEADER`] to allow the server to compress content.
 
 #![no_std]
 #![feature(slice_concat_ext)]
@@ -23,6 +27,9 @@ enum HttpState {
     Responded,
 }
 
+/// A request header that tells the server which compressed content encodings this client accepts.
+pub const ACCEPT_ENCODING_HEADER: &str = "Accept-Encoding: zstd";
+
 /// Checks to see if the provided HTTP request can be properly parsed, and returns true if so.
 pub fn check_http_request(request_bytes: &[u8]) -> bool {
     let mut headers = [httparse::EMPTY_HEADER; 64];
@@ -39,6 +46,9 @@ pub type HttpRequest = String;
 pub struct HttpResponse {
     /// The actual array of raw bytes received from the server, including all of the headers and
     /// body.
+    ///
+    /// If the body was compressed, it has already been decompressed,
+    /// so it may not match the `Content-Length` header.
     pub packet: Vec<u8>,
     /// The length of all headers
     pub header_length: usize,
@@ -318,11 +328,14 @@ impl<'a> HttpClient<'a> {
             }
         }
 
+        let header_length = packet_header_length.ok_or(
+            "BUG: received full HTTP response but couldn't determine packet header length",
+        )?;
+        decode_content(&mut packet_byte_buffer, header_length)?;
+
         Ok(HttpResponse {
             packet: packet_byte_buffer,
-            header_length: packet_header_length.ok_or(
-                "BUG: received full HTTP response but couldn't determine packet header length",
-            )?,
+            header_length,
             status_code: response_status_code
                 .ok_or("BUG: received full HTTP response but couldn't determine its status code")?,
             reason: response_reason.ok_or(
@@ -331,3 +344,42 @@ impl<'a> HttpClient<'a> {
         })
     }
 }
+
+/// Decompresses the content of the given response packet in place
+/// if its `Content-Encoding` header indicates that it was compressed.
+fn decode_content(packet: &mut Vec<u8>, header_length: usize) -> Result<(), &'static str> {
+    let format = {
+        let mut headers = [httparse::EMPTY_HEADER; 64];
+        let mut response = httparse::Response::new(&mut headers);
+        response
+            .parse(&packet[..header_length])
+            .map_err(|_| "http_client: failed to parse HTTP response headers")?;
+        let Some(header) = response
+            .headers
+            .iter()
+            .find(|h| h.name.eq_ignore_ascii_case("Content-Encoding"))
+        else {
+            return Ok(());
+        };
+        let encoding = str::from_utf8(header.value)
+            .map_err(|_| "http_client: Content-Encoding header value was not UTF-8")?;
+        if encoding.trim().eq_ignore_ascii_case("identity") {
+            return Ok(());
+        }
+        decompress::Format::from_encoding(encoding).ok_or_else(|| {
+            error!("http_client: unsupported Content-Encoding {:?}", encoding);
+            "http_client: response had an unsupported Content-Encoding"
+        })?
+    };
+
+    let content = decompress::decompress(format, &packet[header_length..])?;
+    trace!(
+        "http_client: decompressed {} bytes of {} content into {} bytes",
+        packet.len() - header_length,
+        format.encoding(),
+        content.len()
+    );
+    packet.truncate(header_length);
+    packet.extend_from_slice(&content);
+    Ok(())
+}
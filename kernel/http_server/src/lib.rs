@@ -0,0 +1,246 @@
+//! A small HTTP/1.1 server.
+//!
+//! Handlers are registered for each method and path on a [`Router`],
+//! which an [`HttpServer`] uses to respond to requests received on a TCP port.
+//!
+//! Handlers can respond with any value that implements [`serialize::Serialize`] as JSON
+//! using [`Response::serialized()`].
+//!
+//! Every response is sent with `Connection: close`, so each connection serves a single request.
+//! The number of concurrent connections is fixed when the server is created.
+
+#![no_std]
+
+extern crate alloc;
+
+mod router;
+
+pub use router::{reason_phrase, Handler, Request, Response, Router};
+
+use alloc::{sync::Arc, vec, vec::Vec};
+use core::time::Duration;
+use log::{trace, warn};
+use metrics::{Counter, Histogram};
+use net::{tcp, NetworkInterface, Socket};
+use time::Instant;
+
+/// The maximum size of a request, including its header and body.
+pub const MAX_REQUEST_SIZE: usize = 64 * 1024;
+
+/// How long a connection may be idle before it's aborted.
+const CONNECTION_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long the server sleeps in [`HttpServer::run()`] when there's nothing to do.
+const IDLE_POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+const SOCKET_RX_BUFFER_SIZE: usize = 4096;
+const SOCKET_TX_BUFFER_SIZE: usize = 8192;
+
+static REQUESTS: Counter = Counter::new(
+    "theseus_http_requests_total",
+    "The number of HTTP requests handled by all HTTP servers.",
+);
+
+static REQUEST_DURATION: Histogram = Histogram::new(
+    "theseus_http_request_duration_microseconds",
+    "The time taken by HTTP handlers to produce a response.",
+    &[100, 500, 1_000, 5_000, 10_000, 50_000, 100_000, 500_000, 1_000_000],
+);
+
+/// A connection to a single client, or a socket listening for one.
+struct Connection {
+    socket: Socket<tcp::Socket<'static>>,
+    /// The bytes of the request received so far.
+    request: Vec<u8>,
+    /// The bytes of the response and how many of them were sent so far.
+    response: Option<(Vec<u8>, usize)>,
+    /// Whether the response was fully sent and the connection is being closed.
+    closing: bool,
+    last_activity: Instant,
+}
+
+/// Takes the request from the given received bytes if it's complete,
+/// or returns an error status code if it's invalid.
+fn take_request(received: &mut Vec<u8>) -> Option<Result<Request, u16>> {
+    let (mut request, header_len) = match router::parse_request_head(received) {
+        Ok(Some(parsed)) => parsed,
+        Ok(None) if received.len() >= MAX_REQUEST_SIZE => return Some(Err(431)),
+        Ok(None) => return None,
+        Err(status) => return Some(Err(status)),
+    };
+    let body_len = match router::content_length(&request) {
+        Ok(len) if header_len + len > MAX_REQUEST_SIZE => return Some(Err(413)),
+        Ok(len) => len,
+        Err(status) => return Some(Err(status)),
+    };
+    if received.len() < header_len + body_len {
+        return None;
+    }
+    request.body = received[header_len..header_len + body_len].to_vec();
+    received.clear();
+    Some(Ok(request))
+}
+
+/// An HTTP server that responds to requests on a port of a network interface.
+pub struct HttpServer {
+    interface: Arc<NetworkInterface>,
+    port: u16,
+    router: Router,
+    connections: Vec<Connection>,
+}
+
+impl HttpServer {
+    /// Creates a server that listens on the given `port` of the given `interface`,
+    /// serving up to `max_connections` clients at once using the given `router`.
+    pub fn new(
+        interface: Arc<NetworkInterface>,
+        port: u16,
+        router: Router,
+        max_connections: usize,
+    ) -> Result<Self, &'static str> {
+        if max_connections == 0 {
+            return Err("an HTTP server must allow at least one connection");
+        }
+        metrics::register(&REQUESTS)?;
+        metrics::register(&REQUEST_DURATION)?;
+        let connections = (0..max_connections)
+            .map(|_| {
+                let rx_buffer = tcp::SocketBuffer::new(vec![0; SOCKET_RX_BUFFER_SIZE]);
+                let tx_buffer = tcp::SocketBuffer::new(vec![0; SOCKET_TX_BUFFER_SIZE]);
+                let socket = interface
+                    .clone()
+                    .add_socket(tcp::Socket::new(rx_buffer, tx_buffer));
+                socket
+                    .lock()
+                    .listen(port)
+                    .map_err(|_| "failed to listen on HTTP server socket")?;
+                Ok(Connection {
+                    socket,
+                    request: Vec::new(),
+                    response: None,
+                    closing: false,
+                    last_activity: Instant::now(),
+                })
+            })
+            .collect::<Result<Vec<_>, &'static str>>()?;
+
+        Ok(Self {
+            interface,
+            port,
+            router,
+            connections,
+        })
+    }
+
+    /// Returns the port that this server listens on.
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Serves requests forever, or until an error occurs.
+    pub fn run(&mut self) -> Result<(), &'static str> {
+        loop {
+            self.interface.poll();
+            if !self.poll() {
+                sleep::sleep(IDLE_POLL_INTERVAL).map_err(|_| "HTTP server failed to sleep")?;
+            }
+        }
+    }
+
+    /// Makes progress on every connection, without polling the interface.
+    ///
+    /// Returns whether any connection made progress.
+    pub fn poll(&mut self) -> bool {
+        let mut progress = false;
+        for i in 0..self.connections.len() {
+            progress |= self.poll_connection(i);
+        }
+        progress
+    }
+
+    fn poll_connection(&mut self, index: usize) -> bool {
+        let Connection {
+            socket,
+            request,
+            response,
+            closing,
+            last_activity,
+        } = &mut self.connections[index];
+        let mut socket = socket.lock();
+
+        if !socket.is_open() {
+            request.clear();
+            *response = None;
+            *closing = false;
+            if socket.listen(self.port).is_err() {
+                warn!("http_server: failed to listen on port {}", self.port);
+            }
+            return false;
+        }
+        if !socket.is_active() {
+            // Still listening for a client.
+            *last_activity = Instant::now();
+            return false;
+        }
+        if last_activity.elapsed() >= CONNECTION_TIMEOUT {
+            trace!("http_server: aborting idle connection from {:?}", socket.remote_endpoint());
+            socket.abort();
+            return false;
+        }
+        if *closing {
+            return false;
+        }
+
+        if let Some((bytes, sent)) = response {
+            if !socket.can_send() {
+                return false;
+            }
+            match socket.send_slice(&bytes[*sent..]) {
+                Ok(0) => return false,
+                Ok(n) => *sent += n,
+                Err(_) => {
+                    socket.abort();
+                    return false;
+                }
+            }
+            *last_activity = Instant::now();
+            if *sent == bytes.len() {
+                socket.close();
+                *response = None;
+                *closing = true;
+            }
+            return true;
+        }
+
+        if !socket.can_recv() {
+            if !socket.may_recv() {
+                // The client closed its side of the connection before sending a full request.
+                socket.close();
+                *closing = true;
+            }
+            return false;
+        }
+        let _ = socket.recv(|data| {
+            request.extend_from_slice(data);
+            (data.len(), ())
+        });
+        *last_activity = Instant::now();
+
+        // The handler may use the network interface, so the socket must not be locked while it runs.
+        drop(socket);
+        let bytes = match take_request(request) {
+            Some(Ok(request)) => {
+                trace!("http_server: {} {}", request.method, request.path);
+                let start = Instant::now();
+                let response = self.router.handle(&request);
+                REQUESTS.inc();
+                REQUEST_DURATION.observe(start.elapsed().as_micros() as u64);
+                response.to_bytes(request.method != "HEAD")
+            }
+            Some(Err(status)) => Response::error(status).to_bytes(true),
+            None => return true,
+        };
+        *response = Some((bytes, 0));
+        true
+    }
+}
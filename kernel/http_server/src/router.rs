@@ -0,0 +1,275 @@
+//! Requests, responses, and the routes that map between them.
+
+use alloc::{
+    boxed::Box,
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::str;
+use serialize::Serialize;
+
+/// A handler that produces a response for a request.
+pub type Handler = Box<dyn Fn(&Request) -> Response + Send + Sync>;
+
+/// An HTTP request received by the server.
+#[derive(Debug)]
+pub struct Request {
+    /// The request method, e.g., `GET`.
+    pub method: String,
+    /// The path of the request target, without the query string.
+    pub path: String,
+    /// The query string of the request target, without the leading `?`.
+    pub query: Option<String>,
+    /// The request headers, in the order they were received.
+    pub headers: Vec<(String, Vec<u8>)>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    /// Returns the value of the first header with the given `name`, which is case-insensitive.
+    pub fn header(&self, name: &str) -> Option<&[u8]> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_slice())
+    }
+
+    /// Returns the value of the given `key` in the query string, if present.
+    pub fn query_param(&self, key: &str) -> Option<&str> {
+        self.query.as_deref()?.split('&').find_map(|pair| {
+            let (k, v) = pair.split_once('=').unwrap_or((pair, ""));
+            (k == key).then_some(v)
+        })
+    }
+}
+
+/// An HTTP response to be sent by the server.
+#[derive(Clone, Debug)]
+pub struct Response {
+    /// The status code, e.g., 200 or 404.
+    pub status: u16,
+    /// Additional headers; `Content-Length` and `Connection` are always added by the server.
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    /// Returns a response with the given `status` code and no body.
+    pub fn new(status: u16) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+
+    /// Returns a response with the given `status` code and a body of the given `content_type`.
+    pub fn with_body(status: u16, content_type: &str, body: impl Into<Vec<u8>>) -> Self {
+        Self::new(status)
+            .header("Content-Type", content_type)
+            .body(body)
+    }
+
+    /// Returns a successful response with a plain text body.
+    pub fn text(body: impl Into<String>) -> Self {
+        Self::with_body(200, "text/plain; charset=utf-8", body.into())
+    }
+
+    /// Returns a successful response with a JSON body.
+    pub fn json(body: impl Into<String>) -> Self {
+        Self::with_body(200, "application/json", body.into())
+    }
+
+    /// Returns a successful response whose body is the given `value` serialized as JSON,
+    /// or an error response if it couldn't be serialized.
+    pub fn serialized<T: Serialize + ?Sized>(value: &T) -> Self {
+        match serialize::to_json(value) {
+            Ok(body) => Self::json(body),
+            Err(_) => Self::error(500),
+        }
+    }
+
+    /// Returns an error response with the given `status` code,
+    /// whose body is the status code's reason phrase.
+    pub fn error(status: u16) -> Self {
+        Self::with_body(status, "text/plain; charset=utf-8", reason_phrase(status))
+    }
+
+    /// Adds a header to this response.
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Replaces the body of this response.
+    pub fn body(mut self, body: impl Into<Vec<u8>>) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    /// Serializes this response into the bytes sent on the wire.
+    ///
+    /// If `include_body` is false, e.g., for a `HEAD` request,
+    /// the headers still describe the body but the body itself is left out.
+    pub(crate) fn to_bytes(&self, include_body: bool) -> Vec<u8> {
+        let mut bytes = format!("HTTP/1.1 {} {}\r\n", self.status, reason_phrase(self.status))
+            .into_bytes();
+        for (name, value) in &self.headers {
+            bytes.extend_from_slice(format!("{name}: {value}\r\n").as_bytes());
+        }
+        bytes.extend_from_slice(
+            format!("Content-Length: {}\r\nConnection: close\r\n\r\n", self.body.len()).as_bytes(),
+        );
+        if include_body {
+            bytes.extend_from_slice(&self.body);
+        }
+        bytes
+    }
+}
+
+/// Returns the standard reason phrase for the given status code.
+pub fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        204 => "No Content",
+        301 => "Moved Permanently",
+        302 => "Found",
+        304 => "Not Modified",
+        400 => "Bad Request",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        408 => "Request Timeout",
+        413 => "Payload Too Large",
+        431 => "Request Header Fields Too Large",
+        500 => "Internal Server Error",
+        501 => "Not Implemented",
+        503 => "Service Unavailable",
+        _ => "Unknown",
+    }
+}
+
+struct Route {
+    method: String,
+    path: String,
+    handler: Handler,
+}
+
+/// A set of handlers, each registered for a method and path.
+///
+/// A path ending with `/*` matches every path that starts with it, excluding the `*`.
+/// If multiple routes match a request, the one that was registered first is used.
+#[derive(Default)]
+pub struct Router {
+    routes: Vec<Route>,
+}
+
+impl Router {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a handler for requests with the given `method` and `path`.
+    pub fn route<F>(&mut self, method: &str, path: &str, handler: F) -> &mut Self
+    where
+        F: Fn(&Request) -> Response + Send + Sync + 'static,
+    {
+        self.routes.push(Route {
+            method: method.to_string(),
+            path: path.to_string(),
+            handler: Box::new(handler),
+        });
+        self
+    }
+
+    /// Registers a handler for `GET` requests with the given `path`.
+    ///
+    /// The handler is also used for `HEAD` requests, whose response body is left out.
+    pub fn get<F>(&mut self, path: &str, handler: F) -> &mut Self
+    where
+        F: Fn(&Request) -> Response + Send + Sync + 'static,
+    {
+        self.route("GET", path, handler)
+    }
+
+    /// Returns the paths of all registered routes, in the order they were registered.
+    pub fn paths(&self) -> impl Iterator<Item = &str> {
+        self.routes.iter().map(|r| r.path.as_str())
+    }
+
+    /// Returns the response of the handler registered for the given `request`.
+    pub fn handle(&self, request: &Request) -> Response {
+        let method = match request.method.as_str() {
+            "HEAD" => "GET",
+            m => m,
+        };
+        let mut path_matched = false;
+        for route in self.routes.iter().filter(|r| path_matches(&r.path, &request.path)) {
+            if route.method == method {
+                return (route.handler)(request);
+            }
+            path_matched = true;
+        }
+        if path_matched {
+            Response::error(405)
+        } else {
+            Response::error(404)
+        }
+    }
+}
+
+fn path_matches(route_path: &str, path: &str) -> bool {
+    match route_path.strip_suffix('*') {
+        Some(prefix) if route_path.ends_with("/*") => path.starts_with(prefix),
+        _ => route_path == path,
+    }
+}
+
+/// Parses the request line and headers of a request from the start of `bytes`.
+///
+/// Returns `Ok(None)` if more bytes are needed, or the request (with an empty body)
+/// and the length of its header if they were complete.
+pub(crate) fn parse_request_head(bytes: &[u8]) -> Result<Option<(Request, usize)>, u16> {
+    let mut headers = [httparse::EMPTY_HEADER; 64];
+    let mut parsed = httparse::Request::new(&mut headers);
+    let header_len = match parsed.parse(bytes) {
+        Ok(httparse::Status::Complete(len)) => len,
+        Ok(httparse::Status::Partial) => return Ok(None),
+        Err(httparse::Error::TooManyHeaders) => return Err(431),
+        Err(_) => return Err(400),
+    };
+    let (method, target) = parsed.method.zip(parsed.path).ok_or(400u16)?;
+    let (path, query) = match target.split_once('?') {
+        Some((path, query)) => (path, Some(query.to_string())),
+        None => (target, None),
+    };
+    let request = Request {
+        method: method.to_string(),
+        path: path.to_string(),
+        query,
+        headers: parsed
+            .headers
+            .iter()
+            .map(|h| (h.name.to_string(), h.value.to_vec()))
+            .collect(),
+        body: Vec::new(),
+    };
+    Ok(Some((request, header_len)))
+}
+
+/// Returns the length of the body that follows the header of the given `request`.
+pub(crate) fn content_length(request: &Request) -> Result<usize, u16> {
+    if request.header("Transfer-Encoding").is_some() {
+        // Chunked request bodies aren't supported.
+        return Err(501);
+    }
+    match request.header("Content-Length") {
+        Some(value) => str::from_utf8(value)
+            .ok()
+            .and_then(|v| v.trim().parse().ok())
+            .ok_or(400),
+        None => Ok(0),
+    }
+}
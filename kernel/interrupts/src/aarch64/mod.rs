@@ -10,7 +10,7 @@ use tock_registers::interfaces::Readable;
 use tock_registers::registers::InMemoryRegister;
 
 use interrupt_controller::{
-    LocalInterruptController, SystemInterruptController, InterruptDestination,
+    LocalInterruptController, SystemInterruptController,
     LocalInterruptControllerApi, AArch64LocalInterruptControllerApi, SystemInterruptControllerApi,
 };
 use arm_boards::BOARD_CONFIG;
@@ -18,7 +18,7 @@ use sync_irq::IrqSafeRwLock;
 use cpu::current_cpu;
 use log::*;
 
-pub use interrupt_controller::InterruptNumber;
+pub use interrupt_controller::{InterruptNumber, InterruptDestination};
 
 // This assembly file contains trampolines to `extern "C"` functions defined below.
 global_asm!(include_str!("table.s"));
@@ -31,12 +31,12 @@ pub const PL011_RX_SPI: InterruptNumber = BOARD_CONFIG.pl011_rx_spi;
 /// which Theseus currently uses for preemptive task switching.
 pub const CPU_LOCAL_TIMER_IRQ: InterruptNumber = BOARD_CONFIG.cpu_local_timer_ppi;
 
-/// The IRQ/IPI number for TLB Shootdowns
+/// The IRQ/IPI number for cross-CPU function calls, which are also used for TLB Shootdowns.
 ///
 /// Note: This is arbitrarily defined in the range 0..16,
 /// which is reserved for IPIs (SGIs - for software generated
 /// interrupts - in GIC terminology).
-pub const TLB_SHOOTDOWN_IPI: InterruptNumber = 2;
+pub const CPU_CALL_IPI: InterruptNumber = 2;
 
 const MAX_IRQ_NUM: usize = 256;
 
@@ -57,6 +57,12 @@ struct EsrEL1(InMemoryRegister<u64, ESR_EL1::Register>);
 #[macro_export]
 #[doc = include_str!("../macro-doc.md")]
 macro_rules! interrupt_handler {
+    ($name:ident, _, mut $stack_frame:ident, $code:block) => {
+        interrupt_handler!($name, 0, $stack_frame, $code);
+    };
+    ($name:ident, $x86_64_eoi_param:expr, mut $stack_frame:ident, $code:block) => {
+        interrupt_handler!($name, $x86_64_eoi_param, $stack_frame, $code);
+    };
     ($name:ident, _, $stack_frame:ident, $code:block) => {
         interrupt_handler!($name, 0, $stack_frame, $code);
     };
@@ -119,9 +125,9 @@ pub fn init_ap() {
     int_ctrl.init_secondary_cpu_interface();
     int_ctrl.set_minimum_priority(0);
 
-    // Enable the TLB shootdown IPI to be delivered to this CPU.
-    // On the bootstrap CPU, this is done in `setup_tlb_shootdown_handler()`.
-    int_ctrl.enable_fast_local_interrupt(TLB_SHOOTDOWN_IPI, true);
+    // Enable the cross-CPU function call IPI to be delivered to this CPU.
+    // On the bootstrap CPU, this is done in `setup_cpu_call_handler()`.
+    int_ctrl.enable_fast_local_interrupt(CPU_CALL_IPI, true);
 
     // Enable the CPU-local timer interrupt to be delivered to this CPU.
     // On the bootstrap CPU, this is done in `setup_timer_interrupt()`.
@@ -189,12 +195,12 @@ pub fn setup_ipi_handler(handler: InterruptHandler, local_num: InterruptNumber)
     Ok(())
 }
 
-/// This function registers an interrupt handler for the TLB Shootdown IPI
+/// This function registers an interrupt handler for the cross-CPU function call IPI
 /// and handles interrupt controller configuration for that interrupt.
 ///
-/// Returns an error if the TLB Shootdown interrupt number already has a registered handler.
-pub fn setup_tlb_shootdown_handler(handler: InterruptHandler) -> Result<(), &'static str> {
-    if let Err(existing_handler) = register_interrupt(TLB_SHOOTDOWN_IPI, handler) {
+/// Returns an error if the function call interrupt number already has a registered handler.
+pub fn setup_cpu_call_handler(handler: InterruptHandler) -> Result<(), &'static str> {
+    if let Err(existing_handler) = register_interrupt(CPU_CALL_IPI, handler) {
         if handler as InterruptHandler != existing_handler {
             return Err("A different interrupt handler has already been setup for that IPI");
         }
@@ -204,7 +210,7 @@ pub fn setup_tlb_shootdown_handler(handler: InterruptHandler) -> Result<(), &'st
         // enable this interrupt as a Fast interrupt (FIQ / Group 0 interrupt)
         let int_ctrl = LocalInterruptController::get()
             .ok_or("LocalInterruptController was not yet initialized")?;
-        int_ctrl.enable_fast_local_interrupt(TLB_SHOOTDOWN_IPI, true);
+        int_ctrl.enable_fast_local_interrupt(CPU_CALL_IPI, true);
     }
 
     Ok(())
@@ -273,19 +279,20 @@ pub fn broadcast_ipi(ipi_num: InterruptNumber) {
     int_ctrl.send_ipi(ipi_num, InterruptDestination::AllOtherCpus);
 }
 
-/// Broadcast the TLB Shootdown Inter-Processor Interrupt to all other
-/// CPU cores in the system
+/// Sends the cross-CPU function call Inter-Processor Interrupt
+/// to the given CPU core(s) in the system
 ///
 /// This IPI uses fast interrupts (FIQs) as an NMI alternative.
-pub fn broadcast_tlb_shootdown_ipi() {
+pub fn send_cpu_call_ipi(destination: InterruptDestination) {
     let int_ctrl = LocalInterruptController::get()
         .expect("LocalInterruptController was not yet initialized");
-    int_ctrl.send_fast_ipi(TLB_SHOOTDOWN_IPI, InterruptDestination::AllOtherCpus);
+    int_ctrl.send_fast_ipi(CPU_CALL_IPI, destination);
 }
 
 /// Send an "end of interrupt" signal, notifying the interrupt chip that
 /// the given interrupt request `irq` has been serviced.
 pub fn eoi(irq_num: InterruptNumber) {
+    crate::count_interrupt(irq_num);
     let int_ctrl = LocalInterruptController::get()
         .expect("LocalInterruptController was not yet initialized");
     int_ctrl.end_of_interrupt(irq_num);
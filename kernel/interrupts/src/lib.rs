@@ -10,6 +10,37 @@ mod arch;
 
 pub use arch::*;
 
+use core::sync::atomic::{AtomicU64, Ordering};
+
+/// The number of times that each interrupt has been serviced, indexed by interrupt number.
+static INTERRUPT_COUNTS: [AtomicU64; 256] = {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const ZERO: AtomicU64 = AtomicU64::new(0);
+    [ZERO; 256]
+};
+
+/// Counts one more servicing of the given interrupt, which [`eoi`] does on every architecture.
+fn count_interrupt(num: InterruptNumber) {
+    INTERRUPT_COUNTS[num as usize].fetch_add(1, Ordering::Relaxed);
+}
+
+/// Invokes `func` with the interrupt number and count of each interrupt
+/// that has been serviced at least once on any CPU, in order of interrupt number.
+///
+/// Interrupts are counted when their handler signals the end of the interrupt via [`eoi`],
+/// so the counts don't include exceptions or interrupts that don't require an EOI.
+pub fn for_each_interrupt_count<F>(mut func: F)
+where
+    F: FnMut(InterruptNumber, u64),
+{
+    for (num, count) in INTERRUPT_COUNTS.iter().enumerate() {
+        let count = count.load(Ordering::Relaxed);
+        if count > 0 {
+            func(num as InterruptNumber, count);
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Eq)]
 #[repr(C)]
 pub enum EoiBehaviour {
@@ -37,6 +37,17 @@ static RESERVED_IRQ_LIST: [u8; 3] = [
 #[macro_export]
 #[doc = include_str!("../macro-doc.md")]
 macro_rules! interrupt_handler {
+    ($name:ident, _, mut $stack_frame:ident, $code:block) => {
+        interrupt_handler!($name, 0, mut $stack_frame, $code);
+    };
+    ($name:ident, $x86_64_eoi_param:expr, mut $stack_frame:ident, $code:block) => {
+        extern "x86-interrupt" fn $name(mut sf: $crate::InterruptStackFrame) {
+            let $stack_frame = &mut sf;
+            if let $crate::EoiBehaviour::HandlerDidNotSendEoi = $code {
+                $crate::eoi($x86_64_eoi_param);
+            }
+        }
+    };
     ($name:ident, _, $stack_frame:ident, $code:block) => {
         interrupt_handler!($name, 0, $stack_frame, $code);
     };
@@ -276,6 +287,23 @@ pub fn deregister_interrupt(interrupt_num: u8, func: InterruptHandler) -> Result
     }
 }
 
+/// Invokes `func` with the interrupt number and handler address of each
+/// IRQ (not exception) that currently has a handler registered.
+///
+/// The IDT is locked while `func` runs, so `func` must not register or deregister interrupts.
+pub fn for_each_registered_interrupt<F>(mut func: F)
+where
+    F: FnMut(InterruptNumber, usize),
+{
+    let idt = IDT.lock();
+    for (i, entry) in idt.slice(32..=255).iter().enumerate() {
+        let handler_addr = entry.handler_addr().as_u64() as usize;
+        if handler_addr != 0 && handler_addr != unimplemented_interrupt_handler as usize {
+            func((i + 32) as InterruptNumber, handler_addr);
+        }
+    }
+}
+
 /// Send an end of interrupt signal, notifying the interrupt chip that
 /// the given interrupt request `irq` has been serviced. 
 ///
@@ -285,6 +313,7 @@ pub fn deregister_interrupt(interrupt_num: u8, func: InterruptHandler) -> Result
 /// The `irq` argument is only used if the legacy `PIC` chip is active on this system;
 /// newer APIC chips do not use this.
 pub fn eoi(irq: InterruptNumber) {
+    crate::count_interrupt(irq);
     match INTERRUPT_CHIP.load() {
         InterruptChip::APIC | InterruptChip::X2APIC => {
             if let Some(my_apic) = apic::get_my_apic() {
@@ -200,7 +200,26 @@ pub trait BlockWriter: BlockIo {
 
     /// Flushes this entire writer's output stream, 
     /// ensuring all contents in intermediate buffers are fully written out. 
+    ///
+    /// For a storage device, this includes the device's own volatile write cache:
+    /// once this returns, all blocks written beforehand are durable,
+    /// i.e., they will survive a power loss.
+    /// Thus, this also acts as a write barrier: no block written afterwards
+    /// can become durable before those written beforehand.
     fn flush(&mut self) -> Result<(), IoError>;
+
+    /// Like [`BlockWriter::write_blocks()`], but with Force Unit Access (FUA) semantics:
+    /// once this returns, the written blocks are durable, whereas other blocks
+    /// still held in intermediate buffers or caches need not be.
+    ///
+    /// This is cheaper than [`BlockWriter::flush()`] on devices that support FUA natively,
+    /// e.g., for committing a single journal or superblock update.
+    /// The default implementation writes the blocks and then flushes this entire writer.
+    fn write_blocks_fua(&mut self, buffer: &[u8], block_offset: usize) -> Result<usize, IoError> {
+        let blocks_written = self.write_blocks(buffer, block_offset)?;
+        self.flush()?;
+        Ok(blocks_written)
+    }
 }
 
 impl<W> BlockWriter for Box<W> where W: BlockWriter + ?Sized {
@@ -208,12 +227,18 @@ impl<W> BlockWriter for Box<W> where W: BlockWriter + ?Sized {
         (**self).write_blocks(buffer, block_offset)
     }
     fn flush(&mut self) -> Result<(), IoError> { (**self).flush() }
+    fn write_blocks_fua(&mut self, buffer: &[u8], block_offset: usize) -> Result<usize, IoError> {
+        (**self).write_blocks_fua(buffer, block_offset)
+    }
 }
 impl<W> BlockWriter for &mut W where W: BlockWriter + ?Sized {
     fn write_blocks(&mut self, buffer: &[u8], block_offset: usize) -> Result<usize, IoError> {
         (**self).write_blocks(buffer, block_offset)
     }
     fn flush(&mut self) -> Result<(), IoError> { (**self).flush() }
+    fn write_blocks_fua(&mut self, buffer: &[u8], block_offset: usize) -> Result<usize, IoError> {
+        (**self).write_blocks_fua(buffer, block_offset)
+    }
 }
 
 
@@ -435,6 +460,7 @@ impl<RW> BlockWriter for ByteReaderWriterWrapper<RW> where RW: BlockReader + Blo
     delegate!{ to self.0 {
         fn write_blocks(&mut self, buffer: &[u8], block_offset: usize) -> Result<usize, IoError>;
         fn flush(&mut self) -> Result<(), IoError>;
+        fn write_blocks_fua(&mut self, buffer: &[u8], block_offset: usize) -> Result<usize, IoError>;
     } }
 }
 
@@ -471,7 +497,10 @@ impl<RW> KnownLength for ByteWriterWrapper<RW> where RW: KnownLength + BlockRead
     delegate!{ to self.0 { fn len(&self) -> usize; } }
 }
 impl<RW> BlockWriter for ByteWriterWrapper<RW> where RW: BlockReader + BlockWriter {
-    delegate!{ to self.0 { fn write_blocks(&mut self, buffer: &[u8], block_offset: usize) -> Result<usize, IoError>; } }
+    delegate!{ to self.0 {
+        fn write_blocks(&mut self, buffer: &[u8], block_offset: usize) -> Result<usize, IoError>;
+        fn write_blocks_fua(&mut self, buffer: &[u8], block_offset: usize) -> Result<usize, IoError>;
+    } }
     fn flush(&mut self) -> Result<(), IoError> { BlockWriter::flush(&mut self.0) }
 }
 
@@ -565,6 +594,7 @@ impl<IO> BlockWriter for ReaderWriter<IO> where IO: BlockWriter {
     delegate!{ to self.io {
         fn write_blocks(&mut self, buffer: &[u8], block_offset: usize) -> Result<usize, IoError>; 
         fn flush(&mut self) -> Result<(), IoError>;
+        fn write_blocks_fua(&mut self, buffer: &[u8], block_offset: usize) -> Result<usize, IoError>;
     } }
 }
 impl<IO> ByteReader for ReaderWriter<IO> where IO: ByteReader {
@@ -640,6 +670,7 @@ impl<IO> BlockWriter for Writer<IO> where IO: BlockWriter {
     delegate!{ to self.0 {
         fn write_blocks(&mut self, buffer: &[u8], block_offset: usize) -> Result<usize, IoError>; 
         fn flush(&mut self) -> Result<(), IoError>;
+        fn write_blocks_fua(&mut self, buffer: &[u8], block_offset: usize) -> Result<usize, IoError>;
     } }
 }
 impl<IO> ByteWriter for Writer<IO> where IO: ByteWriter {
@@ -792,6 +823,7 @@ impl<'io, IO, L, B> BlockWriter for LockableIo<'io, IO, L, B>
     delegate!{ to self.lock_mut() {
         fn write_blocks(&mut self, buffer: &[u8], block_offset: usize) -> Result<usize, IoError>; 
         fn flush(&mut self) -> Result<(), IoError>;
+        fn write_blocks_fua(&mut self, buffer: &[u8], block_offset: usize) -> Result<usize, IoError>;
     } }
 }
 impl<'io, IO, L, B> ByteReader for LockableIo<'io, IO, L, B>
@@ -850,6 +882,7 @@ impl<'io, IO, L, B> BlockWriter for &LockableIo<'io, IO, L, B>
     delegate!{ to self.lock_mut() {
         fn write_blocks(&mut self, buffer: &[u8], block_offset: usize) -> Result<usize, IoError>; 
         fn flush(&mut self) -> Result<(), IoError>;
+        fn write_blocks_fua(&mut self, buffer: &[u8], block_offset: usize) -> Result<usize, IoError>;
     } }
 }
 impl<'io, IO, L, B> ByteReader for &LockableIo<'io, IO, L, B>
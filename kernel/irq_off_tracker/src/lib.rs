@@ -0,0 +1,222 @@
+//! Tracks how long interrupts stay disabled on each CPU.
+//!
+//! Once [`init()`] is invoked, every interrupt-disabled section created via
+//! [`sync_irq::hold_interrupts()`] (including all acquisitions of IRQ-safe locks)
+//! is timed from the moment interrupts are disabled until they are re-enabled.
+//! This crate maintains per-CPU statistics about those sections,
+//! including the worst-case duration, and logs a warning with the task that was running
+//! whenever a section exceeds the configurable [warning threshold].
+//!
+//! The hooks invoked by [`sync_irq`] only update atomic per-CPU statistics,
+//! as they run with interrupts disabled and may interrupt code that holds any lock.
+//! Warnings are logged later by a reporter task, which looks up the name of the task
+//! that had interrupts disabled. Thus, the stack of the code that disabled interrupts
+//! is not unwound, because the unwinder would need to acquire locks and allocate memory.
+//!
+//! Note that sections created by directly invoking `irq_safety::hold_interrupts()`
+//! bypass [`sync_irq`] and are thus not tracked.
+//!
+//! [warning threshold]: set_warning_threshold
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::{format, string::String};
+use atomic_linked_list::atomic_map::AtomicMap;
+use core::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use cpu::CpuId;
+use spin::Once;
+use sync_irq::IrqOffHooks;
+use time::{Duration, Instant};
+
+/// The default threshold above which a warning is logged: 1 millisecond.
+pub const DEFAULT_WARNING_THRESHOLD: Duration = Duration::from_millis(1);
+
+/// How often the reporter task logs the warnings recorded by the hooks.
+const REPORT_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The hooks registered with [`sync_irq`] to measure interrupt-disabled sections.
+static HOOKS: IrqOffHooks = IrqOffHooks {
+    on_disable,
+    on_enable,
+};
+
+/// The duration (in nanoseconds) above which an interrupt-disabled section triggers a warning.
+static WARNING_THRESHOLD_NANOS: AtomicU64 = AtomicU64::new(DEFAULT_WARNING_THRESHOLD.as_nanos() as u64);
+
+/// The statistics of each CPU, which are created in [`init()`] before the hooks are registered.
+static CPU_STATS: AtomicMap<CpuId, CpuStats> = AtomicMap::new();
+
+/// The ID of the reporter task, whose own interrupt-disabled sections (e.g., while logging)
+/// don't trigger warnings, as that would cause it to warn about itself endlessly.
+static REPORTER: Once<usize> = Once::new();
+
+/// The internal, atomically-updated statistics for a single CPU.
+#[derive(Default)]
+struct CpuStats {
+    sections: AtomicU64,
+    total_nanos: AtomicU64,
+    max_nanos: AtomicU64,
+    warnings: AtomicU64,
+    /// The longest section that exceeded the threshold since the reporter task last ran,
+    /// or `0` if there is none.
+    pending_nanos: AtomicU64,
+    /// The ID of the task that was running during the section in `pending_nanos`.
+    pending_task_id: AtomicUsize,
+    /// The number of sections that exceeded the threshold since the reporter task last ran.
+    pending_warnings: AtomicU64,
+}
+
+/// A snapshot of the interrupt-disabled section statistics for a single CPU.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct IrqOffStats {
+    /// The number of interrupt-disabled sections that have been measured.
+    pub sections: u64,
+    /// The cumulative time that interrupts have been disabled.
+    pub total: Duration,
+    /// The longest time that interrupts were disabled in a single section.
+    pub max: Duration,
+    /// The number of sections that exceeded the warning threshold.
+    pub warnings: u64,
+}
+impl IrqOffStats {
+    /// Returns the average time that interrupts were disabled per section.
+    pub fn average(&self) -> Duration {
+        if self.sections == 0 {
+            Duration::ZERO
+        } else {
+            Duration::from_nanos((self.total.as_nanos() / self.sections as u128) as u64)
+        }
+    }
+}
+
+/// Starts tracking interrupt-disabled sections on all CPUs.
+///
+/// The first invocation spawns the reporter task that logs warnings,
+/// so this must be invoked after all CPUs have been brought up.
+pub fn init() -> Result<(), &'static str> {
+    REPORTER.try_call_once(spawn_reporter)?;
+    sync_irq::set_irq_off_hooks(Some(&HOOKS));
+    log::info!("Tracking interrupt-disabled sections, warning threshold: {:?}", warning_threshold());
+    Ok(())
+}
+
+/// Stops tracking interrupt-disabled sections.
+///
+/// Existing statistics are retained; see [`reset_stats()`].
+pub fn disable() {
+    sync_irq::set_irq_off_hooks(None);
+}
+
+/// Returns the current warning threshold.
+pub fn warning_threshold() -> Duration {
+    Duration::from_nanos(WARNING_THRESHOLD_NANOS.load(Ordering::Relaxed))
+}
+
+/// Sets the duration above which an interrupt-disabled section triggers a warning.
+pub fn set_warning_threshold(threshold: Duration) {
+    let nanos = u64::try_from(threshold.as_nanos()).unwrap_or(u64::MAX);
+    WARNING_THRESHOLD_NANOS.store(nanos, Ordering::Relaxed);
+}
+
+/// Returns a snapshot of the statistics for the given CPU,
+/// or `None` if that CPU wasn't present when [`init()`] was first invoked.
+pub fn stats(cpu: CpuId) -> Option<IrqOffStats> {
+    CPU_STATS.get(&cpu).map(|stats| IrqOffStats {
+        sections: stats.sections.load(Ordering::Relaxed),
+        total: Duration::from_nanos(stats.total_nanos.load(Ordering::Relaxed)),
+        max: Duration::from_nanos(stats.max_nanos.load(Ordering::Relaxed)),
+        warnings: stats.warnings.load(Ordering::Relaxed),
+    })
+}
+
+/// Resets the statistics of all CPUs.
+pub fn reset_stats() {
+    for (_cpu, stats) in CPU_STATS.iter() {
+        stats.sections.store(0, Ordering::Relaxed);
+        stats.total_nanos.store(0, Ordering::Relaxed);
+        stats.max_nanos.store(0, Ordering::Relaxed);
+        stats.warnings.store(0, Ordering::Relaxed);
+    }
+}
+
+/// Creates the statistics of every CPU and spawns the reporter task,
+/// returning the reporter task's ID.
+fn spawn_reporter() -> Result<usize, &'static str> {
+    for cpu in cpu::cpus() {
+        CPU_STATS.insert(cpu, CpuStats::default());
+    }
+    let reporter = spawn::new_task_builder(reporter_loop, ())
+        .name(String::from("irq_off_tracker_reporter"))
+        .spawn()?;
+    Ok(reporter.id)
+}
+
+/// The entry point of the reporter task, which periodically logs the warnings recorded by the hooks.
+fn reporter_loop(_: ()) -> Result<(), &'static str> {
+    loop {
+        for (&cpu, stats) in CPU_STATS.iter() {
+            report(cpu, stats);
+        }
+        let _ = sleep::sleep(REPORT_INTERVAL);
+    }
+}
+
+/// Logs a warning about the longest interrupt-disabled section on the given CPU
+/// that exceeded the threshold since the last report, if any.
+fn report(cpu: CpuId, stats: &CpuStats) {
+    let elapsed = stats.pending_nanos.swap(0, Ordering::Relaxed);
+    if elapsed == 0 {
+        return;
+    }
+    let task_id = stats.pending_task_id.load(Ordering::Relaxed);
+    let count = stats.pending_warnings.swap(0, Ordering::Relaxed);
+    let task_name = task::get_task(task_id)
+        .and_then(|t| t.upgrade())
+        .map(|t| t.name.clone());
+    log::warn!("Interrupts were disabled on CPU {} for {:?} (threshold {:?}, worst case {:?}) in task {} ({:?}){}",
+        cpu,
+        Duration::from_nanos(elapsed),
+        warning_threshold(),
+        Duration::from_nanos(stats.max_nanos.load(Ordering::Relaxed)),
+        task_id,
+        task_name,
+        if count > 1 { format!(", the longest of {count} such sections") } else { String::new() },
+    );
+}
+
+/// Returns the current monotonic time in nanoseconds.
+fn now_nanos() -> u64 {
+    (Instant::now() - Instant::ZERO).as_nanos() as u64
+}
+
+/// Invoked by [`sync_irq`] right after interrupts have been disabled.
+fn on_disable() -> u64 {
+    now_nanos()
+}
+
+/// Invoked by [`sync_irq`] right before interrupts are re-enabled.
+///
+/// Interrupts are still disabled here, so we cannot be migrated to another CPU.
+/// This only updates atomics, leaving the logging to the reporter task.
+fn on_enable(start_nanos: u64) {
+    let elapsed = now_nanos().saturating_sub(start_nanos);
+    let Some(stats) = CPU_STATS.get(&cpu::current_cpu()) else { return };
+
+    stats.sections.fetch_add(1, Ordering::Relaxed);
+    stats.total_nanos.fetch_add(elapsed, Ordering::Relaxed);
+    stats.max_nanos.fetch_max(elapsed, Ordering::Relaxed);
+
+    if elapsed > WARNING_THRESHOLD_NANOS.load(Ordering::Relaxed) {
+        let task_id = task::get_my_current_task_id();
+        if REPORTER.get() == Some(&task_id) {
+            return;
+        }
+        stats.warnings.fetch_add(1, Ordering::Relaxed);
+        stats.pending_warnings.fetch_add(1, Ordering::Relaxed);
+        if stats.pending_nanos.fetch_max(elapsed, Ordering::Relaxed) < elapsed {
+            stats.pending_task_id.store(task_id, Ordering::Relaxed);
+        }
+    }
+}
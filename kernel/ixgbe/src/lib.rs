@@ -28,6 +28,7 @@ extern crate mpmc;
 extern crate rand;
 extern crate hpet;
 extern crate net;
+extern crate rss;
 extern crate nic_initialization;
 extern crate intel_ethernet;
 extern crate nic_buffers;
@@ -68,6 +69,9 @@ use rand::{
 };
 use core::mem::ManuallyDrop;
 use hashbrown::HashMap;
+use cpu::CpuId;
+use net::FlowTuple;
+use rss::RssConfig;
 
 /// Vendor ID for Intel
 pub const INTEL_VEND:                   u16 = 0x8086;  
@@ -103,6 +107,9 @@ pub const IXGBE_NUM_RX_QUEUES_ENABLED:          u8      = 64;
 /// Do NOT set this greater than 64 since the queues 65-128 don't seem to work, 
 /// most likely because they need additional configuration.
 pub const IXGBE_NUM_TX_QUEUES_ENABLED:          u8      = 64;
+/// The maximum number of receive queues that RSS can spread flows across,
+/// since each redirection table entry only holds a 4-bit queue index.
+pub const IXGBE_MAX_RSS_QUEUES:                 u8      = 16;
 
 
 
@@ -179,6 +186,8 @@ pub struct IxgbeNic {
     tx_queues: Vec<TxQueue<IxgbeTxQueueRegisters,AdvancedTxDescriptor>>,
     /// Registers for the disabled queues
     tx_registers_disabled: Vec<IxgbeTxQueueRegisters>,
+    /// The hash key and redirection table programmed into the NIC, if RSS is enabled.
+    rss: Option<RssConfig>,
 }
 
 impl net::NetworkDevice for IxgbeNic {
@@ -189,15 +198,23 @@ impl net::NetworkDevice for IxgbeNic {
     }
 
     fn receive(&mut self) -> Option<ReceivedFrame> {
-        // by default, when using the physical NIC interface, we receive on queue 0.
-        let qid = 0;
-        // return one frame from the queue's received frames
-        self.rx_queues[qid].received_frames.pop_front()
+        // When RSS is enabled, flows are spread across the first `num_queues` queues,
+        // each of which is filled by its interrupt handler on the queue's CPU.
+        // Otherwise, when using the physical NIC interface, we receive on queue 0.
+        let num_queues = self.rss.as_ref().map_or(1, |rss| rss.num_queues() as usize);
+        // return one frame from the queues' received frames
+        self.rx_queues[..num_queues].iter_mut()
+            .find_map(|rxq| rxq.received_frames.pop_front())
     }
 
     fn mac_address(&self) -> [u8; 6] {
         self.mac_spoofed.unwrap_or(self.mac_hardware)
     }
+
+    fn cpu_for_flow(&self, flow: &FlowTuple) -> Option<CpuId> {
+        let qid = self.rss.as_ref()?.queue_for_flow(flow);
+        self.rx_queues[qid as usize].cpu_id
+    }
 }
 
 // Functions that setup the NIC struct and handle the sending and receiving of packets.
@@ -219,6 +236,8 @@ impl IxgbeNic {
     ///     The number of handlers must be less than or equal to `IXGBE_NUM_RX_QUEUES_ENABLED`.
     ///     If interrupts are disabled, this should be set to None.
     /// * `enable_rss`: true if receive side scaling is enabled.
+    ///     Flows are then spread across up to `IXGBE_MAX_RSS_QUEUES` receive queues,
+    ///     each of which is assigned to a different CPU that handles its interrupts.
     /// * `rx_buffer_size_kbytes`: The size of receive buffers. 
     /// * `num_rx_descriptors`: The number of descriptors in each receive queue.
     /// * `num_tx_descriptors`: The number of descriptors in each transmit queue.
@@ -341,6 +360,21 @@ impl IxgbeNic {
             id += 1;
         }
 
+        // Assign the RSS queues to CPUs before enabling interrupts, so that each queue's interrupt is redirected to its CPU.
+        let rss = if enable_rss {
+            let num_rss_queues = match interrupts {
+                Some(ref ints) => ints.len().min(IXGBE_MAX_RSS_QUEUES as usize),
+                None => IXGBE_MAX_RSS_QUEUES as usize,
+            };
+            let num_rss_queues = num_rss_queues.min(cpu::cpu_count() as usize).max(1);
+            for (rxq, cpu_id) in rx_queues.iter_mut().take(num_rss_queues).zip(cpu::cpus()) {
+                rxq.cpu_id = Some(cpu_id);
+            }
+            Some(RssConfig::with_key(Self::random_rss_key()?, num_rss_queues as u8)?)
+        } else {
+            None
+        };
+
         // enable msi-x interrupts if required and return the assigned interrupt numbers
         let interrupt_num =
             if let Some(interrupt_handlers) = interrupts {
@@ -355,8 +389,8 @@ impl IxgbeNic {
             };
 
         // enable Receive Side Scaling if required
-        if enable_rss {
-            Self::enable_rss(&mut mapped_registers2, &mut mapped_registers3)?;
+        if let Some(ref rss) = rss {
+            Self::enable_rss(&mut mapped_registers2, &mut mapped_registers3, rss)?;
         }
 
         // wait 10 seconds for the link to come up, as seen in other ixgbe drivers
@@ -381,6 +415,7 @@ impl IxgbeNic {
             num_tx_queues: IXGBE_NUM_TX_QUEUES_ENABLED,
             tx_queues,
             tx_registers_disabled: tx_mapped_registers,
+            rss,
         };
 
         info!("Link is up with speed: {} Mb/s", ixgbe_nic.link_speed() as u32);
@@ -902,42 +937,56 @@ impl IxgbeNic {
         regs.dmatxctl.write(val | TE); 
     }
 
-    /// Enable multiple receive queues with RSS.
+    /// Returns a random key for the RSS hash function, so that remote hosts can't predict which queue a flow is steered to.
+    fn random_rss_key() -> Result<[u8; rss::KEY_LEN], &'static str> {
+        let seed = get_hpet().as_ref().ok_or("couldn't get HPET timer")?.get_counter();
+        let mut rng = SmallRng::seed_from_u64(seed);
+        let mut key = [0; rss::KEY_LEN];
+        rng.fill_bytes(&mut key);
+        Ok(key)
+    }
+
+    /// Enable multiple receive queues with RSS, using the hash key and redirection table in `rss`.
     /// Part of queue initialization is done in the rx_init function.
     pub fn enable_rss(
         regs2: &mut IntelIxgbeRegisters2, 
-        regs3: &mut IntelIxgbeRegisters3
+        regs3: &mut IntelIxgbeRegisters3,
+        rss: &RssConfig,
     ) -> Result<(), &'static str> {
+        if rss.num_queues() > IXGBE_MAX_RSS_QUEUES {
+            return Err("RSS can spread flows across at most 16 queues");
+        }
+
         // enable RSS writeback in the header field of the receive descriptor
         regs2.rxcsum.write(RXCSUM_PCSD);
         
-        // enable RSS and set fields that will be used by hash function
-        // right now we're using the udp port and ipv4 address.
-        regs3.mrqc.write(MRQC_MRQE_RSS | MRQC_UDPIPV4 ); 
+        // enable RSS and set fields that will be used by hash function:
+        // the ip addresses, plus the tcp or udp ports if present.
+        regs3.mrqc.write(MRQC_MRQE_RSS | MRQC_IPV4 | MRQC_TCPIPV4 | MRQC_UDPIPV4 | MRQC_IPV6 | MRQC_TCPIPV6 | MRQC_UDPIPV6); 
 
-        //set the random keys for the hash function
-        let seed = get_hpet().as_ref().ok_or("couldn't get HPET timer")?.get_counter();
-        let mut rng = SmallRng::seed_from_u64(seed);
-        for rssrk in regs3.rssrk.iter_mut() {
-            rssrk.write(rng.next_u32());
+        // set the keys for the hash function, where the first key byte is the lowest byte of the first register
+        for (rssrk, key) in regs3.rssrk.iter_mut().zip(rss.key().chunks_exact(4)) {
+            rssrk.write(u32::from_le_bytes([key[0], key[1], key[2], key[3]]));
         }
 
         // Initialize the RSS redirection table
-        // each reta register has 4 redirection entries
-        // since mapping to queues is random and based on a hash, we randomly assign 1 queue to each reta register
-        let mut qid = 0;
-        for reta in regs3.reta.iter_mut() {
-            //set 4 entries to the same queue number
-            let val = qid << RETA_ENTRY_0_OFFSET | qid << RETA_ENTRY_1_OFFSET | qid << RETA_ENTRY_2_OFFSET | qid << RETA_ENTRY_3_OFFSET;
+        // each reta register has 4 redirection entries, where the first entry is in the lowest byte
+        for (reta, entries) in regs3.reta.iter_mut().zip(rss.table().chunks_exact(4)) {
+            let val = (entries[0] as u32) << RETA_ENTRY_0_OFFSET
+                | (entries[1] as u32) << RETA_ENTRY_1_OFFSET
+                | (entries[2] as u32) << RETA_ENTRY_2_OFFSET
+                | (entries[3] as u32) << RETA_ENTRY_3_OFFSET;
             reta.write(val);
-
-            // next 4 entries will be assigned to the next queue
-            qid = (qid + 1) % IXGBE_NUM_RX_QUEUES_ENABLED as u32;
         }
 
         Ok(())
     }
 
+    /// Returns the hash key and redirection table used for RSS, if it's enabled.
+    pub fn rss_config(&self) -> Option<&RssConfig> {
+        self.rss.as_ref()
+    }
+
     /// Enables Direct Cache Access for the device.
     /// TODO: Not working yet because we need to have a separate driver for DCA
     /// which enables it for the CPU and chipset, and registers devices that can use DCA (I think).
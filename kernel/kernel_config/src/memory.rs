@@ -6,7 +6,10 @@
 //! * 509: kernel heap.
 //! * 508: recursive mapping for accessing the P4 root page table frame
 //!        of an upcoming new page table.
-//! * 507 down to 0: available for general usage.
+//! * 507 down to 256: available for general usage.
+//! * 255 down to 1: reserved for mappings private to a separate address space,
+//!        which are never used for general allocations.
+//! * 0: available for general usage, including the initial identity mappings.
 
 // On x86_64, addresses must be sign-extended.
 // On theseus, we choose to have all addresses
@@ -63,6 +66,11 @@ pub const KERNEL_HEAP_P4_INDEX: usize = ENTRIES_PER_PAGE_TABLE - 3;
 /// Value: 508. The 508th entry is used to temporarily recursively map the P4 root page table frame
 ///             of an upcoming (new) page table such that it can be accessed and modified.
 pub const UPCOMING_PAGE_TABLE_RECURSIVE_P4_INDEX: usize = ENTRIES_PER_PAGE_TABLE - 4;
+/// Value: 1. The first P4 entry reserved for mappings that are private to an address space.
+pub const PRIVATE_REGION_P4_START_INDEX: usize = 1;
+/// Value: 255. The last P4 entry reserved for mappings that are private to an address space,
+///             which is the last entry in the lower half of the address space.
+pub const PRIVATE_REGION_P4_END_INDEX: usize = ENTRIES_PER_PAGE_TABLE / 2 - 1;
 
 
 pub const MAX_PAGE_NUMBER: usize = MAX_VIRTUAL_ADDRESS / PAGE_SIZE;
@@ -109,3 +117,12 @@ pub const KERNEL_HEAP_MAX_SIZE: usize = ADDRESSABILITY_PER_P4_ENTRY;
 /// The start of the virtual address range covered by the 508th P4 entry,
 /// i.e., [`UPCOMING_PAGE_TABLE_RECURSIVE_P4_INDEX`];
 pub const UPCOMING_PAGE_TABLE_RECURSIVE_P4_START: usize = canonicalize(UPCOMING_PAGE_TABLE_RECURSIVE_P4_INDEX << (P4_INDEX_SHIFT + PAGE_SHIFT));
+
+/// The start of the lower-half virtual address range reserved for private mappings,
+/// i.e., [`PRIVATE_REGION_P4_START_INDEX`].
+/// Actual value: 0x0000_0080_0000_0000
+pub const PRIVATE_REGION_START: usize = PRIVATE_REGION_P4_START_INDEX << (P4_INDEX_SHIFT + PAGE_SHIFT);
+/// The (inclusive) end of the lower-half virtual address range reserved for private mappings,
+/// i.e., [`PRIVATE_REGION_P4_END_INDEX`].
+/// Actual value: 0x0000_7FFF_FFFF_FFFF
+pub const PRIVATE_REGION_END: usize = ((PRIVATE_REGION_P4_END_INDEX + 1) << (P4_INDEX_SHIFT + PAGE_SHIFT)) - 1;
@@ -128,8 +128,13 @@ fn handle_keyboard_input(keyboard: &PS2Keyboard, queue: &Queue<Event>, scan_code
                 KeyboardModifiers::CONTROL_LEFT
             });
         }
+        // The right Alt key is the AltGr key on many non-US layouts.
         Ok(Keycode::Alt) => {
-            modifiers.insert(KeyboardModifiers::ALT);
+            modifiers.insert(if extended {
+                KeyboardModifiers::ALT_GR
+            } else {
+                KeyboardModifiers::ALT
+            });
         }
         Ok(Keycode::LeftShift) => {
             modifiers.insert(KeyboardModifiers::SHIFT_LEFT);
@@ -152,7 +157,11 @@ fn handle_keyboard_input(keyboard: &PS2Keyboard, queue: &Queue<Event>, scan_code
             });
         }
         Ok(Keycode::AltReleased) => {
-            modifiers.remove(KeyboardModifiers::ALT);
+            modifiers.remove(if extended {
+                KeyboardModifiers::ALT_GR
+            } else {
+                KeyboardModifiers::ALT
+            });
         }
         Ok(Keycode::LeftShiftReleased) => {
             modifiers.remove(KeyboardModifiers::SHIFT_LEFT);
@@ -0,0 +1,256 @@
+//! Keyboard layouts that translate key presses into characters.
+//!
+//! The keyboard driver reports which key was pressed as a [`Keycode`],
+//! which is named after the key at that position on a US keyboard.
+//! A [`Keymap`] translates it into the character that the key types in a given layout.
+//!
+//! Apart from the built-in US layout, layouts are loaded from the keymap files in
+//! [`KEYMAP_DIRECTORY`], e.g., `/extra_files/keymaps/de.keymap` defines the `de` layout.
+//! A keymap file lists the keys that type something different than on a US keyboard,
+//! one key per line:
+//!
+//! ```text
+//! # KEY   NORMAL  SHIFTED  ALTGR
+//! Y       z       Z
+//! Num2    2       "        ²
+//! Minus   ß       ?        \
+//! ```
+//!
+//! * `KEY` is the name of a [`Keycode`], e.g., `Q`, `Num1`, or `Semicolon`.
+//! * The characters that the key types on its own, with `Shift`, and with `AltGr`.
+//!   Each one is written either as itself, as a code point such as `U+0023`, or as `-` if the key
+//!   types nothing. `#` and `-` must be written as code points.
+//!   The `AltGr` character is optional.
+//! * Lines that are empty or start with `#` are ignored.
+//!
+//! `Caps Lock` acts like `Shift` for keys that type a lowercase letter on their own
+//! and the same letter in uppercase with `Shift`.
+//!
+//! Each terminal uses the layout named by its [`ENV_VAR`] environment variable,
+//! or the US layout if that isn't set. The `loadkeys` application sets that variable.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::{
+    collections::BTreeMap,
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use keycodes_ascii::{KeyboardModifiers, Keycode};
+use path::Path;
+
+/// The directory that contains the keymap files.
+pub const KEYMAP_DIRECTORY: &str = "/extra_files/keymaps";
+
+/// The file extension of keymap files.
+pub const KEYMAP_EXTENSION: &str = "keymap";
+
+/// The environment variable that holds the name of a terminal's keyboard layout.
+pub const ENV_VAR: &str = "KEYMAP";
+
+/// The name of the built-in US layout, which is used by default.
+pub const DEFAULT_LAYOUT: &str = "us";
+
+/// The characters that a key types in a layout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct KeyChars {
+    normal: Option<char>,
+    shifted: Option<char>,
+    alt_gr: Option<char>,
+}
+
+/// A keyboard layout, which maps keys to the characters they type.
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    name: String,
+    /// The keys that type something different than on a US keyboard,
+    /// indexed by their keycode.
+    keys: BTreeMap<u8, KeyChars>,
+}
+
+impl Keymap {
+    /// Returns the built-in US layout.
+    pub fn us() -> Keymap {
+        Keymap {
+            name: DEFAULT_LAYOUT.to_string(),
+            keys: BTreeMap::new(),
+        }
+    }
+
+    /// Parses the contents of a keymap file, which defines the layout with the given name.
+    ///
+    /// See the [crate-level docs](crate) for the format of keymap files.
+    pub fn parse(name: &str, text: &str) -> Result<Keymap, String> {
+        let mut keys = BTreeMap::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = |message: &str| format!("line {}: {}", number + 1, message);
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if !(3..=4).contains(&fields.len()) {
+                return Err(error("expected a key followed by two or three characters"));
+            }
+            let keycode = keycode_from_name(fields[0])
+                .ok_or_else(|| error(&format!("unknown key {:?}", fields[0])))?;
+            let character = |field: Option<&&str>| match field {
+                Some(field) => parse_char(field).ok_or_else(|| error(&format!("invalid character {:?}", field))),
+                None => Ok(None),
+            };
+            let chars = KeyChars {
+                normal: character(fields.get(1))?,
+                shifted: character(fields.get(2))?,
+                alt_gr: character(fields.get(3))?,
+            };
+            if keys.insert(keycode as u8, chars).is_some() {
+                return Err(error(&format!("key {:?} is mapped twice", fields[0])));
+            }
+        }
+        Ok(Keymap { name: name.to_string(), keys })
+    }
+
+    /// Returns the name of this layout.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the character that the given key types while the given modifiers are active,
+    /// or `None` if it doesn't type anything, e.g., because it's an arrow key.
+    pub fn translate(&self, keycode: Keycode, modifiers: KeyboardModifiers) -> Option<char> {
+        let Some(chars) = self.keys.get(&(keycode as u8)) else {
+            return keycode.to_ascii(modifiers);
+        };
+        if modifiers.is_alt_gr() && chars.alt_gr.is_some() {
+            return chars.alt_gr;
+        }
+        let is_letter = match (chars.normal, chars.shifted) {
+            (Some(normal), Some(shifted)) => normal.is_lowercase() && normal.to_uppercase().eq([shifted]),
+            _ => false,
+        };
+        // Caps Lock acts like Shift for letters, so the two cancel each other out.
+        if modifiers.is_shift() != (modifiers.is_caps_lock() && is_letter) {
+            chars.shifted
+        } else {
+            chars.normal
+        }
+    }
+}
+
+/// Loads the layout with the given name from its keymap file.
+///
+/// The file is read every time, so changes to it take effect the next time a layout is chosen.
+pub fn load(name: &str) -> Result<Keymap, String> {
+    if name == DEFAULT_LAYOUT {
+        return Ok(Keymap::us());
+    }
+    if name.is_empty() || name.contains('/') {
+        return Err(format!("invalid layout name {:?}", name));
+    }
+    let path = format!("{}/{}.{}", KEYMAP_DIRECTORY, name, KEYMAP_EXTENSION);
+    let file = Path::new(&path)
+        .get_file(root::get_root())
+        .ok_or_else(|| format!("there is no layout named {:?}", name))?;
+    let mut file = file.lock();
+    let mut bytes = vec![0; file.len()];
+    let read = file.read_at(&mut bytes, 0).map_err(|_| format!("failed to read {}", path))?;
+    bytes.truncate(read);
+    let text = String::from_utf8(bytes).map_err(|_| format!("{} is not UTF-8 text", path))?;
+    Keymap::parse(name, &text).map_err(|e| format!("{}: {}", path, e))
+}
+
+/// Returns the names of all available layouts in alphabetical order,
+/// including the built-in US layout.
+pub fn available() -> Vec<String> {
+    let mut names = vec![DEFAULT_LAYOUT.to_string()];
+    if let Some(dir) = Path::new(KEYMAP_DIRECTORY).get_dir(root::get_root()) {
+        let suffix = format!(".{}", KEYMAP_EXTENSION);
+        names.extend(
+            dir.lock()
+                .list()
+                .into_iter()
+                .filter_map(|file| file.strip_suffix(suffix.as_str()).map(String::from)),
+        );
+    }
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// Returns the keycode with the given name, e.g., `Semicolon` for [`Keycode::Semicolon`].
+fn keycode_from_name(name: &str) -> Option<Keycode> {
+    (0..keycodes_ascii::KEY_RELEASED_OFFSET)
+        .filter_map(|value| Keycode::try_from(value).ok())
+        .find(|keycode| format!("{:?}", keycode) == name)
+}
+
+/// Parses a character in a keymap file.
+///
+/// Returns `Some(None)` for `-`, which means that the key doesn't type anything.
+fn parse_char(field: &str) -> Option<Option<char>> {
+    if field == "-" {
+        return Some(None);
+    }
+    if let Some(hex) = field.strip_prefix("U+") {
+        return u32::from_str_radix(hex, 16).ok().and_then(char::from_u32).map(Some);
+    }
+    let mut chars = field.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Some(Some(c)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GERMAN: &str = "
+        # A few keys of the German layout.
+        Y       z       Z
+        Z       y       Y
+        Num2    2       \"       ²
+        Minus   ß       ?       \\
+        Quote   ä       Ä
+        Backslash U+0023 '
+        Slash   U+002D  _
+        Backtick -      °
+    ";
+
+    #[test]
+    fn parse_and_translate() {
+        let keymap = Keymap::parse("de", GERMAN).unwrap();
+        let none = KeyboardModifiers::new();
+        let shift = KeyboardModifiers::SHIFT_LEFT;
+        let caps = KeyboardModifiers::CAPS_LOCK;
+        let alt_gr = KeyboardModifiers::ALT_GR;
+
+        assert_eq!(keymap.name(), "de");
+        assert_eq!(keymap.translate(Keycode::Y, none), Some('z'));
+        assert_eq!(keymap.translate(Keycode::Z, shift), Some('Y'));
+        assert_eq!(keymap.translate(Keycode::Num2, shift), Some('"'));
+        assert_eq!(keymap.translate(Keycode::Num2, alt_gr), Some('²'));
+        assert_eq!(keymap.translate(Keycode::Quote, caps), Some('Ä'));
+        assert_eq!(keymap.translate(Keycode::Quote, caps | shift), Some('ä'));
+        assert_eq!(keymap.translate(Keycode::Minus, caps), Some('ß'));
+        assert_eq!(keymap.translate(Keycode::Backslash, none), Some('#'));
+        assert_eq!(keymap.translate(Keycode::Slash, none), Some('-'));
+        assert_eq!(keymap.translate(Keycode::Backtick, none), None);
+        // Keys that aren't listed type the same as on a US keyboard.
+        assert_eq!(keymap.translate(Keycode::Q, shift), Some('Q'));
+        assert_eq!(keymap.translate(Keycode::Q, alt_gr), Some('q'));
+        assert_eq!(keymap.translate(Keycode::Left, none), None);
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(Keymap::parse("x", "Foo a A").unwrap_err(), "line 1: unknown key \"Foo\"");
+        assert_eq!(Keymap::parse("x", "\nQ ab A").unwrap_err(), "line 2: invalid character \"ab\"");
+        assert_eq!(Keymap::parse("x", "Q a").unwrap_err(), "line 1: expected a key followed by two or three characters");
+        assert_eq!(Keymap::parse("x", "Q a A\nQ b B").unwrap_err(), "line 2: key \"Q\" is mapped twice");
+    }
+}
@@ -6,6 +6,7 @@
 //! * Handling the command line user input.
 //! * Displaying the cursor at the right position
 //! * Handling events delivered from the window manager.
+//! * Selecting text with the mouse and copying it to the [`clipboard`].
 
 #![no_std]
 
@@ -25,6 +26,7 @@ extern crate window;
 extern crate text_display;
 extern crate shapes;
 extern crate color;
+extern crate clipboard;
 
 use core::ops::DerefMut;
 use alloc::string::{String, ToString};
@@ -32,15 +34,17 @@ use alloc::vec::Vec;
 use cursor::*;
 use text_display::TextDisplay;
 use displayable::Displayable;
-use event_types::Event;
+use event_types::{Event, MousePositionEvent};
 use font::{CHARACTER_HEIGHT, CHARACTER_WIDTH};
 use framebuffer::{Framebuffer, Pixel};
 use color::Color;
 use shapes::{Coord, Rectangle};
 use window::Window;
 use time::Duration;
+use selection::Selection;
 
 pub mod cursor;
+pub mod selection;
 
 pub const FONT_FOREGROUND_COLOR: Color = color::LIGHT_GREEN;
 pub const FONT_BACKGROUND_COLOR: Color = color::BLACK;
@@ -68,6 +72,10 @@ pub struct Terminal {
     text_display: TextDisplay,
     /// The cursor of the terminal.
     pub cursor: Cursor,
+    /// The text selected with the mouse, if any.
+    selection: Option<Selection>,
+    /// Whether the left mouse button is being held to make a selection.
+    selecting: bool,
 }
 
 /// Private methods of `Terminal`.
@@ -90,9 +98,9 @@ impl Terminal {
         let mut start_idx = end_idx;
         // Grabs a max-size slice of the scrollback buffer (usually does not totally fit because of newlines)
         let result = if end_idx > buffer_width * buffer_height {
-            self.scrollback_buffer.get(end_idx - buffer_width * buffer_height..end_idx)
+            self.scrollback_slice(end_idx - buffer_width * buffer_height, end_idx)
         } else {
-            self.scrollback_buffer.get(0..end_idx)
+            self.scrollback_slice(0, end_idx)
         };
 
         if let Some(slice) = result {
@@ -177,9 +185,9 @@ impl Terminal {
         let mut end_idx = start_idx;
         // Grabs a max-size slice of the scrollback buffer (usually does not totally fit because of newlines)
         let result = if start_idx + buffer_width * buffer_height > scrollback_buffer_len {
-            self.scrollback_buffer.get(start_idx..scrollback_buffer_len-1)
+            self.scrollback_slice(start_idx, scrollback_buffer_len-1)
         } else {
-            self.scrollback_buffer.get(start_idx..start_idx + buffer_width * buffer_height)
+            self.scrollback_slice(start_idx, start_idx + buffer_width * buffer_height)
         };
 
         // calculate the starting index for the slice
@@ -256,10 +264,10 @@ impl Terminal {
         let result;
         let slice_len;
         if buffer_width < start_idx {
-            result = self.scrollback_buffer.as_str().get(start_idx - buffer_width .. start_idx);
+            result = self.scrollback_slice(start_idx - buffer_width, start_idx);
             slice_len = buffer_width;
         } else {
-            result = self.scrollback_buffer.as_str().get(0 .. start_idx);
+            result = self.scrollback_slice(0, start_idx);
             slice_len = start_idx;
         }
         // Searches this slice for a newline
@@ -307,10 +315,10 @@ impl Terminal {
             // Grabs a slice (the size of the buffer width at most) of the scrollback buffer that is directly below the current slice being displayed on the text display
             if self.scrollback_buffer.len() > end_idx + buffer_width {
                 slice_len = buffer_width;
-                result = self.scrollback_buffer.as_str().get(end_idx .. end_idx + buffer_width);
+                result = self.scrollback_slice(end_idx, end_idx + buffer_width);
             } else {
                 slice_len = self.scrollback_buffer.len() - end_idx -1; 
-                result = self.scrollback_buffer.as_str().get(end_idx .. self.scrollback_buffer.len());
+                result = self.scrollback_slice(end_idx, self.scrollback_buffer.len());
             }
             // Searches the grabbed slice for a newline
             if let Some(slice) = result {
@@ -370,6 +378,7 @@ impl Terminal {
 
     /// Updates the text display by taking a string index and displaying as much as it starting from the passed string index (i.e. starts from the top of the display and goes down)
     fn update_display_forwards(&mut self, start_idx: usize) -> Result<(), &'static str> {
+        let start_idx = floor_char_boundary(&self.scrollback_buffer, start_idx);
         self.scroll_start_idx = start_idx;
         let result = self.calc_end_idx(start_idx);
         let end_idx = match result {
@@ -377,11 +386,11 @@ impl Terminal {
             Err(ScrollError::OffEndBound) => {
                 let new_end_idx = self.scrollback_buffer.len() -1;
                 let new_start_idx = self.calc_start_idx(new_end_idx).0;
-                self.scroll_start_idx = new_start_idx;
+                self.scroll_start_idx = floor_char_boundary(&self.scrollback_buffer, new_start_idx);
                 new_end_idx
             },
         };
-        let result  = self.scrollback_buffer.get(start_idx..=end_idx); // =end_idx includes the end index in the slice
+        let result  = self.scrollback_slice(start_idx, end_idx + 1); // +1 includes the end index in the slice
         if let Some(slice) = result {
             self.text_display.set_text(slice);
             self.display_text()?;
@@ -394,16 +403,77 @@ impl Terminal {
     /// Display the text displayable in the window and render it to the screen
     fn display_text(&mut self) -> Result<(), &'static str>{
         let coord = self.window.area().top_left;
-        let area_to_render = self.text_display.display(coord, self.window.framebuffer_mut().deref_mut())?;
+        let mut area_to_render = self.text_display.display(coord, self.window.framebuffer_mut().deref_mut())?;
+        if self.selection.is_some() {
+            // The whole text area is redrawn because the selection may cover any part of it.
+            self.display_selection(coord);
+            area_to_render = self.window.area();
+        }
         self.window.render(Some(area_to_render))
     }
 
+    /// Draws the selected characters that are on the screen with inverted colors.
+    fn display_selection(&mut self, coord: Coord) {
+        let Some(selection) = self.selection else { return };
+        let (columns, lines) = self.get_text_dimensions();
+        let Some(visible) = self.scrollback_buffer.get(self.scroll_start_idx..) else { return };
+        // Make the selected range relative to the start of the visible text.
+        let range = selection.range();
+        let range = range.start.saturating_sub(self.scroll_start_idx)..range.end.saturating_sub(self.scroll_start_idx);
+        let mut framebuffer = self.window.framebuffer_mut();
+        for (_index, c, column, line) in selection::positions(visible, columns, lines, range) {
+            framebuffer_printer::print_character(
+                framebuffer.deref_mut(),
+                c,
+                FONT_BACKGROUND_COLOR.into(),
+                FONT_FOREGROUND_COLOR.into(),
+                coord,
+                column,
+                line,
+            );
+        }
+    }
+
+    /// Returns the index in the scrollback buffer of the character shown at the given coordinate,
+    /// which is relative to the terminal's window.
+    fn index_at(&self, coordinate: Coord) -> Option<usize> {
+        let offset = coordinate - self.window.area().top_left;
+        if offset.x < 0 || offset.y < 0 {
+            return None;
+        }
+        let (columns, lines) = self.get_text_dimensions();
+        let (column, line) = (offset.x as usize / CHARACTER_WIDTH, offset.y as usize / CHARACTER_HEIGHT);
+        if column >= columns || line >= lines {
+            return None;
+        }
+        let visible = self.scrollback_buffer.get(self.scroll_start_idx..)?;
+        selection::index_at(visible, columns, column, line).map(|index| index + self.scroll_start_idx)
+    }
+
+    /// Removes the selection, if any.
+    /// Note that one needs to call `refresh_display` to remove it from the screen.
+    fn clear_selection(&mut self) {
+        if self.selection.take().is_some() {
+            self.selecting = false;
+            self.text_display.reset_cache();
+        }
+    }
+
+    /// Returns the part of the scrollback buffer between the given byte indices,
+    /// widened as needed so that it doesn't split a character that is encoded with multiple bytes.
+    fn scrollback_slice(&self, start: usize, end: usize) -> Option<&str> {
+        let start = floor_char_boundary(&self.scrollback_buffer, start);
+        let end = ceil_char_boundary(&self.scrollback_buffer, end);
+        self.scrollback_buffer.get(start..end)
+    }
+
     /// Updates the text display by taking a string index and displaying as much as it can going backwards from the passed string index (i.e. starts from the bottom of the display and goes up)
     fn update_display_backwards(&mut self, end_idx: usize) -> Result<(), &'static str> {
         let (start_idx, _cursor_pos) = self.calc_start_idx(end_idx);
+        let start_idx = floor_char_boundary(&self.scrollback_buffer, start_idx);
         self.scroll_start_idx = start_idx;
 
-        let result = self.scrollback_buffer.get(start_idx..end_idx);
+        let result = self.scrollback_slice(start_idx, end_idx);
 
         if let Some(slice) = result {
             self.text_display.set_text(slice);
@@ -442,6 +512,8 @@ impl Terminal {
             is_scroll_end: true,
             text_display,
             cursor: Cursor::default(),
+            selection: None,
+            selecting: false,
         };
         terminal.display_text()?;
 
@@ -483,10 +555,10 @@ impl Terminal {
     ///
     /// After invoke this function, one must call `refresh_display` to get the updates actually showed on the screen.
     pub fn insert_char(&mut self, c: char, offset_from_end: usize) -> Result<(), &'static str> {
-        let buflen = self.scrollback_buffer.len();
-        if buflen < offset_from_end { return Err("offset_from_end is larger than length of scrollback buffer"); }
-        let insert_idx = buflen - offset_from_end;
-        self.scrollback_buffer.insert_str(insert_idx, &c.to_string());
+        let insert_idx = byte_index_from_end(&self.scrollback_buffer, offset_from_end)
+            .ok_or("offset_from_end is larger than length of scrollback buffer")?;
+        self.clear_selection();
+        self.scrollback_buffer.insert(insert_idx, c);
         Ok(())
     }
 
@@ -500,10 +572,10 @@ impl Terminal {
     ///
     /// After invoke this function, one must call `refresh_display` to get the updates actually showed on the screen.
     pub fn remove_char(&mut self, offset_from_end: usize) -> Result<(), &'static str> {
-        let buflen = self.scrollback_buffer.len();
-        if buflen < offset_from_end { return Err("offset_from_end is larger than length of scrollback buffer"); }
         if offset_from_end == 0 { return Err("cannot remove character at offset_from_end == 0"); }
-        let remove_idx = buflen - offset_from_end;
+        let remove_idx = byte_index_from_end(&self.scrollback_buffer, offset_from_end)
+            .ok_or("offset_from_end is larger than length of scrollback buffer")?;
+        self.clear_selection();
         self.scrollback_buffer.remove(remove_idx);
         Ok(())
     }
@@ -580,6 +652,7 @@ impl Terminal {
 
     /// Clear the scrollback buffer and reset the scroll positions.
     pub fn clear(&mut self) {
+        self.clear_selection();
         self.scrollback_buffer.clear();
         self.scroll_start_idx = 0;
         self.is_scroll_end = true;
@@ -598,6 +671,66 @@ impl Terminal {
         }
     }
 
+    /// Handles a mouse event that occurred within the terminal's window.
+    ///
+    /// Dragging the mouse with the left button held selects text, and a click removes the selection.
+    /// Scrolling the mouse wheel scrolls the screen by a line.
+    pub fn handle_mouse_event(&mut self, event: &MousePositionEvent) -> Result<(), &'static str> {
+        if event.scrolling_up {
+            return self.move_screen_line_up();
+        }
+        if event.scrolling_down {
+            return self.move_screen_line_down();
+        }
+
+        let index = self.index_at(event.coordinate);
+        match (self.selecting, event.left_button_hold) {
+            // The left button was just pressed, so start a new selection.
+            (false, true) => {
+                self.clear_selection();
+                self.selection = index.map(Selection::new);
+                self.selecting = self.selection.is_some();
+            }
+            // The mouse was dragged, so extend the selection, or select until the end of the text
+            // if the mouse is beyond it.
+            (true, true) => {
+                let last = self.scrollback_buffer.len().saturating_sub(1);
+                if let Some(selection) = self.selection.as_mut() {
+                    selection.extend_to(index.unwrap_or(last));
+                }
+            }
+            // The left button was released; a mere click doesn't select anything.
+            (true, false) => {
+                self.selecting = false;
+                if self.selection.map_or(false, |selection| selection.is_single()) {
+                    self.clear_selection();
+                }
+            }
+            (false, false) => return Ok(()),
+        }
+        self.text_display.reset_cache();
+        self.refresh_display()
+    }
+
+    /// Returns the text selected with the mouse, if any.
+    pub fn selected_text(&self) -> Option<String> {
+        let range = self.selection?.range();
+        self.scrollback_slice(range.start, range.end).map(String::from)
+    }
+
+    /// Copies the text selected with the mouse to the clipboard.
+    ///
+    /// Returns `false` if no text was selected.
+    pub fn copy_selection(&self) -> bool {
+        match self.selected_text() {
+            Some(text) => {
+                clipboard::set(text);
+                true
+            }
+            None => false,
+        }
+    }
+
     /// Display the cursor of the terminal.
     pub fn display_cursor(&mut self) -> Result<(), &'static str> {
         // get info about the text displayable
@@ -639,8 +772,8 @@ impl Terminal {
     /// Updates the position of a cursor.
     /// # Arguments
     /// * `offset_from_end`: the position of the cursor relative to the end of text in number of characters.
-    /// * `underlying_char`: the ASCII code of the underlying character when the cursor is unseen.
-    pub fn update_cursor_pos(&mut self, offset_from_end: usize, underlying_char: u8) {
+    /// * `underlying_char`: the underlying character that is shown when the cursor is unseen.
+    pub fn update_cursor_pos(&mut self, offset_from_end: usize, underlying_char: char) {
         self.cursor.offset_from_end = offset_from_end;
         self.cursor.underlying_char = underlying_char;
     }
@@ -654,3 +787,29 @@ impl Terminal {
         Ok(())
     }
 }
+
+/// Returns the byte index in `s` of the character that is `offset_from_end` characters
+/// before the end of `s`, or the length of `s` if `offset_from_end` is zero.
+///
+/// This converts a cursor position, which counts characters from the end of the text,
+/// to an index that `String::insert()` and `String::remove()` accept.
+/// Returns `None` if `s` has fewer than `offset_from_end` characters.
+pub fn byte_index_from_end(s: &str, offset_from_end: usize) -> Option<usize> {
+    match offset_from_end {
+        0 => Some(s.len()),
+        n => s.char_indices().rev().nth(n - 1).map(|(index, _)| index),
+    }
+}
+
+/// Returns the largest index that is at most `index` and is at a character boundary of `s`.
+fn floor_char_boundary(s: &str, index: usize) -> usize {
+    if index >= s.len() {
+        return s.len();
+    }
+    (0..=index).rev().find(|&i| s.is_char_boundary(i)).unwrap_or(0)
+}
+
+/// Returns the smallest index that is at least `index` and is at a character boundary of `s`.
+fn ceil_char_boundary(s: &str, index: usize) -> usize {
+    (index..s.len()).find(|&i| s.is_char_boundary(i)).unwrap_or(s.len())
+}
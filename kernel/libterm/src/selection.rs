@@ -0,0 +1,108 @@
+//! Selection of text in the terminal with the mouse.
+//!
+//! A selection is a range of characters in the scrollback buffer, from the character where the
+//! left button was pressed (the anchor) to the character that the mouse is over now (the head),
+//! inclusive. Characters are identified by the index of their first byte in the scrollback buffer.
+//!
+//! The functions here map between characters and their positions on the text display.
+//! They must match how [`framebuffer_printer::print_string()`] lays out text:
+//! each character occupies one column no matter how many bytes it is encoded with,
+//! a line wraps once it is full, and a newline ends the line it is on.
+
+use core::ops::Range;
+
+/// A range of selected characters in the scrollback buffer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Selection {
+    /// The index of the character where the selection started.
+    anchor: usize,
+    /// The index of the character where the selection currently ends, which may precede the anchor.
+    head: usize,
+}
+
+impl Selection {
+    /// Creates a selection of the single character at the given index.
+    pub fn new(index: usize) -> Selection {
+        Selection { anchor: index, head: index }
+    }
+
+    /// Moves the end of the selection to the character at the given index.
+    pub fn extend_to(&mut self, index: usize) {
+        self.head = index;
+    }
+
+    /// Returns whether the selection was never extended beyond the character it started at,
+    /// e.g., because the mouse was merely clicked.
+    pub fn is_single(&self) -> bool {
+        self.anchor == self.head
+    }
+
+    /// Returns a range that contains the index of every selected character.
+    ///
+    /// If the last selected character is encoded with more than one byte,
+    /// the end of the range falls within that character.
+    pub fn range(&self) -> Range<usize> {
+        self.anchor.min(self.head)..self.anchor.max(self.head) + 1
+    }
+}
+
+/// Returns the index of the character in `text` that is shown at the given column and line
+/// of a text display that is `columns` characters wide.
+///
+/// A position after the end of a line maps to the newline that ends the line.
+/// Returns `None` if the position is after the end of the text.
+pub fn index_at(text: &str, columns: usize, column: usize, line: usize) -> Option<usize> {
+    let mut curr_column = 0;
+    let mut curr_line = 0;
+    for (index, c) in text.char_indices() {
+        if c != '\n' && curr_column == columns {
+            curr_column = 0;
+            curr_line += 1;
+        }
+        if curr_line > line {
+            break;
+        }
+        if curr_line == line && (curr_column == column || c == '\n' && curr_column < column) {
+            return Some(index);
+        }
+        if c == '\n' {
+            curr_column = 0;
+            curr_line += 1;
+        } else {
+            curr_column += 1;
+        }
+    }
+    None
+}
+
+/// Returns each character of `text` whose index is within the given `range`,
+/// along with the column and line at which it is shown
+/// on a text display that is `columns` characters wide and `lines` characters tall.
+///
+/// Newlines and characters that don't fit on the display are skipped.
+pub fn positions(
+    text: &str,
+    columns: usize,
+    lines: usize,
+    range: Range<usize>,
+) -> impl Iterator<Item = (usize, char, usize, usize)> + '_ {
+    let mut curr_column = 0;
+    let mut curr_line = 0;
+    text.char_indices()
+        .map(move |(index, c)| {
+            if c == '\n' {
+                curr_column = 0;
+                curr_line += 1;
+                return None;
+            }
+            if curr_column == columns {
+                curr_column = 0;
+                curr_line += 1;
+            }
+            curr_column += 1;
+            Some((index, c, curr_column - 1, curr_line))
+        })
+        .take_while(move |position| position.map_or(true, |(_, _, _, line)| line < lines))
+        .flatten()
+        .filter(move |(index, _, _, _)| range.contains(index))
+}
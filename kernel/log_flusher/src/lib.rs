@@ -0,0 +1,41 @@
+//! A task that writes the logger's buffered records to its writers.
+//!
+//! Once [`init()`] is invoked, log records are written to per-CPU ring buffers
+//! instead of directly to the logger's writers (see [`logger::enable_buffering()`]),
+//! and a dedicated task periodically drains those ring buffers to the writers.
+//! Thus, log statements no longer wait for slow writers like serial ports,
+//! at the cost of their output being delayed by up to the [`FLUSH_INTERVAL`].
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::string::ToString;
+use core::time::Duration;
+
+/// How often the buffered log records are written to the logger's writers.
+pub const FLUSH_INTERVAL: Duration = Duration::from_millis(5);
+
+/// The capacity of each CPU's ring buffer in bytes.
+///
+/// At the default [`FLUSH_INTERVAL`], this fits a burst of several hundred typical records per CPU.
+pub const BUFFER_CAPACITY: usize = 64 * 1024;
+
+/// Spawns the flusher task and then enables buffering in the logger.
+///
+/// This must be invoked after all CPUs have been registered and the full logger was initialized.
+pub fn init() -> Result<(), &'static str> {
+    spawn::new_task_builder(flush_loop, ())
+        .name("log_flusher".to_string())
+        .spawn()?;
+    logger::enable_buffering(BUFFER_CAPACITY)?;
+    log::info!("Enabled buffered logging with {} KiB per CPU.", BUFFER_CAPACITY / 1024);
+    Ok(())
+}
+
+fn flush_loop(_: ()) -> Result<(), &'static str> {
+    loop {
+        logger::flush_buffered();
+        sleep::sleep(FLUSH_INTERVAL).map_err(|_| "log flusher task failed to sleep")?;
+    }
+}
@@ -0,0 +1,301 @@
+//! Per-CPU ring buffers that log records are written to once buffering is enabled.
+//!
+//! Each CPU writes only to its own ring buffer, with interrupts held while it does so,
+//! such that a record written by an interrupt handler can't be interleaved
+//! with the record of the task that it interrupted.
+//! Thus, writing a record never waits for a lock or for a slow writer like a serial port;
+//! it only costs formatting the record and copying it into the ring buffer.
+//! If the ring buffer is full, the record is dropped and counted instead.
+//!
+//! A single consumer at a time drains the ring buffers via [`drain()`].
+//! Every record is tagged with a global sequence number when it's written,
+//! such that records from different CPUs are drained in the order they were logged.
+
+use alloc::{boxed::Box, vec::Vec};
+use core::{
+    cell::UnsafeCell,
+    cmp,
+    fmt::{self, Write},
+    ptr,
+    sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+};
+use cpu::CpuId;
+use spin::Once;
+
+/// The maximum length of a single formatted record in bytes.
+///
+/// Longer records are truncated.
+pub const MAX_RECORD_LENGTH: usize = 512;
+
+/// Appended to a record that was truncated, which also resets its color and ends its line.
+const TRUNCATED_SUFFIX: &str = " [truncated]\x1b[0m\n";
+
+/// The size of the header that precedes each record in a ring buffer:
+/// the record's sequence number followed by its length.
+const HEADER_SIZE: usize = 8 + 2;
+
+/// The smallest ring buffer capacity, which fits one record of the maximum length.
+pub const MIN_CAPACITY: usize = HEADER_SIZE + MAX_RECORD_LENGTH;
+
+/// The ring buffer of each CPU, sorted by CPU ID.
+static RINGS: Once<Box<[(CpuId, Ring)]>> = Once::new();
+
+/// Whether records are currently written to the ring buffers.
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// The sequence number of the next record to be written.
+static NEXT_SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+/// Whether a consumer is currently draining the ring buffers.
+static DRAINING: AtomicBool = AtomicBool::new(false);
+
+/// The number of dropped records that were already reported by [`drain()`].
+static REPORTED_DROPS: AtomicU64 = AtomicU64::new(0);
+
+/// A single-producer, single-consumer ring buffer of variable-length records.
+struct Ring {
+    data: Box<[UnsafeCell<u8>]>,
+    /// The total number of bytes ever written, which only the ring's CPU advances.
+    head: AtomicUsize,
+    /// The total number of bytes ever read, which only the consumer advances.
+    tail: AtomicUsize,
+    /// The number of records that didn't fit and haven't been reported yet.
+    dropped: AtomicU64,
+}
+
+// SAFETY: the bytes between `tail` and `head` are only accessed by the single consumer,
+// and all other bytes are only accessed by the ring's CPU, while it holds interrupts.
+unsafe impl Sync for Ring {}
+
+impl Ring {
+    fn new(capacity: usize) -> Ring {
+        Ring {
+            data: (0..capacity).map(|_| UnsafeCell::new(0)).collect(),
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+            dropped: AtomicU64::new(0),
+        }
+    }
+
+    fn capacity(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Copies `bytes` into the ring buffer starting at the given position,
+    /// wrapping around at its end.
+    ///
+    /// # Safety
+    /// The caller must have exclusive access to the given range of the ring buffer.
+    unsafe fn write_at(&self, position: usize, bytes: &[u8]) {
+        let start = position % self.capacity();
+        let first = cmp::min(bytes.len(), self.capacity() - start);
+        let base = UnsafeCell::raw_get(self.data.as_ptr());
+        ptr::copy_nonoverlapping(bytes.as_ptr(), base.add(start), first);
+        ptr::copy_nonoverlapping(bytes.as_ptr().add(first), base, bytes.len() - first);
+    }
+
+    /// Copies bytes from the ring buffer starting at the given position into `out`,
+    /// wrapping around at its end.
+    ///
+    /// # Safety
+    /// The caller must have exclusive access to the given range of the ring buffer.
+    unsafe fn read_at(&self, position: usize, out: &mut [u8]) {
+        let start = position % self.capacity();
+        let first = cmp::min(out.len(), self.capacity() - start);
+        let base = UnsafeCell::raw_get(self.data.as_ptr()) as *const u8;
+        ptr::copy_nonoverlapping(base.add(start), out.as_mut_ptr(), first);
+        ptr::copy_nonoverlapping(base, out.as_mut_ptr().add(first), out.len() - first);
+    }
+
+    /// Appends a record, or counts it as dropped if there isn't enough space for it.
+    ///
+    /// This must only be invoked on the ring's CPU with interrupts held.
+    fn push(&self, sequence: u64, record: &[u8]) {
+        let needed = HEADER_SIZE + record.len();
+        let head = self.head.load(Ordering::Relaxed);
+        let tail = self.tail.load(Ordering::Acquire);
+        if self.capacity() - head.wrapping_sub(tail) < needed {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+        let mut header = [0; HEADER_SIZE];
+        header[..8].copy_from_slice(&sequence.to_le_bytes());
+        header[8..].copy_from_slice(&(record.len() as u16).to_le_bytes());
+        // SAFETY: the bytes after `head` are free, and only this CPU writes to them.
+        unsafe {
+            self.write_at(head, &header);
+            self.write_at(head.wrapping_add(HEADER_SIZE), record);
+        }
+        self.head.store(head.wrapping_add(needed), Ordering::Release);
+    }
+
+    /// Returns the sequence number and length of the oldest record, if there is one.
+    ///
+    /// This must only be invoked by the consumer.
+    fn peek(&self) -> Option<(u64, usize)> {
+        let tail = self.tail.load(Ordering::Relaxed);
+        if self.head.load(Ordering::Acquire) == tail {
+            return None;
+        }
+        let mut header = [0; HEADER_SIZE];
+        // SAFETY: the bytes between `tail` and `head` were written and only the consumer reads them.
+        unsafe { self.read_at(tail, &mut header) };
+        let mut sequence = [0; 8];
+        sequence.copy_from_slice(&header[..8]);
+        let length = u16::from_le_bytes([header[8], header[9]]);
+        Some((u64::from_le_bytes(sequence), length as usize))
+    }
+
+    /// Removes the oldest record, which has the given length, copying it into `out`.
+    ///
+    /// This must only be invoked by the consumer, after [`Ring::peek()`] returned that record.
+    fn pop(&self, length: usize, out: &mut [u8]) {
+        let tail = self.tail.load(Ordering::Relaxed);
+        // SAFETY: the bytes between `tail` and `head` were written and only the consumer reads them.
+        unsafe { self.read_at(tail.wrapping_add(HEADER_SIZE), &mut out[..length]) };
+        self.tail.store(tail.wrapping_add(HEADER_SIZE + length), Ordering::Release);
+    }
+}
+
+/// A record being formatted on the stack, which is truncated if it's too long.
+struct RecordBuffer {
+    bytes: [u8; MAX_RECORD_LENGTH],
+    length: usize,
+    truncated: bool,
+}
+
+impl RecordBuffer {
+    fn new() -> RecordBuffer {
+        RecordBuffer { bytes: [0; MAX_RECORD_LENGTH], length: 0, truncated: false }
+    }
+
+    /// Returns the formatted record, ending with the [`TRUNCATED_SUFFIX`] if it was truncated.
+    fn finish(&mut self) -> &[u8] {
+        if self.truncated {
+            let end = self.length + TRUNCATED_SUFFIX.len();
+            self.bytes[self.length..end].copy_from_slice(TRUNCATED_SUFFIX.as_bytes());
+            self.length = end;
+        }
+        &self.bytes[..self.length]
+    }
+}
+
+impl Write for RecordBuffer {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        if self.truncated {
+            return Ok(());
+        }
+        // Leave room for the suffix, and only truncate at a character boundary.
+        let available = MAX_RECORD_LENGTH - TRUNCATED_SUFFIX.len() - self.length;
+        let mut length = cmp::min(s.len(), available);
+        while !s.is_char_boundary(length) {
+            length -= 1;
+        }
+        self.bytes[self.length..self.length + length].copy_from_slice(&s.as_bytes()[..length]);
+        self.length += length;
+        self.truncated = length < s.len();
+        Ok(())
+    }
+}
+
+/// Creates a ring buffer with the given capacity in bytes for each CPU,
+/// if they don't exist yet, and starts writing records to them.
+pub(crate) fn enable(capacity: usize) -> Result<(), &'static str> {
+    if capacity < MIN_CAPACITY {
+        return Err("log ring buffers must be able to hold at least one record of the maximum length");
+    }
+    RINGS.call_once(|| {
+        let mut cpus: Vec<CpuId> = cpu::cpus().collect();
+        cpus.sort_unstable();
+        cpus.into_iter().map(|cpu| (cpu, Ring::new(capacity))).collect()
+    });
+    ENABLED.store(true, Ordering::Release);
+    Ok(())
+}
+
+/// Stops writing records to the ring buffers.
+///
+/// Records that were already written remain in the ring buffers until they're drained.
+pub(crate) fn disable() {
+    ENABLED.store(false, Ordering::Release);
+}
+
+/// Returns whether records are currently written to the ring buffers.
+pub(crate) fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Acquire)
+}
+
+/// Writes the given record to the current CPU's ring buffer.
+///
+/// Returns `false` if buffering isn't enabled or the current CPU has no ring buffer,
+/// in which case the caller must write the record itself.
+pub(crate) fn write(arguments: fmt::Arguments) -> bool {
+    if !is_enabled() {
+        return false;
+    }
+    let Some(rings) = RINGS.get() else { return false };
+
+    let mut record = RecordBuffer::new();
+    let _ = record.write_fmt(arguments);
+    let record = record.finish();
+
+    // Hold interrupts such that this task can't migrate to another CPU
+    // and an interrupt handler on this CPU can't write to the ring buffer concurrently.
+    let _held_interrupts = sync_irq::hold_interrupts();
+    let current = cpu::current_cpu();
+    let Ok(index) = rings.binary_search_by_key(&current, |(cpu, _)| *cpu) else { return false };
+    let sequence = NEXT_SEQUENCE.fetch_add(1, Ordering::Relaxed);
+    rings[index].1.push(sequence, record);
+    true
+}
+
+/// Removes the records from all ring buffers and passes each of them to `write`,
+/// in the order they were written.
+/// If any records were dropped since the last drain, `write` is also passed a note saying so.
+///
+/// Only the records that were written before this was invoked are drained,
+/// such that this doesn't run forever if records are written faster than they're drained.
+/// If another drain is already in progress, this does nothing.
+///
+/// Returns the number of records drained.
+pub(crate) fn drain(mut write: impl FnMut(fmt::Arguments)) -> usize {
+    let Some(rings) = RINGS.get() else { return 0 };
+    if DRAINING.compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed).is_err() {
+        return 0;
+    }
+
+    let end = NEXT_SEQUENCE.load(Ordering::Relaxed);
+    let mut record = [0; MAX_RECORD_LENGTH];
+    let mut count = 0;
+    loop {
+        let oldest = rings.iter()
+            .filter_map(|(_, ring)| ring.peek().map(|(sequence, length)| (sequence, length, ring)))
+            .filter(|(sequence, ..)| *sequence < end)
+            .min_by_key(|(sequence, ..)| *sequence);
+        let Some((_, length, ring)) = oldest else { break };
+        ring.pop(length, &mut record);
+        match core::str::from_utf8(&record[..length]) {
+            Ok(record) => write(format_args!("{record}")),
+            Err(_) => write(format_args!("<invalid log record>\n")),
+        }
+        count += 1;
+    }
+
+    let dropped: u64 = rings.iter().map(|(_, ring)| ring.dropped.swap(0, Ordering::Relaxed)).sum();
+    if dropped > 0 {
+        REPORTED_DROPS.fetch_add(dropped, Ordering::Relaxed);
+        write(format_args!(
+            "\x1b[33m---- {dropped} log records were dropped because the log buffer was full ----\x1b[0m\n"
+        ));
+    }
+
+    DRAINING.store(false, Ordering::Release);
+    count
+}
+
+/// Returns the total number of records that were dropped because a ring buffer was full.
+pub(crate) fn dropped_records() -> u64 {
+    let unreported: u64 = RINGS.get()
+        .map_or(0, |rings| rings.iter().map(|(_, ring)| ring.dropped.load(Ordering::Relaxed)).sum());
+    REPORTED_DROPS.load(Ordering::Relaxed) + unreported
+}
@@ -0,0 +1,97 @@
+//! Per-crate log level filters.
+//!
+//! By default, every log record is subject to the global log level set by [`set_log_level()`].
+//! A crate's log level can be overridden at runtime, e.g., to enable the `trace!()` statements
+//! of a single crate that's being debugged without flooding the log with every other crate's.
+//!
+//! A record's crate is the first component of its target, which the `log` macros
+//! set to the module path of the code that invoked them, e.g., `mod_mgmt::parse_nano_core`.
+//!
+//! Since the `log` macros discard records above [`log::max_level()`] before reaching the logger,
+//! the maximum level is kept at the most verbose of the global and all per-crate levels,
+//! and [`enabled()`] then applies the level of each record's crate.
+//!
+//! [`set_log_level()`]: ::set_log_level
+
+use alloc::{string::String, vec::Vec};
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use log::{LevelFilter, Metadata};
+use sync_irq::IrqSafeRwLock;
+
+/// The log level of crates that don't have a filter of their own.
+static GLOBAL_LEVEL: AtomicUsize = AtomicUsize::new(LevelFilter::Trace as usize);
+/// Whether any per-crate filters exist, which lets records skip looking them up.
+static HAS_FILTERS: AtomicBool = AtomicBool::new(false);
+/// The per-crate filters, sorted by crate name.
+static FILTERS: IrqSafeRwLock<Vec<(String, LevelFilter)>> = IrqSafeRwLock::new(Vec::new());
+
+/// Returns whether a record with the given metadata should be logged.
+#[inline(always)]
+pub fn enabled(metadata: &Metadata) -> bool {
+    metadata.level() <= level_for(metadata.target())
+}
+
+/// Returns the log level that applies to records with the given target.
+fn level_for(target: &str) -> LevelFilter {
+    if HAS_FILTERS.load(Ordering::Acquire) {
+        let crate_name = target.split("::").next().unwrap_or(target);
+        let filters = FILTERS.read();
+        if let Ok(index) = filters.binary_search_by(|(name, _)| name.as_str().cmp(crate_name)) {
+            return filters[index].1;
+        }
+    }
+    global_level()
+}
+
+pub fn global_level() -> LevelFilter {
+    level_filter_from_usize(GLOBAL_LEVEL.load(Ordering::Relaxed))
+}
+
+pub fn set_global_level(level: LevelFilter) {
+    GLOBAL_LEVEL.store(level as usize, Ordering::Relaxed);
+    update_max_level(&FILTERS.read());
+}
+
+/// Sets or removes (if `level` is `None`) the filter of the given crate,
+/// returning the crate's previous filter level, if any.
+pub fn set_crate_level(crate_name: &str, level: Option<LevelFilter>) -> Option<LevelFilter> {
+    // Crate names may contain dashes, but a record's target uses underscores instead.
+    let crate_name = crate_name.replace('-', "_");
+    let mut filters = FILTERS.write();
+    let search = filters.binary_search_by(|(name, _)| name.as_str().cmp(&crate_name));
+    let previous = match (search, level) {
+        (Ok(index), Some(level)) => Some(core::mem::replace(&mut filters[index].1, level)),
+        (Ok(index), None) => Some(filters.remove(index).1),
+        (Err(index), Some(level)) => {
+            filters.insert(index, (crate_name, level));
+            None
+        }
+        (Err(_), None) => None,
+    };
+    HAS_FILTERS.store(!filters.is_empty(), Ordering::Release);
+    update_max_level(&filters);
+    previous
+}
+
+/// Returns a copy of all per-crate filters, sorted by crate name.
+pub fn crate_levels() -> Vec<(String, LevelFilter)> {
+    FILTERS.read().clone()
+}
+
+fn update_max_level(filters: &[(String, LevelFilter)]) {
+    let max = filters.iter()
+        .map(|(_, level)| *level)
+        .fold(global_level(), core::cmp::max);
+    log::set_max_level(max);
+}
+
+fn level_filter_from_usize(level: usize) -> LevelFilter {
+    match level {
+        0 => LevelFilter::Off,
+        1 => LevelFilter::Error,
+        2 => LevelFilter::Warn,
+        3 => LevelFilter::Info,
+        4 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    }
+}
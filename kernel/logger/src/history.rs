@@ -0,0 +1,68 @@
+//! A history of the most recent log records, kept in memory such that it can be retrieved later,
+//! e.g., to include it in a system report.
+//!
+//! The history holds a fixed number of bytes; once it's full,
+//! the oldest records are evicted a whole line at a time to make room for new ones.
+
+use alloc::{collections::VecDeque, string::String};
+use core::{fmt::{self, Write}, sync::atomic::{AtomicBool, Ordering}};
+use sync_irq::IrqSafeMutex;
+
+/// The history of recent log records, which is `None` until it's enabled.
+static HISTORY: IrqSafeMutex<Option<History>> = IrqSafeMutex::new(None);
+
+/// Whether the history is enabled, which lets records skip its lock if it isn't.
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+struct History {
+    bytes: VecDeque<u8>,
+    capacity: usize,
+}
+
+impl Write for History {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        // Only the end of a string that's larger than the entire history can be kept.
+        let bytes = s.as_bytes();
+        let bytes = &bytes[bytes.len().saturating_sub(self.capacity) ..];
+        let overflow = (self.bytes.len() + bytes.len()).saturating_sub(self.capacity);
+        if overflow > 0 {
+            // Evict through the end of the line that contains the last byte that must be evicted.
+            let end = self.bytes.iter()
+                .skip(overflow - 1)
+                .position(|&b| b == b'\n')
+                .map_or(self.bytes.len(), |i| overflow + i);
+            self.bytes.drain(.. end);
+        }
+        self.bytes.extend(bytes);
+        Ok(())
+    }
+}
+
+/// Starts keeping up to `capacity` bytes of recent log records.
+///
+/// If the history is already enabled, this does nothing.
+pub(crate) fn enable(capacity: usize) {
+    let mut history = HISTORY.lock();
+    if history.is_none() {
+        *history = Some(History { bytes: VecDeque::with_capacity(capacity), capacity });
+        ENABLED.store(true, Ordering::Release);
+    }
+}
+
+/// Appends the given formatted record to the history, if it's enabled.
+pub(crate) fn record(arguments: fmt::Arguments) {
+    if !ENABLED.load(Ordering::Acquire) {
+        return;
+    }
+    if let Some(history) = HISTORY.lock().as_mut() {
+        let _ = history.write_fmt(arguments);
+    }
+}
+
+/// Returns the contents of the history, oldest record first,
+/// or `None` if it isn't enabled.
+pub(crate) fn contents() -> Option<String> {
+    HISTORY.lock().as_mut().map(|history| {
+        String::from_utf8_lossy(history.bytes.make_contiguous()).into_owned()
+    })
+}
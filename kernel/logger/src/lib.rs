@@ -9,6 +9,23 @@
 //! Early log messages (before memory management is initialized) are saved
 //! to a static fixed-sized buffer such that they are not lost and
 //! can be retrieved once logging sinks are ready to be used.
+//!
+//! By default, each log statement is written to the writers synchronously,
+//! which means that it waits for every writer, e.g., a slow serial port,
+//! while holding the logger's lock with interrupts disabled.
+//! Once [`enable_buffering()`] is invoked, log statements are instead written
+//! to a ring buffer on the current CPU, without taking any global lock,
+//! and the buffered records are written to the writers by [`flush_buffered()`],
+//! which is typically invoked periodically by a dedicated task (see the `log_flusher` crate).
+//! This bounds the latency of a log statement by the time it takes to format the record,
+//! which matters most for log statements in interrupt handlers.
+//! The `log_eval` benchmark measures that latency with and without buffering.
+//!
+//! The most recent log records can also be kept in memory, once [`enable_history()`] is invoked,
+//! such that they can be retrieved via [`history()`], e.g., to include them in a bug report.
+//!
+//! Besides the global log level, each crate can have its own log level,
+//! which is set at runtime via [`set_crate_log_level()`], e.g., by the `loglevel` application.
 
 #![no_std]
 #![feature(trait_alias)]
@@ -18,15 +35,23 @@ extern crate crossbeam_utils;
 extern crate log;
 extern crate sync_irq;
 extern crate serial_port_basic;
+extern crate cpu;
+extern crate spin;
 
-use log::{Record, Level, Metadata, Log};
+mod buffered;
+mod filter;
+mod history;
+
+use log::{Record, Level, LevelFilter, Metadata, Log};
 use core::{fmt::{self, Write}, ops::Deref};
 use sync_irq::IrqSafeMutex;
 use serial_port_basic::SerialPort;
-use alloc::{sync::Arc, vec::Vec};
+use alloc::{string::String, sync::Arc, vec::Vec};
+use crossbeam_utils::atomic::AtomicCell;
 
 #[cfg(mirror_log_to_vga)]
 pub use mirror_log::set_log_mirror_function;
+pub use buffered::{MAX_RECORD_LENGTH, MIN_CAPACITY as MIN_BUFFER_CAPACITY};
 
 /// By default, Theseus will print all log levels, including `Trace` and above.
 pub const DEFAULT_LOG_LEVEL: Level = Level::Trace;
@@ -57,6 +82,11 @@ static EARLY_LOG_BUFFER: IrqSafeMutex<EarlyLogBuffer<EARLY_LOG_BUFFER_SIZE>> =
 /// If `None`, it is uninitialized, and the [`EARLY_LOGGER`] will be used as a fallback.
 static LOGGER: IrqSafeMutex<Option<Logger>> = IrqSafeMutex::new(None);
 
+/// The callback that will optionally be invoked with every log record
+/// that is enabled by the current log level, e.g., to forward it to a remote machine.
+static RECORD_SINK: AtomicCell<Option<fn(&Record)>> = AtomicCell::new(None);
+const _: () = assert!(AtomicCell::<fn(&Record)>::is_lock_free());
+
 /// An early logger that can only write to a fixed number of [`SerialPort`]s,
 /// intended for basic use before dynamic heap allocation is available.
 struct EarlyLogger([Option<SerialPort>; LOG_MAX_WRITERS]);
@@ -205,26 +235,36 @@ impl DummyLogger {
     /// A re-implementation of [`core::fmt::Write::write_fmt()`]
     /// that doesn't require `&mut self`.
     ///
-    /// This function writes to the real (fully-featured) [`LOGGER`] if it has been initialized;
-    /// otherwise, it falls back to writing to the [`EARLY_LOGGER`] instead.
+    /// If buffering is enabled, this writes to the current CPU's ring buffer.
+    /// Otherwise, this writes to the real (fully-featured) [`LOGGER`] if it has been initialized,
+    /// or falls back to writing to the [`EARLY_LOGGER`] instead.
     fn write_fmt(&self, arguments: fmt::Arguments) -> fmt::Result {
-        if let Some(logger) = &*LOGGER.lock() {
-            for writer in logger.writers.iter() {
-                let _ = writer.deref().lock().write_fmt(arguments);
-            }
-        } else {
-            let _ = EARLY_LOGGER.lock().write_fmt(arguments);
+        if buffered::write(arguments) {
+            return Ok(());
         }
-        // If there was an error above, there's literally nothing we can do but ignore it,
-        // because there is no other lower-level way to log errors than this logger.
+        write_to_writers(arguments);
         Ok(())
     }
 }
 
+/// Writes directly to the real [`LOGGER`]'s writers if it has been initialized,
+/// or to the [`EARLY_LOGGER`] otherwise.
+fn write_to_writers(arguments: fmt::Arguments) {
+    if let Some(logger) = &*LOGGER.lock() {
+        for writer in logger.writers.iter() {
+            let _ = writer.deref().lock().write_fmt(arguments);
+        }
+    } else {
+        let _ = EARLY_LOGGER.lock().write_fmt(arguments);
+    }
+    // If there was an error above, there's literally nothing we can do but ignore it,
+    // because there is no other lower-level way to log errors than this logger.
+}
+
 impl Log for DummyLogger {
     #[inline(always)]
     fn enabled(&self, metadata: &Metadata) -> bool {
-        metadata.level() <= log::max_level()
+        filter::enabled(metadata)
     }
 
     fn log(&self, record: &Record) {
@@ -265,10 +305,16 @@ impl Log for DummyLogger {
                 record.args(),
             ));
         }
+
+        history::record(format_args!("{}{}:{}: {}\n", level_str, file_loc, line_loc, record.args()));
+
+        if let Some(func) = RECORD_SINK.load() {
+            func(record);
+        }
     }
 
     fn flush(&self) {
-        // flushing the log is a no-op, since there is no write buffering.
+        flush_buffered();
     }
 }
 
@@ -335,8 +381,103 @@ where
 /// 
 /// If `Level::Info` is set, `debug!()` and `trace!()` will not be logged, 
 /// but `info!()`, `warn!()`, and `error!()` will be. 
+///
+/// This doesn't apply to crates that have their own log level; see [`set_crate_log_level()`].
 pub fn set_log_level(level: Level) {
-    log::set_max_level(level.to_level_filter())
+    filter::set_global_level(level.to_level_filter())
+}
+
+/// Returns the global log level, which applies to all crates without their own log level.
+pub fn log_level() -> LevelFilter {
+    filter::global_level()
+}
+
+/// Sets the log level of the given crate, overriding the global log level for its log messages.
+/// If `level` is `None`, the crate's log level is removed, such that the global log level applies again.
+///
+/// A log message belongs to the crate whose code invoked the log macro,
+/// as determined by the first component of the message's target (by default, its module path).
+/// Thus, this affects all log messages of the crate, even those from functions inlined into other crates,
+/// but not those of its dependencies.
+///
+/// Returns the crate's previous log level, if it had one.
+pub fn set_crate_log_level(crate_name: &str, level: Option<LevelFilter>) -> Option<LevelFilter> {
+    filter::set_crate_level(crate_name, level)
+}
+
+/// Returns the names and log levels of all crates that have their own log level, sorted by name.
+pub fn crate_log_levels() -> Vec<(String, LevelFilter)> {
+    filter::crate_levels()
+}
+
+/// Sets the function that every enabled log record will be passed to,
+/// in addition to being written to the logger's writers.
+///
+/// Unlike the writers, the sink receives the record itself, including its level and target,
+/// so it can format the record however it needs to.
+/// Pass `None` to remove the current sink.
+///
+/// The sink may be invoked from any context, including interrupt handlers,
+/// so it must not block.
+pub fn set_record_sink(func: Option<fn(&Record)>) {
+    RECORD_SINK.store(func);
+}
+
+/// Starts writing log records to per-CPU ring buffers, each of which holds `capacity` bytes,
+/// instead of writing them directly to the logger's writers.
+///
+/// The buffered records are only written to the writers when [`flush_buffered()`] is invoked,
+/// so the caller must ensure that happens regularly, e.g., from a dedicated task.
+/// If a CPU's ring buffer is full, its new records are dropped until it's flushed.
+///
+/// The ring buffers are created on the first invocation, for each CPU that exists then,
+/// so this must be invoked after all CPUs have been registered.
+/// Later invocations re-enable buffering but don't change the capacity.
+/// Returns an error if `capacity` is smaller than [`MIN_BUFFER_CAPACITY`].
+pub fn enable_buffering(capacity: usize) -> Result<(), &'static str> {
+    buffered::enable(capacity)
+}
+
+/// Stops buffering log records, such that they're written directly to the logger's writers again,
+/// and flushes the records that were buffered.
+pub fn disable_buffering() {
+    buffered::disable();
+    flush_buffered();
+}
+
+/// Returns whether log records are currently written to per-CPU ring buffers.
+pub fn is_buffering() -> bool {
+    buffered::is_enabled()
+}
+
+/// Writes the buffered log records of all CPUs to the logger's writers, in the order they were logged.
+///
+/// Returns the number of records written.
+/// If another flush is in progress, this returns `0` without waiting for it.
+pub fn flush_buffered() -> usize {
+    buffered::drain(write_to_writers)
+}
+
+/// Returns the number of log records that were dropped because a ring buffer was full.
+pub fn dropped_records() -> u64 {
+    buffered::dropped_records()
+}
+
+/// Starts keeping the most recent log records in memory, up to `capacity` bytes in total,
+/// such that they can be retrieved via [`history()`].
+///
+/// Once the history is full, the oldest records are evicted to make room for new ones.
+/// Note that every record is then also written to the history under a global lock,
+/// even if [buffering](enable_buffering) is enabled.
+/// Later invocations don't change the capacity.
+pub fn enable_history(capacity: usize) {
+    history::enable(capacity)
+}
+
+/// Returns the most recent log records, oldest first and without their colors,
+/// or `None` if [`enable_history()`] hasn't been invoked.
+pub fn history() -> Option<String> {
+    history::contents()
 }
 
 /// Convenience function for writing formatted arguments to the logger.
@@ -0,0 +1,142 @@
+//! A loopback storage device, which exposes a file as a block device,
+//! e.g., to mount a disk image stored in another filesystem.
+//!
+//! Holes in the backing file are translated into unwritten regions of the device:
+//! they read as zeros, and blocks that are discarded or written with all zeros
+//! are punched out of the file instead of being stored,
+//! so that sparse disk images don't consume memory or disk space for their empty regions.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::sync::Arc;
+use fs_node::FileRef;
+use io::{BlockIo, BlockReader, BlockWriter, IoError, KnownLength};
+use spin::Mutex;
+use storage_device::{StorageDevice, StorageDeviceRef};
+
+/// The default block size of a loopback device, in bytes.
+pub const DEFAULT_BLOCK_SIZE: usize = 512;
+
+/// A storage device whose blocks are stored in a file.
+pub struct LoopDevice {
+    file: FileRef,
+    block_size: usize,
+    num_blocks: usize,
+}
+
+impl LoopDevice {
+    /// Creates a new loopback device backed by the given `file`, with blocks of `block_size` bytes.
+    ///
+    /// The device covers all the whole blocks within the file's current length.
+    /// To create a sparse disk image, first extend an empty file via [`fs_node::File::set_len()`].
+    pub fn new(file: FileRef, block_size: usize) -> Result<StorageDeviceRef, &'static str> {
+        if block_size == 0 {
+            return Err("loopback device block size must not be zero");
+        }
+        let num_blocks = file.lock().len() / block_size;
+        let device = LoopDevice { file, block_size, num_blocks };
+        Ok(Arc::new(Mutex::new(device)))
+    }
+
+    /// Returns the file that backs this device.
+    pub fn file(&self) -> &FileRef {
+        &self.file
+    }
+
+    /// Returns whether the given block is unwritten, i.e., lies within a hole of the backing file.
+    pub fn is_unwritten(&self, block: usize) -> bool {
+        let offset = block * self.block_size;
+        let file = self.file.lock();
+        match file.seek_hole(offset) {
+            // The block is unwritten if a hole starts at the block and no data follows within it.
+            Some(hole) if hole == offset => file.seek_data(offset)
+                .map_or(true, |data| data >= offset + self.block_size),
+            _ => false,
+        }
+    }
+
+    /// Returns the byte range in the backing file of the given range of blocks,
+    /// or an error if it extends beyond the end of this device.
+    fn byte_range(&self, block_offset: usize, num_blocks: usize) -> Result<(usize, usize), IoError> {
+        let end_block = block_offset.checked_add(num_blocks).ok_or(IoError::InvalidInput)?;
+        if end_block > self.num_blocks {
+            return Err(IoError::InvalidInput);
+        }
+        Ok((block_offset * self.block_size, num_blocks * self.block_size))
+    }
+}
+
+impl BlockIo for LoopDevice {
+    fn block_size(&self) -> usize {
+        self.block_size
+    }
+}
+
+impl KnownLength for LoopDevice {
+    fn len(&self) -> usize {
+        self.num_blocks * self.block_size
+    }
+}
+
+impl BlockReader for LoopDevice {
+    fn read_blocks(&mut self, buffer: &mut [u8], block_offset: usize) -> Result<usize, IoError> {
+        if buffer.len() % self.block_size != 0 {
+            return Err(IoError::InvalidInput);
+        }
+        let num_blocks = buffer.len() / self.block_size;
+        let (offset, len) = self.byte_range(block_offset, num_blocks)?;
+        if len == 0 {
+            return Ok(0);
+        }
+        // Holes in the backing file read as zeros.
+        self.file.lock().read_at(buffer, offset)?;
+        Ok(num_blocks)
+    }
+}
+
+impl BlockWriter for LoopDevice {
+    fn write_blocks(&mut self, buffer: &[u8], block_offset: usize) -> Result<usize, IoError> {
+        if buffer.len() % self.block_size != 0 {
+            return Err(IoError::InvalidInput);
+        }
+        let num_blocks = buffer.len() / self.block_size;
+        let (offset, _len) = self.byte_range(block_offset, num_blocks)?;
+        let mut file = self.file.lock();
+        let is_zero = |i: usize| buffer[(i * self.block_size) .. ((i + 1) * self.block_size)].iter().all(|b| *b == 0);
+        // Write each run of non-zero blocks as data, and punch out each run of zeroed blocks.
+        let mut run_start = 0;
+        while run_start < num_blocks {
+            let zero_run = is_zero(run_start);
+            let mut run_end = run_start + 1;
+            while run_end < num_blocks && is_zero(run_end) == zero_run {
+                run_end += 1;
+            }
+            let run_offset = offset + run_start * self.block_size;
+            let run_len = (run_end - run_start) * self.block_size;
+            if zero_run {
+                file.punch_hole(run_offset, run_len)?;
+            } else {
+                file.write_at(&buffer[(run_start * self.block_size) .. (run_end * self.block_size)], run_offset)?;
+            }
+            run_start = run_end;
+        }
+        Ok(num_blocks)
+    }
+
+    fn flush(&mut self) -> Result<(), IoError> {
+        self.file.lock().sync_data()
+    }
+}
+
+impl StorageDevice for LoopDevice {
+    fn size_in_blocks(&self) -> usize {
+        self.num_blocks
+    }
+
+    fn discard_blocks(&mut self, block_offset: usize, num_blocks: usize) -> Result<(), IoError> {
+        let (offset, len) = self.byte_range(block_offset, num_blocks)?;
+        self.file.lock().punch_hole(offset, len)
+    }
+}
@@ -0,0 +1,162 @@
+//! A pool of recently freed `MappedPages` that can be reused for new mappings.
+//!
+//! Loading and unloading many crates, e.g., when bulk-loading a namespace or swapping crates,
+//! repeatedly allocates virtual pages and physical frames, maps them, and then unmaps and frees them again.
+//! Instead of unmapping a crate's section pages when it is dropped, they can be [`recycle`]d into this pool,
+//! and a later allocation of a similar size can [`take`] them, keeping both the pages and frames
+//! and requiring only that their flags be changed.
+//!
+//! Taking pages from the pool can optionally defer the resulting TLB invalidations
+//! into a [`TlbFlushBatch`], such that several mappings can be obtained with a single flush.
+//!
+//! Note that the contents of pages taken from the pool are not cleared,
+//! just like newly-allocated frames.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::collections::VecDeque;
+use log::trace;
+use memory::{MappedPages, Mapper, PageRange, PteFlagsArch, TlbFlushBatch};
+use spin::Mutex;
+
+/// The default maximum number of pages held in the pool (16 MiB of 4KiB pages).
+pub const DEFAULT_CAPACITY_IN_PAGES: usize = 4096;
+
+static POOL: Mutex<MappedPagesPool> = Mutex::new(MappedPagesPool::new(DEFAULT_CAPACITY_IN_PAGES));
+
+/// Adds the given `MappedPages` to the pool such that they can be reused by a future call to [`take`].
+///
+/// If this exceeds the pool's capacity, the oldest mappings in the pool are dropped (unmapped).
+/// The given mapping is dropped if it's larger than the pool's entire capacity.
+pub fn recycle(mp: MappedPages) {
+    POOL.lock().recycle(mp);
+}
+
+/// Takes a mapping of exactly `num_pages` pages from the pool and remaps it with the given `flags`.
+///
+/// If `within` is `Some`, only a mapping that lies entirely within that range of pages is taken.
+///
+/// If `batch` is `Some`, the TLB invalidations needed for remapping are added to it,
+/// and the returned pages must not be accessed until the `batch` has been flushed.
+/// Otherwise, they are invalidated immediately.
+///
+/// Returns `None` if no suitable mapping was found or the mapping couldn't be remapped,
+/// in which case the caller should allocate and map new pages.
+pub fn take<F: Into<PteFlagsArch>>(
+    num_pages: usize,
+    within: Option<&PageRange>,
+    flags: F,
+    active_table_mapper: &mut Mapper,
+    batch: Option<&mut TlbFlushBatch>,
+) -> Option<MappedPages> {
+    let mut mp = POOL.lock().take(num_pages, within)?;
+    let remapped = match batch {
+        Some(batch) => mp.remap_batched(active_table_mapper, flags, batch),
+        None => mp.remap(active_table_mapper, flags),
+    };
+    match remapped {
+        Ok(()) => Some(mp),
+        Err(e) => {
+            trace!("mapped_pages_pool::take(): failed to remap {:?}: {}", mp, e);
+            None
+        }
+    }
+}
+
+/// Sets the maximum number of pages held in the pool, dropping the oldest mappings to fit within it.
+///
+/// A capacity of zero disables the pool.
+pub fn set_capacity(capacity_in_pages: usize) {
+    POOL.lock().set_capacity(capacity_in_pages);
+}
+
+/// Returns the number of pages currently held in the pool.
+pub fn cached_pages() -> usize {
+    POOL.lock().cached_pages
+}
+
+/// Drops (unmaps) all mappings held in the pool.
+pub fn clear() {
+    let entries = {
+        let mut pool = POOL.lock();
+        pool.cached_pages = 0;
+        core::mem::take(&mut pool.entries)
+    };
+    // Unmap the entries after releasing the lock.
+    drop(entries);
+}
+
+
+/// A set of mapped pages that are no longer in use, ordered from oldest to newest.
+struct MappedPagesPool {
+    entries: VecDeque<MappedPages>,
+    cached_pages: usize,
+    capacity_in_pages: usize,
+}
+
+impl MappedPagesPool {
+    const fn new(capacity_in_pages: usize) -> MappedPagesPool {
+        MappedPagesPool {
+            entries: VecDeque::new(),
+            cached_pages: 0,
+            capacity_in_pages,
+        }
+    }
+
+    fn recycle(&mut self, mp: MappedPages) {
+        let size = mp.size_in_pages();
+        if size == 0 || size > self.capacity_in_pages {
+            return;
+        }
+        self.evict(self.capacity_in_pages - size);
+        self.cached_pages += size;
+        self.entries.push_back(mp);
+    }
+
+    /// Removes the best-fitting mapping, i.e., the smallest one that has at least `num_pages` pages,
+    /// and splits off any excess pages back into the pool.
+    fn take(&mut self, num_pages: usize, within: Option<&PageRange>) -> Option<MappedPages> {
+        if num_pages == 0 {
+            return None;
+        }
+        let (index, _) = self.entries.iter()
+            .enumerate()
+            .filter(|(_, mp)| mp.size_in_pages() >= num_pages)
+            .filter(|(_, mp)| within.map_or(true, |range| range.contains_range(mp.range())))
+            .min_by_key(|(_, mp)| mp.size_in_pages())?;
+        let mp = self.entries.remove(index)?;
+        self.cached_pages -= mp.size_in_pages();
+
+        if mp.size_in_pages() == num_pages {
+            return Some(mp);
+        }
+        let split_at = *mp.start() + num_pages;
+        match mp.split(split_at) {
+            Ok((mp, excess)) => {
+                self.cached_pages += excess.size_in_pages();
+                self.entries.push_back(excess);
+                Some(mp)
+            }
+            Err(mp) => {
+                self.cached_pages += mp.size_in_pages();
+                self.entries.push_back(mp);
+                None
+            }
+        }
+    }
+
+    fn set_capacity(&mut self, capacity_in_pages: usize) {
+        self.capacity_in_pages = capacity_in_pages;
+        self.evict(capacity_in_pages);
+    }
+
+    /// Drops the oldest mappings until at most `max_pages` pages remain in the pool.
+    fn evict(&mut self, max_pages: usize) {
+        while self.cached_pages > max_pages {
+            let Some(oldest) = self.entries.pop_front() else { break };
+            self.cached_pages -= oldest.size_in_pages();
+        }
+    }
+}
@@ -11,27 +11,38 @@ extern crate fs_node;
 extern crate memory;
 extern crate irq_safety;
 extern crate io;
+extern crate fs_quota;
 
 
-use alloc::string::String;
+use alloc::{collections::BTreeMap, string::String};
 use fs_node::{DirRef, WeakDirRef, File, FsNode};
-use memory::{MappedPages, get_kernel_mmi_ref, allocate_pages_by_bytes, PteFlags};
+use memory::{MappedPages, Page, get_kernel_mmi_ref, allocate_pages_by_bytes, PteFlags, PAGE_SIZE};
 use alloc::sync::Arc;
 use spin::Mutex;
 use fs_node::{FileOrDir, FileRef};
 use io::{ByteReader, ByteWriter, IoError, KnownLength};
+use fs_quota::FileOwner;
 
-/// The struct that represents a file in memory that is backed by MappedPages
+/// The mapping returned by [`MemFile::as_mapping()`] for a file without any contents.
+static EMPTY_MAPPING: MappedPages = MappedPages::empty();
+
+/// The struct that represents a file in memory that is backed by MappedPages.
+///
+/// A `MemFile` can be sparse: its contents are stored as a set of extents,
+/// and the ranges between them (holes) read as zeros without consuming any memory.
 pub struct MemFile {
     /// The name of the file.
     name: String,
     /// The length in bytes of the file.
-    /// Note that this is not the same as the capacity of its underlying MappedPages object. 
+    /// Note that this is not the same as the capacity of its underlying MappedPages objects. 
     len: usize,
-    /// The underlying contents of this file in memory.
-    mp: MappedPages,
+    /// The underlying contents of this file in memory, keyed by their page-aligned offset within the file.
+    /// Extents never overlap, and the ranges between them are holes.
+    extents: BTreeMap<usize, MappedPages>,
     /// The parent directory that contains this file.
     parent: WeakDirRef,
+    /// The task group that owns this file, which is charged for the memory used by its extents.
+    owner: FileOwner,
 }
 
 impl MemFile {
@@ -43,16 +54,97 @@ impl MemFile {
 
     /// Creates a new `MemFile` in the given `parent` directory with the contents of the given `mapped_pages`.
     pub fn from_mapped_pages(mapped_pages: MappedPages, name: String, len: usize, parent: &DirRef) -> Result<FileRef, &'static str> {
+        let mut owner = FileOwner::for_current_group()?;
+        owner.charge(mapped_pages.size_in_bytes())?;
+        let mut extents = BTreeMap::new();
+        if mapped_pages.size_in_bytes() != 0 {
+            extents.insert(0, mapped_pages);
+        }
         let memfile = MemFile {
             name,
             len,
-            mp: mapped_pages, 
+            extents,
             parent: Arc::downgrade(parent), 
+            owner,
         };
         let file_ref = Arc::new(Mutex::new(memfile)) as FileRef;
         parent.lock().insert(FileOrDir::File(file_ref.clone()))?; // adds the newly created file to the tree
         Ok(file_ref)
     }
+
+    /// Returns the number of bytes of memory used to store this file's contents,
+    /// which is less than its length if it has holes.
+    pub fn allocated_bytes(&self) -> usize {
+        self.extents.values().map(MappedPages::size_in_bytes).sum()
+    }
+
+    /// Returns an iterator over the extents that overlap the byte range `[start, end)`,
+    /// along with the offset of each extent.
+    fn extents_overlapping(&self, start: usize, end: usize) -> impl Iterator<Item = (usize, &MappedPages)> {
+        let first = self.extents.range(..=start).next_back().map_or(0, |(offset, _)| *offset);
+        self.extents.range(first..end)
+            .map(|(offset, mp)| (*offset, mp))
+            .filter(move |(offset, mp)| offset + mp.size_in_bytes() > start)
+    }
+
+    /// Returns an error if any extent that overlaps the byte range `[start, end)` isn't writable.
+    fn check_writable(&self, start: usize, end: usize) -> Result<(), IoError> {
+        if self.extents_overlapping(start, end).any(|(_, mp)| !mp.flags().is_writable()) {
+            return Err(IoError::from("MemFile: existing MappedPages were not writable"));
+        }
+        Ok(())
+    }
+
+    /// Overwrites the byte range `[start, end)` with zeros, wherever it isn't already a hole.
+    fn zero_range(&mut self, start: usize, end: usize) -> Result<(), IoError> {
+        let offsets: alloc::vec::Vec<usize> = self.extents_overlapping(start, end).map(|(offset, _)| offset).collect();
+        for offset in offsets {
+            let mp = self.extents.get_mut(&offset).ok_or("BUG: MemFile extent disappeared")?;
+            let zero_start = start.max(offset);
+            let zero_end = end.min(offset + mp.size_in_bytes());
+            mp.as_slice_mut::<u8>(zero_start - offset, zero_end - zero_start)?.fill(0);
+        }
+        Ok(())
+    }
+
+    /// Releases the memory of all whole pages within the byte range `[start, end)`,
+    /// which must be page-aligned, turning them into a hole.
+    fn release_pages(&mut self, start: usize, end: usize) -> Result<(), IoError> {
+        let allocated = self.allocated_bytes();
+        let offsets: alloc::vec::Vec<usize> = self.extents_overlapping(start, end).map(|(offset, _)| offset).collect();
+        for offset in offsets {
+            let mp = self.extents.remove(&offset).ok_or("BUG: MemFile extent disappeared")?;
+            let extent_end = offset + mp.size_in_bytes();
+            let extent_start_address = mp.start_address();
+            let page_at = |file_offset: usize| Page::containing_address(extent_start_address + (file_offset - offset));
+
+            // Keep the part of the extent after the hole, if any.
+            let mp = if end < extent_end {
+                let (before, after) = mp.split(page_at(end)).map_err(|_| "MemFile: couldn't split extent")?;
+                self.extents.insert(end, after);
+                before
+            } else {
+                mp
+            };
+            // Keep the part of the extent before the hole, if any, and drop the rest.
+            if offset < start {
+                let (before, _hole) = mp.split(page_at(start)).map_err(|_| "MemFile: couldn't split extent")?;
+                self.extents.insert(offset, before);
+            }
+        }
+        self.owner.release(allocated - self.allocated_bytes());
+        Ok(())
+    }
+}
+
+/// Rounds the given offset down to the nearest page boundary.
+fn page_align_down(offset: usize) -> usize {
+    offset - (offset % PAGE_SIZE)
+}
+
+/// Rounds the given offset up to the nearest page boundary.
+fn page_align_up(offset: usize) -> usize {
+    page_align_down(offset.saturating_add(PAGE_SIZE - 1))
 }
 
 impl ByteReader for MemFile {
@@ -63,71 +155,85 @@ impl ByteReader for MemFile {
         }
         // read from the offset until the end of the file, but not more than the buffer length
         let read_bytes = core::cmp::min(self.len - offset, buffer.len());
-        buffer[..read_bytes].copy_from_slice(
-            self.mp.as_slice(offset, read_bytes).map_err(IoError::from)?
-        ); 
+        let end = offset + read_bytes;
+        let buffer = &mut buffer[..read_bytes];
+        // holes read as zeros
+        buffer.fill(0);
+        for (extent_offset, mp) in self.extents_overlapping(offset, end) {
+            let copy_start = offset.max(extent_offset);
+            let copy_end = end.min(extent_offset + mp.size_in_bytes());
+            buffer[(copy_start - offset) .. (copy_end - offset)].copy_from_slice(
+                mp.as_slice(copy_start - extent_offset, copy_end - copy_start).map_err(IoError::from)?
+            );
+        }
         Ok(read_bytes) 
     }
 }
 
 impl ByteWriter for MemFile {
     fn write_at(&mut self, buffer: &[u8], offset: usize) -> Result<usize, IoError> {
-        // error out if the underlying mapped pages are already allocated and not writeable
-        if !self.mp.flags().is_writable() && self.mp.size_in_bytes() != 0 {
-            return Err(IoError::from("MemFile::write(): existing MappedPages were not writable"));
+        if buffer.is_empty() {
+            return Ok(0);
         }
-        
         let end = buffer.len() + offset;
-        // check to see if we can fit the write buffer into the existing mapped pages region
-        if end <= self.mp.size_in_bytes() {
-            let dest_slice = self.mp.as_slice_mut::<u8>(offset, buffer.len())?;
+        // error out if the underlying mapped pages are already allocated and not writeable
+        self.check_writable(offset, end)?;
+
+        // check to see if we can fit the write buffer into an existing extent
+        let containing_extent = self.extents.range_mut(..=offset).next_back()
+            .filter(|(extent_offset, mp)| end <= **extent_offset + mp.size_in_bytes());
+        if let Some((extent_offset, mp)) = containing_extent {
+            let dest_slice = mp.as_slice_mut::<u8>(offset - *extent_offset, buffer.len())?;
             // actually perform the write operation
             dest_slice.copy_from_slice(buffer);
-            // if the buffer written into the mapped pages exceeds the current size, we set the new size equal to 
-            // this value, otherwise, the size remains the same
-            if end > self.len { 
-                self.len = end; 
-            }
-            Ok(buffer.len()) // we wrote all of the requested bytes successfully
         } 
-        // if not, we need to reallocate a new mapped pages 
+        // if not, we need to allocate a new extent that covers the write and all extents it overlaps,
+        // leaving the rest of the file untouched, e.g., any holes before or after it.
         else {
-            // If the mapped pages are empty (this is the first allocation), we make them writable
-            let prev_flags = if self.mp.size_in_bytes() == 0 {
-                PteFlags::new().valid(true).writable(true).into()
-            } 
-            // Otherwise, use the existing mapped pages flags
-            else {
-                self.mp.flags()
-            };
-            
+            let overlapping: alloc::vec::Vec<usize> = self.extents_overlapping(page_align_down(offset), page_align_up(end))
+                .map(|(extent_offset, _)| extent_offset)
+                .collect();
+            let new_start = overlapping.first().map_or(page_align_down(offset), |first| (*first).min(page_align_down(offset)));
+            let new_end = overlapping.last()
+                .and_then(|last| self.extents.get(last).map(|mp| last + mp.size_in_bytes()))
+                .map_or(page_align_up(end), |last_end| last_end.max(page_align_up(end)));
+
             let kernel_mmi_ref = get_kernel_mmi_ref().ok_or("KERNEL_MMI was not yet initialized!")?;
-            let pages = allocate_pages_by_bytes(end).ok_or("could not allocate pages")?;
-            let mut new_mapped_pages = kernel_mmi_ref.lock().page_table.map_allocated_pages(pages, prev_flags)?;
-            
-            // first, we need to copy over the bytes from the previous mapped pages
-            {
-                // copy_limit copies bytes to min(the write offset, all the bytes of the existing mapped pages)
-                // The write does not overlap with existing content, so we copy all existing content
-                let copy_limit = if offset > self.len { 
-                    self.len
-                } else { // Otherwise, we only copy up to where the overlap begins
-                    offset
-                };
-                let existing_bytes = self.mp.as_slice(0, copy_limit)?;
-                let copy_slice = new_mapped_pages.as_slice_mut::<u8>(0, copy_limit)?;
-                copy_slice.copy_from_slice(existing_bytes);
-            } 
+            let pages = allocate_pages_by_bytes(new_end - new_start).ok_or("could not allocate pages")?;
+            let mut new_mapped_pages = kernel_mmi_ref.lock().page_table.map_allocated_pages(
+                pages,
+                PteFlags::new().valid(true).writable(true),
+            )?;
+            // charge the file's owner for the memory that the new extent adds beyond the extents it replaces
+            let replaced: usize = overlapping.iter()
+                .filter_map(|extent_offset| self.extents.get(extent_offset))
+                .map(MappedPages::size_in_bytes)
+                .sum();
+            self.owner.charge((new_end - new_start) - replaced)?;
+
+            // the new extent may include parts of holes, which must read as zeros
+            new_mapped_pages.as_slice_mut::<u8>(0, new_end - new_start)?.fill(0);
+
+            // first, we need to copy over the bytes from the overlapped extents
+            for extent_offset in overlapping {
+                let mp = self.extents.remove(&extent_offset).ok_or("BUG: MemFile extent disappeared")?;
+                let dest = new_mapped_pages.as_slice_mut::<u8>(extent_offset - new_start, mp.size_in_bytes())?;
+                dest.copy_from_slice(mp.as_slice(0, mp.size_in_bytes())?);
+            }
             
-            // second, we write the new content into the reallocated mapped pages
+            // second, we write the new content into the new extent
             {
-                let dest_slice = new_mapped_pages.as_slice_mut::<u8>(offset, buffer.len())?;
+                let dest_slice = new_mapped_pages.as_slice_mut::<u8>(offset - new_start, buffer.len())?;
                 dest_slice.copy_from_slice(buffer); // writes the desired contents into the correct area in the mapped page
             }
-            self.mp = new_mapped_pages;
-            self.len = end;
-            Ok(buffer.len())
+            self.extents.insert(new_start, new_mapped_pages);
+        }
+        // if the buffer written exceeds the current size, we set the new size equal to 
+        // this value, otherwise, the size remains the same
+        if end > self.len { 
+            self.len = end; 
         }
+        Ok(buffer.len())
     }
 
     fn flush(&mut self) -> Result<(), IoError> { Ok(()) }
@@ -141,8 +247,76 @@ impl KnownLength for MemFile {
 }
 
 impl File for MemFile {
+    /// Returns the single `MappedPages` that contains this file's contents,
+    /// which fails if the file is sparse, i.e., its contents aren't contiguous in memory.
     fn as_mapping(&self) -> Result<&MappedPages, &'static str> {
-        Ok(&self.mp)
+        let mut extents = self.extents.iter();
+        match (extents.next(), extents.next()) {
+            (None, _) if self.len == 0 => Ok(&EMPTY_MAPPING),
+            (Some((0, mp)), None) if mp.size_in_bytes() >= self.len => Ok(mp),
+            _ => Err("MemFile is sparse, so its contents aren't contiguously mapped"),
+        }
+    }
+
+    fn set_len(&mut self, len: usize) -> Result<(), IoError> {
+        if len < self.len {
+            self.check_writable(len, self.len)?;
+            // Release the pages beyond the new end, and zero the rest of the last page,
+            // such that the file reads as zeros if it is extended again.
+            let release_start = page_align_up(len);
+            if let Some(last_end) = self.extents.iter().next_back().map(|(offset, mp)| offset + mp.size_in_bytes()) {
+                if release_start < last_end {
+                    self.release_pages(release_start, last_end)?;
+                }
+            }
+            self.zero_range(len, release_start)?;
+        }
+        // Growing the file just creates a hole at its end.
+        self.len = len;
+        Ok(())
+    }
+
+    fn punch_hole(&mut self, offset: usize, len: usize) -> Result<(), IoError> {
+        let end = offset.saturating_add(len).min(self.len);
+        if offset >= end {
+            return Ok(());
+        }
+        self.check_writable(offset, end)?;
+        let release_start = page_align_up(offset);
+        let release_end = page_align_down(end);
+        if release_start < release_end {
+            self.release_pages(release_start, release_end)?;
+            self.zero_range(offset, release_start)?;
+            self.zero_range(release_end, end)
+        } else {
+            // The hole doesn't cover a whole page.
+            self.zero_range(offset, end)
+        }
+    }
+
+    fn seek_data(&self, offset: usize) -> Option<usize> {
+        if offset >= self.len {
+            return None;
+        }
+        self.extents_overlapping(offset, self.len)
+            .next()
+            .map(|(extent_offset, _)| extent_offset.max(offset))
+            .filter(|data| *data < self.len)
+    }
+
+    fn seek_hole(&self, offset: usize) -> Option<usize> {
+        if offset >= self.len {
+            return None;
+        }
+        // Skip over contiguous extents that cover the offset.
+        let mut pos = offset;
+        for (extent_offset, mp) in self.extents_overlapping(offset, self.len) {
+            if extent_offset > pos {
+                break;
+            }
+            pos = extent_offset + mp.size_in_bytes();
+        }
+        Some(pos.min(self.len))
     }
 }
 
@@ -158,4 +332,9 @@ impl FsNode for MemFile {
     fn set_parent_dir(&mut self, new_parent: WeakDirRef) {
         self.parent = new_parent;
     }
+
+    fn set_name(&mut self, name: String) -> Result<(), &'static str> {
+        self.name = name;
+        Ok(())
+    }
 }
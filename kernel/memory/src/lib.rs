@@ -20,7 +20,7 @@ mod paging;
 pub use self::paging::{
     PageTable, Mapper, Mutability, Mutable, Immutable,
     MappedPages, BorrowedMappedPages, BorrowedSliceMappedPages,
-    translate,
+    TlbFlushBatch, translate, is_private_address,
 };
 
 pub use memory_structs::*;
@@ -65,6 +65,7 @@ use alloc::{sync::Arc, vec::Vec};
 use frame_allocator::{PhysicalMemoryRegion, MemoryRegionType, FramesIteratorRequest};
 use no_drop::NoDrop;
 pub use kernel_config::memory::PAGE_SIZE;
+use kernel_config::memory::{PRIVATE_REGION_START, PRIVATE_REGION_END};
 
 /// The memory management info and address space of the kernel
 static KERNEL_MMI: Once<MmiRef> = Once::new();
@@ -229,6 +230,55 @@ pub fn create_identity_mapping<F: Into<PteFlagsArch>>(
 }
 
 
+/// Creates a new, separate address space that shares all kernel mappings with
+/// the address space of `current_mmi`, which must be the currently-active one.
+///
+/// The new address space can be given to a new task such that it runs with its own
+/// private mappings; see [`PageTable::new_address_space()`] for more details.
+pub fn create_address_space(current_mmi: &MmiRef) -> Result<MmiRef, &'static str> {
+    let page_table = PageTable::new_address_space(&mut current_mmi.lock().page_table)?;
+    Ok(Arc::new(IrqSafeMutex::new(MemoryManagementInfo {
+        page_table,
+        extra_mapped_pages: Vec::new(),
+    })))
+}
+
+/// Returns the range of pages in the region reserved for mappings
+/// that are private to a single address space.
+///
+/// Pages for a private mapping can be allocated from this range, e.g.,
+/// via [`allocate_pages_by_bytes_in_range()`].
+pub fn private_page_range() -> PageRange {
+    PageRange::new(
+        Page::containing_address(VirtualAddress::new_canonical(PRIVATE_REGION_START)),
+        Page::containing_address(VirtualAddress::new_canonical(PRIVATE_REGION_END)),
+    )
+}
+
+/// Creates a new mapping of at least `size_in_bytes` within the private region
+/// of the `target_mmi` address space, which is only visible to tasks in that address space.
+///
+/// `current_mmi` must be the currently-active address space, but may be the same as `target_mmi`.
+///
+/// # Locking / Deadlock
+/// This acquires the locks on both `current_mmi` and `target_mmi`, in that order.
+pub fn create_private_mapping<F: Into<PteFlagsArch>>(
+    current_mmi: &MmiRef,
+    target_mmi: &MmiRef,
+    size_in_bytes: usize,
+    flags: F,
+) -> Result<MappedPages, &'static str> {
+    let allocated_pages = allocate_pages_by_bytes_in_range(size_in_bytes, &private_page_range())?;
+    let mut current = current_mmi.lock();
+    if Arc::ptr_eq(current_mmi, target_mmi) {
+        current.page_table.map_allocated_pages(allocated_pages, flags)
+    } else {
+        let mut target = target_mmi.lock();
+        current.page_table.map_allocated_pages_in(&mut target.page_table, allocated_pages, flags)
+    }
+}
+
+
 static BROADCAST_TLB_SHOOTDOWN_FUNC: Once<fn(PageRange)> = Once::new();
 
 /// Set the function callback that will be invoked every time a TLB shootdown is necessary,
@@ -7,6 +7,7 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
+use alloc::vec::Vec;
 use core::{
     borrow::{Borrow, BorrowMut},
     cmp::Ordering,
@@ -19,15 +20,16 @@ use core::{
 };
 use log::{error, warn, debug, trace};
 use memory_structs::{PageSize, Page4K};
-use crate::{BROADCAST_TLB_SHOOTDOWN_FUNC, VirtualAddress, PhysicalAddress, Page, Frame, FrameRange, AllocatedPages, AllocatedFrames, UnmappedFrames}; 
+use crate::{BROADCAST_TLB_SHOOTDOWN_FUNC, VirtualAddress, PhysicalAddress, Page, PageRange, Frame, FrameRange, AllocatedPages, AllocatedFrames, UnmappedFrames}; 
 use crate::paging::{
     get_current_p4,
+    is_private_address,
     table::{P4, UPCOMING_P4, Table, Level4},
 };
 use pte_flags::PteFlagsArch;
 use spin::Once;
 use kernel_config::memory::PAGE_SIZE;
-use super::tlb_flush_virt_addr;
+use super::{tlb_flush_all, tlb_flush_virt_addr};
 use zerocopy::FromBytes;
 use page_table_entry::UnmapResult;
 use owned_borrowed_trait::{OwnedOrBorrowed, Owned, Borrowed};
@@ -188,6 +190,86 @@ impl Mapper {
             .or_else(huge_page)
     }
 
+    /// Returns the flags of the page table entry that maps the given 4K `Page`,
+    /// or `None` if that page is not mapped.
+    ///
+    /// Unlike [`Mapper::translate_page()`], this does not handle huge pages.
+    pub fn page_flags(&self, page: Page) -> Option<PteFlagsArch> {
+        self.p4().next_table(page.p4_index())
+            .and_then(|p3| p3.next_table(page.p3_index()))
+            .and_then(|p2| p2.next_table(page.p2_index()))
+            .map(|p1| &p1[page.p1_index()])
+            .filter(|pte| pte.pointed_frame().is_some())
+            .map(|pte| pte.flags())
+    }
+
+    /// Walks all four levels of this page table and invokes the given function
+    /// for every valid leaf mapping, including huge pages.
+    ///
+    /// The arguments to `func` are the starting virtual address of the mapping,
+    /// its size in bytes, and its effective flags.
+    /// The effective flags are those of the leaf entry, except that a mapping is only
+    /// considered writable or executable if the entries at *all* levels permit it.
+    ///
+    /// The P4 entries used for recursive page table mappings are skipped,
+    /// as they don't represent real memory mappings.
+    pub fn for_each_mapping<F>(&self, mut func: F)
+    where
+        F: FnMut(VirtualAddress, usize, PteFlagsArch),
+    {
+        use kernel_config::memory::{
+            ENTRIES_PER_PAGE_TABLE, PAGE_SHIFT,
+            P1_INDEX_SHIFT, P2_INDEX_SHIFT, P3_INDEX_SHIFT, P4_INDEX_SHIFT,
+            RECURSIVE_P4_INDEX, UPCOMING_PAGE_TABLE_RECURSIVE_P4_INDEX,
+        };
+
+        // Combines the permissions of a higher-level entry with those of a lower-level entry.
+        let combine = |upper: PteFlagsArch, lower: PteFlagsArch| {
+            lower
+                .writable(upper.is_writable() && lower.is_writable())
+                .executable(upper.is_executable() && lower.is_executable())
+        };
+        let vaddr = |p4: usize, p3: usize, p2: usize, p1: usize| VirtualAddress::new_canonical(
+            p4 << (PAGE_SHIFT + P4_INDEX_SHIFT)
+            | p3 << (PAGE_SHIFT + P3_INDEX_SHIFT)
+            | p2 << (PAGE_SHIFT + P2_INDEX_SHIFT)
+            | p1 << (PAGE_SHIFT + P1_INDEX_SHIFT)
+        );
+
+        let p4 = self.p4();
+        for i4 in 0 .. ENTRIES_PER_PAGE_TABLE {
+            if i4 == RECURSIVE_P4_INDEX || i4 == UPCOMING_PAGE_TABLE_RECURSIVE_P4_INDEX {
+                continue;
+            }
+            let f4 = p4[i4].flags();
+            let Some(p3) = p4.next_table(i4) else { continue };
+            for i3 in 0 .. ENTRIES_PER_PAGE_TABLE {
+                let f3 = combine(f4, p3[i3].flags());
+                if !f3.is_valid() { continue; }
+                let Some(p2) = p3.next_table(i3) else {
+                    // A valid entry without a next-level table is a huge 1GiB page.
+                    func(vaddr(i4, i3, 0, 0), 1 << (PAGE_SHIFT + P3_INDEX_SHIFT), f3);
+                    continue;
+                };
+                for i2 in 0 .. ENTRIES_PER_PAGE_TABLE {
+                    let f2 = combine(f3, p2[i2].flags());
+                    if !f2.is_valid() { continue; }
+                    let Some(p1) = p2.next_table(i2) else {
+                        // A valid entry without a next-level table is a huge 2MiB page.
+                        func(vaddr(i4, i3, i2, 0), 1 << (PAGE_SHIFT + P2_INDEX_SHIFT), f2);
+                        continue;
+                    };
+                    for i1 in 0 .. ENTRIES_PER_PAGE_TABLE {
+                        let f1 = combine(f2, p1[i1].flags());
+                        if f1.is_valid() {
+                            func(vaddr(i4, i3, i2, i1), PAGE_SIZE, f1);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
     /*
      * An unfinished implementation of a generically-sized translate routine that handles huge pages.
      *
@@ -685,6 +767,43 @@ impl MappedPages {
             return Ok(());
         }
 
+        self.set_flags(active_table_mapper, new_flags)?;
+        for page in self.pages.range().clone() {
+            tlb_flush_virt_addr(page.start_address());
+        }
+        
+        if let Some(func) = BROADCAST_TLB_SHOOTDOWN_FUNC.get() {
+            func(self.pages.range().clone());
+        }
+        Ok(())
+    }   
+
+    /// Changes the mapping flags of this `MappedPages`'s page table entries, like [`Self::remap()`],
+    /// but defers invalidating their stale TLB entries by adding them to the given `batch`.
+    ///
+    /// These pages must not be accessed until the `batch` has been flushed.
+    pub fn remap_batched<F: Into<PteFlagsArch>>(
+        &mut self,
+        active_table_mapper: &mut Mapper,
+        new_flags: F,
+        batch: &mut TlbFlushBatch,
+    ) -> Result<(), &'static str> {
+        if self.size_in_pages() == 0 { return Ok(()); }
+
+        let new_flags = new_flags.into()
+            .exclusive(self.flags.is_exclusive())
+            .valid(true);
+        if new_flags == self.flags {
+            return Ok(());
+        }
+
+        self.set_flags(active_table_mapper, new_flags)?;
+        batch.add(self.pages.range().clone());
+        Ok(())
+    }
+
+    /// Sets the flags of this `MappedPages`'s page table entries without flushing them from the TLB.
+    fn set_flags(&mut self, active_table_mapper: &mut Mapper, new_flags: PteFlagsArch) -> Result<(), &'static str> {
         for page in self.pages.range().clone() {
             let p1 = active_table_mapper.p4_mut()
                 .next_table_mut(page.p4_index())
@@ -693,17 +812,10 @@ impl MappedPages {
                 .ok_or("mapping code does not support huge pages")?;
             
             p1[page.p1_index()].set_flags(new_flags);
-
-            tlb_flush_virt_addr(page.start_address());
         }
-        
-        if let Some(func) = BROADCAST_TLB_SHOOTDOWN_FUNC.get() {
-            func(self.pages.range().clone());
-        }
-
         self.flags = new_flags;
         Ok(())
-    }   
+    }
     
     /// Consumes and unmaps this `MappedPages` object without auto-deallocating its `AllocatedPages` and `AllocatedFrames`,
     /// allowing the caller to continue using them directly, e.g., reusing them for a future mapping. 
@@ -747,7 +859,11 @@ impl MappedPages {
     fn unmap(&mut self, active_table_mapper: &mut Mapper) -> Result<Option<AllocatedFrames>, &'static str> {
         if self.size_in_pages() == 0 { return Ok(None); }
 
-        if active_table_mapper.target_p4 != self.page_table_p4 {
+        // Mappings outside of the private region are shared by all address spaces,
+        // so they can be unmapped from any of them.
+        let is_shared = !is_private_address(self.start_address())
+            && !is_private_address(self.pages.end().start_address());
+        if active_table_mapper.target_p4 != self.page_table_p4 && !is_shared {
             error!("BUG: MappedPages::unmap(): {:?}\n    current P4 {:?} must equal original P4 {:?}, \
                 cannot unmap MappedPages from a different page table than they were originally mapped to!",
                 self, get_current_p4(), self.page_table_p4
@@ -1124,6 +1240,74 @@ impl Drop for MappedPages {
 }
 
 
+/// The number of pending pages above which flushing a [`TlbFlushBatch`]
+/// flushes the entire TLB instead of each page individually.
+const TLB_FLUSH_ALL_THRESHOLD: usize = 64;
+
+/// A set of TLB invalidations that have been deferred such that they can be performed together.
+///
+/// [`MappedPages::remap()`] invalidates each remapped page immediately
+/// and broadcasts a TLB shootdown for every call.
+/// When remapping many `MappedPages` at once, [`MappedPages::remap_batched()`] instead
+/// records the remapped pages here, and [`TlbFlushBatch::flush()`] invalidates all of them at once,
+/// flushing the entire TLB if there are many pages.
+///
+/// Any pending invalidations are performed when the batch is dropped.
+#[derive(Debug, Default)]
+pub struct TlbFlushBatch {
+    ranges: Vec<PageRange>,
+    num_pages: usize,
+}
+
+impl TlbFlushBatch {
+    /// Creates a new, empty batch of TLB invalidations.
+    pub const fn new() -> TlbFlushBatch {
+        TlbFlushBatch { ranges: Vec::new(), num_pages: 0 }
+    }
+
+    /// Returns the number of pages that are pending invalidation.
+    pub fn num_pages(&self) -> usize {
+        self.num_pages
+    }
+
+    /// Returns `true` if there are no pages pending invalidation.
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    fn add(&mut self, pages: PageRange) {
+        self.num_pages += pages.size_in_pages();
+        self.ranges.push(pages);
+    }
+
+    /// Invalidates all pending pages in the TLB of this CPU and all other CPUs.
+    pub fn flush(&mut self) {
+        if self.ranges.is_empty() { return; }
+
+        if self.num_pages > TLB_FLUSH_ALL_THRESHOLD {
+            tlb_flush_all();
+        } else {
+            for page in self.ranges.iter().flat_map(|range| range.clone()) {
+                tlb_flush_virt_addr(page.start_address());
+            }
+        }
+        let broadcast = BROADCAST_TLB_SHOOTDOWN_FUNC.get();
+        for range in self.ranges.drain(..) {
+            if let Some(func) = broadcast {
+                func(range);
+            }
+        }
+        self.num_pages = 0;
+    }
+}
+
+impl Drop for TlbFlushBatch {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+
 /// A borrowed [`MappedPages`] object that derefs to `&T` and optionally also `&mut T`.
 ///
 /// ## Type parameters
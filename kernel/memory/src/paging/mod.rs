@@ -19,7 +19,7 @@ pub use self::{
     temporary_page::TemporaryPage,
     mapper::{
         Mapper, MappedPages, BorrowedMappedPages, BorrowedSliceMappedPages,
-        Mutability, Mutable, Immutable, translate,
+        Mutability, Mutable, Immutable, TlbFlushBatch, translate,
     },
 };
 
@@ -38,7 +38,11 @@ use super::{
 use pte_flags::PteFlagsArch;
 use no_drop::NoDrop;
 use boot_info::BootInformation;
-use kernel_config::memory::{RECURSIVE_P4_INDEX, PAGE_SIZE, UPCOMING_PAGE_TABLE_RECURSIVE_P4_INDEX};
+use kernel_config::memory::{
+    RECURSIVE_P4_INDEX, PAGE_SIZE, UPCOMING_PAGE_TABLE_RECURSIVE_P4_INDEX, ENTRIES_PER_PAGE_TABLE,
+    PRIVATE_REGION_P4_START_INDEX, PRIVATE_REGION_P4_END_INDEX, PRIVATE_REGION_START, PRIVATE_REGION_END,
+};
+use spin::Once;
 
 #[cfg(target_arch = "aarch64")]
 use memory_aarch64::set_as_active_page_table_root;
@@ -189,23 +193,93 @@ impl PageTable {
     /// After this function, the given `new_table` will be the currently-active `PageTable`.
     pub fn switch(&mut self, new_table: &PageTable) {
         // debug!("PageTable::switch() old table: {:?}, new table: {:?}", self, new_table);
+        new_table.activate();
+    }
 
+    /// Sets this `PageTable` as the currently-active page table on this CPU.
+    ///
+    /// This is used when switching between tasks in different address spaces.
+    /// Because all address spaces share the same kernel mappings
+    /// (see [`PageTable::new_address_space()`]), the kernel can continue running
+    /// after switching, but only this page table's private mappings will be visible.
+    pub fn activate(&self) {
         #[cfg(target_arch = "x86_64")]
         unsafe { 
             use x86_64::{PhysAddr, structures::paging::frame::PhysFrame, registers::control::{Cr3, Cr3Flags}};
             Cr3::write(
-                PhysFrame::containing_address(PhysAddr::new_truncate(new_table.p4_table.start_address().value() as u64)),
+                PhysFrame::containing_address(PhysAddr::new_truncate(self.p4_table.start_address().value() as u64)),
                 Cr3Flags::empty(),
             )
         };
 
         #[cfg(target_arch = "aarch64")] {
-            set_as_active_page_table_root(new_table.physical_address());
+            set_as_active_page_table_root(self.physical_address());
             // This is only required on aarch64, as setting CR3 on x86_64 flushes the TLB.
             tlb_flush_all();
         }
     }
 
+    /// Returns `true` if this `PageTable` is the currently-active page table on this CPU.
+    pub fn is_active(&self) -> bool {
+        get_current_p4() == *self.p4_table.start()
+    }
+
+    /// Creates a new `PageTable` for a separate address space.
+    ///
+    /// The new page table shares all kernel mappings with the `current_page_table`,
+    /// i.e., every P4 entry except for those in the private region
+    /// (see [`PRIVATE_REGION_START`]) and those used for recursive mappings.
+    /// Its private region starts out empty; use [`PageTable::map_allocated_pages_in()`]
+    /// to create mappings that are only visible in the new address space.
+    ///
+    /// The `current_page_table` must be the currently-active page table.
+    ///
+    /// The first time this is invoked, all shared P4 entries in the current page table
+    /// are populated with (empty) P3 tables, such that any future kernel mappings
+    /// will be made in page table frames that are shared by all address spaces.
+    pub fn new_address_space(current_page_table: &mut PageTable) -> Result<PageTable, &'static str> {
+        if !current_page_table.is_active() {
+            return Err("PageTable::new_address_space(): the current page table must be active");
+        }
+
+        SHARED_P4_ENTRIES_POPULATED.call_once(|| {
+            let higher_level_flags = PteFlagsArch::new().adjust_for_higher_level_pte();
+            for index in shared_p4_indices() {
+                current_page_table.p4_mut().next_table_create(index, higher_level_flags);
+            }
+        });
+
+        let new_p4_frame = frame_allocator::allocate_frames(1)
+            .ok_or("PageTable::new_address_space(): couldn't allocate frame for new P4 table")?;
+        let mut new_table = PageTable::new_table(current_page_table, new_p4_frame, None)?;
+        current_page_table.with(&mut new_table, |new_mapper, current_mapper| {
+            for index in shared_p4_indices() {
+                new_mapper.p4_mut()[index].alias_from(&current_mapper.p4()[index]);
+            }
+            Ok(())
+        })?;
+        Ok(new_table)
+    }
+
+    /// Maps the given `pages` to newly-allocated frames in the `target` page table,
+    /// which may be a different address space than this `PageTable`.
+    ///
+    /// This `PageTable` (`self`) must be the currently-active page table.
+    ///
+    /// The returned `MappedPages` belong to the `target` address space;
+    /// if they are in its private region, they must only be accessed and dropped
+    /// while the `target` page table is active.
+    pub fn map_allocated_pages_in<FL: Into<PteFlagsArch>>(
+        &mut self,
+        target: &mut PageTable,
+        pages: AllocatedPages,
+        flags: FL,
+    ) -> Result<MappedPages, &'static str> {
+        if self.p4_table.start() == target.p4_table.start() {
+            return self.map_allocated_pages(pages, flags);
+        }
+        self.with(target, |target_mapper, _| target_mapper.map_allocated_pages(pages, flags))
+    }
 
     /// Returns the physical address of this page table's top-level p4 frame
     pub fn physical_address(&self) -> PhysicalAddress {
@@ -214,6 +288,26 @@ impl PageTable {
 }
 
 
+/// Whether all shared P4 entries have been populated; see [`PageTable::new_address_space()`].
+static SHARED_P4_ENTRIES_POPULATED: Once<()> = Once::new();
+
+/// Returns an iterator over the indices of the P4 entries that are shared by all address spaces.
+fn shared_p4_indices() -> impl Iterator<Item = usize> {
+    (0 .. ENTRIES_PER_PAGE_TABLE).filter(|&index|
+        !(PRIVATE_REGION_P4_START_INDEX ..= PRIVATE_REGION_P4_END_INDEX).contains(&index)
+        && index != RECURSIVE_P4_INDEX
+        && index != UPCOMING_PAGE_TABLE_RECURSIVE_P4_INDEX
+    )
+}
+
+/// Returns `true` if the given virtual address is within the region reserved for
+/// mappings that are private to a single address space.
+///
+/// All other addresses are mapped identically in every address space.
+pub fn is_private_address(vaddr: VirtualAddress) -> bool {
+    (PRIVATE_REGION_START ..= PRIVATE_REGION_END).contains(&vaddr.value())
+}
+
 /// Returns the current top-level (P4) root page table frame.
 pub fn get_current_p4() -> Frame<Page4K> {
     Frame::containing_address(get_p4())
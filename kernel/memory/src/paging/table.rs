@@ -145,6 +145,12 @@ impl<L: HierarchicalLevel> Table<L> {
             );
             self.next_table_mut(index).unwrap().zero();
             core::mem::forget(af); // we currently forget frames allocated as page table frames since we don't yet have a way to track them.
+        } else if flags.contains(PteFlagsArch::_USER_ACCESSIBLE) {
+            // A user-accessible mapping is only accessible if all higher-level entries are too.
+            let existing_flags = self[index].flags();
+            if !existing_flags.contains(PteFlagsArch::_USER_ACCESSIBLE) {
+                self[index].set_flags(existing_flags | PteFlagsArch::_USER_ACCESSIBLE);
+            }
         }
         self.next_table_mut(index).unwrap()
     }
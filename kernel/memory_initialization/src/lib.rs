@@ -5,13 +5,14 @@ extern crate alloc;
 
 use log::{error, debug};
 use memory::{MmiRef, MappedPages, VirtualAddress, InitialMemoryMappings, EarlyIdentityMappedPages};
-use kernel_config::memory::{KERNEL_HEAP_START, KERNEL_HEAP_INITIAL_SIZE};
+use kernel_config::memory::{KERNEL_HEAP_START, KERNEL_HEAP_INITIAL_SIZE, KERNEL_HEAP_MAX_SIZE};
 use boot_info::{BootInformation, Module};
 use alloc::{
     string::String, 
     vec::Vec,
 };
 use heap::HEAP_FLAGS;
+use memory_regions::RegionKind;
 use stack::Stack;
 use no_drop::NoDrop;
 use bootloader_modules::BootloaderModule;
@@ -115,6 +116,13 @@ pub fn init_memory_management(
         heap_mapped_pages,
     );
 
+    // Reserve the heap's entire virtual address range, into which it can grow later.
+    memory_regions::reserve(
+        "kernel heap",
+        RegionKind::Heap,
+        VirtualAddress::new_canonical(heap_start)..VirtualAddress::new_canonical(heap_start + KERNEL_HEAP_MAX_SIZE),
+    )?;
+
     // Because bootloader modules may overlap with the actual boot information, 
     // we need to preserve those records here in a separate list,
     // such that we can unmap the boot info pages & frames here but still access that info in the future.
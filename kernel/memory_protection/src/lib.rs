@@ -0,0 +1,221 @@
+//! Hardware-enforced memory protection features and an audit of kernel mappings.
+//!
+//! [`init()`] registers [`cpu_features`] hooks that enable the following on each CPU:
+//! * SMEP (Supervisor Mode Execution Prevention): the kernel cannot execute user pages.
+//! * SMAP (Supervisor Mode Access Prevention): the kernel cannot access user pages
+//!   except within an explicit window opened by [`allow_user_access()`].
+//! * NX (No-Execute): pages mapped without the executable flag cannot be executed.
+//!
+//! The [`audit()`] function walks a page table looking for mappings that are both
+//! writable and executable (W+X), which violate the W^X policy that Theseus
+//! otherwise upholds when loading crates, and reports the crate that owns each one.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::{string::String, vec::Vec};
+use core::{fmt, ops::Range, sync::atomic::{AtomicBool, Ordering}};
+use cpu_features::Feature;
+use memory::{PageTable, VirtualAddress};
+
+/// Whether SMAP has been enabled, which determines whether user access windows are needed.
+static SMAP_ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Registers the hooks that enable SMEP, SMAP, and NX on each CPU that supports them.
+///
+/// This must be invoked before [`cpu_features::run_hooks()`] is invoked on the BSP.
+pub fn init() {
+    cpu_features::register_hook("enforce NX", Feature::Nx, arch::enable_nx);
+    cpu_features::register_hook("enable SMEP", Feature::Smep, arch::enable_smep);
+    cpu_features::register_hook("enable SMAP", Feature::Smap, || {
+        arch::enable_smap()?;
+        SMAP_ENABLED.store(true, Ordering::Release);
+        Ok(())
+    });
+}
+
+/// Returns whether SMAP is enabled, i.e., whether the kernel must
+/// use [`allow_user_access()`] before accessing user memory.
+pub fn is_smap_enabled() -> bool {
+    SMAP_ENABLED.load(Ordering::Acquire)
+}
+
+/// Opens a window during which the kernel may access user-accessible memory,
+/// which lasts until the returned guard is dropped.
+///
+/// When SMAP is enabled, this sets the `RFLAGS.AC` flag (via `stac`),
+/// and dropping the guard clears it again (via `clac`).
+/// These windows should be as short as possible, e.g., a single copy to or from user memory.
+///
+/// Because `RFLAGS.AC` is saved and restored across interrupts and context switches,
+/// opening a window only affects the current execution context.
+pub fn allow_user_access() -> UserAccessGuard {
+    let opened = is_smap_enabled() && !arch::user_access_allowed();
+    if opened {
+        arch::stac();
+    }
+    UserAccessGuard { opened }
+}
+
+/// A guard that allows the kernel to access user memory; see [`allow_user_access()`].
+///
+/// Nested guards are permitted; only the outermost guard closes the window.
+pub struct UserAccessGuard {
+    opened: bool,
+}
+
+impl Drop for UserAccessGuard {
+    fn drop(&mut self) {
+        if self.opened {
+            arch::clac();
+        }
+    }
+}
+
+/// Runs the given closure within a user access window; see [`allow_user_access()`].
+pub fn with_user_access<R>(func: impl FnOnce() -> R) -> R {
+    let _guard = allow_user_access();
+    func()
+}
+
+
+/// A contiguous range of virtual memory that is mapped as both writable and executable.
+#[derive(Debug)]
+pub struct WxViolation {
+    /// The virtual address range of the offending mapping(s).
+    pub range: Range<VirtualAddress>,
+    /// The name of the crate that owns this mapping, if one could be found.
+    pub owner: Option<String>,
+    /// The subsystem or memory region that this mapping is in.
+    pub region: &'static str,
+}
+
+impl fmt::Display for WxViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "W+X mapping {:#X} - {:#X} ({} bytes) in {}, owned by {}",
+            self.range.start, self.range.end, self.range.end.value() - self.range.start.value(),
+            self.region, self.owner.as_deref().unwrap_or("<unknown>"),
+        )
+    }
+}
+
+/// Scans all mappings in the given `page_table` for those that are both writable and executable.
+///
+/// Adjacent violating pages are coalesced into a single [`WxViolation`].
+/// For each one, this attempts to find the crate that owns it by searching
+/// the initial kernel namespace and its recursive namespaces.
+///
+/// # Locking / Deadlock
+/// This may obtain the lock on every crate and section in the kernel namespace.
+pub fn audit(page_table: &PageTable) -> Vec<WxViolation> {
+    let mut ranges: Vec<Range<VirtualAddress>> = Vec::new();
+    page_table.for_each_mapping(|start, size, flags| {
+        if !(flags.is_writable() && flags.is_executable()) {
+            return;
+        }
+        let end = start + size;
+        match ranges.last_mut() {
+            Some(last) if last.end == start => last.end = end,
+            _ => ranges.push(start .. end),
+        }
+    });
+
+    let namespace = mod_mgmt::get_initial_kernel_namespace();
+    ranges.into_iter().map(|range| {
+        let owner = namespace
+            .and_then(|ns| ns.get_crate_containing_address(range.start, true))
+            .map(|krate| String::from(krate.lock_as_ref().crate_name.as_str()));
+        WxViolation { region: region_of(range.start), owner, range }
+    }).collect()
+}
+
+/// Audits the currently-active page table and logs a warning for each W+X violation.
+///
+/// Returns the number of violations found.
+pub fn audit_and_report() -> Result<usize, &'static str> {
+    let kernel_mmi_ref = memory::get_kernel_mmi_ref().ok_or("kernel MMI was not yet initialized")?;
+    let violations = audit(&kernel_mmi_ref.lock().page_table);
+    for violation in &violations {
+        log::warn!("{}", violation);
+    }
+    if violations.is_empty() {
+        log::info!("W+X audit: no writable and executable mappings found");
+    } else {
+        log::warn!("W+X audit: found {} writable and executable mappings", violations.len());
+    }
+    Ok(violations.len())
+}
+
+/// Returns a description of the fixed memory region that contains the given address.
+fn region_of(vaddr: VirtualAddress) -> &'static str {
+    use kernel_config::memory::{
+        KERNEL_HEAP_START, KERNEL_HEAP_MAX_SIZE, KERNEL_OFFSET, KERNEL_TEXT_START,
+    };
+    let addr = vaddr.value();
+    if (KERNEL_HEAP_START .. KERNEL_HEAP_START + KERNEL_HEAP_MAX_SIZE).contains(&addr) {
+        "kernel heap"
+    } else if addr >= KERNEL_OFFSET {
+        "base kernel image"
+    } else if addr >= KERNEL_TEXT_START {
+        "loaded crate region"
+    } else if addr < (1 << 47) {
+        "lower half (identity mapped or user)"
+    } else {
+        "other kernel region"
+    }
+}
+
+
+#[cfg(target_arch = "x86_64")]
+mod arch {
+    use core::arch::asm;
+    use x86_64::registers::{
+        control::{Cr4, Cr4Flags},
+        model_specific::{Efer, EferFlags},
+        rflags::{self, RFlags},
+    };
+
+    pub(crate) fn enable_nx() -> Result<(), &'static str> {
+        // The boot code already sets this, but we ensure it is set on every CPU.
+        // SAFE: NX support was verified via CPUID, and all mappings set the NX bit intentionally.
+        unsafe { Efer::update(|f| f.insert(EferFlags::NO_EXECUTE_ENABLE)) };
+        Ok(())
+    }
+
+    pub(crate) fn enable_smep() -> Result<(), &'static str> {
+        // SAFE: the kernel never executes code from user-accessible pages.
+        unsafe { Cr4::update(|f| f.insert(Cr4Flags::SUPERVISOR_MODE_EXECUTION_PROTECTION)) };
+        Ok(())
+    }
+
+    pub(crate) fn enable_smap() -> Result<(), &'static str> {
+        // SAFE: the kernel only accesses user-accessible pages within a user access window.
+        unsafe { Cr4::update(|f| f.insert(Cr4Flags::SUPERVISOR_MODE_ACCESS_PREVENTION)) };
+        Ok(())
+    }
+
+    pub(crate) fn user_access_allowed() -> bool {
+        rflags::read().contains(RFlags::ALIGNMENT_CHECK)
+    }
+
+    pub(crate) fn stac() {
+        // SAFE: this only sets the AC flag, and is only invoked when SMAP is supported.
+        unsafe { asm!("stac", options(nomem, nostack)) };
+    }
+
+    pub(crate) fn clac() {
+        // SAFE: this only clears the AC flag, and is only invoked when SMAP is supported.
+        unsafe { asm!("clac", options(nomem, nostack)) };
+    }
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+mod arch {
+    pub(crate) fn enable_nx() -> Result<(), &'static str> { Ok(()) }
+    pub(crate) fn enable_smep() -> Result<(), &'static str> { Ok(()) }
+    pub(crate) fn enable_smap() -> Result<(), &'static str> { Ok(()) }
+    pub(crate) fn user_access_allowed() -> bool { true }
+    pub(crate) fn stac() { }
+    pub(crate) fn clac() { }
+}
@@ -0,0 +1,115 @@
+//! A registry of the kernel's reserved virtual memory regions.
+//!
+//! Subsystems that set aside a fixed region of the kernel's address space,
+//! such as the nano_core's static data, the kernel heap, per-CPU areas, or MMIO mappings,
+//! reserve that region here under a descriptive name.
+//! Reserving a region that overlaps an existing one fails and reports both regions,
+//! which catches layout bugs as soon as the offending region is set up
+//! rather than when its memory is eventually corrupted.
+//!
+//! The registry can also identify which region an arbitrary address belongs to,
+//! e.g., to explain where a stray pointer landed when diagnosing a fault.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::{borrow::Cow, vec::Vec};
+use core::{fmt, ops::Range};
+use log::error;
+use memory_structs::VirtualAddress;
+use spin::Mutex;
+
+/// All reserved regions, sorted by their start address.
+static REGIONS: Mutex<Vec<Region>> = Mutex::new(Vec::new());
+
+/// The kinds of memory that a reserved region can hold.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RegionKind {
+    /// Static data of the nano_core, i.e., its `.data` or `.bss` sections.
+    StaticData,
+    /// The kernel heap, from which heap arenas are carved out.
+    Heap,
+    /// An area that holds per-CPU data.
+    CpuLocal,
+    /// Memory-mapped I/O registers of a device.
+    Mmio,
+}
+
+/// A reserved region of virtual memory.
+#[derive(Clone, Debug)]
+pub struct Region {
+    /// A description of the region's contents, e.g., "nano_core .bss".
+    pub name: Cow<'static, str>,
+    pub kind: RegionKind,
+    /// The region's virtual addresses; the end is exclusive.
+    pub range: Range<VirtualAddress>,
+}
+
+impl Region {
+    fn overlaps(&self, range: &Range<VirtualAddress>) -> bool {
+        self.range.start < range.end && range.start < self.range.end
+    }
+}
+
+impl fmt::Display for Region {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({:?}) [{:#X}, {:#X})", self.name, self.kind, self.range.start, self.range.end)
+    }
+}
+
+/// Reserves the given range of virtual memory as a region with the given name and kind.
+///
+/// Returns an error if the range is empty or overlaps any already-reserved region,
+/// in which case the overlapping region is also logged.
+pub fn reserve(
+    name: impl Into<Cow<'static, str>>,
+    kind: RegionKind,
+    range: Range<VirtualAddress>,
+) -> Result<(), &'static str> {
+    let region = Region { name: name.into(), kind, range };
+    if region.range.start >= region.range.end {
+        error!("memory_regions: cannot reserve empty region {}", region);
+        return Err("memory_regions: cannot reserve an empty region");
+    }
+
+    let mut regions = REGIONS.lock();
+    let index = regions.partition_point(|r| r.range.start < region.range.start);
+    // Since the regions don't overlap each other, only the regions adjacent to the new one can overlap it.
+    let neighbors = index.saturating_sub(1)..(index + 1).min(regions.len());
+    if let Some(existing) = regions[neighbors].iter().find(|r| r.overlaps(&region.range)) {
+        error!("memory_regions: region {} overlaps already-reserved region {}", region, existing);
+        return Err("memory_regions: region overlaps an already-reserved region");
+    }
+    regions.insert(index, region);
+    Ok(())
+}
+
+/// Releases the reserved region that starts at the given address, returning it.
+pub fn release(start: VirtualAddress) -> Option<Region> {
+    let mut regions = REGIONS.lock();
+    let index = regions.binary_search_by_key(&start, |r| r.range.start).ok()?;
+    Some(regions.remove(index))
+}
+
+/// Returns the reserved region that contains the given address, if any.
+pub fn region_containing(vaddr: VirtualAddress) -> Option<Region> {
+    find(&REGIONS.lock(), vaddr).cloned()
+}
+
+/// Like [`region_containing()`], but returns `None` instead of waiting
+/// if the registry is currently locked, e.g., when invoked from an exception handler
+/// that may have interrupted a task holding the lock.
+pub fn try_region_containing(vaddr: VirtualAddress) -> Option<Region> {
+    find(&REGIONS.try_lock()?, vaddr).cloned()
+}
+
+/// Returns a copy of all reserved regions, sorted by their start address.
+pub fn regions() -> Vec<Region> {
+    REGIONS.lock().clone()
+}
+
+fn find(regions: &[Region], vaddr: VirtualAddress) -> Option<&Region> {
+    let index = regions.partition_point(|r| r.range.start <= vaddr);
+    regions[..index].last().filter(|r| vaddr < r.range.end)
+}
@@ -122,6 +122,7 @@ where
         // because they contain read-only initializer data "images" for each TLS area.
         // In fact, .tbss can be completedly ignored because it represents a read-only data image of all zeroes,
         // so there's no point in keeping it around.
+        // Read-only data with relocations (.data.rel.ro) is folded into .data by the linker script.
         //
         // Those are the only sections we care about; we ignore subsequent `.debug_*` sections (and .got).
         let static_str_name = match section.name() {
@@ -0,0 +1,517 @@
+//! A registry of metrics that subsystems use to expose their behavior over time,
+//! which can be exported in the [Prometheus text format].
+//!
+//! Metrics are usually declared as statics and registered once, for example:
+//! ```ignore
+//! static PACKETS: Counter = Counter::new("theseus_packets_total", "The number of packets received.");
+//!
+//! metrics::register(&PACKETS)?;
+//! PACKETS.inc();
+//! ```
+//!
+//! [`Counter`]s and [`Histogram`]s are split into shards that are each updated by a subset of CPUs,
+//! such that updates on different CPUs rarely contend for the same cache line;
+//! the shards are aggregated whenever the metric is read.
+//! Values that are easier to compute when they're exported, e.g., the amount of free memory,
+//! can be exposed with an [`FnMetric`].
+//!
+//! Metrics can also be exported as a [`snapshot()`], which can be serialized into any format.
+//!
+//! [Prometheus text format]: https://prometheus.io/docs/instrumenting/exposition_formats/
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::{
+    collections::BTreeMap,
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{
+    fmt::{self, Write},
+    ptr,
+    sync::atomic::{AtomicI64, AtomicU64, Ordering},
+};
+use serialize::Serialize;
+use spin::Mutex;
+
+/// The number of shards that each counter and histogram is split into.
+pub const NUM_SHARDS: usize = 16;
+
+/// The maximum number of buckets in a [`Histogram`], excluding the implicit `+Inf` bucket.
+pub const MAX_BUCKETS: usize = 16;
+
+/// The set of all registered metrics, in the order they were registered.
+static REGISTRY: Mutex<Vec<&'static dyn Metric>> = Mutex::new(Vec::new());
+
+/// The type of a metric, which determines how its samples are interpreted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum MetricKind {
+    /// A value that only ever increases, e.g., the number of packets received.
+    #[serialize(rename = "counter")]
+    Counter,
+    /// A value that can increase and decrease, e.g., the number of running tasks.
+    #[serialize(rename = "gauge")]
+    Gauge,
+    /// A distribution of observed values, e.g., the latencies of requests.
+    #[serialize(rename = "histogram")]
+    Histogram,
+}
+
+impl MetricKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            MetricKind::Counter => "counter",
+            MetricKind::Gauge => "gauge",
+            MetricKind::Histogram => "histogram",
+        }
+    }
+}
+
+/// A metric that can be registered and exported.
+pub trait Metric: Sync {
+    /// The name of the metric, e.g., `theseus_packets_total`.
+    fn name(&self) -> &str;
+    /// A description of what the metric measures.
+    fn help(&self) -> &str;
+    fn kind(&self) -> MetricKind;
+    /// Writes the current samples of the metric.
+    fn collect(&self, samples: &mut Samples<'_>) -> fmt::Result;
+}
+
+/// The destination that a [`Metric`] writes its samples to.
+pub struct Samples<'w> {
+    name: &'w str,
+    sink: Sink<'w>,
+}
+
+/// Where [`Samples`] are written to.
+enum Sink<'w> {
+    /// Written as lines of the Prometheus text format.
+    Text(&'w mut dyn Write),
+    /// Collected into a [`MetricSnapshot`].
+    Records(&'w mut Vec<Sample>),
+}
+
+impl Samples<'_> {
+    /// Writes a sample of the metric with the given `labels` and `value`.
+    ///
+    /// The name of the sample is the metric's name followed by `suffix`,
+    /// which is usually empty but is, e.g., `_bucket` for the buckets of histograms.
+    pub fn write(
+        &mut self,
+        suffix: &str,
+        labels: &[(&str, &dyn fmt::Display)],
+        value: impl fmt::Display,
+    ) -> fmt::Result {
+        let writer = match &mut self.sink {
+            Sink::Text(writer) => writer,
+            Sink::Records(records) => {
+                records.push(Sample {
+                    name: format!("{}{}", self.name, suffix),
+                    labels: labels
+                        .iter()
+                        .map(|(name, value)| (String::from(*name), value.to_string()))
+                        .collect(),
+                    value: value.to_string().parse().unwrap_or(f64::NAN),
+                });
+                return Ok(());
+            }
+        };
+        write!(writer, "{}{}", self.name, suffix)?;
+        if !labels.is_empty() {
+            writer.write_char('{')?;
+            for (i, (name, value)) in labels.iter().enumerate() {
+                if i > 0 {
+                    writer.write_char(',')?;
+                }
+                write!(writer, "{name}=\"")?;
+                write!(Escaper(&mut **writer), "{value}")?;
+                writer.write_char('"')?;
+            }
+            writer.write_char('}')?;
+        }
+        writeln!(writer, " {value}")
+    }
+}
+
+/// A writer that escapes backslashes, double quotes, and newlines, as required in label values.
+struct Escaper<'w>(&'w mut dyn Write);
+
+impl Write for Escaper<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            match c {
+                '\\' => self.0.write_str("\\\\")?,
+                '"' => self.0.write_str("\\\"")?,
+                '\n' => self.0.write_str("\\n")?,
+                c => self.0.write_char(c)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A value aligned to its own cache line.
+#[repr(align(64))]
+struct Shard<T>(T);
+
+/// Returns the index of the shard that the current CPU updates.
+fn shard_index() -> usize {
+    cpu::current_cpu().value() as usize % NUM_SHARDS
+}
+
+/// A value that only ever increases.
+pub struct Counter {
+    name: &'static str,
+    help: &'static str,
+    shards: [Shard<AtomicU64>; NUM_SHARDS],
+}
+
+impl Counter {
+    pub const fn new(name: &'static str, help: &'static str) -> Self {
+        #[allow(clippy::declare_interior_mutable_const)]
+        const ZERO: Shard<AtomicU64> = Shard(AtomicU64::new(0));
+        Self {
+            name,
+            help,
+            shards: [ZERO; NUM_SHARDS],
+        }
+    }
+
+    pub fn inc(&self) {
+        self.add(1);
+    }
+
+    pub fn add(&self, value: u64) {
+        self.shards[shard_index()].0.fetch_add(value, Ordering::Relaxed);
+    }
+
+    /// Returns the sum of the counter's shards.
+    pub fn get(&self) -> u64 {
+        self.shards.iter().map(|s| s.0.load(Ordering::Relaxed)).sum()
+    }
+}
+
+impl Metric for Counter {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn help(&self) -> &str {
+        self.help
+    }
+
+    fn kind(&self) -> MetricKind {
+        MetricKind::Counter
+    }
+
+    fn collect(&self, samples: &mut Samples<'_>) -> fmt::Result {
+        samples.write("", &[], self.get())
+    }
+}
+
+/// A value that can be set, increased, and decreased.
+pub struct Gauge {
+    name: &'static str,
+    help: &'static str,
+    value: AtomicI64,
+}
+
+impl Gauge {
+    pub const fn new(name: &'static str, help: &'static str) -> Self {
+        Self {
+            name,
+            help,
+            value: AtomicI64::new(0),
+        }
+    }
+
+    pub fn set(&self, value: i64) {
+        self.value.store(value, Ordering::Relaxed);
+    }
+
+    pub fn add(&self, value: i64) {
+        self.value.fetch_add(value, Ordering::Relaxed);
+    }
+
+    pub fn sub(&self, value: i64) {
+        self.value.fetch_sub(value, Ordering::Relaxed);
+    }
+
+    pub fn inc(&self) {
+        self.add(1);
+    }
+
+    pub fn dec(&self) {
+        self.sub(1);
+    }
+
+    pub fn get(&self) -> i64 {
+        self.value.load(Ordering::Relaxed)
+    }
+}
+
+impl Metric for Gauge {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn help(&self) -> &str {
+        self.help
+    }
+
+    fn kind(&self) -> MetricKind {
+        MetricKind::Gauge
+    }
+
+    fn collect(&self, samples: &mut Samples<'_>) -> fmt::Result {
+        samples.write("", &[], self.get())
+    }
+}
+
+struct HistogramShard {
+    buckets: [AtomicU64; MAX_BUCKETS],
+    sum: AtomicU64,
+    count: AtomicU64,
+}
+
+/// A distribution of observed values, counted in buckets with fixed upper bounds.
+pub struct Histogram {
+    name: &'static str,
+    help: &'static str,
+    bounds: &'static [u64],
+    shards: [Shard<HistogramShard>; NUM_SHARDS],
+}
+
+impl Histogram {
+    /// Creates a histogram whose buckets have the given inclusive upper `bounds`.
+    ///
+    /// The `bounds` must be in increasing order and have at most [`MAX_BUCKETS`] elements.
+    /// Values larger than the last bound are only counted in the implicit `+Inf` bucket.
+    pub const fn new(name: &'static str, help: &'static str, bounds: &'static [u64]) -> Self {
+        assert!(bounds.len() <= MAX_BUCKETS, "a histogram has too many buckets");
+        let mut i = 1;
+        while i < bounds.len() {
+            assert!(bounds[i - 1] < bounds[i], "histogram bounds must be increasing");
+            i += 1;
+        }
+
+        #[allow(clippy::declare_interior_mutable_const)]
+        const ZERO: AtomicU64 = AtomicU64::new(0);
+        #[allow(clippy::declare_interior_mutable_const)]
+        const EMPTY: Shard<HistogramShard> = Shard(HistogramShard {
+            buckets: [ZERO; MAX_BUCKETS],
+            sum: ZERO,
+            count: ZERO,
+        });
+        Self {
+            name,
+            help,
+            bounds,
+            shards: [EMPTY; NUM_SHARDS],
+        }
+    }
+
+    /// Records an observed `value`.
+    pub fn observe(&self, value: u64) {
+        let shard = &self.shards[shard_index()].0;
+        if let Some(i) = self.bounds.iter().position(|&bound| value <= bound) {
+            shard.buckets[i].fetch_add(1, Ordering::Relaxed);
+        }
+        shard.sum.fetch_add(value, Ordering::Relaxed);
+        shard.count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Returns the number of observed values.
+    pub fn count(&self) -> u64 {
+        self.shards.iter().map(|s| s.0.count.load(Ordering::Relaxed)).sum()
+    }
+
+    /// Returns the sum of all observed values.
+    pub fn sum(&self) -> u64 {
+        self.shards.iter().map(|s| s.0.sum.load(Ordering::Relaxed)).sum()
+    }
+}
+
+impl Metric for Histogram {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn help(&self) -> &str {
+        self.help
+    }
+
+    fn kind(&self) -> MetricKind {
+        MetricKind::Histogram
+    }
+
+    fn collect(&self, samples: &mut Samples<'_>) -> fmt::Result {
+        // Prometheus buckets are cumulative, i.e., each one includes all smaller buckets.
+        let mut cumulative = 0;
+        for (i, bound) in self.bounds.iter().enumerate() {
+            cumulative += self
+                .shards
+                .iter()
+                .map(|s| s.0.buckets[i].load(Ordering::Relaxed))
+                .sum::<u64>();
+            samples.write("_bucket", &[("le", bound as &dyn fmt::Display)], cumulative)?;
+        }
+        let count = self.count();
+        samples.write("_bucket", &[("le", &"+Inf" as &dyn fmt::Display)], count)?;
+        samples.write("_sum", &[], self.sum())?;
+        samples.write("_count", &[], count)
+    }
+}
+
+/// A metric whose samples are computed by a function whenever it's exported.
+pub struct FnMetric {
+    name: &'static str,
+    help: &'static str,
+    kind: MetricKind,
+    collect: fn(&mut Samples<'_>) -> fmt::Result,
+}
+
+impl FnMetric {
+    pub const fn new(
+        name: &'static str,
+        help: &'static str,
+        kind: MetricKind,
+        collect: fn(&mut Samples<'_>) -> fmt::Result,
+    ) -> Self {
+        Self {
+            name,
+            help,
+            kind,
+            collect,
+        }
+    }
+}
+
+impl Metric for FnMetric {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn help(&self) -> &str {
+        self.help
+    }
+
+    fn kind(&self) -> MetricKind {
+        self.kind
+    }
+
+    fn collect(&self, samples: &mut Samples<'_>) -> fmt::Result {
+        (self.collect)(samples)
+    }
+}
+
+/// Returns whether `name` is a valid Prometheus metric name.
+fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == ':')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == ':')
+}
+
+/// Returns whether `a` and `b` refer to the same metric instance.
+fn is_same_metric(a: &dyn Metric, b: &dyn Metric) -> bool {
+    ptr::eq(a as *const dyn Metric as *const (), b as *const dyn Metric as *const ())
+}
+
+/// Registers the given `metric` such that it's included when metrics are exported.
+///
+/// Registering the same metric more than once has no effect,
+/// but registering a different metric with the same name returns an error.
+pub fn register(metric: &'static dyn Metric) -> Result<(), &'static str> {
+    if !is_valid_name(metric.name()) {
+        return Err("invalid metric name");
+    }
+    let mut registry = REGISTRY.lock();
+    match registry.iter().find(|m| m.name() == metric.name()) {
+        Some(existing) if is_same_metric(*existing, metric) => Ok(()),
+        Some(_) => Err("a different metric with the same name is already registered"),
+        None => {
+            registry.push(metric);
+            Ok(())
+        }
+    }
+}
+
+/// Unregisters the metric with the given `name`, returning whether it was registered.
+pub fn unregister(name: &str) -> bool {
+    let mut registry = REGISTRY.lock();
+    let len = registry.len();
+    registry.retain(|m| m.name() != name);
+    registry.len() != len
+}
+
+/// Writes all registered metrics to `writer` in the Prometheus text format.
+pub fn render_prometheus(writer: &mut dyn Write) -> fmt::Result {
+    // Metrics are collected without the registry locked, since collecting them may register others.
+    let metrics = REGISTRY.lock().clone();
+    for metric in metrics {
+        write!(writer, "# HELP {} ", metric.name())?;
+        for c in metric.help().chars() {
+            match c {
+                '\\' => writer.write_str("\\\\")?,
+                '\n' => writer.write_str("\\n")?,
+                c => writer.write_char(c)?,
+            }
+        }
+        writeln!(writer, "\n# TYPE {} {}", metric.name(), metric.kind().as_str())?;
+        metric.collect(&mut Samples {
+            name: metric.name(),
+            sink: Sink::Text(&mut *writer),
+        })?;
+    }
+    Ok(())
+}
+
+/// The samples of a metric at the time it was collected by [`snapshot()`].
+#[derive(Clone, Debug, Serialize)]
+pub struct MetricSnapshot {
+    pub name: String,
+    pub help: String,
+    pub kind: MetricKind,
+    pub samples: Vec<Sample>,
+}
+
+/// A single sample of a metric.
+#[derive(Clone, Debug, Serialize)]
+pub struct Sample {
+    /// The name of the metric followed by a suffix, e.g., `_bucket`, as in the text format.
+    pub name: String,
+    pub labels: BTreeMap<String, String>,
+    /// The value of the sample, which is NaN if it couldn't be parsed as a number.
+    pub value: f64,
+}
+
+/// Returns the current samples of all registered metrics, in the order they were registered.
+///
+/// Unlike [`render_prometheus()`], this can be serialized into any format.
+pub fn snapshot() -> Vec<MetricSnapshot> {
+    let metrics = REGISTRY.lock().clone();
+    metrics
+        .into_iter()
+        .filter_map(|metric| {
+            let mut samples = Vec::new();
+            metric
+                .collect(&mut Samples {
+                    name: metric.name(),
+                    sink: Sink::Records(&mut samples),
+                })
+                .ok()?;
+            Some(MetricSnapshot {
+                name: String::from(metric.name()),
+                help: String::from(metric.help()),
+                kind: metric.kind(),
+                samples,
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,113 @@
+//! An interval map from virtual addresses to the loaded sections that occupy them.
+//!
+//! This allows an arbitrary address, e.g., an instruction pointer from a backtrace or a fault,
+//! to be mapped back to its section without iterating over every section of every crate.
+//!
+//! A crate's merged sections (e.g., its whole `.text` section) contain the individual symbol sections
+//! that were merged into them, so the two kinds are kept in separate maps.
+//! Symbol sections never overlap each other, nor do merged sections,
+//! so within each map the section containing an address is always the one that starts closest before it.
+
+use alloc::{collections::BTreeMap, sync::Arc};
+use core::ops::Bound::{Included, Unbounded};
+use spin::RwLock;
+use crate_metadata::{LoadedCrate, LoadedSection, StrongSectionRef, WeakSectionRef};
+use crate_metadata_serde::SectionType;
+use memory::VirtualAddress;
+
+/// A section in a [`SectionAddressMap`], keyed by its starting address.
+#[derive(Clone)]
+struct Entry {
+    /// The exclusive ending address of the section.
+    end: VirtualAddress,
+    section: WeakSectionRef,
+}
+
+type IntervalMap = BTreeMap<VirtualAddress, Entry>;
+
+/// A map from virtual address ranges to the sections loaded at those ranges.
+pub struct SectionAddressMap {
+    /// The individual symbol sections, e.g., a single function.
+    symbol_sections: RwLock<IntervalMap>,
+    /// The merged sections that hold all of a crate's sections of a given type, e.g., `.text`.
+    merged_sections: RwLock<IntervalMap>,
+}
+
+impl SectionAddressMap {
+    /// Creates a new empty address map.
+    pub const fn new() -> SectionAddressMap {
+        SectionAddressMap {
+            symbol_sections: RwLock::new(BTreeMap::new()),
+            merged_sections: RwLock::new(BTreeMap::new()),
+        }
+    }
+
+    /// Adds all of the given crate's sections to this map.
+    pub fn insert_crate(&self, krate: &LoadedCrate) {
+        let mut symbol_sections = self.symbol_sections.write();
+        let mut merged_sections = self.merged_sections.write();
+        for sec in krate.sections.values().filter(|sec| is_indexable(sec)) {
+            let map = if is_merged(sec) { &mut merged_sections } else { &mut symbol_sections };
+            map.insert(sec.virt_addr, Entry {
+                end: sec.virt_addr + sec.size,
+                section: Arc::downgrade(sec),
+            });
+        }
+    }
+
+    /// Removes all of the given crate's sections from this map.
+    ///
+    /// Entries at the same addresses that refer to other crates' sections are left untouched.
+    pub fn remove_crate(&self, krate: &LoadedCrate) {
+        let mut symbol_sections = self.symbol_sections.write();
+        let mut merged_sections = self.merged_sections.write();
+        for sec in krate.sections.values().filter(|sec| is_indexable(sec)) {
+            let map = if is_merged(sec) { &mut merged_sections } else { &mut symbol_sections };
+            if map.get(&sec.virt_addr).is_some_and(|entry| entry.section.as_ptr() == Arc::as_ptr(sec)) {
+                map.remove(&sec.virt_addr);
+            }
+        }
+    }
+
+    /// Returns the most specific section that contains the given `virt_addr`,
+    /// along with the offset of `virt_addr` into that section.
+    ///
+    /// A symbol section is preferred over the merged section that contains it.
+    pub fn get(&self, virt_addr: VirtualAddress) -> Option<(StrongSectionRef, usize)> {
+        lookup(&self.symbol_sections.read(), virt_addr)
+            .or_else(|| lookup(&self.merged_sections.read(), virt_addr))
+    }
+}
+
+impl Clone for SectionAddressMap {
+    fn clone(&self) -> SectionAddressMap {
+        SectionAddressMap {
+            symbol_sections: RwLock::new(self.symbol_sections.read().clone()),
+            merged_sections: RwLock::new(self.merged_sections.read().clone()),
+        }
+    }
+}
+
+/// Finds the live section that starts closest before `virt_addr` and checks whether it contains `virt_addr`.
+///
+/// Entries for sections that have since been dropped are skipped.
+fn lookup(map: &IntervalMap, virt_addr: VirtualAddress) -> Option<(StrongSectionRef, usize)> {
+    let (start, entry, section) = map.range((Unbounded, Included(virt_addr)))
+        .rev()
+        .find_map(|(start, entry)| entry.section.upgrade().map(|sec| (start, entry, sec)))?;
+    if virt_addr < entry.end {
+        Some((section, virt_addr.value() - start.value()))
+    } else {
+        None
+    }
+}
+
+/// TLS and CLS sections have offsets rather than real addresses, and empty sections occupy no addresses.
+fn is_indexable(sec: &LoadedSection) -> bool {
+    sec.size > 0 && !sec.typ.is_tls() && sec.typ != SectionType::Cls
+}
+
+/// Merged sections have the standard name for their section type, e.g., `.text`.
+fn is_merged(sec: &LoadedSection) -> bool {
+    sec.name.as_str() == sec.typ.name()
+}
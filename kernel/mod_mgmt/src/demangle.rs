@@ -0,0 +1,81 @@
+//! Demangling of symbol names into the `name::h<hash>` form that Theseus uses to identify sections.
+//!
+//! Rust's legacy mangling scheme (`_ZN...17h<hash>E`) embeds a hash in each symbol,
+//! which `rustc_demangle` appends to the demangled name after a [`SECTION_HASH_DELIMITER`].
+//! The v0 mangling scheme (`_R...`) has no such per-symbol hash;
+//! instead, each crate in a symbol's path carries a disambiguator,
+//! which is derived from the crate's metadata (including its `-C metadata` hash).
+//! For v0 symbols, we use the disambiguator of the first crate in the demangled path
+//! as the symbol's hash, such that all symbol names follow the same convention.
+
+use alloc::string::{String, ToString};
+use rustc_demangle::demangle;
+use crate::SECTION_HASH_DELIMITER;
+
+/// The prefix of symbols mangled with Rust's v0 mangling scheme on ELF platforms.
+const V0_PREFIX: &str = "_R";
+
+/// Demangles the given symbol name, which may have been mangled with either
+/// Rust's legacy or v0 mangling scheme, into the form `my_crate::foo::h<hash>`.
+///
+/// Names that aren't mangled, e.g., `#[no_mangle]` symbols, are returned as is.
+pub fn demangle_symbol(mangled: &str) -> String {
+    let demangled = demangle(mangled);
+    if !mangled.starts_with(V0_PREFIX) {
+        return demangled.to_string();
+    }
+    // The non-alternate form includes each crate's disambiguator, e.g., `my_crate[6f1a9ac2b4e7d003]::foo`,
+    // whereas the alternate form omits them, e.g., `my_crate::foo`.
+    let name = format!("{:#}", demangled);
+    match crate_disambiguator(&demangled.to_string()) {
+        Some(hash) => format!("{}{}{:0>16}", name, SECTION_HASH_DELIMITER, hash),
+        None => name,
+    }
+}
+
+/// Returns the first crate disambiguator in the given demangled v0 symbol,
+/// i.e., the hex digits within the brackets that directly follow a crate name.
+///
+/// Brackets that don't follow an identifier, e.g., slice types like `[u8]`, are skipped.
+fn crate_disambiguator(demangled: &str) -> Option<&str> {
+    let bytes = demangled.as_bytes();
+    demangled.match_indices('[').find_map(|(open, _)| {
+        let follows_ident = open > 0 && {
+            let prev = bytes[open - 1];
+            prev.is_ascii_alphanumeric() || prev == b'_'
+        };
+        if !follows_ident {
+            return None;
+        }
+        let rest = &demangled[open + 1 ..];
+        let hash = &rest[.. rest.find(']')?];
+        let is_hash = !hash.is_empty() && hash.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'));
+        is_hash.then_some(hash)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use super::*;
+
+    #[test]
+    fn legacy() {
+        assert_eq!(demangle_symbol("_ZN7console4init17h71243d883671cb51E"), "console::init::h71243d883671cb51");
+        assert_eq!(demangle_symbol("nano_core_start"), "nano_core_start");
+    }
+
+    #[test]
+    fn v0() {
+        // `mycrate::example_function`, with a crate disambiguator of 0xd5dca811468a0108.
+        assert_eq!(
+            demangle_symbol("_RNvCsimnGJIYDPwO_7mycrate16example_function"),
+            "mycrate::example_function::hd5dca811468a0108"
+        );
+        // `<[u8] as mycrate::Foo>::foo`, whose first bracketed item is a slice.
+        assert_eq!(
+            demangle_symbol("_RNvXCsimnGJIYDPwO_7mycrateShNtB2_3Foo3foo"),
+            "<[u8] as mycrate::Foo>::foo::hd5dca811468a0108"
+        );
+    }
+}
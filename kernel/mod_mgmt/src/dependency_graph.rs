@@ -0,0 +1,230 @@
+//! A graph of the dependencies between the crates in a namespace.
+//!
+//! Dependencies between sections are recorded when a section is relocated against a section
+//! in another crate; see [`LoadedSection::sections_i_depend_on()`].
+//! A [`DependencyGraph`] aggregates them into dependencies between whole crates,
+//! which is what unloading, swapping, and visualizing crates requires,
+//! e.g., a crate can only be unloaded after all the crates that depend on it.
+//!
+//! [`LoadedSection::sections_i_depend_on()`]: crate::LoadedSection::sections_i_depend_on
+
+use alloc::{collections::VecDeque, vec::Vec};
+use core::fmt;
+use hashbrown::HashMap;
+use crate::{CowArc, CrateNamespace, StrRef, StrongCrateRef, WeakCrateRef};
+
+/// A snapshot of the dependencies between the crates in a namespace.
+pub struct DependencyGraph {
+    crates: Vec<(StrRef, StrongCrateRef)>,
+    /// For each crate, the indices of the crates that it depends on, in ascending order.
+    dependencies: Vec<Vec<usize>>,
+    /// For each crate, the indices of the crates that depend on it, in ascending order.
+    dependents: Vec<Vec<usize>>,
+}
+
+impl DependencyGraph {
+    /// Builds the graph of the dependencies between the crates in the given `namespace`, as they're currently linked.
+    /// If `recursive` is true, the crates in recursive namespaces that are visible to `namespace` are included as well.
+    ///
+    /// Dependencies on crates that aren't included, e.g., on crates in a recursive namespace
+    /// when `recursive` is false, are omitted.
+    pub fn new(namespace: &CrateNamespace, recursive: bool) -> DependencyGraph {
+        let crates: Vec<(StrRef, StrongCrateRef)> = namespace.crates(recursive).collect();
+        let weak_refs: Vec<WeakCrateRef> = crates.iter().map(|(_, crate_ref)| CowArc::downgrade(crate_ref)).collect();
+        let indices: HashMap<&str, usize> = crates.iter()
+            .enumerate()
+            .map(|(index, (name, _))| (name.as_str(), index))
+            .collect();
+
+        let mut dependencies = vec![Vec::new(); crates.len()];
+        let mut dependents = vec![Vec::new(); crates.len()];
+        for (index, (_, crate_ref)) in crates.iter().enumerate() {
+            // Release this crate's lock before locking the crates it depends on.
+            let crates_i_depend_on = crate_ref.lock_as_ref().crates_i_depend_on();
+            for weak_dep in crates_i_depend_on {
+                let Some(dep) = weak_dep.upgrade() else { continue };
+                let dep_name = dep.lock_as_ref().crate_name.clone();
+                // A crate with the same name may be a different one, e.g., one in a namespace that isn't included.
+                match indices.get(dep_name.as_str()) {
+                    Some(&dep_index) if dep_index != index && weak_refs[dep_index].ptr_eq(&weak_dep) => {
+                        dependencies[index].push(dep_index);
+                        dependents[dep_index].push(index);
+                    }
+                    _ => { }
+                }
+            }
+        }
+        for list in dependencies.iter_mut().chain(dependents.iter_mut()) {
+            list.sort_unstable();
+            list.dedup();
+        }
+
+        DependencyGraph { crates, dependencies, dependents }
+    }
+
+    /// Returns the number of crates in this graph.
+    pub fn len(&self) -> usize {
+        self.crates.len()
+    }
+
+    /// Returns `true` if this graph has no crates.
+    pub fn is_empty(&self) -> bool {
+        self.crates.is_empty()
+    }
+
+    /// Returns an iterator over the crates in this graph, in no particular order.
+    pub fn crates(&self) -> impl Iterator<Item = (&StrRef, &StrongCrateRef)> {
+        self.crates.iter().map(|(name, crate_ref)| (name, crate_ref))
+    }
+
+    /// Returns the crate with the given name, if it's in this graph.
+    pub fn get(&self, crate_name: &str) -> Option<&StrongCrateRef> {
+        self.index_of(crate_name).map(|index| &self.crates[index].1)
+    }
+
+    /// Returns the names of the crates that the crate named `crate_name` directly depends on.
+    pub fn dependencies_of(&self, crate_name: &str) -> impl Iterator<Item = &StrRef> {
+        self.names_of(self.index_of(crate_name).map(|index| &self.dependencies[index][..]))
+    }
+
+    /// Returns the names of the crates that directly depend on the crate named `crate_name`.
+    pub fn dependents_of(&self, crate_name: &str) -> impl Iterator<Item = &StrRef> {
+        self.names_of(self.index_of(crate_name).map(|index| &self.dependents[index][..]))
+    }
+
+    /// Returns the names of all crates that directly or indirectly depend on the crate named `crate_name`,
+    /// i.e., the crates that would be affected by unloading or swapping it,
+    /// ordered such that each crate comes before the crates it depends on.
+    pub fn transitive_dependents_of(&self, crate_name: &str) -> Vec<&StrRef> {
+        let Some(start) = self.index_of(crate_name) else { return Vec::new() };
+        let mut visited = vec![false; self.crates.len()];
+        visited[start] = true;
+        let mut queue: VecDeque<usize> = self.dependents[start].iter().copied().collect();
+        while let Some(index) = queue.pop_front() {
+            if !visited[index] {
+                visited[index] = true;
+                queue.extend(&self.dependents[index]);
+            }
+        }
+        visited[start] = false;
+        topological_sort(&self.dependencies, &self.dependents)
+            .into_iter()
+            .rev()
+            .filter(|&index| visited[index])
+            .map(|index| &self.crates[index].0)
+            .collect()
+    }
+
+    /// Returns an iterator over the crates in this graph in topological order,
+    /// i.e., each crate comes after all of the crates it depends on.
+    /// Reversing it yields an order in which crates can be unloaded.
+    ///
+    /// Crates that depend on each other in a cycle come after the other crates they depend on,
+    /// in no particular order relative to each other.
+    pub fn topological_order(&self) -> impl DoubleEndedIterator<Item = (&StrRef, &StrongCrateRef)> {
+        topological_sort(&self.dependencies, &self.dependents)
+            .into_iter()
+            .map(|index| (&self.crates[index].0, &self.crates[index].1))
+    }
+
+    /// Writes this graph in the DOT format of Graphviz, with an edge from each crate to each crate it depends on.
+    pub fn write_dot<W: fmt::Write>(&self, mut writer: W) -> fmt::Result {
+        writeln!(writer, "digraph crates {{")?;
+        for (index, (name, _)) in self.crates.iter().enumerate() {
+            writeln!(writer, "    \"{}\";", name)?;
+            for &dep_index in &self.dependencies[index] {
+                writeln!(writer, "    \"{}\" -> \"{}\";", name, self.crates[dep_index].0)?;
+            }
+        }
+        writeln!(writer, "}}")
+    }
+
+    fn index_of(&self, crate_name: &str) -> Option<usize> {
+        self.crates.iter().position(|(name, _)| name.as_str() == crate_name)
+    }
+
+    fn names_of<'a>(&'a self, indices: Option<&'a [usize]>) -> impl Iterator<Item = &'a StrRef> {
+        indices.unwrap_or(&[]).iter().map(move |&index| &self.crates[index].0)
+    }
+}
+
+impl CrateNamespace {
+    /// Returns the graph of the dependencies between the crates in this namespace,
+    /// as described in [`DependencyGraph::new()`].
+    pub fn dependency_graph(&self, recursive: bool) -> DependencyGraph {
+        DependencyGraph::new(self, recursive)
+    }
+}
+
+/// Returns the indices of the nodes of a graph in an order in which
+/// each node comes after all of the nodes it depends on.
+///
+/// `dependencies[i]` and `dependents[i]` are the nodes that node `i` depends on
+/// and that depend on node `i`, respectively, without duplicates.
+/// Cycles are broken by placing the lowest-indexed node that hasn't been placed yet,
+/// as if its remaining dependencies had already been placed.
+fn topological_sort(dependencies: &[Vec<usize>], dependents: &[Vec<usize>]) -> Vec<usize> {
+    let num_nodes = dependencies.len();
+    let mut remaining: Vec<usize> = dependencies.iter().map(Vec::len).collect();
+    let mut ready: VecDeque<usize> = (0 .. num_nodes).filter(|&index| remaining[index] == 0).collect();
+    let mut placed = vec![false; num_nodes];
+    let mut order = Vec::with_capacity(num_nodes);
+    loop {
+        while let Some(index) = ready.pop_front() {
+            order.push(index);
+            placed[index] = true;
+            for &dependent in &dependents[index] {
+                if remaining[dependent] > 0 {
+                    remaining[dependent] -= 1;
+                    if remaining[dependent] == 0 {
+                        ready.push_back(dependent);
+                    }
+                }
+            }
+        }
+        match (0 .. num_nodes).find(|&index| !placed[index]) {
+            Some(index) => {
+                remaining[index] = 0;
+                ready.push_back(index);
+            }
+            None => return order,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use super::*;
+
+    /// Returns the `dependents` lists that are the inverse of the given `dependencies` lists.
+    fn invert(dependencies: &[Vec<usize>]) -> Vec<Vec<usize>> {
+        let mut dependents = vec![Vec::new(); dependencies.len()];
+        for (index, deps) in dependencies.iter().enumerate() {
+            for &dep in deps {
+                dependents[dep].push(index);
+            }
+        }
+        dependents
+    }
+
+    fn sort(dependencies: &[Vec<usize>]) -> Vec<usize> {
+        topological_sort(dependencies, &invert(dependencies))
+    }
+
+    #[test]
+    fn dependencies_come_first() {
+        // 0 depends on 1 and 2, which both depend on 3.
+        let dependencies = [vec![1, 2], vec![3], vec![3], vec![]];
+        assert_eq!(sort(&dependencies), [3, 1, 2, 0]);
+        assert_eq!(sort(&[]), []);
+    }
+
+    #[test]
+    fn cycles_are_broken() {
+        // 0 and 1 depend on each other, 2 depends on 0, and 1 depends on 3.
+        let dependencies = [vec![1], vec![0, 3], vec![0], vec![]];
+        let order = sort(&dependencies);
+        assert_eq!(order, [3, 0, 1, 2]);
+    }
+}
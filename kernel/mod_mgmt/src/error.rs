@@ -0,0 +1,163 @@
+//! The error types returned when parsing the nano_core, loading and linking crates, or unloading them.
+
+use alloc::string::{String, ToString};
+use core::fmt;
+
+/// The message used for an unsupported relocation type, which also explains how to avoid it.
+const UNSUPPORTED_RELOCATION_MSG: &str = "found unsupported relocation type. \
+    --> Compile with 'relocation-model=static', 'code-model=large', and 'tls-model=local-exec'";
+
+/// An error that occurred while parsing the nano_core or loading a crate object file.
+///
+/// The variants that carry a `&'static str` hold a message describing the specific error,
+/// which is what [`Display`](fmt::Display) prints.
+///
+/// For compatibility with callers that return `&'static str` errors,
+/// a `LoadError` can be converted into a `&'static str` (e.g., with `?`),
+/// which yields a static description of the error without any dynamic details,
+/// or into a `String`, which yields the full [`Display`](fmt::Display) message.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LoadError {
+    /// A section that is required to parse or load the file is missing.
+    SectionMissing(&'static str),
+    /// A section had unexpected flags or type, or its contents couldn't be parsed.
+    InvalidSection(&'static str),
+    /// A relocation entry had this type, which isn't supported.
+    RelocationUnsupported(u32),
+    /// The symbol with this name, which is needed by a relocation,
+    /// couldn't be found in the namespace, nor could the crate containing it be loaded.
+    SymbolNotFound(String),
+    /// The symbol needed by a relocation was found, but with a different hash than expected,
+    /// meaning it comes from an ABI-incompatible version of its crate.
+    /// This is only reported when strict symbol hash checking is enabled.
+    SymbolHashMismatch {
+        /// The name of the symbol that the relocation refers to.
+        symbol: String,
+        /// The hash that the relocation expected the symbol to have.
+        expected: String,
+        /// The hash of the symbol that was actually found.
+        found: String,
+    },
+    /// A crate with this name has already been loaded into the namespace.
+    CrateAlreadyLoaded(String),
+    /// Memory for the loaded sections couldn't be allocated or mapped,
+    /// or a section wasn't covered by the memory it was expected to be in.
+    MappingFailed(&'static str),
+    /// The file isn't a valid crate object file or nano_core file.
+    InvalidFile(&'static str),
+    /// Any other error.
+    Other(&'static str),
+}
+
+impl LoadError {
+    /// Returns a static description of this error.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LoadError::SectionMissing(msg)
+            | LoadError::InvalidSection(msg)
+            | LoadError::MappingFailed(msg)
+            | LoadError::InvalidFile(msg)
+            | LoadError::Other(msg) => msg,
+            LoadError::RelocationUnsupported(_) => UNSUPPORTED_RELOCATION_MSG,
+            LoadError::SymbolNotFound(_) => "Couldn't get symbol for foreign relocation entry, nor load its containing crate",
+            LoadError::SymbolHashMismatch { .. } => "found symbol for foreign relocation entry, but its hash didn't match (incompatible version)",
+            LoadError::CrateAlreadyLoaded(_) => "the crate has already been loaded, cannot load it again in the same namespace",
+        }
+    }
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadError::RelocationUnsupported(typ) => write!(
+                f,
+                "found unsupported relocation type {}. \
+                --> Compile with 'relocation-model=static', 'code-model=large', and 'tls-model=local-exec'",
+                typ,
+            ),
+            LoadError::SymbolNotFound(symbol) => write!(f, "{}: {:?}", self.as_str(), symbol),
+            LoadError::SymbolHashMismatch { symbol, expected, found } => write!(
+                f,
+                "{}: {:?}, expected hash {:?}, found hash {:?}",
+                self.as_str(), symbol, expected, found,
+            ),
+            LoadError::CrateAlreadyLoaded(crate_name) => write!(f, "{}: {:?}", self.as_str(), crate_name),
+            _ => f.write_str(self.as_str()),
+        }
+    }
+}
+
+impl From<&'static str> for LoadError {
+    fn from(msg: &'static str) -> Self {
+        LoadError::Other(msg)
+    }
+}
+
+impl From<LoadError> for &'static str {
+    fn from(err: LoadError) -> Self {
+        err.as_str()
+    }
+}
+
+impl From<LoadError> for String {
+    fn from(err: LoadError) -> Self {
+        err.to_string()
+    }
+}
+
+
+/// An error that prevented a crate from being unloaded from a namespace.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum UnloadError {
+    /// No crate with this name is loaded in the namespace itself (excluding its recursive namespaces).
+    CrateNotFound(String),
+    /// The crate is also part of another namespace, which would still be using it.
+    CrateShared(String),
+    /// The crate is still in use, because a section in another crate depends on one of its sections.
+    CrateInUse {
+        /// The name of the crate that was to be unloaded.
+        crate_name: String,
+        /// The name of the section in the crate to be unloaded that is depended upon.
+        section: String,
+        /// The name of the other crate's section that depends on it.
+        dependent_section: String,
+    },
+}
+
+impl UnloadError {
+    /// Returns a static description of this error.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UnloadError::CrateNotFound(_) => "the crate to unload isn't loaded in this namespace",
+            UnloadError::CrateShared(_) => "the crate to unload is shared with another namespace",
+            UnloadError::CrateInUse { .. } => "the crate to unload is still in use by another crate",
+        }
+    }
+}
+
+impl fmt::Display for UnloadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UnloadError::CrateNotFound(crate_name) | UnloadError::CrateShared(crate_name) => {
+                write!(f, "{}: {:?}", self.as_str(), crate_name)
+            }
+            UnloadError::CrateInUse { crate_name, section, dependent_section } => write!(
+                f,
+                "{}: section {:?} in crate {:?} is depended on by section {:?}",
+                self.as_str(), section, crate_name, dependent_section,
+            ),
+        }
+    }
+}
+
+impl From<UnloadError> for &'static str {
+    fn from(err: UnloadError) -> Self {
+        err.as_str()
+    }
+}
+
+impl From<UnloadError> for String {
+    fn from(err: UnloadError) -> Self {
+        err.to_string()
+    }
+}
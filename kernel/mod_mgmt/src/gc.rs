@@ -0,0 +1,81 @@
+//! Unloading orphaned crates, i.e., crates that other crates used to depend on but no longer do.
+//!
+//! A crate is usually loaded because another crate depends on it.
+//! Once every crate that depended on it has been unloaded, e.g., when an application exits
+//! or when its dependents are swapped out for new crates, it remains loaded but unused.
+//! [`CrateNamespace::gc()`] finds and unloads such crates, freeing their memory.
+
+use alloc::{sync::Arc, vec::Vec};
+use crate::{parse_nano_core::NANO_CORE_CRATE_NAME, CrateNamespace, StrRef, StrongCrateRef};
+
+impl CrateNamespace {
+    /// Unloads every orphaned crate in this namespace, returning the names of the unloaded crates.
+    ///
+    /// A crate is an orphan if all of the following are true:
+    /// * at least one of its sections had dependents in other crates, but none of them remain loaded,
+    /// * no section of the crate is referenced from outside of the crate,
+    ///   e.g., by a section that depends on it, a TLS area, or a caller of one of its functions,
+    /// * the crate isn't shared with another namespace, and
+    /// * it isn't the nano_core.
+    ///
+    /// Thus, crates that were loaded explicitly and never depended upon,
+    /// e.g., applications and the captain, are never orphans.
+    ///
+    /// Unloading a crate runs its destructors, removes its symbols from this namespace,
+    /// and drops its dependencies on other crates,
+    /// which may turn them into orphans as well, so this repeats until no orphans remain.
+    /// Crates in this namespace's recursive namespace aren't considered.
+    pub fn gc(&self) -> Vec<StrRef> {
+        let mut unloaded = Vec::new();
+        loop {
+            let orphans: Vec<StrRef> = self.crates(false)
+                .filter(|(_, crate_ref)| is_orphan(crate_ref))
+                .map(|(crate_name, _)| crate_name)
+                .collect();
+            let num_unloaded = unloaded.len();
+            for crate_name in orphans {
+                if let Some(_removed_crate) = self.remove_orphan(&crate_name) {
+                    info!("gc(): unloaded orphaned crate {:?} from namespace {:?}", crate_name, self.name);
+                    unloaded.push(crate_name);
+                }
+            }
+            if unloaded.len() == num_unloaded {
+                return unloaded;
+            }
+        }
+    }
+
+    /// Removes the crate named `crate_name` and its symbols from this namespace after running its destructors,
+    /// and returns it, if it's still an orphan.
+    fn remove_orphan(&self, crate_name: &StrRef) -> Option<StrongCrateRef> {
+        let mut crate_tree = self.crate_tree.lock();
+        // Check again, as a new crate may have been linked against this crate since it was found to be an orphan.
+        if !is_orphan(crate_tree.get(crate_name)?) {
+            return None;
+        }
+        let crate_ref = crate_tree.remove(crate_name)?;
+        drop(crate_tree);
+        self.finish_unloading_crate(&crate_ref);
+        Some(crate_ref)
+    }
+}
+
+/// Returns whether the given crate is an orphan, as described in [`CrateNamespace::gc()`].
+fn is_orphan(crate_ref: &StrongCrateRef) -> bool {
+    if crate_ref.is_shared() {
+        return false;
+    }
+    let krate = crate_ref.lock_as_ref();
+    if krate.crate_name.as_str() == NANO_CORE_CRATE_NAME {
+        return false;
+    }
+    let mut had_dependents = false;
+    for sec in krate.sections.values() {
+        // Each section that depends on this one holds a strong reference to it.
+        if Arc::strong_count(sec) > 1 {
+            return false;
+        }
+        had_dependents |= !sec.inner.read().sections_dependent_on_me.is_empty();
+    }
+    had_dependents
+}
@@ -0,0 +1,127 @@
+//! Support for running the static constructors and destructors of loaded crates,
+//! i.e., the functions listed in their `.init_array` and `.fini_array` sections.
+//!
+//! Rust code rarely uses these, but C code linked into a crate
+//! (or crates that use a `#[link_section = ".init_array"]` static) may rely on them.
+
+use alloc::vec::Vec;
+use core::mem::size_of;
+use log::{error, warn};
+use memory::VirtualAddress;
+use hashbrown::HashMap;
+use xmas_elf::ElfFile;
+use crate::{CrateNamespace, SectionType, Shndx, StrongCrateRef, StrongSectionRef};
+
+/// The priority of `.init_array`/`.fini_array` sections whose name has no numeric suffix,
+/// which are ordered after all sections with an explicit priority, like the GNU linker does.
+const DEFAULT_PRIORITY: u32 = 65535;
+
+/// Returns the shndxs of the `.init_array` and `.fini_array` sections among the given sections,
+/// each in the order in which their functions must be invoked.
+///
+/// Sections with a numeric priority suffix, e.g., `.init_array.00100`, are ordered by that priority;
+/// constructors run in increasing order of priority, and destructors in decreasing order.
+pub(crate) fn find_init_fini_arrays(
+    elf_file: &ElfFile,
+    sections: &HashMap<Shndx, StrongSectionRef>,
+) -> (Vec<Shndx>, Vec<Shndx>) {
+    let priority = |shndx: Shndx| elf_file.section_header(shndx as u16).ok()
+        .and_then(|sec| sec.get_name(elf_file).ok())
+        .and_then(|name| name.rsplit_once('.'))
+        .and_then(|(_, suffix)| suffix.parse::<u32>().ok())
+        .unwrap_or(DEFAULT_PRIORITY);
+
+    let mut init_array = Vec::new();
+    let mut fini_array = Vec::new();
+    for (shndx, sec) in sections {
+        match sec.typ {
+            SectionType::InitArray => init_array.push((priority(*shndx), *shndx)),
+            SectionType::FiniArray => fini_array.push((priority(*shndx), *shndx)),
+            _ => { }
+        }
+    }
+    init_array.sort_unstable();
+    fini_array.sort_unstable_by(|a, b| b.cmp(a));
+    (
+        init_array.into_iter().map(|(_, shndx)| shndx).collect(),
+        fini_array.into_iter().map(|(_, shndx)| shndx).collect(),
+    )
+}
+
+impl CrateNamespace {
+    /// Invokes the constructors listed in the given crate's `.init_array` sections.
+    ///
+    /// This must only be called once the crate has been fully relocated and added to this namespace.
+    pub(crate) fn run_constructors(&self, crate_ref: &StrongCrateRef) -> Result<(), &'static str> {
+        let addresses = array_entries(crate_ref.lock_as_ref().init_array_sections_iter(), false)?;
+        for function in self.array_functions(crate_ref, addresses)? {
+            function();
+        }
+        Ok(())
+    }
+
+    /// Invokes the destructors listed in the given crate's `.fini_array` sections.
+    ///
+    /// This must be called after the crate has been removed from this namespace's crate list,
+    /// such that it can no longer be linked against, but before its sections are removed
+    /// from the section address map, as each destructor is checked to be within one of its sections.
+    /// Unlike constructors, destructors that cannot be invoked are only logged,
+    /// such that the crate can still be unloaded.
+    pub(crate) fn run_destructors(&self, crate_ref: &StrongCrateRef) {
+        let addresses = array_entries(crate_ref.lock_as_ref().fini_array_sections_iter(), true);
+        let functions = addresses.and_then(|addresses| self.array_functions(crate_ref, addresses));
+        match functions {
+            Ok(functions) => functions.into_iter().for_each(|function| function()),
+            Err(e) => error!("Couldn't run the destructors of crate {:?}: {}", crate_ref.lock_as_ref().crate_name, e),
+        }
+    }
+
+    /// Converts the given `.init_array` or `.fini_array` entries into functions,
+    /// checking that each one is within a `.text` section in this namespace.
+    ///
+    /// The given crate must not be locked, as finding the section that contains an address may lock it.
+    fn array_functions(
+        &self,
+        crate_ref: &StrongCrateRef,
+        addresses: Vec<usize>,
+    ) -> Result<Vec<extern "C" fn()>, &'static str> {
+        let mut functions = Vec::with_capacity(addresses.len());
+        for address in addresses {
+            let is_text = VirtualAddress::new(address)
+                .and_then(|vaddr| self.get_section_containing_address(vaddr, false))
+                .is_some();
+            if !is_text {
+                warn!("crate {:?} has an .init_array or .fini_array entry {:#X} that isn't a function",
+                    crate_ref.lock_as_ref().crate_name, address,
+                );
+                return Err("an .init_array or .fini_array entry isn't the address of a function");
+            }
+            // SAFETY: the address is within a `.text` section, and the compiler only emits
+            //         constructors and destructors that take no arguments and return nothing.
+            functions.push(unsafe { core::mem::transmute::<usize, extern "C" fn()>(address) });
+        }
+        Ok(functions)
+    }
+}
+
+/// Returns the entries of the given `.init_array` or `.fini_array` sections,
+/// in the order in which they must be invoked, i.e., reversed within each section if `reverse` is true.
+///
+/// Null and `-1` entries are skipped, as in other ELF loaders.
+fn array_entries<'a>(
+    sections: impl Iterator<Item = &'a StrongSectionRef>,
+    reverse: bool,
+) -> Result<Vec<usize>, &'static str> {
+    let mut entries = Vec::new();
+    for sec in sections {
+        let mapped_pages = sec.mapped_pages.lock();
+        let section_entries = mapped_pages.as_slice::<usize>(sec.mapped_pages_offset, sec.size / size_of::<usize>())?;
+        let valid_entries = section_entries.iter().copied().filter(|&e| e != 0 && e != usize::MAX);
+        if reverse {
+            entries.extend(valid_entries.rev());
+        } else {
+            entries.extend(valid_entries);
+        }
+    }
+    Ok(entries)
+}
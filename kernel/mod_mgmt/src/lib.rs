@@ -13,11 +13,10 @@ use alloc::{
     sync::{Arc, Weak}, vec::Vec
 };
 use spin::{Mutex, Once};
-use xmas_elf::{ElfFile, sections::{SHF_ALLOC, SHF_EXECINSTR, SHF_TLS, SHF_WRITE, SectionData, ShType}, symbol_table::{Binding, Type}};
-use memory::{MmiRef, MemoryManagementInfo, VirtualAddress, MappedPages, PteFlags, allocate_pages_by_bytes, allocate_frames_by_bytes_at, PageRange, allocate_pages_by_bytes_in_range};
+use xmas_elf::{ElfFile, sections::{SHF_ALLOC, SHN_UNDEF, SHF_EXECINSTR, SHF_TLS, SHF_WRITE, SectionData, ShType}, symbol_table::{Binding, Type}};
+use memory::{MmiRef, MemoryManagementInfo, VirtualAddress, MappedPages, PteFlags, allocate_pages_by_bytes, allocate_frames_by_bytes_at, PageRange, allocate_pages_by_bytes_in_range, TlbFlushBatch, PAGE_SIZE};
 use bootloader_modules::BootloaderModule;
 use cow_arc::CowArc;
-use rustc_demangle::demangle;
 use qp_trie::Trie;
 use fs_node::{FileOrDir, File, FileRef, DirRef};
 use vfs_node::VFSDirectory;
@@ -29,10 +28,33 @@ use crate_metadata_serde::{CLS_SECTION_FLAG, CLS_SYMBOL_TYPE};
 pub use local_storage_initializer::{TlsInitializer, TlsDataImage};
 pub use crate_name_utils::*;
 pub use crate_metadata::*;
-
+pub use demangle::demangle_symbol;
+pub use dependency_graph::DependencyGraph;
+pub use symbol_map::{SymbolMap, SymbolMapGuard};
+pub use address_map::SectionAddressMap;
+pub use signature::SignaturePolicy;
+pub use remote::CrateFetcher;
+pub use symbol_index::SymbolIndex;
+pub use symbol_aliases::SymbolAliases;
+
+mod demangle;
+mod dependency_graph;
+mod gc;
 pub mod parse_nano_core;
+mod remote;
 pub mod replace_nano_core_crates;
+mod address_map;
+mod error;
+mod init_fini;
+pub mod metadata_cache;
 mod serde;
+pub mod signature;
+mod symbol_aliases;
+mod symbol_index;
+mod symbol_map;
+pub mod versions;
+
+pub use error::{LoadError, UnloadError};
 
 
 /// The name of the directory that contains all of the CrateNamespace files.
@@ -42,6 +64,10 @@ pub const NAMESPACES_DIRECTORY_NAME: &str = "namespaces";
 pub const EXTRA_FILES_DIRECTORY_NAME: &str = "extra_files";
 const EXTRA_FILES_DIRECTORY_DELIMITER: char = '!';
 
+/// The name of the archive of all bootloader modules (once decompressed),
+/// which is used when the bootloader only supports a limited number of modules, e.g., limine.
+const BOOT_MODULES_ARCHIVE_NAME: &str = "modules.cpio";
+
 /// The initial `CrateNamespace` that all kernel crates are added to by default.
 static INITIAL_KERNEL_NAMESPACE: Once<Arc<CrateNamespace>> = Once::new();
 
@@ -81,6 +107,26 @@ static TLS_INITIALIZER: Mutex<TlsInitializer> = Mutex::new(TlsInitializer::new()
 /// The returned `CrateNamespace` will itself be empty, having no crates and no symbols in its map.
 ///
 pub fn create_application_namespace(recursive_namespace: Option<Arc<CrateNamespace>>) -> Result<Arc<CrateNamespace>, &'static str> {
+    new_application_namespace(recursive_namespace, None)
+}
+
+/// Create a new application `CrateNamespace`, like [`create_application_namespace()`],
+/// but which can only use the crates in its `recursive_namespace` that are allowed by the given `contract`.
+///
+/// This enforces least privilege at the linking layer: application crates loaded into the
+/// returned namespace cannot link against any other crates from the recursive namespace,
+/// nor cause them to be loaded.
+pub fn create_restricted_application_namespace(
+    recursive_namespace: Option<Arc<CrateNamespace>>,
+    contract: VisibilityContract,
+) -> Result<Arc<CrateNamespace>, &'static str> {
+    new_application_namespace(recursive_namespace, Some(contract))
+}
+
+fn new_application_namespace(
+    recursive_namespace: Option<Arc<CrateNamespace>>,
+    contract: Option<VisibilityContract>,
+) -> Result<Arc<CrateNamespace>, &'static str> {
     // (1) use the initial kernel CrateNamespace as the new app namespace's recursive namespace if none was provided.
     let recursive_namespace = recursive_namespace
         .or_else(|| get_initial_kernel_namespace().cloned())
@@ -91,13 +137,86 @@ pub fn create_application_namespace(recursive_namespace: Option<Arc<CrateNamespa
         .and_then(|ns_dir| ns_dir.lock().get_dir(&default_app_namespace_name))
         .ok_or("Couldn't find the directory for the default application CrateNamespace")?;
     // (3) create the actual new application CrateNamespace.
-    let new_app_namespace = Arc::new(CrateNamespace::new(
+    let mut new_app_namespace = CrateNamespace::new(
         default_app_namespace_name,
         NamespaceDir::new(default_app_namespace_dir),
         Some(recursive_namespace),
-    ));
+    );
+    new_app_namespace.visibility_contract = contract;
 
-    Ok(new_app_namespace)
+    Ok(Arc::new(new_app_namespace))
+}
+
+
+/// The crates that are visible to every namespace, regardless of its [`VisibilityContract`],
+/// because virtually all Rust code depends on them.
+pub const ALWAYS_VISIBLE_CRATES: &[&str] = &["core", "alloc", "compiler_builtins"];
+
+/// A contract that specifies which crates and symbols in a `CrateNamespace`'s recursive namespace
+/// are visible to it, i.e., which ones its own crates can link against.
+///
+/// Crates are specified by name without their hash suffix, e.g., `"task"` rather than `"task-843a6138"`.
+/// The crates in [`ALWAYS_VISIBLE_CRATES`] are always visible.
+///
+/// In addition to whole crates, a contract can expose a curated allowlist of individual symbols,
+/// e.g., a minimal "syscall surface" of kernel functions, without exposing the rest of their crates.
+/// Symbols are also specified without their hash suffix, e.g., `"task::get_my_current_task"`.
+/// An allowed symbol ending in `"::"` exposes every symbol with that prefix, e.g., all items in a module.
+///
+/// The contract only restricts the boundary between a namespace and its recursive namespace;
+/// crates within the recursive namespace can still use each other as usual.
+#[derive(Debug, Clone, Default)]
+pub struct VisibilityContract {
+    allowed_crates: BTreeSet<String>,
+    allowed_symbols: BTreeSet<String>,
+}
+
+impl VisibilityContract {
+    /// Creates a new contract that only allows the given crates, plus the [`ALWAYS_VISIBLE_CRATES`].
+    pub fn new<I, S>(allowed_crates: I) -> VisibilityContract
+        where I: IntoIterator<Item = S>,
+              S: Into<String>,
+    {
+        VisibilityContract {
+            allowed_crates: allowed_crates.into_iter().map(Into::into).collect(),
+            allowed_symbols: BTreeSet::new(),
+        }
+    }
+
+    /// Additionally allows the given symbols, which are visible even if their crates are not.
+    pub fn with_allowed_symbols<I, S>(mut self, allowed_symbols: I) -> VisibilityContract
+        where I: IntoIterator<Item = S>,
+              S: Into<String>,
+    {
+        self.allowed_symbols.extend(allowed_symbols.into_iter().map(Into::into));
+        self
+    }
+
+    /// Returns whether the crate with the given name, which may include a hash suffix,
+    /// is visible under this contract.
+    pub fn allows(&self, crate_name: &str) -> bool {
+        let name = crate_name.split(CRATE_HASH_DELIMITER).next().unwrap_or(crate_name);
+        ALWAYS_VISIBLE_CRATES.contains(&name) || self.allowed_crates.contains(name)
+    }
+
+    /// Returns whether the symbol with the given name, which may include a hash suffix,
+    /// is explicitly allowed by this contract, regardless of whether its crate is.
+    pub fn allows_symbol(&self, symbol_name: &str) -> bool {
+        let name = LoadedSection::section_name_without_hash(symbol_name);
+        let name = name.strip_suffix(SECTION_HASH_DELIMITER).unwrap_or(name);
+        self.allowed_symbols.contains(name)
+            || self.allowed_symbols.iter().any(|allowed| allowed.ends_with("::") && name.starts_with(allowed.as_str()))
+    }
+
+    /// Returns an iterator over the names of the crates explicitly allowed by this contract.
+    pub fn allowed_crates(&self) -> impl Iterator<Item = &str> {
+        self.allowed_crates.iter().map(String::as_str)
+    }
+
+    /// Returns an iterator over the names of the symbols explicitly allowed by this contract.
+    pub fn allowed_symbols(&self) -> impl Iterator<Item = &str> {
+        self.allowed_symbols.iter().map(String::as_str)
+    }
 }
 
 
@@ -110,7 +229,14 @@ pub fn init(
     let (_namespaces_dir, default_kernel_namespace_dir) = parse_bootloader_modules_into_files(bootloader_modules, kernel_mmi)?;
     // Create the default CrateNamespace for kernel crates.
     let name = default_kernel_namespace_dir.lock().get_name();
-    let default_namespace = CrateNamespace::new(name, default_kernel_namespace_dir, None);
+    let mut default_namespace = CrateNamespace::new(name, default_kernel_namespace_dir, None);
+    default_namespace.signature_policy = if cfg!(require_crate_signatures) {
+        SignaturePolicy::Required
+    } else if signature::has_public_key() {
+        SignaturePolicy::VerifyIfSigned
+    } else {
+        SignaturePolicy::Unchecked
+    };
     Ok(INITIAL_KERNEL_NAMESPACE.call_once(|| Arc::new(default_namespace)))
 }
 
@@ -181,47 +307,42 @@ fn parse_bootloader_modules_into_files(
         let name = m.name();
         let size = m.size_in_bytes();
 
-        if name == "modules.cpio.lz4" {
+        // A compressed module, e.g., "k#foo.o.zst", is decompressed into new pages
+        // and then treated just like an uncompressed module with the same name minus the extension.
+        // A crate object file may also be compressed without changing its name,
+        // in which case it's identified by the magic number at the start of its contents.
+        let compressed = decompress::Format::from_file_name(name).or_else(|| {
+            CrateType::from_module_name(name).ok()?;
+            let format = decompress::Format::detect(mp.as_slice(0, size).ok()?)?;
+            Some((format, name))
+        });
+        let Some((format, name)) = compressed else {
+            process_module(name, size, mp)?;
+            continue;
+        };
+        let bytes = decompress::decompress(format, mp.as_slice(0, size)?)?;
+
+        if name == BOOT_MODULES_ARCHIVE_NAME {
             // The bootloader modules were compressed/archived into one large module at build time,
             // so we must extract them here.
 
             #[cfg(feature = "extract_boot_modules")]
             {
-                let bytes = mp.as_slice(0, size)?;
-                let tar = lz4_flex::block::decompress_size_prepended(bytes)
-                    .map_err(|_e| "lz4 decompression of bootloader modules failed")?;
-                /*
-                 * TODO: avoid using tons of heap space for decompression by
-                 *       allocating a separate MappedPages instance and using `decompress_into()`.
-                 *       We can determined the uncompressed size ahead of time using the following:
-                 */
-                let _uncompressed_size = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize;
-                for entry in cpio_reader::iter_files(&tar) {
-                    let name = entry.name();
+                for entry in cpio_reader::iter_files(&bytes) {
                     let bytes = entry.file();
-                    let size = bytes.len();
-                    let mut mp = {
-                        let flags = PteFlags::new().valid(true).writable(true);
-                        let allocated_pages = allocate_pages_by_bytes(size).ok_or("couldn't allocate pages")?;
-                        kernel_mmi.page_table.map_allocated_pages(allocated_pages, flags)?
-                    };
-                    {
-                        let slice = mp.as_slice_mut(0, size)?;
-                        slice.copy_from_slice(bytes);
-                    }
-                    process_module(name, size, mp)?;
+                    process_module(entry.name(), bytes.len(), copy_into_new_pages(bytes, kernel_mmi)?)?;
                 }
                 continue;
             }
             #[cfg(not(feature = "extract_boot_modules"))]
             {
-                let err_msg = "BUG: found `modules.cpio.lz4` bootloader module, but the `extract_boot_modules` feature was disabled!";
+                let err_msg = "BUG: found a compressed `modules.cpio` bootloader module, but the `extract_boot_modules` feature was disabled!";
                 error!("{}", err_msg);
                 return Err(err_msg);
             }
         }
 
-        process_module(name, size, mp)?;
+        process_module(name, bytes.len(), copy_into_new_pages(&bytes, kernel_mmi)?)?;
     }
 
     debug!("Created namespace directories: {:?}", prefix_map.keys().map(|s| &**s).collect::<Vec<&str>>().join(", "));
@@ -231,6 +352,19 @@ fn parse_bootloader_modules_into_files(
     ))
 }
 
+/// Copies the given bytes into newly-allocated pages, which become the backing memory
+/// of a module that was decompressed or extracted from an archive.
+fn copy_into_new_pages(
+    bytes: &[u8],
+    kernel_mmi: &mut MemoryManagementInfo,
+) -> Result<MappedPages, &'static str> {
+    let flags = PteFlags::new().valid(true).writable(true);
+    let allocated_pages = allocate_pages_by_bytes(bytes.len()).ok_or("couldn't allocate pages")?;
+    let mut mp = kernel_mmi.page_table.map_allocated_pages(allocated_pages, flags)?;
+    mp.as_slice_mut(0, bytes.len())?.copy_from_slice(bytes);
+    Ok(mp)
+}
+
 /// Adds the given extra file to the directory of extra files
 ///
 /// See the top-level Makefile target "extra_files" for an explanation of how these work.
@@ -277,12 +411,6 @@ fn parse_extra_file(
 
 
 
-/// A "symbol map" from a fully-qualified demangled symbol String
-/// to weak reference to a `LoadedSection`.
-/// This is used for relocations, and for looking up function names.
-pub type SymbolMap = Trie<StrRef, WeakSectionRef>;
-
-
 /// A wrapper around a `Directory` reference that offers special convenience functions
 /// for getting and inserting crate object files into a directory.  
 ///
@@ -367,14 +495,15 @@ impl NamespaceDir {
     ///    with a preceding `CrateType` prefix.
     /// * `content`: the bytes that will be written into the file.
     ///
+    /// Any existing file with the same name is replaced atomically,
+    /// so a partially downloaded crate object file is never visible in this directory.
+    ///
     /// # Examples 
     /// * The file "k#keyboard-36be916209949cef.o" will be written to "./keyboard-36be916209949cef.o". 
     /// * The file "a#ps.o" will be placed into "./ps.o". 
     pub fn write_crate_object_file(&self, crate_object_file_name: &str, content: &[u8]) -> Result<FileRef, &'static str> {
         let (_crate_type, _prefix, objfilename) = CrateType::from_module_name(crate_object_file_name)?;
-        let cfile = MemFile::create(String::from(objfilename), &self.0)?;
-        cfile.lock().write_at(content, 0)?;
-        Ok(cfile)
+        fs_utils::write_atomically(&self.0, objfilename, content)
     }
 }
 
@@ -430,6 +559,7 @@ impl Drop for AppCrateRef {
         let crate_locked = self.crate_ref.lock_as_ref();
         // First, remove the actual crate from the namespace.
         if let Some(_removed_app_crate) = self.namespace.crate_tree().lock().remove(&crate_locked.crate_name) {
+            self.namespace.section_address_map.remove_crate(&crate_locked);
             // Second, remove all of the crate's global symbols from the namespace's symbol map.
             let mut symbol_map = self.namespace.symbol_map().lock();
             for sec_to_remove in crate_locked.global_sections_iter() {
@@ -486,12 +616,29 @@ pub struct CrateNamespace {
     /// Maps a fully-qualified symbol name string to a corresponding `LoadedSection`,
     /// which is guaranteed to be part of one of the crates in this `CrateNamespace`.  
     /// Symbols declared as "no_mangle" will appear in the map with no crate prefix, as expected.
-    symbol_map: Mutex<SymbolMap>,
+    symbol_map: SymbolMap,
+
+    /// A map from the address ranges of all sections in the crates in this `CrateNamespace`
+    /// to those sections, used to quickly find the section that contains a given address.
+    /// This is kept in sync with the `crate_tree` by [`CrateNamespace::add_crate()`]
+    /// and [`CrateNamespace::remove_crate()`].
+    section_address_map: SectionAddressMap,
+
+    /// An index of the symbols defined by the crate object files in this namespace's directory,
+    /// used to find the crate that defines a missing symbol when that can't be inferred from the symbol's name.
+    symbol_index: SymbolIndex,
+
+    /// Aliases from the old names of renamed symbols to their new names,
+    /// which are used to resolve symbols that cannot be found by their exact name.
+    /// See [`CrateNamespace::add_symbol_alias()`].
+    symbol_aliases: SymbolAliases,
 
     /// The `CrateNamespace` that lies below this namespace, and can also be used by this namespace
     /// to resolve symbols and load crates that are relied on by other crates in this namespace.
     /// So, for example, if this namespace contains a set of application crates,
     /// its `recursive_namespace` could contain the set of kernel crates that these application crates rely on.
+    ///
+    /// Crates and symbols in this namespace shadow those with the same name in the `recursive_namespace`.
     recursive_namespace: Option<Arc<CrateNamespace>>,
 
     /// The thread-local storage (TLS) area "image" that is used as the initial data for each `Task`
@@ -513,6 +660,23 @@ pub struct CrateNamespace {
     /// Thus, it is false by default, and should only be enabled with expert knowledge, 
     /// ideally only temporarily in order to manually load a given crate.
     fuzzy_symbol_matching: bool,
+
+    /// A setting that toggles whether resolving a dependency requires the found symbol's hash
+    /// to exactly match the hash of the symbol that the dependent section refers to.
+    /// If `true`, linking against a symbol with a different hash fails with
+    /// [`LoadError::SymbolHashMismatch`], which would otherwise be possible via
+    /// fuzzy symbol matching or symbol aliases.
+    ///
+    /// A new namespace inherits this setting from its `recursive_namespace`, if any.
+    strict_symbol_hashes: bool,
+
+    /// If `Some`, only the crates allowed by this contract can be used from the `recursive_namespace`.
+    /// If `None`, all crates in the `recursive_namespace` can be used.
+    visibility_contract: Option<VisibilityContract>,
+
+    /// Whether crates must be signed in order to be loaded into this namespace.
+    /// A new namespace inherits this policy from its `recursive_namespace`, if any.
+    signature_policy: SignaturePolicy,
 }
 
 impl CrateNamespace {
@@ -523,14 +687,25 @@ impl CrateNamespace {
     /// * `recursive_namespace`: another `CrateNamespace` that can optionally be used 
     ///    to recursively resolve missing crates/symbols. 
     pub fn new(name: String, dir: NamespaceDir, recursive_namespace: Option<Arc<CrateNamespace>>) -> CrateNamespace {
+        let signature_policy = recursive_namespace.as_ref()
+            .map(|r_ns| r_ns.signature_policy)
+            .unwrap_or_default();
+        let strict_symbol_hashes = recursive_namespace.as_ref()
+            .map_or(false, |r_ns| r_ns.strict_symbol_hashes);
         CrateNamespace {
             name,
             dir,
             recursive_namespace,
             tls_initializer: &TLS_INITIALIZER,
             crate_tree: Mutex::new(Trie::new()),
-            symbol_map: Mutex::new(SymbolMap::new()),
+            symbol_map: SymbolMap::new(),
+            section_address_map: SectionAddressMap::new(),
+            symbol_index: SymbolIndex::new(),
+            symbol_aliases: SymbolAliases::new(),
             fuzzy_symbol_matching: false,
+            strict_symbol_hashes,
+            visibility_contract: None,
+            signature_policy,
         }
     }
 
@@ -550,6 +725,60 @@ impl CrateNamespace {
         self.recursive_namespace.as_ref()
     }
 
+    /// Returns the contract that restricts which crates in the recursive namespace
+    /// are visible to this `CrateNamespace`, if any.
+    pub fn visibility_contract(&self) -> Option<&VisibilityContract> {
+        self.visibility_contract.as_ref()
+    }
+
+    /// Returns the policy for verifying the signatures of crates loaded into this `CrateNamespace`.
+    pub fn signature_policy(&self) -> SignaturePolicy {
+        self.signature_policy
+    }
+
+    /// Sets the policy for verifying the signatures of crates loaded into this `CrateNamespace`.
+    ///
+    /// This doesn't affect crates that have already been loaded.
+    pub fn set_signature_policy(&mut self, policy: SignaturePolicy) {
+        self.signature_policy = policy;
+    }
+
+    /// Returns whether the crate with the given name in the recursive namespace is visible to this namespace.
+    fn is_recursive_crate_visible(&self, crate_name: &str) -> bool {
+        self.visibility_contract.as_ref().map_or(true, |c| c.allows(crate_name))
+    }
+
+    /// Returns whether the crate with the given name in the recursive namespace is shadowed by
+    /// a crate with the same name in this namespace.
+    fn shadows_crate(&self, crate_name: &str) -> bool {
+        self.crate_tree.lock().get(crate_name.as_bytes()).is_some()
+    }
+
+    /// Returns whether the symbol with the given name in the recursive namespace is shadowed by
+    /// a symbol with the same name in this namespace.
+    fn shadows_symbol(&self, symbol_name: &str) -> bool {
+        self.symbol_map.get(symbol_name).is_some()
+    }
+
+    /// Returns whether the given section in the recursive namespace is visible to this namespace.
+    fn is_recursive_symbol_visible(&self, section: &WeakSectionRef) -> bool {
+        let Some(contract) = self.visibility_contract.as_ref() else {
+            return true;
+        };
+        let Some(sec) = section.upgrade() else {
+            return false;
+        };
+        contract.allows_symbol(&sec.name)
+            || sec.parent_crate.upgrade().is_some_and(|parent| contract.allows(&parent.lock_as_ref().crate_name))
+    }
+
+    /// Returns whether the crate with the given name in the recursive namespace can be loaded
+    /// in order to provide the given symbol to this namespace.
+    fn is_recursive_crate_visible_for_symbol(&self, crate_name: &str, demangled_full_symbol: &str) -> bool {
+        self.visibility_contract.as_ref()
+            .map_or(true, |c| c.allows(crate_name) || c.allows_symbol(demangled_full_symbol))
+    }
+
     /// Returns a new copy of this namespace's initial TLS area,
     /// which can be used as the initial TLS area data image for a new task.
     pub fn get_tls_initializer_data(&self) -> TlsDataImage {
@@ -562,7 +791,7 @@ impl CrateNamespace {
     }
 
     #[doc(hidden)]
-    pub fn symbol_map(&self) -> &Mutex<SymbolMap> {
+    pub fn symbol_map(&self) -> &SymbolMap {
         &self.symbol_map
     }
 
@@ -576,14 +805,167 @@ impl CrateNamespace {
         self.fuzzy_symbol_matching = false;
     }
 
+    /// Returns whether this namespace refuses to link against symbols whose hash
+    /// differs from the one that the dependent section expects.
+    pub fn strict_symbol_hashes(&self) -> bool {
+        self.strict_symbol_hashes
+    }
+
+    /// Sets whether this namespace refuses to link against symbols whose hash
+    /// differs from the one that the dependent section expects.
+    ///
+    /// This doesn't affect crates that have already been loaded.
+    pub fn set_strict_symbol_hashes(&mut self, strict: bool) {
+        self.strict_symbol_hashes = strict;
+    }
+
+    /// Adds the given crate to this `CrateNamespace` under the given `crate_name`,
+    /// replacing and returning any crate that already had that name.
+    ///
+    /// This does not add the crate's symbols to this namespace's symbol map.
+    ///
+    /// # Locking
+    /// This obtains the lock on the given crate, so the caller must not hold it.
+    pub fn add_crate(&self, crate_name: StrRef, crate_ref: StrongCrateRef) -> Option<StrongCrateRef> {
+        let replaced = self.crate_tree.lock().insert(crate_name, crate_ref.clone_shallow());
+        if let Some(ref replaced) = replaced {
+            self.section_address_map.remove_crate(&replaced.lock_as_ref());
+        }
+        self.section_address_map.insert_crate(&crate_ref.lock_as_ref());
+        replaced
+    }
+
+    /// Removes the crate with the given `crate_name` from this `CrateNamespace` and returns it.
+    ///
+    /// This does not remove the crate's symbols from this namespace's symbol map.
+    ///
+    /// # Locking
+    /// This obtains the lock on the removed crate, so the caller must not hold it.
+    pub fn remove_crate(&self, crate_name: &str) -> Option<StrongCrateRef> {
+        let removed = self.crate_tree.lock().remove(crate_name.as_bytes())?;
+        self.section_address_map.remove_crate(&removed.lock_as_ref());
+        Some(removed)
+    }
+
+    /// Unloads the crate with the given `crate_name` from this `CrateNamespace`,
+    /// removing it and its symbols from this namespace.
+    ///
+    /// The crate's symbols are removed from this namespace's symbol map first,
+    /// such that no other crate can be linked against it while it is being unloaded.
+    /// The destructors in the crate's `.fini_array` sections are then invoked once it has been removed
+    /// from this namespace's crate list, but before its sections are removed from the section address map.
+    ///
+    /// The crate is then dropped, which frees the memory holding its sections,
+    /// as soon as no other references to it remain, e.g., from an `AppCrateRef` held by a running task.
+    ///
+    /// This fails without modifying this namespace if:
+    /// * the crate isn't in this namespace itself; crates in recursive namespaces must be unloaded from those namespaces,
+    /// * the crate is also part of another namespace, or
+    /// * a section in another crate still depends on (holds a strong reference to) one of this crate's sections,
+    ///   in which case that dependent crate must be unloaded first.
+    ///
+    /// # Locking
+    /// This obtains the locks on this namespace's crate list, the crate being unloaded,
+    /// and the crates that depend on it, so the caller must not hold any of them.
+    pub fn unload_crate(&self, crate_name: &str) -> Result<(), UnloadError> {
+        let mut crate_tree = self.crate_tree.lock();
+        let crate_ref = crate_tree.get(crate_name.as_bytes())
+            .ok_or_else(|| UnloadError::CrateNotFound(crate_name.to_string()))?;
+        if crate_ref.is_shared() {
+            return Err(UnloadError::CrateShared(crate_name.to_string()));
+        }
+
+        // Other crates are linked against this crate by looking up its symbols in the symbol map,
+        // not via the crate list, so its symbols must be removed before checking for dependents.
+        // Otherwise, a crate could be linked against this crate after the check.
+        let removed_symbols = self.remove_crate_symbols(crate_ref);
+        let dependent = {
+            let krate = crate_ref.lock_as_ref();
+            let weak_crate_ref = CowArc::downgrade(crate_ref);
+            krate.sections.values().find_map(|sec| {
+                sec.inner.read().sections_dependent_on_me.iter()
+                    .filter_map(|dependent| dependent.section.upgrade())
+                    .find(|dependent_sec| !dependent_sec.parent_crate.ptr_eq(&weak_crate_ref))
+                    .map(|dependent_sec| (sec.name.as_str().to_string(), dependent_sec.name.as_str().to_string()))
+            })
+        };
+        if let Some((section, dependent_section)) = dependent {
+            // The crate remains loaded, so restore its symbols, unless another crate has since provided them.
+            let mut symbol_map = self.symbol_map.lock();
+            for (symbol, section) in removed_symbols {
+                if symbol_map.get(symbol.as_bytes()).is_none() {
+                    symbol_map.insert(symbol, section);
+                }
+            }
+            return Err(UnloadError::CrateInUse { crate_name: crate_name.to_string(), section, dependent_section });
+        }
+
+        let crate_ref = crate_tree.remove(crate_name.as_bytes())
+            .ok_or_else(|| UnloadError::CrateNotFound(crate_name.to_string()))?;
+        drop(crate_tree);
+        self.finish_unloading_crate(&crate_ref);
+
+        // The crate is dropped here unless something else still holds a reference to it.
+        let weak_crate_ref = CowArc::downgrade(&crate_ref);
+        drop(crate_ref);
+        if weak_crate_ref.upgrade().is_some() {
+            debug!("unload_crate(): crate {:?} was unloaded from namespace {:?}, but is still referenced elsewhere", crate_name, self.name);
+        }
+        Ok(())
+    }
+
+    /// Finishes unloading the given crate, which has already been removed from this namespace's crate list,
+    /// by running its destructors and then removing its sections and symbols from this namespace.
+    ///
+    /// Symbols that now refer to a section in another crate, e.g., one that replaced this crate, are left alone.
+    ///
+    /// # Locking
+    /// This obtains the lock on the given crate, so the caller must not hold it.
+    fn finish_unloading_crate(&self, crate_ref: &StrongCrateRef) {
+        // Run the crate's destructors while its sections can still be found by address.
+        self.run_destructors(crate_ref);
+        self.section_address_map.remove_crate(&crate_ref.lock_as_ref());
+        self.remove_crate_symbols(crate_ref);
+    }
+
+    /// Removes the given crate's symbols from this namespace's symbol map,
+    /// returning the removed symbols and the sections they referred to.
+    ///
+    /// Symbols that refer to a section in another crate, e.g., one that replaced this crate, are left alone.
+    ///
+    /// # Locking
+    /// This obtains the lock on the given crate, so the caller must not hold it.
+    fn remove_crate_symbols(&self, crate_ref: &StrongCrateRef) -> Vec<(StrRef, WeakSectionRef)> {
+        let krate = crate_ref.lock_as_ref();
+        let weak_crate_ref = CowArc::downgrade(crate_ref);
+        let mut symbol_map = self.symbol_map.lock();
+        let symbols = krate.global_sections_iter()
+            .map(|sec| &sec.name)
+            .chain(&krate.reexported_symbols);
+        let mut removed = Vec::new();
+        for symbol in symbols {
+            let refers_to_crate = symbol_map.get(symbol.as_bytes())
+                .and_then(|sec| sec.upgrade())
+                .map_or(true, |sec| sec.parent_crate.ptr_eq(&weak_crate_ref));
+            if refers_to_crate {
+                if let Some(section) = symbol_map.remove(symbol) {
+                    removed.push((symbol.clone(), section));
+                }
+            }
+        }
+        removed
+    }
+
     /// Returns a list of all of the crate names currently loaded into this `CrateNamespace`,
     /// including all crates in any recursive namespaces as well if `recursive` is `true`.
+    /// Crates in a recursive namespace that are shadowed by a crate of the same name are excluded.
     /// This is a slow method mostly for debugging, since it allocates a new vector of crate names.
     pub fn crate_names(&self, recursive: bool) -> Vec<StrRef> {
         let mut crates: Vec<StrRef> = self.crate_tree.lock().keys().cloned().collect();
 
         if recursive {
             if let Some(mut crates_recursive) = self.recursive_namespace.as_ref().map(|r_ns| r_ns.crate_names(recursive)) {
+                crates_recursive.retain(|name| !self.shadows_crate(name.as_str()));
                 crates.append(&mut crates_recursive);
             }
         }
@@ -591,7 +973,8 @@ impl CrateNamespace {
     }
 
     /// Iterates over all crates in this namespace and calls the given function `f` on each crate.
-    /// If `recursive` is true, crates in recursive namespaces are included in the iteration as well.
+    /// If `recursive` is true, crates in recursive namespaces are included in the iteration as well,
+    /// except for those shadowed by a crate of the same name in a namespace above them.
     ///
     /// The function `f` is called with two arguments: the name of the crate, and a reference to the crate.
     /// The function `f` must return a boolean value that indicates whether to continue iterating; 
@@ -601,18 +984,66 @@ impl CrateNamespace {
         recursive: bool,
         mut f: F
     ) where F: FnMut(&str, &StrongCrateRef) -> bool {
+        self.for_each_crate_internal(recursive, &mut f);
+    }
+
+    /// The implementation of [`for_each_crate()`](#method.for_each_crate),
+    /// which takes a trait object such that it can recursively wrap `f` without infinite monomorphization.
+    /// Returns `false` if the iteration was stopped by `f`.
+    fn for_each_crate_internal(
+        &self,
+        recursive: bool,
+        f: &mut dyn FnMut(&str, &StrongCrateRef) -> bool,
+    ) -> bool {
         for (crate_name, crate_ref) in self.crate_tree.lock().iter() {
             let keep_going = f(crate_name.as_str(), crate_ref);
             if !keep_going {
-                return;
+                return false;
+            }
+        }
+
+        if recursive {
+            if let Some(ref r_ns) = self.recursive_namespace {
+                return r_ns.for_each_crate_internal(recursive, &mut |crate_name, crate_ref| {
+                    self.shadows_crate(crate_name) || f(crate_name, crate_ref)
+                });
             }
         }
+        true
+    }
+
+    /// Returns an iterator over all crates in this namespace, in the form of a tuple
+    /// containing the crate's name and a shallow-cloned reference to the crate.
+    /// If `recursive` is true, the crates in recursive namespaces that are visible
+    /// to this namespace are included as well.
+    ///
+    /// Unlike [`for_each_crate()`](#method.for_each_crate), this iterates over a snapshot
+    /// of the crate list, so no lock is held while the caller inspects each crate.
+    pub fn crates(&self, recursive: bool) -> impl Iterator<Item = (StrRef, StrongCrateRef)> {
+        let mut crates: Vec<(StrRef, StrongCrateRef)> = self.crate_tree.lock().iter()
+            .map(|(name, crate_ref)| (name.clone(), crate_ref.clone_shallow()))
+            .collect();
 
         if recursive {
             if let Some(ref r_ns) = self.recursive_namespace {
-                r_ns.for_each_crate(recursive, f);
+                crates.extend(r_ns.crates(recursive)
+                    .filter(|(name, _)| self.is_recursive_crate_visible(name.as_str()))
+                );
             }
         }
+        crates.into_iter()
+    }
+
+    /// Returns an iterator over all sections of the given type in the crates in this namespace.
+    /// If `recursive` is true, the sections of visible crates in recursive namespaces are included as well.
+    pub fn sections_of_type(&self, typ: SectionType, recursive: bool) -> impl Iterator<Item = StrongSectionRef> {
+        self.crates(recursive).flat_map(move |(_, crate_ref)| {
+            let sections: Vec<StrongSectionRef> = crate_ref.lock_as_ref()
+                .sections_of_type(typ)
+                .cloned()
+                .collect();
+            sections
+        })
     }
 
     /// Acquires the lock on this `CrateNamespace`'s crate list and returns the crate 
@@ -627,7 +1058,10 @@ impl CrateNamespace {
     pub fn get_crate(&self, crate_name: &str) -> Option<StrongCrateRef> {
         self.crate_tree.lock().get(crate_name.as_bytes())
             .map(CowArc::clone_shallow)
-            .or_else(|| self.recursive_namespace.as_ref().and_then(|r_ns| r_ns.get_crate(crate_name)))
+            .or_else(|| self.recursive_namespace.as_ref()
+                .filter(|_| self.is_recursive_crate_visible(crate_name))
+                .and_then(|r_ns| r_ns.get_crate(crate_name))
+            )
     }
 
     /// Acquires the lock on this `CrateNamespace`'s crate list and returns the crate 
@@ -649,7 +1083,10 @@ impl CrateNamespace {
     ) -> Option<(StrongCrateRef, &'n Arc<CrateNamespace>)> {
         namespace.crate_tree.lock().get(crate_name.as_bytes())
             .map(|c| (CowArc::clone_shallow(c), namespace))
-            .or_else(|| namespace.recursive_namespace.as_ref().and_then(|r_ns| Self::get_crate_and_namespace(r_ns, crate_name)))
+            .or_else(|| namespace.recursive_namespace.as_ref()
+                .filter(|_| namespace.is_recursive_crate_visible(crate_name))
+                .and_then(|r_ns| Self::get_crate_and_namespace(r_ns, crate_name))
+            )
     }
 
     /// Finds the `LoadedCrate`s whose names start with the given `crate_name_prefix`.
@@ -683,6 +1120,9 @@ impl CrateNamespace {
         let mut crates_in_recursive_namespace = namespace.recursive_namespace.as_ref()
             .map(|r_ns| Self::get_crates_starting_with(r_ns, crate_name_prefix))
             .unwrap_or_default();
+        crates_in_recursive_namespace.retain(|(name, ..)|
+            namespace.is_recursive_crate_visible(name.as_str()) && crates.get(name.as_bytes()).is_none()
+        );
 
         // Third, we combine the lists into one list that spans all namespaces.
         crates_in_this_namespace.append(&mut crates_in_recursive_namespace);
@@ -814,17 +1254,19 @@ impl CrateNamespace {
         crate_object_file: &FileRef,
         kernel_mmi_ref: &MmiRef,
         verbose_log: bool
-    ) -> Result<AppCrateRef, &'static str> {
+    ) -> Result<AppCrateRef, LoadError> {
         debug!("load_crate_as_application(): trying to load application crate at {:?}", crate_object_file.lock().get_absolute_path());
         // Don't use a backup namespace when loading applications;
         // we must be able to find all symbols in only this namespace and its backing recursive namespaces.
         let new_crate_ref = namespace.load_crate_internal(crate_object_file, None, kernel_mmi_ref, verbose_log)?;
-        {
+        let new_crate_name = {
             let new_crate = new_crate_ref.lock_as_ref();
             let _new_syms = namespace.add_symbols(new_crate.sections.values(), verbose_log);
-            namespace.crate_tree.lock().insert(new_crate.crate_name.clone(), CowArc::clone_shallow(&new_crate_ref));
             info!("loaded new application crate: {:?}, num sections: {}, added {} new symbols", new_crate.crate_name, new_crate.sections.len(), _new_syms);
-        }
+            new_crate.crate_name.clone()
+        };
+        namespace.add_crate(new_crate_name, CowArc::clone_shallow(&new_crate_ref));
+        namespace.run_constructors(&new_crate_ref)?;
         Ok(AppCrateRef {
             crate_ref: new_crate_ref,
             namespace: Arc::clone(namespace),
@@ -836,6 +1278,9 @@ impl CrateNamespace {
     /// Returns a Result containing the number of symbols that were added to the symbol map
     /// as a result of loading this crate.
     ///
+    /// Once the crate has been added to this namespace, the constructors in its `.init_array` sections are invoked.
+    /// If they cannot be invoked, an error is returned, but the crate remains loaded.
+    ///
     /// # Arguments
     /// * `crate_object_file`: the crate object file that will be loaded into this `CrateNamespace`.
     /// * `temp_backup_namespace`: the `CrateNamespace` that should be searched for missing symbols 
@@ -850,7 +1295,7 @@ impl CrateNamespace {
         temp_backup_namespace: Option<&CrateNamespace>,
         kernel_mmi_ref: &MmiRef,
         verbose_log: bool
-    ) -> Result<(StrongCrateRef, usize), &'static str> {
+    ) -> Result<(StrongCrateRef, usize), LoadError> {
         #[cfg(not(loscd_eval))]
         debug!("load_crate: trying to load crate at {:?}", crate_object_file.lock().get_absolute_path());
         let new_crate_ref = self.load_crate_internal(crate_object_file, temp_backup_namespace, kernel_mmi_ref, verbose_log)?;
@@ -863,7 +1308,8 @@ impl CrateNamespace {
 
         #[cfg(not(loscd_eval))]
         info!("loaded new crate {:?}, num sections: {}, added {} new symbols.", new_crate_name, _num_sections, new_syms);
-        self.crate_tree.lock().insert(new_crate_name, new_crate_ref.clone_shallow());
+        self.add_crate(new_crate_name, new_crate_ref.clone_shallow());
+        self.run_constructors(&new_crate_ref)?;
         Ok((new_crate_ref, new_syms))
     }
 
@@ -876,10 +1322,16 @@ impl CrateNamespace {
         temp_backup_namespace: Option<&CrateNamespace>,
         kernel_mmi_ref: &MmiRef,
         verbose_log: bool
-    ) -> Result<StrongCrateRef, &'static str> {
+    ) -> Result<StrongCrateRef, LoadError> {
         let cf = crate_object_file.lock();
+        if let Some(new_crate_ref) = metadata_cache::restore(self, cf.deref(), kernel_mmi_ref) {
+            versions::check_loaded_crate(&new_crate_ref)?;
+            return Ok(new_crate_ref);
+        }
         let (new_crate_ref, elf_file) = self.load_crate_sections(cf.deref(), kernel_mmi_ref, verbose_log)?;
         self.perform_relocations(&elf_file, &new_crate_ref, temp_backup_namespace, kernel_mmi_ref, verbose_log)?;
+        versions::check_loaded_crate(&new_crate_ref)?;
+        metadata_cache::record(self, elf_file.input, &new_crate_ref);
         Ok(new_crate_ref)
     }
 
@@ -899,7 +1351,7 @@ impl CrateNamespace {
         temp_backup_namespace: Option<&CrateNamespace>,
         kernel_mmi_ref: &MmiRef,
         verbose_log: bool,
-    ) -> Result<(), &'static str>
+    ) -> Result<(), LoadError>
         where I: Iterator<Item = &'f FileRef>
     {
         // First, lock all of the crate object files.
@@ -916,11 +1368,20 @@ impl CrateNamespace {
             partially_loaded_crates.push((new_crate_ref, elf_file));
         }
 
-        // Finally, we do all of the relocations.
+        // Then, we do all of the relocations.
+        let mut new_crates = Vec::with_capacity(partially_loaded_crates.len());
         for (new_crate_ref, elf_file) in partially_loaded_crates {
             self.perform_relocations(&elf_file, &new_crate_ref, temp_backup_namespace, kernel_mmi_ref, verbose_log)?;
+            versions::check_loaded_crate(&new_crate_ref)?;
+            metadata_cache::record(self, elf_file.input, &new_crate_ref);
             let name = new_crate_ref.lock_as_ref().crate_name.clone();
-            self.crate_tree.lock().insert(name, new_crate_ref);
+            self.add_crate(name, new_crate_ref.clone_shallow());
+            new_crates.push(new_crate_ref);
+        }
+
+        // Finally, once all crates have been added, their constructors can safely call into one another.
+        for new_crate_ref in &new_crates {
+            self.run_constructors(new_crate_ref)?;
         }
 
         Ok(())
@@ -931,7 +1392,7 @@ impl CrateNamespace {
     /// but uses a copy-on-write/clone-on-write semantic that creates 
     /// a special shared reference to each crate that indicates it is shared across multiple namespaces.
     ///
-    /// In other words, crates in the new namespace returned by this fucntions 
+    /// In other words, crates in the new namespace returned by this function 
     /// are fully shared with crates in *this* namespace, 
     /// until either namespace attempts to modify a shared crate in the future.
     ///
@@ -955,11 +1416,44 @@ impl CrateNamespace {
             tls_initializer: &TLS_INITIALIZER,
             recursive_namespace: self.recursive_namespace.clone(),
             crate_tree: Mutex::new(self.crate_tree.lock().clone()),
-            symbol_map: Mutex::new(self.symbol_map.lock().clone()),
+            symbol_map: self.symbol_map.clone(),
+            section_address_map: self.section_address_map.clone(),
+            symbol_index: self.symbol_index.clone(),
+            symbol_aliases: self.symbol_aliases.clone(),
             fuzzy_symbol_matching: self.fuzzy_symbol_matching,
+            strict_symbol_hashes: self.strict_symbol_hashes,
+            visibility_contract: self.visibility_contract.clone(),
+            signature_policy: self.signature_policy,
         }
     }
 
+    /// Creates a cheap fork of this `CrateNamespace` with the given `name`,
+    /// in which experiments like loading or swapping in a patched crate can be run
+    /// without affecting this namespace.
+    ///
+    /// The fork initially shares all of its crates with this namespace, as in [`clone_on_write()`],
+    /// so no crate metadata or sections are copied until a shared crate is actually modified.
+    /// Giving the fork a distinct name keeps per-namespace state, e.g., the [`metadata_cache`],
+    /// from conflating crates loaded into the fork with those loaded into this namespace.
+    ///
+    /// [`clone_on_write()`]: CrateNamespace::clone_on_write
+    pub fn fork(&self, name: String) -> CrateNamespace {
+        let mut forked = self.clone_on_write();
+        forked.name = name;
+        forked
+    }
+
+    /// Returns the names of the crates in this namespace (excluding its recursive namespace)
+    /// that are still shared with another namespace, e.g., one that this namespace was forked from.
+    ///
+    /// Such crates will be deeply copied before they are modified in this namespace.
+    pub fn shared_crates(&self) -> Vec<StrRef> {
+        self.crate_tree.lock().iter()
+            .filter(|(_, crate_ref)| crate_ref.is_shared())
+            .map(|(crate_name, _)| crate_name.clone())
+            .collect()
+    }
+
 
     /// Finds all of the weak dependents (sections that depend on the given `old_section`)
     /// and rewrites their relocation entries to point to the given `new_section`.
@@ -1049,13 +1543,13 @@ impl CrateNamespace {
         crate_file: &'f dyn File,
         kernel_mmi_ref: &MmiRef,
         _verbose_log: bool
-    ) -> Result<(StrongCrateRef, ElfFile<'f>), &'static str> {
+    ) -> Result<(StrongCrateRef, ElfFile<'f>), LoadError> {
         let mapped_pages  = crate_file.as_mapping()?;
         let size_in_bytes = crate_file.len();
         let abs_path      = PathBuf::from(crate_file.get_absolute_path());
         let crate_name    = StrRef::from(
             crate_name_from_path(&abs_path)
-                .ok_or("failed to get crate name from path")?
+                .ok_or(LoadError::InvalidFile("failed to get crate name from path"))?
         );
 
         // First, check to make sure this crate hasn't already been loaded. 
@@ -1063,14 +1557,14 @@ impl CrateNamespace {
         // so to load an application crate multiple times and run multiple instances of it,
         // you can create a top-level new namespace to hold that application crate.
         if self.get_crate(&crate_name).is_some() {
-            return Err("the crate has already been loaded, cannot load it again in the same namespace");
+            return Err(LoadError::CrateAlreadyLoaded(crate_name.to_string()));
         }
 
         // It's probably better to pass in the actual crate file reference so we can use it here,
         // but since we don't currently do that, we just get another reference to the crate object file via its Path.
         let crate_object_file = match Path::get_absolute(&abs_path) {
             Some(FileOrDir::File(f)) => f,
-            _ => return Err("BUG: load_crate_sections(): couldn't get crate object file path"),
+            _ => return Err(LoadError::Other("BUG: load_crate_sections(): couldn't get crate object file path")),
         };
 
         // Parse the crate file as an ELF file
@@ -1082,9 +1576,23 @@ impl CrateNamespace {
         let typ = elf_file.header.pt2.type_().as_type();
         if typ != Type::Relocatable {
             error!("load_crate_sections(): crate \"{}\" was a {:?} Elf File, must be Relocatable!", &crate_name, typ);
-            return Err("not a relocatable elf file");
+            return Err(LoadError::InvalidFile("not a relocatable elf file"));
         }
 
+        // Check that elf_file was built for this architecture, as its relocations are architecture-specific.
+        // The `e_machine` field immediately follows the 16-byte `e_ident` and the 2-byte `e_type` fields.
+        let machine = byte_slice.get(18..20).map(|bytes| u16::from_ne_bytes([bytes[0], bytes[1]]));
+        if machine != Some(crate_metadata::ELF_MACHINE) {
+            error!("load_crate_sections(): crate \"{}\" was built for ELF machine type {:?}, expected {}", &crate_name, machine, crate_metadata::ELF_MACHINE);
+            return Err(LoadError::InvalidFile("elf file was built for a different architecture"));
+        }
+
+        // Verify the crate's signature before any of its sections are mapped, let alone made executable.
+        signature::verify(self.signature_policy, crate_file, byte_slice, &elf_file).map_err(|e| {
+            error!("load_crate_sections(): crate \"{}\" failed signature verification: {}", &crate_name, e);
+            e
+        })?;
+
         // If a `.theseus_merged` section exists (it should come before any .text section),
         // then the object file's sections have been merged by a partial relinking step.
         // If so, then we can use a much faster version of loading/linking.
@@ -1126,6 +1634,9 @@ impl CrateNamespace {
             tls_sections:            BTreeSet::new(),
             cls_sections:            BTreeSet::new(),
             data_sections:           BTreeSet::new(),
+            eh_frame:                None,
+            init_array:              Vec::new(),
+            fini_array:              Vec::new(),
             reexported_symbols:      BTreeSet::new(),
         });
         let new_crate_weak_ref = CowArc::downgrade(&new_crate);
@@ -1155,6 +1666,8 @@ impl CrateNamespace {
         {
             let mut new_crate_mut = new_crate.lock_as_mut()
                 .ok_or("BUG: load_crate_sections(): couldn't get exclusive mutable access to new_crate")?;
+            new_crate_mut.eh_frame        = find_eh_frame(&loaded_sections);
+            (new_crate_mut.init_array, new_crate_mut.fini_array) = init_fini::find_init_fini_arrays(&elf_file, &loaded_sections);
             new_crate_mut.sections        = loaded_sections;
             new_crate_mut.global_sections = global_sections;
             new_crate_mut.tls_sections    = tls_sections;
@@ -1186,7 +1699,7 @@ impl CrateNamespace {
         text_pages:   Option<(Arc<Mutex<MappedPages>>, Range<VirtualAddress>)>,
         rodata_pages: Option<(Arc<Mutex<MappedPages>>, Range<VirtualAddress>)>,
         data_pages:   Option<(Arc<Mutex<MappedPages>>, Range<VirtualAddress>)>,
-    ) -> Result<SectionMetadata, &'static str> {
+    ) -> Result<SectionMetadata, LoadError> {
 
         let mut text_pages_locked       = text_pages  .as_ref().map(|(tp, tp_range)| (tp.clone(), tp.lock(), tp_range.start));
         let mut read_only_pages_locked  = rodata_pages.as_ref().map(|(rp, rp_range)| (rp.clone(), rp.lock(), rp_range.start));
@@ -1257,12 +1770,15 @@ impl CrateNamespace {
                 mapped_pages_offset = 0;
                 (mapped_pages_ref, mapped_pages, virt_addr) = text_pages_locked.as_mut()
                     .map(|(tp_ref, tp, tp_start_vaddr)| (tp_ref, tp, *tp_start_vaddr + mapped_pages_offset))
-                    .ok_or("BUG: ELF file contained a .text section, but no text_pages were allocated")?;
+                    .ok_or(LoadError::MappingFailed("BUG: ELF file contained a .text section, but no text_pages were allocated"))?;
             }
 
-            // Otherwise, if writable (excluding TLS and CLS), copy the .data/.bss section into `data_pages`.
+            // Otherwise, if writable (excluding TLS and CLS), copy the .data/.bss section into `data_pages`,
+            // along with any .init_array/.fini_array sections, which are just arrays of function pointers.
             else if is_write && !is_tls && !is_cls {
                 match sec.get_type() {
+                    Ok(ShType::InitArray) => typ = SectionType::InitArray,
+                    Ok(ShType::FiniArray) => typ = SectionType::FiniArray,
                     Ok(ShType::ProgBits) => {
                         typ = SectionType::Data;
                         data_shndx.get_or_insert(shndx);
@@ -1273,7 +1789,7 @@ impl CrateNamespace {
                     }
                     _other => {
                         error!("BUG: writable section was neither PROGBITS (.data) nor NOBITS (.bss): type: {:?}, {:X?}", _other, sec);
-                        return Err("BUG: writable section was neither PROGBITS (.data) nor NOBITS (.bss)");
+                        return Err(LoadError::InvalidSection("BUG: writable section was neither PROGBITS (.data) nor NOBITS (.bss)"));
                     }
                 };
 
@@ -1281,8 +1797,10 @@ impl CrateNamespace {
                 mapped_pages_offset = sec_offset - *starting_offset_of_data;
                 (mapped_pages_ref, mapped_pages, virt_addr) = read_write_pages_locked.as_mut()
                     .map(|(dp_ref, dp, dp_start_vaddr)| (dp_ref, dp, *dp_start_vaddr + mapped_pages_offset))
-                    .ok_or("BUG: ELF file contained a .data/.bss section, but no data_pages were allocated")?;
-                data_sections.insert(shndx);
+                    .ok_or(LoadError::MappingFailed("BUG: ELF file contained a .data/.bss section, but no data_pages were allocated"))?;
+                if typ.is_data_or_bss() {
+                    data_sections.insert(shndx);
+                }
             }
 
             // Otherwise, if TLS section, copy its data into `rodata_pages`.
@@ -1304,13 +1822,13 @@ impl CrateNamespace {
                     }
                     _other => {
                         error!("BUG: TLS section was neither PROGBITS (.tdata) nor NOBITS (.tbss): type: {:?}, {:X?}", _other, sec);
-                        return Err("BUG: TLS section was neither PROGBITS (.tdata) nor NOBITS (.tbss)");
+                        return Err(LoadError::InvalidSection("BUG: TLS section was neither PROGBITS (.tdata) nor NOBITS (.tbss)"));
                     }
                 };
 
                 (mapped_pages_ref, mapped_pages) = read_only_pages_locked.as_mut()
                     .map(|(rp_ref, rp, _)| (rp_ref, rp))
-                    .ok_or("BUG: ELF file contained a .tdata/.tbss section, but no rodata_pages were allocated")?;
+                    .ok_or(LoadError::MappingFailed("BUG: ELF file contained a .tdata/.tbss section, but no rodata_pages were allocated"))?;
                 // Use a placeholder vaddr; it will be replaced in `add_new_dynamic_section()` below.
                 virt_addr = VirtualAddress::zero();
                 tls_sections.insert(shndx);
@@ -1323,14 +1841,14 @@ impl CrateNamespace {
 
                         (mapped_pages_ref, mapped_pages) = read_only_pages_locked.as_mut()
                             .map(|(rp_ref, rp, _)| (rp_ref, rp))
-                            .ok_or("BUG: ELF file contained a .cls section, but no rodata_pages were allocated")?;
+                            .ok_or(LoadError::MappingFailed("BUG: ELF file contained a .cls section, but no rodata_pages were allocated"))?;
                         // Use a placeholder vaddr; it will be replaced in `add_new_dynamic_section()` below.
                         virt_addr = VirtualAddress::zero();
                         cls_sections.insert(shndx);
                     }
                     ty => {
                         error!("CLS section had incorrect type: {ty:?}");
-                        return Err("CLS section had incorrect type");
+                        return Err(LoadError::InvalidSection("CLS section had incorrect type"));
                     },
                 }
             }
@@ -1344,7 +1862,7 @@ impl CrateNamespace {
                     Ok(_other)                        => { /* fall through to next `else if` block */ }
                     Err(_e)                           => {
                         error!("BUG: Error: {:?}, couldn't get section name for {:?}", _e, sec);
-                        return Err("BUG: couldn't get section name");
+                        return Err(LoadError::InvalidSection("BUG: couldn't get section name"));
                     }
                 }
                 is_rodata || is_eh_frame || is_gcc_except_table
@@ -1364,7 +1882,7 @@ impl CrateNamespace {
                 mapped_pages_offset = sec_offset - *read_only_start;
                 (mapped_pages_ref, mapped_pages, virt_addr) = read_only_pages_locked.as_mut()
                     .map(|(rp_ref, rp, rp_start_vaddr)| (rp_ref, rp, *rp_start_vaddr + mapped_pages_offset))
-                    .ok_or("BUG: ELF file contained a read-only section, but no rodata_pages were allocated")?;
+                    .ok_or(LoadError::MappingFailed("BUG: ELF file contained a read-only section, but no rodata_pages were allocated"))?;
             }
 
             // Finally, any other section type is considered unhandled, so return an error!
@@ -1375,7 +1893,7 @@ impl CrateNamespace {
                     continue;
                 }
                 error!("unhandled sec, name: {:?}, {:X?}", sec_name, sec);
-                return Err("load_crate_with_merged_sections(): section with unhandled type, name, or flags!");
+                return Err(LoadError::InvalidSection("load_crate_with_merged_sections(): section with unhandled type, name, or flags!"));
             }
 
             // Actually copy the section data from the ELF file to the given destination MappedPages.
@@ -1387,7 +1905,7 @@ impl CrateNamespace {
                     Ok(SectionData::Empty) => dest_slice.fill(0),
                     _other => {
                         error!("Couldn't get section data for merged section: {:?}", _other);
-                        return Err("couldn't get section data for merged section");
+                        return Err(LoadError::InvalidSection("couldn't get section data for merged section"));
                     }
                 }
             }
@@ -1411,7 +1929,7 @@ impl CrateNamespace {
                 // which is used for relocation entries that ask for a section's offset from the TLS base.
                 let (_tls_offset, new_tls_section) = self.tls_initializer.lock()
                     .add_new_dynamic_section(new_section, sec.align() as usize)
-                    .map_err(|_| "Failed to add new dynamic TLS section")?;
+                    .map_err(|_| LoadError::MappingFailed("Failed to add new dynamic TLS section"))?;
 
                 // trace!("Updated new TLS section to have offset {:#X}: {:?}", _tls_offset, new_tls_section);
                 if new_tls_section.typ == SectionType::TlsData {
@@ -1422,7 +1940,7 @@ impl CrateNamespace {
                 new_tls_section
             } else if is_cls {
                 let (_, new_cls_section) = cls_allocator::add_dynamic_section(new_section, sec.align() as usize)
-                    .map_err(|_| "Failed to add new dynamic CLS section")?;
+                    .map_err(|_| LoadError::MappingFailed("Failed to add new dynamic CLS section"))?;
                 cls_shndx_and_section = Some((shndx, Arc::clone(&new_cls_section)));
                 new_cls_section
             } else {
@@ -1464,10 +1982,13 @@ impl CrateNamespace {
                 error!("BUG: Error: {:?}, couldn't get symtab entry binding: {}", _e, symbol_entry as &dyn Entry);
                 "BUG: couldn't get symtab entry binding"
             })?;
-            let is_global = sec_binding == Binding::Global;
+            // Weak symbols are visible to other crates just like global ones,
+            // but are overridden by any global symbol with the same name.
+            let is_weak = sec_binding == Binding::Weak;
+            let is_global = sec_binding == Binding::Global || is_weak;
             let is_tls = sec_type == Type::Tls;
             let is_cls = sec_type == Type::OsSpecific(CLS_SYMBOL_TYPE);
-            let demangled = demangle(sec_name).to_string().as_str().into();
+            let demangled = demangle_symbol(sec_name).as_str().into();
 
             // Declare the items we need to create a new `LoadedSection`.
             let typ: SectionType;
@@ -1479,7 +2000,7 @@ impl CrateNamespace {
             if sec_type == Type::Func {
                 let (tp_ref, tp_start_vaddr) = text_pages_locked.as_ref()
                     .map(|(mp_arc, _, mp_vaddr)| (mp_arc, *mp_vaddr))
-                    .ok_or("BUG: found FUNC symbol but no text_pages were allocated")?;
+                    .ok_or(LoadError::MappingFailed("BUG: found FUNC symbol but no text_pages were allocated"))?;
 
                 typ = SectionType::Text;
                 mapped_pages = tp_ref;
@@ -1495,7 +2016,7 @@ impl CrateNamespace {
                 if Some(sym_shndx) == rodata_shndx {
                     let (rp_ref, rp_start_vaddr) = read_only_pages_locked.as_ref()
                         .map(|(mp_arc, _, mp_vaddr)| (mp_arc, *mp_vaddr))
-                        .ok_or("BUG: found OBJECT symbol in .rodata but no rodata_pages were allocated")?;
+                        .ok_or(LoadError::MappingFailed("BUG: found OBJECT symbol in .rodata but no rodata_pages were allocated"))?;
 
                     typ = SectionType::Rodata;
                     mapped_pages = rp_ref;
@@ -1509,7 +2030,7 @@ impl CrateNamespace {
 
                     let (dp_ref, dp_start_vaddr) = read_write_pages_locked.as_ref()
                         .map(|(mp_arc, _, mp_vaddr)| (mp_arc, *mp_vaddr))
-                        .ok_or("BUG: found OBJECT symbol in .data/.bss but no data_pages were allocated")?;
+                        .ok_or(LoadError::MappingFailed("BUG: found OBJECT symbol in .data/.bss but no data_pages were allocated"))?;
                     let read_write_start = read_write_offset.ok_or("BUG: found OBJECT symbol in .data/.bss but `data_offset` was unknown")?;
 
                     if Some(sym_shndx) == data_shndx {
@@ -1521,7 +2042,7 @@ impl CrateNamespace {
                         mapped_pages_offset = sec_value + (bss_offset - read_write_start);
                     } else {
                         error!("BUG: found OBJECT symbol with an shndx that wasn't in .rodata, .data, or .bss: {}", symbol_entry as &dyn Entry);
-                        return Err("BUG: found OBJECT symbol with an shndx that wasn't in .rodata, .data, or .bss");
+                        return Err(LoadError::Other("BUG: found OBJECT symbol with an shndx that wasn't in .rodata, .data, or .bss"));
                     };
                     mapped_pages = dp_ref;
                     virt_addr = dp_start_vaddr + mapped_pages_offset;
@@ -1543,7 +2064,7 @@ impl CrateNamespace {
                 // the symbol's value (`sec_value`) to that of the corresponding merged section.
                 let rp_ref = read_only_pages_locked.as_ref()
                     .map(|(mp_arc, ..)| mp_arc)
-                    .ok_or("BUG: found TLS symbol but no rodata_pages were allocated")?;
+                    .ok_or(LoadError::MappingFailed("BUG: found TLS symbol but no rodata_pages were allocated"))?;
 
                 if let Some((tdata_shndx, ref tdata_sec)) = tdata_shndx_and_section && sym_shndx == tdata_shndx {
                     typ = SectionType::TlsData;
@@ -1557,7 +2078,7 @@ impl CrateNamespace {
                     virt_addr = tbss_sec.virt_addr + sec_value;
                 } else {
                     error!("BUG: found TLS symbol with an shndx that wasn't in .tdata or .tbss: {}", symbol_entry as &dyn Entry);
-                    return Err("BUG: found TLS symbol with an shndx that wasn't in .tdata or .tbss");
+                    return Err(LoadError::Other("BUG: found TLS symbol with an shndx that wasn't in .tdata or .tbss"));
                 };
                 mapped_pages = rp_ref;
             }
@@ -1566,7 +2087,7 @@ impl CrateNamespace {
                 let sym_shndx = symbol_entry.shndx() as Shndx;
                 let rp_ref = read_only_pages_locked.as_ref()
                     .map(|(mp_arc, ..)| mp_arc)
-                    .ok_or("BUG: found CLS symbol but no rodata_pages were allocated")?;
+                    .ok_or(LoadError::MappingFailed("BUG: found CLS symbol but no rodata_pages were allocated"))?;
 
                 if let Some((cls_shndx, ref cls_sec)) = cls_shndx_and_section && sym_shndx == cls_shndx {
                     typ = SectionType::Cls;
@@ -1574,14 +2095,14 @@ impl CrateNamespace {
                     virt_addr = cls_sec.virt_addr + sec_value;
                 } else {
                     error!("BUG: found CLS symbol with an shndx that wasn't in .cls: {}", symbol_entry as &dyn Entry);
-                    return Err("BUG: found CLS symbol with an shndx that wasn't in .cls");
+                    return Err(LoadError::Other("BUG: found CLS symbol with an shndx that wasn't in .cls"));
                 };
                 mapped_pages = rp_ref;
             }
 
             else {
                 error!("Found unexpected symbol type: {}", symbol_entry as &dyn Entry);
-                return Err("Found unexpected symbol type");
+                return Err(LoadError::Other("Found unexpected symbol type"));
             }
 
             // Create the new `LoadedSection`
@@ -1596,7 +2117,7 @@ impl CrateNamespace {
                     sec_size,
                     is_global,
                     new_crate.clone(),
-                ))
+                ).with_weak_binding(is_weak))
             );
 
             if is_global {
@@ -1669,30 +2190,42 @@ impl CrateNamespace {
         text_pages:   Option<(Arc<Mutex<MappedPages>>, Range<VirtualAddress>)>,
         rodata_pages: Option<(Arc<Mutex<MappedPages>>, Range<VirtualAddress>)>,
         data_pages:   Option<(Arc<Mutex<MappedPages>>, Range<VirtualAddress>)>,
-    ) -> Result<SectionMetadata, &'static str> {
+    ) -> Result<SectionMetadata, LoadError> {
 
-        // Check the symbol table to get the set of sections that are global (publicly visible).
-        let global_sections: BTreeSet<Shndx> = {
+        // Check the symbol table to get the set of sections that are global (publicly visible),
+        // and the subset of those that are weak, i.e., can be overridden by a global symbol.
+        let (global_sections, weak_sections): (BTreeSet<Shndx>, BTreeSet<Shndx>) = {
             // For us to properly load the ELF file, it must NOT have been fully stripped,
             // meaning that it must still have its symbol table section. Otherwise, relocations will not work.
             let symtab = find_symbol_table(elf_file)?;
 
             let mut globals: BTreeSet<Shndx> = BTreeSet::new();
+            let mut weaks: BTreeSet<Shndx> = BTreeSet::new();
             use xmas_elf::symbol_table::Entry;
             for entry in symtab.iter() {
-                // Include all symbols with "GLOBAL" binding, regardless of visibility.  
-                if entry.get_binding() == Ok(xmas_elf::symbol_table::Binding::Global) {
-                    match entry.get_type() {
-                        Ok(xmas_elf::symbol_table::Type::Func
-                            | xmas_elf::symbol_table::Type::Object
-                            | xmas_elf::symbol_table::Type::Tls) => {
-                            globals.insert(entry.shndx() as Shndx);
+                // Include all symbols with "GLOBAL" or "WEAK" binding, regardless of visibility.  
+                let is_weak = match entry.get_binding() {
+                    Ok(Binding::Global) => false,
+                    Ok(Binding::Weak) => true,
+                    _ => continue,
+                };
+                // Undefined weak symbols are references to other crates, not definitions.
+                if entry.shndx() == SHN_UNDEF {
+                    continue;
+                }
+                match entry.get_type() {
+                    Ok(xmas_elf::symbol_table::Type::Func
+                        | xmas_elf::symbol_table::Type::Object
+                        | xmas_elf::symbol_table::Type::Tls) => {
+                        globals.insert(entry.shndx() as Shndx);
+                        if is_weak {
+                            weaks.insert(entry.shndx() as Shndx);
                         }
-                        _ => continue,
                     }
+                    _ => continue,
                 }
             }
-            globals
+            (globals, weaks)
         };
 
         // Since .text sections come at the beginning of the object file,
@@ -1711,7 +2244,7 @@ impl CrateNamespace {
             let text_size = tp_range.end.value() - tp_range.start.value();
             let mut tp_locked = tp.lock();
             let text_destination: &mut [u8] = tp_locked.as_slice_mut(0, text_size)?;
-            let text_source = elf_file.input.get(..text_size).ok_or("BUG: end of last .text section was miscalculated to be beyond ELF file bounds")?;
+            let text_source = elf_file.input.get(..text_size).ok_or(LoadError::InvalidFile("BUG: end of last .text section was miscalculated to be beyond ELF file bounds"))?;
             text_destination.copy_from_slice(text_source);
         }
 
@@ -1725,6 +2258,10 @@ impl CrateNamespace {
         const RODATA_PREFIX:           &str = ".rodata.";
         const DATA_PREFIX:             &str = ".data.";
         const BSS_PREFIX:              &str = ".bss.";
+        // RISC-V's small data sections, which are loaded just like their regular counterparts.
+        const SMALL_RODATA_PREFIX:     &str = ".srodata.";
+        const SMALL_DATA_PREFIX:       &str = ".sdata.";
+        const SMALL_BSS_PREFIX:        &str = ".sbss.";
         const CLS_PREFIX:              &str = ".cls.";
         const TLS_DATA_PREFIX:         &str = ".tdata.";
         const TLS_BSS_PREFIX:          &str = ".tbss.";
@@ -1749,10 +2286,13 @@ impl CrateNamespace {
                 } else {
                     // Ignore special "empty" placeholder sections
                     match $sec_name {
-                        ".text"   => continue,
-                        ".rodata" => continue,
-                        ".data"   => continue,
-                        ".bss"    => continue,
+                        ".text"    => continue,
+                        ".rodata"  => continue,
+                        ".data"    => continue,
+                        ".bss"     => continue,
+                        ".srodata" => continue,
+                        ".sdata"   => continue,
+                        ".sbss"    => continue,
                         _ => {
                             const ERROR_STR: &'static str = const_format::concatcp!(
                                 "Failed to get the ", $prefix, 
@@ -1792,7 +2332,7 @@ impl CrateNamespace {
                 Ok(name) => name,
                 Err(_e) => {
                     error!("Couldn't get section name for section [{}]: {:?}\n    error: {}", shndx, sec, _e);
-                    return Err("couldn't get section name");
+                    return Err(LoadError::InvalidSection("couldn't get section name"));
                 }
             };
 
@@ -1815,7 +2355,7 @@ impl CrateNamespace {
                     }
                     _ => {
                         error!("Couldn't get next section for zero-sized section {}", shndx);
-                        return Err("couldn't get next section for a zero-sized section");
+                        return Err(LoadError::InvalidSection("couldn't get next section for a zero-sized section"));
                     }
                 }
             } else {
@@ -1848,7 +2388,7 @@ impl CrateNamespace {
                 } else {
                     name
                 };
-                let demangled = demangle(name).to_string().as_str().into();
+                let demangled = demangle_symbol(name).as_str().into();
 
                 // We already copied the content of all .text sections above, 
                 // so here we just record the metadata into a new `LoadedSection` object.
@@ -1867,11 +2407,11 @@ impl CrateNamespace {
                             sec_size,
                             is_global,
                             new_crate.clone(),
-                        ))
+                        ).with_weak_binding(weak_sections.contains(&shndx)))
                     );
                 }
                 else {
-                    return Err("BUG: ELF file contained a .text* section, but no text_pages were allocated");
+                    return Err(LoadError::MappingFailed("BUG: ELF file contained a .text* section, but no text_pages were allocated"));
                 }
             }
 
@@ -1887,7 +2427,7 @@ impl CrateNamespace {
                 } else {
                     try_get_symbol_name_after_prefix!(sec_name, TLS_DATA_PREFIX)
                 };
-                let demangled = demangle(name).to_string().as_str().into();
+                let demangled = demangle_symbol(name).as_str().into();
 
                 if let Some((ref rp_ref, ref mut rp)) = read_only_pages_locked {
                     let (mapped_pages_offset, sec_typ) = if is_bss {
@@ -1901,7 +2441,7 @@ impl CrateNamespace {
                             Ok(SectionData::Undefined(sec_data)) => dest_slice.copy_from_slice(sec_data),
                             _other => {
                                 error!("load_crate_sections(): Couldn't get section data for TLS .tdata section [{}] {}: {:?}", shndx, sec_name, _other);
-                                return Err("couldn't get section data in TLS .tdata section");
+                                return Err(LoadError::InvalidSection("couldn't get section data in TLS .tdata section"));
                             }
                         };
                         // As with all other normal sections, use the current offset for these read-only pages.
@@ -1926,7 +2466,7 @@ impl CrateNamespace {
                     // which is used for relocation entries that ask for a section's offset from the TLS base.
                     let (_tls_offset, new_tls_section) = self.tls_initializer.lock()
                         .add_new_dynamic_section(new_tls_section, sec_align)
-                        .map_err(|_| "Failed to add new TLS section")?;
+                        .map_err(|_| LoadError::MappingFailed("Failed to add new TLS section"))?;
 
                     // trace!("\t --> updated new TLS section: {:?}", new_tls_section);
                     loaded_sections.insert(shndx, new_tls_section);
@@ -1935,17 +2475,17 @@ impl CrateNamespace {
                     rodata_offset += sec_size.next_multiple_of(sec_align);
                 }
                 else {
-                    return Err("no rodata_pages were allocated when handling TLS section");
+                    return Err(LoadError::MappingFailed("no rodata_pages were allocated when handling TLS section"));
                 }
             }
 
             else if is_cls {
                 if sec.get_type() != Ok(ShType::ProgBits) {
-                    return Err("CLS section had wrong type");
+                    return Err(LoadError::InvalidSection("CLS section had wrong type"));
                 }
 
                 let name = try_get_symbol_name_after_prefix!(sec_name, CLS_PREFIX);
-                let demangled = demangle(name).to_string().as_str().into();
+                let demangled = demangle_symbol(name).as_str().into();
 
                 if let Some((ref rp_ref, ref mut rp)) = read_only_pages_locked {
                     let (mapped_pages_offset, sec_typ) = {
@@ -1955,7 +2495,7 @@ impl CrateNamespace {
                             Ok(SectionData::Undefined(sec_data)) => dest_slice.copy_from_slice(sec_data),
                             _other => {
                                 error!("load_crate_sections(): Couldn't get section data for CLS .cls section [{}] {}: {:?}", shndx, sec_name, _other);
-                                return Err("couldn't get section data in CLS .cls section");
+                                return Err(LoadError::InvalidSection("couldn't get section data in CLS .cls section"));
                             }
                         };
                         // As with all other normal sections, use the current offset for these read-only pages.
@@ -1973,7 +2513,7 @@ impl CrateNamespace {
                         new_crate.clone(),
                     );
                     let (_, new_cls_section) = cls_allocator::add_dynamic_section(new_cls_section, sec_align)
-                        .map_err(|_| "Failed to add new CLS section")?;
+                        .map_err(|_| LoadError::MappingFailed("Failed to add new CLS section"))?;
 
                     loaded_sections.insert(shndx, new_cls_section);
                     cls_sections.insert(shndx);
@@ -1981,7 +2521,48 @@ impl CrateNamespace {
                     rodata_offset += sec_size.next_multiple_of(sec_align);
                 }
                 else {
-                    return Err("no rodata_pages were allocated when handling TLS section");
+                    return Err(LoadError::MappingFailed("no rodata_pages were allocated when handling TLS section"));
+                }
+            }
+
+            // Before handling other writable sections, handle .init_array/.fini_array sections,
+            // which are loaded like .data sections but are never globally visible.
+            else if is_write && matches!(sec.get_type(), Ok(ShType::InitArray | ShType::FiniArray)) {
+                let typ = if sec.get_type() == Ok(ShType::InitArray) {
+                    SectionType::InitArray
+                } else {
+                    SectionType::FiniArray
+                };
+                if let Some((ref dp_ref, ref mut dp)) = read_write_pages_locked {
+                    let dest_vaddr = dp.address_at_offset(data_offset)
+                        .ok_or(LoadError::MappingFailed("BUG: data_offset wasn't within data_pages"))?;
+                    let dest_slice: &mut [u8] = dp.as_slice_mut(data_offset, sec_size)?;
+                    match sec.get_data(elf_file) {
+                        Ok(SectionData::Undefined(sec_data)) => dest_slice.copy_from_slice(sec_data),
+                        _other => {
+                            error!("load_crate_sections(): Couldn't get section data for {} section [{}] {}: {:?}", typ.name(), shndx, sec_name, _other);
+                            return Err(LoadError::InvalidSection("couldn't get section data in .init_array/.fini_array section"));
+                        }
+                    }
+
+                    loaded_sections.insert(
+                        shndx,
+                        Arc::new(LoadedSection::new(
+                            typ,
+                            section_name_str_ref(&typ),
+                            Arc::clone(dp_ref),
+                            data_offset,
+                            dest_vaddr,
+                            sec_size,
+                            false, // .init_array/.fini_array sections are never globally visible
+                            new_crate.clone(),
+                        ))
+                    );
+
+                    data_offset += sec_size.next_multiple_of(sec_align);
+                }
+                else {
+                    return Err(LoadError::MappingFailed("no data_pages were allocated for .init_array/.fini_array section"));
                 }
             }
 
@@ -1989,8 +2570,12 @@ impl CrateNamespace {
             else if is_write {
                 // check if this section is .bss or .data
                 let is_bss = sec.get_type() == Ok(ShType::NoBits);
-                let name = if is_bss {
+                let name = if is_bss && sec_name.starts_with(SMALL_BSS_PREFIX) {
+                    try_get_symbol_name_after_prefix!(sec_name, SMALL_BSS_PREFIX)
+                } else if is_bss {
                     try_get_symbol_name_after_prefix!(sec_name, BSS_PREFIX)
+                } else if sec_name.starts_with(SMALL_DATA_PREFIX) {
+                    try_get_symbol_name_after_prefix!(sec_name, SMALL_DATA_PREFIX)
                 } else {
                     try_get_symbol_name_after_prefix!(sec_name, DATA_PREFIX)
                         // Currently, .rel.ro sections no longer exist in object files compiled for Theseus.
@@ -2002,19 +2587,19 @@ impl CrateNamespace {
                         //     }
                         // })
                 };
-                let demangled = demangle(name).to_string().as_str().into();
+                let demangled = demangle_symbol(name).as_str().into();
 
                 if let Some((ref dp_ref, ref mut dp)) = read_write_pages_locked {
                     // here: we're ready to copy the data/bss section to the proper address
                     let dest_vaddr = dp.address_at_offset(data_offset)
-                        .ok_or("BUG: data_offset wasn't within data_pages")?;
+                        .ok_or(LoadError::MappingFailed("BUG: data_offset wasn't within data_pages"))?;
                     let dest_slice: &mut [u8] = dp.as_slice_mut(data_offset, sec_size)?;
                     match sec.get_data(elf_file) {
                         Ok(SectionData::Undefined(sec_data)) => dest_slice.copy_from_slice(sec_data),
                         Ok(SectionData::Empty) => dest_slice.fill(0),
                         _other => {
                             error!("load_crate_sections(): Couldn't get section data for .data section [{}] {}: {:?}", shndx, sec_name, _other);
-                            return Err("couldn't get section data in .data section");
+                            return Err(LoadError::InvalidSection("couldn't get section data in .data section"));
                         }
                     }
 
@@ -2029,33 +2614,37 @@ impl CrateNamespace {
                             sec_size,
                             global_sections.contains(&shndx),
                             new_crate.clone(),
-                        ))
+                        ).with_weak_binding(weak_sections.contains(&shndx)))
                     );
                     data_sections.insert(shndx);
 
                     data_offset += sec_size.next_multiple_of(sec_align);
                 }
                 else {
-                    return Err("no data_pages were allocated for .data/.bss section");
+                    return Err(LoadError::MappingFailed("no data_pages were allocated for .data/.bss section"));
                 }
             }
 
             // Fourth, if neither executable nor TLS nor writable, handle .rodata sections.
-            else if sec_name.starts_with(RODATA_PREFIX) {
-                let name = try_get_symbol_name_after_prefix!(sec_name, RODATA_PREFIX);
-                let demangled = demangle(name).to_string().as_str().into();
+            else if sec_name.starts_with(RODATA_PREFIX) || sec_name.starts_with(SMALL_RODATA_PREFIX) {
+                let name = if sec_name.starts_with(SMALL_RODATA_PREFIX) {
+                    try_get_symbol_name_after_prefix!(sec_name, SMALL_RODATA_PREFIX)
+                } else {
+                    try_get_symbol_name_after_prefix!(sec_name, RODATA_PREFIX)
+                };
+                let demangled = demangle_symbol(name).as_str().into();
 
                 if let Some((ref rp_ref, ref mut rp)) = read_only_pages_locked {
                     // here: we're ready to copy the rodata section to the proper address
                     let dest_vaddr = rp.address_at_offset(rodata_offset)
-                        .ok_or("BUG: rodata_offset wasn't within rodata_mapped_pages")?;
+                        .ok_or(LoadError::MappingFailed("BUG: rodata_offset wasn't within rodata_mapped_pages"))?;
                     let dest_slice: &mut [u8] = rp.as_slice_mut(rodata_offset, sec_size)?;
                     match sec.get_data(elf_file) {
                         Ok(SectionData::Undefined(sec_data)) => dest_slice.copy_from_slice(sec_data),
                         Ok(SectionData::Empty) => dest_slice.fill(0),
                         _other => {
                             error!("load_crate_sections(): Couldn't get section data for .rodata section [{}] {}: {:?}", shndx, sec_name, _other);
-                            return Err("couldn't get section data in .rodata section");
+                            return Err(LoadError::InvalidSection("couldn't get section data in .rodata section"));
                         }
                     }
 
@@ -2070,13 +2659,13 @@ impl CrateNamespace {
                             sec_size,
                             global_sections.contains(&shndx),
                             new_crate.clone(),
-                        ))
+                        ).with_weak_binding(weak_sections.contains(&shndx)))
                     );
 
                     rodata_offset += sec_size.next_multiple_of(sec_align);
                 }
                 else {
-                    return Err("no rodata_pages were allocated when handling .rodata section");
+                    return Err(LoadError::MappingFailed("no rodata_pages were allocated when handling .rodata section"));
                 }
             }
 
@@ -2092,14 +2681,14 @@ impl CrateNamespace {
                 if let Some((ref rp_ref, ref mut rp)) = read_only_pages_locked {
                     // here: we're ready to copy the rodata section to the proper address
                     let dest_vaddr = rp.address_at_offset(rodata_offset)
-                        .ok_or("BUG: rodata_offset wasn't within rodata_mapped_pages")?;
+                        .ok_or(LoadError::MappingFailed("BUG: rodata_offset wasn't within rodata_mapped_pages"))?;
                     let dest_slice: &mut [u8]  = rp.as_slice_mut(rodata_offset, sec_size)?;
                     match sec.get_data(elf_file) {
                         Ok(SectionData::Undefined(sec_data)) => dest_slice.copy_from_slice(sec_data),
                         Ok(SectionData::Empty) => dest_slice.fill(0),
                         _other => {
                             error!("load_crate_sections(): Couldn't get section data for .gcc_except_table section [{}] {}: {:?}", shndx, sec_name, _other);
-                            return Err("couldn't get section data in .gcc_except_table section");
+                            return Err(LoadError::InvalidSection("couldn't get section data in .gcc_except_table section"));
                         }
                     }
 
@@ -2121,7 +2710,7 @@ impl CrateNamespace {
                     rodata_offset += sec_size.next_multiple_of(sec_align);
                 }
                 else {
-                    return Err("no rodata_pages were allocated when handling .gcc_except_table");
+                    return Err(LoadError::MappingFailed("no rodata_pages were allocated when handling .gcc_except_table"));
                 }
             }
 
@@ -2131,14 +2720,14 @@ impl CrateNamespace {
                 if let Some((ref rp_ref, ref mut rp)) = read_only_pages_locked {
                     // here: we're ready to copy the rodata section to the proper address
                     let dest_vaddr = rp.address_at_offset(rodata_offset)
-                        .ok_or("BUG: rodata_offset wasn't within rodata_mapped_pages")?;
+                        .ok_or(LoadError::MappingFailed("BUG: rodata_offset wasn't within rodata_mapped_pages"))?;
                     let dest_slice: &mut [u8]  = rp.as_slice_mut(rodata_offset, sec_size)?;
                     match sec.get_data(elf_file) {
                         Ok(SectionData::Undefined(sec_data)) => dest_slice.copy_from_slice(sec_data),
                         Ok(SectionData::Empty) => dest_slice.fill(0),
                         _other => {
                             error!("load_crate_sections(): Couldn't get section data for .eh_frame section [{}] {}: {:?}", shndx, sec_name, _other);
-                            return Err("couldn't get section data in .eh_frame section");
+                            return Err(LoadError::InvalidSection("couldn't get section data in .eh_frame section"));
                         }
                     }
 
@@ -2160,7 +2749,7 @@ impl CrateNamespace {
                     rodata_offset += sec_size.next_multiple_of(sec_align);
                 }
                 else {
-                    return Err("no rodata_pages were allocated when handling .eh_frame");
+                    return Err(LoadError::MappingFailed("no rodata_pages were allocated when handling .eh_frame"));
                 }
             }
 
@@ -2171,7 +2760,7 @@ impl CrateNamespace {
                     continue;
                 }
                 error!("unhandled section [{}], name: {}, sec: {:?}", shndx, sec_name, sec);
-                return Err("load_crate_sections(): section with unhandled type, name, or flags!");
+                return Err(LoadError::InvalidSection("load_crate_sections(): section with unhandled type, name, or flags!"));
             }
         }
 
@@ -2195,7 +2784,7 @@ impl CrateNamespace {
         temp_backup_namespace: Option<&CrateNamespace>,
         kernel_mmi_ref: &MmiRef,
         verbose_log: bool
-    ) -> Result<(), &'static str> {
+    ) -> Result<(), LoadError> {
         let mut new_crate = new_crate_ref.lock_as_mut()
             .ok_or("BUG: perform_relocations(): couldn't get exclusive mutable access to new_crate")?;
         if verbose_log { debug!("=========== moving on to the relocations for crate {} =========", new_crate.crate_name); }
@@ -2221,7 +2810,7 @@ impl CrateNamespace {
                 Ok(Rela64(rela_arr)) => rela_arr,
                 _ => {
                     error!("Found Rela section that wasn't able to be parsed as Rela64: {:?}", sec);
-                    return Err("Found Rela section that wasn't able to be parsed as Rela64");
+                    return Err(LoadError::InvalidSection("Found Rela section that wasn't able to be parsed as Rela64"));
                 }
             };
 
@@ -2249,6 +2838,14 @@ impl CrateNamespace {
                     target_sec.mapped_pages_offset + target_sec.size,
                 )?;
 
+                // On RISC-V, the relocation for the low part of a PC-relative value refers to a label
+                // at the instruction relocated by its high part, so we index the high parts by their offsets.
+                #[cfg(target_arch = "riscv64")]
+                let high_parts: BTreeMap<u64, &xmas_elf::sections::Rela<u64>> = rela_array.iter()
+                    .filter(|r| RelocationEntry::from_elf_relocation(r).is_high_part())
+                    .map(|r| (r.get_offset(), r))
+                    .collect();
+
                 // iterate through each relocation entry in the relocation array for the target_sec
                 for rela_entry in rela_array {
                     if verbose_log {
@@ -2256,12 +2853,32 @@ impl CrateNamespace {
                             rela_entry.get_offset(), rela_entry.get_addend(), rela_entry.get_symbol_table_index(), rela_entry.get_type());
                     }
 
+                    #[allow(unused_mut)]
+                    let mut relocation_entry = RelocationEntry::from_elf_relocation(rela_entry);
+                    if relocation_entry.is_ignored() {
+                        continue;
+                    }
+
                     use xmas_elf::symbol_table::Entry;
-                    let source_sec_entry = &symtab[rela_entry.get_symbol_table_index() as usize];
+                    #[allow(unused_mut)]
+                    let mut symbol_index = rela_entry.get_symbol_table_index() as usize;
+
+                    // Pair a RISC-V low part with its high part, such that it depends on the high part's symbol directly.
+                    #[cfg(target_arch = "riscv64")]
+                    if relocation_entry.is_paired_low_part() {
+                        let label = &symtab[symbol_index];
+                        let high_part = high_parts.get(&label.value().wrapping_add(rela_entry.get_addend()))
+                            .filter(|_| label.shndx() as usize == target_sec_shndx)
+                            .ok_or(LoadError::InvalidSection("couldn't find the high-part relocation referred to by a low-part relocation"))?;
+                        relocation_entry = relocation_entry.pair_with(&RelocationEntry::from_elf_relocation(high_part))?;
+                        symbol_index = high_part.get_symbol_table_index() as usize;
+                    }
+
+                    let source_sec_entry = &symtab[symbol_index];
                     let source_sec_shndx = source_sec_entry.shndx() as usize;
                     let source_sec_value = source_sec_entry.value() as usize;
                     if verbose_log {
-                        let source_sec_header_name = source_sec_entry.get_section_header(elf_file, rela_entry.get_symbol_table_index() as usize)
+                        let source_sec_header_name = source_sec_entry.get_section_header(elf_file, symbol_index)
                             .and_then(|s| s.get_name(elf_file));
                         trace!("             relevant section [{}]: {:?}, value: {:#X}", source_sec_shndx, source_sec_header_name, source_sec_value);
                         // trace!("             Entry name {} {:?} vis {:?} bind {:?} type {:?} shndx {} value {} size {}", 
@@ -2286,7 +2903,7 @@ impl CrateNamespace {
                             if let Ok(source_sec_name) = source_sec_entry.get_name(elf_file) {
                                 const DATARELRO: &str = ".data.rel.ro.";
                                 let source_sec_name = if source_sec_name.starts_with(DATARELRO) {
-                                    source_sec_name.get(DATARELRO.len() ..).ok_or("Couldn't get name of .data.rel.ro. section")?
+                                    source_sec_name.get(DATARELRO.len() ..).ok_or(LoadError::InvalidSection("Couldn't get name of .data.rel.ro. section"))?
                                 } else {
                                     source_sec_name
                                 };
@@ -2295,63 +2912,85 @@ impl CrateNamespace {
                                 if source_sec_name == "__THESEUS_CLS_SIZE" {
                                     #[cfg(target_arch = "aarch64")]
                                     {
-                                        return Err("encountered `__THESEUS_CLS_SIZE` relocation on AArch64");
+                                        return Err(LoadError::Other("encountered `__THESEUS_CLS_SIZE` relocation on AArch64"));
+                                    }
+                                    #[cfg(target_arch = "riscv64")]
+                                    {
+                                        return Err(LoadError::Other("encountered `__THESEUS_CLS_SIZE` relocation on RISC-V"));
                                     }
                                     #[cfg(target_arch = "x86_64")]
                                     {
                                         let cls_size = VirtualAddress::new(usize::MAX).unwrap();
-                                        let relocation_entry = RelocationEntry::from_elf_relocation(rela_entry);
                                         write_relocation(
                                             relocation_entry,
                                             target_sec_slice,
                                             target_sec.mapped_pages_offset,
                                             cls_size,
                                             verbose_log,
-                                        )?;
+                                        ).map_err(|_| LoadError::RelocationUnsupported(relocation_entry.typ))?;
                                         continue;
                                     }
                                 } else if source_sec_name == "__THESEUS_TLS_SIZE" {
                                     #[cfg(target_arch = "x86_64")]
                                     let tls_size = VirtualAddress::new(usize::MAX).unwrap();
-                                    #[cfg(target_arch = "aarch64")]
+                                    #[cfg(any(target_arch = "aarch64", target_arch = "riscv64"))]
                                     let tls_size = VirtualAddress::zero();
 
-                                    let relocation_entry = RelocationEntry::from_elf_relocation(rela_entry);
                                     write_relocation(
                                         relocation_entry,
                                         target_sec_slice,
                                         target_sec.mapped_pages_offset,
                                         tls_size,
                                         verbose_log,
-                                    )?;
+                                    ).map_err(|_| LoadError::RelocationUnsupported(relocation_entry.typ))?;
                                     continue;
                                 }
                                 
-                                let demangled = demangle(source_sec_name).to_string();
+                                let demangled = demangle_symbol(source_sec_name);
 
                                 // search for the symbol's demangled name in the kernel's symbol map
-                                self.get_symbol_or_load(&demangled, temp_backup_namespace, kernel_mmi_ref, verbose_log)
-                                    .upgrade()
-                                    .ok_or("Couldn't get symbol for foreign relocation entry, nor load its containing crate")
+                                match self.get_symbol_or_load(&demangled, temp_backup_namespace, kernel_mmi_ref, verbose_log).upgrade() {
+                                    Some(sec) => {
+                                        if self.strict_symbol_hashes {
+                                            check_symbol_hash(&demangled, &sec)?;
+                                        }
+                                        Ok(sec)
+                                    }
+                                    // An undefined weak reference to a symbol that doesn't exist anywhere resolves to null.
+                                    None if source_sec_shndx == SHN_UNDEF as usize && source_sec_entry.get_binding() == Ok(Binding::Weak) => {
+                                        if verbose_log {
+                                            debug!("             resolving undefined weak symbol {:?} to null", demangled);
+                                        }
+                                        write_relocation(
+                                            relocation_entry,
+                                            target_sec_slice,
+                                            target_sec.mapped_pages_offset,
+                                            VirtualAddress::zero(),
+                                            verbose_log,
+                                        ).map_err(|_| LoadError::RelocationUnsupported(relocation_entry.typ))?;
+                                        target_sec_data_was_modified = true;
+                                        continue;
+                                    }
+                                    None => Err(LoadError::SymbolNotFound(demangled)),
+                                }
                             }
                             else {
                                 let _source_sec_header = source_sec_entry
-                                    .get_section_header(elf_file, rela_entry.get_symbol_table_index() as usize)
+                                    .get_section_header(elf_file, symbol_index)
                                     .and_then(|s| s.get_name(elf_file));
                                 error!("Couldn't get name of source section [{}] {:?}, needed for non-local relocation entry", source_sec_shndx, _source_sec_header);
-                                Err("Couldn't get source section's name, needed for non-local relocation entry")
+                                Err(LoadError::Other("Couldn't get source section's name, needed for non-local relocation entry"))
                             }
                         }
                     }?;
 
-                    let relocation_entry = RelocationEntry::from_elf_relocation(rela_entry);
                     write_relocation(
                         relocation_entry,
                         target_sec_slice,
                         target_sec.mapped_pages_offset,
                         source_sec.virt_addr + source_sec_value,
                         verbose_log
-                    )?;
+                    ).map_err(|_| LoadError::RelocationUnsupported(relocation_entry.typ))?;
                     target_sec_data_was_modified = true;
 
                     if source_and_target_in_same_crate {
@@ -2411,6 +3050,13 @@ impl CrateNamespace {
         }
         // data/bss sections are already mapped properly, since they're supposed to be writable
 
+        // Now that the `.eh_frame` section has been relocated, register it such that this crate can be unwound.
+        if new_crate.eh_frame.is_some() {
+            if let Err(e) = unwind_info::register(&new_crate) {
+                warn!("perform_relocations(): couldn't register unwind info for crate {}: {}", new_crate.crate_name, e);
+            }
+        }
+
 
         // By default, we can safely remove the metadata for all private (non-global) .rodata sections
         // that do not have any strong dependencies (its `sections_i_depend_on` list is empty).
@@ -2434,16 +3080,30 @@ impl CrateNamespace {
     /// Adds the given symbol to this namespace's symbol map.
     /// If the symbol already exists in the symbol map, this replaces the existing symbol with the new one, warning if they differ in size.
     /// Returns true if the symbol was added, and false if it already existed and thus was merely replaced.
+    ///
+    /// A weak symbol never replaces an existing strong (non-weak) symbol that is still loaded,
+    /// whereas a strong symbol always replaces an existing weak one.
     fn add_symbol(
-        existing_symbol_map: &mut SymbolMap,
+        existing_symbol_map: &mut SymbolMapGuard,
         new_section_key: StrRef,
         new_section: &StrongSectionRef,
         log_replacements: bool,
     ) -> bool {
-        match existing_symbol_map.entry(new_section_key) {
-            qp_trie::Entry::Occupied(mut old_val) => {
+        if new_section.weak {
+            let existing_is_strong = existing_symbol_map.get(new_section_key.as_bytes())
+                .and_then(|weak_sec| weak_sec.upgrade())
+                .map_or(false, |existing_sec| !existing_sec.weak);
+            if existing_is_strong {
+                if log_replacements {
+                    debug!("         add_symbol(): keeping strong symbol instead of weak symbol: {:?}", new_section.name);
+                }
+                return false;
+            }
+        }
+        match existing_symbol_map.insert(new_section_key, Arc::downgrade(new_section)) {
+            Some(old_val) => {
                 if log_replacements {
-                    if let Some(old_sec) = old_val.get().upgrade() {
+                    if let Some(old_sec) = old_val.upgrade() {
                         // debug!("       add_symbol(): replacing section: old: {:?}, new: {:?}", old_sec, new_section);
                         if new_section.size != old_sec.size {
                             warn!("Unexpectedly replacing differently-sized section: old: ({}B) {:?}, new: ({}B) {:?}", old_sec.size, old_sec.name, new_section.size, new_section.name);
@@ -2453,14 +3113,12 @@ impl CrateNamespace {
                         }
                     }
                 }
-                old_val.insert(Arc::downgrade(new_section));
                 false
             }
-            qp_trie::Entry::Vacant(new_entry) => {
+            None => {
                 if log_replacements {
                     debug!("         add_symbol(): Adding brand new symbol: new: {:?}", new_section);
                 }
-                new_entry.insert(Arc::downgrade(new_section));
                 true
             }
         }
@@ -2468,7 +3126,8 @@ impl CrateNamespace {
 
     /// Adds only *global* symbols in the given `sections` iterator to this namespace's symbol map,
     ///
-    /// If a symbol already exists in the symbol map, this replaces the existing symbol but does not count it as a newly-added one.
+    /// If a symbol already exists in the symbol map, this replaces the existing symbol but does not count it as a newly-added one,
+    /// unless the existing symbol is strong and the new one is weak, in which case the existing symbol is kept.
     ///
     /// Returns the number of *new* unique symbols added.
     pub fn add_symbols<'a, I>(
@@ -2485,7 +3144,8 @@ impl CrateNamespace {
     /// Adds symbols in the given `sections` iterator to this namespace's symbol map,
     /// but only sections that are *global* AND for which the given `filter_func` returns true. 
     ///
-    /// If a symbol already exists in the symbol map, this replaces the existing symbol but does not count it as a newly-added one.
+    /// If a symbol already exists in the symbol map, this replaces the existing symbol but does not count it as a newly-added one,
+    /// unless the existing symbol is strong and the new one is weak, in which case the existing symbol is kept.
     ///
     /// Returns the number of *new* unique symbols added.
     fn add_symbols_filtered<'a, I, F>(
@@ -2600,7 +3260,8 @@ impl CrateNamespace {
     /// so to avoid deadlock, please ensure that the caller task does not hold any such locks.
     ///
     /// # Note
-    /// This is a slow procedure because, in the worst case,
+    /// Sections of crates that were added via [`add_crate()`](Self::add_crate) are found quickly
+    /// using an address map. Otherwise, this is a slow procedure because, in the worst case,
     /// it will iterate through **every** section in **every** loaded crate 
     /// in this namespace (and its recursive namespace),
     /// not just the publicly-visible (global) sections. 
@@ -2609,6 +3270,11 @@ impl CrateNamespace {
         virt_addr: VirtualAddress,
         search_all_section_types: bool,
     ) -> Option<(StrongSectionRef, usize)> {
+        // Crates added via `add_crate()` can be found quickly in the section address maps.
+        if let Some((sec, offset)) = self.get_indexed_section_containing_address(virt_addr) {
+            let eligible_section = sec.typ == SectionType::Text || search_all_section_types;
+            return eligible_section.then_some((sec, offset));
+        }
 
         // First, we find the crate that contains the address, then later we narrow it down.
         let containing_crate = self.get_crate_containing_address(virt_addr, search_all_section_types)?;
@@ -2650,12 +3316,44 @@ impl CrateNamespace {
         merged_section_and_offset
     }
 
+    /// Finds the section that contains the given `virt_addr` using the section address maps
+    /// of this namespace and its recursive namespaces, along with the offset of `virt_addr` into that section.
+    fn get_indexed_section_containing_address(&self, virt_addr: VirtualAddress) -> Option<(StrongSectionRef, usize)> {
+        if let Some((sec, offset)) = self.section_address_map.get(virt_addr) {
+            // Ensure that the section's crate is still part of this namespace,
+            // as crates may have been removed from the `crate_tree` directly.
+            let in_this_namespace = sec.parent_crate.upgrade().is_some_and(|parent| {
+                let crate_name = parent.lock_as_ref().crate_name.clone();
+                self.crate_tree.lock().get(crate_name.as_bytes())
+                    .is_some_and(|c| CowArc::downgrade(c).ptr_eq(&sec.parent_crate))
+            });
+            if in_this_namespace {
+                return Some((sec, offset));
+            }
+        }
+        self.recursive_namespace.as_ref()
+            .and_then(|r_ns| r_ns.get_indexed_section_containing_address(virt_addr))
+    }
+
     /// Like [`get_symbol()`](#method.get_symbol), but also returns the exact `CrateNamespace` where the symbol was found.
+    ///
+    /// If the symbol cannot be found by its exact name, it is resolved via this namespace's
+    /// [symbol aliases](#method.add_symbol_alias), if any.
     pub fn get_symbol_and_namespace(&self, demangled_full_symbol: &str) -> Option<(WeakSectionRef, &CrateNamespace)> {
-        let weak_symbol = self.symbol_map.lock().get(demangled_full_symbol.as_bytes()).cloned();
+        self.get_symbol_and_namespace_exact(demangled_full_symbol)
+            .or_else(|| self.get_aliased_symbol_and_namespace(demangled_full_symbol))
+    }
+
+    /// Like [`get_symbol_and_namespace()`](#method.get_symbol_and_namespace),
+    /// but ignores this namespace's symbol aliases (though not those of its recursive namespace).
+    fn get_symbol_and_namespace_exact(&self, demangled_full_symbol: &str) -> Option<(WeakSectionRef, &CrateNamespace)> {
+        let weak_symbol = self.symbol_map.get(demangled_full_symbol);
         weak_symbol.map(|sym| (sym, self))
             // search the recursive namespace if the symbol cannot be found in this namespace
-            .or_else(|| self.recursive_namespace.as_ref().and_then(|rns| rns.get_symbol_and_namespace(demangled_full_symbol)))
+            .or_else(|| self.recursive_namespace.as_ref()
+                .and_then(|rns| rns.get_symbol_and_namespace(demangled_full_symbol))
+                .filter(|(sym, _ns)| self.is_recursive_symbol_visible(sym))
+            )
     }
 
     /// A convenience function that returns a weak reference to the `LoadedSection`
@@ -2689,9 +3387,9 @@ impl CrateNamespace {
     ///
     /// (4) Fourth, if the missing symbol isn't in the backup namespace either, 
     ///     try to load its containing crate from the object file. 
-    ///     This can only be done for symbols that have a leading crate name, such as "my_crate::foo";
-    ///     if a symbol was given the `no_mangle` attribute, then we will not be able to find it,
-    ///     and that symbol's containing crate should be manually loaded before invoking this. 
+    ///     The crate is first inferred from the crate names within the symbol, such as "my_crate::foo";
+    ///     if that fails, e.g., because the symbol was given the `no_mangle` attribute,
+    ///     then the [`SymbolIndex`] of each namespace's object files is consulted.
     ///
     ///
     /// # Arguments
@@ -2815,7 +3513,7 @@ impl CrateNamespace {
 
         // We add a shared reference to that section's parent crate to this namespace as well, 
         // to prevent that crate from being dropped while this namespace still relies on it.
-        self.crate_tree.lock().insert(parent_crate_name, parent_crate_ref);
+        self.add_crate(parent_crate_name, parent_crate_ref);
         Some(sec)
     }
 
@@ -2825,8 +3523,9 @@ impl CrateNamespace {
     /// If successful, the new crate is loaded into this `CrateNamespace` and the symbol's section is returned.
     /// If this namespace does not contain any matching crates, its recursive namespaces are searched as well.
     ///
-    /// This approach only works for mangled symbols that contain a crate name, such as "my_crate::foo". 
-    /// If "foo()" was marked no_mangle, then we don't know which crate to load because there is no "my_crate::" prefix before it.
+    /// This approach primarily works for mangled symbols that contain a crate name, such as "my_crate::foo". 
+    /// If "foo()" was marked no_mangle, then we don't know which crate to load because there is no "my_crate::" prefix before it,
+    /// so we fall back to searching the [`SymbolIndex`] of this namespace and its recursive namespaces.
     ///
     /// Note: while attempting to find the missing `demangled_full_symbol`, this function may end up
     /// loading *multiple* crates into this `CrateNamespace` or its recursive namespaces, due to two reasons:
@@ -2857,6 +3556,13 @@ impl CrateNamespace {
             // so they'll only be searched if the symbol isn't found in the current namespace.
             for (potential_crate_file, ns_of_crate_file) in self.method_get_crate_object_files_starting_with(&potential_crate_name) {
                 let potential_crate_file_path = PathBuf::from(potential_crate_file.lock().get_absolute_path());
+                // Crates from the recursive namespace(s) can only be loaded if they're visible to this namespace.
+                if !core::ptr::eq(ns_of_crate_file, self)
+                    && !self.is_recursive_crate_visible_for_symbol(crate_name_from_path(&potential_crate_file_path)?, demangled_full_symbol)
+                {
+                    trace!("  (skipping crate {:?} that isn't visible to namespace {:?})", potential_crate_file_path, self.name);
+                    continue;
+                }
                 // Check to make sure this crate is not already loaded into this namespace (or its recursive namespace).
                 if self.get_crate(crate_name_from_path(&potential_crate_file_path)?).is_some() {
                     trace!("  (skipping already-loaded crate {:?})", potential_crate_file_path);
@@ -2886,10 +3592,61 @@ impl CrateNamespace {
             }
         }
 
+        // As a last resort, look up which object file defines the symbol, which is slower but works for any symbol.
+        if let Some(sec) = self.load_crate_from_symbol_index(demangled_full_symbol, temp_backup_namespace, kernel_mmi_ref, verbose_log) {
+            return Some(sec);
+        }
+
         warn!("Couldn't find/load crate(s) that may contain the missing symbol {:?}", demangled_full_symbol);
         None
     }
 
+    /// Searches the [`SymbolIndex`] of this namespace and then its recursive namespaces
+    /// for the object file that defines the given `demangled_full_symbol`,
+    /// and loads that crate into the namespace whose directory contains it.
+    ///
+    /// Any crates that the newly-loaded crate depends on are loaded on demand as well while it is being linked.
+    fn load_crate_from_symbol_index(
+        &self,
+        demangled_full_symbol: &str,
+        temp_backup_namespace: Option<&CrateNamespace>,
+        kernel_mmi_ref: &MmiRef,
+        verbose_log: bool,
+    ) -> Option<WeakSectionRef> {
+        let mut namespace = Some(self);
+        while let Some(ns) = namespace {
+            namespace = ns.recursive_namespace.as_deref();
+            let Some(crate_file) = ns.symbol_index.find(&ns.dir, demangled_full_symbol) else {
+                continue;
+            };
+            let crate_file_path = PathBuf::from(crate_file.lock().get_absolute_path());
+            let crate_name = crate_name_from_path(&crate_file_path)?;
+            // Crates from the recursive namespace(s) can only be loaded if they're visible to this namespace.
+            if !core::ptr::eq(ns, self) && !self.is_recursive_crate_visible_for_symbol(crate_name, demangled_full_symbol) {
+                trace!("  (skipping crate {:?} that isn't visible to namespace {:?})", crate_file_path, self.name);
+                return None;
+            }
+            // If the crate is already loaded, then the symbol index is stale and the symbol isn't in that crate.
+            if self.get_crate(crate_name).is_some() {
+                trace!("  (skipping already-loaded crate {:?})", crate_file_path);
+                return None;
+            }
+            #[cfg(not(loscd_eval))]
+            info!("Symbol {:?} not initially found in namespace {:?}, loading crate {:?} that defines it into namespace {:?}.",
+                demangled_full_symbol, self.name, crate_name, ns.name);
+
+            return match ns.load_crate(&crate_file, temp_backup_namespace, kernel_mmi_ref, verbose_log) {
+                Ok(_) => ns.get_symbol_internal(demangled_full_symbol),
+                Err(_e) => {
+                    error!("Found symbol's (\"{}\") defining crate in the symbol index, but couldn't load the crate file {:?}. Error: {:?}",
+                        demangled_full_symbol, crate_file_path, _e);
+                    None
+                }
+            };
+        }
+        None
+    }
+
 
     /// Returns a copied list of the corresponding `LoadedSection`s 
     /// with names that start with the given `symbol_prefix`.
@@ -2910,6 +3667,39 @@ impl CrateNamespace {
             .collect();
 
         if let Some(mut syms_recursive) = self.recursive_namespace.as_ref().map(|r_ns| r_ns.find_symbols_starting_with(symbol_prefix)) {
+            syms_recursive.retain(|(name, sym)| self.is_recursive_symbol_visible(sym) && !self.shadows_symbol(name));
+            syms.append(&mut syms_recursive);
+        }
+
+        syms
+    }
+
+
+    /// Returns a copied list of the corresponding `LoadedSection`s
+    /// with names that match the given shell-style wildcard `pattern`,
+    /// as supported by [`path::glob_match()`].
+    /// This will also search the recursive namespace's symbol map.
+    ///
+    /// The symbol map is only searched for symbols that start with the part of the `pattern`
+    /// before its first wildcard, so patterns that start with a literal prefix are much faster.
+    ///
+    /// # Example
+    /// The symbol map contains `my_crate::foo::h843a613894da0c24`,
+    /// `my_crate::bar::foo::h933a635894ce0f12`, and `my_crate::bar::baz::h1b4e2d3c5a697f80`.
+    /// Calling `find_symbols_matching("my_crate::*foo::*")` will return
+    /// a vector containing the first two sections.
+    pub fn find_symbols_matching(&self, pattern: &str) -> Vec<(String, WeakSectionRef)> {
+        let literal_prefix = pattern
+            .find(['*', '?', '['])
+            .map_or(pattern, |end| &pattern[..end]);
+        let mut syms: Vec<(String, WeakSectionRef)> = self.symbol_map.lock()
+            .iter_prefix(literal_prefix.as_bytes())
+            .filter(|(k, _v)| path::glob_match(pattern, k.as_str()))
+            .map(|(k, v)| (String::from(k.as_str()), v.clone()))
+            .collect();
+
+        if let Some(mut syms_recursive) = self.recursive_namespace.as_ref().map(|r_ns| r_ns.find_symbols_matching(pattern)) {
+            syms_recursive.retain(|(_name, sym)| self.is_recursive_symbol_visible(sym));
             syms.append(&mut syms_recursive);
         }
 
@@ -2926,6 +3716,7 @@ impl CrateNamespace {
             .collect();
 
         if let Some(mut syms_recursive) = self.recursive_namespace.as_ref().map(|r_ns| r_ns.find_symbols_starting_with_and_namespace(symbol_prefix)) {
+            syms_recursive.retain(|(name, sym, _ns)| self.is_recursive_symbol_visible(sym) && !self.shadows_symbol(name));
             syms.append(&mut syms_recursive);
         }
 
@@ -2973,8 +3764,12 @@ impl CrateNamespace {
             .filter(|_| iter.next().is_none()) // ensure single element
             .cloned();
 
-        // Second, we see if there's a single matching symbol in the recursive namespace.
-        let symbol_in_recursive_namespace = self.recursive_namespace.as_ref().and_then(|r_ns| r_ns.get_symbol_starting_with_internal(symbol_prefix));
+        // Second, we see if there's a single matching symbol in the recursive namespace
+        // that isn't shadowed by a symbol of the same name in this namespace.
+        let symbol_in_recursive_namespace = self.recursive_namespace.as_ref()
+            .and_then(|r_ns| r_ns.get_symbol_starting_with_internal(symbol_prefix))
+            .filter(|sym| self.is_recursive_symbol_visible(sym))
+            .filter(|sym| !sym.upgrade().is_some_and(|sec| self.shadows_symbol(sec.name.as_str())));
 
         // There can only be one matching crate across all recursive namespaces.
         symbol_in_this_namespace.xor(symbol_in_recursive_namespace)
@@ -3042,11 +3837,22 @@ struct SectionPages {
 ///   128 MiB away from the current instruction.
 ///   Thus, we restrict the range of .text section locations to ensure they are within 128 MiB.
 ///   At some point in the future, this will be a limitation, but not for a long, long time.
+/// * On riscv64, crates are compiled with the `medany` code model, which can only address
+///   symbols within 2 GiB of the current instruction, so we restrict .text sections to 1 GiB.
 /// * On x86_64, this is not necessary, so the range is `None`.
 pub const KERNEL_TEXT_ADDR_RANGE: Option<PageRange> = {
     #[cfg(target_arch = "x86_64")] {
         None
     }
+    #[cfg(target_arch = "riscv64")] {
+        const ONE_GIB: usize = 0x4000_0000;
+        let start_vaddr = VirtualAddress::new_canonical(kernel_config::memory::KERNEL_OFFSET);
+        let end_vaddr = VirtualAddress::new_canonical(start_vaddr.value() + ONE_GIB - 1);
+        Some(PageRange::new(
+            memory::Page::containing_address(start_vaddr),
+            memory::Page::containing_address(end_vaddr),
+        ))
+    }
     #[cfg(target_arch = "aarch64")] {
         const ONE_MIB: usize = 0x10_0000;
         let start_vaddr = VirtualAddress::new_canonical(kernel_config::memory::KERNEL_OFFSET);
@@ -3063,7 +3869,7 @@ pub const KERNEL_TEXT_ADDR_RANGE: Option<PageRange> = {
 
 /// Allocates and maps memory sufficient to hold the sections that are found in the given `ElfFile`.
 /// Only sections that are marked "allocated" (`ALLOC`) in the ELF object file will contribute to the mappings' sizes.
-fn allocate_section_pages(elf_file: &ElfFile, kernel_mmi_ref: &MmiRef) -> Result<SectionPages, &'static str> {
+fn allocate_section_pages(elf_file: &ElfFile, kernel_mmi_ref: &MmiRef) -> Result<SectionPages, LoadError> {
     // Calculate how many bytes (and thus how many pages) we need for each of the three section types.
     //
     // If there are multiple .text sections, they will all exist at the beginning of the object file,
@@ -3085,7 +3891,7 @@ fn allocate_section_pages(elf_file: &ElfFile, kernel_mmi_ref: &MmiRef) -> Result
             let sec = if (sec.size() == 0) && (sec.get_name(elf_file) != Ok(".text")) {
                 // warn!("Unlikely scenario: found zero-sized sec {:X?}", sec);
                 let next_sec = elf_file.section_header((shndx + 1) as u16)
-                    .map_err(|_| "couldn't get next section for a zero-sized section")?;
+                    .map_err(|_| LoadError::InvalidSection("couldn't get next section for a zero-sized section"))?;
                 if next_sec.offset() == sec.offset() {
                     // warn!("Using next_sec {:X?} instead of zero-sized sec {:X?}", next_sec, sec);
                     next_sec
@@ -3121,7 +3927,7 @@ fn allocate_section_pages(elf_file: &ElfFile, kernel_mmi_ref: &MmiRef) -> Result
                 if sec.get_type() == Ok(ShType::ProgBits) {
                     ro_bytes += addend;
                 } else {
-                    return Err("CLS section had unexpected type");
+                    return Err(LoadError::InvalidSection("CLS section had unexpected type"));
                 }
             } else if is_write {
                 // this includes both .bss and .data sections
@@ -3139,19 +3945,31 @@ fn allocate_section_pages(elf_file: &ElfFile, kernel_mmi_ref: &MmiRef) -> Result
 
     // Allocate contiguous virtual memory pages for each section and map them to random frames as writable.
     // We must allocate these pages separately because they use different flags.
-    let alloc_sec = |size_in_bytes: usize, within_range: Option<&PageRange>, flags: PteFlags| {
+    //
+    // Mappings recycled from previously-dropped crates are reused if possible,
+    // deferring their TLB invalidations such that they can all be flushed at once below.
+    let mut tlb_flush_batch = TlbFlushBatch::new();
+    let mut alloc_sec = |size_in_bytes: usize, within_range: Option<&PageRange>, flags: PteFlags| {
+        let flags = flags.valid(true).writable(true);
+        if let Some(mp) = mapped_pages_pool::take(
+            size_in_bytes.div_ceil(PAGE_SIZE),
+            within_range,
+            flags,
+            &mut kernel_mmi_ref.lock().page_table,
+            Some(&mut tlb_flush_batch),
+        ) {
+            return Ok(mp);
+        }
+
         let allocated_pages = if let Some(range) = within_range {
             allocate_pages_by_bytes_in_range(size_in_bytes, range)
-                .map_err(|_| "Couldn't allocate pages in text section address range")?
+                .map_err(|_| LoadError::MappingFailed("Couldn't allocate pages in text section address range"))?
         } else {
             allocate_pages_by_bytes(size_in_bytes)
-                .ok_or("Couldn't allocate pages for new section")?
+                .ok_or(LoadError::MappingFailed("Couldn't allocate pages for new section"))?
         };
 
-        kernel_mmi_ref.lock().page_table.map_allocated_pages(
-            allocated_pages,
-            flags.valid(true).writable(true)
-        )
+        kernel_mmi_ref.lock().page_table.map_allocated_pages(allocated_pages, flags)
     };
 
     let executable_pages = if exec_bytes > 0 {
@@ -3169,6 +3987,7 @@ fn allocate_section_pages(elf_file: &ElfFile, kernel_mmi_ref: &MmiRef) -> Result
     } else {
         None
     };
+    tlb_flush_batch.flush();
 
     let range_tuple = |mp: MappedPages, size_in_bytes: usize| {
         let start = mp.start_address();
@@ -3217,7 +4036,7 @@ fn dump_weak_dependents(sec: &LoadedSection, prefix: String) {
 
 /// Returns a reference to the symbol table in the given `ElfFile`.
 pub fn find_symbol_table<'e>(elf_file: &'e ElfFile)
-    -> Result<&'e [xmas_elf::symbol_table::Entry64], &'static str>
+    -> Result<&'e [xmas_elf::symbol_table::Entry64], LoadError>
     {
     use xmas_elf::sections::SectionData::SymbolTable64;
     let symtab_data = elf_file.section_iter()
@@ -3228,8 +4047,37 @@ pub fn find_symbol_table<'e>(elf_file: &'e ElfFile)
     match symtab_data {
         Ok(SymbolTable64(symtab)) => Ok(symtab),
         _ => {
-            Err("no symbol table found. Was file stripped?")
+            Err(LoadError::SectionMissing("no symbol table found. Was file stripped?"))
+        }
+    }
+}
+
+
+/// Returns the shndx of the `.eh_frame` section among the given sections, if there is one.
+fn find_eh_frame(sections: &HashMap<Shndx, StrongSectionRef>) -> Option<Shndx> {
+    sections.iter()
+        .find(|(_, sec)| sec.typ == SectionType::EhFrame)
+        .map(|(shndx, _)| *shndx)
+}
+
+
+/// Checks that the hash of the given `found_section` matches the hash of the symbol
+/// that a relocation expected, i.e., that both come from the same version of their crate.
+///
+/// Symbols without a hash, e.g., unmangled ones, are always considered compatible.
+fn check_symbol_hash(expected_symbol: &str, found_section: &LoadedSection) -> Result<(), LoadError> {
+    let expected = LoadedSection::section_name_hash(expected_symbol);
+    let found = LoadedSection::section_name_hash(&found_section.name);
+    match (expected, found) {
+        (Some(expected), Some(found)) if expected != found => {
+            error!("Symbol {:?} resolved to {:?} from an incompatible version of its crate", expected_symbol, found_section.name);
+            Err(LoadError::SymbolHashMismatch {
+                symbol: String::from(expected_symbol),
+                expected: String::from(expected),
+                found: String::from(found),
+            })
         }
+        _ => Ok(()),
     }
 }
 
@@ -0,0 +1,400 @@
+//! A cache of fully-loaded and linked crates, which allows them to be restored much faster
+//! than they can be loaded from their object files, e.g., on the next boot.
+//!
+//! While recording is enabled, a snapshot of each newly-loaded crate is taken right after it has been
+//! relocated, but before its constructors have been run.
+//! The snapshot includes the crate's `LoadedCrate` and `LoadedSection` metadata,
+//! the relocated contents of its text, rodata, and data pages (and their virtual addresses),
+//! the dependencies that were established by linking it,
+//! and a hash of the object file that it was loaded from.
+//! These snapshots can be written to a file with [`save()`] and read back in with [`load()`].
+//!
+//! Once a cache has been loaded, [`CrateNamespace::load_crate()`] and related functions
+//! first try to restore each crate from the cache, which is only done if:
+//! 1. the crate's object file has the same hash as the one it was recorded from,
+//! 2. the crate's object file still passes signature verification, if required,
+//! 3. every section that the crate depends on is present at the same address it was at when recorded, and
+//! 4. the virtual addresses of the crate's pages are still available.
+//!
+//! Otherwise, the crate is loaded and linked from its object file as usual.
+//!
+//! Crates with TLS or CLS sections are never recorded, as those sections must be
+//! registered with the TLS and CLS areas at load time.
+
+use alloc::{
+    collections::{BTreeMap, BTreeSet},
+    string::String,
+    sync::Arc,
+    vec::Vec,
+};
+use core::{ops::{Deref, Range}, sync::atomic::{AtomicBool, Ordering}};
+use crate_metadata_serde::SerializedSection;
+use hashing::{Digest, Sha256};
+use ::serde::{Deserialize, Serialize};
+use crate::*;
+
+/// The version of the cache's serialized format,
+/// which must be changed whenever the format of the types below changes.
+const CACHE_FORMAT_VERSION: u32 = 2;
+
+/// Whether snapshots of newly-loaded crates should be recorded.
+static RECORDING: AtomicBool = AtomicBool::new(false);
+/// The crates recorded since recording was enabled, keyed by namespace name and crate name.
+static RECORDED: Mutex<BTreeMap<(String, String), CachedCrate>> = Mutex::new(BTreeMap::new());
+/// The crates that can be restored, keyed by namespace name and crate name.
+static RESTORABLE: Mutex<BTreeMap<(String, String), CachedCrate>> = Mutex::new(BTreeMap::new());
+
+/// A snapshot of a single loaded and linked crate.
+#[derive(Serialize, Deserialize)]
+struct CachedCrate {
+    /// The SHA-256 hash of the crate object file that this crate was loaded from.
+    object_file_hash: [u8; 32],
+    text_pages: Option<CachedPages>,
+    rodata_pages: Option<CachedPages>,
+    data_pages: Option<CachedPages>,
+    sections: Vec<(Shndx, SerializedSection)>,
+    global_sections: BTreeSet<Shndx>,
+    /// The subset of global sections that have weak binding.
+    weak_sections: BTreeSet<Shndx>,
+    data_sections: BTreeSet<Shndx>,
+    eh_frame: Option<Shndx>,
+    init_array: Vec<Shndx>,
+    fini_array: Vec<Shndx>,
+    reexported_symbols: Vec<String>,
+    dependencies: Vec<CachedDependency>,
+}
+
+/// The relocated contents of one of a crate's memory regions.
+#[derive(Serialize, Deserialize)]
+struct CachedPages {
+    start_address: usize,
+    bytes: Vec<u8>,
+}
+
+/// A dependency of one of a crate's sections on a section in another crate.
+#[derive(Serialize, Deserialize)]
+struct CachedDependency {
+    target_shndx: Shndx,
+    source_crate: String,
+    source_shndx: Shndx,
+    /// The address of the source section at the time this dependency was recorded,
+    /// which the relocated contents of the target section refer to.
+    source_virt_addr: usize,
+    relocation_type: u32,
+    relocation_addend: usize,
+    relocation_offset: usize,
+}
+
+/// Starts recording a snapshot of each crate that is subsequently loaded into any namespace.
+pub fn enable_recording() {
+    RECORDING.store(true, Ordering::Release);
+}
+
+/// Stops recording snapshots of newly-loaded crates, but keeps the ones that were already recorded.
+pub fn disable_recording() {
+    RECORDING.store(false, Ordering::Release);
+}
+
+/// Returns the names of the namespaces and crates that have been recorded so far.
+pub fn recorded_crates() -> Vec<(String, String)> {
+    RECORDED.lock().keys().cloned().collect()
+}
+
+/// Writes all recorded crate snapshots to the given file, overwriting its contents.
+///
+/// Returns the number of bytes written.
+pub fn save(file: &FileRef) -> Result<usize, &'static str> {
+    let encode_error = |e: bincode::error::EncodeError| {
+        error!("metadata_cache::save(): error serializing crate metadata: {}", e);
+        "failed to serialize the crate metadata cache"
+    };
+    // The format version comes first, such that it can be checked before decoding the rest.
+    let mut bytes = bincode::serde::encode_to_vec(CACHE_FORMAT_VERSION, bincode::config::standard()).map_err(encode_error)?;
+    bytes.extend(bincode::serde::encode_to_vec(&*RECORDED.lock(), bincode::config::standard()).map_err(encode_error)?);
+    file.lock().write_at(&bytes, 0)?;
+    Ok(bytes.len())
+}
+
+/// Reads crate snapshots from the given file, which was previously written by [`save()`],
+/// such that those crates can be restored when they are next loaded.
+///
+/// Returns the number of crates that can be restored.
+pub fn load(file: &FileRef) -> Result<usize, &'static str> {
+    let bytes = {
+        let mut file = file.lock();
+        let mut bytes = vec![0u8; file.len()];
+        file.read_at(&mut bytes, 0)?;
+        bytes
+    };
+    let decode_error = |e: bincode::error::DecodeError| {
+        error!("metadata_cache::load(): error deserializing crate metadata: {}", e);
+        "failed to deserialize the crate metadata cache"
+    };
+    let (version, version_len): (u32, _) = bincode::serde::decode_from_slice(&bytes, bincode::config::standard()).map_err(decode_error)?;
+    if version != CACHE_FORMAT_VERSION {
+        warn!("metadata_cache::load(): ignoring cache with format version {}, expected {}", version, CACHE_FORMAT_VERSION);
+        return Err("the crate metadata cache has an unsupported format version");
+    }
+    let (crates, _len): (BTreeMap<(String, String), CachedCrate>, _) =
+        bincode::serde::decode_from_slice(&bytes[version_len..], bincode::config::standard()).map_err(decode_error)?;
+    let mut restorable = RESTORABLE.lock();
+    restorable.extend(crates);
+    Ok(restorable.len())
+}
+
+/// Discards all crate snapshots that were loaded but not yet restored.
+pub fn clear() {
+    RESTORABLE.lock().clear();
+}
+
+
+/// Records a snapshot of the given newly-loaded crate, if recording is enabled.
+///
+/// This must be called after the crate has been relocated but before its constructors have been run.
+pub(crate) fn record(namespace: &CrateNamespace, crate_bytes: &[u8], new_crate_ref: &StrongCrateRef) {
+    if !RECORDING.load(Ordering::Acquire) {
+        return;
+    }
+    // Dependencies within the same crate aren't recorded, so they couldn't be restored.
+    if cfg!(internal_deps) {
+        return;
+    }
+    let new_crate = new_crate_ref.lock_as_ref();
+    if !new_crate.tls_sections.is_empty() || !new_crate.cls_sections.is_empty() {
+        return;
+    }
+    match snapshot(&new_crate, crate_bytes) {
+        Ok(cached) => {
+            RECORDED.lock().insert((String::from(namespace.name()), String::from(new_crate.crate_name.deref())), cached);
+        }
+        Err(e) => warn!("metadata_cache: couldn't record crate {}: {}", new_crate.crate_name, e),
+    }
+}
+
+fn snapshot(new_crate: &LoadedCrate, crate_bytes: &[u8]) -> Result<CachedCrate, &'static str> {
+    let snapshot_pages = |pages: &Option<(Arc<Mutex<MappedPages>>, Range<VirtualAddress>)>| -> Result<Option<CachedPages>, &'static str> {
+        let Some((mp, range)) = pages else { return Ok(None) };
+        let mp = mp.lock();
+        let len = range.end.value() - range.start.value();
+        let offset = range.start.value() - mp.start_address().value();
+        Ok(Some(CachedPages {
+            start_address: range.start.value(),
+            bytes: mp.as_slice::<u8>(offset, len)?.to_vec(),
+        }))
+    };
+
+    let mut sections = Vec::with_capacity(new_crate.sections.len());
+    let mut dependencies = Vec::new();
+    for (shndx, sec) in &new_crate.sections {
+        sections.push((*shndx, SerializedSection {
+            name: String::from(sec.name.deref()),
+            ty: sec.typ,
+            global: sec.global,
+            virtual_address: sec.virt_addr.value(),
+            offset: sec.mapped_pages_offset,
+            size: sec.size,
+        }));
+        for strong_dep in &sec.inner.read().sections_i_depend_on {
+            let source_sec = &strong_dep.section;
+            let source_crate_ref = source_sec.parent_crate.upgrade()
+                .ok_or("couldn't get the parent crate of a dependency")?;
+            let source_crate = source_crate_ref.lock_as_ref();
+            let source_shndx = source_crate.sections.iter()
+                .find(|(_, s)| Arc::ptr_eq(s, source_sec))
+                .map(|(shndx, _)| *shndx)
+                .ok_or("couldn't find a dependency in its parent crate")?;
+            dependencies.push(CachedDependency {
+                target_shndx: *shndx,
+                source_crate: String::from(source_crate.crate_name.deref()),
+                source_shndx,
+                source_virt_addr: source_sec.virt_addr.value(),
+                relocation_type: strong_dep.relocation.typ,
+                relocation_addend: strong_dep.relocation.addend,
+                relocation_offset: strong_dep.relocation.offset,
+            });
+        }
+    }
+
+    Ok(CachedCrate {
+        object_file_hash: Sha256::digest(crate_bytes),
+        text_pages: snapshot_pages(&new_crate.text_pages)?,
+        rodata_pages: snapshot_pages(&new_crate.rodata_pages)?,
+        data_pages: snapshot_pages(&new_crate.data_pages)?,
+        sections,
+        global_sections: new_crate.global_sections.clone(),
+        weak_sections: new_crate.sections.iter().filter(|(_, sec)| sec.weak).map(|(shndx, _)| *shndx).collect(),
+        data_sections: new_crate.data_sections.clone(),
+        eh_frame: new_crate.eh_frame,
+        init_array: new_crate.init_array.clone(),
+        fini_array: new_crate.fini_array.clone(),
+        reexported_symbols: new_crate.reexported_symbols.iter().map(|s| String::from(s.deref())).collect(),
+        dependencies,
+    })
+}
+
+
+/// Tries to restore the crate in the given object file from the cache into the given `namespace`.
+///
+/// Like `CrateNamespace::load_crate_internal()`, this does not add the restored crate
+/// nor its symbols to the namespace.
+///
+/// Returns `None` if the crate isn't cached or cannot be restored,
+/// in which case it should be loaded from its object file as usual.
+pub(crate) fn restore(
+    namespace: &CrateNamespace,
+    crate_file: &dyn File,
+    kernel_mmi_ref: &MmiRef,
+) -> Option<StrongCrateRef> {
+    let abs_path = PathBuf::from(crate_file.get_absolute_path());
+    let crate_name = crate_name_from_path(&abs_path)?;
+    let key = (String::from(namespace.name()), String::from(crate_name));
+    let cached = RESTORABLE.lock().remove(&key)?;
+    match restore_crate(namespace, crate_file, &abs_path, cached, kernel_mmi_ref) {
+        Ok(new_crate_ref) => {
+            #[cfg(not(loscd_eval))]
+            debug!("metadata_cache: restored crate {} in namespace {}", crate_name, namespace.name());
+            Some(new_crate_ref)
+        }
+        Err(e) => {
+            warn!("metadata_cache: couldn't restore crate {} in namespace {}: {}", crate_name, namespace.name(), e);
+            None
+        }
+    }
+}
+
+fn restore_crate(
+    namespace: &CrateNamespace,
+    crate_file: &dyn File,
+    abs_path: &Path,
+    cached: CachedCrate,
+    kernel_mmi_ref: &MmiRef,
+) -> Result<StrongCrateRef, &'static str> {
+    let crate_name = StrRef::from(crate_name_from_path(abs_path).ok_or("failed to get crate name from path")?);
+    if namespace.get_crate(&crate_name).is_some() {
+        return Err("the crate has already been loaded");
+    }
+
+    let byte_slice: &[u8] = crate_file.as_mapping()?.as_slice(0, crate_file.len())?;
+    if Sha256::digest(byte_slice) != cached.object_file_hash {
+        return Err("the crate object file has changed");
+    }
+    let elf_file = ElfFile::new(byte_slice)?;
+    signature::verify(namespace.signature_policy, crate_file, byte_slice, &elf_file)?;
+    let crate_object_file = match Path::get_absolute(abs_path) {
+        Some(FileOrDir::File(f)) => f,
+        _ => return Err("couldn't get crate object file path"),
+    };
+
+    // Ensure that every section this crate depends on is present where it was when the crate was linked,
+    // as the crate's relocated contents refer to those addresses.
+    let mut dependencies = Vec::with_capacity(cached.dependencies.len());
+    for dep in &cached.dependencies {
+        let source_sec = namespace.get_crate(&dep.source_crate)
+            .and_then(|source_crate| {
+                let source_sec = source_crate.lock_as_ref().sections.get(&dep.source_shndx).cloned();
+                source_sec
+            })
+            .filter(|source_sec| source_sec.virt_addr.value() == dep.source_virt_addr)
+            .ok_or("a section that the crate depends on is missing or has moved")?;
+        dependencies.push(source_sec);
+    }
+
+    let restore_pages = |pages: &Option<CachedPages>, flags: PteFlags| -> Result<Option<(Arc<Mutex<MappedPages>>, Range<VirtualAddress>)>, &'static str> {
+        let Some(pages) = pages else { return Ok(None) };
+        let start = VirtualAddress::new(pages.start_address).ok_or("invalid cached page address")?;
+        let allocated_pages = memory::allocate_pages_by_bytes_at(start, pages.bytes.len())?;
+        let mut mp = kernel_mmi_ref.lock().page_table.map_allocated_pages(
+            allocated_pages,
+            DATA_BSS_SECTION_FLAGS,
+        )?;
+        mp.as_slice_mut::<u8>(0, pages.bytes.len())?.copy_from_slice(&pages.bytes);
+        if !flags.is_writable() {
+            mp.remap(&mut kernel_mmi_ref.lock().page_table, flags)?;
+        }
+        Ok(Some((Arc::new(Mutex::new(mp)), start..(start + pages.bytes.len()))))
+    };
+    let text_pages   = restore_pages(&cached.text_pages, TEXT_SECTION_FLAGS)?;
+    let rodata_pages = restore_pages(&cached.rodata_pages, RODATA_SECTION_FLAGS)?;
+    let data_pages   = restore_pages(&cached.data_pages, DATA_BSS_SECTION_FLAGS)?;
+
+    let new_crate = CowArc::new(LoadedCrate {
+        crate_name,
+        debug_symbols_file:      Arc::downgrade(&crate_object_file),
+        object_file:             crate_object_file,
+        sections:                HashMap::new(),
+        text_pages:              text_pages.clone(),
+        rodata_pages:            rodata_pages.clone(),
+        data_pages:              data_pages.clone(),
+        global_sections:         cached.global_sections,
+        tls_sections:            BTreeSet::new(),
+        cls_sections:            BTreeSet::new(),
+        data_sections:           cached.data_sections,
+        eh_frame:                cached.eh_frame,
+        init_array:              cached.init_array,
+        fini_array:              cached.fini_array,
+        reexported_symbols:      cached.reexported_symbols.iter().map(|s| StrRef::from(s.as_str())).collect(),
+    });
+    let new_crate_weak_ref = CowArc::downgrade(&new_crate);
+
+    let mut sections = HashMap::with_capacity(cached.sections.len());
+    for (shndx, sec) in cached.sections {
+        let mapped_pages = match sec.ty {
+            SectionType::Text => &text_pages,
+            SectionType::Data
+            | SectionType::Bss
+            | SectionType::InitArray
+            | SectionType::FiniArray => &data_pages,
+            _ => &rodata_pages,
+        };
+        let mapped_pages = mapped_pages.as_ref().map(|(mp, _)| Arc::clone(mp))
+            .ok_or("a cached section's pages weren't cached")?;
+        let name = match sec.ty {
+            SectionType::EhFrame
+            | SectionType::GccExceptTable
+            | SectionType::InitArray
+            | SectionType::FiniArray => section_name_str_ref(&sec.ty),
+            _ => sec.name.as_str().into(),
+        };
+        let virt_addr = VirtualAddress::new(sec.virtual_address).ok_or("invalid cached section address")?;
+        sections.insert(shndx, Arc::new(LoadedSection::new(
+            sec.ty,
+            name,
+            mapped_pages,
+            sec.offset,
+            virt_addr,
+            sec.size,
+            sec.global,
+            new_crate_weak_ref.clone(),
+        ).with_weak_binding(cached.weak_sections.contains(&shndx))));
+    }
+
+    // Re-establish the dependencies between this crate's sections and the sections in other crates.
+    for (dep, source_sec) in cached.dependencies.iter().zip(dependencies) {
+        let target_sec = sections.get(&dep.target_shndx).ok_or("a cached dependency's target section is missing")?;
+        let relocation = RelocationEntry {
+            typ: dep.relocation_type,
+            addend: dep.relocation_addend,
+            offset: dep.relocation_offset,
+        };
+        source_sec.inner.write().sections_dependent_on_me.push(WeakDependent {
+            section: Arc::downgrade(target_sec),
+            relocation,
+        });
+        target_sec.inner.write().sections_i_depend_on.push(StrongDependency {
+            section: source_sec,
+            relocation,
+        });
+    }
+
+    {
+        let mut new_crate_mut = new_crate.lock_as_mut()
+            .ok_or("BUG: couldn't get exclusive mutable access to new_crate")?;
+        new_crate_mut.sections = sections;
+        if new_crate_mut.eh_frame.is_some() {
+            if let Err(e) = unwind_info::register(&new_crate_mut) {
+                warn!("metadata_cache: couldn't register unwind info for crate {}: {}", new_crate_mut.crate_name, e);
+            }
+        }
+    }
+    Ok(new_crate)
+}
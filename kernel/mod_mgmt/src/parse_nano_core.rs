@@ -5,25 +5,37 @@
 
 #![allow(clippy::type_complexity)]
 
-use alloc::{collections::{BTreeMap, BTreeSet}, string::{String, ToString}, sync::Arc};
-use crate::{CrateNamespace, mp_range, CLS_SECTION_FLAG};
+use alloc::{collections::{BTreeMap, BTreeSet}, string::String, sync::Arc, vec::Vec};
+use core::ops::Range;
+use crate::{CrateNamespace, LoadError, demangle_symbol, find_eh_frame, mp_range, unwind_info, CLS_SECTION_FLAG};
 use fs_node::FileRef;
 use path::PathBuf;
-use rustc_demangle::demangle;
 use spin::Mutex;
 use cow_arc::{CowArc, CowWeak};
-use cstr_core::CStr;
 use memory::{VirtualAddress, MappedPages};
+use memory_regions::RegionKind;
 use crate_metadata::*;
 use hashbrown::HashMap;
 use xmas_elf::{ElfFile, sections::{SectionData, ShType, SHF_ALLOC, SHF_WRITE, SHF_EXECINSTR, SHF_TLS}};
 use no_drop::NoDrop;
 
+mod dwarf;
+mod protect;
+mod symbol_file;
 
 /// The file name (without extension) that we expect to see in the namespace's kernel crate directory.
 /// The trailing period '.' is there to avoid matching the "nano_core-<hash>.o" object file.
 const NANO_CORE_FILENAME_PREFIX: &str = "nano_core.";
-const NANO_CORE_CRATE_NAME: &str = "nano_core";
+pub(crate) const NANO_CORE_CRATE_NAME: &str = "nano_core";
+
+/// If `true`, a nano_core `.bin` file is parsed using its DWARF debug info,
+/// which describes each function and static variable along with its exact size and linkage name.
+/// Only the symbols that aren't described by the debug info, e.g., those defined in assembly code,
+/// are then obtained from the symbol table.
+///
+/// If `false`, or if the nano_core's debug info was stripped,
+/// a `.bin` file is parsed using only its symbol table.
+const PARSE_NANO_CORE_DEBUG_INFO: bool = true;
 
 /// The items returned from the [`parse_nano_core()`] routine.
 pub struct NanoCoreItems {
@@ -44,6 +56,13 @@ pub struct NanoCoreItems {
 /// We consider both `GLOBAL` and `WEAK` symbols to be global public symbols; this is necessary because symbols that are
 /// compiler builtins, such as memset, memcpy, etc, are symbols with weak linkage in newer versions of Rust (2021 and later).
 ///
+/// `load_bias` is the difference between the address at which the nano_core was loaded
+/// and the address at which it was linked, e.g., the slide chosen by a bootloader that loads
+/// the kernel at a randomized base address (KASLR), or `0` if the nano_core runs at its linked addresses.
+/// It's added to every address in the nano_core file before that address is used to find
+/// the section's offset into `text_pages`, `rodata_pages`, or `data_pages`.
+/// A slide towards lower addresses is given as its two's complement, i.e., `slide.wrapping_neg()`.
+///
 /// # Return
 /// * If successful, this returns the set of important [`NanoCoreItems`].
 /// * If an error occurs, the returned `Result::Err` contains the passed-in `text_pages`, `rodata_pages`, and `data_pages`,
@@ -54,8 +73,9 @@ pub fn parse_nano_core(
     text_pages: MappedPages,
     rodata_pages: MappedPages,
     data_pages: MappedPages,
+    load_bias: usize,
     verbose_log: bool,
-) -> Result<NanoCoreItems, (&'static str, NoDrop<[Arc<Mutex<MappedPages>>; 3]>)> {
+) -> Result<NanoCoreItems, (LoadError, NoDrop<[Arc<Mutex<MappedPages>>; 3]>)> {
     let text_pages   = Arc::new(Mutex::new(text_pages));
     let rodata_pages = Arc::new(Mutex::new(rodata_pages));
     let data_pages   = Arc::new(Mutex::new(data_pages));
@@ -66,8 +86,8 @@ pub fn parse_nano_core(
         ($expr:expr) => {
             match $expr {
                 Ok(val) => val,
-                Err(err_msg) => return Err((
-                    err_msg,
+                Err(err) => return Err((
+                    LoadError::from(err),
                     NoDrop::new([text_pages, rodata_pages, data_pages]),
                 )),
             }
@@ -103,18 +123,20 @@ pub fn parse_nano_core(
                 &text_pages,
                 &rodata_pages,
                 &data_pages,
+                load_bias,
                 verbose_log
             )
         }
         Some("bin") => {
             parse_nano_core_symbol_file_or_binary(
-                parse_nano_core_binary,
+                if PARSE_NANO_CORE_DEBUG_INFO { parse_nano_core_debug_info } else { parse_nano_core_binary },
                 bytes,
                 Arc::clone(&nano_core_file),
                 real_namespace,
                 &text_pages,
                 &rodata_pages,
                 &data_pages,
+                load_bias,
                 verbose_log
             )
         }
@@ -122,7 +144,7 @@ pub fn parse_nano_core(
             let (deserialized, _): (crate_metadata_serde::SerializedCrate, _) = try_mp!(
                 bincode::serde::decode_from_slice(bytes, bincode::config::standard()).map_err(|e| {
                     error!("parse_nano_core(): error deserializing nano_core: {e}");
-                    "parse_nano_core(): error deserializing nano_core"
+                    LoadError::InvalidFile("parse_nano_core(): error deserializing nano_core")
                 })
             );
             drop(nano_core_file_locked);
@@ -133,16 +155,25 @@ pub fn parse_nano_core(
                 &text_pages,
                 &rodata_pages,
                 &data_pages,
+                load_bias,
                 verbose_log,
             )
         },
-        _ => Err(
+        _ => Err(LoadError::InvalidFile(
             "nano_core object file had unexpected file extension. Expected \".bin\", \".sym\" or \".serde\"",
-        ),
+        )),
     };
 
     let (nano_core_crate_ref, init_symbol_values, num_new_symbols) = try_mp!(parse_result);
 
+    // Now that we know where the nano_core's sections are, ensure that its code and read-only data
+    // can't be modified, since the bootloader's mappings may be more permissive than necessary.
+    let num_fixed = try_mp!(protect::protect_nano_core_pages(&nano_core_crate_ref.lock_as_ref()));
+    if num_fixed > 0 {
+        warn!("parse_nano_core(): fixed {} violations of the nano_core's page permissions.", num_fixed);
+    }
+    reserve_static_data_regions(&nano_core_crate_ref.lock_as_ref());
+
     // Now that we've initialized the nano_core, i.e., set up its sections,
     // we can obtain a new TLS data image and initialize the TLS register to point to it.
     early_tls::insert(namespace.get_tls_initializer_data());
@@ -154,6 +185,27 @@ pub fn parse_nano_core(
     })
 }
 
+/// Returns the range of virtual addresses covered by the nano_core's sections of the given type,
+/// e.g., the bounds of its `.data` or `.bss` sections, if it has any such sections.
+pub fn section_bounds(nano_core: &LoadedCrate, typ: SectionType) -> Option<Range<VirtualAddress>> {
+    nano_core.sections.values()
+        .filter(|sec| sec.typ == typ && sec.size > 0)
+        .map(|sec| sec.virt_addr..(sec.virt_addr + sec.size))
+        .reduce(|bounds, range| bounds.start.min(range.start)..bounds.end.max(range.end))
+}
+
+/// Reserves the nano_core's `.data` and `.bss` bounds as static data regions,
+/// such that any other region reserved later is checked against them.
+fn reserve_static_data_regions(nano_core: &LoadedCrate) {
+    for (name, typ) in [("nano_core .data", SectionType::Data), ("nano_core .bss", SectionType::Bss)] {
+        if let Some(bounds) = section_bounds(nano_core, typ) {
+            debug!("parse_nano_core(): {} spans {:#X} to {:#X}", name, bounds.start, bounds.end);
+            // An overlap is reported by `reserve()` itself, and isn't fatal to the nano_core.
+            let _ = memory_regions::reserve(name, RegionKind::StaticData, bounds);
+        }
+    }
+}
+
 #[allow(clippy::too_many_arguments)]
 fn parse_nano_core_symbol_file_or_binary(
     f: fn(
@@ -163,17 +215,19 @@ fn parse_nano_core_symbol_file_or_binary(
         &Arc<Mutex<MappedPages>>,
         &Arc<Mutex<MappedPages>>,
         &Arc<Mutex<MappedPages>>,
-    ) -> Result<ParsedCrateItems, &'static str>,
+        usize,
+    ) -> Result<ParsedCrateItems, LoadError>,
     bytes: &[u8],
     nano_core_file: FileRef,
     real_namespace: &Arc<CrateNamespace>,
     text_pages: &Arc<Mutex<MappedPages>>,
     rodata_pages: &Arc<Mutex<MappedPages>>,
     data_pages: &Arc<Mutex<MappedPages>>,
+    load_bias: usize,
     verbose_log: bool,
 ) -> Result<
     (StrongCrateRef, BTreeMap<String, usize>, usize),
-    &'static str,
+    LoadError,
 > {
     let crate_name = StrRef::from(NANO_CORE_CRATE_NAME);
     // Create the LoadedCrate instance to represent the nano_core. It will be properly
@@ -190,6 +244,9 @@ fn parse_nano_core_symbol_file_or_binary(
         tls_sections:        BTreeSet::new(),
         cls_sections:        BTreeSet::new(),
         data_sections:       BTreeSet::new(),
+        eh_frame:            None,
+        init_array:          Vec::new(),
+        fini_array:          Vec::new(),
         reexported_symbols:  BTreeSet::new(),
     });
 
@@ -199,7 +256,8 @@ fn parse_nano_core_symbol_file_or_binary(
         CowArc::downgrade(&nano_core_crate_ref), 
         text_pages, 
         rodata_pages, 
-        data_pages
+        data_pages,
+        load_bias,
     )?;
 
     // Access and propertly set the new_crate's sections list and other items.
@@ -210,9 +268,13 @@ fn parse_nano_core_symbol_file_or_binary(
     let new_syms = real_namespace.add_symbols(parsed_crate_items.sections.values(), verbose_log);
     trace!("parse_nano_core(): finished adding symbols.");
 
+    new_crate_mut.eh_frame        = find_eh_frame(&parsed_crate_items.sections);
     new_crate_mut.sections        = parsed_crate_items.sections;
     new_crate_mut.global_sections = parsed_crate_items.global_sections;
     new_crate_mut.data_sections   = parsed_crate_items.data_sections;
+    if let Err(e) = unwind_info::register(&new_crate_mut) {
+        warn!("parse_nano_core(): couldn't register the nano_core's unwind info: {}", e);
+    }
     
     // // Dump loaded sections for verification. See pull request #559 for more details:
     // for (_, section) in new_crate_mut.sections.iter() {
@@ -222,7 +284,7 @@ fn parse_nano_core_symbol_file_or_binary(
     drop(new_crate_mut);
 
     // Add the newly-parsed nano_core crate to the kernel namespace.
-    real_namespace.crate_tree.lock().insert(crate_name, nano_core_crate_ref.clone_shallow());
+    real_namespace.add_crate(crate_name, nano_core_crate_ref.clone_shallow());
     info!("Finished parsing nano_core crate, {} new symbols.", new_syms);
     Ok((nano_core_crate_ref, parsed_crate_items.init_symbols, new_syms))
 }
@@ -230,6 +292,9 @@ fn parse_nano_core_symbol_file_or_binary(
 /// Parses the nano_core symbol file that represents the already loaded (and currently running) nano_core code.
 /// Basically, just searches the section list for offsets, size, and flag data,
 /// and parses the symbol table to populate the list of sections.
+///
+/// The symbol file may be in any of the formats supported by the [`symbol_file`] module,
+/// which is detected from its contents.
 fn parse_nano_core_symbol_file(
     bytes: &[u8],
     namespace:     &Arc<CrateNamespace>,
@@ -237,56 +302,33 @@ fn parse_nano_core_symbol_file(
     text_pages:    &Arc<Mutex<MappedPages>>,
     rodata_pages:  &Arc<Mutex<MappedPages>>,
     data_pages:    &Arc<Mutex<MappedPages>>,
-) -> Result<ParsedCrateItems, &'static str> {
-    let symbol_cstr = CStr::from_bytes_with_nul(bytes).map_err(|e| {
-        error!("parse_nano_core_symbol_file(): error casting nano_core symbol file to CStr: {:?}", e);
-        "FromBytesWithNulError occurred when casting nano_core symbol file to CStr"
+    load_bias:     usize,
+) -> Result<ParsedCrateItems, LoadError> {
+    // The symbol file is plain text of exactly `bytes.len()` bytes, so it needn't be null-terminated.
+    // Older symbol files did end with a null byte, which is ignored for compatibility.
+    let bytes = bytes.strip_suffix(&[0]).unwrap_or(bytes);
+    let symbol_str = core::str::from_utf8(bytes).map_err(|e| {
+        error!("parse_nano_core_symbol_file(): nano_core symbol file isn't valid UTF-8: {:?}", e);
+        LoadError::InvalidFile("Utf8Error occurred when parsing nano_core symbol file")
     })?;
-    let symbol_str = symbol_cstr.to_str().map_err(|e| {
-        error!("parse_nano_core_symbol_file(): error with CStr::to_str(): {:?}", e);
-        "Utf8Error occurred when parsing nano_core symbols CStr"
+    let (format, symbol_file) = symbol_file::parse(symbol_str).map_err(|e| {
+        error!("parse_nano_core_symbol_file(): error parsing nano_core symbol file: {}", e);
+        LoadError::InvalidFile(e)
     })?;
+    debug!("parse_nano_core_symbol_file(): parsed {} symbol file with {} sections and {} symbols",
+        format, symbol_file.sections.len(), symbol_file.symbols.len());
 
     let mut text_shndx:     Option<Shndx> = None;
     let mut rodata_shndx:   Option<Shndx> = None;
     let mut data_shndx:     Option<Shndx> = None;
     let mut bss_shndx:      Option<Shndx> = None;
+    let mut extra_shndxs:   BTreeMap<Shndx, SectionType> = BTreeMap::new();
     let mut tls_data_info:  Option<(Shndx, VirtualAddress)> = None;
     let mut tls_bss_info:   Option<(Shndx, VirtualAddress)> = None;
     let mut cls_info:       Option<(Shndx, VirtualAddress)> = None;
     let mut total_tls_size: usize = 0;
     let mut total_cls_size: usize = 0;
 
-    /// An internal function that parses a section header's index (e.g., "[7]") out of the given str.
-    /// Returns a tuple of the parsed `Shndx` and the rest of the unparsed str after the shndx.
-    fn parse_section_ndx(str_ref: &str) -> Option<(Shndx, &str)> {
-        let open  = str_ref.find('[');
-        let close = str_ref.find(']');
-        open.and_then(|start| close.and_then(|end|
-            str_ref.get(start + 1 .. end)
-                .and_then(|t| t.trim().parse::<usize>().ok())
-                .and_then(|shndx| str_ref.get(end + 1 ..).map(|the_rest| (shndx, the_rest)))
-        ))
-    }
-
-    /// An internal function that parses a section header's address and size
-    /// from a string that starts at the `Name` field of a line.
-    fn parse_section_vaddr_size(sec_hdr_line_starting_at_name: &str) -> Option<(VirtualAddress, usize)> {
-        let mut tokens = sec_hdr_line_starting_at_name.split_whitespace();
-        tokens.next(); // skip Name 
-        tokens.next(); // skip Type
-        let addr_hex_str = tokens.next();
-        tokens.next(); // skip Off (offset)
-        let size_hex_str = tokens.next();
-        // parse both the Address and Size fields as hex strings
-        addr_hex_str.and_then(|a| usize::from_str_radix(a, 16).ok())
-            .and_then(VirtualAddress::new)
-            .and_then(|vaddr| size_hex_str
-                .and_then(|s| usize::from_str_radix(s, 16).ok())
-                .map(|size| (vaddr, size))
-            )
-    }
-
     // We will fill in these crate items while parsing the symbol file.
     let mut crate_items = ParsedCrateItems::empty();
     // As the nano_core doesn't have one section per function/data/rodata, we fake it here with an arbitrary section counter
@@ -297,194 +339,83 @@ fn parse_nano_core_symbol_file(
     // The reason we first look for the section indices is because we create
     // individual sections per symbol instead of one for each of those four sections,
     // which is how normal Rust crates are built and loaded (one section per symbol).
-    let file_iterator = symbol_str.lines().enumerate();
-    for (_line_num, line) in file_iterator.clone() {
-
-        // skip empty lines
-        let line = line.trim();
-        if line.is_empty() { continue; }
-        // debug!("Looking at line: {:?}", line);
-
-        if line.contains(".text ") && line.contains("PROGBITS") {
-            text_shndx = parse_section_ndx(line).map(|(shndx, _)| shndx);
-        }
-        else if line.contains(".rodata ") && line.contains("PROGBITS") {
-            rodata_shndx = parse_section_ndx(line).map(|(shndx, _)| shndx);
-        }
-        else if line.contains(".tdata ") && line.contains("PROGBITS") {
-            tls_data_info = parse_section_ndx(line)
-                .and_then(|(shndx, rest_of_line)| parse_section_vaddr_size(rest_of_line)
-                    .map(|(vaddr, size)| {
-                        total_tls_size += size;
-                        (shndx, vaddr)
-                    })
-                );
-        }
-        else if line.contains(".tbss ") && line.contains("NOBITS") {
-            tls_bss_info = parse_section_ndx(line)
-                    .and_then(|(shndx, rest_of_line)| parse_section_vaddr_size(rest_of_line)
-                    .map(|(vaddr, size)| {
-                        total_tls_size += size;
-                        (shndx, vaddr)
-                    })
-                );
-        }
-        else if line.contains(".cls") && line.contains("PROGBITS") {
-            cls_info = parse_section_ndx(line)
-                .and_then(|(shndx, rest_of_line)| parse_section_vaddr_size(rest_of_line)
-                    .map(|(vaddr, size)| {
-                        total_cls_size += size;
-                        (shndx, vaddr)
-                    })
-                );
-        }
-        else if line.contains(".data ") && line.contains("PROGBITS") {
-            data_shndx = parse_section_ndx(line).map(|(shndx, _)| shndx);
-        }
-        else if line.contains(".bss ") && line.contains("NOBITS") {
-            bss_shndx = parse_section_ndx(line).map(|(shndx, _)| shndx);
-        }
-        else if let Some(start) = line.find(".eh_frame ") {
-            let (sec_vaddr, sec_size) = parse_section_vaddr_size(&line[start..])
-                .ok_or("Failed to parse the .eh_frame section header's address and size")?;
-            let mapped_pages_offset = rodata_pages.lock().offset_of_address(sec_vaddr)
-                .ok_or("the nano_core .eh_frame section wasn't covered by the read-only mapped pages!")?;
-            let typ = SectionType::EhFrame;
-            crate_items.sections.insert(
-                section_counter,
-                Arc::new(LoadedSection::new(
-                    typ,
-                    section_name_str_ref(&typ),
-                    Arc::clone(rodata_pages),
-                    mapped_pages_offset,
-                    sec_vaddr,
-                    sec_size,
-                    false, // .eh_frame is not global
-                    new_crate_weak_ref.clone(),
-                ))
-            );
-            section_counter += 1;
-        }
-        else if let Some(start) = line.find(".gcc_except_table ") {
-            let (sec_vaddr, sec_size) = parse_section_vaddr_size(&line[start..])
-                .ok_or("Failed to parse the .gcc_except_table section header's address and size")?;
-            let mapped_pages_offset = rodata_pages.lock().offset_of_address(sec_vaddr)
-                .ok_or("the nano_core .gcc_except_table section wasn't covered by the read-only mapped pages!")?;
-            let typ = SectionType::GccExceptTable;
-            crate_items.sections.insert(
-                section_counter,
-                Arc::new(LoadedSection::new(
-                    typ,
-                    section_name_str_ref(&typ),
-                    Arc::clone(rodata_pages),
-                    mapped_pages_offset,
-                    sec_vaddr,
-                    sec_size,
-                    false, // .gcc_except_table is not global
-                    new_crate_weak_ref.clone(),
-                ))
-            );
-            section_counter += 1;
+    // The .eh_frame and .gcc_except_table sections are each added as a single section.
+    let mut add_unwinding_section = |typ: SectionType, sec: &symbol_file::SectionHeader| -> Result<(), LoadError> {
+        let sec_vaddr = loaded_vaddr(sec.vaddr, load_bias)
+            .ok_or(LoadError::MappingFailed("the nano_core .eh_frame or .gcc_except_table section had an invalid address"))?;
+        let mapped_pages_offset = rodata_pages.lock().offset_of_address(sec_vaddr)
+            .ok_or(LoadError::MappingFailed("the nano_core .eh_frame or .gcc_except_table section wasn't covered by the read-only mapped pages!"))?;
+        crate_items.sections.insert(
+            section_counter,
+            Arc::new(LoadedSection::new(
+                typ,
+                section_name_str_ref(&typ),
+                Arc::clone(rodata_pages),
+                mapped_pages_offset,
+                sec_vaddr,
+                sec.size,
+                false, // .eh_frame and .gcc_except_table are not global
+                new_crate_weak_ref.clone(),
+            ))
+        );
+        section_counter += 1;
+        Ok(())
+    };
+    for sec in &symbol_file.sections {
+        let sec_vaddr = loaded_vaddr(sec.vaddr, load_bias);
+        match (sec.name, sec.typ) {
+            (".text",   "PROGBITS") => text_shndx   = Some(sec.shndx),
+            (".rodata", "PROGBITS") => rodata_shndx = Some(sec.shndx),
+            (".data",   "PROGBITS") => data_shndx   = Some(sec.shndx),
+            (".bss",    "NOBITS")   => bss_shndx    = Some(sec.shndx),
+            (".tdata",  "PROGBITS") => if let Some(vaddr) = sec_vaddr {
+                total_tls_size += sec.size;
+                tls_data_info = Some((sec.shndx, vaddr));
+            },
+            (".tbss",   "NOBITS")   => if let Some(vaddr) = sec_vaddr {
+                total_tls_size += sec.size;
+                tls_bss_info = Some((sec.shndx, vaddr));
+            },
+            (".cls",    "PROGBITS") => if let Some(vaddr) = sec_vaddr {
+                total_cls_size += sec.size;
+                cls_info = Some((sec.shndx, vaddr));
+            },
+            (".eh_frame", _)         => add_unwinding_section(SectionType::EhFrame, sec)?,
+            (".gcc_except_table", _) => add_unwinding_section(SectionType::GccExceptTable, sec)?,
+            (name, typ) => if let Some(extra_typ) = extra_section_type(name, typ == "NOBITS") {
+                extra_shndxs.insert(sec.shndx, extra_typ);
+            },
         }
     }
 
-    let text_shndx    = text_shndx  .ok_or("parse_nano_core_symbol_file(): couldn't find .text section index")?;
-    let rodata_shndx  = rodata_shndx.ok_or("parse_nano_core_symbol_file(): couldn't find .rodata section index")?;
-    let data_shndx    = data_shndx  .ok_or("parse_nano_core_symbol_file(): couldn't find .data section index")?;
-    let bss_shndx     = bss_shndx   .ok_or("parse_nano_core_symbol_file(): couldn't find .bss section index")?;
+    let text_shndx    = text_shndx  .ok_or(LoadError::SectionMissing("parse_nano_core_symbol_file(): couldn't find .text section index"))?;
+    let rodata_shndx  = rodata_shndx.ok_or(LoadError::SectionMissing("parse_nano_core_symbol_file(): couldn't find .rodata section index"))?;
+    let data_shndx    = data_shndx  .ok_or(LoadError::SectionMissing("parse_nano_core_symbol_file(): couldn't find .data section index"))?;
+    let bss_shndx     = bss_shndx   .ok_or(LoadError::SectionMissing("parse_nano_core_symbol_file(): couldn't find .bss section index"))?;
     let main_sec_info = MainSectionInfo {
         text_shndx,
         rodata_shndx,
         data_shndx,
         bss_shndx,
+        extra_shndxs,
         tls_data_info,
         tls_bss_info,
         cls_info,
         total_tls_size,
         total_cls_size,
+        load_bias,
     };
 
-    // second, skip ahead to the start of the symbol table: a line which contains ".symtab" but does NOT contain "SYMTAB"
-    let is_start_of_symbol_table = |line: &str| { line.contains(".symtab") && !line.contains("SYMTAB") };
-    let mut file_iterator = file_iterator.skip_while(|(_line_num, line)|  !is_start_of_symbol_table(line));
-    // skip the symbol table start line, e.g., "Symbol table '.symtab' contains N entries:"
-    if let Some((_num, _line)) = file_iterator.next() {
-        // trace!("SKIPPING LINE {}: {}", _num + 1, _line);
-    }
-    // skip one more line, the line with the column headers, e.g., "Num:     Value     Size Type   Bind   Vis ..."
-    if let Some((_num, _line)) = file_iterator.next() {
-        // trace!("SKIPPING LINE {}: {}", _num + 1, _line);
-    }
-
     {
         let text_pages_locked = text_pages.lock();
         let rodata_pages_locked = rodata_pages.lock();
         let data_pages_locked = data_pages.lock();
 
-        // third, parse each symbol table entry
-        for (_line_num, line) in file_iterator {
-            if line.is_empty() { continue; }
-            // CLS symbols have an OS specific symbol type which messes with the parser.
-            let line = line.replace("<OS specific>: ", "");
-            
-            // we need the following items from a symbol table entry:
-            // * Value (address),      column 1
-            // * Size,                 column 2
-            // * Bind (visibility),    column 4
-            // * Ndx,                  column 6
-            // * DemangledName#hash    column 7 to end
-
-            // Can't use split_whitespace() here, because we need to splitn and then get the remainder of the line
-            // after we've split the first 7 columns by whitespace. So we write a custom closure to group multiple whitespaces together.
-            // We use "splitn(8, ..)" because it stops at the 8th column (column index 7) and gets the rest of the line in a single iteration.
-            let mut prev_whitespace = true; // by default, we start assuming that the previous element was whitespace.
-            let mut parts = line.splitn(8, |c: char| {
-                if c.is_whitespace() {
-                    if prev_whitespace {
-                        false
-                    } else {
-                        prev_whitespace = true;
-                        true
-                    }
-                } else {
-                    prev_whitespace = false;
-                    false
-                }
-            }).map(str::trim);
-
-            let _num      = parts.next().ok_or("parse_nano_core_symbol_file(): couldn't get column 0 'Num'")?;
-            let sec_vaddr = parts.next().ok_or("parse_nano_core_symbol_file(): couldn't get column 1 'Value'")?;
-            let sec_size  = parts.next().ok_or("parse_nano_core_symbol_file(): couldn't get column 2 'Size'")?;
-            let _typ      = parts.next().ok_or("parse_nano_core_symbol_file(): couldn't get column 3 'Type'")?;
-            let bind      = parts.next().ok_or("parse_nano_core_symbol_file(): couldn't get column 4 'Bind'")?;
-            let _vis      = parts.next().ok_or("parse_nano_core_symbol_file(): couldn't get column 5 'Vis'")?;
-            let sec_ndx   = parts.next().ok_or("parse_nano_core_symbol_file(): couldn't get column 6 'Ndx'")?;
-            let name      = parts.next().ok_or("parse_nano_core_symbol_file(): couldn't get column 7 'Name'")?;
-            
-            let global = bind == "GLOBAL" || bind == "WEAK";
-            let sec_vaddr = usize::from_str_radix(sec_vaddr, 16).map_err(|e| {
-                error!("parse_nano_core_symbol_file(): error parsing virtual address Value at line {}: {:?}\n    line: {}", _line_num + 1, e, line);
-                "parse_nano_core_symbol_file(): couldn't parse virtual address (value column)"
-            })?;
-            let sec_size = sec_size.parse::<usize>().or_else(|e| {
-                sec_size.get(2 ..).ok_or(e).and_then(|sec_size_hex| usize::from_str_radix(sec_size_hex, 16))
-            }).map_err(|e| {
-                error!("parse_nano_core_symbol_file(): error parsing size at line {}: {:?}\n    line: {}", _line_num + 1, e, line);
-                "parse_nano_core_symbol_file(): couldn't parse size column"
-            })?;
-
-            // while vaddr and size are required, ndx could be valid or not. 
-            let sec_ndx = match sec_ndx.parse::<usize>() {
-                // If ndx is a valid number, proceed on. 
-                Ok(ndx) => ndx,
-                // Otherwise, if ndx is not a number (e.g., "ABS"), then we just skip that entry (go onto the next line). 
-                _ => {
-                    trace!("parse_nano_core_symbol_file(): skipping line {}: {}", _line_num + 1, line);
-                    continue;
-                }
-            };
-
-            // debug!("parse_nano_core_symbol_file(): name: {}, vaddr: {:#X}, size: {:#X}, sec_ndx {}", name, sec_vaddr, sec_size, sec_ndx);
+        // Second, add a section for each symbol.
+        for sym in &symbol_file.symbols {
+            // Names are demangled when the symbol file is generated or parsed, but any that are still mangled
+            // (e.g., with the v0 scheme) are demangled here; demangling is a no-op for other names.
+            let name = demangle_symbol(&sym.name);
 
             add_new_section(
                 namespace,
@@ -498,17 +429,16 @@ fn parse_nano_core_symbol_file(
                 &data_pages_locked,
                 &new_crate_weak_ref,
                 &mut section_counter,
-                sec_ndx,
-                StrRef::from(name),
-                sec_size,
-                sec_vaddr,
-                global
+                sym.shndx,
+                StrRef::from(name.as_str()),
+                sym.size,
+                sym.vaddr,
+                sym.global,
             )?;
-
-        } // end of loop over all lines
+        }
     }
     
-    trace!("parse_nano_core_symbol_file(): finished looping over symtab.");
+    trace!("parse_nano_core_symbol_file(): finished adding {} symbols.", symbol_file.symbols.len());
     Ok(crate_items)
 }
 
@@ -523,102 +453,275 @@ fn parse_nano_core_binary(
     text_pages:    &Arc<Mutex<MappedPages>>,
     rodata_pages:  &Arc<Mutex<MappedPages>>,
     data_pages:    &Arc<Mutex<MappedPages>>,
-) -> Result<ParsedCrateItems, &'static str> {
+    load_bias:     usize,
+) -> Result<ParsedCrateItems, LoadError> {
     let elf_file = ElfFile::new(bytes)?; // returns Err(&str) if ELF parse fails
+    let symtab = find_symbol_table(&elf_file)?;
+
+    // We will fill in these crate items while parsing the symbol file.
+    let mut crate_items = ParsedCrateItems::empty();
+    // As the nano_core doesn't have one section per function/data/rodata, we fake it here with an arbitrary section counter
+    let mut section_counter = 0;
+    let main_sec_info = parse_main_section_headers(
+        &elf_file,
+        &mut crate_items,
+        rodata_pages,
+        &new_crate_weak_ref,
+        &mut section_counter,
+        load_bias,
+    )?;
+
+    add_symbol_table_sections(
+        &elf_file,
+        symtab,
+        &BTreeSet::new(),
+        namespace,
+        &main_sec_info,
+        &mut crate_items,
+        text_pages,
+        rodata_pages,
+        data_pages,
+        &new_crate_weak_ref,
+        &mut section_counter,
+    )?;
+
+    Ok(crate_items)
+}
+
+/// Parses the nano_core ELF binary using its DWARF debug info.
+///
+/// Each function and static variable described by the debug info is added as a section,
+/// and then the remaining symbols in the symbol table are added as in [`parse_nano_core_binary()`].
+fn parse_nano_core_debug_info(
+    bytes: &[u8],
+    namespace:     &Arc<CrateNamespace>,
+    new_crate_weak_ref: WeakCrateRef,
+    text_pages:    &Arc<Mutex<MappedPages>>,
+    rodata_pages:  &Arc<Mutex<MappedPages>>,
+    data_pages:    &Arc<Mutex<MappedPages>>,
+    load_bias:     usize,
+) -> Result<ParsedCrateItems, LoadError> {
+    let elf_file = ElfFile::new(bytes)?; // returns Err(&str) if ELF parse fails
+    let symtab = find_symbol_table(&elf_file)?;
+
+    let mut crate_items = ParsedCrateItems::empty();
+    let mut section_counter = 0;
+    let main_sec_info = parse_main_section_headers(
+        &elf_file,
+        &mut crate_items,
+        rodata_pages,
+        &new_crate_weak_ref,
+        &mut section_counter,
+        load_bias,
+    )?;
+
+    let debug_symbols = dwarf::debug_symbols(|name| {
+        elf_file.find_section_by_name(name).map_or(&[][..], |sec| sec.raw_data(&elf_file))
+    })?;
+    if debug_symbols.is_empty() {
+        warn!("parse_nano_core_debug_info(): the nano_core has no debug info, using only its symbol table.");
+    }
+
+    // The debug info doesn't always mark symbols that the linker exported as external,
+    // so the symbol table determines which symbols are global as well.
+    use xmas_elf::symbol_table::{Entry, Binding};
+    let mut global_names = BTreeSet::new();
+    for entry in symtab.iter() {
+        if matches!(entry.get_binding(), Ok(Binding::Global | Binding::Weak)) {
+            global_names.insert(entry.get_name(&elf_file)?);
+        }
+    }
+
+    // The address range of each main and extra section, used to find which section a symbol is in.
+    let mut main_ranges = Vec::with_capacity(4 + main_sec_info.extra_shndxs.len());
+    let main_shndxs = [main_sec_info.text_shndx, main_sec_info.rodata_shndx, main_sec_info.data_shndx, main_sec_info.bss_shndx];
+    for shndx in main_shndxs.iter().copied().chain(main_sec_info.extra_shndxs.keys().copied()) {
+        let sec = elf_file.section_header(shndx as u16)?;
+        let start = sec.address() as usize;
+        main_ranges.push((shndx, start .. start + sec.size() as usize));
+    }
+    let tls_data_size = match main_sec_info.tls_data_info {
+        Some((shndx, _)) => elf_file.section_header(shndx as u16)?.size() as usize,
+        None => 0,
+    };
+
+    let mut parsed_names = BTreeSet::new();
+    {
+        let text_pages_locked = text_pages.lock();
+        let rodata_pages_locked = rodata_pages.lock();
+        let data_pages_locked = data_pages.lock();
+
+        for symbol in &debug_symbols {
+            // Symbols of unknown size, and those outside of the main and TLS sections (e.g., CLS symbols),
+            // are left to the symbol table.
+            if symbol.size == 0 { continue; }
+            let (shndx, sec_vaddr_value) = match symbol.location {
+                dwarf::SymbolLocation::Address(vaddr) => {
+                    match main_ranges.iter().find(|(_, range)| range.contains(&vaddr)) {
+                        Some((shndx, _)) => (*shndx, vaddr),
+                        None => continue,
+                    }
+                }
+                dwarf::SymbolLocation::TlsOffset(offset) => {
+                    // TLS symbols are located by their offset, and .tbss immediately follows .tdata.
+                    let tls_info = if offset < tls_data_size {
+                        main_sec_info.tls_data_info
+                    } else {
+                        main_sec_info.tls_bss_info
+                    };
+                    match tls_info {
+                        Some((shndx, _)) => (shndx, offset),
+                        None => continue,
+                    }
+                }
+            };
+            let global = symbol.global || global_names.contains(symbol.name.as_str());
+            let demangled = demangle_symbol(&symbol.name);
+
+            add_new_section(
+                namespace,
+                &main_sec_info,
+                &mut crate_items,
+                text_pages,
+                rodata_pages,
+                data_pages,
+                &text_pages_locked,
+                &rodata_pages_locked,
+                &data_pages_locked,
+                &new_crate_weak_ref,
+                &mut section_counter,
+                shndx,
+                demangled.as_str().into(),
+                symbol.size,
+                sec_vaddr_value,
+                global
+            )?;
+            parsed_names.insert(symbol.name.as_str());
+        }
+    }
 
+    // Add the symbols that weren't described by the debug info,
+    // e.g., those defined in assembly code and the linker-defined constants.
+    add_symbol_table_sections(
+        &elf_file,
+        symtab,
+        &parsed_names,
+        namespace,
+        &main_sec_info,
+        &mut crate_items,
+        text_pages,
+        rodata_pages,
+        data_pages,
+        &new_crate_weak_ref,
+        &mut section_counter,
+    )?;
+
+    Ok(crate_items)
+}
+
+/// Returns the symbol table of the given nano_core ELF binary.
+fn find_symbol_table<'a>(elf_file: &ElfFile<'a>) -> Result<&'a [xmas_elf::symbol_table::Entry64], LoadError> {
     // For us to properly load the ELF file, it must NOT have been stripped,
     // meaning that it must still have its symbol table section. Otherwise, relocations will not work.
     let sssec = elf_file.section_iter().find(|sec| sec.get_type() == Ok(ShType::SymTab));
-    let symtab = match sssec.ok_or("no symtab section").and_then(|s| s.get_data(&elf_file)) {
-        Ok(SectionData::SymbolTable64(symtab)) => symtab,
+    match sssec.ok_or("no symtab section").and_then(|s| s.get_data(elf_file)) {
+        Ok(SectionData::SymbolTable64(symtab)) => Ok(symtab),
         _ => {
             error!("parse_nano_core_binary(): can't load file: no symbol table found. Was file stripped?");
-            return Err("cannot load nano_core: no symbol table found. Was file stripped?");
+            Err(LoadError::SectionMissing("cannot load nano_core: no symbol table found. Was file stripped?"))
         }
-    };
-    
+    }
+}
+
+/// Finds the main sections of the given nano_core ELF binary: .text, .rodata, .data, .bss, and optionally TLS sections.
+///
+/// The .eh_frame and .gcc_except_table sections are added to the given `crate_items` directly.
+fn parse_main_section_headers(
+    elf_file:           &ElfFile,
+    crate_items:        &mut ParsedCrateItems,
+    rodata_pages:       &Arc<Mutex<MappedPages>>,
+    new_crate_weak_ref: &WeakCrateRef,
+    section_counter:    &mut Shndx,
+    load_bias:          usize,
+) -> Result<MainSectionInfo, LoadError> {
     // Find info about the main sections: .text, .rodata, .data, .bss, and optionally TLS sections
     let mut text_shndx:     Option<Shndx> = None;
     let mut rodata_shndx:   Option<Shndx> = None;
     let mut data_shndx:     Option<Shndx> = None;
     let mut bss_shndx:      Option<Shndx> = None;
+    let mut extra_shndxs:   BTreeMap<Shndx, SectionType> = BTreeMap::new();
     let mut tls_data_info:  Option<(Shndx, VirtualAddress)> = None;
     let mut tls_bss_info:   Option<(Shndx, VirtualAddress)> = None;
     let mut cls_info:       Option<(Shndx, VirtualAddress)> = None;
     let mut total_tls_size: usize = 0;
     let mut total_cls_size: usize = 0;
 
-    // We will fill in these crate items while parsing the symbol file.
-    let mut crate_items = ParsedCrateItems::empty();
-    // As the nano_core doesn't have one section per function/data/rodata, we fake it here with an arbitrary section counter
-    let mut section_counter = 0;
-    
     for (shndx, sec) in elf_file.section_iter().enumerate() {
         // trace!("parse_nano_core_binary(): looking at sec[{}]: {:?}", shndx, sec);
         // skip null section and any empty sections
         let sec_size = sec.size() as usize;
         if sec_size == 0 { continue; }
                
-        match sec.get_name(&elf_file) {
+        match sec.get_name(elf_file) {
             Ok(".text") => {
                 if sec.flags() & (SHF_ALLOC | SHF_WRITE | SHF_EXECINSTR) != (SHF_ALLOC | SHF_EXECINSTR) {
-                    return Err(".text section had wrong flags!");
+                    return Err(LoadError::InvalidSection(".text section had wrong flags!"));
                 }
                 text_shndx = Some(shndx);
             }
             Ok(".rodata") => {
                 if sec.flags() & (SHF_ALLOC | SHF_WRITE | SHF_EXECINSTR) != (SHF_ALLOC) {
-                    return Err(".rodata section had wrong flags!");
+                    return Err(LoadError::InvalidSection(".rodata section had wrong flags!"));
                 }
                 rodata_shndx = Some(shndx);
             }
             Ok(".data") => {
                 if sec.flags() & (SHF_ALLOC | SHF_WRITE | SHF_EXECINSTR) != (SHF_ALLOC | SHF_WRITE) {
-                    return Err(".data section had wrong flags!");
+                    return Err(LoadError::InvalidSection(".data section had wrong flags!"));
                 }
                 data_shndx = Some(shndx);
             }
             Ok(".bss") => {
                 if sec.flags() & (SHF_ALLOC | SHF_WRITE | SHF_EXECINSTR) != (SHF_ALLOC | SHF_WRITE) {
-                    return Err(".bss section had wrong flags!");
+                    return Err(LoadError::InvalidSection(".bss section had wrong flags!"));
                 }
                 bss_shndx = Some(shndx);
             }
             Ok(".tdata") => {
                 if sec.flags() & (SHF_ALLOC | SHF_WRITE | SHF_EXECINSTR | SHF_TLS) != (SHF_ALLOC | SHF_WRITE | SHF_TLS) {
-                    return Err(".tdata section had wrong flags!");
+                    return Err(LoadError::InvalidSection(".tdata section had wrong flags!"));
                 }
-                let sec_vaddr = VirtualAddress::new(sec.address() as usize)
-                    .ok_or("the nano_core .tdata section had an invalid virtual address")?;
+                let sec_vaddr = loaded_vaddr(sec.address() as usize, load_bias)
+                    .ok_or(LoadError::MappingFailed("the nano_core .tdata section had an invalid virtual address"))?;
                 tls_data_info = Some((shndx, sec_vaddr));
                 total_tls_size += sec_size;
             }
             Ok(".tbss") => {
                 if sec.flags() & (SHF_ALLOC | SHF_WRITE | SHF_EXECINSTR | SHF_TLS) != (SHF_ALLOC | SHF_WRITE | SHF_TLS) {
-                    return Err(".tbss section had wrong flags!");
+                    return Err(LoadError::InvalidSection(".tbss section had wrong flags!"));
                 }
-                let sec_vaddr = VirtualAddress::new(sec.address() as usize)
-                    .ok_or("the nano_core .tbss section had an invalid virtual address")?;
+                let sec_vaddr = loaded_vaddr(sec.address() as usize, load_bias)
+                    .ok_or(LoadError::MappingFailed("the nano_core .tbss section had an invalid virtual address"))?;
                 tls_bss_info = Some((shndx, sec_vaddr));
                 total_tls_size += sec_size;
             }
             Ok(".cls") => {
                 if sec.flags() & (SHF_ALLOC | SHF_WRITE | SHF_EXECINSTR | CLS_SECTION_FLAG) != (SHF_ALLOC | SHF_WRITE | CLS_SECTION_FLAG) {
-                    return Err(".cls section had wrong flags!");
+                    return Err(LoadError::InvalidSection(".cls section had wrong flags!"));
                 }
-                let sec_vaddr = VirtualAddress::new(sec.address() as usize)
-                    .ok_or("the nano_core .cls section had an invalid virtual address")?;
+                let sec_vaddr = loaded_vaddr(sec.address() as usize, load_bias)
+                    .ok_or(LoadError::MappingFailed("the nano_core .cls section had an invalid virtual address"))?;
                 cls_info = Some((shndx, sec_vaddr));
                 total_cls_size += sec_size;
             }
             Ok(".gcc_except_table") => {
-                let sec_vaddr = VirtualAddress::new(sec.address() as usize)
-                    .ok_or("the nano_core .gcc_except_table section had an invalid virtual address")?;
+                let sec_vaddr = loaded_vaddr(sec.address() as usize, load_bias)
+                    .ok_or(LoadError::MappingFailed("the nano_core .gcc_except_table section had an invalid virtual address"))?;
                 let mapped_pages_offset = rodata_pages.lock().offset_of_address(sec_vaddr)
-                    .ok_or("the nano_core .gcc_except_table section wasn't covered by the read-only mapped pages!")?;
+                    .ok_or(LoadError::MappingFailed("the nano_core .gcc_except_table section wasn't covered by the read-only mapped pages!"))?;
                 let typ = SectionType::GccExceptTable;
                 crate_items.sections.insert(
-                    section_counter,
+                    *section_counter,
                     Arc::new(LoadedSection::new(
                         typ,
                         section_name_str_ref(&typ),
@@ -630,16 +733,16 @@ fn parse_nano_core_binary(
                         new_crate_weak_ref.clone(),
                     ))
                 );
-                section_counter += 1;
+                *section_counter += 1;
             }
             Ok(".eh_frame") => {
-                let sec_vaddr = VirtualAddress::new(sec.address() as usize)
-                    .ok_or("the nano_core .eh_frame section had an invalid virtual address")?;
+                let sec_vaddr = loaded_vaddr(sec.address() as usize, load_bias)
+                    .ok_or(LoadError::MappingFailed("the nano_core .eh_frame section had an invalid virtual address"))?;
                 let mapped_pages_offset = rodata_pages.lock().offset_of_address(sec_vaddr)
-                    .ok_or("the nano_core .eh_frame section wasn't covered by the read-only mapped pages!")?;
+                    .ok_or(LoadError::MappingFailed("the nano_core .eh_frame section wasn't covered by the read-only mapped pages!"))?;
                 let typ = SectionType::EhFrame;
                 crate_items.sections.insert(
-                    section_counter,
+                    *section_counter,
                     Arc::new(LoadedSection::new(
                         typ,
                         section_name_str_ref(&typ),
@@ -651,7 +754,18 @@ fn parse_nano_core_binary(
                         new_crate_weak_ref.clone(),
                     ))
                 );
-                section_counter += 1;
+                *section_counter += 1;
+            }
+            Ok(name) => {
+                let Some(typ) = extra_section_type(name, sec.get_type() == Ok(ShType::NoBits)) else {
+                    continue;
+                };
+                let expected_flags = if typ == SectionType::Rodata { SHF_ALLOC } else { SHF_ALLOC | SHF_WRITE };
+                if sec.flags() & (SHF_ALLOC | SHF_WRITE | SHF_EXECINSTR) != expected_flags {
+                    error!("parse_nano_core_binary(): section {:?} had wrong flags: {:#X}", name, sec.flags());
+                    return Err(LoadError::InvalidSection("a nano_core section had wrong flags!"));
+                }
+                extra_shndxs.insert(shndx, typ);
             }
             _ => {
                 continue;
@@ -659,65 +773,104 @@ fn parse_nano_core_binary(
         }
     }
 
-    let text_shndx    = text_shndx.ok_or("couldn't find .text section in nano_core ELF")?;
-    let rodata_shndx  = rodata_shndx.ok_or("couldn't find .rodata section in nano_core ELF")?;
-    let data_shndx    = data_shndx.ok_or("couldn't find .data section in nano_core ELF")?;
-    let bss_shndx     = bss_shndx.ok_or("couldn't find .bss section in nano_core ELF")?;
-    let main_sec_info = MainSectionInfo {
+    let text_shndx    = text_shndx.ok_or(LoadError::SectionMissing("couldn't find .text section in nano_core ELF"))?;
+    let rodata_shndx  = rodata_shndx.ok_or(LoadError::SectionMissing("couldn't find .rodata section in nano_core ELF"))?;
+    let data_shndx    = data_shndx.ok_or(LoadError::SectionMissing("couldn't find .data section in nano_core ELF"))?;
+    let bss_shndx     = bss_shndx.ok_or(LoadError::SectionMissing("couldn't find .bss section in nano_core ELF"))?;
+    Ok(MainSectionInfo {
         text_shndx,
         rodata_shndx,
         data_shndx,
         bss_shndx,
+        extra_shndxs,
         tls_data_info,
         tls_bss_info,
         cls_info,
         total_tls_size,
         total_cls_size,
-    };
-    
-    {
-        let text_pages_locked = text_pages.lock();
-        let rodata_pages_locked = rodata_pages.lock();
-        let data_pages_locked = data_pages.lock();
+        load_bias,
+    })
+}
 
-        // Iterate through the symbol table so we can find which sections are global (publicly visible).
-        use xmas_elf::symbol_table::{Entry, Binding};
-        for entry in symtab.iter() {
-            if let (Ok(bind), Ok(typ)) = (entry.get_binding(), entry.get_type()) {
-                // public symbols can have any visibility setting, but it's the binding that matters (GLOBAL/WEAK vs. LOCAL)
-                let global = bind == Binding::Global || bind == Binding::Weak;
-                if (typ == xmas_elf::symbol_table::Type::Func || typ == xmas_elf::symbol_table::Type::Object) || global {
-                    let sec_vaddr_value = entry.value() as usize;
-                    let sec_size = entry.size() as usize;
-                    let name = entry.get_name(&elf_file)?;
-
-                    let demangled = demangle(name).to_string();
-                    // debug!("parse_nano_core_binary(): name: {}, demangled: {}, vaddr: {:#X}, size: {:#X}", name, demangled, sec_value, sec_size);
-
-                    add_new_section(
-                        namespace,
-                        &main_sec_info, 
-                        &mut crate_items, 
-                        text_pages, 
-                        rodata_pages, 
-                        data_pages, 
-                        &text_pages_locked,
-                        &rodata_pages_locked,
-                        &data_pages_locked,
-                        &new_crate_weak_ref,
-                        &mut section_counter,
-                        entry.shndx() as usize,
-                        demangled.as_str().into(),
-                        sec_size,
-                        sec_vaddr_value,
-                        global
-                    )?;
-                }
+/// Adds a section for each function, object, or global symbol in the given symbol table of the nano_core,
+/// skipping the symbols whose names are in `skipped_names`.
+#[allow(clippy::too_many_arguments)]
+fn add_symbol_table_sections(
+    elf_file:           &ElfFile,
+    symtab:             &[xmas_elf::symbol_table::Entry64],
+    skipped_names:      &BTreeSet<&str>,
+    namespace:          &Arc<CrateNamespace>,
+    main_sec_info:      &MainSectionInfo,
+    crate_items:        &mut ParsedCrateItems,
+    text_pages:         &Arc<Mutex<MappedPages>>,
+    rodata_pages:       &Arc<Mutex<MappedPages>>,
+    data_pages:         &Arc<Mutex<MappedPages>>,
+    new_crate_weak_ref: &WeakCrateRef,
+    section_counter:    &mut Shndx,
+) -> Result<(), LoadError> {
+    let text_pages_locked = text_pages.lock();
+    let rodata_pages_locked = rodata_pages.lock();
+    let data_pages_locked = data_pages.lock();
+
+    // Iterate through the symbol table so we can find which sections are global (publicly visible).
+    use xmas_elf::symbol_table::{Entry, Binding};
+    for entry in symtab.iter() {
+        if let (Ok(bind), Ok(typ)) = (entry.get_binding(), entry.get_type()) {
+            // public symbols can have any visibility setting, but it's the binding that matters (GLOBAL/WEAK vs. LOCAL)
+            let global = bind == Binding::Global || bind == Binding::Weak;
+            if (typ == xmas_elf::symbol_table::Type::Func || typ == xmas_elf::symbol_table::Type::Object) || global {
+                let sec_vaddr_value = entry.value() as usize;
+                let sec_size = entry.size() as usize;
+                let name = entry.get_name(elf_file)?;
+                if skipped_names.contains(name) { continue; }
+
+                let demangled = demangle_symbol(name);
+                // debug!("parse_nano_core_binary(): name: {}, demangled: {}, vaddr: {:#X}, size: {:#X}", name, demangled, sec_value, sec_size);
+
+                add_new_section(
+                    namespace,
+                    main_sec_info,
+                    crate_items,
+                    text_pages,
+                    rodata_pages,
+                    data_pages,
+                    &text_pages_locked,
+                    &rodata_pages_locked,
+                    &data_pages_locked,
+                    new_crate_weak_ref,
+                    section_counter,
+                    entry.shndx() as usize,
+                    demangled.as_str().into(),
+                    sec_size,
+                    sec_vaddr_value,
+                    global
+                )?;
             }
         }
     }
+    Ok(())
+}
 
-    Ok(crate_items)
+/// Returns the address at which the nano_core item linked at `linked_vaddr` was loaded,
+/// given the nano_core's `load_bias`.
+fn loaded_vaddr(linked_vaddr: usize, load_bias: usize) -> Option<VirtualAddress> {
+    VirtualAddress::new(linked_vaddr.wrapping_add(load_bias))
+}
+
+/// The section index of absolute symbols, whose values aren't addresses within any section.
+const SHN_ABS: Shndx = 0xFFF1;
+
+/// Returns the value of a symbol that isn't in any of the main, TLS, or CLS sections
+/// (e.g., a symbol in `.init`), which is recorded as an init symbol.
+///
+/// Like all other nano_core addresses, its value must include the `load_bias`,
+/// unless it's an absolute symbol (e.g., a linker constant), whose value isn't an address.
+fn init_symbol_value(sec_ndx: Shndx, value: usize, load_bias: usize) -> usize {
+    if sec_ndx == SHN_ABS {
+        value
+    } else {
+        value.wrapping_add(load_bias)
+    }
 }
 
 /// The collection of sections and symbols obtained while parsing the nano_core crate.
@@ -741,20 +894,72 @@ impl ParsedCrateItems {
 }
 
 /// The section header indices (shndx) for the main sections:
-/// .text, .rodata, .data, and .bss.
+/// .text, .rodata, .data, and .bss, along with any of the [`EXTRA_SECTIONS`].
 /// 
 /// If TLS sections are present, e.g., .tdata or .tbss, 
 /// their `shndx` and virtual address are also included here.
+/// These virtual addresses already include the `load_bias`, which must still be applied
+/// to the addresses of symbols in the main sections.
 struct MainSectionInfo {
     text_shndx:      Shndx,
     rodata_shndx:    Shndx,
     data_shndx:      Shndx,
     bss_shndx:       Shndx,
+    /// The extra sections that are present, mapped to the type of their symbols.
+    extra_shndxs:    BTreeMap<Shndx, SectionType>,
     tls_data_info:   Option<(Shndx, VirtualAddress)>,
     cls_info:        Option<(Shndx, VirtualAddress)>,
     tls_bss_info:    Option<(Shndx, VirtualAddress)>,
     total_tls_size:  usize,
     total_cls_size:  usize,
+    load_bias:       usize,
+}
+
+impl MainSectionInfo {
+    /// Returns the type of the symbols in the section with the given index,
+    /// if it's one of the main sections or an extra section.
+    fn section_type(&self, shndx: Shndx) -> Option<SectionType> {
+        if shndx == self.text_shndx {
+            Some(SectionType::Text)
+        } else if shndx == self.rodata_shndx {
+            Some(SectionType::Rodata)
+        } else if shndx == self.data_shndx {
+            Some(SectionType::Data)
+        } else if shndx == self.bss_shndx {
+            Some(SectionType::Bss)
+        } else {
+            self.extra_shndxs.get(&shndx).copied()
+        }
+    }
+}
+
+/// Sections other than the main .text, .rodata, .data, and .bss sections
+/// whose symbols are loaded just like those of a main section,
+/// because the linker places them adjacent to it, such that they're covered by the same pages.
+///
+/// Each is given along with the type of its symbols, which determines the pages that contain it.
+/// The TLS and CLS sections (.tdata, .tbss, and .cls) are handled separately,
+/// as their symbols' addresses are offsets into the TLS or CLS area.
+///
+/// Read-only data that contains relocations (.data.rel.ro) isn't listed here because
+/// the nano_core's linker script folds it into .data, and the global offset table (.got)
+/// isn't listed because it's unsupported and thus never loaded.
+const EXTRA_SECTIONS: &[(&str, SectionType)] = &[
+    // RISC-V's small data sections.
+    (".srodata",     SectionType::Rodata),
+    (".sdata",       SectionType::Data),
+    (".sbss",        SectionType::Bss),
+];
+
+/// Returns the type of the symbols in the extra section with the given `name`, if it's one of the [`EXTRA_SECTIONS`].
+///
+/// A section is only recognized if it's `nobits` (i.e., has no data in the file) exactly when its symbols are .bss symbols;
+/// otherwise, it isn't loaded into the pages of the main section.
+fn extra_section_type(name: &str, nobits: bool) -> Option<SectionType> {
+    EXTRA_SECTIONS.iter()
+        .find(|(extra_name, _)| *extra_name == name)
+        .map(|(_, typ)| *typ)
+        .filter(|typ| (*typ == SectionType::Bss) == nobits)
 }
 
 /// A convenience function that separates out the logic 
@@ -779,57 +984,24 @@ fn add_new_section(
     sec_size: usize,
     sec_vaddr: usize,
     global: bool,
-) -> Result<(), &'static str> {
-    let new_section = if sec_ndx == main_section_info.text_shndx {
-        let sec_vaddr = VirtualAddress::new(sec_vaddr)
-            .ok_or("new text section had invalid virtual address")?;
-        Some(Arc::new(LoadedSection::new(
-            SectionType::Text,
-            sec_name,
-            Arc::clone(text_pages),
-            text_pages_locked.offset_of_address(sec_vaddr).ok_or("nano_core text section wasn't covered by its mapped pages!")?,
-            sec_vaddr,
-            sec_size,
-            global,
-            new_crate_weak_ref.clone(), 
-        )))
-    }
-    else if sec_ndx == main_section_info.rodata_shndx {
-        let sec_vaddr = VirtualAddress::new(sec_vaddr)
-            .ok_or("new rodata section had invalid virtual address")?;
-        Some(Arc::new(LoadedSection::new(
-            SectionType::Rodata,
-            sec_name,
-            Arc::clone(rodata_pages),
-            rodata_pages_locked.offset_of_address(sec_vaddr).ok_or("nano_core rodata section wasn't covered by its mapped pages!")?,
-            sec_vaddr,
-            sec_size,
-            global,
-            new_crate_weak_ref.clone(),
-        )))
-    }
-    else if sec_ndx == main_section_info.data_shndx {
-        let sec_vaddr = VirtualAddress::new(sec_vaddr)
-            .ok_or("new data section had invalid virtual address")?;
-        Some(Arc::new(LoadedSection::new(
-            SectionType::Data,
-            sec_name,
-            Arc::clone(data_pages),
-            data_pages_locked.offset_of_address(sec_vaddr).ok_or("nano_core data section wasn't covered by its mapped pages!")?,
-            sec_vaddr,
-            sec_size,
-            global,
-            new_crate_weak_ref.clone(),
-        )))
-    }
-    else if sec_ndx == main_section_info.bss_shndx {
-        let sec_vaddr = VirtualAddress::new(sec_vaddr)
-            .ok_or("new bss section had invalid virtual address")?;
+) -> Result<(), LoadError> {
+    let new_section = if let Some(typ) = main_section_info.section_type(sec_ndx) {
+        let (pages, pages_locked) = match typ {
+            SectionType::Text   => (text_pages, text_pages_locked),
+            SectionType::Rodata => (rodata_pages, rodata_pages_locked),
+            _                   => (data_pages, data_pages_locked),
+        };
+        let sec_vaddr = loaded_vaddr(sec_vaddr, main_section_info.load_bias)
+            .ok_or(LoadError::MappingFailed("new nano_core section had invalid virtual address"))?;
+        let mapped_pages_offset = pages_locked.offset_of_address(sec_vaddr).ok_or_else(|| {
+            error!("parse_nano_core: {:?} section {:?} at {:#X} wasn't covered by its mapped pages", typ, sec_name.as_str(), sec_vaddr.value());
+            LoadError::MappingFailed("nano_core section wasn't covered by its mapped pages!")
+        })?;
         Some(Arc::new(LoadedSection::new(
-            SectionType::Bss,
+            typ,
             sec_name,
-            Arc::clone(data_pages),
-            data_pages_locked.offset_of_address(sec_vaddr).ok_or("nano_core bss section wasn't covered by its mapped pages!")?,
+            Arc::clone(pages),
+            mapped_pages_offset,
             sec_vaddr,
             sec_size,
             global,
@@ -853,8 +1025,8 @@ fn add_new_section(
             sec_name,
             Arc::clone(rodata_pages),
             // TLS sections are lumped into the ".rodata" MappedPages with the read-only data sections.
-            rodata_pages_locked.offset_of_address(tls_sec_data_vaddr).ok_or("nano_core TLS .tdata section wasn't covered by the .rodata mapped pages!")?,
-            VirtualAddress::new(tls_offset).ok_or("new TLS .tdata section had invalid virtual address (TLS offset)")?,
+            rodata_pages_locked.offset_of_address(tls_sec_data_vaddr).ok_or(LoadError::MappingFailed("nano_core TLS .tdata section wasn't covered by the .rodata mapped pages!"))?,
+            VirtualAddress::new(tls_offset).ok_or(LoadError::MappingFailed("new TLS .tdata section had invalid virtual address (TLS offset)"))?,
             sec_size,
             global,
             new_crate_weak_ref.clone(),
@@ -864,7 +1036,7 @@ fn add_new_section(
             tls_section,
             tls_offset,
             main_section_info.total_tls_size,
-        ).map_err(|_| "BUG: failed to add static TLS section to the TLS area")?;
+        ).map_err(|_| LoadError::MappingFailed("BUG: failed to add static TLS section to the TLS area"))?;
         Some(tls_section_ref)
     }
     else if main_section_info.tls_bss_info.map_or(false, |(shndx, _)| sec_ndx == shndx) {
@@ -885,7 +1057,7 @@ fn add_new_section(
             sec_name,
             Arc::clone(rodata_pages),
             mapped_pages_offset,
-            VirtualAddress::new(tls_offset).ok_or("new TLS .tbss section had invalid virtual address (TLS offset)")?,
+            VirtualAddress::new(tls_offset).ok_or(LoadError::MappingFailed("new TLS .tbss section had invalid virtual address (TLS offset)"))?,
             sec_size,
             global,
             new_crate_weak_ref.clone(),
@@ -895,7 +1067,7 @@ fn add_new_section(
             tls_section,
             tls_offset,
             main_section_info.total_tls_size,
-        ).map_err(|_| "BUG: failed to add static TLS section to the TLS area initializer")?;
+        ).map_err(|_| LoadError::MappingFailed("BUG: failed to add static TLS section to the TLS area initializer"))?;
         Some(tls_section_ref)
     }
     else if main_section_info.cls_info.map_or(false, |(shndx, _)| sec_ndx == shndx) {
@@ -907,8 +1079,8 @@ fn add_new_section(
             sec_name,
             Arc::clone(rodata_pages),
             // CLS sections are lumped into the ".rodata" MappedPages with the read-only data sections.
-            rodata_pages_locked.offset_of_address(cls_sec_data_vaddr).ok_or("nano_core CLS .cls section wasn't covered by the .rodata mapped pages!")?,
-            VirtualAddress::new(cls_offset).ok_or("new TLS .cls section had invalid virtual address (CLS offset)")?,
+            rodata_pages_locked.offset_of_address(cls_sec_data_vaddr).ok_or(LoadError::MappingFailed("nano_core CLS .cls section wasn't covered by the .rodata mapped pages!"))?,
+            VirtualAddress::new(cls_offset).ok_or(LoadError::MappingFailed("new TLS .cls section had invalid virtual address (CLS offset)"))?,
             sec_size,
             global,
             new_crate_weak_ref.clone(),
@@ -918,10 +1090,11 @@ fn add_new_section(
             cls_section,
             cls_offset,
             main_section_info.total_cls_size,
-        ).map_err(|_| "BUG: failed to add static CLS section to the CLS area")?;
+        ).map_err(|_| LoadError::MappingFailed("BUG: failed to add static CLS section to the CLS area"))?;
         Some(cls_section_ref)
     } else {
-        crate_items.init_symbols.insert(String::from(sec_name.as_str()), sec_vaddr);
+        let value = init_symbol_value(sec_ndx, sec_vaddr, main_section_info.load_bias);
+        crate_items.init_symbols.insert(String::from(sec_name.as_str()), value);
         None
     };
 
@@ -939,3 +1112,24 @@ fn add_new_section(
 
     Ok(())
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load_bias_applies_to_sections_and_init_symbols() {
+        let load_bias = 0x20_0000;
+        assert_eq!(loaded_vaddr(0xFFFF_FFFF_8010_0000, load_bias), VirtualAddress::new(0xFFFF_FFFF_8030_0000));
+        assert_eq!(loaded_vaddr(0xFFFF_FFFF_8010_0000, 0), VirtualAddress::new(0xFFFF_FFFF_8010_0000));
+        // A negative bias, i.e., the nano_core was loaded below its link address.
+        assert_eq!(loaded_vaddr(0xFFFF_FFFF_8030_0000, load_bias.wrapping_neg()), VirtualAddress::new(0xFFFF_FFFF_8010_0000));
+
+        // Init symbols (e.g., in `.init`) are biased just like sections.
+        assert_eq!(init_symbol_value(1, 0x10_0040, load_bias), 0x30_0040);
+        assert_eq!(init_symbol_value(1, 0x10_0040, 0), 0x10_0040);
+        // Absolute symbols aren't addresses, so they're never biased.
+        assert_eq!(init_symbol_value(SHN_ABS, 0x1000, load_bias), 0x1000);
+    }
+}
@@ -0,0 +1,233 @@
+//! Extracts the functions and static variables of the nano_core from its DWARF debug info.
+//!
+//! Each function and static is described by a debugging information entry (DIE)
+//! in the `.debug_info` section, which includes its linkage name, address, and size,
+//! and whether it's externally visible.
+//! Symbols that aren't described by the debug info, e.g., those defined in assembly
+//! or by the linker script, must instead be obtained from the symbol table.
+
+use alloc::{collections::BTreeSet, string::String, vec::Vec};
+use gimli::{AttributeValue, EndianSlice, LittleEndian, Operation, Unit, UnitOffset};
+
+type Reader<'a> = EndianSlice<'a, LittleEndian>;
+
+/// The maximum number of type DIEs that are followed to find the size of a static variable,
+/// e.g., through typedefs and `const` qualifiers.
+const MAX_TYPE_DEPTH: usize = 8;
+
+/// Where a symbol described by the debug info is located.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(super) enum SymbolLocation {
+    /// The symbol is at this virtual address.
+    Address(usize),
+    /// The symbol is a thread-local variable at this offset into the TLS area.
+    TlsOffset(usize),
+}
+
+/// A function or static variable described by the debug info.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(super) struct DebugSymbol {
+    /// The (mangled) linkage name of the symbol.
+    pub(super) name: String,
+    pub(super) location: SymbolLocation,
+    pub(super) size: usize,
+    /// Whether the symbol is externally visible.
+    pub(super) global: bool,
+}
+
+/// Returns every function and static variable described by the debug info,
+/// given a function that returns the contents of an ELF section by its name,
+/// or an empty slice if there's no such section.
+///
+/// Each symbol is returned only once, even if it's described by multiple compilation units.
+pub(super) fn debug_symbols<'a, F>(section_data: F) -> Result<Vec<DebugSymbol>, &'static str>
+where
+    F: Fn(&str) -> &'a [u8],
+{
+    let dwarf = gimli::Dwarf::load(|id| -> Result<Reader<'a>, ()> {
+        Ok(EndianSlice::new(section_data(id.name()), LittleEndian))
+    })
+    .map_err(|_| "failed to load the nano_core's DWARF sections")?;
+
+    let mut symbols = Vec::new();
+    let mut seen = BTreeSet::new();
+    let mut headers = dwarf.units();
+    while let Some(header) = headers.next().map_err(|_| "invalid DWARF unit header in the nano_core")? {
+        let unit = dwarf.unit(header).map_err(|_| "invalid DWARF unit in the nano_core")?;
+        let mut entries = unit.entries();
+        while let Some((_, entry)) = entries.next_dfs().map_err(|_| "invalid DWARF entry in the nano_core")? {
+            let symbol = match entry.tag() {
+                gimli::DW_TAG_subprogram => function(&dwarf, &unit, entry.offset()),
+                gimli::DW_TAG_variable => static_variable(&dwarf, &unit, entry.offset()),
+                _ => continue,
+            };
+            // Entries that can't be interpreted are skipped rather than failing the whole parse,
+            // as their symbols can still be found in the symbol table.
+            if let Ok(Some(symbol)) = symbol {
+                if seen.insert((symbol.name.clone(), symbol.location)) {
+                    symbols.push(symbol);
+                }
+            }
+        }
+    }
+    Ok(symbols)
+}
+
+/// Returns the symbol for the function described by the given DIE,
+/// or `None` if it wasn't emitted, e.g., because it was inlined or discarded by the linker.
+fn function(
+    dwarf: &gimli::Dwarf<Reader<'_>>,
+    unit: &Unit<Reader<'_>>,
+    offset: UnitOffset,
+) -> gimli::Result<Option<DebugSymbol>> {
+    let entry = unit.entry(offset)?;
+    let low_pc = match entry.attr_value(gimli::DW_AT_low_pc)? {
+        Some(value) => dwarf.attr_address(unit, value)?,
+        None => None,
+    };
+    // Functions discarded by the linker have their address set to zero.
+    let Some(low_pc) = low_pc.filter(|&pc| pc != 0) else {
+        return Ok(None);
+    };
+    let size = match entry.attr_value(gimli::DW_AT_high_pc)? {
+        Some(AttributeValue::Udata(size)) => size,
+        Some(value) => dwarf
+            .attr_address(unit, value)?
+            .map_or(0, |high_pc| high_pc.saturating_sub(low_pc)),
+        None => 0,
+    };
+    Ok(named_symbol(dwarf, unit, offset)?.map(|(name, global)| DebugSymbol {
+        name,
+        location: SymbolLocation::Address(low_pc as usize),
+        size: size as usize,
+        global,
+    }))
+}
+
+/// Returns the symbol for the static variable described by the given DIE,
+/// or `None` if it isn't a static, e.g., because it's a local variable on the stack.
+fn static_variable(
+    dwarf: &gimli::Dwarf<Reader<'_>>,
+    unit: &Unit<Reader<'_>>,
+    offset: UnitOffset,
+) -> gimli::Result<Option<DebugSymbol>> {
+    let entry = unit.entry(offset)?;
+    let Some(AttributeValue::Exprloc(expression)) = entry.attr_value(gimli::DW_AT_location)? else {
+        return Ok(None);
+    };
+    // Statics are located by `DW_OP_addr <address>`,
+    // and thread-local statics by `DW_OP_const* <offset> DW_OP_form_tls_address`.
+    let mut operations = expression.operations(unit.encoding());
+    let location = match (operations.next()?, operations.next()?, operations.next()?) {
+        (Some(Operation::Address { address }), None, _) if address != 0 => {
+            SymbolLocation::Address(address as usize)
+        }
+        (Some(Operation::UnsignedConstant { value }), Some(Operation::TLS), None) => {
+            SymbolLocation::TlsOffset(value as usize)
+        }
+        _ => return Ok(None),
+    };
+    let size = match entry.attr_value(gimli::DW_AT_type)? {
+        Some(AttributeValue::UnitRef(type_offset)) => type_size(unit, type_offset, 0)?.unwrap_or(0),
+        _ => 0,
+    };
+    Ok(named_symbol(dwarf, unit, offset)?.map(|(name, global)| DebugSymbol {
+        name,
+        location,
+        size: size as usize,
+        global,
+    }))
+}
+
+/// Returns the linkage name of the function or variable described by the given DIE,
+/// and whether it's externally visible.
+///
+/// The linkage name may also be given by the DIE of the item's abstract instance or declaration.
+/// Items whose names aren't mangled, e.g., `#[no_mangle]` functions, have no linkage name,
+/// so their regular name is used instead.
+fn named_symbol(
+    dwarf: &gimli::Dwarf<Reader<'_>>,
+    unit: &Unit<Reader<'_>>,
+    offset: UnitOffset,
+) -> gimli::Result<Option<(String, bool)>> {
+    let mut global = false;
+    let mut plain_name = None;
+    let mut linkage_name = None;
+    let mut next = Some(offset);
+    // An item's DIE refers to at most its abstract instance, which may refer to its declaration.
+    for _ in 0..3 {
+        let Some(offset) = next.take() else { break };
+        let entry = unit.entry(offset)?;
+        if let Some(AttributeValue::Flag(external)) = entry.attr_value(gimli::DW_AT_external)? {
+            global |= external;
+        }
+        linkage_name = match entry.attr_value(gimli::DW_AT_linkage_name)? {
+            Some(name) => Some(name),
+            None => entry.attr_value(gimli::DW_AT_MIPS_linkage_name)?,
+        };
+        if linkage_name.is_some() {
+            break;
+        }
+        if plain_name.is_none() {
+            plain_name = entry.attr_value(gimli::DW_AT_name)?;
+        }
+        for attr in [gimli::DW_AT_abstract_origin, gimli::DW_AT_specification] {
+            if let Some(AttributeValue::UnitRef(origin)) = entry.attr_value(attr)? {
+                next = Some(origin);
+                break;
+            }
+        }
+    }
+    let Some(name) = linkage_name.or(plain_name) else {
+        return Ok(None);
+    };
+    let name = dwarf.attr_string(unit, name)?;
+    Ok(core::str::from_utf8(name.slice()).ok().map(|name| (String::from(name), global)))
+}
+
+/// Returns the size in bytes of the type described by the given DIE, if it's known.
+fn type_size(unit: &Unit<Reader<'_>>, offset: UnitOffset, depth: usize) -> gimli::Result<Option<u64>> {
+    if depth > MAX_TYPE_DEPTH {
+        return Ok(None);
+    }
+    let entry = unit.entry(offset)?;
+    if let Some(size) = entry.attr_value(gimli::DW_AT_byte_size)?.and_then(|value| value.udata_value()) {
+        return Ok(Some(size));
+    }
+    let Some(AttributeValue::UnitRef(inner)) = entry.attr_value(gimli::DW_AT_type)? else {
+        return Ok(None);
+    };
+    let Some(inner_size) = type_size(unit, inner, depth + 1)? else {
+        return Ok(None);
+    };
+    if entry.tag() != gimli::DW_TAG_array_type {
+        // Typedefs and qualifiers have the same size as the type they refer to.
+        return Ok(Some(inner_size));
+    }
+
+    // An array's size is its element size multiplied by the length of each of its dimensions.
+    let mut size = inner_size;
+    let mut tree = unit.entries_tree(Some(offset))?;
+    let mut children = tree.root()?.children();
+    while let Some(child) = children.next()? {
+        let child = child.entry();
+        if child.tag() != gimli::DW_TAG_subrange_type {
+            continue;
+        }
+        let count = match child.attr_value(gimli::DW_AT_count)?.and_then(|value| value.udata_value()) {
+            Some(count) => count,
+            None => {
+                let lower = child
+                    .attr_value(gimli::DW_AT_lower_bound)?
+                    .and_then(|value| value.udata_value())
+                    .unwrap_or(0);
+                match child.attr_value(gimli::DW_AT_upper_bound)?.and_then(|value| value.udata_value()) {
+                    Some(upper) => (upper + 1).saturating_sub(lower),
+                    None => return Ok(None),
+                }
+            }
+        };
+        size = size.saturating_mul(count);
+    }
+    Ok(Some(size))
+}
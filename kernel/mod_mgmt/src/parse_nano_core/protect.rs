@@ -0,0 +1,61 @@
+//! Hardens the page permissions of the nano_core's already-running code and read-only data.
+//!
+//! The bootloader maps the nano_core with permissions derived from its ELF section flags,
+//! and parsing the nano_core keeps those mappings exactly as they are.
+//! Once the nano_core has been parsed, its section metadata tells us which pages must be immutable,
+//! so we verify that its `.text` pages aren't writable and that its `.rodata` pages
+//! are neither writable nor executable, remapping them if they are.
+
+use alloc::sync::Arc;
+use crate_metadata::{LoadedCrate, SectionType};
+
+/// Verifies the permissions of the nano_core's text and rodata pages,
+/// remapping them with the proper permissions if necessary.
+///
+/// A section that isn't covered by the pages expected for its type can't be fixed
+/// without also changing the permissions of whatever else is in its pages,
+/// so such sections are only reported.
+///
+/// Returns the number of violations that were fixed by remapping.
+pub(super) fn protect_nano_core_pages(nano_core: &LoadedCrate) -> Result<usize, &'static str> {
+    let (Some((text_pages, _)), Some((rodata_pages, _))) = (&nano_core.text_pages, &nano_core.rodata_pages) else {
+        return Err("protect_nano_core_pages(): the nano_core crate had no text or rodata pages");
+    };
+
+    for sec in nano_core.sections.values() {
+        let expected_pages = match sec.typ {
+            SectionType::Text => text_pages,
+            SectionType::Rodata | SectionType::EhFrame | SectionType::GccExceptTable => rodata_pages,
+            _ => continue,
+        };
+        if !Arc::ptr_eq(&sec.mapped_pages, expected_pages) {
+            error!("protect_nano_core_pages(): nano_core {:?} section {:?} at {:#X} is not in the nano_core's {} pages, \
+                so its permissions cannot be enforced",
+                sec.typ, sec.name, sec.virt_addr, sec.typ.name(),
+            );
+        }
+    }
+
+    let kernel_mmi_ref = memory::get_kernel_mmi_ref()
+        .ok_or("protect_nano_core_pages(): KERNEL_MMI was not yet initialized!")?;
+    let mut num_fixed = 0;
+
+    let mut text_pages = text_pages.lock();
+    let text_flags = text_pages.flags();
+    if text_flags.is_writable() {
+        warn!("protect_nano_core_pages(): nano_core .text pages {:?} were writable, remapping them as read-only.", *text_pages);
+        text_pages.remap(&mut kernel_mmi_ref.lock().page_table, text_flags.writable(false))?;
+        num_fixed += 1;
+    }
+    drop(text_pages);
+
+    let mut rodata_pages = rodata_pages.lock();
+    let rodata_flags = rodata_pages.flags();
+    if rodata_flags.is_writable() || rodata_flags.is_executable() {
+        warn!("protect_nano_core_pages(): nano_core .rodata pages {:?} were writable or executable, remapping them as read-only.", *rodata_pages);
+        rodata_pages.remap(&mut kernel_mmi_ref.lock().page_table, rodata_flags.writable(false).executable(false))?;
+        num_fixed += 1;
+    }
+
+    Ok(num_fixed)
+}
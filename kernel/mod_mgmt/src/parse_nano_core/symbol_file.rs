@@ -0,0 +1,491 @@
+//! Parsing of the nano_core symbol file, which lists the nano_core's section headers and symbols as text.
+//!
+//! A symbol file can be in any of several formats, each of which is parsed into the same [`SymbolFile`].
+//! The format is detected from the contents of the file, by trying each of the [`FORMATS`] in order:
+//! * [`Compact`]: a purpose-built format that lists exactly what's needed, one item per line,
+//! * [`Readelf`]: the output of `readelf -S -s -W`,
+//! * [`Nm`]: the output of `nm -S --defined-only`, preceded by the section headers from `readelf -S -W`.
+//!
+//! Symbol names in the [`Nm`] format are demangled while parsing. Other formats' symbol names
+//! are returned as they appear in the symbol file, so they may still need to be demangled.
+
+use alloc::{borrow::Cow, vec::Vec};
+use crate::demangle_symbol;
+use crate_metadata::Shndx;
+
+/// The first line of a symbol file in the [`Compact`] format.
+pub(super) const COMPACT_MAGIC: &str = "theseus-nano-core-symbols";
+/// The version of the [`Compact`] format that is parsed.
+pub(super) const COMPACT_VERSION: u32 = 1;
+
+/// A section header listed in a symbol file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct SectionHeader<'s> {
+    pub shndx: Shndx,
+    pub name: &'s str,
+    /// The ELF section type, e.g., `PROGBITS` or `NOBITS`.
+    pub typ: &'s str,
+    pub vaddr: usize,
+    pub size: usize,
+}
+
+/// A symbol listed in a symbol file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct Symbol<'s> {
+    pub name: Cow<'s, str>,
+    /// The address of this symbol, or its offset into the TLS or CLS area for TLS and CLS symbols.
+    pub vaddr: usize,
+    pub size: usize,
+    pub global: bool,
+    /// The index of the section header that this symbol is in.
+    pub shndx: Shndx,
+}
+
+/// The section headers and symbols listed in a symbol file.
+#[derive(Debug, Default)]
+pub(super) struct SymbolFile<'s> {
+    pub sections: Vec<SectionHeader<'s>>,
+    /// The symbols that are defined in a section; absolute and undefined symbols are omitted.
+    pub symbols: Vec<Symbol<'s>>,
+}
+
+/// A format of symbol file that can be parsed.
+pub(super) trait SymbolFileFormat: Sync {
+    /// The name of this format, for logging.
+    fn name(&self) -> &'static str;
+    /// Returns whether the given symbol file appears to be in this format.
+    fn detect(&self, text: &str) -> bool;
+    /// Parses the given symbol file, which is in this format.
+    fn parse<'s>(&self, text: &'s str) -> Result<SymbolFile<'s>, &'static str>;
+}
+
+/// The supported formats, in the order in which detection is attempted.
+pub(super) static FORMATS: &[&dyn SymbolFileFormat] = &[&Compact, &Readelf, &Nm];
+
+/// Detects the format of the given symbol file and parses it.
+///
+/// Returns the name of the detected format along with the parsed symbol file.
+pub(super) fn parse(text: &str) -> Result<(&'static str, SymbolFile<'_>), &'static str> {
+    let format = FORMATS.iter()
+        .find(|format| format.detect(text))
+        .ok_or("the nano_core symbol file isn't in any supported format")?;
+    format.parse(text).map(|file| (format.name(), file))
+}
+
+/// The compact format, which consists of a header line followed by one line per section header or symbol:
+/// ```text
+/// theseus-nano-core-symbols 1
+/// S <shndx> <name> <type> <address> <size>
+/// G <shndx> <address> <size> <name>
+/// L <shndx> <address> <size> <name>
+/// ```
+/// Section header lines start with `S`, and symbol lines start with `G` for global symbols
+/// or `L` for local symbols. Addresses and sizes are in hexadecimal.
+/// Empty lines and lines starting with `#` are ignored.
+pub(super) struct Compact;
+
+impl SymbolFileFormat for Compact {
+    fn name(&self) -> &'static str {
+        "compact"
+    }
+
+    fn detect(&self, text: &str) -> bool {
+        Fields(text).next() == Some(COMPACT_MAGIC)
+    }
+
+    fn parse<'s>(&self, text: &'s str) -> Result<SymbolFile<'s>, &'static str> {
+        let mut lines = text.lines();
+        let version = lines.next()
+            .and_then(|header| header.trim().strip_prefix(COMPACT_MAGIC))
+            .and_then(|version| version.trim().parse::<u32>().ok())
+            .ok_or("compact symbol file has an invalid header line")?;
+        if version != COMPACT_VERSION {
+            return Err("compact symbol file has an unsupported version");
+        }
+
+        let mut file = SymbolFile::default();
+        for line in lines {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut fields = Fields(line);
+            let tag = fields.next().ok_or("compact symbol file has an empty line")?;
+            match tag {
+                "S" => {
+                    file.sections.push(SectionHeader {
+                        shndx: fields.decimal().ok_or("compact symbol file has an invalid section index")?,
+                        name:  fields.next().ok_or("compact symbol file is missing a section name")?,
+                        typ:   fields.next().ok_or("compact symbol file is missing a section type")?,
+                        vaddr: fields.hex().ok_or("compact symbol file has an invalid section address")?,
+                        size:  fields.hex().ok_or("compact symbol file has an invalid section size")?,
+                    });
+                }
+                "G" | "L" => {
+                    let shndx = fields.decimal().ok_or("compact symbol file has an invalid symbol section index")?;
+                    let vaddr = fields.hex().ok_or("compact symbol file has an invalid symbol address")?;
+                    let size  = fields.hex().ok_or("compact symbol file has an invalid symbol size")?;
+                    let name  = fields.rest().ok_or("compact symbol file is missing a symbol name")?;
+                    file.symbols.push(Symbol { name: name.into(), vaddr, size, global: tag == "G", shndx });
+                }
+                _ => return Err("compact symbol file has a line with an unknown tag"),
+            }
+        }
+        Ok(file)
+    }
+}
+
+/// The output of `readelf -S -s -W`, i.e., the section headers followed by the symbol table,
+/// in which mangled symbol names may have been demangled.
+pub(super) struct Readelf;
+
+impl Readelf {
+    /// Returns whether the given line starts the symbol table,
+    /// i.e., it contains ".symtab" but isn't the `.symtab` section's header, which contains "SYMTAB".
+    fn is_start_of_symbol_table(line: &str) -> bool {
+        line.contains(".symtab") && !line.contains("SYMTAB")
+    }
+
+    /// Parses a symbol table entry, e.g.,
+    /// `42: ffffffff80100000    24 FUNC    GLOBAL DEFAULT    1 nano_core::start::h0123456789abcdef`.
+    ///
+    /// Returns `Ok(None)` for symbols that aren't in a section, e.g., ones whose `Ndx` is `ABS`.
+    fn parse_symbol(line: &str) -> Result<Option<Symbol<'_>>, &'static str> {
+        let mut fields = Fields(line);
+        let _num  = fields.next().ok_or("readelf symbol file: couldn't get column 0 'Num'")?;
+        let vaddr = fields.next().ok_or("readelf symbol file: couldn't get column 1 'Value'")?;
+        let size  = fields.next().ok_or("readelf symbol file: couldn't get column 2 'Size'")?;
+        let typ   = fields.next().ok_or("readelf symbol file: couldn't get column 3 'Type'")?;
+        // CLS symbols have an OS-specific symbol type, which readelf prints as "<OS specific>: <number>".
+        if typ == "<OS" {
+            fields.next();
+            fields.next();
+        }
+        let bind  = fields.next().ok_or("readelf symbol file: couldn't get column 4 'Bind'")?;
+        let _vis  = fields.next().ok_or("readelf symbol file: couldn't get column 5 'Vis'")?;
+        let ndx   = fields.next().ok_or("readelf symbol file: couldn't get column 6 'Ndx'")?;
+        let name  = fields.rest().ok_or("readelf symbol file: couldn't get column 7 'Name'")?;
+
+        let vaddr = parse_hex(vaddr).ok_or("readelf symbol file: couldn't parse virtual address (value column)")?;
+        // Sizes are printed in decimal, unless they're too large, in which case they're printed in hex.
+        let size = size.parse::<usize>().ok()
+            .or_else(|| size.strip_prefix("0x").and_then(parse_hex))
+            .ok_or("readelf symbol file: couldn't parse size column")?;
+        // The `Ndx` may not be a number, e.g., "ABS", in which case the symbol isn't in a section.
+        let Ok(shndx) = ndx.parse::<Shndx>() else {
+            return Ok(None);
+        };
+        Ok(Some(Symbol {
+            name: name.into(),
+            vaddr,
+            size,
+            global: bind == "GLOBAL" || bind == "WEAK",
+            shndx,
+        }))
+    }
+}
+
+impl SymbolFileFormat for Readelf {
+    fn name(&self) -> &'static str {
+        "readelf"
+    }
+
+    fn detect(&self, text: &str) -> bool {
+        text.lines().any(Self::is_start_of_symbol_table)
+    }
+
+    fn parse<'s>(&self, text: &'s str) -> Result<SymbolFile<'s>, &'static str> {
+        let mut file = SymbolFile::default();
+        let mut lines = text.lines();
+
+        // First, parse the section headers, which come before the symbol table.
+        for line in lines.by_ref() {
+            if Self::is_start_of_symbol_table(line) {
+                break;
+            }
+            file.sections.extend(parse_readelf_section_header(line));
+        }
+        // Skip the line with the column headers, e.g., "Num:    Value          Size Type    Bind   Vis ..."
+        lines.next();
+
+        // Second, parse each symbol table entry.
+        for line in lines {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            file.symbols.extend(Self::parse_symbol(line)?);
+        }
+        Ok(file)
+    }
+}
+
+/// The output of `nm -S --defined-only`, preceded by the section headers from `readelf -S -W`.
+///
+/// Symbol names must be mangled, i.e., `nm` must be run without `-C`, because `nm`'s demangling
+/// omits the hashes that distinguish symbols; they're demangled with [`demangle_symbol()`] instead.
+///
+/// `nm` doesn't say which section each symbol is in, so a symbol is assigned to the section
+/// whose address range contains it. The values of TLS and CLS symbols are offsets rather than addresses,
+/// so those that aren't in any section's address range are assigned to the `.tdata`, `.tbss`, or `.cls` section
+/// based on their symbol type: data (`d`), BSS (`b`), or read-only data (`r`), respectively.
+/// Symbols that can't be assigned to a section are omitted.
+pub(super) struct Nm;
+
+impl Nm {
+    /// Parses a line of `nm` output, e.g.,
+    /// `ffffffff80100000 0000000000000018 T _ZN9nano_core5start17h0123456789abcdefE`,
+    /// into the symbol's address, size, type, and name. The size is optional.
+    fn parse_line(line: &str) -> Option<(usize, usize, char, &str)> {
+        let mut fields = Fields(line);
+        let vaddr = fields.hex()?;
+        let field = fields.next()?;
+        // The size is printed as zero-padded hex, so it's never a single character like the type.
+        let (size, typ) = if field.len() == 1 {
+            (0, field)
+        } else {
+            (parse_hex(field)?, fields.next()?)
+        };
+        let mut typ_chars = typ.chars();
+        let (Some(typ), None) = (typ_chars.next(), typ_chars.next()) else {
+            return None;
+        };
+        Some((vaddr, size, typ, fields.rest()?))
+    }
+
+    /// Returns the index of the section that the given symbol is in, if it can be determined.
+    fn find_section(sections: &[SectionHeader], vaddr: usize, typ: char) -> Option<Shndx> {
+        let is_tls_or_cls = |sec: &SectionHeader| matches!(sec.name, ".tdata" | ".tbss" | ".cls");
+        let containing = sections.iter().find(|sec|
+            !is_tls_or_cls(sec)
+                && sec.vaddr != 0
+                && sec.vaddr <= vaddr
+                && vaddr - sec.vaddr < sec.size
+        );
+        if let Some(sec) = containing {
+            return Some(sec.shndx);
+        }
+        let offset_section = match typ.to_ascii_lowercase() {
+            'd' => ".tdata",
+            'b' => ".tbss",
+            'r' => ".cls",
+            _ => return None,
+        };
+        sections.iter()
+            .find(|sec| sec.name == offset_section)
+            .map(|sec| sec.shndx)
+    }
+}
+
+impl SymbolFileFormat for Nm {
+    fn name(&self) -> &'static str {
+        "nm"
+    }
+
+    fn detect(&self, text: &str) -> bool {
+        text.lines().any(|line| Self::parse_line(line).is_some())
+    }
+
+    fn parse<'s>(&self, text: &'s str) -> Result<SymbolFile<'s>, &'static str> {
+        let mut file = SymbolFile::default();
+        let mut nm_lines = Vec::new();
+        for line in text.lines() {
+            if line.trim_start().starts_with('[') {
+                file.sections.extend(parse_readelf_section_header(line));
+            } else if let Some(nm_line) = Self::parse_line(line) {
+                nm_lines.push(nm_line);
+            }
+        }
+        if file.sections.is_empty() {
+            return Err("nm symbol file must be preceded by the section headers from readelf");
+        }
+
+        // Mangled names never contain "::", so such names must have been demangled by `nm -C`.
+        if nm_lines.iter().any(|(_, _, _, name)| name.contains("::")) {
+            return Err("nm symbol file must contain mangled symbol names, i.e., the output of `nm` without `-C`");
+        }
+
+        for (vaddr, size, typ, name) in nm_lines {
+            // Skip undefined, weak undefined, absolute, and debugging symbols.
+            if matches!(typ, 'U' | 'v' | 'w' | 'A' | 'a' | 'N' | 'n' | '-') {
+                continue;
+            }
+            let Some(shndx) = Self::find_section(&file.sections, vaddr, typ) else {
+                continue;
+            };
+            file.symbols.push(Symbol {
+                name: demangle_symbol(name).into(),
+                vaddr,
+                size,
+                // Uppercase types are global, as is `u`, a unique global symbol.
+                global: typ.is_ascii_uppercase() || typ == 'u',
+                shndx,
+            });
+        }
+        Ok(file)
+    }
+}
+
+/// Parses a section header line from `readelf -S -W`, e.g.,
+/// `[ 1] .text   PROGBITS   ffffffff80100000 001000 012345 00  AX  0   0 16`.
+///
+/// Returns `None` for lines that aren't section headers, and for the null section header,
+/// which has no name.
+fn parse_readelf_section_header(line: &str) -> Option<SectionHeader<'_>> {
+    let (shndx, rest) = line.trim_start().strip_prefix('[')?.split_once(']')?;
+    let shndx = shndx.trim().parse::<Shndx>().ok().filter(|&shndx| shndx != 0)?;
+    let mut fields = Fields(rest);
+    let name  = fields.next()?;
+    let typ   = fields.next()?;
+    let vaddr = fields.hex()?;
+    let _offset = fields.next()?;
+    let size  = fields.hex()?;
+    Some(SectionHeader { shndx, name, typ, vaddr, size })
+}
+
+/// Parses a hexadecimal number, with or without a leading "0x".
+fn parse_hex(s: &str) -> Option<usize> {
+    usize::from_str_radix(s.strip_prefix("0x").unwrap_or(s), 16).ok()
+}
+
+/// The remaining whitespace-separated fields of a line.
+struct Fields<'s>(&'s str);
+
+impl<'s> Fields<'s> {
+    /// Returns the next field.
+    fn next(&mut self) -> Option<&'s str> {
+        let s = self.0.trim_start();
+        if s.is_empty() {
+            return None;
+        }
+        let end = s.find(char::is_whitespace).unwrap_or(s.len());
+        self.0 = &s[end ..];
+        Some(&s[.. end])
+    }
+
+    /// Returns the next field parsed as a decimal number.
+    fn decimal(&mut self) -> Option<usize> {
+        self.next()?.parse().ok()
+    }
+
+    /// Returns the next field parsed as a hexadecimal number.
+    fn hex(&mut self) -> Option<usize> {
+        self.next().and_then(parse_hex)
+    }
+
+    /// Returns the rest of the line, which may contain whitespace, or `None` if it's empty.
+    fn rest(self) -> Option<&'s str> {
+        Some(self.0.trim()).filter(|rest| !rest.is_empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use super::*;
+
+    const SECTION_HEADERS: &str = "\
+Section Headers:
+  [Nr] Name              Type            Address          Off    Size   ES Flg Lk Inf Al
+  [ 0]                   NULL            0000000000000000 000000 000000 00      0   0  0
+  [ 1] .text             PROGBITS        ffffffff80100000 001000 010000 00  AX  0   0 16
+  [ 2] .rodata           PROGBITS        ffffffff80110000 011000 002000 00   A  0   0 16
+  [ 3] .tdata            PROGBITS        ffffffff80112000 013000 000010 00 WAT  0   0  8
+  [ 4] .tbss             NOBITS          ffffffff80112010 013010 000020 00 WAT  0   0  8
+  [ 5] .data             PROGBITS        ffffffff80113000 014000 001000 00  WA  0   0  8
+  [ 6] .comment          PROGBITS        0000000000000000 015000 000040 01  MS  0   0  1
+";
+
+    fn header(shndx: Shndx, name: &'static str, typ: &'static str, vaddr: usize, size: usize) -> SectionHeader<'static> {
+        SectionHeader { shndx, name, typ, vaddr, size }
+    }
+
+    fn symbol(name: &'static str, vaddr: usize, size: usize, global: bool, shndx: Shndx) -> Symbol<'static> {
+        Symbol { name: name.into(), vaddr, size, global, shndx }
+    }
+
+    #[test]
+    fn readelf() {
+        let text = std::format!("{SECTION_HEADERS}
+  [ 7] .symtab           SYMTAB          0000000000000000 015040 000100 18      8   2  8
+
+Symbol table '.symtab' contains 5 entries:
+   Num:    Value          Size Type    Bind   Vis      Ndx Name
+     1: ffffffff80100000    24 FUNC    GLOBAL DEFAULT    1 nano_core::start::h0123456789abcdef
+     2: ffffffff80110000 0x20000 OBJECT  LOCAL  DEFAULT    2 <[u8] as core::fmt::Debug>::fmt
+     3: 0000000000000008     8 <OS specific>: 10 GLOBAL DEFAULT    3 TLS_VAR
+     4: 0000000000001000     0 NOTYPE  GLOBAL DEFAULT  ABS __some_constant
+");
+        let (format, file) = parse(&text).unwrap();
+        assert_eq!(format, "readelf");
+        assert_eq!(file.sections.len(), 7);
+        assert_eq!(file.sections[0], header(1, ".text", "PROGBITS", 0xffffffff80100000, 0x10000));
+        assert_eq!(file.sections[3], header(4, ".tbss", "NOBITS", 0xffffffff80112010, 0x20));
+        assert_eq!(file.symbols, [
+            symbol("nano_core::start::h0123456789abcdef", 0xffffffff80100000, 24, true, 1),
+            symbol("<[u8] as core::fmt::Debug>::fmt", 0xffffffff80110000, 0x20000, false, 2),
+            symbol("TLS_VAR", 8, 8, true, 3),
+        ]);
+    }
+
+    #[test]
+    fn nm() {
+        let text = std::format!("{SECTION_HEADERS}
+ffffffff80100000 0000000000000018 T _ZN9nano_core5start17h0123456789abcdefE
+ffffffff80100020 t local_fn
+ffffffff80110000 0000000000000004 R _ZN4core3fmt5Debug3fmt17hfedcba9876543210E
+0000000000000008 0000000000000008 d TLS_DATA
+0000000000000018 0000000000000008 B TLS_BSS
+0000000000001000 A __some_constant
+                 U undefined
+");
+        let (format, file) = parse(&text).unwrap();
+        assert_eq!(format, "nm");
+        assert_eq!(file.sections.len(), 6);
+        assert_eq!(file.symbols, [
+            symbol("nano_core::start::h0123456789abcdef", 0xffffffff80100000, 0x18, true, 1),
+            symbol("local_fn", 0xffffffff80100020, 0, false, 1),
+            symbol("core::fmt::Debug::fmt::hfedcba9876543210", 0xffffffff80110000, 4, true, 2),
+            symbol("TLS_DATA", 8, 8, false, 3),
+            symbol("TLS_BSS", 0x18, 8, true, 4),
+        ]);
+
+        assert!(Nm.parse("ffffffff80100000 T _ZN9nano_core5start17h0123456789abcdefE\n").is_err());
+        // Names demangled by `nm -C` lack their hashes, so they're rejected.
+        let demangled = std::format!("{SECTION_HEADERS}ffffffff80100000 0000000000000018 T nano_core::start\n");
+        assert!(Nm.parse(&demangled).is_err());
+    }
+
+    #[test]
+    fn compact() {
+        let text = "\
+theseus-nano-core-symbols 1
+# sections
+S 1 .text PROGBITS ffffffff80100000 10000
+S 3 .tdata PROGBITS ffffffff80112000 10
+
+G 1 ffffffff80100000 18 nano_core::start::h0123456789abcdef
+L 3 8 8 <T as core::fmt::Debug>::fmt
+";
+        let (format, file) = parse(text).unwrap();
+        assert_eq!(format, "compact");
+        assert_eq!(file.sections, [
+            header(1, ".text", "PROGBITS", 0xffffffff80100000, 0x10000),
+            header(3, ".tdata", "PROGBITS", 0xffffffff80112000, 0x10),
+        ]);
+        assert_eq!(file.symbols, [
+            symbol("nano_core::start::h0123456789abcdef", 0xffffffff80100000, 0x18, true, 1),
+            symbol("<T as core::fmt::Debug>::fmt", 8, 8, false, 3),
+        ]);
+
+        assert!(parse("theseus-nano-core-symbols 2\n").is_err());
+        assert!(parse("theseus-nano-core-symbols 1\nS 1 .text PROGBITS\n").is_err());
+        assert!(parse("theseus-nano-core-symbols 1\nX 1\n").is_err());
+    }
+
+    #[test]
+    fn unknown_format() {
+        assert!(parse("").is_err());
+        assert!(parse("hello world\n").is_err());
+    }
+}
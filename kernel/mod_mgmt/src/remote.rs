@@ -0,0 +1,60 @@
+//! Loading crates whose object files are fetched from a remote source, e.g., over the network.
+//!
+//! `mod_mgmt` doesn't know how to fetch object files itself, as it sits below the networking stack;
+//! rather, higher-level crates implement [`CrateFetcher`] for a given transport protocol.
+//!
+//! A fetched object file is written into the namespace's directory, just like
+//! a crate object file that was loaded by the bootloader,
+//! and is then loaded as usual, e.g., with the namespace's signature policy applied to it.
+
+use alloc::vec::Vec;
+use memory::MmiRef;
+use crate::{CrateNamespace, StrongCrateRef};
+
+/// A source from which crate object files can be fetched, e.g., a remote server.
+pub trait CrateFetcher {
+    /// Fetches the contents of the crate object file with the given name,
+    /// which includes its crate type prefix, e.g., `"k#keyboard-36be916209949cef.o"`.
+    ///
+    /// The contents may be compressed in any format that can be detected by
+    /// [`decompress::Format::detect()`].
+    fn fetch(&self, crate_object_file_name: &str) -> Result<Vec<u8>, &'static str>;
+}
+
+impl CrateNamespace {
+    /// Fetches the crate object file with the given name from the given `fetcher`,
+    /// writes it into this namespace's directory, and loads it into this namespace.
+    ///
+    /// Returns an error if a crate object file with that name already exists in this namespace's directory.
+    ///
+    /// # Arguments
+    /// * `fetcher`: the source of the crate object file.
+    /// * `crate_object_file_name`: the name of the crate object file, including its crate type prefix.
+    /// * The remaining arguments are the same as for [`CrateNamespace::load_crate()`].
+    pub fn fetch_and_load_crate(
+        &self,
+        fetcher: &dyn CrateFetcher,
+        crate_object_file_name: &str,
+        temp_backup_namespace: Option<&CrateNamespace>,
+        kernel_mmi_ref: &MmiRef,
+        verbose_log: bool,
+    ) -> Result<(StrongCrateRef, usize), &'static str> {
+        if self.dir.get_crate_object_file(crate_object_file_name).is_some() {
+            return Err("a crate object file with that name already exists in the namespace directory");
+        }
+        let content = fetcher.fetch(crate_object_file_name)?;
+        let content = decompress::decompress_if_compressed(&content)?;
+        let crate_object_file = self.dir.write_crate_object_file(crate_object_file_name, &content)?;
+
+        #[cfg(not(loscd_eval))]
+        debug!("fetched crate object file {:?} ({} bytes)", crate_object_file_name, content.len());
+        self.load_crate(&crate_object_file, temp_backup_namespace, kernel_mmi_ref, verbose_log)
+            .map_err(|e| {
+                // Don't leave behind an object file that couldn't be loaded.
+                if let Some(parent) = crate_object_file.lock().get_parent_dir() {
+                    let _ = parent.lock().remove(&fs_node::FileOrDir::File(crate_object_file.clone()));
+                }
+                e
+            })
+    }
+}
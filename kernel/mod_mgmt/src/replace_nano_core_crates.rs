@@ -156,6 +156,6 @@ fn load_crate_using_nano_core_data_sections(
         new_crate_name, _num_new_sections, _num_new_syms
     );
     // (6) Add the newly-loaded crate to the namespace.
-    namespace.crate_tree.lock().insert(new_crate_name, new_crate_ref.clone_shallow());
+    namespace.add_crate(new_crate_name, new_crate_ref.clone_shallow());
     Ok(new_crate_ref)
 }
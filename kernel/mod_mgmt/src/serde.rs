@@ -8,6 +8,10 @@ use crate_metadata_serde::{SerializedCrate, SerializedSection};
 
 /// Converts the given [`SerializedCrate`] into a [`LoadedCrate`]
 /// and its sections into [`LoadedSection`]s.
+///
+/// The `load_bias` is added to the virtual address of each section other than TLS and CLS sections,
+/// whose virtual addresses are offsets, as well as to the value of each init symbol;
+/// see [`parse_nano_core()`](crate::parse_nano_core::parse_nano_core).
 pub(crate) fn into_loaded_crate(
     serialized_crate: SerializedCrate,
     object_file: FileRef,
@@ -15,8 +19,9 @@ pub(crate) fn into_loaded_crate(
     text_pages: &Arc<Mutex<MappedPages>>,
     rodata_pages: &Arc<Mutex<MappedPages>>,
     data_pages: &Arc<Mutex<MappedPages>>,
+    load_bias: usize,
     verbose_log: bool,
-) -> Result<(StrongCrateRef, BTreeMap<String, usize>, usize), &'static str> {
+) -> Result<(StrongCrateRef, BTreeMap<String, usize>, usize), LoadError> {
     let crate_name: StrRef = serialized_crate.crate_name.as_str().into();
 
     let total_tls_size: usize = serialized_crate.tls_sections
@@ -46,6 +51,9 @@ pub(crate) fn into_loaded_crate(
         tls_sections:        serialized_crate.tls_sections,
         cls_sections:        serialized_crate.cls_sections,
         data_sections:       serialized_crate.data_sections,
+        eh_frame:            None,
+        init_array:          Vec::new(),
+        fini_array:          Vec::new(),
         reexported_symbols:  BTreeSet::new(),
     });
     let parent_crate_weak_ref = CowArc::downgrade(&loaded_crate);
@@ -67,6 +75,7 @@ pub(crate) fn into_loaded_crate(
                 data_pages,
                 total_tls_size,
                 total_cls_size,
+                load_bias,
             )?,
         );
     }
@@ -76,11 +85,15 @@ pub(crate) fn into_loaded_crate(
     let mut loaded_crate_mut = loaded_crate.lock_as_mut().ok_or(
         "BUG: SerializedCrate::into_loaded_crate(): couldn't get exclusive mutable access to loaded_crate",
     )?;
+    loaded_crate_mut.eh_frame = find_eh_frame(&sections);
     loaded_crate_mut.sections = sections;
+    if let Err(e) = unwind_info::register(&loaded_crate_mut) {
+        warn!("couldn't register the nano_core's unwind info: {}", e);
+    }
     drop(loaded_crate_mut);
 
     // Add the newly-parsed nano_core crate to the kernel namespace.
-    namespace.crate_tree.lock().insert(crate_name, loaded_crate.clone_shallow());
+    namespace.add_crate(crate_name, loaded_crate.clone_shallow());
     info!("Finished parsing nano_core crate, added {} new symbols.", num_new_syms);
     
     // // Dump loaded sections for verification. See pull request #542/#559 for more details:
@@ -90,7 +103,12 @@ pub(crate) fn into_loaded_crate(
     // }
     // drop(loaded_crate_ref);
 
-    Ok((loaded_crate, serialized_crate.init_symbols, num_new_syms))
+    // Serialized init symbols never include absolute symbols, so they're all addresses.
+    let init_symbols = serialized_crate.init_symbols
+        .into_iter()
+        .map(|(name, linked_vaddr)| (name, linked_vaddr.wrapping_add(load_bias)))
+        .collect();
+    Ok((loaded_crate, init_symbols, num_new_syms))
 }
 
 
@@ -105,7 +123,8 @@ fn into_loaded_section(
     data_pages:         &Arc<Mutex<MappedPages>>,
     total_tls_size:     usize,
     total_cls_size:     usize,
-) -> Result<Arc<LoadedSection>, &'static str> {
+    load_bias:          usize,
+) -> Result<Arc<LoadedSection>, LoadError> {
     let mapped_pages = match serialized_section.ty {
         SectionType::Text => Arc::clone(text_pages),
         SectionType::Rodata
@@ -115,16 +134,24 @@ fn into_loaded_section(
         | SectionType::GccExceptTable
         | SectionType::EhFrame => Arc::clone(rodata_pages),
         SectionType::Data
-        | SectionType::Bss => Arc::clone(data_pages),
+        | SectionType::Bss
+        | SectionType::InitArray
+        | SectionType::FiniArray => Arc::clone(data_pages),
+    };
+    let load_bias = match serialized_section.ty {
+        SectionType::TlsData | SectionType::TlsBss | SectionType::Cls => 0,
+        _ => load_bias,
     };
-    let virt_addr = VirtualAddress::new(serialized_section.virtual_address)
-        .ok_or("SerializedSection::into_loaded_section(): invalid virtual address")?;
+    let virt_addr = VirtualAddress::new(serialized_section.virtual_address.wrapping_add(load_bias))
+        .ok_or(LoadError::MappingFailed("SerializedSection::into_loaded_section(): invalid virtual address"))?;
 
     let loaded_section = LoadedSection::new(
         serialized_section.ty,
         match serialized_section.ty {
             SectionType::EhFrame
-            | SectionType::GccExceptTable => crate::section_name_str_ref(&serialized_section.ty),
+            | SectionType::GccExceptTable
+            | SectionType::InitArray
+            | SectionType::FiniArray => crate::section_name_str_ref(&serialized_section.ty),
             _ => serialized_section.name.as_str().into(),
         },
         mapped_pages,
@@ -142,7 +169,7 @@ fn into_loaded_section(
             // which is necessary to properly calculate relocation entries that depend upon them.
             serialized_section.virtual_address,
             total_tls_size,
-        ).map_err(|_| "BUG: failed to add deserialized static TLS section to the TLS area")
+        ).map_err(|_| LoadError::MappingFailed("BUG: failed to add deserialized static TLS section to the TLS area"))
         // On AArch64, the linker includes a _TLS_MODULE_BASE_ zero sized symbol that we don't want to add.
     } else if serialized_section.ty == SectionType::Cls && serialized_section.size > 0 {
         cls_allocator::add_static_section(loaded_section, serialized_section.virtual_address, total_cls_size).map_err(|e| panic!("{:?}", e))
@@ -0,0 +1,154 @@
+//! Verification of ed25519 signatures on crate object files.
+//!
+//! A crate object file can be signed in one of two ways:
+//! 1. Embedded: an ELF note section named [`SIGNATURE_NOTE_SECTION_NAME`] whose note
+//!    (name `"Theseus"`, type [`SIGNATURE_NOTE_TYPE`]) holds the 64-byte signature.
+//!    The signed message is the entire object file with the signature bytes zeroed out.
+//! 2. Sidecar: a file in the same directory as the object file, named after it
+//!    with a [`SIGNATURE_FILE_EXTENSION`] suffix, which holds the 64-byte signature
+//!    of the entire object file.
+//!
+//! An embedded signature takes precedence over a sidecar signature.
+//!
+//! Signatures are checked against the public key given by the `THESEUS_CRATE_SIGNING_PUBLIC_KEY`
+//! environment variable at build time, a 64-character hex string.
+//! If no public key was provided, no crate can be verified.
+
+use alloc::format;
+use ed25519_compact::{PublicKey, Signature};
+use fs_node::{File, FileOrDir};
+use xmas_elf::{sections::ShType, ElfFile};
+
+/// The name of the ELF note section that holds a crate's embedded signature.
+pub const SIGNATURE_NOTE_SECTION_NAME: &str = ".note.theseus.signature";
+/// The owner name of the ELF note that holds a crate's embedded signature.
+pub const SIGNATURE_NOTE_NAME: &[u8] = b"Theseus\0";
+/// The type of the ELF note that holds a crate's embedded signature.
+pub const SIGNATURE_NOTE_TYPE: u32 = 0x5349_474E; // "SIGN"
+/// The suffix appended to a crate object file's name to get the name of its sidecar signature file.
+pub const SIGNATURE_FILE_EXTENSION: &str = ".sig";
+
+/// The public key that crate object files are verified against, embedded at build time.
+const PUBLIC_KEY_HEX: Option<&str> = option_env!("THESEUS_CRATE_SIGNING_PUBLIC_KEY");
+
+/// Whether and how a `CrateNamespace` verifies the signatures of crates before loading them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SignaturePolicy {
+    /// Signatures are not checked at all.
+    #[default]
+    Unchecked,
+    /// Signed crates must have a valid signature, but unsigned crates are loaded as well.
+    VerifyIfSigned,
+    /// Only crates with a valid signature are loaded.
+    Required,
+}
+
+/// Returns whether a public key was embedded at build time, i.e., whether crates can be verified.
+pub fn has_public_key() -> bool {
+    public_key().is_some()
+}
+
+/// Verifies the signature of the given crate object file according to the given `policy`.
+///
+/// # Arguments
+/// * `policy`: the signature policy of the namespace that the crate is being loaded into.
+/// * `crate_file`: the crate object file, used to find its sidecar signature file.
+/// * `bytes`: the contents of the crate object file.
+/// * `elf_file`: the parsed ELF file of the crate object file, used to find its embedded signature.
+pub fn verify(
+    policy: SignaturePolicy,
+    crate_file: &dyn File,
+    bytes: &[u8],
+    elf_file: &ElfFile,
+) -> Result<(), &'static str> {
+    if policy == SignaturePolicy::Unchecked {
+        return Ok(());
+    }
+
+    let signature = match embedded_signature(bytes, elf_file)? {
+        Some((signature, offset)) => Some((signature, Some(offset))),
+        None => sidecar_signature(crate_file)?.map(|signature| (signature, None)),
+    };
+    let Some((signature, embedded_offset)) = signature else {
+        return match policy {
+            SignaturePolicy::Required => Err("crate object file is not signed, but the namespace requires signatures"),
+            _ => Ok(()),
+        };
+    };
+
+    let public_key = public_key().ok_or("no public key was provided at build time to verify crate signatures")?;
+    let mut state = public_key.verify_incremental(&signature)
+        .map_err(|_| "invalid crate signature or public key")?;
+    match embedded_offset {
+        Some(offset) => {
+            state.absorb(&bytes[..offset]);
+            state.absorb([0u8; Signature::BYTES]);
+            state.absorb(&bytes[offset + Signature::BYTES ..]);
+        }
+        None => state.absorb(bytes),
+    }
+    state.verify().map_err(|_| "crate object file's signature doesn't match its contents")
+}
+
+/// Parses the public key that was embedded at build time, if any.
+fn public_key() -> Option<PublicKey> {
+    let hex = PUBLIC_KEY_HEX?.trim();
+    let mut key = [0u8; PublicKey::BYTES];
+    if hex.len() != key.len() * 2 {
+        return None;
+    }
+    for (i, byte) in key.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(hex.get(i * 2 .. i * 2 + 2)?, 16).ok()?;
+    }
+    Some(PublicKey::new(key))
+}
+
+/// Finds the signature embedded in the given ELF file's signature note section,
+/// returning it along with its offset into the ELF file's `bytes`.
+fn embedded_signature(bytes: &[u8], elf_file: &ElfFile) -> Result<Option<(Signature, usize)>, &'static str> {
+    let Some(sec) = elf_file.section_iter()
+        .find(|sec| sec.get_name(elf_file) == Ok(SIGNATURE_NOTE_SECTION_NAME))
+    else {
+        return Ok(None);
+    };
+    if sec.get_type() != Ok(ShType::Note) {
+        return Err("crate signature section is not a note section");
+    }
+    let start = sec.offset() as usize;
+    let note = bytes.get(start .. start + sec.size() as usize)
+        .ok_or("crate signature note section is out of bounds")?;
+
+    // An ELF note is a header of three 4-byte words (name size, descriptor size, and type),
+    // followed by the name and the descriptor, each padded to a 4-byte boundary.
+    let word = |i: usize| u32::from_ne_bytes([note[i * 4], note[i * 4 + 1], note[i * 4 + 2], note[i * 4 + 3]]);
+    const HEADER_SIZE: usize = 12;
+    if note.len() < HEADER_SIZE {
+        return Err("crate signature note is too small");
+    }
+    let (name_size, desc_size, typ) = (word(0) as usize, word(1) as usize, word(2));
+    let desc_start = HEADER_SIZE + ((name_size + 3) & !3);
+    if note.get(HEADER_SIZE .. HEADER_SIZE + name_size) != Some(SIGNATURE_NOTE_NAME)
+        || typ != SIGNATURE_NOTE_TYPE
+        || desc_size != Signature::BYTES
+    {
+        return Err("crate signature note has an unexpected name, type, or size");
+    }
+    let desc = note.get(desc_start .. desc_start + Signature::BYTES)
+        .ok_or("crate signature note is truncated")?;
+    let signature = Signature::from_slice(desc).map_err(|_| "invalid crate signature")?;
+    Ok(Some((signature, start + desc_start)))
+}
+
+/// Reads the signature from the given crate object file's sidecar signature file, if one exists.
+fn sidecar_signature(crate_file: &dyn File) -> Result<Option<Signature>, &'static str> {
+    let sig_file_name = format!("{}{}", crate_file.get_name(), SIGNATURE_FILE_EXTENSION);
+    let Some(FileOrDir::File(sig_file)) = crate_file.get_parent_dir().and_then(|dir| dir.lock().get(&sig_file_name)) else {
+        return Ok(None);
+    };
+    let sig_file = sig_file.lock();
+    if sig_file.len() != Signature::BYTES {
+        return Err("crate signature file has an unexpected size");
+    }
+    let sig_bytes: &[u8] = sig_file.as_mapping()?.as_slice(0, Signature::BYTES)?;
+    Signature::from_slice(sig_bytes).map(Some).map_err(|_| "invalid crate signature")
+}
@@ -0,0 +1,194 @@
+//! Aliases from the old names of renamed symbols to their new names.
+//!
+//! When a crate is swapped for a newer version in which some functions were renamed,
+//! other crates may still refer to those functions by their old names.
+//! Instead of relinking all such dependents at once, the new crate's old names can be declared as
+//! aliases of their new names, e.g., `my_crate::old_fn -> my_crate::new_fn`.
+//! A symbol that isn't found by its exact name is then resolved via these aliases,
+//! which allows dependents to be updated gradually during a transition period,
+//! after which the aliases can be removed.
+//!
+//! Aliases are matched without the symbols' trailing hash values, since those change
+//! whenever a crate is rebuilt.
+
+use alloc::{
+    collections::BTreeMap,
+    format,
+    string::String,
+    vec::Vec,
+};
+use log::warn;
+use spin::Mutex;
+use crate::{CrateNamespace, WeakSectionRef, SECTION_HASH_DELIMITER};
+
+/// A map from the old names of renamed symbols to their new names.
+pub struct SymbolAliases {
+    /// Each old symbol name (without its hash) mapped to the new symbol name as given.
+    inner: Mutex<BTreeMap<String, String>>,
+}
+
+impl SymbolAliases {
+    /// Creates a new empty set of aliases.
+    pub const fn new() -> SymbolAliases {
+        SymbolAliases {
+            inner: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Adds an alias such that `old_symbol` resolves to `new_symbol`,
+    /// returning the previous target of `old_symbol`, if any.
+    ///
+    /// Returns an error if the alias would (transitively) resolve `old_symbol` to itself.
+    pub fn insert(&self, old_symbol: &str, new_symbol: &str) -> Result<Option<String>, &'static str> {
+        let old_symbol = symbol_name_without_hash(old_symbol);
+        let mut inner = self.inner.lock();
+        let mut next = symbol_name_without_hash(new_symbol);
+        loop {
+            if next == old_symbol {
+                return Err("symbol alias would resolve the old symbol to itself");
+            }
+            match inner.get(next) {
+                Some(target) => next = symbol_name_without_hash(target),
+                None => break,
+            }
+        }
+        Ok(inner.insert(String::from(old_symbol), String::from(new_symbol)))
+    }
+
+    /// Removes the alias for `old_symbol`, returning its target, if any.
+    pub fn remove(&self, old_symbol: &str) -> Option<String> {
+        self.inner.lock().remove(symbol_name_without_hash(old_symbol))
+    }
+
+    /// Returns the symbol name that `symbol` ultimately resolves to by following its aliases,
+    /// or `None` if there is no alias for `symbol`.
+    pub fn resolve(&self, symbol: &str) -> Option<String> {
+        let inner = self.inner.lock();
+        let mut target = inner.get(symbol_name_without_hash(symbol))?;
+        // `insert()` ensures there are no cycles, so this always terminates.
+        while let Some(next) = inner.get(symbol_name_without_hash(target)) {
+            target = next;
+        }
+        Some(target.clone())
+    }
+
+    /// Returns all aliases as `(old_symbol, new_symbol)` pairs, sorted by the old symbol name.
+    pub fn to_vec(&self) -> Vec<(String, String)> {
+        self.inner.lock().iter()
+            .map(|(old, new)| (old.clone(), new.clone()))
+            .collect()
+    }
+}
+
+impl Default for SymbolAliases {
+    fn default() -> Self {
+        SymbolAliases::new()
+    }
+}
+
+impl Clone for SymbolAliases {
+    fn clone(&self) -> SymbolAliases {
+        SymbolAliases {
+            inner: Mutex::new(self.inner.lock().clone()),
+        }
+    }
+}
+
+impl CrateNamespace {
+    /// Declares that the symbol `old_symbol` was renamed to `new_symbol`,
+    /// such that crates in (or above) this namespace that still depend on `old_symbol`
+    /// will be linked against `new_symbol` instead.
+    ///
+    /// Both names may be given with or without their trailing hash;
+    /// `old_symbol` matches all symbols with the same name regardless of their hash.
+    /// Aliases are only consulted when a symbol cannot be found by its exact name.
+    pub fn add_symbol_alias(&self, old_symbol: &str, new_symbol: &str) -> Result<(), &'static str> {
+        if let Some(previous) = self.symbol_aliases.insert(old_symbol, new_symbol)? {
+            warn!("Symbol alias {:?} in namespace {} was changed from {:?} to {:?}",
+                old_symbol, self.name, previous, new_symbol,
+            );
+        }
+        Ok(())
+    }
+
+    /// Removes the alias for the given `old_symbol` from this namespace,
+    /// e.g., once all of its dependents have been updated to use the new symbol name.
+    ///
+    /// Returns the new symbol name that `old_symbol` was an alias of, if any.
+    /// Crates that were already linked via this alias are unaffected.
+    pub fn remove_symbol_alias(&self, old_symbol: &str) -> Option<String> {
+        self.symbol_aliases.remove(old_symbol)
+    }
+
+    /// Returns all symbol aliases in this namespace (excluding its recursive namespace)
+    /// as `(old_symbol, new_symbol)` pairs.
+    pub fn symbol_aliases(&self) -> Vec<(String, String)> {
+        self.symbol_aliases.to_vec()
+    }
+
+    /// Finds the symbol that the given `demangled_full_symbol` is an alias of in this namespace,
+    /// along with the exact `CrateNamespace` where that symbol was found.
+    ///
+    /// If the alias target was given without a hash, it must match exactly one symbol.
+    pub fn get_aliased_symbol_and_namespace(&self, demangled_full_symbol: &str) -> Option<(WeakSectionRef, &CrateNamespace)> {
+        let target = self.symbol_aliases.resolve(demangled_full_symbol)?;
+        self.get_symbol_and_namespace_exact(&target).or_else(|| {
+            let prefix = format!("{}{}", symbol_name_without_hash(&target), SECTION_HASH_DELIMITER);
+            let mut matches = self.find_symbols_starting_with_and_namespace(&prefix);
+            if matches.len() == 1 {
+                matches.pop().map(|(_name, sym, ns)| (sym, ns))
+            } else {
+                warn!("Symbol alias {:?} -> {:?} matched {} symbols in namespace {}",
+                    demangled_full_symbol, target, matches.len(), self.name,
+                );
+                None
+            }
+        })
+    }
+}
+
+/// Returns the given symbol name without its trailing hash and hash delimiter, if it has one.
+///
+/// # Examples
+/// name: "`keyboard_new::init::h832430094f98e56b`", return value: "`keyboard_new::init`"
+/// name: "`keyboard_new::handler`", return value: "`keyboard_new::handler`"
+fn symbol_name_without_hash(name: &str) -> &str {
+    match name.rsplit_once(SECTION_HASH_DELIMITER) {
+        Some((prefix, hash)) if !hash.is_empty() && hash.bytes().all(|b| b.is_ascii_hexdigit()) => prefix,
+        _ => name,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aliases_ignore_hashes() {
+        let aliases = SymbolAliases::new();
+        aliases.insert("my_crate::old_fn::h843a613894da0c24", "my_crate::new_fn").unwrap();
+        assert_eq!(aliases.resolve("my_crate::old_fn::h0123456789abcdef").as_deref(), Some("my_crate::new_fn"));
+        assert_eq!(aliases.resolve("my_crate::old_fn").as_deref(), Some("my_crate::new_fn"));
+        assert_eq!(aliases.resolve("my_crate::handler"), None);
+    }
+
+    #[test]
+    fn alias_chains_are_followed() {
+        let aliases = SymbolAliases::new();
+        aliases.insert("a::f", "a::g").unwrap();
+        aliases.insert("a::g", "a::h::h843a613894da0c24").unwrap();
+        assert_eq!(aliases.resolve("a::f").as_deref(), Some("a::h::h843a613894da0c24"));
+        assert_eq!(aliases.remove("a::g").as_deref(), Some("a::h::h843a613894da0c24"));
+        assert_eq!(aliases.resolve("a::f").as_deref(), Some("a::g"));
+    }
+
+    #[test]
+    fn alias_cycles_are_rejected() {
+        let aliases = SymbolAliases::new();
+        assert!(aliases.insert("a::f", "a::f").is_err());
+        aliases.insert("a::f", "a::g").unwrap();
+        aliases.insert("a::g", "a::h").unwrap();
+        assert!(aliases.insert("a::h", "a::f::h843a613894da0c24").is_err());
+        assert_eq!(aliases.to_vec().len(), 2);
+    }
+}
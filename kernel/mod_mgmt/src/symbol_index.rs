@@ -0,0 +1,143 @@
+//! An index of the global symbols defined by the crate object files in a namespace's directory,
+//! including those whose crates haven't been loaded yet.
+//!
+//! When a relocation refers to a symbol that isn't loaded, the loader first infers which crate defines it
+//! from the crate name prefixes within the symbol, e.g., `my_crate` in `my_crate::foo::h843a9ea794da0c24`.
+//! That doesn't work for `no_mangle` symbols, nor for symbols whose defining crate isn't among their prefixes,
+//! so this index is consulted as a last resort.
+//!
+//! The index is built lazily upon its first lookup, and is then incrementally updated
+//! whenever object files are added to or removed from the directory.
+
+use alloc::{
+    collections::BTreeMap,
+    string::{String, ToString},
+    vec::Vec,
+};
+use fs_node::FileRef;
+use rustc_demangle::demangle;
+use spin::Mutex;
+use xmas_elf::{ElfFile, sections::SHN_UNDEF, symbol_table::{Binding, Entry, Type}};
+use crate::{find_symbol_table, NamespaceDir, StrRef};
+
+/// The file extension of crate object files, which are the only files that are indexed.
+const OBJECT_FILE_EXTENSION: &str = ".o";
+
+/// A map from symbol names to the crate object files that define them.
+pub struct SymbolIndex {
+    inner: Mutex<IndexInner>,
+}
+
+#[derive(Clone)]
+struct IndexInner {
+    /// The demangled name of each indexed symbol, mapped to the name of the object file that defines it.
+    symbols: BTreeMap<StrRef, StrRef>,
+    /// The name of each indexed object file, mapped to the symbols that it defines.
+    files: BTreeMap<StrRef, Vec<StrRef>>,
+}
+
+impl SymbolIndex {
+    /// Creates a new empty index.
+    pub const fn new() -> SymbolIndex {
+        SymbolIndex {
+            inner: Mutex::new(IndexInner {
+                symbols: BTreeMap::new(),
+                files: BTreeMap::new(),
+            }),
+        }
+    }
+
+    /// Returns the object file in the given `dir` that defines the symbol `demangled_full_symbol`, if any.
+    ///
+    /// Before searching, this indexes any object files that were added to the `dir` since the last search,
+    /// and forgets those that were removed from it.
+    pub fn find(&self, dir: &NamespaceDir, demangled_full_symbol: &str) -> Option<FileRef> {
+        let file_name = {
+            let mut inner = self.inner.lock();
+            inner.update(dir);
+            inner.symbols.get(demangled_full_symbol)?.clone()
+        };
+        dir.lock().get_file(&file_name)
+    }
+
+    /// Forgets all indexed object files, such that they will be indexed again upon the next search.
+    ///
+    /// This is only necessary if an object file's contents were overwritten without changing its name.
+    pub fn clear(&self) {
+        let mut inner = self.inner.lock();
+        inner.symbols.clear();
+        inner.files.clear();
+    }
+}
+
+impl Clone for SymbolIndex {
+    fn clone(&self) -> SymbolIndex {
+        SymbolIndex {
+            inner: Mutex::new(self.inner.lock().clone()),
+        }
+    }
+}
+
+impl IndexInner {
+    /// Brings this index up to date with the object files currently in the given `dir`.
+    fn update(&mut self, dir: &NamespaceDir) {
+        let file_names: Vec<String> = dir.lock().list()
+            .into_iter()
+            .filter(|name| name.ends_with(OBJECT_FILE_EXTENSION))
+            .collect();
+
+        let removed_files: Vec<StrRef> = self.files.keys()
+            .filter(|indexed| !file_names.iter().any(|name| name.as_str() == indexed.as_str()))
+            .cloned()
+            .collect();
+        for file_name in removed_files {
+            for symbol in self.files.remove(&file_name).unwrap_or_default() {
+                if self.symbols.get(&symbol) == Some(&file_name) {
+                    self.symbols.remove(&symbol);
+                }
+            }
+        }
+
+        for name in file_names {
+            if self.files.contains_key(name.as_str()) {
+                continue;
+            }
+            let file_name = StrRef::from(name.as_str());
+            let file = dir.lock().get_file(&name);
+            let symbols = match file.ok_or("file was removed").and_then(|f| defined_symbols(&f)) {
+                Ok(symbols) => symbols,
+                Err(e) => {
+                    // Record the file anyway, such that we don't try to index it again.
+                    warn!("SymbolIndex: couldn't index the symbols in object file {:?}: {}", name, e);
+                    Vec::new()
+                }
+            };
+            for symbol in &symbols {
+                self.symbols.insert(symbol.clone(), file_name.clone());
+            }
+            self.files.insert(file_name, symbols);
+        }
+    }
+}
+
+/// Returns the demangled names of the global symbols defined (not merely referenced) in the given object file.
+fn defined_symbols(file: &FileRef) -> Result<Vec<StrRef>, &'static str> {
+    let file = file.lock();
+    let mapped_pages = file.as_mapping()?;
+    let byte_slice: &[u8] = mapped_pages.as_slice(0, file.len())?;
+    let elf_file = ElfFile::new(byte_slice)?;
+    let symtab = find_symbol_table(&elf_file)?;
+
+    let mut symbols = Vec::new();
+    for entry in symtab.iter() {
+        if entry.get_binding() != Ok(Binding::Global) || entry.shndx() == SHN_UNDEF {
+            continue;
+        }
+        if !matches!(entry.get_type(), Ok(Type::Func | Type::Object | Type::Tls)) {
+            continue;
+        }
+        let name = entry.get_name(&elf_file)?;
+        symbols.push(StrRef::from(demangle(name).to_string().as_str()));
+    }
+    Ok(symbols)
+}
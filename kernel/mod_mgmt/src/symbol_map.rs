@@ -0,0 +1,171 @@
+//! The map of all global symbols in a `CrateNamespace`.
+//!
+//! Exact lookups by symbol name are by far the most common operation on a symbol map,
+//! as every relocation of every newly-loaded crate must resolve its target symbol.
+//! Thus, each symbol is stored in one of several hash map shards, selected by the hash of its name,
+//! such that exact lookups only need to briefly lock a single shard for reading
+//! and can run concurrently with each other and with lookups in other shards.
+//!
+//! Each symbol is also stored in a trie, which supports the prefix searches
+//! used for fuzzy symbol matching and for finding a crate's symbols.
+//! All modifications go through a [`SymbolMapGuard`], which holds the trie's lock
+//! while it updates both the trie and the relevant shard, keeping the two in sync.
+
+use alloc::{format, string::String, vec::Vec};
+use core::ops::Deref;
+use hashbrown::HashMap;
+use qp_trie::Trie;
+use spin::{Mutex, MutexGuard, RwLock};
+use crate_metadata::{StrRef, WeakSectionRef};
+
+/// The number of hash map shards in each symbol map; must be a power of two.
+const NUM_SHARDS: usize = 64;
+
+/// A "symbol map" from a fully-qualified demangled symbol name
+/// to a weak reference to a `LoadedSection`.
+/// This is used for relocations, and for looking up function names.
+pub struct SymbolMap {
+    /// All symbols, ordered by name, for prefix searches.
+    trie: Mutex<Trie<StrRef, WeakSectionRef>>,
+    /// All symbols, sharded by the hash of their name, for fast exact lookups.
+    shards: [RwLock<HashMap<StrRef, WeakSectionRef>>; NUM_SHARDS],
+}
+
+impl SymbolMap {
+    /// Creates a new empty symbol map.
+    pub fn new() -> SymbolMap {
+        SymbolMap {
+            trie: Mutex::new(Trie::new()),
+            shards: core::array::from_fn(|_| RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// Returns the section for the symbol with exactly the given name, if it exists.
+    ///
+    /// This only locks the shard that could contain the symbol, not the whole symbol map.
+    pub fn get(&self, name: &str) -> Option<WeakSectionRef> {
+        self.shard(name).read().get(name).cloned()
+    }
+
+    /// Locks this symbol map, allowing it to be modified or searched by prefix.
+    ///
+    /// Exact lookups via [`SymbolMap::get()`] aren't blocked while the symbol map is locked.
+    pub fn lock(&self) -> SymbolMapGuard<'_> {
+        SymbolMapGuard {
+            trie: self.trie.lock(),
+            shards: &self.shards,
+        }
+    }
+
+    /// Checks that the trie and the shards contain exactly the same symbols,
+    /// returning a description of each discrepancy found.
+    pub fn check_consistency(&self) -> Vec<String> {
+        // Holding the trie's lock ensures that no shard is modified while they're checked.
+        let trie = self.trie.lock();
+        let mut problems = Vec::new();
+        let mut num_in_shards = 0;
+        for (index, shard) in self.shards.iter().enumerate() {
+            let shard = shard.read();
+            num_in_shards += shard.len();
+            for (name, section) in shard.iter() {
+                if shard_index(name) != index {
+                    problems.push(format!("symbol {:?} is in shard {} instead of shard {}", name, index, shard_index(name)));
+                }
+                match trie.get(name.as_bytes()) {
+                    Some(trie_section) if trie_section.ptr_eq(section) => { }
+                    Some(_) => problems.push(format!("symbol {:?} maps to different sections in the trie and its shard", name)),
+                    None => problems.push(format!("symbol {:?} is in its shard but not in the trie", name)),
+                }
+            }
+        }
+        if num_in_shards != trie.count() {
+            problems.push(format!("the trie has {} symbols, but the shards have {}", trie.count(), num_in_shards));
+        }
+        problems
+    }
+
+    fn shard(&self, name: &str) -> &RwLock<HashMap<StrRef, WeakSectionRef>> {
+        &self.shards[shard_index(name)]
+    }
+}
+
+impl Default for SymbolMap {
+    fn default() -> Self {
+        SymbolMap::new()
+    }
+}
+
+impl Clone for SymbolMap {
+    fn clone(&self) -> Self {
+        // Holding the trie's lock ensures that no shard is modified while they're copied.
+        let trie = self.trie.lock();
+        SymbolMap {
+            trie: Mutex::new(trie.clone()),
+            shards: core::array::from_fn(|i| RwLock::new(self.shards[i].read().clone())),
+        }
+    }
+}
+
+/// A locked [`SymbolMap`].
+///
+/// This dereferences to the symbol map's trie, which can be used to search symbols by prefix.
+/// Symbols must be added and removed via this guard's methods, never via the trie directly.
+pub struct SymbolMapGuard<'m> {
+    trie: MutexGuard<'m, Trie<StrRef, WeakSectionRef>>,
+    shards: &'m [RwLock<HashMap<StrRef, WeakSectionRef>>; NUM_SHARDS],
+}
+
+impl<'m> SymbolMapGuard<'m> {
+    /// Adds the given symbol, returning the section it previously mapped to, if any.
+    pub fn insert(&mut self, name: StrRef, section: WeakSectionRef) -> Option<WeakSectionRef> {
+        self.shards[shard_index(&name)].write().insert(name.clone(), section.clone());
+        self.trie.insert(name, section)
+    }
+
+    /// Removes the given symbol, returning the section it mapped to, if any.
+    pub fn remove(&mut self, name: &str) -> Option<WeakSectionRef> {
+        self.shards[shard_index(name)].write().remove(name);
+        self.trie.remove(name.as_bytes())
+    }
+}
+
+impl<'m> Deref for SymbolMapGuard<'m> {
+    type Target = Trie<StrRef, WeakSectionRef>;
+    fn deref(&self) -> &Self::Target {
+        &self.trie
+    }
+}
+
+/// Returns the index of the shard that the symbol with the given name belongs in.
+///
+/// This uses the FNV-1a hash, which is cheap to compute for short strings like symbol names.
+fn shard_index(name: &str) -> usize {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in name.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    // The high bits are better mixed than the low bits.
+    (hash >> 32) as usize & (NUM_SHARDS - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shard_index_is_in_bounds_and_spread() {
+        let mut counts = [0usize; NUM_SHARDS];
+        for i in 0..(NUM_SHARDS * 100) {
+            let name = alloc::format!("my_crate::foo_{i}::h843a613894da0c24");
+            counts[shard_index(&name)] += 1;
+        }
+        // Every shard should receive a reasonable share of similar-looking symbols.
+        assert!(counts.iter().all(|&count| count > 50 && count < 150), "{:?}", counts);
+    }
+
+    #[test]
+    fn shard_index_is_deterministic() {
+        assert_eq!(shard_index("core::panicking::panic"), shard_index("core::panicking::panic"));
+    }
+}
@@ -0,0 +1,145 @@
+//! Checks that linked crates have compatible versions, as declared via the `crate_version` crate.
+//!
+//! A crate's version requirements are checked against the crates it actually links against
+//! once it has been loaded and relocated,
+//! and a replacement crate's version is checked against the requirements of the crates
+//! that depend on the crate it replaces before it's swapped in.
+
+use alloc::{
+    collections::BTreeMap,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::fmt;
+use crate_version::{CrateVersion, Incompatibility, Requirement, REQUIREMENTS_SYMBOL_NAME, VERSION_SYMBOL_NAME};
+use crate::{CrateNamespace, LoadedCrate, SectionType, StrongCrateRef, SECTION_HASH_DELIMITER};
+
+/// A crate whose version doesn't satisfy the requirement of a crate that depends on it.
+#[derive(Clone, Debug)]
+pub struct VersionMismatch {
+    /// The name of the crate that has the requirement.
+    pub dependent: String,
+    /// The name of the required crate, without a hash.
+    pub dependency: String,
+    pub required: CrateVersion,
+    /// The version of the required crate, if it declares one.
+    pub found: Option<CrateVersion>,
+    pub reason: Incompatibility,
+}
+
+impl fmt::Display for VersionMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} requires {} {}, ", self.dependent, self.dependency, self.required)?;
+        match self.found {
+            Some(found) => write!(f, "found {} ({})", found, self.reason),
+            None => write!(f, "found a crate with {}", self.reason),
+        }
+    }
+}
+
+/// The error returned when one or more crates' version requirements aren't satisfied.
+#[derive(Clone, Debug)]
+pub struct IncompatibleVersions(pub Vec<VersionMismatch>);
+
+impl fmt::Display for IncompatibleVersions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} incompatible crate version(s):", self.0.len())?;
+        for mismatch in &self.0 {
+            write!(f, "\n    {}", mismatch)?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the version that the given crate declares via `crate_version::declare!()`, if any.
+pub fn crate_version(krate: &LoadedCrate) -> Option<CrateVersion> {
+    with_static_bytes(krate, VERSION_SYMBOL_NAME, CrateVersion::decode).flatten()
+}
+
+/// Returns the version requirements that the given crate declares via `crate_version::requires!()`.
+pub fn crate_requirements(krate: &LoadedCrate) -> Vec<Requirement> {
+    with_static_bytes(krate, REQUIREMENTS_SYMBOL_NAME, |bytes| Requirement::decode_all(bytes).collect())
+        .unwrap_or_default()
+}
+
+/// Checks that the crates that the given crate links against satisfy its version requirements.
+///
+/// Requirements on crates that it doesn't link against are ignored.
+pub fn check_dependencies(crate_ref: &StrongCrateRef) -> Result<(), IncompatibleVersions> {
+    let krate = crate_ref.lock_as_ref();
+    let requirements = crate_requirements(&krate);
+    if requirements.is_empty() {
+        return Ok(());
+    }
+
+    // The crates that this crate actually links against, by name without their hash.
+    let mut dependencies: BTreeMap<String, StrongCrateRef> = BTreeMap::new();
+    for sec in krate.sections.values() {
+        for dependency in &sec.inner.read().sections_i_depend_on {
+            if let Some(dep_crate) = dependency.section.parent_crate.upgrade() {
+                if dep_crate.ptr_eq(crate_ref) {
+                    continue;
+                }
+                let name = dep_crate.lock_as_ref().crate_name_without_hash().to_string();
+                dependencies.entry(name).or_insert(dep_crate);
+            }
+        }
+    }
+
+    let mismatches: Vec<VersionMismatch> = requirements.iter()
+        .filter_map(|req| {
+            let dep_crate = dependencies.get(req.crate_name())?;
+            check_requirement(&krate.crate_name, req, &dep_crate.lock_as_ref())
+        })
+        .collect();
+    if mismatches.is_empty() { Ok(()) } else { Err(IncompatibleVersions(mismatches)) }
+}
+
+/// Checks that the given `new_crate` satisfies the version requirements of all crates
+/// in the given `namespace` (and its recursive namespaces) that require a crate of the same name,
+/// i.e., that the `new_crate` can replace that crate.
+pub fn check_dependents(namespace: &CrateNamespace, new_crate: &LoadedCrate) -> Result<(), IncompatibleVersions> {
+    let new_crate_name = new_crate.crate_name_without_hash();
+    let mut mismatches = Vec::new();
+    namespace.for_each_crate(true, |_crate_name, crate_ref| {
+        let krate = crate_ref.lock_as_ref();
+        for req in crate_requirements(&krate).iter().filter(|req| req.crate_name() == new_crate_name) {
+            mismatches.extend(check_requirement(&krate.crate_name, req, new_crate));
+        }
+        true
+    });
+    if mismatches.is_empty() { Ok(()) } else { Err(IncompatibleVersions(mismatches)) }
+}
+
+/// Checks a crate's dependencies after it has been loaded and relocated,
+/// logging any mismatches in detail.
+pub(crate) fn check_loaded_crate(crate_ref: &StrongCrateRef) -> Result<(), &'static str> {
+    check_dependencies(crate_ref).map_err(|e| {
+        error!("crate {} can't be linked: {}", crate_ref.lock_as_ref().crate_name, e);
+        "crate's dependencies don't satisfy its version requirements"
+    })
+}
+
+fn check_requirement(dependent: &str, req: &Requirement, dependency: &LoadedCrate) -> Option<VersionMismatch> {
+    let found = crate_version(dependency);
+    let result = match found {
+        Some(version) => version.satisfies(&req.version),
+        None => Err(Incompatibility::Unversioned),
+    };
+    result.err().map(|reason| VersionMismatch {
+        dependent: dependent.to_string(),
+        dependency: req.crate_name().to_string(),
+        required: req.version,
+        found,
+        reason,
+    })
+}
+
+/// Invokes the given function with the contents of the given crate's static with the given name.
+fn with_static_bytes<R>(krate: &LoadedCrate, symbol_name: &str, f: impl FnOnce(&[u8]) -> R) -> Option<R> {
+    let expected_name = format!("{}{}{}", krate.crate_name_as_prefix(), symbol_name, SECTION_HASH_DELIMITER);
+    let sec = krate.find_section(|sec| sec.typ == SectionType::Rodata && sec.name_without_hash() == expected_name)?;
+    let sec_pages = sec.mapped_pages.lock();
+    let bytes: &[u8] = sec_pages.as_slice(sec.mapped_pages_offset, sec.size).ok()?;
+    Some(f(bytes))
+}
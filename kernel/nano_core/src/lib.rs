@@ -118,6 +118,10 @@ where
     }
 
     let rsdp_address = boot_info.rsdp();
+    let efi_runtime = boot_info.efi_runtime();
+    let command_line = boot_info.command_line()
+        .map(boot_info::CommandLine::new)
+        .unwrap_or_default();
     // init memory management: set up stack with guard page, heap, kernel text/data mappings, etc
     let (
         kernel_mmi_ref,
@@ -139,6 +143,7 @@ where
 
     println!("nano_core(): initialized memory subsystem.");
     println!("nano_core(): bootloader-provided RSDP address: {:X?}", rsdp_address);
+    println!("nano_core(): kernel command line: {:?}", command_line);
 
     // Dump basic information about this build of Theseus.
     log::info!("\n    \
@@ -163,6 +168,8 @@ where
         text_mapped_pages.into_inner(),
         rodata_mapped_pages.into_inner(),
         data_mapped_pages.into_inner(),
+        // Theseus's bootloaders load the nano_core at the addresses it was linked at.
+        0,
         false,
     ) {
         Ok(NanoCoreItems { nano_core_crate_ref, init_symbol_values, num_new_symbols }) => {
@@ -207,7 +214,10 @@ where
 
             (nano_core_crate_ref, multicore_info)
         }
-        Err((msg, _mapped_pages_array)) => return Err(msg),
+        Err((err, _mapped_pages_array)) => {
+            println!("nano_core(): failed to parse the nano_core crate: {}", err);
+            return Err(err.into());
+        }
     };
 
     #[cfg(loadable)] {
@@ -240,9 +250,10 @@ where
         identity_mappings: identity_mapped_pages,
     };
     #[cfg(not(loadable))] {
-        captain::init(kernel_mmi_ref, stack, drop_after_init, multicore_info, rsdp_address)?;
+        captain::init(kernel_mmi_ref, stack, drop_after_init, multicore_info, rsdp_address, efi_runtime, command_line)?;
     }
     #[cfg(loadable)] {
+        use boot_info::{CommandLine, EfiRuntimeInfo};
         use captain::DropAfterInit;
         use memory::{MmiRef, PhysicalAddress};
         use no_drop::NoDrop;
@@ -254,10 +265,10 @@ where
             .ok_or("no single symbol matching \"captain::init\"")?;
         log::info!("The nano_core (in loadable mode) is invoking the captain init function: {:?}", section);
 
-        type CaptainInitFunc = fn(MmiRef, NoDrop<Stack>, DropAfterInit, MulticoreBringupInfo, Option<PhysicalAddress>) -> Result<(), &'static str>;
+        type CaptainInitFunc = fn(MmiRef, NoDrop<Stack>, DropAfterInit, MulticoreBringupInfo, Option<PhysicalAddress>, Option<EfiRuntimeInfo>, CommandLine) -> Result<(), &'static str>;
         let func: &CaptainInitFunc = unsafe { section.as_func() }?;
 
-        func(kernel_mmi_ref, stack, drop_after_init, multicore_info, rsdp_address)?;
+        func(kernel_mmi_ref, stack, drop_after_init, multicore_info, rsdp_address, efi_runtime, command_line)?;
     }
 
     // the captain shouldn't return ...
@@ -1,8 +1,12 @@
-use alloc::vec;
+use alloc::{vec, vec::Vec};
 
+use cpu::CpuId;
 use log::error;
 use nic_buffers::{ReceivedFrame, TransmitBuffer};
 use smoltcp::phy;
+
+use crate::{interface::Counters, zero_copy};
+use rss::FlowTuple;
 pub use smoltcp::phy::DeviceCapabilities;
 
 /// Standard maximum transition unit for ethernet cards.
@@ -37,6 +41,15 @@ pub trait NetworkDevice: Send + Sync {
         caps.max_transmission_unit = STANDARD_MTU;
         caps
     }
+
+    /// Returns the CPU on which received packets of the given `flow` are processed,
+    /// or `None` if the device doesn't steer flows to specific CPUs.
+    ///
+    /// Devices that use receive-side scaling to spread flows across per-CPU receive queues
+    /// should override this.
+    fn cpu_for_flow(&self, _flow: &FlowTuple) -> Option<CpuId> {
+        None
+    }
 }
 
 /// Wrapper around a network device.
@@ -48,10 +61,14 @@ pub trait NetworkDevice: Send + Sync {
 /// ```
 pub(crate) struct DeviceWrapper<'a> {
     pub(crate) inner: &'a mut dyn NetworkDevice,
+    pub(crate) counters: &'a Counters,
+    pub(crate) zero_copy_bindings: &'a zero_copy::Bindings,
+    /// The MTU of the interface, which overrides the device's MTU.
+    pub(crate) mtu: usize,
 }
 
 impl<'a> phy::Device for DeviceWrapper<'a> {
-    type RxToken<'b> = RxToken where Self: 'b;
+    type RxToken<'b> = RxToken<'b> where Self: 'b;
 
     type TxToken<'c> = TxToken<'c> where Self: 'c;
 
@@ -59,47 +76,60 @@ impl<'a> phy::Device for DeviceWrapper<'a> {
         &mut self,
         _: smoltcp::time::Instant,
     ) -> Option<(Self::RxToken<'_>, Self::TxToken<'_>)> {
-        let frame = self.inner.receive()?;
-        Some((RxToken { inner: frame }, TxToken { device: self.inner }))
+        let frame = loop {
+            let frame = self.inner.receive()?;
+            Counters::add(&self.counters.rx_packets, 1);
+            Counters::add(&self.counters.rx_bytes, frame.0.iter().map(|buf| buf.len()).sum());
+            match zero_copy::deliver(self.zero_copy_bindings, self.counters, frame) {
+                Ok(()) => continue,
+                Err(frame) => break frame,
+            }
+        };
+        Some((
+            RxToken { inner: frame, counters: self.counters },
+            TxToken { device: self.inner, counters: self.counters },
+        ))
     }
 
     fn transmit(&mut self, _: smoltcp::time::Instant) -> Option<Self::TxToken<'_>> {
-        Some(TxToken { device: self.inner })
+        Some(TxToken { device: self.inner, counters: self.counters })
     }
 
     fn capabilities(&self) -> DeviceCapabilities {
-        self.inner.capabilities()
+        let mut caps = self.inner.capabilities();
+        caps.max_transmission_unit = self.mtu;
+        caps
     }
 }
 
 /// The receive token.
-pub(crate) struct RxToken {
+pub(crate) struct RxToken<'a> {
     inner: ReceivedFrame,
+    counters: &'a Counters,
 }
 
-impl phy::RxToken for RxToken {
+impl<'a> phy::RxToken for RxToken<'a> {
     fn consume<R, F>(mut self, f: F) -> R
     where
         F: FnOnce(&mut [u8]) -> R,
     {
-        if self.inner.0.len() > 1 {
-            error!(
-                "DeviceWrapper::receive(): received frame with {} buffers",
-                self.inner.0.len()
-            );
+        // smoltcp copies the payloads of received packets into its sockets' buffers.
+        Counters::add(&self.counters.rx_copied_bytes, self.inner.0.iter().map(|buf| buf.len()).sum());
+        match self.inner.0.as_mut_slice() {
+            [buffer] => f(buffer),
+            buffers => {
+                // A frame that spans multiple buffers must be copied into one contiguous slice.
+                let mut frame: Vec<u8> = buffers.iter().flat_map(|buf| buf.iter().copied()).collect();
+                f(&mut frame)
+            }
         }
-        let slice = self
-            .inner
-            .0
-            .first_mut()
-            .expect("received frame spanning no buffers");
-        f(slice)
     }
 }
 
 /// The transmit token.
 pub(crate) struct TxToken<'a> {
     device: &'a mut dyn NetworkDevice,
+    counters: &'a Counters,
 }
 
 impl<'a> phy::TxToken for TxToken<'a> {
@@ -113,12 +143,15 @@ impl<'a> phy::TxToken for TxToken<'a> {
                 let mut buf = TransmitBuffer::new(len).expect("failed to allocate transmit buffer");
                 let ret = f(&mut buf);
                 self.device.send(buf);
+                Counters::add(&self.counters.tx_packets, 1);
+                Counters::add(&self.counters.tx_bytes, len.into());
                 ret
             }
             Err(_) => {
                 // For appropriate behavior on an error, see this smoltcp changelog entry:
                 // <https://github.com/smoltcp-rs/smoltcp/blob/fa7fd3c321b8a3bbe1a8a4ee2ee5dc1b63231d6b/CHANGELOG.md?plain=1#L57>
                 error!("packet too large: dropping packet");
+                Counters::add(&self.counters.tx_dropped, 1);
                 let mut buf = vec![0; len];
                 f(&mut buf)
             }
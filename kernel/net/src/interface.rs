@@ -1,6 +1,11 @@
-use alloc::{sync::Arc, vec::Vec};
-use core::marker::PhantomData;
+use alloc::{string::String, sync::Arc, vec::Vec};
+use core::{
+    marker::PhantomData,
+    sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+};
 
+use cpu::CpuId;
+use rss::FlowTuple;
 use smoltcp::{iface, phy::DeviceCapabilities, socket::AnySocket, wire};
 pub use smoltcp::{
     iface::SocketSet,
@@ -9,50 +14,223 @@ pub use smoltcp::{
 use sync_block::Mutex;
 use sync_irq::IrqSafeMutex;
 
-use crate::{device::DeviceWrapper, NetworkDevice, Socket};
+use crate::{device::DeviceWrapper, zero_copy, NetworkDevice, Socket, ZeroCopyUdpSocket};
+
+/// The minimum MTU of an interface, which is the minimum MTU required by IPv4.
+const MIN_MTU: usize = 68;
 
 /// A network interface.
 ///
 /// This is a wrapper around a network device which provides higher level
 /// abstractions such as polling sockets.
 pub struct NetworkInterface {
+    name: String,
     pub(crate) inner: Mutex<iface::Interface>,
     device: &'static IrqSafeMutex<dyn crate::NetworkDevice>,
     pub(crate) sockets: Mutex<SocketSet<'static>>,
+    up: AtomicBool,
+    /// The MTU of the interface, which may be lower than the device's MTU.
+    mtu: AtomicUsize,
+    gateway: Mutex<Option<IpAddress>>,
+    counters: Counters,
+    pub(crate) zero_copy_bindings: zero_copy::Bindings,
+}
+
+/// A snapshot of the traffic counters of a [`NetworkInterface`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct InterfaceCounters {
+    pub rx_packets: u64,
+    pub rx_bytes: u64,
+    /// The number of received packets that were dropped because the interface was down.
+    pub rx_dropped: u64,
+    pub tx_packets: u64,
+    pub tx_bytes: u64,
+    /// The number of packets that were dropped because they were too large to transmit.
+    pub tx_dropped: u64,
+    /// The number of received packets that were delivered to a [`ZeroCopyUdpSocket`].
+    pub rx_zero_copy_packets: u64,
+    /// The number of payload bytes that were delivered to a [`ZeroCopyUdpSocket`] without copying.
+    pub rx_zero_copy_bytes: u64,
+    /// The number of received bytes that were copied, either by smoltcp into socket buffers
+    /// or to reassemble frames that spanned multiple receive buffers.
+    pub rx_copied_bytes: u64,
+}
+
+/// The live traffic counters of a [`NetworkInterface`].
+#[derive(Default)]
+pub(crate) struct Counters {
+    pub(crate) rx_packets: AtomicU64,
+    pub(crate) rx_bytes: AtomicU64,
+    pub(crate) rx_dropped: AtomicU64,
+    pub(crate) tx_packets: AtomicU64,
+    pub(crate) tx_bytes: AtomicU64,
+    pub(crate) tx_dropped: AtomicU64,
+    pub(crate) rx_zero_copy_packets: AtomicU64,
+    pub(crate) rx_zero_copy_bytes: AtomicU64,
+    pub(crate) rx_copied_bytes: AtomicU64,
+}
+
+impl Counters {
+    pub(crate) fn add(counter: &AtomicU64, value: usize) {
+        counter.fetch_add(value as u64, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> InterfaceCounters {
+        InterfaceCounters {
+            rx_packets: self.rx_packets.load(Ordering::Relaxed),
+            rx_bytes: self.rx_bytes.load(Ordering::Relaxed),
+            rx_dropped: self.rx_dropped.load(Ordering::Relaxed),
+            tx_packets: self.tx_packets.load(Ordering::Relaxed),
+            tx_bytes: self.tx_bytes.load(Ordering::Relaxed),
+            tx_dropped: self.tx_dropped.load(Ordering::Relaxed),
+            rx_zero_copy_packets: self.rx_zero_copy_packets.load(Ordering::Relaxed),
+            rx_zero_copy_bytes: self.rx_zero_copy_bytes.load(Ordering::Relaxed),
+            rx_copied_bytes: self.rx_copied_bytes.load(Ordering::Relaxed),
+        }
+    }
 }
 
 impl NetworkInterface {
-    pub(crate) fn new<T>(device: &'static IrqSafeMutex<T>, ip: IpCidr, gateway: IpAddress) -> Self
+    pub(crate) fn new<T>(
+        name: String,
+        device: &'static IrqSafeMutex<T>,
+        ip: IpCidr,
+        gateway: Option<IpAddress>,
+    ) -> Self
     where
         T: NetworkDevice,
     {
         let hardware_addr = wire::EthernetAddress(device.lock().mac_address()).into();
+        let mtu = device.lock().capabilities().max_transmission_unit;
+        let counters = Counters::default();
+        let zero_copy_bindings = zero_copy::Bindings::default();
 
         let mut wrapper = DeviceWrapper {
             inner: &mut *device.lock(),
+            counters: &counters,
+            zero_copy_bindings: &zero_copy_bindings,
+            mtu,
         };
 
         let mut config = iface::Config::new(hardware_addr);
         config.random_seed = random::next_u64();
 
         let mut interface =
-            iface::Interface::new(config, &mut wrapper, smoltcp::time::Instant::ZERO);
+            iface::Interface::new(config, &mut wrapper, smoltcp_now());
         interface.update_ip_addrs(|ip_addrs| {
             // NOTE: This won't fail as ip_addrs has a capacity of 2 (defined in smoltcp)
             // and this is the only address we are pushing.
             ip_addrs.push(ip).unwrap();
         });
-        match gateway {
-            IpAddress::Ipv4(addr) => interface.routes_mut().add_default_ipv4_route(addr),
-            IpAddress::Ipv6(addr) => interface.routes_mut().add_default_ipv6_route(addr),
+        if let Some(gateway) = gateway {
+            add_default_route(&mut interface, gateway).expect("btree map route storage exhausted");
         }
-        .expect("btree map route storage exhausted");
 
         Self {
+            name,
             inner: Mutex::new(interface),
             device,
             sockets: Mutex::new(SocketSet::new(Vec::new())),
+            up: AtomicBool::new(true),
+            mtu: AtomicUsize::new(mtu),
+            gateway: Mutex::new(gateway),
+            counters,
+            zero_copy_bindings,
+        }
+    }
+
+    /// Returns the name of the interface, e.g., `eth0` or `lo`.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns whether this is the loopback interface.
+    pub fn is_loopback(&self) -> bool {
+        self.name == crate::LOOPBACK_INTERFACE_NAME
+    }
+
+    /// Returns whether the interface is up, i.e., is sending and receiving packets.
+    pub fn is_up(&self) -> bool {
+        self.up.load(Ordering::Acquire)
+    }
+
+    /// Brings the interface up or down.
+    ///
+    /// While the interface is down, packets aren't sent and received packets are dropped.
+    pub fn set_up(&self, up: bool) {
+        self.up.store(up, Ordering::Release);
+    }
+
+    /// Returns the MAC address of the interface.
+    pub fn mac_address(&self) -> [u8; 6] {
+        self.device.lock().mac_address()
+    }
+
+    /// Returns the MTU of the interface.
+    pub fn mtu(&self) -> usize {
+        self.mtu.load(Ordering::Relaxed)
+    }
+
+    /// Sets the MTU of the interface.
+    ///
+    /// Returns an error if the MTU is smaller than the minimum required by IPv4
+    /// or larger than the MTU supported by the underlying device.
+    pub fn set_mtu(&self, mtu: usize) -> Result<(), &'static str> {
+        if mtu < MIN_MTU {
+            return Err("MTU is too small");
+        }
+        if mtu > self.device.lock().capabilities().max_transmission_unit {
+            return Err("MTU is larger than the device supports");
+        }
+        self.mtu.store(mtu, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Returns the IP addresses (and their subnets) assigned to the interface.
+    pub fn ip_addrs(&self) -> Vec<IpCidr> {
+        self.inner.lock().ip_addrs().to_vec()
+    }
+
+    /// Replaces the IP addresses (and their subnets) assigned to the interface.
+    ///
+    /// Returns an error if more addresses are given than the interface supports.
+    pub fn set_ip_addrs(&self, addrs: &[IpCidr]) -> Result<(), &'static str> {
+        let mut result = Ok(());
+        self.inner.lock().update_ip_addrs(|ip_addrs| {
+            if addrs.len() > ip_addrs.capacity() {
+                result = Err("too many IP addresses for one interface");
+                return;
+            }
+            ip_addrs.clear();
+            for addr in addrs {
+                // This can't fail, as we checked the capacity above.
+                let _ = ip_addrs.push(*addr);
+            }
+        });
+        result
+    }
+
+    /// Returns the default gateway of the interface, if any.
+    pub fn gateway(&self) -> Option<IpAddress> {
+        *self.gateway.lock()
+    }
+
+    /// Sets or removes the default gateway of the interface.
+    pub fn set_gateway(&self, gateway: Option<IpAddress>) -> Result<(), &'static str> {
+        let mut inner = self.inner.lock();
+        let routes = inner.routes_mut();
+        routes.remove_default_ipv4_route();
+        routes.remove_default_ipv6_route();
+        if let Some(gateway) = gateway {
+            add_default_route(&mut *inner, gateway).map_err(|_| "route table is full")?;
         }
+        *self.gateway.lock() = gateway;
+        Ok(())
+    }
+
+    /// Returns a snapshot of the interface's traffic counters.
+    pub fn counters(&self) -> InterfaceCounters {
+        self.counters.snapshot()
     }
 
     /// Adds a socket to the interface.
@@ -68,21 +246,69 @@ impl NetworkInterface {
         }
     }
 
+    /// Binds a [`ZeroCopyUdpSocket`] to the given `port` on this interface,
+    /// which can queue up to `capacity` received datagrams.
+    ///
+    /// Returns an error if another zero-copy socket is already bound to the port.
+    pub fn bind_zero_copy_udp(
+        self: Arc<Self>,
+        port: u16,
+        capacity: usize,
+    ) -> Result<ZeroCopyUdpSocket, &'static str> {
+        ZeroCopyUdpSocket::bind(self, port, capacity)
+    }
+
     /// Polls the sockets associated with the interface.
     ///
     /// Returns a boolean indicating whether the readiness of any socket may
     /// have changed.
     pub fn poll(&self) -> bool {
+        if !self.is_up() {
+            let mut device = self.device.lock();
+            while device.receive().is_some() {
+                Counters::add(&self.counters.rx_dropped, 1);
+            }
+            return false;
+        }
+
         let mut inner = self.inner.lock();
         let mut wrapper = DeviceWrapper {
             inner: &mut *self.device.lock(),
+            counters: &self.counters,
+            zero_copy_bindings: &self.zero_copy_bindings,
+            mtu: self.mtu(),
         };
         let mut sockets = self.sockets.lock();
 
-        inner.poll(smoltcp::time::Instant::ZERO, &mut wrapper, &mut sockets)
+        inner.poll(smoltcp_now(), &mut wrapper, &mut sockets)
+    }
+
+    /// Returns the CPU on which received packets of the given `flow` are processed,
+    /// if the underlying device steers flows to specific CPUs.
+    pub fn cpu_for_flow(&self, flow: &FlowTuple) -> Option<CpuId> {
+        self.device.lock().cpu_for_flow(flow)
     }
 
     pub fn capabilities(&self) -> DeviceCapabilities {
-        self.device.lock().capabilities()
+        let mut caps = self.device.lock().capabilities();
+        caps.max_transmission_unit = self.mtu();
+        caps
     }
 }
+
+fn add_default_route(
+    interface: &mut iface::Interface,
+    gateway: IpAddress,
+) -> Result<Option<iface::Route>, iface::RouteTableFull> {
+    match gateway {
+        IpAddress::Ipv4(addr) => interface.routes_mut().add_default_ipv4_route(addr),
+        IpAddress::Ipv6(addr) => interface.routes_mut().add_default_ipv6_route(addr),
+    }
+}
+
+/// Returns the current time as a smoltcp timestamp, which drives TCP timers such as
+/// retransmissions, delayed ACKs, and keep-alives.
+fn smoltcp_now() -> smoltcp::time::Instant {
+    let since_boot = time::Instant::ZERO.elapsed();
+    smoltcp::time::Instant::from_micros(since_boot.as_micros() as i64)
+}
@@ -0,0 +1,77 @@
+//! Metrics about the traffic on each network interface.
+
+use core::fmt::{self, Display};
+
+use metrics::{FnMetric, MetricKind, Samples};
+
+use crate::InterfaceCounters;
+
+static BYTES: FnMetric = FnMetric::new(
+    "theseus_network_bytes_total",
+    "The number of bytes received or transmitted on each network interface.",
+    MetricKind::Counter,
+    |samples| collect(samples, |c| (c.rx_bytes, c.tx_bytes)),
+);
+
+static PACKETS: FnMetric = FnMetric::new(
+    "theseus_network_packets_total",
+    "The number of packets received or transmitted on each network interface.",
+    MetricKind::Counter,
+    |samples| collect(samples, |c| (c.rx_packets, c.tx_packets)),
+);
+
+static DROPPED_PACKETS: FnMetric = FnMetric::new(
+    "theseus_network_dropped_packets_total",
+    "The number of packets dropped while receiving or transmitting on each network interface.",
+    MetricKind::Counter,
+    |samples| collect(samples, |c| (c.rx_dropped, c.tx_dropped)),
+);
+
+static RECEIVED_BYTES_BY_PATH: FnMetric = FnMetric::new(
+    "theseus_network_received_bytes_by_path_total",
+    "The number of received bytes on each network interface that were delivered to sockets \
+    without copying (zero_copy) or copied into socket buffers (copied).",
+    MetricKind::Counter,
+    collect_received_bytes_by_path,
+);
+
+/// Registers the network metrics, if they haven't been registered already.
+pub(crate) fn register() {
+    for metric in [&BYTES, &PACKETS, &DROPPED_PACKETS, &RECEIVED_BYTES_BY_PATH] {
+        if let Err(e) = metrics::register(metric) {
+            log::warn!("net: failed to register metric {}: {}", metrics::Metric::name(metric), e);
+        }
+    }
+}
+
+/// Writes a received and a transmitted sample for each interface,
+/// using `select` to pick those values from the interface's counters.
+fn collect(samples: &mut Samples<'_>, select: fn(&InterfaceCounters) -> (u64, u64)) -> fmt::Result {
+    let interfaces = crate::get_interfaces().lock().clone();
+    for interface in interfaces {
+        let (rx, tx) = select(&interface.counters());
+        let name: &dyn Display = &interface.name();
+        samples.write("", &[("interface", name), ("direction", &"rx" as &dyn Display)], rx)?;
+        samples.write("", &[("interface", name), ("direction", &"tx" as &dyn Display)], tx)?;
+    }
+    Ok(())
+}
+
+fn collect_received_bytes_by_path(samples: &mut Samples<'_>) -> fmt::Result {
+    let interfaces = crate::get_interfaces().lock().clone();
+    for interface in interfaces {
+        let counters = interface.counters();
+        let name: &dyn Display = &interface.name();
+        samples.write(
+            "",
+            &[("interface", name), ("path", &"zero_copy" as &dyn Display)],
+            counters.rx_zero_copy_bytes,
+        )?;
+        samples.write(
+            "",
+            &[("interface", name), ("path", &"copied" as &dyn Display)],
+            counters.rx_copied_bytes,
+        )?;
+    }
+    Ok(())
+}
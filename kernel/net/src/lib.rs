@@ -2,25 +2,33 @@
 
 extern crate alloc;
 
-use alloc::{sync::Arc, vec::Vec};
+use alloc::{format, sync::Arc, vec::Vec};
 
 use smoltcp::wire::Ipv4Address;
-use spin::Mutex;
+use spin::{Mutex, Once};
 use sync_irq::IrqSafeMutex;
 
 mod device;
 mod interface;
+mod loopback;
+mod interface_metrics;
+pub mod ping;
 mod socket;
+mod tcp_options;
+mod zero_copy;
 
 pub use device::{DeviceCapabilities, NetworkDevice};
-pub use interface::{IpAddress, IpCidr, NetworkInterface, SocketSet};
+pub use interface::{InterfaceCounters, IpAddress, IpCidr, NetworkInterface, SocketSet};
 pub use smoltcp::{
     phy,
     socket::{icmp, tcp, udp},
     time::Instant,
     wire::{self, IpEndpoint},
 };
+pub use rss::{FlowAddrs, FlowTuple};
 pub use socket::{LockedSocket, Socket};
+pub use tcp_options::{TcpOptions, MAX_RX_BUFFER_SIZE};
+pub use zero_copy::ZeroCopyUdpSocket;
 
 /// A randomly chosen IP address that must be outside of the DHCP range.
 ///
@@ -32,6 +40,14 @@ const DEFAULT_LOCAL_IP: &str = "10.0.2.15/24";
 /// `10.0.2.2` is the default QEMU user-slirp networking gateway IP.
 const DEFAULT_GATEWAY_IP: IpAddress = IpAddress::Ipv4(Ipv4Address::new(10, 0, 2, 2));
 
+/// The name of the loopback interface.
+pub const LOOPBACK_INTERFACE_NAME: &str = "lo";
+
+/// The address of the loopback interface.
+const LOOPBACK_IP: &str = "127.0.0.1/8";
+
+static LOOPBACK_DEVICE: Once<IrqSafeMutex<loopback::LoopbackDevice>> = Once::new();
+
 // TODO: Make mutex rwlock?
 // TODO: Use atomic append-only vec?
 static NETWORK_INTERFACES: Mutex<Vec<Arc<NetworkInterface>>> = Mutex::new(Vec::new());
@@ -40,30 +56,59 @@ static NETWORK_INTERFACES: Mutex<Vec<Arc<NetworkInterface>>> = Mutex::new(Vec::n
 ///
 /// The function will convert the device to an interface and it will then be
 /// accessible using [`get_interfaces()`].
+/// Interfaces are named `eth0`, `eth1`, etc., in the order they were registered.
 pub fn register_device<T>(device: &'static IrqSafeMutex<T>) -> Arc<NetworkInterface>
 where
     T: 'static + NetworkDevice + Send,
 {
+    let mut interfaces = NETWORK_INTERFACES.lock();
+    let index = interfaces.iter().filter(|i| !i.is_loopback()).count();
     let interface = NetworkInterface::new(
+        format!("eth{index}"),
         device,
         // TODO: use DHCP to acquire an IP address and gateway.
         DEFAULT_LOCAL_IP.parse().unwrap(),
-        DEFAULT_GATEWAY_IP,
+        Some(DEFAULT_GATEWAY_IP),
     );
 
     let interface_arc = Arc::new(interface);
-    NETWORK_INTERFACES.lock().push(interface_arc.clone());
+    interfaces.push(interface_arc.clone());
+    interface_metrics::register();
     interface_arc
 }
 
+/// Creates and registers the loopback interface, if it hasn't already been registered,
+/// and returns it.
+pub fn init_loopback() -> Arc<NetworkInterface> {
+    let mut interfaces = NETWORK_INTERFACES.lock();
+    if let Some(lo) = interfaces.iter().find(|i| i.is_loopback()) {
+        return lo.clone();
+    }
+    let device = LOOPBACK_DEVICE.call_once(Default::default);
+    let interface = Arc::new(NetworkInterface::new(
+        LOOPBACK_INTERFACE_NAME.into(),
+        device,
+        LOOPBACK_IP.parse().unwrap(),
+        None,
+    ));
+    interfaces.push(interface.clone());
+    interface_metrics::register();
+    interface
+}
+
 /// Returns a list of available interfaces behind a mutex.
 pub fn get_interfaces() -> &'static Mutex<Vec<Arc<NetworkInterface>>> {
     &NETWORK_INTERFACES
 }
 
-/// Returns the first available interface.
+/// Returns the interface with the given name, e.g., `eth0` or `lo`.
+pub fn get_interface(name: &str) -> Option<Arc<NetworkInterface>> {
+    NETWORK_INTERFACES.lock().iter().find(|i| i.name() == name).cloned()
+}
+
+/// Returns the first available interface that isn't the loopback interface.
 pub fn get_default_interface() -> Option<Arc<NetworkInterface>> {
-    NETWORK_INTERFACES.lock().first().cloned()
+    NETWORK_INTERFACES.lock().iter().find(|i| !i.is_loopback()).cloned()
 }
 
 /// Returns a port in the range reserved for private, dynamic, and ephemeral
@@ -0,0 +1,52 @@
+//! A software loopback device, which receives every packet sent on it.
+
+use alloc::{collections::VecDeque, vec};
+
+use nic_buffers::{ReceiveBuffer, ReceivedFrame, TransmitBuffer};
+use spin::Once;
+
+use crate::NetworkDevice;
+
+/// The MAC address of the loopback device, which is locally administered.
+const LOOPBACK_MAC: [u8; 6] = [0x02, 0, 0, 0, 0, 0];
+
+/// The maximum number of packets that can be queued on the loopback device
+/// before further packets are dropped.
+const MAX_QUEUED_PACKETS: usize = 256;
+
+/// The pool to which looped-back buffers are returned once they've been consumed,
+/// such that they can be reused for later packets.
+static RX_BUFFER_POOL: Once<mpmc::Queue<ReceiveBuffer>> = Once::new();
+
+#[derive(Default)]
+pub(crate) struct LoopbackDevice {
+    queue: VecDeque<TransmitBuffer>,
+}
+
+impl NetworkDevice for LoopbackDevice {
+    fn send(&mut self, buf: TransmitBuffer) {
+        if self.queue.len() < MAX_QUEUED_PACKETS {
+            self.queue.push_back(buf);
+        }
+    }
+
+    fn receive(&mut self) -> Option<ReceivedFrame> {
+        let buf = self.queue.pop_front()?;
+        let pool = RX_BUFFER_POOL.call_once(|| mpmc::Queue::with_capacity(MAX_QUEUED_PACKETS));
+
+        // Reuse a previously looped-back buffer if possible, otherwise convert this one.
+        let rx_buf = match pool.pop() {
+            Some(mut rx_buf) if rx_buf.capacity() >= buf.len() => {
+                rx_buf.set_length(buf.length()).ok()?;
+                rx_buf.copy_from_slice(&buf);
+                rx_buf
+            }
+            _ => buf.into_receive_buffer(pool).ok()?,
+        };
+        Some(ReceivedFrame(vec![rx_buf]))
+    }
+
+    fn mac_address(&self) -> [u8; 6] {
+        LOOPBACK_MAC
+    }
+}
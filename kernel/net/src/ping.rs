@@ -0,0 +1,198 @@
+//! An ICMP echo (ping) client.
+//!
+//! Replying to echo requests is handled by each [`NetworkInterface`] itself
+//! whenever it's up and polled, so there is no separate echo service.
+
+use alloc::{collections::BTreeMap, sync::Arc, vec, vec::Vec};
+use core::time::Duration;
+
+use smoltcp::{
+    phy::ChecksumCapabilities,
+    socket::icmp::{Endpoint, PacketBuffer, PacketMetadata, Socket},
+    wire::{Icmpv4Packet, Icmpv4Repr},
+};
+use time::Instant;
+
+use crate::{IpAddress, NetworkInterface};
+
+/// The maximum number of data bytes in each echo request.
+pub const MAX_PAYLOAD_SIZE: usize = 1472;
+
+/// The number of echo replies that can be buffered before being processed.
+const NUM_BUFFERED_PACKETS: usize = 8;
+
+/// The size of an ICMPv4 echo header.
+const ICMP_HEADER_SIZE: usize = 8;
+
+/// Options for [`ping()`].
+#[derive(Clone, Debug)]
+pub struct PingOptions {
+    /// The number of echo requests to send.
+    pub count: u16,
+    /// The time to wait between sending each echo request.
+    pub interval: Duration,
+    /// The time to wait for a reply after the last echo request is sent.
+    pub reply_timeout: Duration,
+    /// The maximum time to spend pinging, regardless of how many replies were received.
+    pub timeout: Option<Duration>,
+    /// The number of data bytes in each echo request, at most [`MAX_PAYLOAD_SIZE`].
+    pub payload_size: usize,
+}
+
+impl Default for PingOptions {
+    fn default() -> Self {
+        Self {
+            count: 4,
+            interval: Duration::from_secs(1),
+            reply_timeout: Duration::from_secs(1),
+            timeout: None,
+            payload_size: 56,
+        }
+    }
+}
+
+/// An echo reply received by [`ping()`].
+#[derive(Clone, Copy, Debug)]
+pub struct PingReply {
+    pub seq_no: u16,
+    /// The number of data bytes in the reply.
+    pub bytes: usize,
+    /// The round-trip time of the echo request and its reply.
+    pub rtt: Duration,
+}
+
+/// Statistics about a completed [`ping()`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PingStatistics {
+    pub transmitted: u16,
+    pub received: u16,
+    pub min_rtt: Option<Duration>,
+    pub max_rtt: Option<Duration>,
+    pub avg_rtt: Option<Duration>,
+}
+
+impl PingStatistics {
+    /// Returns the percentage of echo requests that weren't replied to.
+    pub fn packet_loss(&self) -> f64 {
+        if self.transmitted == 0 {
+            return 0.0;
+        }
+        100.0 - (self.received as f64 / self.transmitted as f64) * 100.0
+    }
+}
+
+/// Sends ICMP echo requests to the given IPv4 `remote` address on the given `interface`,
+/// invoking `on_reply` for each echo reply, and returns statistics once done.
+///
+/// This busy-polls the interface until all replies were received or the timeouts
+/// in the given `options` have elapsed.
+pub fn ping<F>(
+    interface: &Arc<NetworkInterface>,
+    remote: IpAddress,
+    options: &PingOptions,
+    mut on_reply: F,
+) -> Result<PingStatistics, &'static str>
+where
+    F: FnMut(&PingReply),
+{
+    if !matches!(remote, IpAddress::Ipv4(_)) {
+        return Err("only IPv4 addresses can be pinged");
+    }
+    if options.payload_size > MAX_PAYLOAD_SIZE {
+        return Err("ping payload size is too large");
+    }
+
+    let packet_size = ICMP_HEADER_SIZE + options.payload_size;
+    let buffer_size = NUM_BUFFERED_PACKETS * packet_size;
+    let rx_buffer = PacketBuffer::new(
+        vec![PacketMetadata::EMPTY; NUM_BUFFERED_PACKETS],
+        vec![0; buffer_size],
+    );
+    let tx_buffer = PacketBuffer::new(
+        vec![PacketMetadata::EMPTY; NUM_BUFFERED_PACKETS],
+        vec![0; buffer_size],
+    );
+    let socket = interface.clone().add_socket(Socket::new(rx_buffer, tx_buffer));
+
+    // Use a random identifier so that concurrent pings don't receive each other's replies.
+    let ident = random::next_u32() as u16;
+    socket
+        .lock()
+        .bind(Endpoint::Ident(ident))
+        .map_err(|_| "failed to bind ICMP socket")?;
+
+    let data = vec![0; options.payload_size];
+    let start = Instant::now();
+    let deadline = options.timeout.map(|t| start + t);
+    let mut send_times: BTreeMap<u16, Instant> = BTreeMap::new();
+    let mut rtts: Vec<Duration> = Vec::new();
+    let mut last_sent: Option<Instant> = None;
+    let mut num_sent: u16 = 0;
+
+    loop {
+        let now = Instant::now();
+        let send_due = last_sent.map_or(true, |t| t + options.interval <= now);
+        if num_sent < options.count && send_due && socket.lock().can_send() {
+            let repr = Icmpv4Repr::EchoRequest {
+                ident,
+                seq_no: num_sent,
+                data: &data,
+            };
+            let mut locked = socket.lock();
+            let payload = locked
+                .send(repr.buffer_len(), remote)
+                .map_err(|_| "failed to send echo request")?;
+            repr.emit(&mut Icmpv4Packet::new_unchecked(payload), &ChecksumCapabilities::default());
+            drop(locked);
+
+            send_times.insert(num_sent, now);
+            last_sent = Some(now);
+            num_sent += 1;
+        }
+
+        interface.poll();
+
+        while socket.lock().can_recv() {
+            let mut locked = socket.lock();
+            let (payload, _) = locked.recv().map_err(|_| "failed to receive packet")?;
+            let received_at = Instant::now();
+            let Ok(packet) = Icmpv4Packet::new_checked(payload) else { continue };
+            let Ok(repr) = Icmpv4Repr::parse(&packet, &ChecksumCapabilities::default()) else { continue };
+            if let Icmpv4Repr::EchoReply { ident: reply_ident, seq_no, data } = repr {
+                if reply_ident != ident {
+                    continue;
+                }
+                if let Some(sent_at) = send_times.remove(&seq_no) {
+                    let reply = PingReply {
+                        seq_no,
+                        bytes: data.len(),
+                        rtt: received_at.duration_since(sent_at),
+                    };
+                    drop(locked);
+                    rtts.push(reply.rtt);
+                    on_reply(&reply);
+                }
+            }
+        }
+
+        let now = Instant::now();
+        let all_sent = num_sent == options.count;
+        let all_received = rtts.len() == options.count as usize;
+        let reply_timed_out = last_sent.map_or(true, |t| t + options.reply_timeout <= now);
+        if (all_sent && (all_received || reply_timed_out))
+            || deadline.is_some_and(|d| d <= now)
+        {
+            break;
+        }
+        core::hint::spin_loop();
+    }
+
+    let total: Duration = rtts.iter().sum();
+    Ok(PingStatistics {
+        transmitted: num_sent,
+        received: rtts.len() as u16,
+        min_rtt: rtts.iter().min().copied(),
+        max_rtt: rtts.iter().max().copied(),
+        avg_rtt: (!rtts.is_empty()).then(|| total / rtts.len() as u32),
+    })
+}
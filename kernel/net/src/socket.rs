@@ -1,13 +1,15 @@
 use crate::NetworkInterface;
 use alloc::sync::Arc;
+use cpu::CpuId;
 use core::{
     marker::PhantomData,
     ops::{Deref, DerefMut},
 };
+use rss::{FlowAddrs, FlowTuple};
 use smoltcp::{
     iface::{SocketHandle, SocketSet},
     socket::AnySocket,
-    wire::{IpEndpoint, IpListenEndpoint},
+    wire::{IpAddress, IpEndpoint, IpListenEndpoint},
 };
 use sync_block::MutexGuard;
 
@@ -87,3 +89,33 @@ where
         }
     }
 }
+
+impl Socket<smoltcp::socket::tcp::Socket<'static>> {
+    /// Returns the CPU on which received packets of this connection are processed,
+    /// if the interface's device steers flows to specific CPUs, e.g., using receive-side scaling.
+    ///
+    /// Returns `None` if the socket isn't connected.
+    ///
+    /// The task servicing this socket can be pinned to the returned CPU
+    /// (see `spawn::TaskBuilder::pin_on_cpu()`), such that the connection's data never crosses CPUs.
+    pub fn flow_cpu(&self) -> Option<CpuId> {
+        // The socket lock must be released before the device is locked,
+        // as `NetworkInterface::poll()` acquires them in the opposite order.
+        let (remote, local) = {
+            let socket = self.lock();
+            (socket.remote_endpoint()?, socket.local_endpoint()?)
+        };
+        self.interface.cpu_for_flow(&received_flow(remote, local)?)
+    }
+}
+
+/// Returns the flow of packets received from `remote` to `local`,
+/// or `None` if the endpoints use different IP versions.
+fn received_flow(remote: IpEndpoint, local: IpEndpoint) -> Option<FlowTuple> {
+    let addrs = match (remote.addr, local.addr) {
+        (IpAddress::Ipv4(src), IpAddress::Ipv4(dst)) => FlowAddrs::Ipv4 { src: src.0, dst: dst.0 },
+        (IpAddress::Ipv6(src), IpAddress::Ipv6(dst)) => FlowAddrs::Ipv6 { src: src.0, dst: dst.0 },
+        _ => return None,
+    };
+    Some(FlowTuple { addrs, ports: Some((remote.port, local.port)) })
+}
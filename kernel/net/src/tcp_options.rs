@@ -0,0 +1,102 @@
+//! Performance tuning options for TCP sockets.
+//!
+//! smoltcp negotiates the following TCP extensions automatically:
+//! * Window scaling, which is used if the receive buffer is larger than 64 KiB,
+//!   such that more than 64 KiB can be in flight at once.
+//! * Selective acknowledgments (SACK), which tell the sender which out-of-order segments
+//!   were received, such that only the missing ones are retransmitted.
+//!
+//! The version of smoltcp that Theseus uses doesn't implement congestion control,
+//! so senders are only limited by the receiver's window.
+
+use alloc::{sync::Arc, vec};
+use core::time::Duration;
+
+use smoltcp::socket::tcp;
+
+use crate::{NetworkInterface, Socket};
+
+/// The largest window that can be advertised without window scaling.
+const MAX_UNSCALED_WINDOW: usize = u16::MAX as usize;
+
+/// The largest receive buffer that smoltcp supports,
+/// since RFC 7323 limits the window scale shift to 14.
+pub const MAX_RX_BUFFER_SIZE: usize = 1 << 30;
+
+/// Options that tune the performance of a TCP socket.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TcpOptions {
+    /// The size of the receive buffer, which bounds the receive window.
+    ///
+    /// Window scaling is used if this is larger than 64 KiB.
+    /// This can't be larger than [`MAX_RX_BUFFER_SIZE`].
+    pub rx_buffer_size: usize,
+    /// The size of the transmit buffer, which bounds how much data can be in flight.
+    pub tx_buffer_size: usize,
+    /// Whether Nagle's algorithm is used, which delays sending small segments
+    /// while earlier data is unacknowledged, in order to send fewer, larger segments.
+    pub nagle: bool,
+    /// How long acknowledgments are delayed, in the hope that they can be combined with data
+    /// or with the acknowledgment of a later segment, or `None` to acknowledge immediately.
+    pub ack_delay: Option<Duration>,
+    /// How often keep-alive packets are sent on an idle connection, if at all.
+    pub keep_alive: Option<Duration>,
+    /// How long the connection may go without receiving anything before it's aborted, if at all.
+    pub timeout: Option<Duration>,
+}
+
+impl Default for TcpOptions {
+    /// Returns smoltcp's defaults, with 8 KiB buffers.
+    fn default() -> Self {
+        Self {
+            rx_buffer_size: 8192,
+            tx_buffer_size: 8192,
+            nagle: true,
+            ack_delay: Some(Duration::from_millis(10)),
+            keep_alive: None,
+            timeout: None,
+        }
+    }
+}
+
+impl TcpOptions {
+    /// Returns the window scale shift that a socket with these options advertises,
+    /// which is `0` if window scaling isn't used.
+    pub fn window_shift(&self) -> u8 {
+        // This matches how smoltcp chooses the shift when it creates a socket.
+        let bits = usize::BITS - self.rx_buffer_size.leading_zeros();
+        bits.saturating_sub(16) as u8
+    }
+
+    /// Returns whether a socket with these options uses window scaling.
+    pub fn uses_window_scaling(&self) -> bool {
+        self.rx_buffer_size > MAX_UNSCALED_WINDOW
+    }
+
+    /// Applies the options that can be changed after a socket was created.
+    pub fn apply(&self, socket: &mut tcp::Socket<'_>) {
+        socket.set_nagle_enabled(self.nagle);
+        socket.set_ack_delay(self.ack_delay.map(Into::into));
+        socket.set_keep_alive(self.keep_alive.map(Into::into));
+        socket.set_timeout(self.timeout.map(Into::into));
+    }
+}
+
+impl NetworkInterface {
+    /// Adds a TCP socket with the given options to the interface.
+    ///
+    /// Returns an error if the receive buffer is larger than [`MAX_RX_BUFFER_SIZE`].
+    pub fn add_tcp_socket(
+        self: Arc<Self>,
+        options: &TcpOptions,
+    ) -> Result<Socket<tcp::Socket<'static>>, &'static str> {
+        if options.rx_buffer_size > MAX_RX_BUFFER_SIZE {
+            return Err("TCP receive buffer cannot be larger than 1 GiB");
+        }
+        let rx_buffer = tcp::SocketBuffer::new(vec![0; options.rx_buffer_size]);
+        let tx_buffer = tcp::SocketBuffer::new(vec![0; options.tx_buffer_size]);
+        let mut socket = tcp::Socket::new(rx_buffer, tx_buffer);
+        options.apply(&mut socket);
+        Ok(self.add_socket(socket))
+    }
+}
@@ -0,0 +1,147 @@
+//! Sockets that receive UDP payloads directly from the NIC's receive buffers, without copying them.
+//!
+//! Received frames are normally handed to smoltcp, which copies their payloads
+//! into each socket's receive buffer.
+//! Instead, frames destined to a port bound by a [`ZeroCopyUdpSocket`] are parsed in place,
+//! their headers are stripped off, and the remaining [`PacketSegment`] is queued on the socket.
+//! The underlying receive buffer is returned to the NIC once the segment is dropped,
+//! so segments shouldn't be held for long, or the NIC may run out of receive buffers.
+
+use alloc::{collections::BTreeMap, collections::VecDeque, sync::Arc};
+
+use nic_buffers::{PacketSegment, ReceivedFrame};
+use smoltcp::wire::{
+    EthernetFrame, EthernetProtocol, IpAddress, IpEndpoint, IpProtocol, Ipv4Packet, UdpPacket,
+};
+use spin::Mutex;
+
+use crate::{interface::Counters, NetworkInterface};
+
+/// The header length of an Ethernet frame without a VLAN tag.
+const ETHERNET_HEADER_LEN: usize = 14;
+/// The header length of a UDP datagram.
+const UDP_HEADER_LEN: usize = 8;
+
+/// The received datagrams queued on a [`ZeroCopyUdpSocket`], and how many it can hold.
+pub(crate) struct SegmentQueue {
+    datagrams: Mutex<VecDeque<(IpEndpoint, PacketSegment)>>,
+    capacity: usize,
+}
+
+/// The zero-copy UDP sockets bound on an interface, by port.
+pub(crate) type Bindings = Mutex<BTreeMap<u16, Arc<SegmentQueue>>>;
+
+/// A UDP socket that receives datagrams as segments of the NIC's receive buffers.
+///
+/// The socket is bound to a port on every address of its interface,
+/// and can only receive datagrams; replies can be sent with a regular UDP socket.
+/// Fragmented datagrams aren't supported and are handled by smoltcp as usual.
+pub struct ZeroCopyUdpSocket {
+    port: u16,
+    queue: Arc<SegmentQueue>,
+    interface: Arc<NetworkInterface>,
+}
+
+impl ZeroCopyUdpSocket {
+    pub(crate) fn bind(
+        interface: Arc<NetworkInterface>,
+        port: u16,
+        capacity: usize,
+    ) -> Result<Self, &'static str> {
+        let queue = Arc::new(SegmentQueue {
+            datagrams: Mutex::new(VecDeque::with_capacity(capacity)),
+            capacity,
+        });
+        let mut bindings = interface.zero_copy_bindings.lock();
+        if bindings.contains_key(&port) {
+            return Err("port is already bound by a zero-copy socket");
+        }
+        bindings.insert(port, queue.clone());
+        drop(bindings);
+        Ok(Self { port, queue, interface })
+    }
+
+    /// Returns the port that this socket is bound to.
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Returns the oldest received datagram and its source, if any.
+    ///
+    /// Datagrams are only received when the interface is polled.
+    pub fn recv(&self) -> Option<(IpEndpoint, PacketSegment)> {
+        self.queue.datagrams.lock().pop_front()
+    }
+
+    /// Returns whether a datagram can be received.
+    pub fn can_recv(&self) -> bool {
+        !self.queue.datagrams.lock().is_empty()
+    }
+}
+
+impl Drop for ZeroCopyUdpSocket {
+    fn drop(&mut self) {
+        self.interface.zero_copy_bindings.lock().remove(&self.port);
+    }
+}
+
+/// Queues the given frame on the zero-copy socket it's destined to, if any,
+/// or otherwise returns it so it can be handed to smoltcp.
+pub(crate) fn deliver(
+    bindings: &Bindings,
+    counters: &Counters,
+    frame: ReceivedFrame,
+) -> Result<(), ReceivedFrame> {
+    let mut buffers = frame.0;
+    let datagram = match buffers.as_slice() {
+        [buffer] => parse_udp(buffer),
+        _ => None,
+    };
+    let Some((source, port, offset, len)) = datagram else {
+        return Err(ReceivedFrame(buffers));
+    };
+    let Some(queue) = bindings.lock().get(&port).cloned() else {
+        return Err(ReceivedFrame(buffers));
+    };
+
+    let mut datagrams = queue.datagrams.lock();
+    if datagrams.len() >= queue.capacity {
+        // The socket is full, so the datagram is dropped, as smoltcp would do.
+        Counters::add(&counters.rx_dropped, 1);
+        return Ok(());
+    }
+    let mut segment = PacketSegment::from(buffers.remove(0));
+    // These can't fail, as the datagram was checked to fit within the buffer.
+    let _ = segment.strip_front(offset as u16);
+    let _ = segment.truncate(len as u16);
+    Counters::add(&counters.rx_zero_copy_packets, 1);
+    Counters::add(&counters.rx_zero_copy_bytes, segment.len());
+    datagrams.push_back((source, segment));
+    Ok(())
+}
+
+/// Parses the given Ethernet frame as a valid, unfragmented IPv4 UDP datagram.
+///
+/// Returns its source, its destination port, and the offset and length of its payload.
+fn parse_udp(frame: &[u8]) -> Option<(IpEndpoint, u16, usize, usize)> {
+    let ethernet = EthernetFrame::new_checked(frame).ok()?;
+    if ethernet.ethertype() != EthernetProtocol::Ipv4 {
+        return None;
+    }
+    let ipv4 = Ipv4Packet::new_checked(ethernet.payload()).ok()?;
+    if ipv4.next_header() != IpProtocol::Udp
+        || ipv4.more_frags()
+        || ipv4.frag_offset() != 0
+        || !ipv4.verify_checksum()
+    {
+        return None;
+    }
+    let udp = UdpPacket::new_checked(ipv4.payload()).ok()?;
+    let (src_addr, dst_addr) = (IpAddress::Ipv4(ipv4.src_addr()), IpAddress::Ipv4(ipv4.dst_addr()));
+    if !udp.verify_checksum(&src_addr, &dst_addr) {
+        return None;
+    }
+    let offset = ETHERNET_HEADER_LEN + usize::from(ipv4.header_len()) + UDP_HEADER_LEN;
+    let len = usize::from(udp.len()) - UDP_HEADER_LEN;
+    Some((IpEndpoint::new(src_addr, udp.src_port()), udp.dst_port(), offset, len))
+}
@@ -0,0 +1,188 @@
+//! Fetchers that retrieve crate object files from a remote server over the network,
+//! such that they can be loaded with [`CrateNamespace::fetch_and_load_crate()`].
+//!
+//! Two protocols are supported:
+//! * HTTP, via [`HttpFetcher`], which requests each object file from a path on the server,
+//!   e.g., the `OBJECT_FILES_BUILD_DIR` directory served by the build server.
+//! * TFTP, via [`TftpFetcher`], which reads each object file by name,
+//!   as is commonly supported by network boot servers.
+//!
+//! [`CrateNamespace::fetch_and_load_crate()`]: mod_mgmt::CrateNamespace::fetch_and_load_crate
+
+#![no_std]
+
+extern crate alloc;
+
+pub mod tftp;
+
+use alloc::{format, string::String, sync::Arc, vec, vec::Vec};
+use http_client::{check_http_request, HttpClient, ACCEPT_ENCODING_HEADER};
+use log::{debug, error, warn};
+use mod_mgmt::CrateFetcher;
+use net::{
+    udp::{self, PacketBuffer, PacketMetadata},
+    IpEndpoint, NetworkInterface,
+};
+use percent_encoding::{utf8_percent_encode, DEFAULT_ENCODE_SET};
+use time::{Duration, Instant};
+
+/// How long to wait for an HTTP response before giving up.
+const HTTP_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long to wait for the next TFTP packet before retransmitting the last packet that was sent.
+const TFTP_RETRANSMIT_TIMEOUT: Duration = Duration::from_secs(1);
+/// The number of consecutive retransmissions after which a TFTP transfer is abandoned.
+const TFTP_MAX_RETRANSMITS: u32 = 5;
+/// The number of packets that the TFTP socket can buffer in each direction.
+const TFTP_SOCKET_PACKETS: usize = 4;
+
+/// Fetches crate object files from an HTTP server.
+pub struct HttpFetcher {
+    interface: Arc<NetworkInterface>,
+    remote_endpoint: IpEndpoint,
+    base_path: String,
+}
+
+impl HttpFetcher {
+    /// Creates a fetcher that requests each object file from `<base_path>/<object file name>`
+    /// on the server at the given `remote_endpoint`.
+    pub fn new(interface: Arc<NetworkInterface>, remote_endpoint: IpEndpoint, base_path: &str) -> HttpFetcher {
+        HttpFetcher {
+            interface,
+            remote_endpoint,
+            base_path: base_path.trim_end_matches('/').into(),
+        }
+    }
+}
+
+impl CrateFetcher for HttpFetcher {
+    fn fetch(&self, crate_object_file_name: &str) -> Result<Vec<u8>, &'static str> {
+        let path = format!("{}/{}", self.base_path, crate_object_file_name);
+        let request = format!(
+            "GET {} HTTP/1.1\r\nHost: {}:{}\r\n{}\r\nConnection: close\r\n\r\n",
+            utf8_percent_encode(&path, DEFAULT_ENCODE_SET),
+            self.remote_endpoint.addr,
+            self.remote_endpoint.port,
+            ACCEPT_ENCODING_HEADER,
+        );
+        if !check_http_request(request.as_bytes()) {
+            error!("net_crate_fetcher: created improper HTTP request: {:?}", request);
+            return Err("net_crate_fetcher: created improper HTTP request");
+        }
+
+        let mut client = HttpClient::new(&self.interface, net::get_ephemeral_port(), self.remote_endpoint)?;
+        let response = client.send(request, Some(HTTP_REQUEST_TIMEOUT));
+        if !client.is_closed() {
+            client.abort();
+        }
+        let response = response?;
+        let content = response.as_result_err_str()?.to_vec();
+        debug!("net_crate_fetcher: fetched {:?} over HTTP ({} bytes)", path, content.len());
+        Ok(content)
+    }
+}
+
+/// Fetches crate object files from a TFTP server.
+pub struct TftpFetcher {
+    interface: Arc<NetworkInterface>,
+    server: IpEndpoint,
+}
+
+impl TftpFetcher {
+    /// Creates a fetcher that reads each object file by name from the given TFTP `server`,
+    /// which usually listens on port [`tftp::TFTP_PORT`].
+    pub fn new(interface: Arc<NetworkInterface>, server: IpEndpoint) -> TftpFetcher {
+        TftpFetcher { interface, server }
+    }
+}
+
+impl CrateFetcher for TftpFetcher {
+    fn fetch(&self, crate_object_file_name: &str) -> Result<Vec<u8>, &'static str> {
+        let packet_size = tftp::DATA_HEADER_SIZE + tftp::BLOCK_SIZE;
+        let rx_buffer = PacketBuffer::new(
+            vec![PacketMetadata::EMPTY; TFTP_SOCKET_PACKETS],
+            vec![0; TFTP_SOCKET_PACKETS * packet_size],
+        );
+        let tx_buffer = PacketBuffer::new(
+            vec![PacketMetadata::EMPTY; TFTP_SOCKET_PACKETS],
+            vec![0; TFTP_SOCKET_PACKETS * packet_size],
+        );
+        let socket = self.interface.clone().add_socket(udp::Socket::new(rx_buffer, tx_buffer));
+        socket.lock()
+            .bind(net::get_ephemeral_port())
+            .map_err(|_| "net_crate_fetcher: failed to bind TFTP socket")?;
+
+        // The server replies from a new port (its transfer ID), to which all ACKs must be sent.
+        let mut server_tid: Option<IpEndpoint> = None;
+        let mut last_sent: Vec<u8> = tftp::read_request(crate_object_file_name);
+        let mut last_sent_to = self.server;
+        let mut expected_block: u16 = 1;
+        let mut content = Vec::new();
+        let mut retransmits = 0;
+        let mut last_activity = Instant::now();
+        socket.lock().send_slice(&last_sent, last_sent_to)
+            .map_err(|_| "net_crate_fetcher: failed to send TFTP read request")?;
+
+        loop {
+            self.interface.poll();
+
+            let mut locked = socket.lock();
+            if !locked.can_recv() {
+                drop(locked);
+                if last_activity.elapsed() < TFTP_RETRANSMIT_TIMEOUT {
+                    continue;
+                }
+                retransmits += 1;
+                if retransmits > TFTP_MAX_RETRANSMITS {
+                    error!("net_crate_fetcher: TFTP transfer of {:?} timed out", crate_object_file_name);
+                    return Err("net_crate_fetcher: TFTP transfer timed out");
+                }
+                socket.lock().send_slice(&last_sent, last_sent_to)
+                    .map_err(|_| "net_crate_fetcher: failed to retransmit TFTP packet")?;
+                last_activity = Instant::now();
+                continue;
+            }
+
+            let (bytes, metadata) = locked.recv().map_err(|_| "net_crate_fetcher: failed to receive TFTP packet")?;
+            let from = metadata.endpoint;
+            if from.addr != self.server.addr || server_tid.map_or(false, |tid| tid != from) {
+                warn!("net_crate_fetcher: ignoring TFTP packet from unexpected endpoint {}", from);
+                continue;
+            }
+            match tftp::Packet::parse(bytes) {
+                Some(tftp::Packet::Data { block, data }) if block == expected_block => {
+                    content.extend_from_slice(data);
+                    let is_last = data.len() < tftp::BLOCK_SIZE;
+                    server_tid = Some(from);
+                    last_sent = tftp::ack(block).to_vec();
+                    last_sent_to = from;
+                    locked.send_slice(&last_sent, last_sent_to)
+                        .map_err(|_| "net_crate_fetcher: failed to send TFTP ACK")?;
+                    drop(locked);
+                    // Flush the final ACK, as nothing else will be sent on this socket.
+                    self.interface.poll();
+                    if is_last {
+                        debug!("net_crate_fetcher: fetched {:?} over TFTP ({} bytes)", crate_object_file_name, content.len());
+                        return Ok(content);
+                    }
+                    // Block numbers wrap around for files larger than 32 MiB.
+                    expected_block = expected_block.wrapping_add(1);
+                    retransmits = 0;
+                    last_activity = Instant::now();
+                }
+                // A duplicate of the previous block means that our ACK was lost, so the server will wait for it.
+                Some(tftp::Packet::Data { block, .. }) if block == expected_block.wrapping_sub(1) => {
+                    locked.send_slice(&last_sent, last_sent_to)
+                        .map_err(|_| "net_crate_fetcher: failed to send TFTP ACK")?;
+                }
+                Some(tftp::Packet::Data { .. }) => {}
+                Some(tftp::Packet::Error { code, message }) => {
+                    error!("net_crate_fetcher: TFTP server returned error {} for {:?}: {}",
+                        code, crate_object_file_name, String::from_utf8_lossy(message),
+                    );
+                    return Err("net_crate_fetcher: TFTP server returned an error");
+                }
+                None => warn!("net_crate_fetcher: ignoring malformed TFTP packet"),
+            }
+        }
+    }
+}
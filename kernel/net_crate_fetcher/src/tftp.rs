@@ -0,0 +1,92 @@
+//! Encoding and decoding of the TFTP packets used to read a file, as defined in RFC 1350.
+
+use alloc::vec::Vec;
+
+/// The well-known port on which TFTP servers listen for requests.
+pub const TFTP_PORT: u16 = 69;
+/// The size of a full DATA block; a shorter block ends the transfer.
+pub const BLOCK_SIZE: usize = 512;
+/// The size of the opcode and block number that precede the data in a DATA packet.
+pub const DATA_HEADER_SIZE: usize = 4;
+
+const OPCODE_RRQ: u16 = 1;
+const OPCODE_DATA: u16 = 3;
+const OPCODE_ACK: u16 = 4;
+const OPCODE_ERROR: u16 = 5;
+
+/// A packet received from a TFTP server while reading a file.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Packet<'p> {
+    /// A block of the file.
+    Data { block: u16, data: &'p [u8] },
+    /// An error that ends the transfer.
+    Error { code: u16, message: &'p [u8] },
+}
+
+impl<'p> Packet<'p> {
+    /// Parses a packet received from the server,
+    /// returning `None` if it's malformed or isn't one that a server sends to a reading client.
+    pub fn parse(bytes: &'p [u8]) -> Option<Packet<'p>> {
+        let opcode = u16::from_be_bytes([*bytes.first()?, *bytes.get(1)?]);
+        let number = u16::from_be_bytes([*bytes.get(2)?, *bytes.get(3)?]);
+        let rest = &bytes[DATA_HEADER_SIZE..];
+        match opcode {
+            OPCODE_DATA if rest.len() <= BLOCK_SIZE => Some(Packet::Data { block: number, data: rest }),
+            OPCODE_ERROR => {
+                // The message is null-terminated, but be lenient if the terminator is missing.
+                let len = rest.iter().position(|&b| b == 0).unwrap_or(rest.len());
+                Some(Packet::Error { code: number, message: &rest[..len] })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Returns a read request (RRQ) for the file with the given name, in binary ("octet") mode.
+pub fn read_request(file_name: &str) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(2 + file_name.len() + 1 + b"octet".len() + 1);
+    packet.extend_from_slice(&OPCODE_RRQ.to_be_bytes());
+    packet.extend_from_slice(file_name.as_bytes());
+    packet.push(0);
+    packet.extend_from_slice(b"octet");
+    packet.push(0);
+    packet
+}
+
+/// Returns an acknowledgment (ACK) of the DATA packet with the given block number.
+pub fn ack(block: u16) -> [u8; 4] {
+    let [op0, op1] = OPCODE_ACK.to_be_bytes();
+    let [b0, b1] = block.to_be_bytes();
+    [op0, op1, b0, b1]
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use super::*;
+
+    #[test]
+    fn requests() {
+        assert_eq!(read_request("k#foo.o"), b"\x00\x01k#foo.o\x00octet\x00");
+        assert_eq!(ack(0x0102), [0, 4, 1, 2]);
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            Packet::parse(b"\x00\x03\x00\x07abc"),
+            Some(Packet::Data { block: 7, data: b"abc" })
+        );
+        assert_eq!(Packet::parse(b"\x00\x03\x00\x01"), Some(Packet::Data { block: 1, data: b"" }));
+        assert_eq!(
+            Packet::parse(b"\x00\x05\x00\x01File not found\x00"),
+            Some(Packet::Error { code: 1, message: b"File not found" })
+        );
+        assert_eq!(Packet::parse(b"\x00\x03\x00"), None);
+        assert_eq!(Packet::parse(b"\x00\x04\x00\x01"), None);
+
+        let mut too_long = std::vec![0, 3, 0, 1];
+        too_long.resize(DATA_HEADER_SIZE + BLOCK_SIZE + 1, 0);
+        assert_eq!(Packet::parse(&too_long), None);
+    }
+}
@@ -0,0 +1,383 @@
+//! A client for the subset of NFS version 3 (RFC 1813) needed to browse, read, and write files,
+//! along with the MOUNT and portmapper protocols needed to connect to a server.
+
+use crate::rpc::{Credentials, RpcClient};
+use crate::xdr::{XdrReader, XdrWriter};
+use alloc::{string::String, sync::Arc, vec::Vec};
+use core::time::Duration;
+use log::debug;
+use net::{IpAddress, IpEndpoint, NetworkInterface};
+
+/// The port of the portmapper, which tells clients which port each RPC program uses.
+pub const PORTMAPPER_PORT: u16 = 111;
+
+const PORTMAPPER_PROGRAM: u32 = 100000;
+const PORTMAPPER_VERSION: u32 = 2;
+const PORTMAPPER_GETPORT: u32 = 3;
+const IPPROTO_TCP: u32 = 6;
+
+const MOUNT_PROGRAM: u32 = 100005;
+const MOUNT_VERSION: u32 = 3;
+const MOUNT_MNT: u32 = 1;
+
+const NFS_PROGRAM: u32 = 100003;
+const NFS_VERSION: u32 = 3;
+const NFS_GETATTR: u32 = 1;
+const NFS_LOOKUP: u32 = 3;
+const NFS_READ: u32 = 6;
+const NFS_WRITE: u32 = 7;
+const NFS_CREATE: u32 = 8;
+const NFS_MKDIR: u32 = 9;
+const NFS_REMOVE: u32 = 12;
+const NFS_RMDIR: u32 = 13;
+const NFS_RENAME: u32 = 14;
+const NFS_READDIR: u32 = 16;
+
+/// The `stable_how` of writes that must reach stable storage before the server replies.
+const FILE_SYNC: u32 = 2;
+/// The `createhow3` mode that creates a file or truncates an existing one.
+const UNCHECKED: u32 = 0;
+
+const NF3DIR: u32 = 2;
+
+/// The maximum length of a file handle.
+const MAX_HANDLE_LEN: usize = 64;
+/// The maximum length of a file name.
+const MAX_NAME_LEN: usize = 255;
+
+/// The most bytes that are read or written in a single call.
+pub const MAX_TRANSFER_SIZE: usize = 32 * 1024;
+
+/// How long the client waits for the server to respond before giving up.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The server's opaque identifier for a file.
+pub type FileHandle = Vec<u8>;
+
+/// The attributes of a file.
+#[derive(Clone, Copy, Debug)]
+pub struct Attr {
+    pub is_dir: bool,
+    pub mode: u32,
+    pub uid: u32,
+    pub gid: u32,
+    pub size: u64,
+    pub mtime_sec: u32,
+}
+
+/// A connection to the NFS service of a server.
+pub struct Client {
+    rpc: RpcClient,
+}
+
+impl Client {
+    /// Connects to the NFS server at `server` and mounts the exported directory `export`,
+    /// returning the client and the handle of the exported directory.
+    ///
+    /// The ports of the MOUNT and NFS services are looked up using the server's portmapper.
+    /// Since the client doesn't use a privileged port, the export may need to allow that,
+    /// e.g., with the `insecure` option on Linux.
+    pub fn connect(
+        interface: Arc<NetworkInterface>,
+        server: IpAddress,
+        export: &str,
+        credentials: Credentials,
+    ) -> Result<(Self, FileHandle), &'static str> {
+        let (mount_port, nfs_port) = {
+            let mut portmapper = RpcClient::connect(
+                interface.clone(),
+                IpEndpoint::new(server, PORTMAPPER_PORT),
+                PORTMAPPER_PROGRAM,
+                PORTMAPPER_VERSION,
+                None,
+                DEFAULT_TIMEOUT,
+            )?;
+            (
+                get_port(&mut portmapper, MOUNT_PROGRAM, MOUNT_VERSION)?,
+                get_port(&mut portmapper, NFS_PROGRAM, NFS_VERSION)?,
+            )
+        };
+        debug!("nfs: mountd is on port {}, nfsd is on port {}", mount_port, nfs_port);
+
+        let root = {
+            let mut mountd = RpcClient::connect(
+                interface.clone(),
+                IpEndpoint::new(server, mount_port),
+                MOUNT_PROGRAM,
+                MOUNT_VERSION,
+                Some(credentials),
+                DEFAULT_TIMEOUT,
+            )?;
+            let reply = mountd.call(MOUNT_MNT, XdrWriter::new().string(export))?;
+            let mut reader = XdrReader::new(&reply);
+            status(&mut reader)?;
+            reader.opaque(MAX_HANDLE_LEN)?.to_vec()
+        };
+
+        let rpc = RpcClient::connect(
+            interface,
+            IpEndpoint::new(server, nfs_port),
+            NFS_PROGRAM,
+            NFS_VERSION,
+            Some(credentials),
+            DEFAULT_TIMEOUT,
+        )?;
+        Ok((Self { rpc }, root))
+    }
+
+    /// Returns the attributes of the file with the given handle.
+    pub fn getattr(&mut self, fh: &[u8]) -> Result<Attr, &'static str> {
+        let reply = self.rpc.call(NFS_GETATTR, XdrWriter::new().opaque(fh))?;
+        let mut reader = XdrReader::new(&reply);
+        status(&mut reader)?;
+        fattr(&mut reader)
+    }
+
+    /// Returns the handle and attributes of the file named `name` in the directory `dir`.
+    pub fn lookup(&mut self, dir: &[u8], name: &str) -> Result<(FileHandle, Attr), &'static str> {
+        let reply = self.rpc.call(NFS_LOOKUP, diropargs(dir, name))?;
+        let mut reader = XdrReader::new(&reply);
+        status(&mut reader)?;
+        let fh = reader.opaque(MAX_HANDLE_LEN)?.to_vec();
+        let attr = match post_op_attr(&mut reader)? {
+            Some(attr) => attr,
+            None => self.getattr(&fh)?,
+        };
+        Ok((fh, attr))
+    }
+
+    /// Returns the names of the entries in the directory `dir`, excluding `.` and `..`.
+    pub fn read_dir(&mut self, dir: &[u8]) -> Result<Vec<String>, &'static str> {
+        let mut names = Vec::new();
+        let mut cookie = 0;
+        let mut cookie_verifier = [0; 8];
+        loop {
+            let args = XdrWriter::new()
+                .opaque(dir)
+                .u64(cookie)
+                .fixed_opaque(&cookie_verifier)
+                .u32(MAX_TRANSFER_SIZE as u32);
+            let reply = self.rpc.call(NFS_READDIR, args)?;
+            let mut reader = XdrReader::new(&reply);
+            status(&mut reader)?;
+            post_op_attr(&mut reader)?;
+            cookie_verifier.copy_from_slice(reader.fixed_opaque(8)?);
+            while reader.bool()? {
+                let _file_id = reader.u64()?;
+                let name = reader.string(MAX_NAME_LEN)?;
+                cookie = reader.u64()?;
+                if name != "." && name != ".." {
+                    names.push(String::from(name));
+                }
+            }
+            if reader.bool()? {
+                return Ok(names);
+            }
+        }
+    }
+
+    /// Reads from the file `fh`, starting at `offset`.
+    ///
+    /// Fewer bytes than the length of `buffer` may be read;
+    /// zero bytes are read at the end of the file.
+    pub fn read(
+        &mut self,
+        fh: &[u8],
+        offset: u64,
+        buffer: &mut [u8],
+    ) -> Result<usize, &'static str> {
+        let count = buffer.len().min(MAX_TRANSFER_SIZE);
+        let args = XdrWriter::new().opaque(fh).u64(offset).u32(count as u32);
+        let reply = self.rpc.call(NFS_READ, args)?;
+        let mut reader = XdrReader::new(&reply);
+        status(&mut reader)?;
+        post_op_attr(&mut reader)?;
+        let _count = reader.u32()?;
+        let _eof = reader.bool()?;
+        let data = reader.opaque(count)?;
+        buffer[..data.len()].copy_from_slice(data);
+        Ok(data.len())
+    }
+
+    /// Writes to the file `fh`, starting at `offset`.
+    ///
+    /// Fewer bytes than the length of `data` may be written.
+    pub fn write(&mut self, fh: &[u8], offset: u64, data: &[u8]) -> Result<usize, &'static str> {
+        let count = data.len().min(MAX_TRANSFER_SIZE);
+        let args = XdrWriter::new()
+            .opaque(fh)
+            .u64(offset)
+            .u32(count as u32)
+            .u32(FILE_SYNC)
+            .opaque(&data[..count]);
+        let reply = self.rpc.call(NFS_WRITE, args)?;
+        let mut reader = XdrReader::new(&reply);
+        status(&mut reader)?;
+        wcc_data(&mut reader)?;
+        Ok((reader.u32()? as usize).min(count))
+    }
+
+    /// Creates a file named `name` in the directory `dir`, or truncates it if it already exists,
+    /// and returns its handle.
+    pub fn create(
+        &mut self,
+        dir: &[u8],
+        name: &str,
+        mode: u32,
+    ) -> Result<FileHandle, &'static str> {
+        let args = diropargs(dir, name)
+            .u32(UNCHECKED)
+            .append(sattr(Some(mode), Some(0)));
+        let reply = self.rpc.call(NFS_CREATE, args)?;
+        self.created_handle(&reply, dir, name)
+    }
+
+    /// Creates a directory named `name` in the directory `dir` and returns its handle.
+    pub fn mkdir(&mut self, dir: &[u8], name: &str, mode: u32) -> Result<FileHandle, &'static str> {
+        let args = diropargs(dir, name).append(sattr(Some(mode), None));
+        let reply = self.rpc.call(NFS_MKDIR, args)?;
+        self.created_handle(&reply, dir, name)
+    }
+
+    /// Returns the handle in the reply to a `CREATE` or `MKDIR` call,
+    /// looking it up if the server didn't include it.
+    fn created_handle(
+        &mut self,
+        reply: &[u8],
+        dir: &[u8],
+        name: &str,
+    ) -> Result<FileHandle, &'static str> {
+        let mut reader = XdrReader::new(reply);
+        status(&mut reader)?;
+        if reader.bool()? {
+            Ok(reader.opaque(MAX_HANDLE_LEN)?.to_vec())
+        } else {
+            self.lookup(dir, name).map(|(fh, _)| fh)
+        }
+    }
+
+    /// Removes the file or, if `is_dir`, the empty directory named `name` from the directory `dir`.
+    pub fn remove(&mut self, dir: &[u8], name: &str, is_dir: bool) -> Result<(), &'static str> {
+        let procedure = if is_dir { NFS_RMDIR } else { NFS_REMOVE };
+        let reply = self.rpc.call(procedure, diropargs(dir, name))?;
+        status(&mut XdrReader::new(&reply))
+    }
+
+    /// Atomically renames the file or directory named `old_name` in the directory `old_dir`
+    /// to `new_name` in the directory `new_dir`, replacing any existing file with that name.
+    pub fn rename(
+        &mut self,
+        old_dir: &[u8],
+        old_name: &str,
+        new_dir: &[u8],
+        new_name: &str,
+    ) -> Result<(), &'static str> {
+        let args = diropargs(old_dir, old_name).append(diropargs(new_dir, new_name));
+        let reply = self.rpc.call(NFS_RENAME, args)?;
+        status(&mut XdrReader::new(&reply))
+    }
+}
+
+/// Asks the portmapper which TCP port the given program is using.
+fn get_port(portmapper: &mut RpcClient, program: u32, version: u32) -> Result<u16, &'static str> {
+    let args = XdrWriter::new()
+        .u32(program)
+        .u32(version)
+        .u32(IPPROTO_TCP)
+        .u32(0);
+    let reply = portmapper.call(PORTMAPPER_GETPORT, args)?;
+    match XdrReader::new(&reply).u32()? {
+        0 => Err("nfs: the server doesn't provide NFSv3 over TCP"),
+        port => u16::try_from(port).map_err(|_| "nfs: portmapper returned an invalid port"),
+    }
+}
+
+fn diropargs(dir: &[u8], name: &str) -> XdrWriter {
+    XdrWriter::new().opaque(dir).string(name)
+}
+
+/// Encodes the attributes to set on a new file, leaving the owner and times unchanged.
+fn sattr(mode: Option<u32>, size: Option<u64>) -> XdrWriter {
+    let w = match mode {
+        Some(mode) => XdrWriter::new().bool(true).u32(mode),
+        None => XdrWriter::new().bool(false),
+    };
+    // The uid and gid are left to the server.
+    let w = w.bool(false).bool(false);
+    let w = match size {
+        Some(size) => w.bool(true).u64(size),
+        None => w.bool(false),
+    };
+    // DONT_CHANGE the access and modification times.
+    w.u32(0).u32(0)
+}
+
+/// Reads the status that starts every NFS and MOUNT result.
+fn status(reader: &mut XdrReader<'_>) -> Result<(), &'static str> {
+    match reader.u32()? {
+        0 => Ok(()),
+        status => Err(error_message(status)),
+    }
+}
+
+fn fattr(reader: &mut XdrReader<'_>) -> Result<Attr, &'static str> {
+    let ty = reader.u32()?;
+    let mode = reader.u32()?;
+    let _nlink = reader.u32()?;
+    let uid = reader.u32()?;
+    let gid = reader.u32()?;
+    let size = reader.u64()?;
+    // used, rdev, fsid, fileid, and atime
+    reader.skip(8 + 8 + 8 + 8 + 8)?;
+    let mtime_sec = reader.u32()?;
+    // mtime nanoseconds and ctime
+    reader.skip(4 + 8)?;
+    Ok(Attr {
+        is_dir: ty == NF3DIR,
+        mode,
+        uid,
+        gid,
+        size,
+        mtime_sec,
+    })
+}
+
+fn post_op_attr(reader: &mut XdrReader<'_>) -> Result<Option<Attr>, &'static str> {
+    if reader.bool()? {
+        fattr(reader).map(Some)
+    } else {
+        Ok(None)
+    }
+}
+
+/// Skips the attributes of a file from before and after it was modified.
+fn wcc_data(reader: &mut XdrReader<'_>) -> Result<(), &'static str> {
+    if reader.bool()? {
+        // size, mtime, and ctime
+        reader.skip(8 + 8 + 8)?;
+    }
+    post_op_attr(reader).map(|_| ())
+}
+
+/// Returns a description of the given NFS or MOUNT error status.
+pub fn error_message(status: u32) -> &'static str {
+    match status {
+        1 => "nfs: operation not permitted",
+        2 => "nfs: no such file or directory",
+        5 => "nfs: I/O error",
+        13 => "nfs: permission denied",
+        17 => "nfs: file exists",
+        20 => "nfs: not a directory",
+        21 => "nfs: is a directory",
+        22 => "nfs: invalid argument",
+        27 => "nfs: file too large",
+        28 => "nfs: no space left on device",
+        30 => "nfs: read-only file system",
+        63 => "nfs: file name too long",
+        66 => "nfs: directory not empty",
+        70 => "nfs: stale file handle",
+        10001 => "nfs: invalid file handle",
+        10004 => "nfs: operation not supported",
+        _ => "nfs: server returned an error",
+    }
+}
@@ -0,0 +1,328 @@
+//! A minimal NFS version 3 client, which makes a directory exported by a remote server
+//! visible within Theseus's VFS.
+//!
+//! This allows diskless Theseus machines to load applications from a server
+//! and store files such as logs on it.
+//! Only TCP is supported, and the server's portmapper is used to find its MOUNT and NFS services.
+//!
+//! Nodes are created lazily: looking up a name in an [`NfsDirectory`] looks it up on the server.
+//! Inserting a file into an [`NfsDirectory`] copies its contents to the server,
+//! and inserting a directory creates an empty directory of the same name.
+
+#![no_std]
+
+extern crate alloc;
+
+mod client;
+mod rpc;
+pub mod xdr;
+
+pub use client::{error_message, Attr, Client, FileHandle, MAX_TRANSFER_SIZE, PORTMAPPER_PORT};
+pub use rpc::Credentials;
+
+use alloc::{
+    string::{String, ToString},
+    sync::{Arc, Weak},
+    vec,
+    vec::Vec,
+};
+use fs_node::{DirRef, Directory, File, FileOrDir, FileRef, FsNode, WeakDirRef};
+use io::{ByteReader, ByteWriter, IoError, KnownLength};
+use log::warn;
+use memory::{allocate_pages_by_bytes, get_kernel_mmi_ref, MappedPages, PteFlags};
+use spin::{Mutex, Once};
+
+/// The permissions given to files created on the server.
+const FILE_MODE: u32 = 0o644;
+/// The permissions given to directories created on the server.
+const DIR_MODE: u32 = 0o755;
+
+/// A connection to an NFS server that's shared by every node of a mounted tree.
+pub type ClientRef = Arc<sync_block::Mutex<Client>>;
+
+/// Inserts the exported directory with the given handle `root` into `parent`
+/// as a directory named `name`.
+pub fn mount(
+    client: Client,
+    root: FileHandle,
+    parent: &DirRef,
+    name: String,
+) -> Result<DirRef, &'static str> {
+    if parent.lock().get(&name).is_some() {
+        return Err("nfs: a node with the mount point's name already exists");
+    }
+    let root = NfsDirectory::create(Node {
+        name,
+        parent: Arc::downgrade(parent),
+        parent_ref: None,
+        client: Arc::new(sync_block::Mutex::new(client)),
+        fh: root,
+    });
+    parent.lock().insert(FileOrDir::Dir(root.clone()))?;
+    Ok(root)
+}
+
+/// The state common to every node backed by a file on the server.
+struct Node {
+    name: String,
+    parent: WeakDirRef,
+    /// Lazily created nodes aren't held onto by their parent,
+    /// so they keep their parent alive instead.
+    parent_ref: Option<DirRef>,
+    client: ClientRef,
+    fh: FileHandle,
+}
+
+impl Node {
+    fn set_parent_dir(&mut self, new_parent: WeakDirRef) {
+        self.parent = new_parent;
+        self.parent_ref = None;
+    }
+}
+
+/// A directory on an NFS server.
+pub struct NfsDirectory {
+    node: Node,
+    self_ref: WeakDirRef,
+}
+
+impl NfsDirectory {
+    fn create(node: Node) -> DirRef {
+        Arc::new_cyclic(|self_ref: &Weak<Mutex<NfsDirectory>>| {
+            Mutex::new(NfsDirectory {
+                node,
+                self_ref: self_ref.clone() as WeakDirRef,
+            })
+        })
+    }
+
+    fn get_internal(&self, name: &str) -> Result<FileOrDir, &'static str> {
+        if name.is_empty() || name == "." || name == ".." || name.contains('/') {
+            return Err("nfs: invalid file name");
+        }
+        let (fh, attr) = self.node.client.lock().lookup(&self.node.fh, name)?;
+        let node = Node {
+            name: name.to_string(),
+            parent: self.self_ref.clone(),
+            parent_ref: self.self_ref.upgrade(),
+            client: self.node.client.clone(),
+            fh,
+        };
+        Ok(if attr.is_dir {
+            FileOrDir::Dir(NfsDirectory::create(node))
+        } else {
+            FileOrDir::File(Arc::new(Mutex::new(NfsFile::new(node))) as FileRef)
+        })
+    }
+}
+
+impl Directory for NfsDirectory {
+    fn get(&self, name: &str) -> Option<FileOrDir> {
+        self.get_internal(name).ok()
+    }
+
+    /// Creates a copy of the given node on the server.
+    ///
+    /// Files are copied along with their contents, whereas only an empty directory
+    /// is created for a directory.
+    fn insert(&mut self, node: FileOrDir) -> Result<Option<FileOrDir>, &'static str> {
+        let name = node.get_name();
+        match node {
+            FileOrDir::Dir(_) => {
+                self.node.client.lock().mkdir(&self.node.fh, &name, DIR_MODE)?;
+            }
+            FileOrDir::File(file) => {
+                // The file may be on the same server,
+                // so it must be read before the client is locked.
+                let contents = read_all(&file)?;
+                let mut client = self.node.client.lock();
+                let fh = client.create(&self.node.fh, &name, FILE_MODE)?;
+                write_all(&mut client, &fh, 0, &contents)?;
+            }
+        }
+        Ok(None)
+    }
+
+    fn remove(&mut self, node: &FileOrDir) -> Option<FileOrDir> {
+        let name = node.get_name();
+        match self.node.client.lock().remove(&self.node.fh, &name, node.is_dir()) {
+            Ok(()) => Some(node.clone()),
+            Err(e) => {
+                warn!("nfs: failed to remove {:?}: {}", name, e);
+                None
+            }
+        }
+    }
+
+    fn rename(&mut self, old_name: &str, new_name: &str) -> Result<Option<FileOrDir>, &'static str> {
+        if new_name.is_empty() || new_name == "." || new_name == ".." || new_name.contains('/') {
+            return Err("nfs: invalid file name");
+        }
+        self.node.client.lock().rename(&self.node.fh, old_name, &self.node.fh, new_name)?;
+        Ok(None)
+    }
+
+    fn list(&self) -> Vec<String> {
+        match self.node.client.lock().read_dir(&self.node.fh) {
+            Ok(names) => names,
+            Err(e) => {
+                warn!("nfs: failed to list {:?}: {}", self.node.name, e);
+                Vec::new()
+            }
+        }
+    }
+}
+
+impl FsNode for NfsDirectory {
+    fn get_name(&self) -> String {
+        self.node.name.clone()
+    }
+
+    fn get_parent_dir(&self) -> Option<DirRef> {
+        self.node.parent.upgrade()
+    }
+
+    fn set_parent_dir(&mut self, new_parent: WeakDirRef) {
+        self.node.set_parent_dir(new_parent);
+    }
+}
+
+/// A regular file on an NFS server.
+pub struct NfsFile {
+    node: Node,
+    /// The contents of the file, read in full when it's first mapped.
+    mapping: Once<MappedPages>,
+}
+
+impl NfsFile {
+    fn new(node: Node) -> Self {
+        Self {
+            node,
+            mapping: Once::new(),
+        }
+    }
+
+    /// Reads the entire file into newly allocated pages.
+    fn map_contents(&self) -> Result<MappedPages, &'static str> {
+        let len = self.len();
+        if len == 0 {
+            return Ok(MappedPages::empty());
+        }
+        let kernel_mmi_ref = get_kernel_mmi_ref().ok_or("KERNEL_MMI was not yet initialized!")?;
+        let pages = allocate_pages_by_bytes(len).ok_or("could not allocate pages")?;
+        let mut mapped_pages = kernel_mmi_ref
+            .lock()
+            .page_table
+            .map_allocated_pages(pages, PteFlags::new().valid(true).writable(true))?;
+        let buffer = mapped_pages.as_slice_mut(0, len)?;
+        if read_full(&mut self.node.client.lock(), &self.node.fh, 0, buffer)? != len {
+            return Err("nfs: file was truncated while it was being mapped");
+        }
+        Ok(mapped_pages)
+    }
+}
+
+impl ByteReader for NfsFile {
+    fn read_at(&mut self, buffer: &mut [u8], offset: usize) -> Result<usize, IoError> {
+        let mut client = self.node.client.lock();
+        Ok(read_full(&mut client, &self.node.fh, offset as u64, buffer)?)
+    }
+}
+
+impl ByteWriter for NfsFile {
+    fn write_at(&mut self, buffer: &[u8], offset: usize) -> Result<usize, IoError> {
+        // Any existing mapping no longer reflects the file's contents.
+        self.mapping = Once::new();
+        write_all(&mut self.node.client.lock(), &self.node.fh, offset as u64, buffer)?;
+        Ok(buffer.len())
+    }
+
+    fn flush(&mut self) -> Result<(), IoError> {
+        // Every write is already committed to stable storage on the server.
+        Ok(())
+    }
+}
+
+impl KnownLength for NfsFile {
+    fn len(&self) -> usize {
+        match self.node.client.lock().getattr(&self.node.fh) {
+            Ok(attr) => attr.size as usize,
+            Err(e) => {
+                warn!("nfs: failed to get the length of {:?}: {}", self.node.name, e);
+                0
+            }
+        }
+    }
+}
+
+impl File for NfsFile {
+    fn as_mapping(&self) -> Result<&MappedPages, &'static str> {
+        if let Some(mapped_pages) = self.mapping.get() {
+            return Ok(mapped_pages);
+        }
+        let mapped_pages = self.map_contents()?;
+        Ok(self.mapping.call_once(|| mapped_pages))
+    }
+}
+
+impl FsNode for NfsFile {
+    fn get_name(&self) -> String {
+        self.node.name.clone()
+    }
+
+    fn get_parent_dir(&self) -> Option<DirRef> {
+        self.node.parent.upgrade()
+    }
+
+    fn set_parent_dir(&mut self, new_parent: WeakDirRef) {
+        self.node.set_parent_dir(new_parent);
+    }
+}
+
+/// Reads from the file `fh`, starting at `offset`,
+/// until `buffer` is full or the end of the file is reached.
+fn read_full(
+    client: &mut Client,
+    fh: &[u8],
+    offset: u64,
+    buffer: &mut [u8],
+) -> Result<usize, &'static str> {
+    let mut read = 0;
+    while read < buffer.len() {
+        let n = client.read(fh, offset + read as u64, &mut buffer[read..])?;
+        if n == 0 {
+            break;
+        }
+        read += n;
+    }
+    Ok(read)
+}
+
+/// Writes all of `data` to the file `fh`, starting at `offset`.
+fn write_all(client: &mut Client, fh: &[u8], offset: u64, data: &[u8]) -> Result<(), &'static str> {
+    let mut written = 0;
+    while written < data.len() {
+        let n = client.write(fh, offset + written as u64, &data[written..])?;
+        if n == 0 {
+            return Err("nfs: server didn't accept any written bytes");
+        }
+        written += n;
+    }
+    Ok(())
+}
+
+/// Reads the entire contents of the given file.
+fn read_all(file: &FileRef) -> Result<Vec<u8>, &'static str> {
+    let mut file = file.lock();
+    let mut contents = vec![0; file.len()];
+    let mut read = 0;
+    while read < contents.len() {
+        let n = file.read_at(&mut contents[read..], read)?;
+        if n == 0 {
+            break;
+        }
+        read += n;
+    }
+    contents.truncate(read);
+    Ok(contents)
+}
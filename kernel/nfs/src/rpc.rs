@@ -0,0 +1,284 @@
+//! An ONC RPC (RFC 5531) client that sends calls over a TCP connection.
+//!
+//! Over TCP, each message is sent as a record made of fragments,
+//! each of which is preceded by a four-byte header containing its length
+//! and whether it's the last fragment of the record.
+
+use crate::xdr::{XdrReader, XdrWriter};
+use alloc::{sync::Arc, vec, vec::Vec};
+use core::time::Duration;
+use net::{tcp, IpEndpoint, NetworkInterface, Socket};
+use time::Instant;
+
+const RPC_VERSION: u32 = 2;
+const MSG_CALL: u32 = 0;
+const MSG_REPLY: u32 = 1;
+const MSG_ACCEPTED: u32 = 0;
+const ACCEPT_SUCCESS: u32 = 0;
+const AUTH_NONE: u32 = 0;
+const AUTH_UNIX: u32 = 1;
+
+/// The bit of a record fragment header that's set for the last fragment of a record.
+const LAST_FRAGMENT: u32 = 1 << 31;
+
+/// The maximum size of a reply that the client accepts.
+const MAX_RECORD_SIZE: usize = 1024 * 1024;
+
+/// The name that the client identifies itself with in its credentials.
+const MACHINE_NAME: &str = "theseus";
+
+/// How long the client sleeps between polls of the network interface while waiting.
+const POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+const SOCKET_BUFFER_SIZE: usize = 64 * 1024;
+
+/// The user and group that calls are made on behalf of.
+#[derive(Clone, Copy, Debug)]
+pub struct Credentials {
+    pub uid: u32,
+    pub gid: u32,
+}
+
+/// A connection to a single RPC program on a server.
+pub struct RpcClient {
+    interface: Arc<NetworkInterface>,
+    socket: Socket<tcp::Socket<'static>>,
+    program: u32,
+    version: u32,
+    credentials: Option<Credentials>,
+    next_xid: u32,
+    /// Bytes received from the server that aren't yet part of a complete record.
+    received: Vec<u8>,
+    timeout: Duration,
+}
+
+impl RpcClient {
+    /// Connects to the given `version` of the given `program` at `remote`.
+    ///
+    /// Calls use `AUTH_UNIX` with the given credentials, or `AUTH_NONE` if there are none.
+    pub fn connect(
+        interface: Arc<NetworkInterface>,
+        remote: IpEndpoint,
+        program: u32,
+        version: u32,
+        credentials: Option<Credentials>,
+        timeout: Duration,
+    ) -> Result<Self, &'static str> {
+        let rx_buffer = tcp::SocketBuffer::new(vec![0; SOCKET_BUFFER_SIZE]);
+        let tx_buffer = tcp::SocketBuffer::new(vec![0; SOCKET_BUFFER_SIZE]);
+        let socket = interface
+            .clone()
+            .add_socket(tcp::Socket::new(rx_buffer, tx_buffer));
+        socket
+            .lock()
+            .connect(remote, net::get_ephemeral_port())
+            .map_err(|_| "nfs: failed to connect socket")?;
+
+        let client = Self {
+            interface,
+            socket,
+            program,
+            version,
+            credentials,
+            // Start from the current time, such that replies to an earlier
+            // connection from the same port aren't mistaken for replies to this one.
+            next_xid: Instant::now().duration_since(Instant::ZERO).as_micros() as u32,
+            received: Vec::new(),
+            timeout,
+        };
+        client.wait_for(|socket| {
+            if socket.may_send() {
+                Ok(true)
+            } else if socket.is_open() {
+                Ok(false)
+            } else {
+                Err("nfs: the server refused the connection")
+            }
+        })?;
+        Ok(client)
+    }
+
+    /// Calls the given `procedure` with the given encoded `args`,
+    /// and returns the encoded results.
+    pub fn call(&mut self, procedure: u32, args: XdrWriter) -> Result<Vec<u8>, &'static str> {
+        let xid = self.next_xid;
+        self.next_xid = self.next_xid.wrapping_add(1);
+
+        let call = XdrWriter::new()
+            .u32(xid)
+            .u32(MSG_CALL)
+            .u32(RPC_VERSION)
+            .u32(self.program)
+            .u32(self.version)
+            .u32(procedure);
+        let call = match self.credentials {
+            Some(credentials) => {
+                let body = XdrWriter::new()
+                    .u32(0)
+                    .string(MACHINE_NAME)
+                    .u32(credentials.uid)
+                    .u32(credentials.gid)
+                    .u32(0)
+                    .finish();
+                call.u32(AUTH_UNIX).opaque(&body)
+            }
+            None => call.u32(AUTH_NONE).opaque(&[]),
+        };
+        let call = call.u32(AUTH_NONE).opaque(&[]).append(args);
+        let record = XdrWriter::new()
+            .u32(LAST_FRAGMENT | call.len() as u32)
+            .append(call)
+            .finish();
+        self.send(&record)?;
+
+        loop {
+            let reply = self.receive()?;
+            let mut reader = XdrReader::new(&reply);
+            if reader.u32()? != xid {
+                // A late reply to an earlier call that timed out.
+                continue;
+            }
+            if reader.u32()? != MSG_REPLY {
+                return Err("nfs: server sent a call instead of a reply");
+            }
+            if reader.u32()? != MSG_ACCEPTED {
+                return Err("nfs: server denied the call; check the export's permissions");
+            }
+            let _verifier_flavor = reader.u32()?;
+            reader.opaque(400)?;
+            return match reader.u32()? {
+                ACCEPT_SUCCESS => Ok(reader.remaining().to_vec()),
+                1 => Err("nfs: server doesn't provide the program"),
+                2 => Err("nfs: server doesn't support the program version"),
+                3 => Err("nfs: server doesn't support the procedure"),
+                4 => Err("nfs: server couldn't decode the arguments"),
+                _ => Err("nfs: server failed to execute the call"),
+            };
+        }
+    }
+
+    fn send(&mut self, mut bytes: &[u8]) -> Result<(), &'static str> {
+        self.wait_for(|socket| {
+            if !socket.may_send() {
+                return Err("nfs: connection to the server was closed");
+            }
+            let sent = socket
+                .send_slice(bytes)
+                .map_err(|_| "nfs: failed to send to the server")?;
+            bytes = &bytes[sent..];
+            Ok(bytes.is_empty())
+        })
+    }
+
+    /// Receives the next complete record, with its fragment headers removed.
+    fn receive(&mut self) -> Result<Vec<u8>, &'static str> {
+        let mut received = core::mem::take(&mut self.received);
+        let mut record_len = None;
+        let result = self.wait_for(|socket| {
+            if socket.can_recv() {
+                socket
+                    .recv(|data| {
+                        received.extend_from_slice(data);
+                        (data.len(), ())
+                    })
+                    .map_err(|_| "nfs: failed to receive from the server")?;
+            } else if !socket.may_recv() {
+                return Err("nfs: connection to the server was closed");
+            }
+            record_len = complete_record_len(&received)?;
+            Ok(record_len.is_some())
+        });
+        if let Err(e) = result {
+            self.received = received;
+            return Err(e);
+        }
+        let Some(record_len) = record_len else {
+            return Err("nfs: no record was received");
+        };
+        self.received = received.split_off(record_len);
+        Ok(strip_fragment_headers(&received))
+    }
+
+    /// Polls the interface until `done` returns true or an error, or the timeout elapses.
+    fn wait_for<F>(&self, mut done: F) -> Result<(), &'static str>
+    where
+        F: FnMut(&mut net::LockedSocket<'_, tcp::Socket<'static>>) -> Result<bool, &'static str>,
+    {
+        let start = Instant::now();
+        loop {
+            self.interface.poll();
+            if done(&mut self.socket.lock())? {
+                self.interface.poll();
+                return Ok(());
+            }
+            if start.elapsed() >= self.timeout {
+                return Err("nfs: timed out waiting for the server");
+            }
+            sleep::sleep(POLL_INTERVAL).map_err(|_| "nfs: failed to sleep")?;
+        }
+    }
+}
+
+impl Drop for RpcClient {
+    fn drop(&mut self) {
+        self.socket.lock().close();
+        self.interface.poll();
+    }
+}
+
+/// Returns the length of the complete record, including its fragment headers,
+/// at the start of `bytes`, or `None` if more bytes are needed.
+fn complete_record_len(bytes: &[u8]) -> Result<Option<usize>, &'static str> {
+    let mut offset = 0;
+    let mut total = 0;
+    while let Some(header) = bytes.get(offset..offset + 4) {
+        let header = u32::from_be_bytes([header[0], header[1], header[2], header[3]]);
+        let len = (header & !LAST_FRAGMENT) as usize;
+        total += len;
+        if total > MAX_RECORD_SIZE {
+            return Err("nfs: server sent a record that was too large");
+        }
+        offset += 4 + len;
+        if offset > bytes.len() {
+            return Ok(None);
+        }
+        if header & LAST_FRAGMENT != 0 {
+            return Ok(Some(offset));
+        }
+    }
+    Ok(None)
+}
+
+/// Returns the contents of the given complete record without its fragment headers.
+fn strip_fragment_headers(mut record: &[u8]) -> Vec<u8> {
+    let mut contents = Vec::with_capacity(record.len());
+    while record.len() >= 4 {
+        let header = u32::from_be_bytes([record[0], record[1], record[2], record[3]]);
+        let len = (header & !LAST_FRAGMENT) as usize;
+        contents.extend_from_slice(&record[4..4 + len]);
+        record = &record[4 + len..];
+    }
+    contents
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn fragmented_record() {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&3u32.to_be_bytes());
+        bytes.extend_from_slice(b"abc");
+        assert_eq!(complete_record_len(&bytes), Ok(None));
+        bytes.extend_from_slice(&(LAST_FRAGMENT | 2).to_be_bytes());
+        bytes.extend_from_slice(b"d");
+        assert_eq!(complete_record_len(&bytes), Ok(None));
+        bytes.extend_from_slice(b"e");
+        let len = bytes.len();
+        bytes.extend_from_slice(&LAST_FRAGMENT.to_be_bytes());
+
+        assert_eq!(complete_record_len(&bytes), Ok(Some(len)));
+        assert_eq!(strip_fragment_headers(&bytes[..len]), b"abcde");
+    }
+}
@@ -0,0 +1,173 @@
+//! Encoding and decoding of XDR data, as used by ONC RPC and NFS.
+//!
+//! Every item is a multiple of four bytes long and big endian;
+//! variable-length opaque data and strings are prefixed with their length
+//! and padded with zeros to a multiple of four bytes.
+
+use alloc::vec::Vec;
+use core::str;
+
+/// Builds a sequence of XDR items.
+#[derive(Default)]
+pub struct XdrWriter {
+    bytes: Vec<u8>,
+}
+
+impl XdrWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn u32(mut self, value: u32) -> Self {
+        self.bytes.extend_from_slice(&value.to_be_bytes());
+        self
+    }
+
+    pub fn u64(mut self, value: u64) -> Self {
+        self.bytes.extend_from_slice(&value.to_be_bytes());
+        self
+    }
+
+    pub fn bool(self, value: bool) -> Self {
+        self.u32(value as u32)
+    }
+
+    /// Appends fixed-length opaque data, whose length is known to the receiver.
+    pub fn fixed_opaque(mut self, data: &[u8]) -> Self {
+        self.bytes.extend_from_slice(data);
+        self.bytes.resize(self.bytes.len() + padding(data.len()), 0);
+        self
+    }
+
+    /// Appends variable-length opaque data, prefixed with its length.
+    pub fn opaque(self, data: &[u8]) -> Self {
+        self.u32(data.len() as u32).fixed_opaque(data)
+    }
+
+    pub fn string(self, s: &str) -> Self {
+        self.opaque(s.as_bytes())
+    }
+
+    /// Appends the bytes of an already encoded sequence of items.
+    pub fn append(mut self, other: XdrWriter) -> Self {
+        self.bytes.extend_from_slice(&other.bytes);
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    pub fn finish(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+/// Reads a sequence of XDR items in order.
+pub struct XdrReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> XdrReader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], &'static str> {
+        if len > self.bytes.len() {
+            return Err("XDR data was truncated");
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    pub fn u32(&mut self) -> Result<u32, &'static str> {
+        let b = self.take(4)?;
+        Ok(u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    pub fn u64(&mut self) -> Result<u64, &'static str> {
+        Ok(((self.u32()? as u64) << 32) | self.u32()? as u64)
+    }
+
+    pub fn bool(&mut self) -> Result<bool, &'static str> {
+        match self.u32()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err("XDR boolean was neither 0 nor 1"),
+        }
+    }
+
+    /// Reads fixed-length opaque data of the given length.
+    pub fn fixed_opaque(&mut self, len: usize) -> Result<&'a [u8], &'static str> {
+        let data = self.take(len)?;
+        self.take(padding(len))?;
+        Ok(data)
+    }
+
+    /// Reads variable-length opaque data, which must be at most `max_len` bytes long.
+    pub fn opaque(&mut self, max_len: usize) -> Result<&'a [u8], &'static str> {
+        let len = self.u32()? as usize;
+        if len > max_len {
+            return Err("XDR opaque data was longer than allowed");
+        }
+        self.fixed_opaque(len)
+    }
+
+    pub fn string(&mut self, max_len: usize) -> Result<&'a str, &'static str> {
+        str::from_utf8(self.opaque(max_len)?).map_err(|_| "XDR string was not valid UTF-8")
+    }
+
+    /// Skips the given number of bytes.
+    pub fn skip(&mut self, len: usize) -> Result<(), &'static str> {
+        self.take(len).map(|_| ())
+    }
+
+    /// Returns the bytes that haven't been read yet.
+    pub fn remaining(&self) -> &'a [u8] {
+        self.bytes
+    }
+}
+
+/// Returns the number of padding bytes that follow opaque data of the given length.
+fn padding(len: usize) -> usize {
+    (4 - len % 4) % 4
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let bytes = XdrWriter::new()
+            .u32(7)
+            .u64(0x1_0000_0002)
+            .bool(true)
+            .string("abcde")
+            .opaque(&[1, 2, 3, 4])
+            .finish();
+        assert_eq!(bytes.len(), 4 + 8 + 4 + (4 + 8) + (4 + 4));
+
+        let mut reader = XdrReader::new(&bytes);
+        assert_eq!(reader.u32(), Ok(7));
+        assert_eq!(reader.u64(), Ok(0x1_0000_0002));
+        assert_eq!(reader.bool(), Ok(true));
+        assert_eq!(reader.string(255), Ok("abcde"));
+        assert_eq!(reader.opaque(4), Ok(&[1, 2, 3, 4][..]));
+        assert!(reader.remaining().is_empty());
+    }
+
+    #[test]
+    fn truncated() {
+        let bytes = XdrWriter::new().string("abcde").finish();
+        assert!(XdrReader::new(&bytes[..bytes.len() - 1]).string(255).is_err());
+        assert!(XdrReader::new(&[0, 0, 0, 2]).bool().is_err());
+        assert!(XdrReader::new(&bytes).opaque(4).is_err());
+    }
+}
@@ -8,7 +8,7 @@ extern crate memory;
 extern crate mpmc;
 
 use core::ops::{Deref, DerefMut};
-use alloc::vec::Vec;
+use alloc::{sync::Arc, vec::Vec};
 use memory::{PhysicalAddress, MappedPages, PteFlags, create_contiguous_mapping};
 
 /// A buffer that stores a packet to be transmitted through the NIC
@@ -54,6 +54,14 @@ impl TransmitBuffer {
             Ok(())
         }
     }
+
+    /// Converts this buffer into a `ReceiveBuffer` with the same contents without copying it,
+    /// e.g., to loop a transmitted packet back as a received packet.
+    ///
+    /// When the returned buffer is dropped, it will be returned to the given `pool`.
+    pub fn into_receive_buffer(self, pool: &'static mpmc::Queue<ReceiveBuffer>) -> Result<ReceiveBuffer, &'static str> {
+        ReceiveBuffer::new(self.mp, self.phys_addr, self.length, pool)
+    }
 }
 
 impl Deref for TransmitBuffer {
@@ -115,11 +123,18 @@ impl ReceiveBuffer {
         self.length
     }
 
+    /// Returns the maximum length of this buffer, i.e., the size of its underlying memory.
+    pub fn capacity(&self) -> usize {
+        self.mp.size_in_bytes()
+    }
+
     /// Sets the buffers length.
     ///
-    /// Returns an error if the length is greater than the current length.
+    /// Returns an error if the length is greater than the buffer's capacity.
+    /// Note that buffers returned to their pool have a length of `0`,
+    /// so their length must be set before they can be reused.
     pub fn set_length(&mut self, length: u16) -> Result<(), &'static str> {
-        if length > self.length {
+        if usize::from(length) > self.capacity() {
             Err("ReceiveBuffer::set_length(): length too long")
         } else {
             self.length = length;
@@ -178,3 +193,77 @@ impl Drop for ReceiveBuffer {
 
 /// A network (e.g., Ethernet) frame that has been received by the NIC.
 pub struct ReceivedFrame(pub Vec<ReceiveBuffer>);
+
+
+/// A reference-counted window into a [`ReceiveBuffer`],
+/// which lets a received packet be handed up the network stack without copying it.
+///
+/// Each layer strips its header off the front of the segment, leaving the payload for the next layer;
+/// the stripped bytes remain in the buffer as headroom.
+/// Cloning a segment only increments the buffer's reference count,
+/// and the buffer is returned to its pool once every segment referring to it has been dropped.
+#[derive(Clone)]
+pub struct PacketSegment {
+    buffer: Arc<ReceiveBuffer>,
+    start: u16,
+    end: u16,
+}
+
+impl PacketSegment {
+    /// Returns the number of bytes before the start of this segment in its buffer,
+    /// e.g., the headers that have been stripped from it.
+    pub fn headroom(&self) -> u16 {
+        self.start
+    }
+
+    /// Returns the physical address of the start of this segment.
+    pub fn phys_addr(&self) -> PhysicalAddress {
+        self.buffer.phys_addr() + usize::from(self.start)
+    }
+
+    /// Removes the first `len` bytes from this segment, e.g., to strip a header.
+    pub fn strip_front(&mut self, len: u16) -> Result<(), &'static str> {
+        if len > self.end - self.start {
+            return Err("PacketSegment::strip_front(): length too long");
+        }
+        self.start += len;
+        Ok(())
+    }
+
+    /// Shortens this segment to the given `len`, e.g., to remove padding or a trailer.
+    pub fn truncate(&mut self, len: u16) -> Result<(), &'static str> {
+        if len > self.end - self.start {
+            return Err("PacketSegment::truncate(): length too long");
+        }
+        self.end = self.start + len;
+        Ok(())
+    }
+
+    /// Returns a new segment for the given range of this segment, sharing the same buffer.
+    pub fn slice(&self, start: u16, len: u16) -> Result<PacketSegment, &'static str> {
+        let mut segment = self.clone();
+        segment.strip_front(start)?;
+        segment.truncate(len)?;
+        Ok(segment)
+    }
+}
+
+impl From<ReceiveBuffer> for PacketSegment {
+    fn from(buffer: ReceiveBuffer) -> Self {
+        let end = buffer.length();
+        PacketSegment {
+            buffer: Arc::new(buffer),
+            start: 0,
+            end,
+        }
+    }
+}
+
+impl Deref for PacketSegment {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        // The buffer's length can't change while it's shared, and `end` never exceeds it.
+        &self.buffer[usize::from(self.start)..usize::from(self.end)]
+    }
+}
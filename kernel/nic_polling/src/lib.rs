@@ -0,0 +1,345 @@
+//! Adaptive interrupt moderation for NIC receive queues.
+//!
+//! At low packet rates, a receive queue raises an interrupt for every burst of frames,
+//! which keeps latency low.
+//! At high packet rates, the overhead of handling an interrupt for every burst dominates,
+//! so it's cheaper to mask the queue's interrupts and poll it instead.
+//!
+//! Each queue registered with [`register()`] starts in interrupt mode,
+//! and its driver reports every receive interrupt to [`AdaptiveQueue::on_interrupt()`].
+//! Once the queue receives frames faster than its [`ModerationConfig::polling_threshold`],
+//! it switches to polled mode: the driver masks the queue's interrupts,
+//! and a dedicated polling task on the queue's CPU polls it repeatedly.
+//! Each poll receives at most [`ModerationConfig::budget`] frames,
+//! such that a busy queue can't starve the other queues polled on the same CPU.
+//! Once the queue has been idle for [`ModerationConfig::idle_timeout`],
+//! its interrupts are re-enabled and it returns to interrupt mode.
+//!
+//! Statistics about each queue are exposed as metrics, e.g., `theseus_nic_queue_polls_total`.
+
+#![no_std]
+
+extern crate alloc;
+
+mod queue_metrics;
+
+use alloc::{collections::BTreeMap, format, string::String, sync::Arc, vec::Vec};
+use core::{
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+    time::Duration,
+};
+use cpu::CpuId;
+use log::error;
+use spin::{Mutex, Once};
+use sync_irq::IrqSafeMutex;
+use task::JoinableTaskRef;
+use time::Instant;
+
+/// How long frames are counted for before the receive rate of a queue in interrupt mode is checked.
+const RATE_WINDOW: Duration = Duration::from_millis(10);
+
+/// A NIC receive queue that can be polled instead of raising interrupts.
+///
+/// This is implemented by NIC drivers.
+pub trait PolledQueue: Send + Sync {
+    /// Receives at most `budget` frames from the queue and passes them to the network stack.
+    ///
+    /// Returns the number of frames received.
+    fn poll(&self, budget: usize) -> usize;
+
+    /// Enables or disables the queue's receive interrupts.
+    fn set_interrupts_enabled(&self, enabled: bool);
+}
+
+/// The parameters that determine when a queue switches between interrupt and polled modes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ModerationConfig {
+    /// The number of frames per second above which the queue switches to polled mode.
+    pub polling_threshold: u64,
+    /// The maximum number of frames received from the queue in a single poll.
+    pub budget: usize,
+    /// How long the queue must go without receiving a frame in polled mode
+    /// before it switches back to interrupt mode.
+    pub idle_timeout: Duration,
+}
+
+impl Default for ModerationConfig {
+    fn default() -> Self {
+        Self {
+            polling_threshold: 20_000,
+            budget: 64,
+            idle_timeout: Duration::from_millis(2),
+        }
+    }
+}
+
+/// Whether a queue is driven by interrupts or by its CPU's polling task.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    Interrupts,
+    Polling,
+}
+
+/// A snapshot of the statistics of a queue; see [`AdaptiveQueue::stats()`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct QueueStats {
+    /// The number of receive interrupts that were handled.
+    pub interrupts: u64,
+    /// The number of frames received while handling interrupts.
+    pub interrupt_frames: u64,
+    /// The number of times the queue was polled in polled mode.
+    pub polls: u64,
+    /// The number of frames received while polling.
+    pub polled_frames: u64,
+    /// The number of polls that received a full budget of frames,
+    /// meaning more frames were likely still waiting.
+    pub budget_exhausted: u64,
+    /// The number of switches from interrupt mode to polled mode.
+    pub switches_to_polling: u64,
+    /// The number of switches from polled mode back to interrupt mode.
+    pub switches_to_interrupts: u64,
+}
+
+#[derive(Default)]
+struct Counters {
+    interrupts: AtomicU64,
+    interrupt_frames: AtomicU64,
+    polls: AtomicU64,
+    polled_frames: AtomicU64,
+    budget_exhausted: AtomicU64,
+    switches_to_polling: AtomicU64,
+    switches_to_interrupts: AtomicU64,
+}
+
+impl Counters {
+    fn add(counter: &AtomicU64, value: u64) {
+        counter.fetch_add(value, Ordering::Relaxed);
+    }
+}
+
+/// The frames received by a queue in interrupt mode since `start`.
+struct RateWindow {
+    start: Instant,
+    frames: u64,
+}
+
+/// A receive queue whose interrupts are moderated adaptively.
+pub struct AdaptiveQueue {
+    name: String,
+    queue: Arc<dyn PolledQueue>,
+    config: ModerationConfig,
+    poller: Arc<Poller>,
+    polling: AtomicBool,
+    window: IrqSafeMutex<RateWindow>,
+    /// When the queue last stopped receiving frames in polled mode, if it has.
+    idle_since: IrqSafeMutex<Option<Instant>>,
+    counters: Counters,
+}
+
+impl AdaptiveQueue {
+    /// Returns the name of this queue, as used in its metrics.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the CPU whose polling task polls this queue.
+    pub fn cpu(&self) -> CpuId {
+        self.poller.cpu
+    }
+
+    /// Returns the current mode of this queue.
+    pub fn mode(&self) -> Mode {
+        if self.polling.load(Ordering::Acquire) {
+            Mode::Polling
+        } else {
+            Mode::Interrupts
+        }
+    }
+
+    /// Returns a snapshot of this queue's statistics.
+    pub fn stats(&self) -> QueueStats {
+        let c = &self.counters;
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        QueueStats {
+            interrupts: load(&c.interrupts),
+            interrupt_frames: load(&c.interrupt_frames),
+            polls: load(&c.polls),
+            polled_frames: load(&c.polled_frames),
+            budget_exhausted: load(&c.budget_exhausted),
+            switches_to_polling: load(&c.switches_to_polling),
+            switches_to_interrupts: load(&c.switches_to_interrupts),
+        }
+    }
+
+    /// Records a receive interrupt, in which the driver received the given number of frames.
+    ///
+    /// This should be invoked from the queue's interrupt handler.
+    /// If this returns [`Mode::Polling`], the driver must mask the queue's receive interrupts
+    /// until they're re-enabled via [`PolledQueue::set_interrupts_enabled()`].
+    pub fn on_interrupt(self: &Arc<Self>, frames: usize) -> Mode {
+        Counters::add(&self.counters.interrupts, 1);
+        Counters::add(&self.counters.interrupt_frames, frames as u64);
+        if self.polling.load(Ordering::Acquire) {
+            // The interrupt was raised before the driver masked it.
+            return Mode::Polling;
+        }
+
+        let now = Instant::now();
+        let mut window = self.window.lock();
+        window.frames += frames as u64;
+        let elapsed = now.duration_since(window.start);
+        if elapsed < RATE_WINDOW {
+            return Mode::Interrupts;
+        }
+        let rate = window.frames * 1_000_000 / (elapsed.as_micros() as u64).max(1);
+        *window = RateWindow { start: now, frames: 0 };
+        drop(window);
+        if rate < self.config.polling_threshold {
+            return Mode::Interrupts;
+        }
+
+        self.polling.store(true, Ordering::Release);
+        Counters::add(&self.counters.switches_to_polling, 1);
+        self.poller.add(self.clone());
+        Mode::Polling
+    }
+
+    /// Polls this queue once, returning whether it should remain in polled mode.
+    fn poll(&self) -> bool {
+        let received = self.queue.poll(self.config.budget);
+        Counters::add(&self.counters.polls, 1);
+        Counters::add(&self.counters.polled_frames, received as u64);
+        if received >= self.config.budget {
+            Counters::add(&self.counters.budget_exhausted, 1);
+        }
+
+        let mut idle_since = self.idle_since.lock();
+        if received > 0 {
+            *idle_since = None;
+            return true;
+        }
+        let now = Instant::now();
+        let since = *idle_since.get_or_insert(now);
+        now.duration_since(since) < self.config.idle_timeout
+    }
+
+    /// Switches this queue back to interrupt mode, once it's no longer polled.
+    fn return_to_interrupts(&self) {
+        *self.idle_since.lock() = None;
+        *self.window.lock() = RateWindow { start: Instant::now(), frames: 0 };
+        self.polling.store(false, Ordering::Release);
+        Counters::add(&self.counters.switches_to_interrupts, 1);
+        self.queue.set_interrupts_enabled(true);
+    }
+}
+
+/// The queues in polled mode on a single CPU, and the task that polls them.
+struct Poller {
+    cpu: CpuId,
+    queues: IrqSafeMutex<Vec<Arc<AdaptiveQueue>>>,
+    task: Once<JoinableTaskRef>,
+}
+
+impl Poller {
+    fn add(&self, queue: Arc<AdaptiveQueue>) {
+        self.queues.lock().push(queue);
+        if let Some(task) = self.task.get() {
+            if task.unblock().is_err() {
+                error!("nic_polling: couldn't unblock polling task on CPU {}", self.cpu);
+            }
+        }
+    }
+
+    fn remove(&self, queue: &Arc<AdaptiveQueue>) {
+        self.queues.lock().retain(|q| !Arc::ptr_eq(q, queue));
+    }
+}
+
+/// The entry point of a CPU's polling task, which polls the CPU's queues that are in polled mode.
+fn poll_loop(poller: Arc<Poller>) -> ! {
+    let current_task = task::get_my_current_task().expect("nic_polling: couldn't get current task");
+    loop {
+        let queues = {
+            let queues = poller.queues.lock();
+            if queues.is_empty() {
+                // Block while holding the lock, so a queue that's added concurrently unblocks us.
+                if current_task.block().is_err() {
+                    error!("nic_polling: couldn't block polling task on CPU {}", poller.cpu);
+                }
+                drop(queues);
+                task::schedule();
+                continue;
+            }
+            queues.clone()
+        };
+
+        for queue in queues {
+            if !queue.poll() {
+                poller.remove(&queue);
+                queue.return_to_interrupts();
+            }
+        }
+        task::schedule();
+    }
+}
+
+/// All registered queues, in the order they were registered.
+static QUEUES: Mutex<Vec<Arc<AdaptiveQueue>>> = Mutex::new(Vec::new());
+
+/// The polling task of each CPU that has any registered queues.
+static POLLERS: Mutex<BTreeMap<CpuId, Arc<Poller>>> = Mutex::new(BTreeMap::new());
+
+/// Registers a receive queue whose interrupts are moderated adaptively,
+/// which is polled on the given CPU while in polled mode.
+///
+/// The queue starts in interrupt mode.
+pub fn register(
+    name: String,
+    queue: Arc<dyn PolledQueue>,
+    cpu: CpuId,
+    config: ModerationConfig,
+) -> Result<Arc<AdaptiveQueue>, &'static str> {
+    if config.budget == 0 {
+        return Err("polling budget must be at least one frame");
+    }
+    let poller = poller_for(cpu)?;
+    let queue = Arc::new(AdaptiveQueue {
+        name,
+        queue,
+        config,
+        poller,
+        polling: AtomicBool::new(false),
+        window: IrqSafeMutex::new(RateWindow { start: Instant::now(), frames: 0 }),
+        idle_since: IrqSafeMutex::new(None),
+        counters: Counters::default(),
+    });
+    QUEUES.lock().push(queue.clone());
+    queue_metrics::register();
+    Ok(queue)
+}
+
+/// Returns all registered queues.
+pub fn queues() -> Vec<Arc<AdaptiveQueue>> {
+    QUEUES.lock().clone()
+}
+
+/// Returns the poller for the given CPU, spawning its polling task if it doesn't exist yet.
+fn poller_for(cpu: CpuId) -> Result<Arc<Poller>, &'static str> {
+    let mut pollers = POLLERS.lock();
+    if let Some(poller) = pollers.get(&cpu) {
+        return Ok(poller.clone());
+    }
+    let poller = Arc::new(Poller {
+        cpu,
+        queues: IrqSafeMutex::new(Vec::new()),
+        task: Once::new(),
+    });
+    let task = spawn::new_task_builder(poll_loop, poller.clone())
+        .name(format!("nic_poller_cpu_{cpu}"))
+        .pin_on_cpu(cpu)
+        .block()
+        .spawn()?;
+    poller.task.call_once(|| task);
+    pollers.insert(cpu, poller.clone());
+    Ok(poller)
+}
@@ -0,0 +1,97 @@
+//! Metrics about the interrupt moderation of each registered queue.
+
+use core::fmt::{self, Display};
+
+use metrics::{FnMetric, MetricKind, Samples};
+
+use crate::{Mode, QueueStats};
+
+static INTERRUPTS: FnMetric = FnMetric::new(
+    "theseus_nic_queue_interrupts_total",
+    "The number of receive interrupts handled for each adaptively moderated NIC queue.",
+    MetricKind::Counter,
+    |samples| collect(samples, |s| s.interrupts),
+);
+
+static POLLS: FnMetric = FnMetric::new(
+    "theseus_nic_queue_polls_total",
+    "The number of times each NIC queue was polled while in polled mode.",
+    MetricKind::Counter,
+    |samples| collect(samples, |s| s.polls),
+);
+
+static BUDGET_EXHAUSTED: FnMetric = FnMetric::new(
+    "theseus_nic_queue_budget_exhausted_total",
+    "The number of polls of each NIC queue that received a full budget of frames.",
+    MetricKind::Counter,
+    |samples| collect(samples, |s| s.budget_exhausted),
+);
+
+static FRAMES: FnMetric = FnMetric::new(
+    "theseus_nic_queue_frames_total",
+    "The number of frames received by each NIC queue in interrupt mode and in polled mode.",
+    MetricKind::Counter,
+    |samples| {
+        collect_pair(samples, "mode", ("interrupts", "polling"), |s| {
+            (s.interrupt_frames, s.polled_frames)
+        })
+    },
+);
+
+static MODE_SWITCHES: FnMetric = FnMetric::new(
+    "theseus_nic_queue_mode_switches_total",
+    "The number of times each NIC queue switched to polled mode or back to interrupt mode.",
+    MetricKind::Counter,
+    |samples| {
+        collect_pair(samples, "to", ("polling", "interrupts"), |s| {
+            (s.switches_to_polling, s.switches_to_interrupts)
+        })
+    },
+);
+
+static POLLING: FnMetric = FnMetric::new(
+    "theseus_nic_queue_polling",
+    "Whether each NIC queue is currently in polled mode (1) or interrupt mode (0).",
+    MetricKind::Gauge,
+    |samples| {
+        for queue in crate::queues() {
+            let polling = u8::from(queue.mode() == Mode::Polling);
+            samples.write("", &[("queue", &queue.name() as &dyn Display)], polling)?;
+        }
+        Ok(())
+    },
+);
+
+/// Registers the queue metrics, if they haven't been registered already.
+pub(crate) fn register() {
+    for metric in [&INTERRUPTS, &POLLS, &BUDGET_EXHAUSTED, &FRAMES, &MODE_SWITCHES, &POLLING] {
+        if let Err(e) = metrics::register(metric) {
+            log::warn!("nic_polling: failed to register metric {}: {}", metrics::Metric::name(metric), e);
+        }
+    }
+}
+
+/// Writes a sample for each queue, using `select` to pick the value from the queue's statistics.
+fn collect(samples: &mut Samples<'_>, select: fn(&QueueStats) -> u64) -> fmt::Result {
+    for queue in crate::queues() {
+        let name: &dyn Display = &queue.name();
+        samples.write("", &[("queue", name)], select(&queue.stats()))?;
+    }
+    Ok(())
+}
+
+/// Writes two samples for each queue, which differ in the value of the label `label`.
+fn collect_pair(
+    samples: &mut Samples<'_>,
+    label: &str,
+    (first, second): (&str, &str),
+    select: fn(&QueueStats) -> (u64, u64),
+) -> fmt::Result {
+    for queue in crate::queues() {
+        let name: &dyn Display = &queue.name();
+        let (first_value, second_value) = select(&queue.stats());
+        samples.write("", &[("queue", name), (label, &first as &dyn Display)], first_value)?;
+        samples.write("", &[("queue", name), (label, &second as &dyn Display)], second_value)?;
+    }
+    Ok(())
+}
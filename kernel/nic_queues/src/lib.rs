@@ -80,12 +80,25 @@ impl<S: RxQueueRegisters, T: RxDescriptor> RxQueue<S,T> {
     /// Polls the queue and removes all received packets from it.
     /// The received packets are stored in the receive queue's `received_frames` FIFO queue.
     pub fn poll_queue_and_store_received_packets(&mut self) -> Result<(), &'static str> {
+        self.poll_queue_with_budget(usize::MAX).map(|_| ())
+    }
+
+    /// Polls the queue and removes at most `budget` received frames from it,
+    /// leaving any further frames in the queue for the next poll.
+    /// The received frames are stored in the receive queue's `received_frames` FIFO queue.
+    ///
+    /// Returns the number of frames received.
+    pub fn poll_queue_with_budget(&mut self, budget: usize) -> Result<usize, &'static str> {
         let mut cur = self.rx_cur as usize;
        
         let mut receive_buffers_in_frame: Vec<ReceiveBuffer> = Vec::new();
         let mut _total_packet_length: u16 = 0;
+        let mut received_frames = 0;
 
-        while self.rx_descs[cur].descriptor_done() {
+        // A frame that spans multiple receive buffers is always received completely.
+        while self.rx_descs[cur].descriptor_done()
+            && (received_frames < budget || !receive_buffers_in_frame.is_empty())
+        {
             // get information about the current receive buffer
             let length = self.rx_descs[cur].length();
             _total_packet_length += length as u16;
@@ -122,6 +135,7 @@ impl<S: RxQueueRegisters, T: RxDescriptor> RxQueue<S,T> {
             if self.rx_descs[cur].end_of_packet() {
                 let buffers = core::mem::take(&mut receive_buffers_in_frame);
                 self.received_frames.push_back(ReceivedFrame(buffers));
+                received_frames += 1;
             } else {
                 warn!("NIC::poll_queue_and_store_received_packets(): Received multi-rxbuffer frame, this scenario not fully tested!");
             }
@@ -129,7 +143,7 @@ impl<S: RxQueueRegisters, T: RxDescriptor> RxQueue<S,T> {
             cur = self.rx_cur as usize;
         }
 
-        Ok(())
+        Ok(received_frames)
     }
 
     /// Returns the earliest received ethernet frame.
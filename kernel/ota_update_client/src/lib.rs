@@ -1,5 +1,7 @@
 //! Functions to communicate with a network server that provides over-the-air live update functionality.
 //! 
+//! The [`repository`] module implements a versioned protocol for querying the server
+//! for individual crate versions, rather than whole update builds.
 
 #![no_std]
 #![feature(slice_concat_ext)]
@@ -15,6 +17,8 @@ extern crate itertools;
 extern crate time;
 extern crate net;
 
+pub mod repository;
+
 use core::str;
 use alloc::{
     vec::Vec,
@@ -25,7 +29,7 @@ use alloc::{
 use itertools::Itertools;
 use sha3::{Digest, Sha3_512};
 use percent_encoding::{DEFAULT_ENCODE_SET, utf8_percent_encode};
-use http_client::{HttpResponse, HttpClient, check_http_request};
+use http_client::{HttpResponse, HttpClient, check_http_request, ACCEPT_ENCODING_HEADER};
 use time::{Duration, Instant};
 use net::{IpEndpoint, wire::Ipv4Address, NetworkInterface};
 
@@ -327,11 +331,12 @@ fn download_files<S: AsRef<str>>(
             let uri = utf8_percent_encode(path, DEFAULT_ENCODE_SET);
             let version = "HTTP/1.1";
             let connection = if is_last_request { "Connection: close" } else { "Connection: keep-alive" };
-            format!("{} {} {}\r\n{}\r\n{}\r\n\r\n",
+            format!("{} {} {}\r\n{}\r\n{}\r\n{}\r\n\r\n",
                 method,
                 uri,
                 version,
                 format_args!("Host: {}:{}", remote_endpoint.addr, remote_endpoint.port), // host
+                ACCEPT_ENCODING_HEADER,
                 connection
             )
         };
@@ -0,0 +1,347 @@
+//! A protocol for querying a crate repository for the available versions of crates,
+//! and downloading them such that they can be staged for a live update.
+//!
+//! The repository is served over HTTP by the update server, under `/repository`:
+//! * `/repository/versions`: the protocol versions that the server supports, one per line,
+//!   of which the client uses the highest version that it also supports.
+//! * `/repository/v<N>/index`: every available crate, one per line.
+//! * `/repository/v<N>/objects/<object file>`: the object file of a crate.
+//!
+//! In version 1 of the protocol, each line of the index contains these whitespace-separated fields:
+//! ```text
+//! <crate name> <version> <object file> <hash> <dependencies> <signature>
+//! ```
+//! * The crate name excludes the crate type prefix and the hash, e.g., `keyboard`.
+//! * The object file is the crate's module file name, e.g., `k#keyboard-36be916209949cef.o`.
+//! * The hash is the hex-encoded SHA3-512 hash of the object file.
+//! * The dependencies are a comma-separated list of the object files of the crates
+//!   that this crate was built against, or `-` if there are none.
+//!   Since object file names end with a hash, they identify the exact dependencies.
+//! * The signature is a hex-encoded signature of the hash, or `-` if the crate is unsigned.
+
+use alloc::{
+    borrow::ToOwned,
+    collections::BTreeSet,
+    string::{String, ToString},
+    sync::Arc,
+    vec::Vec,
+};
+use core::cmp::Ordering;
+use net::{IpEndpoint, NetworkInterface};
+
+/// The versions of the repository protocol that this client supports.
+pub const SUPPORTED_PROTOCOL_VERSIONS: &[u32] = &[1];
+
+/// The path of the list of protocol versions that the repository supports.
+const PROTOCOL_VERSIONS_PATH: &str = "/repository/versions";
+
+/// Checks the signatures of crates downloaded from a repository.
+pub trait SignatureVerifier {
+    /// Returns whether `signature` is a valid signature of the given `message`,
+    /// which is the hex-encoded hash of a crate object file.
+    fn verify(&self, message: &[u8], signature: &[u8]) -> bool;
+}
+
+/// A version of a crate that's available from a repository.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CrateEntry {
+    /// The name of the crate, without its crate type prefix or hash, e.g., `keyboard`.
+    pub name: String,
+    pub version: String,
+    /// The module file name of the crate's object file, e.g., `k#keyboard-36be916209949cef.o`.
+    pub object_file: String,
+    /// The hex-encoded SHA3-512 hash of the object file.
+    pub hash: String,
+    /// The object files of the crates that this crate depends on.
+    pub dependencies: Vec<String>,
+    pub signature: Option<Vec<u8>>,
+}
+
+impl CrateEntry {
+    /// Parses a single line of a version 1 index.
+    fn parse(line: &str) -> Result<CrateEntry, &'static str> {
+        let mut fields = line.split_whitespace();
+        let mut next = || fields.next().ok_or("repository index line had too few fields");
+        let name = next()?.to_string();
+        let version = next()?.to_string();
+        let object_file = next()?.to_string();
+        let hash = next()?.to_string();
+        let dependencies = match next()? {
+            "-" => Vec::new(),
+            deps => deps.split(',').map(String::from).collect(),
+        };
+        let signature = match next()? {
+            "-" => None,
+            sig => Some(decode_hex(sig).ok_or("repository index had an invalid signature")?),
+        };
+        if fields.next().is_some() {
+            return Err("repository index line had too many fields");
+        }
+        Ok(CrateEntry { name, version, object_file, hash, dependencies, signature })
+    }
+}
+
+/// The list of crates available from a repository.
+#[derive(Debug, Default)]
+pub struct Index {
+    entries: Vec<CrateEntry>,
+}
+
+impl Index {
+    /// Parses the lines of a version 1 index, ignoring empty lines and comments starting with `#`.
+    pub fn parse<S: AsRef<str>>(lines: &[S]) -> Result<Index, &'static str> {
+        let entries = lines.iter()
+            .map(|line| line.as_ref().trim())
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(CrateEntry::parse)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Index { entries })
+    }
+
+    /// Returns every crate version in the index.
+    pub fn entries(&self) -> &[CrateEntry] {
+        &self.entries
+    }
+
+    /// Returns the available versions of the crate with the given `name`, from newest to oldest.
+    pub fn versions_of(&self, name: &str) -> Vec<&CrateEntry> {
+        let mut versions: Vec<&CrateEntry> = self.entries.iter().filter(|e| e.name == name).collect();
+        versions.sort_by(|a, b| compare_versions(&b.version, &a.version));
+        versions
+    }
+
+    /// Returns the given `version` of the crate with the given `name`,
+    /// or its newest version if no `version` is given.
+    pub fn find(&self, name: &str, version: Option<&str>) -> Option<&CrateEntry> {
+        let versions = self.versions_of(name);
+        match version {
+            Some(v) => versions.into_iter().find(|e| e.version == v),
+            None => versions.into_iter().next(),
+        }
+    }
+
+    /// Returns the crate whose object file has the given name.
+    pub fn by_object_file(&self, object_file: &str) -> Option<&CrateEntry> {
+        self.entries.iter().find(|e| e.object_file == object_file)
+    }
+
+    /// Returns the given `requested` crates along with every dependency they need,
+    /// transitively, that `is_present` says isn't already available.
+    ///
+    /// Dependencies are listed before the crates that depend on them.
+    /// Returns an error if a missing dependency isn't in this index.
+    pub fn resolve<'i, F>(
+        &'i self,
+        requested: &[&'i CrateEntry],
+        is_present: F,
+    ) -> Result<Vec<&'i CrateEntry>, String>
+    where
+        F: Fn(&str) -> bool,
+    {
+        fn visit<'i, F: Fn(&str) -> bool>(
+            index: &'i Index,
+            entry: &'i CrateEntry,
+            is_present: &F,
+            visited: &mut BTreeSet<&'i str>,
+            resolved: &mut Vec<&'i CrateEntry>,
+        ) -> Result<(), String> {
+            if !visited.insert(&entry.object_file) {
+                return Ok(());
+            }
+            for dependency in &entry.dependencies {
+                if is_present(dependency) {
+                    continue;
+                }
+                let dependency = index.by_object_file(dependency).ok_or_else(|| format!(
+                    "{} {} depends on {}, which is neither loaded nor in the repository",
+                    entry.name, entry.version, dependency,
+                ))?;
+                visit(index, dependency, is_present, visited, resolved)?;
+            }
+            resolved.push(entry);
+            Ok(())
+        }
+
+        let mut visited = BTreeSet::new();
+        let mut resolved = Vec::new();
+        for &entry in requested {
+            visit(self, entry, &is_present, &mut visited, &mut resolved)?;
+        }
+        Ok(resolved)
+    }
+}
+
+/// A connection to a crate repository, using a protocol version that both sides support.
+pub struct RepositoryClient {
+    iface: Arc<NetworkInterface>,
+    remote_endpoint: IpEndpoint,
+    protocol_version: u32,
+}
+
+impl RepositoryClient {
+    /// Asks the repository at `remote_endpoint` which protocol versions it supports,
+    /// and picks the highest one that this client also supports.
+    pub fn connect(
+        iface: &Arc<NetworkInterface>,
+        remote_endpoint: IpEndpoint,
+    ) -> Result<RepositoryClient, &'static str> {
+        let server_versions = super::download_string_file(iface, remote_endpoint, PROTOCOL_VERSIONS_PATH)?;
+        let protocol_version = negotiate_version(&server_versions)
+            .ok_or("the repository doesn't support any protocol version known to this client")?;
+        debug!("ota_update_client: using repository protocol version {}", protocol_version);
+        Ok(RepositoryClient {
+            iface: iface.clone(),
+            remote_endpoint,
+            protocol_version,
+        })
+    }
+
+    /// Returns the protocol version used with the repository.
+    pub fn protocol_version(&self) -> u32 {
+        self.protocol_version
+    }
+
+    /// Downloads the list of crates available from the repository.
+    pub fn index(&self) -> Result<Index, &'static str> {
+        let path = format!("/repository/v{}/index", self.protocol_version);
+        let lines = super::download_string_file(&self.iface, self.remote_endpoint, &path)?;
+        Index::parse(&lines)
+    }
+
+    /// Downloads the object files of the given crates, returning their contents in the same order.
+    ///
+    /// Each object file must match its hash. If a `verifier` is given,
+    /// each crate must also be signed, and its signature must be valid.
+    pub fn download(
+        &self,
+        crates: &[&CrateEntry],
+        verifier: Option<&dyn SignatureVerifier>,
+    ) -> Result<Vec<Vec<u8>>, String> {
+        if crates.is_empty() {
+            return Ok(Vec::new());
+        }
+        if let Some(verifier) = verifier {
+            for entry in crates {
+                let signature = entry.signature.as_ref()
+                    .ok_or_else(|| format!("{} {} is not signed", entry.name, entry.version))?;
+                if !verifier.verify(entry.hash.as_bytes(), signature) {
+                    return Err(format!("{} {} has an invalid signature", entry.name, entry.version));
+                }
+            }
+        }
+
+        let paths: Vec<String> = crates.iter()
+            .map(|entry| format!("/repository/v{}/objects/{}", self.protocol_version, entry.object_file))
+            .collect();
+        let files = super::download_files(&self.iface, self.remote_endpoint, paths)?;
+        crates.iter().zip(files)
+            .map(|(entry, file)| {
+                let content = file.content.as_result_err_str()?;
+                if super::verify_hash(content, &entry.hash) {
+                    Ok(content.to_owned())
+                } else {
+                    Err(format!("downloaded object file {} did not match its hash", entry.object_file))
+                }
+            })
+            .collect()
+    }
+}
+
+/// Returns the highest protocol version in `server_versions` that this client supports.
+fn negotiate_version<S: AsRef<str>>(server_versions: &[S]) -> Option<u32> {
+    server_versions.iter()
+        .filter_map(|v| v.as_ref().trim().parse::<u32>().ok())
+        .filter(|v| SUPPORTED_PROTOCOL_VERSIONS.contains(v))
+        .max()
+}
+
+/// Compares two version strings, such as `1.10.0` and `1.9.2`,
+/// by comparing each of their dot-separated components numerically if possible.
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    let mut a_parts = a.split('.');
+    let mut b_parts = b.split('.');
+    loop {
+        let ordering = match (a_parts.next(), b_parts.next()) {
+            (None, None) => return Ordering::Equal,
+            (Some(_), None) => return Ordering::Greater,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(x), Some(y)) => match (x.parse::<u64>(), y.parse::<u64>()) {
+                (Ok(x), Ok(y)) => x.cmp(&y),
+                _ => x.cmp(y),
+            },
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+}
+
+fn decode_hex(s: &str) -> Option<Vec<u8>> {
+    if s.len() % 2 != 0 {
+        return None;
+    }
+    (0..s.len()).step_by(2)
+        .map(|i| s.get(i..i + 2).and_then(|byte| u8::from_str_radix(byte, 16).ok()))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const INDEX: &[&str] = &[
+        "# name version object_file hash dependencies signature",
+        "keyboard 0.1.0 k#keyboard-aaaa.o 01ab k#event_types-cccc.o,k#spin-dddd.o -",
+        "keyboard 0.10.0 k#keyboard-bbbb.o 02cd k#event_types-eeee.o 0aff",
+        "event_types 0.1.0 k#event_types-cccc.o 03ef - -",
+        "",
+        "event_types 0.2.0 k#event_types-eeee.o 04ab - -",
+    ];
+
+    #[test]
+    fn parse_index() {
+        let index = Index::parse(INDEX).unwrap();
+        assert_eq!(index.entries().len(), 4);
+        let keyboard = index.find("keyboard", None).unwrap();
+        assert_eq!(keyboard.version, "0.10.0");
+        assert_eq!(keyboard.signature, Some(vec![0x0a, 0xff]));
+        let old = index.find("keyboard", Some("0.1.0")).unwrap();
+        assert_eq!(old.dependencies, ["k#event_types-cccc.o", "k#spin-dddd.o"]);
+        assert_eq!(old.signature, None);
+
+        assert!(Index::parse(&["keyboard 0.1.0 k#keyboard-aaaa.o 01ab -"]).is_err());
+        assert!(Index::parse(&["keyboard 0.1.0 k#keyboard-aaaa.o 01ab - - extra"]).is_err());
+        assert!(Index::parse(&["keyboard 0.1.0 k#keyboard-aaaa.o 01ab - 0g"]).is_err());
+    }
+
+    #[test]
+    fn resolve_dependencies() {
+        let index = Index::parse(INDEX).unwrap();
+        let new = index.find("keyboard", None).unwrap();
+        let resolved = index.resolve(&[new], |_| false).unwrap();
+        let names: Vec<&str> = resolved.iter().map(|e| e.object_file.as_str()).collect();
+        assert_eq!(names, ["k#event_types-eeee.o", "k#keyboard-bbbb.o"]);
+
+        let resolved = index.resolve(&[new], |dep| dep == "k#event_types-eeee.o").unwrap();
+        assert_eq!(resolved, [new]);
+
+        // `spin` isn't in the index, so it must already be loaded.
+        let old = index.find("keyboard", Some("0.1.0")).unwrap();
+        assert!(index.resolve(&[old], |_| false).is_err());
+        assert_eq!(index.resolve(&[old], |dep| dep == "k#spin-dddd.o").unwrap().len(), 2);
+    }
+
+    #[test]
+    fn version_negotiation() {
+        assert_eq!(negotiate_version(&["1", "2"]), Some(1));
+        assert_eq!(negotiate_version(&[" 1 "]), Some(1));
+        assert_eq!(negotiate_version(&["2", "three"]), None);
+    }
+
+    #[test]
+    fn version_ordering() {
+        assert_eq!(compare_versions("0.10.0", "0.9.1"), Ordering::Greater);
+        assert_eq!(compare_versions("1.0", "1.0.0"), Ordering::Less);
+        assert_eq!(compare_versions("1.0.0", "1.0.0"), Ordering::Equal);
+    }
+}
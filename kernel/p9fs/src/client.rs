@@ -0,0 +1,428 @@
+//! A 9P2000.L client that sends requests to a server over a TCP connection.
+
+use crate::protocol::{self, Attr, MessageReader, MessageWriter, Qid};
+use alloc::{string::String, sync::Arc, vec, vec::Vec};
+use core::time::Duration;
+use log::{debug, warn};
+use net::{tcp, IpEndpoint, NetworkInterface, Socket};
+use time::Instant;
+
+/// The largest message size that the client asks the server to use.
+pub const DEFAULT_MSIZE: u32 = 64 * 1024;
+
+/// The smallest message size that the client accepts from the server.
+const MIN_MSIZE: u32 = 4096;
+
+/// How long the client waits for the server to respond before giving up.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long the client sleeps between polls of the network interface while waiting.
+const POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// Requests are sent one at a time, so they can all share the same tag.
+const TAG: u16 = 0;
+
+/// A connection to a 9P server.
+///
+/// Files on the server are referred to by fids, which are chosen by the client.
+/// A fid must be released with [`Client::clunk()`] once it's no longer needed.
+pub struct Client {
+    interface: Arc<NetworkInterface>,
+    socket: Socket<tcp::Socket<'static>>,
+    msize: u32,
+    next_fid: u32,
+    free_fids: Vec<u32>,
+    /// Bytes received from the server that aren't yet part of a complete message.
+    received: Vec<u8>,
+    timeout: Duration,
+}
+
+impl Client {
+    /// Connects to the 9P server at `remote` using the given `interface`,
+    /// and negotiates the protocol version and message size.
+    pub fn connect(
+        interface: Arc<NetworkInterface>,
+        remote: IpEndpoint,
+    ) -> Result<Self, &'static str> {
+        let buffer_size = DEFAULT_MSIZE as usize + protocol::HEADER_SIZE;
+        let rx_buffer = tcp::SocketBuffer::new(vec![0; buffer_size]);
+        let tx_buffer = tcp::SocketBuffer::new(vec![0; buffer_size]);
+        let socket = interface
+            .clone()
+            .add_socket(tcp::Socket::new(rx_buffer, tx_buffer));
+        socket
+            .lock()
+            .connect(remote, net::get_ephemeral_port())
+            .map_err(|_| "p9fs: failed to connect socket")?;
+
+        let mut client = Self {
+            interface,
+            socket,
+            msize: DEFAULT_MSIZE,
+            next_fid: 0,
+            free_fids: Vec::new(),
+            received: Vec::new(),
+            timeout: DEFAULT_TIMEOUT,
+        };
+        client.wait_for(|socket| {
+            if socket.may_send() {
+                Ok(true)
+            } else if socket.is_open() {
+                Ok(false)
+            } else {
+                Err("p9fs: the server refused the connection")
+            }
+        })?;
+        client.negotiate_version()?;
+        Ok(client)
+    }
+
+    /// Returns the maximum message size agreed upon with the server.
+    pub fn msize(&self) -> u32 {
+        self.msize
+    }
+
+    /// Sets how long the client waits for each response from the server.
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    fn negotiate_version(&mut self) -> Result<(), &'static str> {
+        let request = MessageWriter::new(protocol::TVERSION, protocol::NOTAG)
+            .u32(DEFAULT_MSIZE)
+            .string(protocol::VERSION);
+        let reply = self.rpc(request, protocol::RVERSION)?;
+        let mut reader = reader(&reply)?;
+        let msize = reader.u32()?;
+        let version = reader.string()?;
+        if version != protocol::VERSION {
+            warn!("p9fs: server offered unsupported version {:?}", version);
+            return Err("p9fs: the server doesn't support 9P2000.L");
+        }
+        if !(MIN_MSIZE..=DEFAULT_MSIZE).contains(&msize) {
+            return Err("p9fs: the server chose an unsupported message size");
+        }
+        debug!("p9fs: negotiated {} with msize {}", version, msize);
+        self.msize = msize;
+        Ok(())
+    }
+
+    /// Returns an unused fid.
+    fn alloc_fid(&mut self) -> u32 {
+        self.free_fids.pop().unwrap_or_else(|| {
+            let fid = self.next_fid;
+            self.next_fid += 1;
+            fid
+        })
+    }
+
+    /// Attaches to the file tree named `aname` on the server as the user `uname`,
+    /// returning a fid for its root.
+    pub fn attach(&mut self, uname: &str, aname: &str) -> Result<(u32, Qid), &'static str> {
+        let fid = self.alloc_fid();
+        let request = MessageWriter::new(protocol::TATTACH, TAG)
+            .u32(fid)
+            .u32(protocol::NOFID)
+            .string(uname)
+            .string(aname)
+            .u32(!0);
+        match self.rpc(request, protocol::RATTACH) {
+            Ok(reply) => Ok((fid, reader(&reply)?.qid()?)),
+            Err(e) => {
+                self.free_fids.push(fid);
+                Err(e)
+            }
+        }
+    }
+
+    /// Returns a new fid for the file reached by walking the given `names` from `fid`,
+    /// or a copy of `fid` if `names` is empty.
+    ///
+    /// Returns the qid of the file, or `None` if it's a copy of `fid`.
+    pub fn walk(&mut self, fid: u32, names: &[&str]) -> Result<(u32, Option<Qid>), &'static str> {
+        if names.len() > protocol::MAX_WALK_NAMES {
+            return Err("p9fs: too many path components to walk at once");
+        }
+        let new_fid = self.alloc_fid();
+        let mut request = MessageWriter::new(protocol::TWALK, TAG)
+            .u32(fid)
+            .u32(new_fid)
+            .u16(names.len() as u16);
+        for name in names {
+            request = request.string(name);
+        }
+        let result = self.rpc(request, protocol::RWALK).and_then(|reply| {
+            let mut reader = reader(&reply)?;
+            let count = reader.u16()? as usize;
+            // The server returns fewer qids than names if only part of the walk succeeded,
+            // in which case the new fid isn't used.
+            if count != names.len() {
+                return Err(protocol::error_message(2));
+            }
+            let mut qid = None;
+            for _ in 0..count {
+                qid = Some(reader.qid()?);
+            }
+            Ok(qid)
+        });
+        match result {
+            Ok(qid) => Ok((new_fid, qid)),
+            Err(e) => {
+                self.free_fids.push(new_fid);
+                Err(e)
+            }
+        }
+    }
+
+    /// Opens the file that `fid` refers to with the given Linux open `flags`.
+    ///
+    /// Returns the maximum number of bytes that can be read or written at once,
+    /// or zero if that's only limited by the message size.
+    pub fn lopen(&mut self, fid: u32, flags: u32) -> Result<(Qid, u32), &'static str> {
+        let request = MessageWriter::new(protocol::TLOPEN, TAG).u32(fid).u32(flags);
+        let reply = self.rpc(request, protocol::RLOPEN)?;
+        let mut reader = reader(&reply)?;
+        Ok((reader.qid()?, reader.u32()?))
+    }
+
+    /// Creates and opens a file named `name` in the directory that `fid` refers to,
+    /// after which `fid` refers to the new file.
+    pub fn lcreate(
+        &mut self,
+        fid: u32,
+        name: &str,
+        flags: u32,
+        mode: u32,
+    ) -> Result<Qid, &'static str> {
+        let request = MessageWriter::new(protocol::TLCREATE, TAG)
+            .u32(fid)
+            .string(name)
+            .u32(flags)
+            .u32(mode)
+            .u32(0);
+        let reply = self.rpc(request, protocol::RLCREATE)?;
+        reader(&reply)?.qid()
+    }
+
+    /// Creates a directory named `name` in the directory that `fid` refers to.
+    pub fn mkdir(&mut self, fid: u32, name: &str, mode: u32) -> Result<Qid, &'static str> {
+        let request = MessageWriter::new(protocol::TMKDIR, TAG)
+            .u32(fid)
+            .string(name)
+            .u32(mode)
+            .u32(0);
+        let reply = self.rpc(request, protocol::RMKDIR)?;
+        reader(&reply)?.qid()
+    }
+
+    /// Atomically renames the file or directory named `old_name` in the directory that `old_dir_fid` refers to
+    /// to `new_name` in the directory that `new_dir_fid` refers to, replacing any existing file with that name.
+    pub fn renameat(
+        &mut self,
+        old_dir_fid: u32,
+        old_name: &str,
+        new_dir_fid: u32,
+        new_name: &str,
+    ) -> Result<(), &'static str> {
+        let request = MessageWriter::new(protocol::TRENAMEAT, TAG)
+            .u32(old_dir_fid)
+            .string(old_name)
+            .u32(new_dir_fid)
+            .string(new_name);
+        self.rpc(request, protocol::RRENAMEAT).map(|_| ())
+    }
+
+    /// Removes the file or directory named `name` from the directory that `fid` refers to.
+    pub fn unlinkat(&mut self, fid: u32, name: &str, flags: u32) -> Result<(), &'static str> {
+        let request = MessageWriter::new(protocol::TUNLINKAT, TAG)
+            .u32(fid)
+            .string(name)
+            .u32(flags);
+        self.rpc(request, protocol::RUNLINKAT).map(|_| ())
+    }
+
+    /// Returns the attributes of the file that `fid` refers to.
+    pub fn getattr(&mut self, fid: u32) -> Result<Attr, &'static str> {
+        let request = MessageWriter::new(protocol::TGETATTR, TAG)
+            .u32(fid)
+            .u64(protocol::GETATTR_BASIC);
+        let reply = self.rpc(request, protocol::RGETATTR)?;
+        reader(&reply)?.attr()
+    }
+
+    /// Returns the names and qids of the entries in the opened directory that `fid` refers to,
+    /// excluding `.` and `..`.
+    pub fn read_dir(&mut self, fid: u32) -> Result<Vec<(String, Qid)>, &'static str> {
+        let mut entries = Vec::new();
+        let mut offset = 0;
+        loop {
+            let request = MessageWriter::new(protocol::TREADDIR, TAG)
+                .u32(fid)
+                .u64(offset)
+                .u32(self.msize - protocol::READ_OVERHEAD);
+            let reply = self.rpc(request, protocol::RREADDIR)?;
+            let batch = protocol::dir_entries(reader(&reply)?.data()?)?;
+            let Some(last) = batch.last() else {
+                return Ok(entries);
+            };
+            offset = last.offset;
+            entries.extend(
+                batch
+                    .iter()
+                    .filter(|e| e.name != "." && e.name != "..")
+                    .map(|e| (String::from(e.name), e.qid)),
+            );
+        }
+    }
+
+    /// Reads from the opened file that `fid` refers to, starting at `offset`.
+    ///
+    /// Fewer bytes than the length of `buffer` may be read;
+    /// zero bytes are read at the end of the file.
+    pub fn read(
+        &mut self,
+        fid: u32,
+        offset: u64,
+        buffer: &mut [u8],
+    ) -> Result<usize, &'static str> {
+        let count = buffer.len().min((self.msize - protocol::READ_OVERHEAD) as usize);
+        let request = MessageWriter::new(protocol::TREAD, TAG)
+            .u32(fid)
+            .u64(offset)
+            .u32(count as u32);
+        let reply = self.rpc(request, protocol::RREAD)?;
+        let data = reader(&reply)?.data()?;
+        if data.len() > count {
+            return Err("p9fs: server returned more data than requested");
+        }
+        buffer[..data.len()].copy_from_slice(data);
+        Ok(data.len())
+    }
+
+    /// Writes to the opened file that `fid` refers to, starting at `offset`.
+    ///
+    /// Fewer bytes than the length of `data` may be written.
+    pub fn write(&mut self, fid: u32, offset: u64, data: &[u8]) -> Result<usize, &'static str> {
+        let count = data.len().min((self.msize - protocol::WRITE_OVERHEAD) as usize);
+        let request = MessageWriter::new(protocol::TWRITE, TAG)
+            .u32(fid)
+            .u64(offset)
+            .data(&data[..count]);
+        let reply = self.rpc(request, protocol::RWRITE)?;
+        Ok((reader(&reply)?.u32()? as usize).min(count))
+    }
+
+    /// Blocks until all data written to the opened file that `fid` refers to is durable on the server.
+    ///
+    /// If `data_only` is `true`, metadata that isn't needed to read back the data may not be durable yet.
+    pub fn fsync(&mut self, fid: u32, data_only: bool) -> Result<(), &'static str> {
+        let request = MessageWriter::new(protocol::TFSYNC, TAG)
+            .u32(fid)
+            .u32(u32::from(data_only));
+        self.rpc(request, protocol::RFSYNC).map(|_| ())
+    }
+
+    /// Releases the given `fid`, which may then be reused.
+    pub fn clunk(&mut self, fid: u32) -> Result<(), &'static str> {
+        let request = MessageWriter::new(protocol::TCLUNK, TAG).u32(fid);
+        let result = self.rpc(request, protocol::RCLUNK).map(|_| ());
+        // The fid is released even if the server returned an error.
+        self.free_fids.push(fid);
+        result
+    }
+
+    /// Sends the given request and returns the reply, which must be of the given type.
+    fn rpc(&mut self, request: MessageWriter, reply_ty: u8) -> Result<Vec<u8>, &'static str> {
+        let request = request.finish();
+        if request.len() > self.msize as usize {
+            return Err("p9fs: request is larger than the message size");
+        }
+        self.send(&request)?;
+        let reply = self.receive()?;
+
+        let (ty, tag, mut reader) = MessageReader::new(&reply)?;
+        if tag != TAG && tag != protocol::NOTAG {
+            return Err("p9fs: server replied with an unexpected tag");
+        }
+        if ty == protocol::RLERROR {
+            return Err(protocol::error_message(reader.u32()?));
+        }
+        if ty != reply_ty {
+            return Err("p9fs: server replied with an unexpected message type");
+        }
+        Ok(reply)
+    }
+
+    fn send(&mut self, mut bytes: &[u8]) -> Result<(), &'static str> {
+        self.wait_for(|socket| {
+            if !socket.may_send() {
+                return Err("p9fs: connection to the server was closed");
+            }
+            let sent = socket
+                .send_slice(bytes)
+                .map_err(|_| "p9fs: failed to send to the server")?;
+            bytes = &bytes[sent..];
+            Ok(bytes.is_empty())
+        })
+    }
+
+    fn receive(&mut self) -> Result<Vec<u8>, &'static str> {
+        let msize = self.msize as usize;
+        let mut received = core::mem::take(&mut self.received);
+        let result = self.wait_for(|socket| {
+            if socket.can_recv() {
+                socket
+                    .recv(|data| {
+                        received.extend_from_slice(data);
+                        (data.len(), ())
+                    })
+                    .map_err(|_| "p9fs: failed to receive from the server")?;
+            } else if !socket.may_recv() {
+                return Err("p9fs: connection to the server was closed");
+            }
+            match protocol::message_size(&received) {
+                Some(size) if size < protocol::HEADER_SIZE || size > msize => {
+                    Err("p9fs: server sent a message with an invalid size")
+                }
+                Some(size) => Ok(received.len() >= size),
+                None => Ok(false),
+            }
+        });
+        result?;
+        // Keep any bytes that follow the message for the next call.
+        let size = protocol::message_size(&received).unwrap_or(0);
+        self.received = received.split_off(size);
+        Ok(received)
+    }
+
+    /// Polls the interface until `done` returns true or an error, or the timeout elapses.
+    fn wait_for<F>(&self, mut done: F) -> Result<(), &'static str>
+    where
+        F: FnMut(&mut net::LockedSocket<'_, tcp::Socket<'static>>) -> Result<bool, &'static str>,
+    {
+        let start = Instant::now();
+        loop {
+            self.interface.poll();
+            if done(&mut self.socket.lock())? {
+                self.interface.poll();
+                return Ok(());
+            }
+            if start.elapsed() >= self.timeout {
+                return Err("p9fs: timed out waiting for the server");
+            }
+            sleep::sleep(POLL_INTERVAL).map_err(|_| "p9fs: failed to sleep")?;
+        }
+    }
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        self.socket.lock().close();
+        self.interface.poll();
+    }
+}
+
+/// Returns a reader for the fields of the given reply, which was already validated.
+fn reader(reply: &[u8]) -> Result<MessageReader<'_>, &'static str> {
+    MessageReader::new(reply).map(|(_, _, reader)| reader)
+}
@@ -0,0 +1,417 @@
+//! A 9P2000.L filesystem client, which makes a directory tree exported by a remote
+//! 9P server visible within Theseus's VFS.
+//!
+//! This is primarily meant for sharing a directory of the development host with Theseus
+//! running in QEMU, e.g., with a 9P server such as `diod` listening on the host,
+//! which QEMU's user networking makes reachable at `10.0.2.2`.
+//! Crate object files built on the host can then be loaded directly from the mounted directory.
+//!
+//! The connection to the server uses TCP, as there is no virtio transport yet.
+//!
+//! Nodes are created lazily: looking up a name in a [`P9Directory`] walks to it on the server,
+//! and each node releases its fid on the server when it's dropped.
+//! Inserting a file into a [`P9Directory`] copies its contents to the server,
+//! and inserting a directory creates an empty directory of the same name.
+
+#![no_std]
+
+extern crate alloc;
+
+mod client;
+pub mod protocol;
+
+pub use client::{Client, DEFAULT_MSIZE};
+
+use alloc::{
+    string::{String, ToString},
+    sync::{Arc, Weak},
+    vec,
+    vec::Vec,
+};
+use fs_node::{DirRef, Directory, File, FileOrDir, FileRef, FsNode, WeakDirRef};
+use io::{ByteReader, ByteWriter, IoError, KnownLength};
+use log::warn;
+use memory::{allocate_pages_by_bytes, get_kernel_mmi_ref, MappedPages, PteFlags};
+use protocol::Qid;
+use spin::{Mutex, Once};
+
+/// The standard port that 9P servers listen on.
+pub const DEFAULT_PORT: u16 = 564;
+
+/// The permissions given to files created on the server.
+const FILE_MODE: u32 = 0o644;
+/// The permissions given to directories created on the server.
+const DIR_MODE: u32 = 0o755;
+
+/// A connection to a 9P server that's shared by every node of a mounted tree.
+pub type ClientRef = Arc<sync_block::Mutex<Client>>;
+
+/// Attaches to the tree named `aname` on the server that `client` is connected to,
+/// and inserts its root into `parent` as a directory named `name`.
+pub fn mount(
+    client: Client,
+    uname: &str,
+    aname: &str,
+    parent: &DirRef,
+    name: String,
+) -> Result<DirRef, &'static str> {
+    if parent.lock().get(&name).is_some() {
+        return Err("p9fs: a node with the mount point's name already exists");
+    }
+    let client = Arc::new(sync_block::Mutex::new(client));
+    let (fid, _) = client.lock().attach(uname, aname)?;
+    let root = P9Directory::create(Node {
+        name,
+        parent: Arc::downgrade(parent),
+        parent_ref: None,
+        client,
+        fid,
+    });
+    parent.lock().insert(FileOrDir::Dir(root.clone()))?;
+    Ok(root)
+}
+
+/// The state common to every node backed by a file on the server.
+struct Node {
+    name: String,
+    parent: WeakDirRef,
+    /// Lazily created nodes aren't held onto by their parent,
+    /// so they keep their parent alive instead.
+    parent_ref: Option<DirRef>,
+    client: ClientRef,
+    /// A fid for this node's file, which is never opened, such that it can be walked from.
+    fid: u32,
+}
+
+impl Node {
+    /// Returns a new fid for this node's file that's opened with the given `flags`.
+    fn open(&self, flags: u32) -> Result<u32, &'static str> {
+        let mut client = self.client.lock();
+        let (fid, _) = client.walk(self.fid, &[])?;
+        if let Err(e) = client.lopen(fid, flags) {
+            let _ = client.clunk(fid);
+            return Err(e);
+        }
+        Ok(fid)
+    }
+
+    fn set_parent_dir(&mut self, new_parent: WeakDirRef) {
+        self.parent = new_parent;
+        self.parent_ref = None;
+    }
+}
+
+impl Drop for Node {
+    fn drop(&mut self) {
+        let _ = self.client.lock().clunk(self.fid);
+    }
+}
+
+/// A directory on a 9P server.
+pub struct P9Directory {
+    node: Node,
+    self_ref: WeakDirRef,
+}
+
+impl P9Directory {
+    fn create(node: Node) -> DirRef {
+        Arc::new_cyclic(|self_ref: &Weak<Mutex<P9Directory>>| {
+            Mutex::new(P9Directory {
+                node,
+                self_ref: self_ref.clone() as WeakDirRef,
+            })
+        })
+    }
+
+    fn get_internal(&self, name: &str) -> Result<FileOrDir, &'static str> {
+        if name.is_empty() || name == "." || name == ".." || name.contains('/') {
+            return Err("p9fs: invalid file name");
+        }
+        let (fid, qid) = self.node.client.lock().walk(self.node.fid, &[name])?;
+        let node = Node {
+            name: name.to_string(),
+            parent: self.self_ref.clone(),
+            parent_ref: self.self_ref.upgrade(),
+            client: self.node.client.clone(),
+            fid,
+        };
+        Ok(match qid {
+            Some(qid) if qid.is_dir() => FileOrDir::Dir(P9Directory::create(node)),
+            _ => FileOrDir::File(Arc::new(Mutex::new(P9File::new(node))) as FileRef),
+        })
+    }
+
+    fn list_internal(&self) -> Result<Vec<(String, Qid)>, &'static str> {
+        let fid = self.node.open(protocol::O_RDONLY | protocol::O_DIRECTORY)?;
+        let mut client = self.node.client.lock();
+        let entries = client.read_dir(fid);
+        let _ = client.clunk(fid);
+        entries
+    }
+
+    /// Creates a file named `name` on the server with the given contents.
+    fn create_file(&self, name: &str, contents: &[u8]) -> Result<(), &'static str> {
+        let mut client = self.node.client.lock();
+        let (fid, _) = client.walk(self.node.fid, &[])?;
+        let flags = protocol::O_WRONLY | protocol::O_CREAT | protocol::O_TRUNC;
+        let result = client
+            .lcreate(fid, name, flags, FILE_MODE)
+            .and_then(|_| write_all(&mut client, fid, 0, contents));
+        let _ = client.clunk(fid);
+        result
+    }
+}
+
+impl Directory for P9Directory {
+    fn get(&self, name: &str) -> Option<FileOrDir> {
+        self.get_internal(name).ok()
+    }
+
+    /// Creates a copy of the given node on the server.
+    ///
+    /// Files are copied along with their contents, whereas only an empty directory
+    /// is created for a directory.
+    fn insert(&mut self, node: FileOrDir) -> Result<Option<FileOrDir>, &'static str> {
+        let name = node.get_name();
+        match node {
+            FileOrDir::Dir(_) => {
+                self.node.client.lock().mkdir(self.node.fid, &name, DIR_MODE)?;
+            }
+            FileOrDir::File(file) => {
+                // The file may be on the same server,
+                // so it must be read before the client is locked.
+                let contents = read_all(&file)?;
+                self.create_file(&name, &contents)?;
+            }
+        }
+        Ok(None)
+    }
+
+    fn remove(&mut self, node: &FileOrDir) -> Option<FileOrDir> {
+        let flags = if node.is_dir() { protocol::AT_REMOVEDIR } else { 0 };
+        let name = node.get_name();
+        match self.node.client.lock().unlinkat(self.node.fid, &name, flags) {
+            Ok(()) => Some(node.clone()),
+            Err(e) => {
+                warn!("p9fs: failed to remove {:?}: {}", name, e);
+                None
+            }
+        }
+    }
+
+    fn rename(&mut self, old_name: &str, new_name: &str) -> Result<Option<FileOrDir>, &'static str> {
+        if new_name.is_empty() || new_name == "." || new_name == ".." || new_name.contains('/') {
+            return Err("p9fs: invalid file name");
+        }
+        self.node.client.lock().renameat(self.node.fid, old_name, self.node.fid, new_name)?;
+        Ok(None)
+    }
+
+    fn list(&self) -> Vec<String> {
+        match self.list_internal() {
+            Ok(entries) => entries.into_iter().map(|(name, _)| name).collect(),
+            Err(e) => {
+                warn!("p9fs: failed to list {:?}: {}", self.node.name, e);
+                Vec::new()
+            }
+        }
+    }
+}
+
+impl FsNode for P9Directory {
+    fn get_name(&self) -> String {
+        self.node.name.clone()
+    }
+
+    fn get_parent_dir(&self) -> Option<DirRef> {
+        self.node.parent.upgrade()
+    }
+
+    fn set_parent_dir(&mut self, new_parent: WeakDirRef) {
+        self.node.set_parent_dir(new_parent);
+    }
+}
+
+/// A regular file on a 9P server.
+///
+/// The file is opened separately for reading and for writing when first needed.
+pub struct P9File {
+    node: Node,
+    read_fid: Option<u32>,
+    write_fid: Option<u32>,
+    /// The contents of the file, read in full when it's first mapped.
+    mapping: Once<MappedPages>,
+}
+
+impl P9File {
+    fn new(node: Node) -> Self {
+        Self {
+            node,
+            read_fid: None,
+            write_fid: None,
+            mapping: Once::new(),
+        }
+    }
+
+    /// Has the server make everything written to this file durable.
+    fn fsync(&mut self, data_only: bool) -> Result<(), IoError> {
+        // If this file was never written to, there's nothing to sync.
+        let Some(fid) = self.write_fid else {
+            return Ok(());
+        };
+        Ok(self.node.client.lock().fsync(fid, data_only)?)
+    }
+
+    /// Reads the entire file into newly allocated pages.
+    fn map_contents(&self) -> Result<MappedPages, &'static str> {
+        let len = self.len();
+        if len == 0 {
+            return Ok(MappedPages::empty());
+        }
+        let kernel_mmi_ref = get_kernel_mmi_ref().ok_or("KERNEL_MMI was not yet initialized!")?;
+        let pages = allocate_pages_by_bytes(len).ok_or("could not allocate pages")?;
+        let mut mapped_pages = kernel_mmi_ref
+            .lock()
+            .page_table
+            .map_allocated_pages(pages, PteFlags::new().valid(true).writable(true))?;
+
+        let fid = self.node.open(protocol::O_RDONLY)?;
+        let mut client = self.node.client.lock();
+        let result = mapped_pages
+            .as_slice_mut(0, len)
+            .and_then(|buffer| read_full(&mut client, fid, 0, buffer));
+        let _ = client.clunk(fid);
+        if result? != len {
+            return Err("p9fs: file was truncated while it was being mapped");
+        }
+        Ok(mapped_pages)
+    }
+}
+
+impl ByteReader for P9File {
+    fn read_at(&mut self, buffer: &mut [u8], offset: usize) -> Result<usize, IoError> {
+        let fid = match self.read_fid {
+            Some(fid) => fid,
+            None => *self.read_fid.insert(self.node.open(protocol::O_RDONLY)?),
+        };
+        Ok(read_full(&mut self.node.client.lock(), fid, offset as u64, buffer)?)
+    }
+}
+
+impl ByteWriter for P9File {
+    fn write_at(&mut self, buffer: &[u8], offset: usize) -> Result<usize, IoError> {
+        let fid = match self.write_fid {
+            Some(fid) => fid,
+            None => *self.write_fid.insert(self.node.open(protocol::O_WRONLY)?),
+        };
+        // Any existing mapping no longer reflects the file's contents.
+        self.mapping = Once::new();
+        write_all(&mut self.node.client.lock(), fid, offset as u64, buffer)?;
+        Ok(buffer.len())
+    }
+
+    fn flush(&mut self) -> Result<(), IoError> {
+        Ok(())
+    }
+}
+
+impl KnownLength for P9File {
+    fn len(&self) -> usize {
+        match self.node.client.lock().getattr(self.node.fid) {
+            Ok(attr) => attr.size as usize,
+            Err(e) => {
+                warn!("p9fs: failed to get the length of {:?}: {}", self.node.name, e);
+                0
+            }
+        }
+    }
+}
+
+impl File for P9File {
+    fn as_mapping(&self) -> Result<&MappedPages, &'static str> {
+        if let Some(mapped_pages) = self.mapping.get() {
+            return Ok(mapped_pages);
+        }
+        let mapped_pages = self.map_contents()?;
+        Ok(self.mapping.call_once(|| mapped_pages))
+    }
+
+    fn sync_all(&mut self) -> Result<(), IoError> {
+        self.fsync(false)
+    }
+
+    fn sync_data(&mut self) -> Result<(), IoError> {
+        self.fsync(true)
+    }
+}
+
+impl FsNode for P9File {
+    fn get_name(&self) -> String {
+        self.node.name.clone()
+    }
+
+    fn get_parent_dir(&self) -> Option<DirRef> {
+        self.node.parent.upgrade()
+    }
+
+    fn set_parent_dir(&mut self, new_parent: WeakDirRef) {
+        self.node.set_parent_dir(new_parent);
+    }
+}
+
+impl Drop for P9File {
+    fn drop(&mut self) {
+        let mut client = self.node.client.lock();
+        for fid in self.read_fid.into_iter().chain(self.write_fid) {
+            let _ = client.clunk(fid);
+        }
+    }
+}
+
+/// Reads from the opened `fid`, starting at `offset`,
+/// until `buffer` is full or the end of the file is reached.
+fn read_full(
+    client: &mut Client,
+    fid: u32,
+    offset: u64,
+    buffer: &mut [u8],
+) -> Result<usize, &'static str> {
+    let mut read = 0;
+    while read < buffer.len() {
+        let n = client.read(fid, offset + read as u64, &mut buffer[read..])?;
+        if n == 0 {
+            break;
+        }
+        read += n;
+    }
+    Ok(read)
+}
+
+/// Writes all of `data` to the opened `fid`, starting at `offset`.
+fn write_all(client: &mut Client, fid: u32, offset: u64, data: &[u8]) -> Result<(), &'static str> {
+    let mut written = 0;
+    while written < data.len() {
+        let n = client.write(fid, offset + written as u64, &data[written..])?;
+        if n == 0 {
+            return Err("p9fs: server didn't accept any written bytes");
+        }
+        written += n;
+    }
+    Ok(())
+}
+
+/// Reads the entire contents of the given file.
+fn read_all(file: &FileRef) -> Result<Vec<u8>, &'static str> {
+    let mut file = file.lock();
+    let mut contents = vec![0; file.len()];
+    let mut read = 0;
+    while read < contents.len() {
+        let n = file.read_at(&mut contents[read..], read)?;
+        if n == 0 {
+            break;
+        }
+        read += n;
+    }
+    contents.truncate(read);
+    Ok(contents)
+}
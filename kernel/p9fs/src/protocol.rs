@@ -0,0 +1,358 @@
+//! Encoding and decoding of 9P2000.L messages.
+//!
+//! Every message starts with a header of `size[4] type[1] tag[2]`,
+//! where `size` includes the header itself. All integers are little endian,
+//! strings are prefixed with their length as a `u16`, and data with its length as a `u32`.
+
+use alloc::vec::Vec;
+use core::str;
+
+/// The protocol version requested by the client.
+pub const VERSION: &str = "9P2000.L";
+
+/// The size of the header that starts every message.
+pub const HEADER_SIZE: usize = 7;
+
+/// The tag used by `Tversion` messages.
+pub const NOTAG: u16 = !0;
+/// The fid used in place of an authentication fid when none is needed.
+pub const NOFID: u32 = !0;
+
+/// The size of everything in an `Rread` message except the data that was read.
+pub const READ_OVERHEAD: u32 = HEADER_SIZE as u32 + 4;
+/// The size of everything in a `Twrite` message except the data to be written.
+pub const WRITE_OVERHEAD: u32 = HEADER_SIZE as u32 + 4 + 8 + 4;
+
+pub const RLERROR: u8 = 7;
+pub const TLOPEN: u8 = 12;
+pub const RLOPEN: u8 = 13;
+pub const TLCREATE: u8 = 14;
+pub const RLCREATE: u8 = 15;
+pub const TGETATTR: u8 = 24;
+pub const RGETATTR: u8 = 25;
+pub const TREADDIR: u8 = 40;
+pub const RREADDIR: u8 = 41;
+pub const TFSYNC: u8 = 50;
+pub const RFSYNC: u8 = 51;
+pub const TMKDIR: u8 = 72;
+pub const RMKDIR: u8 = 73;
+pub const TRENAMEAT: u8 = 74;
+pub const RRENAMEAT: u8 = 75;
+pub const TUNLINKAT: u8 = 76;
+pub const RUNLINKAT: u8 = 77;
+pub const TVERSION: u8 = 100;
+pub const RVERSION: u8 = 101;
+pub const TATTACH: u8 = 104;
+pub const RATTACH: u8 = 105;
+pub const TWALK: u8 = 110;
+pub const RWALK: u8 = 111;
+pub const TREAD: u8 = 116;
+pub const RREAD: u8 = 117;
+pub const TWRITE: u8 = 118;
+pub const RWRITE: u8 = 119;
+pub const TCLUNK: u8 = 120;
+pub const RCLUNK: u8 = 121;
+
+/// The maximum number of names in a single `Twalk` message.
+pub const MAX_WALK_NAMES: usize = 16;
+
+// Linux open flags, as used by `Tlopen` and `Tlcreate`.
+pub const O_RDONLY: u32 = 0o0;
+pub const O_WRONLY: u32 = 0o1;
+pub const O_CREAT: u32 = 0o100;
+pub const O_TRUNC: u32 = 0o1000;
+pub const O_DIRECTORY: u32 = 0o200000;
+
+/// The `Tunlinkat` flag for removing a directory.
+pub const AT_REMOVEDIR: u32 = 0x200;
+
+/// The `Tgetattr` request mask for the basic fields, i.e., everything but
+/// the birth time, generation, and data version.
+pub const GETATTR_BASIC: u64 = 0x7ff;
+
+/// The bit of [`Qid::ty`] that's set for directories.
+pub const QTDIR: u8 = 0x80;
+
+/// The server's unique identifier for a file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Qid {
+    pub ty: u8,
+    pub version: u32,
+    pub path: u64,
+}
+
+impl Qid {
+    pub fn is_dir(&self) -> bool {
+        self.ty & QTDIR != 0
+    }
+}
+
+/// The attributes of a file returned by `Tgetattr`.
+#[derive(Clone, Copy, Debug)]
+pub struct Attr {
+    pub qid: Qid,
+    pub mode: u32,
+    pub uid: u32,
+    pub gid: u32,
+    pub size: u64,
+    pub mtime_sec: u64,
+}
+
+/// An entry returned by `Treaddir`.
+#[derive(Clone, Debug)]
+pub struct DirEntry<'a> {
+    pub qid: Qid,
+    /// The offset from which to continue reading the directory after this entry.
+    pub offset: u64,
+    pub name: &'a str,
+}
+
+/// Builds a single message.
+pub struct MessageWriter {
+    bytes: Vec<u8>,
+}
+
+impl MessageWriter {
+    /// Starts a message of the given type and tag.
+    pub fn new(ty: u8, tag: u16) -> Self {
+        let mut bytes = Vec::with_capacity(64);
+        bytes.extend_from_slice(&[0; 4]);
+        bytes.push(ty);
+        bytes.extend_from_slice(&tag.to_le_bytes());
+        Self { bytes }
+    }
+
+    pub fn u8(mut self, value: u8) -> Self {
+        self.bytes.push(value);
+        self
+    }
+
+    pub fn u16(mut self, value: u16) -> Self {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    pub fn u32(mut self, value: u32) -> Self {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    pub fn u64(mut self, value: u64) -> Self {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    /// Appends a string, which must be shorter than 64 KiB.
+    pub fn string(self, s: &str) -> Self {
+        let len = s.len().min(u16::MAX as usize);
+        let mut this = self.u16(len as u16);
+        this.bytes.extend_from_slice(&s.as_bytes()[..len]);
+        this
+    }
+
+    /// Appends data prefixed with its length.
+    pub fn data(self, data: &[u8]) -> Self {
+        let mut this = self.u32(data.len() as u32);
+        this.bytes.extend_from_slice(data);
+        this
+    }
+
+    /// Returns the bytes of the finished message, with its size filled in.
+    pub fn finish(mut self) -> Vec<u8> {
+        let size = self.bytes.len() as u32;
+        self.bytes[..4].copy_from_slice(&size.to_le_bytes());
+        self.bytes
+    }
+}
+
+/// Reads the fields of a single message in order.
+pub struct MessageReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> MessageReader<'a> {
+    /// Returns the type and tag of the given complete message,
+    /// and a reader for the fields that follow its header.
+    pub fn new(message: &'a [u8]) -> Result<(u8, u16, Self), &'static str> {
+        let size = message_size(message).ok_or("9P message is shorter than its header")?;
+        if size != message.len() || size < HEADER_SIZE {
+            return Err("9P message has an invalid size");
+        }
+        let tag = u16::from_le_bytes([message[5], message[6]]);
+        Ok((message[4], tag, Self { bytes: &message[HEADER_SIZE..] }))
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], &'static str> {
+        if len > self.bytes.len() {
+            return Err("9P message was truncated");
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    pub fn u8(&mut self) -> Result<u8, &'static str> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub fn u16(&mut self) -> Result<u16, &'static str> {
+        let b = self.take(2)?;
+        Ok(u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    pub fn u32(&mut self) -> Result<u32, &'static str> {
+        let b = self.take(4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    pub fn u64(&mut self) -> Result<u64, &'static str> {
+        let b = self.take(8)?;
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(b);
+        Ok(u64::from_le_bytes(bytes))
+    }
+
+    pub fn string(&mut self) -> Result<&'a str, &'static str> {
+        let len = self.u16()? as usize;
+        str::from_utf8(self.take(len)?).map_err(|_| "9P string was not valid UTF-8")
+    }
+
+    pub fn data(&mut self) -> Result<&'a [u8], &'static str> {
+        let len = self.u32()? as usize;
+        self.take(len)
+    }
+
+    pub fn qid(&mut self) -> Result<Qid, &'static str> {
+        Ok(Qid {
+            ty: self.u8()?,
+            version: self.u32()?,
+            path: self.u64()?,
+        })
+    }
+
+    /// Reads the body of an `Rgetattr` message.
+    pub fn attr(&mut self) -> Result<Attr, &'static str> {
+        let _valid = self.u64()?;
+        let qid = self.qid()?;
+        let mode = self.u32()?;
+        let uid = self.u32()?;
+        let gid = self.u32()?;
+        let _nlink = self.u64()?;
+        let _rdev = self.u64()?;
+        let size = self.u64()?;
+        let _blksize = self.u64()?;
+        let _blocks = self.u64()?;
+        let _atime = (self.u64()?, self.u64()?);
+        let mtime_sec = self.u64()?;
+        Ok(Attr { qid, mode, uid, gid, size, mtime_sec })
+    }
+
+    /// Reads a single directory entry from the data of an `Rreaddir` message.
+    pub fn dir_entry(&mut self) -> Result<DirEntry<'a>, &'static str> {
+        let qid = self.qid()?;
+        let offset = self.u64()?;
+        let _ty = self.u8()?;
+        let name = self.string()?;
+        Ok(DirEntry { qid, offset, name })
+    }
+
+    /// Returns whether every field has been read.
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+}
+
+/// Returns the size of the message that starts at the beginning of `bytes`,
+/// or `None` if not enough bytes were given to tell.
+pub fn message_size(bytes: &[u8]) -> Option<usize> {
+    let size = bytes.get(..4)?;
+    Some(u32::from_le_bytes([size[0], size[1], size[2], size[3]]) as usize)
+}
+
+/// Parses every entry in the data of an `Rreaddir` message.
+pub fn dir_entries(data: &[u8]) -> Result<Vec<DirEntry<'_>>, &'static str> {
+    let mut reader = MessageReader { bytes: data };
+    let mut entries = Vec::new();
+    while !reader.is_empty() {
+        entries.push(reader.dir_entry()?);
+    }
+    Ok(entries)
+}
+
+/// Returns a description of the given Linux error number, as sent in an `Rlerror` message.
+pub fn error_message(errno: u32) -> &'static str {
+    match errno {
+        1 => "9P: operation not permitted",
+        2 => "9P: no such file or directory",
+        5 => "9P: I/O error",
+        9 => "9P: bad file descriptor",
+        12 => "9P: out of memory",
+        13 => "9P: permission denied",
+        17 => "9P: file exists",
+        20 => "9P: not a directory",
+        21 => "9P: is a directory",
+        22 => "9P: invalid argument",
+        27 => "9P: file too large",
+        28 => "9P: no space left on device",
+        30 => "9P: read-only file system",
+        36 => "9P: file name too long",
+        39 => "9P: directory not empty",
+        95 => "9P: operation not supported",
+        _ => "9P: server returned an error",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let message = MessageWriter::new(TVERSION, NOTAG)
+            .u32(8192)
+            .string(VERSION)
+            .finish();
+        assert_eq!(message_size(&message), Some(message.len()));
+
+        let (ty, tag, mut reader) = MessageReader::new(&message).unwrap();
+        assert_eq!((ty, tag), (TVERSION, NOTAG));
+        assert_eq!(reader.u32(), Ok(8192));
+        assert_eq!(reader.string(), Ok(VERSION));
+        assert!(reader.is_empty());
+        assert!(reader.u8().is_err());
+    }
+
+    #[test]
+    fn invalid_size() {
+        let mut message = MessageWriter::new(TCLUNK, 1).u32(3).finish();
+        message.pop();
+        assert!(MessageReader::new(&message).is_err());
+        assert!(MessageReader::new(&message[..3]).is_err());
+    }
+
+    #[test]
+    fn readdir_entries() {
+        let entry = |ty, path, offset, name| {
+            MessageWriter::new(0, 0)
+                .u8(ty)
+                .u32(0)
+                .u64(path)
+                .u64(offset)
+                .u8(0)
+                .string(name)
+                .finish()
+                .split_off(HEADER_SIZE)
+        };
+        let mut data = entry(QTDIR, 1, 1, "src");
+        data.extend(entry(0, 2, 2, "Cargo.toml"));
+
+        let entries = dir_entries(&data).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert!(entries[0].qid.is_dir());
+        assert_eq!(entries[0].name, "src");
+        assert!(!entries[1].qid.is_dir());
+        assert_eq!((entries[1].offset, entries[1].name), (2, "Cargo.toml"));
+
+        assert!(dir_entries(&data[..data.len() - 1]).is_err());
+    }
+}
@@ -27,6 +27,9 @@ extern crate spin;
 extern crate intrusive_collections;
 use intrusive_collections::Bound;
 
+#[cfg(test)]
+mod test;
+
 mod static_array_rb_tree;
 // mod static_array_linked_list;
 
@@ -57,6 +60,18 @@ static DESIGNATED_PAGES_HIGH_START: Page = Page::containing_address(
 	VirtualAddress::new_canonical(UPCOMING_PAGE_TABLE_RECURSIVE_P4_START)
 );
 
+/// The first page of the region reserved for mappings that are private to an address space.
+const PRIVATE_PAGES_START: Page = Page::containing_address(VirtualAddress::new_canonical(PRIVATE_REGION_START));
+
+/// The last page of the region reserved for mappings that are private to an address space.
+///
+/// General allocation requests never use pages within the private region,
+/// not even as a last resort, so private pages must be explicitly requested within
+/// [`PRIVATE_REGION_START`] and [`PRIVATE_REGION_END`].
+/// This ensures that all mappings created in the shared part of the address space
+/// are visible to every address space.
+const PRIVATE_PAGES_END: Page = Page::containing_address(VirtualAddress::new_canonical(PRIVATE_REGION_END));
+
 const MIN_PAGE: Page = Page::containing_address(VirtualAddress::zero());
 const MAX_PAGE: Page = Page::containing_address(VirtualAddress::new_canonical(MAX_VIRTUAL_ADDRESS));
 
@@ -546,6 +561,7 @@ fn find_specific_chunk(
 /// If no range is specified, this function first attempts to find a suitable chunk
 /// that is **not** within the designated regions,
 /// and only allocates from the designated regions as a backup option.
+/// Pages within the private region are never allocated unless that range is specified.
 ///
 /// If an alignment is specified (in terms of number of 4KiB pages), then the starting page
 /// in the allocated range must be aligned to that number of pages.
@@ -558,12 +574,15 @@ fn find_any_chunk(
 ) -> Result<(AllocatedPages, DeferredAllocAction<'static>), AllocationError> {
 	let designated_low_end = DESIGNATED_PAGES_LOW_END.get()
 		.ok_or(AllocationError::NotInitialized)?;
-	let full_range = PageRange::<Page4K>::new(*designated_low_end + 1, DESIGNATED_PAGES_HIGH_START - 1);
+	let full_range = PageRange::<Page4K>::new(
+		max(*designated_low_end, PRIVATE_PAGES_END) + 1,
+		DESIGNATED_PAGES_HIGH_START - 1,
+	);
 	let range = within_range.unwrap_or(&full_range);
 
 	// During the first pass, we only search within the given range.
 	// If no range was given, we search from the end of the low designated region
-	// to the start of the high designated region.
+	// (or the private region, whichever is higher) to the start of the high designated region.
 	match list.0 {
 		Inner::Array(ref mut arr) => {
 			for elem in arr.iter_mut() {
@@ -573,7 +592,7 @@ fn find_any_chunk(
 					let lowest_possible_start_page = max(chunk.start(), range.start())
 						.align_up(alignment_4k_pages);
 					let highest_possible_end_page  = *min(chunk.end(), range.end());
-					if lowest_possible_start_page + num_pages <= highest_possible_end_page {
+					if fits_within(lowest_possible_start_page, num_pages, highest_possible_end_page) {
 						return adjust_chosen_chunk(
 							lowest_possible_start_page,
 							num_pages,
@@ -608,7 +627,7 @@ fn find_any_chunk(
 				let lowest_possible_start_page = max(chunk.start(), range.start())
 					.align_up(alignment_4k_pages);
 				let highest_possible_end_page  = *min(chunk.end(), range.end());
-				if lowest_possible_start_page + num_pages <= highest_possible_end_page {
+				if fits_within(lowest_possible_start_page, num_pages, highest_possible_end_page) {
 					return adjust_chosen_chunk(
 						lowest_possible_start_page,
 						num_pages,
@@ -632,20 +651,29 @@ fn find_any_chunk(
 	}
 
 	// If we can't find any suitable chunks in the non-designated regions, then look in both designated regions.
+	// The private region must still be excluded, even if it overlaps the low designated region.
 	warn!("PageAllocator: unlikely scenario: non-designated chunks are all allocated, \
 		  falling back to allocating {} pages from designated regions!", num_pages);
+	let low_designated_range = PageRange::<Page4K>::new(MIN_PAGE, min(*designated_low_end, PRIVATE_PAGES_START - 1));
+	let high_designated_range = PageRange::<Page4K>::new(DESIGNATED_PAGES_HIGH_START, MAX_PAGE);
 	match list.0 {
 		Inner::Array(ref mut arr) => {
 			for elem in arr.iter_mut() {
 				if let Some(chunk) = elem {
-					let lowest_possible_start_page = chunk.start().align_up(alignment_4k_pages);
-					if lowest_possible_start_page + num_pages <= *chunk.end() {
-						return adjust_chosen_chunk(
-							lowest_possible_start_page,
-							num_pages,
-							&chunk.clone(),
-							ValueRefMut::Array(elem),
-						);
+					// A chunk in the early static array may span both designated and non-designated pages,
+					// so only the parts of it within the designated regions are eligible.
+					for designated_range in [&low_designated_range, &high_designated_range] {
+						let lowest_possible_start_page = max(chunk.start(), designated_range.start())
+							.align_up(alignment_4k_pages);
+						let highest_possible_end_page  = *min(chunk.end(), designated_range.end());
+						if fits_within(lowest_possible_start_page, num_pages, highest_possible_end_page) {
+							return adjust_chosen_chunk(
+								lowest_possible_start_page,
+								num_pages,
+								&chunk.clone(),
+								ValueRefMut::Array(elem),
+							);
+						}
 					}
 				}
 			}
@@ -665,10 +693,12 @@ fn find_any_chunk(
 			//
 			// RBTree doesn't have a `range_mut()` method, so we use cursors for two rounds of iteration.
 			// The first iterates over the lower designated region, from higher addresses to lower, down to zero.
-			let mut cursor = tree.upper_bound_mut(Bound::Included(designated_low_end));
+			// Chunks may have been merged across region boundaries, so each chunk is clamped to the region.
+			let mut cursor = tree.upper_bound_mut(Bound::Included(low_designated_range.end()));
 			while let Some(chunk) = cursor.get().map(|w| w.deref()) {
 				let lowest_possible_start_page = chunk.start().align_up(alignment_4k_pages);
-				if lowest_possible_start_page + num_pages <= *chunk.end() {
+				let highest_possible_end_page  = *min(chunk.end(), low_designated_range.end());
+				if fits_within(lowest_possible_start_page, num_pages, highest_possible_end_page) {
 					return adjust_chosen_chunk(
 						lowest_possible_start_page,
 						num_pages,
@@ -682,12 +712,13 @@ fn find_any_chunk(
 			// The second iterates over the higher designated region, from the highest (max) address down to the designated region boundary.
 			let mut cursor = tree.upper_bound_mut::<Chunk>(Bound::Unbounded);
 			while let Some(chunk) = cursor.get().map(|w| w.deref()) {
-				if chunk.start() < &DESIGNATED_PAGES_HIGH_START {
+				if chunk.end() < high_designated_range.start() {
 					// we already iterated over non-designated pages in the first match statement above, so we're out of memory. 
 					break; 
 				}
-				let lowest_possible_start_page = chunk.start().align_up(alignment_4k_pages);
-				if lowest_possible_start_page + num_pages <= *chunk.end() {
+				let lowest_possible_start_page = max(chunk.start(), high_designated_range.start())
+					.align_up(alignment_4k_pages);
+				if fits_within(lowest_possible_start_page, num_pages, *chunk.end()) {
 					return adjust_chosen_chunk(
 						lowest_possible_start_page,
 						num_pages,
@@ -703,6 +734,14 @@ fn find_any_chunk(
 	Err(AllocationError::OutOfAddressSpace(num_pages, None))
 }
 
+/// Returns whether `num_pages` pages starting at `start_page` fit at or below `end_page`.
+///
+/// This compares page numbers because adding to a `Page` saturates at the highest page,
+/// which would let an oversized request "fit" within a chunk at the end of the address space.
+fn fits_within(start_page: Page, num_pages: usize, end_page: Page) -> bool {
+	start_page.number().saturating_add(num_pages) <= end_page.number()
+}
+
 
 /// The final part of the main allocation routine. 
 ///
@@ -0,0 +1,40 @@
+//! Tests for the page allocator, mainly that general allocations never use the private region.
+
+use super::*;
+
+fn is_private(pages: &AllocatedPages<Page4K>) -> bool {
+    *pages.end() >= PRIVATE_PAGES_START && *pages.start() <= PRIVATE_PAGES_END
+}
+
+/// Checks that an allocation request that only fits within the private region fails,
+/// and that a small one is satisfied from a designated region instead.
+fn check_fallback_excludes_private_region() {
+    let private_region_pages = PRIVATE_PAGES_END.number() - PRIVATE_PAGES_START.number() + 1;
+    assert!(allocate_pages(private_region_pages / 2).is_none());
+
+    let pages = allocate_pages(1).expect("couldn't allocate a page from a designated region");
+    assert!(!is_private(&pages), "allocated private pages {:?}", pages);
+    let designated_low_end = *DESIGNATED_PAGES_LOW_END.get().unwrap();
+    assert!(*pages.end() <= designated_low_end || *pages.start() >= DESIGNATED_PAGES_HIGH_START);
+}
+
+#[test]
+fn exhausted_general_region_never_falls_back_to_private_region() {
+    init(VirtualAddress::new_canonical(0x40_0000)).unwrap();
+
+    // Allocate (nearly) all pages between the private region and the high designated region.
+    // The final page is left over because a chunk's last page is never allocated.
+    let general_region_pages = DESIGNATED_PAGES_HIGH_START.number() - (PRIVATE_PAGES_END.number() + 1);
+    let general = allocate_pages(general_region_pages - 1).expect("couldn't allocate the general region");
+    assert!(!is_private(&general));
+    assert!(*general.end() < DESIGNATED_PAGES_HIGH_START);
+
+    // While the free list is still backed by the early static array.
+    check_fallback_excludes_private_region();
+
+    // And again once the free list is backed by the heap.
+    convert_page_allocator_to_heap_based();
+    check_fallback_excludes_private_region();
+
+    drop(general);
+}
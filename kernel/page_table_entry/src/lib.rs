@@ -94,6 +94,17 @@ impl PageTableEntry {
         self.0 = (frame.start_address().value() as u64) | flags.bits();
     }
 
+    /// Sets this `PageTableEntry` to point to the same frame with the same flags as `other`,
+    /// but never as an exclusive mapping.
+    ///
+    /// This is intended only for sharing a lower-level page table frame
+    /// between multiple higher-level page tables, e.g., sharing the same P3 tables
+    /// across the P4 root tables of multiple address spaces.
+    pub fn alias_from(&mut self, other: &PageTableEntry) {
+        self.0 = other.0;
+        self.set_flags(other.flags().exclusive(false));
+    }
+
     /// Sets the flags components of this `PageTableEntry` to `new_flags`.
     ///
     /// This does not modify the frame part of the page table entry.
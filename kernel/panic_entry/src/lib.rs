@@ -63,6 +63,8 @@ fn panic_entry_point(info: &PanicInfo) -> ! {
         // basic early panic printing with no dependencies
         println!("\nHalting due to early panic: {}", info);
     }
+    // Write out any buffered log records, as the task that normally does so may never run again.
+    log::logger().flush();
 
     // If we failed to handle the panic, there's not really much we can do about it,
     // other than just let the thread spin endlessly (which doesn't hurt correctness but is inefficient). 
@@ -0,0 +1,191 @@
+//! Matching of file names against shell-style wildcard patterns.
+
+use crate::{Component, Path, PathBuf};
+use alloc::{string::String, vec, vec::Vec};
+use fs_node::FileOrDir;
+
+const WILDCARDS: &[char] = &['*', '?', '['];
+
+/// Returns whether the file name matches the given wildcard pattern.
+///
+/// The pattern may contain:
+/// - `*`, which matches any sequence of characters, including an empty one;
+/// - `?`, which matches any single character;
+/// - `[...]`, which matches any single character in the brackets. The
+///   brackets may contain ranges such as `a-z`, and the set is negated if it
+///   starts with `!` or `^`.
+///
+/// Any other character matches only itself. Wildcards never match a leading
+/// `.`, so hidden files are only matched by patterns that start with `.`.
+///
+/// # Examples
+///
+/// ```
+/// # use path::glob_match;
+/// assert!(glob_match("*.rs", "lib.rs"));
+/// assert!(!glob_match("*.rs", "lib.rs.bk"));
+/// assert!(glob_match("file?.[a-c]", "file1.b"));
+/// assert!(!glob_match("[!f]*", "file"));
+/// assert!(!glob_match("*", ".hidden"));
+/// assert!(glob_match(".*", ".hidden"));
+/// ```
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    if name.starts_with('.') && !pattern.starts_with('.') {
+        return false;
+    }
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+
+    // The position just after the last `*`, and the position in `name` that it
+    // was last tried to match up to, so the match can be retried with the `*`
+    // matching one more character.
+    let mut backtrack = None;
+    let (mut p, mut n) = (0, 0);
+    while n < name.len() {
+        if let Some(&c) = pattern.get(p) {
+            if c == '*' {
+                p += 1;
+                backtrack = Some((p, n));
+                continue;
+            }
+            let matched = match c {
+                '?' => Some(p + 1),
+                '[' => match_set(&pattern[p..], name[n]).map(|len| p + len),
+                c => (c == name[n]).then_some(p + 1),
+            };
+            if let Some(next) = matched {
+                p = next;
+                n += 1;
+                continue;
+            }
+        }
+        match backtrack {
+            Some((after_star, matched_up_to)) => {
+                p = after_star;
+                n = matched_up_to + 1;
+                backtrack = Some((after_star, n));
+            }
+            None => return false,
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Matches a character against the bracketed set at the start of `pattern`.
+///
+/// Returns the length of the set if it contains the character. A `[` without
+/// a closing `]` only matches itself.
+fn match_set(pattern: &[char], c: char) -> Option<usize> {
+    let negated = matches!(pattern.get(1), Some('!' | '^'));
+    let start = if negated { 2 } else { 1 };
+    // A `]` straight after the opening bracket is part of the set.
+    let Some(end) = pattern
+        .iter()
+        .skip(start + 1)
+        .position(|&ch| ch == ']')
+        .map(|i| i + start + 1)
+    else {
+        return (c == '[').then_some(1);
+    };
+
+    let set = &pattern[start..end];
+    let mut contains = false;
+    let mut i = 0;
+    while i < set.len() {
+        if i + 2 < set.len() && set[i + 1] == '-' {
+            contains |= (set[i]..=set[i + 2]).contains(&c);
+            i += 3;
+        } else {
+            contains |= set[i] == c;
+            i += 1;
+        }
+    }
+    (contains != negated).then_some(end + 1)
+}
+
+impl Path {
+    /// Returns true if any component of the path contains a wildcard.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use path::Path;
+    /// assert!(Path::new("src/*.rs").is_glob());
+    /// assert!(!Path::new("src/lib.rs").is_glob());
+    /// ```
+    #[inline]
+    pub fn is_glob(&self) -> bool {
+        self.inner.contains(WILDCARDS)
+    }
+
+    // TODO: Move out of path crate.
+    /// Returns the paths of the existing files and directories that match
+    /// this path, where each component may be a pattern as described in
+    /// [`glob_match`].
+    ///
+    /// The path can be relative or absolute, and the returned paths are
+    /// relative or absolute accordingly. Matches are sorted by name within
+    /// each directory.
+    pub fn glob(&self, cwd: &fs_node::DirRef) -> Vec<PathBuf> {
+        let mut components = self.components().peekable();
+        let (prefix, start) = match components.peek() {
+            Some(Component::RootDir) => {
+                components.next();
+                (String::from("/"), root::get_root().clone())
+            }
+            _ => (String::new(), cwd.clone()),
+        };
+        let mut matches = vec![(prefix, FileOrDir::Dir(start))];
+
+        for component in components {
+            let mut next_matches = Vec::new();
+            for (prefix, node) in matches {
+                let FileOrDir::Dir(dir) = node else {
+                    continue;
+                };
+                let join = |name: &str| {
+                    let mut path = prefix.clone();
+                    if !path.is_empty() && !path.ends_with('/') {
+                        path.push('/');
+                    }
+                    path.push_str(name);
+                    path
+                };
+                match &component {
+                    Component::RootDir => {}
+                    Component::CurDir => next_matches.push((join("."), FileOrDir::Dir(dir))),
+                    Component::ParentDir => {
+                        let parent = dir.lock().get_parent_dir();
+                        if let Some(parent) = parent {
+                            next_matches.push((join(".."), FileOrDir::Dir(parent)));
+                        }
+                    }
+                    Component::Normal(pattern) if pattern.contains(WILDCARDS) => {
+                        let dir = dir.lock();
+                        let mut names = dir.list();
+                        names.sort();
+                        for name in names {
+                            if glob_match(pattern, &name) {
+                                if let Some(node) = dir.get(&name) {
+                                    next_matches.push((join(&name), node));
+                                }
+                            }
+                        }
+                    }
+                    Component::Normal(name) => {
+                        let node = dir.lock().get(name);
+                        if let Some(node) = node {
+                            next_matches.push((join(name), node));
+                        }
+                    }
+                }
+            }
+            matches = next_matches;
+        }
+
+        matches
+            .into_iter()
+            .map(|(path, _)| PathBuf::from(path))
+            .collect()
+    }
+}
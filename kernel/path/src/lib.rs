@@ -8,6 +8,7 @@
 extern crate alloc;
 
 mod component;
+mod glob;
 
 use alloc::{borrow::ToOwned, string::String, vec, vec::Vec};
 use core::{
@@ -17,6 +18,7 @@ use core::{
 };
 
 pub use component::{Component, Components};
+pub use glob::glob_match;
 
 /// A slice of a path.
 ///
@@ -29,6 +29,10 @@
 
 extern crate alloc;
 
+mod reset;
+
+pub use reset::PciConfigState;
+
 use log::*;
 use core::{fmt, ops::{Deref, DerefMut}, mem::size_of, task::Waker};
 use alloc::vec::Vec;
@@ -147,12 +151,19 @@ pci_register!(PCI_INTERRUPT_PIN,       0x3D, 1);
 pci_register!(PCI_MIN_GRANT,           0x3E, 1);
 pci_register!(PCI_MAX_LATENCY,         0x3F, 1);
 
+// The below registers only exist in the configuration space of PCI-to-PCI bridges (header type 1).
+pci_register!(PCI_SECONDARY_BUS,       0x19, 1);
+pci_register!(PCI_SUBORDINATE_BUS,     0x1A, 1);
+pci_register!(PCI_BRIDGE_CONTROL,      0x3E, 2);
+
 const PCI_COMMAND_INT_DISABLED: u16 = 1 << 10;
 
 #[repr(u8)]
 pub enum PciCapability {
-    Msi  = 0x05,
-    Msix = 0x11,
+    Msi              = 0x05,
+    PciExpress       = 0x10,
+    Msix             = 0x11,
+    AdvancedFeatures = 0x13,
 }
 
 /// If a BAR's bits [2:1] equal this value, that BAR describes a 64-bit address.
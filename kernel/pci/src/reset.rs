@@ -0,0 +1,225 @@
+//! Resetting PCI devices and saving and restoring their configuration space,
+//! such that a device that stopped responding can be recovered without rebooting.
+//!
+//! Two kinds of resets are supported:
+//! * A function-level reset (FLR) resets only a single device (function),
+//!   via either its PCI Express capability or its Advanced Features capability.
+//! * A bus reset resets *all* devices on a bus, via the secondary bus reset bit
+//!   of the PCI-to-PCI bridge upstream of that bus.
+//!
+//! Both kinds of resets return a device's configuration space to its power-on state,
+//! including its BARs and command register, so it should be saved beforehand
+//! via [`PciLocation::save_config_space()`] and restored afterwards.
+//!
+//! Only the standard 256-byte configuration space is saved and restored,
+//! as the PCI Express extended configuration space isn't yet accessible.
+//!
+//! Resetting a device takes on the order of 100ms, during which the current task sleeps,
+//! so resets must be performed from a task context without holding any spinlocks.
+
+use crate::{
+    pci_device_iter, PciCapability, PciDevice, PciLocation, PciRegister, RegisterSpan::FullDword,
+    PCI_BRIDGE_CONTROL, PCI_SECONDARY_BUS, PCI_SUBORDINATE_BUS, PCI_VENDOR_ID,
+};
+use core::{ops::RangeInclusive, time::Duration};
+use log::warn;
+use time::Instant;
+
+/// The number of 4-byte chunks in the standard PCI configuration space.
+const CONFIG_SPACE_DWORDS: usize = 64;
+/// The number of 4-byte chunks in the header of the PCI configuration space.
+const HEADER_DWORDS: usize = 16;
+
+// Offsets of registers within the PCI Express capability, and their relevant bits.
+const PCIE_DEVICE_CAPABILITIES: u8 = 0x04;
+const PCIE_DEVICE_CONTROL:      u8 = 0x08;
+const PCIE_DEVICE_STATUS:       u8 = 0x0A;
+const PCIE_DEVCAP_FLR:                  u32 = 1 << 28;
+const PCIE_DEVCTL_INITIATE_FLR:         u16 = 1 << 15;
+const PCIE_DEVSTA_TRANSACTIONS_PENDING: u16 = 1 << 5;
+
+// Offsets of registers within the Advanced Features capability, and their relevant bits.
+const AF_CAPABILITIES: u8 = 0x03;
+const AF_CONTROL:      u8 = 0x04;
+const AF_STATUS:       u8 = 0x05;
+const AF_CAP_TRANSACTIONS_PENDING:    u8 = 1 << 0;
+const AF_CAP_FLR:                     u8 = 1 << 1;
+const AF_CTRL_INITIATE_FLR:           u8 = 1 << 0;
+const AF_STATUS_TRANSACTIONS_PENDING: u8 = 1 << 0;
+
+const BRIDGE_CONTROL_SECONDARY_BUS_RESET: u16 = 1 << 6;
+/// The header type (ignoring the multi-function bit) of a PCI-to-PCI bridge.
+const HEADER_TYPE_BRIDGE: u8 = 0x01;
+
+/// How long to wait for a device's pending transactions to complete before resetting it anyway.
+const PENDING_TRANSACTIONS_TIMEOUT: Duration = Duration::from_millis(100);
+/// How long to hold the secondary bus reset bit, which must be at least 1ms.
+const BUS_RESET_DURATION: Duration = Duration::from_millis(2);
+/// How long a device may take after a reset before it can be accessed.
+const RESET_RECOVERY_DELAY: Duration = Duration::from_millis(100);
+/// How long to wait for a device to respond to configuration space accesses after a reset.
+const DEVICE_READY_TIMEOUT: Duration = Duration::from_secs(1);
+/// How long to sleep between successive checks of a device's status while waiting on it.
+const POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// A copy of a device's configuration space, saved via [`PciLocation::save_config_space()`].
+#[derive(Clone, Debug)]
+pub struct PciConfigState {
+    dwords: [u32; CONFIG_SPACE_DWORDS],
+}
+
+impl PciLocation {
+    /// Saves a copy of this device's configuration space.
+    ///
+    /// Returns an error if the device doesn't respond to configuration space accesses.
+    pub fn save_config_space(&self) -> Result<PciConfigState, &'static str> {
+        let mut dwords = [0; CONFIG_SPACE_DWORDS];
+        for (index, dword) in dwords.iter_mut().enumerate() {
+            *dword = self.pci_read_32(dword_register(index));
+        }
+        if dwords[0] == u32::MAX {
+            return Err("PCI device didn't respond to configuration space accesses");
+        }
+        Ok(PciConfigState { dwords })
+    }
+
+    /// Restores this device's configuration space from the given saved `state`.
+    ///
+    /// Only the registers that differ from the saved state are written.
+    /// The header is restored last and in reverse order, such that the BARs are restored
+    /// before the command register re-enables the device's memory and I/O decoding.
+    pub fn restore_config_space(&self, state: &PciConfigState) -> Result<(), &'static str> {
+        if self.pci_read_32(dword_register(0)) != state.dwords[0] {
+            return Err("PCI device's vendor and device IDs didn't match its saved configuration space");
+        }
+        let capabilities = HEADER_DWORDS..CONFIG_SPACE_DWORDS;
+        let header = (1..HEADER_DWORDS).rev();
+        for index in capabilities.chain(header) {
+            let register = dword_register(index);
+            if self.pci_read_32(register) != state.dwords[index] {
+                self.pci_write_32(register, state.dwords[index]);
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns whether this device supports a function-level reset (FLR).
+    pub fn supports_function_level_reset(&self) -> bool {
+        self.pcie_flr_capability().is_some() || self.af_flr_capability().is_some()
+    }
+
+    /// Resets only this device (function) via a function-level reset (FLR),
+    /// and waits for it to become ready again.
+    ///
+    /// This device's configuration space must be restored afterwards.
+    pub fn function_level_reset(&self) -> Result<(), &'static str> {
+        if let Some(cap) = self.pcie_flr_capability() {
+            let status = PciRegister::from_offset(cap + PCIE_DEVICE_STATUS, 2);
+            self.wait_for_pending_transactions(|| {
+                self.pci_read_16(status) & PCIE_DEVSTA_TRANSACTIONS_PENDING == 0
+            })?;
+            let control = PciRegister::from_offset(cap + PCIE_DEVICE_CONTROL, 2);
+            self.pci_write_16(control, self.pci_read_16(control) | PCIE_DEVCTL_INITIATE_FLR);
+        } else if let Some(cap) = self.af_flr_capability() {
+            let status = PciRegister::from_offset(cap + AF_STATUS, 1);
+            self.wait_for_pending_transactions(|| {
+                self.pci_read_8(status) & AF_STATUS_TRANSACTIONS_PENDING == 0
+            })?;
+            self.pci_write_8(PciRegister::from_offset(cap + AF_CONTROL, 1), AF_CTRL_INITIATE_FLR);
+        } else {
+            return Err("PCI device doesn't support function-level reset");
+        }
+        self.wait_until_ready()
+    }
+
+    /// Resets all devices on this device's bus via the secondary bus reset bit
+    /// of the upstream PCI-to-PCI bridge, and waits for this device to become ready again.
+    ///
+    /// This also resets all devices on any buses behind bridges on this device's bus;
+    /// see [`PciLocation::bus_reset_range()`].
+    ///
+    /// The configuration space of *every* reset device must be restored afterwards,
+    /// so this should only be used if this device doesn't support a function-level reset.
+    pub fn bus_reset(&self) -> Result<(), &'static str> {
+        let bridge = self.upstream_bridge()?.ok_or("PCI device isn't behind a PCI-to-PCI bridge")?;
+        let control = bridge.pci_read_16(PCI_BRIDGE_CONTROL);
+        bridge.pci_write_16(PCI_BRIDGE_CONTROL, control | BRIDGE_CONTROL_SECONDARY_BUS_RESET);
+        let slept = delay(BUS_RESET_DURATION);
+        bridge.pci_write_16(PCI_BRIDGE_CONTROL, control & !BRIDGE_CONTROL_SECONDARY_BUS_RESET);
+        slept?;
+        self.wait_until_ready()
+    }
+
+    /// Returns the range of buses whose devices are all reset by [`PciLocation::bus_reset()`],
+    /// i.e., this device's bus and all buses subordinate to it.
+    pub fn bus_reset_range(&self) -> Result<RangeInclusive<u8>, &'static str> {
+        let bridge = self.upstream_bridge()?.ok_or("PCI device isn't behind a PCI-to-PCI bridge")?;
+        Ok(bridge.pci_read_8(PCI_SECONDARY_BUS)..=bridge.pci_read_8(PCI_SUBORDINATE_BUS))
+    }
+
+    /// Returns the PCI-to-PCI bridge whose secondary bus is this device's bus, if any.
+    pub fn upstream_bridge(&self) -> Result<Option<&'static PciDevice>, &'static str> {
+        Ok(pci_device_iter()?.find(|dev|
+            dev.header_type & 0x7F == HEADER_TYPE_BRIDGE
+                && dev.pci_read_8(PCI_SECONDARY_BUS) == self.bus
+        ))
+    }
+
+    /// Returns the offset of this device's PCI Express capability, if it supports FLR.
+    fn pcie_flr_capability(&self) -> Option<u8> {
+        let cap = self.find_pci_capability(PciCapability::PciExpress)?;
+        let device_capabilities = self.pci_read_32(PciRegister::from_offset(cap + PCIE_DEVICE_CAPABILITIES, 4));
+        (device_capabilities & PCIE_DEVCAP_FLR != 0).then_some(cap)
+    }
+
+    /// Returns the offset of this device's Advanced Features capability, if it supports FLR.
+    fn af_flr_capability(&self) -> Option<u8> {
+        let cap = self.find_pci_capability(PciCapability::AdvancedFeatures)?;
+        let af_capabilities = self.pci_read_8(PciRegister::from_offset(cap + AF_CAPABILITIES, 1));
+        // Initiating an FLR requires being able to check for pending transactions first.
+        let required = AF_CAP_FLR | AF_CAP_TRANSACTIONS_PENDING;
+        (af_capabilities & required == required).then_some(cap)
+    }
+
+    /// Waits until `no_pending_transactions` returns true, or logs a warning upon a timeout.
+    fn wait_for_pending_transactions(&self, no_pending_transactions: impl FnMut() -> bool) -> Result<(), &'static str> {
+        if !wait_until(PENDING_TRANSACTIONS_TIMEOUT, no_pending_transactions)? {
+            warn!("PCI device at {} still has pending transactions, resetting it anyway", self);
+        }
+        Ok(())
+    }
+
+    /// Waits for this device to respond to configuration space accesses after a reset.
+    fn wait_until_ready(&self) -> Result<(), &'static str> {
+        delay(RESET_RECOVERY_DELAY)?;
+        if wait_until(DEVICE_READY_TIMEOUT, || self.pci_read_16(PCI_VENDOR_ID) != 0xFFFF)? {
+            Ok(())
+        } else {
+            Err("PCI device didn't become ready after being reset")
+        }
+    }
+}
+
+/// Returns the register for the 4-byte chunk at the given `index` in the configuration space.
+fn dword_register(index: usize) -> PciRegister {
+    PciRegister { index: index as u8, span: FullDword }
+}
+
+/// Puts the current task to sleep for the given `duration`.
+fn delay(duration: Duration) -> Result<(), &'static str> {
+    sleep::sleep(duration).map_err(|_| "couldn't sleep while waiting on a PCI device")
+}
+
+/// Sleeps until the given `condition` is true, returning `false` if the `timeout` elapsed first.
+fn wait_until(timeout: Duration, mut condition: impl FnMut() -> bool) -> Result<bool, &'static str> {
+    let start = Instant::now();
+    loop {
+        if condition() {
+            return Ok(true);
+        }
+        if start.elapsed() >= timeout {
+            return Ok(false);
+        }
+        delay(POLL_INTERVAL)?;
+    }
+}
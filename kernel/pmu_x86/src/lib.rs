@@ -57,6 +57,7 @@
 //! # Note
 //! Currently, the PMU-based sampler will only capture samples on the same core as it was initialized and started from. 
 //! So, if you run `pmu_x86::init()` and `pmu_x86::start_samples()` on CPU core 2, it will only sample events on core 2.
+//! To initialize the PMU on every core at once, run `pmu_x86::init_all_cores()` instead of `pmu_x86::init()`.
 
 #![no_std]
 
@@ -74,6 +75,7 @@ extern crate cpu;
 #[macro_use] extern crate log;
 extern crate mod_mgmt;
 extern crate bit_field;
+extern crate cpu_call;
 
 use msr::*;
 use x86_64::{VirtAddr, registers::model_specific::Msr, structures::idt::InterruptStackFrame};
@@ -283,6 +285,28 @@ pub fn init() -> Result<(), &'static str> {
     Ok(())
 }
 
+/// Initializes the PMU on every core, rather than only on the current core like [`init()`].
+///
+/// The registers of the other cores are set up by running a function on each of them via [`cpu_call`].
+/// Cores that have already been initialized are left untouched, such that their counters aren't reset.
+///
+/// # Warning
+/// This function should only be called after all the cores have been booted up.
+pub fn init_all_cores() -> Result<(), &'static str> {
+    init()?;
+
+    let already_initialized = CORES_INITIALIZED.lock().clone();
+    cpu_call::run_on_other_cpus(|| {
+        if !already_initialized.contains(&cpu::current_cpu().into_u8()) {
+            init_registers();
+        }
+    }).map_err(|_e| "pmu_x86: failed to initialize the PMU registers on other cores")?;
+
+    CORES_INITIALIZED.lock().extend(cpu::cpus().map(|cpu| cpu.into_u8()));
+    trace!("PMU initialized on all cores");
+    Ok(())
+}
+
 /// Part of the initialization routine which actually does the work of setting up the registers.
 /// This must be called for every core that wants to use the PMU.
 fn init_registers() {
@@ -7,12 +7,45 @@
 #![feature(negative_impls, thread_local)]
 
 use cpu::CpuId;
+use crossbeam_utils::atomic::AtomicCell;
 
 /// A reference to the preemption counter for the current CPU (in CPU-local storage).
 // NOTE: This offset must be kept in sync with `cpu_local::PerCpuField`.
 #[cls_macros::cpu_local(cls_dep = false)]
 static PREEMPTION_COUNT: u8 = 0;
 
+/// Functions that are invoked when preemption transitions between
+/// being enabled and disabled on a CPU.
+///
+/// This allows higher-level crates to instrument preemption-disabled sections,
+/// e.g., to measure how long preemption stays disabled, without this crate
+/// depending on them.
+///
+/// Both hooks are invoked with preemption disabled, so neither can be re-entered on the same CPU.
+/// Because a `PreemptionGuard` may be recovered by a different task after a task switch,
+/// a section that begins in one task may end in another task on the same CPU.
+#[derive(Debug)]
+pub struct PreemptionHooks {
+    /// Invoked right after preemption was disabled on the given CPU.
+    pub on_disable: fn(CpuId),
+    /// Invoked right before preemption is re-enabled on the given CPU.
+    pub on_enable: fn(CpuId),
+}
+
+/// The currently-registered hooks for preemption-disabled sections, if any.
+static PREEMPTION_HOOKS: AtomicCell<Option<&'static PreemptionHooks>> = AtomicCell::new(None);
+
+// Ensure that `AtomicCell<Option<&'static PreemptionHooks>>` is actually a lock-free atomic.
+const _: () = assert!(AtomicCell::<Option<&'static PreemptionHooks>>::is_lock_free());
+
+/// Registers the given `hooks` to be invoked at the start and end of
+/// every preemption-disabled section on every CPU.
+///
+/// Passing `None` removes the currently-registered hooks.
+pub fn set_preemption_hooks(hooks: Option<&'static PreemptionHooks>) {
+    PREEMPTION_HOOKS.store(hooks);
+}
+
 /// Prevents preemption (preemptive task switching) from occurring
 /// until the returned guard object is dropped.
 ///
@@ -53,6 +86,12 @@ fn hold_preemption_internal<const DISABLE_TIMER: bool>() -> PreemptionGuard {
 
     // When transitioning from preemption being enabled to disabled,
     // (optionally) disable the local timer interrupt used for preemptive task switching.
+    if guard.preemption_was_enabled {
+        if let Some(hooks) = PREEMPTION_HOOKS.load() {
+            (hooks.on_disable)(cpu_id);
+        }
+    }
+
     if DISABLE_TIMER && guard.preemption_was_enabled {
         // log::trace!(" CPU {}:   disabling local timer interrupt", cpu_id);
         #[cfg(target_arch = "x86_64")]
@@ -123,6 +162,13 @@ impl Drop for PreemptionGuard {
             cpu_id,
         );
 
+        // Invoke the hook before decrementing the counter, such that preemption is still disabled.
+        if PREEMPTION_COUNT.load() == 1 {
+            if let Some(hooks) = PREEMPTION_HOOKS.load() {
+                (hooks.on_enable)(cpu_id);
+            }
+        }
+
         let prev_val = PREEMPTION_COUNT.fetch_sub(1);
 
         // If the previous counter value was 1, that means the current value is 0,
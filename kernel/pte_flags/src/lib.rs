@@ -84,7 +84,8 @@ bitflags! {
         /// * If set, userspace (unprivileged mode) can access this page.
         /// * If not set, only kernelspace (privileged mode) can access this page.
         ///
-        /// This is not used in Theseus, because it has a single privilege level.
+        /// Theseus only uses this for the few mappings that are exposed to
+        /// code running in userspace; all other mappings are kernel-only.
         //
         // This does not require a conversion between architectures.
         const _USER_ACCESSIBLE = PteFlagsArch::_USER_ACCESSIBLE.bits();
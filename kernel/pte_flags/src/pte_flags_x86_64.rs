@@ -38,7 +38,7 @@ bitflags! {
         /// * If set, userspace (ring 3) can access this page.
         /// * If not set, only kernelspace (ring 0) can access this page.
         ///
-        /// This is unused in Theseus because it is a single privilege level OS.
+        /// Theseus only sets this for mappings that are exposed to code running in ring 3.
         const _USER_ACCESSIBLE   = 1 << 2;
 
         /// * If set, writes to this page go directly to memory.
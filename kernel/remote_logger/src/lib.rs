@@ -0,0 +1,372 @@
+//! Forwards kernel log records to a remote collector over UDP, in the syslog format.
+//!
+//! Once [`init()`] is called, every enabled log record is queued in a bounded local buffer
+//! and a background task sends it to the configured collector as an [RFC 5424] message.
+//! Sending is rate limited, and while there is no network interface that is up,
+//! records are held in the buffer such that they're sent once the network comes up.
+//! If the buffer is full, the oldest records are dropped and the collector
+//! is told how many were lost once sending resumes.
+//!
+//! Records logged by the network stack and NIC drivers aren't forwarded,
+//! since sending each record could itself cause more records to be logged.
+//!
+//! [RFC 5424]: https://www.rfc-editor.org/rfc/rfc5424
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::{
+    collections::VecDeque,
+    format,
+    string::{String, ToString},
+    sync::Arc,
+    vec,
+};
+use core::{
+    fmt::Write,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+use log::{Level, LevelFilter, Record};
+use net::{
+    udp::{self, PacketBuffer, PacketMetadata},
+    IpEndpoint, NetworkInterface, Socket,
+};
+use spin::Mutex;
+use sync_irq::IrqSafeMutex;
+use time::Instant;
+
+/// The standard syslog port.
+pub const DEFAULT_COLLECTOR_PORT: u16 = 514;
+
+/// The maximum size of a syslog message, such that it fits in a single
+/// unfragmented UDP packet on a standard Ethernet link.
+const MAX_MESSAGE_SIZE: usize = 1472;
+
+/// How often the forwarding task checks for queued records.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// The number of buffered packets in the UDP socket's transmit buffer.
+const NUM_SOCKET_PACKETS: usize = 16;
+
+/// The crates whose log records aren't forwarded.
+const EXCLUDED_CRATES: &[&str] = &[
+    "remote_logger",
+    "net",
+    "smoltcp",
+    "nic_buffers",
+    "nic_queues",
+    "e1000",
+    "ixgbe",
+    "mlx5",
+    "rtl8139",
+    "rtl8168",
+];
+
+/// The syslog facility for kernel messages.
+const FACILITY_KERN: u8 = 0;
+
+/// The configuration of the remote logger.
+#[derive(Clone, Debug)]
+pub struct Config {
+    /// The address and port of the syslog collector.
+    pub collector: IpEndpoint,
+    /// The host name that identifies this machine in each message.
+    pub hostname: String,
+    /// The maximum log level that is forwarded.
+    pub max_level: LevelFilter,
+    /// The maximum number of messages sent per second, on average.
+    pub max_rate: u32,
+    /// The maximum number of messages that can be sent at once
+    /// after no messages have been sent for a while.
+    pub burst: u32,
+    /// The maximum number of records held while they can't be sent.
+    pub buffer_capacity: usize,
+}
+
+impl Config {
+    /// Returns a configuration with default settings that sends to the given `collector`.
+    pub fn new(collector: IpEndpoint) -> Self {
+        Self {
+            collector,
+            hostname: "theseus".to_string(),
+            max_level: LevelFilter::Info,
+            max_rate: 100,
+            burst: 200,
+            buffer_capacity: 1024,
+        }
+    }
+}
+
+/// Statistics about forwarded records.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Statistics {
+    /// The number of records that were sent to the collector.
+    pub sent: u64,
+    /// The number of records that were dropped because the buffer was full.
+    pub dropped: u64,
+    /// The number of records currently waiting to be sent.
+    pub buffered: usize,
+}
+
+/// A log record that is waiting to be sent.
+struct QueuedRecord {
+    level: Level,
+    /// The time since boot at which the record was logged.
+    timestamp: Duration,
+    /// The name of the crate that logged the record.
+    app_name: String,
+    text: String,
+}
+
+struct Buffer {
+    records: VecDeque<QueuedRecord>,
+    capacity: usize,
+    max_level: LevelFilter,
+}
+
+struct State {
+    config: Option<Config>,
+    task_running: bool,
+}
+
+/// Records waiting to be sent, which may be accessed from interrupt context.
+static BUFFER: IrqSafeMutex<Buffer> = IrqSafeMutex::new(Buffer {
+    records: VecDeque::new(),
+    capacity: 0,
+    max_level: LevelFilter::Off,
+});
+
+static STATE: Mutex<State> = Mutex::new(State {
+    config: None,
+    task_running: false,
+});
+
+static SENT: AtomicU64 = AtomicU64::new(0);
+static DROPPED: AtomicU64 = AtomicU64::new(0);
+/// The number of dropped records that the collector hasn't been told about yet.
+static UNREPORTED_DROPS: AtomicU64 = AtomicU64::new(0);
+
+/// Starts forwarding log records using the given `config`.
+///
+/// If records are already being forwarded, the configuration is replaced,
+/// and records that are already buffered are kept.
+pub fn init(config: Config) -> Result<(), &'static str> {
+    if config.max_rate == 0 || config.burst == 0 {
+        return Err("the remote logger's rate limit must be non-zero");
+    }
+    if config.buffer_capacity == 0 {
+        return Err("the remote logger's buffer capacity must be non-zero");
+    }
+
+    let mut state = STATE.lock();
+    {
+        let mut buffer = BUFFER.lock();
+        buffer.capacity = config.buffer_capacity;
+        buffer.max_level = config.max_level;
+        while buffer.records.len() > buffer.capacity {
+            buffer.records.pop_front();
+            record_drop();
+        }
+    }
+    state.config = Some(config);
+
+    if !state.task_running {
+        spawn::new_task_builder(forward_loop, ())
+            .name("remote_logger".to_string())
+            .spawn()?;
+        state.task_running = true;
+    }
+    logger::set_record_sink(Some(sink));
+    Ok(())
+}
+
+/// Stops forwarding log records and discards any that are still buffered.
+pub fn stop() {
+    logger::set_record_sink(None);
+    let mut state = STATE.lock();
+    state.config = None;
+    let mut buffer = BUFFER.lock();
+    buffer.records.clear();
+    buffer.max_level = LevelFilter::Off;
+}
+
+/// Returns the current configuration, or `None` if records aren't being forwarded.
+pub fn config() -> Option<Config> {
+    STATE.lock().config.clone()
+}
+
+/// Returns statistics about forwarded records.
+pub fn statistics() -> Statistics {
+    Statistics {
+        sent: SENT.load(Ordering::Relaxed),
+        dropped: DROPPED.load(Ordering::Relaxed),
+        buffered: BUFFER.lock().records.len(),
+    }
+}
+
+fn record_drop() {
+    DROPPED.fetch_add(1, Ordering::Relaxed);
+    UNREPORTED_DROPS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// The logger's record sink, which queues the given record to be sent.
+fn sink(record: &Record) {
+    let app_name = record.target().split("::").next().unwrap_or_default();
+    if EXCLUDED_CRATES.contains(&app_name) {
+        return;
+    }
+    // Don't block, since this record may have been logged while the buffer was already locked,
+    // e.g., by a heap allocation made while queueing another record.
+    let Some(mut buffer) = BUFFER.try_lock() else {
+        record_drop();
+        return;
+    };
+    if record.level() > buffer.max_level {
+        return;
+    }
+
+    if buffer.records.len() >= buffer.capacity {
+        buffer.records.pop_front();
+        record_drop();
+    }
+    buffer.records.push_back(QueuedRecord {
+        level: record.level(),
+        timestamp: Instant::ZERO.elapsed(),
+        app_name: app_name.to_string(),
+        text: format!(
+            "{}:{}: {}",
+            record.file().unwrap_or("??"),
+            record.line().unwrap_or(0),
+            record.args()
+        ),
+    });
+}
+
+/// A UDP socket that sends messages through a specific interface.
+struct Sender {
+    interface: Arc<NetworkInterface>,
+    socket: Socket<udp::Socket<'static>>,
+}
+
+impl Sender {
+    fn new(interface: Arc<NetworkInterface>) -> Result<Self, &'static str> {
+        let rx_buffer = PacketBuffer::new(vec![PacketMetadata::EMPTY; 1], vec![0; 0]);
+        let tx_buffer = PacketBuffer::new(
+            vec![PacketMetadata::EMPTY; NUM_SOCKET_PACKETS],
+            vec![0; NUM_SOCKET_PACKETS * MAX_MESSAGE_SIZE],
+        );
+        let socket = interface.clone().add_socket(udp::Socket::new(rx_buffer, tx_buffer));
+        socket
+            .lock()
+            .bind(net::get_ephemeral_port())
+            .map_err(|_| "failed to bind remote logger socket")?;
+        Ok(Self { interface, socket })
+    }
+}
+
+/// A token bucket that limits how many messages can be sent.
+struct RateLimiter {
+    tokens: u32,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    fn refill(&mut self, config: &Config) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill);
+        let new_tokens = elapsed.as_micros() * config.max_rate as u128 / 1_000_000;
+        if new_tokens > 0 {
+            self.tokens = self.tokens.saturating_add(new_tokens as u32).min(config.burst);
+            self.last_refill = now;
+        }
+    }
+}
+
+/// The entry point of the task that sends buffered records to the collector.
+fn forward_loop(_: ()) -> Result<(), &'static str> {
+    let mut sender: Option<Sender> = None;
+    let mut limiter = RateLimiter {
+        tokens: 0,
+        last_refill: Instant::now(),
+    };
+
+    loop {
+        let config = {
+            let mut state = STATE.lock();
+            match state.config.clone() {
+                Some(config) => config,
+                None => {
+                    state.task_running = false;
+                    return Ok(());
+                }
+            }
+        };
+
+        match (&sender, net::get_default_interface()) {
+            (Some(s), Some(i)) if Arc::ptr_eq(&s.interface, &i) => {}
+            (_, Some(i)) => sender = Sender::new(i).ok(),
+            (_, None) => sender = None,
+        }
+
+        limiter.refill(&config);
+        // Hold records in the buffer until there is an interface that is up to send them on.
+        if let Some(sender) = sender.as_ref().filter(|s| s.interface.is_up()) {
+            while limiter.tokens > 0 && sender.socket.lock().can_send() {
+                let message = match UNREPORTED_DROPS.swap(0, Ordering::Relaxed) {
+                    0 => match BUFFER.lock().records.pop_front() {
+                        Some(record) => format_message(&config, &record),
+                        None => break,
+                    },
+                    drops => format_message(&config, &QueuedRecord {
+                        level: Level::Warn,
+                        timestamp: Instant::ZERO.elapsed(),
+                        app_name: "remote_logger".to_string(),
+                        text: format!("{drops} log records were dropped"),
+                    }),
+                };
+                if sender.socket.lock().send_slice(message.as_bytes(), config.collector).is_err() {
+                    break;
+                }
+                limiter.tokens -= 1;
+                SENT.fetch_add(1, Ordering::Relaxed);
+            }
+            sender.interface.poll();
+        }
+
+        sleep::sleep(POLL_INTERVAL).map_err(|_| "remote logger task failed to sleep")?;
+    }
+}
+
+/// Formats the given `record` as an RFC 5424 syslog message.
+///
+/// The timestamp field is left empty, because there's no reliable wall-clock time;
+/// instead, the time since boot is prepended to the message text.
+fn format_message(config: &Config, record: &QueuedRecord) -> String {
+    let severity = match record.level {
+        Level::Error => 3,
+        Level::Warn => 4,
+        Level::Info => 6,
+        Level::Debug | Level::Trace => 7,
+    };
+    let mut message = String::new();
+    let _ = write!(
+        message,
+        "<{}>1 - {} {} - - - [{:>5}.{:06}] {}",
+        FACILITY_KERN * 8 + severity,
+        config.hostname,
+        record.app_name,
+        record.timestamp.as_secs(),
+        record.timestamp.subsec_micros(),
+        record.text,
+    );
+    if message.len() > MAX_MESSAGE_SIZE {
+        let mut end = MAX_MESSAGE_SIZE;
+        while !message.is_char_boundary(end) {
+            end -= 1;
+        }
+        message.truncate(end);
+    }
+    message
+}
@@ -61,6 +61,10 @@ impl Directory for RootDirectory {
         self.children.keys().cloned().collect()
     }
 
+    fn entries(&self) -> Vec<FileOrDir> {
+        self.children.values().cloned().collect()
+    }
+
     fn remove(&mut self, node: &FileOrDir) -> Option<FileOrDir> {
         // Prevents removal of root
         if let FileOrDir::Dir(dir) = node {
@@ -0,0 +1,384 @@
+//! Support for the Realtek RTL8139 family of fast ethernet NICs,
+//! which are common in older commodity hardware and are emulated by QEMU and VirtualBox.
+//!
+//! Unlike most other NICs, the RTL8139 doesn't use rings of DMA descriptors.
+//! Instead, it writes received packets back-to-back into a single DMA ring buffer,
+//! from which this driver copies each packet into a `ReceiveBuffer`,
+//! and it has 4 fixed transmit descriptors that are used in a round-robin fashion.
+//!
+//! The RTL8139 can only DMA to and from 32-bit physical addresses.
+//!
+//! MSI is used for interrupts if the device supports it, otherwise legacy INTx interrupts are used.
+
+#![no_std]
+#![feature(abi_x86_interrupt)]
+
+extern crate alloc;
+
+mod regs;
+
+use alloc::{collections::VecDeque, format, sync::Arc, vec::Vec};
+use interrupts::{eoi, InterruptNumber};
+use lazy_static::lazy_static;
+use log::{debug, error, info, warn};
+use memory::{create_contiguous_mapping, map_frame_range, BorrowedMappedPages, MappedPages, Mutable, PhysicalAddress, MMIO_FLAGS};
+use nic_buffers::{ReceiveBuffer, ReceivedFrame, TransmitBuffer};
+use pci::{PciConfigSpaceAccessMechanism, PciDevice};
+use regs::*;
+use spin::Once;
+use sync_irq::IrqSafeMutex;
+use x86_64::structures::idt::InterruptStackFrame;
+
+/// Vendor ID for Realtek
+pub const REALTEK_VEND: u16 = 0x10EC;
+/// Device ID for the RTL8139, including the QEMU and VirtualBox emulated NICs
+pub const RTL8139_DEV: u16 = 0x8139;
+
+/// The BAR that holds the memory-mapped registers; BAR0 holds the I/O port registers.
+const MMIO_BAR_INDEX: usize = 1;
+
+/// The size of the receive ring buffer, not including the extra space at its end.
+const RX_RING_SIZE: usize = 8192;
+/// The size of the memory allocated for the receive ring buffer.
+///
+/// This includes 16 bytes of padding and space for one maximum-sized packet
+/// written past the end of the ring, as we use the `RCR_WRAP` mode.
+const RX_RING_ALLOC_SIZE: usize = RX_RING_SIZE + 16 + 1500;
+/// The size of the header that the NIC writes before each packet in the receive ring buffer.
+const RX_HEADER_SIZE: usize = 4;
+/// The size in bytes of the frame check sequence at the end of each received packet.
+const FCS_LENGTH: usize = 4;
+/// The maximum length of a received packet, including its frame check sequence.
+const MAX_RX_PACKET_SIZE: usize = 1518;
+
+/// The number of transmit descriptors, which is fixed in hardware.
+const NUM_TX_DESC: usize = 4;
+/// The minimum length of an ethernet frame, which is not padded by the NIC.
+const MIN_TX_PACKET_SIZE: u16 = 60;
+
+/// The size of each receive buffer that received packets are copied into.
+const RX_BUFFER_SIZE_IN_BYTES: u16 = 2048;
+
+/// The interrupts that are enabled for this NIC.
+const ENABLED_INTERRUPTS: u16 = INT_ROK | INT_RER | INT_TOK | INT_TER | INT_RXOVW | INT_LINK_CHG | INT_FOVW;
+
+/// The single instance of the RTL8139 NIC.
+/// TODO: support multiple NICs, like all other NIC drivers.
+static RTL8139_NIC: Once<IrqSafeMutex<Rtl8139Nic>> = Once::new();
+
+/// Returns a reference to the Rtl8139Nic wrapped in a IrqSafeMutex,
+/// if it exists and has been initialized.
+pub fn get_rtl8139_nic() -> Option<&'static IrqSafeMutex<Rtl8139Nic>> {
+    RTL8139_NIC.get()
+}
+
+/// How many ReceiveBuffers are preallocated for this driver to use.
+const RX_BUFFER_POOL_SIZE: usize = 256;
+lazy_static! {
+    /// The pool of pre-allocated receive buffers that are used by the RTL8139 NIC
+    /// and temporarily given to higher layers in the networking stack.
+    static ref RX_BUFFER_POOL: mpmc::Queue<ReceiveBuffer> = mpmc::Queue::with_capacity(RX_BUFFER_POOL_SIZE);
+}
+
+/// Struct representing an RTL8139 network interface card.
+pub struct Rtl8139Nic {
+    /// The PCI device of this NIC.
+    pci_device: &'static PciDevice,
+    /// The interrupt vector number used by this device to trigger interrupts.
+    interrupt_num: Option<InterruptNumber>,
+    /// The actual MAC address burnt into the hardware of this NIC.
+    mac_hardware: [u8; 6],
+    /// The optional spoofed MAC address to use in place of `mac_hardware` when transmitting.
+    mac_spoofed: Option<[u8; 6]>,
+    /// The ring buffer that the NIC writes received packets into.
+    rx_ring: MappedPages,
+    /// The offset into `rx_ring` of the next packet to be read.
+    rx_offset: usize,
+    /// The queue of received Ethernet frames, ready for consumption by a higher layer.
+    received_frames: VecDeque<ReceivedFrame>,
+    /// The buffers currently being transmitted by each transmit descriptor,
+    /// which must be kept alive until the NIC has finished copying them.
+    tx_bufs: [Option<TransmitBuffer>; NUM_TX_DESC],
+    /// The index of the next transmit descriptor to use.
+    tx_cur: usize,
+    /// memory-mapped registers
+    regs: BorrowedMappedPages<Rtl8139Registers, Mutable>,
+    deferred_task: Option<task::JoinableTaskRef>,
+}
+
+impl Rtl8139Nic {
+    /// Initializes the new RTL8139 network interface card that is connected as the given PciDevice.
+    ///
+    /// `init_interrupts` must be called after the NIC has been registered with the `net` subsystem.
+    pub fn init(pci_device: &'static PciDevice) -> Result<&'static IrqSafeMutex<Rtl8139Nic>, &'static str> {
+        if RTL8139_NIC.is_completed() {
+            return Err("rtl8139: only one RTL8139 NIC is currently supported");
+        }
+        if (pci_device.bars[MMIO_BAR_INDEX] as u8 & 0x1) == PciConfigSpaceAccessMechanism::IoPort as u8 {
+            return Err("rtl8139::init(): BAR1 is of I/O type");
+        }
+        let mem_base = pci_device.determine_mem_base(MMIO_BAR_INDEX)?;
+
+        // set the bus mastering bit for this PciDevice, which allows it to use DMA
+        pci_device.pci_set_command_bus_master_bit();
+
+        let mut regs = map_frame_range(mem_base, core::mem::size_of::<Rtl8139Registers>(), MMIO_FLAGS)?
+            .into_borrowed_mut::<Rtl8139Registers>(0)
+            .map_err(|(_mp, err)| err)?;
+
+        // Power on the NIC, then reset it.
+        regs.config1.write(0);
+        Self::reset(&mut regs)?;
+        let mac_hardware = core::array::from_fn(|i| regs.idr[i].read());
+        debug!("rtl8139: read hardware MAC address: {:02x?}", mac_hardware);
+
+        init_rx_buf_pool()?;
+
+        let (rx_ring, rx_ring_paddr) = create_contiguous_mapping(RX_RING_ALLOC_SIZE, MMIO_FLAGS)?;
+        regs.rbstart.write(dma_address(rx_ring_paddr)?);
+        // Mask all interrupts until `init_interrupts()` is invoked.
+        regs.imr.write(0);
+        regs.isr.write(0xFFFF);
+        regs.rcr.write(RCR_MXDMA_UNLIMITED | RCR_WRAP | RCR_AB | RCR_AM | RCR_APM);
+        regs.tcr.write(TCR_IFG_NORMAL | TCR_MXDMA_2048);
+        regs.cr.write(CR_RE | CR_TE);
+        regs.capr.write(capr_value(0));
+
+        let nic = Rtl8139Nic {
+            pci_device,
+            interrupt_num: None,
+            mac_hardware,
+            mac_spoofed: None,
+            rx_ring,
+            rx_offset: 0,
+            received_frames: VecDeque::new(),
+            tx_bufs: Default::default(),
+            tx_cur: 0,
+            regs,
+            deferred_task: None,
+        };
+        Ok(RTL8139_NIC.call_once(|| IrqSafeMutex::new(nic)))
+    }
+
+    /// Resets the NIC and waits for the reset to complete.
+    fn reset(regs: &mut Rtl8139Registers) -> Result<(), &'static str> {
+        const RESET_POLL_ATTEMPTS: usize = 100_000;
+        regs.cr.write(CR_RST);
+        for _ in 0..RESET_POLL_ATTEMPTS {
+            if (regs.cr.read() & CR_RST) == 0 {
+                return Ok(());
+            }
+            core::hint::spin_loop();
+        }
+        Err("rtl8139: timed out waiting for the NIC to reset")
+    }
+
+    /// Initializes the interrupt handler and enables interrupts for this NIC,
+    /// using MSI if the device supports it.
+    ///
+    /// The provided `interface` must be the network interface associated with this NIC.
+    /// This interface will be polled in a deferred task upon an interrupt being triggered
+    /// for a received packet.
+    pub fn init_interrupts(
+        &mut self,
+        interface: Arc<net::NetworkInterface>,
+    ) -> Result<(), &'static str> {
+        let (interrupt_num, deferred_task) = if self.pci_device.modern_interrupt_support().msi {
+            let interrupt_num = interrupts::register_msi_interrupt(rtl8139_handler)?;
+            let bsp = cpu::bootstrap_cpu().ok_or("rtl8139: couldn't get bootstrap CPU")?;
+            self.pci_device.pci_enable_msi(bsp.value() as u8, interrupt_num)?;
+            self.pci_device.pci_set_intx_disable_bit(true);
+            let deferred_task = deferred_interrupt_tasks::spawn_deferred_task(
+                poll_interface,
+                interface,
+                Some(format!("rtl8139_deferred_task_msi_{:#X}", interrupt_num)),
+            )?;
+            info!("rtl8139: using MSI interrupt {:#X}", interrupt_num);
+            (interrupt_num, deferred_task)
+        } else {
+            let interrupt_num = match self.pci_device.pci_get_intx_info() {
+                Ok((Some(irq), _pin)) => irq + interrupts::IRQ_BASE_OFFSET,
+                _ => return Err("rtl8139: PCI device had no interrupt number (IRQ vector)"),
+            };
+            let deferred_task = deferred_interrupt_tasks::register_interrupt_handler(
+                interrupt_num,
+                rtl8139_handler,
+                poll_interface,
+                interface,
+                Some(format!("rtl8139_deferred_task_irq_{:#X}", interrupt_num)),
+            )
+            .map_err(|error| {
+                error!("error registering rtl8139 handler: {:?}", error);
+                "rtl8139 interrupt number was already in use! Sharing IRQs is currently unsupported."
+            })?;
+            (interrupt_num, deferred_task)
+        };
+        self.interrupt_num = Some(interrupt_num);
+        self.deferred_task = Some(deferred_task);
+
+        self.regs.isr.write(0xFFFF);
+        self.regs.imr.write(ENABLED_INTERRUPTS);
+        Ok(())
+    }
+
+    pub fn spoof_mac(&mut self, spoofed_mac_addr: [u8; 6]) {
+        self.mac_spoofed = Some(spoofed_mac_addr);
+    }
+
+    /// Returns whether the link is currently up.
+    pub fn link_up(&self) -> bool {
+        (self.regs.msr.read() & MSR_LINKB) == 0
+    }
+
+    /// Copies all packets out of the receive ring buffer into the queue of received frames.
+    fn poll_rx_ring(&mut self) -> Result<(), &'static str> {
+        while (self.regs.cr.read() & CR_BUFE) == 0 {
+            let header = self.rx_ring.as_slice::<u16>(self.rx_offset, 2)?;
+            let (status, length) = (header[0], header[1] as usize);
+            if (status & RX_HEADER_ROK) == 0 || !(FCS_LENGTH..=MAX_RX_PACKET_SIZE).contains(&length) {
+                warn!("rtl8139: received bad packet (status {:#X}, length {}), resetting receiver", status, length);
+                return self.reset_rx();
+            }
+
+            let packet = self.rx_ring.as_slice::<u8>(self.rx_offset + RX_HEADER_SIZE, length - FCS_LENGTH)?;
+            let mut rx_buf = match RX_BUFFER_POOL.pop() {
+                Some(rx_buf) => rx_buf,
+                None => {
+                    warn!("NIC RX BUF POOL WAS EMPTY.... reallocating! This means that no task is consuming the accumulated received ethernet frames.");
+                    new_receive_buffer()?
+                }
+            };
+            rx_buf.set_length(packet.len() as u16)?;
+            rx_buf.copy_from_slice(packet);
+            self.received_frames.push_back(ReceivedFrame(Vec::from([rx_buf])));
+
+            // Each packet (including its header) starts at a 4-byte aligned offset.
+            self.rx_offset = (self.rx_offset + RX_HEADER_SIZE + length + 3) & !3;
+            self.rx_offset %= RX_RING_SIZE;
+            self.regs.capr.write(capr_value(self.rx_offset));
+        }
+        Ok(())
+    }
+
+    /// Resets the receiver after an error, discarding all packets in the receive ring buffer.
+    fn reset_rx(&mut self) -> Result<(), &'static str> {
+        self.regs.cr.write(CR_TE);
+        self.rx_offset = 0;
+        self.regs.cr.write(CR_RE | CR_TE);
+        self.regs.rcr.write(RCR_MXDMA_UNLIMITED | RCR_WRAP | RCR_AB | RCR_AM | RCR_APM);
+        self.regs.capr.write(capr_value(0));
+        Ok(())
+    }
+
+    /// The main interrupt handling routine for the RTL8139 NIC.
+    /// This should be invoked from the actual interrupt handler entry point.
+    fn handle_interrupt(&mut self) -> Result<(), &'static str> {
+        let status = self.regs.isr.read();
+        // Acknowledge all interrupts that we're about to handle.
+        self.regs.isr.write(status);
+
+        if (status & INT_LINK_CHG) == INT_LINK_CHG {
+            debug!("rtl8139::handle_interrupt(): link status changed, link up: {}", self.link_up());
+        }
+        if (status & (INT_RER | INT_TER | INT_RXOVW | INT_FOVW)) != 0 {
+            error!("rtl8139::handle_interrupt(): rx/tx error, status: {:#X}", status);
+        }
+        if (status & (INT_ROK | INT_RXOVW | INT_FOVW)) != 0 {
+            self.poll_rx_ring()?;
+            if let Some(ref deferred_task) = self.deferred_task {
+                let _ = deferred_task.unblock();
+            } else {
+                error!("rtl8139::handle_interrupt(): no deferred task");
+            }
+        }
+        Ok(())
+    }
+}
+
+impl net::NetworkDevice for Rtl8139Nic {
+    fn send(&mut self, buf: TransmitBuffer) {
+        let cur = self.tx_cur;
+        // Wait for the NIC to finish with the previous packet sent using this descriptor.
+        if self.tx_bufs[cur].take().is_some() {
+            while (self.regs.tsd[cur].read() & TSD_OWN) == 0 {
+                core::hint::spin_loop();
+            }
+        }
+        let paddr = match dma_address(buf.phys_addr()) {
+            Ok(paddr) => paddr,
+            Err(e) => {
+                error!("rtl8139: dropping packet: {}", e);
+                return;
+            }
+        };
+        let length = buf.length().max(MIN_TX_PACKET_SIZE) as u32;
+        self.regs.tsad[cur].write(paddr);
+        // Writing the length also clears the OWN bit, which starts the transmission.
+        self.regs.tsd[cur].write(length & TSD_SIZE_MASK);
+        self.tx_bufs[cur] = Some(buf);
+        self.tx_cur = (cur + 1) % NUM_TX_DESC;
+    }
+
+    fn receive(&mut self) -> Option<ReceivedFrame> {
+        self.received_frames.pop_front()
+    }
+
+    /// Returns the MAC address.
+    fn mac_address(&self) -> [u8; 6] {
+        self.mac_spoofed.unwrap_or(self.mac_hardware)
+    }
+}
+
+/// Returns the value of the CAPR register that corresponds to the given receive ring `offset`.
+///
+/// For unknown reasons, the NIC expects CAPR to be 16 bytes behind the actual read offset.
+fn capr_value(offset: usize) -> u16 {
+    (offset as u16).wrapping_sub(16)
+}
+
+/// Returns the given physical address as a 32-bit address that the NIC can DMA to and from.
+fn dma_address(paddr: PhysicalAddress) -> Result<u32, &'static str> {
+    u32::try_from(paddr.value()).map_err(|_| "rtl8139: buffer is above the 4 GiB physical address limit")
+}
+
+/// Allocates a new receive buffer that belongs to the receive buffer pool.
+fn new_receive_buffer() -> Result<ReceiveBuffer, &'static str> {
+    let (mp, phys_addr) = create_contiguous_mapping(RX_BUFFER_SIZE_IN_BYTES as usize, MMIO_FLAGS)?;
+    ReceiveBuffer::new(mp, phys_addr, RX_BUFFER_SIZE_IN_BYTES, &RX_BUFFER_POOL)
+}
+
+/// Fills the receive buffer pool.
+///
+/// The NIC doesn't DMA into receive buffers directly, so this doesn't use
+/// `nic_initialization::init_rx_buf_pool`.
+fn init_rx_buf_pool() -> Result<(), &'static str> {
+    for _ in 0..RX_BUFFER_POOL_SIZE {
+        if RX_BUFFER_POOL.push(new_receive_buffer()?).is_err() {
+            return Err("rtl8139: rx buffer pool is full");
+        }
+    }
+    Ok(())
+}
+
+extern "x86-interrupt" fn rtl8139_handler(_stack_frame: InterruptStackFrame) {
+    if let Some(nic_ref) = RTL8139_NIC.get() {
+        let mut nic = nic_ref.lock();
+        if let Err(e) = nic.handle_interrupt() {
+            error!("rtl8139_handler(): error handling interrupt: {:?}", e);
+        }
+        if let Some(interrupt_num) = nic.interrupt_num {
+            eoi(interrupt_num);
+        }
+    } else {
+        error!("BUG: rtl8139_handler(): RTL8139 NIC hasn't yet been initialized!");
+    }
+}
+
+/// This function is used as a deferred interrupt task.
+///
+/// After processing the interrupt, the network interface associated with the NIC will be
+/// polled to process the received data.
+fn poll_interface(interface: &Arc<net::NetworkInterface>) -> Result<(), ()> {
+    interface.poll();
+    Ok(())
+}
@@ -0,0 +1,99 @@
+//! The memory-mapped registers of the RTL8139 and the values written to them.
+
+use volatile::{Volatile, ReadOnly};
+use zerocopy::FromBytes;
+
+/// The layout in memory of the RTL8139 registers.
+#[derive(FromBytes)]
+#[repr(C)]
+pub struct Rtl8139Registers {
+    /// The MAC address
+    pub idr:                        [ReadOnly<u8>; 6],      // 0x0 - 0x5
+    _padding0:                      [u8; 10],               // 0x6 - 0xF
+
+    /// Transmit status of each of the 4 transmit descriptors
+    pub tsd:                        [Volatile<u32>; 4],     // 0x10 - 0x1F
+    /// Transmit start address of each of the 4 transmit descriptors
+    pub tsad:                       [Volatile<u32>; 4],     // 0x20 - 0x2F
+    /// Receive buffer start address
+    pub rbstart:                    Volatile<u32>,          // 0x30
+    _padding1:                      [u8; 3],                // 0x34 - 0x36
+
+    /// Command register
+    pub cr:                         Volatile<u8>,           // 0x37
+    /// Current address of packet read, i.e., how far the driver has read from the receive buffer
+    pub capr:                       Volatile<u16>,          // 0x38
+    /// Current buffer address, i.e., how far the NIC has written into the receive buffer
+    pub cbr:                        ReadOnly<u16>,          // 0x3A
+    /// Interrupt mask register
+    pub imr:                        Volatile<u16>,          // 0x3C
+    /// Interrupt status register, in which bits are cleared by writing 1 to them
+    pub isr:                        Volatile<u16>,          // 0x3E
+    /// Transmit configuration register
+    pub tcr:                        Volatile<u32>,          // 0x40
+    /// Receive configuration register
+    pub rcr:                        Volatile<u32>,          // 0x44
+    _padding2:                      [u8; 10],               // 0x48 - 0x51
+
+    /// Configuration register 1, used to power on the NIC
+    pub config1:                    Volatile<u8>,           // 0x52
+    _padding3:                      [u8; 5],                // 0x53 - 0x57
+
+    /// Media status register
+    pub msr:                        ReadOnly<u8>,           // 0x58
+    _padding4:                      [u8; 167],              // 0x59 - 0xFF
+}
+
+const _: () = assert!(core::mem::size_of::<Rtl8139Registers>() == 256);
+
+/// CR: reset the NIC, which is cleared by HW when complete
+pub const CR_RST:                   u8 = 1 << 4;
+/// CR: receiver enable
+pub const CR_RE:                    u8 = 1 << 3;
+/// CR: transmitter enable
+pub const CR_TE:                    u8 = 1 << 2;
+/// CR: the receive buffer is empty
+pub const CR_BUFE:                  u8 = 1 << 0;
+
+/// TSD: the NIC has finished copying the packet into its FIFO, so the buffer can be reused
+pub const TSD_OWN:                  u32 = 1 << 13;
+/// The bits of TSD that hold the packet length
+pub const TSD_SIZE_MASK:            u32 = 0x1FFF;
+
+/// RCR: accept broadcast packets
+pub const RCR_AB:                   u32 = 1 << 3;
+/// RCR: accept multicast packets
+pub const RCR_AM:                   u32 = 1 << 2;
+/// RCR: accept packets with a destination address matching our MAC address
+pub const RCR_APM:                  u32 = 1 << 1;
+/// RCR: packets that would overflow the end of the receive buffer are written past its end
+/// instead of wrapping around to its beginning, such that each packet is contiguous
+pub const RCR_WRAP:                 u32 = 1 << 7;
+/// RCR: unlimited DMA burst size
+pub const RCR_MXDMA_UNLIMITED:      u32 = 0b111 << 8;
+
+/// TCR: DMA burst size of 2048 bytes
+pub const TCR_MXDMA_2048:           u32 = 0b111 << 8;
+/// TCR: the standard interframe gap time
+pub const TCR_IFG_NORMAL:           u32 = 0b11 << 24;
+
+/// MSR: the link is down
+pub const MSR_LINKB:                u8 = 1 << 2;
+
+/// Rx packet header status: the packet was received OK
+pub const RX_HEADER_ROK:            u16 = 1 << 0;
+
+/// Interrupt: receive OK
+pub const INT_ROK:                  u16 = 1 << 0;
+/// Interrupt: receive error
+pub const INT_RER:                  u16 = 1 << 1;
+/// Interrupt: transmit OK
+pub const INT_TOK:                  u16 = 1 << 2;
+/// Interrupt: transmit error
+pub const INT_TER:                  u16 = 1 << 3;
+/// Interrupt: receive buffer overflow
+pub const INT_RXOVW:                u16 = 1 << 4;
+/// Interrupt: link status change
+pub const INT_LINK_CHG:             u16 = 1 << 5;
+/// Interrupt: Rx FIFO overflow
+pub const INT_FOVW:                 u16 = 1 << 6;
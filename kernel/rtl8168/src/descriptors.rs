@@ -0,0 +1,109 @@
+//! The DMA descriptors used by the RTL8168 in C+ mode.
+//!
+//! Both receive and transmit descriptors have the same 16-byte layout.
+//! They implement the descriptor traits from `intel_ethernet` such that
+//! the generic receive and transmit queues in `nic_queues` can be used with them.
+//!
+//! Unlike Intel NICs, the RTL8168 has no head/tail registers; instead, each descriptor
+//! has an ownership bit that is set when it is handed to the NIC and cleared by the NIC
+//! once it is done with it, and the last descriptor in a ring is marked by an end-of-ring bit.
+
+use memory::PhysicalAddress;
+use volatile::Volatile;
+use zerocopy::FromBytes;
+use intel_ethernet::descriptors::{RxDescriptor, TxDescriptor};
+
+/// Descriptor bit: the descriptor is owned by the NIC
+const DESC_OWN:                     u32 = 1 << 31;
+/// Descriptor bit: this is the last descriptor in the ring
+const DESC_EOR:                     u32 = 1 << 30;
+/// Descriptor bit: this is the first descriptor of a packet
+const DESC_FS:                      u32 = 1 << 29;
+/// Descriptor bit: this is the last descriptor of a packet
+const DESC_LS:                      u32 = 1 << 28;
+/// The bits of a descriptor that hold the buffer or packet length
+const DESC_LENGTH_MASK:             u32 = 0x3FFF;
+
+/// The size in bytes of the frame check sequence at the end of each received packet.
+const FCS_LENGTH:                   u64 = 4;
+
+/// The size of each receive buffer, which is given to the NIC in each receive descriptor.
+pub const RX_BUFFER_SIZE_IN_BYTES:  u16 = 2048;
+
+/// A receive or transmit descriptor.
+#[derive(FromBytes)]
+#[repr(C)]
+pub struct Rtl8168Descriptor {
+    /// The ownership, end-of-ring, and packet boundary bits, along with the length
+    opts1:      Volatile<u32>,
+    /// VLAN tag and checksum offload information, which is currently unused
+    opts2:      Volatile<u32>,
+    /// The starting physical address of the packet buffer
+    phys_addr:  Volatile<u64>,
+}
+
+impl Rtl8168Descriptor {
+    /// Marks this descriptor as the last one in its ring.
+    pub fn set_end_of_ring(&mut self) {
+        let opts1 = self.opts1.read();
+        self.opts1.write(opts1 | DESC_EOR);
+    }
+
+    fn end_of_ring_bit(&self) -> u32 {
+        self.opts1.read() & DESC_EOR
+    }
+}
+
+impl RxDescriptor for Rtl8168Descriptor {
+    fn init(&mut self, packet_buffer_address: PhysicalAddress) {
+        self.phys_addr.write(packet_buffer_address.value() as u64);
+        self.opts2.write(0);
+        self.opts1.write(DESC_OWN | RX_BUFFER_SIZE_IN_BYTES as u32);
+    }
+
+    fn set_packet_address(&mut self, packet_buffer_address: PhysicalAddress) {
+        self.phys_addr.write(packet_buffer_address.value() as u64);
+    }
+
+    /// Hands this descriptor back to the NIC.
+    fn reset_status(&mut self) {
+        self.opts2.write(0);
+        self.opts1.write(DESC_OWN | self.end_of_ring_bit() | RX_BUFFER_SIZE_IN_BYTES as u32);
+    }
+
+    fn descriptor_done(&self) -> bool {
+        (self.opts1.read() & DESC_OWN) == 0
+    }
+
+    fn end_of_packet(&self) -> bool {
+        (self.opts1.read() & DESC_LS) == DESC_LS
+    }
+
+    /// Returns the length of the received packet, excluding its frame check sequence.
+    fn length(&self) -> u64 {
+        ((self.opts1.read() & DESC_LENGTH_MASK) as u64).saturating_sub(FCS_LENGTH)
+    }
+}
+
+impl TxDescriptor for Rtl8168Descriptor {
+    fn init(&mut self) {
+        self.phys_addr.write(0);
+        self.opts2.write(0);
+        self.opts1.write(0);
+    }
+
+    fn send(&mut self, transmit_buffer_addr: PhysicalAddress, transmit_buffer_length: u16) {
+        self.phys_addr.write(transmit_buffer_addr.value() as u64);
+        self.opts2.write(0);
+        self.opts1.write(
+            DESC_OWN | self.end_of_ring_bit() | DESC_FS | DESC_LS
+            | (transmit_buffer_length as u32 & DESC_LENGTH_MASK)
+        );
+    }
+
+    fn wait_for_packet_tx(&self) {
+        while (self.opts1.read() & DESC_OWN) == DESC_OWN {
+            core::hint::spin_loop();
+        }
+    }
+}
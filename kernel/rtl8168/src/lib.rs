@@ -0,0 +1,378 @@
+//! Support for the Realtek RTL8168/RTL8169 family of gigabit ethernet NICs,
+//! which are found on many commodity motherboards.
+//!
+//! The NIC is operated in C+ mode, in which packets are received and transmitted
+//! via rings of DMA descriptors, similar to Intel NICs.
+//! Thus, this driver uses the generic receive and transmit queues from `nic_queues`.
+//!
+//! MSI is used for interrupts if the device supports it, otherwise legacy INTx interrupts are used.
+
+#![no_std]
+#![feature(abi_x86_interrupt)]
+
+extern crate alloc;
+
+mod descriptors;
+mod regs;
+
+use alloc::{collections::VecDeque, format, sync::Arc, vec::Vec};
+use descriptors::{Rtl8168Descriptor, RX_BUFFER_SIZE_IN_BYTES};
+use interrupts::{eoi, InterruptNumber};
+use lazy_static::lazy_static;
+use log::{debug, error, info};
+use memory::{map_frame_range, BorrowedMappedPages, BorrowedSliceMappedPages, Mutable, MMIO_FLAGS};
+use nic_buffers::{ReceiveBuffer, ReceivedFrame, TransmitBuffer};
+use nic_initialization::{init_rx_buf_pool, init_rx_queue, init_tx_queue};
+use nic_queues::{RxQueue, RxQueueRegisters, TxQueue, TxQueueRegisters};
+use pci::{PciConfigSpaceAccessMechanism, PciDevice};
+use regs::*;
+use spin::{Mutex, Once};
+use sync_irq::IrqSafeMutex;
+use x86_64::structures::idt::InterruptStackFrame;
+
+/// Vendor ID for Realtek
+pub const REALTEK_VEND: u16 = 0x10EC;
+/// Device IDs of the NICs supported by this driver: the RTL8168, RTL8161, and RTL8169.
+pub const RTL8168_DEVS: [u16; 3] = [0x8168, 0x8161, 0x8169];
+
+/// The BAR that holds the memory-mapped registers; BAR0 holds the I/O port registers.
+const MMIO_BAR_INDEX: usize = 2;
+
+const RTL8168_NUM_RX_DESC: u16 = 64;
+const RTL8168_NUM_TX_DESC: u16 = 64;
+
+/// The maximum transmit packet size, in units of 128 bytes.
+const MAX_TX_PACKET_SIZE: u8 = 0x3B;
+
+/// The interrupts that are enabled for this NIC.
+const ENABLED_INTERRUPTS: u16 = INT_ROK | INT_RER | INT_TOK | INT_TER | INT_RDU | INT_LINK_CHG | INT_FOVW;
+
+/// The single instance of the RTL8168 NIC.
+/// TODO: support multiple NICs, like all other NIC drivers.
+static RTL8168_NIC: Once<IrqSafeMutex<Rtl8168Nic>> = Once::new();
+
+/// Returns a reference to the Rtl8168Nic wrapped in a IrqSafeMutex,
+/// if it exists and has been initialized.
+pub fn get_rtl8168_nic() -> Option<&'static IrqSafeMutex<Rtl8168Nic>> {
+    RTL8168_NIC.get()
+}
+
+/// How many ReceiveBuffers are preallocated for this driver to use.
+const RX_BUFFER_POOL_SIZE: usize = 256;
+lazy_static! {
+    /// The pool of pre-allocated receive buffers that are used by the RTL8168 NIC
+    /// and temporarily given to higher layers in the networking stack.
+    static ref RX_BUFFER_POOL: mpmc::Queue<ReceiveBuffer> = mpmc::Queue::with_capacity(RX_BUFFER_POOL_SIZE);
+}
+
+/// The registers of the NIC, which are shared by the NIC and its receive and transmit queues
+/// because they're all within a single page.
+type SharedRegisters = Arc<Mutex<BorrowedMappedPages<Rtl8168Registers, Mutable>>>;
+
+/// Implements the `RxQueueRegisters` trait, which is required to store the registers in an `RxQueue` object.
+///
+/// The RTL8168 has no ring length, head, or tail registers, so setting them does nothing.
+struct Rtl8168RxQueueRegisters(SharedRegisters);
+
+impl RxQueueRegisters for Rtl8168RxQueueRegisters {
+    fn set_rdbal(&mut self, value: u32) {
+        self.0.lock().rdsar_lo.write(value);
+    }
+    fn set_rdbah(&mut self, value: u32) {
+        self.0.lock().rdsar_hi.write(value);
+    }
+    fn set_rdlen(&mut self, _value: u32) { }
+    fn set_rdh(&mut self, _value: u32) { }
+    fn set_rdt(&mut self, _value: u32) { }
+}
+
+/// Implements the `TxQueueRegisters` trait, which is required to store the registers in a `TxQueue` object.
+///
+/// The RTL8168 has no ring length, head, or tail registers; instead, setting the tail
+/// notifies the NIC that there are new packets in the transmit descriptor ring.
+struct Rtl8168TxQueueRegisters(SharedRegisters);
+
+impl TxQueueRegisters for Rtl8168TxQueueRegisters {
+    fn set_tdbal(&mut self, value: u32) {
+        self.0.lock().tnpds_lo.write(value);
+    }
+    fn set_tdbah(&mut self, value: u32) {
+        self.0.lock().tnpds_hi.write(value);
+    }
+    fn set_tdlen(&mut self, _value: u32) { }
+    fn set_tdh(&mut self, _value: u32) { }
+    fn set_tdt(&mut self, _value: u32) {
+        self.0.lock().tppoll.write(TPPOLL_NPQ);
+    }
+}
+
+/// Struct representing an RTL8168 network interface card.
+pub struct Rtl8168Nic {
+    /// The PCI device of this NIC.
+    pci_device: &'static PciDevice,
+    /// The interrupt vector number used by this device to trigger interrupts.
+    interrupt_num: Option<InterruptNumber>,
+    /// The actual MAC address burnt into the hardware of this NIC.
+    mac_hardware: [u8; 6],
+    /// The optional spoofed MAC address to use in place of `mac_hardware` when transmitting.
+    mac_spoofed: Option<[u8; 6]>,
+    /// Receive queue with descriptors
+    rx_queue: RxQueue<Rtl8168RxQueueRegisters, Rtl8168Descriptor>,
+    /// Transmit queue with descriptors
+    tx_queue: TxQueue<Rtl8168TxQueueRegisters, Rtl8168Descriptor>,
+    /// memory-mapped registers
+    regs: SharedRegisters,
+    deferred_task: Option<task::JoinableTaskRef>,
+}
+
+impl Rtl8168Nic {
+    /// Initializes the new RTL8168 network interface card that is connected as the given PciDevice.
+    ///
+    /// `init_interrupts` must be called after the NIC has been registered with the `net` subsystem.
+    pub fn init(pci_device: &'static PciDevice) -> Result<&'static IrqSafeMutex<Rtl8168Nic>, &'static str> {
+        if RTL8168_NIC.is_completed() {
+            return Err("rtl8168: only one RTL8168 NIC is currently supported");
+        }
+        if (pci_device.bars[MMIO_BAR_INDEX] as u8 & 0x1) == PciConfigSpaceAccessMechanism::IoPort as u8 {
+            return Err("rtl8168::init(): BAR2 is of I/O type");
+        }
+        let mem_base = pci_device.determine_mem_base(MMIO_BAR_INDEX)?;
+
+        // set the bus mastering bit for this PciDevice, which allows it to use DMA
+        pci_device.pci_set_command_bus_master_bit();
+
+        let mut regs = map_frame_range(mem_base, core::mem::size_of::<Rtl8168Registers>(), MMIO_FLAGS)?
+            .into_borrowed_mut::<Rtl8168Registers>(0)
+            .map_err(|(_mp, err)| err)?;
+
+        Self::reset(&mut regs)?;
+        let mac_hardware = core::array::from_fn(|i| regs.idr[i].read());
+        debug!("rtl8168: read hardware MAC address: {:02x?}", mac_hardware);
+
+        // The configuration registers must be unlocked before they can be written to,
+        // and the transmitter must be enabled before configuring it.
+        regs.cr9346.write(CR9346_UNLOCK);
+        regs.cr.write(CR_TE);
+        regs.rcr.write(RCR_RXFTH_NONE | RCR_MXDMA_UNLIMITED | RCR_AB | RCR_AM | RCR_APM);
+        regs.tcr.write(TCR_IFG_NORMAL | TCR_MXDMA_UNLIMITED);
+        regs.rms.write(RX_BUFFER_SIZE_IN_BYTES);
+        regs.mtps.write(MAX_TX_PACKET_SIZE);
+        // Mask all interrupts until `init_interrupts()` is invoked.
+        regs.imr.write(0);
+        regs.isr.write(0xFFFF);
+
+        let regs = Arc::new(Mutex::new(regs));
+        let mut rx_registers = Rtl8168RxQueueRegisters(regs.clone());
+        let mut tx_registers = Rtl8168TxQueueRegisters(regs.clone());
+
+        // initialize the buffer pool
+        init_rx_buf_pool(RX_BUFFER_POOL_SIZE, RX_BUFFER_SIZE_IN_BYTES, &RX_BUFFER_POOL)?;
+
+        let (rx_descs, rx_buffers) = Self::rx_init(&mut rx_registers)?;
+        let rxq = RxQueue {
+            id: 0,
+            regs: rx_registers,
+            rx_descs,
+            num_rx_descs: RTL8168_NUM_RX_DESC,
+            rx_cur: 0,
+            rx_bufs_in_use: rx_buffers,
+            rx_buffer_size_bytes: RX_BUFFER_SIZE_IN_BYTES,
+            received_frames: VecDeque::new(),
+            cpu_id: None,
+            rx_buffer_pool: &RX_BUFFER_POOL,
+            filter_num: None
+        };
+
+        let tx_descs = Self::tx_init(&mut tx_registers)?;
+        let txq = TxQueue {
+            id: 0,
+            regs: tx_registers,
+            tx_descs,
+            num_tx_descs: RTL8168_NUM_TX_DESC,
+            tx_cur: 0,
+            cpu_id: None,
+        };
+
+        {
+            let mut regs = regs.lock();
+            regs.cr.write(CR_RE | CR_TE);
+            regs.cr9346.write(CR9346_LOCK);
+        }
+
+        let nic = Rtl8168Nic {
+            pci_device,
+            interrupt_num: None,
+            mac_hardware,
+            mac_spoofed: None,
+            rx_queue: rxq,
+            tx_queue: txq,
+            regs,
+            deferred_task: None,
+        };
+        Ok(RTL8168_NIC.call_once(|| IrqSafeMutex::new(nic)))
+    }
+
+    /// Resets the NIC and waits for the reset to complete.
+    fn reset(regs: &mut Rtl8168Registers) -> Result<(), &'static str> {
+        const RESET_POLL_ATTEMPTS: usize = 100_000;
+        regs.cr.write(CR_RST);
+        for _ in 0..RESET_POLL_ATTEMPTS {
+            if (regs.cr.read() & CR_RST) == 0 {
+                return Ok(());
+            }
+            core::hint::spin_loop();
+        }
+        Err("rtl8168: timed out waiting for the NIC to reset")
+    }
+
+    /// Initializes the ring of receive descriptors and their corresponding receive buffers,
+    /// and returns a tuple including both of them.
+    fn rx_init(
+        rx_regs: &mut Rtl8168RxQueueRegisters,
+    ) -> Result<(
+        BorrowedSliceMappedPages<Rtl8168Descriptor, Mutable>,
+        Vec<ReceiveBuffer>
+    ), &'static str> {
+        let (mut rx_descs, rx_bufs_in_use) = init_rx_queue(
+            RTL8168_NUM_RX_DESC as usize,
+            &RX_BUFFER_POOL,
+            RX_BUFFER_SIZE_IN_BYTES as usize,
+            rx_regs,
+        )?;
+        rx_descs.last_mut().ok_or("rtl8168: no rx descriptors")?.set_end_of_ring();
+        Ok((rx_descs, rx_bufs_in_use))
+    }
+
+    /// Initializes the ring of transmit descriptors and returns it.
+    fn tx_init(
+        tx_regs: &mut Rtl8168TxQueueRegisters,
+    ) -> Result<BorrowedSliceMappedPages<Rtl8168Descriptor, Mutable>, &'static str> {
+        let mut tx_descs = init_tx_queue(RTL8168_NUM_TX_DESC as usize, tx_regs)?;
+        tx_descs.last_mut().ok_or("rtl8168: no tx descriptors")?.set_end_of_ring();
+        Ok(tx_descs)
+    }
+
+    /// Initializes the interrupt handler and enables interrupts for this NIC,
+    /// using MSI if the device supports it.
+    ///
+    /// The provided `interface` must be the network interface associated with this NIC.
+    /// This interface will be polled in a deferred task upon an interrupt being triggered
+    /// for a received packet.
+    pub fn init_interrupts(
+        &mut self,
+        interface: Arc<net::NetworkInterface>,
+    ) -> Result<(), &'static str> {
+        let (interrupt_num, deferred_task) = if self.pci_device.modern_interrupt_support().msi {
+            let interrupt_num = interrupts::register_msi_interrupt(rtl8168_handler)?;
+            let bsp = cpu::bootstrap_cpu().ok_or("rtl8168: couldn't get bootstrap CPU")?;
+            self.pci_device.pci_enable_msi(bsp.value() as u8, interrupt_num)?;
+            self.pci_device.pci_set_intx_disable_bit(true);
+            let deferred_task = deferred_interrupt_tasks::spawn_deferred_task(
+                poll_interface,
+                interface,
+                Some(format!("rtl8168_deferred_task_msi_{:#X}", interrupt_num)),
+            )?;
+            info!("rtl8168: using MSI interrupt {:#X}", interrupt_num);
+            (interrupt_num, deferred_task)
+        } else {
+            let interrupt_num = match self.pci_device.pci_get_intx_info() {
+                Ok((Some(irq), _pin)) => irq + interrupts::IRQ_BASE_OFFSET,
+                _ => return Err("rtl8168: PCI device had no interrupt number (IRQ vector)"),
+            };
+            let deferred_task = deferred_interrupt_tasks::register_interrupt_handler(
+                interrupt_num,
+                rtl8168_handler,
+                poll_interface,
+                interface,
+                Some(format!("rtl8168_deferred_task_irq_{:#X}", interrupt_num)),
+            )
+            .map_err(|error| {
+                error!("error registering rtl8168 handler: {:?}", error);
+                "rtl8168 interrupt number was already in use! Sharing IRQs is currently unsupported."
+            })?;
+            (interrupt_num, deferred_task)
+        };
+        self.interrupt_num = Some(interrupt_num);
+        self.deferred_task = Some(deferred_task);
+
+        let mut regs = self.regs.lock();
+        regs.isr.write(0xFFFF);
+        regs.imr.write(ENABLED_INTERRUPTS);
+        Ok(())
+    }
+
+    pub fn spoof_mac(&mut self, spoofed_mac_addr: [u8; 6]) {
+        self.mac_spoofed = Some(spoofed_mac_addr);
+    }
+
+    /// Returns whether the link is currently up.
+    pub fn link_up(&self) -> bool {
+        (self.regs.lock().phy_status.read() & PHY_STATUS_LINK) == PHY_STATUS_LINK
+    }
+
+    /// The main interrupt handling routine for the RTL8168 NIC.
+    /// This should be invoked from the actual interrupt handler entry point.
+    fn handle_interrupt(&mut self) -> Result<(), &'static str> {
+        let status = {
+            let mut regs = self.regs.lock();
+            let status = regs.isr.read();
+            // Acknowledge all interrupts that we're about to handle.
+            regs.isr.write(status);
+            status
+        };
+
+        if (status & INT_LINK_CHG) == INT_LINK_CHG {
+            debug!("rtl8168::handle_interrupt(): link status changed, link up: {}", self.link_up());
+        }
+        if (status & (INT_RER | INT_TER | INT_FOVW)) != 0 {
+            error!("rtl8168::handle_interrupt(): rx/tx error, status: {:#X}", status);
+        }
+        if (status & (INT_ROK | INT_RDU | INT_FOVW)) != 0 {
+            self.rx_queue.poll_queue_and_store_received_packets()?;
+            if let Some(ref deferred_task) = self.deferred_task {
+                let _ = deferred_task.unblock();
+            } else {
+                error!("rtl8168::handle_interrupt(): no deferred task");
+            }
+        }
+        Ok(())
+    }
+}
+
+impl net::NetworkDevice for Rtl8168Nic {
+    fn send(&mut self, buf: TransmitBuffer) {
+        self.tx_queue.send_on_queue(buf);
+    }
+
+    fn receive(&mut self) -> Option<ReceivedFrame> {
+        self.rx_queue.received_frames.pop_front()
+    }
+
+    /// Returns the MAC address.
+    fn mac_address(&self) -> [u8; 6] {
+        self.mac_spoofed.unwrap_or(self.mac_hardware)
+    }
+}
+
+extern "x86-interrupt" fn rtl8168_handler(_stack_frame: InterruptStackFrame) {
+    if let Some(nic_ref) = RTL8168_NIC.get() {
+        let mut nic = nic_ref.lock();
+        if let Err(e) = nic.handle_interrupt() {
+            error!("rtl8168_handler(): error handling interrupt: {:?}", e);
+        }
+        if let Some(interrupt_num) = nic.interrupt_num {
+            eoi(interrupt_num);
+        }
+    } else {
+        error!("BUG: rtl8168_handler(): RTL8168 NIC hasn't yet been initialized!");
+    }
+}
+
+/// This function is used as a deferred interrupt task.
+///
+/// After processing the interrupt, the network interface associated with the NIC will be
+/// polled to process the received data.
+fn poll_interface(interface: &Arc<net::NetworkInterface>) -> Result<(), ()> {
+    interface.poll();
+    Ok(())
+}
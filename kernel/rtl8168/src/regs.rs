@@ -0,0 +1,111 @@
+//! The memory-mapped registers of the RTL8168 and the values written to them.
+//!
+//! Unlike Intel NICs, all registers fit within a single 256-byte region,
+//! so the receive and transmit queues share access to it with the rest of the driver.
+
+use volatile::{Volatile, ReadOnly};
+use zerocopy::FromBytes;
+
+/// The layout in memory of the RTL8168 registers.
+#[derive(FromBytes)]
+#[repr(C)]
+pub struct Rtl8168Registers {
+    /// The MAC address
+    pub idr:                        [ReadOnly<u8>; 6],      // 0x0 - 0x5
+    _padding0:                      [u8; 26],               // 0x6 - 0x1F
+
+    /// Transmit Normal Priority Descriptors start address, low and high 32 bits
+    pub tnpds_lo:                   Volatile<u32>,          // 0x20
+    pub tnpds_hi:                   Volatile<u32>,          // 0x24
+    _padding1:                      [u8; 15],               // 0x28 - 0x36
+
+    /// Command register
+    pub cr:                         Volatile<u8>,           // 0x37
+    /// Transmit priority polling register
+    pub tppoll:                     Volatile<u8>,           // 0x38
+    _padding2:                      [u8; 3],                // 0x39 - 0x3B
+
+    /// Interrupt mask register
+    pub imr:                        Volatile<u16>,          // 0x3C
+    /// Interrupt status register, in which bits are cleared by writing 1 to them
+    pub isr:                        Volatile<u16>,          // 0x3E
+    /// Transmit configuration register
+    pub tcr:                        Volatile<u32>,          // 0x40
+    /// Receive configuration register
+    pub rcr:                        Volatile<u32>,          // 0x44
+    _padding3:                      [u8; 8],                // 0x48 - 0x4F
+
+    /// 93C46 (EEPROM) command register, used to unlock the configuration registers
+    pub cr9346:                     Volatile<u8>,           // 0x50
+    _padding4:                      [u8; 27],               // 0x51 - 0x6B
+
+    /// PHY status register
+    pub phy_status:                 ReadOnly<u8>,           // 0x6C
+    _padding5:                      [u8; 109],              // 0x6D - 0xD9
+
+    /// Receive (Rx) packet maximum size
+    pub rms:                        Volatile<u16>,          // 0xDA
+    _padding6:                      [u8; 4],                // 0xDC - 0xDF
+
+    /// C+ command register
+    pub cpcr:                       Volatile<u16>,          // 0xE0
+    _padding7:                      [u8; 2],                // 0xE2 - 0xE3
+
+    /// Receive Descriptor Start Address, low and high 32 bits
+    pub rdsar_lo:                   Volatile<u32>,          // 0xE4
+    pub rdsar_hi:                   Volatile<u32>,          // 0xE8
+    /// Max transmit packet size, in units of 128 bytes
+    pub mtps:                       Volatile<u8>,           // 0xEC
+    _padding8:                      [u8; 19],               // 0xED - 0xFF
+}
+
+const _: () = assert!(core::mem::size_of::<Rtl8168Registers>() == 256);
+
+/// CR: reset the NIC, which is cleared by HW when complete
+pub const CR_RST:                   u8 = 1 << 4;
+/// CR: receiver enable
+pub const CR_RE:                    u8 = 1 << 3;
+/// CR: transmitter enable
+pub const CR_TE:                    u8 = 1 << 2;
+
+/// TPPOLL: a normal priority packet is ready to be transmitted
+pub const TPPOLL_NPQ:               u8 = 1 << 6;
+
+/// 9346CR: unlock the configuration registers
+pub const CR9346_UNLOCK:            u8 = 0xC0;
+/// 9346CR: lock the configuration registers
+pub const CR9346_LOCK:              u8 = 0x00;
+
+/// RCR: accept broadcast packets
+pub const RCR_AB:                   u32 = 1 << 3;
+/// RCR: accept multicast packets
+pub const RCR_AM:                   u32 = 1 << 2;
+/// RCR: accept packets with a destination address matching our MAC address
+pub const RCR_APM:                  u32 = 1 << 1;
+/// RCR: unlimited DMA burst size
+pub const RCR_MXDMA_UNLIMITED:      u32 = 0b111 << 8;
+/// RCR: no Rx FIFO threshold, i.e., only begin DMA transfers once a whole packet is received
+pub const RCR_RXFTH_NONE:           u32 = 0b111 << 13;
+
+/// TCR: unlimited DMA burst size
+pub const TCR_MXDMA_UNLIMITED:      u32 = 0b111 << 8;
+/// TCR: the standard interframe gap time
+pub const TCR_IFG_NORMAL:           u32 = 0b11 << 24;
+
+/// PHY status: the link is up
+pub const PHY_STATUS_LINK:          u8 = 1 << 1;
+
+/// Interrupt: receive OK
+pub const INT_ROK:                  u16 = 1 << 0;
+/// Interrupt: receive error
+pub const INT_RER:                  u16 = 1 << 1;
+/// Interrupt: transmit OK
+pub const INT_TOK:                  u16 = 1 << 2;
+/// Interrupt: transmit error
+pub const INT_TER:                  u16 = 1 << 3;
+/// Interrupt: Rx descriptors unavailable
+pub const INT_RDU:                  u16 = 1 << 4;
+/// Interrupt: link status change
+pub const INT_LINK_CHG:             u16 = 1 << 5;
+/// Interrupt: Rx FIFO overflow
+pub const INT_FOVW:                 u16 = 1 << 6;
@@ -10,11 +10,25 @@
 //! legacy compatbility, and to act as an easy landing page for code search.
 //! That means that a caller need only depend on [`task`], not this crate,
 //! to invoke the scheduler (yield the CPU) to switch to another task.
+//!
+//! ## Per-CPU timeslices
+//! The preemption timer period (timeslice) of each CPU can be changed at runtime
+//! via [`set_timeslice()`], e.g., to give realtime CPUs shorter timeslices than batch CPUs.
+//! The new period is applied by the target CPU itself, either immediately
+//! (if it is the current CPU) or upon its next timer interrupt.
 
 #![no_std]
-#![cfg_attr(target_arch = "x86_64", feature(abi_x86_interrupt))]
+#![cfg_attr(target_arch = "x86_64", feature(abi_x86_interrupt, naked_functions))]
+
+extern crate alloc;
 
+use alloc::vec::Vec;
+use atomic_linked_list::atomic_map::AtomicMap;
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use cpu::CpuId;
 use interrupts::{self, CPU_LOCAL_TIMER_IRQ, interrupt_handler, eoi, EoiBehaviour};
+use kernel_config::time::CONFIG_TIMESLICE_PERIOD_MICROSECONDS;
+use spin::Mutex;
 
 /// Re-exports for convenience and legacy compatibility.
 pub use task::scheduler::{inherit_priority, priority, schedule, set_priority};
@@ -48,7 +62,9 @@ pub fn init() -> Result<(), &'static str> {
 }
 
 // Architecture-independent timer interrupt handler for preemptive scheduling.
-interrupt_handler!(timer_tick_handler, _, _stack_frame, {
+interrupt_handler!(timer_tick_handler, _, mut _stack_frame, {
+    apply_pending_timeslice();
+
     #[cfg(target_arch = "aarch64")]
     generic_timer_aarch64::set_next_timer_interrupt(get_timeslice_ticks());
 
@@ -68,25 +84,264 @@ interrupt_handler!(timer_tick_handler, _, _stack_frame, {
     // because we switch tasks here, which doesn't return.
     eoi(CPU_LOCAL_TIMER_IRQ);
 
-    schedule();
+    task::scheduler::preempt();
+
+    // This is a preemption point, so arrange for any notifications that were sent
+    // to the current task while it wasn't running to be delivered once we return to it.
+    #[cfg(target_arch = "x86_64")]
+    deliver_notifications_upon_return(_stack_frame);
 
     EoiBehaviour::HandlerSentEoi
 });
 
+/// If the current task has pending notifications, modifies the given `stack_frame` such that
+/// returning from this interrupt delivers them in the context of the current task,
+/// i.e., with interrupts enabled and outside of this interrupt handler,
+/// after which the interrupted code resumes.
+///
+/// This is only done if the interrupted code could have been preempted,
+/// i.e., it is kernel code that wasn't holding preemption.
+/// Otherwise, the notifications will be delivered at a later preemption point
+/// or when the current task returns from a blocking call.
+#[cfg(target_arch = "x86_64")]
+fn deliver_notifications_upon_return(stack_frame: &mut interrupts::InterruptStackFrame) {
+    use x86_64::VirtAddr;
+
+    let interrupted_kernel_code = stack_frame.code_segment & 0b11 == 0;
+    if !interrupted_kernel_code || !preemption::preemption_enabled() {
+        return;
+    }
+    if !task::with_current_task(|t| t.has_pending_notifications()).unwrap_or(false) {
+        return;
+    }
+
+    // Emulate a `call` to the trampoline from the interrupted instruction.
+    // Theseus is compiled without a red zone, so nothing below the stack pointer is in use.
+    let return_address = stack_frame.instruction_pointer.as_u64();
+    let sp = stack_frame.stack_pointer.as_u64() - 8;
+    // SAFETY: the interrupted kernel code's stack has room for the return address,
+    // and the trampoline preserves all of the interrupted code's registers.
+    unsafe {
+        (sp as *mut u64).write(return_address);
+        stack_frame.as_mut().update(|frame| {
+            frame.instruction_pointer = VirtAddr::new(deliver_notifications_trampoline as usize as u64);
+            frame.stack_pointer = VirtAddr::new(sp);
+        });
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+cfg_if::cfg_if! {
+    // Theseus kernel code uses SIMD registers only if it was built with SIMD support,
+    // in which case they must be preserved across the notification handlers as well.
+    if #[cfg(target_feature = "avx")] {
+        macro_rules! save_simd_registers { () => { concat!(
+            "sub rsp, 832\n",
+            // XRSTOR requires the reserved bytes of the XSAVE header to be zero.
+            "xor eax, eax\n",
+            "mov qword ptr [rsp + 512], rax\n", "mov qword ptr [rsp + 520], rax\n",
+            "mov qword ptr [rsp + 528], rax\n", "mov qword ptr [rsp + 536], rax\n",
+            "mov qword ptr [rsp + 544], rax\n", "mov qword ptr [rsp + 552], rax\n",
+            "mov qword ptr [rsp + 560], rax\n", "mov qword ptr [rsp + 568], rax\n",
+            "mov eax, 7\n",
+            "xor edx, edx\n",
+            "xsave64 [rsp]\n",
+        )}; }
+        macro_rules! restore_simd_registers { () => { concat!(
+            "mov eax, 7\n",
+            "xor edx, edx\n",
+            "xrstor64 [rsp]\n",
+        )}; }
+    } else if #[cfg(target_feature = "sse2")] {
+        macro_rules! save_simd_registers { () => { concat!(
+            "sub rsp, 512\n",
+            "fxsave64 [rsp]\n",
+        )}; }
+        macro_rules! restore_simd_registers { () => { concat!(
+            "fxrstor64 [rsp]\n",
+        )}; }
+    } else {
+        macro_rules! save_simd_registers { () => { "" }; }
+        macro_rules! restore_simd_registers { () => { "" }; }
+    }
+}
+
+/// Delivers the current task's pending notifications and then returns to the interrupted code,
+/// whose instruction pointer was pushed onto the stack by [`deliver_notifications_upon_return()`].
+///
+/// All registers and flags are preserved, as the interrupted code didn't expect this call.
+#[cfg(target_arch = "x86_64")]
+#[naked]
+unsafe extern "C" fn deliver_notifications_trampoline() {
+    core::arch::asm!(
+        "pushfq",
+        "push rax", "push rcx", "push rdx", "push rsi", "push rdi",
+        "push r8", "push r9", "push r10", "push r11",
+        // The callee-saved `rbp` holds the unaligned stack pointer across the call.
+        "push rbp",
+        "mov rbp, rsp",
+        "and rsp, -64",
+        save_simd_registers!(),
+        "call {deliver_pending}",
+        restore_simd_registers!(),
+        "mov rsp, rbp",
+        "pop rbp",
+        "pop r11", "pop r10", "pop r9", "pop r8",
+        "pop rdi", "pop rsi", "pop rdx", "pop rcx", "pop rax",
+        "popfq",
+        "ret",
+        deliver_pending = sym deliver_pending,
+        options(noreturn),
+    );
+}
+
+#[cfg(target_arch = "x86_64")]
+extern "C" fn deliver_pending() {
+    task::notifications::deliver_pending();
+}
+
+
+/// The shortest timeslice period (in microseconds) that a CPU may be configured to use.
+pub const MIN_TIMESLICE_MICROSECONDS: u32 = 100;
+/// The longest timeslice period (in microseconds) that a CPU may be configured to use.
+pub const MAX_TIMESLICE_MICROSECONDS: u32 = 1_000_000;
+
+/// The timeslice settings of a single CPU.
+#[derive(Debug)]
+struct Timeslice {
+    /// The requested timeslice period, in microseconds.
+    microseconds: AtomicU32,
+    /// Whether `microseconds` has been changed but not yet applied by its CPU.
+    pending: AtomicBool,
+}
+
+/// The timeslice settings of each CPU whose timeslice has been changed.
+///
+/// CPUs that are not in this map use the default [`CONFIG_TIMESLICE_PERIOD_MICROSECONDS`].
+static TIMESLICES: AtomicMap<CpuId, Timeslice> = AtomicMap::new();
+
+/// Serializes updates to [`TIMESLICES`] such that a CPU's entry is only inserted once.
+///
+/// This is never acquired by the timer interrupt handler, which only reads [`TIMESLICES`].
+static TIMESLICE_UPDATE_LOCK: Mutex<()> = Mutex::new(());
+
+/// The set of functions that are invoked when a CPU's timeslice is changed.
+static TIMESLICE_LISTENERS: Mutex<Vec<fn(TimesliceChanged)>> = Mutex::new(Vec::new());
 
-/// Returns the (cached) number of system timer ticks needed for the scheduling timeslice interval.
+/// The event emitted when a CPU's timeslice period is changed via [`set_timeslice()`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimesliceChanged {
+    /// The CPU whose timeslice was changed.
+    pub cpu: CpuId,
+    /// The previous timeslice period, in microseconds.
+    pub old_microseconds: u32,
+    /// The new timeslice period, in microseconds.
+    pub new_microseconds: u32,
+}
+
+/// Registers a function that will be invoked whenever a CPU's timeslice is changed.
+///
+/// Listeners are invoked synchronously in the context of the task
+/// that called [`set_timeslice()`], so they should be short and must not
+/// call [`set_timeslice()`] themselves.
+pub fn register_timeslice_listener(listener: fn(TimesliceChanged)) {
+    TIMESLICE_LISTENERS.lock().push(listener);
+}
+
+/// Returns the timeslice period of the given CPU, in microseconds.
+///
+/// If a change to this CPU's timeslice is still pending,
+/// this returns the newly-requested value.
+pub fn timeslice(cpu: CpuId) -> u32 {
+    TIMESLICES.get(&cpu)
+        .map(|ts| ts.microseconds.load(Ordering::Acquire))
+        .unwrap_or(CONFIG_TIMESLICE_PERIOD_MICROSECONDS)
+}
+
+/// Sets the preemptive timeslice period of the given CPU to `microseconds`.
+///
+/// If `cpu` is the current CPU, the new period takes effect immediately;
+/// otherwise, it takes effect upon that CPU's next timer interrupt,
+/// because each CPU can only reprogram its own local timer.
+///
+/// All listeners registered via [`register_timeslice_listener()`]
+/// are notified of the change.
+///
+/// ## Return
+/// * `Ok(old_period)`, the previous timeslice period in microseconds, upon success.
+/// * `Err` if `microseconds` is outside of the range from
+///   [`MIN_TIMESLICE_MICROSECONDS`] to [`MAX_TIMESLICE_MICROSECONDS`].
+pub fn set_timeslice(cpu: CpuId, microseconds: u32) -> Result<u32, &'static str> {
+    if !(MIN_TIMESLICE_MICROSECONDS ..= MAX_TIMESLICE_MICROSECONDS).contains(&microseconds) {
+        return Err("the given timeslice period was out of bounds");
+    }
+
+    let update_lock = TIMESLICE_UPDATE_LOCK.lock();
+    let old_microseconds = match TIMESLICES.get(&cpu) {
+        Some(ts) => {
+            let old = ts.microseconds.swap(microseconds, Ordering::AcqRel);
+            ts.pending.store(true, Ordering::Release);
+            old
+        }
+        None => {
+            TIMESLICES.insert(cpu, Timeslice {
+                microseconds: AtomicU32::new(microseconds),
+                pending: AtomicBool::new(true),
+            });
+            CONFIG_TIMESLICE_PERIOD_MICROSECONDS
+        }
+    };
+    drop(update_lock);
+
+    // If the target CPU is the current CPU, we can apply the new timeslice right away.
+    // Holding preemption ensures we don't migrate to a different CPU while doing so.
+    let preemption_guard = preemption::hold_preemption();
+    if preemption_guard.cpu_id() == cpu {
+        apply_pending_timeslice();
+    }
+    drop(preemption_guard);
+
+    log::info!("Changed timeslice of CPU {} from {} us to {} us", cpu, old_microseconds, microseconds);
+    let event = TimesliceChanged { cpu, old_microseconds, new_microseconds: microseconds };
+    for listener in TIMESLICE_LISTENERS.lock().iter() {
+        listener(event);
+    }
+    Ok(old_microseconds)
+}
+
+/// Applies a pending timeslice change (if any) to the current CPU's local timer.
+///
+/// This must be invoked with preemption or interrupts disabled.
+fn apply_pending_timeslice() {
+    let Some(ts) = TIMESLICES.get(&cpu::current_cpu()) else { return };
+    if !ts.pending.swap(false, Ordering::AcqRel) {
+        return;
+    }
+
+    #[cfg(target_arch = "x86_64")] {
+        let microseconds = ts.microseconds.load(Ordering::Acquire);
+        match apic::get_my_apic() {
+            Some(lapic) => if let Err(e) = lapic.write().set_timer_period(microseconds) {
+                log::error!("Failed to set timeslice of CPU {} to {} us: {}", cpu::current_cpu(), microseconds, e);
+            }
+            None => log::error!("BUG: couldn't get the local APIC to apply a new timeslice"),
+        }
+    }
+
+    // On aarch64, the new timeslice is used the next time the one-shot timer is armed.
+}
+
+/// Returns the number of system timer ticks needed for the current CPU's scheduling timeslice interval.
 ///
 /// This is only needed on aarch64 because it only effectively offers a one-shot timer;
 /// x86_64 can be configured once as a recurring periodic timer.
 #[cfg(target_arch = "aarch64")]
 fn get_timeslice_ticks() -> u64 {
-    use kernel_config::time::CONFIG_TIMESLICE_PERIOD_MICROSECONDS;
-
-    static TIMESLICE_TICKS: spin::Once<u64> = spin::Once::new();
+    static TICK_PERIOD_FEMTOSECS: spin::Once<u64> = spin::Once::new();
 
-    *TIMESLICE_TICKS.call_once(|| {
-        let timeslice_femtosecs = (CONFIG_TIMESLICE_PERIOD_MICROSECONDS as u64) * 1_000_000_000;
-        let tick_period_femtosecs = generic_timer_aarch64::timer_period_femtoseconds();
-        timeslice_femtosecs / tick_period_femtosecs
-    })
+    let tick_period_femtosecs = *TICK_PERIOD_FEMTOSECS.call_once(
+        generic_timer_aarch64::timer_period_femtoseconds
+    );
+    let timeslice_femtosecs = (timeslice(cpu::current_cpu()) as u64) * 1_000_000_000;
+    timeslice_femtosecs / tick_period_femtosecs
 }
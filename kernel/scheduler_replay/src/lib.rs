@@ -0,0 +1,441 @@
+//! Records scheduling decisions and deterministically replays them on a single CPU,
+//! in order to reproduce race conditions in task coordination code.
+//!
+//! ## Recording
+//! [`start_recording()`] installs a [`ScheduleHook`](task::scheduler::ScheduleHook) that logs
+//! every scheduling decision on the given CPUs into a [`Trace`], along with whether it was
+//! a voluntary yield or a timer preemption. Code under test can also log lock acquisitions
+//! via [`lock_acquired()`] and mark instrumented preemption points via [`preemption_point()`];
+//! each preemption is recorded along with how many preemption points the preempted task had passed.
+//!
+//! ## Replaying
+//! [`start_replay()`] replays a trace recorded on a single CPU by overriding that CPU's
+//! scheduler policy. Timer interrupts are ignored; instead, each recorded preemption is
+//! re-executed at the first preemption point (or timer interrupt) at which the preempted task
+//! has passed as many preemption points as it had when it was originally preempted.
+//! Voluntary yields are replayed by switching to the recorded next task, and lock acquisitions
+//! are checked against the recorded lock acquisition order.
+//!
+//! Tasks and locks are identified differently in each run, so the replay pairs each recorded
+//! task with a live task of the same name, in the order in which they first appear,
+//! and pairs each recorded lock with a live lock in the order in which they're first acquired.
+//! Thus, the workload must spawn its tasks in the same order as in the recorded run.
+//!
+//! As soon as the replayed run diverges from the trace, e.g., because a task yields
+//! where the trace expected a different task to run, the replay stops enforcing the trace
+//! and the [`Divergence`] is reported via [`replay_report()`].
+//!
+//! ## Example
+//! ```ignore
+//! scheduler_replay::start_recording(&[cpu], 100_000)?;
+//! run_workload_pinned_to(cpu);
+//! let trace = scheduler_replay::stop_recording()?;
+//!
+//! scheduler_replay::start_replay(cpu, trace)?;
+//! run_workload_pinned_to(cpu);
+//! let report = scheduler_replay::stop_replay()?;
+//! ```
+
+#![no_std]
+
+extern crate alloc;
+
+mod mapping;
+
+use alloc::{collections::BTreeMap, vec::Vec};
+use core::sync::atomic::{AtomicU8, Ordering};
+use cpu::CpuId;
+use log::warn;
+use mapping::Mapping;
+use sync_irq::IrqSafeMutex;
+use task::{scheduler::{ScheduleReason, Scheduler}, TaskRef};
+
+const MODE_OFF: u8 = 0;
+const MODE_RECORDING: u8 = 1;
+const MODE_REPLAYING: u8 = 2;
+
+/// Whether a trace is currently being recorded or replayed.
+static MODE: AtomicU8 = AtomicU8::new(MODE_OFF);
+static RECORDING: IrqSafeMutex<Option<Recording>> = IrqSafeMutex::new(None);
+static REPLAY: IrqSafeMutex<Option<Replay>> = IrqSafeMutex::new(None);
+/// The number of preemption points that each task has passed, keyed by task ID.
+static PREEMPTION_POINTS: IrqSafeMutex<BTreeMap<usize, u64>> = IrqSafeMutex::new(BTreeMap::new());
+
+/// Identifies a task within a trace.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TaskKey {
+    /// The task's ID in the recorded run.
+    pub id: usize,
+    /// A hash of the task's name, which is used to pair it with a live task during replay.
+    pub name_hash: u64,
+}
+
+impl TaskKey {
+    fn of(task: &TaskRef) -> TaskKey {
+        TaskKey { id: task.id, name_hash: name_hash(&task.name) }
+    }
+}
+
+/// An event in a [`Trace`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+    /// A scheduling decision, in which `from` was the current task and `to` was selected to run next.
+    ///
+    /// Preemptions that didn't switch tasks aren't recorded.
+    Schedule {
+        cpu: CpuId,
+        reason: ScheduleReason,
+        from: TaskKey,
+        to: TaskKey,
+        /// The number of preemption points that `from` had passed.
+        point: u64,
+    },
+    /// An instrumented lock at the given address was acquired by the given task.
+    LockAcquired {
+        cpu: CpuId,
+        task: TaskKey,
+        lock: usize,
+    },
+}
+
+impl Event {
+    fn cpu(&self) -> CpuId {
+        match *self {
+            Event::Schedule { cpu, .. } | Event::LockAcquired { cpu, .. } => cpu,
+        }
+    }
+}
+
+/// A recorded sequence of scheduling decisions and lock acquisitions.
+#[derive(Clone, Debug, Default)]
+pub struct Trace {
+    pub events: Vec<Event>,
+    /// Whether events were dropped because the trace reached its maximum length.
+    pub truncated: bool,
+}
+
+impl Trace {
+    /// Returns the CPUs on which the events in this trace occurred.
+    pub fn cpus(&self) -> Vec<CpuId> {
+        let mut cpus: Vec<CpuId> = self.events.iter().map(Event::cpu).collect();
+        cpus.sort_unstable();
+        cpus.dedup();
+        cpus
+    }
+}
+
+/// The point at which a replayed run diverged from its trace.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Divergence {
+    /// The index of the event in the trace that couldn't be replayed.
+    pub event_index: usize,
+    pub description: &'static str,
+}
+
+/// The progress of a replay.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ReplayReport {
+    /// The number of events that were replayed.
+    pub replayed: usize,
+    /// The total number of events in the trace.
+    pub total: usize,
+    /// The point at which the replay diverged from the trace, if it did.
+    pub divergence: Option<Divergence>,
+}
+
+struct Recording {
+    cpus: Vec<CpuId>,
+    trace: Trace,
+    capacity: usize,
+}
+
+impl Recording {
+    fn push(&mut self, event: Event) {
+        if self.trace.events.len() < self.capacity {
+            self.trace.events.push(event);
+        } else {
+            self.trace.truncated = true;
+        }
+    }
+}
+
+struct Replay {
+    cpu: CpuId,
+    events: Vec<Event>,
+    /// The index of the next event to be replayed.
+    next: usize,
+    tasks: Mapping,
+    locks: Mapping,
+    divergence: Option<Divergence>,
+}
+
+impl Replay {
+    /// Returns the next event to be replayed, unless the replay is over.
+    fn next_event(&self) -> Option<Event> {
+        if self.divergence.is_some() {
+            return None;
+        }
+        self.events.get(self.next).copied()
+    }
+
+    fn diverge(&mut self, description: &'static str) {
+        warn!("Replay diverged from the trace at event {}: {}", self.next, description);
+        self.divergence = Some(Divergence { event_index: self.next, description });
+    }
+
+    /// Returns whether the given recorded task can be paired with the given live task.
+    fn matches(&self, recorded: TaskKey, live: &TaskRef) -> bool {
+        self.tasks.is_compatible(recorded.id, live.id)
+            && (self.tasks.live(recorded.id).is_some() || recorded.name_hash == name_hash(&live.name))
+    }
+
+    fn report(&self) -> ReplayReport {
+        ReplayReport { replayed: self.next, total: self.events.len(), divergence: self.divergence }
+    }
+}
+
+/// Starts recording the scheduling decisions on the given CPUs, keeping at most `capacity` events.
+///
+/// The trace's memory is allocated up front, such that recording doesn't allocate while scheduling.
+pub fn start_recording(cpus: &[CpuId], capacity: usize) -> Result<(), &'static str> {
+    let mut recording = RECORDING.lock();
+    if MODE.compare_exchange(MODE_OFF, MODE_RECORDING, Ordering::AcqRel, Ordering::Acquire).is_err() {
+        return Err("scheduler_replay: already recording or replaying");
+    }
+    PREEMPTION_POINTS.lock().clear();
+    *recording = Some(Recording {
+        cpus: cpus.to_vec(),
+        trace: Trace { events: Vec::with_capacity(capacity), truncated: false },
+        capacity,
+    });
+    drop(recording);
+    task::scheduler::set_schedule_hook(Some(schedule_hook));
+    Ok(())
+}
+
+/// Stops recording and returns the recorded trace.
+pub fn stop_recording() -> Result<Trace, &'static str> {
+    if MODE.load(Ordering::Acquire) != MODE_RECORDING {
+        return Err("scheduler_replay: not recording");
+    }
+    task::scheduler::set_schedule_hook(None);
+    let recording = RECORDING.lock().take().ok_or("scheduler_replay: not recording")?;
+    MODE.store(MODE_OFF, Ordering::Release);
+    Ok(recording.trace)
+}
+
+/// Starts replaying the given trace on the given CPU.
+///
+/// The trace must have been recorded on a single CPU.
+/// The workload should then be started in the same way as it was while recording,
+/// with its tasks pinned to the given CPU.
+pub fn start_replay(cpu: CpuId, trace: Trace) -> Result<(), &'static str> {
+    if trace.cpus().len() > 1 {
+        return Err("scheduler_replay: only traces recorded on a single CPU can be replayed");
+    }
+    let mut replay = REPLAY.lock();
+    if MODE.compare_exchange(MODE_OFF, MODE_REPLAYING, Ordering::AcqRel, Ordering::Acquire).is_err() {
+        return Err("scheduler_replay: already recording or replaying");
+    }
+    PREEMPTION_POINTS.lock().clear();
+    *replay = Some(Replay {
+        cpu,
+        events: trace.events,
+        next: 0,
+        tasks: Mapping::default(),
+        locks: Mapping::default(),
+        divergence: None,
+    });
+    drop(replay);
+    task::scheduler::set_schedule_hook(Some(schedule_hook));
+    Ok(())
+}
+
+/// Returns the progress of the current replay, if any.
+pub fn replay_report() -> Option<ReplayReport> {
+    REPLAY.lock().as_ref().map(Replay::report)
+}
+
+/// Stops replaying, returning the final progress of the replay.
+pub fn stop_replay() -> Result<ReplayReport, &'static str> {
+    if MODE.load(Ordering::Acquire) != MODE_REPLAYING {
+        return Err("scheduler_replay: not replaying");
+    }
+    task::scheduler::set_schedule_hook(None);
+    let replay = REPLAY.lock().take().ok_or("scheduler_replay: not replaying")?;
+    MODE.store(MODE_OFF, Ordering::Release);
+    Ok(replay.report())
+}
+
+/// Marks an instrumented preemption point in the current task.
+///
+/// While replaying, this switches tasks if the trace recorded that the current task
+/// was preempted after passing this many preemption points.
+/// Otherwise, this only counts the preemption points that each task passes.
+pub fn preemption_point() {
+    if MODE.load(Ordering::Acquire) == MODE_OFF {
+        return;
+    }
+    let Ok(task_id) = task::with_current_task(|t| t.id) else { return };
+    let passed = {
+        let mut points = PREEMPTION_POINTS.lock();
+        let passed = points.entry(task_id).or_insert(0);
+        *passed += 1;
+        *passed
+    };
+
+    if MODE.load(Ordering::Acquire) == MODE_REPLAYING {
+        let preemption_due = REPLAY.lock().as_ref().is_some_and(|replay| {
+            replay.cpu == cpu::current_cpu() && matches!(
+                replay.next_event(),
+                Some(Event::Schedule { reason: ScheduleReason::Preemption, from, point, .. })
+                    if point <= passed && replay.tasks.is_compatible(from.id, task_id)
+            )
+        });
+        if preemption_due {
+            task::schedule();
+        }
+    }
+}
+
+/// Records or checks the acquisition of the given instrumented lock by the current task.
+///
+/// This should be invoked right after the lock is acquired.
+pub fn lock_acquired<T: ?Sized>(lock: &T) {
+    let mode = MODE.load(Ordering::Acquire);
+    if mode == MODE_OFF {
+        return;
+    }
+    let lock = lock as *const T as *const () as usize;
+    let cpu = cpu::current_cpu();
+    let Ok(task) = task::with_current_task(|t| t.clone()) else { return };
+
+    if mode == MODE_RECORDING {
+        if let Some(recording) = RECORDING.lock().as_mut().filter(|r| r.cpus.contains(&cpu)) {
+            recording.push(Event::LockAcquired { cpu, task: TaskKey::of(&task), lock });
+        }
+        return;
+    }
+
+    let mut replay = REPLAY.lock();
+    let Some(replay) = replay.as_mut().filter(|r| r.cpu == cpu) else { return };
+    match replay.next_event() {
+        Some(Event::LockAcquired { task: recorded_task, lock: recorded_lock, .. }) => {
+            if !replay.matches(recorded_task, &task) {
+                replay.diverge("a different task acquired a lock");
+            } else if !replay.locks.pair(recorded_lock, lock) {
+                replay.diverge("a different lock was acquired");
+            } else {
+                replay.tasks.pair(recorded_task.id, task.id);
+                replay.next += 1;
+            }
+        }
+        Some(Event::Schedule { .. }) => replay.diverge("a lock was acquired instead of switching tasks"),
+        None => { }
+    }
+}
+
+/// The [`ScheduleHook`](task::scheduler::ScheduleHook) that records or replays scheduling decisions.
+fn schedule_hook(
+    cpu: CpuId,
+    reason: ScheduleReason,
+    current: &TaskRef,
+    next: TaskRef,
+    scheduler: &dyn Scheduler,
+) -> TaskRef {
+    match MODE.load(Ordering::Acquire) {
+        MODE_RECORDING => {
+            record_decision(cpu, reason, current, &next);
+            next
+        }
+        MODE_REPLAYING => replay_decision(cpu, reason, current, next, scheduler),
+        _ => next,
+    }
+}
+
+fn record_decision(cpu: CpuId, reason: ScheduleReason, current: &TaskRef, next: &TaskRef) {
+    if reason == ScheduleReason::Preemption && current.id == next.id {
+        return;
+    }
+    let point = preemption_points_passed(current.id);
+    if let Some(recording) = RECORDING.lock().as_mut().filter(|r| r.cpus.contains(&cpu)) {
+        recording.push(Event::Schedule {
+            cpu,
+            reason,
+            from: TaskKey::of(current),
+            to: TaskKey::of(next),
+            point,
+        });
+    }
+}
+
+fn replay_decision(
+    cpu: CpuId,
+    reason: ScheduleReason,
+    current: &TaskRef,
+    next: TaskRef,
+    scheduler: &dyn Scheduler,
+) -> TaskRef {
+    let passed = preemption_points_passed(current.id);
+    let mut replay = REPLAY.lock();
+    let Some(replay) = replay.as_mut().filter(|r| r.cpu == cpu) else { return next };
+    let Some(event) = replay.next_event() else { return next };
+
+    let (recorded_reason, from, to, point) = match event {
+        Event::Schedule { reason, from, to, point, .. } => (reason, from, to, point),
+        Event::LockAcquired { .. } => {
+            // Timer interrupts are ignored during replay, as recorded preemptions are replayed explicitly.
+            if reason == ScheduleReason::Preemption {
+                return current.clone();
+            }
+            replay.diverge("a task yielded instead of acquiring a lock");
+            return next;
+        }
+    };
+    let is_due = match recorded_reason {
+        ScheduleReason::Yield => reason == ScheduleReason::Yield,
+        ScheduleReason::Preemption => passed >= point,
+    };
+    if !is_due {
+        if reason == ScheduleReason::Preemption {
+            return current.clone();
+        }
+        replay.diverge("a task yielded before reaching its recorded preemption point");
+        return next;
+    }
+    if !replay.matches(from, current) {
+        if reason == ScheduleReason::Preemption {
+            return current.clone();
+        }
+        replay.diverge("a different task was running");
+        return next;
+    }
+
+    // The recorded next task may be the current task, the idle task chosen by the policy, or any task in the runqueue.
+    let candidates = scheduler.tasks();
+    let chosen = [current, &next].into_iter()
+        .chain(candidates.iter())
+        .filter(|t| replay.matches(to, t) && (t.id == current.id || t.is_runnable()))
+        // An unpaired recorded task is paired with the earliest-spawned matching task.
+        .min_by_key(|t| t.id)
+        .cloned();
+    let Some(chosen) = chosen else {
+        replay.diverge("the recorded next task isn't runnable");
+        return next;
+    };
+    replay.tasks.pair(from.id, current.id);
+    replay.tasks.pair(to.id, chosen.id);
+    replay.next += 1;
+    chosen
+}
+
+fn preemption_points_passed(task_id: usize) -> u64 {
+    PREEMPTION_POINTS.lock().get(&task_id).copied().unwrap_or(0)
+}
+
+/// Returns the 64-bit FNV-1a hash of the given name.
+fn name_hash(name: &str) -> u64 {
+    name.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
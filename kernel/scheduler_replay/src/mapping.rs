@@ -0,0 +1,77 @@
+//! A one-to-one mapping between the identifiers of a recorded run and those of the replayed run.
+
+use alloc::collections::BTreeMap;
+
+/// A one-to-one mapping between recorded identifiers and live identifiers,
+/// e.g., task IDs or lock addresses, which differ between runs.
+///
+/// Identifiers are paired in the order in which they first appear,
+/// so a deterministic replay pairs each recorded identifier with its live counterpart.
+#[derive(Debug, Default)]
+pub(crate) struct Mapping {
+    recorded_to_live: BTreeMap<usize, usize>,
+    live_to_recorded: BTreeMap<usize, usize>,
+}
+
+impl Mapping {
+    /// Returns the live identifier that the given recorded identifier is paired with, if any.
+    pub(crate) fn live(&self, recorded: usize) -> Option<usize> {
+        self.recorded_to_live.get(&recorded).copied()
+    }
+
+    /// Returns whether the given recorded identifier could be paired with the given live identifier,
+    /// i.e., whether they're already paired with each other or both unpaired.
+    pub(crate) fn is_compatible(&self, recorded: usize, live: usize) -> bool {
+        match (self.recorded_to_live.get(&recorded), self.live_to_recorded.get(&live)) {
+            (Some(&l), Some(&r)) => l == live && r == recorded,
+            (None, None) => true,
+            _ => false,
+        }
+    }
+
+    /// Pairs the given recorded identifier with the given live identifier if they're both unpaired.
+    ///
+    /// Returns whether they are now paired with each other.
+    pub(crate) fn pair(&mut self, recorded: usize, live: usize) -> bool {
+        if !self.is_compatible(recorded, live) {
+            return false;
+        }
+        self.recorded_to_live.insert(recorded, live);
+        self.live_to_recorded.insert(live, recorded);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pairs_unpaired_identifiers() {
+        let mut mapping = Mapping::default();
+        assert!(mapping.pair(10, 110));
+        assert!(mapping.pair(11, 120));
+        assert_eq!(mapping.live(10), Some(110));
+        assert_eq!(mapping.live(11), Some(120));
+        assert_eq!(mapping.live(12), None);
+    }
+
+    #[test]
+    fn repairing_is_idempotent() {
+        let mut mapping = Mapping::default();
+        assert!(mapping.pair(10, 110));
+        assert!(mapping.pair(10, 110));
+        assert_eq!(mapping.live(10), Some(110));
+    }
+
+    #[test]
+    fn rejects_conflicting_pairs() {
+        let mut mapping = Mapping::default();
+        assert!(mapping.pair(10, 110));
+        assert!(!mapping.pair(10, 120));
+        assert!(!mapping.pair(11, 110));
+        assert!(!mapping.is_compatible(11, 110));
+        assert!(mapping.is_compatible(11, 120));
+        assert_eq!(mapping.live(11), None);
+    }
+}
@@ -0,0 +1,161 @@
+//! An RGB image and its encodings in the PNG and PPM formats.
+
+use alloc::{format, vec::Vec};
+use hashing::{Crc32, Digest};
+
+/// The signature at the start of every PNG file.
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n'];
+/// The PNG color type of 8-bit RGB pixels without an alpha channel.
+const PNG_COLOR_TYPE_RGB: u8 = 2;
+/// The PNG filter type that encodes each byte as the difference from the byte above it.
+const PNG_FILTER_UP: u8 = 2;
+/// The zlib compression level of PNG image data, from 0 (none) to 10 (best).
+const PNG_COMPRESSION_LEVEL: u8 = 6;
+
+/// An image of 8-bit RGB pixels, stored row by row.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Image {
+    width: usize,
+    height: usize,
+    rgb: Vec<u8>,
+}
+
+impl Image {
+    /// Creates an image from the given RGB bytes, three per pixel, stored row by row.
+    pub fn from_rgb(width: usize, height: usize, rgb: Vec<u8>) -> Result<Image, &'static str> {
+        if width == 0 || height == 0 || width > u32::MAX as usize || height > u32::MAX as usize {
+            return Err("invalid image dimensions");
+        }
+        if width.checked_mul(height).and_then(|pixels| pixels.checked_mul(3)) != Some(rgb.len()) {
+            return Err("the RGB data doesn't match the image dimensions");
+        }
+        Ok(Image { width, height, rgb })
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns the RGB bytes of this image, three per pixel, stored row by row.
+    pub fn rgb(&self) -> &[u8] {
+        &self.rgb
+    }
+
+    /// Encodes this image as a binary PPM ("P6") file.
+    pub fn to_ppm(&self) -> Vec<u8> {
+        let header = format!("P6\n{} {}\n255\n", self.width, self.height);
+        let mut ppm = Vec::with_capacity(header.len() + self.rgb.len());
+        ppm.extend_from_slice(header.as_bytes());
+        ppm.extend_from_slice(&self.rgb);
+        ppm
+    }
+
+    /// Encodes this image as a PNG file.
+    pub fn to_png(&self) -> Vec<u8> {
+        let row_len = self.width * 3;
+        // Each row is preceded by its filter type.
+        let mut filtered = Vec::with_capacity((row_len + 1) * self.height);
+        let mut previous_row: &[u8] = &[];
+        for row in self.rgb.chunks_exact(row_len) {
+            filtered.push(PNG_FILTER_UP);
+            if previous_row.is_empty() {
+                filtered.extend_from_slice(row);
+            } else {
+                filtered.extend(row.iter().zip(previous_row).map(|(&byte, &above)| byte.wrapping_sub(above)));
+            }
+            previous_row = row;
+        }
+        let image_data = miniz_oxide::deflate::compress_to_vec_zlib(&filtered, PNG_COMPRESSION_LEVEL);
+
+        let mut header = [0u8; 13];
+        header[0..4].copy_from_slice(&(self.width as u32).to_be_bytes());
+        header[4..8].copy_from_slice(&(self.height as u32).to_be_bytes());
+        header[8] = 8; // bit depth
+        header[9] = PNG_COLOR_TYPE_RGB;
+        // The compression method, filter method, and interlace method are all 0.
+
+        let mut png = Vec::with_capacity(PNG_SIGNATURE.len() + image_data.len() + 64);
+        png.extend_from_slice(&PNG_SIGNATURE);
+        write_png_chunk(&mut png, b"IHDR", &header);
+        write_png_chunk(&mut png, b"IDAT", &image_data);
+        write_png_chunk(&mut png, b"IEND", &[]);
+        png
+    }
+}
+
+/// Appends a PNG chunk with the given type and data, followed by its checksum.
+fn write_png_chunk(png: &mut Vec<u8>, chunk_type: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let checksum_start = png.len();
+    png.extend_from_slice(chunk_type);
+    png.extend_from_slice(data);
+    let checksum = Crc32::digest(&png[checksum_start..]);
+    png.extend_from_slice(&checksum.to_be_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use super::*;
+    use alloc::vec;
+
+    /// A 3x2 image with a different color in each pixel.
+    fn image() -> Image {
+        let rgb = vec![
+            255, 0, 0,   0, 255, 0,   0, 0, 255,
+            10, 20, 30,  40, 50, 60,  70, 80, 90,
+        ];
+        Image::from_rgb(3, 2, rgb).unwrap()
+    }
+
+    #[test]
+    fn dimensions_are_checked() {
+        assert!(Image::from_rgb(3, 2, vec![0; 17]).is_err());
+        assert!(Image::from_rgb(0, 0, vec![]).is_err());
+    }
+
+    #[test]
+    fn ppm() {
+        let ppm = image().to_ppm();
+        assert!(ppm.starts_with(b"P6\n3 2\n255\n"));
+        assert_eq!(&ppm[11..], image().rgb());
+    }
+
+    #[test]
+    fn png_round_trip() {
+        let image = image();
+        let png = image.to_png();
+        assert!(png.starts_with(&PNG_SIGNATURE));
+
+        // Parse the chunks, checking their checksums.
+        let mut chunks = vec![];
+        let mut rest = &png[PNG_SIGNATURE.len()..];
+        while !rest.is_empty() {
+            let len = u32::from_be_bytes(rest[0..4].try_into().unwrap()) as usize;
+            let (typ, data) = (&rest[4..8], &rest[8..8 + len]);
+            let checksum = u32::from_be_bytes(rest[8 + len..12 + len].try_into().unwrap());
+            assert_eq!(Crc32::digest(&rest[4..8 + len]), checksum);
+            chunks.push((typ, data));
+            rest = &rest[12 + len..];
+        }
+        let types: Vec<&[u8]> = chunks.iter().map(|(typ, _)| *typ).collect();
+        assert_eq!(types, [b"IHDR".as_slice(), b"IDAT", b"IEND"]);
+        assert_eq!(chunks[0].1, [0, 0, 0, 3, 0, 0, 0, 2, 8, 2, 0, 0, 0]);
+
+        // Decompress and unfilter the image data.
+        let filtered = miniz_oxide::inflate::decompress_to_vec_zlib(chunks[1].1).unwrap();
+        let mut rgb = vec![];
+        let mut previous_row = vec![0u8; 9];
+        for row in filtered.chunks_exact(10) {
+            assert_eq!(row[0], PNG_FILTER_UP);
+            let row: Vec<u8> = row[1..].iter().zip(&previous_row).map(|(&b, &above)| b.wrapping_add(above)).collect();
+            rgb.extend_from_slice(&row);
+            previous_row = row;
+        }
+        assert_eq!(rgb, image.rgb());
+    }
+}
@@ -0,0 +1,178 @@
+//! Captures the composited framebuffer of the window manager as an image,
+//! e.g., to document GUI work or to compare the screen against a reference image in tests.
+//!
+//! Images can be saved as PNG or PPM files in the VFS, either once via [`capture_to_file()`]
+//! or periodically via [`start_recording()`], which saves each frame as a separate numbered file.
+
+#![no_std]
+
+extern crate alloc;
+
+mod image;
+
+pub use image::Image;
+
+use alloc::{format, string::{String, ToString}, sync::Arc, vec::Vec};
+use core::{sync::atomic::{AtomicBool, AtomicUsize, Ordering}, time::Duration};
+use fs_node::{DirRef, FileRef};
+use log::{error, info};
+use memfs::MemFile;
+use spin::Mutex;
+
+/// An image file format.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// Compressed, and viewable almost everywhere.
+    Png,
+    /// Uncompressed and trivial to parse, e.g., by test scripts.
+    Ppm,
+}
+
+impl Format {
+    /// Returns the format with the given name or file extension, e.g., `"png"`.
+    pub fn from_extension(extension: &str) -> Option<Format> {
+        match extension {
+            e if e.eq_ignore_ascii_case("png") => Some(Format::Png),
+            e if e.eq_ignore_ascii_case("ppm") => Some(Format::Ppm),
+            _ => None,
+        }
+    }
+
+    /// Returns the file extension used for this format, without the leading dot.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Png => "png",
+            Format::Ppm => "ppm",
+        }
+    }
+
+    /// Encodes the given image in this format.
+    pub fn encode(self, image: &Image) -> Vec<u8> {
+        match self {
+            Format::Png => image.to_png(),
+            Format::Ppm => image.to_ppm(),
+        }
+    }
+}
+
+/// Captures the current contents of the window manager's final (composited) framebuffer.
+pub fn capture() -> Result<Image, &'static str> {
+    let window_manager = window_manager::WINDOW_MANAGER.get()
+        .ok_or("the window manager hasn't been initialized")?
+        .lock();
+    let (width, height) = window_manager.final_fb.get_size();
+    let mut rgb = Vec::with_capacity(width * height * 3);
+    for pixel in window_manager.final_fb.buffer() {
+        rgb.extend_from_slice(&[pixel.red, pixel.green, pixel.blue]);
+    }
+    drop(window_manager);
+    Image::from_rgb(width, height, rgb)
+}
+
+/// Saves the given image in the given format as a new file with the given name in the given directory.
+pub fn save(image: &Image, format: Format, dir: &DirRef, name: &str) -> Result<FileRef, &'static str> {
+    if dir.lock().get(name).is_some() {
+        return Err("a file with that name already exists");
+    }
+    let file = MemFile::create(name.to_string(), dir)?;
+    file.lock()
+        .write_at(&format.encode(image), 0)
+        .map_err(|_| "failed to write image file")?;
+    Ok(file)
+}
+
+/// Captures the composited framebuffer and saves it in the given format
+/// as a new file with the given name in the given directory.
+pub fn capture_to_file(dir: &DirRef, name: &str, format: Format) -> Result<FileRef, &'static str> {
+    save(&capture()?, format, dir, name)
+}
+
+/// The state of an ongoing recording.
+struct Recording {
+    stop: Arc<AtomicBool>,
+    frames: Arc<AtomicUsize>,
+}
+
+/// The ongoing recording, if any. Only one recording can run at a time.
+static RECORDING: Mutex<Option<Recording>> = Mutex::new(None);
+
+/// The parameters of the task that captures frames during a recording.
+struct RecordingTask {
+    dir: DirRef,
+    prefix: String,
+    format: Format,
+    interval: Duration,
+    max_frames: Option<usize>,
+    stop: Arc<AtomicBool>,
+    frames: Arc<AtomicUsize>,
+}
+
+/// Starts capturing the composited framebuffer every `interval` in a new task,
+/// saving each frame in the given `dir` as `<prefix><frame number>.<extension>`,
+/// e.g., `frame00000.png`.
+///
+/// The recording ends after `max_frames` frames, if given, or once [`stop_recording()`] is invoked.
+pub fn start_recording(
+    dir: DirRef,
+    prefix: &str,
+    format: Format,
+    interval: Duration,
+    max_frames: Option<usize>,
+) -> Result<(), &'static str> {
+    let mut recording = RECORDING.lock();
+    if recording.as_ref().is_some_and(|r| !r.stop.load(Ordering::Acquire)) {
+        return Err("a recording is already in progress");
+    }
+    let stop = Arc::new(AtomicBool::new(false));
+    let frames = Arc::new(AtomicUsize::new(0));
+    let task = RecordingTask {
+        dir,
+        prefix: prefix.to_string(),
+        format,
+        interval,
+        max_frames,
+        stop: stop.clone(),
+        frames: frames.clone(),
+    };
+    spawn::new_task_builder(record, task)
+        .name("screen_capture_recording".to_string())
+        .spawn()?;
+    *recording = Some(Recording { stop, frames });
+    Ok(())
+}
+
+/// Stops the ongoing recording, returning the number of frames that it captured.
+pub fn stop_recording() -> Option<usize> {
+    let recording = RECORDING.lock().take()?;
+    recording.stop.store(true, Ordering::Release);
+    Some(recording.frames.load(Ordering::Acquire))
+}
+
+/// Returns the number of frames captured so far by the ongoing recording, if any.
+pub fn recording_frames() -> Option<usize> {
+    RECORDING.lock()
+        .as_ref()
+        .filter(|r| !r.stop.load(Ordering::Acquire))
+        .map(|r| r.frames.load(Ordering::Acquire))
+}
+
+/// The entry point of the task that captures frames during a recording.
+fn record(task: RecordingTask) -> Result<(), &'static str> {
+    let mut frame = 0;
+    while !task.stop.load(Ordering::Acquire) && task.max_frames.map_or(true, |max| frame < max) {
+        let name = format!("{}{:05}.{}", task.prefix, frame, task.format.extension());
+        if let Err(e) = capture_to_file(&task.dir, &name, task.format) {
+            error!("screen_capture: recording stopped, failed to save frame {:?}: {}", name, e);
+            break;
+        }
+        frame += 1;
+        task.frames.store(frame, Ordering::Release);
+        if sleep::sleep(task.interval).is_err() {
+            break;
+        }
+    }
+    // Let the next recording start without requiring `stop_recording()` to be invoked.
+    task.stop.store(true, Ordering::Release);
+    info!("screen_capture: recording ended after {} frames", frame);
+    Ok(())
+}
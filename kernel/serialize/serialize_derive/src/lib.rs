@@ -0,0 +1,190 @@
+//! Exports the [`macro@Serialize`] derive macro for the `serialize` crate.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{
+    parse_macro_input, parse_quote, Attribute, Data, DeriveInput, Fields, GenericParam, Ident,
+    LitStr,
+};
+
+/// Derives `serialize::Serialize` for a struct or enum.
+///
+/// * Structs with named fields are serialized as structs.
+/// * Structs with a single unnamed field (newtypes) are serialized as that field.
+/// * Other tuple structs are serialized as sequences, and unit structs as units.
+/// * Enum variants are serialized as unit variants if they have no fields,
+///   and otherwise as variants containing their fields, following the same rules as structs.
+///
+/// Fields and variants accept the following attributes:
+/// * `#[serialize(rename = "name")]` serializes the field or variant with the given name.
+/// * `#[serialize(skip)]` omits the field.
+#[proc_macro_derive(Serialize, attributes(serialize))]
+pub fn derive_serialize(input: TokenStream) -> TokenStream {
+    let mut input = parse_macro_input!(input as DeriveInput);
+    match expand(&mut input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn expand(input: &mut DeriveInput) -> syn::Result<TokenStream2> {
+    for param in &mut input.generics.params {
+        if let GenericParam::Type(ty) = param {
+            ty.bounds.push(parse_quote!(::serialize::Serialize));
+        }
+    }
+    let name = &input.ident;
+    let name_str = name.to_string();
+
+    let body = match &input.data {
+        Data::Struct(data) => {
+            let (pattern, fields) = serialize_fields(&data.fields, &name_str)?;
+            quote! {
+                let #name #pattern = self;
+                #fields
+            }
+        }
+        // An enum without variants can't be instantiated, but `self` still has to be matched
+        // by value for the compiler to accept that there are no cases.
+        Data::Enum(data) if data.variants.is_empty() => quote! {
+            match *self {}
+        },
+        Data::Enum(data) => {
+            let mut arms = Vec::new();
+            for (index, variant) in data.variants.iter().enumerate() {
+                let ident = &variant.ident;
+                let variant_str = Options::parse(&variant.attrs)?
+                    .rename
+                    .unwrap_or_else(|| ident.to_string());
+                let index = index as u32;
+                if variant.fields.is_empty() {
+                    let (pattern, _) = serialize_fields(&variant.fields, &variant_str)?;
+                    arms.push(quote! {
+                        #name::#ident #pattern => serializer.serialize_unit_variant(#name_str, #index, #variant_str),
+                    });
+                } else {
+                    let (pattern, fields) = serialize_fields(&variant.fields, &variant_str)?;
+                    arms.push(quote! {
+                        #name::#ident #pattern => {
+                            serializer.begin_variant(#name_str, #index, #variant_str)?;
+                            (#fields)?;
+                            serializer.end_variant()
+                        }
+                    });
+                }
+            }
+            quote! {
+                match self {
+                    #(#arms)*
+                }
+            }
+        }
+        Data::Union(data) => {
+            return Err(syn::Error::new(
+                data.union_token.span,
+                "`Serialize` can't be derived for unions",
+            ))
+        }
+    };
+
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::serialize::Serialize for #name #ty_generics #where_clause {
+            fn serialize<__S: ::serialize::Serializer + ?Sized>(
+                &self,
+                serializer: &mut __S,
+            ) -> ::core::result::Result<(), __S::Error> {
+                #body
+            }
+        }
+    })
+}
+
+/// The options given by `#[serialize(...)]` attributes.
+#[derive(Default)]
+struct Options {
+    rename: Option<String>,
+    skip: bool,
+}
+
+impl Options {
+    fn parse(attrs: &[Attribute]) -> syn::Result<Options> {
+        let mut options = Options::default();
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident("serialize")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    options.rename = Some(meta.value()?.parse::<LitStr>()?.value());
+                    Ok(())
+                } else if meta.path.is_ident("skip") {
+                    options.skip = true;
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `rename = \"...\"` or `skip`"))
+                }
+            })?;
+        }
+        Ok(options)
+    }
+}
+
+/// Returns a pattern that destructures the given fields, and an expression that serializes them.
+fn serialize_fields(fields: &Fields, name: &str) -> syn::Result<(TokenStream2, TokenStream2)> {
+    let bindings: Vec<Ident> = (0..fields.len()).map(|i| format_ident!("__field{}", i)).collect();
+    let pattern = match fields {
+        Fields::Named(_) => {
+            let idents = fields.iter().map(|field| &field.ident);
+            quote!({ #(#idents: #bindings),* })
+        }
+        Fields::Unnamed(_) => quote!(( #(#bindings),* )),
+        Fields::Unit => quote!(),
+    };
+
+    let mut names = Vec::new();
+    let mut included = Vec::new();
+    let mut skipped = Vec::new();
+    for (field, binding) in fields.iter().zip(&bindings) {
+        let options = Options::parse(&field.attrs)?;
+        if options.skip {
+            skipped.push(binding);
+        } else {
+            names.push(options.rename.unwrap_or_else(|| {
+                field.ident.as_ref().map(Ident::to_string).unwrap_or_default()
+            }));
+            included.push(binding);
+        }
+    }
+
+    let len = included.len();
+    let serialize = match fields {
+        Fields::Named(_) => quote! {
+            serializer.begin_struct(#name, #len)?;
+            #(
+                serializer.struct_field(#names)?;
+                ::serialize::Serialize::serialize(#included, serializer)?;
+            )*
+            serializer.end_struct()
+        },
+        Fields::Unnamed(unnamed) if unnamed.unnamed.len() == 1 && len == 1 => quote! {
+            ::serialize::Serialize::serialize(#(#included)*, serializer)
+        },
+        Fields::Unnamed(_) => quote! {
+            serializer.begin_seq(#len)?;
+            #(
+                serializer.seq_element()?;
+                ::serialize::Serialize::serialize(#included, serializer)?;
+            )*
+            serializer.end_seq()
+        },
+        Fields::Unit => quote! {
+            serializer.serialize_unit()
+        },
+    };
+    Ok((
+        pattern,
+        quote! {{
+            #( let _ = #skipped; )*
+            #serialize
+        }},
+    ))
+}
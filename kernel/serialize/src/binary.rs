@@ -0,0 +1,154 @@
+//! Serialization into a compact binary format.
+//!
+//! The format isn't self-describing: reading it back requires knowing the type of the value.
+//! Values are encoded as follows:
+//! * Unsigned integers are LEB128 varints, signed integers are zigzag-encoded varints,
+//!   and floats are 8 little-endian bytes.
+//! * Bools are a single byte, `0` or `1`.
+//! * Strings and byte slices are their length as a varint, followed by their bytes.
+//! * `()` and unit structs are nothing at all.
+//! * `None` is a `0` byte, and `Some(value)` is a `1` byte followed by `value`.
+//! * Sequences and maps are their length as a varint, followed by their elements
+//!   or alternating keys and values.
+//! * Structs are their fields in declaration order, without names.
+//! * Enum variants are their index as a varint, followed by their data.
+
+use crate::{Serialize, Serializer};
+use alloc::vec::Vec;
+use core::convert::Infallible;
+
+/// A [`Serializer`] that appends the compact binary format to a byte vector.
+///
+/// This serializer never fails.
+pub struct BinarySerializer<'v> {
+    bytes: &'v mut Vec<u8>,
+}
+
+impl<'v> BinarySerializer<'v> {
+    /// Creates a serializer that appends to the given `bytes`.
+    pub fn new(bytes: &'v mut Vec<u8>) -> BinarySerializer<'v> {
+        BinarySerializer { bytes }
+    }
+
+    fn write_varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.bytes.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        self.bytes.push(value as u8);
+    }
+}
+
+impl Serializer for BinarySerializer<'_> {
+    type Error = Infallible;
+
+    fn serialize_bool(&mut self, value: bool) -> Result<(), Infallible> {
+        self.bytes.push(value as u8);
+        Ok(())
+    }
+
+    fn serialize_u64(&mut self, value: u64) -> Result<(), Infallible> {
+        self.write_varint(value);
+        Ok(())
+    }
+
+    fn serialize_i64(&mut self, value: i64) -> Result<(), Infallible> {
+        self.write_varint(((value << 1) ^ (value >> 63)) as u64);
+        Ok(())
+    }
+
+    fn serialize_f64(&mut self, value: f64) -> Result<(), Infallible> {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+        Ok(())
+    }
+
+    fn serialize_str(&mut self, value: &str) -> Result<(), Infallible> {
+        self.serialize_bytes(value.as_bytes())
+    }
+
+    fn serialize_bytes(&mut self, value: &[u8]) -> Result<(), Infallible> {
+        self.write_varint(value.len() as u64);
+        self.bytes.extend_from_slice(value);
+        Ok(())
+    }
+
+    fn serialize_unit(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+
+    fn serialize_none(&mut self) -> Result<(), Infallible> {
+        self.bytes.push(0);
+        Ok(())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Infallible> {
+        self.bytes.push(1);
+        value.serialize(self)
+    }
+
+    fn begin_seq(&mut self, len: usize) -> Result<(), Infallible> {
+        self.write_varint(len as u64);
+        Ok(())
+    }
+
+    fn seq_element(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+
+    fn end_seq(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+
+    fn begin_map(&mut self, len: usize) -> Result<(), Infallible> {
+        self.write_varint(len as u64);
+        Ok(())
+    }
+
+    fn map_key(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+
+    fn map_value(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+
+    fn end_map(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+
+    fn begin_struct(&mut self, _name: &'static str, _len: usize) -> Result<(), Infallible> {
+        Ok(())
+    }
+
+    fn struct_field(&mut self, _name: &'static str) -> Result<(), Infallible> {
+        Ok(())
+    }
+
+    fn end_struct(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+
+    fn serialize_unit_variant(
+        &mut self,
+        _name: &'static str,
+        index: u32,
+        _variant: &'static str,
+    ) -> Result<(), Infallible> {
+        self.write_varint(index as u64);
+        Ok(())
+    }
+
+    fn begin_variant(
+        &mut self,
+        _name: &'static str,
+        index: u32,
+        _variant: &'static str,
+    ) -> Result<(), Infallible> {
+        self.write_varint(index as u64);
+        Ok(())
+    }
+
+    fn end_variant(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+}
@@ -0,0 +1,225 @@
+//! Serialization into JSON.
+//!
+//! Values are mapped onto JSON as follows:
+//! * Integers and floats are JSON numbers, except that NaN and infinities are `null`.
+//! * Strings and chars are JSON strings, and byte slices are arrays of numbers.
+//! * `()`, unit structs, and `None` are `null`, and `Some(value)` is just `value`.
+//! * Sequences, tuples, and tuple structs are arrays, and newtype structs are their inner value.
+//! * Maps are objects, whose keys must be strings, integers, bools, or unit enum variants.
+//! * Structs are objects with a member for each field.
+//! * Unit enum variants are the variant name as a string, and other variants are
+//!   an object with a single member named after the variant, whose value is its data.
+
+use crate::{Error, Serialize, Serializer};
+use core::fmt::{self, Write};
+
+/// A [`Serializer`] that writes compact JSON, without any whitespace, into a [`fmt::Write`]r.
+pub struct JsonSerializer<W> {
+    writer: W,
+    /// Whether the next element of the innermost array or object is its first element,
+    /// i.e., doesn't need a preceding comma.
+    first: bool,
+    /// Whether a map key is being serialized.
+    in_key: bool,
+}
+
+impl<W: Write> JsonSerializer<W> {
+    /// Creates a serializer that writes into the given `writer`.
+    pub fn new(writer: W) -> JsonSerializer<W> {
+        JsonSerializer { writer, first: true, in_key: false }
+    }
+
+    /// Returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    /// Writes a comma unless this is the first element of the innermost array or object.
+    fn separator(&mut self) -> Result<(), Error> {
+        if !self.first {
+            self.writer.write_char(',')?;
+        }
+        self.first = false;
+        Ok(())
+    }
+
+    /// Writes a value that isn't a string, which must be quoted if it's used as a map key.
+    fn write_scalar(&mut self, args: fmt::Arguments) -> Result<(), Error> {
+        if self.in_key {
+            self.writer.write_char('"')?;
+            self.writer.write_fmt(args)?;
+            self.writer.write_char('"')?;
+        } else {
+            self.writer.write_fmt(args)?;
+        }
+        Ok(())
+    }
+
+    /// Returns an error if a map key is being serialized, for values that can't be keys.
+    fn not_key(&self) -> Result<(), Error> {
+        if self.in_key {
+            Err(Error::InvalidKey)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn write_str_literal(&mut self, value: &str) -> Result<(), Error> {
+        self.writer.write_char('"')?;
+        let mut unescaped_start = 0;
+        for (i, c) in value.char_indices() {
+            let escape = match c {
+                '"' => "\\\"",
+                '\\' => "\\\\",
+                '\n' => "\\n",
+                '\r' => "\\r",
+                '\t' => "\\t",
+                c if (c as u32) < 0x20 => "",
+                _ => continue,
+            };
+            self.writer.write_str(&value[unescaped_start..i])?;
+            if escape.is_empty() {
+                write!(self.writer, "\\u{:04x}", c as u32)?;
+            } else {
+                self.writer.write_str(escape)?;
+            }
+            unescaped_start = i + c.len_utf8();
+        }
+        self.writer.write_str(&value[unescaped_start..])?;
+        self.writer.write_char('"')?;
+        Ok(())
+    }
+}
+
+impl<W: Write> Serializer for JsonSerializer<W> {
+    type Error = Error;
+
+    fn serialize_bool(&mut self, value: bool) -> Result<(), Error> {
+        self.write_scalar(format_args!("{}", value))
+    }
+
+    fn serialize_u64(&mut self, value: u64) -> Result<(), Error> {
+        self.write_scalar(format_args!("{}", value))
+    }
+
+    fn serialize_i64(&mut self, value: i64) -> Result<(), Error> {
+        self.write_scalar(format_args!("{}", value))
+    }
+
+    fn serialize_f64(&mut self, value: f64) -> Result<(), Error> {
+        self.not_key()?;
+        if value.is_finite() {
+            write!(self.writer, "{}", value)?;
+        } else {
+            self.writer.write_str("null")?;
+        }
+        Ok(())
+    }
+
+    fn serialize_str(&mut self, value: &str) -> Result<(), Error> {
+        self.write_str_literal(value)
+    }
+
+    fn serialize_bytes(&mut self, value: &[u8]) -> Result<(), Error> {
+        self.collect_seq(value.len(), value)
+    }
+
+    fn serialize_unit(&mut self) -> Result<(), Error> {
+        self.not_key()?;
+        self.writer.write_str("null")?;
+        Ok(())
+    }
+
+    fn serialize_none(&mut self) -> Result<(), Error> {
+        self.serialize_unit()
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    fn begin_seq(&mut self, _len: usize) -> Result<(), Error> {
+        self.not_key()?;
+        self.writer.write_char('[')?;
+        self.first = true;
+        Ok(())
+    }
+
+    fn seq_element(&mut self) -> Result<(), Error> {
+        self.separator()
+    }
+
+    fn end_seq(&mut self) -> Result<(), Error> {
+        self.writer.write_char(']')?;
+        self.first = false;
+        Ok(())
+    }
+
+    fn begin_map(&mut self, _len: usize) -> Result<(), Error> {
+        self.not_key()?;
+        self.writer.write_char('{')?;
+        self.first = true;
+        Ok(())
+    }
+
+    fn map_key(&mut self) -> Result<(), Error> {
+        self.separator()?;
+        self.in_key = true;
+        Ok(())
+    }
+
+    fn map_value(&mut self) -> Result<(), Error> {
+        self.in_key = false;
+        self.writer.write_char(':')?;
+        Ok(())
+    }
+
+    fn end_map(&mut self) -> Result<(), Error> {
+        self.writer.write_char('}')?;
+        self.first = false;
+        Ok(())
+    }
+
+    fn begin_struct(&mut self, _name: &'static str, _len: usize) -> Result<(), Error> {
+        self.begin_map(0)
+    }
+
+    fn struct_field(&mut self, name: &'static str) -> Result<(), Error> {
+        self.separator()?;
+        self.write_str_literal(name)?;
+        self.writer.write_char(':')?;
+        Ok(())
+    }
+
+    fn end_struct(&mut self) -> Result<(), Error> {
+        self.end_map()
+    }
+
+    fn serialize_unit_variant(
+        &mut self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<(), Error> {
+        self.write_str_literal(variant)
+    }
+
+    fn begin_variant(
+        &mut self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<(), Error> {
+        self.not_key()?;
+        self.writer.write_char('{')?;
+        self.write_str_literal(variant)?;
+        self.writer.write_char(':')?;
+        Ok(())
+    }
+
+    fn end_variant(&mut self) -> Result<(), Error> {
+        self.writer.write_char('}')?;
+        self.first = false;
+        Ok(())
+    }
+}
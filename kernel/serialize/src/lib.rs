@@ -0,0 +1,466 @@
+//! A lightweight, streaming serialization framework for exporting kernel state.
+//!
+//! Types implement [`Serialize`], usually via `#[derive(Serialize)]`,
+//! by describing their structure to a [`Serializer`], which writes it out in a specific format
+//! as it goes, without building an intermediate representation.
+//! Two formats are provided:
+//! * JSON, via [`to_json()`] or [`json::JsonSerializer`], e.g., for HTTP endpoints.
+//! * A compact binary format, via [`to_binary()`] or [`binary::BinarySerializer`],
+//!   e.g., for writing state to storage or sending it to another machine.
+//!
+//! Unlike serde, this crate only supports serialization, not deserialization.
+//!
+//! ```
+//! use serialize::Serialize;
+//!
+//! #[derive(Serialize)]
+//! struct Task {
+//!     id: usize,
+//!     name: &'static str,
+//!     #[serialize(rename = "cpu")]
+//!     running_on: Option<u32>,
+//! }
+//!
+//! let task = Task { id: 7, name: "shell", running_on: None };
+//! assert_eq!(serialize::to_json(&task).unwrap(), r#"{"id":7,"name":"shell","cpu":null}"#);
+//! ```
+//!
+//! The derive macro supports structs and enums, including generic ones,
+//! and the following attributes:
+//! * `#[serialize(rename = "name")]` on a field or variant serializes it with the given name.
+//! * `#[serialize(skip)]` on a field omits it.
+
+#![no_std]
+
+extern crate alloc;
+// Allows the derive macro's generated code to refer to this crate as `::serialize` within it.
+extern crate self as serialize;
+
+pub mod binary;
+pub mod json;
+
+pub use serialize_derive::Serialize;
+
+use alloc::{
+    borrow::{Cow, ToOwned},
+    boxed::Box,
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    rc::Rc,
+    string::String,
+    sync::Arc,
+    vec::Vec,
+};
+use core::{fmt, time::Duration};
+
+/// An error that occurred while serializing a value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The underlying writer failed.
+    Write,
+    /// A map key was of a type that the format doesn't support as a key,
+    /// e.g., a sequence used as a JSON object key.
+    InvalidKey,
+}
+
+impl From<fmt::Error> for Error {
+    fn from(_: fmt::Error) -> Self {
+        Error::Write
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Error::Write => "failed to write serialized data",
+            Error::InvalidKey => "map key type isn't supported by this format",
+        })
+    }
+}
+
+/// A value that can be serialized into any format.
+pub trait Serialize {
+    /// Describes this value to the given `serializer`.
+    fn serialize<S: Serializer + ?Sized>(&self, serializer: &mut S) -> Result<(), S::Error>;
+}
+
+/// A format that values can be serialized into.
+///
+/// Compound values are serialized by calling a `begin_*` method, then describing each part,
+/// then calling the matching `end_*` method:
+/// * Sequences: [`begin_seq()`], then [`seq_element()`] followed by each element,
+///   then [`end_seq()`].
+/// * Maps: [`begin_map()`], then [`map_key()`] followed by each key
+///   and [`map_value()`] followed by its value, then [`end_map()`].
+/// * Structs: [`begin_struct()`], then [`struct_field()`] followed by each field's value,
+///   then [`end_struct()`].
+/// * Enum variants that contain data: [`begin_variant()`], then the data
+///   (a single value, a sequence, or a struct), then [`end_variant()`].
+///
+/// [`begin_seq()`]: Serializer::begin_seq
+/// [`seq_element()`]: Serializer::seq_element
+/// [`end_seq()`]: Serializer::end_seq
+/// [`begin_map()`]: Serializer::begin_map
+/// [`map_key()`]: Serializer::map_key
+/// [`map_value()`]: Serializer::map_value
+/// [`end_map()`]: Serializer::end_map
+/// [`begin_struct()`]: Serializer::begin_struct
+/// [`struct_field()`]: Serializer::struct_field
+/// [`end_struct()`]: Serializer::end_struct
+/// [`begin_variant()`]: Serializer::begin_variant
+/// [`end_variant()`]: Serializer::end_variant
+pub trait Serializer {
+    type Error;
+
+    fn serialize_bool(&mut self, value: bool) -> Result<(), Self::Error>;
+    fn serialize_u64(&mut self, value: u64) -> Result<(), Self::Error>;
+    fn serialize_i64(&mut self, value: i64) -> Result<(), Self::Error>;
+    fn serialize_f64(&mut self, value: f64) -> Result<(), Self::Error>;
+    fn serialize_str(&mut self, value: &str) -> Result<(), Self::Error>;
+    fn serialize_bytes(&mut self, value: &[u8]) -> Result<(), Self::Error>;
+    /// Serializes a value that contains no data, e.g., `()` or a unit struct.
+    fn serialize_unit(&mut self) -> Result<(), Self::Error>;
+    /// Serializes an absent optional value.
+    fn serialize_none(&mut self) -> Result<(), Self::Error>;
+    /// Serializes a present optional value.
+    fn serialize_some<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Self::Error>;
+
+    /// Begins a sequence of `len` elements.
+    fn begin_seq(&mut self, len: usize) -> Result<(), Self::Error>;
+    /// Precedes each element of a sequence.
+    fn seq_element(&mut self) -> Result<(), Self::Error>;
+    fn end_seq(&mut self) -> Result<(), Self::Error>;
+
+    /// Begins a map of `len` entries.
+    fn begin_map(&mut self, len: usize) -> Result<(), Self::Error>;
+    /// Precedes the key of each entry of a map.
+    fn map_key(&mut self) -> Result<(), Self::Error>;
+    /// Precedes the value of each entry of a map.
+    fn map_value(&mut self) -> Result<(), Self::Error>;
+    fn end_map(&mut self) -> Result<(), Self::Error>;
+
+    /// Begins a struct with the given name and number of fields.
+    fn begin_struct(&mut self, name: &'static str, len: usize) -> Result<(), Self::Error>;
+    /// Precedes the value of the field with the given name.
+    fn struct_field(&mut self, name: &'static str) -> Result<(), Self::Error>;
+    fn end_struct(&mut self) -> Result<(), Self::Error>;
+
+    /// Serializes an enum variant that contains no data.
+    fn serialize_unit_variant(
+        &mut self,
+        name: &'static str,
+        index: u32,
+        variant: &'static str,
+    ) -> Result<(), Self::Error>;
+    /// Begins an enum variant that contains data.
+    fn begin_variant(
+        &mut self,
+        name: &'static str,
+        index: u32,
+        variant: &'static str,
+    ) -> Result<(), Self::Error>;
+    fn end_variant(&mut self) -> Result<(), Self::Error>;
+
+    /// Serializes the items of `iter`, which must yield exactly `len` items, as a sequence.
+    fn collect_seq<I>(&mut self, len: usize, iter: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator,
+        I::Item: Serialize,
+    {
+        self.begin_seq(len)?;
+        for item in iter {
+            self.seq_element()?;
+            item.serialize(self)?;
+        }
+        self.end_seq()
+    }
+
+    /// Serializes the entries of `iter`, which must yield exactly `len` entries, as a map.
+    fn collect_map<K, V, I>(&mut self, len: usize, iter: I) -> Result<(), Self::Error>
+    where
+        K: Serialize,
+        V: Serialize,
+        I: IntoIterator<Item = (K, V)>,
+    {
+        self.begin_map(len)?;
+        for (key, value) in iter {
+            self.map_key()?;
+            key.serialize(self)?;
+            self.map_value()?;
+            value.serialize(self)?;
+        }
+        self.end_map()
+    }
+}
+
+/// Returns the given value serialized as compact JSON.
+pub fn to_json<T: Serialize + ?Sized>(value: &T) -> Result<String, Error> {
+    let mut json = String::new();
+    value.serialize(&mut json::JsonSerializer::new(&mut json))?;
+    Ok(json)
+}
+
+/// Returns the given value serialized in the compact binary format; see [`binary`].
+pub fn to_binary<T: Serialize + ?Sized>(value: &T) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    match value.serialize(&mut binary::BinarySerializer::new(&mut bytes)) {
+        Ok(()) => bytes,
+        Err(never) => match never {},
+    }
+}
+
+macro_rules! impl_unsigned {
+    ($($ty:ty),*) => {$(
+        impl Serialize for $ty {
+            fn serialize<S: Serializer + ?Sized>(&self, serializer: &mut S) -> Result<(), S::Error> {
+                serializer.serialize_u64(*self as u64)
+            }
+        }
+    )*};
+}
+
+macro_rules! impl_signed {
+    ($($ty:ty),*) => {$(
+        impl Serialize for $ty {
+            fn serialize<S: Serializer + ?Sized>(&self, serializer: &mut S) -> Result<(), S::Error> {
+                serializer.serialize_i64(*self as i64)
+            }
+        }
+    )*};
+}
+
+impl_unsigned!(u8, u16, u32, u64, usize);
+impl_signed!(i8, i16, i32, i64, isize);
+
+impl Serialize for f32 {
+    fn serialize<S: Serializer + ?Sized>(&self, serializer: &mut S) -> Result<(), S::Error> {
+        serializer.serialize_f64(*self as f64)
+    }
+}
+
+impl Serialize for f64 {
+    fn serialize<S: Serializer + ?Sized>(&self, serializer: &mut S) -> Result<(), S::Error> {
+        serializer.serialize_f64(*self)
+    }
+}
+
+impl Serialize for bool {
+    fn serialize<S: Serializer + ?Sized>(&self, serializer: &mut S) -> Result<(), S::Error> {
+        serializer.serialize_bool(*self)
+    }
+}
+
+impl Serialize for char {
+    fn serialize<S: Serializer + ?Sized>(&self, serializer: &mut S) -> Result<(), S::Error> {
+        serializer.serialize_str(self.encode_utf8(&mut [0; 4]))
+    }
+}
+
+impl Serialize for str {
+    fn serialize<S: Serializer + ?Sized>(&self, serializer: &mut S) -> Result<(), S::Error> {
+        serializer.serialize_str(self)
+    }
+}
+
+impl Serialize for String {
+    fn serialize<S: Serializer + ?Sized>(&self, serializer: &mut S) -> Result<(), S::Error> {
+        serializer.serialize_str(self)
+    }
+}
+
+impl Serialize for () {
+    fn serialize<S: Serializer + ?Sized>(&self, serializer: &mut S) -> Result<(), S::Error> {
+        serializer.serialize_unit()
+    }
+}
+
+/// A [`Duration`] is serialized as a whole number of nanoseconds.
+impl Serialize for Duration {
+    fn serialize<S: Serializer + ?Sized>(&self, serializer: &mut S) -> Result<(), S::Error> {
+        serializer.serialize_u64(self.as_nanos() as u64)
+    }
+}
+
+impl<T: Serialize> Serialize for Option<T> {
+    fn serialize<S: Serializer + ?Sized>(&self, serializer: &mut S) -> Result<(), S::Error> {
+        match self {
+            Some(value) => serializer.serialize_some(value),
+            None => serializer.serialize_none(),
+        }
+    }
+}
+
+impl<T: Serialize> Serialize for [T] {
+    fn serialize<S: Serializer + ?Sized>(&self, serializer: &mut S) -> Result<(), S::Error> {
+        serializer.collect_seq(self.len(), self)
+    }
+}
+
+impl<T: Serialize, const N: usize> Serialize for [T; N] {
+    fn serialize<S: Serializer + ?Sized>(&self, serializer: &mut S) -> Result<(), S::Error> {
+        serializer.collect_seq(N, self)
+    }
+}
+
+impl<T: Serialize> Serialize for Vec<T> {
+    fn serialize<S: Serializer + ?Sized>(&self, serializer: &mut S) -> Result<(), S::Error> {
+        serializer.collect_seq(self.len(), self)
+    }
+}
+
+impl<T: Serialize> Serialize for VecDeque<T> {
+    fn serialize<S: Serializer + ?Sized>(&self, serializer: &mut S) -> Result<(), S::Error> {
+        serializer.collect_seq(self.len(), self)
+    }
+}
+
+impl<T: Serialize> Serialize for BTreeSet<T> {
+    fn serialize<S: Serializer + ?Sized>(&self, serializer: &mut S) -> Result<(), S::Error> {
+        serializer.collect_seq(self.len(), self)
+    }
+}
+
+impl<K: Serialize, V: Serialize> Serialize for BTreeMap<K, V> {
+    fn serialize<S: Serializer + ?Sized>(&self, serializer: &mut S) -> Result<(), S::Error> {
+        serializer.collect_map(self.len(), self)
+    }
+}
+
+macro_rules! impl_deref {
+    ($($ty:ty),*) => {$(
+        impl<T: Serialize + ?Sized> Serialize for $ty {
+            fn serialize<S: Serializer + ?Sized>(&self, serializer: &mut S) -> Result<(), S::Error> {
+                (**self).serialize(serializer)
+            }
+        }
+    )*};
+}
+
+impl_deref!(&T, &mut T, Box<T>, Rc<T>, Arc<T>);
+
+impl<T: Serialize + ToOwned + ?Sized> Serialize for Cow<'_, T> {
+    fn serialize<S: Serializer + ?Sized>(&self, serializer: &mut S) -> Result<(), S::Error> {
+        (**self).serialize(serializer)
+    }
+}
+
+macro_rules! impl_tuple {
+    ($len:expr => $($name:ident $index:tt),+) => {
+        impl<$($name: Serialize),+> Serialize for ($($name,)+) {
+            fn serialize<S: Serializer + ?Sized>(&self, serializer: &mut S) -> Result<(), S::Error> {
+                serializer.begin_seq($len)?;
+                $(
+                    serializer.seq_element()?;
+                    self.$index.serialize(serializer)?;
+                )+
+                serializer.end_seq()
+            }
+        }
+    };
+}
+
+impl_tuple!(1 => A 0);
+impl_tuple!(2 => A 0, B 1);
+impl_tuple!(3 => A 0, B 1, C 2);
+impl_tuple!(4 => A 0, B 1, C 2, D 3);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[derive(Serialize)]
+    struct Point {
+        x: i32,
+        y: i32,
+    }
+
+    #[derive(Serialize)]
+    struct Meters(f64);
+
+    #[derive(Serialize)]
+    struct Pair<T>(T, T);
+
+    #[derive(Serialize)]
+    struct Unit;
+
+    #[derive(Serialize)]
+    struct Task<'a> {
+        #[serialize(rename = "task_id")]
+        id: usize,
+        name: &'a str,
+        #[serialize(skip)]
+        _secret: u64,
+        cpu: Option<u8>,
+        children: Vec<Point>,
+    }
+
+    #[derive(Serialize)]
+    enum Shape {
+        Empty,
+        #[serialize(rename = "dot")]
+        Point(Point),
+        Line(Point, Point),
+        Circle { radius: u32 },
+    }
+
+    #[test]
+    fn json() {
+        let task = Task {
+            id: 3,
+            name: "a \"quoted\"\nname",
+            _secret: 42,
+            cpu: Some(1),
+            children: vec![Point { x: 1, y: -2 }],
+        };
+        assert_eq!(
+            to_json(&task).unwrap(),
+            r#"{"task_id":3,"name":"a \"quoted\"\nname","cpu":1,"children":[{"x":1,"y":-2}]}"#,
+        );
+        assert_eq!(to_json(&Meters(1.5)).unwrap(), "1.5");
+        assert_eq!(to_json(&Pair("a", "b")).unwrap(), r#"["a","b"]"#);
+        assert_eq!(to_json(&Unit).unwrap(), "null");
+        assert_eq!(to_json(&f64::NAN).unwrap(), "null");
+        assert_eq!(to_json(&Vec::<u8>::new()).unwrap(), "[]");
+
+        let shapes = [
+            Shape::Empty,
+            Shape::Point(Point { x: 0, y: 0 }),
+            Shape::Line(Point { x: 0, y: 0 }, Point { x: 1, y: 1 }),
+            Shape::Circle { radius: 2 },
+        ];
+        assert_eq!(
+            to_json(&shapes).unwrap(),
+            concat!(
+                r#"["Empty",{"dot":{"x":0,"y":0}},"#,
+                r#"{"Line":[{"x":0,"y":0},{"x":1,"y":1}]},{"Circle":{"radius":2}}]"#,
+            ),
+        );
+
+        let mut map = BTreeMap::new();
+        map.insert(2, vec![(); 2]);
+        map.insert(10, Vec::new());
+        assert_eq!(to_json(&map).unwrap(), r#"{"2":[null,null],"10":[]}"#);
+        let mut map = BTreeMap::new();
+        map.insert(vec![1], 1);
+        assert_eq!(to_json(&map), Err(Error::InvalidKey));
+    }
+
+    #[test]
+    fn binary() {
+        let task = Task {
+            id: 300,
+            name: "ab",
+            _secret: 42,
+            cpu: None,
+            children: vec![Point { x: -1, y: 64 }],
+        };
+        assert_eq!(
+            to_binary(&task),
+            [0xAC, 0x02, 2, b'a', b'b', 0, 1, 1, 0x80, 0x01],
+        );
+        assert_eq!(to_binary(&Shape::Circle { radius: 5 }), [3, 5]);
+        assert_eq!(to_binary(&Shape::Empty), [0]);
+        assert_eq!(to_binary(&Some(1.0f64))[..2], [1, 0]);
+        assert_eq!(to_binary(&i64::MIN), [0xFF; 9].iter().copied().chain([1]).collect::<Vec<u8>>());
+    }
+}
@@ -0,0 +1,90 @@
+//! The policy that decides whether and when a crashed service is restarted.
+
+use core::time::Duration;
+
+/// An exponential backoff between restarts, with a limit on consecutive crashes.
+///
+/// A service that runs for at least `stable_after` before crashing is considered to have recovered,
+/// so its next restart starts over from the initial delay.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Backoff {
+    /// The delay before the first restart after a crash.
+    pub initial: Duration,
+    /// The maximum delay before a restart.
+    pub max: Duration,
+    /// How long a service must run for its crash to no longer count as consecutive.
+    pub stable_after: Duration,
+    /// The number of consecutive crashes after which the service isn't restarted anymore.
+    pub max_consecutive_crashes: u32,
+}
+
+impl Backoff {
+    /// A backoff from 100ms up to 10s, which gives up after 8 consecutive crashes.
+    pub const DEFAULT: Backoff = Backoff {
+        initial: Duration::from_millis(100),
+        max: Duration::from_secs(10),
+        stable_after: Duration::from_secs(30),
+        max_consecutive_crashes: 8,
+    };
+
+    /// Returns the number of consecutive crashes after a crash that occurred
+    /// once the service had been running for `uptime`.
+    pub fn consecutive_crashes(&self, previous: u32, uptime: Duration) -> u32 {
+        if uptime >= self.stable_after {
+            1
+        } else {
+            previous.saturating_add(1)
+        }
+    }
+
+    /// Returns how long to wait before restarting a service that has crashed `consecutive` times in a row,
+    /// or `None` if it shouldn't be restarted.
+    pub fn delay(&self, consecutive: u32) -> Option<Duration> {
+        if consecutive == 0 {
+            return Some(Duration::ZERO);
+        }
+        if consecutive > self.max_consecutive_crashes {
+            return None;
+        }
+        let factor = 1u32.checked_shl(consecutive - 1).unwrap_or(u32::MAX);
+        Some(self.initial.checked_mul(factor).map_or(self.max, |delay| delay.min(self.max)))
+    }
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Backoff::DEFAULT
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delay_doubles_up_to_max() {
+        let backoff = Backoff::DEFAULT;
+        let delays: [u64; 8] = [100, 200, 400, 800, 1600, 3200, 6400, 10_000];
+        for (i, &millis) in delays.iter().enumerate() {
+            assert_eq!(backoff.delay(i as u32 + 1), Some(Duration::from_millis(millis)));
+        }
+    }
+
+    #[test]
+    fn gives_up_after_max_consecutive_crashes() {
+        let backoff = Backoff { max_consecutive_crashes: 3, ..Backoff::DEFAULT };
+        assert!(backoff.delay(3).is_some());
+        assert_eq!(backoff.delay(4), None);
+
+        let unlimited = Backoff { max_consecutive_crashes: u32::MAX, ..Backoff::DEFAULT };
+        assert_eq!(unlimited.delay(40), Some(unlimited.max));
+    }
+
+    #[test]
+    fn stable_service_resets_consecutive_crashes() {
+        let backoff = Backoff::DEFAULT;
+        assert_eq!(backoff.consecutive_crashes(5, Duration::from_secs(1)), 6);
+        assert_eq!(backoff.consecutive_crashes(5, backoff.stable_after), 1);
+        assert_eq!(backoff.consecutive_crashes(u32::MAX, Duration::ZERO), u32::MAX);
+    }
+}
@@ -0,0 +1,397 @@
+//! Restartable service domains, which contain the failure of a service crate.
+//!
+//! A service domain runs a service, i.e., an application-like crate with a `main` function,
+//! in its own application [`CrateNamespace`](mod_mgmt::CrateNamespace) under a supervisor task.
+//! If the service panics (or is killed by an exception), its task is unwound,
+//! and the supervisor restarts it from scratch:
+//! 1. The crates loaded into the old namespace are unloaded along with it,
+//!    and the service crate is loaded fresh into a new namespace.
+//! 2. The domain's channels are re-created, so the new instance of the service
+//!    doesn't inherit messages or state from the old one.
+//! 3. If the service crate registers a device driver, its devices are rebound to the fresh driver.
+//! 4. Subscribers are notified that the service crashed and was restarted,
+//!    so that they can obtain new [`sender()`]s for its channels.
+//!
+//! Restarts are delayed by an exponential [`Backoff`], and a service that keeps crashing
+//! is eventually given up on.
+//!
+//! ```ignore
+//! ServiceDomain::new("netstack", "netstack_service")
+//!     .channel::<Request>("requests", 16)
+//!     .start()?;
+//!
+//! // In the service:
+//! let requests = service_domain::receiver::<Request>("netstack", "requests")?;
+//! // In a client:
+//! let requests = service_domain::sender::<Request>("netstack", "requests")?;
+//! ```
+
+#![no_std]
+
+extern crate alloc;
+
+mod backoff;
+
+pub use backoff::Backoff;
+
+use alloc::{
+    boxed::Box,
+    collections::BTreeMap,
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{any::Any, marker::PhantomData};
+use log::{error, info, warn};
+use path::PathBuf;
+use sync_channel::{Receiver, Sender};
+use sync_irq::IrqSafeMutex;
+use task::{ExitValue, JoinableTaskRef, KillReason, TaskRef};
+use time::Instant;
+
+/// All service domains, by name.
+static DOMAINS: IrqSafeMutex<BTreeMap<String, DomainState>> = IrqSafeMutex::new(BTreeMap::new());
+
+/// The senders of the channels on which notifications are published.
+static SUBSCRIBERS: IrqSafeMutex<Vec<Sender<Notification>>> = IrqSafeMutex::new(Vec::new());
+
+/// The status of a service domain.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Status {
+    /// The service's crates are being loaded.
+    Starting,
+    /// The service is running.
+    Running,
+    /// The service crashed and is waiting to be restarted.
+    BackingOff,
+    /// The service crashed too many times in a row, or couldn't be restarted.
+    Failed,
+    /// The service returned from its `main` function, or was stopped via [`stop()`].
+    Stopped,
+}
+
+/// A change in the state of a service domain.
+#[derive(Clone, Debug)]
+pub struct Notification {
+    pub domain: String,
+    pub event: Event,
+}
+
+/// The events that subscribers are notified of.
+#[derive(Clone, Debug)]
+pub enum Event {
+    /// The given generation of the service was started, with newly-created channels.
+    /// Every generation after the first is a restart.
+    Started { generation: u64 },
+    /// The service crashed for the given reason.
+    Crashed { reason: String, crashes: u32 },
+    /// The service won't be restarted anymore.
+    Failed { crashes: u32 },
+    /// The service exited normally or was stopped.
+    Stopped,
+}
+
+/// A snapshot of the state of a service domain.
+#[derive(Clone, Debug)]
+pub struct DomainInfo {
+    pub name: String,
+    pub status: Status,
+    /// The number of times the service has been started.
+    pub generation: u64,
+    /// The total number of times the service has crashed.
+    pub crashes: u32,
+    /// The number of times the service has crashed in a row, without running stably in between.
+    pub consecutive_crashes: u32,
+    pub last_crash: Option<String>,
+}
+
+/// The state of a service domain that's shared between its supervisor and other tasks.
+struct DomainState {
+    info: DomainInfo,
+    /// The current instance of the service.
+    task: Option<TaskRef>,
+    /// The endpoints of each channel: the receiver for the service, and the sender for its clients.
+    endpoints: BTreeMap<String, (Box<dyn Any + Send>, Box<dyn Any + Send>)>,
+    stop_requested: bool,
+}
+
+/// Creates the endpoints of one of a domain's channels.
+trait ChannelFactory: Send {
+    fn create(&self) -> (Box<dyn Any + Send>, Box<dyn Any + Send>);
+}
+
+struct TypedChannel<T> {
+    capacity: usize,
+    _message: PhantomData<fn() -> T>,
+}
+
+impl<T: Send + 'static> ChannelFactory for TypedChannel<T> {
+    fn create(&self) -> (Box<dyn Any + Send>, Box<dyn Any + Send>) {
+        let (sender, receiver) = sync_channel::new_channel::<T>(self.capacity);
+        (Box::new(receiver), Box::new(sender))
+    }
+}
+
+/// The description of a service domain, which is started via [`ServiceDomain::start()`].
+pub struct ServiceDomain {
+    name: String,
+    crate_object_file: PathBuf,
+    args: Vec<String>,
+    channels: Vec<(String, Box<dyn ChannelFactory>)>,
+    backoff: Backoff,
+}
+
+impl ServiceDomain {
+    /// Creates a domain with the given name for the service in the given crate object file,
+    /// which is found in the default application namespace's directory.
+    pub fn new(name: &str, crate_object_file: &str) -> ServiceDomain {
+        ServiceDomain {
+            name: name.to_string(),
+            crate_object_file: PathBuf::from(crate_object_file.to_string()),
+            args: Vec::new(),
+            channels: Vec::new(),
+            backoff: Backoff::DEFAULT,
+        }
+    }
+
+    /// Sets the arguments passed to the service's `main` function each time it's started.
+    pub fn args(mut self, args: Vec<String>) -> ServiceDomain {
+        self.args = args;
+        self
+    }
+
+    /// Adds a channel of messages of type `T` from clients to the service,
+    /// which is re-created each time the service is started.
+    pub fn channel<T: Send + 'static>(mut self, name: &str, capacity: usize) -> ServiceDomain {
+        self.channels.push((name.to_string(), Box::new(TypedChannel::<T> { capacity, _message: PhantomData })));
+        self
+    }
+
+    /// Sets the backoff between restarts, which is [`Backoff::DEFAULT`] by default.
+    pub fn backoff(mut self, backoff: Backoff) -> ServiceDomain {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Starts the service under a new supervisor task, which restarts it whenever it crashes.
+    pub fn start(self) -> Result<(), &'static str> {
+        {
+            let mut domains = DOMAINS.lock();
+            if domains.contains_key(&self.name) {
+                return Err("service_domain: a domain with that name already exists");
+            }
+            domains.insert(self.name.clone(), DomainState {
+                info: DomainInfo {
+                    name: self.name.clone(),
+                    status: Status::Starting,
+                    generation: 0,
+                    crashes: 0,
+                    consecutive_crashes: 0,
+                    last_crash: None,
+                },
+                task: None,
+                endpoints: BTreeMap::new(),
+                stop_requested: false,
+            });
+        }
+        let name = self.name.clone();
+        let result = spawn::new_task_builder(supervise, self)
+            .name(format!("service_domain_{}", name))
+            .spawn();
+        if result.is_err() {
+            DOMAINS.lock().remove(&name);
+        }
+        result.map(|_| ())
+    }
+}
+
+/// The entry point of a domain's supervisor task.
+fn supervise(domain: ServiceDomain) {
+    let mut previous: Option<JoinableTaskRef> = None;
+    loop {
+        let Some(generation) = with_state(&domain.name, |state| {
+            if state.stop_requested {
+                return None;
+            }
+            state.info.status = Status::Starting;
+            state.info.generation += 1;
+            state.endpoints = domain.channels.iter()
+                .map(|(name, factory)| (name.clone(), factory.create()))
+                .collect();
+            Some(state.info.generation)
+        }).flatten() else {
+            break;
+        };
+
+        let task = match start_service(&domain, generation, previous.take()) {
+            Ok(task) => task,
+            Err(e) => {
+                error!("service_domain: couldn't start service {:?}: {}", domain.name, e);
+                set_status(&domain.name, Status::Failed);
+                publish(&domain.name, Event::Failed { crashes: crashes(&domain.name) });
+                return;
+            }
+        };
+        info!("service_domain: started generation {} of service {:?}", generation, domain.name);
+        publish(&domain.name, Event::Started { generation });
+
+        let start_time = Instant::now();
+        let exit_value = task.join();
+        let uptime = start_time.elapsed();
+        let reason = match exit_value {
+            Ok(ExitValue::Killed(KillReason::Requested)) if stop_requested(&domain.name) => break,
+            Ok(ExitValue::Completed(_)) => break,
+            Ok(ExitValue::Killed(reason)) => reason.to_string(),
+            Err(e) => e.to_string(),
+        };
+        // Keep the old instance (and thus its crates) alive until the new instance
+        // has taken over its devices, as that requires the old driver to quiesce them.
+        previous = Some(task);
+
+        let (crashes, consecutive) = with_state(&domain.name, |state| {
+            state.info.crashes = state.info.crashes.saturating_add(1);
+            state.info.consecutive_crashes = domain.backoff.consecutive_crashes(state.info.consecutive_crashes, uptime);
+            state.info.last_crash = Some(reason.clone());
+            state.info.status = Status::BackingOff;
+            state.task = None;
+            // Disconnect the old channels, so clients don't wait on a service that's gone.
+            state.endpoints.clear();
+            (state.info.crashes, state.info.consecutive_crashes)
+        }).unwrap_or_default();
+        warn!("service_domain: service {:?} crashed ({} crashes, {} in a row): {}", domain.name, crashes, consecutive, reason);
+        publish(&domain.name, Event::Crashed { reason, crashes });
+
+        let Some(delay) = domain.backoff.delay(consecutive) else {
+            error!("service_domain: giving up on service {:?} after {} consecutive crashes", domain.name, consecutive);
+            set_status(&domain.name, Status::Failed);
+            publish(&domain.name, Event::Failed { crashes });
+            return;
+        };
+        if sleep::sleep(delay).is_err() {
+            warn!("service_domain: couldn't sleep before restarting service {:?}", domain.name);
+        }
+    }
+
+    set_status(&domain.name, Status::Stopped);
+    with_state(&domain.name, |state| {
+        state.task = None;
+        state.endpoints.clear();
+    });
+    publish(&domain.name, Event::Stopped);
+}
+
+/// Loads the service's crates into a fresh namespace and spawns a new instance of it.
+fn start_service(
+    domain: &ServiceDomain,
+    generation: u64,
+    previous: Option<JoinableTaskRef>,
+) -> Result<JoinableTaskRef, &'static str> {
+    let namespace = mod_mgmt::create_application_namespace(None)?;
+    let task = spawn::new_application_task_builder(domain.crate_object_file.as_ref(), Some(namespace))?
+        .argument(domain.args.clone())
+        .name(format!("{}_{}", domain.name, generation))
+        .block()
+        .spawn()?;
+
+    if let Some(app_crate) = task.app_crate.as_ref() {
+        if device_manager::driver_from_crate(app_crate).is_ok() {
+            if let Err(e) = device_manager::rebind_swapped_driver_crate(app_crate) {
+                error!("service_domain: couldn't rebind the devices of service {:?}: {}", domain.name, e);
+            }
+        }
+    }
+    // Now that the old instance's devices have been handed over, its crates can be unloaded.
+    drop(previous);
+
+    with_state(&domain.name, |state| {
+        state.task = Some(TaskRef::clone(&task));
+        state.info.status = Status::Running;
+    });
+    task.unblock().map_err(|_| "service_domain: couldn't unblock the new service task")?;
+    Ok(task)
+}
+
+fn with_state<R>(domain: &str, f: impl FnOnce(&mut DomainState) -> R) -> Option<R> {
+    DOMAINS.lock().get_mut(domain).map(f)
+}
+
+fn set_status(domain: &str, status: Status) {
+    with_state(domain, |state| state.info.status = status);
+}
+
+fn crashes(domain: &str) -> u32 {
+    with_state(domain, |state| state.info.crashes).unwrap_or_default()
+}
+
+fn stop_requested(domain: &str) -> bool {
+    with_state(domain, |state| state.stop_requested).unwrap_or_default()
+}
+
+/// Returns the service's end of the given channel of the given domain,
+/// which receives the messages sent by clients to the current instance of the service.
+pub fn receiver<T: Send + 'static>(domain: &str, channel: &str) -> Result<Receiver<T>, &'static str> {
+    endpoint(domain, channel, |(receiver, _)| receiver.downcast_ref::<Receiver<T>>().cloned())
+}
+
+/// Returns a client's end of the given channel of the given domain,
+/// which sends messages to the current instance of the service.
+///
+/// Once the service crashes, this sender is disconnected, and a new one must be obtained.
+pub fn sender<T: Send + 'static>(domain: &str, channel: &str) -> Result<Sender<T>, &'static str> {
+    endpoint(domain, channel, |(_, sender)| sender.downcast_ref::<Sender<T>>().cloned())
+}
+
+fn endpoint<E>(
+    domain: &str,
+    channel: &str,
+    f: impl FnOnce(&(Box<dyn Any + Send>, Box<dyn Any + Send>)) -> Option<E>,
+) -> Result<E, &'static str> {
+    let domains = DOMAINS.lock();
+    let state = domains.get(domain).ok_or("service_domain: no such domain")?;
+    let endpoints = state.endpoints.get(channel).ok_or("service_domain: the domain has no such channel")?;
+    f(endpoints).ok_or("service_domain: the channel has a different message type")
+}
+
+/// Stops the service of the given domain, without restarting it.
+pub fn stop(domain: &str) -> Result<(), &'static str> {
+    let task = with_state(domain, |state| {
+        state.stop_requested = true;
+        state.task.clone()
+    }).ok_or("service_domain: no such domain")?;
+    match task {
+        Some(task) => task.kill(KillReason::Requested),
+        None => Ok(()),
+    }
+}
+
+/// Returns a snapshot of the state of all service domains.
+pub fn domains() -> Vec<DomainInfo> {
+    DOMAINS.lock().values().map(|state| state.info.clone()).collect()
+}
+
+/// Subscribes to notifications about all service domains,
+/// which are received on the returned channel.
+///
+/// Notifications that don't fit into the channel's capacity are dropped.
+/// The subscription ends when the returned receiver is dropped.
+pub fn subscribe(capacity: usize) -> Receiver<Notification> {
+    let (sender, receiver) = sync_channel::new_channel(capacity);
+    SUBSCRIBERS.lock().push(sender);
+    receiver
+}
+
+/// Publishes the given event of the given domain to all subscribers.
+fn publish(domain: &str, event: Event) {
+    // Send outside of the lock, as sending may wake up a subscriber.
+    let subscribers = SUBSCRIBERS.lock().clone();
+    let mut disconnected = false;
+    for subscriber in &subscribers {
+        let notification = Notification { domain: domain.to_string(), event: event.clone() };
+        if let Err((_, sync_channel::Error::ChannelDisconnected)) = subscriber.try_send(notification) {
+            disconnected = true;
+        }
+    }
+    if disconnected {
+        SUBSCRIBERS.lock().retain(|subscriber| !subscriber.is_disconnected());
+    }
+}
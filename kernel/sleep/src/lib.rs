@@ -120,16 +120,23 @@ pub fn unblock_sleeping_tasks() {
 
 /// Blocks the current task by putting it to sleep for `duration` ticks.
 ///
+/// This may return early if a notification is sent to the current task
+/// and handled by it; see [`task::notifications`].
+///
 /// Returns the current task's run state if it can't be blocked.
 pub fn sleep(duration: Duration) -> Result<(), RunState> {
     let current_time = now::<Monotonic>();
     let resume_time = current_time + duration;
 
-    let current_task = get_my_current_task().unwrap();
-    // Add the current task to the delayed tasklist and then block it.
-    add_to_delayed_tasklist(SleepingTaskNode{action: Action::Sync(current_task.clone()), resume_time});
-    current_task.block()?;
+    {
+        let current_task = get_my_current_task().unwrap();
+        // Add the current task to the delayed tasklist and then block it.
+        add_to_delayed_tasklist(SleepingTaskNode{action: Action::Sync(current_task.clone()), resume_time});
+        current_task.block()?;
+    }
     task::schedule();
+    // The current task may have been woken early to receive a notification.
+    task::notifications::deliver_pending();
     Ok(())
 }
 
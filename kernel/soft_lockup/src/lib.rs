@@ -0,0 +1,243 @@
+//! Detects soft lockups, in which a CPU doesn't switch tasks for too long.
+//!
+//! A task that is stuck with preemption disabled (e.g., spinning on a lock
+//! or looping while holding a preemption-safe lock), or that otherwise never yields its CPU,
+//! starves every other task on that CPU.
+//!
+//! Once [`init()`] is invoked, this crate spawns a watchdog task pinned to each CPU.
+//! Every watchdog periodically records that it was able to run on its CPU,
+//! and then checks when the other CPUs' watchdogs last ran.
+//! If a watchdog hasn't run for longer than the configurable [threshold],
+//! its CPU is locked up, and a warning is logged with the task that is currently running
+//! on that CPU and how long that CPU has had preemption disabled, if it has.
+//!
+//! Because the check happens on other CPUs, lockups are detected even though
+//! [`preemption::hold_preemption()`] masks the local timer interrupt on x86_64.
+//! The [`preemption`] crate's hooks only record when preemption is disabled and re-enabled,
+//! and all reporting is done by the watchdog tasks.
+//!
+//! Note that the stuck CPU's stack is not unwound, because the unwinder needs
+//! to acquire locks and allocate memory, which the stuck CPU may be holding.
+//!
+//! [threshold]: set_threshold
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::{format, string::String};
+use atomic_linked_list::atomic_map::AtomicMap;
+use core::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use cpu::CpuId;
+use preemption::PreemptionHooks;
+use spin::Once;
+use time::{Duration, Instant};
+
+/// The default threshold above which a soft lockup is reported: 100 milliseconds.
+pub const DEFAULT_THRESHOLD: Duration = Duration::from_millis(100);
+
+/// The hooks registered with [`preemption`] to measure preemption-disabled sections.
+static HOOKS: PreemptionHooks = PreemptionHooks {
+    on_disable,
+    on_enable,
+};
+
+/// Whether soft lockups are currently being detected.
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Ensures that the watchdog tasks are only spawned once.
+static WATCHDOGS: Once<()> = Once::new();
+
+/// The duration (in nanoseconds) that a CPU may go without running its watchdog
+/// before it is considered to be locked up.
+static THRESHOLD_NANOS: AtomicU64 = AtomicU64::new(DEFAULT_THRESHOLD.as_nanos() as u64);
+
+/// The state of each CPU, which is created in [`init()`] before any hooks or watchdogs run.
+static CPU_STATES: AtomicMap<CpuId, CpuState> = AtomicMap::new();
+
+/// The internal, atomically-updated state of a single CPU.
+#[derive(Default)]
+struct CpuState {
+    /// The time (in nanoseconds) at which preemption was disabled,
+    /// or `0` if preemption is currently enabled.
+    disabled_since_nanos: AtomicU64,
+    /// The time (in nanoseconds) at which this CPU's watchdog last ran,
+    /// or `0` if it hasn't run since detection was enabled.
+    last_watchdog_nanos: AtomicU64,
+    /// The ID of the task that was running on this CPU when it was last found to be locked up.
+    stuck_task_id: AtomicUsize,
+    /// Whether the current lockup has already been reported.
+    reported: AtomicBool,
+    lockups: AtomicU64,
+    max_nanos: AtomicU64,
+}
+
+/// A snapshot of the soft lockup statistics for a single CPU.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SoftLockupStats {
+    /// The number of soft lockups that have been reported.
+    pub lockups: u64,
+    /// The longest time that preemption was disabled in a single section.
+    pub max: Duration,
+    /// How long preemption has currently been disabled, if it is disabled.
+    pub current: Option<Duration>,
+}
+
+/// Starts detecting soft lockups on all CPUs.
+///
+/// The first invocation spawns a watchdog task on each CPU,
+/// so this must be invoked after all CPUs have been brought up.
+pub fn init() -> Result<(), &'static str> {
+    WATCHDOGS.try_call_once(spawn_watchdogs)?;
+
+    // Treat every watchdog as having just run, so that time spent disabled isn't a lockup.
+    let now = now_nanos();
+    for (_cpu, state) in CPU_STATES.iter() {
+        state.last_watchdog_nanos.store(now, Ordering::Relaxed);
+    }
+    ENABLED.store(true, Ordering::Release);
+    preemption::set_preemption_hooks(Some(&HOOKS));
+    log::info!("Detecting soft lockups, threshold: {:?}", threshold());
+    Ok(())
+}
+
+/// Stops detecting soft lockups.
+///
+/// Existing statistics are retained.
+pub fn disable() {
+    preemption::set_preemption_hooks(None);
+    ENABLED.store(false, Ordering::Release);
+    for (_cpu, state) in CPU_STATES.iter() {
+        state.disabled_since_nanos.store(0, Ordering::Relaxed);
+        state.last_watchdog_nanos.store(0, Ordering::Relaxed);
+    }
+}
+
+/// Returns the current soft lockup threshold.
+pub fn threshold() -> Duration {
+    Duration::from_nanos(THRESHOLD_NANOS.load(Ordering::Relaxed))
+}
+
+/// Sets the duration that a CPU may go without switching to its watchdog task
+/// before it is reported as a soft lockup.
+pub fn set_threshold(threshold: Duration) {
+    let nanos = u64::try_from(threshold.as_nanos()).unwrap_or(u64::MAX);
+    THRESHOLD_NANOS.store(nanos, Ordering::Relaxed);
+}
+
+/// Returns a snapshot of the statistics for the given CPU,
+/// or `None` if that CPU wasn't present when [`init()`] was first invoked.
+pub fn stats(cpu: CpuId) -> Option<SoftLockupStats> {
+    CPU_STATES.get(&cpu).map(|state| {
+        let since = state.disabled_since_nanos.load(Ordering::Relaxed);
+        SoftLockupStats {
+            lockups: state.lockups.load(Ordering::Relaxed),
+            max: Duration::from_nanos(state.max_nanos.load(Ordering::Relaxed)),
+            current: (since != 0).then(|| Duration::from_nanos(now_nanos().saturating_sub(since))),
+        }
+    })
+}
+
+/// Creates the state of every CPU and spawns a watchdog task pinned to each one.
+fn spawn_watchdogs() -> Result<(), &'static str> {
+    for cpu in cpu::cpus() {
+        CPU_STATES.insert(cpu, CpuState::default());
+    }
+    for cpu in cpu::cpus() {
+        spawn::new_task_builder(watchdog_loop, cpu)
+            .name(format!("soft_lockup_watchdog_{cpu}"))
+            .pin_on_cpu(cpu)
+            .spawn()?;
+    }
+    Ok(())
+}
+
+/// The entry point of the watchdog task on the given CPU.
+///
+/// Each iteration records that this CPU was able to switch to its watchdog,
+/// and then checks whether any other CPU's watchdog has been starved for too long.
+fn watchdog_loop(cpu: CpuId) -> Result<(), &'static str> {
+    let state = CPU_STATES.get(&cpu).ok_or("soft_lockup: watchdog's CPU has no state")?;
+    loop {
+        if ENABLED.load(Ordering::Acquire) {
+            let now = now_nanos();
+            let last = state.last_watchdog_nanos.swap(now, Ordering::Relaxed);
+            if state.reported.swap(false, Ordering::Relaxed) {
+                log::warn!("Soft lockup on CPU {} ended after {:?}",
+                    cpu, Duration::from_nanos(now.saturating_sub(last)),
+                );
+            }
+            check_other_cpus(cpu, now);
+        }
+        // Check several times per threshold such that lockups are reported promptly.
+        let _ = sleep::sleep(threshold() / 4);
+    }
+}
+
+/// Reports each CPU other than `current` whose watchdog hasn't run for longer than the threshold.
+fn check_other_cpus(current: CpuId, now: u64) {
+    let threshold_nanos = THRESHOLD_NANOS.load(Ordering::Relaxed);
+    for (&cpu, state) in CPU_STATES.iter() {
+        if cpu == current {
+            continue;
+        }
+        let last = state.last_watchdog_nanos.load(Ordering::Relaxed);
+        let stalled = now.saturating_sub(last);
+        if last != 0
+            && stalled > threshold_nanos
+            && !state.reported.swap(true, Ordering::Relaxed)
+        {
+            state.lockups.fetch_add(1, Ordering::Relaxed);
+            report(cpu, state, now, Duration::from_nanos(stalled));
+        }
+    }
+}
+
+/// Logs a warning about a soft lockup on the given CPU,
+/// including the task currently running on it and how long it has had preemption disabled.
+fn report(cpu: CpuId, state: &CpuState, now: u64, stalled: Duration) {
+    // The stuck CPU only reads its current task ID, which needs no locks.
+    let task = cpu_call::run_on_cpu(cpu, || {
+        state.stuck_task_id.store(task::get_my_current_task_id(), Ordering::Relaxed);
+    }).ok().map(|_| {
+        let id = state.stuck_task_id.load(Ordering::Relaxed);
+        let name = task::get_task(id).and_then(|t| t.upgrade()).map(|t| t.name.clone());
+        (id, name)
+    });
+    let preemption = match state.disabled_since_nanos.load(Ordering::Relaxed) {
+        0 => String::from("preemption is enabled"),
+        since => format!("preemption has been disabled for {:?}", Duration::from_nanos(now.saturating_sub(since))),
+    };
+
+    match task {
+        Some((id, name)) => log::warn!(
+            "Soft lockup on CPU {}: no task switch for {:?} (threshold {:?}), {}, current task: {} ({:?})",
+            cpu, stalled, threshold(), preemption, id, name,
+        ),
+        None => log::warn!(
+            "Soft lockup on CPU {}: no task switch for {:?} (threshold {:?}), {}, current task unknown",
+            cpu, stalled, threshold(), preemption,
+        ),
+    }
+}
+
+/// Returns the current monotonic time in nanoseconds, which is never `0`.
+fn now_nanos() -> u64 {
+    ((Instant::now() - Instant::ZERO).as_nanos() as u64).max(1)
+}
+
+/// Invoked by [`preemption`] right after preemption was disabled on the given CPU.
+fn on_disable(cpu: CpuId) {
+    if let Some(state) = CPU_STATES.get(&cpu) {
+        state.disabled_since_nanos.store(now_nanos(), Ordering::Relaxed);
+    }
+}
+
+/// Invoked by [`preemption`] right before preemption is re-enabled on the given CPU.
+fn on_enable(cpu: CpuId) {
+    let Some(state) = CPU_STATES.get(&cpu) else { return };
+    let since = state.disabled_since_nanos.swap(0, Ordering::Relaxed);
+    if since != 0 {
+        state.max_nanos.fetch_max(now_nanos().saturating_sub(since), Ordering::Relaxed);
+    }
+}
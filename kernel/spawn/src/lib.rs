@@ -32,7 +32,7 @@ use memory::{get_kernel_mmi_ref, MmiRef};
 use stack::Stack;
 use task::{Task, TaskRef, RestartInfo, RunState, JoinableTaskRef, ExitableTaskRef, FailureCleanupFunction};
 use task_struct::ExposedTask;
-use mod_mgmt::{CrateNamespace, SectionType, SECTION_HASH_DELIMITER};
+use mod_mgmt::{CrateNamespace, SectionType, VisibilityContract, SECTION_HASH_DELIMITER};
 use path::{Path, PathBuf};
 use fs_node::FileOrDir;
 use preemption::{hold_preemption, PreemptionGuard};
@@ -265,6 +265,29 @@ pub fn new_application_task_builder(
     Ok(tb)
 }
 
+/// Creates a builder for a new application `Task` whose crates are isolated
+/// in a new child `CrateNamespace`. 
+/// 
+/// The child namespace is built atop the current task's namespace, 
+/// but can only link against the crates and symbols in it that are allowed by the given `contract`.
+/// Thus, the new application crate (and any other application crates it depends on) 
+/// can only use the APIs exposed by those crates, which enforces least privilege at the linking layer.
+/// A contract that allows only a curated set of symbols, rather than whole crates,
+/// gives the application a minimal "syscall surface" into the kernel.
+/// 
+/// Loading fails if the application crate depends on a crate or symbol that the `contract` doesn't allow.
+/// 
+/// See [`new_application_task_builder()`] for more details.
+pub fn new_restricted_application_task_builder(
+    crate_object_file: &Path,
+    contract: VisibilityContract,
+) -> Result<TaskBuilder<MainFunc, MainFuncArg, MainFuncRet>, &'static str> {
+    let parent_namespace = task::with_current_task(|t| t.get_namespace().clone())
+        .map_err(|_| "spawn::new_restricted_application_task_builder(): couldn't get current task")?;
+    let child_namespace = mod_mgmt::create_restricted_application_namespace(Some(parent_namespace), contract)?;
+    new_application_task_builder(crate_object_file, Some(child_namespace))
+}
+
 /// A struct that offers a builder pattern to create and customize new `Task`s.
 /// 
 /// Note that the new `Task` will not actually be created until [`spawn()`](struct.TaskBuilder.html#method.spawn) is invoked.
@@ -287,6 +310,7 @@ pub struct TaskBuilder<F, A, R> {
     pin_on_cpu: Option<CpuId>,
     blocked: bool,
     idle: bool,
+    separate_address_space: bool,
     post_build_function: Option<Box<
         dyn FnOnce(&mut Task) -> Result<Option<FailureCleanupFunction>, &'static str>
     >>,
@@ -313,6 +337,7 @@ impl<F, A, R> TaskBuilder<F, A, R>
             pin_on_cpu: None,
             blocked: false,
             idle: false,
+            separate_address_space: false,
             post_build_function: None,
 
             #[cfg(simd_personality)]
@@ -361,6 +386,17 @@ impl<F, A, R> TaskBuilder<F, A, R>
         self
     }
 
+    /// Run the new Task in a new, separate address space instead of its parent's address space.
+    ///
+    /// The new address space shares all kernel mappings with the parent's address space,
+    /// but has its own private region; see [`memory::create_address_space()`].
+    /// This is an optional isolation mode, as Theseus normally runs all tasks
+    /// in a single address space.
+    pub fn separate_address_space(mut self) -> TaskBuilder<F, A, R> {
+        self.separate_address_space = true;
+        self
+    }
+
     /// Set the new Task's `RunState` to be `Blocked` instead of `Runnable` when it is first spawned.
     /// This allows another task to delay the new task's execution arbitrarily, 
     /// e.g., to set up other things for the newly-spawned (but not yet running) task. 
@@ -397,6 +433,10 @@ impl<F, A, R> TaskBuilder<F, A, R>
             new_task.simd = self.simd;
         }
 
+        if self.separate_address_space {
+            new_task.mmi = memory::create_address_space(&new_task.mmi)?;
+        }
+
         setup_context_trampoline(&mut new_task, task_wrapper::<F, A, R>)?;
 
         // We use the bottom of the new task's stack for its entry function and arguments. 
@@ -893,10 +933,13 @@ fn task_cleanup_final_internal(current_task: &ExitableTaskRef) {
         }
     }
 
-    // Third, reap the task if it has been orphaned (if it's non-joinable).
+    // Third, remove any kernel objects owned by this task from their handle tables.
+    handle_table::release_owned_by(current_task.id);
+
+    // Fourth, reap the task if it has been orphaned (if it's non-joinable).
     current_task.reap_if_orphaned();
 
-    // Fourth, synchronize memory with the release fence of the "parent" task
+    // Fifth, synchronize memory with the release fence of the "parent" task
     // in `TaskBuilder::spawn()`.
     fence(Ordering::Acquire)
 }
@@ -64,7 +64,7 @@ use alloc::{
 };
 use spin::Mutex;
 use downcast_rs::Downcast;
-use io::{BlockIo, KnownLength, BlockReader, BlockWriter};
+use io::{BlockIo, KnownLength, BlockReader, BlockWriter, IoError};
 
 
 /// A trait that represents a storage controller,
@@ -100,6 +100,15 @@ pub type StorageControllerRef = Arc<Mutex<dyn StorageController + Send>>;
 pub trait StorageDevice: BlockIo + BlockReader + BlockWriter + KnownLength + Downcast {
 	/// Returns the total size of this device, given in number of blocks (sectors).
     fn size_in_blocks(&self) -> usize;
+
+    /// Informs this device that the given range of blocks no longer holds useful data,
+    /// like the ATA TRIM or SCSI UNMAP commands, such that they read as zeros afterwards
+    /// and the device can release the space backing them.
+    ///
+    /// The default implementation returns an error, as not all devices support discarding blocks.
+    fn discard_blocks(&mut self, _block_offset: usize, _num_blocks: usize) -> Result<(), IoError> {
+        Err(IoError::Other("this storage device doesn't support discarding blocks"))
+    }
 }
 impl_downcast!(StorageDevice);
 
@@ -3,7 +3,7 @@ use preemption::hold_preemption_no_timer_disable;
 use mpmc_queue::Queue;
 use sync::DeadlockPrevention;
 use sync_spin::Spin;
-use task::{get_my_current_task, TaskRef};
+use task::{get_my_current_task, TaskRef, WaitReason};
 
 /// A condition variable.
 ///
@@ -38,6 +38,7 @@ where
     pub fn wait<'a, T: ?Sized>(&self, guard: MutexGuard<'a, T>) -> MutexGuard<'a, T> {
         let task = get_my_current_task().unwrap();
         let mutex = guard.mutex();
+        task.set_wait_reason(Some(WaitReason::of("Condvar", self)));
 
         let preemption_guard = self
             .inner
@@ -62,7 +63,10 @@ where
                     Err(preemption_guard)
                 }
             }) {
-                Ok(mutex_guard) => return mutex_guard,
+                Ok(mutex_guard) => {
+                    task.set_wait_reason(None);
+                    return mutex_guard;
+                }
                 Err(preemption_guard) => {
                     drop(preemption_guard);
                 }
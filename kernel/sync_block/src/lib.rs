@@ -5,6 +5,7 @@ mod condvar;
 
 use core::sync::atomic::{AtomicUsize, Ordering};
 use sync::{spin, MutexFlavor, RwLockFlavor};
+use task::WaitReason;
 use wait_queue::WaitQueue;
 
 pub use condvar::Condvar;
@@ -101,7 +102,7 @@ impl MutexFlavor for Block {
             #[cfg(priority_inheritance)]
             let _priority_guard = scheduler::inherit_priority(&holder_task);
 
-            data.queue.wait_until(|| Self::try_lock(mutex, data))
+            data.queue.wait_until_with_reason(WaitReason::of("Mutex", mutex), || Self::try_lock(mutex, data))
         } else {
             // Unlikely case that another thread just acquired the lock, but hasn't yet set
             // data.holder.
@@ -112,7 +113,7 @@ impl MutexFlavor for Block {
             }
 
             // Slow path
-            data.queue.wait_until(|| Self::try_lock(mutex, data))
+            data.queue.wait_until_with_reason(WaitReason::of("Mutex", mutex), || Self::try_lock(mutex, data))
         }
     }
 
@@ -174,7 +175,7 @@ impl RwLockFlavor for Block {
         if let Some(guards) = Self::try_read(rw_lock, data) {
             guards
         } else {
-            data.readers.wait_until(|| Self::try_read(rw_lock, data))
+            data.readers.wait_until_with_reason(WaitReason::of("RwLock (read)", rw_lock), || Self::try_read(rw_lock, data))
         }
     }
 
@@ -191,7 +192,7 @@ impl RwLockFlavor for Block {
         if let Some(guards) = Self::try_write(rw_lock, data) {
             guards
         } else {
-            data.writers.wait_until(|| Self::try_write(rw_lock, data))
+            data.writers.wait_until_with_reason(WaitReason::of("RwLock (write)", rw_lock), || Self::try_write(rw_lock, data))
         }
     }
 
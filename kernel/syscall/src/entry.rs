@@ -0,0 +1,260 @@
+//! The low-level routines that transition between the kernel and userspace.
+
+use alloc::boxed::Box;
+use core::arch::asm;
+use memory::VirtualAddress;
+use x86_64::{
+    registers::{
+        model_specific::{Efer, EferFlags, KernelGsBase, LStar, SFMask, Star},
+        rflags::RFlags,
+    },
+    VirtAddr,
+};
+use crate::{dispatch, SyscallArgs};
+
+/// Per-CPU data used by the system call entry point before it has a valid kernel stack.
+///
+/// The `KernelGsBase` MSR points to this, such that the entry point can access it
+/// via the `gs` segment after a `swapgs` instruction.
+///
+/// The offsets of these fields are hardcoded in [`syscall_entry()`].
+#[repr(C)]
+struct SyscallCpuData {
+    /// A pointer to this CPU's TSS RSP0 entry, i.e., the top of the kernel stack
+    /// for the task currently running in userspace on this CPU. Offset 0.
+    rsp0_ptr: *const u64,
+    /// Scratch space for saving the user stack pointer. Offset 8.
+    user_rsp_scratch: u64,
+}
+
+/// The stack frame pushed by [`syscall_entry()`], which holds the user's registers.
+///
+/// The order of these fields must be the reverse of the order in which they are pushed.
+#[repr(C)]
+struct SyscallFrame {
+    r9:  usize,
+    r8:  usize,
+    r10: usize,
+    rdx: usize,
+    rsi: usize,
+    rdi: usize,
+    rax: usize,
+    /// The user's `rflags`, saved by the `syscall` instruction.
+    r11: usize,
+    /// The user's instruction pointer, saved by the `syscall` instruction.
+    rcx: usize,
+    user_rsp: usize,
+}
+
+/// The `rflags` bits that are cleared upon entry to the kernel via `syscall`.
+const SYSCALL_RFLAGS_MASK: RFlags = RFlags::INTERRUPT_FLAG
+    .union(RFlags::TRAP_FLAG)
+    .union(RFlags::DIRECTION_FLAG)
+    .union(RFlags::ALIGNMENT_CHECK)
+    .union(RFlags::NESTED_TASK);
+
+/// Configures the `syscall` instruction on the current CPU.
+///
+/// This is registered as a [`cpu_features`] hook by [`crate::init()`].
+pub(crate) fn init_cpu() -> Result<(), &'static str> {
+    let kernel_cs = gdt::AvailableSegmentSelector::KernelCode.get().ok_or("GDT was not yet initialized")?;
+    let kernel_ds = gdt::AvailableSegmentSelector::KernelData.get().ok_or("GDT was not yet initialized")?;
+    let user_cs   = gdt::AvailableSegmentSelector::UserCode64.get().ok_or("GDT was not yet initialized")?;
+    let user_ss   = gdt::AvailableSegmentSelector::UserData32.get().ok_or("GDT was not yet initialized")?;
+    let rsp0_ptr = tss::rsp0_ptr(cpu::current_cpu()).ok_or("TSS was not yet initialized on this CPU")?;
+
+    // This is only done once per CPU, so leaking it is fine.
+    let cpu_data = Box::leak(Box::new(SyscallCpuData { rsp0_ptr, user_rsp_scratch: 0 })) as *mut _;
+
+    // SAFE: the segment selectors match the GDT layout expected by `syscall` and `sysret`,
+    //       and the entry point is valid for the lifetime of the system.
+    unsafe {
+        Star::write(user_cs, user_ss, kernel_cs, kernel_ds)?;
+        LStar::write(VirtAddr::new(syscall_entry as usize as u64));
+        SFMask::write(SYSCALL_RFLAGS_MASK);
+        KernelGsBase::write(VirtAddr::new(cpu_data as u64));
+        Efer::update(|f| f.insert(EferFlags::SYSTEM_CALL_EXTENSIONS));
+    }
+    Ok(())
+}
+
+
+/// The entry point for the `syscall` instruction.
+///
+/// Upon entry, interrupts are disabled, `rcx` holds the user's instruction pointer,
+/// `r11` holds the user's `rflags`, and `rsp` still points to the user stack.
+///
+/// Note that the GS base always holds the kernel's CPU-local storage pointer,
+/// even while running in userspace, so `swapgs` is only used briefly here
+/// to access this CPU's [`SyscallCpuData`].
+#[naked]
+unsafe extern "C" fn syscall_entry() -> ! {
+    asm!(
+        // Switch to the kernel stack of the current task, which is stored in the TSS RSP0 entry.
+        "swapgs",
+        "mov gs:[8], rsp",
+        "mov rsp, gs:[0]",
+        "mov rsp, [rsp]",
+        "and rsp, -16",
+        "push qword ptr gs:[8]",
+        "swapgs",
+        // Save the user's registers, forming a `SyscallFrame`.
+        "push rcx",
+        "push r11",
+        "push rax",
+        "push rdi",
+        "push rsi",
+        "push rdx",
+        "push r10",
+        "push r8",
+        "push r9",
+        // The stack is now 16-byte aligned, as required before a call.
+        "mov rdi, rsp",
+        "call {handler}",
+        // Interrupts must be disabled before switching back to the user stack,
+        // as an interrupt in ring 0 would otherwise push its frame onto the user stack.
+        "cli",
+        "pop r9",
+        "pop r8",
+        "pop r10",
+        "pop rdx",
+        "pop rsi",
+        "pop rdi",
+        "pop rax",
+        "pop r11",
+        "pop rcx",
+        "pop rsp",
+        "sysretq",
+        handler = sym syscall_handler,
+        options(noreturn)
+    )
+}
+
+/// The Rust portion of the system call entry point.
+extern "C" fn syscall_handler(frame: &mut SyscallFrame) {
+    // It's safe to handle interrupts now that we're on the current task's kernel stack.
+    irq_safety::enable_interrupts();
+
+    let args = SyscallArgs {
+        number: frame.rax,
+        args: [frame.rdi, frame.rsi, frame.rdx, frame.r10, frame.r8, frame.r9],
+    };
+    frame.rax = match dispatch(&args) {
+        Ok(value) => value,
+        Err(err) => err.to_return_value(),
+    };
+
+    // `sysret` with a non-canonical return address would fault in ring 0 on the user stack,
+    // so we must ensure it's a valid userspace address.
+    if !VirtualAddress::new(frame.rcx).is_some_and(memory::is_private_address) {
+        log::error!("syscall: invalid userspace return address {:#X}, killing task", frame.rcx);
+        let _ = task::with_current_task(|t| t.kill(task::KillReason::Requested));
+        // We should never reach here, as the current task has been killed.
+        loop { task::schedule(); }
+    }
+}
+
+
+/// Switches to userspace at the given `entry` point, using the given `user_stack_top`.
+///
+/// Returns when the userspace code invokes the exit system call, via [`exit_user_mode()`].
+///
+/// This saves the kernel's callee-saved registers and `rflags` on the current stack;
+/// the resulting stack pointer is used as the top of the kernel stack
+/// for interrupts and system calls from userspace, and is restored upon exit.
+#[naked]
+pub(crate) unsafe extern "C" fn enter_user_mode(
+    entry: usize,
+    user_stack_top: usize,
+    user_cs: usize,
+    user_ss: usize,
+) -> isize {
+    asm!(
+        // Save the kernel's state, which will be restored in `return_to_kernel`.
+        // Together with the return address, this keeps the stack 16-byte aligned.
+        "push rbp",
+        "push rbx",
+        "push r12",
+        "push r13",
+        "push r14",
+        "push r15",
+        "pushfq",
+        "mov r12, rdi",
+        "mov r13, rsi",
+        "mov r14, rdx",
+        "mov r15, rcx",
+        "mov rdi, rsp",
+        "call {prepare}",
+        // Build the frame for `iretq`: SS, RSP, RFLAGS (interrupts enabled), CS, RIP.
+        "push r15",
+        "push r13",
+        "push 0x202",
+        "push r14",
+        "push r12",
+        // Don't leak any kernel register values into userspace.
+        "xor eax, eax",
+        "xor ebx, ebx",
+        "xor ecx, ecx",
+        "xor edx, edx",
+        "xor esi, esi",
+        "xor edi, edi",
+        "xor ebp, ebp",
+        "xor r8d, r8d",
+        "xor r9d, r9d",
+        "xor r10d, r10d",
+        "xor r11d, r11d",
+        "xor r12d, r12d",
+        "xor r13d, r13d",
+        "xor r14d, r14d",
+        "xor r15d, r15d",
+        "iretq",
+        prepare = sym prepare_user_entry,
+        options(noreturn)
+    )
+}
+
+/// Records the given `kernel_stack_top` as the current task's kernel stack for
+/// transitions from userspace, and sets the TSS RSP0 entry accordingly.
+extern "C" fn prepare_user_entry(kernel_stack_top: usize) {
+    let top = VirtualAddress::new_canonical(kernel_stack_top);
+    let _ = task::with_current_task(|t| t.set_userspace_kernel_stack_top(Some(top)));
+    if let Err(e) = tss::tss_set_rsp0(top) {
+        log::error!("BUG: couldn't set TSS RSP0 before entering userspace: {}", e);
+    }
+}
+
+/// Leaves userspace and returns `exit_code` from the call to [`enter_user_mode()`]
+/// that brought the current task into userspace.
+///
+/// This must only be invoked from a system call handler.
+/// Any values on the stack between that handler and the original call to
+/// [`enter_user_mode()`] are discarded without being dropped.
+pub(crate) fn exit_user_mode(exit_code: isize) -> ! {
+    irq_safety::disable_interrupts();
+    let kernel_stack_top = task::with_current_task(|t| {
+        let top = t.userspace_kernel_stack_top();
+        t.set_userspace_kernel_stack_top(None);
+        top
+    }).ok().flatten().expect("BUG: exit_user_mode(): current task isn't running in userspace");
+
+    // SAFE: `kernel_stack_top` is the stack pointer saved by `enter_user_mode()`.
+    unsafe { return_to_kernel(kernel_stack_top.value(), exit_code) }
+}
+
+/// Restores the kernel state saved by [`enter_user_mode()`] and returns from it.
+#[naked]
+unsafe extern "C" fn return_to_kernel(kernel_stack_top: usize, exit_code: isize) -> ! {
+    asm!(
+        "mov rsp, rdi",
+        "mov rax, rsi",
+        "popfq",
+        "pop r15",
+        "pop r14",
+        "pop r13",
+        "pop r12",
+        "pop rbx",
+        "pop rbp",
+        "ret",
+        options(noreturn)
+    )
+}
@@ -0,0 +1,223 @@
+//! Support for executing code in userspace (ring 3) and handling system calls from it.
+//!
+//! Theseus runs everything in a single privilege level by default.
+//! This crate allows a kernel task to drop into ring 3 via [`run_in_user_mode()`]
+//! in order to run untrusted code that is isolated from the kernel by the hardware.
+//! Such code can only interact with the kernel by invoking the `syscall` instruction,
+//! which enters the kernel through the entry point in this crate and is dispatched
+//! to the handler registered for the requested system call number.
+//!
+//! The system call ABI follows the usual x86_64 convention:
+//! * `rax` holds the system call number,
+//! * `rdi`, `rsi`, `rdx`, `r10`, `r8`, and `r9` hold up to six arguments,
+//! * `rax` holds the return value upon return, where negative values are [`SyscallError`]s,
+//! * `rcx` and `r11` are clobbered; all other registers are preserved.
+//!
+//! User code and data must be mapped into the private region of the current address space
+//! using [`map_user_pages()`]; all other memory is inaccessible from ring 3.
+//! The kernel should only access user memory via [`copy_from_user()`] and [`copy_to_user()`],
+//! which validate the user-provided addresses.
+//!
+//! # Limitations
+//! * Userspace code must not modify the `FS` or `GS` segment registers or their base addresses,
+//!   as the kernel currently relies on them for task-local and CPU-local storage
+//!   even when handling interrupts that occurred while running in ring 3.
+//! * If userspace code triggers an exception, the task running it is killed,
+//!   just like a kernel task would be.
+
+#![no_std]
+#![feature(naked_functions)]
+
+extern crate alloc;
+
+mod entry;
+mod user_mem;
+
+pub use user_mem::{copy_from_user, copy_to_user, map_user_pages};
+
+use core::fmt;
+use cpu_features::Feature;
+use memory::{is_private_address, VirtualAddress};
+use spin::RwLock;
+
+/// The maximum number of system calls that can be registered.
+pub const MAX_SYSCALLS: usize = 256;
+
+/// The numbers of the built-in system calls.
+pub mod numbers {
+    /// Returns from userspace back into the kernel code that invoked
+    /// [`run_in_user_mode()`](crate::run_in_user_mode), with the exit code in the first argument.
+    pub const EXIT:        usize = 0;
+    /// Logs the UTF-8 string at the address in the first argument
+    /// with the length in bytes in the second argument.
+    pub const LOG:         usize = 1;
+    /// Yields the CPU to another task.
+    pub const YIELD:       usize = 2;
+    /// Returns the ID of the current task.
+    pub const GET_TASK_ID: usize = 3;
+}
+
+/// The arguments passed from userspace to a system call handler.
+#[derive(Debug, Clone, Copy)]
+pub struct SyscallArgs {
+    /// The system call number, taken from `rax`.
+    pub number: usize,
+    /// The arguments, taken from `rdi`, `rsi`, `rdx`, `r10`, `r8`, and `r9`, in that order.
+    pub args: [usize; 6],
+}
+
+/// The errors that a system call can return to userspace.
+///
+/// These are returned as the negated value of each variant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(isize)]
+pub enum SyscallError {
+    /// No handler was registered for the requested system call number.
+    NoSuchSyscall   = 1,
+    /// An argument was invalid.
+    InvalidArgument = 2,
+    /// A user-provided address was not mapped or not accessible from userspace.
+    BadAddress      = 3,
+    /// The handler failed for another reason.
+    Failed          = 4,
+}
+
+impl SyscallError {
+    /// Returns the value that represents this error in the `rax` register.
+    pub fn to_return_value(self) -> usize {
+        (-(self as isize)) as usize
+    }
+}
+
+impl fmt::Display for SyscallError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let msg = match self {
+            SyscallError::NoSuchSyscall   => "no such system call",
+            SyscallError::InvalidArgument => "invalid argument",
+            SyscallError::BadAddress      => "bad address",
+            SyscallError::Failed          => "system call failed",
+        };
+        f.write_str(msg)
+    }
+}
+
+/// The signature of a system call handler.
+///
+/// The returned value is placed in `rax` upon returning to userspace.
+pub type SyscallHandler = fn(&SyscallArgs) -> Result<usize, SyscallError>;
+
+/// The system call dispatch table, indexed by system call number.
+static SYSCALL_TABLE: RwLock<[Option<SyscallHandler>; MAX_SYSCALLS]> = RwLock::new([None; MAX_SYSCALLS]);
+
+/// Registers the built-in system calls and a [`cpu_features`] hook that
+/// configures the `syscall` instruction on each CPU.
+///
+/// This must be invoked before [`cpu_features::run_hooks()`] is invoked on the BSP,
+/// after the GDT and TSS have been initialized.
+pub fn init() -> Result<(), &'static str> {
+    register_syscall(numbers::EXIT,        sys_exit)?;
+    register_syscall(numbers::LOG,         sys_log)?;
+    register_syscall(numbers::YIELD,       sys_yield)?;
+    register_syscall(numbers::GET_TASK_ID, sys_get_task_id)?;
+    cpu_features::register_hook("enable SYSCALL", Feature::Syscall, entry::init_cpu);
+    Ok(())
+}
+
+/// Registers the given `handler` for the system call with the given `number`.
+///
+/// Returns an error if `number` is out of range or already has a registered handler.
+pub fn register_syscall(number: usize, handler: SyscallHandler) -> Result<(), &'static str> {
+    let mut table = SYSCALL_TABLE.write();
+    let slot = table.get_mut(number).ok_or("system call number is out of range")?;
+    if slot.is_some() {
+        return Err("a handler is already registered for that system call number");
+    }
+    *slot = Some(handler);
+    Ok(())
+}
+
+/// Removes the handler for the system call with the given `number`, if any.
+pub fn unregister_syscall(number: usize) -> Option<SyscallHandler> {
+    SYSCALL_TABLE.write().get_mut(number).and_then(Option::take)
+}
+
+/// Invokes the handler registered for the system call in the given `args`.
+fn dispatch(args: &SyscallArgs) -> Result<usize, SyscallError> {
+    // Copy the handler out of the table such that the lock isn't held while it runs,
+    // which is important because some handlers (e.g., exit) never return.
+    let handler = SYSCALL_TABLE.read()
+        .get(args.number)
+        .copied()
+        .flatten()
+        .ok_or(SyscallError::NoSuchSyscall)?;
+    handler(args)
+}
+
+
+/// Runs the code at `entry` in userspace (ring 3), using the stack that ends at `user_stack_top`.
+///
+/// This returns when that code invokes the [`EXIT`](numbers::EXIT) system call,
+/// with the exit code it passed to that system call.
+/// Both `entry` and the user stack must have been mapped via [`map_user_pages()`]
+/// in the current task's address space.
+///
+/// While running in userspace, interrupts and system calls from userspace
+/// use the region of the current task's kernel stack below this function's frame.
+pub fn run_in_user_mode(entry: VirtualAddress, user_stack_top: VirtualAddress) -> Result<isize, &'static str> {
+    if !is_private_address(entry) || !is_private_address(user_stack_top - 1) {
+        return Err("userspace entry point and stack must be within the private region");
+    }
+    let already_in_userspace = task::with_current_task(|t| t.is_userspace())
+        .map_err(|_| "run_in_user_mode(): couldn't get current task")?;
+    if already_in_userspace {
+        return Err("run_in_user_mode(): current task is already running in userspace");
+    }
+    let user_cs = gdt::AvailableSegmentSelector::UserCode64.get()
+        .ok_or("run_in_user_mode(): GDT was not yet initialized")?;
+    // Use the same stack segment that `sysret` will load, for consistency.
+    let user_ss = gdt::AvailableSegmentSelector::UserData32.get()
+        .ok_or("run_in_user_mode(): GDT was not yet initialized")?;
+
+    // Interrupts remain disabled until the switch to userspace, and are restored upon exit.
+    let _held_interrupts = irq_safety::hold_interrupts();
+    // SAFE: the entry point and stack are in the private region, which is only accessible
+    //       to userspace if mapped with `map_user_pages()`.
+    let exit_code = unsafe {
+        entry::enter_user_mode(entry.value(), user_stack_top.value(), user_cs.0 as usize, user_ss.0 as usize)
+    };
+    Ok(exit_code)
+}
+
+
+/// The handler for [`numbers::EXIT`].
+fn sys_exit(args: &SyscallArgs) -> Result<usize, SyscallError> {
+    entry::exit_user_mode(args.args[0] as isize)
+}
+
+/// The handler for [`numbers::LOG`].
+fn sys_log(args: &SyscallArgs) -> Result<usize, SyscallError> {
+    /// The maximum length of a single log message from userspace.
+    const MAX_LOG_LEN: usize = 256;
+
+    let len = args.args[1];
+    if len > MAX_LOG_LEN {
+        return Err(SyscallError::InvalidArgument);
+    }
+    let addr = VirtualAddress::new(args.args[0]).ok_or(SyscallError::BadAddress)?;
+    let mut buf = [0u8; MAX_LOG_LEN];
+    copy_from_user(&mut buf[..len], addr)?;
+    let msg = core::str::from_utf8(&buf[..len]).map_err(|_| SyscallError::InvalidArgument)?;
+    log::info!("[user task {}] {}", task::get_my_current_task_id(), msg);
+    Ok(len)
+}
+
+/// The handler for [`numbers::YIELD`].
+fn sys_yield(_args: &SyscallArgs) -> Result<usize, SyscallError> {
+    task::schedule();
+    Ok(0)
+}
+
+/// The handler for [`numbers::GET_TASK_ID`].
+fn sys_get_task_id(_args: &SyscallArgs) -> Result<usize, SyscallError> {
+    Ok(task::get_my_current_task_id())
+}
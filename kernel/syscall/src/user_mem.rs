@@ -0,0 +1,82 @@
+//! Mapping memory for userspace and safely copying data to and from it.
+
+use memory::{
+    is_private_address, MappedPages, Page, PageRange, PteFlags, PteFlagsArch, VirtualAddress,
+};
+use fault_tolerant_copy::copy_maybe_faulting;
+use crate::SyscallError;
+
+/// Maps at least `size_in_bytes` of memory that is accessible from userspace
+/// into the private region of the current task's address space.
+///
+/// The mapping is never executable and writable at the same time;
+/// to load code, map it as writable, copy the code into it within a
+/// [`memory_protection::with_user_access()`] window, and then remap it as executable.
+pub fn map_user_pages(
+    size_in_bytes: usize,
+    writable: bool,
+    executable: bool,
+) -> Result<MappedPages, &'static str> {
+    if writable && executable {
+        return Err("userspace mappings cannot be both writable and executable");
+    }
+    let flags = PteFlags::new()
+        .valid(true)
+        .writable(writable)
+        .executable(executable)
+        | PteFlags::_USER_ACCESSIBLE;
+    let mmi = task::with_current_task(|t| t.mmi.clone())
+        .map_err(|_| "map_user_pages(): couldn't get current task")?;
+    memory::create_private_mapping(&mmi, &mmi, size_in_bytes, flags)
+}
+
+/// Copies `dst.len()` bytes from the userspace address `src` into `dst`.
+///
+/// Returns [`SyscallError::BadAddress`] if any part of the source range
+/// is not mapped as accessible from userspace in the current address space.
+pub fn copy_from_user(dst: &mut [u8], src: VirtualAddress) -> Result<(), SyscallError> {
+    validate_user_range(src, dst.len(), false)?;
+    memory_protection::with_user_access(|| {
+        // SAFE: the source range was validated above, and cannot overlap with kernel memory.
+        // It may still be unmapped concurrently by another task, which is tolerated here.
+        unsafe { copy_maybe_faulting(dst.as_mut_ptr(), src.value() as *const u8, dst.len()) }
+    }).map_err(|_| SyscallError::BadAddress)
+}
+
+/// Copies the bytes in `src` to the userspace address `dst`.
+///
+/// Returns [`SyscallError::BadAddress`] if any part of the destination range
+/// is not mapped as writable and accessible from userspace in the current address space.
+pub fn copy_to_user(dst: VirtualAddress, src: &[u8]) -> Result<(), SyscallError> {
+    validate_user_range(dst, src.len(), true)?;
+    memory_protection::with_user_access(|| {
+        // SAFE: the destination range was validated above, and cannot overlap with kernel memory.
+        // It may still be unmapped concurrently by another task, which is tolerated here.
+        unsafe { copy_maybe_faulting(dst.value() as *mut u8, src.as_ptr(), src.len()) }
+    }).map_err(|_| SyscallError::BadAddress)
+}
+
+/// Ensures that the range of `len` bytes starting at `start` is within the private region
+/// and is entirely mapped as accessible from userspace (and writable, if `write` is `true`).
+fn validate_user_range(start: VirtualAddress, len: usize, write: bool) -> Result<(), SyscallError> {
+    if len == 0 {
+        return Ok(());
+    }
+    let end = start.value().checked_add(len - 1)
+        .and_then(VirtualAddress::new)
+        .ok_or(SyscallError::BadAddress)?;
+    if !is_private_address(start) || !is_private_address(end) {
+        return Err(SyscallError::BadAddress);
+    }
+    let pages = PageRange::new(Page::containing_address(start), Page::containing_address(end));
+    let accessible = task::with_current_task(|t| {
+        let mmi = t.mmi.lock();
+        pages.into_iter().all(|page| {
+            mmi.page_table.page_flags(page).is_some_and(|flags| {
+                flags.contains(PteFlagsArch::_USER_ACCESSIBLE) && (!write || flags.is_writable())
+            })
+        })
+    }).map_err(|_| SyscallError::Failed)?;
+
+    if accessible { Ok(()) } else { Err(SyscallError::BadAddress) }
+}
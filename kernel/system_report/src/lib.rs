@@ -0,0 +1,358 @@
+//! Gathers the state of the running system into a single report that can be attached to a bug report.
+//!
+//! A [`SystemReport`] includes:
+//! * which machine produced it, as described by its firmware,
+//! * the boot timeline, i.e., when each milestone recorded via [`record_boot_milestone()`] was reached,
+//! * the crates loaded into each namespace, with their hashes and versions,
+//! * physical memory usage,
+//! * how many times each interrupt has been serviced,
+//! * every task and its state,
+//! * the configuration and counters of each network interface,
+//! * all registered metrics,
+//! * the most recent log records, if the logger keeps a history of them.
+//!
+//! A report can be serialized as JSON or in the compact binary format of the `serialize` crate,
+//! and then either written to a file via [`save()`] or streamed out via [`SystemReport::write_json()`].
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::{
+    format,
+    string::{String, ToString},
+    sync::Arc,
+    vec::Vec,
+};
+use core::fmt;
+use fs_node::{DirRef, FileRef};
+use memfs::MemFile;
+use mod_mgmt::{CrateNamespace, CRATE_HASH_DELIMITER};
+use serialize::Serialize;
+use spin::Mutex;
+use time::{Duration, Instant, Monotonic, WallTime};
+
+/// The milestones reached while booting, in the order they were reached.
+static BOOT_TIMELINE: Mutex<Vec<(&'static str, Instant)>> = Mutex::new(Vec::new());
+
+/// Records that the boot process has reached the milestone with the given name.
+///
+/// The first milestone is the start of the boot timeline,
+/// so it should be recorded as soon as a monotonic clock source is available.
+pub fn record_boot_milestone(name: &'static str) {
+    let now = time::now::<Monotonic>();
+    BOOT_TIMELINE.lock().push((name, now));
+}
+
+/// The format that a [`SystemReport`] is serialized in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Binary,
+}
+
+impl Format {
+    /// Returns the format that's conventionally saved with the given file extension.
+    pub fn from_extension(extension: &str) -> Option<Format> {
+        match extension {
+            "json" => Some(Format::Json),
+            "bin" => Some(Format::Binary),
+            _ => None,
+        }
+    }
+
+    /// Returns the file extension conventionally used for this format.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Json => "json",
+            Format::Binary => "bin",
+        }
+    }
+}
+
+/// A snapshot of the state of the running system.
+#[derive(Serialize)]
+pub struct SystemReport {
+    /// The wall-clock time at which this report was generated.
+    pub wall_time: Duration,
+    /// How long after the first boot milestone this report was generated.
+    pub since_boot: Option<Duration>,
+    /// The machine that generated this report, or `None` if its firmware doesn't describe it.
+    pub machine: Option<MachineInfo>,
+    pub boot_timeline: Vec<BootMilestone>,
+    pub namespaces: Vec<NamespaceInfo>,
+    pub memory: MemoryInfo,
+    pub interrupts: Vec<InterruptInfo>,
+    pub tasks: Vec<TaskInfo>,
+    pub network: Vec<InterfaceInfo>,
+    pub metrics: Vec<metrics::MetricSnapshot>,
+    /// The most recent log records, or `None` if the logger doesn't keep a history of them.
+    pub log: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct MachineInfo {
+    pub manufacturer: Option<String>,
+    pub product_name: Option<String>,
+    pub serial_number: Option<String>,
+    pub uuid: Option<String>,
+    pub baseboard: Option<String>,
+    pub bios_version: Option<String>,
+    /// The total size in bytes of the memory installed in all memory devices.
+    pub installed_memory: u64,
+}
+
+#[derive(Serialize)]
+pub struct BootMilestone {
+    pub name: &'static str,
+    /// How long after the first boot milestone this one was reached.
+    pub elapsed: Duration,
+}
+
+#[derive(Serialize)]
+pub struct NamespaceInfo {
+    pub name: String,
+    pub crates: Vec<CrateInfo>,
+}
+
+#[derive(Serialize)]
+pub struct CrateInfo {
+    pub name: String,
+    /// The hash that was appended to the crate's name when it was compiled, if any.
+    pub hash: Option<String>,
+    /// The version that the crate declares via `crate_version::declare!()`, if any.
+    pub version: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct MemoryInfo {
+    pub frame_size: usize,
+    pub general_frames: usize,
+    pub free_general_frames: usize,
+    pub reserved_frames: usize,
+    pub free_reserved_frames: usize,
+}
+
+#[derive(Serialize)]
+pub struct InterruptInfo {
+    pub number: u8,
+    pub count: u64,
+    /// The name of the section that handles this interrupt, if it could be determined.
+    pub handler: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct TaskInfo {
+    pub id: usize,
+    pub name: String,
+    pub runstate: String,
+    pub cpu: Option<u32>,
+    pub pinned_cpu: Option<u32>,
+    pub is_application: bool,
+    pub is_idle: bool,
+}
+
+#[derive(Serialize)]
+pub struct InterfaceInfo {
+    pub name: String,
+    pub mac_address: String,
+    pub is_up: bool,
+    pub mtu: usize,
+    pub ip_addresses: Vec<String>,
+    pub gateway: Option<String>,
+    pub rx_packets: u64,
+    pub rx_bytes: u64,
+    pub rx_dropped: u64,
+    pub tx_packets: u64,
+    pub tx_bytes: u64,
+    pub tx_dropped: u64,
+}
+
+impl SystemReport {
+    /// Writes this report as JSON to the given `writer`, as it's being serialized.
+    pub fn write_json<W: fmt::Write>(&self, writer: W) -> Result<(), serialize::Error> {
+        self.serialize(&mut serialize::json::JsonSerializer::new(writer))
+    }
+
+    /// Returns this report serialized in the given format.
+    pub fn encode(&self, format: Format) -> Result<Vec<u8>, &'static str> {
+        match format {
+            Format::Json => serialize::to_json(self)
+                .map(String::into_bytes)
+                .map_err(|_| "failed to serialize the system report as JSON"),
+            Format::Binary => Ok(serialize::to_binary(self)),
+        }
+    }
+}
+
+/// Gathers the current state of the system into a new report.
+///
+/// Crates are listed for the current task's namespace and its recursive namespaces,
+/// or for the initial kernel namespace if there is no current task.
+pub fn generate() -> SystemReport {
+    let timeline = BOOT_TIMELINE.lock().clone();
+    let boot_start = timeline.first().map(|(_, instant)| *instant);
+    let namespace = task::with_current_task(|t| t.get_namespace().clone())
+        .ok()
+        .or_else(|| mod_mgmt::get_initial_kernel_namespace().cloned());
+
+    SystemReport {
+        wall_time: time::now::<WallTime>(),
+        since_boot: boot_start.map(|start| time::now::<Monotonic>().duration_since(start)),
+        machine: machine(),
+        boot_timeline: timeline.iter()
+            .map(|&(name, instant)| BootMilestone {
+                name,
+                elapsed: boot_start.map_or(Duration::ZERO, |start| instant.duration_since(start)),
+            })
+            .collect(),
+        namespaces: namespace.as_ref().map(namespaces).unwrap_or_default(),
+        memory: memory_info(),
+        interrupts: interrupts_info(namespace.as_deref()),
+        tasks: tasks(),
+        network: network(),
+        metrics: metrics::snapshot(),
+        log: logger::history(),
+    }
+}
+
+/// Saves the given report in the given format as a new file with the given name in the given directory.
+pub fn save(report: &SystemReport, format: Format, dir: &DirRef, name: &str) -> Result<FileRef, &'static str> {
+    if dir.lock().get(name).is_some() {
+        return Err("a file with that name already exists");
+    }
+    let bytes = report.encode(format)?;
+    let file = MemFile::create(name.to_string(), dir)?;
+    file.lock()
+        .write_at(&bytes, 0)
+        .map_err(|_| "failed to write system report file")?;
+    Ok(file)
+}
+
+fn namespaces(namespace: &Arc<CrateNamespace>) -> Vec<NamespaceInfo> {
+    let mut namespaces = Vec::new();
+    let mut next = Some(namespace);
+    while let Some(ns) = next {
+        let mut crates = Vec::new();
+        ns.for_each_crate(false, |name, crate_ref| {
+            let version = mod_mgmt::versions::crate_version(&crate_ref.lock_as_ref());
+            let (name, hash) = match name.rsplit_once(CRATE_HASH_DELIMITER) {
+                Some((name, hash)) if hash.bytes().all(|b| b.is_ascii_hexdigit()) => (name, Some(hash)),
+                _ => (name, None),
+            };
+            crates.push(CrateInfo {
+                name: name.to_string(),
+                hash: hash.map(String::from),
+                version: version.map(|v| v.to_string()),
+            });
+            true
+        });
+        crates.sort_by(|a, b| a.name.cmp(&b.name));
+        namespaces.push(NamespaceInfo { name: ns.name().to_string(), crates });
+        next = ns.recursive_namespace();
+    }
+    namespaces
+}
+
+fn machine() -> Option<MachineInfo> {
+    let info = firmware_info::get().ok()?;
+    let system = info.system.clone().unwrap_or_default();
+    // The baseboard is identified by its manufacturer and product name, whichever are given.
+    let baseboard = info.baseboard.as_ref().and_then(|board| {
+        let names: Vec<&str> = [&board.manufacturer, &board.product_name].into_iter().flatten().map(String::as_str).collect();
+        (!names.is_empty()).then(|| names.join(" "))
+    });
+    Some(MachineInfo {
+        manufacturer: system.manufacturer,
+        product_name: system.product_name,
+        serial_number: system.serial_number,
+        uuid: system.uuid,
+        baseboard,
+        bios_version: info.bios.as_ref().and_then(|bios| bios.version.clone()),
+        installed_memory: info.installed_memory(),
+    })
+}
+
+fn memory_info() -> MemoryInfo {
+    let stats = frame_allocator::stats();
+    MemoryInfo {
+        frame_size: memory::PAGE_SIZE,
+        general_frames: stats.general_frames,
+        free_general_frames: stats.free_general_frames,
+        reserved_frames: stats.reserved_frames,
+        free_reserved_frames: stats.free_reserved_frames,
+    }
+}
+
+fn interrupts_info(namespace: Option<&CrateNamespace>) -> Vec<InterruptInfo> {
+    let mut counts = Vec::new();
+    interrupts::for_each_interrupt_count(|number, count| counts.push((number, count)));
+
+    #[cfg(target_arch = "x86_64")]
+    let handlers = {
+        let mut handlers = Vec::new();
+        interrupts::for_each_registered_interrupt(|number, addr| handlers.push((number, addr)));
+        handlers
+    };
+    #[cfg(not(target_arch = "x86_64"))]
+    let handlers: Vec<(u8, usize)> = Vec::new();
+
+    // Look up handler names after the IDT has been unlocked, since that can be slow.
+    counts.into_iter()
+        .map(|(number, count)| InterruptInfo {
+            number,
+            count,
+            handler: handlers.iter()
+                .find(|(n, _)| *n == number)
+                .and_then(|&(_, addr)| namespace?.get_section_containing_address(
+                    memory::VirtualAddress::new_canonical(addr),
+                    false,
+                ))
+                .map(|(section, _)| String::from(&*section.name)),
+        })
+        .collect()
+}
+
+fn tasks() -> Vec<TaskInfo> {
+    task::all_tasks()
+        .iter()
+        .filter_map(|(_, t)| t.upgrade())
+        .map(|task| TaskInfo {
+            id: task.id,
+            name: task.name.clone(),
+            runstate: format!("{:?}", task.runstate()),
+            cpu: task.running_on_cpu().map(|c| c.value()),
+            pinned_cpu: task.pinned_cpu().map(|c| c.value()),
+            is_application: task.is_application(),
+            is_idle: task.is_an_idle_task,
+        })
+        .collect()
+}
+
+fn network() -> Vec<InterfaceInfo> {
+    net::get_interfaces()
+        .lock()
+        .iter()
+        .map(|interface| {
+            let mac = interface.mac_address();
+            let counters = interface.counters();
+            InterfaceInfo {
+                name: interface.name().to_string(),
+                mac_address: format!(
+                    "{:02x}:{:02x}:{:02x}:{:02x}:{:02x}:{:02x}",
+                    mac[0], mac[1], mac[2], mac[3], mac[4], mac[5]
+                ),
+                is_up: interface.is_up(),
+                mtu: interface.mtu(),
+                ip_addresses: interface.ip_addrs().iter().map(ToString::to_string).collect(),
+                gateway: interface.gateway().map(|gateway| gateway.to_string()),
+                rx_packets: counters.rx_packets,
+                rx_bytes: counters.rx_bytes,
+                rx_dropped: counters.rx_dropped,
+                tx_packets: counters.tx_packets,
+                tx_bytes: counters.tx_bytes,
+                tx_dropped: counters.tx_dropped,
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,145 @@
+//! Inspection and control of other tasks, e.g., by shell applications like `ps` and `kill`.
+//!
+//! [`list()`] and [`info()`] take a snapshot of the states of tasks,
+//! including their runstate, CPU, priority, and total CPU time.
+//!
+//! [`request()`] performs an [`Action`] on a task, such as killing or suspending it,
+//! after checking that the current task is permitted to do so:
+//! * Idle tasks cannot be controlled at all.
+//! * Application tasks can only control other application tasks, not kernel tasks.
+//! * A task cannot suspend itself, as nothing would be able to resume it.
+
+use alloc::{string::String, vec::Vec};
+use core::time::Duration;
+use cpu::CpuId;
+use crate::{all_tasks, get_task, notifications, scheduler, with_current_task, RunState, TaskRef};
+
+pub use task_struct::Notification;
+
+/// A snapshot of the states of a task, as returned by [`list()`] or [`info()`].
+#[derive(Clone, Debug)]
+pub struct TaskInfo {
+    /// The unique ID of the task.
+    pub id: usize,
+    /// The name of the task.
+    pub name: String,
+    /// The runstate of the task.
+    pub runstate: RunState,
+    /// Whether the task is suspended, which is independent from its runstate.
+    pub suspended: bool,
+    /// The CPU that the task is currently running on, if any.
+    pub cpu: Option<CpuId>,
+    /// The CPU that the task is pinned to, if any.
+    pub pinned_cpu: Option<CpuId>,
+    /// The priority of the task, if it's on a priority run queue.
+    pub priority: Option<u8>,
+    /// The total time that the task has spent running.
+    pub cpu_time: Duration,
+    /// Whether the task is an idle task.
+    pub is_idle: bool,
+    /// Whether the task is an application task.
+    pub is_application: bool,
+}
+
+impl TaskInfo {
+    /// Returns a single character that describes the kind of the task:
+    /// `I` for an idle task, `A` for an application task, or `K` for a kernel task.
+    pub fn kind(&self) -> char {
+        if self.is_idle {
+            'I'
+        } else if self.is_application {
+            'A'
+        } else {
+            'K'
+        }
+    }
+}
+
+/// Returns a snapshot of the states of the given task.
+pub fn info(task: &TaskRef) -> TaskInfo {
+    TaskInfo {
+        id: task.id,
+        name: task.name.clone(),
+        runstate: task.runstate(),
+        suspended: task.is_suspended(),
+        cpu: task.running_on_cpu(),
+        pinned_cpu: task.pinned_cpu(),
+        priority: scheduler::priority(task),
+        cpu_time: task.cpu_time(),
+        is_idle: task.is_an_idle_task,
+        is_application: task.is_application(),
+    }
+}
+
+/// Returns a snapshot of the states of all tasks that currently exist, ordered by task ID.
+///
+/// Like [`all_tasks()`], this is expensive and should be used rarely.
+pub fn list() -> Vec<TaskInfo> {
+    all_tasks()
+        .into_iter()
+        .filter_map(|(_, task)| task.upgrade())
+        .map(|task| info(&task))
+        .collect()
+}
+
+/// An action that can be performed on a task via [`request()`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    /// Sends the given notification to the task, e.g., to kill it.
+    Notify(Notification),
+    /// Suspends the task such that it won't be scheduled in until it's resumed.
+    Suspend,
+    /// Resumes a suspended task.
+    Resume,
+    /// Sets the priority of the task, which must be on a priority run queue.
+    SetPriority(u8),
+}
+
+/// Performs the given `action` on the task with the given ID,
+/// if the current task is permitted to do so.
+///
+/// See the [module-level documentation](self) for which actions are permitted.
+pub fn request(task_id: usize, action: Action) -> Result<(), &'static str> {
+    let task = get_task(task_id)
+        .and_then(|task| task.upgrade())
+        .ok_or("no task with that ID exists")?;
+    check_permission(&task, action)?;
+
+    match action {
+        Action::Notify(notification) => notifications::send(&task, notification),
+        Action::Suspend => {
+            task.suspend();
+            Ok(())
+        }
+        Action::Resume => {
+            task.unsuspend();
+            Ok(())
+        }
+        Action::SetPriority(priority) => {
+            if scheduler::set_priority(&task, priority) {
+                Ok(())
+            } else {
+                Err("the task isn't on a priority run queue")
+            }
+        }
+    }
+}
+
+/// Returns an error if the current task isn't permitted to perform `action` on `task`.
+fn check_permission(task: &TaskRef, action: Action) -> Result<(), &'static str> {
+    if task.has_exited() {
+        return Err("the task has already exited");
+    }
+    if task.is_an_idle_task {
+        return Err("idle tasks cannot be controlled");
+    }
+    let (current_id, current_is_application) = with_current_task(|t| (t.id, t.is_application()))
+        .map_err(|_| "couldn't get current task")?;
+    if current_is_application && !task.is_application() {
+        return Err("application tasks cannot control kernel tasks");
+    }
+    if action == Action::Suspend && task.id == current_id {
+        return Err("a task cannot suspend itself");
+    }
+    Ok(())
+}
@@ -17,6 +17,8 @@
 //!    * [`get_my_current_task_id()`] is fastest if you just want the ID of the current task.
 //!      Note that it is fairly expensive to obtain a task reference from a task ID.
 //! 2. Register a kill handler for the current task -- [`set_kill_handler()`].
+//!    Send asynchronous notifications to tasks -- see the [`notifications`] module.
+//!    Inspect and control other tasks, e.g., from the shell -- see the [`control`] module.
 //! 3. Yield the current CPU and schedule in another task -- [`schedule()`].
 //! 4. Switch from the current task to another specific "next" task -- [`task_switch()`].
 //!
@@ -30,6 +32,8 @@
 
 extern crate alloc;
 
+pub mod control;
+pub mod notifications;
 pub mod scheduler;
 
 use alloc::{
@@ -52,18 +56,20 @@ use irq_safety::hold_interrupts;
 use log::error;
 use environment::Environment;
 use memory::MmiRef;
+use metrics::{FnMetric, MetricKind, Samples};
 use no_drop::NoDrop;
 use preemption::PreemptionGuard;
 use spin::Mutex;
 use sync_irq::IrqSafeMutex;
 use stack::Stack;
 use task_struct::ExposedTask;
+use time::Instant;
 
 
 // Re-export main types from `task_struct`.
 pub use task_struct::{
     ExitValue, InheritedStates, KillHandler, KillReason,
-    PanicInfoOwned, RestartInfo, RunState, Task,
+    PanicInfoOwned, RestartInfo, RunState, Task, WaitReason,
 };
 #[cfg(simd_personality)]
 pub use task_struct::SimdExt;
@@ -91,6 +97,38 @@ pub fn all_tasks() -> Vec<(usize, WeakTaskRef)> {
     v
 }
 
+/// The number of tasks in each runstate, which is counted whenever metrics are exported.
+static TASKS_METRIC: FnMetric = FnMetric::new(
+    "theseus_tasks",
+    "The number of tasks in each runstate.",
+    MetricKind::Gauge,
+    collect_tasks_metric,
+);
+
+fn collect_tasks_metric(samples: &mut Samples<'_>) -> fmt::Result {
+    const RUNSTATES: [RunState; 5] = [
+        RunState::Initing,
+        RunState::Runnable,
+        RunState::Blocked,
+        RunState::Exited,
+        RunState::Reaped,
+    ];
+    let mut counts = [0usize; RUNSTATES.len()];
+    for (_, task) in all_tasks() {
+        if let Some(task) = task.upgrade() {
+            let runstate = task.runstate();
+            if let Some(i) = RUNSTATES.iter().position(|r| *r == runstate) {
+                counts[i] += 1;
+            }
+        }
+    }
+    for (runstate, count) in RUNSTATES.iter().zip(counts) {
+        let runstate = format!("{runstate:?}");
+        samples.write("", &[("runstate", &runstate as &dyn fmt::Display)], count)?;
+    }
+    Ok(())
+}
+
 
 /// The signature of a Task's failure cleanup function.
 pub type FailureCleanupFunction = fn(ExitableTaskRef, KillReason) -> !;
@@ -215,7 +253,10 @@ impl TaskRef {
     pub fn kill(&self, reason: KillReason) -> Result<(), &'static str> {
         // TODO FIXME: cause a panic in this Task such that it will start the unwinding process
         // instead of immediately causing it to exit
-        self.internal_exit(ExitValue::Killed(reason))
+        self.internal_exit(ExitValue::Killed(reason))?;
+        // A killed task never runs its own exit path, so the objects it owns must be released here.
+        handle_table::release_owned_by(self.id);
+        Ok(())
     }
 
     /// The internal routine that actually exits or kills a Task.
@@ -780,44 +821,26 @@ fn task_switch_inner(
     //     }
     // }
 
-    // Note that because userspace support is currently disabled, this will never happen.
-    // // Change the privilege stack (RSP0) in the TSS.
-    // // We can safely skip setting the TSS RSP0 when switching to a kernel task, 
-    // // i.e., when `next` is not a userspace task.
-    // if next.is_userspace() {
-    //     let (stack_bottom, stack_size) = {
-    //         let kstack = &next.task.0.task.inner().lock().kstack;
-    //         (kstack.bottom(), kstack.size_in_bytes())
-    //     };
-    //     let new_tss_rsp0 = stack_bottom + (stack_size / 2); // the middle half of the stack
-    //     if tss::tss_set_rsp0(new_tss_rsp0).is_ok() { 
-    //         // debug!("task_switch [2]: new_tss_rsp = {:#X}", new_tss_rsp0);
-    //     } else {
-    //         error!("task_switch(): failed to set CPU {} TSS RSP0, aborting task switch!", cpu_id);
-    //         return (false, preemption_guard);
-    //     }
-    // }
+    // Change the privilege stack (RSP0) in the TSS if the next task is running in userspace,
+    // such that interrupts and system calls from userspace use that task's kernel stack.
+    // We can safely skip this when switching to a kernel task.
+    #[cfg(target_arch = "x86_64")]
+    if let Some(new_tss_rsp0) = next.userspace_kernel_stack_top() {
+        if tss::tss_set_rsp0(new_tss_rsp0).is_err() {
+            error!("task_switch(): failed to set CPU {} TSS RSP0, aborting task switch!", cpu_id);
+            return Err((false, preemption_guard));
+        }
+    }
 
-    // // Switch page tables. 
-    // // Since there is only a single address space (as userspace support is currently disabled),
-    // // we do not need to do this at all.
-    // if false {
-    //     let prev_mmi = &curr.mmi;
-    //     let next_mmi = &next.mmi;
-    //    
-    //     if Arc::ptr_eq(prev_mmi, next_mmi) {
-    //         // do nothing because we're not changing address spaces
-    //         // debug!("task_switch [3]: prev_mmi is the same as next_mmi!");
-    //     } else {
-    //         // time to change to a different address space and switch the page tables!
-    //         let mut prev_mmi_locked = prev_mmi.lock();
-    //         let next_mmi_locked = next_mmi.lock();
-    //         // debug!("task_switch [3]: switching tables! From {} {:?} to {} {:?}", 
-    //         //         curr.name, prev_mmi_locked.page_table, next.name, next_mmi_locked.page_table);
-    //
-    //         prev_mmi_locked.page_table.switch(&next_mmi_locked.page_table);
-    //     }
-    // }
+    // Switch page tables if the next task runs in a different address space.
+    // All address spaces share the same kernel mappings (see `memory::create_address_space()`),
+    // so this only changes which private mappings are visible.
+    if !Arc::ptr_eq(&curr.mmi, &next.mmi) {
+        let next_mmi = next.mmi.lock();
+        if !next_mmi.page_table.is_active() {
+            next_mmi.page_table.activate();
+        }
+    }
 
     let prev_task_saved_sp: *mut usize = {
         let mut inner = curr.0.task.inner().lock(); // ensure the lock is released
@@ -828,9 +851,19 @@ fn task_switch_inner(
         inner.saved_sp
     };
 
+    // Account for the time that the current task has just spent running,
+    // and start timing the next task, which is done before it is marked as running.
+    let now = Instant::now();
+    let ran_for = now.duration_since(curr.0.task.switched_in_at().load());
+    curr.0.task.cpu_time_nanos().fetch_add(ran_for.as_nanos() as u64, Ordering::AcqRel);
+    next.0.task.switched_in_at().store(now);
+
     // Mark the current task as no longer running
     curr.0.task.running_on_cpu().store(None.into());
 
+    // Save or defer saving the current task's FPU/SIMD state, depending on the FPU mode.
+    fpu_state::switch(&curr.fpu_state, &next.fpu_state, &preemption_guard);
+
     // After this point, we may need to mutate the `curr_task_tls_slot` (if curr has exited),
     // so we use local variables to store some necessary info about the curr task
     // and then end our immutable borrow of the current task.
@@ -1097,6 +1130,9 @@ pub fn bootstrap_task(
     let namespace = mod_mgmt::get_initial_kernel_namespace()
         .ok_or("Must initalize kernel CrateNamespace (mod_mgmt) before the tasking subsystem.")?
         .clone();
+    // Registering the same metrics again on each CPU has no effect.
+    metrics::register(&scheduler::CONTEXT_SWITCHES)?;
+    metrics::register(&TASKS_METRIC)?;
     let env = Arc::new(Mutex::new(Environment::default()));
     let mut bootstrap_task = Task::new(
         Some(stack.into_inner()),
@@ -0,0 +1,111 @@
+//! Asynchronous delivery of [`Notification`]s to tasks, similar to POSIX signals.
+//!
+//! A notification is sent to a task via [`send()`], which marks it as pending.
+//! The recipient task itself delivers its pending notifications via [`deliver_pending()`],
+//! which is invoked at preemption points and when returning from blocking calls
+//! (e.g., sleeping or waiting on a wait queue).
+//! Notifications are always delivered in the context of the recipient task, never within
+//! an interrupt handler: at a preemption point, the timer interrupt handler arranges for
+//! the interrupted code to invoke [`deliver_pending()`] before it resumes (currently x86_64 only).
+//! Thus, even a task running a busy loop that never yields will receive notifications.
+//!
+//! Upon delivery, the recipient's handler for that notification is invoked if it has
+//! registered one via [`set_handler()`]; otherwise, its [default action] is taken.
+//!
+//! [default action]: Notification::default_action
+
+use irq_safety::hold_interrupts;
+use crate::{with_current_task, KillReason, TaskRef};
+
+pub use task_struct::{DefaultAction, Notification, NotificationHandler, NUM_NOTIFICATIONS};
+
+/// Sends the given `notification` to the given `task`.
+///
+/// If the notification's default action is to kill the task and the task is blocked,
+/// it will be unblocked such that it can promptly receive the notification.
+/// Note that a suspended task will not receive notifications until it is unsuspended.
+///
+/// Returns an error if the task has already exited.
+pub fn send(task: &TaskRef, notification: Notification) -> Result<(), &'static str> {
+    if task.has_exited() {
+        return Err("cannot send a notification to a task that has exited");
+    }
+    task.add_pending_notification(notification);
+    if notification.default_action() == DefaultAction::Kill {
+        // The task may not be blocked, in which case there's nothing to do.
+        let _ = task.unblock();
+    }
+    Ok(())
+}
+
+/// Registers the given `handler` for the given kind of `notification` for the current task,
+/// replacing any previous handler.
+///
+/// Returns an error if `notification` cannot be handled, i.e., [`Notification::Kill`].
+///
+/// # Locking / Deadlock
+/// Obtains the lock on the current `Task`'s inner state in order to mutate it.
+pub fn set_handler(notification: Notification, handler: NotificationHandler) -> Result<(), &'static str> {
+    if notification == Notification::Kill {
+        return Err("the Kill notification cannot be handled");
+    }
+    with_current_task(|t| {
+        t.0.task.inner().lock().notification_handlers[notification as usize] = Some(handler);
+    })
+    .map_err(|_| "couldn't get current task")
+}
+
+/// Removes the current task's handler for the given kind of `notification`,
+/// such that its default action will be taken upon delivery.
+///
+/// # Locking / Deadlock
+/// Obtains the lock on the current `Task`'s inner state in order to mutate it.
+pub fn remove_handler(notification: Notification) -> Option<NotificationHandler> {
+    with_current_task(|t| t.0.task.inner().lock().notification_handlers[notification as usize].take())
+        .ok()
+        .flatten()
+}
+
+/// Delivers all notifications that are pending for the current task.
+///
+/// Each notification is handled by the current task's handler for it, if any;
+/// otherwise, its default action is taken, which may kill the current task.
+/// In that case, this function does not return.
+///
+/// This is invoked automatically at preemption points and blocking-call return sites,
+/// but a task can also invoke it directly, e.g., in a long-running loop.
+/// It must not be invoked from an interrupt handler.
+pub fn deliver_pending() {
+    let Ok(pending) = with_current_task(|t| {
+        t.has_pending_notifications().then(|| t.take_pending_notifications())
+    }) else {
+        return;
+    };
+
+    for notification in pending.into_iter().flatten() {
+        let handler = if notification == Notification::Kill {
+            None
+        } else {
+            with_current_task(|t| t.0.task.inner().lock().notification_handlers[notification as usize])
+                .ok()
+                .flatten()
+        };
+        match (handler, notification.default_action()) {
+            (Some(handler), _) => handler(notification),
+            (None, DefaultAction::Kill) => kill_current_task(notification),
+            (None, DefaultAction::Ignore) => { }
+        }
+    }
+}
+
+/// Kills the current task due to the given `notification` and switches away from it.
+fn kill_current_task(notification: Notification) -> ! {
+    let _held_interrupts = hold_interrupts();
+    let res = with_current_task(|t| t.kill(KillReason::Requested));
+    if !matches!(res, Ok(Ok(()))) {
+        log::error!("BUG: couldn't kill current task upon notification {:?}", notification);
+    }
+    loop {
+        crate::schedule();
+    }
+}
@@ -1,8 +1,9 @@
 use alloc::{boxed::Box, sync::Arc, vec::Vec};
-use core::ptr;
+use core::{ptr, sync::atomic::{AtomicBool, Ordering}};
 
 use cpu::CpuId;
-use spin::Mutex;
+use metrics::Counter;
+use spin::{Mutex, RwLock};
 use sync_preemption::PreemptionSafeMutex;
 
 use crate::TaskRef;
@@ -26,6 +27,36 @@ static SCHEDULER: Option<Arc<ConcurrentScheduler>> = None;
 
 type ConcurrentScheduler = PreemptionSafeMutex<dyn Scheduler>;
 
+/// The number of times that [`schedule`] switched to a different task, across all CPUs.
+pub static CONTEXT_SWITCHES: Counter = Counter::new(
+    "theseus_context_switches_total",
+    "The number of times a CPU switched from one task to another.",
+);
+
+/// Why the scheduler was invoked.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScheduleReason {
+    /// The current task yielded the CPU, e.g., via [`schedule`] or by blocking.
+    Yield,
+    /// The current task was preempted by the timer interrupt, via [`preempt`].
+    Preemption,
+}
+
+/// A function that observes, and may override, every scheduling decision on every CPU.
+///
+/// It is invoked with the current CPU, the reason for scheduling, the current task,
+/// the task selected by the scheduler policy, and the current CPU's scheduler,
+/// and returns the task to switch to, which must be runnable on this CPU.
+///
+/// It is invoked with preemption held and the current CPU's scheduler locked,
+/// possibly from an interrupt handler, so it must not invoke any functions in this module.
+pub type ScheduleHook = fn(CpuId, ScheduleReason, &TaskRef, TaskRef, &dyn Scheduler) -> TaskRef;
+
+/// The hook that is invoked on every scheduling decision, if any.
+static SCHEDULE_HOOK: RwLock<Option<ScheduleHook>> = RwLock::new(None);
+/// Whether [`SCHEDULE_HOOK`] is set, which lets [`schedule`] skip reading it in the common case.
+static SCHEDULE_HOOK_SET: AtomicBool = AtomicBool::new(false);
+
 /// Yields the current CPU by selecting a new `Task` to run next,
 /// and then switches to that new `Task`.
 ///
@@ -41,6 +72,18 @@ type ConcurrentScheduler = PreemptionSafeMutex<dyn Scheduler>;
 ///   continue running.
 #[doc(alias("yield"))]
 pub fn schedule() -> bool {
+    schedule_for(ScheduleReason::Yield)
+}
+
+/// Preempts the current task, switching to the next task selected by the scheduler policy.
+///
+/// This behaves exactly like [`schedule`], but is invoked by the timer interrupt handler
+/// such that a [`ScheduleHook`] can distinguish preemptions from voluntary yields.
+pub fn preempt() -> bool {
+    schedule_for(ScheduleReason::Preemption)
+}
+
+fn schedule_for(reason: ScheduleReason) -> bool {
     let preemption_guard = preemption::hold_preemption();
     // If preemption was not previously enabled (before we disabled it above),
     // then we shouldn't perform a task switch here.
@@ -52,19 +95,59 @@ pub fn schedule() -> bool {
     let cpu_id = preemption_guard.cpu_id();
 
     let next_task = SCHEDULER.update_guarded(
-        |scheduler| scheduler.as_ref().unwrap().lock().next(),
+        |scheduler| {
+            let mut scheduler = scheduler.as_ref().unwrap().lock();
+            let next_task = scheduler.next();
+            if SCHEDULE_HOOK_SET.load(Ordering::Acquire) {
+                apply_schedule_hook(cpu_id, reason, next_task, &*scheduler)
+            } else {
+                next_task
+            }
+        },
         &preemption_guard,
     );
 
     let (did_switch, recovered_preemption_guard) =
         super::task_switch(next_task, cpu_id, preemption_guard);
 
+    if did_switch {
+        CONTEXT_SWITCHES.inc();
+    }
+
     // log::trace!("AFTER TASK_SWITCH CALL (CPU {}) new current: {:?}, interrupts are {}", cpu_id, super::get_my_current_task(), irq_safety::interrupts_enabled());
 
     drop(recovered_preemption_guard);
     did_switch
 }
 
+fn apply_schedule_hook(
+    cpu_id: CpuId,
+    reason: ScheduleReason,
+    next_task: TaskRef,
+    scheduler: &dyn Scheduler,
+) -> TaskRef {
+    let Some(hook) = *SCHEDULE_HOOK.read() else {
+        return next_task;
+    };
+    super::with_current_task_and_value(
+        |current, next_task| hook(cpu_id, reason, current, next_task, scheduler),
+        next_task,
+    )
+    .unwrap_or_else(|next_task| next_task)
+}
+
+/// Sets the hook that is invoked on every scheduling decision, returning the previous hook.
+///
+/// Passing `None` removes the current hook.
+pub fn set_schedule_hook(hook: Option<ScheduleHook>) -> Option<ScheduleHook> {
+    // Holding preemption ensures that the timer interrupt can't try to read the hook on this CPU
+    // while we hold the write lock.
+    let _preemption_guard = preemption::hold_preemption();
+    let mut current = SCHEDULE_HOOK.write();
+    SCHEDULE_HOOK_SET.store(hook.is_some(), Ordering::Release);
+    core::mem::replace(&mut *current, hook)
+}
+
 /// Sets the scheduler policy for the given CPU.
 pub fn set_policy<T>(cpu_id: CpuId, scheduler: T)
 where
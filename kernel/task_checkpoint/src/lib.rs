@@ -0,0 +1,436 @@
+//! Experimental support for checkpointing a cooperating task's state in memory
+//! and later restoring it as a new task.
+//!
+//! This is intended as a primitive for fault tolerance and task migration studies,
+//! not as a general-purpose mechanism.
+//!
+//! A task opts into checkpointing by running a function via [`run_checkpointable()`],
+//! which executes it on a dedicated stack. From within that function, the task can call
+//! [`checkpoint()`] at any point, which captures:
+//! * the callee-saved registers, stack pointer, and instruction pointer at that call site,
+//! * the in-use contents of the dedicated stack, and
+//! * the contents of the given [`MappedPages`] regions owned by the task.
+//!
+//! A [`Checkpoint`] can then be passed to [`restore()`], which spawns a new task
+//! that resumes execution by returning from that same call to [`checkpoint()`],
+//! this time with [`CheckpointOutcome::Restored`].
+//!
+//! # Restrictions
+//! Because the saved stack contents may contain pointers into the stack itself
+//! and into the snapshotted regions, a checkpoint is restored at the same virtual addresses
+//! it was taken from. Thus, a checkpoint can only be restored once the original
+//! checkpointable stack and regions have been freed, e.g., after the original task has failed
+//! or returned from [`run_checkpointable()`], and only one restored instance can exist at a time.
+//!
+//! The snapshotted regions must be owned by values on the checkpointable stack,
+//! as that stack's contents become the owner of the restored regions.
+//! Anything else the function depends on, e.g., heap objects and other tasks,
+//! is not part of the checkpoint, so the function must not hold references to such state,
+//! nor any lock guards, across a call to [`checkpoint()`].
+//!
+//! Theseus does not yet support copy-on-write mappings, so snapshotted regions are
+//! eagerly copied into new read-only pages when the checkpoint is taken.
+
+#![no_std]
+#![feature(naked_functions)]
+
+extern crate alloc;
+
+use alloc::{string::String, sync::Arc, vec::Vec};
+use core::{arch::asm, cell::Cell, ffi::c_void, mem::size_of};
+use memory::{MappedPages, Mapper, PageRange, PteFlags, PteFlagsArch, VirtualAddress};
+use stack::Stack;
+use task::JoinableTaskRef;
+use thread_local_macro::thread_local;
+
+
+thread_local!{
+    /// The bounds of the checkpointable stack that the current task is running on, if any.
+    static CHECKPOINTABLE_STACK: Cell<Option<StackBounds>> = Cell::new(None);
+}
+
+/// The signature of a function that can be run via [`run_checkpointable()`].
+pub type CheckpointableFunc = fn(usize) -> usize;
+
+/// The result of a successful call to [`checkpoint()`].
+pub enum CheckpointOutcome {
+    /// The checkpoint was taken, and execution continues in the original task.
+    Saved(Arc<Checkpoint>),
+    /// Execution resumed from a checkpoint in a task spawned by [`restore()`].
+    Restored,
+}
+
+/// A snapshot of a cooperating task's state, taken by [`checkpoint()`].
+pub struct Checkpoint {
+    registers: SavedRegisters,
+    /// The start of the checkpointable stack's guard page.
+    stack_start: VirtualAddress,
+    /// The size of the checkpointable stack, excluding its guard page.
+    stack_size_in_pages: usize,
+    /// The in-use contents of the checkpointable stack, which end at its top.
+    stack_contents: Vec<u8>,
+    regions: Vec<RegionSnapshot>,
+    /// The name of the task that took this checkpoint.
+    task_name: String,
+}
+
+impl Checkpoint {
+    /// Returns the name of the task that took this checkpoint.
+    pub fn task_name(&self) -> &str {
+        &self.task_name
+    }
+
+    /// Returns the number of bytes of stack contents saved in this checkpoint.
+    pub fn saved_stack_len(&self) -> usize {
+        self.stack_contents.len()
+    }
+
+    /// Returns the ranges of pages that were snapshotted in this checkpoint.
+    pub fn regions(&self) -> impl Iterator<Item = &PageRange> {
+        self.regions.iter().map(|r| &r.pages)
+    }
+}
+
+/// A snapshot of one region of memory owned by the checkpointed task.
+struct RegionSnapshot {
+    /// The pages that the region was originally mapped to.
+    pages: PageRange,
+    /// The flags that the region was originally mapped with.
+    flags: PteFlagsArch,
+    /// A read-only copy of the region's contents.
+    contents: MappedPages,
+}
+
+/// The bounds of a checkpointable stack.
+#[derive(Clone, Copy)]
+struct StackBounds {
+    /// The start of the stack's guard page.
+    start: VirtualAddress,
+    /// The lowest usable address of the stack.
+    bottom: VirtualAddress,
+    /// The address just beyond the top of the stack.
+    top: VirtualAddress,
+}
+
+impl StackBounds {
+    fn of(stack: &Stack) -> StackBounds {
+        StackBounds {
+            start: stack.guard_page().start_address(),
+            bottom: stack.bottom(),
+            top: stack.top_unusable(),
+        }
+    }
+}
+
+/// The registers saved at a call to [`checkpoint()`].
+///
+/// The offsets of these fields are hardcoded in [`save_and_call()`] and [`restore_context()`].
+#[derive(Clone, Copy, Default)]
+#[repr(C)]
+struct SavedRegisters {
+    rbx: usize,
+    rbp: usize,
+    r12: usize,
+    r13: usize,
+    r14: usize,
+    r15: usize,
+    /// The stack pointer just after returning from the call.
+    rsp: usize,
+    /// The return address of the call.
+    rip: usize,
+}
+
+
+/// Runs `func(arg)` on a newly-allocated stack of `stack_size_in_pages`,
+/// such that it can take checkpoints via [`checkpoint()`].
+///
+/// Returns the value returned by `func`.
+/// Checkpointable functions cannot be nested within each other.
+pub fn run_checkpointable(
+    func: CheckpointableFunc,
+    arg: usize,
+    stack_size_in_pages: usize,
+) -> Result<usize, &'static str> {
+    if CHECKPOINTABLE_STACK.with(Cell::get).is_some() {
+        return Err("run_checkpointable(): already running a checkpointable function");
+    }
+    let mmi_ref = task::with_current_task(|t| t.mmi.clone())
+        .map_err(|_| "run_checkpointable(): couldn't get current task")?;
+    let stack = stack::alloc_stack(stack_size_in_pages, &mut mmi_ref.lock().page_table)
+        .ok_or("run_checkpointable(): couldn't allocate stack")?;
+
+    // SAFE: the stack was just allocated and is dropped only after `func` returns.
+    Ok(run_on_stack(&stack, |top| unsafe { call_on_stack(top, func, arg) }))
+}
+
+/// Takes a checkpoint of the current task, which must be running a function
+/// via [`run_checkpointable()`], including the contents of the given `regions`.
+///
+/// Returns [`CheckpointOutcome::Saved`] in the current task,
+/// and [`CheckpointOutcome::Restored`] in each task that is later restored from the checkpoint.
+pub fn checkpoint(regions: &[&MappedPages]) -> Result<CheckpointOutcome, &'static str> {
+    let bounds = CHECKPOINTABLE_STACK.with(Cell::get)
+        .ok_or("checkpoint(): not running within run_checkpointable()")?;
+    let mut request = CaptureRequest { bounds, regions, result: None };
+    let mut registers = SavedRegisters::default();
+
+    // SAFE: `capture` only reads from the stack above the saved stack pointer,
+    //       and only writes to `request`, after the stack contents have been copied.
+    let restored = unsafe {
+        save_and_call(&mut registers, capture, &mut request as *mut CaptureRequest as *mut c_void)
+    };
+    if restored != 0 {
+        return Ok(CheckpointOutcome::Restored);
+    }
+    request.result
+        .unwrap_or(Err("BUG: checkpoint(): capture callback didn't run"))
+        .map(|c| CheckpointOutcome::Saved(Arc::new(c)))
+}
+
+/// Spawns a new task that resumes execution from the given `checkpoint`.
+///
+/// The new task's exit value is the `usize` returned by the checkpointable function.
+///
+/// Returns an error if the checkpoint's stack or regions cannot be restored
+/// at their original addresses, e.g., because they are still in use.
+pub fn restore(checkpoint: Arc<Checkpoint>) -> Result<JoinableTaskRef, &'static str> {
+    let mmi_ref = task::with_current_task(|t| t.mmi.clone())
+        .map_err(|_| "restore(): couldn't get current task")?;
+    let (stack, regions) = {
+        let mut mmi = mmi_ref.lock();
+        let stack = rebuild_stack(&checkpoint, &mut mmi.page_table)?;
+        let regions = checkpoint.regions.iter()
+            .map(|r| r.rebuild(&mut mmi.page_table))
+            .collect::<Result<Vec<_>, _>>()?;
+        (stack, regions)
+    };
+    let name = alloc::format!("{}_restored", checkpoint.task_name);
+    spawn::new_task_builder(resume, ResumeArgs { checkpoint, stack, regions })
+        .name(name)
+        .spawn()
+}
+
+
+/// The data passed from [`checkpoint()`] to [`capture()`].
+struct CaptureRequest<'r> {
+    bounds: StackBounds,
+    regions: &'r [&'r MappedPages],
+    result: Option<Result<Checkpoint, &'static str>>,
+}
+
+/// Invoked by [`save_and_call()`] on the checkpointable stack, below the frames being saved.
+extern "C" fn capture(registers: &SavedRegisters, request: *mut c_void) {
+    // SAFE: `checkpoint()` passes a pointer to its `CaptureRequest`, which outlives this call.
+    let request = unsafe { &mut *(request as *mut CaptureRequest) };
+    let result = capture_inner(registers, request.bounds, request.regions);
+    request.result = Some(result);
+}
+
+fn capture_inner(
+    registers: &SavedRegisters,
+    bounds: StackBounds,
+    regions: &[&MappedPages],
+) -> Result<Checkpoint, &'static str> {
+    let (rsp, top) = (registers.rsp, bounds.top.value());
+    if rsp < bounds.bottom.value() || rsp >= top {
+        return Err("checkpoint(): stack pointer is outside of the checkpointable stack");
+    }
+    // SAFE: the range from the saved stack pointer to the top is the in-use part of the stack,
+    //       which isn't modified until after this function returns.
+    let stack_contents = unsafe { core::slice::from_raw_parts(rsp as *const u8, top - rsp) }.to_vec();
+
+    let (mmi_ref, task_name) = task::with_current_task(|t| (t.mmi.clone(), t.name.clone()))
+        .map_err(|_| "checkpoint(): couldn't get current task")?;
+    let mut mmi = mmi_ref.lock();
+    let regions = regions.iter()
+        .map(|mp| RegionSnapshot::take(mp, &mut mmi.page_table))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Checkpoint {
+        registers: *registers,
+        stack_start: bounds.start,
+        stack_size_in_pages: (top - bounds.bottom.value()) / memory::PAGE_SIZE,
+        stack_contents,
+        regions,
+        task_name,
+    })
+}
+
+impl RegionSnapshot {
+    fn take(region: &MappedPages, page_table: &mut Mapper) -> Result<RegionSnapshot, &'static str> {
+        let flags = region.flags();
+        let contents = region.deep_copy(page_table, Some(flags.writable(false).executable(false)))?;
+        Ok(RegionSnapshot { pages: region.range().clone(), flags, contents })
+    }
+
+    /// Maps new pages at this region's original address and copies its contents into them.
+    fn rebuild(&self, page_table: &mut Mapper) -> Result<MappedPages, &'static str> {
+        let pages = memory::allocate_pages_at(self.pages.start_address(), self.pages.size_in_pages())
+            .map_err(|_| "restore(): a checkpointed region's pages are still in use")?;
+        let mut mp = page_table.map_allocated_pages(pages, self.flags.writable(true))?;
+        let size = mp.size_in_bytes();
+        mp.as_slice_mut::<u8>(0, size)?.copy_from_slice(self.contents.as_slice(0, size)?);
+        if !self.flags.is_writable() {
+            mp.remap(page_table, self.flags)?;
+        }
+        Ok(mp)
+    }
+}
+
+/// Maps a new stack at the checkpointable stack's original address and copies
+/// the saved stack contents into it.
+fn rebuild_stack(checkpoint: &Checkpoint, page_table: &mut Mapper) -> Result<Stack, &'static str> {
+    let pages = memory::allocate_pages_at(checkpoint.stack_start, checkpoint.stack_size_in_pages + 1)
+        .map_err(|_| "restore(): the checkpointed stack's pages are still in use")?;
+    let stack_start_page = *pages.start() + 1;
+    let (guard_page, stack_pages) = pages.split(stack_start_page)
+        .map_err(|_| "BUG: restore(): couldn't split off stack guard page")?;
+    let mut stack_pages = page_table.map_allocated_pages(stack_pages, PteFlags::new().writable(true))?;
+    let len = checkpoint.stack_contents.len();
+    let offset = stack_pages.size_in_bytes().checked_sub(len)
+        .ok_or("BUG: restore(): saved stack contents are larger than the stack")?;
+    stack_pages.as_slice_mut::<u8>(offset, len)?.copy_from_slice(&checkpoint.stack_contents);
+    Stack::from_pages(guard_page, stack_pages)
+        .map_err(|_| "BUG: restore(): couldn't create stack from restored pages")
+}
+
+/// The argument passed to a task spawned by [`restore()`].
+struct ResumeArgs {
+    checkpoint: Arc<Checkpoint>,
+    stack: Stack,
+    regions: Vec<MappedPages>,
+}
+
+/// The entry point of a task spawned by [`restore()`].
+fn resume(args: ResumeArgs) -> usize {
+    let ResumeArgs { checkpoint, stack, regions } = args;
+    // The restored stack contents contain the owners of the restored regions.
+    for region in regions {
+        core::mem::forget(region);
+    }
+    let return_slot = (stack.top_unusable() - size_of::<usize>()).value() as *mut usize;
+    // SAFE: the stack contents were restored at their original addresses,
+    //       and the return slot is where `call_on_stack()` expects it.
+    run_on_stack(&stack, |_| unsafe { resume_on_stack(return_slot, &checkpoint.registers) })
+}
+
+/// Runs `f` with the top of the given `stack`, while it is the current checkpointable stack.
+fn run_on_stack(stack: &Stack, f: impl FnOnce(usize) -> usize) -> usize {
+    let bounds = StackBounds::of(stack);
+    CHECKPOINTABLE_STACK.with(|s| s.set(Some(bounds)));
+    let ret = f(bounds.top.value());
+    CHECKPOINTABLE_STACK.with(|s| s.set(None));
+    ret
+}
+
+
+/// Switches to the stack that ends at `stack_top` and invokes `func(arg)` on it,
+/// then switches back and returns its return value.
+///
+/// The previous stack pointer is saved at the top of the new stack,
+/// where [`resume_on_stack()`] overwrites it for a restored task.
+#[naked]
+unsafe extern "C" fn call_on_stack(stack_top: usize, func: CheckpointableFunc, arg: usize) -> usize {
+    asm!(
+        // These are restored after returning from `func`, which is required
+        // because `func` may return to a different caller via `resume_on_stack()`.
+        "push rbp",
+        "push rbx",
+        "push r12",
+        "push r13",
+        "push r14",
+        "push r15",
+        "mov rax, rsp",
+        "mov rsp, rdi",
+        "push rax",
+        // Keep the stack 16-byte aligned before the call.
+        "sub rsp, 8",
+        "mov rdi, rdx",
+        "call rsi",
+        "add rsp, 8",
+        "pop rsp",
+        "pop r15",
+        "pop r14",
+        "pop r13",
+        "pop r12",
+        "pop rbx",
+        "pop rbp",
+        "ret",
+        options(noreturn)
+    )
+}
+
+/// Resumes execution from the given saved `registers` on a restored checkpointable stack,
+/// returning the value returned by the checkpointable function once it completes.
+///
+/// The current stack pointer is saved into `return_slot` such that
+/// [`call_on_stack()`] returns to this function's caller.
+#[naked]
+unsafe extern "C" fn resume_on_stack(return_slot: *mut usize, registers: *const SavedRegisters) -> usize {
+    asm!(
+        // Mirror the frame pushed by `call_on_stack()`.
+        "push rbp",
+        "push rbx",
+        "push r12",
+        "push r13",
+        "push r14",
+        "push r15",
+        "mov [rdi], rsp",
+        "mov rdi, rsi",
+        "mov esi, 1",
+        "jmp {restore}",
+        restore = sym restore_context,
+        options(noreturn)
+    )
+}
+
+/// Saves the callee-saved registers and the caller's stack and instruction pointers
+/// into `registers`, then invokes `callback(registers, data)`.
+///
+/// Returns 0 when returning normally, or a nonzero value when resumed via [`restore_context()`].
+#[naked]
+unsafe extern "C" fn save_and_call(
+    registers: *mut SavedRegisters,
+    callback: extern "C" fn(&SavedRegisters, *mut c_void),
+    data: *mut c_void,
+) -> usize {
+    asm!(
+        "mov [rdi + 0x00], rbx",
+        "mov [rdi + 0x08], rbp",
+        "mov [rdi + 0x10], r12",
+        "mov [rdi + 0x18], r13",
+        "mov [rdi + 0x20], r14",
+        "mov [rdi + 0x28], r15",
+        "lea rax, [rsp + 8]",
+        "mov [rdi + 0x30], rax",
+        "mov rax, [rsp]",
+        "mov [rdi + 0x38], rax",
+        // Keep the stack 16-byte aligned before the call.
+        "sub rsp, 8",
+        "mov rax, rsi",
+        "mov rsi, rdx",
+        "call rax",
+        "add rsp, 8",
+        "xor eax, eax",
+        "ret",
+        options(noreturn)
+    )
+}
+
+/// Restores the given saved `registers`, returning `value` from the corresponding
+/// call to [`save_and_call()`].
+#[naked]
+unsafe extern "C" fn restore_context(registers: *const SavedRegisters, value: usize) -> ! {
+    asm!(
+        "mov rbx, [rdi + 0x00]",
+        "mov rbp, [rdi + 0x08]",
+        "mov r12, [rdi + 0x10]",
+        "mov r13, [rdi + 0x18]",
+        "mov r14, [rdi + 0x20]",
+        "mov r15, [rdi + 0x28]",
+        "mov rsp, [rdi + 0x30]",
+        "mov rax, rsi",
+        "jmp qword ptr [rdi + 0x38]",
+        options(noreturn)
+    )
+}
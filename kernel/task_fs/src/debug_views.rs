@@ -0,0 +1,179 @@
+//! Read-only files that show the state of the scheduler and its tasks,
+//! such that a hang can be diagnosed without attaching a debugger:
+//!
+//! * `/tasks/runqueues`: the tasks in each CPU's runqueue.
+//! * `/tasks/blocked`: every blocked task and what it's waiting on.
+//! * `/tasks/<id>/sched`: the scheduling state of a single task.
+//!
+//! The contents of these files are generated anew each time they are read.
+
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::sync::Arc;
+use core::fmt::Write;
+use spin::Mutex;
+use fs_node::{DirRef, WeakDirRef, FileOrDir, File, FileRef, FsNode};
+use memory::MappedPages;
+use task::{TaskRef, WeakTaskRef, RunState};
+use path::{Path, PathBuf};
+use io::{ByteReader, ByteWriter, KnownLength, IoError};
+use crate::TASKS_DIRECTORY_PATH;
+
+/// The name of the file in the tasks directory that lists each CPU's runqueue.
+pub const RUNQUEUES_FILE_NAME: &str = "runqueues";
+/// The name of the file in the tasks directory that lists all blocked tasks.
+pub const BLOCKED_FILE_NAME: &str = "blocked";
+/// The name of the file in each task's directory that shows its scheduling state.
+pub const SCHED_FILE_NAME: &str = "sched";
+
+/// Returns the debugging file in the tasks directory with the given name, if any.
+pub(crate) fn get_tasks_file(name: &str) -> Option<FileRef> {
+    let generate: fn() -> String = match name {
+        RUNQUEUES_FILE_NAME => runqueues,
+        BLOCKED_FILE_NAME => blocked_tasks,
+        _ => return None,
+    };
+    let file = DebugFile::new(name, PathBuf::from(String::from(TASKS_DIRECTORY_PATH)), Box::new(generate));
+    Some(Arc::new(Mutex::new(file)) as FileRef)
+}
+
+/// Returns the file that shows the scheduling state of the given task.
+pub(crate) fn get_sched_file(task_id: usize, taskref: WeakTaskRef) -> FileRef {
+    let file = DebugFile::new(
+        SCHED_FILE_NAME,
+        PathBuf::from(format!("{TASKS_DIRECTORY_PATH}/{task_id}")),
+        Box::new(move || task_sched(&taskref)),
+    );
+    Arc::new(Mutex::new(file)) as FileRef
+}
+
+/// Lists the tasks in each CPU's runqueue.
+///
+/// Note that this locks all runqueues at once while collecting their tasks.
+fn runqueues() -> String {
+    let mut output = String::new();
+    for (cpu, tasks) in task::scheduler::tasks() {
+        let _ = writeln!(output, "CPU {} ({} tasks):", cpu, tasks.len());
+        write_header(&mut output);
+        for taskref in &tasks {
+            write_task(&mut output, taskref);
+        }
+        output.push('\n');
+    }
+    output
+}
+
+/// Lists every blocked task along with what it's waiting on.
+fn blocked_tasks() -> String {
+    let mut output = String::new();
+    write_header(&mut output);
+    for (_id, weak_taskref) in task::all_tasks() {
+        if let Some(taskref) = weak_taskref.upgrade() {
+            if taskref.runstate() == RunState::Blocked {
+                write_task(&mut output, &taskref);
+            }
+        }
+    }
+    output
+}
+
+/// Shows the scheduling state of the given task.
+fn task_sched(taskref: &WeakTaskRef) -> String {
+    let Some(taskref) = taskref.upgrade() else {
+        return String::from("Task Not Found");
+    };
+    let cpu = taskref.running_on_cpu().map(|cpu| format!("{cpu}")).unwrap_or_else(|| String::from("-"));
+    let priority = task::scheduler::priority(&taskref).map(|p| format!("{p}")).unwrap_or_else(|| String::from("-"));
+    let waiting_on = taskref.wait_reason().map(|reason| format!("{reason}")).unwrap_or_else(|| String::from("-"));
+    format!("{0:<16} {1:?}\n{2:<16} {3}\n{4:<16} {5}\n{6:<16} {7:?} ago\n{8:<16} {9:?}\n{10:<16} {11}\n",
+        "runstate", taskref.runstate(),
+        "cpu", cpu,
+        "priority", priority,
+        "last scheduled", taskref.last_scheduled().elapsed(),
+        "cpu time", taskref.cpu_time(),
+        "waiting on", waiting_on,
+    )
+}
+
+fn write_header(output: &mut String) {
+    let _ = writeln!(output, "{:<6} {:<30} {:<10} {:<4} {:<20} WAITING ON", "ID", "NAME", "RUNSTATE", "CPU", "LAST SCHEDULED");
+}
+
+fn write_task(output: &mut String, taskref: &TaskRef) {
+    let cpu = taskref.running_on_cpu().map(|cpu| format!("{cpu}")).unwrap_or_else(|| String::from("-"));
+    let waiting_on = taskref.wait_reason().map(|reason| format!("{reason}")).unwrap_or_else(|| String::from("-"));
+    let _ = writeln!(output, "{:<6} {:<30} {:<10} {:<4} {:<20} {}",
+        taskref.id,
+        taskref.name,
+        format!("{:?}", taskref.runstate()),
+        cpu,
+        format!("{:?} ago", taskref.last_scheduled().elapsed()),
+        waiting_on,
+    );
+}
+
+
+/// A lazily generated, read-only file whose contents are produced by a function upon each read.
+struct DebugFile {
+    name: &'static str,
+    parent_path: PathBuf,
+    generate: Box<dyn Fn() -> String + Send>,
+}
+
+impl DebugFile {
+    fn new(name: &'static str, parent_path: PathBuf, generate: Box<dyn Fn() -> String + Send>) -> DebugFile {
+        DebugFile { name, parent_path, generate }
+    }
+}
+
+impl FsNode for DebugFile {
+    fn get_absolute_path(&self) -> String {
+        format!("{}/{}", self.parent_path, self.name)
+    }
+
+    fn get_name(&self) -> String {
+        String::from(self.name)
+    }
+
+    fn get_parent_dir(&self) -> Option<DirRef> {
+        match Path::get_absolute(&self.parent_path) {
+            Some(FileOrDir::Dir(d)) => Some(d),
+            _ => None,
+        }
+    }
+
+    fn set_parent_dir(&mut self, _: WeakDirRef) {
+        // do nothing
+    }
+}
+
+impl ByteReader for DebugFile {
+    fn read_at(&mut self, buf: &mut [u8], offset: usize) -> Result<usize, IoError> {
+        let output = (self.generate)();
+        if offset > output.len() {
+            return Err(IoError::InvalidInput);
+        }
+        let count = core::cmp::min(buf.len(), output.len() - offset);
+        buf[..count].copy_from_slice(&output.as_bytes()[offset..(offset + count)]);
+        Ok(count)
+    }
+}
+
+impl ByteWriter for DebugFile {
+    fn write_at(&mut self, _buffer: &[u8], _offset: usize) -> Result<usize, IoError> {
+        Err(IoError::from("not permitted to write task contents through the task VFS"))
+    }
+    fn flush(&mut self) -> Result<(), IoError> { Ok(()) }
+}
+
+impl KnownLength for DebugFile {
+    fn len(&self) -> usize {
+        (self.generate)().len()
+    }
+}
+
+impl File for DebugFile {
+    fn as_mapping(&self) -> Result<&MappedPages, &'static str> {
+        Err("task files are autogenerated, cannot be memory mapped")
+    }
+}
@@ -10,6 +10,8 @@
 //!     about the task's memory management information
 //! 5) MmiFile: lazily computed file that contains information about the task's
 //!     memory management information
+//! 6) debug_views: lazily computed files about the scheduler's runqueues, blocked tasks,
+//!     and each task's scheduling state, for diagnosing hangs
 //! 
 //! * Note that all the structs here are NOT persistent in the filesystem EXCEPT
 //! for the TaskFs struct, which contains all the individual TaskDirs. This means 
@@ -35,6 +37,8 @@ extern crate path;
 extern crate root;
 extern crate io;
 
+mod debug_views;
+
 use alloc::string::{String, ToString};
 use alloc::vec::Vec;
 use spin::Mutex;
@@ -45,6 +49,8 @@ use task::WeakTaskRef;
 use path::{Path, PathBuf};
 use io::{ByteReader, ByteWriter, KnownLength, IoError};
 
+pub use debug_views::{RUNQUEUES_FILE_NAME, BLOCKED_FILE_NAME, SCHED_FILE_NAME};
+
 
 /// The name of the VFS directory that exposes task info in the root. 
 pub const TASKS_DIRECTORY_NAME: &str = "tasks";
@@ -77,6 +83,9 @@ impl TaskFs {
     }
 
     fn get_internal(&self, node: &str) -> Result<FileOrDir, &'static str> {
+        if let Some(file) = debug_views::get_tasks_file(node) {
+            return Ok(FileOrDir::File(file));
+        }
         let id = node.parse::<usize>().map_err(|_e| "could not parse Task ID as usize")?;
         let task_ref = task::get_task(id).ok_or("No task existed for Task ID")?;
         let parent_dir = self.get_self_pointer().ok_or("BUG: tasks directory wasn't in root")?;
@@ -124,7 +133,7 @@ impl Directory for TaskFs {
 
     /// Returns a string listing all the children in the directory
     fn list(&self) -> Vec<String> {
-        let mut tasks_string = Vec::new();
+        let mut tasks_string = vec![RUNQUEUES_FILE_NAME.to_string(), BLOCKED_FILE_NAME.to_string()];
         for (id, _taskref) in task::all_tasks() {
             tasks_string.push(format!("{id}"));
         }
@@ -188,12 +197,16 @@ impl Directory for TaskDir {
             return Some(FileOrDir::Dir(Arc::new(Mutex::new(mmi_dir)) as DirRef));
         }
 
+        if child_name == SCHED_FILE_NAME {
+            return Some(FileOrDir::File(debug_views::get_sched_file(self.task_id, self.taskref.clone())));
+        }
+
         None
     }
 
     /// Returns a string listing all the children in the directory
     fn list(&self) -> Vec<String> {
-        let children = vec!["mmi".to_string(), "taskInfo".to_string()];
+        let children = vec!["mmi".to_string(), SCHED_FILE_NAME.to_string(), "taskInfo".to_string()];
         children
     }
 
@@ -0,0 +1,160 @@
+//! Per-task tables of resources, which are closed automatically when their owning task exits.
+//!
+//! Resources such as files, sockets, channel endpoints, and memory mappings are closed when
+//! they're dropped, but a task that exits abnormally, e.g., because it was killed,
+//! may never drop the resources on its stack.
+//! Instead, a task can store its resources in this crate's table,
+//! which drops every resource that a task still owns when that task exits,
+//! regardless of how it exited.
+//!
+//! ```ignore
+//! let file = task_resources::insert(file_ref);
+//! // ...
+//! let file_ref = task_resources::get(file).ok_or("file was closed")?;
+//! ```
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::{sync::Arc, vec::Vec};
+use core::{
+    any::{self, Any},
+    fmt,
+    marker::PhantomData,
+};
+use handle_table::{Handle, HandleTable};
+use log::debug;
+use sync_irq::IrqSafeMutex;
+
+/// A resource in the table, along with the name of its type for diagnostics.
+struct Resource {
+    value: Arc<dyn Any + Send + Sync>,
+    type_name: &'static str,
+}
+
+/// The resources owned by all tasks.
+static RESOURCES: IrqSafeMutex<HandleTable<Resource>> = IrqSafeMutex::new(HandleTable::new());
+
+/// A handle to a resource of type `R` in the table.
+pub struct ResourceHandle<R> {
+    handle: Handle,
+    _resource: PhantomData<fn() -> R>,
+}
+
+impl<R> ResourceHandle<R> {
+    /// Returns the untyped handle of the resource.
+    pub fn handle(&self) -> Handle {
+        self.handle
+    }
+}
+
+impl<R> Clone for ResourceHandle<R> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<R> Copy for ResourceHandle<R> {}
+
+impl<R> fmt::Debug for ResourceHandle<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ResourceHandle<{}>({})", any::type_name::<R>(), self.handle)
+    }
+}
+
+/// Information about a resource in the table; see [`resources_of()`].
+#[derive(Clone, Copy, Debug)]
+pub struct ResourceInfo {
+    pub handle: Handle,
+    /// The name of the resource's type, e.g., `net::Socket<...>`.
+    pub type_name: &'static str,
+}
+
+/// Adds the given resource to the table, owned by the current task.
+pub fn insert<R: Any + Send + Sync>(resource: R) -> ResourceHandle<R> {
+    insert_for(task::get_my_current_task_id(), resource)
+}
+
+/// Adds the given resource to the table, owned by the task with the given ID.
+///
+/// The resource is dropped when that task exits, unless it's removed from the table first.
+pub fn insert_for<R: Any + Send + Sync>(task_id: usize, resource: R) -> ResourceHandle<R> {
+    handle_table::register_owner_cleanup(release);
+    let resource = Resource {
+        value: Arc::new(resource),
+        type_name: any::type_name::<R>(),
+    };
+    ResourceHandle {
+        handle: RESOURCES.lock().insert_owned(task_id, resource),
+        _resource: PhantomData,
+    }
+}
+
+/// Returns a reference to the resource with the given handle,
+/// or `None` if it has been removed from the table.
+///
+/// The table's lock isn't held while the returned reference is used,
+/// so using it may block, e.g., to send on a socket.
+/// The resource is only dropped once the returned reference is dropped,
+/// even if it's removed from the table before then,
+/// so the reference shouldn't be held longer than necessary:
+/// a killed task never drops the references on its stack.
+pub fn get<R: Any + Send + Sync>(handle: ResourceHandle<R>) -> Option<Arc<R>> {
+    let value = RESOURCES.lock().get(handle.handle)?.value.clone();
+    value.downcast().ok()
+}
+
+/// Removes the resource with the given handle from the table and drops it, i.e., closes it.
+///
+/// Returns whether the resource was in the table.
+pub fn close<R>(handle: ResourceHandle<R>) -> bool {
+    // The resource is dropped after the lock is released, since dropping it may take a while.
+    let resource = RESOURCES.lock().remove(handle.handle);
+    resource.is_some()
+}
+
+/// Removes the resource with the given handle from the table without closing it,
+/// such that it's no longer closed when its owner exits.
+///
+/// Returns `None` if the resource isn't in the table, or is still referenced elsewhere;
+/// in the latter case, the resource is left in the table.
+pub fn take<R: Any + Send + Sync>(handle: ResourceHandle<R>) -> Option<R> {
+    let mut resources = RESOURCES.lock();
+    let resource = resources.get(handle.handle)?;
+    if Arc::strong_count(&resource.value) != 1 {
+        return None;
+    }
+    let value = resources.remove(handle.handle)?.value.downcast::<R>().ok()?;
+    Arc::try_unwrap(value).ok()
+}
+
+/// Transfers ownership of the resource with the given handle to the task with the given ID,
+/// such that it's closed when that task exits instead.
+///
+/// Returns whether the resource was in the table.
+pub fn transfer<R>(handle: ResourceHandle<R>, task_id: usize) -> bool {
+    RESOURCES.lock().set_owner(handle.handle, Some(task_id))
+}
+
+/// Returns information about every resource owned by the task with the given ID.
+pub fn resources_of(task_id: usize) -> Vec<ResourceInfo> {
+    let resources = RESOURCES.lock();
+    resources
+        .iter()
+        .filter(|(handle, _)| resources.owner(*handle) == Some(task_id))
+        .map(|(handle, resource)| ResourceInfo {
+            handle,
+            type_name: resource.type_name,
+        })
+        .collect()
+}
+
+/// Drops every resource owned by the task with the given ID, which has exited.
+fn release(task_id: usize) {
+    let resources = RESOURCES.lock().remove_owned_by(task_id);
+    if !resources.is_empty() {
+        debug!("task_resources: closing {} resources left open by task {}", resources.len(), task_id);
+    }
+    // The resources are dropped here, after the lock is released.
+}
@@ -17,8 +17,9 @@ use core::{
     hash::{Hash, Hasher},
     ops::Deref,
     panic::PanicInfo,
-    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering},
     task::Waker,
+    time::Duration,
 };
 use alloc::{
     boxed::Box,
@@ -30,12 +31,15 @@ use cpu::{CpuId, OptionalCpuId};
 use crossbeam_utils::atomic::AtomicCell;
 use sync_irq::IrqSafeMutex;
 use log::{warn, trace};
-use memory::MmiRef;
+use memory::{MmiRef, VirtualAddress};
 use stack::Stack;
 use kernel_config::memory::KERNEL_STACK_SIZE_IN_PAGES;
 use mod_mgmt::{AppCrateRef, CrateNamespace, TlsDataImage};
 use environment::Environment;
+use fpu_state::FpuState;
+use handle_table::{Handle, HandleTable};
 use spin::Mutex;
+use time::Instant;
 
 /// The function signature of the callback that will be invoked when a `Task`
 /// panics or otherwise fails, e.g., a machine exception occurs.
@@ -106,6 +110,66 @@ impl fmt::Display for KillReason {
 }
 
 
+/// An asynchronous notification that can be sent to a `Task`, similar to a POSIX signal.
+///
+/// Pending notifications are delivered when the recipient `Task` reaches
+/// a preemption point or returns from a blocking call.
+/// Upon delivery, the `Task`'s handler for that notification is invoked if it has one;
+/// otherwise, the notification's [default action](Notification::default_action) is taken.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(u8)]
+pub enum Notification {
+    /// Requests that the `Task` exit immediately. This cannot be handled.
+    /// Analogous to SIGKILL.
+    Kill      = 0,
+    /// Requests that the `Task` stop what it's doing, e.g., the user pressed `Ctrl + C`.
+    /// Analogous to SIGINT.
+    Interrupt = 1,
+    /// An application-defined notification, which is ignored by default.
+    User1     = 2,
+    /// An application-defined notification, which is ignored by default.
+    User2     = 3,
+}
+/// The number of different kinds of [`Notification`]s.
+pub const NUM_NOTIFICATIONS: usize = 4;
+
+/// What happens upon delivery of a [`Notification`] that the recipient doesn't handle.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DefaultAction {
+    /// The recipient `Task` is killed.
+    Kill,
+    /// The notification is discarded.
+    Ignore,
+}
+
+impl Notification {
+    /// All kinds of notifications, in the order in which they are delivered.
+    pub const ALL: [Notification; NUM_NOTIFICATIONS] = [
+        Notification::Kill, Notification::Interrupt, Notification::User1, Notification::User2,
+    ];
+
+    /// Returns the action taken when this notification is delivered to a `Task`
+    /// that hasn't registered a handler for it.
+    pub const fn default_action(self) -> DefaultAction {
+        match self {
+            Notification::Kill | Notification::Interrupt => DefaultAction::Kill,
+            Notification::User1 | Notification::User2 => DefaultAction::Ignore,
+        }
+    }
+
+    /// Returns this notification's bit in a set of pending notifications.
+    const fn bit(self) -> u32 {
+        1 << self as u32
+    }
+}
+
+/// The function signature of a handler for a [`Notification`].
+///
+/// Handlers may be invoked from an interrupt context at a preemption point,
+/// so they should be short and must not block.
+pub type NotificationHandler = fn(Notification);
+
+
 /// The two ways a `Task` can exit, including possible return values and conditions.
 #[derive(Debug)]
 pub enum ExitValue {
@@ -142,6 +206,32 @@ pub enum RunState {
 }
 
 
+/// What a blocked `Task` is waiting on, which is recorded for diagnosing hangs.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct WaitReason {
+    /// The kind of object that the task is waiting on, e.g., `"WaitQueue"` or `"Mutex"`.
+    pub kind: &'static str,
+    /// The address of the object that the task is waiting on, which identifies it.
+    pub address: usize,
+}
+
+impl WaitReason {
+    /// Returns a `WaitReason` for waiting on the given `object` of the given `kind`.
+    pub fn of<T: ?Sized>(kind: &'static str, object: &T) -> WaitReason {
+        WaitReason {
+            kind,
+            address: object as *const T as *const () as usize,
+        }
+    }
+}
+
+impl fmt::Display for WaitReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at {:#X}", self.kind, self.address)
+    }
+}
+
+
 #[cfg(simd_personality)]
 /// The supported levels of SIMD extensions that a `Task` can use.
 #[derive(Debug, Copy, Clone, PartialEq)]
@@ -195,9 +285,21 @@ pub struct TaskInner {
     pub restart_info: Option<RestartInfo>,
     /// The waker that is awoken when this task completes.
     pub waker: Option<Waker>,
+    /// The handlers for each kind of [`Notification`], indexed by the notification's value.
+    /// A notification without a handler will result in its default action.
+    pub notification_handlers: [Option<NotificationHandler>; NUM_NOTIFICATIONS],
 }
 
 
+/// The IDs of all tasks that currently exist.
+///
+/// Task IDs are generational handles, so the ID of an exited task is never reused as-is:
+/// a new task that reuses its slot gets a different ID.
+/// Handles are never zero, so `0` can be used as a task ID that indicates the absence of a task,
+/// e.g., in sync primitives.
+static TASK_IDS: IrqSafeMutex<HandleTable<()>> = IrqSafeMutex::new(HandleTable::new());
+
+
 /// A structure that contains contextual information for a thread of execution. 
 ///
 /// # Implementation note
@@ -213,7 +315,7 @@ pub struct Task {
     /// This must not be public because it permits interior mutability of key task states.
     inner: IrqSafeMutex<TaskInner>,
 
-    /// The unique identifier of this Task.
+    /// The unique identifier of this Task, which is the raw value of a generational [`Handle`].
     pub id: usize,
     /// The simple name of this Task.
     pub name: String,
@@ -234,6 +336,29 @@ pub struct Task {
     ///
     /// This is not public because it permits interior mutability.
     suspended: AtomicBool,
+    /// The top of the kernel stack region used upon a transition from userspace to this task,
+    /// or `0` if this task is not currently running code in userspace.
+    ///
+    /// This is not public because it permits interior mutability.
+    userspace_kernel_stack_top: AtomicUsize,
+    /// The set of [`Notification`]s that have been sent to this task but not yet delivered,
+    /// in which each bit corresponds to one kind of notification.
+    ///
+    /// This is not public because it permits interior mutability.
+    pending_notifications: AtomicU32,
+    /// The total time, in nanoseconds, that this task has spent running on any CPU,
+    /// not including the time since it was most recently switched to.
+    ///
+    /// This is not public because it permits interior mutability.
+    cpu_time_nanos: AtomicU64,
+    /// The time at which this task was most recently switched to.
+    ///
+    /// This is not public because it permits interior mutability.
+    switched_in_at: AtomicCell<Instant>,
+    /// What this task is currently waiting on, if it was recorded when it blocked.
+    ///
+    /// This is not public because it permits interior mutability.
+    wait_reason: AtomicCell<Option<WaitReason>>,
     /// Memory management details: page tables, mappings, allocators, etc.
     /// This is shared among all other tasks in the same address space.
     pub mmi: MmiRef, 
@@ -250,6 +375,9 @@ pub struct Task {
     /// Upon each task switch, we must set the value of the TLS base register 
     /// (e.g., FsBase on x86_64) to the value of this TLS area's self pointer.
     tls_area: TlsDataImage,
+    /// The extended processor state (FPU/SIMD registers) of this task,
+    /// which is saved and restored across context switches.
+    pub fpu_state: Arc<FpuState>,
     
     #[cfg(simd_personality)]
     /// Whether this Task is SIMD enabled and what level of SIMD extensions it uses.
@@ -259,6 +387,7 @@ pub struct Task {
 // Ensure that atomic fields in the `Tast` struct are actually lock-free atomics.
 const _: () = assert!(AtomicCell::<OptionalCpuId>::is_lock_free());
 const _: () = assert!(AtomicCell::<RunState>::is_lock_free());
+const _: () = assert!(AtomicCell::<Instant>::is_lock_free());
 
 impl fmt::Debug for Task {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -307,17 +436,12 @@ impl Task {
         stack: Option<Stack>,
         states_to_inherit: InheritedStates,
     ) -> Result<Task, &'static str> {
-        /// The counter of task IDs. We start at `1` such that `0` can be used 
-        /// as a task ID that indicates the absence of a task, e.g., in sync primitives. 
-        static TASKID_COUNTER: AtomicUsize = AtomicUsize::new(1);
-
         let (mmi, namespace, env, app_crate) = states_to_inherit.into_tuple();
         let kstack = stack
             .or_else(|| stack::alloc_stack(KERNEL_STACK_SIZE_IN_PAGES, &mut mmi.lock().page_table))
             .ok_or("couldn't allocate stack for new Task!")?;
 
-        // TODO: re-use old task IDs again, instead of simply blindly counting up.
-        let task_id = TASKID_COUNTER.fetch_add(1, Ordering::Relaxed);
+        let task_id = TASK_IDS.lock().insert(()).to_raw() as usize;
 
         // Obtain a new copied instance of the TLS data image for this task.
         let tls_area = namespace.get_tls_initializer_data();
@@ -331,17 +455,24 @@ impl Task {
                 env,
                 restart_info: None,
                 waker: None,
+                notification_handlers: [None; NUM_NOTIFICATIONS],
             }),
             id: task_id,
             name: format!("task_{task_id}"),
             running_on_cpu: AtomicCell::new(None.into()),
             runstate: AtomicCell::new(RunState::Initing),
             suspended: AtomicBool::new(false),
+            userspace_kernel_stack_top: AtomicUsize::new(0),
+            pending_notifications: AtomicU32::new(0),
+            cpu_time_nanos: AtomicU64::new(0),
+            switched_in_at: AtomicCell::new(Instant::now()),
+            wait_reason: AtomicCell::new(None),
             mmi,
             is_an_idle_task: false,
             app_crate,
             namespace,
             tls_area,
+            fpu_state: FpuState::new(),
 
             #[cfg(simd_personality)]
             simd: SimdExt::None,
@@ -548,6 +679,84 @@ impl Task {
     pub fn is_suspended(&self) -> bool {
         self.suspended.load(Ordering::Acquire)
     }
+
+    /// Returns the total time that this `Task` has spent running on any CPU.
+    ///
+    /// If this `Task` is currently running, this includes the time
+    /// since it was most recently switched to.
+    pub fn cpu_time(&self) -> Duration {
+        let mut cpu_time = Duration::from_nanos(self.cpu_time_nanos.load(Ordering::Acquire));
+        if self.is_running() {
+            cpu_time += self.switched_in_at.load().elapsed();
+        }
+        cpu_time
+    }
+
+    /// Returns the time at which this `Task` was most recently switched to,
+    /// or the time at which it was created if it has never run.
+    pub fn last_scheduled(&self) -> Instant {
+        self.switched_in_at.load()
+    }
+
+    /// Returns what this `Task` is waiting on, if it's blocked on something that recorded it.
+    ///
+    /// This is only meant for diagnostics, e.g., to find out which lock a hung task is waiting on.
+    pub fn wait_reason(&self) -> Option<WaitReason> {
+        self.wait_reason.load()
+    }
+
+    /// Records what this `Task` is about to wait on, or `None` once it's done waiting.
+    ///
+    /// Blocking primitives should set this before blocking the current task.
+    pub fn set_wait_reason(&self, reason: Option<WaitReason>) {
+        self.wait_reason.store(reason);
+    }
+
+    /// Marks the given `notification` as pending for this `Task`.
+    ///
+    /// Returns `true` if it was not already pending.
+    ///
+    /// This does not deliver the notification; see the `task::notifications` module.
+    pub fn add_pending_notification(&self, notification: Notification) -> bool {
+        let bit = notification.bit();
+        self.pending_notifications.fetch_or(bit, Ordering::AcqRel) & bit == 0
+    }
+
+    /// Returns `true` if this `Task` has any pending notifications.
+    pub fn has_pending_notifications(&self) -> bool {
+        self.pending_notifications.load(Ordering::Acquire) != 0
+    }
+
+    /// Removes and returns all of this `Task`'s pending notifications,
+    /// in the order in which they should be delivered.
+    pub fn take_pending_notifications(&self) -> impl Iterator<Item = Notification> {
+        let pending = self.pending_notifications.swap(0, Ordering::AcqRel);
+        Notification::ALL.into_iter().filter(move |n| pending & n.bit() != 0)
+    }
+
+    /// Returns `true` if this `Task` is currently executing code in userspace.
+    pub fn is_userspace(&self) -> bool {
+        self.userspace_kernel_stack_top.load(Ordering::Acquire) != 0
+    }
+
+    /// Returns the top of the kernel stack region that the CPU should switch to
+    /// upon an interrupt or system call from userspace while running this `Task`,
+    /// or `None` if this `Task` is not executing code in userspace.
+    pub fn userspace_kernel_stack_top(&self) -> Option<VirtualAddress> {
+        match self.userspace_kernel_stack_top.load(Ordering::Acquire) {
+            0 => None,
+            top => VirtualAddress::new(top),
+        }
+    }
+
+    /// Sets the top of the kernel stack region used upon a transition from userspace
+    /// into this `Task`, or clears it if `None`.
+    ///
+    /// This should only be invoked by the `Task` itself right before it enters
+    /// or right after it returns from userspace.
+    pub fn set_userspace_kernel_stack_top(&self, top: Option<VirtualAddress>) {
+        self.userspace_kernel_stack_top.store(top.map_or(0, |t| t.value()), Ordering::Release);
+    }
 }
 
 impl Drop for Task {
@@ -563,6 +772,10 @@ impl Drop for Task {
             warn!("While dropping task {:?}, its kill handler callback was still present. Removing it now.", self);
             drop(kill_handler);
         }
+
+        if let Some(handle) = Handle::from_raw(self.id as u64) {
+            TASK_IDS.lock().remove(handle);
+        }
     }
 }
 
@@ -612,6 +825,14 @@ impl ExposedTask {
     pub fn runstate(&self) -> &AtomicCell<RunState> {
         &self.runstate
     }
+    #[inline(always)]
+    pub fn cpu_time_nanos(&self) -> &AtomicU64 {
+        &self.cpu_time_nanos
+    }
+    #[inline(always)]
+    pub fn switched_in_at(&self) -> &AtomicCell<Instant> {
+        &self.switched_in_at
+    }
 }
 
 
@@ -16,9 +16,13 @@ static EARLY_SLEEPER_PERIOD: AtomicCell<Period> = AtomicCell::new(Period::MAX);
 
 static MONOTONIC_NOW_FUNCTION: AtomicCell<fn() -> Instant> = AtomicCell::new(dummy::monotonic_now);
 static MONOTONIC_PERIOD: AtomicCell<Period> = AtomicCell::new(Period::MAX);
+static MONOTONIC_COUNTER_ACCESS: AtomicCell<CounterAccess> = AtomicCell::new(CounterAccess::Unavailable);
 
 static WALL_TIME_NOW_FUNCTION: AtomicCell<fn() -> Duration> = AtomicCell::new(dummy::wall_time_now);
 static WALL_TIME_PERIOD: AtomicCell<Period> = AtomicCell::new(Period::MAX);
+static WALL_TIME_COUNTER_ACCESS: AtomicCell<CounterAccess> = AtomicCell::new(CounterAccess::Unavailable);
+
+static CLOCK_SOURCE_CHANGE_CALLBACK: AtomicCell<Option<fn()>> = AtomicCell::new(None);
 
 /// A measurement of a monotonically nondecreasing clock.
 ///
@@ -157,6 +161,17 @@ impl fmt::Display for Period {
     }
 }
 
+/// How a clock source's counter can be read directly by unprivileged code,
+/// without calling into the kernel.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(u32)]
+pub enum CounterAccess {
+    /// The counter can only be read by the kernel.
+    Unavailable = 0,
+    /// The counter is the x86_64 Time Stamp Counter, read via `rdtsc`.
+    Tsc = 1,
+}
+
 /// Register the clock source that can be used to sleep when interrupts are
 /// disabled.
 ///
@@ -221,13 +236,40 @@ where
     {
         let now_fn = T::ClockType::now_fn();
         now_fn.store(T::now);
+        T::ClockType::counter_access_atomic().store(T::COUNTER_ACCESS);
 
+        if let Some(callback) = CLOCK_SOURCE_CHANGE_CALLBACK.load() {
+            callback();
+        }
         true
     } else {
         false
     }
 }
 
+/// Sets the function that will be invoked every time a clock source is replaced
+/// by [`register_clock_source`].
+pub fn set_clock_source_change_callback(callback: fn()) {
+    CLOCK_SOURCE_CHANGE_CALLBACK.store(Some(callback));
+}
+
+/// Returns the period of the current clock source of the specified type.
+pub fn period<T>() -> Period
+where
+    T: ClockType,
+{
+    T::period_atomic().load()
+}
+
+/// Returns how the counter of the current clock source of the specified type
+/// can be read by unprivileged code.
+pub fn counter_access<T>() -> CounterAccess
+where
+    T: ClockType,
+{
+    T::counter_access_atomic().load()
+}
+
 /// Returns the current time.
 ///
 /// Monotonic clocks return an [`Instant`] whereas wall time clocks return a
@@ -255,6 +297,12 @@ pub trait ClockSource {
     /// [`Duration`] signifying the time since 12:00am January 1st 1970 (i.e.
     /// Unix time).
     fn now() -> <Self::ClockType as ClockType>::Unit;
+
+    /// How unprivileged code can read this clock's counter directly.
+    ///
+    /// If this is not [`CounterAccess::Unavailable`], the value returned by
+    /// [`ClockSource::now`] must be the raw value of that counter.
+    const COUNTER_ACCESS: CounterAccess = CounterAccess::Unavailable;
 }
 
 /// A hardware clock that can sleep without relying on interrupts.
@@ -289,6 +337,8 @@ pub trait ClockType: private::Sealed {
     fn now_fn() -> &'static AtomicCell<fn() -> Self::Unit>;
     #[doc(hidden)]
     fn period_atomic() -> &'static AtomicCell<Period>;
+    #[doc(hidden)]
+    fn counter_access_atomic() -> &'static AtomicCell<CounterAccess>;
 }
 
 pub struct Monotonic;
@@ -305,6 +355,10 @@ impl ClockType for Monotonic {
     fn period_atomic() -> &'static AtomicCell<Period> {
         &MONOTONIC_PERIOD
     }
+
+    fn counter_access_atomic() -> &'static AtomicCell<CounterAccess> {
+        &MONOTONIC_COUNTER_ACCESS
+    }
 }
 
 pub struct WallTime;
@@ -321,6 +375,10 @@ impl ClockType for WallTime {
     fn period_atomic() -> &'static AtomicCell<Period> {
         &WALL_TIME_PERIOD
     }
+
+    fn counter_access_atomic() -> &'static AtomicCell<CounterAccess> {
+        &WALL_TIME_COUNTER_ACCESS
+    }
 }
 
 mod private {
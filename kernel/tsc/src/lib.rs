@@ -11,6 +11,8 @@ impl time::ClockSource for Tsc {
     fn now() -> Instant {
         Instant::new(tsc_value())
     }
+
+    const COUNTER_ACCESS: time::CounterAccess = time::CounterAccess::Tsc;
 }
 
 /// Returns the frequency of the TSC for the system, currently measured using
@@ -28,6 +28,18 @@ pub fn tss_set_rsp0(new_privilege_stack_top: VirtualAddress) -> Result<(), &'sta
     Ok(())
 }
 
+/// Returns a pointer to the given CPU's TSS privilege stack 0 (RSP0) entry.
+///
+/// This allows low-level entry code, e.g., the system call entry point, to read
+/// the current privilege stack without locking the TSS.
+/// That is safe because RSP0 is only changed by [`tss_set_rsp0()`] on the same CPU.
+pub fn rsp0_ptr(cpu_id: CpuId) -> Option<*const u64> {
+    TSS.get(&cpu_id).map(|tss| {
+        // SAFE: we only compute the address of the (packed) field without creating a reference to it.
+        unsafe { core::ptr::addr_of!((*tss.as_mut_ptr()).privilege_stack_table) as *const u64 }
+    })
+}
+
 
 /// Sets up TSS entry for the given CPU core. 
 ///
@@ -74,9 +74,9 @@ use registers::{Registers, LandingRegisters, SavedRegs};
 use fallible_iterator::FallibleIterator;
 use mod_mgmt::{
     CrateNamespace,
-    SectionType,
     StrongCrateRef,
     StrongSectionRef,
+    unwind_info,
 };
 use memory::VirtualAddress;
 use task::{TaskRef, KillReason};
@@ -344,15 +344,24 @@ impl FallibleIterator for StackFrameIter {
             .ok_or("caller wasn't a valid virtual address")?;
 
         // Get unwind info for the call site ("caller") address.
-        let (eh_frame_sec, base_addrs) = self.namespace
-            // First: search the current namespace's crates to see if any of them contain the caller address.
-            .get_crate_containing_address(caller_virt_addr, false)
-            .and_then(|crate_ref| get_eh_frame_info(&crate_ref)
-                .map(|(eh_frame_sec, base_addrs)| 
-                    (EhFrameReference::Section(eh_frame_sec), base_addrs)
+        let (eh_frame_sec, base_addrs) = unwind_info::lookup(caller_virt_addr)
+            // First: search the unwind info registered by all loaded crates for the caller address.
+            .map(|(eh_frame_sec, text)| {
+                let base_addrs = BaseAddresses::default()
+                    .set_eh_frame(eh_frame_sec.virt_addr.value() as u64)
+                    .set_text(text.start.value() as u64);
+                (EhFrameReference::Section(eh_frame_sec), base_addrs)
+            })
+            // Second: search the current namespace's crates to see if any of them contain the caller address.
+            .or_else(|| self.namespace
+                .get_crate_containing_address(caller_virt_addr, false)
+                .and_then(|crate_ref| get_eh_frame_info(&crate_ref)
+                    .map(|(eh_frame_sec, base_addrs)| 
+                        (EhFrameReference::Section(eh_frame_sec), base_addrs)
+                    )
                 )
             )
-            // Second: search externally-registered unwind info for the caller address.
+            // Third: search externally-registered unwind info for the caller address.
             .or_else(|| external_unwind_info::get_unwind_info(caller_virt_addr)
                 .map(|uw_info| {
                     let base_addrs = BaseAddresses::default()
@@ -687,8 +696,7 @@ impl UnwindRowReference {
 fn get_eh_frame_info(crate_ref: &StrongCrateRef) -> Option<(StrongSectionRef, BaseAddresses)> {
     let krate = crate_ref.lock_as_ref();
 
-    let eh_frame_sec = krate.sections.values()
-        .find(|s| s.typ == SectionType::EhFrame)?;
+    let eh_frame_sec = krate.eh_frame_section()?;
     
     let eh_frame_vaddr = eh_frame_sec.virt_addr.value();
     let text_pages_vaddr = krate.text_pages.as_ref()?.1.start.value();
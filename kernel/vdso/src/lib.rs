@@ -0,0 +1,265 @@
+//! A read-only page of timekeeping and CPU information that unprivileged code
+//! can read directly, similar to the vDSO data page on other OSes.
+//!
+//! The page contains a [`VdsoData`] structure with the parameters of the current
+//! monotonic clock source, which CPUs are online, and the CPU ID of each APIC ID.
+//! With it, userspace and WASM applications can compute the current monotonic time
+//! and determine which CPU they're running on without a system call or kernel call,
+//! using [`VdsoData::monotonic_now()`] and [`VdsoData::current_cpu()`].
+//!
+//! The kernel updates the clock parameters whenever a new clock source is registered,
+//! using a sequence counter such that readers never observe a partial update.
+//!
+//! Kernel code can access the page via [`data()`], whereas userspace must first map it
+//! into its address space via [`map_into_current_address_space()`].
+
+#![no_std]
+
+use core::sync::atomic::{fence, AtomicU32, AtomicU64, Ordering};
+use cpu_features::Feature;
+use memory::{
+    AllocatedFrames, MappedPages, Mapper, PteFlags, VirtualAddress, PAGE_SIZE,
+};
+use spin::{Mutex, Once};
+use time::{CounterAccess, Duration, Monotonic, Period};
+
+/// The maximum number of CPUs described in the [`VdsoData`] page.
+pub const MAX_CPUS: usize = 256;
+
+/// The number of hardware IDs (e.g., APIC IDs on x86_64) that can be translated
+/// into CPU IDs via the [`VdsoData`] page.
+pub const MAX_HARDWARE_IDS: usize = 256;
+
+/// The version of the [`VdsoData`] layout, which is incremented upon any change to it.
+pub const VDSO_DATA_VERSION: u32 = 2;
+
+/// The contents of the shared page.
+///
+/// All fields are only updated by the kernel; everyone else can only read them.
+#[repr(C)]
+pub struct VdsoData {
+    /// The version of this structure's layout, i.e., [`VDSO_DATA_VERSION`].
+    version: AtomicU32,
+    /// The sequence counter that protects the clock parameters,
+    /// which is odd while an update is in progress.
+    seq: AtomicU32,
+    /// The [`CounterAccess`] of the monotonic clock source.
+    clock_mode: AtomicU32,
+    /// Whether the current CPU's hardware ID can be read via `rdtscp`,
+    /// i.e., the APIC ID that the kernel stores in `IA32_TSC_AUX`.
+    cpu_id_available: AtomicU32,
+    /// The period of the monotonic clock source, in femtoseconds.
+    period_femtos: AtomicU64,
+    /// Info about each CPU, indexed by CPU ID.
+    cpus: [VdsoCpuInfo; MAX_CPUS],
+    /// The CPU ID plus one of the CPU with each hardware ID, indexed by hardware ID,
+    /// or zero if no CPU has that hardware ID.
+    hardware_ids: [AtomicU32; MAX_HARDWARE_IDS],
+}
+const _: () = assert!(core::mem::size_of::<VdsoData>() <= PAGE_SIZE);
+
+/// Per-CPU info in the [`VdsoData`] page.
+#[repr(C)]
+pub struct VdsoCpuInfo {
+    /// Whether this CPU is online.
+    online: AtomicU32,
+    _reserved: AtomicU32,
+}
+
+impl VdsoData {
+    /// Returns the version of this structure's layout.
+    pub fn version(&self) -> u32 {
+        self.version.load(Ordering::Relaxed)
+    }
+
+    /// Returns a consistent snapshot of the monotonic clock source's parameters.
+    pub fn clock_params(&self) -> (CounterAccess, Period) {
+        loop {
+            let seq = self.seq.load(Ordering::Acquire);
+            if seq % 2 == 1 {
+                core::hint::spin_loop();
+                continue;
+            }
+            let mode = self.clock_mode.load(Ordering::Relaxed);
+            let period = self.period_femtos.load(Ordering::Relaxed);
+            fence(Ordering::Acquire);
+            if self.seq.load(Ordering::Relaxed) == seq {
+                let access = if mode == CounterAccess::Tsc as u32 {
+                    CounterAccess::Tsc
+                } else {
+                    CounterAccess::Unavailable
+                };
+                return (access, Period::new(period));
+            }
+        }
+    }
+
+    /// Returns the current value of the monotonic clock, measured from when its counter was zero.
+    ///
+    /// Returns `None` if the current clock source cannot be read without calling into the kernel.
+    pub fn monotonic_now(&self) -> Option<Duration> {
+        let (access, period) = self.clock_params();
+        let counter = arch::read_counter(access)?;
+        let femtos = u128::from(counter) * u128::from(period);
+        Some(Duration::from_nanos((femtos / 1_000_000) as u64))
+    }
+
+    /// Returns the ID of the CPU that the caller is currently running on.
+    ///
+    /// Note that the caller may be migrated to another CPU at any point,
+    /// so the result should only be used as a hint.
+    ///
+    /// Returns `None` if this cannot be determined without calling into the kernel.
+    pub fn current_cpu(&self) -> Option<u32> {
+        if self.cpu_id_available.load(Ordering::Acquire) == 0 {
+            return None;
+        }
+        let hardware_id = arch::read_hardware_id()?;
+        let id = self.hardware_ids.get(hardware_id as usize)?
+            .load(Ordering::Acquire)
+            .checked_sub(1)?;
+        self.is_cpu_online(id).then_some(id)
+    }
+
+    /// Returns whether the CPU with the given ID is online.
+    pub fn is_cpu_online(&self, cpu_id: u32) -> bool {
+        self.cpus.get(cpu_id as usize)
+            .is_some_and(|cpu| cpu.online.load(Ordering::Acquire) != 0)
+    }
+
+    /// Returns the number of online CPUs.
+    pub fn cpu_count(&self) -> usize {
+        self.cpus.iter().filter(|cpu| cpu.online.load(Ordering::Relaxed) != 0).count()
+    }
+}
+
+
+/// The kernel's state for the shared page.
+struct Vdso {
+    /// The frame that holds the [`VdsoData`], which is mapped non-exclusively
+    /// into the kernel and into each address space that requests it.
+    frames: AllocatedFrames,
+    /// The kernel's mapping of the page, which is never unmapped.
+    kernel_mapping: MappedPages,
+}
+
+static VDSO: Once<Vdso> = Once::new();
+
+/// Serializes updates to the clock parameters.
+static UPDATE_LOCK: Mutex<()> = Mutex::new(());
+
+/// Allocates and initializes the shared page, and registers the [`cpu_features`] hook
+/// that records each CPU's ID such that it can be determined by unprivileged code.
+///
+/// This must be invoked before [`cpu_features::run_hooks()`] is invoked on the BSP.
+pub fn init() -> Result<(), &'static str> {
+    let kernel_mmi_ref = memory::get_kernel_mmi_ref().ok_or("vdso::init(): kernel MMI was not yet initialized")?;
+    let frames = memory::allocate_frames(1).ok_or("vdso::init(): couldn't allocate frame")?;
+    let pages = memory::allocate_pages(1).ok_or("vdso::init(): couldn't allocate page")?;
+    let flags = PteFlags::new().valid(true).writable(true);
+    // SAFE: the kernel mapping and all others map the same frame, which is never deallocated.
+    let mut kernel_mapping = unsafe {
+        Mapper::map_to_non_exclusive(&mut kernel_mmi_ref.lock().page_table, pages, &frames, flags)?
+    };
+    kernel_mapping.as_slice_mut::<u8>(0, PAGE_SIZE)?.fill(0);
+
+    VDSO.call_once(|| Vdso { frames, kernel_mapping });
+    let data = data().ok_or("BUG: vdso::init(): page wasn't initialized")?;
+    data.version.store(VDSO_DATA_VERSION, Ordering::Relaxed);
+
+    update_clock_params();
+    time::set_clock_source_change_callback(update_clock_params);
+    cpu_features::register_hook("publish CPU ID of TSC_AUX", Feature::Rdtscp, init_cpu);
+    Ok(())
+}
+
+/// Returns the shared page, if it has been initialized.
+pub fn data() -> Option<&'static VdsoData> {
+    VDSO.get().map(|vdso| {
+        // SAFE: the kernel mapping is valid forever, was zeroed upon creation,
+        //       and is only modified via atomic fields.
+        unsafe { &*(vdso.kernel_mapping.start_address().value() as *const VdsoData) }
+    })
+}
+
+/// Maps the shared page as read-only and user-accessible into the private region
+/// of the current task's address space, and returns its address.
+///
+/// The mapping is owned by that address space and lasts as long as it does.
+pub fn map_into_current_address_space() -> Result<VirtualAddress, &'static str> {
+    let vdso = VDSO.get().ok_or("vdso page was not yet initialized")?;
+    let mmi_ref = task::with_current_task(|t| t.mmi.clone())
+        .map_err(|_| "map_into_current_address_space(): couldn't get current task")?;
+    let pages = memory::allocate_pages_by_bytes_in_range(PAGE_SIZE, &memory::private_page_range())?;
+    let flags = PteFlags::new().valid(true) | PteFlags::_USER_ACCESSIBLE;
+
+    let mut mmi = mmi_ref.lock();
+    // SAFE: the page is mapped read-only, and its frame is never deallocated.
+    let mapping = unsafe { Mapper::map_to_non_exclusive(&mut mmi.page_table, pages, &vdso.frames, flags)? };
+    let address = mapping.start_address();
+    mmi.extra_mapped_pages.push(mapping);
+    Ok(address)
+}
+
+/// Copies the parameters of the current monotonic clock source into the shared page.
+fn update_clock_params() {
+    let Some(data) = data() else { return };
+    let _guard = UPDATE_LOCK.lock();
+    data.seq.fetch_add(1, Ordering::Relaxed);
+    fence(Ordering::Release);
+    data.clock_mode.store(time::counter_access::<Monotonic>() as u32, Ordering::Relaxed);
+    data.period_femtos.store(time::period::<Monotonic>().into(), Ordering::Relaxed);
+    data.seq.fetch_add(1, Ordering::Release);
+}
+
+/// Records the current CPU's ID in the shared page, along with the hardware ID
+/// by which unprivileged code can identify the current CPU.
+fn init_cpu() -> Result<(), &'static str> {
+    let data = data().ok_or("vdso page was not yet initialized")?;
+    let cpu_id = cpu::current_cpu().value();
+    let cpu = data.cpus.get(cpu_id as usize).ok_or("CPU ID is too large for the vdso page")?;
+    let hardware_id = arch::current_hardware_id().ok_or("current CPU has no hardware ID")?;
+    data.hardware_ids.get(hardware_id as usize)
+        .ok_or("hardware ID is too large for the vdso page")?
+        .store(cpu_id + 1, Ordering::Release);
+    cpu.online.store(1, Ordering::Release);
+    if cpu::is_bootstrap_cpu() {
+        data.cpu_id_available.store(1, Ordering::Release);
+    }
+    Ok(())
+}
+
+
+#[cfg(target_arch = "x86_64")]
+mod arch {
+    use time::CounterAccess;
+
+    pub(crate) fn read_counter(access: CounterAccess) -> Option<u64> {
+        match access {
+            // SAFE: `rdtsc` is permitted in userspace because CR4.TSD is not set.
+            CounterAccess::Tsc => Some(unsafe { core::arch::x86_64::_rdtsc() }),
+            CounterAccess::Unavailable => None,
+        }
+    }
+
+    /// Reads `IA32_TSC_AUX`, in which the `apic` crate stores each CPU's APIC ID.
+    pub(crate) fn read_hardware_id() -> Option<u32> {
+        let mut aux = 0;
+        // SAFE: this is only invoked if every CPU supports RDTSCP.
+        unsafe { core::arch::x86_64::__rdtscp(&mut aux) };
+        Some(aux)
+    }
+
+    pub(crate) fn current_hardware_id() -> Option<u32> {
+        Some(apic::current_cpu().value())
+    }
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+mod arch {
+    use time::CounterAccess;
+
+    pub(crate) fn read_counter(_access: CounterAccess) -> Option<u64> { None }
+    pub(crate) fn read_hardware_id() -> Option<u32> { None }
+    pub(crate) fn current_hardware_id() -> Option<u32> { None }
+}
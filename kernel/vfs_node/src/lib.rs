@@ -65,6 +65,10 @@ impl Directory for VFSDirectory {
         self.children.keys().cloned().collect()
     }
 
+    fn entries(&self) -> Vec<FileOrDir> {
+        self.children.values().cloned().collect()
+    }
+
     fn remove(&mut self, node: &FileOrDir) -> Option<FileOrDir> {
         if let Some(mut old_node) = self.children.remove(&node.get_name()) {
             old_node.set_parent_dir(Weak::<Mutex<VFSDirectory>>::new());
@@ -73,6 +77,31 @@ impl Directory for VFSDirectory {
             None
         }
     }
+
+    fn rename(&mut self, old_name: &str, new_name: &str) -> Result<Option<FileOrDir>, &'static str> {
+        let is_dir = self.children.get(old_name)
+            .ok_or("no node with that name exists in this directory")?
+            .is_dir();
+        if old_name == new_name {
+            return Ok(None);
+        }
+        if let Some(existing) = self.children.get(new_name) {
+            if existing.is_dir() != is_dir {
+                return Err("cannot replace a file with a directory or a directory with a file");
+            }
+        }
+
+        // Both entries are only modified while this directory is locked, so the rename is atomic.
+        let mut node = self.children.remove(old_name).ok_or("BUG: renamed node disappeared")?;
+        if let Err(e) = node.set_name(String::from(new_name)) {
+            self.children.insert(String::from(old_name), node);
+            return Err(e);
+        }
+        Ok(self.children.insert(String::from(new_name), node).map(|mut old_node| {
+            old_node.set_parent_dir(Weak::<Mutex<VFSDirectory>>::new());
+            old_node
+        }))
+    }
 }
 
 impl FsNode for VFSDirectory {
@@ -88,4 +117,9 @@ impl FsNode for VFSDirectory {
     fn set_parent_dir(&mut self, new_parent: WeakDirRef) {
         self.parent = new_parent;
     }
+
+    fn set_name(&mut self, name: String) -> Result<(), &'static str> {
+        self.name = name;
+        Ok(())
+    }
 }
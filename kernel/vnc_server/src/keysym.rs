@@ -0,0 +1,96 @@
+//! Translation of the X11 keysyms that clients send in key events into Theseus keycodes.
+
+use keycodes_ascii::{KeyboardModifiers, Keycode};
+
+/// A key that corresponds to a keysym.
+pub(crate) struct Key {
+    pub keycode: Keycode,
+    /// The modifier that is held while this key is pressed, which is empty for non-modifier keys.
+    pub modifier: KeyboardModifiers,
+    /// The character that this key must produce, if the keysym was a printable character.
+    pub character: Option<char>,
+}
+
+impl Key {
+    fn new(keycode: Keycode) -> Key {
+        Key { keycode, modifier: KeyboardModifiers::empty(), character: None }
+    }
+
+    fn modifier(keycode: Keycode, modifier: KeyboardModifiers) -> Key {
+        Key { keycode, modifier, character: None }
+    }
+}
+
+/// Returns the key for the given keysym, or `None` if it has no equivalent on a Theseus keyboard.
+pub(crate) fn to_key(keysym: u32) -> Option<Key> {
+    let key = match keysym {
+        // Printable Latin-1 keysyms are equal to their ASCII characters.
+        0x20..=0x7E => return char_key(keysym as u8 as char),
+        // Keypad digits and operators are treated as their regular characters.
+        0xFFB0..=0xFFB9 => return char_key((b'0' + (keysym - 0xFFB0) as u8) as char),
+        0xFFAA => return char_key('*'),
+        0xFFAB => return char_key('+'),
+        0xFFAD => return char_key('-'),
+        0xFFAE => return char_key('.'),
+        0xFFAF => return char_key('/'),
+
+        0xFF08 => Key::new(Keycode::Backspace),
+        0xFF09 => Key::new(Keycode::Tab),
+        0xFF0D | 0xFF8D => Key::new(Keycode::Enter),
+        0xFF13 => Key::new(Keycode::Pause),
+        0xFF14 => Key::new(Keycode::ScrollLock),
+        0xFF1B => Key::new(Keycode::Escape),
+        0xFF50 => Key::new(Keycode::Home),
+        0xFF51 => Key::new(Keycode::Left),
+        0xFF52 => Key::new(Keycode::Up),
+        0xFF53 => Key::new(Keycode::Right),
+        0xFF54 => Key::new(Keycode::Down),
+        0xFF55 => Key::new(Keycode::PageUp),
+        0xFF56 => Key::new(Keycode::PageDown),
+        0xFF57 => Key::new(Keycode::End),
+        0xFF63 => Key::new(Keycode::Insert),
+        0xFF67 => Key::new(Keycode::Menu),
+        0xFF7F => Key::new(Keycode::NumLock),
+        0xFFBE..=0xFFC7 => Key::new(Keycode::try_from(Keycode::F1 as u8 + (keysym - 0xFFBE) as u8).ok()?),
+        0xFFC8 => Key::new(Keycode::F11),
+        0xFFC9 => Key::new(Keycode::F12),
+        0xFFE5 => Key::new(Keycode::CapsLock),
+        0xFFFF => Key::new(Keycode::Delete),
+
+        0xFFE1 => Key::modifier(Keycode::LeftShift, KeyboardModifiers::SHIFT_LEFT),
+        0xFFE2 => Key::modifier(Keycode::RightShift, KeyboardModifiers::SHIFT_RIGHT),
+        0xFFE3 => Key::modifier(Keycode::Control, KeyboardModifiers::CONTROL_LEFT),
+        0xFFE4 => Key::modifier(Keycode::Control, KeyboardModifiers::CONTROL_RIGHT),
+        0xFFE9 => Key::modifier(Keycode::Alt, KeyboardModifiers::ALT),
+        // Alt_R and ISO_Level3_Shift, i.e., AltGr.
+        0xFFEA | 0xFE03 => Key::modifier(Keycode::Alt, KeyboardModifiers::ALT_GR),
+        0xFFEB => Key::modifier(Keycode::SuperKeyLeft, KeyboardModifiers::SUPER_KEY_LEFT),
+        0xFFEC => Key::modifier(Keycode::SuperKeyRight, KeyboardModifiers::SUPER_KEY_RIGHT),
+        _ => return None,
+    };
+    Some(key)
+}
+
+/// Returns the key that produces the given ASCII character, with or without shift.
+fn char_key(character: char) -> Option<Key> {
+    let keycodes = || (Keycode::Escape as u8..=Keycode::Menu as u8).filter_map(|k| Keycode::try_from(k).ok());
+    let keycode = keycodes()
+        .find(|k| k.to_ascii(KeyboardModifiers::empty()) == Some(character))
+        .or_else(|| keycodes().find(|k| k.to_ascii(KeyboardModifiers::SHIFT_LEFT) == Some(character)))?;
+    Some(Key { keycode, modifier: KeyboardModifiers::empty(), character: Some(character) })
+}
+
+/// Returns the modifiers with which the given key should be reported,
+/// such that it produces its character regardless of whether the client's shift key is held,
+/// e.g., because the client's keyboard layout differs from Theseus's.
+pub(crate) fn modifiers_for(key: &Key, modifiers: KeyboardModifiers) -> KeyboardModifiers {
+    let Some(character) = key.character else { return modifiers };
+    if key.keycode.to_ascii(modifiers) == Some(character) {
+        return modifiers;
+    }
+    if modifiers.is_shift() {
+        modifiers.difference(KeyboardModifiers::SHIFT_LEFT | KeyboardModifiers::SHIFT_RIGHT)
+    } else {
+        modifiers.union(KeyboardModifiers::SHIFT_LEFT)
+    }
+}
@@ -0,0 +1,453 @@
+//! A minimal VNC server, which serves the window manager's composited screen
+//! to remote clients over the Remote Framebuffer (RFB) protocol,
+//! and injects their keyboard and mouse input into the window manager.
+//!
+//! This allows a machine without a display, keyboard, or mouse to be used interactively
+//! over the network, in which case the window manager can be initialized with
+//! [`window_manager::init_headless()`] to composite windows into an in-memory screen.
+//!
+//! Clients aren't authenticated and pixels are always sent in the raw encoding,
+//! so this server should only be used on trusted networks.
+
+#![no_std]
+
+extern crate alloc;
+
+mod keysym;
+pub mod rfb;
+
+use alloc::{sync::Arc, vec, vec::Vec};
+use core::time::Duration;
+use event_types::Event;
+use keycodes_ascii::{KeyAction, KeyEvent, KeyboardModifiers, Keycode};
+use log::{debug, trace, warn};
+use mouse_data::{MouseButtons, MouseEvent, MouseMovementRelative};
+use net::{tcp, NetworkInterface, Socket};
+use rfb::{ClientMessage, PixelFormat, Rect};
+use time::Instant;
+
+/// The port that VNC servers conventionally listen on for the first display.
+pub const DEFAULT_PORT: u16 = 5900;
+
+/// The name of the desktop that is shown by clients.
+const DESKTOP_NAME: &str = "Theseus";
+
+/// How long a connection may be idle before it's aborted.
+/// Clients continuously request updates, so this is only reached if a client hangs.
+const CONNECTION_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How long the server sleeps in [`VncServer::run()`] when there's nothing to do.
+const IDLE_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+const SOCKET_RX_BUFFER_SIZE: usize = 4096;
+const SOCKET_TX_BUFFER_SIZE: usize = 64 * 1024;
+
+/// The stage of the RFB handshake that a connection is in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Stage {
+    /// Waiting for a client to connect, after which the server sends its protocol version.
+    Listening,
+    /// Waiting for the client's protocol version.
+    Version,
+    /// Waiting for the client to choose a security type.
+    Security { minor_version: u8 },
+    /// Waiting for the client's `ClientInit` message.
+    ClientInit,
+    /// The handshake is done; the client sends normal messages.
+    Normal,
+}
+
+/// A connection to a single client, or a socket listening for one.
+struct Connection {
+    socket: Socket<tcp::Socket<'static>>,
+    stage: Stage,
+    /// The bytes received but not yet parsed.
+    received: Vec<u8>,
+    /// The bytes to be sent and how many of them were sent so far.
+    outgoing: Option<(Vec<u8>, usize)>,
+    format: PixelFormat,
+    /// The update that the client requested but hasn't been sent yet.
+    pending_update: Option<(bool, Rect)>,
+    /// The screen's pixels as the client last saw them.
+    client_screen: Vec<u32>,
+    /// The state of the client's modifier keys.
+    modifiers: KeyboardModifiers,
+    /// The state of the client's mouse buttons.
+    buttons: u8,
+    last_activity: Instant,
+}
+
+impl Connection {
+    fn reset(&mut self) {
+        self.stage = Stage::Listening;
+        self.received.clear();
+        self.outgoing = None;
+        self.format = PixelFormat::DEFAULT;
+        self.pending_update = None;
+        self.client_screen.clear();
+        self.modifiers = KeyboardModifiers::empty();
+        self.buttons = 0;
+    }
+
+    fn send(&mut self, bytes: Vec<u8>) {
+        match &mut self.outgoing {
+            Some((outgoing, _)) => outgoing.extend_from_slice(&bytes),
+            None => self.outgoing = Some((bytes, 0)),
+        }
+    }
+}
+
+/// A snapshot of the window manager's composited screen.
+struct Screen {
+    width: u16,
+    height: u16,
+    /// Each pixel as an `0x00RRGGBB` color.
+    pixels: Vec<u32>,
+}
+
+impl Screen {
+    fn capture() -> Result<Screen, &'static str> {
+        let window_manager = window_manager::WINDOW_MANAGER.get()
+            .ok_or("the window manager hasn't been initialized")?
+            .lock();
+        let (width, height) = window_manager.final_fb.get_size();
+        let pixels = window_manager.final_fb.buffer().iter()
+            .map(|p| (p.red as u32) << 16 | (p.green as u32) << 8 | p.blue as u32)
+            .collect();
+        Ok(Screen {
+            width: u16::try_from(width).map_err(|_| "the screen is too wide for VNC")?,
+            height: u16::try_from(height).map_err(|_| "the screen is too tall for VNC")?,
+            pixels,
+        })
+    }
+}
+
+/// A VNC server that serves the screen to clients on a port of a network interface.
+pub struct VncServer {
+    interface: Arc<NetworkInterface>,
+    port: u16,
+    connections: Vec<Connection>,
+}
+
+impl VncServer {
+    /// Creates a server that listens on the given `port` of the given `interface`,
+    /// serving up to `max_connections` clients at once.
+    ///
+    /// The window manager must already be initialized.
+    pub fn new(
+        interface: Arc<NetworkInterface>,
+        port: u16,
+        max_connections: usize,
+    ) -> Result<Self, &'static str> {
+        if max_connections == 0 {
+            return Err("a VNC server must allow at least one connection");
+        }
+        if window_manager::WINDOW_MANAGER.get().is_none() {
+            return Err("the window manager hasn't been initialized");
+        }
+        let connections = (0..max_connections)
+            .map(|_| {
+                let rx_buffer = tcp::SocketBuffer::new(vec![0; SOCKET_RX_BUFFER_SIZE]);
+                let tx_buffer = tcp::SocketBuffer::new(vec![0; SOCKET_TX_BUFFER_SIZE]);
+                let socket = interface
+                    .clone()
+                    .add_socket(tcp::Socket::new(rx_buffer, tx_buffer));
+                socket
+                    .lock()
+                    .listen(port)
+                    .map_err(|_| "failed to listen on VNC server socket")?;
+                Ok(Connection {
+                    socket,
+                    stage: Stage::Listening,
+                    received: Vec::new(),
+                    outgoing: None,
+                    format: PixelFormat::DEFAULT,
+                    pending_update: None,
+                    client_screen: Vec::new(),
+                    modifiers: KeyboardModifiers::empty(),
+                    buttons: 0,
+                    last_activity: Instant::now(),
+                })
+            })
+            .collect::<Result<Vec<_>, &'static str>>()?;
+
+        Ok(Self {
+            interface,
+            port,
+            connections,
+        })
+    }
+
+    /// Returns the port that this server listens on.
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Serves clients forever, or until an error occurs.
+    pub fn run(&mut self) -> Result<(), &'static str> {
+        loop {
+            self.interface.poll();
+            if !self.poll()? {
+                sleep::sleep(IDLE_POLL_INTERVAL).map_err(|_| "VNC server failed to sleep")?;
+            }
+        }
+    }
+
+    /// Makes progress on every connection, without polling the interface.
+    ///
+    /// Returns whether any connection made progress.
+    pub fn poll(&mut self) -> Result<bool, &'static str> {
+        let mut progress = false;
+        for connection in &mut self.connections {
+            progress |= poll_connection(connection, self.port);
+        }
+
+        // Only capture the screen if a client is waiting for an update.
+        let waiting = |c: &Connection| c.pending_update.is_some() && c.outgoing.is_none();
+        if self.connections.iter().any(waiting) {
+            let screen = Screen::capture()?;
+            for connection in self.connections.iter_mut().filter(|c| waiting(c)) {
+                progress |= send_update(connection, &screen);
+            }
+        }
+        Ok(progress)
+    }
+}
+
+fn poll_connection(connection: &mut Connection, port: u16) -> bool {
+    let mut socket = connection.socket.lock();
+
+    if !socket.is_open() {
+        if socket.listen(port).is_err() {
+            warn!("vnc_server: failed to listen on port {}", port);
+        }
+        drop(socket);
+        connection.reset();
+        return false;
+    }
+    if !socket.is_active() {
+        // Still listening for a client.
+        connection.last_activity = Instant::now();
+        return false;
+    }
+    if connection.last_activity.elapsed() >= CONNECTION_TIMEOUT {
+        trace!("vnc_server: aborting idle connection from {:?}", socket.remote_endpoint());
+        socket.abort();
+        return false;
+    }
+    if connection.stage == Stage::Listening {
+        debug!("vnc_server: new connection from {:?}", socket.remote_endpoint());
+        connection.outgoing = Some((rfb::PROTOCOL_VERSION.to_vec(), 0));
+        connection.stage = Stage::Version;
+    }
+
+    let mut progress = false;
+    if let Some((bytes, sent)) = &mut connection.outgoing {
+        if socket.can_send() {
+            match socket.send_slice(&bytes[*sent..]) {
+                Ok(0) => {}
+                Ok(n) => {
+                    *sent += n;
+                    progress = true;
+                }
+                Err(_) => {
+                    socket.abort();
+                    return false;
+                }
+            }
+            if *sent == bytes.len() {
+                connection.outgoing = None;
+            }
+        }
+    }
+
+    if socket.can_recv() {
+        let _ = socket.recv(|data| {
+            connection.received.extend_from_slice(data);
+            (data.len(), ())
+        });
+        progress = true;
+    } else if !socket.may_recv() {
+        socket.close();
+        return progress;
+    }
+    if progress {
+        connection.last_activity = Instant::now();
+    }
+
+    // The input queues are unrelated to the socket, but there's no need to hold its lock any longer.
+    drop(socket);
+    if let Err(e) = handle_received(connection) {
+        warn!("vnc_server: closing connection: {}", e);
+        connection.socket.lock().abort();
+    }
+    progress
+}
+
+/// Handles all complete messages that were received on the given connection.
+fn handle_received(connection: &mut Connection) -> Result<(), &'static str> {
+    loop {
+        let consumed = match connection.stage {
+            Stage::Listening => return Ok(()),
+            Stage::Version => {
+                let Some(bytes) = connection.received.get(..12) else { return Ok(()) };
+                let bytes = bytes.try_into().map_err(|_| "invalid protocol version")?;
+                let minor_version = rfb::parse_protocol_version(bytes).ok_or("invalid protocol version")?;
+                if minor_version == 3 {
+                    // The server chooses the security type in version 3.3.
+                    connection.send(u32::from(rfb::SECURITY_TYPE_NONE).to_be_bytes().to_vec());
+                    connection.stage = Stage::ClientInit;
+                } else {
+                    connection.send(vec![1, rfb::SECURITY_TYPE_NONE]);
+                    connection.stage = Stage::Security { minor_version };
+                }
+                12
+            }
+            Stage::Security { minor_version } => {
+                let Some(&security_type) = connection.received.first() else { return Ok(()) };
+                if security_type != rfb::SECURITY_TYPE_NONE {
+                    return Err("client chose an unsupported security type");
+                }
+                if minor_version >= 8 {
+                    // The security handshake succeeded.
+                    connection.send(0u32.to_be_bytes().to_vec());
+                }
+                connection.stage = Stage::ClientInit;
+                1
+            }
+            Stage::ClientInit => {
+                if connection.received.is_empty() {
+                    return Ok(());
+                }
+                // Clients may ask for exclusive access, but the server is always shared.
+                let screen = Screen::capture()?;
+                connection.send(rfb::server_init(screen.width, screen.height, &connection.format, DESKTOP_NAME));
+                connection.stage = Stage::Normal;
+                1
+            }
+            Stage::Normal => {
+                let Some((message, len)) = rfb::parse_client_message(&connection.received)? else { return Ok(()) };
+                handle_message(connection, message)?;
+                len
+            }
+        };
+        connection.received.drain(..consumed);
+    }
+}
+
+fn handle_message(connection: &mut Connection, message: ClientMessage) -> Result<(), &'static str> {
+    match message {
+        ClientMessage::SetPixelFormat(format) => {
+            if !format.is_supported() {
+                return Err("client requested an unsupported pixel format");
+            }
+            connection.format = format;
+            // Pixels already sent in the old format must be sent again.
+            connection.client_screen.clear();
+        }
+        // Every client supports the raw encoding, which is the only one this server uses.
+        ClientMessage::SetEncodings(_) => {}
+        ClientMessage::FramebufferUpdateRequest { incremental, rect } => {
+            connection.pending_update = Some((incremental, rect));
+        }
+        ClientMessage::KeyEvent { down, keysym } => inject_key(connection, down, keysym)?,
+        ClientMessage::PointerEvent { buttons, x, y } => inject_pointer(connection, buttons, x, y)?,
+        ClientMessage::ClientCutText(text) => {
+            trace!("vnc_server: ignoring {} bytes of client cut text", text.len());
+        }
+    }
+    Ok(())
+}
+
+/// Sends the update that the given connection's client is waiting for, if anything changed.
+///
+/// Returns whether an update was sent.
+fn send_update(connection: &mut Connection, screen: &Screen) -> bool {
+    let Some((incremental, requested)) = connection.pending_update else { return false };
+    let requested = requested.clamp(screen.width, screen.height);
+    if connection.client_screen.len() != screen.pixels.len() {
+        // The client hasn't seen any of the screen yet, or the screen changed size.
+        // No pixel has this value, so the entire screen is considered to have changed.
+        connection.client_screen = vec![!0; screen.pixels.len()];
+    }
+    let rect = if incremental {
+        match rfb::changed_region(&connection.client_screen, &screen.pixels, screen.width as usize, requested) {
+            Some(changed) => changed,
+            // Nothing changed, so keep the request pending until something does.
+            None => return false,
+        }
+    } else {
+        requested
+    };
+    connection.pending_update = None;
+
+    let stride = screen.width as usize;
+    for y in rect.y as usize..(rect.y + rect.height) as usize {
+        let row = y * stride + rect.x as usize..y * stride + (rect.x + rect.width) as usize;
+        connection.client_screen[row.clone()].copy_from_slice(&screen.pixels[row]);
+    }
+    // Requests must be answered even if they don't cover any part of the screen.
+    connection.send(rfb::framebuffer_update(&screen.pixels, stride, rect, &connection.format));
+    true
+}
+
+/// Injects the given key event into the window manager.
+fn inject_key(connection: &mut Connection, down: bool, keysym: u32) -> Result<(), &'static str> {
+    let Some(key) = keysym::to_key(keysym) else {
+        trace!("vnc_server: ignoring unsupported keysym {:#X}", keysym);
+        return Ok(());
+    };
+    if !key.modifier.is_empty() {
+        connection.modifiers.set(key.modifier, down);
+    }
+    // The "*Lock" keys are toggled only upon being pressed, not when released.
+    if down {
+        match key.keycode {
+            Keycode::CapsLock => connection.modifiers.toggle(KeyboardModifiers::CAPS_LOCK),
+            Keycode::NumLock => connection.modifiers.toggle(KeyboardModifiers::NUM_LOCK),
+            Keycode::ScrollLock => connection.modifiers.toggle(KeyboardModifiers::SCROLL_LOCK),
+            _ => {}
+        }
+    }
+    let action = if down { KeyAction::Pressed } else { KeyAction::Released };
+    let modifiers = keysym::modifiers_for(&key, connection.modifiers);
+    let (keyboard_queue, _) = window_manager::input_queues().ok_or("the window manager hasn't been initialized")?;
+    if keyboard_queue.push(Event::new_keyboard_event(KeyEvent::new(key.keycode, action, modifiers))).is_err() {
+        warn!("vnc_server: dropped key event because the keyboard input queue is full");
+    }
+    Ok(())
+}
+
+/// Injects the given pointer event into the window manager as a mouse movement
+/// from the current position of the mouse pointer to the given position.
+fn inject_pointer(connection: &mut Connection, buttons: u8, x: u16, y: u16) -> Result<(), &'static str> {
+    let current = window_manager::WINDOW_MANAGER.get()
+        .ok_or("the window manager hasn't been initialized")?
+        .lock()
+        .get_mouse_position();
+    let clamp = |delta: isize| delta.clamp(i16::MIN as isize, i16::MAX as isize) as i16;
+    let dx = clamp(x as isize - current.x);
+    // Mice report upward movement as positive, unlike screen coordinates.
+    let dy = clamp(current.y - y as isize);
+    let scroll = match buttons & 0b11000 {
+        0b01000 => 1,
+        0b10000 => -1,
+        _ => 0,
+    };
+    if dx == 0 && dy == 0 && scroll == 0 && buttons == connection.buttons {
+        return Ok(());
+    }
+    connection.buttons = buttons;
+
+    let mouse_buttons = MouseButtons::new()
+        .with_left(buttons & 0b001 != 0)
+        .with_middle(buttons & 0b010 != 0)
+        .with_right(buttons & 0b100 != 0);
+    let event = MouseEvent::new(mouse_buttons, MouseMovementRelative::new(dx, dy, scroll));
+    let (_, mouse_queue) = window_manager::input_queues().ok_or("the window manager hasn't been initialized")?;
+    if mouse_queue.push(Event::MouseMovementEvent(event)).is_err() {
+        warn!("vnc_server: dropped pointer event because the mouse input queue is full");
+    }
+    Ok(())
+}
+
@@ -0,0 +1,342 @@
+//! Encoding and decoding of the messages of the Remote Framebuffer (RFB) protocol,
+//! as specified in RFC 6143.
+//!
+//! Only the parts of the protocol that a minimal server needs are supported:
+//! no authentication and the raw encoding of true-color pixels.
+
+use alloc::vec::Vec;
+
+/// The protocol version sent by the server, i.e., version 3.8.
+pub const PROTOCOL_VERSION: &[u8; 12] = b"RFB 003.008\n";
+
+/// The security type that requires no authentication.
+pub const SECURITY_TYPE_NONE: u8 = 1;
+
+/// The encoding that sends each pixel of a rectangle as is.
+pub const ENCODING_RAW: i32 = 0;
+
+/// The maximum length of cut text that a client may send, beyond which it is considered malicious.
+pub const MAX_CUT_TEXT_LEN: usize = 64 * 1024;
+
+/// Parses the protocol version that a client sent in response to [`PROTOCOL_VERSION`],
+/// returning the minor version that should be used: 3, 7, or 8.
+pub fn parse_protocol_version(bytes: &[u8; 12]) -> Option<u8> {
+    if &bytes[..4] != b"RFB " || bytes[7] != b'.' || bytes[11] != b'\n' {
+        return None;
+    }
+    let parse = |digits: &[u8]| digits.iter().try_fold(0u16, |n, &d| {
+        d.is_ascii_digit().then(|| n * 10 + (d - b'0') as u16)
+    });
+    let major = parse(&bytes[4..7])?;
+    let minor = parse(&bytes[8..11])?;
+    match (major, minor) {
+        (3, 0..=6) => Some(3),
+        (3, 7) => Some(7),
+        (3, _) | (4.., _) => Some(8),
+        _ => None,
+    }
+}
+
+/// A rectangular region of the screen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rect {
+    pub x: u16,
+    pub y: u16,
+    pub width: u16,
+    pub height: u16,
+}
+
+impl Rect {
+    /// Returns the part of this rectangle that lies within a screen of the given size.
+    pub fn clamp(self, screen_width: u16, screen_height: u16) -> Rect {
+        let x = self.x.min(screen_width);
+        let y = self.y.min(screen_height);
+        Rect {
+            x,
+            y,
+            width: self.width.min(screen_width - x),
+            height: self.height.min(screen_height - y),
+        }
+    }
+
+    /// Returns whether this rectangle contains no pixels.
+    pub fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+}
+
+/// How pixel values are represented on the wire.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PixelFormat {
+    pub bits_per_pixel: u8,
+    pub depth: u8,
+    pub big_endian: bool,
+    pub true_color: bool,
+    pub red_max: u16,
+    pub green_max: u16,
+    pub blue_max: u16,
+    pub red_shift: u8,
+    pub green_shift: u8,
+    pub blue_shift: u8,
+}
+
+impl PixelFormat {
+    /// The format that the server uses unless a client requests another one:
+    /// 32-bit little-endian pixels with 8 bits for each of red, green, and blue.
+    pub const DEFAULT: PixelFormat = PixelFormat {
+        bits_per_pixel: 32,
+        depth: 24,
+        big_endian: false,
+        true_color: true,
+        red_max: 255,
+        green_max: 255,
+        blue_max: 255,
+        red_shift: 16,
+        green_shift: 8,
+        blue_shift: 0,
+    };
+
+    fn parse(bytes: &[u8]) -> PixelFormat {
+        PixelFormat {
+            bits_per_pixel: bytes[0],
+            depth: bytes[1],
+            big_endian: bytes[2] != 0,
+            true_color: bytes[3] != 0,
+            red_max: u16::from_be_bytes([bytes[4], bytes[5]]),
+            green_max: u16::from_be_bytes([bytes[6], bytes[7]]),
+            blue_max: u16::from_be_bytes([bytes[8], bytes[9]]),
+            red_shift: bytes[10],
+            green_shift: bytes[11],
+            blue_shift: bytes[12],
+        }
+    }
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&[self.bits_per_pixel, self.depth, self.big_endian as u8, self.true_color as u8]);
+        out.extend_from_slice(&self.red_max.to_be_bytes());
+        out.extend_from_slice(&self.green_max.to_be_bytes());
+        out.extend_from_slice(&self.blue_max.to_be_bytes());
+        out.extend_from_slice(&[self.red_shift, self.green_shift, self.blue_shift, 0, 0, 0]);
+    }
+
+    /// Returns whether the server can send pixels in this format.
+    ///
+    /// Color maps are not supported, only true-color formats.
+    pub fn is_supported(&self) -> bool {
+        self.true_color && matches!(self.bits_per_pixel, 8 | 16 | 32)
+    }
+
+    /// Appends the given `0x00RRGGBB` color to `out` in this format.
+    pub fn write_pixel(&self, rgb: u32, out: &mut Vec<u8>) {
+        let scale = |component: u32, max: u16| component * max as u32 / 255;
+        let value = scale((rgb >> 16) & 0xFF, self.red_max) << self.red_shift
+            | scale((rgb >> 8) & 0xFF, self.green_max) << self.green_shift
+            | scale(rgb & 0xFF, self.blue_max) << self.blue_shift;
+        match (self.bits_per_pixel, self.big_endian) {
+            (8, _) => out.push(value as u8),
+            (16, false) => out.extend_from_slice(&(value as u16).to_le_bytes()),
+            (16, true) => out.extend_from_slice(&(value as u16).to_be_bytes()),
+            (_, false) => out.extend_from_slice(&value.to_le_bytes()),
+            (_, true) => out.extend_from_slice(&value.to_be_bytes()),
+        }
+    }
+}
+
+/// A message sent from a client to the server after the initial handshake.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ClientMessage {
+    SetPixelFormat(PixelFormat),
+    SetEncodings(Vec<i32>),
+    FramebufferUpdateRequest {
+        incremental: bool,
+        rect: Rect,
+    },
+    KeyEvent {
+        down: bool,
+        keysym: u32,
+    },
+    PointerEvent {
+        /// Bits 0-2 are the left, middle, and right buttons;
+        /// bits 3 and 4 are scrolling up and down.
+        buttons: u8,
+        x: u16,
+        y: u16,
+    },
+    ClientCutText(Vec<u8>),
+}
+
+/// Parses the client message at the start of the given bytes.
+///
+/// Returns the message and the number of bytes it occupied,
+/// `Ok(None)` if more bytes must be received before the message is complete,
+/// or an error if the message is invalid.
+pub fn parse_client_message(bytes: &[u8]) -> Result<Option<(ClientMessage, usize)>, &'static str> {
+    let u16_at = |i: usize| u16::from_be_bytes([bytes[i], bytes[i + 1]]);
+    let u32_at = |i: usize| u32::from_be_bytes([bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]]);
+
+    let Some(&message_type) = bytes.first() else { return Ok(None) };
+    let len = match message_type {
+        0 => 20,
+        2 if bytes.len() >= 4 => 4 + 4 * u16_at(2) as usize,
+        3 => 10,
+        4 => 8,
+        5 => 6,
+        6 if bytes.len() >= 8 => {
+            let text_len = u32_at(4) as usize;
+            if text_len > MAX_CUT_TEXT_LEN {
+                return Err("client cut text is too long");
+            }
+            8 + text_len
+        }
+        2 | 6 => return Ok(None),
+        _ => return Err("unknown client message type"),
+    };
+    if bytes.len() < len {
+        return Ok(None);
+    }
+
+    let message = match message_type {
+        0 => ClientMessage::SetPixelFormat(PixelFormat::parse(&bytes[4..20])),
+        2 => ClientMessage::SetEncodings(
+            bytes[4..len].chunks_exact(4)
+                .map(|e| i32::from_be_bytes([e[0], e[1], e[2], e[3]]))
+                .collect()
+        ),
+        3 => ClientMessage::FramebufferUpdateRequest {
+            incremental: bytes[1] != 0,
+            rect: Rect { x: u16_at(2), y: u16_at(4), width: u16_at(6), height: u16_at(8) },
+        },
+        4 => ClientMessage::KeyEvent { down: bytes[1] != 0, keysym: u32_at(4) },
+        5 => ClientMessage::PointerEvent { buttons: bytes[1], x: u16_at(2), y: u16_at(4) },
+        _ => ClientMessage::ClientCutText(bytes[8..len].to_vec()),
+    };
+    Ok(Some((message, len)))
+}
+
+/// Returns the `ServerInit` message, which describes the screen to a newly-connected client.
+pub fn server_init(width: u16, height: u16, format: &PixelFormat, name: &str) -> Vec<u8> {
+    let mut out = Vec::with_capacity(24 + name.len());
+    out.extend_from_slice(&width.to_be_bytes());
+    out.extend_from_slice(&height.to_be_bytes());
+    format.encode(&mut out);
+    out.extend_from_slice(&(name.len() as u32).to_be_bytes());
+    out.extend_from_slice(name.as_bytes());
+    out
+}
+
+/// Returns a `FramebufferUpdate` message containing the given rectangle of the screen in the raw encoding.
+///
+/// The screen's pixels are given as `0x00RRGGBB` colors, in rows of `stride` pixels.
+pub fn framebuffer_update(pixels: &[u32], stride: usize, rect: Rect, format: &PixelFormat) -> Vec<u8> {
+    let bytes_per_pixel = format.bits_per_pixel as usize / 8;
+    let mut out = Vec::with_capacity(16 + rect.width as usize * rect.height as usize * bytes_per_pixel);
+    // The message type and padding, followed by the number of rectangles.
+    out.extend_from_slice(&[0, 0]);
+    out.extend_from_slice(&1u16.to_be_bytes());
+    for value in [rect.x, rect.y, rect.width, rect.height] {
+        out.extend_from_slice(&value.to_be_bytes());
+    }
+    out.extend_from_slice(&ENCODING_RAW.to_be_bytes());
+    for y in rect.y as usize..(rect.y + rect.height) as usize {
+        let row = y * stride;
+        for &pixel in &pixels[row + rect.x as usize..row + (rect.x + rect.width) as usize] {
+            format.write_pixel(pixel, &mut out);
+        }
+    }
+    out
+}
+
+/// Returns the smallest rectangle within `within` that contains every pixel
+/// that differs between the `previous` and `current` screens, if any.
+///
+/// Both screens' pixels are given in rows of `stride` pixels.
+pub fn changed_region(previous: &[u32], current: &[u32], stride: usize, within: Rect) -> Option<Rect> {
+    let (mut min_x, mut min_y, mut max_x, mut max_y) = (u16::MAX, u16::MAX, 0, 0);
+    for y in within.y..within.y + within.height {
+        let row = y as usize * stride;
+        for x in within.x..within.x + within.width {
+            let i = row + x as usize;
+            if previous[i] != current[i] {
+                min_x = min_x.min(x);
+                max_x = max_x.max(x);
+                min_y = min_y.min(y);
+                max_y = max_y.max(y);
+            }
+        }
+    }
+    (min_x <= max_x).then(|| Rect {
+        x: min_x,
+        y: min_y,
+        width: max_x - min_x + 1,
+        height: max_y - min_y + 1,
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn protocol_versions() {
+        assert_eq!(parse_protocol_version(b"RFB 003.008\n"), Some(8));
+        assert_eq!(parse_protocol_version(b"RFB 003.007\n"), Some(7));
+        assert_eq!(parse_protocol_version(b"RFB 003.003\n"), Some(3));
+        assert_eq!(parse_protocol_version(b"RFB 003.889\n"), Some(8));
+        assert_eq!(parse_protocol_version(b"GET / HTTP/1"), None);
+    }
+
+    #[test]
+    fn partial_and_complete_messages() {
+        let pointer = [5, 0b001, 0, 10, 0, 20];
+        assert_eq!(parse_client_message(&pointer[..4]), Ok(None));
+        assert_eq!(
+            parse_client_message(&pointer),
+            Ok(Some((ClientMessage::PointerEvent { buttons: 1, x: 10, y: 20 }, 6))),
+        );
+
+        let encodings = [2, 0, 0, 2, 0, 0, 0, 0, 0xFF, 0xFF, 0xFF, 0x21, 9];
+        assert_eq!(parse_client_message(&encodings[..7]), Ok(None));
+        assert_eq!(
+            parse_client_message(&encodings),
+            Ok(Some((ClientMessage::SetEncodings(vec![ENCODING_RAW, -223]), 12))),
+        );
+
+        assert!(parse_client_message(&[6, 0, 0, 0, 0xFF, 0xFF, 0xFF, 0xFF]).is_err());
+        assert!(parse_client_message(&[42]).is_err());
+    }
+
+    #[test]
+    fn pixel_formats() {
+        let mut out = Vec::new();
+        PixelFormat::DEFAULT.write_pixel(0x00AABBCC, &mut out);
+        assert_eq!(out, [0xCC, 0xBB, 0xAA, 0x00]);
+
+        let rgb565 = PixelFormat {
+            bits_per_pixel: 16, depth: 16, big_endian: true, true_color: true,
+            red_max: 31, green_max: 63, blue_max: 31,
+            red_shift: 11, green_shift: 5, blue_shift: 0,
+        };
+        out.clear();
+        rgb565.write_pixel(0x00FF00FF, &mut out);
+        assert_eq!(out, [0xF8, 0x1F]);
+    }
+
+    #[test]
+    fn changed_regions() {
+        let previous = vec![0; 16];
+        let mut current = previous.clone();
+        let screen = Rect { x: 0, y: 0, width: 4, height: 4 };
+        assert_eq!(changed_region(&previous, &current, 4, screen), None);
+
+        current[4 + 1] = 1;
+        current[2 * 4 + 2] = 1;
+        assert_eq!(changed_region(&previous, &current, 4, screen), Some(Rect { x: 1, y: 1, width: 2, height: 2 }));
+        assert_eq!(changed_region(&previous, &current, 4, Rect { x: 3, y: 0, width: 1, height: 4 }), None);
+
+        let update = framebuffer_update(&current, 4, Rect { x: 1, y: 1, width: 2, height: 1 }, &PixelFormat::DEFAULT);
+        assert_eq!(update.len(), 16 + 2 * 4);
+        assert_eq!(&update[16..20], &[1, 0, 0, 0]);
+    }
+}
@@ -5,7 +5,7 @@ use mpmc_queue::Queue;
 use preemption::hold_preemption;
 use sync::DeadlockPrevention;
 use sync_spin::Spin;
-use task::{get_my_current_task, TaskRef};
+use task::{get_my_current_task, TaskRef, WaitReason};
 
 /// A queue of tasks waiting for an event to occur.
 ///
@@ -41,11 +41,23 @@ where
     }
 
     /// Blocks the current task until the given condition succeeds.
-    pub fn wait_until<F, T>(&self, mut condition: F) -> T
+    pub fn wait_until<F, T>(&self, condition: F) -> T
     where
         F: FnMut() -> Option<T>,
     {
-        let task = get_my_current_task().unwrap();
+        self.wait_until_with_reason(WaitReason::of("WaitQueue", self), condition)
+    }
+
+    /// Blocks the current task until the given condition succeeds,
+    /// recording the given `reason` as what the task is waiting on while it's blocked.
+    ///
+    /// This is useful for primitives built atop a wait queue, e.g., a mutex,
+    /// such that a blocked task's wait reason refers to that primitive.
+    pub fn wait_until_with_reason<F, T>(&self, reason: WaitReason, mut condition: F) -> T
+    where
+        F: FnMut() -> Option<T>,
+    {
+        let mut task = get_my_current_task().unwrap();
         loop {
             let wrapped_condition = || {
                 if let Some(value) = condition() {
@@ -54,16 +66,27 @@ where
                     // Ensure that we don't get preempted after blocking ourselves
                     // before we get a chance to release the internal lock of the queue.
                     let preemption_guard = hold_preemption();
+                    task.set_wait_reason(Some(reason));
                     task.block().unwrap();
                     Err(preemption_guard)
                 }
             };
 
             match self.inner.push_if_fail(task.clone(), wrapped_condition) {
-                Ok(value) => return value,
+                Ok(value) => {
+                    task.set_wait_reason(None);
+                    return value;
+                }
                 Err(preemption_guard) => {
                     drop(preemption_guard);
                     scheduler::schedule();
+                    // The current task may have been woken to receive a notification,
+                    // which may kill it, so we must not hold a reference to it.
+                    if task.has_pending_notifications() {
+                        drop(task);
+                        task::notifications::deliver_pending();
+                        task = get_my_current_task().unwrap();
+                    }
                 }
             }
         }
@@ -48,6 +48,9 @@ use window_inner::{WindowInner, WindowMovingStatus};
 /// The instance of the default window manager
 pub static WINDOW_MANAGER: Once<Mutex<WindowManager>> = Once::new();
 
+/// The producer ends of the window manager's keyboard and mouse event queues.
+static INPUT_QUEUES: Once<(Queue<Event>, Queue<Event>)> = Once::new();
+
 /// The width and height size of mouse in number of pixels.
 const MOUSE_POINTER_SIZE_Y: usize = 18;
 const MOUSE_POINTER_SIZE_X: usize = 11;
@@ -597,11 +600,39 @@ impl WindowManager {
     pub fn get_screen_size(&self) -> (usize, usize) {
         self.final_fb.get_size()
     }
+
+    /// Returns the current position of the mouse pointer on the screen.
+    pub fn get_mouse_position(&self) -> Coord {
+        self.mouse
+    }
 }
 
 /// Initialize the window manager. It returns (keyboard_producer, mouse_producer) for the I/O devices.
 pub fn init() -> Result<(Queue<Event>, Queue<Event>), &'static str> {
     let final_fb: Framebuffer<AlphaPixel> = framebuffer::init()?;
+    init_with_framebuffer(final_fb)
+}
+
+/// Initialize the window manager without a physical display,
+/// such that windows are composited into an in-memory screen of the given size,
+/// e.g., to be viewed remotely on a headless machine.
+///
+/// It returns (keyboard_producer, mouse_producer), just like [`init()`].
+pub fn init_headless(width: usize, height: usize) -> Result<(Queue<Event>, Queue<Event>), &'static str> {
+    let final_fb: Framebuffer<AlphaPixel> = Framebuffer::new(width, height, None)?;
+    init_with_framebuffer(final_fb)
+}
+
+/// Returns the producer ends of the window manager's (keyboard, mouse) event queues,
+/// such that input from other sources, e.g., a remote desktop client, can be injected.
+pub fn input_queues() -> Option<(Queue<Event>, Queue<Event>)> {
+    INPUT_QUEUES.get().cloned()
+}
+
+fn init_with_framebuffer(final_fb: Framebuffer<AlphaPixel>) -> Result<(Queue<Event>, Queue<Event>), &'static str> {
+    if WINDOW_MANAGER.is_completed() {
+        return Err("the window manager has already been initialized");
+    }
     let (width, height) = final_fb.get_size();
 
     let mut bottom_fb = Framebuffer::new(width, height, None)?;
@@ -641,6 +672,7 @@ pub fn init() -> Result<(Queue<Event>, Queue<Event>), &'static str> {
         .name("window_manager_loop".to_string())
         .spawn()?;
 
+    INPUT_QUEUES.call_once(|| (key_producer.clone(), mouse_producer.clone()));
     Ok((key_producer, mouse_producer))
 }
 
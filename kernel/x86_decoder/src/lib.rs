@@ -0,0 +1,798 @@
+//! A decoder for x86_64 machine code that formats instructions in Intel syntax.
+//!
+//! This supports the general-purpose instructions that the compiler emits for Theseus's crates,
+//! the system instructions used within the kernel, and the most common SSE data movement instructions.
+//! That is enough to inspect the code of a loaded section and verify the targets of its
+//! calls, jumps, and RIP-relative accesses, which is what this crate is meant for.
+//! Other instructions, including most floating-point and vector instructions,
+//! cannot be decoded and are reported as such.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::{vec, vec::Vec};
+use core::fmt;
+
+/// The maximum length of a single x86 instruction in bytes.
+pub const MAX_INSTRUCTION_LEN: usize = 15;
+
+const REGS64: [&str; 16] = [
+    "rax", "rcx", "rdx", "rbx", "rsp", "rbp", "rsi", "rdi",
+    "r8", "r9", "r10", "r11", "r12", "r13", "r14", "r15",
+];
+const REGS32: [&str; 16] = [
+    "eax", "ecx", "edx", "ebx", "esp", "ebp", "esi", "edi",
+    "r8d", "r9d", "r10d", "r11d", "r12d", "r13d", "r14d", "r15d",
+];
+const REGS16: [&str; 16] = [
+    "ax", "cx", "dx", "bx", "sp", "bp", "si", "di",
+    "r8w", "r9w", "r10w", "r11w", "r12w", "r13w", "r14w", "r15w",
+];
+const REGS8: [&str; 16] = [
+    "al", "cl", "dl", "bl", "spl", "bpl", "sil", "dil",
+    "r8b", "r9b", "r10b", "r11b", "r12b", "r13b", "r14b", "r15b",
+];
+/// The 8-bit registers that are used when there is no REX prefix.
+const REGS8_LEGACY: [&str; 8] = ["al", "cl", "dl", "bl", "ah", "ch", "dh", "bh"];
+const XMM_REGS: [&str; 16] = [
+    "xmm0", "xmm1", "xmm2", "xmm3", "xmm4", "xmm5", "xmm6", "xmm7",
+    "xmm8", "xmm9", "xmm10", "xmm11", "xmm12", "xmm13", "xmm14", "xmm15",
+];
+const CONTROL_REGS: [&str; 16] = [
+    "cr0", "cr1", "cr2", "cr3", "cr4", "cr5", "cr6", "cr7",
+    "cr8", "cr9", "cr10", "cr11", "cr12", "cr13", "cr14", "cr15",
+];
+const SEGMENT_REGS: [&str; 6] = ["es", "cs", "ss", "ds", "fs", "gs"];
+
+const JCC: [&str; 16] = [
+    "jo", "jno", "jb", "jae", "je", "jne", "jbe", "ja",
+    "js", "jns", "jp", "jnp", "jl", "jge", "jle", "jg",
+];
+const SETCC: [&str; 16] = [
+    "seto", "setno", "setb", "setae", "sete", "setne", "setbe", "seta",
+    "sets", "setns", "setp", "setnp", "setl", "setge", "setle", "setg",
+];
+const CMOVCC: [&str; 16] = [
+    "cmovo", "cmovno", "cmovb", "cmovae", "cmove", "cmovne", "cmovbe", "cmova",
+    "cmovs", "cmovns", "cmovp", "cmovnp", "cmovl", "cmovge", "cmovle", "cmovg",
+];
+const ALU: [&str; 8] = ["add", "or", "adc", "sbb", "and", "sub", "xor", "cmp"];
+const SHIFTS: [&str; 8] = ["rol", "ror", "rcl", "rcr", "shl", "shr", "shl", "sar"];
+const GROUP3: [&str; 8] = ["test", "test", "not", "neg", "mul", "imul", "div", "idiv"];
+const BIT_TESTS: [&str; 4] = ["bt", "bts", "btr", "btc"];
+
+/// A decoded instruction.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Instruction {
+    /// The address that the instruction was decoded at.
+    pub address: u64,
+    /// The length of the instruction in bytes.
+    pub len: usize,
+    /// A `lock` or repeat prefix, if the instruction has one.
+    pub prefix: Option<&'static str>,
+    pub mnemonic: &'static str,
+    pub operands: Vec<Operand>,
+}
+
+impl Instruction {
+    /// Returns the address that this instruction jumps to, calls, or accesses relative to
+    /// the instruction pointer, if any.
+    ///
+    /// These are the addresses that relocations are typically applied to.
+    pub fn target(&self) -> Option<u64> {
+        self.operands.iter().find_map(|operand| match operand {
+            Operand::Target(address) => Some(*address),
+            Operand::Memory(memory) if memory.base == Some("rip") => Some(
+                self.address
+                    .wrapping_add(self.len as u64)
+                    .wrapping_add(memory.displacement as u64),
+            ),
+            _ => None,
+        })
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(prefix) = self.prefix {
+            write!(f, "{} ", prefix)?;
+        }
+        f.write_str(self.mnemonic)?;
+        for (i, operand) in self.operands.iter().enumerate() {
+            write!(f, "{}{}", if i == 0 { " " } else { ", " }, operand)?;
+        }
+        Ok(())
+    }
+}
+
+/// An operand of an [`Instruction`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Operand {
+    Register(&'static str),
+    Immediate(i64),
+    Memory(Memory),
+    /// The absolute address that a relative jump or call goes to.
+    Target(u64),
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Operand::Register(name) => f.write_str(name),
+            Operand::Immediate(value) if *value < 0 => write!(f, "-{:#x}", value.unsigned_abs()),
+            Operand::Immediate(value) => write!(f, "{:#x}", value),
+            Operand::Memory(memory) => write!(f, "{}", memory),
+            Operand::Target(address) => write!(f, "{:#x}", address),
+        }
+    }
+}
+
+/// A memory operand, which accesses `segment:[base + index * scale + displacement]`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Memory {
+    /// The size in bytes of the value that is accessed,
+    /// or `None` if it's not accessed as a single value, e.g., by `lea`.
+    pub size: Option<u8>,
+    pub segment: Option<&'static str>,
+    /// The base register, which is `rip` for addresses relative to the next instruction.
+    pub base: Option<&'static str>,
+    pub index: Option<&'static str>,
+    pub scale: u8,
+    pub displacement: i64,
+}
+
+impl fmt::Display for Memory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let size_name = match self.size {
+            Some(1) => Some("byte"),
+            Some(2) => Some("word"),
+            Some(4) => Some("dword"),
+            Some(8) => Some("qword"),
+            Some(16) => Some("xmmword"),
+            _ => None,
+        };
+        if let Some(size_name) = size_name {
+            write!(f, "{} ptr ", size_name)?;
+        }
+        if let Some(segment) = self.segment {
+            write!(f, "{}:", segment)?;
+        }
+        f.write_str("[")?;
+        if let Some(base) = self.base {
+            f.write_str(base)?;
+        }
+        if let Some(index) = self.index {
+            let separator = if self.base.is_some() { "+" } else { "" };
+            write!(f, "{}{}*{}", separator, index, self.scale)?;
+        }
+        if self.base.is_none() && self.index.is_none() {
+            write!(f, "{:#x}", self.displacement as u64)?;
+        } else if self.displacement < 0 {
+            write!(f, "-{:#x}", self.displacement.unsigned_abs())?;
+        } else if self.displacement > 0 {
+            write!(f, "+{:#x}", self.displacement)?;
+        }
+        f.write_str("]")
+    }
+}
+
+/// Decodes the instruction at the start of `bytes`, which is located at the given `address`.
+///
+/// Returns `None` if the instruction is not supported or is cut off by the end of `bytes`.
+pub fn decode(bytes: &[u8], address: u64) -> Option<Instruction> {
+    let mut decoder = Decoder {
+        bytes: &bytes[..bytes.len().min(MAX_INSTRUCTION_LEN)],
+        pos: 0,
+        address,
+        rex: 0,
+        operand_size_prefix: false,
+        address_size_prefix: false,
+        repeat: None,
+        segment: None,
+        prefix: None,
+    };
+
+    let opcode = loop {
+        let byte = decoder.byte()?;
+        match byte {
+            0xf0 => decoder.prefix = Some("lock"),
+            0xf2 | 0xf3 => decoder.repeat = Some(byte),
+            0x26 => decoder.segment = Some("es"),
+            0x2e => decoder.segment = Some("cs"),
+            0x36 => decoder.segment = Some("ss"),
+            0x3e => decoder.segment = Some("ds"),
+            0x64 => decoder.segment = Some("fs"),
+            0x65 => decoder.segment = Some("gs"),
+            0x66 => decoder.operand_size_prefix = true,
+            0x67 => decoder.address_size_prefix = true,
+            // A REX prefix must come immediately before the opcode.
+            0x40..=0x4f => {
+                decoder.rex = byte;
+                break decoder.byte()?;
+            }
+            _ => break byte,
+        }
+    };
+
+    let (mnemonic, operands) = if opcode == 0x0f {
+        decoder.two_byte_opcode()?
+    } else {
+        decoder.one_byte_opcode(opcode)?
+    };
+    Some(Instruction {
+        address,
+        len: decoder.pos,
+        prefix: decoder.prefix,
+        mnemonic,
+        operands,
+    })
+}
+
+type Decoded = Option<(&'static str, Vec<Operand>)>;
+
+#[derive(Clone, Copy)]
+struct ModRm {
+    md: u8,
+    reg: u8,
+    rm: u8,
+}
+
+struct Decoder<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    address: u64,
+    /// The REX prefix, or `0` if there isn't one.
+    rex: u8,
+    operand_size_prefix: bool,
+    address_size_prefix: bool,
+    /// The `0xF2` or `0xF3` prefix, if there is one.
+    repeat: Option<u8>,
+    segment: Option<&'static str>,
+    prefix: Option<&'static str>,
+}
+
+impl<'a> Decoder<'a> {
+    fn byte(&mut self) -> Option<u8> {
+        let byte = *self.bytes.get(self.pos)?;
+        self.pos += 1;
+        Some(byte)
+    }
+
+    /// Reads a little-endian immediate value of the given size in bytes, sign-extending it.
+    fn immediate(&mut self, size: u8) -> Option<i64> {
+        let bytes = self.bytes.get(self.pos..self.pos + size as usize)?;
+        self.pos += size as usize;
+        Some(match size {
+            1 => bytes[0] as i8 as i64,
+            2 => i16::from_le_bytes(bytes.try_into().ok()?) as i64,
+            4 => i32::from_le_bytes(bytes.try_into().ok()?) as i64,
+            _ => i64::from_le_bytes(bytes.try_into().ok()?),
+        })
+    }
+
+    /// Reads a relative offset of the given size in bytes, which must end the instruction.
+    fn relative(&mut self, size: u8) -> Option<Operand> {
+        let offset = self.immediate(size)?;
+        let next_instruction = self.address.wrapping_add(self.pos as u64);
+        Some(Operand::Target(next_instruction.wrapping_add(offset as u64)))
+    }
+
+    fn rex_w(&self) -> bool {
+        self.rex & 0b1000 != 0
+    }
+
+    /// Returns the operand size in bytes of instructions that default to 32-bit operands.
+    fn operand_size(&self) -> u8 {
+        if self.rex_w() {
+            8
+        } else if self.operand_size_prefix {
+            2
+        } else {
+            4
+        }
+    }
+
+    /// Returns the operand size in bytes of instructions that default to 64-bit operands,
+    /// such as `push` and `pop`.
+    fn stack_operand_size(&self) -> u8 {
+        if self.operand_size_prefix { 2 } else { 8 }
+    }
+
+    /// Returns the name of a general-purpose or XMM register of the given size in bytes.
+    fn register(&self, num: u8, size: u8) -> &'static str {
+        let num = num as usize;
+        match size {
+            1 if self.rex == 0 => REGS8_LEGACY[num & 7],
+            1 => REGS8[num],
+            2 => REGS16[num],
+            4 => REGS32[num],
+            16 => XMM_REGS[num],
+            _ => REGS64[num],
+        }
+    }
+
+    /// Returns the register encoded in the low bits of the opcode, extended by REX.B.
+    fn opcode_register(&self, opcode: u8, size: u8) -> Operand {
+        Operand::Register(self.register(opcode & 7 | (self.rex & 1) << 3, size))
+    }
+
+    fn modrm(&mut self) -> Option<ModRm> {
+        let byte = self.byte()?;
+        Some(ModRm { md: byte >> 6, reg: (byte >> 3) & 7, rm: byte & 7 })
+    }
+
+    /// Returns the register selected by the reg field of the ModRM byte.
+    fn reg(&self, modrm: ModRm, size: u8) -> Operand {
+        Operand::Register(self.register(modrm.reg | (self.rex & 0b100) << 1, size))
+    }
+
+    /// Returns the register or memory operand selected by the mod and rm fields of the ModRM byte.
+    fn rm(&mut self, modrm: ModRm, size: u8) -> Option<Operand> {
+        self.rm_sized(modrm, size, size)
+    }
+
+    /// Like [`Decoder::rm()`], but with different sizes for register and memory operands.
+    fn rm_sized(&mut self, modrm: ModRm, register_size: u8, memory_size: u8) -> Option<Operand> {
+        if modrm.md == 3 {
+            Some(Operand::Register(self.register(modrm.rm | (self.rex & 1) << 3, register_size)))
+        } else {
+            self.memory(modrm, Some(memory_size))
+        }
+    }
+
+    /// Decodes the memory operand selected by the ModRM byte,
+    /// returning `None` if it selects a register instead.
+    fn memory(&mut self, modrm: ModRm, size: Option<u8>) -> Option<Operand> {
+        if modrm.md == 3 {
+            return None;
+        }
+        let registers = if self.address_size_prefix { &REGS32 } else { &REGS64 };
+        let mut base = None;
+        let mut index = None;
+        let mut scale = 1;
+        let mut displacement_size = match modrm.md {
+            1 => 1,
+            2 => 4,
+            _ => 0,
+        };
+        if modrm.rm == 4 {
+            let sib = self.byte()?;
+            scale = 1 << (sib >> 6);
+            let index_num = (sib >> 3) & 7 | (self.rex & 0b10) << 2;
+            if index_num != 4 {
+                index = Some(registers[index_num as usize]);
+            }
+            if sib & 7 == 5 && modrm.md == 0 {
+                displacement_size = 4;
+            } else {
+                base = Some(registers[(sib & 7 | (self.rex & 1) << 3) as usize]);
+            }
+        } else if modrm.rm == 5 && modrm.md == 0 {
+            base = Some(if self.address_size_prefix { "eip" } else { "rip" });
+            displacement_size = 4;
+        } else {
+            base = Some(registers[(modrm.rm | (self.rex & 1) << 3) as usize]);
+        }
+        let displacement = if displacement_size == 0 { 0 } else { self.immediate(displacement_size)? };
+        Some(Operand::Memory(Memory { size, segment: self.segment, base, index, scale, displacement }))
+    }
+
+    /// Decodes the operands of the common forms that are selected by the low bits of many opcodes:
+    /// `0`: `r/m8, r8`; `1`: `r/m, r`; `2`: `r8, r/m8`; `3`: `r, r/m`; `4`: `al, imm8`; and `5`: `rax, imm`.
+    fn standard_operands(&mut self, form: u8) -> Option<Vec<Operand>> {
+        let size = if form & 1 == 0 { 1 } else { self.operand_size() };
+        match form {
+            0 | 1 => {
+                let modrm = self.modrm()?;
+                let rm = self.rm(modrm, size)?;
+                Some(vec![rm, self.reg(modrm, size)])
+            }
+            2 | 3 => {
+                let modrm = self.modrm()?;
+                let rm = self.rm(modrm, size)?;
+                Some(vec![self.reg(modrm, size), rm])
+            }
+            _ => {
+                let immediate = self.immediate(size.min(4))?;
+                Some(vec![Operand::Register(self.register(0, size)), Operand::Immediate(immediate)])
+            }
+        }
+    }
+
+    fn one_byte_opcode(&mut self, opcode: u8) -> Decoded {
+        let no_operands = |mnemonic| Some((mnemonic, Vec::new()));
+        match opcode {
+            0x00..=0x3f if opcode & 7 < 6 => {
+                Some((ALU[(opcode >> 3) as usize], self.standard_operands(opcode & 7)?))
+            }
+            0x50..=0x57 => Some(("push", vec![self.opcode_register(opcode, self.stack_operand_size())])),
+            0x58..=0x5f => Some(("pop", vec![self.opcode_register(opcode, self.stack_operand_size())])),
+            0x63 => {
+                let size = self.operand_size();
+                let modrm = self.modrm()?;
+                let rm = self.rm(modrm, 4)?;
+                Some(("movsxd", vec![self.reg(modrm, size), rm]))
+            }
+            0x68 => {
+                let size = self.stack_operand_size().min(4);
+                Some(("push", vec![Operand::Immediate(self.immediate(size)?)]))
+            }
+            0x6a => Some(("push", vec![Operand::Immediate(self.immediate(1)?)])),
+            0x69 | 0x6b => {
+                let size = self.operand_size();
+                let modrm = self.modrm()?;
+                let rm = self.rm(modrm, size)?;
+                let immediate = self.immediate(if opcode == 0x69 { size.min(4) } else { 1 })?;
+                Some(("imul", vec![self.reg(modrm, size), rm, Operand::Immediate(immediate)]))
+            }
+            0x70..=0x7f => Some((JCC[(opcode & 0xf) as usize], vec![self.relative(1)?])),
+            0x80 | 0x81 | 0x83 => {
+                let size = if opcode == 0x80 { 1 } else { self.operand_size() };
+                let modrm = self.modrm()?;
+                let rm = self.rm(modrm, size)?;
+                let immediate = self.immediate(if opcode == 0x81 { size.min(4) } else { 1 })?;
+                Some((ALU[modrm.reg as usize], vec![rm, Operand::Immediate(immediate)]))
+            }
+            0x84 | 0x85 => Some(("test", self.standard_operands(opcode & 1)?)),
+            0x86 | 0x87 => Some(("xchg", self.standard_operands(opcode & 1)?)),
+            0x88..=0x8b => Some(("mov", self.standard_operands(opcode & 3)?)),
+            0x8c | 0x8e => {
+                let modrm = self.modrm()?;
+                let segment = Operand::Register(SEGMENT_REGS.get(modrm.reg as usize)?);
+                let rm = self.rm_sized(modrm, self.operand_size(), 2)?;
+                Some(("mov", if opcode == 0x8c { vec![rm, segment] } else { vec![segment, rm] }))
+            }
+            0x8d => {
+                let size = self.operand_size();
+                let modrm = self.modrm()?;
+                let memory = self.memory(modrm, None)?;
+                Some(("lea", vec![self.reg(modrm, size), memory]))
+            }
+            0x8f => {
+                let modrm = self.modrm()?;
+                if modrm.reg != 0 {
+                    return None;
+                }
+                Some(("pop", vec![self.rm(modrm, self.stack_operand_size())?]))
+            }
+            0x90 if self.rex & 1 == 0 => no_operands(if self.repeat == Some(0xf3) { "pause" } else { "nop" }),
+            0x90..=0x97 => {
+                let size = self.operand_size();
+                Some(("xchg", vec![self.opcode_register(opcode, size), Operand::Register(self.register(0, size))]))
+            }
+            0x98 => no_operands(match self.operand_size() { 2 => "cbw", 4 => "cwde", _ => "cdqe" }),
+            0x99 => no_operands(match self.operand_size() { 2 => "cwd", 4 => "cdq", _ => "cqo" }),
+            0x9c => no_operands(if self.operand_size_prefix { "pushf" } else { "pushfq" }),
+            0x9d => no_operands(if self.operand_size_prefix { "popf" } else { "popfq" }),
+            0xa4..=0xa7 | 0xaa..=0xaf => {
+                let size = if opcode & 1 == 0 { 1 } else { self.operand_size() };
+                let (names, compares) = match opcode & !1 {
+                    0xa4 => (["movsb", "movsw", "movsd", "movsq"], false),
+                    0xa6 => (["cmpsb", "cmpsw", "cmpsd", "cmpsq"], true),
+                    0xaa => (["stosb", "stosw", "stosd", "stosq"], false),
+                    0xac => (["lodsb", "lodsw", "lodsd", "lodsq"], false),
+                    _ => (["scasb", "scasw", "scasd", "scasq"], true),
+                };
+                self.prefix = match (self.repeat, compares) {
+                    (Some(0xf3), false) => Some("rep"),
+                    (Some(0xf3), true) => Some("repe"),
+                    (Some(_), _) => Some("repne"),
+                    (None, _) => self.prefix,
+                };
+                no_operands(names[size.trailing_zeros() as usize])
+            }
+            0xa8 | 0xa9 => Some(("test", self.standard_operands(4 + (opcode & 1))?)),
+            0xb0..=0xb7 => {
+                let register = self.opcode_register(opcode, 1);
+                Some(("mov", vec![register, Operand::Immediate(self.immediate(1)?)]))
+            }
+            0xb8..=0xbf => {
+                let size = self.operand_size();
+                let register = self.opcode_register(opcode, size);
+                let mnemonic = if size == 8 { "movabs" } else { "mov" };
+                Some((mnemonic, vec![register, Operand::Immediate(self.immediate(size)?)]))
+            }
+            0xc0 | 0xc1 | 0xd0..=0xd3 => {
+                let size = if opcode & 1 == 0 { 1 } else { self.operand_size() };
+                let modrm = self.modrm()?;
+                let rm = self.rm(modrm, size)?;
+                let count = match opcode {
+                    0xc0 | 0xc1 => Operand::Immediate(self.immediate(1)? as u8 as i64),
+                    0xd0 | 0xd1 => Operand::Immediate(1),
+                    _ => Operand::Register("cl"),
+                };
+                Some((SHIFTS[modrm.reg as usize], vec![rm, count]))
+            }
+            0xc2 => Some(("ret", vec![Operand::Immediate(self.immediate(2)? as u16 as i64)])),
+            0xc3 => no_operands("ret"),
+            0xc6 | 0xc7 => {
+                let size = if opcode == 0xc6 { 1 } else { self.operand_size() };
+                let modrm = self.modrm()?;
+                if modrm.reg != 0 {
+                    return None;
+                }
+                let rm = self.rm(modrm, size)?;
+                Some(("mov", vec![rm, Operand::Immediate(self.immediate(size.min(4))?)]))
+            }
+            0xc9 => no_operands("leave"),
+            0xcc => no_operands("int3"),
+            0xcd => Some(("int", vec![Operand::Immediate(self.immediate(1)? as u8 as i64)])),
+            0xcf => no_operands(if self.rex_w() { "iretq" } else { "iretd" }),
+            0xe3 => Some(("jrcxz", vec![self.relative(1)?])),
+            0xe8 => Some(("call", vec![self.relative(4)?])),
+            0xe9 => Some(("jmp", vec![self.relative(4)?])),
+            0xeb => Some(("jmp", vec![self.relative(1)?])),
+            0xf4 => no_operands("hlt"),
+            0xf5 => no_operands("cmc"),
+            0xf6 | 0xf7 => {
+                let size = if opcode == 0xf6 { 1 } else { self.operand_size() };
+                let modrm = self.modrm()?;
+                let rm = self.rm(modrm, size)?;
+                let mut operands = vec![rm];
+                if modrm.reg < 2 {
+                    operands.push(Operand::Immediate(self.immediate(size.min(4))?));
+                }
+                Some((GROUP3[modrm.reg as usize], operands))
+            }
+            0xf8 => no_operands("clc"),
+            0xf9 => no_operands("stc"),
+            0xfa => no_operands("cli"),
+            0xfb => no_operands("sti"),
+            0xfc => no_operands("cld"),
+            0xfd => no_operands("std"),
+            0xfe => {
+                let modrm = self.modrm()?;
+                let mnemonic = match modrm.reg {
+                    0 => "inc",
+                    1 => "dec",
+                    _ => return None,
+                };
+                Some((mnemonic, vec![self.rm(modrm, 1)?]))
+            }
+            0xff => {
+                let modrm = self.modrm()?;
+                let (mnemonic, size) = match modrm.reg {
+                    0 => ("inc", self.operand_size()),
+                    1 => ("dec", self.operand_size()),
+                    2 => ("call", 8),
+                    4 => ("jmp", 8),
+                    6 => ("push", self.stack_operand_size()),
+                    _ => return None,
+                };
+                Some((mnemonic, vec![self.rm(modrm, size)?]))
+            }
+            _ => None,
+        }
+    }
+
+    fn two_byte_opcode(&mut self) -> Decoded {
+        let no_operands = |mnemonic| Some((mnemonic, Vec::new()));
+        // The mandatory prefix that selects between different SSE instructions.
+        let sse_prefix = if self.operand_size_prefix { Some(0x66) } else { self.repeat };
+        let opcode = self.byte()?;
+        match opcode {
+            0x01 => {
+                let modrm = self.modrm()?;
+                if modrm.md == 3 {
+                    return no_operands(match (modrm.reg, modrm.rm) {
+                        (1, 2) => "clac",
+                        (1, 3) => "stac",
+                        (2, 0) => "xgetbv",
+                        (2, 1) => "xsetbv",
+                        (7, 0) => "swapgs",
+                        (7, 1) => "rdtscp",
+                        _ => return None,
+                    });
+                }
+                let mnemonic = match modrm.reg {
+                    0 => "sgdt",
+                    1 => "sidt",
+                    2 => "lgdt",
+                    3 => "lidt",
+                    7 => "invlpg",
+                    _ => return None,
+                };
+                Some((mnemonic, vec![self.memory(modrm, None)?]))
+            }
+            0x05 => no_operands("syscall"),
+            0x07 => no_operands(if self.rex_w() { "sysretq" } else { "sysretd" }),
+            0x09 => no_operands("wbinvd"),
+            0x0b => no_operands("ud2"),
+            0x10 | 0x11 | 0x28 | 0x29 => {
+                let (mnemonic, memory_size) = match (opcode & !1, sse_prefix) {
+                    (0x10, None) => ("movups", 16),
+                    (0x10, Some(0x66)) => ("movupd", 16),
+                    (0x10, Some(0xf3)) => ("movss", 4),
+                    (0x10, _) => ("movsd", 8),
+                    (_, None) => ("movaps", 16),
+                    (_, Some(0x66)) => ("movapd", 16),
+                    _ => return None,
+                };
+                self.sse_operands(mnemonic, memory_size, opcode & 1 == 1)
+            }
+            0x57 => match sse_prefix {
+                None => self.sse_operands("xorps", 16, false),
+                Some(0x66) => self.sse_operands("xorpd", 16, false),
+                _ => None,
+            },
+            0x6f | 0x7f => {
+                let mnemonic = match sse_prefix {
+                    Some(0x66) => "movdqa",
+                    Some(0xf3) => "movdqu",
+                    _ => return None,
+                };
+                self.sse_operands(mnemonic, 16, opcode == 0x7f)
+            }
+            0x6e | 0x7e if sse_prefix == Some(0x66) => {
+                let (mnemonic, size) = if self.rex_w() { ("movq", 8) } else { ("movd", 4) };
+                let modrm = self.modrm()?;
+                let rm = self.rm(modrm, size)?;
+                let xmm = self.reg(modrm, 16);
+                Some((mnemonic, if opcode == 0x6e { vec![xmm, rm] } else { vec![rm, xmm] }))
+            }
+            0x7e if sse_prefix == Some(0xf3) => self.sse_operands("movq", 8, false),
+            0xd6 if sse_prefix == Some(0x66) => self.sse_operands("movq", 8, true),
+            0xef if sse_prefix == Some(0x66) => self.sse_operands("pxor", 16, false),
+            0x1e if self.repeat == Some(0xf3) && self.bytes.get(self.pos) == Some(&0xfa) => {
+                self.pos += 1;
+                no_operands("endbr64")
+            }
+            0x1f => {
+                let modrm = self.modrm()?;
+                Some(("nop", vec![self.rm(modrm, self.operand_size())?]))
+            }
+            0x20 | 0x22 => {
+                let modrm = self.modrm()?;
+                let control = Operand::Register(CONTROL_REGS[(modrm.reg | (self.rex & 0b100) << 1) as usize]);
+                let general = Operand::Register(REGS64[(modrm.rm | (self.rex & 1) << 3) as usize]);
+                Some(("mov", if opcode == 0x20 { vec![general, control] } else { vec![control, general] }))
+            }
+            0x30 => no_operands("wrmsr"),
+            0x31 => no_operands("rdtsc"),
+            0x32 => no_operands("rdmsr"),
+            0x40..=0x4f => Some((CMOVCC[(opcode & 0xf) as usize], self.standard_operands(3)?)),
+            0x80..=0x8f => Some((JCC[(opcode & 0xf) as usize], vec![self.relative(4)?])),
+            0x90..=0x9f => {
+                let modrm = self.modrm()?;
+                Some((SETCC[(opcode & 0xf) as usize], vec![self.rm(modrm, 1)?]))
+            }
+            0xa2 => no_operands("cpuid"),
+            0xa3 | 0xab | 0xb3 | 0xbb => {
+                Some((BIT_TESTS[((opcode >> 3) & 3) as usize], self.standard_operands(1)?))
+            }
+            0xae => {
+                let modrm = self.modrm()?;
+                if modrm.md == 3 {
+                    return no_operands(match modrm.reg {
+                        5 => "lfence",
+                        6 => "mfence",
+                        7 => "sfence",
+                        _ => return None,
+                    });
+                }
+                let (mnemonic, size) = match (modrm.reg, self.rex_w()) {
+                    (0, false) => ("fxsave", None),
+                    (0, true) => ("fxsave64", None),
+                    (1, false) => ("fxrstor", None),
+                    (1, true) => ("fxrstor64", None),
+                    (2, _) => ("ldmxcsr", Some(4)),
+                    (3, _) => ("stmxcsr", Some(4)),
+                    (4, false) => ("xsave", None),
+                    (4, true) => ("xsave64", None),
+                    (5, false) => ("xrstor", None),
+                    (5, true) => ("xrstor64", None),
+                    (6, false) => ("xsaveopt", None),
+                    (6, true) => ("xsaveopt64", None),
+                    _ => ("clflush", Some(1)),
+                };
+                Some((mnemonic, vec![self.memory(modrm, size)?]))
+            }
+            0xaf => Some(("imul", self.standard_operands(3)?)),
+            0xb0 | 0xb1 => Some(("cmpxchg", self.standard_operands(opcode & 1)?)),
+            0xb6 | 0xb7 | 0xbe | 0xbf => {
+                let size = self.operand_size();
+                let modrm = self.modrm()?;
+                let rm = self.rm(modrm, if opcode & 1 == 0 { 1 } else { 2 })?;
+                let mnemonic = if opcode < 0xb8 { "movzx" } else { "movsx" };
+                Some((mnemonic, vec![self.reg(modrm, size), rm]))
+            }
+            0xb8 if self.repeat == Some(0xf3) => Some(("popcnt", self.standard_operands(3)?)),
+            0xba => {
+                let modrm = self.modrm()?;
+                if modrm.reg < 4 {
+                    return None;
+                }
+                let rm = self.rm(modrm, self.operand_size())?;
+                let immediate = self.immediate(1)? as u8 as i64;
+                Some((BIT_TESTS[(modrm.reg - 4) as usize], vec![rm, Operand::Immediate(immediate)]))
+            }
+            0xbc | 0xbd => {
+                let mnemonic = match (opcode, self.repeat == Some(0xf3)) {
+                    (0xbc, false) => "bsf",
+                    (0xbc, true) => "tzcnt",
+                    (_, false) => "bsr",
+                    (_, true) => "lzcnt",
+                };
+                Some((mnemonic, self.standard_operands(3)?))
+            }
+            0xc0 | 0xc1 => Some(("xadd", self.standard_operands(opcode & 1)?)),
+            0xc7 => {
+                let modrm = self.modrm()?;
+                match (modrm.md == 3, modrm.reg) {
+                    (false, 1) => {
+                        let (mnemonic, size) = if self.rex_w() { ("cmpxchg16b", 16) } else { ("cmpxchg8b", 8) };
+                        Some((mnemonic, vec![self.memory(modrm, Some(size))?]))
+                    }
+                    (true, 6) => Some(("rdrand", vec![self.rm(modrm, self.operand_size())?])),
+                    (true, 7) => Some(("rdseed", vec![self.rm(modrm, self.operand_size())?])),
+                    _ => None,
+                }
+            }
+            0xc8..=0xcf => Some(("bswap", vec![self.opcode_register(opcode, self.operand_size())])),
+            _ => None,
+        }
+    }
+
+    /// Decodes the operands of an SSE instruction that moves data between an XMM register
+    /// and another XMM register or memory, with the XMM register first unless `store` is true.
+    fn sse_operands(&mut self, mnemonic: &'static str, memory_size: u8, store: bool) -> Decoded {
+        let modrm = self.modrm()?;
+        let rm = self.rm_sized(modrm, 16, memory_size)?;
+        let xmm = self.reg(modrm, 16);
+        Some((mnemonic, if store { vec![rm, xmm] } else { vec![xmm, rm] }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use alloc::{format, string::String};
+
+    fn disassemble(bytes: &[u8], address: u64) -> (String, usize) {
+        let instruction = decode(bytes, address).expect("failed to decode instruction");
+        (format!("{}", instruction), instruction.len)
+    }
+
+    #[test]
+    fn general_purpose_instructions() {
+        let cases: &[(&[u8], &str)] = &[
+            (&[0x55], "push rbp"),
+            (&[0x48, 0x89, 0xe5], "mov rbp, rsp"),
+            (&[0x48, 0x83, 0xec, 0x10], "sub rsp, 0x10"),
+            (&[0x48, 0x83, 0xe4, 0xf0], "and rsp, -0x10"),
+            (&[0x8b, 0x45, 0xfc], "mov eax, dword ptr [rbp-0x4]"),
+            (&[0x42, 0x8b, 0x04, 0x8b], "mov eax, dword ptr [rbx+r9*4]"),
+            (&[0x40, 0x88, 0xc6], "mov sil, al"),
+            (&[0x88, 0xe0], "mov al, ah"),
+            (&[0x64, 0x48, 0x8b, 0x04, 0x25, 0x28, 0x00, 0x00, 0x00], "mov rax, qword ptr fs:[0x28]"),
+            (&[0x48, 0xb8, 0x88, 0x77, 0x66, 0x55, 0x44, 0x33, 0x22, 0x11], "movabs rax, 0x1122334455667788"),
+            (&[0x0f, 0xb6, 0x07], "movzx eax, byte ptr [rdi]"),
+            (&[0xf0, 0x48, 0x0f, 0xb1, 0x0a], "lock cmpxchg qword ptr [rdx], rcx"),
+            (&[0xf3, 0x48, 0xab], "rep stosq"),
+            (&[0x66, 0x2e, 0x0f, 0x1f, 0x84, 0x00, 0x00, 0x00, 0x00, 0x00], "nop word ptr cs:[rax+rax*1]"),
+            (&[0xff, 0x24, 0xc5, 0x00, 0x10, 0x00, 0x00], "jmp qword ptr [rax*8+0x1000]"),
+            (&[0xf3, 0x0f, 0x1e, 0xfa], "endbr64"),
+            (&[0x0f, 0x0b], "ud2"),
+            (&[0xc3], "ret"),
+        ];
+        for (bytes, expected) in cases {
+            assert_eq!(disassemble(bytes, 0), (String::from(*expected), bytes.len()));
+        }
+    }
+
+    #[test]
+    fn targets() {
+        let call = decode(&[0xe8, 0xfb, 0xff, 0xff, 0xff], 0x1000).unwrap();
+        assert_eq!(format!("{}", call), "call 0x1000");
+        assert_eq!(call.target(), Some(0x1000));
+
+        let lea = decode(&[0x48, 0x8d, 0x05, 0x10, 0x00, 0x00, 0x00, 0x90], 0x2000).unwrap();
+        assert_eq!(format!("{}", lea), "lea rax, [rip+0x10]");
+        assert_eq!(lea.target(), Some(0x2017));
+
+        // Truncated and unsupported instructions.
+        assert_eq!(decode(&[0xe8, 0x00, 0x00], 0), None);
+        assert_eq!(decode(&[0x0f, 0xff], 0), None);
+    }
+}
@@ -167,6 +167,12 @@ impl<T> CowWeak<T> {
             arc: Arc::new(InnerArc{ inner_arc }),
         })
     }
+
+    /// Returns true if the two `CowWeak`s point to the same value,
+    /// even if they were obtained from different outer references to it.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Weak::ptr_eq(&self.weak.inner_weak, &other.weak.inner_weak)
+    }
 }
 impl<T> Clone for CowWeak<T> {
     fn clone(&self) -> CowWeak<T> {
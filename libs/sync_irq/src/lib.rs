@@ -1,6 +1,7 @@
 #![no_std]
 
-use irq_safety::{hold_interrupts, HeldInterrupts};
+use crossbeam_utils::atomic::AtomicCell;
+use irq_safety::{interrupts_enabled, HeldInterrupts};
 
 pub type Mutex<T> = sync::Mutex<T, DisableIrq>;
 pub type MutexGuard<'a, T> = sync::MutexGuard<'a, T, DisableIrq>;
@@ -21,7 +22,7 @@ pub type IrqSafeRwLockWriteGuard<'a, T> = RwLockWriteGuard<'a, T>;
 pub struct DisableIrq {}
 
 impl sync::DeadlockPrevention for DisableIrq {
-    type Guard = HeldInterrupts;
+    type Guard = HeldIrqs;
 
     const EXPENSIVE: bool = true;
 
@@ -30,3 +31,78 @@ impl sync::DeadlockPrevention for DisableIrq {
         hold_interrupts()
     }
 }
+
+/// Functions that are invoked when an interrupt-disabled section
+/// begins and ends on the current CPU.
+///
+/// This allows higher-level crates to instrument interrupt-disabled sections,
+/// e.g., to measure how long interrupts stay masked, without this crate
+/// depending on them.
+#[derive(Debug)]
+pub struct IrqOffHooks {
+    /// Invoked right after interrupts were disabled by [`hold_interrupts()`].
+    ///
+    /// The returned value is an opaque token passed to `on_enable`.
+    pub on_disable: fn() -> u64,
+    /// Invoked right before interrupts are re-enabled by dropping a [`HeldIrqs`] guard,
+    /// with the token returned from the corresponding `on_disable` call.
+    ///
+    /// Interrupts are still disabled when this is invoked.
+    pub on_enable: fn(u64),
+}
+
+/// The currently-registered hooks for interrupt-disabled sections, if any.
+static IRQ_OFF_HOOKS: AtomicCell<Option<&'static IrqOffHooks>> = AtomicCell::new(None);
+
+// Ensure that `AtomicCell<Option<&'static IrqOffHooks>>` is actually a lock-free atomic.
+const _: () = assert!(AtomicCell::<Option<&'static IrqOffHooks>>::is_lock_free());
+
+/// Registers the given `hooks` to be invoked at the start and end of
+/// every interrupt-disabled section created via [`hold_interrupts()`].
+///
+/// Passing `None` removes the currently-registered hooks.
+/// Sections that began before the hooks were changed will still
+/// invoke the `on_enable` hook that was registered when they began.
+pub fn set_irq_off_hooks(hooks: Option<&'static IrqOffHooks>) {
+    IRQ_OFF_HOOKS.store(hooks);
+}
+
+/// Disables interrupts on the current CPU until the returned guard is dropped,
+/// at which point they will be restored to their prior state.
+///
+/// This is a wrapper around [`irq_safety::hold_interrupts()`] that also invokes
+/// the registered [`IrqOffHooks`] when this causes interrupts to transition
+/// from enabled to disabled, i.e., for the outermost interrupt-disabled section.
+#[inline]
+pub fn hold_interrupts() -> HeldIrqs {
+    let were_enabled = interrupts_enabled();
+    let held = irq_safety::hold_interrupts();
+    let token = if were_enabled {
+        IRQ_OFF_HOOKS.load().map(|hooks| (hooks, (hooks.on_disable)()))
+    } else {
+        None
+    };
+    HeldIrqs { token, _held: held }
+}
+
+/// A guard type that keeps interrupts disabled until it is dropped.
+///
+/// Obtain one via [`hold_interrupts()`].
+pub struct HeldIrqs {
+    /// The hooks and token from the start of this section,
+    /// if this guard was the one that disabled interrupts.
+    token: Option<(&'static IrqOffHooks, u64)>,
+    /// The inner guard, which re-enables interrupts (if needed) when dropped.
+    /// Fields are dropped after `Drop::drop()` runs, so this happens
+    /// after the `on_enable` hook has been invoked.
+    _held: HeldInterrupts,
+}
+
+impl Drop for HeldIrqs {
+    #[inline]
+    fn drop(&mut self) {
+        if let Some((hooks, token)) = self.token.take() {
+            (hooks.on_enable)(token);
+        }
+    }
+}
@@ -6,6 +6,13 @@
 //! its demangled symbol and the trailing hash value, separated by a space.
 //! For example, an input of "_ZN7console4init17h71243d883671cb51E"
 //! produces an output of "console::init::h71243d883671cb51".
+//! Symbols mangled with the v0 scheme (starting with "_R") have no hash value,
+//! so the disambiguator of the first crate in the symbol's path is used instead,
+//! just like `mod_mgmt::demangle_symbol()` does.
+//!
+//! With the "-c" argument, the output is instead converted into the compact symbol file format
+//! that the nano_core symbol file parser in `mod_mgmt` understands,
+//! which lists only the section headers and the symbols that are in a section.
 
 extern crate rustc_demangle;
 extern crate getopts; 
@@ -21,6 +28,7 @@ fn main() {
 
     let mut opts = Options::new();
     opts.optopt("o", "", "set output file path", "OUTPUT_PATH");
+    opts.optflag("c", "compact", "output the compact symbol file format instead of readelf's format");
     opts.optflag("h", "help", "print help menu");
 
     let matches = match opts.parse(&args[1..]) {
@@ -75,15 +83,16 @@ fn main() {
     // parse each symbol table entry and demangle the names
     for line in file_iterator {
         // println!("line: {}", line);
-        // we need to find the mangled symbol in each symtab entry, which always starts with "_ZN"
-        if let Some(index) = line.find("_ZN") {
+        // we need to find the mangled symbol in each symtab entry, which always starts with "_ZN",
+        // or with "_R" for the v0 mangling scheme (which must be at the start of the name column)
+        if let Some(index) = line.find("_ZN").or_else(|| line.find(" _R").map(|i| i + 1)) {
             let (first_half, name_mangled) = line.split_at(index);
             let demangled = demangle_symbol(name_mangled);
             output.push_str(first_half); // no newline after this, since it's just a split line
             output.push_str(&demangled);
             output.push_str("\n");
         }
-        // if we cannot find "_ZN", then there wasn't a mangled symbol (it might've been no_mangle)
+        // if we cannot find "_ZN" or "_R", then there wasn't a mangled symbol (it might've been no_mangle)
         else {
             // so just preserve the line as is
             output.push_str(line);
@@ -93,6 +102,10 @@ fn main() {
     }
 
 
+    if matches.opt_present("c") {
+        output = to_compact(&output);
+    }
+
     // if we had a "-o OUTPUT_FILE" argument, then write the output to that file 
     if matches.opt_present("o") {
         let output_file_path = match matches.opt_str("o") {
@@ -123,6 +136,92 @@ fn print_usage(program: &str, opts: Options) {
 
 
 
+/// Converts the (demangled) output of `readelf -S -s -W` into the compact symbol file format:
+/// a header line, then one "S <shndx> <name> <type> <address> <size>" line per section header,
+/// then one "G|L <shndx> <address> <size> <name>" line per global or local symbol that's in a section.
+fn to_compact(readelf: &str) -> String {
+    let mut output = String::from("theseus-nano-core-symbols 1\n");
+    let mut lines = readelf.lines();
+
+    // The section headers come before the symbol table, e.g.,
+    // "[ 1] .text  PROGBITS  ffffffff80100000 001000 012345 00  AX  0   0 16"
+    while let Some(line) = lines.next() {
+        if line.contains(".symtab") && !line.contains("SYMTAB") {
+            break;
+        }
+        let header = match line.trim_start().strip_prefix('[').and_then(|l| l.split_once(']')) {
+            Some((shndx, rest)) => (shndx.trim().parse::<usize>(), rest.split_whitespace().collect::<Vec<_>>()),
+            None => continue,
+        };
+        match header {
+            (Ok(shndx), ref fields) if shndx != 0 && fields.len() >= 5 => {
+                output.push_str(&format!("S {} {} {} {} {}\n", shndx, fields[0], fields[1], fields[2], fields[4]));
+            }
+            _ => continue,
+        }
+    }
+    // skip the line with the column headers
+    lines.next();
+
+    // Each symbol is, e.g., "42: ffffffff80100000 24 FUNC GLOBAL DEFAULT 1 nano_core::start::h0123456789abcdef"
+    for line in lines {
+        let line = line.replace("<OS specific>: ", "");
+        let mut rest = line.trim();
+        let mut fields = Vec::new();
+        while fields.len() < 7 {
+            let end = match rest.find(char::is_whitespace) {
+                Some(end) => end,
+                None => break,
+            };
+            fields.push(&rest[.. end]);
+            rest = rest[end ..].trim_start();
+        }
+        if fields.len() < 7 || rest.is_empty() {
+            continue;
+        }
+        let (value, size, bind, ndx) = (fields[1], fields[2], fields[4], fields[6]);
+        let shndx = match ndx.parse::<usize>() {
+            Ok(shndx) => shndx,
+            Err(_) => continue, // e.g., "ABS" or "UND"
+        };
+        let size = match size.strip_prefix("0x") {
+            Some(hex) => usize::from_str_radix(hex, 16),
+            None => size.parse::<usize>(),
+        };
+        let size = match size {
+            Ok(size) => size,
+            Err(_) => continue,
+        };
+        let global = if bind == "GLOBAL" || bind == "WEAK" { "G" } else { "L" };
+        output.push_str(&format!("{} {} {} {:x} {}\n", global, shndx, value, size, rest));
+    }
+    output
+}
+
 fn demangle_symbol(mangled: &str) -> String {
-    rustc_demangle::demangle(mangled).to_string()
+    let demangled = rustc_demangle::demangle(mangled);
+    if !mangled.starts_with("_R") {
+        return demangled.to_string();
+    }
+    // The non-alternate form includes each crate's disambiguator, e.g., "my_crate[6f1a9ac2b4e7d003]::foo".
+    let name = format!("{:#}", demangled);
+    match crate_disambiguator(&demangled.to_string()) {
+        Some(hash) => format!("{}::h{:0>16}", name, hash),
+        None => name,
+    }
+}
+
+/// Returns the hex digits within the first brackets that directly follow a crate name.
+fn crate_disambiguator(demangled: &str) -> Option<&str> {
+    let bytes = demangled.as_bytes();
+    demangled.match_indices('[').find_map(|(open, _)| {
+        let follows_ident = open > 0 && (bytes[open - 1].is_ascii_alphanumeric() || bytes[open - 1] == b'_');
+        if !follows_ident {
+            return None;
+        }
+        let rest = &demangled[open + 1 ..];
+        let hash = &rest[.. rest.find(']')?];
+        let is_hash = !hash.is_empty() && hash.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'));
+        if is_hash { Some(hash) } else { None }
+    })
 }
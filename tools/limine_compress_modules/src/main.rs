@@ -1,5 +1,6 @@
 extern crate getopts;
 extern crate lz4_flex;
+extern crate zstd;
 
 use lz4_flex::block::compress_prepend_size;
 use getopts::Options;
@@ -14,6 +15,7 @@ fn main() -> Result<(), String> {
     let mut opts = Options::new();
     opts.optopt("o", "", "set compressed file path", "OUTPUT_PATH");
     opts.optopt("i", "", "set uncompressed file path", "INPUT_PATH");
+    opts.optopt("f", "", "set compression format: 'lz4' (the default) or 'zstd'", "FORMAT");
     opts.optflag("h", "help", "print this help menu");
 
     let matches = opts.parse(&args[1..]).map_err(|e| e.to_string())?;
@@ -32,7 +34,12 @@ fn main() -> Result<(), String> {
     let input = read(input_path).ok()
         .ok_or(String::from("failed to read input file"))?;
 
-    let output = compress_prepend_size(&input);
+    let output = match matches.opt_str("f").as_deref() {
+        None | Some("lz4") => compress_prepend_size(&input),
+        Some("zstd") => zstd::bulk::compress(&input, zstd::DEFAULT_COMPRESSION_LEVEL)
+            .map_err(|e| format!("failed to compress input file: {}", e))?,
+        Some(other) => return Err(format!("unsupported compression format: {}", other)),
+    };
 
     write(output_path, output).ok()
         .ok_or(String::from("failed to write to output file"))?;